@@ -43,6 +43,9 @@ type ClientState struct {
 	FrozenHeight types.Height `protobuf:"bytes,5,opt,name=frozen_height,json=frozenHeight,proto3" json:"frozen_height"`
 	// Latest height the client was updated to
 	LatestHeight types.Height `protobuf:"bytes,6,opt,name=latest_height,json=latestHeight,proto3" json:"latest_height"`
+	// Hash of the groth16 verifying key that proofs for this client must be
+	// verified with. Empty if any verifying key is accepted.
+	ZkVerifyingKeyHash []byte `protobuf:"bytes,7,opt,name=zk_verifying_key_hash,json=zkVerifyingKeyHash,proto3" json:"zk_verifying_key_hash,omitempty"`
 }
 
 func (m *ClientState) Reset()         { *m = ClientState{} }
@@ -321,49 +324,50 @@ func init() {
 }
 
 var fileDescriptor_6e4c33c744877a4e = []byte{
-	// 665 bytes of a gzipped FileDescriptorProto
-	0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0xff, 0x9c, 0x54, 0xcf, 0x4e, 0xd4, 0x40,
-	0x1c, 0xde, 0x42, 0x59, 0x60, 0x76, 0x17, 0x4c, 0x43, 0xcc, 0xba, 0x31, 0xbb, 0x64, 0x0f, 0x82,
-	0x1e, 0x5a, 0xba, 0x5e, 0xd4, 0x78, 0x11, 0x24, 0xc1, 0x28, 0x09, 0xa9, 0xc4, 0x83, 0x97, 0x66,
-	0xda, 0xce, 0xb6, 0x13, 0xda, 0x99, 0x66, 0x66, 0xb6, 0x12, 0x9e, 0xc0, 0x23, 0x0f, 0xe0, 0xc1,
-	0x83, 0x0f, 0xe1, 0x23, 0x70, 0x24, 0x31, 0x26, 0x9e, 0xd4, 0xc0, 0x8b, 0x98, 0xf9, 0xd3, 0x65,
-	0x4d, 0x24, 0x10, 0x6f, 0xd3, 0xef, 0xf7, 0x7d, 0xdf, 0x6f, 0xe6, 0x9b, 0xf9, 0x15, 0xf8, 0x13,
-	0x82, 0x29, 0xf1, 0x70, 0x14, 0x7b, 0x39, 0x4e, 0x33, 0x11, 0xe7, 0x18, 0x11, 0xc1, 0xbd, 0x98,
-	0x16, 0x48, 0x44, 0x39, 0xf7, 0x2a, 0x7f, 0xba, 0x76, 0x4b, 0x46, 0x05, 0x75, 0x86, 0x4a, 0xe2,
-	0xe2, 0x28, 0x76, 0x67, 0x25, 0xee, 0x94, 0x56, 0xf9, 0xbd, 0x41, 0x4a, 0x69, 0x9a, 0x23, 0x4f,
-	0x29, 0xa2, 0xc9, 0xd8, 0x13, 0xb8, 0x40, 0x5c, 0xc0, 0xa2, 0xd4, 0x26, 0xbd, 0x81, 0xec, 0x18,
-	0x53, 0x86, 0x3c, 0x2d, 0x57, 0x7d, 0xd4, 0xca, 0x10, 0x36, 0xae, 0x08, 0xb4, 0x28, 0xb0, 0x28,
-	0x6a, 0xd2, 0xf4, 0xcb, 0x10, 0xd7, 0x52, 0x9a, 0x52, 0xb5, 0xf4, 0xe4, 0x4a, 0xa3, 0xc3, 0xaf,
-	0x73, 0xa0, 0xb5, 0xa3, 0xfc, 0xde, 0x0a, 0x28, 0x90, 0x73, 0x0f, 0x2c, 0xc5, 0x19, 0xc4, 0x24,
-	0xc4, 0x49, 0xd7, 0x5a, 0xb7, 0x36, 0x97, 0x83, 0x45, 0xf5, 0xfd, 0x2a, 0x71, 0x36, 0xc0, 0xaa,
-	0x60, 0x13, 0x2e, 0x30, 0x49, 0xc3, 0x12, 0x31, 0x4c, 0x93, 0xee, 0xdc, 0xba, 0xb5, 0x69, 0x07,
-	0x2b, 0x35, 0x7c, 0xa0, 0x50, 0xe7, 0x21, 0xb8, 0x33, 0x21, 0x11, 0x25, 0xc9, 0x0c, 0x73, 0x5e,
-	0x31, 0x57, 0xa7, 0xb8, 0xa1, 0x3e, 0x00, 0xab, 0x05, 0x3c, 0x0e, 0xe3, 0x9c, 0xc6, 0x47, 0x61,
-	0xc2, 0xf0, 0x58, 0x74, 0x6d, 0xc5, 0xec, 0x14, 0xf0, 0x78, 0x47, 0xa2, 0x2f, 0x25, 0xe8, 0xec,
-	0x82, 0xce, 0x98, 0xd1, 0x13, 0x44, 0xc2, 0x0c, 0xc9, 0x2c, 0xbb, 0x0b, 0xeb, 0xd6, 0x66, 0x6b,
-	0xd4, 0x53, 0xe9, 0xca, 0xd3, 0xbb, 0x26, 0x94, 0xca, 0x77, 0xf7, 0x14, 0x63, 0xdb, 0x3e, 0xfb,
-	0x39, 0x68, 0x04, 0x6d, 0x2d, 0xd3, 0x98, 0xb4, 0xc9, 0xa1, 0x40, 0x5c, 0xd4, 0x36, 0xcd, 0xdb,
-	0xda, 0x68, 0x99, 0xc6, 0x9e, 0xd9, 0x1f, 0x3f, 0x0f, 0x1a, 0xc3, 0x2f, 0x16, 0x58, 0xd9, 0xa1,
-	0x84, 0x23, 0xc2, 0x27, 0x5c, 0xa7, 0x77, 0x1f, 0x2c, 0x4f, 0x2f, 0x50, 0xc5, 0x67, 0x07, 0x57,
-	0x80, 0xf3, 0x1c, 0xd8, 0x8c, 0x52, 0xa1, 0x52, 0x6b, 0x8d, 0x86, 0x33, 0x4d, 0xaf, 0xee, 0xaa,
-	0xf2, 0xdd, 0x7d, 0xc4, 0x8e, 0x72, 0x14, 0x50, 0x5a, 0x37, 0x57, 0x2a, 0x67, 0x0b, 0xac, 0x11,
-	0x74, 0x2c, 0xc2, 0x0a, 0xe6, 0x38, 0x81, 0x82, 0x32, 0x1e, 0x66, 0x90, 0x67, 0x2a, 0xd9, 0x76,
-	0xe0, 0xc8, 0xda, 0xbb, 0x69, 0x69, 0x0f, 0xf2, 0xcc, 0x6c, 0xf3, 0x93, 0x05, 0xda, 0xfb, 0x98,
-	0x47, 0x28, 0x83, 0x15, 0xa6, 0x13, 0xe6, 0xec, 0x82, 0xa5, 0x0c, 0xc1, 0x04, 0xb1, 0xd0, 0x57,
-	0x7b, 0x6c, 0x8d, 0x1e, 0xb9, 0x37, 0x3f, 0x55, 0x77, 0x4f, 0x69, 0x82, 0x45, 0xad, 0xf5, 0x67,
-	0x6c, 0x46, 0xe6, 0x44, 0xff, 0x61, 0x33, 0x1a, 0x7e, 0xb7, 0x40, 0xeb, 0x8d, 0x24, 0xeb, 0x82,
-	0x73, 0x17, 0x34, 0xcd, 0xdd, 0xc8, 0xbd, 0xcd, 0x07, 0xe6, 0xcb, 0x79, 0x02, 0x6c, 0x99, 0xa4,
-	0x69, 0xd5, 0x73, 0xf5, 0xe0, 0xb8, 0xf5, 0xe0, 0xb8, 0x87, 0x75, 0xcc, 0xdb, 0x4b, 0x32, 0xb4,
-	0xd3, 0x5f, 0x03, 0x2b, 0x50, 0x0a, 0xf9, 0x6e, 0xff, 0x9d, 0xd9, 0x4a, 0xf5, 0x57, 0x5e, 0xd7,
-	0x26, 0x6c, 0x5f, 0x97, 0xb0, 0x9c, 0x16, 0x58, 0x96, 0x9a, 0xb5, 0xa0, 0x58, 0x8b, 0xb0, 0x2c,
-	0x65, 0x69, 0xf8, 0xcd, 0x02, 0x4d, 0x73, 0xa4, 0x43, 0xd0, 0xe1, 0x38, 0x25, 0x28, 0x09, 0xf5,
-	0xa1, 0x4d, 0xea, 0xde, 0x6d, 0xe2, 0x9a, 0x89, 0x26, 0x68, 0x6b, 0x17, 0xe3, 0xfa, 0x02, 0xe8,
-	0xb9, 0x53, 0xb6, 0x2a, 0xb0, 0xb9, 0x9b, 0x1e, 0x73, 0xd0, 0x31, 0x0a, 0x33, 0x0e, 0x5b, 0x60,
-	0xed, 0x04, 0x31, 0x1a, 0x1e, 0x11, 0xfa, 0x21, 0x47, 0x49, 0x8a, 0xc2, 0x92, 0x51, 0x3a, 0xae,
-	0x9f, 0x94, 0xac, 0xbd, 0xae, 0x4b, 0x07, 0xb2, 0xb2, 0xfd, 0xf4, 0xec, 0xa2, 0x6f, 0x9d, 0x5f,
-	0xf4, 0xad, 0xdf, 0x17, 0x7d, 0xeb, 0xf4, 0xb2, 0xdf, 0x38, 0xbf, 0xec, 0x37, 0x7e, 0x5c, 0xf6,
-	0x1b, 0xef, 0x07, 0x37, 0xfc, 0x20, 0xa3, 0xa6, 0xba, 0xaa, 0xc7, 0x7f, 0x02, 0x00, 0x00, 0xff,
-	0xff, 0xc9, 0xcd, 0xbe, 0x34, 0x4a, 0x05, 0x00, 0x00,
+	// 679 bytes of a gzipped FileDescriptorProto
+	0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0xff, 0x9d, 0x54, 0xcb, 0x6e, 0xd3, 0x40,
+	0x14, 0x8d, 0x53, 0x37, 0x49, 0x27, 0x2f, 0x64, 0x15, 0x14, 0x22, 0xd4, 0xa0, 0x2c, 0x68, 0x61,
+	0x61, 0xe3, 0xb0, 0x01, 0xc4, 0x86, 0x16, 0xa4, 0xa2, 0x52, 0xa9, 0x32, 0x55, 0x17, 0x6c, 0x2c,
+	0xc7, 0x9e, 0xd8, 0xa3, 0xd8, 0x33, 0x91, 0x3d, 0x31, 0x6d, 0xbe, 0x80, 0x65, 0x3f, 0x80, 0x05,
+	0x0b, 0x3e, 0xa6, 0xcb, 0x4a, 0x08, 0x89, 0x15, 0x20, 0x58, 0xf2, 0x13, 0xcc, 0xcb, 0x49, 0x90,
+	0xa8, 0x52, 0xb1, 0x18, 0x69, 0xe6, 0xde, 0x73, 0x8e, 0xef, 0x3d, 0x73, 0xc7, 0xc0, 0x9e, 0x62,
+	0x44, 0xb0, 0x85, 0x86, 0xbe, 0x15, 0xa3, 0x30, 0xa2, 0x7e, 0x8c, 0x20, 0xa6, 0x99, 0xe5, 0x93,
+	0x04, 0xd2, 0x61, 0x9c, 0x59, 0xb9, 0x3d, 0xdf, 0x9b, 0x93, 0x94, 0x50, 0x62, 0xf4, 0x05, 0xc5,
+	0x64, 0x14, 0x73, 0x99, 0x62, 0xce, 0x61, 0xb9, 0xdd, 0xed, 0x85, 0x84, 0x84, 0x31, 0xb4, 0x04,
+	0x63, 0x38, 0x1d, 0x59, 0x14, 0x25, 0x30, 0xa3, 0x5e, 0x32, 0x91, 0x22, 0xdd, 0x1e, 0xff, 0xa2,
+	0x4f, 0x52, 0x68, 0x49, 0xba, 0xf8, 0x8e, 0xd8, 0x29, 0xc0, 0xf6, 0x02, 0x40, 0x92, 0x04, 0xd1,
+	0xa4, 0x00, 0xcd, 0x4f, 0x0a, 0xb8, 0x19, 0x92, 0x90, 0x88, 0xad, 0xc5, 0x77, 0x32, 0xda, 0xff,
+	0x5d, 0x06, 0xf5, 0x3d, 0xa1, 0xf7, 0x86, 0x7a, 0x14, 0x1a, 0xb7, 0x41, 0xcd, 0x8f, 0x3c, 0x84,
+	0x5d, 0x14, 0x74, 0xb4, 0xbb, 0xda, 0xce, 0x86, 0x53, 0x15, 0xe7, 0x57, 0x81, 0xb1, 0x0d, 0xda,
+	0x34, 0x9d, 0x66, 0x14, 0xe1, 0xd0, 0x9d, 0xc0, 0x14, 0x91, 0xa0, 0x53, 0x66, 0x08, 0xdd, 0x69,
+	0x15, 0xe1, 0x23, 0x11, 0x35, 0xee, 0x83, 0x1b, 0x53, 0x3c, 0x24, 0x38, 0x58, 0x42, 0xae, 0x09,
+	0x64, 0x7b, 0x1e, 0x57, 0xd0, 0x7b, 0xa0, 0x9d, 0x78, 0xa7, 0xae, 0x1f, 0x13, 0x7f, 0xec, 0x06,
+	0x29, 0x1a, 0xd1, 0x8e, 0x2e, 0x90, 0x4d, 0x16, 0xde, 0xe3, 0xd1, 0x17, 0x3c, 0x68, 0xbc, 0x04,
+	0xcd, 0x51, 0x4a, 0x66, 0x10, 0xbb, 0x11, 0xe4, 0x5e, 0x76, 0xd6, 0x19, 0xaa, 0x3e, 0xe8, 0x0a,
+	0x77, 0x79, 0xf7, 0xa6, 0x32, 0x25, 0xb7, 0xcd, 0x7d, 0x81, 0xd8, 0xd5, 0x2f, 0xbe, 0xf5, 0x4a,
+	0x4e, 0x43, 0xd2, 0x64, 0x8c, 0xcb, 0xc4, 0xac, 0xcb, 0x8c, 0x16, 0x32, 0x95, 0xeb, 0xca, 0x48,
+	0x9a, 0x92, 0xb1, 0xc1, 0xcd, 0xd9, 0xd8, 0xcd, 0x59, 0x0b, 0xa3, 0x33, 0xde, 0xe3, 0x18, 0x9e,
+	0xb9, 0x91, 0x97, 0x45, 0x9d, 0x2a, 0x93, 0x6b, 0x38, 0xc6, 0x6c, 0x7c, 0x52, 0xe4, 0x0e, 0xe0,
+	0xd9, 0x3e, 0xcb, 0x3c, 0xd5, 0xdf, 0x7f, 0xec, 0x95, 0xfa, 0x9f, 0x34, 0xd0, 0xda, 0x23, 0x38,
+	0x83, 0x38, 0x9b, 0x66, 0xd2, 0xf0, 0x3b, 0x60, 0x63, 0x7e, 0xe7, 0xc2, 0x71, 0xdd, 0x59, 0x04,
+	0x8c, 0x67, 0x40, 0x4f, 0x09, 0xa1, 0xc2, 0xe8, 0xfa, 0xa0, 0xbf, 0x54, 0xe7, 0xe2, 0x7a, 0x59,
+	0xad, 0x87, 0x30, 0x1d, 0xc7, 0xd0, 0x61, 0x48, 0x55, 0xaf, 0x60, 0x19, 0x0f, 0xc1, 0x26, 0x86,
+	0xa7, 0xd4, 0xcd, 0xbd, 0x18, 0x05, 0x1e, 0x25, 0x69, 0x26, 0xcb, 0x5c, 0x93, 0x65, 0xf2, 0xdc,
+	0xc9, 0x3c, 0xb5, 0x54, 0xe6, 0x07, 0x0d, 0x34, 0x0e, 0x51, 0x36, 0x84, 0x91, 0x97, 0x23, 0x32,
+	0x4d, 0x99, 0x6f, 0xb5, 0x08, 0x7a, 0x01, 0x4c, 0x5d, 0x5b, 0xd4, 0x58, 0x1f, 0x3c, 0x30, 0x57,
+	0x4f, 0x37, 0xb3, 0x90, 0x73, 0x9c, 0xaa, 0xe4, 0xda, 0x4b, 0x32, 0x03, 0xd5, 0xd1, 0x7f, 0xc8,
+	0x0c, 0xfa, 0x5f, 0x34, 0x50, 0x7f, 0xcd, 0xc1, 0x32, 0x61, 0xdc, 0x02, 0x15, 0x75, 0x9d, 0xbc,
+	0xb6, 0x35, 0x47, 0x9d, 0x8c, 0xc7, 0x40, 0xe7, 0x4e, 0xaa, 0x4f, 0x75, 0x4d, 0xf9, 0xd6, 0xcc,
+	0xe2, 0xad, 0x99, 0xc7, 0x85, 0xcd, 0xbb, 0x35, 0x6e, 0xda, 0xf9, 0xf7, 0x9e, 0xe6, 0x08, 0x06,
+	0x1f, 0xf5, 0x7f, 0x7b, 0xd6, 0xca, 0xff, 0xf2, 0xeb, 0x4a, 0x87, 0xf5, 0xab, 0x1c, 0xe6, 0x0f,
+	0xcc, 0x9b, 0x4c, 0x24, 0x6a, 0x5d, 0xa0, 0xaa, 0xec, 0xcc, 0x53, 0xfd, 0xcf, 0x1a, 0xa8, 0xa8,
+	0x96, 0x8e, 0x41, 0x33, 0x43, 0x21, 0x86, 0x81, 0x2b, 0x9b, 0x56, 0xae, 0x5b, 0xd7, 0xb1, 0x6b,
+	0xc9, 0x1a, 0xa7, 0x21, 0x55, 0x94, 0xea, 0x73, 0x20, 0x9f, 0xaa, 0x90, 0x15, 0x86, 0x95, 0x57,
+	0xcd, 0xbf, 0xd3, 0x54, 0x0c, 0x35, 0xfa, 0xac, 0xe1, 0x19, 0x4c, 0x89, 0x3b, 0xc6, 0xe4, 0x5d,
+	0x0c, 0x83, 0x10, 0xba, 0xcc, 0x4e, 0x32, 0x2a, 0x46, 0x8a, 0xe7, 0x0e, 0x8a, 0xd4, 0x11, 0xcf,
+	0xec, 0x3e, 0xb9, 0xf8, 0xb9, 0xa5, 0x5d, 0xb2, 0xf5, 0x83, 0xad, 0xf3, 0x5f, 0x5b, 0xa5, 0x4b,
+	0xb6, 0xbe, 0xb2, 0xf5, 0xb6, 0xb7, 0xe2, 0x9f, 0x3a, 0xac, 0x88, 0xab, 0x7a, 0xf4, 0x07, 0x2f,
+	0xc8, 0x97, 0xbe, 0x7d, 0x05, 0x00, 0x00,
 }
 
 func (m *ClientState) Marshal() (dAtA []byte, err error) {
@@ -386,6 +390,13 @@ func (m *ClientState) MarshalToSizedBuffer(dAtA []byte) (int, error) {
 	_ = i
 	var l int
 	_ = l
+	if len(m.ZkVerifyingKeyHash) > 0 {
+		i -= len(m.ZkVerifyingKeyHash)
+		copy(dAtA[i:], m.ZkVerifyingKeyHash)
+		i = encodeVarintCometbls(dAtA, i, uint64(len(m.ZkVerifyingKeyHash)))
+		i--
+		dAtA[i] = 0x3a
+	}
 	{
 		size, err := m.LatestHeight.MarshalToSizedBuffer(dAtA[:i])
 		if err != nil {
@@ -668,6 +679,10 @@ func (m *ClientState) Size() (n int) {
 	n += 1 + l + sovCometbls(uint64(l))
 	l = m.LatestHeight.Size()
 	n += 1 + l + sovCometbls(uint64(l))
+	l = len(m.ZkVerifyingKeyHash)
+	if l > 0 {
+		n += 1 + l + sovCometbls(uint64(l))
+	}
 	return n
 }
 
@@ -943,6 +958,40 @@ func (m *ClientState) Unmarshal(dAtA []byte) error {
 				return err
 			}
 			iNdEx = postIndex
+		case 7:
+			if wireType != 2 {
+				return fmt.Errorf("proto: wrong wireType = %d for field ZkVerifyingKeyHash", wireType)
+			}
+			var byteLen int
+			for shift := uint(0); ; shift += 7 {
+				if shift >= 64 {
+					return ErrIntOverflowCometbls
+				}
+				if iNdEx >= l {
+					return io.ErrUnexpectedEOF
+				}
+				b := dAtA[iNdEx]
+				iNdEx++
+				byteLen |= int(b&0x7F) << shift
+				if b < 0x80 {
+					break
+				}
+			}
+			if byteLen < 0 {
+				return ErrInvalidLengthCometbls
+			}
+			postIndex := iNdEx + byteLen
+			if postIndex < 0 {
+				return ErrInvalidLengthCometbls
+			}
+			if postIndex > l {
+				return io.ErrUnexpectedEOF
+			}
+			m.ZkVerifyingKeyHash = append(m.ZkVerifyingKeyHash[:0], dAtA[iNdEx:postIndex]...)
+			if m.ZkVerifyingKeyHash == nil {
+				m.ZkVerifyingKeyHash = []byte{}
+			}
+			iNdEx = postIndex
 		default:
 			iNdEx = preIndex
 			skippy, err := skipCometbls(dAtA[iNdEx:])
@@ -164,6 +164,10 @@ impl<T: ZkpVerifier> IbcClient for CometblsLightClient<T> {
             .into());
         }
 
+        if let Some(zk_verifying_key_hash) = client_state.data.zk_verifying_key_hash {
+            T::verify_verifying_key_hash(zk_verifying_key_hash).map_err(Error::InvalidZKP)?;
+        }
+
         T::verify_zkp(
             &client_state.data.chain_id,
             trusted_validators_hash.into_encoding(),
@@ -368,6 +372,7 @@ impl<T: ZkpVerifier> IbcClient for CometblsLightClient<T> {
                     trusting_period: scs.trusting_period,
                     latest_height: scs.latest_height,
                     frozen_height: ZERO_HEIGHT,
+                    zk_verifying_key_hash: scs.zk_verifying_key_hash,
                     ..subject_client_state.data
                 },
                 checksum: subject_client_state.checksum,
@@ -10,6 +10,17 @@ pub trait ZkpVerifier {
     ) -> Result<(), cometbls_groth16_verifier::Error> {
         cometbls_groth16_verifier::verify_zkp(chain_id, trusted_validators_hash, header, zkp)
     }
+
+    /// Check that `zk_verifying_key_hash` (as pinned in the client state) is the hash of the
+    /// verifying key compiled into the verifier, [`VERIFYING_KEY_HASH`]. Nothing carried in the
+    /// proof is checked here.
+    ///
+    /// [`VERIFYING_KEY_HASH`]: cometbls_groth16_verifier::VERIFYING_KEY_HASH
+    fn verify_verifying_key_hash(
+        zk_verifying_key_hash: H256,
+    ) -> Result<(), cometbls_groth16_verifier::Error> {
+        cometbls_groth16_verifier::verify_verifying_key_hash(zk_verifying_key_hash)
+    }
 }
 
 impl ZkpVerifier for () {}
@@ -25,4 +36,10 @@ impl ZkpVerifier for MockZKPVerifier {
     ) -> Result<(), cometbls_groth16_verifier::Error> {
         Ok(())
     }
+
+    fn verify_verifying_key_hash(
+        _zk_verifying_key_hash: H256,
+    ) -> Result<(), cometbls_groth16_verifier::Error> {
+        Ok(())
+    }
 }
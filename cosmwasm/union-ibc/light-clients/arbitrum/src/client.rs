@@ -96,7 +96,7 @@ impl IbcClient for ArbitrumLightClient {
         Err(Error::Unimplemented.into())
     }
 
-    fn status(client_state: &Self::ClientState) -> Status {
+    fn status(_ctx: IbcClientCtx<Self>, client_state: &Self::ClientState) -> Status {
         if client_state.frozen_height.height() != 0 {
             Status::Frozen
         } else {
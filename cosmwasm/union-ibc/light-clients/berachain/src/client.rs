@@ -74,7 +74,10 @@ impl IbcClient for BerachainLightClient {
         client_state.latest_height
     }
 
-    fn status(_client_state: &Self::ClientState) -> Status {
+    fn status(
+        _ctx: union_ibc_light_client::IbcClientCtx<Self>,
+        _client_state: &Self::ClientState,
+    ) -> Status {
         // FIXME: expose the ctx to this call to allow threading this call to L1
         // client. generally, we want to thread if a client is an L2 so always
         // provide the ctx?
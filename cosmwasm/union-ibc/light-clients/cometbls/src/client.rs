@@ -85,7 +85,10 @@ impl<T: ZkpVerifier> union_ibc_light_client::IbcClient for CometblsLightClient<T
     }
 
     // TODO(aeryz): pass ctx
-    fn status(client_state: &Self::ClientState) -> union_ibc_msg::lightclient::Status {
+    fn status(
+        _ctx: IbcClientCtx<Self>,
+        client_state: &Self::ClientState,
+    ) -> union_ibc_msg::lightclient::Status {
         if client_state.frozen_height.height() != 0 {
             Status::Frozen
         } else {
@@ -216,6 +219,10 @@ fn verify_header<T: ZkpVerifier>(
         .into());
     }
 
+    if let Some(zk_verifying_key_hash) = client_state.zk_verifying_key_hash {
+        T::verify_verifying_key_hash(zk_verifying_key_hash).map_err(Error::InvalidZKP)?;
+    }
+
     T::verify_zkp(
         &client_state.chain_id,
         trusted_validators_hash.into_encoding(),
@@ -78,7 +78,7 @@ impl union_ibc_light_client::IbcClient for EthereumLightClient {
         client_state.latest_height
     }
 
-    fn status(client_state: &Self::ClientState) -> Status {
+    fn status(_ctx: IbcClientCtx<Self>, client_state: &Self::ClientState) -> Status {
         if client_state.frozen_height.height() != 0 {
             Status::Frozen
         } else {
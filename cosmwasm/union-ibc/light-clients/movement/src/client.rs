@@ -76,9 +76,25 @@ impl union_ibc_light_client::IbcClient for MovementLightClient {
         client_state.latest_block_num
     }
 
-    fn status(client_state: &Self::ClientState) -> Status {
-        if client_state.frozen_height.height() != 0 {
-            Status::Frozen
+    fn status(
+        ctx: union_ibc_light_client::IbcClientCtx<Self>,
+        client_state: &Self::ClientState,
+    ) -> Status {
+        if client_state.is_frozen() {
+            return Status::Frozen;
+        }
+
+        let Ok(consensus_state) = ctx.read_self_consensus_state(client_state.latest_block_num)
+        else {
+            return Status::Expired;
+        };
+
+        // movement timestamps are in microseconds
+        if client_state.is_expired(
+            consensus_state.timestamp,
+            ctx.env.block.time.nanos() / 1_000,
+        ) {
+            Status::Expired
         } else {
             Status::Active
         }
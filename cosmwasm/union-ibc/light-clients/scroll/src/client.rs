@@ -98,7 +98,7 @@ impl IbcClient for ScrollLightClient {
         client_state.latest_slot
     }
 
-    fn status(client_state: &Self::ClientState) -> Status {
+    fn status(_ctx: IbcClientCtx<Self>, client_state: &Self::ClientState) -> Status {
         if client_state.frozen_height.height() == 0 {
             Status::Active
         } else {
@@ -132,7 +132,7 @@ impl IbcClient for TendermintLightClient {
         Err(Error::Unimplemented.into())
     }
 
-    fn status(client_state: &Self::ClientState) -> Status {
+    fn status(_ctx: IbcClientCtx<Self>, client_state: &Self::ClientState) -> Status {
         // FIXME: read latest consensus to verify if client expired
         // if is_client_expired(
         //     &consensus_state.timestamp,
@@ -0,0 +1,186 @@
+pragma solidity ^0.8.27;
+
+import "forge-std/Script.sol";
+
+import "../contracts/core/25-handler/IBCHandler.sol";
+import "../contracts/core/24-host/IBCCommitment.sol";
+
+contract StorePathKeysHandler is IBCHandler {
+    function commit(bytes32 key, bytes32 commitment) public {
+        commitments[key] = commitment;
+    }
+}
+
+// Generates the store path vectors checked by the ibc-union-spec crate
+// (lib/ibc-union-spec/src/test/store_path_keys.json): the key of each path as
+// computed by IBCCommitment, and the storage slot of its commitment in the
+// handler, read back from the storage of a deployed handler.
+//
+// FOUNDRY_PROFILE=script forge script scripts/StorePathKeys.s.sol --json \
+//   | jq '.logs[0] | fromjson' > ../lib/ibc-union-spec/src/test/store_path_keys.json
+contract StorePathKeys is Script {
+    bytes32 constant BATCH_HASH =
+        hex"000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+
+    StorePathKeysHandler handler;
+    string[] vectors;
+
+    function run() public {
+        handler = new StorePathKeysHandler();
+
+        clientState(0);
+        clientState(1);
+        clientState(type(uint32).max);
+
+        consensusState(1, 0);
+        consensusState(1, 1);
+        consensusState(7, type(uint64).max);
+
+        connection(0);
+        connection(1);
+        connection(type(uint32).max);
+
+        channel(0);
+        channel(3);
+        channel(type(uint32).max);
+
+        batchPackets(1, bytes32(0));
+        batchPackets(3, BATCH_HASH);
+        batchPackets(type(uint32).max, bytes32(type(uint256).max));
+
+        batchReceipts(1, bytes32(0));
+        batchReceipts(3, BATCH_HASH);
+        batchReceipts(type(uint32).max, bytes32(type(uint256).max));
+
+        string memory json = "[";
+        for (uint256 i = 0; i < vectors.length; i++) {
+            json = string.concat(json, i == 0 ? "" : ",", vectors[i]);
+        }
+        console.log(string.concat(json, "]"));
+    }
+
+    function clientState(
+        uint32 clientId
+    ) internal {
+        push(
+            string.concat(
+                '{"ClientState":{"client_id":', vm.toString(clientId), "}}"
+            ),
+            string.concat("clients/", vm.toString(clientId), "/clientState"),
+            IBCCommitment.clientStateCommitmentKey(clientId)
+        );
+    }
+
+    function consensusState(uint32 clientId, uint64 height) internal {
+        push(
+            string.concat(
+                '{"ConsensusState":{"client_id":',
+                vm.toString(clientId),
+                ',"height":',
+                vm.toString(height),
+                "}}"
+            ),
+            string.concat(
+                "clients/",
+                vm.toString(clientId),
+                "/consensusStates/",
+                vm.toString(height)
+            ),
+            IBCCommitment.consensusStateCommitmentKey(clientId, height)
+        );
+    }
+
+    function connection(
+        uint32 connectionId
+    ) internal {
+        push(
+            string.concat(
+                '{"Connection":{"connection_id":',
+                vm.toString(connectionId),
+                "}}"
+            ),
+            string.concat("connections/", vm.toString(connectionId)),
+            IBCCommitment.connectionCommitmentKey(connectionId)
+        );
+    }
+
+    function channel(
+        uint32 channelId
+    ) internal {
+        push(
+            string.concat(
+                '{"Channel":{"channel_id":', vm.toString(channelId), "}}"
+            ),
+            string.concat("channels/", vm.toString(channelId)),
+            IBCCommitment.channelCommitmentKey(channelId)
+        );
+    }
+
+    function batchPackets(uint32 channelId, bytes32 batchHash) internal {
+        push(
+            string.concat(
+                '{"BatchPackets":{"channel_id":',
+                vm.toString(channelId),
+                ',"batch_hash":"',
+                vm.toString(batchHash),
+                '"}}'
+            ),
+            string.concat(
+                "channels/",
+                vm.toString(channelId),
+                "/batchPackets/",
+                vm.toString(batchHash)
+            ),
+            IBCCommitment.batchPacketsCommitmentKey(channelId, batchHash)
+        );
+    }
+
+    function batchReceipts(uint32 channelId, bytes32 batchHash) internal {
+        push(
+            string.concat(
+                '{"BatchReceipts":{"channel_id":',
+                vm.toString(channelId),
+                ',"batch_hash":"',
+                vm.toString(batchHash),
+                '"}}'
+            ),
+            string.concat(
+                "channels/",
+                vm.toString(channelId),
+                "/batchReceipts/",
+                vm.toString(batchHash)
+            ),
+            IBCCommitment.batchReceiptsCommitmentKey(channelId, batchHash)
+        );
+    }
+
+    function push(
+        string memory path,
+        string memory display,
+        bytes32 key
+    ) internal {
+        // commitments is the first slot of the handler storage
+        bytes32 commitmentKey = keccak256(abi.encode(key, uint256(0)));
+
+        bytes32 commitment = keccak256(bytes(display));
+        handler.commit(key, commitment);
+        require(
+            vm.load(address(handler), commitmentKey) == commitment,
+            "commitment is not stored at the commitment key"
+        );
+
+        vectors.push(
+            string.concat(
+                '{"path":',
+                path,
+                ',"display":"',
+                display,
+                '","key":"',
+                vm.toString(key),
+                '","commitment_key":"',
+                vm.toString(commitmentKey),
+                '"}'
+            )
+        );
+    }
+}
@@ -19,6 +19,10 @@ pub struct ClientState {
     #[prost(message, optional, tag = "6")]
     pub latest_height:
         ::core::option::Option<super::super::super::super::super::ibc::core::client::v1::Height>,
+    /// Hash of the groth16 verifying key that proofs for this client must be
+    /// verified with. Empty if any verifying key is accepted.
+    #[prost(bytes = "vec", tag = "7")]
+    pub zk_verifying_key_hash: ::prost::alloc::vec::Vec<u8>,
 }
 impl ::prost::Name for ClientState {
     const NAME: &'static str = "ClientState";
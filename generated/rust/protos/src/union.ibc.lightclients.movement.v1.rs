@@ -17,6 +17,8 @@ pub struct ClientState {
         ::core::option::Option<super::super::super::super::super::ibc::core::client::v1::Height>,
     #[prost(uint64, tag = "7")]
     pub latest_block_num: u64,
+    #[prost(uint64, tag = "8")]
+    pub trusting_period: u64,
 }
 impl ::prost::Name for ClientState {
     const NAME: &'static str = "ClientState";
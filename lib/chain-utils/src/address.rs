@@ -0,0 +1,227 @@
+//! Addresses of the relayer, in the format of the chain they are submitted to.
+//!
+//! ibc-union datagrams carry the address of the submitting key as the `relayer`, which must be in
+//! the format of the destination chain: bech32 on cosmos-sdk chains, 20 byte hex on EVM chains,
+//! and 32 byte hex account addresses on move chains. [`RelayerAddress`] derives these from the
+//! signer types of the respective transaction plugins.
+
+use core::{fmt, str::FromStr};
+
+use bip32::secp256k1::ecdsa::SigningKey;
+use ripemd::Ripemd160;
+use sha2::{Digest, Sha256};
+use sha3::Sha3_256;
+use unionlabs::{
+    bech32::Bech32,
+    bytes::Bytes,
+    ethereum::keccak256,
+    hash::{H160, H256},
+    signer::CosmosSigner,
+};
+
+/// The address of a relayer, in the format of the chain it relays to.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum RelayerAddress {
+    /// A bech32 address, as used by cosmos-sdk chains.
+    Bech32 { hrp: String, data: Bytes },
+    /// An EVM address.
+    Eth(H160),
+    /// An aptos (move) account address.
+    Aptos(H256),
+}
+
+impl RelayerAddress {
+    /// The address of a cosmos-sdk signer, `bech32(prefix, ripemd160(sha256(pubkey)))`.
+    pub fn cosmos(signer: &CosmosSigner) -> Self {
+        Self::Bech32 {
+            hrp: signer.prefix().to_owned(),
+            data: Ripemd160::new()
+                .chain_update(Sha256::new().chain_update(signer.public_key()).finalize())
+                .finalize()
+                .to_vec()
+                .into(),
+        }
+    }
+
+    /// The EVM address of `signing_key`, the last 20 bytes of the keccak256 of the uncompressed
+    /// public key.
+    pub fn eth(signing_key: &SigningKey) -> Self {
+        let public_key = signing_key.verifying_key().to_encoded_point(false);
+
+        // skip the 0x04 sec1 tag of the uncompressed point
+        let hash = keccak256(&public_key.as_bytes()[1..]);
+
+        Self::Eth(H160::new(
+            hash.get()[12..].try_into().expect("hash is 32 bytes; qed;"),
+        ))
+    }
+
+    /// The bech32 address of `signing_key` on a cosmos-sdk chain that uses ethsecp256k1 keys
+    /// (such as injective or evmos), which is the [`eth`](Self::eth) address encoded as bech32.
+    pub fn eth_bech32(signing_key: &SigningKey, hrp: impl Into<String>) -> Self {
+        Self::eth(signing_key)
+            .to_bech32(hrp)
+            .expect("eth addresses can be encoded as bech32; qed;")
+    }
+
+    /// The aptos account address of the ed25519 `public_key`, `sha3_256(pubkey || 0x00)`.
+    pub fn aptos(public_key: &[u8; 32]) -> Self {
+        Self::Aptos(
+            Sha3_256::new()
+                .chain_update(public_key)
+                // single-signature ed25519 authentication scheme
+                .chain_update([0])
+                .finalize()
+                .into(),
+        )
+    }
+
+    /// Encode this address as bech32 with `hrp`.
+    ///
+    /// EVM addresses are encoded as is, and bech32 addresses are re-encoded with the new prefix.
+    /// Aptos addresses can't be converted, since they are derived from different keys.
+    pub fn to_bech32(&self, hrp: impl Into<String>) -> Option<Self> {
+        match self {
+            Self::Bech32 { data, .. } => Some(Self::Bech32 {
+                hrp: hrp.into(),
+                data: data.clone(),
+            }),
+            Self::Eth(address) => Some(Self::Bech32 {
+                hrp: hrp.into(),
+                data: address.get().to_vec().into(),
+            }),
+            Self::Aptos(_) => None,
+        }
+    }
+
+    /// The EVM address, if this is one.
+    pub fn as_eth(&self) -> Option<H160> {
+        match self {
+            Self::Eth(address) => Some(*address),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for RelayerAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Bech32 { hrp, data } => write!(f, "{}", Bech32::new(hrp, data)),
+            Self::Eth(address) => write!(f, "{address}"),
+            Self::Aptos(address) => write!(f, "{address}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+#[error("`{0}` is not a 0x-prefixed 20 or 32 byte hex address or a bech32 address")]
+pub struct ParseRelayerAddressError(pub String);
+
+impl FromStr for RelayerAddress {
+    type Err = ParseRelayerAddressError;
+
+    /// Parse an address in any of the supported formats. The format of hex addresses is
+    /// determined by their length.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || ParseRelayerAddressError(s.to_owned());
+
+        if s.starts_with("0x") {
+            match s.len() {
+                42 => s.parse().map(Self::Eth).map_err(|_| err()),
+                66 => s.parse().map(Self::Aptos).map_err(|_| err()),
+                _ => Err(err()),
+            }
+        } else {
+            let bech32 = Bech32::<Bytes>::decode(s).map_err(|_| err())?;
+
+            Ok(Self::Bech32 {
+                hrp: bech32.hrp().clone(),
+                data: bech32.data().clone(),
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hex_literal::hex;
+
+    use super::*;
+
+    // cspell:disable
+    fn signing_key() -> SigningKey {
+        <SigningKey as bip32::PrivateKey>::from_bytes(&hex!(
+            "4e9444a6efd6d42725a250b650a781da2737ea308c839eaccb0f7f3dbd2fea77"
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn cosmos() {
+        let address = RelayerAddress::cosmos(&CosmosSigner::new(signing_key(), "union".to_owned()));
+
+        assert_eq!(
+            address.to_string(),
+            "union14sarpj4p7l68eze5shfx4xtxr7vl92getd5xpq"
+        );
+        assert_eq!(
+            address.to_bech32("prefix").unwrap().to_string(),
+            "prefix14sarpj4p7l68eze5shfx4xtxr7vl92ge20mdc5"
+        );
+    }
+
+    #[test]
+    fn eth() {
+        assert_eq!(
+            RelayerAddress::eth(&signing_key()),
+            RelayerAddress::Eth(H160::new(hex!("be68fc2d8249eb60bfcf0e71d5a0d2f2e292c4ed")))
+        );
+
+        // the first default hardhat/anvil account
+        assert_eq!(
+            RelayerAddress::eth(
+                &<SigningKey as bip32::PrivateKey>::from_bytes(&hex!(
+                    "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80"
+                ))
+                .unwrap()
+            )
+            .to_string(),
+            "0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266"
+        );
+    }
+
+    #[test]
+    fn eth_bech32() {
+        assert_eq!(
+            RelayerAddress::eth_bech32(&signing_key(), "inj").to_string(),
+            "inj1he50ctvzf84kp070pecatgxj7t3f938dez40ee"
+        );
+    }
+
+    #[test]
+    fn aptos() {
+        // the public key of the first ed25519 test vector of RFC 8032
+        assert_eq!(
+            RelayerAddress::aptos(&hex!(
+                "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a"
+            ))
+            .to_string(),
+            "0x63c5215e87770d17b9f4cd47c777e322f4eb152cfd2054c1080fd9d57c48913b"
+        );
+    }
+
+    #[test]
+    fn parse_roundtrip() {
+        for address in [
+            RelayerAddress::cosmos(&CosmosSigner::new(signing_key(), "union".to_owned())),
+            RelayerAddress::eth(&signing_key()),
+            RelayerAddress::aptos(&[1; 32]),
+        ] {
+            assert_eq!(address.to_string().parse::<RelayerAddress>(), Ok(address));
+        }
+
+        assert!("0x1234".parse::<RelayerAddress>().is_err());
+        assert!("union1invalid".parse::<RelayerAddress>().is_err());
+    }
+    // cspell:enable
+}
@@ -0,0 +1,462 @@
+//! Authentication for endpoints behind an authenticated RPC provider.
+//!
+//! Hosted RPC providers commonly require an `Authorization` header on every request, for both
+//! websocket and http/grpc connections. An [`EndpointAuth`] can be configured alongside an
+//! endpoint url; the secret is always read from an environment variable, never from the config
+//! file itself:
+//!
+//! ```json
+//! { "type": "bearer", "token_env": "RPC_TOKEN" }
+//! { "type": "basic", "username": "relayer", "password_env": "RPC_PASSWORD" }
+//! ```
+//!
+//! The resolved [`AuthorizationHeader`] is redacted in its [`Debug`] output. Each client then
+//! attaches it in its own way: see [`AuthorizationHeader::jsonrpsee_headers`] for cometbft rpc
+//! clients, [`GrpcAuth`] for tonic clients, and `http_provider` (with the `alloy` feature) for
+//! alloy providers.
+
+use std::{env, fmt};
+
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use tonic::{
+    metadata::{Ascii, MetadataValue},
+    service::{interceptor::InterceptedService, Interceptor},
+    transport::{Channel, Endpoint},
+    Request, Status,
+};
+use zeroize::Zeroizing;
+
+use crate::net::{GrpcConnector, NetworkConfig};
+
+pub const AUTHORIZATION: &str = "authorization";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum EndpointAuth {
+    /// `Authorization: Bearer <token>`.
+    Bearer {
+        /// The environment variable containing the token.
+        token_env: String,
+    },
+    /// `Authorization: Basic <base64(username:password)>`.
+    Basic {
+        username: String,
+        /// The environment variable containing the password.
+        password_env: String,
+    },
+}
+
+impl EndpointAuth {
+    /// The authentication scheme, for display purposes.
+    #[must_use]
+    pub const fn scheme(&self) -> &'static str {
+        match self {
+            EndpointAuth::Bearer { .. } => "bearer",
+            EndpointAuth::Basic { .. } => "basic",
+        }
+    }
+
+    /// Read the secret from the environment and build the `Authorization` header value.
+    pub fn resolve(&self) -> Result<AuthorizationHeader, EndpointAuthError> {
+        let value = match self {
+            EndpointAuth::Bearer { token_env } => {
+                Zeroizing::new(format!("Bearer {}", *read_env(token_env)?))
+            }
+            EndpointAuth::Basic {
+                username,
+                password_env,
+            } => {
+                let credentials =
+                    Zeroizing::new(format!("{username}:{}", *read_env(password_env)?));
+
+                Zeroizing::new(format!(
+                    "Basic {}",
+                    base64::engine::general_purpose::STANDARD.encode(credentials.as_bytes())
+                ))
+            }
+        };
+
+        // header values must be visible ascii (or spaces), which also rules out line breaks that
+        // would allow injecting additional headers
+        if !value.bytes().all(|b| b == b' ' || b.is_ascii_graphic()) {
+            return Err(EndpointAuthError::InvalidHeaderValue);
+        }
+
+        Ok(AuthorizationHeader(value))
+    }
+}
+
+/// Resolve an optional [`EndpointAuth`].
+pub fn resolve(
+    auth: Option<&EndpointAuth>,
+) -> Result<Option<AuthorizationHeader>, EndpointAuthError> {
+    auth.map(EndpointAuth::resolve).transpose()
+}
+
+fn read_env(var: &str) -> Result<Zeroizing<String>, EndpointAuthError> {
+    let value = Zeroizing::new(env::var(var).map_err(|source| EndpointAuthError::Env {
+        var: var.to_owned(),
+        source,
+    })?);
+
+    if value.trim().is_empty() {
+        return Err(EndpointAuthError::EmptyEnv {
+            var: var.to_owned(),
+        });
+    }
+
+    Ok(Zeroizing::new(value.trim().to_owned()))
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum EndpointAuthError {
+    #[error("unable to read environment variable {var}")]
+    Env {
+        var: String,
+        #[source]
+        source: env::VarError,
+    },
+    #[error("environment variable {var} is empty")]
+    EmptyEnv { var: String },
+    #[error("the authorization header contains invalid characters")]
+    InvalidHeaderValue,
+}
+
+/// The value of an `Authorization` header, i.e. `Bearer <token>`.
+#[derive(Clone, PartialEq, Eq)]
+pub struct AuthorizationHeader(Zeroizing<String>);
+
+impl fmt::Debug for AuthorizationHeader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("AuthorizationHeader(<redacted>)")
+    }
+}
+
+impl AuthorizationHeader {
+    /// The header value. Take care not to log this.
+    #[must_use]
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+
+    /// The headers to pass to [`cometbft_rpc::Client::new_with_headers`].
+    #[must_use]
+    pub fn jsonrpsee_headers(auth: Option<&Self>) -> cometbft_rpc::HeaderMap {
+        let mut headers = cometbft_rpc::HeaderMap::new();
+
+        if let Some(auth) = auth {
+            let mut value = cometbft_rpc::HeaderValue::from_str(auth.expose())
+                .expect("header value is validated on construction; qed;");
+            value.set_sensitive(true);
+
+            headers.insert(AUTHORIZATION, value);
+        }
+
+        headers
+    }
+}
+
+/// A tonic [`Interceptor`] attaching the `Authorization` header to each request, if configured.
+#[derive(Clone, Default)]
+pub struct GrpcAuth {
+    value: Option<MetadataValue<Ascii>>,
+    network: NetworkConfig,
+}
+
+impl fmt::Debug for GrpcAuth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("GrpcAuth")
+            .field(&self.value.as_ref().map(|_| "<redacted>"))
+            .finish()
+    }
+}
+
+impl GrpcAuth {
+    #[must_use]
+    pub fn new(auth: Option<&AuthorizationHeader>) -> Self {
+        Self {
+            value: auth.map(|auth| {
+                let mut value = MetadataValue::try_from(auth.expose())
+                    .expect("header value is validated on construction; qed;");
+                value.set_sensitive(true);
+                value
+            }),
+            network: NetworkConfig::default(),
+        }
+    }
+
+    /// Open connections with `network` in [`connect`](Self::connect), i.e. through a proxy.
+    #[must_use]
+    pub fn with_network(mut self, network: NetworkConfig) -> Self {
+        self.network = network;
+        self
+    }
+
+    /// Connect to the grpc endpoint at `url`. The returned service can be passed to the `new`
+    /// constructor of any generated grpc client, i.e.
+    /// `QueryClient::new(grpc_auth.connect(url).await?)`.
+    pub async fn connect(
+        &self,
+        url: impl Into<String>,
+    ) -> Result<InterceptedService<Channel, Self>, tonic::transport::Error> {
+        let endpoint = Endpoint::from_shared(url.into())?;
+
+        let channel = if self.network.is_direct() {
+            endpoint.connect().await?
+        } else {
+            endpoint
+                .connect_with_connector(GrpcConnector::new(self.network.clone()))
+                .await?
+        };
+
+        Ok(InterceptedService::new(channel, self.clone()))
+    }
+}
+
+/// Build an alloy provider for the http json-rpc endpoint at `url`, sending `auth` with every
+/// request and connecting through the proxy in `network`, if any.
+#[cfg(feature = "alloy")]
+pub fn http_provider(
+    url: &crate::endpoint::HttpUrl,
+    auth: Option<&AuthorizationHeader>,
+    network: &NetworkConfig,
+) -> Result<alloy::providers::RootProvider<alloy::transports::BoxTransport>, crate::BoxDynError> {
+    use alloy::{
+        providers::ProviderBuilder,
+        rpc::client::RpcClient,
+        transports::http::{
+            reqwest::{
+                self,
+                header::{HeaderMap, HeaderValue},
+            },
+            Http,
+        },
+    };
+
+    let mut headers = HeaderMap::new();
+
+    if let Some(auth) = auth {
+        let mut value = HeaderValue::from_str(auth.expose())?;
+        value.set_sensitive(true);
+
+        headers.insert(AUTHORIZATION, value);
+    }
+
+    let mut client = reqwest::Client::builder().default_headers(headers);
+
+    if let Some(proxy) = &network.proxy {
+        client = client.proxy(reqwest::Proxy::all(proxy.as_str())?);
+    }
+
+    if network.prefer_ipv6 {
+        client = client.dns_resolver(std::sync::Arc::new(crate::net::PreferredFamilyResolver {
+            prefer_ipv6: true,
+        }));
+    }
+
+    let transport = Http::with_client(client.build()?, url.as_str().parse()?);
+
+    // authenticated endpoints are hosted providers, so this is never a local node
+    Ok(ProviderBuilder::new().on_client(RpcClient::new(transport, false).boxed()))
+}
+
+impl Interceptor for GrpcAuth {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        if let Some(value) = &self.value {
+            request.metadata_mut().insert(AUTHORIZATION, value.clone());
+        }
+
+        Ok(request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config_shape() {
+        assert_eq!(
+            serde_json::from_str::<EndpointAuth>(r#"{"type":"bearer","token_env":"TOKEN"}"#)
+                .unwrap(),
+            EndpointAuth::Bearer {
+                token_env: "TOKEN".to_owned()
+            }
+        );
+
+        assert_eq!(
+            serde_json::from_str::<EndpointAuth>(
+                r#"{"type":"basic","username":"relayer","password_env":"PASSWORD"}"#
+            )
+            .unwrap(),
+            EndpointAuth::Basic {
+                username: "relayer".to_owned(),
+                password_env: "PASSWORD".to_owned()
+            }
+        );
+
+        // secrets can not be inlined in the config
+        assert!(
+            serde_json::from_str::<EndpointAuth>(r#"{"type":"bearer","token":"secret"}"#).is_err()
+        );
+    }
+
+    #[test]
+    fn resolve_bearer() {
+        env::set_var("CHAIN_UTILS_AUTH_TEST_TOKEN", "secret-token\n");
+
+        let header = EndpointAuth::Bearer {
+            token_env: "CHAIN_UTILS_AUTH_TEST_TOKEN".to_owned(),
+        }
+        .resolve()
+        .unwrap();
+
+        assert_eq!(header.expose(), "Bearer secret-token");
+        assert_eq!(format!("{header:?}"), "AuthorizationHeader(<redacted>)");
+    }
+
+    #[test]
+    fn resolve_basic() {
+        env::set_var("CHAIN_UTILS_AUTH_TEST_PASSWORD", "hunter2");
+
+        let header = EndpointAuth::Basic {
+            username: "relayer".to_owned(),
+            password_env: "CHAIN_UTILS_AUTH_TEST_PASSWORD".to_owned(),
+        }
+        .resolve()
+        .unwrap();
+
+        // base64("relayer:hunter2")
+        assert_eq!(header.expose(), "Basic cmVsYXllcjpodW50ZXIy");
+    }
+
+    #[test]
+    fn missing_env() {
+        env::remove_var("CHAIN_UTILS_AUTH_TEST_MISSING");
+        env::set_var("CHAIN_UTILS_AUTH_TEST_EMPTY", " ");
+
+        assert!(matches!(
+            EndpointAuth::Bearer {
+                token_env: "CHAIN_UTILS_AUTH_TEST_MISSING".to_owned(),
+            }
+            .resolve(),
+            Err(EndpointAuthError::Env { var, .. }) if var == "CHAIN_UTILS_AUTH_TEST_MISSING"
+        ));
+
+        assert!(matches!(
+            EndpointAuth::Bearer {
+                token_env: "CHAIN_UTILS_AUTH_TEST_EMPTY".to_owned(),
+            }
+            .resolve(),
+            Err(EndpointAuthError::EmptyEnv { .. })
+        ));
+    }
+
+    #[test]
+    fn header_injection_is_rejected() {
+        env::set_var("CHAIN_UTILS_AUTH_TEST_INJECTION", "token\r\nx-other: 1");
+
+        assert!(matches!(
+            EndpointAuth::Bearer {
+                token_env: "CHAIN_UTILS_AUTH_TEST_INJECTION".to_owned(),
+            }
+            .resolve(),
+            Err(EndpointAuthError::InvalidHeaderValue)
+        ));
+    }
+
+    fn header() -> AuthorizationHeader {
+        AuthorizationHeader(Zeroizing::new("Bearer secret-token".to_owned()))
+    }
+
+    #[test]
+    fn jsonrpsee_headers() {
+        let headers = AuthorizationHeader::jsonrpsee_headers(Some(&header()));
+
+        assert_eq!(
+            headers[AUTHORIZATION].to_str().unwrap(),
+            "Bearer secret-token"
+        );
+        assert!(headers[AUTHORIZATION].is_sensitive());
+
+        assert!(AuthorizationHeader::jsonrpsee_headers(None).is_empty());
+    }
+
+    #[test]
+    fn grpc_interceptor() {
+        let mut auth = GrpcAuth::new(Some(&header()));
+
+        let request = auth.call(Request::new(())).unwrap();
+        assert_eq!(
+            request
+                .metadata()
+                .get(AUTHORIZATION)
+                .unwrap()
+                .to_str()
+                .unwrap(),
+            "Bearer secret-token"
+        );
+
+        assert_eq!(format!("{auth:?}"), r#"GrpcAuth(Some("<redacted>"))"#);
+
+        let request = GrpcAuth::default().call(Request::new(())).unwrap();
+        assert!(request.metadata().get(AUTHORIZATION).is_none());
+    }
+
+    #[cfg(feature = "alloy")]
+    #[tokio::test]
+    async fn http_provider_sends_authorization() {
+        use alloy::providers::Provider;
+        use tokio::{
+            io::{AsyncReadExt, AsyncWriteExt},
+            net::TcpListener,
+        };
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap())
+            .parse()
+            .unwrap();
+
+        // accept a single request and return its headers
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+
+            let mut request = vec![];
+            let mut chunk = [0; 1024];
+            while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+                let read = stream.read(&mut chunk).await.unwrap();
+                if read == 0 {
+                    break;
+                }
+                request.extend_from_slice(&chunk[..read]);
+            }
+
+            let body = r#"{"jsonrpc":"2.0","id":0,"result":"0x1"}"#;
+            stream
+                .write_all(
+                    format!(
+                        "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\n\
+                        content-length: {}\r\n\r\n{body}",
+                        body.len()
+                    )
+                    .as_bytes(),
+                )
+                .await
+                .unwrap();
+
+            String::from_utf8_lossy(&request).to_ascii_lowercase()
+        });
+
+        let provider = http_provider(&url, Some(&header()), &NetworkConfig::default()).unwrap();
+
+        // the mock server only captures the request, the response is irrelevant
+        let _ = provider.get_chain_id().await;
+
+        let request = server.await.unwrap();
+        assert!(
+            request.contains("\r\nauthorization: bearer secret-token\r\n"),
+            "{request}"
+        );
+    }
+}
@@ -0,0 +1,534 @@
+//! In-memory maps and sets with a bounded size, for state that is kept for the lifetime of a
+//! plugin.
+//!
+//! Plugins run for weeks at a time, so any cache or tracker that is only ever inserted into grows
+//! without bound, even if it is small in practice. [`BoundedMap`] and [`ExpiringSet`] bound their
+//! entries both by count and by age:
+//!
+//! - Entries expire once they were inserted longer than the TTL ago, and are not returned after.
+//! - Once there are more entries than the capacity, the entries that were inserted the longest
+//!   ago are evicted first. Reading an entry does not refresh it, re-inserting it does.
+//!
+//! Insertion order is tracked with a monotonic counter in a queue alongside the entries. Since
+//! the TTL is the same for all entries, the oldest entries are always at the front of the queue,
+//! such that both expiry and eviction only ever pop from the front, which is amortized O(1) per
+//! insert. Queue slots of entries that were re-inserted or removed are skipped when popped, and
+//! compacted once they make up most of the queue.
+//!
+//! # Contention
+//!
+//! All state is behind a single [`Mutex`], which is held for the duration of a single operation
+//! and never across an `.await`. Operations are O(1) amortized, however a single insert may have
+//! to evict every entry that expired since the previous insert. The structures here are accessed a
+//! handful of times per block or batch, where this is negligible; state on a hot path with many
+//! concurrent writers should be sharded instead.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt::Debug,
+    hash::Hash,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+/// The bounds of a [`BoundedMap`] or [`ExpiringSet`], as configured by the operator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BoundsConfig {
+    /// The maximum amount of entries. Once exceeded, the entries that were inserted the longest
+    /// ago are evicted first.
+    pub max_entries: usize,
+    /// How long (in seconds) entries are kept after they are inserted. If not set, entries are
+    /// only evicted once the capacity is exceeded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttl_seconds: Option<u64>,
+}
+
+impl BoundsConfig {
+    #[must_use]
+    pub fn ttl(&self) -> Option<Duration> {
+        self.ttl_seconds.map(Duration::from_secs)
+    }
+}
+
+/// A map bounded by capacity and TTL, see the [module documentation](self).
+#[derive(Debug)]
+pub struct BoundedMap<K, V> {
+    name: &'static str,
+    max_entries: usize,
+    ttl: Option<Duration>,
+    inner: Mutex<Inner<K, V>>,
+}
+
+#[derive(Debug)]
+struct Inner<K, V> {
+    entries: HashMap<K, Entry<V>>,
+    /// `(seq, key)`, in insertion order.
+    order: VecDeque<(u64, K)>,
+    next_seq: u64,
+}
+
+#[derive(Debug)]
+struct Entry<V> {
+    value: V,
+    inserted_at: Instant,
+    /// The slot of this entry in [`Inner::order`].
+    seq: u64,
+}
+
+/// The amount of entries removed by a single eviction pass.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct Evicted {
+    expired: usize,
+    over_capacity: usize,
+}
+
+impl<K: Eq + Hash + Clone, V> Inner<K, V> {
+    fn is_expired(entry: &Entry<V>, ttl: Option<Duration>, now: Instant) -> bool {
+        ttl.is_some_and(|ttl| now.saturating_duration_since(entry.inserted_at) >= ttl)
+    }
+
+    fn evict(&mut self, max_entries: usize, ttl: Option<Duration>, now: Instant) -> Evicted {
+        let mut evicted = Evicted::default();
+
+        while let Some((seq, key)) = self.order.front() {
+            match self.entries.get(key) {
+                Some(entry) if entry.seq == *seq => {
+                    if Self::is_expired(entry, ttl, now) {
+                        evicted.expired += 1;
+                    } else if self.entries.len() > max_entries {
+                        evicted.over_capacity += 1;
+                    } else {
+                        break;
+                    }
+
+                    self.entries.remove(key);
+                }
+                // re-inserted or removed since
+                _ => {}
+            }
+
+            self.order.pop_front();
+        }
+
+        if self.order.len() > 2 * self.entries.len() + 16 {
+            let entries = &self.entries;
+            self.order
+                .retain(|(seq, key)| entries.get(key).is_some_and(|entry| entry.seq == *seq));
+        }
+
+        evicted
+    }
+}
+
+impl<K: Eq + Hash + Clone + Debug, V: Clone> BoundedMap<K, V> {
+    /// Create a new map, holding at most `max_entries` entries for at most `ttl` each. `name` is
+    /// used to identify the map in logs.
+    #[must_use]
+    pub fn new(name: &'static str, max_entries: usize, ttl: Option<Duration>) -> Self {
+        Self {
+            name,
+            max_entries,
+            ttl,
+            inner: Mutex::new(Inner {
+                entries: HashMap::new(),
+                order: VecDeque::new(),
+                next_seq: 0,
+            }),
+        }
+    }
+
+    #[must_use]
+    pub fn from_config(name: &'static str, config: &BoundsConfig) -> Self {
+        Self::new(name, config.max_entries, config.ttl())
+    }
+
+    #[must_use]
+    pub fn get(&self, key: &K) -> Option<V> {
+        self.get_at(key, Instant::now())
+    }
+
+    #[must_use]
+    pub fn get_at(&self, key: &K, now: Instant) -> Option<V> {
+        let mut inner = self.lock();
+
+        let entry = inner.entries.get(key)?;
+
+        if Inner::<K, V>::is_expired(entry, self.ttl, now) {
+            inner.entries.remove(key);
+            return None;
+        }
+
+        Some(entry.value.clone())
+    }
+
+    /// Insert `value` for `key`, returning the previous unexpired value if there was one.
+    pub fn insert(&self, key: K, value: V) -> Option<V> {
+        self.insert_at(key, value, Instant::now())
+    }
+
+    pub fn insert_at(&self, key: K, value: V, now: Instant) -> Option<V> {
+        self.update_at(key, now, |previous| (Some(value), previous.cloned()))
+    }
+
+    /// Call `f` with the current unexpired value of `key` if there is one, and insert the value
+    /// returned by `f` if it is `Some`. This is done under a single lock, such that the entry
+    /// can't be changed concurrently.
+    pub fn update<R>(&self, key: K, f: impl FnOnce(Option<&V>) -> (Option<V>, R)) -> R {
+        self.update_at(key, Instant::now(), f)
+    }
+
+    pub fn update_at<R>(
+        &self,
+        key: K,
+        now: Instant,
+        f: impl FnOnce(Option<&V>) -> (Option<V>, R),
+    ) -> R {
+        let mut inner = self.lock();
+
+        let current = inner
+            .entries
+            .get(&key)
+            .filter(|entry| !Inner::<K, V>::is_expired(entry, self.ttl, now))
+            .map(|entry| &entry.value);
+
+        let (value, r) = f(current);
+
+        if let Some(value) = value {
+            let seq = inner.next_seq;
+            inner.next_seq += 1;
+
+            inner.entries.insert(
+                key.clone(),
+                Entry {
+                    value,
+                    inserted_at: now,
+                    seq,
+                },
+            );
+            inner.order.push_back((seq, key));
+
+            let evicted = inner.evict(self.max_entries, self.ttl, now);
+
+            if evicted != Evicted::default() {
+                debug!(
+                    name = self.name,
+                    expired = evicted.expired,
+                    over_capacity = evicted.over_capacity,
+                    len = inner.entries.len(),
+                    "evicted entries"
+                );
+            }
+        }
+
+        r
+    }
+
+    pub fn remove(&self, key: &K) -> Option<V> {
+        self.lock().entries.remove(key).map(|entry| entry.value)
+    }
+
+    /// Evict all expired entries, returning how many were evicted. Expired entries are otherwise
+    /// only evicted on insert.
+    pub fn evict_expired(&self) -> usize {
+        self.evict_expired_at(Instant::now())
+    }
+
+    pub fn evict_expired_at(&self, now: Instant) -> usize {
+        let mut inner = self.lock();
+
+        let expired = inner.evict(self.max_entries, self.ttl, now).expired;
+
+        if expired > 0 {
+            debug!(
+                name = self.name,
+                expired,
+                len = inner.entries.len(),
+                "evicted expired entries"
+            );
+        }
+
+        expired
+    }
+
+    /// The amount of entries in the map. This may include expired entries that have not been
+    /// evicted yet, but never exceeds the capacity.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.lock().entries.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// All unexpired entries, in no particular order.
+    #[must_use]
+    pub fn to_vec(&self) -> Vec<(K, V)> {
+        self.to_vec_at(Instant::now())
+    }
+
+    #[must_use]
+    pub fn to_vec_at(&self, now: Instant) -> Vec<(K, V)> {
+        self.lock()
+            .entries
+            .iter()
+            .filter(|(_, entry)| !Inner::<K, V>::is_expired(entry, self.ttl, now))
+            .map(|(key, entry)| (key.clone(), entry.value.clone()))
+            .collect()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Inner<K, V>> {
+        self.inner.lock().expect("lock is not poisoned; qed;")
+    }
+}
+
+/// A set bounded by capacity and TTL, see the [module documentation](self).
+#[derive(Debug)]
+pub struct ExpiringSet<K>(BoundedMap<K, ()>);
+
+impl<K: Eq + Hash + Clone + Debug> ExpiringSet<K> {
+    #[must_use]
+    pub fn new(name: &'static str, max_entries: usize, ttl: Option<Duration>) -> Self {
+        Self(BoundedMap::new(name, max_entries, ttl))
+    }
+
+    #[must_use]
+    pub fn from_config(name: &'static str, config: &BoundsConfig) -> Self {
+        Self(BoundedMap::from_config(name, config))
+    }
+
+    /// Insert `key`, returning `true` if it was not already present. An already present key is
+    /// not refreshed.
+    pub fn insert(&self, key: K) -> bool {
+        self.insert_at(key, Instant::now())
+    }
+
+    pub fn insert_at(&self, key: K, now: Instant) -> bool {
+        self.0.update_at(key, now, |present| match present {
+            Some(()) => (None, false),
+            None => (Some(()), true),
+        })
+    }
+
+    #[must_use]
+    pub fn contains(&self, key: &K) -> bool {
+        self.contains_at(key, Instant::now())
+    }
+
+    #[must_use]
+    pub fn contains_at(&self, key: &K, now: Instant) -> bool {
+        self.0.get_at(key, now).is_some()
+    }
+
+    pub fn remove(&self, key: &K) -> bool {
+        self.0.remove(key).is_some()
+    }
+
+    pub fn evict_expired(&self) -> usize {
+        self.0.evict_expired()
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, thread};
+
+    use super::*;
+
+    const SECOND: Duration = Duration::from_secs(1);
+
+    #[test]
+    fn ttl_expiry() {
+        let t0 = Instant::now();
+        let map = BoundedMap::new("test", 10, Some(10 * SECOND));
+
+        map.insert_at(1, "a", t0);
+        map.insert_at(2, "b", t0 + 5 * SECOND);
+
+        assert_eq!(map.get_at(&1, t0 + 9 * SECOND), Some("a"));
+        assert_eq!(map.get_at(&1, t0 + 10 * SECOND), None);
+        assert_eq!(map.get_at(&2, t0 + 10 * SECOND), Some("b"));
+        assert_eq!(map.get_at(&2, t0 + 15 * SECOND), None);
+
+        // expired entries are not returned, but not necessarily evicted yet
+        map.insert_at(3, "c", t0);
+        assert_eq!(map.to_vec_at(t0 + 10 * SECOND), vec![]);
+        assert_eq!(map.evict_expired_at(t0 + 10 * SECOND), 1);
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn reinsert_refreshes_ttl() {
+        let t0 = Instant::now();
+        let map = BoundedMap::new("test", 10, Some(10 * SECOND));
+
+        map.insert_at(1, "a", t0);
+        assert_eq!(map.insert_at(1, "b", t0 + 8 * SECOND), Some("a"));
+
+        assert_eq!(map.get_at(&1, t0 + 12 * SECOND), Some("b"));
+        assert_eq!(map.evict_expired_at(t0 + 12 * SECOND), 0);
+        assert_eq!(map.evict_expired_at(t0 + 18 * SECOND), 1);
+
+        // an expired previous value is not returned
+        map.insert_at(2, "a", t0);
+        assert_eq!(map.insert_at(2, "b", t0 + 10 * SECOND), None);
+    }
+
+    #[test]
+    fn capacity_eviction_order() {
+        let t0 = Instant::now();
+        let map = BoundedMap::new("test", 3, None);
+
+        for i in 0..3 {
+            map.insert_at(i, i, t0);
+        }
+
+        // reads don't refresh entries
+        assert_eq!(map.get_at(&0, t0), Some(0));
+
+        // re-inserting does
+        map.insert_at(1, 10, t0);
+
+        map.insert_at(3, 3, t0);
+        assert_eq!(map.get_at(&0, t0), None);
+
+        map.insert_at(4, 4, t0);
+        assert_eq!(map.get_at(&2, t0), None);
+
+        let mut entries = map.to_vec_at(t0);
+        entries.sort_unstable();
+        assert_eq!(entries, vec![(1, 10), (3, 3), (4, 4)]);
+    }
+
+    #[test]
+    fn removed_entries_are_skipped() {
+        let t0 = Instant::now();
+        let map = BoundedMap::new("test", 2, None);
+
+        map.insert_at(1, (), t0);
+        map.insert_at(2, (), t0);
+        assert_eq!(map.remove(&1), Some(()));
+
+        // the slot of 1 is skipped, nothing is over capacity yet
+        map.insert_at(3, (), t0);
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get_at(&2, t0), Some(()));
+
+        map.insert_at(4, (), t0);
+        assert_eq!(map.get_at(&2, t0), None);
+    }
+
+    #[test]
+    fn order_is_compacted() {
+        let t0 = Instant::now();
+        let map = BoundedMap::new("test", 4, None);
+
+        // the same keys re-inserted over and over
+        for i in 0..10_000 {
+            map.insert_at(i % 4, i, t0);
+        }
+
+        assert_eq!(map.len(), 4);
+        assert!(map.lock().order.len() <= 2 * 4 + 16 + 1);
+    }
+
+    #[test]
+    fn update() {
+        let t0 = Instant::now();
+        let map = BoundedMap::new("test", 10, None);
+
+        let inserted = map.update_at(1, t0, |current| {
+            assert_eq!(current, None);
+            (Some(1), true)
+        });
+        assert!(inserted);
+
+        // keep the current value
+        let inserted = map.update_at(1, t0, |current| {
+            assert_eq!(current, Some(&1));
+            (None, false)
+        });
+        assert!(!inserted);
+        assert_eq!(map.get_at(&1, t0), Some(1));
+    }
+
+    #[test]
+    fn expiring_set() {
+        let t0 = Instant::now();
+        let set = ExpiringSet::new("test", 2, Some(10 * SECOND));
+
+        assert!(set.insert_at("a", t0));
+        assert!(!set.insert_at("a", t0 + SECOND));
+
+        // not refreshed by the second insert
+        assert!(!set.contains_at(&"a", t0 + 10 * SECOND));
+        assert!(set.insert_at("a", t0 + 10 * SECOND));
+
+        assert!(set.insert_at("b", t0 + 10 * SECOND));
+        assert!(set.insert_at("c", t0 + 10 * SECOND));
+        assert_eq!(set.len(), 2);
+        assert!(!set.contains_at(&"a", t0 + 10 * SECOND));
+
+        assert!(set.remove(&"b"));
+        assert!(!set.remove(&"b"));
+    }
+
+    #[test]
+    fn concurrent_access() {
+        const THREADS: usize = 8;
+        const INSERTS: usize = 10_000;
+        const MAX_ENTRIES: usize = 1_000;
+
+        let map = Arc::new(BoundedMap::new("test", MAX_ENTRIES, Some(60 * SECOND)));
+
+        thread::scope(|s| {
+            for t in 0..THREADS {
+                let map = map.clone();
+
+                s.spawn(move || {
+                    for i in 0..INSERTS {
+                        let key = (t, i);
+                        map.insert(key, i);
+
+                        assert!(map.len() <= MAX_ENTRIES);
+
+                        // the entry was either just inserted, or already evicted by another thread
+                        if let Some(value) = map.get(&key) {
+                            assert_eq!(value, i);
+                        }
+                    }
+                });
+            }
+        });
+
+        assert_eq!(map.len(), MAX_ENTRIES);
+    }
+
+    #[test]
+    fn config() {
+        let config = serde_json::from_str::<BoundsConfig>(r#"{"max_entries":100}"#).unwrap();
+
+        assert_eq!(config.ttl(), None);
+
+        let config =
+            serde_json::from_str::<BoundsConfig>(r#"{"max_entries":100,"ttl_seconds":60}"#)
+                .unwrap();
+
+        assert_eq!(config.ttl(), Some(60 * SECOND));
+    }
+}
@@ -1,4 +1,4 @@
-use std::sync::Arc;
+use std::{collections::BTreeMap, fmt, sync::Arc};
 
 use prost::{Message, Name};
 use serde::{Deserialize, Serialize};
@@ -43,12 +43,124 @@ pub struct GasConfig {
     pub max_gas: u64,
     #[serde(default)]
     pub min_gas: u64,
+    /// Multipliers for transactions containing specific kinds of messages, keyed by
+    /// [`GasMessageKind`]. A transaction has a single gas limit, so the highest multiplier of all of
+    /// the messages in it is used. Messages of kinds that are not listed here use
+    /// [`Self::gas_multiplier`].
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub per_message_multipliers: BTreeMap<String, f64>,
+    /// Allow multipliers below 1.0, which will cause transactions to run out of gas unless the
+    /// simulation overestimates the gas used.
+    #[serde(default)]
+    pub allow_low_multiplier: bool,
+}
+
+/// The kinds of messages that can be given a separate gas multiplier in
+/// [`GasConfig::per_message_multipliers`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GasMessageKind {
+    CreateClient,
+    UpdateClient,
+    ConnectionHandshake,
+    ChannelHandshake,
+    PacketRecv,
+    PacketAck,
+    PacketTimeout,
+    /// Any other execution of a wasm contract.
+    WasmExecute,
+}
+
+impl GasMessageKind {
+    pub const ALL: [Self; 8] = [
+        Self::CreateClient,
+        Self::UpdateClient,
+        Self::ConnectionHandshake,
+        Self::ChannelHandshake,
+        Self::PacketRecv,
+        Self::PacketAck,
+        Self::PacketTimeout,
+        Self::WasmExecute,
+    ];
+
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::CreateClient => "create_client",
+            Self::UpdateClient => "update_client",
+            Self::ConnectionHandshake => "connection_handshake",
+            Self::ChannelHandshake => "channel_handshake",
+            Self::PacketRecv => "packet_recv",
+            Self::PacketAck => "packet_ack",
+            Self::PacketTimeout => "packet_timeout",
+            Self::WasmExecute => "wasm_execute",
+        }
+    }
+}
+
+impl fmt::Display for GasMessageKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum GasConfigError {
+    #[error(
+        "`{field}` is {multiplier}, but must be at least 1.0 (set `allow_low_multiplier` to allow this)"
+    )]
+    LowMultiplier { field: String, multiplier: f64 },
+    #[error("`{field}` must be a finite number")]
+    NonFiniteMultiplier { field: String },
 }
 
 impl GasConfig {
+    /// Check that all multipliers are finite, and at least 1.0 unless
+    /// [`Self::allow_low_multiplier`] is set.
+    pub fn validate(&self) -> Result<(), GasConfigError> {
+        let check = |field: String, multiplier: f64| {
+            if !multiplier.is_finite() {
+                Err(GasConfigError::NonFiniteMultiplier { field })
+            } else if multiplier < 1.0 && !self.allow_low_multiplier {
+                Err(GasConfigError::LowMultiplier { field, multiplier })
+            } else {
+                Ok(())
+            }
+        };
+
+        check("gas_multiplier".to_owned(), self.gas_multiplier)?;
+
+        for (kind, multiplier) in &self.per_message_multipliers {
+            check(format!("per_message_multipliers.{kind}"), *multiplier)?;
+        }
+
+        Ok(())
+    }
+
+    /// The multiplier to use for a transaction containing messages of `kinds`; the highest
+    /// multiplier of any of the kinds, or [`Self::gas_multiplier`] for kinds without a configured
+    /// multiplier.
+    pub fn multiplier_for(&self, kinds: impl IntoIterator<Item = GasMessageKind>) -> f64 {
+        kinds
+            .into_iter()
+            .map(|kind| {
+                self.per_message_multipliers
+                    .get(kind.as_str())
+                    .copied()
+                    .unwrap_or(self.gas_multiplier)
+            })
+            .reduce(f64::max)
+            .unwrap_or(self.gas_multiplier)
+    }
+
     pub fn mk_fee(&self, gas: u64) -> Fee {
+        self.mk_fee_with_multiplier(gas, self.gas_multiplier)
+    }
+
+    /// Build the fee for `gas` with `gas_multiplier` instead of [`Self::gas_multiplier`] (see
+    /// [`Self::multiplier_for`]).
+    pub fn mk_fee_with_multiplier(&self, gas: u64, gas_multiplier: f64) -> Fee {
         // gas limit = provided gas * multiplier, clamped between min_gas and max_gas
-        let gas_limit = u128_saturating_mul_f64(gas.into(), self.gas_multiplier)
+        let gas_limit = u128_saturating_mul_f64(gas.into(), gas_multiplier)
             .clamp(self.min_gas.into(), self.max_gas.into());
 
         let amount = u128_saturating_mul_f64(gas.into(), self.gas_price);
@@ -922,3 +1034,99 @@ pub mod cosmos_sdk_error {
         )
     }
 }
+
+#[cfg(test)]
+fn gas_config(per_message_multipliers: &[(&str, f64)]) -> GasConfig {
+    GasConfig {
+        gas_price: 1.0,
+        gas_denom: "muno".to_owned(),
+        gas_multiplier: 1.2,
+        max_gas: 10_000_000,
+        min_gas: 0,
+        per_message_multipliers: per_message_multipliers
+            .iter()
+            .map(|(kind, multiplier)| ((*kind).to_owned(), *multiplier))
+            .collect(),
+        allow_low_multiplier: false,
+    }
+}
+
+#[test]
+fn max_multiplier_across_batch() {
+    let gas_config = gas_config(&[("update_client", 1.4), ("packet_recv", 1.1)]);
+
+    assert_eq!(gas_config.multiplier_for([GasMessageKind::PacketRecv]), 1.1);
+    assert_eq!(
+        gas_config.multiplier_for([GasMessageKind::PacketRecv, GasMessageKind::UpdateClient]),
+        1.4
+    );
+    // kinds without a multiplier use the global multiplier
+    assert_eq!(
+        gas_config.multiplier_for([GasMessageKind::PacketRecv, GasMessageKind::PacketAck]),
+        1.2
+    );
+    assert_eq!(gas_config.multiplier_for([]), 1.2);
+
+    assert_eq!(
+        gas_config
+            .mk_fee_with_multiplier(
+                1000,
+                gas_config.multiplier_for([
+                    GasMessageKind::UpdateClient,
+                    GasMessageKind::PacketRecv,
+                    GasMessageKind::PacketRecv,
+                ]),
+            )
+            .gas_limit,
+        1400
+    );
+}
+
+#[test]
+fn gas_message_kind_serde() {
+    for kind in GasMessageKind::ALL {
+        assert_eq!(
+            serde_json::to_value(kind).unwrap(),
+            serde_json::Value::String(kind.as_str().to_owned())
+        );
+    }
+}
+
+#[test]
+fn low_multipliers_are_rejected() {
+    assert_eq!(gas_config(&[("packet_recv", 1.0)]).validate(), Ok(()));
+
+    assert_eq!(
+        gas_config(&[("packet_recv", 0.9)]).validate(),
+        Err(GasConfigError::LowMultiplier {
+            field: "per_message_multipliers.packet_recv".to_owned(),
+            multiplier: 0.9,
+        })
+    );
+    assert_eq!(
+        GasConfig {
+            gas_multiplier: 0.5,
+            ..gas_config(&[])
+        }
+        .validate(),
+        Err(GasConfigError::LowMultiplier {
+            field: "gas_multiplier".to_owned(),
+            multiplier: 0.5,
+        })
+    );
+    assert_eq!(
+        gas_config(&[("update_client", f64::NAN)]).validate(),
+        Err(GasConfigError::NonFiniteMultiplier {
+            field: "per_message_multipliers.update_client".to_owned(),
+        })
+    );
+
+    assert_eq!(
+        GasConfig {
+            allow_low_multiplier: true,
+            ..gas_config(&[("packet_recv", 0.9)])
+        }
+        .validate(),
+        Ok(())
+    );
+}
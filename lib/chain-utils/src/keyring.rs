@@ -1,10 +1,23 @@
-use std::{collections::HashMap, fmt::Display, hash::Hash, path::PathBuf, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    env,
+    fmt::{self, Debug, Display},
+    fs,
+    hash::Hash,
+    io,
+    path::PathBuf,
+    process::{Command, ExitStatus, Stdio},
+    str,
+    sync::{Arc, RwLock},
+};
 
 use crossbeam_queue::ArrayQueue;
 use futures::Future;
 use rand::prelude::SliceRandom;
 use serde::{Deserialize, Serialize};
 use tracing::{info_span, warn, Instrument};
+use unionlabs::ErrorReporter;
+use zeroize::Zeroizing;
 
 pub trait ChainKeyring {
     type Address: Hash + Eq + Clone + Display + Send + Sync;
@@ -36,6 +49,10 @@ pub struct ConcurrentKeyring<A: Hash + Eq, S> {
     addresses_buffer: Arc<ArrayQueue<A>>,
 
     signers: Arc<HashMap<A, S>>,
+
+    /// Addresses that are skipped by [`Self::with`], i.e. because their balance is too low to pay
+    /// for transactions.
+    paused: Arc<RwLock<HashSet<A>>>,
 }
 
 pub struct KeyringEntry<A, S> {
@@ -76,6 +93,7 @@ impl<A: Hash + Eq + Clone + Display, S: 'static> ConcurrentKeyring<A, S> {
             key_to_address: Arc::new(key_to_address),
             addresses_buffer: Arc::new(addresses_buffer),
             signers: Arc::new(signers),
+            paused: Arc::new(RwLock::new(HashSet::new())),
         }
     }
 
@@ -83,15 +101,132 @@ impl<A: Hash + Eq + Clone + Display, S: 'static> ConcurrentKeyring<A, S> {
         self.key_to_address.iter().map(|(a, b)| (a.as_str(), b))
     }
 
+    /// Exclude `address` from being selected by [`Self::with`] until it is
+    /// [resumed](Self::resume). Returns `false` if the address is not in this keyring or was
+    /// already paused.
+    pub fn pause(&self, address: &A) -> bool {
+        self.address_to_key.contains_key(address)
+            && self
+                .paused
+                .write()
+                .expect("lock is not poisoned")
+                .insert(address.clone())
+    }
+
+    /// Include a [paused](Self::pause) `address` in the selection of [`Self::with`] again.
+    /// Returns `false` if the address was not paused.
+    pub fn resume(&self, address: &A) -> bool {
+        self.paused
+            .write()
+            .expect("lock is not poisoned")
+            .remove(address)
+    }
+
+    pub fn is_paused(&self, address: &A) -> bool {
+        self.paused
+            .read()
+            .expect("lock is not poisoned")
+            .contains(address)
+    }
+
+    /// Take the next available key that is not [paused](Self::pause). Paused keys are put back
+    /// at the end of the ring buffer, such that every key in the buffer is tried at most once.
+    fn next_unpaused(&self) -> Option<A> {
+        for _ in 0..self.addresses_buffer.capacity() {
+            let Some(address) = self.addresses_buffer.pop() else {
+                warn!(keyring = %self.name, "high traffic in keyring");
+                return None;
+            };
+
+            if !self.is_paused(&address) {
+                return Some(address);
+            }
+
+            self.addresses_buffer
+                .push(address)
+                .ok()
+                .expect("no additional items are added; qed;");
+        }
+
+        warn!(keyring = %self.name, "all available keys in keyring are paused");
+
+        None
+    }
+
+    /// Take the available key that is not [paused](Self::pause) with the lowest `rank`, skipping
+    /// keys that `rank` returns `None` for. Ties are broken by the order of the ring buffer.
+    ///
+    /// All of the available keys are taken out of the ring buffer while they are ranked, so a
+    /// concurrent [`Self::with`] may find the keyring empty.
+    fn take_preferred<K: Ord>(&self, rank: impl Fn(&A) -> Option<K>) -> Option<A> {
+        let mut available = Vec::with_capacity(self.addresses_buffer.len());
+
+        while let Some(address) = self.addresses_buffer.pop() {
+            available.push(address);
+        }
+
+        if available.is_empty() {
+            warn!(keyring = %self.name, "high traffic in keyring");
+            return None;
+        }
+
+        let selected = available
+            .iter()
+            .enumerate()
+            .filter(|(_, address)| !self.is_paused(address))
+            .filter_map(|(idx, address)| rank(address).map(|rank| (rank, idx)))
+            .min();
+
+        let selected = selected.map(|(_, idx)| available.remove(idx));
+
+        for address in available {
+            self.addresses_buffer
+                .push(address)
+                .ok()
+                .expect("no additional items are added; qed;");
+        }
+
+        if selected.is_none() {
+            warn!(keyring = %self.name, "none of the available keys in keyring are selectable");
+        }
+
+        selected
+    }
+
     pub async fn with<'a, F: FnOnce(&'a S) -> Fut + 'a, Fut: Future<Output: 'a> + 'a>(
         &'a self,
         f: F,
     ) -> Option<Fut::Output> {
-        let Some(address) = self.addresses_buffer.pop() else {
-            warn!(keyring = %self.name, "high traffic in keyring");
-            return None;
-        };
+        let address = self.next_unpaused()?;
 
+        Some(self.use_key(address, f).await)
+    }
+
+    /// Like [`Self::with`], but use the key with the lowest `rank` instead of the next key in the
+    /// ring buffer. Keys that `rank` returns `None` for are not used, i.e. because they are too
+    /// congested to submit to.
+    pub async fn with_preferred<
+        'a,
+        K: Ord,
+        F: FnOnce(&'a S) -> Fut + 'a,
+        Fut: Future<Output: 'a> + 'a,
+    >(
+        &'a self,
+        rank: impl Fn(&A) -> Option<K>,
+        f: F,
+    ) -> Option<Fut::Output> {
+        let address = self.take_preferred(rank)?;
+
+        Some(self.use_key(address, f).await)
+    }
+
+    /// Run `f` with the signer of `address`, which must have been taken out of the ring buffer,
+    /// and put it back at the end of the ring buffer afterwards.
+    async fn use_key<'a, F: FnOnce(&'a S) -> Fut + 'a, Fut: Future<Output: 'a> + 'a>(
+        &'a self,
+        address: A,
+        f: F,
+    ) -> Fut::Output {
         let key_name = self
             .address_to_key
             .get(&address)
@@ -112,7 +247,7 @@ impl<A: Hash + Eq + Clone + Display, S: 'static> ConcurrentKeyring<A, S> {
             .ok()
             .expect("no additional items are added; qed;");
 
-        Some(r)
+        r
     }
 }
 
@@ -124,13 +259,45 @@ pub struct KeyringConfig {
     pub keys: Vec<KeyringConfigEntry>,
 }
 
+impl KeyringConfig {
+    /// Resolve the key of every entry in this keyring, reading them from their [`KeySource`]s.
+    ///
+    /// This is intended to be called once on startup; the resolved keys are zeroized on drop, so
+    /// they should be dropped as soon as the signers are constructed.
+    pub fn resolve(&self) -> Result<Vec<ResolvedKey>, KeyResolutionError> {
+        self.keys.iter().map(KeyringConfigEntry::resolve).collect()
+    }
+}
+
+/// The key material of a keyring entry, read from its [`KeySource`].
+pub struct ResolvedKey {
+    pub name: String,
+    pub key: Zeroizing<Vec<u8>>,
+}
+
 impl KeyringConfigEntry {
+    pub fn resolve(&self) -> Result<ResolvedKey, KeyResolutionError> {
+        match self {
+            KeyringConfigEntry::File { path } => Err(KeyResolutionError {
+                name: path.display().to_string(),
+                kind: "file",
+                source: KeySourceError::Unsupported,
+            }),
+            KeyringConfigEntry::Raw { name, key } => Ok(ResolvedKey {
+                name: name.clone(),
+                key: key.resolve().map_err(|source| KeyResolutionError {
+                    name: name.clone(),
+                    kind: key.kind(),
+                    source,
+                })?,
+            }),
+        }
+    }
+
     pub fn value(&self) -> Vec<u8> {
-        match &self {
-            KeyringConfigEntry::File { path: _ } => {
-                panic!("file keyring is currently unimplemented")
-            }
-            KeyringConfigEntry::Raw { name: _, key } => key.clone(),
+        match self.resolve() {
+            Ok(resolved) => resolved.key.to_vec(),
+            Err(err) => panic!("{}", ErrorReporter(err)),
         }
     }
 
@@ -147,12 +314,531 @@ impl KeyringConfigEntry {
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case", tag = "type")]
 pub enum KeyringConfigEntry {
-    File {
+    File { path: PathBuf },
+    Raw { name: String, key: KeySource },
+}
+
+/// Where the private key of a keyring entry is read from.
+///
+/// A bare hex string is a [`KeySource::Raw`] key, the other sources are configured as an object
+/// with a single field, i.e. `{ "env": "RELAYER_KEY" }`.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "KeySourceRepr", into = "KeySourceRepr")]
+pub enum KeySource {
+    /// The key itself.
+    Raw(Vec<u8>),
+    /// A file containing the key, either as raw bytes or hex encoded.
+    File(PathBuf),
+    /// An environment variable containing the hex encoded key.
+    Env(String),
+    /// A command printing the hex encoded key to stdout, i.e. `pass show relayer/key`. This allows
+    /// for reading keys from external secret managers through their CLIs.
+    Exec {
+        command: String,
+        #[serde(default)]
+        args: Vec<String>,
+    },
+}
+
+impl KeySource {
+    /// The kind of this source, used in errors.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            KeySource::Raw(_) => "raw",
+            KeySource::File(_) => "file",
+            KeySource::Env(_) => "env",
+            KeySource::Exec { .. } => "exec",
+        }
+    }
+
+    pub fn resolve(&self) -> Result<Zeroizing<Vec<u8>>, KeySourceError> {
+        match self {
+            KeySource::Raw(key) => Ok(Zeroizing::new(key.clone())),
+            KeySource::File(path) => {
+                let contents =
+                    Zeroizing::new(fs::read(path).map_err(|source| KeySourceError::Read {
+                        path: path.clone(),
+                        source,
+                    })?);
+
+                // a hex encoded key is always valid utf8, anything else is the raw key
+                let decoded = str::from_utf8(&contents)
+                    .ok()
+                    .filter(|hex| is_hex(hex))
+                    .map(decode_hex);
+
+                match decoded {
+                    Some(decoded) => decoded,
+                    None => Ok(contents),
+                }
+            }
+            KeySource::Env(var) => {
+                let value =
+                    Zeroizing::new(env::var(var).map_err(|source| KeySourceError::Env {
+                        var: var.clone(),
+                        source,
+                    })?);
+
+                decode_hex(&value)
+            }
+            KeySource::Exec { command, args } => {
+                let output = Command::new(command)
+                    .args(args)
+                    .stdin(Stdio::null())
+                    .output()
+                    .map_err(|source| KeySourceError::Spawn {
+                        command: command.clone(),
+                        source,
+                    })?;
+
+                let stdout = Zeroizing::new(output.stdout);
+
+                if !output.status.success() {
+                    return Err(KeySourceError::ExecFailed {
+                        command: command.clone(),
+                        status: output.status,
+                        stderr: String::from_utf8_lossy(&output.stderr).trim().to_owned(),
+                    });
+                }
+
+                decode_hex(str::from_utf8(&stdout).map_err(|_| KeySourceError::InvalidUtf8)?)
+            }
+        }
+    }
+}
+
+impl Debug for KeySource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeySource::Raw(_) => f.debug_tuple("Raw").field(&"<redacted>").finish(),
+            KeySource::File(path) => f.debug_tuple("File").field(path).finish(),
+            KeySource::Env(var) => f.debug_tuple("Env").field(var).finish(),
+            KeySource::Exec { command, args } => f
+                .debug_struct("Exec")
+                .field("command", command)
+                .field("args", args)
+                .finish(),
+        }
+    }
+}
+
+fn is_hex(s: &str) -> bool {
+    let s = s.trim();
+    let s = s.strip_prefix("0x").unwrap_or(s);
+
+    !s.is_empty() && s.len() % 2 == 0 && s.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// Decode a hex encoded key, optionally `0x` prefixed and surrounded by whitespace.
+fn decode_hex(s: &str) -> Result<Zeroizing<Vec<u8>>, KeySourceError> {
+    let s = s.trim();
+
+    hex::decode(s.strip_prefix("0x").unwrap_or(s))
+        .map(Zeroizing::new)
+        .map_err(KeySourceError::InvalidHex)
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum KeySourceRepr {
+    Raw(#[serde(with = "::serde_utils::hex_string")] Vec<u8>),
+    External(ExternalKeySource),
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+enum ExternalKeySource {
+    File(PathBuf),
+    Env(String),
+    Exec {
+        command: String,
+        #[serde(default)]
+        args: Vec<String>,
+    },
+}
+
+impl From<KeySourceRepr> for KeySource {
+    fn from(value: KeySourceRepr) -> Self {
+        match value {
+            KeySourceRepr::Raw(key) => KeySource::Raw(key),
+            KeySourceRepr::External(ExternalKeySource::File(path)) => KeySource::File(path),
+            KeySourceRepr::External(ExternalKeySource::Env(var)) => KeySource::Env(var),
+            KeySourceRepr::External(ExternalKeySource::Exec { command, args }) => {
+                KeySource::Exec { command, args }
+            }
+        }
+    }
+}
+
+impl From<KeySource> for KeySourceRepr {
+    fn from(value: KeySource) -> Self {
+        match value {
+            KeySource::Raw(key) => KeySourceRepr::Raw(key),
+            KeySource::File(path) => KeySourceRepr::External(ExternalKeySource::File(path)),
+            KeySource::Env(var) => KeySourceRepr::External(ExternalKeySource::Env(var)),
+            KeySource::Exec { command, args } => {
+                KeySourceRepr::External(ExternalKeySource::Exec { command, args })
+            }
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("unable to resolve key `{name}` from {kind} source")]
+pub struct KeyResolutionError {
+    pub name: String,
+    pub kind: &'static str,
+    #[source]
+    pub source: KeySourceError,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum KeySourceError {
+    #[error("file keyring entries are not supported, use a raw entry with a file key source")]
+    Unsupported,
+    #[error("unable to read {}", .path.display())]
+    Read {
         path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    #[error("unable to read environment variable {var}")]
+    Env {
+        var: String,
+        #[source]
+        source: env::VarError,
+    },
+    #[error("unable to spawn `{command}`")]
+    Spawn {
+        command: String,
+        #[source]
+        source: io::Error,
     },
-    Raw {
-        name: String,
-        #[serde(with = "::serde_utils::hex_string")]
-        key: Vec<u8>,
+    #[error("`{command}` exited with {status}: {stderr}")]
+    ExecFailed {
+        command: String,
+        status: ExitStatus,
+        stderr: String,
     },
+    #[error("key is not valid utf8")]
+    InvalidUtf8,
+    #[error("key is not valid hex")]
+    InvalidHex(#[source] hex::FromHexError),
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+
+    fn raw_entry(name: &str, key: KeySource) -> KeyringConfigEntry {
+        KeyringConfigEntry::Raw {
+            name: name.to_owned(),
+            key,
+        }
+    }
+
+    fn print_key(args: &[&str]) -> KeySource {
+        KeySource::Exec {
+            command: "sh".to_owned(),
+            args: [Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("testdata/keyring/print-key.sh")
+                .display()
+                .to_string()]
+            .into_iter()
+            .chain(args.iter().map(|arg| (*arg).to_owned()))
+            .collect(),
+        }
+    }
+
+    #[test]
+    fn parse_bare_hex_as_raw() {
+        let config = serde_json::from_str::<KeyringConfig>(
+            r#"{ "name": "keyring", "keys": [{ "type": "raw", "name": "alice", "key": "0x0102" }] }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.keys,
+            [raw_entry("alice", KeySource::Raw(vec![1, 2]))]
+        );
+
+        // raw keys are still serialized as bare hex strings
+        assert_eq!(
+            serde_json::to_value(&config.keys[0]).unwrap(),
+            serde_json::json!({ "type": "raw", "name": "alice", "key": "0x0102" })
+        );
+    }
+
+    #[test]
+    fn parse_external_sources() {
+        let keys = serde_json::from_str::<Vec<KeyringConfigEntry>>(
+            r#"[
+                { "type": "raw", "name": "file", "key": { "file": "/keys/file" } },
+                { "type": "raw", "name": "env", "key": { "env": "KEY" } },
+                { "type": "raw", "name": "exec", "key": { "exec": { "command": "pass", "args": ["show", "key"] } } },
+                { "type": "raw", "name": "exec-no-args", "key": { "exec": { "command": "print-key" } } }
+            ]"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            keys,
+            [
+                raw_entry("file", KeySource::File("/keys/file".into())),
+                raw_entry("env", KeySource::Env("KEY".to_owned())),
+                raw_entry(
+                    "exec",
+                    KeySource::Exec {
+                        command: "pass".to_owned(),
+                        args: vec!["show".to_owned(), "key".to_owned()],
+                    }
+                ),
+                raw_entry(
+                    "exec-no-args",
+                    KeySource::Exec {
+                        command: "print-key".to_owned(),
+                        args: vec![],
+                    }
+                ),
+            ]
+        );
+
+        for key in keys {
+            assert_eq!(
+                serde_json::from_value::<KeyringConfigEntry>(serde_json::to_value(&key).unwrap())
+                    .unwrap(),
+                key
+            );
+        }
+    }
+
+    #[test]
+    fn raw_key_is_redacted() {
+        assert_eq!(
+            format!("{:?}", KeySource::Raw(vec![1, 2])),
+            r#"Raw("<redacted>")"#
+        );
+    }
+
+    #[test]
+    fn resolve_file() {
+        let dir = env::temp_dir();
+        let pid = std::process::id();
+
+        let hex_path = dir.join(format!("chain-utils-keyring-hex-{pid}"));
+        fs::write(&hex_path, "0x0102\n").unwrap();
+
+        let raw_path = dir.join(format!("chain-utils-keyring-raw-{pid}"));
+        fs::write(&raw_path, [0xff, 0x00, 0x7f]).unwrap();
+
+        assert_eq!(*KeySource::File(hex_path).resolve().unwrap(), [1, 2]);
+        assert_eq!(
+            *KeySource::File(raw_path).resolve().unwrap(),
+            [0xff, 0x00, 0x7f]
+        );
+
+        let err = raw_entry(
+            "missing",
+            KeySource::File(dir.join(format!("chain-utils-keyring-missing-{pid}"))),
+        )
+        .resolve()
+        .err()
+        .unwrap();
+
+        assert_eq!(err.name, "missing");
+        assert_eq!(err.kind, "file");
+        assert!(matches!(err.source, KeySourceError::Read { .. }));
+    }
+
+    #[test]
+    fn resolve_env() {
+        let var = format!("CHAIN_UTILS_KEYRING_TEST_{}", std::process::id());
+
+        env::set_var(&var, "0102");
+        assert_eq!(*KeySource::Env(var.clone()).resolve().unwrap(), [1, 2]);
+
+        env::set_var(&var, "not hex");
+        assert!(matches!(
+            KeySource::Env(var.clone()).resolve(),
+            Err(KeySourceError::InvalidHex(_))
+        ));
+
+        env::remove_var(&var);
+        let err = raw_entry("unset", KeySource::Env(var))
+            .resolve()
+            .err()
+            .unwrap();
+
+        assert_eq!(err.kind, "env");
+        assert!(matches!(err.source, KeySourceError::Env { .. }));
+    }
+
+    #[test]
+    fn resolve_exec() {
+        assert_eq!(*print_key(&["0x0102"]).resolve().unwrap(), [1, 2]);
+
+        let err = raw_entry("alice", print_key(&["0x0102", "3"]))
+            .resolve()
+            .err()
+            .unwrap();
+
+        assert_eq!(err.name, "alice");
+        assert_eq!(err.kind, "exec");
+
+        let KeySourceError::ExecFailed { status, stderr, .. } = err.source else {
+            panic!("expected the command to fail");
+        };
+
+        assert_eq!(status.code(), Some(3));
+        assert_eq!(stderr, "secret not found");
+
+        assert!(matches!(
+            KeySource::Exec {
+                command: "chain-utils-keyring-nonexistent-command".to_owned(),
+                args: vec![],
+            }
+            .resolve(),
+            Err(KeySourceError::Spawn { .. })
+        ));
+    }
+
+    fn keyring() -> ConcurrentKeyring<u8, &'static str> {
+        ConcurrentKeyring::new(
+            "keyring",
+            [(1, "alice"), (2, "bob"), (3, "carol")]
+                .into_iter()
+                .map(|(address, name)| KeyringEntry {
+                    name: name.to_owned(),
+                    address,
+                    signer: name,
+                }),
+        )
+    }
+
+    async fn selected(keyring: &ConcurrentKeyring<u8, &'static str>) -> HashSet<&'static str> {
+        let mut selected = HashSet::new();
+
+        for _ in 0..6 {
+            if let Some(signer) = keyring.with(|signer| async move { *signer }).await {
+                selected.insert(signer);
+            }
+        }
+
+        selected
+    }
+
+    #[tokio::test]
+    async fn paused_keys_are_not_selected() {
+        let keyring = keyring();
+
+        assert!(keyring.pause(&2));
+        assert!(!keyring.pause(&2));
+        assert!(keyring.is_paused(&2));
+
+        assert_eq!(selected(&keyring).await, HashSet::from(["alice", "carol"]));
+
+        assert!(keyring.resume(&2));
+        assert!(!keyring.resume(&2));
+
+        assert_eq!(
+            selected(&keyring).await,
+            HashSet::from(["alice", "bob", "carol"])
+        );
+    }
+
+    #[tokio::test]
+    async fn all_keys_paused() {
+        let keyring = keyring();
+
+        for address in [1, 2, 3] {
+            assert!(keyring.pause(&address));
+        }
+
+        assert!(keyring
+            .with(|signer| async move { *signer })
+            .await
+            .is_none());
+
+        // unknown addresses can't be paused
+        assert!(!keyring.pause(&4));
+
+        keyring.resume(&3);
+
+        assert_eq!(
+            keyring.with(|signer| async move { *signer }).await,
+            Some("carol")
+        );
+    }
+
+    #[tokio::test]
+    async fn preferred_keys_are_selected() {
+        let keyring = keyring();
+
+        // bob is preferred over alice, carol is not selectable
+        let rank = |address: &u8| match address {
+            1 => Some(1),
+            2 => Some(0),
+            _ => None,
+        };
+
+        assert_eq!(
+            keyring
+                .with_preferred(rank, |signer| async move { *signer })
+                .await,
+            Some("bob")
+        );
+
+        // the preferred key is selected again once it is put back
+        assert_eq!(
+            keyring
+                .with_preferred(rank, |signer| async move { *signer })
+                .await,
+            Some("bob")
+        );
+
+        // the ring buffer is intact after ranking
+        assert_eq!(
+            selected(&keyring).await,
+            HashSet::from(["alice", "bob", "carol"])
+        );
+
+        keyring.pause(&2);
+
+        assert_eq!(
+            keyring
+                .with_preferred(rank, |signer| async move { *signer })
+                .await,
+            Some("alice")
+        );
+
+        assert_eq!(
+            keyring
+                .with_preferred(|_| None::<u64>, |signer| async move { *signer })
+                .await,
+            None
+        );
+        assert_eq!(selected(&keyring).await, HashSet::from(["alice", "carol"]));
+    }
+
+    #[test]
+    fn resolve_keyring() {
+        let config = KeyringConfig {
+            name: "keyring".to_owned(),
+            keys: vec![
+                raw_entry("alice", KeySource::Raw(vec![1])),
+                raw_entry("bob", print_key(&["02"])),
+            ],
+        };
+
+        let keys = config.resolve().unwrap();
+
+        assert_eq!(
+            keys.iter()
+                .map(|key| (key.name.as_str(), key.key.to_vec()))
+                .collect::<Vec<_>>(),
+            [("alice", vec![1]), ("bob", vec![2])]
+        );
+    }
 }
@@ -9,8 +9,28 @@ pub mod union;
 
 pub mod cosmos_sdk;
 
+pub mod address;
+
+pub mod auth;
+
+pub mod bounded;
+
+pub mod endpoint;
+
 pub mod private_key;
 
 pub mod keyring;
 
+pub mod light_block;
+
+pub mod net;
+
+pub mod relay_progress;
+
+pub mod spend;
+
+pub mod timeout;
+
+pub mod upgrade_plan;
+
 pub type BoxDynError = Box<dyn core::error::Error + Send + Sync + 'static>;
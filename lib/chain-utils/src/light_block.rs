@@ -0,0 +1,537 @@
+//! Building update headers for tendermint-family clients from the cometbft rpc.
+//!
+//! A [`LightBlock`] is the signed header of a block along with the validator set that signed it,
+//! fetched with [`fetch_light_block`]. The update header of a 07-tendermint client is built from
+//! the light block of the height being updated to and the validator set at the trusted height
+//! with [`build_tm_update_header`]. Cometbls clients are instead updated with a zero knowledge
+//! proof of the commit, requested from galois with [`build_cometbls_prove_request`] and wrapped
+//! into an update header with [`build_cometbls_update_header`].
+
+use std::{collections::HashMap, num::NonZeroU64};
+
+use cometbft_rpc::{
+    rpc_types::{CommitResponse, ValidatorsPagination, ValidatorsResponse},
+    JsonRpcError,
+};
+use cometbft_types::{
+    crypto::public_key::PublicKey,
+    types::{
+        canonical_block_id::CanonicalBlockId, canonical_part_set_header::CanonicalPartSetHeader,
+        commit_sig::CommitSig, signed_header::SignedHeader, signed_msg_type::SignedMsgType,
+        simple_validator::SimpleValidator, validator::Validator, validator_set::ValidatorSet,
+    },
+};
+use cometbls_light_client_types::light_header::LightHeader;
+use galois_rpc::{
+    canonical_vote::CanonicalVote, prove_request::ProveRequest,
+    validator_set_commit::ValidatorSetCommit,
+};
+use num_bigint::BigUint;
+use tracing::{debug, trace};
+use unionlabs::{
+    bounded::{BoundedI64, BoundedU8},
+    hash::{hash_v2::HexUnprefixed, H160, H256},
+    ibc::core::client::height::Height,
+    option_unwrap, result_unwrap,
+};
+
+pub use cometbft_types::types::light_block::LightBlock;
+
+/// The maximum page size of the `validators` rpc method.
+const PER_PAGE: BoundedU8<1, 100> = const { result_unwrap!(BoundedU8::<1, 100>::new_const(100)) };
+
+#[derive(Debug, thiserror::Error)]
+pub enum LightBlockError {
+    #[error("error fetching the light block")]
+    Rpc(#[from] JsonRpcError),
+    #[error(
+        "the validator set at height {height} has {total} validators, but only {found} were \
+        returned"
+    )]
+    IncompleteValidatorSet { height: u64, total: u64, found: u64 },
+    #[error("the proposer {proposer_address} is not in the validator set at height {height}")]
+    MissingProposer {
+        height: u64,
+        proposer_address: H160<HexUnprefixed>,
+    },
+    #[error(transparent)]
+    ValidatorsHashMismatch(#[from] ValidatorsHashMismatch),
+    #[error("validator {address} does not have a bn254 public key")]
+    NonBn254Validator { address: H160<HexUnprefixed> },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error(
+    "the header at height {height} commits to the validators hash {expected}, but the validator \
+    set hashes to {found}"
+)]
+pub struct ValidatorsHashMismatch {
+    pub height: u64,
+    pub expected: H256<HexUnprefixed>,
+    pub found: H256<HexUnprefixed>,
+}
+
+/// The cometbft rpc methods required to fetch a [`LightBlock`].
+#[allow(async_fn_in_trait)]
+pub trait LightBlockClient {
+    async fn commit(&self, height: NonZeroU64) -> Result<CommitResponse, JsonRpcError>;
+
+    async fn validators(
+        &self,
+        height: NonZeroU64,
+        pagination: ValidatorsPagination,
+    ) -> Result<ValidatorsResponse, JsonRpcError>;
+}
+
+impl LightBlockClient for cometbft_rpc::Client {
+    async fn commit(&self, height: NonZeroU64) -> Result<CommitResponse, JsonRpcError> {
+        cometbft_rpc::Client::commit(self, Some(height)).await
+    }
+
+    async fn validators(
+        &self,
+        height: NonZeroU64,
+        pagination: ValidatorsPagination,
+    ) -> Result<ValidatorsResponse, JsonRpcError> {
+        cometbft_rpc::Client::validators(self, Some(height), Some(pagination)).await
+    }
+}
+
+/// Fetch all of the validators at `height`, in the order of the validator set.
+pub async fn fetch_validators(
+    client: &impl LightBlockClient,
+    height: NonZeroU64,
+) -> Result<Vec<Validator>, LightBlockError> {
+    let mut page = const { option_unwrap!(NonZeroU64::new(1)) };
+
+    let mut validators = vec![];
+
+    loop {
+        let response = client
+            .validators(
+                height,
+                ValidatorsPagination {
+                    page,
+                    per_page: Some(PER_PAGE),
+                },
+            )
+            .await?;
+
+        let found = response.validators.len();
+
+        validators.extend(response.validators);
+
+        if validators.len() as u64 >= response.total {
+            return Ok(validators);
+        }
+
+        // an empty page before the end of the set would otherwise be requested again forever
+        if found == 0 {
+            return Err(LightBlockError::IncompleteValidatorSet {
+                height: height.get(),
+                total: response.total,
+                found: validators.len() as u64,
+            });
+        }
+
+        page = page
+            .checked_add(1)
+            .expect("validator count will always be < u64 max");
+    }
+}
+
+/// Fetch the signed header at `height`, and the validator set that signed it.
+///
+/// The validator set is not checked against the header, since the validators hash is computed
+/// differently by cometbls. See [`verify_validators_hash`].
+pub async fn fetch_light_block(
+    client: &impl LightBlockClient,
+    height: NonZeroU64,
+) -> Result<LightBlock, LightBlockError> {
+    let signed_header = client.commit(height).await?.signed_header;
+
+    let validators = fetch_validators(client, height).await?;
+
+    let validator_set = mk_validator_set(
+        height.get(),
+        validators,
+        signed_header.header.proposer_address,
+    )?;
+
+    Ok(LightBlock {
+        signed_header,
+        validator_set,
+    })
+}
+
+/// Build the [`ValidatorSet`] of `validators`, with the validator at `proposer_address` as the
+/// proposer.
+pub fn mk_validator_set(
+    height: u64,
+    validators: Vec<Validator>,
+    proposer_address: H160<HexUnprefixed>,
+) -> Result<ValidatorSet, LightBlockError> {
+    let proposer = validators
+        .iter()
+        .find(|val| val.address == proposer_address)
+        .ok_or(LightBlockError::MissingProposer {
+            height,
+            proposer_address,
+        })?
+        .clone();
+
+    let total_voting_power = validators
+        .iter()
+        .map(|v| v.voting_power.inner())
+        .sum::<i64>();
+
+    Ok(ValidatorSet {
+        validators,
+        proposer,
+        total_voting_power,
+    })
+}
+
+/// Check that the validator set of `light_block` is the one committed to in its header. Only
+/// applicable to tendermint, as the validators hash of cometbls is computed with MiMC (and is
+/// instead checked by the prover).
+pub fn verify_validators_hash(light_block: &LightBlock) -> Result<(), ValidatorsHashMismatch> {
+    let expected = light_block.signed_header.header.validators_hash;
+    let found = tendermint_verifier::utils::validators_hash(&light_block.validator_set)
+        .into_encoding::<HexUnprefixed>();
+
+    if expected == found {
+        Ok(())
+    } else {
+        Err(ValidatorsHashMismatch {
+            height: light_block.signed_header.header.height.inner().unsigned_abs(),
+            expected,
+            found,
+        })
+    }
+}
+
+/// Build the header to update a 07-tendermint client from `trusted` to the height of `target`.
+pub fn build_tm_update_header(
+    trusted: Height,
+    trusted_validators: ValidatorSet,
+    target: LightBlock,
+) -> Result<tendermint_light_client_types::Header, LightBlockError> {
+    verify_validators_hash(&target)?;
+
+    Ok(tendermint_light_client_types::Header {
+        signed_header: target.signed_header,
+        validator_set: target.validator_set,
+        trusted_height: trusted,
+        trusted_validators,
+    })
+}
+
+/// Build the request for the proof of the commit of `target`, signed by `trusted_validators` (the
+/// validators at the trusted height) and the validators of `target`.
+pub fn build_cometbls_prove_request(
+    trusted_validators: Vec<Validator>,
+    target: &LightBlock,
+) -> Result<ProveRequest, LightBlockError> {
+    let signed_header = &target.signed_header;
+
+    Ok(ProveRequest {
+        vote: CanonicalVote {
+            // REVIEW: Should this be hardcoded to precommit?
+            ty: SignedMsgType::Precommit,
+            height: signed_header.commit.height,
+            round: BoundedI64::new_const(signed_header.commit.round.inner().into())
+                .expect("0..=i32::MAX can be converted to 0..=i64::MAX safely"),
+            block_id: CanonicalBlockId {
+                hash: signed_header.commit.block_id.hash.unwrap_or_default(),
+                part_set_header: CanonicalPartSetHeader {
+                    total: signed_header.commit.block_id.part_set_header.total,
+                    hash: signed_header
+                        .commit
+                        .block_id
+                        .part_set_header
+                        .hash
+                        .unwrap_or_default(),
+                },
+            },
+            chain_id: signed_header.header.chain_id.clone(),
+        },
+        untrusted_header: signed_header.header.clone(),
+        trusted_commit: mk_validator_set_commit(trusted_validators, signed_header)?,
+        untrusted_commit: mk_validator_set_commit(
+            target.validator_set.validators.clone(),
+            signed_header,
+        )?,
+    })
+}
+
+/// Build the header to update a cometbls client from `trusted` to the height of `target`, with
+/// the `zero_knowledge_proof` returned by galois for the request built with
+/// [`build_cometbls_prove_request`].
+#[must_use]
+pub fn build_cometbls_update_header(
+    trusted: Height,
+    target: &SignedHeader,
+    zero_knowledge_proof: Vec<u8>,
+) -> cometbls_light_client_types::Header {
+    cometbls_light_client_types::Header {
+        signed_header: LightHeader {
+            height: target.header.height,
+            time: target.header.time,
+            validators_hash: target.header.validators_hash.into_encoding(),
+            next_validators_hash: target.header.next_validators_hash.into_encoding(),
+            app_hash: target.header.app_hash.into_encoding(),
+        },
+        trusted_height: trusted,
+        zero_knowledge_proof,
+    }
+}
+
+/// The signatures of `validators` in the commit of `signed_header`.
+fn mk_validator_set_commit(
+    mut validators: Vec<Validator>,
+    signed_header: &SignedHeader,
+) -> Result<ValidatorSetCommit, LightBlockError> {
+    // Validators must be sorted to match the root, by token then address
+    validators.sort_by(|a, b| {
+        b.voting_power
+            .cmp(&a.voting_power)
+            .then(a.address.cmp(&b.address))
+    });
+
+    // The bitmap is a public input of the circuit, it must fit in Fr (scalar field) bn254
+    let mut bitmap = BigUint::default();
+    // REVIEW: This will over-allocate for the trusted validators; should be benchmarked
+    let mut signatures = Vec::<Vec<u8>>::with_capacity(validators.len());
+
+    let validators_map = validators
+        .iter()
+        .enumerate()
+        .map(|(i, v)| (v.address, i))
+        .collect::<HashMap<_, _>>();
+
+    // For each validator signature, we search for the actual validator in the set and set its
+    // signed bit to 1. We then push the signature only if the validator signed. It's possible that
+    // we don't find a validator for a given signature as the validator set may have drifted
+    // (trusted validator set).
+    for sig in &signed_header.commit.signatures {
+        match sig {
+            CommitSig::Absent => {
+                debug!("validator did not sign");
+            }
+            CommitSig::Commit {
+                validator_address,
+                timestamp: _,
+                signature,
+            } => {
+                if let Some(validator_index) = validators_map.get(validator_address.as_encoding()) {
+                    bitmap.set_bit(*validator_index as u64, true);
+                    signatures.push(signature.clone().into());
+                    trace!(%validator_address, %validator_index, "validator signed");
+                } else {
+                    trace!(
+                        %validator_address,
+                        "validator set drifted, could not find validator signature"
+                    );
+                }
+            }
+            CommitSig::Nil {
+                validator_address, ..
+            } => {
+                trace!(%validator_address, "validator commit is nil");
+            }
+        }
+    }
+
+    let simple_validators = validators
+        .iter()
+        .map(|v| match v.pub_key {
+            PublicKey::Bn254(ref key) => Ok(SimpleValidator {
+                pub_key: PublicKey::Bn254(key.clone()),
+                voting_power: v.voting_power.into(),
+            }),
+            _ => Err(LightBlockError::NonBn254Validator { address: v.address }),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(ValidatorSetCommit {
+        validators: simple_validators,
+        signatures,
+        bitmap: bitmap.to_bytes_be(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{de::DeserializeOwned, Deserialize};
+
+    use super::*;
+
+    #[derive(Deserialize)]
+    struct JsonRpcResponse<T> {
+        result: T,
+    }
+
+    fn response<T: DeserializeOwned>(json: &str) -> T {
+        serde_json::from_str::<JsonRpcResponse<T>>(json)
+            .unwrap()
+            .result
+    }
+
+    /// Responses recorded from a simd devnet with 4 validators (the same blocks as the
+    /// tendermint-verifier tests). The validators at height 291 are served in pages of 2.
+    struct MockClient;
+
+    impl LightBlockClient for MockClient {
+        async fn commit(&self, height: NonZeroU64) -> Result<CommitResponse, JsonRpcError> {
+            Ok(response(match height.get() {
+                288 => include_str!("./test/light_block/commit-288.json"),
+                291 => include_str!("./test/light_block/commit-291.json"),
+                _ => panic!("no commit recorded at height {height}"),
+            }))
+        }
+
+        async fn validators(
+            &self,
+            height: NonZeroU64,
+            pagination: ValidatorsPagination,
+        ) -> Result<ValidatorsResponse, JsonRpcError> {
+            Ok(response(match (height.get(), pagination.page.get()) {
+                (288, 1) => include_str!("./test/light_block/validators-288-1.json"),
+                (291, 1) => include_str!("./test/light_block/validators-291-1.json"),
+                (291, 2) => include_str!("./test/light_block/validators-291-2.json"),
+                (height, page) => panic!("no validators recorded at height {height} page {page}"),
+            }))
+        }
+    }
+
+    fn height(height: u64) -> NonZeroU64 {
+        NonZeroU64::new(height).unwrap()
+    }
+
+    /// The update header from 288 to 291, as accepted by the tendermint verifier.
+    fn expected_header() -> tendermint_light_client_types::Header {
+        serde_json::from_str(include_str!("./test/light_block/header-291.json")).unwrap()
+    }
+
+    #[tokio::test]
+    async fn paginated_validators() {
+        let validators = fetch_validators(&MockClient, height(291)).await.unwrap();
+
+        assert_eq!(validators, expected_header().validator_set.validators);
+    }
+
+    #[tokio::test]
+    async fn tm_update_header() {
+        let trusted = fetch_light_block(&MockClient, height(288)).await.unwrap();
+        let target = fetch_light_block(&MockClient, height(291)).await.unwrap();
+
+        verify_validators_hash(&trusted).unwrap();
+
+        let header = build_tm_update_header(
+            Height::new_with_revision(1, 288),
+            trusted.validator_set.clone(),
+            target,
+        )
+        .unwrap();
+
+        let expected = expected_header();
+
+        // the proposer of the trusted validators is not part of the validators hash, and the
+        // recorded header uses a different one than the proposer of the trusted block
+        assert_eq!(
+            trusted.validator_set.proposer.address,
+            trusted.signed_header.header.proposer_address
+        );
+        assert_eq!(
+            header,
+            tendermint_light_client_types::Header {
+                trusted_validators: ValidatorSet {
+                    proposer: trusted.validator_set.proposer,
+                    ..expected.trusted_validators
+                },
+                ..expected
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn validators_hash_mismatch() {
+        let mut target = fetch_light_block(&MockClient, height(291)).await.unwrap();
+
+        target.validator_set.validators.pop();
+
+        let err = build_tm_update_header(
+            Height::new_with_revision(1, 288),
+            expected_header().trusted_validators,
+            target,
+        )
+        .unwrap_err();
+
+        let LightBlockError::ValidatorsHashMismatch(mismatch) = err else {
+            panic!("expected a validators hash mismatch, found {err:?}");
+        };
+
+        assert_eq!(mismatch.height, 291);
+        assert_eq!(
+            mismatch.expected,
+            expected_header().signed_header.header.validators_hash
+        );
+        assert_ne!(mismatch.found, mismatch.expected);
+        assert_eq!(
+            mismatch.to_string(),
+            format!(
+                "the header at height 291 commits to the validators hash {}, but the validator \
+                set hashes to {}",
+                mismatch.expected, mismatch.found
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn missing_proposer() {
+        let signed_header = MockClient.commit(height(291)).await.unwrap().signed_header;
+
+        let mut validators = expected_header().validator_set.validators;
+        validators.retain(|v| v.address != signed_header.header.proposer_address);
+
+        assert!(matches!(
+            mk_validator_set(291, validators, signed_header.header.proposer_address),
+            Err(LightBlockError::MissingProposer { height: 291, proposer_address })
+                if proposer_address == signed_header.header.proposer_address
+        ));
+    }
+
+    #[tokio::test]
+    async fn cometbls_update_header() {
+        let target = fetch_light_block(&MockClient, height(291)).await.unwrap();
+
+        // the devnet is not a cometbls chain
+        assert!(matches!(
+            build_cometbls_prove_request(target.validator_set.validators.clone(), &target),
+            Err(LightBlockError::NonBn254Validator { .. })
+        ));
+
+        let header = build_cometbls_update_header(
+            Height::new_with_revision(1, 288),
+            &target.signed_header,
+            vec![1, 2, 3],
+        );
+
+        assert_eq!(
+            header.signed_header.height,
+            target.signed_header.header.height
+        );
+        assert_eq!(header.signed_header.time, target.signed_header.header.time);
+        assert_eq!(
+            header.signed_header.validators_hash,
+            target.signed_header.header.validators_hash.into_encoding()
+        );
+        assert_eq!(
+            header.signed_header.app_hash,
+            target.signed_header.header.app_hash.into_encoding()
+        );
+        assert_eq!(header.trusted_height, Height::new_with_revision(1, 288));
+        assert_eq!(header.zero_knowledge_proof, [1, 2, 3]);
+    }
+}
@@ -0,0 +1,463 @@
+//! Tracking of the highest packet sequence relayed per channel, for external monitoring.
+//!
+//! Transaction plugins record the packets of every message whose inclusion on this chain was
+//! confirmed. Progress is tracked per `(chain_id, channel, direction)`, where `chain_id` is the
+//! chain the messages were submitted to: for received packets the channel is the destination
+//! channel, and for acknowledgements it is the source channel. Comparing the highest relayed
+//! sequence against the latest sent sequence on the counterparty gives the lag of the relayer on
+//! that channel.
+//!
+//! Like the [spend ledger](crate::spend), progress is persisted to a small JSON file, which can be
+//! shared between the transaction plugins of multiple chains.
+
+use std::{
+    collections::BTreeMap,
+    io,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::spend::unix_now;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RelayProgressConfig {
+    /// The file that relay progress is persisted to.
+    #[serde(default = "default_path")]
+    pub path: PathBuf,
+}
+
+impl Default for RelayProgressConfig {
+    fn default() -> Self {
+        Self {
+            path: default_path(),
+        }
+    }
+}
+
+fn default_path() -> PathBuf {
+    "relay-progress.json".into()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RelayDirection {
+    /// A packet was received on this chain.
+    Recv,
+    /// A packet sent from this chain was acknowledged.
+    Ack,
+}
+
+/// A packet relayed by a message whose inclusion was confirmed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfirmedPacket {
+    /// The channel on this chain that the packet was handled by.
+    pub channel: String,
+    pub direction: RelayDirection,
+    pub sequence: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RelayProgressEntry {
+    pub chain_id: String,
+    pub channel: String,
+    pub direction: RelayDirection,
+    pub sequence: u64,
+    /// Unix timestamp (in seconds) at which `sequence` was recorded.
+    pub updated_at: u64,
+}
+
+/// The highest relayed sequence per `(chain_id, channel, direction)`, and when it was recorded.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "Vec<RelayProgressEntry>", into = "Vec<RelayProgressEntry>")]
+pub struct RelayProgressLedger {
+    progress: BTreeMap<(String, String, RelayDirection), (u64, u64)>,
+}
+
+impl RelayProgressLedger {
+    /// Load the ledger from `path`. A missing file is treated as an empty ledger.
+    pub fn load(path: &Path) -> Result<Self, RelayProgressError> {
+        match std::fs::read(path) {
+            Ok(bz) => serde_json::from_slice(&bz).map_err(|source| RelayProgressError::Decode {
+                path: path.to_owned(),
+                source,
+            }),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(source) => Err(RelayProgressError::Io {
+                path: path.to_owned(),
+                source,
+            }),
+        }
+    }
+
+    /// Atomically write the ledger to `path`.
+    pub fn store(&self, path: &Path) -> Result<(), RelayProgressError> {
+        let io_err = |source| RelayProgressError::Io {
+            path: path.to_owned(),
+            source,
+        };
+
+        // the ledger may be shared between processes, so each writes to its own temporary file
+        let tmp_path = path.with_extension(format!("{}.tmp", std::process::id()));
+
+        std::fs::write(
+            &tmp_path,
+            serde_json::to_vec_pretty(self).expect("serialization is infallible; qed;"),
+        )
+        .map_err(io_err)?;
+
+        std::fs::rename(&tmp_path, path).map_err(io_err)
+    }
+
+    /// Raise the highest relayed sequence of `channel` in `direction` to `sequence`, returning
+    /// whether it changed. Lower sequences (i.e. packets relayed out of order) are ignored.
+    pub fn record(
+        &mut self,
+        chain_id: &str,
+        channel: &str,
+        direction: RelayDirection,
+        sequence: u64,
+        now: u64,
+    ) -> bool {
+        let entry = self
+            .progress
+            .entry((chain_id.to_owned(), channel.to_owned(), direction))
+            .or_insert((0, now));
+
+        if sequence > entry.0 {
+            *entry = (sequence, now);
+            true
+        } else {
+            false
+        }
+    }
+
+    #[must_use]
+    pub fn progress(&self, chain_id: &str, channel: &str) -> RelayProgress {
+        let get = |direction| {
+            self.progress
+                .get(&(chain_id.to_owned(), channel.to_owned(), direction))
+                .copied()
+        };
+
+        let recv = get(RelayDirection::Recv);
+        let ack = get(RelayDirection::Ack);
+
+        RelayProgress {
+            highest_relayed_recv: recv.map(|(sequence, _)| sequence),
+            highest_relayed_ack: ack.map(|(sequence, _)| sequence),
+            updated_at: recv.into_iter().chain(ack).map(|(_, at)| at).max(),
+        }
+    }
+
+    /// The progress of every channel of `chain_id`.
+    #[must_use]
+    pub fn channels(&self, chain_id: &str) -> BTreeMap<String, RelayProgress> {
+        self.progress
+            .keys()
+            .filter(|(entry_chain_id, _, _)| entry_chain_id == chain_id)
+            .map(|(_, channel, _)| (channel.clone(), self.progress(chain_id, channel)))
+            .collect()
+    }
+
+    /// Replace all entries for `chain_id` with the entries in `other`.
+    fn merge_chain(&mut self, chain_id: &str, other: &Self) {
+        self.progress
+            .retain(|(entry_chain_id, _, _), _| entry_chain_id != chain_id);
+        self.progress.extend(
+            other
+                .progress
+                .iter()
+                .filter(|((entry_chain_id, _, _), _)| entry_chain_id == chain_id)
+                .map(|(k, v)| (k.clone(), *v)),
+        );
+    }
+}
+
+impl From<Vec<RelayProgressEntry>> for RelayProgressLedger {
+    fn from(value: Vec<RelayProgressEntry>) -> Self {
+        let mut ledger = Self::default();
+
+        for RelayProgressEntry {
+            chain_id,
+            channel,
+            direction,
+            sequence,
+            updated_at,
+        } in value
+        {
+            ledger.record(&chain_id, &channel, direction, sequence, updated_at);
+        }
+
+        ledger
+    }
+}
+
+impl From<RelayProgressLedger> for Vec<RelayProgressEntry> {
+    fn from(value: RelayProgressLedger) -> Self {
+        value
+            .progress
+            .into_iter()
+            .map(
+                |((chain_id, channel, direction), (sequence, updated_at))| RelayProgressEntry {
+                    chain_id,
+                    channel,
+                    direction,
+                    sequence,
+                    updated_at,
+                },
+            )
+            .collect()
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum RelayProgressError {
+    #[error("error accessing relay progress at {}", path.display())]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    #[error("invalid relay progress at {}", path.display())]
+    Decode {
+        path: PathBuf,
+        #[source]
+        source: serde_json::Error,
+    },
+}
+
+/// The relay progress of a single channel. Sequences are `None` if no packet has been relayed in
+/// that direction yet.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RelayProgress {
+    pub highest_relayed_recv: Option<u64>,
+    pub highest_relayed_ack: Option<u64>,
+    /// Unix timestamp (in seconds) at which either sequence was last raised.
+    pub updated_at: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RelayProgressReport {
+    pub chain_id: String,
+    pub channels: BTreeMap<String, RelayProgress>,
+}
+
+/// Tracks the relay progress of a single chain, persisting it to the configured file.
+#[derive(Debug, Clone)]
+pub struct RelayProgressTracker {
+    chain_id: String,
+    config: RelayProgressConfig,
+    ledger: Arc<Mutex<RelayProgressLedger>>,
+}
+
+impl RelayProgressTracker {
+    pub fn new(
+        chain_id: impl Into<String>,
+        config: RelayProgressConfig,
+    ) -> Result<Self, RelayProgressError> {
+        let ledger = RelayProgressLedger::load(&config.path)?;
+
+        Ok(Self {
+            chain_id: chain_id.into(),
+            config,
+            ledger: Arc::new(Mutex::new(ledger)),
+        })
+    }
+
+    /// Record the packets of a submitted batch, given the success of each message in the batch
+    /// along with the packets it relays. The packets of failed messages are ignored. The ledger is
+    /// only persisted if any sequence was raised.
+    pub fn record_confirmed<P: IntoIterator<Item = ConfirmedPacket>>(
+        &self,
+        results: impl IntoIterator<Item = (bool, P)>,
+    ) -> Result<(), RelayProgressError> {
+        self.record_confirmed_at(unix_now(), results)
+    }
+
+    pub fn record_confirmed_at<P: IntoIterator<Item = ConfirmedPacket>>(
+        &self,
+        now: u64,
+        results: impl IntoIterator<Item = (bool, P)>,
+    ) -> Result<(), RelayProgressError> {
+        let mut ledger = self.lock();
+
+        let mut changed = false;
+
+        for packet in results
+            .into_iter()
+            .filter(|(success, _)| *success)
+            .flat_map(|(_, packets)| packets)
+        {
+            changed |= ledger.record(
+                &self.chain_id,
+                &packet.channel,
+                packet.direction,
+                packet.sequence,
+                now,
+            );
+        }
+
+        if !changed {
+            return Ok(());
+        }
+
+        // other chains may share this file, so only overwrite the entries for this chain
+        let mut on_disk = RelayProgressLedger::load(&self.config.path)?;
+        on_disk.merge_chain(&self.chain_id, &ledger);
+        on_disk.store(&self.config.path)
+    }
+
+    #[must_use]
+    pub fn progress(&self, channel: &str) -> RelayProgress {
+        self.lock().progress(&self.chain_id, channel)
+    }
+
+    #[must_use]
+    pub fn report(&self) -> RelayProgressReport {
+        RelayProgressReport {
+            chain_id: self.chain_id.clone(),
+            channels: self.lock().channels(&self.chain_id),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, RelayProgressLedger> {
+        self.ledger.lock().expect("lock is not poisoned; qed;")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: u64 = 1_700_000_000;
+
+    fn tracker(name: &str) -> RelayProgressTracker {
+        let path = std::env::temp_dir().join(format!(
+            "chain-utils-relay-progress-{name}-{}.json",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+
+        RelayProgressTracker::new("chain-1", RelayProgressConfig { path }).unwrap()
+    }
+
+    fn packet(channel: &str, direction: RelayDirection, sequence: u64) -> ConfirmedPacket {
+        ConfirmedPacket {
+            channel: channel.to_owned(),
+            direction,
+            sequence,
+        }
+    }
+
+    #[test]
+    fn mixed_results() {
+        let tracker = tracker("mixed");
+
+        tracker
+            .record_confirmed_at(
+                NOW,
+                [
+                    (true, vec![packet("channel-1", RelayDirection::Recv, 5)]),
+                    // failed messages don't count, even if they relay a higher sequence
+                    (false, vec![packet("channel-1", RelayDirection::Recv, 9)]),
+                    (true, vec![packet("channel-1", RelayDirection::Ack, 3)]),
+                    // i.e. a client update
+                    (true, vec![]),
+                ],
+            )
+            .unwrap();
+
+        assert_eq!(
+            tracker.progress("channel-1"),
+            RelayProgress {
+                highest_relayed_recv: Some(5),
+                highest_relayed_ack: Some(3),
+                updated_at: Some(NOW),
+            }
+        );
+
+        // lower sequences don't regress the progress
+        tracker
+            .record_confirmed_at(
+                NOW + 60,
+                [(true, vec![packet("channel-1", RelayDirection::Recv, 4)])],
+            )
+            .unwrap();
+
+        assert_eq!(tracker.progress("channel-1").highest_relayed_recv, Some(5));
+        assert_eq!(tracker.progress("channel-1").updated_at, Some(NOW));
+
+        assert_eq!(tracker.progress("channel-2"), RelayProgress::default());
+    }
+
+    #[test]
+    fn persistence_format() {
+        let tracker = tracker("format");
+
+        tracker
+            .record_confirmed_at(
+                NOW,
+                [(
+                    true,
+                    vec![
+                        packet("channel-1", RelayDirection::Ack, 2),
+                        packet("channel-1", RelayDirection::Recv, 7),
+                    ],
+                )],
+            )
+            .unwrap();
+
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(
+                &std::fs::read(&tracker.config.path).unwrap()
+            )
+            .unwrap(),
+            serde_json::json!([
+                {
+                    "chain_id": "chain-1",
+                    "channel": "channel-1",
+                    "direction": "recv",
+                    "sequence": 7,
+                    "updated_at": NOW,
+                },
+                {
+                    "chain_id": "chain-1",
+                    "channel": "channel-1",
+                    "direction": "ack",
+                    "sequence": 2,
+                    "updated_at": NOW,
+                },
+            ])
+        );
+
+        let reloaded = RelayProgressTracker::new("chain-1", tracker.config.clone()).unwrap();
+        assert_eq!(reloaded.report(), tracker.report());
+
+        // entries for other chains in the same file are preserved
+        let other = RelayProgressTracker::new("chain-2", tracker.config.clone()).unwrap();
+        other
+            .record_confirmed_at(NOW, [(true, [packet("1", RelayDirection::Recv, 1)])])
+            .unwrap();
+        tracker
+            .record_confirmed_at(
+                NOW,
+                [(true, [packet("channel-1", RelayDirection::Recv, 8)])],
+            )
+            .unwrap();
+
+        let ledger = RelayProgressLedger::load(&tracker.config.path).unwrap();
+        assert_eq!(
+            ledger.progress("chain-1", "channel-1").highest_relayed_recv,
+            Some(8)
+        );
+        assert_eq!(
+            ledger.progress("chain-2", "1").highest_relayed_recv,
+            Some(1)
+        );
+    }
+}
@@ -0,0 +1,471 @@
+//! Accounting of the native token spent on transaction fees, with optional enforcement of a daily
+//! budget.
+//!
+//! Spend is tracked per `(chain_id, day)`, where `day` is the number of days since the unix epoch
+//! (i.e. the budget window rolls over at midnight UTC). The ledger is persisted to a small JSON
+//! file, which can be shared between the transaction plugins of multiple chains.
+
+use std::{
+    collections::BTreeMap,
+    io,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+pub const SECONDS_PER_DAY: u64 = 86_400;
+
+/// The amount of days of history to keep in the ledger, per chain.
+pub const RETAINED_DAYS: u64 = 30;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SpendConfig {
+    /// The file that the spend ledger is persisted to.
+    #[serde(default = "default_ledger_path")]
+    pub ledger_path: PathBuf,
+    /// The maximum amount of the native token (in its smallest denomination) that can be spent on
+    /// fees per day. Once this is exceeded, only priority messages (i.e. client updates) will be
+    /// submitted until the budget window rolls over.
+    #[serde(default)]
+    pub daily_budget: Option<u128>,
+}
+
+impl Default for SpendConfig {
+    fn default() -> Self {
+        Self {
+            ledger_path: default_ledger_path(),
+            daily_budget: None,
+        }
+    }
+}
+
+fn default_ledger_path() -> PathBuf {
+    "spend-ledger.json".into()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SpendEntry {
+    pub chain_id: String,
+    pub day: u64,
+    #[serde(with = "::serde_utils::string")]
+    pub spent: u128,
+}
+
+/// Cumulative spend per `(chain_id, day)`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "Vec<SpendEntry>", into = "Vec<SpendEntry>")]
+pub struct SpendLedger {
+    spent: BTreeMap<(String, u64), u128>,
+}
+
+impl SpendLedger {
+    /// Load the ledger from `path`. A missing file is treated as an empty ledger.
+    pub fn load(path: &Path) -> Result<Self, SpendLedgerError> {
+        match std::fs::read(path) {
+            Ok(bz) => serde_json::from_slice(&bz).map_err(|source| SpendLedgerError::Decode {
+                path: path.to_owned(),
+                source,
+            }),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(source) => Err(SpendLedgerError::Io {
+                path: path.to_owned(),
+                source,
+            }),
+        }
+    }
+
+    /// Atomically write the ledger to `path`.
+    pub fn store(&self, path: &Path) -> Result<(), SpendLedgerError> {
+        let io_err = |source| SpendLedgerError::Io {
+            path: path.to_owned(),
+            source,
+        };
+
+        // the ledger may be shared between processes, so each writes to its own temporary file
+        let tmp_path = path.with_extension(format!("{}.tmp", std::process::id()));
+
+        std::fs::write(
+            &tmp_path,
+            serde_json::to_vec_pretty(self).expect("serialization is infallible; qed;"),
+        )
+        .map_err(io_err)?;
+
+        std::fs::rename(&tmp_path, path).map_err(io_err)
+    }
+
+    #[must_use]
+    pub fn spent(&self, chain_id: &str, day: u64) -> u128 {
+        self.spent
+            .get(&(chain_id.to_owned(), day))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Add `amount` to the spend of `chain_id` on `day`, pruning any entries for `chain_id` older
+    /// than [`RETAINED_DAYS`].
+    pub fn record(&mut self, chain_id: &str, day: u64, amount: u128) {
+        let spent = self.spent.entry((chain_id.to_owned(), day)).or_default();
+        *spent = spent.saturating_add(amount);
+
+        let cutoff = day.saturating_sub(RETAINED_DAYS);
+        self.spent.retain(|(entry_chain_id, entry_day), _| {
+            entry_chain_id != chain_id || *entry_day > cutoff
+        });
+    }
+
+    /// All entries for `chain_id`, oldest first.
+    #[must_use]
+    pub fn entries(&self, chain_id: &str) -> Vec<SpendEntry> {
+        self.spent
+            .iter()
+            .filter(|((entry_chain_id, _), _)| entry_chain_id == chain_id)
+            .map(|((chain_id, day), spent)| SpendEntry {
+                chain_id: chain_id.clone(),
+                day: *day,
+                spent: *spent,
+            })
+            .collect()
+    }
+
+    /// Replace all entries for `chain_id` with the entries in `other`.
+    fn merge_chain(&mut self, chain_id: &str, other: &Self) {
+        self.spent
+            .retain(|(entry_chain_id, _), _| entry_chain_id != chain_id);
+        self.spent.extend(
+            other
+                .spent
+                .iter()
+                .filter(|((entry_chain_id, _), _)| entry_chain_id == chain_id)
+                .map(|(k, v)| (k.clone(), *v)),
+        );
+    }
+}
+
+impl From<Vec<SpendEntry>> for SpendLedger {
+    fn from(value: Vec<SpendEntry>) -> Self {
+        let mut ledger = Self::default();
+
+        for SpendEntry {
+            chain_id,
+            day,
+            spent,
+        } in value
+        {
+            ledger.record(&chain_id, day, spent);
+        }
+
+        ledger
+    }
+}
+
+impl From<SpendLedger> for Vec<SpendEntry> {
+    fn from(value: SpendLedger) -> Self {
+        value
+            .spent
+            .into_iter()
+            .map(|((chain_id, day), spent)| SpendEntry {
+                chain_id,
+                day,
+                spent,
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SpendLedgerError {
+    #[error("error accessing spend ledger at {}", path.display())]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    #[error("invalid spend ledger at {}", path.display())]
+    Decode {
+        path: PathBuf,
+        #[source]
+        source: serde_json::Error,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error(
+    "daily gas budget exceeded for {chain_id}: spent {spent} of {budget} on day {day}, \
+    the budget resets at {resets_at}"
+)]
+pub struct BudgetExceeded {
+    pub chain_id: String,
+    pub day: u64,
+    pub spent: u128,
+    pub budget: u128,
+    /// Unix timestamp (in seconds) at which the budget window rolls over.
+    pub resets_at: u64,
+}
+
+/// The result of [`SpendTracker::admit`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Admission<T> {
+    /// Messages that can be submitted now.
+    pub submit: Vec<T>,
+    /// Messages that must be deferred until the budget window rolls over, if the budget is
+    /// exceeded.
+    pub deferred: Option<(BudgetExceeded, Vec<T>)>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SpendReport {
+    pub chain_id: String,
+    pub day: u64,
+    #[serde(with = "::serde_utils::string")]
+    pub spent: u128,
+    #[serde(with = "::serde_utils::string_opt")]
+    pub daily_budget: Option<u128>,
+    pub history: Vec<SpendEntry>,
+}
+
+/// Tracks the spend of a single chain, persisting it to the configured ledger.
+#[derive(Debug, Clone)]
+pub struct SpendTracker {
+    chain_id: String,
+    config: SpendConfig,
+    ledger: Arc<Mutex<SpendLedger>>,
+}
+
+impl SpendTracker {
+    pub fn new(chain_id: impl Into<String>, config: SpendConfig) -> Result<Self, SpendLedgerError> {
+        let ledger = SpendLedger::load(&config.ledger_path)?;
+
+        Ok(Self {
+            chain_id: chain_id.into(),
+            config,
+            ledger: Arc::new(Mutex::new(ledger)),
+        })
+    }
+
+    /// Split `msgs` into the messages that can be submitted now and the messages that must be
+    /// deferred due to the daily budget being exceeded. Messages for which `is_priority` returns
+    /// true are never deferred.
+    pub fn admit<T>(&self, msgs: Vec<T>, is_priority: impl Fn(&T) -> bool) -> Admission<T> {
+        self.admit_at(unix_now(), msgs, is_priority)
+    }
+
+    pub fn admit_at<T>(
+        &self,
+        now: u64,
+        msgs: Vec<T>,
+        is_priority: impl Fn(&T) -> bool,
+    ) -> Admission<T> {
+        match self.check_budget_at(now) {
+            Ok(()) => Admission {
+                submit: msgs,
+                deferred: None,
+            },
+            Err(exceeded) => {
+                let (submit, deferred) = msgs.into_iter().partition::<Vec<_>, _>(is_priority);
+
+                Admission {
+                    submit,
+                    deferred: (!deferred.is_empty()).then_some((exceeded, deferred)),
+                }
+            }
+        }
+    }
+
+    pub fn check_budget_at(&self, now: u64) -> Result<(), BudgetExceeded> {
+        let Some(budget) = self.config.daily_budget else {
+            return Ok(());
+        };
+
+        let day = now / SECONDS_PER_DAY;
+        let spent = self.lock().spent(&self.chain_id, day);
+
+        if spent >= budget {
+            Err(BudgetExceeded {
+                chain_id: self.chain_id.clone(),
+                day,
+                spent,
+                budget,
+                resets_at: (day + 1) * SECONDS_PER_DAY,
+            })
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Record `amount` as spent now, and persist the ledger.
+    pub fn record(&self, amount: u128) -> Result<(), SpendLedgerError> {
+        self.record_at(unix_now(), amount)
+    }
+
+    pub fn record_at(&self, now: u64, amount: u128) -> Result<(), SpendLedgerError> {
+        let mut ledger = self.lock();
+
+        ledger.record(&self.chain_id, now / SECONDS_PER_DAY, amount);
+
+        // other chains may share this ledger file, so only overwrite the entries for this chain
+        let mut on_disk = SpendLedger::load(&self.config.ledger_path)?;
+        on_disk.merge_chain(&self.chain_id, &ledger);
+        on_disk.store(&self.config.ledger_path)
+    }
+
+    #[must_use]
+    pub fn report(&self) -> SpendReport {
+        self.report_at(unix_now())
+    }
+
+    #[must_use]
+    pub fn report_at(&self, now: u64) -> SpendReport {
+        let ledger = self.lock();
+        let day = now / SECONDS_PER_DAY;
+
+        SpendReport {
+            chain_id: self.chain_id.clone(),
+            day,
+            spent: ledger.spent(&self.chain_id, day),
+            daily_budget: self.config.daily_budget,
+            history: ledger.entries(&self.chain_id),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, SpendLedger> {
+        self.ledger.lock().expect("lock is not poisoned; qed;")
+    }
+}
+
+pub(crate) fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("the current timestamp must be greater than the unix epoch")
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: u64 = 20_000;
+    const NOW: u64 = DAY * SECONDS_PER_DAY + 3600;
+
+    fn tracker(name: &str, daily_budget: Option<u128>) -> SpendTracker {
+        let ledger_path = std::env::temp_dir().join(format!(
+            "chain-utils-spend-{name}-{}.json",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&ledger_path);
+
+        SpendTracker::new(
+            "chain-1",
+            SpendConfig {
+                ledger_path,
+                daily_budget,
+            },
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn rollover() {
+        let tracker = tracker("rollover", Some(100));
+
+        tracker.record_at(NOW, 60).unwrap();
+        tracker.record_at(NOW + 60, 60).unwrap();
+
+        assert_eq!(tracker.report_at(NOW).spent, 120);
+        assert!(tracker.check_budget_at(NOW).is_err());
+
+        // the next day starts with a fresh budget
+        let tomorrow = (DAY + 1) * SECONDS_PER_DAY;
+        assert_eq!(tracker.report_at(tomorrow).spent, 0);
+        assert_eq!(tracker.check_budget_at(tomorrow), Ok(()));
+
+        // old entries are eventually pruned
+        tracker
+            .record_at((DAY + RETAINED_DAYS) * SECONDS_PER_DAY, 1)
+            .unwrap();
+        assert_eq!(
+            tracker.report_at(NOW).history,
+            vec![SpendEntry {
+                chain_id: "chain-1".to_owned(),
+                day: DAY + RETAINED_DAYS,
+                spent: 1,
+            }]
+        );
+    }
+
+    #[test]
+    fn ledger_is_persisted() {
+        let tracker = tracker("persisted", None);
+
+        tracker.record_at(NOW, 42).unwrap();
+
+        let reloaded = SpendTracker::new("chain-1", tracker.config.clone()).unwrap();
+        assert_eq!(reloaded.report_at(NOW).spent, 42);
+
+        // entries for other chains in the same file are preserved
+        let other = SpendTracker::new("chain-2", tracker.config.clone()).unwrap();
+        other.record_at(NOW, 7).unwrap();
+        tracker.record_at(NOW, 1).unwrap();
+
+        let ledger = SpendLedger::load(&tracker.config.ledger_path).unwrap();
+        assert_eq!(ledger.spent("chain-1", DAY), 43);
+        assert_eq!(ledger.spent("chain-2", DAY), 7);
+    }
+
+    #[test]
+    fn budget_exceeded_defers() {
+        let tracker = tracker("deferred", Some(100));
+
+        assert_eq!(
+            tracker.admit_at(NOW, vec![1, 2, 3], |_| false),
+            Admission {
+                submit: vec![1, 2, 3],
+                deferred: None,
+            }
+        );
+
+        tracker.record_at(NOW, 100).unwrap();
+
+        assert_eq!(
+            tracker.admit_at(NOW, vec![1, 2, 3], |_| false),
+            Admission {
+                submit: vec![],
+                deferred: Some((
+                    BudgetExceeded {
+                        chain_id: "chain-1".to_owned(),
+                        day: DAY,
+                        spent: 100,
+                        budget: 100,
+                        resets_at: (DAY + 1) * SECONDS_PER_DAY,
+                    },
+                    vec![1, 2, 3]
+                )),
+            }
+        );
+    }
+
+    #[test]
+    fn priority_messages_are_exempt() {
+        let tracker = tracker("priority", Some(100));
+
+        tracker.record_at(NOW, 1000).unwrap();
+
+        let Admission { submit, deferred } =
+            tracker.admit_at(NOW, vec![1, 2, 3, 4], |msg| msg % 2 == 0);
+
+        assert_eq!(submit, vec![2, 4]);
+        assert_eq!(deferred.map(|(_, msgs)| msgs), Some(vec![1, 3]));
+
+        // a batch consisting only of priority messages is submitted in full
+        assert_eq!(
+            tracker.admit_at(NOW, vec![2, 4], |msg| msg % 2 == 0),
+            Admission {
+                submit: vec![2, 4],
+                deferred: None,
+            }
+        );
+    }
+}
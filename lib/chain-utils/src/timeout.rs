@@ -0,0 +1,206 @@
+//! Timeouts for outbound network calls.
+//!
+//! A hung endpoint would otherwise stall the handler awaiting it indefinitely, and since handlers
+//! are awaited by the JSON-RPC server, enough of them exhaust the concurrency of the server. Calls
+//! are wrapped with [`TimeoutConfig::with_timeout`] (or [`with_timeout`] for the defaults), which
+//! bounds the call by the timeout for its [`CallKind`]. The timeouts can be overridden per plugin:
+//!
+//! ```json
+//! { "query": 10, "broadcast": 30, "connect": 5, "proof": 20 }
+//! ```
+//!
+//! A call that times out fails with [`TimedOut`], which is a transient error and is retried.
+
+use std::{fmt, future::Future, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use tokio::time::Instant;
+
+/// The kind of an outbound call, which determines its timeout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CallKind {
+    /// A read of the state of the chain.
+    Query,
+    /// Submitting a transaction, or waiting for it to be included.
+    Broadcast,
+    /// Establishing a connection to an endpoint.
+    Connect,
+    /// A query for a state proof.
+    Proof,
+}
+
+impl CallKind {
+    #[must_use]
+    pub const fn default_timeout(self) -> Duration {
+        match self {
+            Self::Query => Duration::from_secs(10),
+            Self::Broadcast => Duration::from_secs(30),
+            Self::Connect => Duration::from_secs(5),
+            Self::Proof => Duration::from_secs(20),
+        }
+    }
+}
+
+impl fmt::Display for CallKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Query => "query",
+            Self::Broadcast => "broadcast",
+            Self::Connect => "connect",
+            Self::Proof => "proof",
+        })
+    }
+}
+
+/// The timeouts (in seconds) of each [`CallKind`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TimeoutConfig {
+    #[serde(default = "TimeoutConfig::default_query")]
+    pub query: u64,
+    #[serde(default = "TimeoutConfig::default_broadcast")]
+    pub broadcast: u64,
+    #[serde(default = "TimeoutConfig::default_connect")]
+    pub connect: u64,
+    #[serde(default = "TimeoutConfig::default_proof")]
+    pub proof: u64,
+}
+
+impl TimeoutConfig {
+    const fn default_query() -> u64 {
+        CallKind::Query.default_timeout().as_secs()
+    }
+
+    const fn default_broadcast() -> u64 {
+        CallKind::Broadcast.default_timeout().as_secs()
+    }
+
+    const fn default_connect() -> u64 {
+        CallKind::Connect.default_timeout().as_secs()
+    }
+
+    const fn default_proof() -> u64 {
+        CallKind::Proof.default_timeout().as_secs()
+    }
+
+    /// The timeout of calls of `kind`.
+    #[must_use]
+    pub const fn timeout(&self, kind: CallKind) -> Duration {
+        Duration::from_secs(match kind {
+            CallKind::Query => self.query,
+            CallKind::Broadcast => self.broadcast,
+            CallKind::Connect => self.connect,
+            CallKind::Proof => self.proof,
+        })
+    }
+
+    /// Await `fut`, failing with [`TimedOut`] if it does not complete within the timeout of
+    /// `kind`.
+    pub async fn with_timeout<F: Future>(
+        &self,
+        kind: CallKind,
+        fut: F,
+    ) -> Result<F::Output, TimedOut> {
+        let start = Instant::now();
+
+        tokio::time::timeout(self.timeout(kind), fut)
+            .await
+            .map_err(|_| TimedOut {
+                kind,
+                elapsed: start.elapsed(),
+            })
+    }
+}
+
+impl Default for TimeoutConfig {
+    fn default() -> Self {
+        Self {
+            query: Self::default_query(),
+            broadcast: Self::default_broadcast(),
+            connect: Self::default_connect(),
+            proof: Self::default_proof(),
+        }
+    }
+}
+
+/// Await `fut`, failing with [`TimedOut`] if it does not complete within the default timeout of
+/// `kind`.
+pub async fn with_timeout<F: Future>(kind: CallKind, fut: F) -> Result<F::Output, TimedOut> {
+    TimeoutConfig::default().with_timeout(kind, fut).await
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("{kind} call timed out after {elapsed:?}")]
+pub struct TimedOut {
+    pub kind: CallKind,
+    pub elapsed: Duration,
+}
+
+impl From<TimedOut> for tonic::Status {
+    fn from(value: TimedOut) -> Self {
+        tonic::Status::deadline_exceeded(value.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::pending;
+
+    use super::*;
+
+    #[test]
+    fn config_defaults() {
+        let config = serde_json::from_str::<TimeoutConfig>(r#"{ "proof": 60 }"#).unwrap();
+
+        assert_eq!(
+            config,
+            TimeoutConfig {
+                proof: 60,
+                ..Default::default()
+            }
+        );
+
+        assert_eq!(config.timeout(CallKind::Query), Duration::from_secs(10));
+        assert_eq!(config.timeout(CallKind::Broadcast), Duration::from_secs(30));
+        assert_eq!(config.timeout(CallKind::Connect), Duration::from_secs(5));
+        assert_eq!(config.timeout(CallKind::Proof), Duration::from_secs(60));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn times_out() {
+        assert_eq!(
+            with_timeout(CallKind::Query, pending::<()>()).await,
+            Err(TimedOut {
+                kind: CallKind::Query,
+                elapsed: Duration::from_secs(10),
+            })
+        );
+
+        let config = TimeoutConfig {
+            broadcast: 45,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            config
+                .with_timeout(CallKind::Broadcast, pending::<()>())
+                .await
+                .unwrap_err()
+                .to_string(),
+            "broadcast call timed out after 45s"
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn completes_within_timeout() {
+        assert_eq!(
+            with_timeout(CallKind::Connect, async {
+                tokio::time::sleep(Duration::from_secs(4)).await;
+                1
+            })
+            .await,
+            Ok(1)
+        );
+    }
+}
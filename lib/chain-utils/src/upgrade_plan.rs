@@ -0,0 +1,266 @@
+//! Detection of upgrades scheduled with `x/upgrade`.
+//!
+//! When a cosmos-sdk chain schedules an upgrade that changes its ibc client (i.e. a new chain id),
+//! `x/upgrade` commits the client and consensus states that counterparty clients will be upgraded
+//! to under the `upgrade` store, keyed by the height of the plan. The chain halts at that height,
+//! and counterparty clients must then be upgraded with `MsgUpgradeClient` instead of being updated
+//! with headers, using proofs of those states at the plan height.
+//!
+//! The current plan is read with [`query_current_plan`], which performs the `CurrentPlan` grpc
+//! query through the abci query of the node.
+
+use std::num::NonZeroU64;
+
+use cometbft_rpc::{rpc_types::AbciQueryResponse, JsonRpcError};
+use prost::Message;
+use unionlabs::ibc::core::client::height::Height;
+
+/// The grpc path of the `x/upgrade` `CurrentPlan` query.
+pub const CURRENT_PLAN_PATH: &str = "/cosmos.upgrade.v1beta1.Query/CurrentPlan";
+
+/// `cosmos.upgrade.v1beta1.Plan`, which is not included in the generated protos. The deprecated
+/// `time` and `upgraded_client_state` fields are not decoded.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Plan {
+    #[prost(string, tag = "1")]
+    pub name: String,
+    #[prost(int64, tag = "3")]
+    pub height: i64,
+    #[prost(string, tag = "4")]
+    pub info: String,
+}
+
+/// `cosmos.upgrade.v1beta1.QueryCurrentPlanRequest`, which is not included in the generated
+/// protos.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct QueryCurrentPlanRequest {}
+
+/// `cosmos.upgrade.v1beta1.QueryCurrentPlanResponse`, which is not included in the generated
+/// protos.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct QueryCurrentPlanResponse {
+    #[prost(message, optional, tag = "1")]
+    pub plan: Option<Plan>,
+}
+
+/// An upgrade scheduled with `x/upgrade`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScheduledUpgrade {
+    pub name: String,
+    /// The height that the chain halts at. The upgraded client and consensus states are committed
+    /// under this height, and are proven at it.
+    pub height: NonZeroU64,
+    pub info: String,
+}
+
+impl ScheduledUpgrade {
+    /// The height that counterparty clients must be updated to before being upgraded, in the
+    /// revision of the chain before the upgrade.
+    #[must_use]
+    pub fn upgrade_height(&self, revision_number: u64) -> Height {
+        Height::new_with_revision(revision_number, self.height.get())
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum UpgradePlanError {
+    #[error("error querying the current upgrade plan")]
+    Rpc(#[from] JsonRpcError),
+    #[error("the current upgrade plan query failed with code {code}: {log}")]
+    Query { code: u32, log: String },
+    #[error("error decoding the current upgrade plan")]
+    Decode(#[from] prost::DecodeError),
+    #[error("the upgrade plan `{name}` has an invalid height {height}")]
+    InvalidHeight { name: String, height: i64 },
+}
+
+/// The abci query method of the cometbft rpc, which grpc queries are performed through.
+#[allow(async_fn_in_trait)]
+pub trait AbciQueryClient {
+    async fn abci_query(
+        &self,
+        path: &str,
+        data: Vec<u8>,
+        height: Option<NonZeroU64>,
+    ) -> Result<AbciQueryResponse, JsonRpcError>;
+}
+
+impl AbciQueryClient for cometbft_rpc::Client {
+    async fn abci_query(
+        &self,
+        path: &str,
+        data: Vec<u8>,
+        height: Option<NonZeroU64>,
+    ) -> Result<AbciQueryResponse, JsonRpcError> {
+        cometbft_rpc::Client::abci_query(
+            self,
+            path,
+            data,
+            height.map(|height| {
+                i64::try_from(height.get())
+                    .expect("should be fine")
+                    .try_into()
+                    .expect("invalid height")
+            }),
+            false,
+        )
+        .await
+    }
+}
+
+/// Query the upgrade currently scheduled with `x/upgrade` at `height` (or the latest height if
+/// `None`), if there is one.
+pub async fn query_current_plan(
+    client: &impl AbciQueryClient,
+    height: Option<NonZeroU64>,
+) -> Result<Option<ScheduledUpgrade>, UpgradePlanError> {
+    let response = client
+        .abci_query(
+            CURRENT_PLAN_PATH,
+            QueryCurrentPlanRequest {}.encode_to_vec(),
+            height,
+        )
+        .await?
+        .response;
+
+    if response.code != 0 {
+        return Err(UpgradePlanError::Query {
+            code: response.code,
+            log: response.log,
+        });
+    }
+
+    let Some(plan) =
+        QueryCurrentPlanResponse::decode(response.value.as_deref().unwrap_or_default())?.plan
+    else {
+        return Ok(None);
+    };
+
+    let height = u64::try_from(plan.height)
+        .ok()
+        .and_then(NonZeroU64::new)
+        .ok_or_else(|| UpgradePlanError::InvalidHeight {
+            name: plan.name.clone(),
+            height: plan.height,
+        })?;
+
+    Ok(Some(ScheduledUpgrade {
+        name: plan.name,
+        height,
+        info: plan.info,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Deserialize)]
+    struct JsonRpcResponse<T> {
+        result: T,
+    }
+
+    /// Returns the recorded `abci_query` response, checking that the query is the `CurrentPlan`
+    /// query.
+    struct MockClient(AbciQueryResponse);
+
+    impl MockClient {
+        fn recorded(json: &str) -> Self {
+            Self(
+                serde_json::from_str::<JsonRpcResponse<_>>(json)
+                    .unwrap()
+                    .result,
+            )
+        }
+    }
+
+    impl AbciQueryClient for MockClient {
+        async fn abci_query(
+            &self,
+            path: &str,
+            data: Vec<u8>,
+            height: Option<NonZeroU64>,
+        ) -> Result<AbciQueryResponse, JsonRpcError> {
+            assert_eq!(path, CURRENT_PLAN_PATH);
+            assert!(data.is_empty());
+            assert_eq!(height, None);
+
+            Ok(self.0.clone())
+        }
+    }
+
+    #[tokio::test]
+    async fn scheduled_upgrade() {
+        let upgrade = query_current_plan(
+            &MockClient::recorded(include_str!("./test/upgrade/current-plan.json")),
+            None,
+        )
+        .await
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(upgrade.name, "v1.1.0");
+        assert_eq!(upgrade.height.get(), 1200);
+        assert!(upgrade.info.contains("uniond-release-x86_64-linux"));
+        assert_eq!(
+            upgrade.upgrade_height(1),
+            Height::new_with_revision(1, 1200)
+        );
+    }
+
+    #[tokio::test]
+    async fn no_scheduled_upgrade() {
+        assert_eq!(
+            query_current_plan(
+                &MockClient::recorded(include_str!("./test/upgrade/current-plan-none.json")),
+                None,
+            )
+            .await
+            .unwrap(),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn failed_query() {
+        let err = query_current_plan(
+            &MockClient::recorded(include_str!(
+                "./test/upgrade/current-plan-unknown-query.json"
+            )),
+            None,
+        )
+        .await
+        .unwrap_err();
+
+        assert!(
+            matches!(err, UpgradePlanError::Query { code: 6, ref log } if log == "unknown query path: unknown request"),
+            "{err:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn invalid_height() {
+        let mut client = MockClient::recorded(include_str!("./test/upgrade/current-plan.json"));
+
+        client.0.response.value = Some(
+            QueryCurrentPlanResponse {
+                plan: Some(Plan {
+                    name: "v1.1.0".to_owned(),
+                    height: -1,
+                    info: String::new(),
+                }),
+            }
+            .encode_to_vec()
+            .into(),
+        );
+
+        let err = query_current_plan(&client, None).await.unwrap_err();
+
+        assert!(
+            matches!(err, UpgradePlanError::InvalidHeight { height: -1, .. }),
+            "{err:?}"
+        );
+    }
+}
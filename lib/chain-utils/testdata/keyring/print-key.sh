@@ -0,0 +1,10 @@
+#!/bin/sh
+# Prints the key passed as the first argument, the way a secret manager CLI would. Exits with the
+# second argument (if provided) instead.
+
+if [ -n "$2" ]; then
+    echo "secret not found" >&2
+    exit "$2"
+fi
+
+echo "$1"
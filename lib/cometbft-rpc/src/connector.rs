@@ -0,0 +1,98 @@
+use std::{fmt, future::Future, io, pin::Pin, sync::Arc};
+
+use jsonrpsee::{core::client::Client as WsClient, ws_client::WsClientBuilder};
+use tokio::net::TcpStream;
+use tokio_rustls::{
+    rustls::{self, pki_types::ServerName},
+    TlsConnector,
+};
+use unionlabs::ErrorReporter;
+use url::Url;
+
+use crate::JsonRpcError;
+
+type ConnectFuture = Pin<Box<dyn Future<Output = io::Result<TcpStream>> + Send>>;
+
+/// Opens the tcp connection to `(host, port)` for a websocket client, i.e. through a proxy. See
+/// [`Client::new_with_connector`](crate::Client::new_with_connector).
+#[derive(Clone)]
+pub struct Connector(Arc<dyn Fn(String, u16) -> ConnectFuture + Send + Sync>);
+
+impl fmt::Debug for Connector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Connector").finish_non_exhaustive()
+    }
+}
+
+impl Connector {
+    pub fn new<F, Fut>(connect: F) -> Self
+    where
+        F: Fn(String, u16) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = io::Result<TcpStream>> + Send + 'static,
+    {
+        Self(Arc::new(move |host, port| -> ConnectFuture {
+            Box::pin(connect(host, port))
+        }))
+    }
+
+    pub async fn connect(&self, host: String, port: u16) -> io::Result<TcpStream> {
+        (self.0)(host, port).await
+    }
+}
+
+/// Build a websocket client for `url` over a connection opened by `connector`. For `wss://`
+/// urls, tls is negotiated over the connection, verifying the server with the platform's
+/// certificate verifier.
+pub(crate) async fn build_ws_client(
+    builder: WsClientBuilder,
+    url: &str,
+    connector: &Connector,
+) -> Result<WsClient, JsonRpcError> {
+    let parsed =
+        Url::parse(url).map_err(|err| JsonRpcError::Custom(format!("invalid url {url}: {err}")))?;
+
+    let host = parsed
+        .host_str()
+        .ok_or_else(|| JsonRpcError::Custom(format!("url {url} has no host")))?;
+    // ipv6 hosts are bracketed in urls
+    let host = host
+        .strip_prefix('[')
+        .and_then(|host| host.strip_suffix(']'))
+        .unwrap_or(host)
+        .to_owned();
+    let port = parsed
+        .port_or_known_default()
+        .ok_or_else(|| JsonRpcError::Custom(format!("url {url} has no port")))?;
+
+    let stream = connector
+        .connect(host.clone(), port)
+        .await
+        .map_err(|err| transport_error(format!("unable to connect to {url}"), err))?;
+
+    if let Err(err) = stream.set_nodelay(true) {
+        tracing::warn!(err = %ErrorReporter(err), "unable to set nodelay");
+    }
+
+    match parsed.scheme() {
+        "wss" => {
+            // only fails if a provider has already been installed, which is fine
+            let _ = rustls::crypto::ring::default_provider().install_default();
+
+            let server_name = ServerName::try_from(host).map_err(|err| {
+                transport_error(format!("invalid tls server name for {url}"), err)
+            })?;
+
+            let stream = TlsConnector::from(Arc::new(rustls_platform_verifier::tls_config()))
+                .connect(server_name, stream)
+                .await
+                .map_err(|err| transport_error(format!("tls handshake with {url} failed"), err))?;
+
+            builder.build_with_stream(url, stream).await
+        }
+        _ => builder.build_with_stream(url, stream).await,
+    }
+}
+
+fn transport_error(context: String, err: impl std::error::Error) -> JsonRpcError {
+    JsonRpcError::Custom(format!("{context}: {}", ErrorReporter(err)))
+}
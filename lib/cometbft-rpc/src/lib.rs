@@ -26,16 +26,20 @@ use unionlabs::{
 
 use crate::rpc_types::{
     AbciQueryResponse, AllValidatorsResponse, BlockResponse, BlockResultsResponse,
-    BlockchainResponse, BroadcastTxSyncResponse, CommitResponse, Order, StatusResponse, TxResponse,
-    TxSearchResponse, ValidatorsResponse,
+    BlockchainResponse, BroadcastTxSyncResponse, CommitResponse, ConsensusParamsResponse, Order,
+    StatusResponse, TxResponse, TxSearchResponse, ValidatorsResponse,
 };
 
 #[cfg(test)]
 mod tests;
 
+mod connector;
 pub mod rpc_types;
 pub mod serde;
 pub use cometbft_types as types;
+pub use jsonrpsee::http_client::{HeaderMap, HeaderValue};
+
+pub use crate::connector::Connector;
 
 pub type JsonRpcError = jsonrpsee::core::client::Error;
 
@@ -46,15 +50,49 @@ pub struct Client {
 
 impl Client {
     pub async fn new(url: impl AsRef<str>) -> Result<Self, JsonRpcError> {
+        Self::new_with_headers(url, HeaderMap::new()).await
+    }
+
+    /// Connect to `url`, sending `headers` with every http request, or with the websocket
+    /// handshake (including on reconnects) for websocket urls.
+    pub async fn new_with_headers(
+        url: impl AsRef<str>,
+        headers: HeaderMap,
+    ) -> Result<Self, JsonRpcError> {
+        Self::new_with_connector(url, headers, None).await
+    }
+
+    /// Connect to `url` as with [`Self::new_with_headers`], opening the tcp connection (including
+    /// on reconnects) with `connector` if provided, i.e. to connect through a proxy.
+    ///
+    /// A connector is only supported for websocket urls.
+    pub async fn new_with_connector(
+        url: impl AsRef<str>,
+        headers: HeaderMap,
+        connector: Option<Connector>,
+    ) -> Result<Self, JsonRpcError> {
         let url = url.as_ref().to_owned();
 
         let inner = match url.split_once("://") {
             Some(("ws" | "wss", _)) => {
                 let client = reconnecting_jsonrpc_ws_client::Client::new(move || {
-                    WsClientBuilder::default()
+                    let span = debug_span!("cometbft_rpc_client", %url);
+
+                    let builder = WsClientBuilder::default()
                         .enable_ws_ping(PingConfig::new())
-                        .build(url.clone())
-                        .instrument(debug_span!("cometbft_rpc_client", %url))
+                        .set_headers(headers.clone());
+                    let url = url.clone();
+                    let connector = connector.clone();
+
+                    async move {
+                        match connector {
+                            Some(connector) => {
+                                connector::build_ws_client(builder, &url, &connector).await
+                            }
+                            None => builder.build(url).await,
+                        }
+                    }
+                    .instrument(span)
                 });
 
                 // TODO: Config
@@ -65,9 +103,16 @@ impl Client {
 
                 ClientInner::Ws(client)
             }
-            Some(("http" | "https", _)) => {
-                ClientInner::Http(HttpClientBuilder::default().build(url)?)
+            Some(("http" | "https", _)) if connector.is_some() => {
+                return Err(JsonRpcError::Custom(format!(
+                    "a connector is only supported for websocket urls, found {url}"
+                )))
             }
+            Some(("http" | "https", _)) => ClientInner::Http(
+                HttpClientBuilder::default()
+                    .set_headers(headers)
+                    .build(url)?,
+            ),
             _ => return Err(JsonRpcError::Custom(format!("invalid url {url}"))),
         };
 
@@ -195,6 +240,15 @@ impl Client {
         self.inner.request("status", rpc_params!()).await
     }
 
+    pub async fn consensus_params(
+        &self,
+        height: Option<NonZeroU64>,
+    ) -> Result<ConsensusParamsResponse, JsonRpcError> {
+        self.inner
+            .request("consensus_params", (height.map(|x| x.to_string()),))
+            .await
+    }
+
     pub async fn block(&self, height: Option<NonZeroU64>) -> Result<BlockResponse, JsonRpcError> {
         self.inner
             .request("block", (height.map(|x| x.to_string()),))
@@ -164,6 +164,33 @@ pub struct BlockResultsResponse {
     pub finalize_block_events: Option<Vec<Event>>,
 }
 
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConsensusParamsResponse {
+    #[serde(with = "::serde_utils::string")]
+    pub block_height: NonZeroU64,
+    pub consensus_params: ConsensusParams,
+}
+
+/// The consensus params of a chain. Only the block params are currently used, all other params
+/// are ignored.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConsensusParams {
+    pub block: BlockParams,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BlockParams {
+    /// The maximum size of a block, in bytes. `-1` is the maximum size supported by CometBFT
+    /// (100 MiB).
+    #[serde(with = "::serde_utils::string")]
+    pub max_bytes: i64,
+    /// The maximum gas of a block, or `-1` if unlimited.
+    #[serde(with = "::serde_utils::string")]
+    pub max_gas: i64,
+}
+
 #[derive(macros::Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct BroadcastTxSyncResponse {
@@ -341,3 +341,162 @@ mod validators {
         );
     }
 }
+
+mod consensus_params {
+    use std::num::NonZeroU64;
+
+    use super::*;
+    use crate::rpc_types::{BlockParams, ConsensusParams, ConsensusParamsResponse};
+
+    #[test]
+    fn consensus_params() {
+        ensure_json(
+            "testdata/consensus_params/union-testnet-9-1000.json",
+            ConsensusParamsResponse {
+                block_height: NonZeroU64::new(1000).unwrap(),
+                consensus_params: ConsensusParams {
+                    block: BlockParams {
+                        max_bytes: 22020096,
+                        max_gas: -1,
+                    },
+                },
+            },
+        );
+    }
+}
+
+mod headers {
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+    };
+
+    use crate::{Client, Connector, HeaderMap, HeaderValue};
+
+    /// Accept a single http request (or websocket handshake) and return its headers, lowercased.
+    /// The request is answered with `response`.
+    async fn capture_headers(listener: TcpListener, response: &'static str) -> Vec<String> {
+        let (mut stream, _) = listener.accept().await.unwrap();
+
+        let mut request = vec![];
+        let mut chunk = [0; 1024];
+        while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+            let read = stream.read(&mut chunk).await.unwrap();
+            if read == 0 {
+                break;
+            }
+            request.extend_from_slice(&chunk[..read]);
+        }
+
+        stream.write_all(response.as_bytes()).await.unwrap();
+
+        String::from_utf8_lossy(&request)
+            .split("\r\n\r\n")
+            .next()
+            .unwrap()
+            .lines()
+            .skip(1)
+            .map(str::to_ascii_lowercase)
+            .collect()
+    }
+
+    fn authorization() -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("authorization", HeaderValue::from_static("Bearer secret"));
+        headers
+    }
+
+    #[tokio::test]
+    async fn http_requests_include_headers() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+
+        let server = tokio::spawn(capture_headers(
+            listener,
+            "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: 70\r\n\r\n\
+            {\"jsonrpc\":\"2.0\",\"id\":0,\"error\":{\"code\":-32601,\"message\":\"not found\"}}",
+        ));
+
+        let client = Client::new_with_headers(url, authorization())
+            .await
+            .unwrap();
+
+        // the mock server only captures the request, the response is irrelevant
+        let _ = client.status().await;
+
+        let headers = server.await.unwrap();
+        assert!(
+            headers.contains(&"authorization: bearer secret".to_owned()),
+            "{headers:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn websocket_handshake_includes_headers() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}/websocket", listener.local_addr().unwrap());
+
+        let server = tokio::spawn(capture_headers(
+            listener,
+            "HTTP/1.1 401 Unauthorized\r\ncontent-length: 0\r\n\r\n",
+        ));
+
+        // the handshake is rejected, so the client never connects
+        let _ = Client::new_with_headers(url, authorization()).await;
+
+        let headers = server.await.unwrap();
+        assert!(
+            headers.contains(&"authorization: bearer secret".to_owned()),
+            "{headers:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn websocket_connects_with_connector() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let server = tokio::spawn(capture_headers(
+            listener,
+            "HTTP/1.1 401 Unauthorized\r\ncontent-length: 0\r\n\r\n",
+        ));
+
+        // the host doesn't resolve, so the handshake can only reach the server through the
+        // connector
+        let connector = Connector::new(move |host, port| {
+            assert_eq!((host.as_str(), port), ("cometbft.invalid", 26657));
+            TcpStream::connect(addr)
+        });
+
+        let _ = Client::new_with_connector(
+            "ws://cometbft.invalid:26657/websocket",
+            authorization(),
+            Some(connector),
+        )
+        .await;
+
+        let headers = server.await.unwrap();
+        assert!(
+            headers.contains(&"host: cometbft.invalid:26657".to_owned()),
+            "{headers:?}"
+        );
+        assert!(
+            headers.contains(&"authorization: bearer secret".to_owned()),
+            "{headers:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn connector_requires_websocket() {
+        let connector: Connector =
+            Arc::new(|host, port| Box::pin(TcpStream::connect((host, port))));
+
+        assert!(Client::new_with_connector(
+            "http://localhost:26657",
+            HeaderMap::new(),
+            Some(connector)
+        )
+        .await
+        .is_err());
+    }
+}
@@ -7,6 +7,7 @@ use std::{
 };
 
 use gnark_key_parser::VerifyingKey;
+use sha2::{Digest, Sha256};
 use substrate_bn::{G1, G2};
 
 pub const FQ_SIZE: usize = 32;
@@ -24,6 +25,8 @@ fn parse_verifying_key(buf: &[u8]) -> String {
     let pedersen_g = G2Const(parsed_key.commitment_key.g);
     let pedersen_g_root_sigma_neg = G2Const(parsed_key.commitment_key.g_root_sigma_neg);
 
+    let verifying_key_sha256: [u8; 32] = Sha256::digest(buf).into();
+
     let gamma_abc_size = parsed_key.gamma_abc_g1.len();
     let s: String = parsed_key
         .gamma_abc_g1
@@ -44,6 +47,8 @@ fn parse_verifying_key(buf: &[u8]) -> String {
             pub const PEDERSEN_G_ROOT_SIGMA_NEG: ::substrate_bn::G2 = {pedersen_g_root_sigma_neg};
 
             pub const GAMMA_ABC_G1: [substrate_bn::G1; {gamma_abc_size}] = [{s}];
+
+            pub const VERIFYING_KEY_SHA256: [u8; 32] = {verifying_key_sha256:?};
         "#
     )
 }
@@ -5,9 +5,12 @@ extern crate alloc;
 use alloc::vec::Vec;
 use core::marker::PhantomData;
 
-use ark_ff::vec;
 use byteorder::{BigEndian, ByteOrder};
-use cometbls_light_client_types::{light_header::LightHeader, ChainId};
+use cometbls_light_client_types::{
+    light_header::LightHeader,
+    zk_proof::{ZkProof, ZkProofInputs},
+    ChainId,
+};
 use constants::*;
 use hex_literal::hex;
 use sha3::Digest;
@@ -27,6 +30,9 @@ pub const PRIME_R_MINUS_ONE: U256 = U256::from_limbs([
     3486998266802970665,
 ]);
 
+/// The sha256 hash of the gnark verifying key this verifier was built with.
+pub const VERIFYING_KEY_HASH: H256 = H256::new(VERIFYING_KEY_SHA256);
+
 const _: () = assert!(GAMMA_ABC_G1.len() == NB_PUBLIC_INPUTS + 1);
 
 fn hmac_keccak(message: &[u8]) -> [u8; 32] {
@@ -185,6 +191,23 @@ pub enum Error {
     InvalidHeight,
     InvalidTimestamp,
     InvalidSliceLength,
+    InvalidVerifyingKeyHash { expected: H256, actual: H256 },
+}
+
+/// Ensure that this verifier was built with the verifying key that has the `expected` hash, i.e. that `expected` is [`VERIFYING_KEY_HASH`]. This only checks the compiled-in key, not anything carried in a proof.
+///
+/// Clients that pin a verifying key (see [`ClientState::zk_verifying_key_hash`]) must call this before [`verify_zkp`], as a proof produced by a different circuit version will otherwise only be rejected as an [`Error::InvalidProof`].
+///
+/// [`ClientState::zk_verifying_key_hash`]: cometbls_light_client_types::ClientState::zk_verifying_key_hash
+pub fn verify_verifying_key_hash(expected: H256) -> Result<(), Error> {
+    if expected == VERIFYING_KEY_HASH {
+        Ok(())
+    } else {
+        Err(Error::InvalidVerifyingKeyHash {
+            expected,
+            actual: VERIFYING_KEY_HASH,
+        })
+    }
 }
 
 pub fn verify_zkp(
@@ -193,20 +216,39 @@ pub fn verify_zkp(
     header: &LightHeader,
     zkp: impl Into<Vec<u8>>,
 ) -> Result<(), Error> {
+    let inputs = ZkProofInputs::new(chain_id.clone(), trusted_validators_hash, header)
+        .map_err(|_| Error::InvalidTimestamp)?;
+
     verify_generic_zkp_2(
-        chain_id,
-        trusted_validators_hash,
-        header,
+        inputs.hash(),
         PEDERSEN_G,
         PEDERSEN_G_ROOT_SIGMA_NEG,
         ZKP::try_from(zkp.into().as_ref())?,
     )
 }
 
+/// Verify a decoded [`ZkProof`] against the `inputs` it commits to, i.e. to check a proof off
+/// chain before submitting it.
+///
+/// `verifying_key_hash` is the hash of the verifying key that the proof is expected to be valid
+/// for, see [`verify_verifying_key_hash`].
+pub fn verify(
+    verifying_key_hash: H256,
+    proof: &ZkProof,
+    inputs: &ZkProofInputs,
+) -> Result<(), Error> {
+    verify_verifying_key_hash(verifying_key_hash)?;
+
+    verify_generic_zkp_2(
+        inputs.hash(),
+        PEDERSEN_G,
+        PEDERSEN_G_ROOT_SIGMA_NEG,
+        ZKP::try_from(proof.encode().as_slice())?,
+    )
+}
+
 fn verify_generic_zkp_2(
-    chain_id: &ChainId,
-    trusted_validators_hash: H256,
-    header: &LightHeader,
+    inputs_hash: H256,
     g: substrate_bn::G2,
     g_root_sigma_neg: substrate_bn::G2,
     zkp: ZKP<BigEndian>,
@@ -216,44 +258,8 @@ fn verify_generic_zkp_2(
         substrate_bn::Fr::new(x.0 .0.into()).ok_or(Error::InvalidPublicInput)
     };
     let commitment_hash = hash_commitment(&zkp.proof_commitment)?;
-    let mut inputs_hash = <[u8; 32]>::from(
-        sha2::Sha256::new()
-            .chain_update(
-                vec![0u8; 32 - chain_id.as_str().len()]
-                    .into_iter()
-                    .chain(chain_id.as_str().bytes())
-                    .collect::<Vec<_>>(),
-            )
-            .chain_update(
-                U256::from(
-                    u64::try_from(i64::from(header.height)).map_err(|_| Error::InvalidHeight)?,
-                )
-                .to_be_bytes(),
-            )
-            .chain_update(
-                U256::from(
-                    u64::try_from(i64::from(header.time.seconds))
-                        .map_err(|_| Error::InvalidTimestamp)?,
-                )
-                .to_be_bytes(),
-            )
-            .chain_update(
-                U256::from(
-                    u64::try_from(i32::from(header.time.nanos))
-                        .map_err(|_| Error::InvalidTimestamp)?,
-                )
-                .to_be_bytes(),
-            )
-            .chain_update(header.validators_hash)
-            .chain_update(header.next_validators_hash)
-            .chain_update(header.app_hash)
-            .chain_update(trusted_validators_hash)
-            .finalize(),
-    );
-    // drop the most significant byte to fit in bn254 F_r
-    inputs_hash[0] = 0;
     let public_inputs: [substrate_bn::Fr; NB_PUBLIC_INPUTS] = [
-        decode_scalar(U256::from_be_bytes(inputs_hash))?,
+        decode_scalar(U256::from_be_bytes(*inputs_hash.get()))?,
         decode_scalar(commitment_hash)?,
     ];
     let initial_point = GAMMA_ABC_G1[0] + zkp.proof_commitment.into();
@@ -313,6 +319,70 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_verifying_key_hash() {
+        assert_eq!(verify_verifying_key_hash(VERIFYING_KEY_HASH), Ok(()));
+        assert_eq!(
+            VERIFYING_KEY_HASH,
+            H256::new(<[u8; 32]>::from(sha2::Sha256::digest(include_bytes!(
+                "../verifying_key.bin"
+            ))))
+        );
+    }
+
+    #[test]
+    fn test_mismatched_verifying_key_hash() {
+        // the hash of the embedded verifying key with the lowest bit of the last byte flipped,
+        // standing in for the hash of a verifying key of a different circuit
+        let mut expected = VERIFYING_KEY_SHA256;
+        expected[31] ^= 1;
+        let expected: H256 = expected.into();
+
+        assert_eq!(
+            verify_verifying_key_hash(expected),
+            Err(Error::InvalidVerifyingKeyHash {
+                expected,
+                actual: VERIFYING_KEY_HASH,
+            })
+        );
+    }
+
+    #[test]
+    fn test_verify_decoded() {
+        let proof = ZkProof::decode(&hex!("03CF56142A1E03D2445A82100FEAF70C1CD95A731ED85792AFFF5792EC0BDD2108991BB56F9043A269F88903DE616A9AB99A3C5AB778E566744B060456C5616C06BCE7F1930421768C2CBD79F88D08EC3A52D7C9A867064E973064385E9C945E02951190DD7CE1662546733DD540188C96E608CA750FEF36B39E2577833634C70AE6F1A6D00DC6C21446AAF285EF35D944E8782B131300574F9A889C7E708A2325E9A78013BBE869D38B19C602DAF69644C77D177E99ED76398BCEE13C61FDBF2E178A5BA028A36033E54D1D9A0071E82E04079A5305347EBAC6D66F6EBFA48B1DA1BF9DC5A51EFA292E1DC7B85D26F18422EB386C48CA75434039764448BB96268DDC2CF683DDCA4BD83DF21C5631CF784375EEBE77EABC2DE77886BF1D48392C9C52E063B4A7131EAB9ABBA12A9F26888BC37366D41AC7D4BAC0BF6755ACB009BF9F36F380B6D0EEAABF066503A1B6E01DCC965D968D7694E01B1755E6BDD21C7A80B41682748F9B7151714BE34AA79AAD48BBB2A84525F6CDF812658C6E4F")).unwrap();
+
+        let mut inputs = ZkProofInputs::new(
+            ChainId::from_string("union-devnet-1337").unwrap(),
+            hex!("20DDFE7A0F75C65D876316091ECCD494A54A2BB324C872015F73E528D53CB9C4").into(),
+            &LightHeader {
+                height: 3405691582.try_into().unwrap(),
+                time: Timestamp {
+                    seconds: 1732205251.try_into().unwrap(),
+                    nanos: 998131342.try_into().unwrap(),
+                },
+                validators_hash: hex!(
+                    "20DDFE7A0F75C65D876316091ECCD494A54A2BB324C872015F73E528D53CB9C4"
+                )
+                .into(),
+                next_validators_hash: hex!(
+                    "20DDFE7A0F75C65D876316091ECCD494A54A2BB324C872015F73E528D53CB9C4"
+                )
+                .into(),
+                app_hash: hex!("EE7E3E58F98AC95D63CE93B270981DF3EE54CA367F8D521ED1F444717595CD36")
+                    .into(),
+            },
+        )
+        .unwrap();
+
+        assert_eq!(verify(VERIFYING_KEY_HASH, &proof, &inputs), Ok(()));
+
+        inputs.untrusted_height += 1;
+        assert_eq!(
+            verify(VERIFYING_KEY_HASH, &proof, &inputs),
+            Err(Error::InvalidProof)
+        );
+    }
+
     #[test]
     fn test_decode() {
         ZKP::try_from(hex!("1c9bc15a0c4541aff1d12780d6cf4ae2bdc6e3afafceae9d4fa36209fa323b68002e9c77c223d830e5df6a80cdd683f0986353933ee3179970fccc5d893219d30726f3b8c0dbe630b815b01b5557228a0dfeb0e0435bb0d15d1ccff7f6133fc110937d9fceee2f9052468c198fafeca89d524142a0efa9dc4df445853ce617302059018fef03dc34456ad201d2a5420a7d1c8fac57cb48cbe6709ac4da27d1eb250f73eab007d26cbff41ceb4564ab1cdfa83e9ee88be4f816dc841bbf2e90c80186ad9437fce7655c71b54addae1ccea429da3edba3232d073cb7e89ff2d27218556f1af0c446962ace932f637279dd0ad3ef1501fb6da39d5f68282f54bcf6094999672f3d8cbbf0409aef1048175ffff50b03a5154016d307a2ef425ffee509cd447b22ce6331c7a3473b2c6da1f9d550e8c3ab19bde65e699e07f4f2886c03ec4ff2faa0e342de7ac5daf32025acd6070c19ed8b007c121db0d955472c7d2e38d5a943d15bc902613029e4baa8c26034ff280e3a4d5468fcd6745afe53b5").as_slice()).unwrap();
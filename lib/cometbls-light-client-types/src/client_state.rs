@@ -24,6 +24,22 @@ pub struct ClientState {
         serde(default, skip_serializing_if = "H256::is_zero")
     )]
     pub contract_address: H256,
+    /// Hash of the groth16 verifying key that header proofs for this client must be verified with. If this is `None`, proofs are verified with whatever verifying key the light client was built with (the legacy behaviour).
+    ///
+    /// Pinning this allows for the circuit to be upgraded without silently accepting proofs from a different circuit version; after a circuit upgrade, the client must be migrated to the new key hash (see [`ClientState::with_zk_verifying_key_hash`]).
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub zk_verifying_key_hash: Option<H256>,
+}
+
+impl ClientState {
+    /// Migrate this client state to the groth16 verifying key with the provided hash. Passing `None` removes the pin and reverts to the legacy behaviour.
+    #[must_use]
+    pub fn with_zk_verifying_key_hash(self, zk_verifying_key_hash: Option<H256>) -> Self {
+        Self {
+            zk_verifying_key_hash,
+            ..self
+        }
+    }
 }
 
 #[cfg(feature = "proto")]
@@ -46,6 +62,10 @@ pub mod proto {
                 max_clock_drift: value.max_clock_drift,
                 frozen_height: Some(value.frozen_height.into()),
                 latest_height: Some(value.latest_height.into()),
+                zk_verifying_key_hash: value
+                    .zk_verifying_key_hash
+                    .map(Into::into)
+                    .unwrap_or_default(),
             }
         }
     }
@@ -56,6 +76,8 @@ pub mod proto {
         MissingField(#[from] MissingField),
         #[error("invalid chain_id")]
         ChainId(#[from] InvalidLength),
+        #[error("invalid zk_verifying_key_hash")]
+        ZkVerifyingKeyHash(#[source] InvalidLength),
     }
 
     impl TryFrom<protos::union::ibc::lightclients::cometbls::v1::ClientState> for ClientState {
@@ -71,6 +93,16 @@ pub mod proto {
                 frozen_height: required!(value.frozen_height)?.into(),
                 latest_height: required!(value.latest_height)?.into(),
                 contract_address: H256::default(),
+                zk_verifying_key_hash: if value.zk_verifying_key_hash.is_empty() {
+                    None
+                } else {
+                    Some(
+                        value
+                            .zk_verifying_key_hash
+                            .try_into()
+                            .map_err(Error::ZkVerifyingKeyHash)?,
+                    )
+                },
             })
         }
     }
@@ -98,19 +130,42 @@ pub mod ethabi {
             uint64 latestHeight;
             bytes32 contractAddress;
         }
+
+        struct SolClientStateWithZkVerifyingKeyHash {
+            bytes31 chainId;
+            uint64 trustingPeriod;
+            uint64 maxClockDrift;
+            uint64 frozenHeight;
+            uint64 latestHeight;
+            bytes32 contractAddress;
+            bytes32 zkVerifyingKeyHash;
+        }
     }
 
+    // client states without a pinned verifying key are encoded with the legacy layout, so that they remain readable by existing consumers
     impl Encode<EthAbi> for ClientState {
         fn encode(self) -> Vec<u8> {
-            SolClientState {
-                chainId: self.chain_id.into_fixed_bytes(),
-                trustingPeriod: self.trusting_period,
-                maxClockDrift: self.max_clock_drift,
-                frozenHeight: self.frozen_height.height(),
-                latestHeight: self.latest_height.height(),
-                contractAddress: self.contract_address.into(),
+            match self.zk_verifying_key_hash {
+                Some(zk_verifying_key_hash) => SolClientStateWithZkVerifyingKeyHash {
+                    chainId: self.chain_id.into_fixed_bytes(),
+                    trustingPeriod: self.trusting_period,
+                    maxClockDrift: self.max_clock_drift,
+                    frozenHeight: self.frozen_height.height(),
+                    latestHeight: self.latest_height.height(),
+                    contractAddress: self.contract_address.into(),
+                    zkVerifyingKeyHash: zk_verifying_key_hash.into(),
+                }
+                .abi_encode_params(),
+                None => SolClientState {
+                    chainId: self.chain_id.into_fixed_bytes(),
+                    trustingPeriod: self.trusting_period,
+                    maxClockDrift: self.max_clock_drift,
+                    frozenHeight: self.frozen_height.height(),
+                    latestHeight: self.latest_height.height(),
+                    contractAddress: self.contract_address.into(),
+                }
+                .abi_encode_params(),
             }
-            .abi_encode_params()
         }
     }
 
@@ -118,7 +173,21 @@ pub mod ethabi {
         type Error = TryFromEthAbiBytesErrorAlloy<Error>;
 
         fn decode(bytes: &[u8]) -> Result<Self, Self::Error> {
-            let client_state = SolClientState::abi_decode(bytes, true)?;
+            let (client_state, zk_verifying_key_hash) =
+                match SolClientStateWithZkVerifyingKeyHash::abi_decode(bytes, true) {
+                    Ok(client_state) => (
+                        SolClientState {
+                            chainId: client_state.chainId,
+                            trustingPeriod: client_state.trustingPeriod,
+                            maxClockDrift: client_state.maxClockDrift,
+                            frozenHeight: client_state.frozenHeight,
+                            latestHeight: client_state.latestHeight,
+                            contractAddress: client_state.contractAddress,
+                        },
+                        Some(client_state.zkVerifyingKeyHash.into()),
+                    ),
+                    Err(_) => (SolClientState::abi_decode(bytes, true)?, None),
+                };
 
             Ok(Self {
                 chain_id: ChainId::try_from_fixed_bytes(client_state.chainId)
@@ -128,6 +197,7 @@ pub mod ethabi {
                 frozen_height: Height::new(client_state.frozenHeight),
                 latest_height: Height::new(client_state.latestHeight),
                 contract_address: client_state.contractAddress.into(),
+                zk_verifying_key_hash,
             })
         }
     }
@@ -138,3 +208,137 @@ pub mod ethabi {
         ChainId(#[from] FromUtf8Error),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use hex_literal::hex;
+
+    use super::*;
+
+    fn mk_client_state(zk_verifying_key_hash: Option<H256>) -> ClientState {
+        ClientState {
+            chain_id: ChainId::from_string("union-devnet-1337").unwrap(),
+            trusting_period: 1_000_000_000,
+            max_clock_drift: 2_000_000_000,
+            frozen_height: Height::new(0),
+            latest_height: Height::new_with_revision(1337, 100),
+            contract_address: H256::default(),
+            zk_verifying_key_hash,
+        }
+    }
+
+    const ZK_VERIFYING_KEY_HASH: H256 = H256::new(hex!(
+        "4a8d1e4e6fa6c5e0c1e1c2f1b0e4a6b4e1c2d3a4b5c6d7e8f90a1b2c3d4e5f60"
+    ));
+
+    #[test]
+    fn with_zk_verifying_key_hash() {
+        let client_state =
+            mk_client_state(None).with_zk_verifying_key_hash(Some(ZK_VERIFYING_KEY_HASH));
+
+        assert_eq!(client_state, mk_client_state(Some(ZK_VERIFYING_KEY_HASH)));
+        assert_eq!(
+            client_state.with_zk_verifying_key_hash(None),
+            mk_client_state(None)
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn json_legacy_decode() {
+        let json = r#"{"chain_id":"union-devnet-1337","trusting_period":1000000000,"max_clock_drift":2000000000,"frozen_height":"0","latest_height":"1337-100"}"#;
+
+        assert_eq!(
+            serde_json::from_str::<ClientState>(json).unwrap(),
+            mk_client_state(None)
+        );
+    }
+
+    #[cfg(feature = "proto")]
+    #[test]
+    fn proto_legacy_decode() {
+        use unionlabs::{
+            encoding::{DecodeAs, EncodeAs, Proto},
+            test_utils::assert_proto_roundtrip,
+        };
+
+        // the legacy encoding is identical to the encoding of a client state without a pinned key
+        let legacy = protos::union::ibc::lightclients::cometbls::v1::ClientState {
+            chain_id: "union-devnet-1337".to_owned(),
+            trusting_period: 1_000_000_000,
+            max_clock_drift: 2_000_000_000,
+            frozen_height: Some(Height::new(0).into()),
+            latest_height: Some(Height::new_with_revision(1337, 100).into()),
+            zk_verifying_key_hash: vec![],
+        };
+
+        assert_eq!(
+            ClientState::try_from(legacy.clone()).unwrap(),
+            mk_client_state(None)
+        );
+        assert_eq!(
+            protos::union::ibc::lightclients::cometbls::v1::ClientState::from(mk_client_state(
+                None
+            )),
+            legacy
+        );
+
+        assert_proto_roundtrip(&mk_client_state(Some(ZK_VERIFYING_KEY_HASH)));
+
+        assert_eq!(
+            ClientState::decode_as::<Proto>(
+                &mk_client_state(Some(ZK_VERIFYING_KEY_HASH)).encode_as::<Proto>()
+            )
+            .unwrap()
+            .zk_verifying_key_hash,
+            Some(ZK_VERIFYING_KEY_HASH)
+        );
+    }
+
+    #[cfg(feature = "proto")]
+    #[test]
+    fn proto_invalid_zk_verifying_key_hash() {
+        let mut raw = protos::union::ibc::lightclients::cometbls::v1::ClientState::from(
+            mk_client_state(None),
+        );
+        raw.zk_verifying_key_hash = vec![1, 2, 3];
+
+        assert!(matches!(
+            ClientState::try_from(raw),
+            Err(proto::Error::ZkVerifyingKeyHash(_))
+        ));
+    }
+
+    #[cfg(feature = "ethabi")]
+    #[test]
+    fn ethabi_legacy_decode() {
+        use alloy::sol_types::SolValue;
+        use unionlabs::encoding::{DecodeAs, EncodeAs, EthAbi};
+
+        let legacy = ethabi::SolClientState {
+            chainId: mk_client_state(None).chain_id.into_fixed_bytes(),
+            trustingPeriod: 1_000_000_000,
+            maxClockDrift: 2_000_000_000,
+            frozenHeight: 0,
+            latestHeight: 100,
+            contractAddress: H256::default().into(),
+        }
+        .abi_encode_params();
+
+        // ethabi heights are revisionless
+        let expected = ClientState {
+            latest_height: Height::new(100),
+            ..mk_client_state(None)
+        };
+
+        assert_eq!(ClientState::decode_as::<EthAbi>(&legacy).unwrap(), expected);
+        assert_eq!(expected.clone().encode_as::<EthAbi>(), legacy);
+
+        let pinned = expected.with_zk_verifying_key_hash(Some(ZK_VERIFYING_KEY_HASH));
+
+        assert_eq!(
+            ClientState::decode_as::<EthAbi>(&pinned.clone().encode_as::<EthAbi>()).unwrap(),
+            pinned
+        );
+    }
+}
@@ -4,8 +4,10 @@ pub mod consensus_state;
 pub mod header;
 pub mod light_header;
 pub mod misbehaviour;
+pub mod validation;
+pub mod zk_proof;
 
 pub use crate::{
     chain_id::ChainId, client_state::ClientState, consensus_state::ConsensusState, header::Header,
-    light_header::LightHeader, misbehaviour::Misbehaviour,
+    light_header::LightHeader, misbehaviour::Misbehaviour, zk_proof::ZkProof,
 };
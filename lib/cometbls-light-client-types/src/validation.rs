@@ -0,0 +1,128 @@
+//! Sanity checks for a [`ClientState`] before it is used to create a client.
+//!
+//! Unlike 07-tendermint, the proof specs and trust level of a cometbls client are fixed by the
+//! light client and are not part of the client state, so only the periods are checked here.
+
+use crate::ClientState;
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(tag = "violation", rename_all = "snake_case")
+)]
+pub enum ClientStateViolation {
+    #[error("trusting period is zero")]
+    ZeroTrustingPeriod,
+    #[error(
+        "trusting period {trusting_period}ns is not shorter than the unbonding period \
+        {unbonding_period}ns"
+    )]
+    TrustingPeriodTooLong {
+        trusting_period: u64,
+        unbonding_period: u64,
+    },
+    #[error("max clock drift is zero")]
+    ZeroMaxClockDrift,
+}
+
+impl ClientState {
+    /// Check this client state for values that are invalid or almost certainly a mistake.
+    ///
+    /// `chain_unbonding_period` is the unbonding period of the chain this client tracks in
+    /// nanoseconds, as reported by the chain itself. If it is `None`, only the checks that don't
+    /// require it are run.
+    #[must_use]
+    pub fn validate(&self, chain_unbonding_period: Option<u64>) -> Vec<ClientStateViolation> {
+        let mut violations = vec![];
+
+        if self.trusting_period == 0 {
+            violations.push(ClientStateViolation::ZeroTrustingPeriod);
+        }
+
+        if let Some(unbonding_period) = chain_unbonding_period {
+            if self.trusting_period >= unbonding_period {
+                violations.push(ClientStateViolation::TrustingPeriodTooLong {
+                    trusting_period: self.trusting_period,
+                    unbonding_period,
+                });
+            }
+        }
+
+        if self.max_clock_drift == 0 {
+            violations.push(ClientStateViolation::ZeroMaxClockDrift);
+        }
+
+        violations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use unionlabs::{hash::H256, ibc::core::client::height::Height};
+
+    use super::*;
+    use crate::ChainId;
+
+    const UNBONDING_PERIOD: u64 = 1_728_000 * 1_000_000_000;
+
+    fn client_state() -> ClientState {
+        ClientState {
+            chain_id: ChainId::from_string("union-testnet-9").unwrap(),
+            trusting_period: UNBONDING_PERIOD * 85 / 100,
+            max_clock_drift: 600 * 1_000_000_000,
+            frozen_height: Height::new(0),
+            latest_height: Height::new_with_revision(9, 100),
+            contract_address: H256::default(),
+            zk_verifying_key_hash: None,
+        }
+    }
+
+    #[test]
+    fn valid() {
+        assert_eq!(client_state().validate(None), []);
+        assert_eq!(client_state().validate(Some(UNBONDING_PERIOD)), []);
+    }
+
+    #[test]
+    fn zero_trusting_period() {
+        assert_eq!(
+            ClientState {
+                trusting_period: 0,
+                ..client_state()
+            }
+            .validate(Some(UNBONDING_PERIOD)),
+            [ClientStateViolation::ZeroTrustingPeriod]
+        );
+    }
+
+    #[test]
+    fn trusting_period_too_long() {
+        let client_state = ClientState {
+            trusting_period: UNBONDING_PERIOD,
+            ..client_state()
+        };
+
+        // only checked if the unbonding period of the chain is known
+        assert_eq!(client_state.validate(None), []);
+        assert_eq!(
+            client_state.validate(Some(UNBONDING_PERIOD)),
+            [ClientStateViolation::TrustingPeriodTooLong {
+                trusting_period: UNBONDING_PERIOD,
+                unbonding_period: UNBONDING_PERIOD,
+            }]
+        );
+    }
+
+    #[test]
+    fn zero_max_clock_drift() {
+        assert_eq!(
+            ClientState {
+                max_clock_drift: 0,
+                ..client_state()
+            }
+            .validate(None),
+            [ClientStateViolation::ZeroMaxClockDrift]
+        );
+    }
+}
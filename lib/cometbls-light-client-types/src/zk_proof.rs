@@ -0,0 +1,367 @@
+//! The zero knowledge proof of a cometbls [`Header`], as produced by galois.
+//!
+//! The proof is a groth16 proof over bn254 with a single pedersen commitment, and is submitted
+//! on chain as the raw concatenation of its curve points (the `evm_proof` of a galois prove
+//! response). The public inputs are not part of the proof; they are recomputed by the verifier
+//! from the header being proven and the validators hash of the trusted consensus state, see
+//! [`ZkProofInputs`].
+//!
+//! [`Header`]: crate::Header
+
+use sha2::Digest;
+use unionlabs::{
+    errors::{ExpectedLength, InvalidLength},
+    hash::H256,
+};
+
+use crate::{ChainId, Header, LightHeader};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ZkProof {
+    pub a: G1Point,
+    pub b: G2Point,
+    pub c: G1Point,
+    /// The pedersen commitment to the private inputs of the circuit. The hash of this commitment
+    /// is the second public input.
+    pub proof_commitment: G1Point,
+    /// The proof of knowledge of [`Self::proof_commitment`].
+    pub proof_commitment_pok: G1Point,
+}
+
+/// A point in `G1`, as big-endian coordinates.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct G1Point {
+    pub x: H256,
+    pub y: H256,
+}
+
+/// A point in `G2`, as big-endian coordinates. As in the EVM precompiles, the imaginary part of
+/// each coordinate comes first (`[c1, c0]`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct G2Point {
+    pub x: [H256; 2],
+    pub y: [H256; 2],
+}
+
+impl G1Point {
+    pub const SIZE: usize = 64;
+
+    fn decode(bz: &[u8; Self::SIZE]) -> Self {
+        Self {
+            x: word(bz, 0),
+            y: word(bz, 1),
+        }
+    }
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(self.x.get());
+        out.extend_from_slice(self.y.get());
+    }
+}
+
+impl G2Point {
+    pub const SIZE: usize = 128;
+
+    fn decode(bz: &[u8; Self::SIZE]) -> Self {
+        Self {
+            x: [word(bz, 0), word(bz, 1)],
+            y: [word(bz, 2), word(bz, 3)],
+        }
+    }
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        for coordinate in self.x.iter().chain(&self.y) {
+            out.extend_from_slice(coordinate.get());
+        }
+    }
+}
+
+/// The `idx`th 32 byte word of `bz`.
+fn word(bz: &[u8], idx: usize) -> H256 {
+    H256::try_from(&bz[idx * 32..(idx + 1) * 32]).expect("slice is 32 bytes; qed;")
+}
+
+impl ZkProof {
+    /// The size of an encoded proof: `a`, `b`, `c`, the commitment and the proof of knowledge of
+    /// the commitment.
+    pub const SIZE: usize = G1Point::SIZE * 4 + G2Point::SIZE;
+
+    /// Decode a proof from the byte layout that is submitted on chain.
+    ///
+    /// Only the length of `bz` is checked; whether the points are on the curve is checked by the
+    /// verifier.
+    pub fn decode(bz: &[u8]) -> Result<Self, InvalidLength> {
+        let bz = <&[u8; Self::SIZE]>::try_from(bz).map_err(|_| InvalidLength {
+            expected: ExpectedLength::Exact(Self::SIZE),
+            found: bz.len(),
+        })?;
+
+        let (a, bz) = bz.split_at(G1Point::SIZE);
+        let (b, bz) = bz.split_at(G2Point::SIZE);
+        let (c, bz) = bz.split_at(G1Point::SIZE);
+        let (proof_commitment, proof_commitment_pok) = bz.split_at(G1Point::SIZE);
+
+        let g1 = |bz: &[u8]| G1Point::decode(bz.try_into().expect("length is checked; qed;"));
+
+        Ok(Self {
+            a: g1(a),
+            b: G2Point::decode(b.try_into().expect("length is checked; qed;")),
+            c: g1(c),
+            proof_commitment: g1(proof_commitment),
+            proof_commitment_pok: g1(proof_commitment_pok),
+        })
+    }
+
+    /// Encode this proof into the byte layout that is submitted on chain.
+    #[must_use]
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(Self::SIZE);
+
+        self.a.encode(&mut out);
+        self.b.encode(&mut out);
+        self.c.encode(&mut out);
+        self.proof_commitment.encode(&mut out);
+        self.proof_commitment_pok.encode(&mut out);
+
+        out
+    }
+}
+
+/// The values that a [`ZkProof`] commits to, which are hashed into the first public input of the
+/// circuit (see [`Self::hash`]).
+///
+/// The trusted consensus state is identified by its validators hash; the trusted height itself is
+/// not committed to by the proof.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ZkProofInputs {
+    pub chain_id: ChainId,
+    pub untrusted_height: u64,
+    pub untrusted_time_seconds: u64,
+    pub untrusted_time_nanos: u64,
+    pub untrusted_validators_hash: H256,
+    pub untrusted_next_validators_hash: H256,
+    pub untrusted_app_hash: H256,
+    pub trusted_validators_hash: H256,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("the timestamp of the untrusted header is before the unix epoch ({seconds}s)")]
+pub struct NegativeTimestamp {
+    pub seconds: i64,
+}
+
+impl ZkProofInputs {
+    /// The size of the encoded inputs, see [`Self::encode`].
+    pub const SIZE: usize = 8 * 32;
+
+    pub fn new(
+        chain_id: ChainId,
+        trusted_validators_hash: H256,
+        untrusted_header: &LightHeader,
+    ) -> Result<Self, NegativeTimestamp> {
+        let seconds = untrusted_header.time.seconds.inner();
+
+        Ok(Self {
+            chain_id,
+            untrusted_height: untrusted_header
+                .height
+                .inner()
+                .try_into()
+                .expect("value is >= 0; qed;"),
+            untrusted_time_seconds: seconds
+                .try_into()
+                .map_err(|_| NegativeTimestamp { seconds })?,
+            untrusted_time_nanos: untrusted_header
+                .time
+                .nanos
+                .inner()
+                .try_into()
+                .expect("value is >= 0; qed;"),
+            untrusted_validators_hash: untrusted_header.validators_hash.into_encoding(),
+            untrusted_next_validators_hash: untrusted_header.next_validators_hash.into_encoding(),
+            untrusted_app_hash: untrusted_header.app_hash.into_encoding(),
+            trusted_validators_hash,
+        })
+    }
+
+    /// Encode the inputs as the preimage of [`Self::hash`]: each value as a big-endian 32 byte
+    /// word, with the chain id left-padded with zeroes.
+    #[must_use]
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(Self::SIZE);
+
+        out.resize(32 - self.chain_id.as_str().len(), 0);
+        out.extend_from_slice(self.chain_id.as_str().as_bytes());
+
+        for int in [
+            self.untrusted_height,
+            self.untrusted_time_seconds,
+            self.untrusted_time_nanos,
+        ] {
+            out.extend_from_slice(&[0; 24]);
+            out.extend_from_slice(&int.to_be_bytes());
+        }
+
+        for hash in [
+            &self.untrusted_validators_hash,
+            &self.untrusted_next_validators_hash,
+            &self.untrusted_app_hash,
+            &self.trusted_validators_hash,
+        ] {
+            out.extend_from_slice(hash.get());
+        }
+
+        out
+    }
+
+    /// The first public input of the circuit: the sha256 hash of [`Self::encode`], with the most
+    /// significant byte dropped such that it fits in the bn254 scalar field.
+    #[must_use]
+    pub fn hash(&self) -> H256 {
+        let mut hash = <[u8; 32]>::from(sha2::Sha256::digest(self.encode()));
+        hash[0] = 0;
+        hash.into()
+    }
+}
+
+impl Header {
+    /// Decode the [`ZkProof`] of this header.
+    pub fn zk_proof(&self) -> Result<ZkProof, InvalidLength> {
+        ZkProof::decode(&self.zero_knowledge_proof)
+    }
+
+    /// The inputs that the proof of this header must commit to for it to be valid, given the
+    /// chain id of the client and the validators hash of the consensus state at
+    /// [`Self::trusted_height`].
+    pub fn zk_proof_inputs(
+        &self,
+        chain_id: ChainId,
+        trusted_validators_hash: H256,
+    ) -> Result<ZkProofInputs, NegativeTimestamp> {
+        ZkProofInputs::new(chain_id, trusted_validators_hash, &self.signed_header)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hex_literal::hex;
+    use unionlabs::google::protobuf::timestamp::Timestamp;
+
+    use super::*;
+
+    // generated by galois for the header in `header()`, see the cometbls-groth16-verifier tests
+    const PROOF: [u8; ZkProof::SIZE] = hex!("03CF56142A1E03D2445A82100FEAF70C1CD95A731ED85792AFFF5792EC0BDD2108991BB56F9043A269F88903DE616A9AB99A3C5AB778E566744B060456C5616C06BCE7F1930421768C2CBD79F88D08EC3A52D7C9A867064E973064385E9C945E02951190DD7CE1662546733DD540188C96E608CA750FEF36B39E2577833634C70AE6F1A6D00DC6C21446AAF285EF35D944E8782B131300574F9A889C7E708A2325E9A78013BBE869D38B19C602DAF69644C77D177E99ED76398BCEE13C61FDBF2E178A5BA028A36033E54D1D9A0071E82E04079A5305347EBAC6D66F6EBFA48B1DA1BF9DC5A51EFA292E1DC7B85D26F18422EB386C48CA75434039764448BB96268DDC2CF683DDCA4BD83DF21C5631CF784375EEBE77EABC2DE77886BF1D48392C9C52E063B4A7131EAB9ABBA12A9F26888BC37366D41AC7D4BAC0BF6755ACB009BF9F36F380B6D0EEAABF066503A1B6E01DCC965D968D7694E01B1755E6BDD21C7A80B41682748F9B7151714BE34AA79AAD48BBB2A84525F6CDF812658C6E4F");
+
+    const TRUSTED_VALIDATORS_HASH: H256 = H256::new(hex!(
+        "20DDFE7A0F75C65D876316091ECCD494A54A2BB324C872015F73E528D53CB9C4"
+    ));
+
+    fn header() -> Header {
+        Header {
+            signed_header: LightHeader {
+                height: 3405691582.try_into().unwrap(),
+                time: Timestamp {
+                    seconds: 1732205251.try_into().unwrap(),
+                    nanos: 998131342.try_into().unwrap(),
+                },
+                validators_hash: hex!(
+                    "20DDFE7A0F75C65D876316091ECCD494A54A2BB324C872015F73E528D53CB9C4"
+                )
+                .into(),
+                next_validators_hash: hex!(
+                    "20DDFE7A0F75C65D876316091ECCD494A54A2BB324C872015F73E528D53CB9C4"
+                )
+                .into(),
+                app_hash: hex!("EE7E3E58F98AC95D63CE93B270981DF3EE54CA367F8D521ED1F444717595CD36")
+                    .into(),
+            },
+            trusted_height: unionlabs::ibc::core::client::height::Height::new(3405691581),
+            zero_knowledge_proof: PROOF.to_vec(),
+        }
+    }
+
+    #[test]
+    fn decode_encode_round_trip() {
+        let proof = header().zk_proof().unwrap();
+
+        assert_eq!(
+            proof.a,
+            G1Point {
+                x: hex!("03CF56142A1E03D2445A82100FEAF70C1CD95A731ED85792AFFF5792EC0BDD21").into(),
+                y: hex!("08991BB56F9043A269F88903DE616A9AB99A3C5AB778E566744B060456C5616C").into(),
+            }
+        );
+        assert_eq!(
+            proof.proof_commitment_pok,
+            G1Point {
+                x: hex!("09BF9F36F380B6D0EEAABF066503A1B6E01DCC965D968D7694E01B1755E6BDD2").into(),
+                y: hex!("1C7A80B41682748F9B7151714BE34AA79AAD48BBB2A84525F6CDF812658C6E4F").into(),
+            }
+        );
+
+        assert_eq!(proof.encode(), PROOF);
+    }
+
+    #[test]
+    fn decode_invalid_length() {
+        assert_eq!(
+            ZkProof::decode(&PROOF[1..]),
+            Err(InvalidLength {
+                expected: ExpectedLength::Exact(ZkProof::SIZE),
+                found: ZkProof::SIZE - 1,
+            })
+        );
+    }
+
+    #[test]
+    fn inputs() {
+        let inputs = header()
+            .zk_proof_inputs(
+                ChainId::from_string("union-devnet-1337").unwrap(),
+                TRUSTED_VALIDATORS_HASH,
+            )
+            .unwrap();
+
+        assert_eq!(inputs.untrusted_height, 3405691582);
+        assert_eq!(inputs.untrusted_time_seconds, 1732205251);
+        assert_eq!(inputs.untrusted_time_nanos, 998131342);
+        assert_eq!(inputs.trusted_validators_hash, TRUSTED_VALIDATORS_HASH);
+
+        let encoded = inputs.encode();
+
+        assert_eq!(encoded.len(), ZkProofInputs::SIZE);
+        assert_eq!(
+            &encoded[..32],
+            hex!("000000000000000000000000000000756e696f6e2d6465766e65742d31333337")
+        );
+        assert_eq!(
+            &encoded[32..64],
+            hex!("00000000000000000000000000000000000000000000000000000000CAFEBABE")
+        );
+
+        assert_eq!(
+            inputs.hash(),
+            H256::new(hex!(
+                "00EA08DB26D990EDE56F3C02CF0EA38BF91810085E88029F68CF0562A938A326"
+            ))
+        );
+    }
+
+    #[test]
+    fn inputs_negative_timestamp() {
+        let mut header = header();
+        header.signed_header.time.seconds = (-1).try_into().unwrap();
+
+        assert_eq!(
+            header.zk_proof_inputs(
+                ChainId::from_string("union-devnet-1337").unwrap(),
+                TRUSTED_VALIDATORS_HASH,
+            ),
+            Err(NegativeTimestamp { seconds: -1 })
+        );
+    }
+}
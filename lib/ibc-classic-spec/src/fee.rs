@@ -0,0 +1,118 @@
+//! Channel versions of the ICS-29 fee middleware.
+//!
+//! Channels that support relayer incentivization are opened with the version of the app wrapped
+//! in a JSON object along with the version of the fee middleware:
+//!
+//! ```json
+//! {"fee_version":"ics29-1","app_version":"ics20-1"}
+//! ```
+//!
+//! Channels opened with the bare app version work as usual, but without fee support.
+
+use serde::{Deserialize, Serialize};
+
+/// The version of the ICS-29 fee middleware.
+pub const FEE_VERSION: &str = "ics29-1";
+
+/// The version of a channel with the fee middleware enabled.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FeeVersion {
+    pub fee_version: String,
+    pub app_version: String,
+}
+
+impl FeeVersion {
+    /// Wrap `app_version` with the current [`FEE_VERSION`].
+    #[must_use]
+    pub fn new(app_version: impl Into<String>) -> Self {
+        Self {
+            fee_version: FEE_VERSION.to_owned(),
+            app_version: app_version.into(),
+        }
+    }
+
+    /// Parse a channel version, returning `None` if it is not wrapped by the fee middleware.
+    #[must_use]
+    pub fn parse(version: &str) -> Option<Self> {
+        serde_json::from_str(version).ok()
+    }
+
+    /// The channel version, in the same format as ibc-go.
+    #[must_use]
+    pub fn encode(&self) -> String {
+        serde_json::to_string(self).expect("serialization is infallible; qed;")
+    }
+}
+
+/// Wrap the channel `version` with the fee middleware version. Versions that are already wrapped
+/// are returned as is.
+#[must_use]
+pub fn wrap_fee_version(version: String) -> String {
+    if FeeVersion::parse(&version).is_some() {
+        version
+    } else {
+        FeeVersion::new(version).encode()
+    }
+}
+
+/// The version of the app of the channel `version`, with the fee middleware wrapper removed if
+/// present.
+#[must_use]
+pub fn app_version(version: &str) -> String {
+    FeeVersion::parse(version).map_or_else(|| version.to_owned(), |fee| fee.app_version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WRAPPED: &str = r#"{"fee_version":"ics29-1","app_version":"ics20-1"}"#;
+
+    #[test]
+    fn round_trip() {
+        let fee_version = FeeVersion::new("ics20-1");
+
+        assert_eq!(fee_version.encode(), WRAPPED);
+        assert_eq!(FeeVersion::parse(WRAPPED), Some(fee_version));
+    }
+
+    #[test]
+    fn parse_unwrapped() {
+        assert_eq!(FeeVersion::parse("ics20-1"), None);
+        assert_eq!(FeeVersion::parse(""), None);
+        assert_eq!(FeeVersion::parse(r#"{"version":"ics20-1"}"#), None);
+        // ics27 versions are also json, but not fee versions
+        assert_eq!(
+            FeeVersion::parse(
+                r#"{"version":"ics27-1","controller_connection_id":"connection-0","host_connection_id":"connection-0","address":"","encoding":"proto3","tx_type":"sdk_multi_msg"}"#
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn wrap() {
+        assert_eq!(wrap_fee_version("ics20-1".to_owned()), WRAPPED);
+        // already wrapped
+        assert_eq!(wrap_fee_version(WRAPPED.to_owned()), WRAPPED);
+    }
+
+    #[test]
+    fn unwrap() {
+        assert_eq!(app_version(WRAPPED), "ics20-1");
+        assert_eq!(app_version("ics20-1"), "ics20-1");
+
+        let wrapped_json_app_version = FeeVersion::new(r#"{"version":"ics27-1"}"#).encode();
+        assert_eq!(
+            FeeVersion::parse(&wrapped_json_app_version)
+                .unwrap()
+                .app_version,
+            r#"{"version":"ics27-1"}"#
+        );
+        assert_eq!(
+            app_version(&wrapped_json_app_version),
+            r#"{"version":"ics27-1"}"#
+        );
+    }
+}
@@ -3,6 +3,7 @@ use std::{fmt, num::NonZeroU64, str::FromStr};
 use enumorph::Enumorph;
 use macros::{ibc_path, model};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use subset_of::SubsetOf;
 use tracing::info;
 use unionlabs::{
@@ -18,6 +19,7 @@ use unionlabs::{
         },
         client::{
             height::Height, msg_create_client::MsgCreateClient, msg_update_client::MsgUpdateClient,
+            msg_upgrade_client::MsgUpgradeClient,
         },
         connection::{
             connection_end::ConnectionEnd, msg_connection_open_ack::MsgConnectionOpenAck,
@@ -29,7 +31,9 @@ use unionlabs::{
     id::{ChannelId, ClientId, ConnectionId, PortId},
     ErrorReporter,
 };
-use voyager_core::{ClientType, IbcSpec, IbcSpecId, IbcStorePathKey};
+use voyager_core::{ClientType, IbcSpec, IbcSpecId, IbcStorePathKey, TimeoutSpec};
+
+pub mod fee;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum IbcClassic {}
@@ -77,6 +81,8 @@ pub enum StorePath {
     NextSequenceAck(NextSequenceAckPath),
     NextConnectionSequence(NextConnectionSequencePath),
     NextClientSequence(NextClientSequencePath),
+    UpgradedClientState(UpgradedClientStatePath),
+    UpgradedConsensusState(UpgradedConsensusStatePath),
 }
 
 impl fmt::Display for StorePath {
@@ -94,6 +100,8 @@ impl fmt::Display for StorePath {
             Self::NextSequenceAck(path) => write!(f, "{path}"),
             Self::NextConnectionSequence(path) => write!(f, "{path}"),
             Self::NextClientSequence(path) => write!(f, "{path}"),
+            Self::UpgradedClientState(path) => write!(f, "{path}"),
+            Self::UpgradedConsensusState(path) => write!(f, "{path}"),
         }
     }
 }
@@ -114,6 +122,22 @@ impl FromStr for StorePath {
             .or_else(|_| s.parse().map(Self::NextSequenceRecv))
             .or_else(|_| s.parse().map(Self::NextSequenceAck))
             .or_else(|_| s.parse().map(Self::NextConnectionSequence))
+            .or_else(|_| s.parse().map(Self::UpgradedClientState))
+            .or_else(|_| s.parse().map(Self::UpgradedConsensusState))
+    }
+}
+
+impl StorePath {
+    /// The key of the store that this path is committed in on cosmos-sdk chains.
+    ///
+    /// The upgraded client and consensus states are committed by `x/upgrade` in the `upgrade`
+    /// store, all other paths are committed in the `ibc` store.
+    #[must_use]
+    pub fn store_key(&self) -> &'static str {
+        match self {
+            Self::UpgradedClientState(_) | Self::UpgradedConsensusState(_) => "upgrade",
+            _ => "ibc",
+        }
     }
 }
 
@@ -236,6 +260,13 @@ pub struct AcknowledgementPath {
     pub sequence: NonZeroU64,
 }
 
+/// The commitment of a packet acknowledgement, as stored under the [`AcknowledgementPath`] of the
+/// packet.
+#[must_use]
+pub fn commit_acknowledgement(ack: &[u8]) -> H256 {
+    H256::new(Sha256::digest(ack).into())
+}
+
 /// This defaults to `false` for packets which have not yet been received.
 #[ibc_path(
     "receipts/ports/{port_id}/channels/{channel_id:#}/sequences/{sequence}",
@@ -275,6 +306,22 @@ pub struct NextConnectionSequencePath {}
 #[ibc_path("nextClientSequence", u64)]
 pub struct NextClientSequencePath {}
 
+/// The proto encoded `Any` of the client state that counterparty clients are upgraded to by the
+/// upgrade scheduled at `upgrade_height`. This is committed in the `upgrade` store, not the `ibc`
+/// store.
+#[ibc_path("upgradedIBCState/{upgrade_height}/upgradedClient", Bytes)]
+pub struct UpgradedClientStatePath {
+    pub upgrade_height: u64,
+}
+
+/// The proto encoded `Any` of the consensus state that counterparty clients are upgraded to by the
+/// upgrade scheduled at `upgrade_height`. This is committed in the `upgrade` store, not the `ibc`
+/// store.
+#[ibc_path("upgradedIBCState/{upgrade_height}/upgradedConsState", Bytes)]
+pub struct UpgradedConsensusStatePath {
+    pub upgrade_height: u64,
+}
+
 #[derive(Debug, Clone, PartialEq, thiserror::Error)]
 pub enum PathParseError {
     #[error("invalid static segment, expected `{expected}` but found `{found}`")]
@@ -299,6 +346,7 @@ pub enum PathParseError {
 pub enum Datagram {
     CreateClient(MsgCreateClientData),
     UpdateClient(MsgUpdateClient),
+    UpgradeClient(MsgUpgradeClientData),
 
     ConnectionOpenInit(MsgConnectionOpenInit),
     ConnectionOpenTry(MsgConnectionOpenTry),
@@ -322,6 +370,7 @@ impl Datagram {
         match self {
             Datagram::CreateClient(_) => None,
             Datagram::UpdateClient(_) => None,
+            Datagram::UpgradeClient(msg) => Some(msg.upgrade_height),
             Datagram::ConnectionOpenInit(_) => None,
             Datagram::ConnectionOpenTry(msg) => Some(msg.proof_height),
             Datagram::ConnectionOpenAck(msg) => Some(msg.proof_height),
@@ -340,6 +389,7 @@ impl Datagram {
         match self {
             Datagram::CreateClient(_) => "create_client",
             Datagram::UpdateClient(_) => "update_client",
+            Datagram::UpgradeClient(_) => "upgrade_client",
             Datagram::ConnectionOpenInit(_) => "connection_open_init",
             Datagram::ConnectionOpenTry(_) => "connection_open_try",
             Datagram::ConnectionOpenAck(_) => "connection_open_ack",
@@ -494,13 +544,102 @@ pub struct PacketMetadata {
     pub timeout_timestamp: u64,
 }
 
+impl PacketMetadata {
+    /// The timeout of this packet.
+    pub fn timeout(&self) -> TimeoutSpec {
+        TimeoutSpec::from_v1(self.timeout_height, self.timeout_timestamp)
+    }
+}
+
 #[model]
 pub struct ChannelMetadata {
     pub port_id: PortId,
     pub channel_id: ChannelId,
     // REVIEW: Can this be different on either end of a channel?
     pub version: String,
-    pub connection: ConnectionMetadata,
+    /// The connections this channel is built on, starting at the chain the
+    /// channel is on. Only multi-hop (ICS-033) channels have more than one.
+    ///
+    /// This was previously a single `connection`, which is still accepted when
+    /// deserializing.
+    #[serde(
+        alias = "connection",
+        deserialize_with = "connection_hops::deserialize"
+    )]
+    pub connection_hops: Vec<ConnectionMetadata>,
+}
+
+impl ChannelMetadata {
+    /// The first connection hop of this channel, i.e. the connection on the
+    /// chain the channel is on. For single hop channels, this is the only
+    /// connection.
+    #[must_use]
+    pub fn primary_connection(&self) -> &ConnectionMetadata {
+        self.connection_hops
+            .first()
+            .expect("channel metadata has at least one connection hop; qed;")
+    }
+
+    /// The version of the app of this channel. For channels with the ICS-29
+    /// fee middleware enabled, this is the app version wrapped in the
+    /// [`version`](Self::version).
+    #[must_use]
+    pub fn app_version(&self) -> String {
+        fee::app_version(&self.version)
+    }
+
+    /// The fee middleware version of this channel, if it has fees enabled.
+    #[must_use]
+    pub fn fee_version(&self) -> Option<fee::FeeVersion> {
+        fee::FeeVersion::parse(&self.version)
+    }
+
+    /// The connection of this channel, if it is a single hop channel.
+    pub fn single_hop_connection(&self) -> Result<&ConnectionMetadata, MultiHopUnsupported> {
+        match &*self.connection_hops {
+            [connection] => Ok(connection),
+            _ => Err(MultiHopUnsupported {
+                port_id: self.port_id.clone(),
+                channel_id: self.channel_id.clone(),
+                hops: self.connection_hops.len(),
+            }),
+        }
+    }
+}
+
+/// A multi-hop channel was used where only single hop channels are supported
+/// (i.e. when building proofs, which are only constructible for a single hop).
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("channel {channel_id:#} on port {port_id} has {hops} connection hops, but multi-hop channels are not supported")]
+pub struct MultiHopUnsupported {
+    pub port_id: PortId,
+    pub channel_id: ChannelId,
+    pub hops: usize,
+}
+
+mod connection_hops {
+    use serde::{de, Deserialize, Deserializer};
+
+    use crate::ConnectionMetadata;
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(ConnectionMetadata),
+        Many(Vec<ConnectionMetadata>),
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<ConnectionMetadata>, D::Error> {
+        match OneOrMany::deserialize(deserializer)? {
+            OneOrMany::One(connection) => Ok(vec![connection]),
+            OneOrMany::Many(connection_hops) if connection_hops.is_empty() => {
+                Err(de::Error::invalid_length(0, &"at least one connection hop"))
+            }
+            OneOrMany::Many(connection_hops) => Ok(connection_hops),
+        }
+    }
 }
 
 #[model]
@@ -549,13 +688,21 @@ impl FullEvent {
             Self::ChannelOpenTry(ref event) => &event.connection.client_id,
             Self::ChannelOpenAck(ref event) => &event.connection.client_id,
             Self::ChannelOpenConfirm(ref event) => &event.connection.client_id,
-            Self::SendPacket(ref event) => &event.packet.source_channel.connection.client_id,
-            Self::RecvPacket(ref event) => &event.packet.source_channel.connection.client_id,
+            Self::SendPacket(ref event) => {
+                &event.packet.source_channel.primary_connection().client_id
+            }
+            Self::RecvPacket(ref event) => {
+                &event.packet.source_channel.primary_connection().client_id
+            }
             Self::WriteAcknowledgement(ref event) => {
-                &event.packet.source_channel.connection.client_id
+                &event.packet.source_channel.primary_connection().client_id
+            }
+            Self::AcknowledgePacket(ref event) => {
+                &event.packet.source_channel.primary_connection().client_id
+            }
+            Self::TimeoutPacket(ref event) => {
+                &event.packet.source_channel.primary_connection().client_id
             }
-            Self::AcknowledgePacket(ref event) => &event.packet.source_channel.connection.client_id,
-            Self::TimeoutPacket(ref event) => &event.packet.source_channel.connection.client_id,
         }
     }
 
@@ -572,19 +719,33 @@ impl FullEvent {
             Self::ChannelOpenTry(ref event) => Some(&event.connection.counterparty.client_id),
             Self::ChannelOpenAck(ref event) => Some(&event.connection.counterparty.client_id),
             Self::ChannelOpenConfirm(ref event) => Some(&event.connection.counterparty.client_id),
-            Self::SendPacket(ref event) => {
-                Some(&event.packet.destination_channel.connection.client_id)
+            Self::SendPacket(ref event) => Some(
+                &event
+                    .packet
+                    .destination_channel
+                    .primary_connection()
+                    .client_id,
+            ),
+            Self::RecvPacket(ref event) => {
+                Some(&event.packet.source_channel.primary_connection().client_id)
             }
-            Self::RecvPacket(ref event) => Some(&event.packet.source_channel.connection.client_id),
             Self::WriteAcknowledgement(ref event) => {
-                Some(&event.packet.source_channel.connection.client_id)
-            }
-            Self::AcknowledgePacket(ref event) => {
-                Some(&event.packet.destination_channel.connection.client_id)
-            }
-            Self::TimeoutPacket(ref event) => {
-                Some(&event.packet.destination_channel.connection.client_id)
+                Some(&event.packet.source_channel.primary_connection().client_id)
             }
+            Self::AcknowledgePacket(ref event) => Some(
+                &event
+                    .packet
+                    .destination_channel
+                    .primary_connection()
+                    .client_id,
+            ),
+            Self::TimeoutPacket(ref event) => Some(
+                &event
+                    .packet
+                    .destination_channel
+                    .primary_connection()
+                    .client_id,
+            ),
             _ => None,
         }
     }
@@ -596,6 +757,14 @@ pub struct MsgCreateClientData {
     pub client_type: ClientType,
 }
 
+#[model]
+pub struct MsgUpgradeClientData {
+    pub msg: MsgUpgradeClient,
+    /// The height of the upgrade plan on the counterparty. The proofs of the upgraded states are
+    /// at this height, so the client must be updated to exactly this height first.
+    pub upgrade_height: Height,
+}
+
 pub fn log_msg(chain_id: &str, effect: &Datagram) {
     match effect.clone() {
         Datagram::ConnectionOpenInit(message) => {
@@ -772,6 +941,13 @@ pub fn log_msg(chain_id: &str, effect: &Datagram) {
                 %message.client_id,
             )
         }
+        Datagram::UpgradeClient(message) => {
+            info!(
+                %chain_id,
+                %message.msg.client_id,
+                %message.upgrade_height,
+            )
+        }
     }
 }
 
@@ -781,6 +957,16 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn ack_commitment() {
+        assert_eq!(
+            commit_acknowledgement(br#"{"result":"AQ=="}"#),
+            "0x08f7557ed51826fe18d84512bf24ec75001edbaf2123a477df72a0a9f3640a7c"
+                .parse::<H256>()
+                .unwrap()
+        );
+    }
+
     #[test]
     fn parse_ibc_paths_from_str() {
         assert_eq!(
@@ -835,5 +1021,138 @@ mod tests {
                 sequence: 1.try_into().unwrap()
             })
         );
+        assert_eq!(
+            "upgradedIBCState/100/upgradedClient"
+                .parse::<StorePath>()
+                .unwrap(),
+            StorePath::UpgradedClientState(UpgradedClientStatePath {
+                upgrade_height: 100
+            })
+        );
+        assert_eq!(
+            "upgradedIBCState/100/upgradedConsState"
+                .parse::<StorePath>()
+                .unwrap(),
+            StorePath::UpgradedConsensusState(UpgradedConsensusStatePath {
+                upgrade_height: 100
+            })
+        );
+    }
+
+    #[test]
+    fn upgrade_paths_store_key() {
+        let path = StorePath::from(UpgradedClientStatePath {
+            upgrade_height: 100,
+        });
+
+        assert_eq!(path.to_string(), "upgradedIBCState/100/upgradedClient");
+        assert_eq!(path.store_key(), "upgrade");
+        assert_eq!(
+            StorePath::from(UpgradedConsensusStatePath {
+                upgrade_height: 100,
+            })
+            .store_key(),
+            "upgrade"
+        );
+        assert_eq!(
+            StorePath::from(ClientStatePath {
+                client_id: ClientId::new("07-tendermint", 0)
+            })
+            .store_key(),
+            "ibc"
+        );
+    }
+
+    fn connection(client_id: u32, connection_id: u32) -> ConnectionMetadata {
+        ConnectionMetadata {
+            client_id: ClientId::new("07-tendermint", client_id),
+            connection_id: ConnectionId::new(connection_id),
+        }
+    }
+
+    #[test]
+    fn channel_metadata_single_connection_compat() {
+        let channel = serde_json::from_value::<ChannelMetadata>(serde_json::json!({
+            "port_id": "transfer",
+            "channel_id": 1,
+            "version": "ics20-1",
+            "connection": {
+                "client_id": "07-tendermint-2",
+                "connection_id": 3
+            }
+        }))
+        .unwrap();
+
+        assert_eq!(channel.connection_hops, [connection(2, 3)]);
+        assert_eq!(channel.primary_connection(), &connection(2, 3));
+        assert_eq!(channel.single_hop_connection(), Ok(&connection(2, 3)));
+
+        // the old format is read, but only the new format is written
+        let json = serde_json::to_value(&channel).unwrap();
+        assert!(json.get("connection").is_none());
+        assert_eq!(
+            serde_json::from_value::<ChannelMetadata>(json).unwrap(),
+            channel
+        );
+    }
+
+    #[test]
+    fn channel_metadata_multi_hop() {
+        let channel = ChannelMetadata {
+            port_id: PortId::new("transfer").unwrap(),
+            channel_id: ChannelId::new(1),
+            version: "ics20-1".to_owned(),
+            connection_hops: vec![connection(2, 3), connection(4, 5)],
+        };
+
+        let json = serde_json::to_value(&channel).unwrap();
+
+        assert_eq!(
+            json["connection_hops"],
+            serde_json::to_value([connection(2, 3), connection(4, 5)]).unwrap()
+        );
+        assert_eq!(
+            serde_json::from_value::<ChannelMetadata>(json).unwrap(),
+            channel
+        );
+
+        assert_eq!(channel.primary_connection(), &connection(2, 3));
+        assert_eq!(
+            channel.single_hop_connection(),
+            Err(MultiHopUnsupported {
+                port_id: PortId::new("transfer").unwrap(),
+                channel_id: ChannelId::new(1),
+                hops: 2,
+            })
+        );
+    }
+
+    #[test]
+    fn channel_metadata_app_version() {
+        let mut channel = ChannelMetadata {
+            port_id: PortId::new("transfer").unwrap(),
+            channel_id: ChannelId::new(1),
+            version: "ics20-1".to_owned(),
+            connection_hops: vec![connection(2, 3)],
+        };
+
+        assert_eq!(channel.app_version(), "ics20-1");
+        assert_eq!(channel.fee_version(), None);
+
+        channel.version = r#"{"fee_version":"ics29-1","app_version":"ics20-1"}"#.to_owned();
+
+        assert_eq!(channel.app_version(), "ics20-1");
+        assert_eq!(channel.fee_version(), Some(fee::FeeVersion::new("ics20-1")));
+    }
+
+    #[test]
+    fn channel_metadata_without_connection_hops_is_rejected() {
+        serde_json::from_value::<ChannelMetadata>(serde_json::json!({
+            "port_id": "transfer",
+            "channel_id": 1,
+            "version": "ics20-1",
+            "connection_hops": []
+        }))
+        .unwrap_err();
     }
 }
@@ -0,0 +1,246 @@
+//! Batches of packets.
+//!
+//! Packets that have already been sent on a channel can be committed again as a batch
+//! (`batchSend`), such that they can be received on the counterparty with a single proof. The
+//! batch is committed under the [`BatchPacketsPath`] of its batch hash, which is the hash of the
+//! abi encoded packet array, in the order the packets were batched in. Relaying a packet of a batch
+//! therefore requires all of the packets of the batch, in their original order.
+
+use ibc_solidity::Packet;
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Keccak256};
+use unionlabs::{hash::H256, uint::U256};
+
+use crate::{commit_packet, commit_packets, BatchPacketsPath, PACKETS};
+
+/// The hash of a single packet, as computed by `IBCPacketLib.commitPacket`. This is the batch hash
+/// of the packet when it is sent.
+#[must_use]
+pub fn compute_packet_hash(packet: &Packet) -> H256 {
+    commit_packet(packet)
+}
+
+/// The hash that `packets` are committed under when they are received together, as computed by the
+/// contracts when verifying the proof of a `MsgPacketRecv`.
+///
+/// A batch containing a single packet is committed under the hash of the packet itself (see
+/// [`compute_packet_hash`]), and any other batch under the hash of the abi encoded packet array.
+/// The order of `packets` is significant.
+#[must_use]
+pub fn compute_batch_hash(packets: &[Packet]) -> H256 {
+    match packets {
+        [packet] => commit_packet(packet),
+        packets => commit_packets(packets),
+    }
+}
+
+/// The commitment path of a batch of packets, as defined by
+/// `IBCCommitment.batchPacketsCommitmentPath`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct BatchCommitmentPath {
+    pub channel_id: u32,
+    pub batch_hash: H256,
+}
+
+impl BatchCommitmentPath {
+    /// The commitment path of `packets`, batched on `channel_id`.
+    #[must_use]
+    pub fn from_packets(channel_id: u32, packets: &[Packet]) -> Self {
+        Self {
+            channel_id,
+            batch_hash: compute_batch_hash(packets),
+        }
+    }
+
+    /// The abi encoded path, `abi.encode(PACKETS, channelId, batchHash)`.
+    #[must_use]
+    pub fn path(&self) -> Vec<u8> {
+        [
+            PACKETS.to_be_bytes().as_slice(),
+            U256::from(self.channel_id).to_be_bytes().as_slice(),
+            self.batch_hash.get().as_slice(),
+        ]
+        .concat()
+    }
+
+    /// The key of the commitment in the store of the contract, the hash of [`Self::path`].
+    #[must_use]
+    pub fn key(&self) -> H256 {
+        Keccak256::new().chain_update(self.path()).finalize().into()
+    }
+}
+
+impl From<BatchCommitmentPath> for BatchPacketsPath {
+    fn from(value: BatchCommitmentPath) -> Self {
+        Self {
+            channel_id: value.channel_id,
+            batch_hash: value.batch_hash,
+        }
+    }
+}
+
+/// The position of a packet in a batch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BatchMember {
+    pub batch_hash: H256,
+    /// The index of the packet in the batch.
+    pub index: u32,
+}
+
+/// A batch of packets, as committed by `batchSend`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PacketBatch {
+    pub channel_id: u32,
+    pub batch_hash: H256,
+    /// The hashes of the packets of the batch, in order.
+    pub packet_hashes: Vec<H256>,
+}
+
+impl PacketBatch {
+    #[must_use]
+    pub fn new(channel_id: u32, packets: &[Packet]) -> Self {
+        Self {
+            channel_id,
+            batch_hash: compute_batch_hash(packets),
+            packet_hashes: packets.iter().map(compute_packet_hash).collect(),
+        }
+    }
+
+    #[must_use]
+    pub fn commitment_path(&self) -> BatchCommitmentPath {
+        BatchCommitmentPath {
+            channel_id: self.channel_id,
+            batch_hash: self.batch_hash,
+        }
+    }
+
+    /// The index of the first packet in the batch with the hash `packet_hash`, if any.
+    #[must_use]
+    pub fn index_of(&self, packet_hash: H256) -> Option<u32> {
+        self.packet_hashes
+            .iter()
+            .position(|hash| *hash == packet_hash)
+            .map(|index| index as u32)
+    }
+
+    /// The position of the packet with the hash `packet_hash` in the batch, if any.
+    #[must_use]
+    pub fn find(&self, packet_hash: H256) -> Option<BatchMember> {
+        self.index_of(packet_hash).map(|index| BatchMember {
+            batch_hash: self.batch_hash,
+            index,
+        })
+    }
+
+    /// The positions of all of the packets in the batch, in order.
+    pub fn members(&self) -> impl Iterator<Item = BatchMember> + '_ {
+        (0..self.packet_hashes.len()).map(|index| BatchMember {
+            batch_hash: self.batch_hash,
+            index: index as u32,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+    use unionlabs::bytes::Bytes;
+
+    use super::*;
+
+    #[derive(Deserialize)]
+    struct BatchCommitmentVector {
+        packets: Vec<Packet>,
+        packet_hashes: Vec<H256>,
+        batch_hash: H256,
+        commitment_path: Bytes,
+        commitment_key: H256,
+    }
+
+    /// Vectors generated by hashing the abi encoded packets as defined in `IBCPacket.sol`, and the
+    /// resulting batch paths as defined in `IBCCommitment.sol`. The commitment path is on the
+    /// source channel of the first packet.
+    fn vectors() -> Vec<BatchCommitmentVector> {
+        serde_json::from_str(include_str!("./test/batch_commitments.json")).unwrap()
+    }
+
+    #[test]
+    fn batch_commitments() {
+        for vector in vectors() {
+            assert_eq!(
+                vector
+                    .packets
+                    .iter()
+                    .map(compute_packet_hash)
+                    .collect::<Vec<_>>(),
+                vector.packet_hashes
+            );
+            assert_eq!(compute_batch_hash(&vector.packets), vector.batch_hash);
+
+            let path = BatchCommitmentPath::from_packets(
+                vector.packets[0].source_channel,
+                &vector.packets,
+            );
+            assert_eq!(path.path(), vector.commitment_path.as_ref());
+            assert_eq!(path.key(), vector.commitment_key);
+            assert_eq!(BatchPacketsPath::from(path).key(), vector.commitment_key);
+        }
+    }
+
+    #[test]
+    fn batch_order_is_significant() {
+        let vectors = vectors();
+
+        let [a, b] = vectors[1].packets.as_slice() else {
+            panic!("expected a batch of two packets");
+        };
+
+        assert_eq!(vectors[2].packets, [b.clone(), a.clone()]);
+        assert_ne!(vectors[1].batch_hash, vectors[2].batch_hash);
+    }
+
+    #[test]
+    fn single_packet_batch() {
+        let vector = &vectors()[0];
+
+        assert_eq!(vector.batch_hash, vector.packet_hashes[0]);
+        assert_ne!(
+            commit_packets(&vector.packets),
+            compute_batch_hash(&vector.packets)
+        );
+    }
+
+    #[test]
+    fn packet_batch_lookup() {
+        let vector = &vectors()[3];
+
+        let batch = PacketBatch::new(vector.packets[0].source_channel, &vector.packets);
+
+        assert_eq!(batch.batch_hash, vector.batch_hash);
+        assert_eq!(batch.commitment_path().key(), vector.commitment_key);
+
+        assert_eq!(
+            batch.members().collect::<Vec<_>>(),
+            (0..3)
+                .map(|index| BatchMember {
+                    batch_hash: vector.batch_hash,
+                    index,
+                })
+                .collect::<Vec<_>>()
+        );
+
+        assert_eq!(
+            batch.find(vector.packet_hashes[2]),
+            Some(BatchMember {
+                batch_hash: vector.batch_hash,
+                index: 2,
+            })
+        );
+        assert_eq!(batch.find(H256::default()), None);
+
+        // duplicate packets resolve to the first occurrence
+        let vector = &vectors()[4];
+        let batch = PacketBatch::new(vector.packets[0].source_channel, &vector.packets);
+        assert_eq!(batch.index_of(vector.packet_hashes[1]), Some(0));
+    }
+}
@@ -0,0 +1,394 @@
+//! Explicit conversions between the ibc-union [`Connection`] and [`Channel`] types and their
+//! ibc-classic (v1) equivalents.
+//!
+//! ibc-union identifies clients, connections, and channels by their numeric ids only, and does
+//! not store connection versions, delay periods, channel ordering, or upgrade sequences. As such,
+//! converting from ibc-union to v1 requires the client id prefixes (the client types) to be
+//! provided, and converting from v1 to ibc-union will error if the v1 end contains any
+//! information that would be lost in the conversion. Converting an ibc-union end to v1 and back
+//! is lossless.
+
+use ibc_solidity::{Channel, ChannelState, Connection, ConnectionState};
+use unionlabs::{
+    ibc::core::{
+        channel::{self, order::Order},
+        commitment::merkle_prefix::MerklePrefix,
+        connection::{self, connection_end::ConnectionEnd},
+    },
+    id::{ChannelId, ClientId, ConnectionId, Ics24IdParseError, PortId},
+};
+
+/// The information required to reconstruct the v1 client ids of an ibc-union [`Connection`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConnectionIdPrefixes<'a> {
+    /// The prefix of the client on this chain, i.e. the client type.
+    pub client_id_prefix: &'a str,
+    /// The prefix of the client on the counterparty chain, i.e. the counterparty client type.
+    pub counterparty_client_id_prefix: &'a str,
+}
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum ConnectionToV1Error {
+    #[error("invalid connection state")]
+    InvalidState,
+}
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum ConnectionFromV1Error {
+    #[error("connection versions are not supported by ibc-union, found {0} versions")]
+    Versions(usize),
+    #[error("connection delay periods are not supported by ibc-union, found {0}")]
+    DelayPeriod(u64),
+}
+
+/// Convert an ibc-union [`Connection`] into a v1 [`ConnectionEnd`].
+///
+/// The counterparty connection id is `None` if it is `0`, and the connection end will have no
+/// versions, a delay period of `0`, and an empty counterparty merkle prefix.
+pub fn connection_to_v1(
+    connection: &Connection,
+    prefixes: ConnectionIdPrefixes<'_>,
+) -> Result<ConnectionEnd, ConnectionToV1Error> {
+    Ok(ConnectionEnd {
+        client_id: ClientId::new(prefixes.client_id_prefix.to_owned(), connection.client_id),
+        versions: vec![],
+        state: match connection.state {
+            ConnectionState::Unspecified => connection::state::State::UninitializedUnspecified,
+            ConnectionState::Init => connection::state::State::Init,
+            ConnectionState::TryOpen => connection::state::State::Tryopen,
+            ConnectionState::Open => connection::state::State::Open,
+            ConnectionState::__Invalid => return Err(ConnectionToV1Error::InvalidState),
+        },
+        counterparty: connection::counterparty::Counterparty {
+            client_id: ClientId::new(
+                prefixes.counterparty_client_id_prefix.to_owned(),
+                connection.counterparty_client_id,
+            ),
+            connection_id: (connection.counterparty_connection_id != 0)
+                .then_some(ConnectionId::new(connection.counterparty_connection_id)),
+            prefix: MerklePrefix {
+                key_prefix: Default::default(),
+            },
+        },
+        delay_period: 0,
+    })
+}
+
+/// Convert a v1 [`ConnectionEnd`] into an ibc-union [`Connection`].
+///
+/// The client id prefixes and the counterparty merkle prefix are discarded.
+pub fn connection_from_v1(
+    connection_end: &ConnectionEnd,
+) -> Result<Connection, ConnectionFromV1Error> {
+    if !connection_end.versions.is_empty() {
+        return Err(ConnectionFromV1Error::Versions(
+            connection_end.versions.len(),
+        ));
+    }
+
+    if connection_end.delay_period != 0 {
+        return Err(ConnectionFromV1Error::DelayPeriod(
+            connection_end.delay_period,
+        ));
+    }
+
+    Ok(Connection {
+        state: match connection_end.state {
+            connection::state::State::UninitializedUnspecified => ConnectionState::Unspecified,
+            connection::state::State::Init => ConnectionState::Init,
+            connection::state::State::Tryopen => ConnectionState::TryOpen,
+            connection::state::State::Open => ConnectionState::Open,
+        },
+        client_id: connection_end.client_id.id(),
+        counterparty_client_id: connection_end.counterparty.client_id.id(),
+        counterparty_connection_id: connection_end
+            .counterparty
+            .connection_id
+            .as_ref()
+            .map_or(0, ConnectionId::id),
+    })
+}
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum ChannelToV1Error {
+    #[error("invalid channel state")]
+    InvalidState,
+    #[error("counterparty port id is not valid utf8")]
+    PortIdUtf8(#[source] std::string::FromUtf8Error),
+    #[error("invalid counterparty port id")]
+    PortId(#[source] Ics24IdParseError),
+}
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum ChannelFromV1Error {
+    #[error("channel state {0} is not supported by ibc-union")]
+    State(channel::state::State),
+    #[error("channel ordering {0} is not supported by ibc-union")]
+    Ordering(Order),
+    #[error("ibc-union channels must have exactly one connection hop, found {0}")]
+    ConnectionHops(usize),
+    #[error("channel upgrades are not supported by ibc-union, found upgrade sequence {0}")]
+    UpgradeSequence(u64),
+}
+
+/// Convert an ibc-union [`Channel`] into a v1 [`channel::channel::Channel`].
+///
+/// The counterparty channel id is `None` if it is `0`, and the channel will be unordered with an
+/// upgrade sequence of `0`.
+pub fn channel_to_v1(channel: &Channel) -> Result<channel::channel::Channel, ChannelToV1Error> {
+    Ok(channel::channel::Channel {
+        state: match channel.state {
+            ChannelState::Unspecified => channel::state::State::UninitializedUnspecified,
+            ChannelState::Init => channel::state::State::Init,
+            ChannelState::TryOpen => channel::state::State::Tryopen,
+            ChannelState::Open => channel::state::State::Open,
+            ChannelState::Closed => channel::state::State::Closed,
+            ChannelState::__Invalid => return Err(ChannelToV1Error::InvalidState),
+        },
+        ordering: Order::Unordered,
+        counterparty: channel::counterparty::Counterparty {
+            port_id: PortId::new(
+                String::from_utf8(channel.counterparty_port_id.to_vec())
+                    .map_err(ChannelToV1Error::PortIdUtf8)?,
+            )
+            .map_err(ChannelToV1Error::PortId)?,
+            channel_id: (channel.counterparty_channel_id != 0)
+                .then_some(ChannelId::new(channel.counterparty_channel_id)),
+        },
+        connection_hops: vec![ConnectionId::new(channel.connection_id)],
+        version: channel.version.clone(),
+        upgrade_sequence: 0,
+    })
+}
+
+/// Convert a v1 [`channel::channel::Channel`] into an ibc-union [`Channel`].
+pub fn channel_from_v1(channel: &channel::channel::Channel) -> Result<Channel, ChannelFromV1Error> {
+    if channel.ordering != Order::Unordered {
+        return Err(ChannelFromV1Error::Ordering(channel.ordering));
+    }
+
+    if channel.upgrade_sequence != 0 {
+        return Err(ChannelFromV1Error::UpgradeSequence(
+            channel.upgrade_sequence,
+        ));
+    }
+
+    let [connection_id] = &channel.connection_hops[..] else {
+        return Err(ChannelFromV1Error::ConnectionHops(
+            channel.connection_hops.len(),
+        ));
+    };
+
+    Ok(Channel {
+        state: match channel.state {
+            channel::state::State::UninitializedUnspecified => ChannelState::Unspecified,
+            channel::state::State::Init => ChannelState::Init,
+            channel::state::State::Tryopen => ChannelState::TryOpen,
+            channel::state::State::Open => ChannelState::Open,
+            channel::state::State::Closed => ChannelState::Closed,
+            state @ (channel::state::State::Flushing | channel::state::State::Flushcomplete) => {
+                return Err(ChannelFromV1Error::State(state))
+            }
+        },
+        connection_id: connection_id.id(),
+        counterparty_channel_id: channel
+            .counterparty
+            .channel_id
+            .as_ref()
+            .map_or(0, ChannelId::id),
+        counterparty_port_id: channel
+            .counterparty
+            .port_id
+            .as_str()
+            .as_bytes()
+            .to_vec()
+            .into(),
+        version: channel.version.clone(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::sol_types::SolValue;
+
+    use super::*;
+
+    const PREFIXES: ConnectionIdPrefixes<'static> = ConnectionIdPrefixes {
+        client_id_prefix: "cometbls",
+        counterparty_client_id_prefix: "ethereum",
+    };
+
+    fn connection() -> Connection {
+        Connection {
+            state: ConnectionState::TryOpen,
+            client_id: 1,
+            counterparty_client_id: 2,
+            counterparty_connection_id: 3,
+        }
+    }
+
+    fn channel() -> Channel {
+        Channel {
+            state: ChannelState::Open,
+            connection_id: 1,
+            counterparty_channel_id: 2,
+            counterparty_port_id: b"wasm.union1port".to_vec().into(),
+            version: "ucs01-relay-1".to_owned(),
+        }
+    }
+
+    #[test]
+    fn connection_round_trip() {
+        let bz = connection().abi_encode_params();
+
+        let connection_end =
+            connection_to_v1(&Connection::abi_decode_params(&bz, true).unwrap(), PREFIXES).unwrap();
+
+        assert_eq!(connection_end.client_id.to_string(), "cometbls-1");
+        assert_eq!(
+            connection_end.counterparty.client_id.to_string(),
+            "ethereum-2"
+        );
+        assert_eq!(
+            connection_end.counterparty.connection_id,
+            Some(ConnectionId::new(3))
+        );
+
+        assert_eq!(
+            connection_from_v1(&connection_end)
+                .unwrap()
+                .abi_encode_params(),
+            bz
+        );
+    }
+
+    #[test]
+    fn connection_round_trip_no_counterparty_connection() {
+        let connection = Connection {
+            state: ConnectionState::Init,
+            counterparty_connection_id: 0,
+            ..connection()
+        };
+
+        let connection_end = connection_to_v1(&connection, PREFIXES).unwrap();
+
+        assert_eq!(connection_end.counterparty.connection_id, None);
+
+        assert_eq!(
+            connection_from_v1(&connection_end)
+                .unwrap()
+                .abi_encode_params(),
+            connection.abi_encode_params()
+        );
+    }
+
+    #[test]
+    fn connection_from_v1_lossy() {
+        let connection_end = connection_to_v1(&connection(), PREFIXES).unwrap();
+
+        assert_eq!(
+            connection_from_v1(&ConnectionEnd {
+                delay_period: 10,
+                ..connection_end.clone()
+            }),
+            Err(ConnectionFromV1Error::DelayPeriod(10))
+        );
+
+        assert_eq!(
+            connection_from_v1(&ConnectionEnd {
+                versions: vec![connection::version::Version {
+                    identifier: "1".to_owned(),
+                    features: vec![Order::Unordered],
+                }],
+                ..connection_end
+            }),
+            Err(ConnectionFromV1Error::Versions(1))
+        );
+    }
+
+    #[test]
+    fn connection_to_v1_invalid_state() {
+        assert_eq!(
+            connection_to_v1(
+                &Connection {
+                    state: ConnectionState::__Invalid,
+                    ..connection()
+                },
+                PREFIXES
+            ),
+            Err(ConnectionToV1Error::InvalidState)
+        );
+    }
+
+    #[test]
+    fn channel_round_trip() {
+        let bz = channel().abi_encode_params();
+
+        let v1_channel = channel_to_v1(&Channel::abi_decode_params(&bz, true).unwrap()).unwrap();
+
+        assert_eq!(v1_channel.connection_hops, vec![ConnectionId::new(1)]);
+        assert_eq!(v1_channel.counterparty.port_id.as_str(), "wasm.union1port");
+        assert_eq!(v1_channel.counterparty.channel_id, Some(ChannelId::new(2)));
+
+        assert_eq!(
+            channel_from_v1(&v1_channel).unwrap().abi_encode_params(),
+            bz
+        );
+    }
+
+    #[test]
+    fn channel_to_v1_invalid_port_id() {
+        assert!(matches!(
+            channel_to_v1(&Channel {
+                counterparty_port_id: vec![0xff; 20].into(),
+                ..channel()
+            }),
+            Err(ChannelToV1Error::PortIdUtf8(_))
+        ));
+
+        assert!(matches!(
+            channel_to_v1(&Channel {
+                counterparty_port_id: b"".to_vec().into(),
+                ..channel()
+            }),
+            Err(ChannelToV1Error::PortId(_))
+        ));
+    }
+
+    #[test]
+    fn channel_from_v1_lossy() {
+        let v1_channel = channel_to_v1(&channel()).unwrap();
+
+        assert_eq!(
+            channel_from_v1(&channel::channel::Channel {
+                ordering: Order::Ordered,
+                ..v1_channel.clone()
+            }),
+            Err(ChannelFromV1Error::Ordering(Order::Ordered))
+        );
+
+        assert_eq!(
+            channel_from_v1(&channel::channel::Channel {
+                connection_hops: vec![],
+                ..v1_channel.clone()
+            }),
+            Err(ChannelFromV1Error::ConnectionHops(0))
+        );
+
+        assert_eq!(
+            channel_from_v1(&channel::channel::Channel {
+                upgrade_sequence: 1,
+                ..v1_channel.clone()
+            }),
+            Err(ChannelFromV1Error::UpgradeSequence(1))
+        );
+
+        assert_eq!(
+            channel_from_v1(&channel::channel::Channel {
+                state: channel::state::State::Flushing,
+                ..v1_channel
+            }),
+            Err(ChannelFromV1Error::State(channel::state::State::Flushing))
+        );
+    }
+}
@@ -1,9 +1,19 @@
+use core::{
+    fmt::{self, Display},
+    num::{NonZeroU32, ParseIntError},
+    str::FromStr,
+};
+
+use alloy::sol_types::SolValue;
 use enumorph::Enumorph;
-use ibc_solidity::{Channel, Connection, Packet};
+use ibc_solidity::{Channel, Connection, ConnectionState, Packet};
 use serde::{Deserialize, Serialize};
 use sha3::{Digest, Keccak256};
 use unionlabs::{bytes::Bytes, hash::H256, ibc::core::client::height::Height, uint::U256};
-use voyager_core::{ClientType, IbcSpec, IbcSpecId, IbcStorePathKey};
+use voyager_core::{ClientType, HeightFormat, IbcSpec, IbcSpecId, IbcStorePathKey, TimeoutSpec};
+
+pub mod batch;
+pub mod compat;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum IbcUnion {}
@@ -11,7 +21,9 @@ pub enum IbcUnion {}
 impl IbcSpec for IbcUnion {
     const ID: IbcSpecId = IbcSpecId::new_static(IbcSpecId::UNION);
 
-    type ClientId = u32;
+    const HEIGHT_FORMAT: HeightFormat = HeightFormat::Bare;
+
+    type ClientId = UnionId;
 
     type StorePath = StorePath;
 
@@ -27,7 +39,10 @@ impl IbcSpec for IbcUnion {
     }
 
     fn client_state_path(client_id: Self::ClientId) -> Self::StorePath {
-        ClientStatePath { client_id }.into()
+        ClientStatePath {
+            client_id: client_id.get(),
+        }
+        .into()
     }
 
     fn consensus_state_path(
@@ -35,7 +50,7 @@ impl IbcSpec for IbcUnion {
         height: unionlabs::ibc::core::client::height::Height,
     ) -> Self::StorePath {
         ConsensusStatePath {
-            client_id,
+            client_id: client_id.get(),
             height: height.height(),
         }
         .into()
@@ -81,6 +96,100 @@ impl StorePath {
     }
 }
 
+/// The canonical textual form of the path, i.e. `clients/1/clientState`.
+impl Display for StorePath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StorePath::ClientState(path) => path.fmt(f),
+            StorePath::ConsensusState(path) => path.fmt(f),
+            StorePath::Connection(path) => path.fmt(f),
+            StorePath::Channel(path) => path.fmt(f),
+            StorePath::BatchReceipts(path) => path.fmt(f),
+            StorePath::BatchPackets(path) => path.fmt(f),
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum StorePathParseError {
+    #[error("unknown path `{0}`")]
+    UnknownPath(String),
+    #[error("invalid id `{0}`")]
+    Id(String, #[source] ParseIntError),
+    #[error("non-canonical id `{0}`")]
+    NonCanonicalId(String),
+    #[error("invalid batch hash `{0}`")]
+    BatchHash(String, #[source] <H256 as FromStr>::Err),
+    #[error("non-canonical batch hash `{0}`")]
+    NonCanonicalBatchHash(String),
+}
+
+/// Parses the canonical textual form of the path, as produced by the [`Display`] implementation. Non-canonical forms (such as ids with leading zeros) are rejected, such that parsing and formatting a path always round trips.
+impl FromStr for StorePath {
+    type Err = StorePathParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let segments = s.split('/').collect::<Vec<_>>();
+
+        Ok(match segments[..] {
+            ["clients", client_id, "clientState"] => ClientStatePath {
+                client_id: parse_id(client_id)?,
+            }
+            .into(),
+            ["clients", client_id, "consensusStates", height] => ConsensusStatePath {
+                client_id: parse_id(client_id)?,
+                height: parse_id(height)?,
+            }
+            .into(),
+            ["connections", connection_id] => ConnectionPath {
+                connection_id: parse_id(connection_id)?,
+            }
+            .into(),
+            ["channels", channel_id] => ChannelPath {
+                channel_id: parse_id(channel_id)?,
+            }
+            .into(),
+            ["channels", channel_id, "batchReceipts", batch_hash] => BatchReceiptsPath {
+                channel_id: parse_id(channel_id)?,
+                batch_hash: parse_batch_hash(batch_hash)?,
+            }
+            .into(),
+            ["channels", channel_id, "batchPackets", batch_hash] => BatchPacketsPath {
+                channel_id: parse_id(channel_id)?,
+                batch_hash: parse_batch_hash(batch_hash)?,
+            }
+            .into(),
+            _ => return Err(StorePathParseError::UnknownPath(s.to_owned())),
+        })
+    }
+}
+
+fn parse_id<T: FromStr<Err = ParseIntError> + Display>(s: &str) -> Result<T, StorePathParseError> {
+    let id = s
+        .parse::<T>()
+        .map_err(|e| StorePathParseError::Id(s.to_owned(), e))?;
+
+    // reject non-canonical forms, such as `+1` or `01`
+    if id.to_string() != s {
+        return Err(StorePathParseError::NonCanonicalId(s.to_owned()));
+    }
+
+    Ok(id)
+}
+
+fn parse_batch_hash(s: &str) -> Result<H256, StorePathParseError> {
+    let batch_hash = s
+        .parse::<H256>()
+        .map_err(|e| StorePathParseError::BatchHash(s.to_owned(), e))?;
+
+    // reject non-canonical forms, such as uppercase hex
+    if batch_hash.to_string() != s {
+        return Err(StorePathParseError::NonCanonicalBatchHash(s.to_owned()));
+    }
+
+    Ok(batch_hash)
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct ClientStatePath {
     pub client_id: u32,
@@ -103,6 +212,12 @@ impl IbcStorePathKey for ClientStatePath {
     type Value = Bytes;
 }
 
+impl Display for ClientStatePath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "clients/{}/clientState", self.client_id)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct ConsensusStatePath {
     pub client_id: u32,
@@ -127,6 +242,16 @@ impl IbcStorePathKey for ConsensusStatePath {
     type Value = Bytes;
 }
 
+impl Display for ConsensusStatePath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "clients/{}/consensusStates/{}",
+            self.client_id, self.height
+        )
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct ConnectionPath {
     pub connection_id: u32,
@@ -149,6 +274,12 @@ impl IbcStorePathKey for ConnectionPath {
     type Value = Option<Connection>;
 }
 
+impl Display for ConnectionPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "connections/{}", self.connection_id)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct ChannelPath {
     pub channel_id: u32,
@@ -171,6 +302,12 @@ impl IbcStorePathKey for ChannelPath {
     type Value = Option<ibc_solidity::Channel>;
 }
 
+impl Display for ChannelPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "channels/{}", self.channel_id)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct BatchReceiptsPath {
     pub channel_id: u32,
@@ -178,6 +315,18 @@ pub struct BatchReceiptsPath {
 }
 
 impl BatchReceiptsPath {
+    /// The path of the receipt of `packet` on the destination chain.
+    ///
+    /// Receipts are always written per packet, even if the packet was received as part of a
+    /// batch.
+    #[must_use]
+    pub fn from_packet(packet: &Packet) -> Self {
+        Self {
+            channel_id: packet.destination_channel,
+            batch_hash: commit_packet(packet),
+        }
+    }
+
     #[must_use]
     pub fn key(&self) -> H256 {
         Keccak256::new()
@@ -195,6 +344,16 @@ impl IbcStorePathKey for BatchReceiptsPath {
     type Value = H256;
 }
 
+impl Display for BatchReceiptsPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "channels/{}/batchReceipts/{}",
+            self.channel_id, self.batch_hash
+        )
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct BatchPacketsPath {
     pub channel_id: u32,
@@ -219,6 +378,48 @@ impl IbcStorePathKey for BatchPacketsPath {
     type Value = H256;
 }
 
+impl Display for BatchPacketsPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "channels/{}/batchPackets/{}",
+            self.channel_id, self.batch_hash
+        )
+    }
+}
+
+/// The hash of a single packet, as computed by `IBCPacketLib.commitPacket`.
+#[must_use]
+pub fn commit_packet(packet: &Packet) -> H256 {
+    Keccak256::new()
+        .chain_update(packet.abi_encode())
+        .finalize()
+        .into()
+}
+
+/// The hash of a batch of packets, as computed by `IBCPacketLib.commitPackets`.
+///
+/// Note that this is *not* the same as [`commit_packet`] for a batch containing a single packet.
+#[must_use]
+pub fn commit_packets(packets: &[Packet]) -> H256 {
+    Keccak256::new()
+        .chain_update(packets.abi_encode())
+        .finalize()
+        .into()
+}
+
+/// The commitment of a single acknowledgement, as computed by `IBCPacketLib.commitAck`. This is
+/// the value stored under the [`BatchReceiptsPath`] of the acknowledged packet.
+///
+/// The first byte of the hash is replaced with the first byte of [`COMMITMENT_MAGIC`], such that a
+/// written acknowledgement is never confused with a receipt that has not been acknowledged yet.
+#[must_use]
+pub fn commit_ack(ack: &[u8]) -> H256 {
+    let mut commitment: [u8; 32] = Keccak256::new().chain_update(ack).finalize().into();
+    commitment[0] = COMMITMENT_MAGIC.get()[0];
+    H256::new(commitment)
+}
+
 /// All datagrams that are a part of the IBC union specification.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Enumorph)]
 #[serde(tag = "@type", content = "@value", rename_all = "snake_case")]
@@ -262,7 +463,7 @@ impl Datagram {
             Self::ChannelCloseConfirm(_msg) => todo!(),
             Self::PacketRecv(_msg) => todo!(),
             Self::PacketAcknowledgement(_msg) => todo!(),
-            Self::PacketTimeout(_msg) => todo!(),
+            Self::PacketTimeout(msg) => Some(Height::new(msg.proof_height)),
             Self::IntentPacketRecv(_msg) => todo!(),
             Self::BatchSend(_msg) => todo!(),
             Self::BatchAcks(_msg) => todo!(),
@@ -302,36 +503,36 @@ pub struct MsgCreateClient {
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct MsgUpdateClient {
-    pub client_id: u32,
+    pub client_id: ClientId,
     pub client_message: Bytes,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct MsgConnectionOpenInit {
-    pub client_id: u32,
-    pub counterparty_client_id: u32,
+    pub client_id: ClientId,
+    pub counterparty_client_id: ClientId,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct MsgConnectionOpenTry {
-    pub client_id: u32,
-    pub counterparty_client_id: u32,
-    pub counterparty_connection_id: u32,
+    pub client_id: ClientId,
+    pub counterparty_client_id: ClientId,
+    pub counterparty_connection_id: ConnectionId,
     pub proof_init: Bytes,
     pub proof_height: u64,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct MsgConnectionOpenAck {
-    pub connection_id: u32,
-    pub counterparty_connection_id: u32,
+    pub connection_id: ConnectionId,
+    pub counterparty_connection_id: ConnectionId,
     pub proof_try: Bytes,
     pub proof_height: u64,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct MsgConnectionOpenConfirm {
-    pub connection_id: u32,
+    pub connection_id: ConnectionId,
     pub proof_ack: Bytes,
     pub proof_height: u64,
 }
@@ -340,7 +541,7 @@ pub struct MsgConnectionOpenConfirm {
 pub struct MsgChannelOpenInit {
     pub port_id: Bytes,
     pub counterparty_port_id: Bytes,
-    pub connection_id: u32,
+    pub connection_id: ConnectionId,
     pub version: String,
 }
 
@@ -355,16 +556,16 @@ pub struct MsgChannelOpenTry {
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct MsgChannelOpenAck {
-    pub channel_id: u32,
+    pub channel_id: ChannelId,
     pub counterparty_version: String,
-    pub counterparty_channel_id: u32,
+    pub counterparty_channel_id: ChannelId,
     pub proof_try: Bytes,
     pub proof_height: u64,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct MsgChannelOpenConfirm {
-    pub channel_id: u32,
+    pub channel_id: ChannelId,
     pub proof_ack: Bytes,
     pub proof_height: u64,
 }
@@ -391,8 +592,15 @@ pub struct MsgPacketAcknowledgement {
     pub proof_height: u64,
 }
 
+/// Timeouts are per packet: packets that were sent as part of a batch are timed out individually,
+/// since their receipts are written individually on the destination chain.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub struct MsgPacketTimeout {}
+pub struct MsgPacketTimeout {
+    pub packet: Packet,
+    /// Proof of the absence of the [`BatchReceiptsPath`] of the packet on the destination chain.
+    pub proof: Bytes,
+    pub proof_height: u64,
+}
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct MsgIntentPacketRecv {}
@@ -462,13 +670,56 @@ impl FullEvent {
     }
 }
 
-type ClientId = u32;
-type ConnectionId = u32;
-type ChannelId = u32;
+type ClientId = UnionId;
+type ConnectionId = UnionId;
+type ChannelId = UnionId;
+
+/// A client, connection, or channel id in ibc-union.
+///
+/// Ids are assigned sequentially starting from `1`. `0` is used by the contracts as the null value
+/// (i.e. the counterparty connection id of a connection in `Init`), and is never a valid id.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "u32", into = "u32")]
+pub struct UnionId(NonZeroU32);
 
-// type ClientId = NonZeroU32;
-// type ConnectionId = NonZeroU32;
-// type ChannelId = NonZeroU32;
+impl UnionId {
+    #[must_use]
+    pub const fn new(id: u32) -> Option<Self> {
+        match NonZeroU32::new(id) {
+            Some(id) => Some(Self(id)),
+            None => None,
+        }
+    }
+
+    #[must_use]
+    pub const fn get(self) -> u32 {
+        self.0.get()
+    }
+}
+
+impl TryFrom<u32> for UnionId {
+    type Error = InvalidUnionId;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        Self::new(value).ok_or(InvalidUnionId(value))
+    }
+}
+
+impl From<UnionId> for u32 {
+    fn from(value: UnionId) -> Self {
+        value.get()
+    }
+}
+
+impl Display for UnionId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Display::fmt(&self.0, f)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("invalid ibc-union id `{0}`: ids are assigned starting from 1")]
+pub struct InvalidUnionId(pub u32);
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct CreateClient {
@@ -514,12 +765,49 @@ pub struct ConnectionOpenConfirm {
     pub counterparty_connection_id: ConnectionId,
 }
 
+/// The connection a channel handshake event was emitted on.
+///
+/// This is [`Connection`] with checked ids: channels can only be opened on open connections, so
+/// all of the ids are set. The encoding is the same as that of [`Connection`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ChannelConnection {
+    pub state: ConnectionState,
+    pub client_id: ClientId,
+    pub counterparty_client_id: ClientId,
+    pub counterparty_connection_id: ConnectionId,
+}
+
+impl TryFrom<Connection> for ChannelConnection {
+    type Error = InvalidUnionId;
+
+    fn try_from(value: Connection) -> Result<Self, Self::Error> {
+        Ok(Self {
+            state: value.state,
+            client_id: value.client_id.try_into()?,
+            counterparty_client_id: value.counterparty_client_id.try_into()?,
+            counterparty_connection_id: value.counterparty_connection_id.try_into()?,
+        })
+    }
+}
+
+impl From<ChannelConnection> for Connection {
+    fn from(value: ChannelConnection) -> Self {
+        Self {
+            state: value.state,
+            client_id: value.client_id.get(),
+            counterparty_client_id: value.counterparty_client_id.get(),
+            counterparty_connection_id: value.counterparty_connection_id.get(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ChannelOpenInit {
     pub port_id: Bytes,
     pub channel_id: ChannelId,
     pub counterparty_port_id: Bytes,
-    pub connection: Connection,
+    pub connection: ChannelConnection,
     pub version: String,
 }
 
@@ -529,7 +817,7 @@ pub struct ChannelOpenTry {
     pub channel_id: ChannelId,
     pub counterparty_port_id: Bytes,
     pub counterparty_channel_id: ChannelId,
-    pub connection: Connection,
+    pub connection: ChannelConnection,
     pub version: String,
 }
 
@@ -539,7 +827,7 @@ pub struct ChannelOpenAck {
     pub channel_id: ChannelId,
     pub counterparty_port_id: Bytes,
     pub counterparty_channel_id: ChannelId,
-    pub connection: Connection,
+    pub connection: ChannelConnection,
     pub version: String,
 }
 
@@ -549,7 +837,7 @@ pub struct ChannelOpenConfirm {
     pub channel_id: ChannelId,
     pub counterparty_port_id: Bytes,
     pub counterparty_channel_id: ChannelId,
-    pub connection: Connection,
+    pub connection: ChannelConnection,
     pub version: String,
 }
 
@@ -566,6 +854,11 @@ pub struct SendPacket {
     pub packet_data: Bytes,
 
     pub packet: PacketMetadata,
+
+    /// The position of the packet in a batch, if this event was emitted when the packet was
+    /// batched (`batchSend`) rather than when it was sent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub batch: Option<batch::BatchMember>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -623,6 +916,14 @@ pub struct PacketMetadata {
     pub timeout_timestamp: u64,
 }
 
+impl PacketMetadata {
+    /// The timeout of this packet.
+    #[must_use]
+    pub fn timeout(&self) -> TimeoutSpec {
+        TimeoutSpec::from_union(self.timeout_height, self.timeout_timestamp)
+    }
+}
+
 /// All metadata associated with a Channel.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ChannelMetadata {
@@ -638,3 +939,270 @@ pub struct ConnectionMetadata {
     pub client_id: ClientId,
     pub connection_id: ConnectionId,
 }
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+    use unionlabs::ethereum::ibc_commitment_key;
+
+    use super::*;
+
+    #[test]
+    fn ack_commitment() {
+        // keccak256("") with the first byte replaced by the magic value
+        assert_eq!(
+            commit_ack(&[]),
+            "0x01d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470"
+                .parse::<H256>()
+                .unwrap()
+        );
+
+        assert_ne!(commit_ack(b"ack"), COMMITMENT_MAGIC);
+        assert_ne!(commit_ack(b"ack"), commit_ack(b"ACK"));
+    }
+
+    #[derive(Deserialize)]
+    struct StorePathKeyVector {
+        path: StorePath,
+        display: String,
+        key: H256,
+        commitment_key: H256,
+    }
+
+    /// Vectors generated from the solidity handler by `evm/scripts/StorePathKeys.s.sol`, see the
+    /// script for how to regenerate them.
+    fn vectors() -> Vec<StorePathKeyVector> {
+        serde_json::from_str(include_str!("./test/store_path_keys.json")).unwrap()
+    }
+
+    #[test]
+    fn store_path_keys() {
+        for vector in vectors() {
+            assert_eq!(vector.path.key(), vector.key, "{}", vector.display);
+            assert_eq!(
+                H256::new(ibc_commitment_key(vector.path.key()).to_be_bytes()),
+                vector.commitment_key,
+                "{}",
+                vector.display
+            );
+        }
+    }
+
+    #[derive(Deserialize)]
+    struct PacketCommitmentVector {
+        packets: Vec<Packet>,
+        batch_hash: H256,
+        receipts_keys: Vec<H256>,
+    }
+
+    /// Vectors generated by hashing the abi encoded packets as defined in `IBCPacket.sol`, and the
+    /// resulting receipt paths as defined in `IBCCommitment.sol`.
+    #[test]
+    fn packet_commitments() {
+        let vectors: Vec<PacketCommitmentVector> =
+            serde_json::from_str(include_str!("./test/packet_commitments.json")).unwrap();
+
+        for vector in vectors {
+            let batch_hash = match vector.packets.as_slice() {
+                [packet] => commit_packet(packet),
+                packets => commit_packets(packets),
+            };
+            assert_eq!(batch_hash, vector.batch_hash);
+
+            assert_eq!(
+                vector
+                    .packets
+                    .iter()
+                    .map(|packet| BatchReceiptsPath::from_packet(packet).key())
+                    .collect::<Vec<_>>(),
+                vector.receipts_keys
+            );
+        }
+    }
+
+    #[test]
+    fn store_path_vectors_cover_all_variants() {
+        let vectors = vectors();
+
+        let variants: [fn(&StorePath) -> bool; 6] = [
+            |p: &StorePath| matches!(p, StorePath::ClientState(_)),
+            |p: &StorePath| matches!(p, StorePath::ConsensusState(_)),
+            |p: &StorePath| matches!(p, StorePath::Connection(_)),
+            |p: &StorePath| matches!(p, StorePath::Channel(_)),
+            |p: &StorePath| matches!(p, StorePath::BatchReceipts(_)),
+            |p: &StorePath| matches!(p, StorePath::BatchPackets(_)),
+        ];
+
+        for is_variant in variants {
+            assert!(vectors.iter().any(|v| is_variant(&v.path)));
+        }
+    }
+
+    #[test]
+    fn store_path_display_round_trip() {
+        for vector in vectors() {
+            assert_eq!(vector.path.to_string(), vector.display);
+            assert_eq!(vector.display.parse::<StorePath>().unwrap(), vector.path);
+        }
+    }
+
+    #[test]
+    fn store_path_parse_rejects_non_canonical() {
+        for s in [
+            "",
+            "clients",
+            "clients/1",
+            "clients/1/clientState/",
+            "/clients/1/clientState",
+            "clients/+1/clientState",
+            "clients/01/clientState",
+            "clients/-1/clientState",
+            "clients/4294967296/clientState",
+            "clients/1/consensusStates/",
+            "connections/0x1",
+            "channels/1/batchPackets/0x00",
+            "channels/1/batchPackets/0000000000000000000000000000000000000000000000000000000000000000",
+            "channels/1/batchPackets/0xFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF",
+            "channels/1/packets/7",
+        ] {
+            assert!(s.parse::<StorePath>().is_err(), "{s}");
+        }
+    }
+
+    /// Parse arbitrary strings (derived from mutations of valid paths) and ensure that parsing never panics, and that any successfully parsed path round trips.
+    #[test]
+    fn store_path_parse_fuzz() {
+        // xorshift, to keep this test deterministic
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+
+        let alphabet = b"/0123456789abcdefxXclientsStateconsensusnnelbatchPacketsReceipts+-\xff";
+
+        for vector in vectors() {
+            for _ in 0..500 {
+                let mut bz = vector.display.clone().into_bytes();
+
+                for _ in 0..(next() % 4) {
+                    let idx = (next() as usize) % (bz.len() + 1);
+                    match next() % 3 {
+                        0 if idx < bz.len() => {
+                            bz.remove(idx);
+                        }
+                        1 => bz.insert(idx, alphabet[(next() as usize) % alphabet.len()]),
+                        _ => bz.truncate(idx),
+                    }
+                }
+
+                let s = String::from_utf8_lossy(&bz);
+
+                if let Ok(path) = s.parse::<StorePath>() {
+                    assert_eq!(path.to_string(), s);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn union_id() {
+        assert_eq!(UnionId::new(0), None);
+        assert_eq!(UnionId::new(1).map(UnionId::get), Some(1));
+        assert_eq!(UnionId::try_from(0), Err(InvalidUnionId(0)));
+
+        assert_eq!(
+            serde_json::from_str::<UnionId>("7").unwrap(),
+            UnionId::new(7).unwrap()
+        );
+        assert_eq!(
+            serde_json::to_string(&UnionId::new(7).unwrap()).unwrap(),
+            "7"
+        );
+
+        let err = serde_json::from_str::<UnionId>("0")
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.contains("invalid ibc-union id `0`: ids are assigned starting from 1"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn event_ids_are_validated() {
+        let event = FullEvent::from(ConnectionOpenInit {
+            connection_id: UnionId::new(1).unwrap(),
+            client_id: UnionId::new(2).unwrap(),
+            counterparty_client_id: UnionId::new(3).unwrap(),
+        });
+
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(
+            serde_json::from_value::<FullEvent>(json.clone()).unwrap(),
+            event
+        );
+
+        let mut invalid = json;
+        invalid["@value"]["counterparty_client_id"] = 0.into();
+
+        let err = serde_json::from_value::<FullEvent>(invalid)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("invalid ibc-union id `0`"), "{err}");
+    }
+
+    #[test]
+    fn channel_connection_ids_are_validated() {
+        let connection = Connection {
+            state: ConnectionState::Open,
+            client_id: 1,
+            counterparty_client_id: 2,
+            counterparty_connection_id: 3,
+        };
+
+        let event = FullEvent::from(ChannelOpenInit {
+            port_id: b"port".into(),
+            channel_id: UnionId::new(4).unwrap(),
+            counterparty_port_id: b"counterparty-port".into(),
+            connection: connection.clone().try_into().unwrap(),
+            version: "version".to_owned(),
+        });
+
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(
+            json["@value"]["connection"],
+            serde_json::to_value(&connection).unwrap()
+        );
+        assert_eq!(event.counterparty_client_id(), UnionId::new(2));
+
+        let mut invalid = json;
+        invalid["@value"]["connection"]["counterparty_client_id"] = 0.into();
+
+        let err = serde_json::from_value::<FullEvent>(invalid)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("invalid ibc-union id `0`"), "{err}");
+
+        assert_eq!(
+            ChannelConnection::try_from(Connection {
+                counterparty_connection_id: 0,
+                ..connection
+            }),
+            Err(InvalidUnionId(0))
+        );
+    }
+
+    #[test]
+    fn datagram_ids_are_validated() {
+        let err = serde_json::from_value::<MsgUpdateClient>(serde_json::json!({
+            "client_id": 0,
+            "client_message": "0x",
+        }))
+        .unwrap_err()
+        .to_string();
+        assert!(err.contains("invalid ibc-union id `0`"), "{err}");
+    }
+}
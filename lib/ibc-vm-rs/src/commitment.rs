@@ -0,0 +1,148 @@
+//! Commitments to packets and acknowledgements.
+//!
+//! The hash function used for a commitment is mandated by the IBC specification being
+//! implemented, not by the host. ICS-04 commits with sha256, while the ibc-union commitment scheme
+//! is keccak256 based. Hosts provide the hash functions through [`CommitmentHasher`], which allows
+//! hosts with cheap keccak256 and expensive (or no) sha256 to support the schemes that they can.
+
+use unionlabs::ibc::core::channel::packet::Packet;
+
+use crate::IbcError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashScheme {
+    Sha256,
+    Keccak256,
+}
+
+impl core::fmt::Display for HashScheme {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            HashScheme::Sha256 => f.write_str("sha256"),
+            HashScheme::Keccak256 => f.write_str("keccak256"),
+        }
+    }
+}
+
+/// The hash functions provided by a host.
+pub trait CommitmentHasher {
+    /// Hash `data` with `scheme`, or `None` if the host does not support `scheme`.
+    fn hash(&self, scheme: HashScheme, data: &[u8]) -> Option<Vec<u8>>;
+}
+
+/// The hash scheme of packet and acknowledgement commitments, as mandated by ICS-04.
+pub const IBC_CLASSIC_HASH_SCHEME: HashScheme = HashScheme::Sha256;
+
+/// Hash `data` with `scheme`, failing with [`IbcError::UnsupportedHashScheme`] if the host does not
+/// support `scheme`.
+pub fn hash(
+    hasher: &impl CommitmentHasher,
+    scheme: HashScheme,
+    data: &[u8],
+) -> Result<Vec<u8>, IbcError> {
+    hasher
+        .hash(scheme, data)
+        .ok_or(IbcError::UnsupportedHashScheme(scheme))
+}
+
+/// The commitment to `packet`, as stored under its `CommitmentPath`:
+///
+/// ```text
+/// hash(timeout_timestamp || timeout_revision || timeout_height || hash(data))
+/// ```
+pub fn packet_commitment(
+    hasher: &impl CommitmentHasher,
+    packet: &Packet,
+) -> Result<Vec<u8>, IbcError> {
+    let mut preimage = Vec::new();
+    preimage.extend_from_slice(packet.timeout_timestamp.to_be_bytes().as_slice());
+    preimage.extend_from_slice(packet.timeout_height.revision().to_be_bytes().as_slice());
+    preimage.extend_from_slice(packet.timeout_height.height().to_be_bytes().as_slice());
+    preimage.extend_from_slice(&hash(hasher, IBC_CLASSIC_HASH_SCHEME, &packet.data)?);
+
+    hash(hasher, IBC_CLASSIC_HASH_SCHEME, &preimage)
+}
+
+/// The commitment to `ack`, as stored under its `AcknowledgementPath`.
+pub fn acknowledgement_commitment(
+    hasher: &impl CommitmentHasher,
+    ack: &[u8],
+) -> Result<Vec<u8>, IbcError> {
+    hash(hasher, IBC_CLASSIC_HASH_SCHEME, ack)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU64;
+
+    use sha2::{Digest, Sha256};
+    use unionlabs::{
+        ibc::core::client::height::Height,
+        id::{ChannelId, PortId},
+    };
+
+    use super::*;
+
+    struct Sha256Hasher;
+
+    impl CommitmentHasher for Sha256Hasher {
+        fn hash(&self, scheme: HashScheme, data: &[u8]) -> Option<Vec<u8>> {
+            match scheme {
+                HashScheme::Sha256 => Some(Sha256::digest(data).to_vec()),
+                HashScheme::Keccak256 => None,
+            }
+        }
+    }
+
+    fn packet() -> Packet {
+        Packet {
+            sequence: NonZeroU64::new(1).unwrap(),
+            source_port: PortId::new("port-a").unwrap(),
+            source_channel: ChannelId::new(1),
+            destination_port: PortId::new("port-b").unwrap(),
+            destination_channel: ChannelId::new(2),
+            data: b"hello".to_vec().into(),
+            timeout_height: Height::new_with_revision(1, 10),
+            timeout_timestamp: 100,
+        }
+    }
+
+    /// The packet commitment as computed before the hash scheme was configurable.
+    fn legacy_packet_commitment(packet: &Packet) -> Vec<u8> {
+        let mut packet_commitment = Vec::new();
+        packet_commitment.extend_from_slice(packet.timeout_timestamp.to_be_bytes().as_slice());
+        packet_commitment
+            .extend_from_slice(packet.timeout_height.revision().to_be_bytes().as_slice());
+        packet_commitment
+            .extend_from_slice(packet.timeout_height.height().to_be_bytes().as_slice());
+        packet_commitment.extend_from_slice(Sha256::digest(&packet.data).as_slice());
+        Sha256::digest(packet_commitment).to_vec()
+    }
+
+    #[test]
+    fn packet_commitment_is_unchanged() {
+        let commitment = packet_commitment(&Sha256Hasher, &packet()).unwrap();
+
+        assert_eq!(commitment, legacy_packet_commitment(&packet()));
+        assert_eq!(
+            hex::encode(commitment),
+            "57d447530c2671d1b867214e897a822524d6675b722a0137ceb2b584ee7102a9"
+        );
+    }
+
+    #[test]
+    fn acknowledgement_commitment_is_unchanged() {
+        assert_eq!(
+            hex::encode(acknowledgement_commitment(&Sha256Hasher, b"ack").unwrap()),
+            "64a37929fb113e18daa6263a1fb1f90c51d262552efa5a50596f5f653ba955f8"
+        );
+    }
+
+    #[test]
+    fn unsupported_scheme() {
+        assert_eq!(
+            hash(&Sha256Hasher, HashScheme::Keccak256, b"data"),
+            Err(IbcError::UnsupportedHashScheme(HashScheme::Keccak256))
+        );
+    }
+}
@@ -0,0 +1,194 @@
+//! The events emitted by the state machines once they finish.
+//!
+//! These are the standard ibc-go events, with the same attributes:
+//! <https://github.com/cosmos/ibc-go/blob/5c7f28634ecf9b6f275bfd5712778fedcf06d80d/docs/ibc/events.md>
+
+use core::num::NonZeroU64;
+
+use enumorph::Enumorph;
+use serde::{Deserialize, Serialize};
+use unionlabs::{
+    bytes::Bytes,
+    ibc::core::{channel::order::Order, client::height::Height},
+    id::{ChannelId, ClientId, ConnectionId, PortId},
+};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Enumorph)]
+pub enum IbcEvent {
+    CreateClient(CreateClient),
+    UpdateClient(UpdateClient),
+    ClientMisbehaviour(ClientMisbehaviour),
+
+    ConnectionOpenInit(ConnectionOpenInit),
+    ConnectionOpenTry(ConnectionOpenTry),
+    ConnectionOpenAck(ConnectionOpenAck),
+    ConnectionOpenConfirm(ConnectionOpenConfirm),
+
+    ChannelOpenInit(ChannelOpenInit),
+    ChannelOpenTry(ChannelOpenTry),
+    ChannelOpenAck(ChannelOpenAck),
+    ChannelOpenConfirm(ChannelOpenConfirm),
+
+    WriteAcknowledgement(WriteAcknowledgement),
+    RecvPacket(RecvPacket),
+    SendPacket(SendPacket),
+    AcknowledgePacket(AcknowledgePacket),
+    TimeoutPacket(TimeoutPacket),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CreateClient {
+    pub client_id: ClientId,
+    pub client_type: String,
+    pub consensus_height: Height,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UpdateClient {
+    pub client_id: ClientId,
+    pub client_type: String,
+    pub consensus_heights: Vec<Height>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClientMisbehaviour {
+    pub client_id: ClientId,
+    pub client_type: String,
+    pub consensus_height: Height,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConnectionOpenInit {
+    pub connection_id: ConnectionId,
+    pub client_id: ClientId,
+    pub counterparty_client_id: ClientId,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConnectionOpenTry {
+    pub connection_id: ConnectionId,
+    pub client_id: ClientId,
+    pub counterparty_client_id: ClientId,
+    pub counterparty_connection_id: ConnectionId,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConnectionOpenAck {
+    pub connection_id: ConnectionId,
+    pub client_id: ClientId,
+    pub counterparty_client_id: ClientId,
+    pub counterparty_connection_id: ConnectionId,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConnectionOpenConfirm {
+    pub connection_id: ConnectionId,
+    pub client_id: ClientId,
+    pub counterparty_client_id: ClientId,
+    pub counterparty_connection_id: ConnectionId,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChannelOpenInit {
+    pub port_id: PortId,
+    pub channel_id: ChannelId,
+    pub counterparty_port_id: PortId,
+    pub connection_id: ConnectionId,
+    pub version: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChannelOpenTry {
+    pub port_id: PortId,
+    pub channel_id: ChannelId,
+    pub counterparty_port_id: PortId,
+    pub counterparty_channel_id: ChannelId,
+    pub connection_id: ConnectionId,
+    pub version: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChannelOpenAck {
+    pub port_id: PortId,
+    pub channel_id: ChannelId,
+    pub counterparty_port_id: PortId,
+    pub counterparty_channel_id: ChannelId,
+    pub connection_id: ConnectionId,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChannelOpenConfirm {
+    pub port_id: PortId,
+    pub channel_id: ChannelId,
+    pub counterparty_port_id: PortId,
+    pub counterparty_channel_id: ChannelId,
+    pub connection_id: ConnectionId,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WriteAcknowledgement {
+    pub packet_data_hex: Bytes,
+    pub packet_timeout_height: Height,
+    pub packet_timeout_timestamp: u64,
+    pub packet_sequence: NonZeroU64,
+    pub packet_src_port: PortId,
+    pub packet_src_channel: ChannelId,
+    pub packet_dst_port: PortId,
+    pub packet_dst_channel: ChannelId,
+    pub packet_ack_hex: Bytes,
+    pub connection_id: ConnectionId,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecvPacket {
+    pub packet_data_hex: Bytes,
+    pub packet_timeout_height: Height,
+    pub packet_timeout_timestamp: u64,
+    pub packet_sequence: NonZeroU64,
+    pub packet_src_port: PortId,
+    pub packet_src_channel: ChannelId,
+    pub packet_dst_port: PortId,
+    pub packet_dst_channel: ChannelId,
+    pub packet_channel_ordering: Order,
+    pub connection_id: ConnectionId,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SendPacket {
+    pub packet_data_hex: Bytes,
+    pub packet_timeout_height: Height,
+    pub packet_timeout_timestamp: u64,
+    pub packet_sequence: NonZeroU64,
+    pub packet_src_port: PortId,
+    pub packet_src_channel: ChannelId,
+    pub packet_dst_port: PortId,
+    pub packet_dst_channel: ChannelId,
+    pub packet_channel_ordering: Order,
+    pub connection_id: ConnectionId,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AcknowledgePacket {
+    pub packet_timeout_height: Height,
+    pub packet_timeout_timestamp: u64,
+    pub packet_sequence: NonZeroU64,
+    pub packet_src_port: PortId,
+    pub packet_src_channel: ChannelId,
+    pub packet_dst_port: PortId,
+    pub packet_dst_channel: ChannelId,
+    pub packet_channel_ordering: Order,
+    pub connection_id: ConnectionId,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TimeoutPacket {
+    pub packet_timeout_height: Height,
+    pub packet_timeout_timestamp: u64,
+    pub packet_sequence: NonZeroU64,
+    pub packet_src_port: PortId,
+    pub packet_src_channel: ChannelId,
+    pub packet_dst_port: PortId,
+    pub packet_dst_channel: ChannelId,
+    pub packet_channel_ordering: Order,
+    pub connection_id: ConnectionId,
+}
@@ -0,0 +1,305 @@
+//! Run an [`IbcState`] to completion in a single call.
+//!
+//! Hosts that are able to answer light client queries and call into ibc apps
+//! synchronously (i.e. the light clients and apps are embedded in the host) don't
+//! need to drive the state machine step by step. They instead implement
+//! [`QueryHandler`] and [`AppHandler`] and call [`execute`], which resolves every
+//! [`IbcAction`] the state machine yields until it finishes.
+
+use unionlabs::{
+    ibc::core::{
+        channel::{self, order::Order, packet::Packet},
+        client::height::Height,
+        commitment::merkle_path::MerklePath,
+    },
+    id::{ChannelId, ClientId, ConnectionId, PortId},
+};
+
+use crate::{
+    events::IbcEvent, CallbackError, Either, IbcAction, IbcError, IbcHost, IbcMsg, IbcQuery,
+    IbcResponse, IbcState, IbcVmResponse, Runnable,
+};
+
+/// The maximum number of steps [`execute`] runs a state machine for. None of the state machines
+/// take more than a handful of steps, so hitting this limit means that the state machine is
+/// looping.
+pub const MAX_ITERATIONS: usize = 16;
+
+/// Answers the queries and messages sent to the light client of a client.
+///
+/// There is one method per [`IbcQuery`] variant, and one per [`IbcMsg`] variant targeting the
+/// light client (the client creation and update messages). Each method must return the
+/// [`IbcResponse`] variant corresponding to its query, i.e. [`Self::status`] must return
+/// [`IbcResponse::Status`].
+pub trait QueryHandler {
+    fn status(&mut self, client_id: &ClientId) -> IbcResponse;
+
+    fn latest_height(&mut self, client_id: &ClientId) -> IbcResponse;
+
+    fn timestamp_at_height(&mut self, client_id: &ClientId, height: Height) -> IbcResponse;
+
+    #[allow(clippy::too_many_arguments)]
+    fn verify_membership(
+        &mut self,
+        client_id: &ClientId,
+        height: Height,
+        delay_time_period: u64,
+        delay_block_period: u64,
+        proof: Vec<u8>,
+        path: MerklePath,
+        value: Vec<u8>,
+    ) -> IbcResponse;
+
+    /// Light clients that are not able to verify absence proofs return
+    /// [`IbcResponse::NonMembershipUnsupported`].
+    fn verify_non_membership(
+        &mut self,
+        client_id: &ClientId,
+        height: Height,
+        delay_time_period: u64,
+        delay_block_period: u64,
+        proof: Vec<u8>,
+        path: MerklePath,
+    ) -> IbcResponse;
+
+    fn verify_client_message(&mut self, client_id: &ClientId, client_msg: Vec<u8>) -> IbcResponse;
+
+    fn check_for_misbehaviour(&mut self, client_id: &ClientId, client_msg: Vec<u8>) -> IbcResponse;
+
+    fn initialize(
+        &mut self,
+        client_id: &ClientId,
+        client_type: String,
+        client_state: Vec<u8>,
+        consensus_state: Vec<u8>,
+    ) -> IbcResponse;
+
+    fn update_state(&mut self, client_id: &ClientId, client_msg: Vec<u8>) -> IbcResponse;
+
+    fn update_state_on_misbehaviour(
+        &mut self,
+        client_id: &ClientId,
+        client_msg: Vec<u8>,
+    ) -> IbcResponse;
+}
+
+/// Calls the ibc app callbacks, one method per [`IbcMsg`] variant targeting an app.
+///
+/// The callbacks return `Some(err)` to abort the state machine with
+/// [`IbcError::IbcAppCallbackFailed`].
+pub trait AppHandler {
+    fn on_channel_open_init(
+        &mut self,
+        order: Order,
+        connection_hops: Vec<ConnectionId>,
+        port_id: PortId,
+        channel_id: ChannelId,
+        counterparty: channel::counterparty::Counterparty,
+        version: String,
+    ) -> CallbackError;
+
+    fn on_channel_open_try(
+        &mut self,
+        order: Order,
+        connection_hops: Vec<ConnectionId>,
+        port_id: PortId,
+        channel_id: ChannelId,
+        counterparty: channel::counterparty::Counterparty,
+        counterparty_version: String,
+    ) -> CallbackError;
+
+    fn on_channel_open_ack(
+        &mut self,
+        port_id: PortId,
+        channel_id: ChannelId,
+        counterparty_channel_id: String,
+        counterparty_version: String,
+    ) -> CallbackError;
+
+    fn on_channel_open_confirm(&mut self, port_id: PortId, channel_id: ChannelId) -> CallbackError;
+
+    /// Returns the acknowledgement to write for `packet`.
+    fn on_recv_packet(&mut self, packet: Packet) -> Vec<u8>;
+
+    fn on_acknowledge_packet(&mut self, packet: Packet, ack: Vec<u8>) -> CallbackError;
+
+    fn on_timeout_packet(&mut self, packet: Packet) -> CallbackError;
+}
+
+/// Run `initial` to completion, answering its queries with `query_handler` and dispatching its
+/// app callbacks to `app_handler`.
+///
+/// The responses to a query action are passed back to the state machine in the same order as the
+/// queries. Fails with [`IbcError::TooManyIterations`] if the state machine doesn't finish within
+/// [`MAX_ITERATIONS`] steps.
+pub fn execute<H: IbcHost, Q: QueryHandler, A: AppHandler>(
+    initial: IbcState,
+    host: &mut H,
+    query_handler: &mut Q,
+    app_handler: &mut A,
+) -> Result<(Vec<IbcEvent>, IbcVmResponse), H::Error> {
+    execute_with_limit(initial, host, query_handler, app_handler, MAX_ITERATIONS)
+}
+
+/// [`execute`], running at most `max_iterations` steps.
+pub fn execute_with_limit<H: IbcHost, Q: QueryHandler, A: AppHandler>(
+    initial: IbcState,
+    host: &mut H,
+    query_handler: &mut Q,
+    app_handler: &mut A,
+    max_iterations: usize,
+) -> Result<(Vec<IbcEvent>, IbcVmResponse), H::Error> {
+    let mut state = initial;
+    let mut responses = vec![IbcResponse::Empty];
+
+    for _ in 0..max_iterations {
+        match state.process(host, &responses)? {
+            Either::Left((next, action)) => {
+                state = next;
+                responses = match action {
+                    IbcAction::Query((client_id, queries)) => queries
+                        .into_iter()
+                        .map(|query| handle_query(query_handler, &client_id, query))
+                        .collect(),
+                    IbcAction::Write(msg) => vec![handle_msg(query_handler, app_handler, msg)],
+                };
+            }
+            Either::Right(res) => return Ok(res),
+        }
+    }
+
+    Err(IbcError::TooManyIterations(max_iterations).into())
+}
+
+fn handle_query<Q: QueryHandler>(
+    query_handler: &mut Q,
+    client_id: &ClientId,
+    query: IbcQuery,
+) -> IbcResponse {
+    match query {
+        IbcQuery::Status => query_handler.status(client_id),
+        IbcQuery::LatestHeight => query_handler.latest_height(client_id),
+        IbcQuery::VerifyMembership {
+            height,
+            delay_time_period,
+            delay_block_period,
+            proof,
+            path,
+            value,
+        } => query_handler.verify_membership(
+            client_id,
+            height,
+            delay_time_period,
+            delay_block_period,
+            proof,
+            path,
+            value,
+        ),
+        IbcQuery::VerifyNonMembership {
+            height,
+            delay_time_period,
+            delay_block_period,
+            proof,
+            path,
+        } => query_handler.verify_non_membership(
+            client_id,
+            height,
+            delay_time_period,
+            delay_block_period,
+            proof,
+            path,
+        ),
+        IbcQuery::VerifyClientMessage(client_msg) => {
+            query_handler.verify_client_message(client_id, client_msg)
+        }
+        IbcQuery::CheckForMisbehaviour(client_msg) => {
+            query_handler.check_for_misbehaviour(client_id, client_msg)
+        }
+        IbcQuery::TimestampAtHeight(height) => query_handler.timestamp_at_height(client_id, height),
+    }
+}
+
+fn handle_msg<Q: QueryHandler, A: AppHandler>(
+    query_handler: &mut Q,
+    app_handler: &mut A,
+    msg: IbcMsg,
+) -> IbcResponse {
+    match msg {
+        IbcMsg::Initialize {
+            client_id,
+            client_type,
+            client_state,
+            consensus_state,
+        } => query_handler.initialize(&client_id, client_type, client_state, consensus_state),
+        IbcMsg::UpdateStateOnMisbehaviour {
+            client_id,
+            client_msg,
+        } => query_handler.update_state_on_misbehaviour(&client_id, client_msg),
+        IbcMsg::UpdateState {
+            client_id,
+            client_msg,
+        } => query_handler.update_state(&client_id, client_msg),
+        IbcMsg::OnChannelOpenInit {
+            order,
+            connection_hops,
+            port_id,
+            channel_id,
+            counterparty,
+            version,
+        } => IbcResponse::OnChannelOpenInit {
+            err: app_handler.on_channel_open_init(
+                order,
+                connection_hops,
+                port_id,
+                channel_id,
+                counterparty,
+                version,
+            ),
+        },
+        IbcMsg::OnChannelOpenTry {
+            order,
+            connection_hops,
+            port_id,
+            channel_id,
+            counterparty,
+            counterparty_version,
+        } => IbcResponse::OnChannelOpenTry {
+            err: app_handler.on_channel_open_try(
+                order,
+                connection_hops,
+                port_id,
+                channel_id,
+                counterparty,
+                counterparty_version,
+            ),
+        },
+        IbcMsg::OnChannelOpenAck {
+            port_id,
+            channel_id,
+            counterparty_channel_id,
+            counterparty_version,
+        } => IbcResponse::OnChannelOpenAck {
+            err: app_handler.on_channel_open_ack(
+                port_id,
+                channel_id,
+                counterparty_channel_id,
+                counterparty_version,
+            ),
+        },
+        IbcMsg::OnChannelOpenConfirm {
+            port_id,
+            channel_id,
+        } => IbcResponse::OnChannelOpenConfirm {
+            err: app_handler.on_channel_open_confirm(port_id, channel_id),
+        },
+        IbcMsg::OnRecvPacket { packet } => IbcResponse::OnRecvPacket {
+            ack: app_handler.on_recv_packet(packet),
+        },
+        IbcMsg::OnAcknowledgePacket { packet, ack } => IbcResponse::OnAcknowledgePacket {
+            err: app_handler.on_acknowledge_packet(packet, ack),
+        },
+        IbcMsg::OnTimeoutPacket { packet } => IbcResponse::OnTimeoutPacket {
+            err: app_handler.on_timeout_packet(packet),
+        },
+    }
+}
@@ -1,5 +1,7 @@
+use commitment::{CommitmentHasher, HashScheme};
+use events::IbcEvent;
 use frame_support_procedural::PartialEqNoBound;
-use ibc_events::IbcEvent;
+use ibc_classic_spec::StorePath;
 use serde::{Deserialize, Serialize};
 use states::{
     channel_handshake::{ChannelOpenAck, ChannelOpenConfirm, ChannelOpenInit, ChannelOpenTry},
@@ -7,9 +9,10 @@ use states::{
     connection_handshake::{
         ConnectionOpenAck, ConnectionOpenConfirm, ConnectionOpenInit, ConnectionOpenTry,
     },
-    packet::{Acknowledgement, RecvPacket, SendPacket},
+    packet::{Acknowledgement, RecvPacket, SendPacket, TimeoutPacket},
     CreateClient,
 };
+use storage::{Migrations, StorageError};
 use unionlabs::{
     encoding::{Decode, Encode, Proto},
     ibc::core::{
@@ -18,13 +21,19 @@ use unionlabs::{
         commitment::{merkle_path::MerklePath, merkle_prefix::MerklePrefix},
         connection::{self, version::Version},
     },
-    ics24::Path,
     id::{ChannelId, ClientId, ConnectionId, PortId},
 };
 
+pub mod commitment;
+pub mod events;
+pub mod execute;
 pub mod states;
+pub mod storage;
 
 lazy_static::lazy_static! {
+    /// The connection versions supported by this implementation, in order of preference.
+    ///
+    /// Only unordered channels are supported, since packets are always handled with receipts.
     pub static ref DEFAULT_IBC_VERSION: Vec<Version> = vec![Version { identifier: String::from("1"), features: vec![Order::Unordered] }];
 
     // TODO(aeryz): idk if this is enforced by ibc-go or by the spec. Because we don't have merkle prefix in ethereum or near.
@@ -36,6 +45,9 @@ pub enum IbcError {
     #[error("client {0} is not active ({1})")]
     NotActive(ClientId, Status),
 
+    #[error("client type {0} is not allowed")]
+    ClientTypeNotAllowed(String),
+
     // TODO(aeryz): this needs context
     #[error("unexpected action is provided to the state machine")]
     UnexpectedAction,
@@ -64,6 +76,12 @@ pub enum IbcError {
     #[error("membership verification failed")]
     MembershipVerificationFailure,
 
+    #[error("non-membership verification failed")]
+    NonMembershipVerificationFailure,
+
+    #[error("the light client does not support non-membership verification")]
+    NonMembershipUnsupported,
+
     #[error("no supported version is found")]
     NoSupportedVersionFound,
 
@@ -76,6 +94,15 @@ pub enum IbcError {
     #[error("the proposed version contains an unsupported feature ({0})")]
     UnsupportedFeatureInVersion(Order),
 
+    #[error("version ({0}) has no features in common with the supported version")]
+    NoCommonVersionFeatures(String),
+
+    #[error("connection must have exactly one negotiated version, found {0}")]
+    ConnectionVersionNotNegotiated(usize),
+
+    #[error("channel ordering ({0}) is not supported by the connection version")]
+    ChannelOrderingNotSupported(Order),
+
     #[error("the client state is not found for client {0}")]
     ClientStateNotFound(ClientId),
 
@@ -104,40 +131,109 @@ pub enum IbcError {
     #[error("packet is already timed out")]
     TimedOutPacket,
 
+    #[error("packet has not timed out yet (height {0}, timestamp {1})")]
+    PacketNotTimedOut(Height, u64),
+
     #[error("zero timeout is not allowed")]
     ZeroTimeout,
 
+    #[error("timeout has already passed")]
+    TimeoutInPast,
+
+    #[error("timeout timestamp must be at least {min_margin}ns in the future")]
+    TimeoutTooSoon { min_margin: u64 },
+
     #[error("committed packet ({comm}) does not match the calculated one ({exp_comm})", comm = serde_utils::to_hex(.0), exp_comm= serde_utils::to_hex(.1))]
     PacketCommitmentMismatch(Vec<u8>, Vec<u8>),
+
+    #[error("the host does not support the {0} hash scheme")]
+    UnsupportedHashScheme(HashScheme),
+
+    #[error("the state machine did not finish within {0} iterations")]
+    TooManyIterations(usize),
+
+    #[error(transparent)]
+    Storage(#[from] StorageError),
+
+    #[error("the store has schema version {stored}, which is newer than the supported version {supported}")]
+    UnsupportedSchemaVersion { stored: u32, supported: u32 },
 }
 
-pub trait IbcHost: Sized {
+pub trait IbcHost: Sized + CommitmentHasher {
     type Error: core::fmt::Display + core::fmt::Debug + PartialEq + From<IbcError>;
 
     fn next_client_identifier(&mut self, client_type: &str) -> Result<ClientId, Self::Error>;
 
+    /// The client types that are allowed to be created on this host. `None` allows all client types.
+    fn allowed_client_types(&self) -> Option<Vec<String>>;
+
     fn next_connection_identifier(&mut self) -> Result<ConnectionId, Self::Error>;
 
     fn next_channel_identifier(&mut self) -> Result<ChannelId, Self::Error>;
 
     fn client_state(&self, client_id: &ClientId) -> Option<Vec<u8>>;
 
-    fn read<T: Decode<Proto>>(&self, path: &Path) -> Option<T>;
+    fn read<T: Decode<Proto>>(&self, path: &StorePath) -> Option<T>;
 
-    fn read_raw(&self, key: &Path) -> Option<Vec<u8>>;
+    fn read_raw(&self, key: &StorePath) -> Option<Vec<u8>>;
 
-    fn commit_raw(&mut self, key: Path, value: Vec<u8>) -> Result<(), Self::Error>;
+    fn commit_raw(&mut self, key: StorePath, value: Vec<u8>) -> Result<(), Self::Error>;
 
     // TODO(aeryz): generic over encoding
-    fn commit<T: Encode<Proto>>(&mut self, key: Path, value: T) -> Result<(), Self::Error>;
+    fn commit<T: Encode<Proto>>(&mut self, key: StorePath, value: T) -> Result<(), Self::Error>;
+
+    fn delete(&mut self, key: &StorePath) -> Result<(), Self::Error>;
+
+    /// Read the value stored under a reserved key, such as [`storage::SCHEMA_VERSION_KEY`].
+    /// Reserved keys are not ics24 paths, and their values are not part of the ibc store.
+    fn read_reserved(&self, key: &str) -> Option<Vec<u8>>;
+
+    fn commit_reserved(&mut self, key: &str, value: Vec<u8>) -> Result<(), Self::Error>;
+
+    /// Read and decode the value at `path`, failing with [`IbcError::Storage`] if it is not a valid
+    /// encoding of `T`.
+    fn read_decode<T: Decode<Proto>>(&self, path: &StorePath) -> Result<Option<T>, Self::Error> {
+        self.read_raw(path)
+            .map(|raw| {
+                T::decode(&raw).map_err(|err| {
+                    IbcError::Storage(StorageError::new::<T>(path.to_string(), err)).into()
+                })
+            })
+            .transpose()
+    }
+
+    /// Encode and commit `value` at `path`, the counterpart of [`Self::read_decode`].
+    fn commit_encode<T: Encode<Proto>>(
+        &mut self,
+        path: StorePath,
+        value: T,
+    ) -> Result<(), Self::Error> {
+        self.commit_raw(path, value.encode())
+    }
 
-    fn delete(&mut self, key: &Path) -> Result<(), Self::Error>;
+    /// The version of the encoding of the values stored by this host. Hosts bump this when the
+    /// encoding of a stored type changes, and register a migration for the type in
+    /// [`Self::migrations`].
+    fn schema_version(&self) -> u32 {
+        storage::INITIAL_SCHEMA_VERSION
+    }
+
+    /// The migrations run by [`storage::migrate`] when the store was written with a lower
+    /// [`Self::schema_version`].
+    fn migrations(&self) -> Migrations<Self> {
+        Migrations::default()
+    }
 
     fn current_height(&self) -> Height;
 
     fn current_timestamp(&self) -> u64;
 
-    fn sha256(&self, data: Vec<u8>) -> Vec<u8>;
+    /// The minimum time (in nanoseconds) between [`Self::current_timestamp`] and the timeout
+    /// timestamp of a sent packet, to account for clock skew between this host and the
+    /// counterparty. Packets timing out sooner are rejected.
+    fn min_timeout_margin_nanos(&self) -> u64 {
+        0
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Copy, Clone)]
@@ -181,6 +277,12 @@ pub enum IbcResponse {
     VerifyMembership {
         valid: bool,
     },
+    VerifyNonMembership {
+        valid: bool,
+    },
+    /// Returned by hosts whose light client is not able to verify absence
+    /// proofs in response to [`IbcQuery::VerifyNonMembership`].
+    NonMembershipUnsupported,
     VerifyClientMessage {
         valid: bool,
     },
@@ -210,6 +312,9 @@ pub enum IbcResponse {
     OnAcknowledgePacket {
         err: CallbackError,
     },
+    OnTimeoutPacket {
+        err: CallbackError,
+    },
 }
 
 #[derive(enumorph::Enumorph, Debug, Serialize, Deserialize)]
@@ -228,6 +333,7 @@ pub enum IbcState {
     SendPacket(SendPacket),
     RecvPacket(RecvPacket),
     AcknowledgePacket(Acknowledgement),
+    TimeoutPacket(TimeoutPacket),
 }
 
 macro_rules! cast_either {
@@ -248,6 +354,8 @@ impl<T: IbcHost> Runnable<T> for IbcState {
         resp: &[IbcResponse],
     ) -> Result<Either<(Self, IbcAction), (Vec<IbcEvent>, IbcVmResponse)>, <T as IbcHost>::Error>
     {
+        storage::ensure_schema_version(host)?;
+
         let res = cast_either!(
             self,
             host,
@@ -265,7 +373,8 @@ impl<T: IbcHost> Runnable<T> for IbcState {
                 ChannelOpenConfirm,
                 SendPacket,
                 RecvPacket,
-                AcknowledgePacket
+                AcknowledgePacket,
+                TimeoutPacket
             ]
         );
         Ok(res)
@@ -302,6 +411,13 @@ pub enum IbcQuery {
         path: MerklePath,
         value: Vec<u8>,
     },
+    VerifyNonMembership {
+        height: Height,
+        delay_time_period: u64,
+        delay_block_period: u64,
+        proof: Vec<u8>,
+        path: MerklePath,
+    },
 
     VerifyClientMessage(Vec<u8>),
 
@@ -367,6 +483,10 @@ pub enum IbcMsg {
         packet: Packet,
         ack: Vec<u8>,
     },
+
+    OnTimeoutPacket {
+        packet: Packet,
+    },
 }
 
 pub trait Runnable<T: IbcHost>: Serialize + Sized {
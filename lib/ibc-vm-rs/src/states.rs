@@ -3,15 +3,13 @@ pub mod client_state;
 pub mod connection_handshake;
 pub mod packet;
 
+use ibc_classic_spec::{ClientConsensusStatePath, ClientStatePath};
 use serde::{Deserialize, Serialize};
-use unionlabs::{
-    ics24::{ClientConsensusStatePath, ClientStatePath},
-    id::ClientId,
-};
+use unionlabs::id::ClientId;
 
 use crate::{
-    Either, IbcAction, IbcError, IbcEvent, IbcHost, IbcMsg, IbcQuery, IbcResponse, IbcVmResponse,
-    Runnable, Status,
+    events, storage::init_schema_version, Either, IbcAction, IbcError, IbcEvent, IbcHost, IbcMsg,
+    IbcQuery, IbcResponse, IbcVmResponse, Runnable, Status,
 };
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -54,6 +52,13 @@ impl<T: IbcHost> Runnable<T> for CreateClient {
                 },
                 &[IbcResponse::Empty],
             ) => {
+                if host
+                    .allowed_client_types()
+                    .is_some_and(|allowed| !allowed.contains(&client_type))
+                {
+                    return Err(IbcError::ClientTypeNotAllowed(client_type).into());
+                }
+
                 let client_id = host.next_client_identifier(&client_type)?;
                 Either::Left((
                     CreateClient::Initialize {
@@ -101,6 +106,7 @@ impl<T: IbcHost> Runnable<T> for CreateClient {
                     return Err(IbcError::NotActive(client_id, status).into());
                 }
                 let client_id = client_id.clone();
+                init_schema_version(host)?;
                 host.commit_raw(
                     ClientStatePath {
                         client_id: client_id.clone(),
@@ -117,7 +123,7 @@ impl<T: IbcHost> Runnable<T> for CreateClient {
                     consensus_state.clone(),
                 )?;
                 Either::Right((
-                    vec![IbcEvent::CreateClient(ibc_events::CreateClient {
+                    vec![IbcEvent::CreateClient(events::CreateClient {
                         client_id,
                         client_type,
                         consensus_height: height,
@@ -131,3 +137,999 @@ impl<T: IbcHost> Runnable<T> for CreateClient {
         Ok(res)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeMap, num::NonZeroU64};
+
+    use ibc_classic_spec::{
+        ChannelEndPath, CommitmentPath, ConnectionPath, NextSequenceSendPath, ReceiptPath,
+        StorePath,
+    };
+    use unionlabs::{
+        encoding::{Decode, Encode, Proto},
+        ibc::core::{
+            channel::{self, channel::Channel, order::Order, packet::Packet},
+            client::height::Height,
+            commitment::{merkle_path::MerklePath, merkle_prefix::MerklePrefix},
+            connection::{self, connection_end::ConnectionEnd, version::Version},
+        },
+        id::{ChannelId, ConnectionId, PortId},
+    };
+
+    use super::{packet::TimeoutPacket, *};
+    use crate::{
+        commitment::{CommitmentHasher, HashScheme},
+        execute::{execute, execute_with_limit, AppHandler, QueryHandler},
+        storage::{Migrations, INITIAL_SCHEMA_VERSION, SCHEMA_VERSION_KEY},
+        CallbackError, IbcState, DEFAULT_IBC_VERSION,
+    };
+
+    #[derive(Default)]
+    struct MockHost {
+        commitments: BTreeMap<String, Vec<u8>>,
+        client_index: u32,
+        allowed_client_types: Option<Vec<String>>,
+        timestamp: u64,
+        min_timeout_margin: u64,
+        reserved: BTreeMap<String, Vec<u8>>,
+        schema_version: Option<u32>,
+    }
+
+    impl IbcHost for MockHost {
+        type Error = IbcError;
+
+        fn next_client_identifier(&mut self, client_type: &str) -> Result<ClientId, IbcError> {
+            self.client_index += 1;
+
+            Ok(ClientId::new(client_type.to_owned(), self.client_index))
+        }
+
+        fn allowed_client_types(&self) -> Option<Vec<String>> {
+            self.allowed_client_types.clone()
+        }
+
+        fn next_connection_identifier(&mut self) -> Result<ConnectionId, IbcError> {
+            unimplemented!()
+        }
+
+        fn next_channel_identifier(&mut self) -> Result<ChannelId, IbcError> {
+            unimplemented!()
+        }
+
+        fn client_state(&self, client_id: &ClientId) -> Option<Vec<u8>> {
+            self.commitments
+                .get(&format!("clients/{client_id}/clientState"))
+                .cloned()
+        }
+
+        fn read<T: Decode<Proto>>(&self, path: &StorePath) -> Option<T> {
+            self.read_raw(path).map(|bz| T::decode(&bz).unwrap())
+        }
+
+        fn read_raw(&self, key: &StorePath) -> Option<Vec<u8>> {
+            self.commitments.get(&key.to_string()).cloned()
+        }
+
+        fn commit_raw(&mut self, key: StorePath, value: Vec<u8>) -> Result<(), IbcError> {
+            self.commitments.insert(key.to_string(), value);
+            Ok(())
+        }
+
+        fn commit<T: Encode<Proto>>(&mut self, key: StorePath, value: T) -> Result<(), IbcError> {
+            self.commit_raw(key, value.encode())
+        }
+
+        fn delete(&mut self, key: &StorePath) -> Result<(), IbcError> {
+            self.commitments.remove(&key.to_string());
+            Ok(())
+        }
+
+        fn read_reserved(&self, key: &str) -> Option<Vec<u8>> {
+            self.reserved.get(key).cloned()
+        }
+
+        fn commit_reserved(&mut self, key: &str, value: Vec<u8>) -> Result<(), IbcError> {
+            self.reserved.insert(key.to_owned(), value);
+            Ok(())
+        }
+
+        fn schema_version(&self) -> u32 {
+            self.schema_version.unwrap_or(INITIAL_SCHEMA_VERSION)
+        }
+
+        fn migrations(&self) -> Migrations<Self> {
+            Migrations::default().register::<ConnectionEnd>(2, migrate_connection_ends)
+        }
+
+        fn current_height(&self) -> Height {
+            Height::new(1)
+        }
+
+        fn current_timestamp(&self) -> u64 {
+            self.timestamp
+        }
+
+        fn min_timeout_margin_nanos(&self) -> u64 {
+            self.min_timeout_margin
+        }
+    }
+
+    impl CommitmentHasher for MockHost {
+        // identity, so that commitments can be constructed by hand in tests
+        fn hash(&self, scheme: HashScheme, data: &[u8]) -> Option<Vec<u8>> {
+            match scheme {
+                HashScheme::Sha256 => Some(data.to_vec()),
+                HashScheme::Keccak256 => None,
+            }
+        }
+    }
+
+    /// Connection ends were json encoded in version 1 of the mock host's schema, and are proto
+    /// encoded since version 2.
+    fn migrate_connection_ends(host: &mut MockHost) -> Result<(), IbcError> {
+        for (key, value) in &mut host.commitments {
+            if key.starts_with("connections/") {
+                let end: ConnectionEnd = serde_json::from_slice(value).unwrap();
+                *value = end.encode();
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Answers light client queries against a fixed view of the counterparty's
+    /// store, keyed by the ics24 path. The name of every query and message it
+    /// handles is recorded in `calls`.
+    struct MockLightClient {
+        counterparty: BTreeMap<String, Vec<u8>>,
+        status: Status,
+        latest_height: Height,
+        timestamp: u64,
+        supports_non_membership: bool,
+        calls: Vec<&'static str>,
+    }
+
+    impl Default for MockLightClient {
+        fn default() -> Self {
+            Self {
+                counterparty: BTreeMap::new(),
+                status: Status::Active,
+                latest_height: Height::new(10),
+                timestamp: 0,
+                supports_non_membership: false,
+                calls: vec![],
+            }
+        }
+    }
+
+    impl QueryHandler for MockLightClient {
+        fn status(&mut self, _: &ClientId) -> IbcResponse {
+            self.calls.push("status");
+            IbcResponse::Status {
+                status: self.status,
+            }
+        }
+
+        fn latest_height(&mut self, _: &ClientId) -> IbcResponse {
+            self.calls.push("latest_height");
+            IbcResponse::LatestHeight {
+                height: self.latest_height,
+            }
+        }
+
+        fn timestamp_at_height(&mut self, _: &ClientId, _: Height) -> IbcResponse {
+            self.calls.push("timestamp_at_height");
+            IbcResponse::TimestampAtHeight {
+                timestamp: self.timestamp,
+            }
+        }
+
+        fn verify_membership(
+            &mut self,
+            _: &ClientId,
+            _: Height,
+            _: u64,
+            _: u64,
+            _: Vec<u8>,
+            path: MerklePath,
+            value: Vec<u8>,
+        ) -> IbcResponse {
+            self.calls.push("verify_membership");
+            IbcResponse::VerifyMembership {
+                valid: self.counterparty.get(&path.key_path[1]) == Some(&value),
+            }
+        }
+
+        fn verify_non_membership(
+            &mut self,
+            _: &ClientId,
+            _: Height,
+            _: u64,
+            _: u64,
+            _: Vec<u8>,
+            path: MerklePath,
+        ) -> IbcResponse {
+            self.calls.push("verify_non_membership");
+            if self.supports_non_membership {
+                IbcResponse::VerifyNonMembership {
+                    valid: !self.counterparty.contains_key(&path.key_path[1]),
+                }
+            } else {
+                IbcResponse::NonMembershipUnsupported
+            }
+        }
+
+        fn verify_client_message(&mut self, _: &ClientId, _: Vec<u8>) -> IbcResponse {
+            self.calls.push("verify_client_message");
+            IbcResponse::VerifyClientMessage { valid: true }
+        }
+
+        fn check_for_misbehaviour(&mut self, _: &ClientId, _: Vec<u8>) -> IbcResponse {
+            self.calls.push("check_for_misbehaviour");
+            IbcResponse::CheckForMisbehaviour {
+                misbehaviour_found: false,
+            }
+        }
+
+        fn initialize(&mut self, _: &ClientId, _: String, _: Vec<u8>, _: Vec<u8>) -> IbcResponse {
+            self.calls.push("initialize");
+            IbcResponse::Initialize
+        }
+
+        fn update_state(&mut self, _: &ClientId, client_msg: Vec<u8>) -> IbcResponse {
+            self.calls.push("update_state");
+            IbcResponse::UpdateState {
+                consensus_states: vec![(self.latest_height, client_msg)],
+                client_state: b"client_state".to_vec(),
+            }
+        }
+
+        fn update_state_on_misbehaviour(&mut self, _: &ClientId, _: Vec<u8>) -> IbcResponse {
+            self.calls.push("update_state_on_misbehaviour");
+            IbcResponse::UpdateStateOnMisbehaviour
+        }
+    }
+
+    /// An app accepting every callback, recording the name of the callbacks
+    /// that are called.
+    #[derive(Default)]
+    struct MockApp {
+        callbacks: Vec<&'static str>,
+    }
+
+    impl AppHandler for MockApp {
+        fn on_channel_open_init(
+            &mut self,
+            _: Order,
+            _: Vec<ConnectionId>,
+            _: PortId,
+            _: ChannelId,
+            _: channel::counterparty::Counterparty,
+            _: String,
+        ) -> CallbackError {
+            self.callbacks.push("on_channel_open_init");
+            None
+        }
+
+        fn on_channel_open_try(
+            &mut self,
+            _: Order,
+            _: Vec<ConnectionId>,
+            _: PortId,
+            _: ChannelId,
+            _: channel::counterparty::Counterparty,
+            _: String,
+        ) -> CallbackError {
+            self.callbacks.push("on_channel_open_try");
+            None
+        }
+
+        fn on_channel_open_ack(
+            &mut self,
+            _: PortId,
+            _: ChannelId,
+            _: String,
+            _: String,
+        ) -> CallbackError {
+            self.callbacks.push("on_channel_open_ack");
+            None
+        }
+
+        fn on_channel_open_confirm(&mut self, _: PortId, _: ChannelId) -> CallbackError {
+            self.callbacks.push("on_channel_open_confirm");
+            None
+        }
+
+        fn on_recv_packet(&mut self, _: Packet) -> Vec<u8> {
+            self.callbacks.push("on_recv_packet");
+            b"ack".to_vec()
+        }
+
+        fn on_acknowledge_packet(&mut self, _: Packet, _: Vec<u8>) -> CallbackError {
+            self.callbacks.push("on_acknowledge_packet");
+            None
+        }
+
+        fn on_timeout_packet(&mut self, _: Packet) -> CallbackError {
+            self.callbacks.push("on_timeout_packet");
+            None
+        }
+    }
+
+    fn create_client(client_type: &str) -> CreateClient {
+        CreateClient::Init {
+            client_type: client_type.to_owned(),
+            client_state: b"client_state".to_vec(),
+            consensus_state: b"consensus_state".to_vec(),
+        }
+    }
+
+    #[test]
+    fn create_client_works() {
+        let mut host = MockHost {
+            allowed_client_types: Some(vec!["cometbls".to_owned(), "ethereum".to_owned()]),
+            ..Default::default()
+        };
+
+        let mut light_client = MockLightClient::default();
+
+        let (events, response) = execute(
+            create_client("cometbls").into(),
+            &mut host,
+            &mut light_client,
+            &mut MockApp::default(),
+        )
+        .unwrap();
+
+        assert_eq!(response, IbcVmResponse::Empty);
+
+        let [IbcEvent::CreateClient(event)] = &events[..] else {
+            panic!("expected a single CreateClient event");
+        };
+
+        assert_eq!(event.client_id, ClientId::new("cometbls", 1));
+        assert_eq!(event.client_type, "cometbls");
+        assert_eq!(event.consensus_height, Height::new(10));
+
+        assert_eq!(
+            host.client_state(&ClientId::new("cometbls", 1)),
+            Some(b"client_state".to_vec())
+        );
+        assert_eq!(
+            light_client.calls,
+            ["initialize", "status", "latest_height"]
+        );
+
+        assert_eq!(
+            host.read_reserved(SCHEMA_VERSION_KEY),
+            Some(INITIAL_SCHEMA_VERSION.to_be_bytes().to_vec())
+        );
+    }
+
+    #[test]
+    fn create_client_type_not_allowed() {
+        let mut host = MockHost {
+            allowed_client_types: Some(vec!["cometbls".to_owned(), "ethereum".to_owned()]),
+            ..Default::default()
+        };
+
+        let mut light_client = MockLightClient::default();
+
+        assert_eq!(
+            execute(
+                create_client("tendermint").into(),
+                &mut host,
+                &mut light_client,
+                &mut MockApp::default(),
+            )
+            .err(),
+            Some(IbcError::ClientTypeNotAllowed("tendermint".to_owned()))
+        );
+
+        // no identifier is allocated for a rejected client, and the light client is never called
+        assert_eq!(host.client_index, 0);
+        assert!(light_client.calls.is_empty());
+    }
+
+    #[test]
+    fn create_client_frozen_at_creation() {
+        let mut host = MockHost::default();
+
+        let mut light_client = MockLightClient {
+            status: Status::Frozen,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            execute(
+                create_client("cometbls").into(),
+                &mut host,
+                &mut light_client,
+                &mut MockApp::default(),
+            )
+            .err(),
+            Some(IbcError::NotActive(
+                ClientId::new("cometbls", 1),
+                Status::Frozen
+            ))
+        );
+
+        assert_eq!(host.client_state(&ClientId::new("cometbls", 1)), None);
+    }
+
+    #[test]
+    fn execute_iteration_bound() {
+        // creating a client takes three steps: Init, Initialize and FetchLcData
+        assert_eq!(
+            execute_with_limit(
+                create_client("cometbls").into(),
+                &mut MockHost::default(),
+                &mut MockLightClient::default(),
+                &mut MockApp::default(),
+                2,
+            )
+            .err(),
+            Some(IbcError::TooManyIterations(2))
+        );
+
+        assert!(execute_with_limit(
+            create_client("cometbls").into(),
+            &mut MockHost::default(),
+            &mut MockLightClient::default(),
+            &mut MockApp::default(),
+            3,
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn execute_answers_queries_in_order() {
+        let mut host = MockHost::default();
+        let mut light_client = MockLightClient::default();
+
+        execute(
+            create_client("cometbls").into(),
+            &mut host,
+            &mut light_client,
+            &mut MockApp::default(),
+        )
+        .unwrap();
+
+        light_client.calls.clear();
+        light_client.latest_height = Height::new(20);
+
+        // the state machine only accepts the responses in the order of its queries
+        let (events, _) = execute(
+            client_state::UpdateClient::Init {
+                client_id: ClientId::new("cometbls", 1),
+                client_msg: b"header".to_vec(),
+            }
+            .into(),
+            &mut host,
+            &mut light_client,
+            &mut MockApp::default(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            light_client.calls,
+            [
+                "status",
+                "verify_client_message",
+                "check_for_misbehaviour",
+                "update_state"
+            ]
+        );
+
+        let [IbcEvent::UpdateClient(event)] = &events[..] else {
+            panic!("expected a single UpdateClient event");
+        };
+
+        assert_eq!(event.consensus_heights, [Height::new(20)]);
+    }
+
+    fn timed_out_packet() -> Packet {
+        Packet {
+            sequence: NonZeroU64::new(1).unwrap(),
+            source_port: PortId::new("port-a").unwrap(),
+            source_channel: ChannelId::new(1),
+            destination_port: PortId::new("port-b").unwrap(),
+            destination_channel: ChannelId::new(2),
+            data: b"data".to_vec().into(),
+            timeout_height: Height::new(5),
+            timeout_timestamp: 0,
+        }
+    }
+
+    fn receipt_path(packet: &Packet) -> String {
+        ReceiptPath {
+            port_id: packet.destination_port.clone(),
+            channel_id: packet.destination_channel.clone(),
+            sequence: packet.sequence,
+        }
+        .to_string()
+    }
+
+    fn commitment_path(packet: &Packet) -> StorePath {
+        CommitmentPath {
+            port_id: packet.source_port.clone(),
+            channel_id: packet.source_channel.clone(),
+            sequence: packet.sequence,
+        }
+        .into()
+    }
+
+    fn version(identifier: &str, features: &[Order]) -> Version {
+        Version {
+            identifier: identifier.to_owned(),
+            features: features.to_vec(),
+        }
+    }
+
+    fn connection_path() -> StorePath {
+        ConnectionPath {
+            connection_id: ConnectionId::new(1),
+        }
+        .into()
+    }
+
+    fn open_connection_end(versions: Vec<Version>) -> ConnectionEnd {
+        ConnectionEnd {
+            client_id: ClientId::new("mock", 1),
+            versions,
+            state: connection::state::State::Open,
+            counterparty: connection::counterparty::Counterparty {
+                client_id: ClientId::new("mock", 1),
+                connection_id: Some(ConnectionId::new(1)),
+                prefix: MerklePrefix {
+                    key_prefix: b"ibc".to_vec().into(),
+                },
+            },
+            delay_period: 0,
+        }
+    }
+
+    /// A host with an open connection `connection-1` with the provided versions.
+    fn host_with_connection(versions: Vec<Version>) -> MockHost {
+        let mut host = MockHost::default();
+
+        host.commit(connection_path(), open_connection_end(versions))
+            .unwrap();
+
+        host
+    }
+
+    /// A host with an open channel and connection for `packet`.
+    fn host_with_channel(packet: &Packet) -> MockHost {
+        let mut host = host_with_connection(vec![version("1", &[Order::Unordered])]);
+
+        host.commit(
+            ChannelEndPath {
+                port_id: packet.source_port.clone(),
+                channel_id: packet.source_channel.clone(),
+            }
+            .into(),
+            Channel {
+                state: channel::state::State::Open,
+                ordering: Order::Unordered,
+                counterparty: channel::counterparty::Counterparty {
+                    port_id: packet.destination_port.clone(),
+                    channel_id: Some(packet.destination_channel.clone()),
+                },
+                connection_hops: vec![ConnectionId::new(1)],
+                version: "ics20-1".to_owned(),
+                upgrade_sequence: 0,
+            },
+        )
+        .unwrap();
+
+        host
+    }
+
+    /// A host with an open channel and connection, and a commitment for `packet`.
+    fn host_with_commitment(packet: &Packet) -> MockHost {
+        let mut host = host_with_channel(packet);
+
+        // sha256 is the identity in the mock host
+        let mut commitment = Vec::new();
+        commitment.extend_from_slice(&packet.timeout_timestamp.to_be_bytes());
+        commitment.extend_from_slice(&packet.timeout_height.revision().to_be_bytes());
+        commitment.extend_from_slice(&packet.timeout_height.height().to_be_bytes());
+        commitment.extend_from_slice(&packet.data);
+
+        host.commit_raw(commitment_path(packet), commitment)
+            .unwrap();
+
+        host
+    }
+
+    /// Run `TimeoutPacket` to completion against `light_client`.
+    fn timeout_packet(
+        host: &mut MockHost,
+        light_client: &mut MockLightClient,
+        app: &mut MockApp,
+        packet: &Packet,
+    ) -> Result<(Vec<IbcEvent>, IbcVmResponse), IbcError> {
+        execute(
+            TimeoutPacket::Init {
+                packet: packet.clone(),
+                proof_unreceived: b"proof".to_vec(),
+                proof_height: Height::new(10),
+            }
+            .into(),
+            host,
+            light_client,
+            app,
+        )
+    }
+
+    #[test]
+    fn timeout_packet_with_absence_proof() {
+        let packet = timed_out_packet();
+        let mut host = host_with_commitment(&packet);
+
+        let mut light_client = MockLightClient {
+            supports_non_membership: true,
+            ..Default::default()
+        };
+        let mut app = MockApp::default();
+
+        let (events, response) =
+            timeout_packet(&mut host, &mut light_client, &mut app, &packet).unwrap();
+
+        assert_eq!(response, IbcVmResponse::Empty);
+        assert!(matches!(&events[..], [IbcEvent::TimeoutPacket(_)]));
+        assert_eq!(host.read_raw(&commitment_path(&packet)), None);
+
+        assert_eq!(
+            light_client.calls,
+            ["timestamp_at_height", "verify_non_membership"]
+        );
+        assert_eq!(app.callbacks, ["on_timeout_packet"]);
+    }
+
+    #[test]
+    fn timeout_packet_received_on_counterparty() {
+        let packet = timed_out_packet();
+        let mut host = host_with_commitment(&packet);
+
+        let mut light_client = MockLightClient {
+            counterparty: [(receipt_path(&packet), vec![1])].into_iter().collect(),
+            supports_non_membership: true,
+            ..Default::default()
+        };
+        let mut app = MockApp::default();
+
+        assert_eq!(
+            timeout_packet(&mut host, &mut light_client, &mut app, &packet).err(),
+            Some(IbcError::NonMembershipVerificationFailure)
+        );
+        assert!(host.read_raw(&commitment_path(&packet)).is_some());
+        assert!(app.callbacks.is_empty());
+    }
+
+    #[test]
+    fn timeout_packet_non_membership_unsupported() {
+        let packet = timed_out_packet();
+        let mut host = host_with_commitment(&packet);
+
+        assert_eq!(
+            timeout_packet(
+                &mut host,
+                &mut MockLightClient::default(),
+                &mut MockApp::default(),
+                &packet
+            )
+            .err(),
+            Some(IbcError::NonMembershipUnsupported)
+        );
+        assert!(host.read_raw(&commitment_path(&packet)).is_some());
+    }
+
+    fn send_packet(packet: &Packet) -> IbcState {
+        IbcState::from(packet::SendPacket::Init {
+            source_port: packet.source_port.clone(),
+            source_channel: packet.source_channel.clone(),
+            timeout_height: packet.timeout_height,
+            timeout_timestamp: packet.timeout_timestamp,
+            data: packet.data.clone().into_vec(),
+        })
+    }
+
+    /// Run `SendPacket` to completion, with the counterparty client at `latest_height` and
+    /// `latest_timestamp`.
+    fn run_send_packet(
+        host: &mut MockHost,
+        packet: &Packet,
+        latest_height: Height,
+        latest_timestamp: u64,
+    ) -> Result<(Vec<IbcEvent>, IbcVmResponse), IbcError> {
+        execute(
+            send_packet(packet),
+            host,
+            &mut MockLightClient {
+                latest_height,
+                timestamp: latest_timestamp,
+                ..Default::default()
+            },
+            &mut MockApp::default(),
+        )
+    }
+
+    /// A packet on a channel with the next send sequence initialized.
+    fn sendable_packet(timeout_height: Height, timeout_timestamp: u64) -> (MockHost, Packet) {
+        let packet = Packet {
+            timeout_height,
+            timeout_timestamp,
+            ..timed_out_packet()
+        };
+
+        let mut host = host_with_channel(&packet);
+
+        host.commit_raw(
+            NextSequenceSendPath {
+                port_id: packet.source_port.clone(),
+                channel_id: packet.source_channel.clone(),
+            }
+            .into(),
+            1_u64.to_be_bytes().to_vec(),
+        )
+        .unwrap();
+
+        (host, packet)
+    }
+
+    #[test]
+    fn send_packet_timeout_timestamp_in_past() {
+        for timeout_timestamp in [50, 100] {
+            let (mut host, packet) = sendable_packet(Height::default(), timeout_timestamp);
+            host.timestamp = 100;
+
+            assert_eq!(
+                run_send_packet(&mut host, &packet, Height::new(10), 0).err(),
+                Some(IbcError::TimeoutInPast),
+                "{timeout_timestamp}"
+            );
+        }
+    }
+
+    #[test]
+    fn send_packet_timeout_timestamp_margin() {
+        let (mut host, packet) = sendable_packet(Height::default(), 110);
+        host.timestamp = 100;
+        host.min_timeout_margin = 10;
+
+        assert_eq!(
+            run_send_packet(&mut host, &packet, Height::new(10), 0).err(),
+            Some(IbcError::TimeoutTooSoon { min_margin: 10 })
+        );
+
+        let (mut host, packet) = sendable_packet(Height::default(), 111);
+        host.timestamp = 100;
+        host.min_timeout_margin = 10;
+
+        assert!(run_send_packet(&mut host, &packet, Height::new(10), 0).is_ok());
+    }
+
+    #[test]
+    fn send_packet_timeout_height_in_past() {
+        for latest_height in [Height::new(5), Height::new(6)] {
+            let (mut host, packet) = sendable_packet(Height::new(5), 0);
+
+            assert_eq!(
+                run_send_packet(&mut host, &packet, latest_height, 0).err(),
+                Some(IbcError::TimeoutInPast),
+                "{latest_height}"
+            );
+        }
+    }
+
+    #[test]
+    fn send_packet_timestamp_only() {
+        let (mut host, packet) = sendable_packet(Height::default(), 1_000);
+        host.timestamp = 100;
+
+        let (events, response) = run_send_packet(&mut host, &packet, Height::new(10), 500).unwrap();
+
+        assert!(matches!(&events[..], [IbcEvent::SendPacket(_)]));
+        assert_eq!(response, IbcVmResponse::SendPacket { sequence: 1 });
+        assert!(host.read_raw(&commitment_path(&packet)).is_some());
+    }
+
+    #[test]
+    fn pick_version_highest_identifier() {
+        let supported = [
+            version("1", &[Order::Unordered, Order::Ordered]),
+            version("2", &[Order::Unordered]),
+            version("10", &[Order::Ordered, Order::Unordered]),
+        ];
+
+        assert_eq!(
+            connection_handshake::pick_version(
+                &supported,
+                &[
+                    version("1", &[Order::Ordered]),
+                    version("10", &[Order::Unordered]),
+                    version("2", &[Order::Unordered]),
+                    version("3", &[Order::Unordered]),
+                ]
+            ),
+            Ok(version("10", &[Order::Unordered]))
+        );
+
+        // the features are intersected
+        assert_eq!(
+            connection_handshake::pick_version(
+                &supported,
+                &[version("1", &[Order::Ordered, Order::Unordered])]
+            ),
+            Ok(version("1", &[Order::Unordered, Order::Ordered]))
+        );
+
+        assert_eq!(
+            connection_handshake::pick_version(&supported, &[version("3", &[Order::Unordered])]),
+            Err(IbcError::NoSupportedVersionFound)
+        );
+    }
+
+    #[test]
+    fn pick_version_empty_features() {
+        assert_eq!(
+            connection_handshake::pick_version(
+                &DEFAULT_IBC_VERSION,
+                &[version("1", &[Order::Unordered]), version("2", &[])]
+            ),
+            Err(IbcError::EmptyVersionFeatures)
+        );
+    }
+
+    #[test]
+    fn pick_version_no_common_features() {
+        assert_eq!(
+            connection_handshake::pick_version(
+                &DEFAULT_IBC_VERSION,
+                &[version("1", &[Order::Ordered])]
+            ),
+            Err(IbcError::NoCommonVersionFeatures("1".to_owned()))
+        );
+    }
+
+    fn channel_open_init(
+        host: &mut MockHost,
+        ordering: Order,
+    ) -> Result<Either<(IbcState, IbcAction), (Vec<IbcEvent>, IbcVmResponse)>, IbcError> {
+        IbcState::from(channel_handshake::ChannelOpenInit::Init {
+            ordering,
+            connection_hops: vec![ConnectionId::new(1)],
+            port_id: PortId::new("port-a").unwrap(),
+            counterparty: channel::counterparty::Counterparty {
+                port_id: PortId::new("port-b").unwrap(),
+                channel_id: None,
+            },
+            version: "ics20-1".to_owned(),
+        })
+        .process(host, &[IbcResponse::Empty])
+    }
+
+    #[test]
+    fn channel_open_init_ordering() {
+        let mut host = host_with_connection(vec![version("1", &[Order::Unordered])]);
+
+        assert!(matches!(
+            channel_open_init(&mut host, Order::Unordered),
+            Ok(Either::Left((_, IbcAction::Query(_))))
+        ));
+
+        assert_eq!(
+            channel_open_init(&mut host, Order::Ordered).err(),
+            Some(IbcError::ChannelOrderingNotSupported(Order::Ordered))
+        );
+    }
+
+    #[test]
+    fn channel_open_init_version_not_negotiated() {
+        let mut host = host_with_connection(vec![
+            version("1", &[Order::Unordered]),
+            version("2", &[Order::Unordered]),
+        ]);
+
+        assert_eq!(
+            channel_open_init(&mut host, Order::Unordered).err(),
+            Some(IbcError::ConnectionVersionNotNegotiated(2))
+        );
+    }
+
+    #[test]
+    fn read_decode_error_context() {
+        let mut host = MockHost::default();
+
+        host.commit_raw(connection_path(), b"not a connection end".to_vec())
+            .unwrap();
+
+        let Some(IbcError::Storage(err)) = channel_open_init(&mut host, Order::Unordered).err()
+        else {
+            panic!("the connection end is not decodable");
+        };
+
+        assert_eq!(err.key, serde_utils::to_hex(connection_path().to_string()));
+        assert_eq!(
+            err.type_name,
+            "unionlabs::ibc::core::connection::connection_end::ConnectionEnd"
+        );
+        assert!(!err.error.is_empty());
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "unable to decode unionlabs::ibc::core::connection::connection_end::ConnectionEnd stored at {}: {}",
+                err.key, err.error
+            )
+        );
+    }
+
+    /// A host supporting `schema_version`, with a store at version 1 containing a json encoded
+    /// open connection end.
+    fn host_with_v1_connection(schema_version: u32) -> MockHost {
+        let mut host = MockHost {
+            schema_version: Some(schema_version),
+            ..Default::default()
+        };
+
+        host.commit_raw(
+            connection_path(),
+            serde_json::to_vec(&open_connection_end(vec![version(
+                "1",
+                &[Order::Unordered],
+            )]))
+            .unwrap(),
+        )
+        .unwrap();
+        host.commit_reserved(SCHEMA_VERSION_KEY, 1_u32.to_be_bytes().to_vec())
+            .unwrap();
+
+        host
+    }
+
+    #[test]
+    fn migrate_connection_end_encoding() {
+        // without the migration, the json encoded connection end is not decodable
+        assert!(matches!(
+            channel_open_init(&mut host_with_v1_connection(1), Order::Unordered),
+            Err(IbcError::Storage(_))
+        ));
+
+        let mut host = host_with_v1_connection(2);
+
+        assert!(matches!(
+            channel_open_init(&mut host, Order::Unordered),
+            Ok(Either::Left((_, IbcAction::Query(_))))
+        ));
+
+        assert_eq!(
+            host.read_decode::<ConnectionEnd>(&connection_path()),
+            Ok(Some(open_connection_end(vec![version(
+                "1",
+                &[Order::Unordered]
+            )])))
+        );
+        assert_eq!(
+            host.read_reserved(SCHEMA_VERSION_KEY),
+            Some(2_u32.to_be_bytes().to_vec())
+        );
+    }
+
+    #[test]
+    fn schema_version_downgrade() {
+        let mut host = host_with_v1_connection(1);
+        host.commit_reserved(SCHEMA_VERSION_KEY, 2_u32.to_be_bytes().to_vec())
+            .unwrap();
+
+        assert_eq!(
+            channel_open_init(&mut host, Order::Unordered).err(),
+            Some(IbcError::UnsupportedSchemaVersion {
+                stored: 2,
+                supported: 1
+            })
+        );
+    }
+}
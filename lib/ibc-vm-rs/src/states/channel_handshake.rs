@@ -1,3 +1,6 @@
+use ibc_classic_spec::{
+    ChannelEndPath, ConnectionPath, NextSequenceAckPath, NextSequenceRecvPath, NextSequenceSendPath,
+};
 use serde::{Deserialize, Serialize};
 use unionlabs::{
     encoding::{EncodeAs, Proto},
@@ -7,22 +10,19 @@ use unionlabs::{
         commitment::merkle_path::MerklePath,
         connection::{self, connection_end::ConnectionEnd},
     },
-    ics24::{
-        ChannelEndPath, ConnectionPath, NextSequenceAckPath, NextSequenceRecvPath,
-        NextSequenceSendPath,
-    },
     id::{ChannelId, ClientId, ConnectionId, PortId},
 };
 
 use crate::{
-    Either, IbcAction, IbcError, IbcEvent, IbcHost, IbcMsg, IbcQuery, IbcResponse, IbcVmResponse,
-    Runnable, Status,
+    events, Either, IbcAction, IbcError, IbcEvent, IbcHost, IbcMsg, IbcQuery, IbcResponse,
+    IbcVmResponse, Runnable, Status,
 };
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
 #[cfg_attr(feature = "schemars", derive(::schemars::JsonSchema))]
 pub enum ChannelOpenInit {
     Init {
+        ordering: Order,
         connection_hops: Vec<ConnectionId>,
         port_id: PortId,
         counterparty: Counterparty,
@@ -31,6 +31,7 @@ pub enum ChannelOpenInit {
 
     StatusFetched {
         client_id: ClientId,
+        ordering: Order,
         connection_hops: Vec<ConnectionId>,
         port_id: PortId,
         counterparty: Counterparty,
@@ -39,6 +40,7 @@ pub enum ChannelOpenInit {
 
     CallbackCalled {
         channel_id: ChannelId,
+        ordering: Order,
         connection_hops: Vec<ConnectionId>,
         port_id: PortId,
         counterparty: Counterparty,
@@ -56,6 +58,7 @@ impl<T: IbcHost> Runnable<T> for ChannelOpenInit {
         let res = match (self, &resp) {
             (
                 ChannelOpenInit::Init {
+                    ordering,
                     connection_hops,
                     port_id,
                     counterparty,
@@ -64,12 +67,12 @@ impl<T: IbcHost> Runnable<T> for ChannelOpenInit {
                 &[IbcResponse::Empty],
             ) => {
                 let connection: ConnectionEnd = host
-                    .read(
+                    .read_decode(
                         &ConnectionPath {
                             connection_id: connection_hops[0].clone(),
                         }
                         .into(),
-                    )
+                    )?
                     .ok_or(IbcError::ConnectionNotFound(connection_hops[0].to_string()))?;
 
                 if connection.state != connection::state::State::Open {
@@ -80,11 +83,14 @@ impl<T: IbcHost> Runnable<T> for ChannelOpenInit {
                     .into());
                 }
 
+                verify_ordering_supported(&connection, ordering)?;
+
                 // TODO(aeryz): check if port_id is a valid addr here?
 
                 Either::Left((
                     ChannelOpenInit::StatusFetched {
                         client_id: connection.client_id.clone(),
+                        ordering,
                         connection_hops,
                         port_id,
                         counterparty,
@@ -96,6 +102,7 @@ impl<T: IbcHost> Runnable<T> for ChannelOpenInit {
             (
                 ChannelOpenInit::StatusFetched {
                     client_id,
+                    ordering,
                     connection_hops,
                     port_id,
                     counterparty,
@@ -112,13 +119,14 @@ impl<T: IbcHost> Runnable<T> for ChannelOpenInit {
                 Either::Left((
                     ChannelOpenInit::CallbackCalled {
                         channel_id: channel_id.clone(),
+                        ordering,
                         connection_hops: connection_hops.clone(),
                         port_id: port_id.clone(),
                         counterparty: counterparty.clone(),
                         version: version.clone(),
                     },
                     IbcMsg::OnChannelOpenInit {
-                        order: Order::Unordered,
+                        order: ordering,
                         connection_hops,
                         port_id,
                         channel_id,
@@ -131,6 +139,7 @@ impl<T: IbcHost> Runnable<T> for ChannelOpenInit {
             (
                 ChannelOpenInit::CallbackCalled {
                     channel_id,
+                    ordering,
                     connection_hops,
                     port_id,
                     counterparty,
@@ -146,7 +155,7 @@ impl<T: IbcHost> Runnable<T> for ChannelOpenInit {
 
                 let channel = Channel {
                     state: channel::state::State::Init,
-                    ordering: Order::Unordered,
+                    ordering,
                     counterparty: counterparty.clone(),
                     connection_hops: connection_hops.clone(),
                     version: version.clone(),
@@ -188,7 +197,7 @@ impl<T: IbcHost> Runnable<T> for ChannelOpenInit {
                 )?;
 
                 Either::Right((
-                    vec![IbcEvent::ChannelOpenInit(ibc_events::ChannelOpenInit {
+                    vec![IbcEvent::ChannelOpenInit(events::ChannelOpenInit {
                         port_id,
                         channel_id,
                         counterparty_port_id: counterparty.port_id,
@@ -208,6 +217,7 @@ impl<T: IbcHost> Runnable<T> for ChannelOpenInit {
 #[cfg_attr(feature = "schemars", derive(::schemars::JsonSchema))]
 pub enum ChannelOpenTry {
     Init {
+        ordering: Order,
         connection_hops: Vec<ConnectionId>,
         port_id: PortId,
         counterparty: channel::counterparty::Counterparty,
@@ -219,6 +229,7 @@ pub enum ChannelOpenTry {
 
     LcQueriesMade {
         client_id: ClientId,
+        ordering: Order,
         connection_hops: Vec<ConnectionId>,
         port_id: PortId,
         counterparty: channel::counterparty::Counterparty,
@@ -228,6 +239,7 @@ pub enum ChannelOpenTry {
 
     CallbackCalled {
         channel_id: ChannelId,
+        ordering: Order,
         connection_hops: Vec<ConnectionId>,
         port_id: PortId,
         counterparty: channel::counterparty::Counterparty,
@@ -245,6 +257,7 @@ impl<T: IbcHost> Runnable<T> for ChannelOpenTry {
         let res = match (self, &resp) {
             (
                 ChannelOpenTry::Init {
+                    ordering,
                     connection_hops,
                     port_id,
                     counterparty,
@@ -256,12 +269,12 @@ impl<T: IbcHost> Runnable<T> for ChannelOpenTry {
                 &[IbcResponse::Empty],
             ) => {
                 let connection: ConnectionEnd = host
-                    .read(
+                    .read_decode(
                         &ConnectionPath {
                             connection_id: connection_hops[0].clone(),
                         }
                         .into(),
-                    )
+                    )?
                     .ok_or(IbcError::ConnectionNotFound(connection_hops[0].to_string()))?;
 
                 if connection.state != connection::state::State::Open {
@@ -272,9 +285,11 @@ impl<T: IbcHost> Runnable<T> for ChannelOpenTry {
                     .into());
                 }
 
+                verify_ordering_supported(&connection, ordering)?;
+
                 let expected_channel = Channel {
                     state: channel::state::State::Init,
-                    ordering: Order::Unordered,
+                    ordering,
                     counterparty: channel::counterparty::Counterparty {
                         port_id: port_id.clone(),
                         channel_id: None,
@@ -289,6 +304,7 @@ impl<T: IbcHost> Runnable<T> for ChannelOpenTry {
                 Either::Left((
                     ChannelOpenTry::LcQueriesMade {
                         client_id: connection.client_id.clone(),
+                        ordering,
                         connection_hops,
                         port_id,
                         counterparty: counterparty.clone(),
@@ -323,6 +339,7 @@ impl<T: IbcHost> Runnable<T> for ChannelOpenTry {
             }
             (
                 ChannelOpenTry::LcQueriesMade {
+                    ordering,
                     connection_hops,
                     port_id,
                     counterparty,
@@ -344,13 +361,14 @@ impl<T: IbcHost> Runnable<T> for ChannelOpenTry {
                 Either::Left((
                     ChannelOpenTry::CallbackCalled {
                         channel_id: channel_id.clone(),
+                        ordering,
                         connection_hops: connection_hops.clone(),
                         port_id: port_id.clone(),
                         counterparty: counterparty.clone(),
                         version: version.clone(),
                     },
                     IbcMsg::OnChannelOpenTry {
-                        order: Order::Unordered,
+                        order: ordering,
                         connection_hops,
                         port_id,
                         channel_id,
@@ -363,6 +381,7 @@ impl<T: IbcHost> Runnable<T> for ChannelOpenTry {
             (
                 ChannelOpenTry::CallbackCalled {
                     channel_id,
+                    ordering,
                     connection_hops,
                     port_id,
                     counterparty,
@@ -378,7 +397,7 @@ impl<T: IbcHost> Runnable<T> for ChannelOpenTry {
 
                 let channel = Channel {
                     state: channel::state::State::Tryopen,
-                    ordering: Order::Unordered,
+                    ordering,
                     counterparty: counterparty.clone(),
                     connection_hops: connection_hops.clone(),
                     version: version.clone(),
@@ -420,7 +439,7 @@ impl<T: IbcHost> Runnable<T> for ChannelOpenTry {
                 )?;
 
                 Either::Right((
-                    vec![IbcEvent::ChannelOpenTry(ibc_events::ChannelOpenTry {
+                    vec![IbcEvent::ChannelOpenTry(events::ChannelOpenTry {
                         port_id,
                         channel_id,
                         counterparty_port_id: counterparty.port_id,
@@ -486,13 +505,13 @@ impl<T: IbcHost> Runnable<T> for ChannelOpenAck {
                 &[IbcResponse::Empty],
             ) => {
                 let channel: Channel = host
-                    .read(
+                    .read_decode(
                         &ChannelEndPath {
                             port_id: port_id.clone(),
                             channel_id: channel_id.clone(),
                         }
                         .into(),
-                    )
+                    )?
                     .ok_or(IbcError::ChannelNotFound(
                         port_id.clone(),
                         channel_id.clone(),
@@ -507,12 +526,12 @@ impl<T: IbcHost> Runnable<T> for ChannelOpenAck {
                 }
 
                 let connection: ConnectionEnd = host
-                    .read(
+                    .read_decode(
                         &ConnectionPath {
                             connection_id: channel.connection_hops[0].clone(),
                         }
                         .into(),
-                    )
+                    )?
                     .ok_or(IbcError::ConnectionNotFound(
                         channel.connection_hops[0].to_string(),
                     ))?;
@@ -527,7 +546,7 @@ impl<T: IbcHost> Runnable<T> for ChannelOpenAck {
 
                 let expected_channel = Channel {
                     state: channel::state::State::Tryopen,
-                    ordering: Order::Unordered,
+                    ordering: channel.ordering,
                     counterparty: channel::counterparty::Counterparty {
                         port_id: port_id.clone(),
                         channel_id: Some(channel_id.clone()),
@@ -625,9 +644,12 @@ impl<T: IbcHost> Runnable<T> for ChannelOpenAck {
                 }
                 .into();
 
-                let mut channel: Channel = host.read(&channel_path).ok_or(
-                    IbcError::ChannelNotFound(port_id.clone(), channel_id.clone()),
-                )?;
+                let mut channel: Channel =
+                    host.read_decode(&channel_path)?
+                        .ok_or(IbcError::ChannelNotFound(
+                            port_id.clone(),
+                            channel_id.clone(),
+                        ))?;
 
                 channel.state = channel::state::State::Open;
                 channel.version = counterparty_version;
@@ -640,7 +662,7 @@ impl<T: IbcHost> Runnable<T> for ChannelOpenAck {
                 host.commit(channel_path, channel)?;
 
                 Either::Right((
-                    vec![IbcEvent::ChannelOpenAck(ibc_events::ChannelOpenAck {
+                    vec![IbcEvent::ChannelOpenAck(events::ChannelOpenAck {
                         port_id,
                         channel_id,
                         counterparty_port_id,
@@ -702,13 +724,13 @@ impl<T: IbcHost> Runnable<T> for ChannelOpenConfirm {
                 &[IbcResponse::Empty],
             ) => {
                 let channel: Channel = host
-                    .read(
+                    .read_decode(
                         &ChannelEndPath {
                             port_id: port_id.clone(),
                             channel_id: channel_id.clone(),
                         }
                         .into(),
-                    )
+                    )?
                     .ok_or(IbcError::ChannelNotFound(
                         port_id.clone(),
                         channel_id.clone(),
@@ -723,12 +745,12 @@ impl<T: IbcHost> Runnable<T> for ChannelOpenConfirm {
                 }
 
                 let connection: ConnectionEnd = host
-                    .read(
+                    .read_decode(
                         &ConnectionPath {
                             connection_id: channel.connection_hops[0].clone(),
                         }
                         .into(),
-                    )
+                    )?
                     .ok_or(IbcError::ConnectionNotFound(
                         channel.connection_hops[0].to_string(),
                     ))?;
@@ -743,7 +765,7 @@ impl<T: IbcHost> Runnable<T> for ChannelOpenConfirm {
 
                 let expected_channel = Channel {
                     state: channel::state::State::Open,
-                    ordering: Order::Unordered,
+                    ordering: channel.ordering,
                     counterparty: channel::counterparty::Counterparty {
                         port_id: port_id.clone(),
                         channel_id: Some(channel_id.clone()),
@@ -836,9 +858,12 @@ impl<T: IbcHost> Runnable<T> for ChannelOpenConfirm {
                 }
                 .into();
 
-                let mut channel: Channel = host.read(&channel_path).ok_or(
-                    IbcError::ChannelNotFound(port_id.clone(), channel_id.clone()),
-                )?;
+                let mut channel: Channel =
+                    host.read_decode(&channel_path)?
+                        .ok_or(IbcError::ChannelNotFound(
+                            port_id.clone(),
+                            channel_id.clone(),
+                        ))?;
 
                 channel.state = channel::state::State::Open;
 
@@ -847,15 +872,13 @@ impl<T: IbcHost> Runnable<T> for ChannelOpenConfirm {
                 host.commit(channel_path, channel)?;
 
                 Either::Right((
-                    vec![IbcEvent::ChannelOpenConfirm(
-                        ibc_events::ChannelOpenConfirm {
-                            port_id,
-                            channel_id,
-                            counterparty_port_id: counterparty.port_id,
-                            counterparty_channel_id: counterparty.channel_id.unwrap(),
-                            connection_id,
-                        },
-                    )],
+                    vec![IbcEvent::ChannelOpenConfirm(events::ChannelOpenConfirm {
+                        port_id,
+                        channel_id,
+                        counterparty_port_id: counterparty.port_id,
+                        counterparty_channel_id: counterparty.channel_id.unwrap(),
+                        connection_id,
+                    })],
                     IbcVmResponse::Empty,
                 ))
             }
@@ -865,3 +888,18 @@ impl<T: IbcHost> Runnable<T> for ChannelOpenConfirm {
         Ok(res)
     }
 }
+
+/// Ensure that the channel ordering is supported by the negotiated version of the connection.
+fn verify_ordering_supported(connection: &ConnectionEnd, ordering: Order) -> Result<(), IbcError> {
+    let [version] = connection.versions.as_slice() else {
+        return Err(IbcError::ConnectionVersionNotNegotiated(
+            connection.versions.len(),
+        ));
+    };
+
+    if !version.features.contains(&ordering) {
+        return Err(IbcError::ChannelOrderingNotSupported(ordering));
+    }
+
+    Ok(())
+}
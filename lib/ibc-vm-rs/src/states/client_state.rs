@@ -1,14 +1,10 @@
-use ibc_events::ClientMisbehaviour;
+use ibc_classic_spec::{ClientConsensusStatePath, ClientStatePath};
 use serde::{Deserialize, Serialize};
-use unionlabs::{
-    ibc::core::client::height::Height,
-    ics24::{ClientConsensusStatePath, ClientStatePath},
-    id::ClientId,
-};
+use unionlabs::{ibc::core::client::height::Height, id::ClientId};
 
 use crate::{
-    Either, IbcAction, IbcError, IbcEvent, IbcHost, IbcMsg, IbcQuery, IbcResponse, IbcVmResponse,
-    Runnable, Status,
+    events, Either, IbcAction, IbcError, IbcEvent, IbcHost, IbcMsg, IbcQuery, IbcResponse,
+    IbcVmResponse, Runnable, Status,
 };
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -103,7 +99,7 @@ impl<T: IbcHost> Runnable<T> for UpdateClient {
                 UpdateClient::UpdatedStateOnMisbehaviour { client_id },
                 &[IbcResponse::UpdateStateOnMisbehaviour],
             ) => Either::Right((
-                vec![IbcEvent::ClientMisbehaviour(ClientMisbehaviour {
+                vec![IbcEvent::ClientMisbehaviour(events::ClientMisbehaviour {
                     client_id,
                     // TODO(aeryz): why????
                     client_type: "TODO(aeryz) why in the hell do we have this here".to_string(),
@@ -143,7 +139,7 @@ impl<T: IbcHost> Runnable<T> for UpdateClient {
                     .collect::<Result<Vec<_>, <T as IbcHost>::Error>>()?;
 
                 Either::Right((
-                    vec![IbcEvent::UpdateClient(ibc_events::UpdateClient {
+                    vec![IbcEvent::UpdateClient(events::UpdateClient {
                         client_id,
                         client_type: "TODO(aeryz): I hate this".to_string(),
                         consensus_heights,
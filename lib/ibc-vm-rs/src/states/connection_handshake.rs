@@ -1,3 +1,4 @@
+use ibc_classic_spec::ConnectionPath;
 use serde::{Deserialize, Serialize};
 use unionlabs::{
     encoding::{EncodeAs, Proto},
@@ -8,13 +9,12 @@ use unionlabs::{
             self, connection_end::ConnectionEnd, counterparty::Counterparty, version::Version,
         },
     },
-    ics24::ConnectionPath,
     id::{ClientId, ConnectionId},
 };
 
 use crate::{
-    Either, IbcAction, IbcError, IbcEvent, IbcHost, IbcQuery, IbcResponse, IbcVmResponse, Runnable,
-    Status, DEFAULT_IBC_VERSION, DEFAULT_MERKLE_PREFIX,
+    events, storage::init_schema_version, Either, IbcAction, IbcError, IbcEvent, IbcHost, IbcQuery,
+    IbcResponse, IbcVmResponse, Runnable, Status, DEFAULT_IBC_VERSION, DEFAULT_MERKLE_PREFIX,
 };
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -23,7 +23,9 @@ pub enum ConnectionOpenInit {
     Init {
         client_id: ClientId,
         counterparty: Counterparty,
-        version: Version,
+        /// The version to use for the connection. If `None`, all of the supported versions are
+        /// proposed to the counterparty.
+        version: Option<Version>,
         delay_period: u64,
     },
 
@@ -52,13 +54,19 @@ impl<T: IbcHost> Runnable<T> for ConnectionOpenInit {
                 },
                 &[IbcResponse::Empty],
             ) => {
-                // supported version
-                verify_version_supported(&DEFAULT_IBC_VERSION, &version)?;
+                let versions = match version {
+                    Some(version) => {
+                        verify_version_supported(&DEFAULT_IBC_VERSION, &version)?;
+                        vec![version]
+                    }
+                    None => DEFAULT_IBC_VERSION.clone(),
+                };
+
                 Either::Left((
                     ConnectionOpenInit::CheckStatus {
                         client_id: client_id.clone(),
                         counterparty,
-                        versions: DEFAULT_IBC_VERSION.clone(),
+                        versions,
                         delay_period,
                     },
                     (client_id, vec![IbcQuery::Status]).into(),
@@ -97,6 +105,8 @@ impl<T: IbcHost> Runnable<T> for ConnectionOpenInit {
                     delay_period,
                 };
 
+                init_schema_version(host)?;
+
                 host.commit(
                     ConnectionPath {
                         connection_id: connection_id.clone(),
@@ -106,13 +116,11 @@ impl<T: IbcHost> Runnable<T> for ConnectionOpenInit {
                 )?;
 
                 Either::Right((
-                    vec![IbcEvent::ConnectionOpenInit(
-                        ibc_events::ConnectionOpenInit {
-                            connection_id,
-                            client_id,
-                            counterparty_client_id,
-                        },
-                    )],
+                    vec![IbcEvent::ConnectionOpenInit(events::ConnectionOpenInit {
+                        connection_id,
+                        client_id,
+                        counterparty_client_id,
+                    })],
                     IbcVmResponse::Empty,
                 ))
             }
@@ -166,6 +174,56 @@ fn verify_proposed_version(version: &Version, proposed_version: &Version) -> Res
     Ok(())
 }
 
+/// Pick the version to use for a connection out of the versions proposed by the counterparty.
+///
+/// Out of the mutually supported version identifiers, the highest one is picked, with the
+/// features being the intersection of the supported and the proposed features.
+pub fn pick_version(
+    supported_versions: &[Version],
+    counterparty_versions: &[Version],
+) -> Result<Version, IbcError> {
+    // we don't allow nil feature
+    if counterparty_versions.iter().any(|v| v.features.is_empty()) {
+        return Err(IbcError::EmptyVersionFeatures);
+    }
+
+    let (supported_version, counterparty_version) = counterparty_versions
+        .iter()
+        .filter_map(|counterparty_version| {
+            find_supported_version(counterparty_version, supported_versions)
+                .map(|supported_version| (supported_version, counterparty_version))
+        })
+        .max_by(|(a, _), (b, _)| compare_version_identifiers(&a.identifier, &b.identifier))
+        .ok_or(IbcError::NoSupportedVersionFound)?;
+
+    let features = supported_version
+        .features
+        .iter()
+        .filter(|feat| counterparty_version.features.contains(feat))
+        .copied()
+        .collect::<Vec<_>>();
+
+    if features.is_empty() {
+        return Err(IbcError::NoCommonVersionFeatures(
+            supported_version.identifier.clone(),
+        ));
+    }
+
+    Ok(Version {
+        identifier: supported_version.identifier.clone(),
+        features,
+    })
+}
+
+/// Version identifiers are compared numerically if possible, falling back to a lexicographical
+/// comparison otherwise.
+fn compare_version_identifiers(a: &str, b: &str) -> core::cmp::Ordering {
+    match (a.parse::<u64>(), b.parse::<u64>()) {
+        (Ok(a), Ok(b)) => a.cmp(&b),
+        _ => a.cmp(b),
+    }
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone)]
 #[cfg_attr(feature = "schemars", derive(::schemars::JsonSchema))]
 pub enum ConnectionOpenTry {
@@ -181,6 +239,7 @@ pub enum ConnectionOpenTry {
     ConnectionStateVerified {
         client_id: ClientId,
         counterparty: Counterparty,
+        version: Version,
         delay_period: u64,
     },
 }
@@ -204,9 +263,11 @@ impl<T: IbcHost> Runnable<T> for ConnectionOpenTry {
                 },
                 &[IbcResponse::Empty],
             ) => {
+                let version = pick_version(&DEFAULT_IBC_VERSION, &counterparty_versions)?;
+
                 let expected_counterparty = ConnectionEnd {
                     client_id: counterparty.client_id.clone(),
-                    versions: counterparty_versions.clone(),
+                    versions: counterparty_versions,
                     state: connection::state::State::Init,
                     counterparty: Counterparty {
                         client_id: client_id.clone(),
@@ -222,6 +283,7 @@ impl<T: IbcHost> Runnable<T> for ConnectionOpenTry {
                     ConnectionOpenTry::ConnectionStateVerified {
                         client_id: client_id.clone(),
                         counterparty,
+                        version,
                         delay_period,
                     },
                     (
@@ -234,7 +296,10 @@ impl<T: IbcHost> Runnable<T> for ConnectionOpenTry {
                             path: MerklePath {
                                 key_path: vec![
                                     "ibc".to_string(),
-                                    format!("connections/{}", counterparty_connection_id.unwrap()),
+                                    format!(
+                                        "connections/{:#}",
+                                        counterparty_connection_id.unwrap()
+                                    ),
                                 ],
                             },
                             // TODO(aeryz): generic over the encoding
@@ -248,6 +313,7 @@ impl<T: IbcHost> Runnable<T> for ConnectionOpenTry {
                 ConnectionOpenTry::ConnectionStateVerified {
                     client_id,
                     counterparty,
+                    version,
                     delay_period,
                 },
                 &[IbcResponse::VerifyMembership { valid }],
@@ -258,8 +324,7 @@ impl<T: IbcHost> Runnable<T> for ConnectionOpenTry {
                 let connection_id = host.next_connection_identifier()?;
                 let end = ConnectionEnd {
                     client_id: client_id.clone(),
-                    // we only support the default ibc version with unordered channels
-                    versions: DEFAULT_IBC_VERSION.clone(),
+                    versions: vec![version],
                     state: connection::state::State::Tryopen,
                     counterparty: counterparty.clone(),
                     delay_period,
@@ -274,7 +339,7 @@ impl<T: IbcHost> Runnable<T> for ConnectionOpenTry {
                     end,
                 )?;
                 Either::Right((
-                    vec![IbcEvent::ConnectionOpenTry(ibc_events::ConnectionOpenTry {
+                    vec![IbcEvent::ConnectionOpenTry(events::ConnectionOpenTry {
                         connection_id,
                         client_id,
                         counterparty_client_id: counterparty.client_id,
@@ -327,13 +392,13 @@ impl<T: IbcHost> Runnable<T> for ConnectionOpenAck {
                 },
                 &[IbcResponse::Empty],
             ) => {
-                let connection: ConnectionEnd = host
-                    .read(
+                let mut connection: ConnectionEnd = host
+                    .read_decode(
                         &ConnectionPath {
                             connection_id: ConnectionId::from_str_prefixed(&connection_id).unwrap(),
                         }
                         .into(),
-                    )
+                    )?
                     .ok_or(IbcError::ConnectionNotFound(connection_id.clone()))?;
 
                 if connection.state != connection::state::State::Init {
@@ -346,11 +411,15 @@ impl<T: IbcHost> Runnable<T> for ConnectionOpenAck {
 
                 verify_version_supported(&connection.versions, &version)?;
 
+                // the version picked by the counterparty is the only version of the connection from
+                // now on
+                connection.versions = vec![version];
+
                 let client_id = connection.client_id.clone();
 
                 let expected_counterparty = ConnectionEnd {
                     client_id: connection.counterparty.client_id.clone(),
-                    versions: DEFAULT_IBC_VERSION.clone(),
+                    versions: connection.versions.clone(),
                     state: connection::state::State::Tryopen,
                     counterparty: Counterparty {
                         client_id: client_id.clone(),
@@ -416,7 +485,7 @@ impl<T: IbcHost> Runnable<T> for ConnectionOpenAck {
                 )?;
 
                 Either::Right((
-                    vec![IbcEvent::ConnectionOpenAck(ibc_events::ConnectionOpenAck {
+                    vec![IbcEvent::ConnectionOpenAck(events::ConnectionOpenAck {
                         connection_id: ConnectionId::from_str_prefixed(&connection_id).unwrap(),
                         client_id,
                         counterparty_client_id,
@@ -468,12 +537,12 @@ impl<T: IbcHost> Runnable<T> for ConnectionOpenConfirm {
                 &[IbcResponse::Empty],
             ) => {
                 let connection: ConnectionEnd = host
-                    .read(
+                    .read_decode(
                         &ConnectionPath {
                             connection_id: ConnectionId::from_str_prefixed(&connection_id).unwrap(),
                         }
                         .into(),
-                    )
+                    )?
                     .ok_or(IbcError::ConnectionNotFound(connection_id.clone()))?;
 
                 if connection.state != connection::state::State::Tryopen {
@@ -488,7 +557,7 @@ impl<T: IbcHost> Runnable<T> for ConnectionOpenConfirm {
 
                 let expected_counterparty = ConnectionEnd {
                     client_id: connection.counterparty.client_id.clone(),
-                    versions: DEFAULT_IBC_VERSION.clone(),
+                    versions: connection.versions.clone(),
                     state: connection::state::State::Open,
                     counterparty: Counterparty {
                         client_id: client_id.clone(),
@@ -519,7 +588,7 @@ impl<T: IbcHost> Runnable<T> for ConnectionOpenConfirm {
                             path: MerklePath {
                                 key_path: vec![
                                     "ibc".to_string(),
-                                    format!("connections/{counterparty_connection_id}"),
+                                    format!("connections/{counterparty_connection_id:#}"),
                                 ],
                             },
                             // TODO(aeryz): generic encoding
@@ -556,7 +625,7 @@ impl<T: IbcHost> Runnable<T> for ConnectionOpenConfirm {
 
                 Either::Right((
                     vec![IbcEvent::ConnectionOpenConfirm(
-                        ibc_events::ConnectionOpenConfirm {
+                        events::ConnectionOpenConfirm {
                             connection_id: ConnectionId::from_str_prefixed(&connection_id).unwrap(),
                             client_id,
                             counterparty_client_id,
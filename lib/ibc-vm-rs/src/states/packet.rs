@@ -1,3 +1,7 @@
+use ibc_classic_spec::{
+    AcknowledgementPath, ChannelEndPath, CommitmentPath, ConnectionPath, NextSequenceSendPath,
+    ReceiptPath,
+};
 use serde::{Deserialize, Serialize};
 use unionlabs::{
     ibc::core::{
@@ -6,14 +10,13 @@ use unionlabs::{
         commitment::merkle_path::MerklePath,
         connection::{self, connection_end::ConnectionEnd},
     },
-    ics24::{
-        AcknowledgementPath, ChannelEndPath, CommitmentPath, ConnectionPath, NextSequenceSendPath,
-        ReceiptPath,
-    },
     id::{ChannelId, ClientId, ConnectionId, PortId},
 };
 
 use crate::{
+    commitment::{acknowledgement_commitment, packet_commitment},
+    events,
+    storage::StorageError,
     Either, IbcAction, IbcError, IbcEvent, IbcHost, IbcMsg, IbcQuery, IbcResponse, IbcVmResponse,
     Runnable, Status,
 };
@@ -54,13 +57,13 @@ impl<T: IbcHost> Runnable<T> for RecvPacket {
                 &[IbcResponse::Empty],
             ) => {
                 let channel: Channel = host
-                    .read(
+                    .read_decode(
                         &ChannelEndPath {
                             port_id: packet.destination_port.clone(),
                             channel_id: packet.destination_channel.clone(),
                         }
                         .into(),
-                    )
+                    )?
                     .ok_or(IbcError::ChannelNotFound(
                         packet.destination_port.clone(),
                         packet.destination_channel.clone(),
@@ -91,12 +94,12 @@ impl<T: IbcHost> Runnable<T> for RecvPacket {
                 }
 
                 let connection: ConnectionEnd = host
-                    .read(
+                    .read_decode(
                         &ConnectionPath {
                             connection_id: channel.connection_hops[0].clone(),
                         }
                         .into(),
-                    )
+                    )?
                     .ok_or(IbcError::ConnectionNotFound(
                         channel.connection_hops[0].to_string(),
                     ))?;
@@ -136,7 +139,7 @@ impl<T: IbcHost> Runnable<T> for RecvPacket {
                     .into(),
                 ) {
                     Some(_) => Either::Right((
-                        vec![IbcEvent::RecvPacket(ibc_events::RecvPacket {
+                        vec![IbcEvent::RecvPacket(events::RecvPacket {
                             packet_data_hex: packet.data,
                             packet_timeout_height: packet.timeout_height,
                             packet_timeout_timestamp: packet.timeout_timestamp,
@@ -152,7 +155,7 @@ impl<T: IbcHost> Runnable<T> for RecvPacket {
                     )),
                     None => {
                         // TODO(aeryz): known size can be optimized
-                        let packet_commitment = packet_commitment(host, &packet);
+                        let packet_commitment = packet_commitment(host, &packet)?;
 
                         Either::Left((
                             RecvPacket::MembershipVerified {
@@ -177,7 +180,7 @@ impl<T: IbcHost> Runnable<T> for RecvPacket {
                                             .to_string(),
                                         ],
                                     },
-                                    value: host.sha256(packet_commitment),
+                                    value: packet_commitment,
                                 }],
                             )
                                 .into(),
@@ -218,7 +221,7 @@ impl<T: IbcHost> Runnable<T> for RecvPacket {
                     vec![1],
                 )?;
 
-                let mut events = vec![IbcEvent::RecvPacket(ibc_events::RecvPacket {
+                let mut events = vec![IbcEvent::RecvPacket(events::RecvPacket {
                     packet_data_hex: packet.data.clone(),
                     packet_timeout_height: packet.timeout_height,
                     packet_timeout_timestamp: packet.timeout_timestamp,
@@ -254,7 +257,7 @@ pub fn write_acknowledgement<T: IbcHost>(
     channel: &Channel,
     packet: Packet,
     ack: Vec<u8>,
-) -> Result<ibc_events::WriteAcknowledgement, T::Error> {
+) -> Result<events::WriteAcknowledgement, T::Error> {
     let ack_key = AcknowledgementPath {
         port_id: packet.destination_port.clone(),
         channel_id: packet.destination_channel.clone(),
@@ -269,9 +272,9 @@ pub fn write_acknowledgement<T: IbcHost>(
         return Err(IbcError::EmptyAcknowledgement.into());
     }
 
-    host.commit_raw(ack_key, host.sha256(ack.clone()))?;
+    host.commit_raw(ack_key, acknowledgement_commitment(host, &ack)?)?;
 
-    Ok(ibc_events::WriteAcknowledgement {
+    Ok(events::WriteAcknowledgement {
         packet_data_hex: packet.data,
         packet_timeout_height: packet.timeout_height,
         packet_timeout_timestamp: packet.timeout_timestamp,
@@ -343,14 +346,29 @@ impl<T: IbcHost> Runnable<T> for SendPacket {
                     return Err(IbcError::ZeroTimeout.into());
                 }
 
+                // a zero timestamp means the packet only times out by height
+                if timeout_timestamp != 0 {
+                    let current_timestamp = host.current_timestamp();
+
+                    if timeout_timestamp <= current_timestamp {
+                        return Err(IbcError::TimeoutInPast.into());
+                    }
+
+                    let min_margin = host.min_timeout_margin_nanos();
+
+                    if timeout_timestamp <= current_timestamp.saturating_add(min_margin) {
+                        return Err(IbcError::TimeoutTooSoon { min_margin }.into());
+                    }
+                }
+
                 let channel: Channel = host
-                    .read(
+                    .read_decode(
                         &ChannelEndPath {
                             port_id: source_port.clone(),
                             channel_id: source_channel.clone(),
                         }
                         .into(),
-                    )
+                    )?
                     .ok_or(IbcError::ChannelNotFound(
                         source_port.clone(),
                         source_channel.clone(),
@@ -365,12 +383,12 @@ impl<T: IbcHost> Runnable<T> for SendPacket {
                 }
 
                 let connection: ConnectionEnd = host
-                    .read(
+                    .read_decode(
                         &ConnectionPath {
                             connection_id: channel.connection_hops[0].clone(),
                         }
                         .into(),
-                    )
+                    )?
                     .ok_or(IbcError::ConnectionNotFound(
                         channel.connection_hops[0].to_string(),
                     ))?;
@@ -419,6 +437,13 @@ impl<T: IbcHost> Runnable<T> for SendPacket {
                 if status != Status::Active {
                     return Err(IbcError::NotActive(client_id, status).into());
                 }
+
+                // the counterparty is at least at the latest height of the client, so the packet
+                // could never be received before timing out
+                if timeout_height != Default::default() && height >= timeout_height {
+                    return Err(IbcError::TimeoutInPast.into());
+                }
+
                 Either::Left((
                     SendPacket::TimestampFetched {
                         height,
@@ -436,7 +461,7 @@ impl<T: IbcHost> Runnable<T> for SendPacket {
             }
             (
                 SendPacket::TimestampFetched {
-                    height,
+                    height: _,
                     source_port,
                     source_channel,
                     timeout_height,
@@ -449,10 +474,6 @@ impl<T: IbcHost> Runnable<T> for SendPacket {
                 &[IbcResponse::TimestampAtHeight { timestamp }],
             ) => {
                 // TODO(aeryz): if the timestamp is not specified, we don't need to fetch it. could be a nice optimization.
-                if timeout_height != Default::default() && height >= timeout_height {
-                    return Err(IbcError::TimedOutPacket.into());
-                }
-
                 if timeout_timestamp != 0 && timestamp >= timeout_timestamp {
                     return Err(IbcError::TimedOutPacket.into());
                 }
@@ -464,7 +485,14 @@ impl<T: IbcHost> Runnable<T> for SendPacket {
                 .into();
 
                 let sequence =
-                    u64::from_be_bytes(host.read_raw(&sequence_path).unwrap().try_into().unwrap());
+                    <[u8; 8]>::try_from(host.read_raw(&sequence_path).unwrap().as_slice())
+                        .map(u64::from_be_bytes)
+                        .map_err(|err| {
+                            IbcError::Storage(StorageError::new::<u64>(
+                                sequence_path.to_string(),
+                                err,
+                            ))
+                        })?;
 
                 let packet = Packet {
                     sequence: sequence.try_into().unwrap(),
@@ -481,7 +509,7 @@ impl<T: IbcHost> Runnable<T> for SendPacket {
                     sequence_path,
                     sequence.checked_add(1).unwrap().to_be_bytes().to_vec(),
                 )?;
-                let commitment = packet_commitment(host, &packet);
+                let commitment = packet_commitment(host, &packet)?;
                 host.commit_raw(
                     CommitmentPath {
                         port_id: packet.source_port.clone(),
@@ -489,11 +517,11 @@ impl<T: IbcHost> Runnable<T> for SendPacket {
                         sequence: packet.sequence,
                     }
                     .into(),
-                    host.sha256(commitment),
+                    commitment,
                 )?;
 
                 Either::Right((
-                    vec![IbcEvent::SendPacket(ibc_events::SendPacket {
+                    vec![IbcEvent::SendPacket(events::SendPacket {
                         packet_data_hex: packet.data,
                         packet_timeout_height: timeout_height,
                         packet_timeout_timestamp: timeout_timestamp,
@@ -516,15 +544,6 @@ impl<T: IbcHost> Runnable<T> for SendPacket {
     }
 }
 
-fn packet_commitment<T: IbcHost>(host: &mut T, packet: &Packet) -> Vec<u8> {
-    let mut packet_commitment = Vec::new();
-    packet_commitment.extend_from_slice(packet.timeout_timestamp.to_be_bytes().as_slice());
-    packet_commitment.extend_from_slice(packet.timeout_height.revision().to_be_bytes().as_slice());
-    packet_commitment.extend_from_slice(packet.timeout_height.height().to_be_bytes().as_slice());
-    packet_commitment.extend_from_slice(host.sha256(packet.data.clone().into_vec()).as_slice());
-    packet_commitment
-}
-
 #[derive(Serialize, Deserialize, Debug)]
 pub enum Acknowledgement {
     Init {
@@ -564,13 +583,13 @@ impl<T: IbcHost> Runnable<T> for Acknowledgement {
                 &[IbcResponse::Empty],
             ) => {
                 let channel: Channel = host
-                    .read(
+                    .read_decode(
                         &ChannelEndPath {
                             port_id: packet.source_port.clone(),
                             channel_id: packet.source_channel.clone(),
                         }
                         .into(),
-                    )
+                    )?
                     .ok_or(IbcError::ChannelNotFound(
                         packet.source_port.clone(),
                         packet.source_channel.clone(),
@@ -602,12 +621,12 @@ impl<T: IbcHost> Runnable<T> for Acknowledgement {
                 }
 
                 let connection: ConnectionEnd = host
-                    .read(
+                    .read_decode(
                         &ConnectionPath {
                             connection_id: channel.connection_hops[0].clone(),
                         }
                         .into(),
-                    )
+                    )?
                     .ok_or(IbcError::ConnectionNotFound(
                         channel.connection_hops[0].to_string(),
                     ))?;
@@ -629,7 +648,7 @@ impl<T: IbcHost> Runnable<T> for Acknowledgement {
                     .into(),
                 ) else {
                     return Ok(Either::Right((
-                        vec![IbcEvent::AcknowledgePacket(ibc_events::AcknowledgePacket {
+                        vec![IbcEvent::AcknowledgePacket(events::AcknowledgePacket {
                             packet_timeout_height: packet.timeout_height,
                             packet_timeout_timestamp: packet.timeout_timestamp,
                             packet_sequence: packet.sequence,
@@ -644,8 +663,7 @@ impl<T: IbcHost> Runnable<T> for Acknowledgement {
                     )));
                 };
 
-                let packet_commitment = packet_commitment(host, &packet);
-                let packet_commitment = host.sha256(packet_commitment);
+                let packet_commitment = packet_commitment(host, &packet)?;
                 if commitment != packet_commitment {
                     return Err(
                         IbcError::PacketCommitmentMismatch(commitment, packet_commitment).into(),
@@ -676,7 +694,7 @@ impl<T: IbcHost> Runnable<T> for Acknowledgement {
                                     .to_string(),
                                 ],
                             },
-                            value: host.sha256(ack),
+                            value: acknowledgement_commitment(host, &ack)?,
                         }],
                     )
                         .into(),
@@ -723,7 +741,7 @@ impl<T: IbcHost> Runnable<T> for Acknowledgement {
                 )?;
 
                 Either::Right((
-                    vec![IbcEvent::AcknowledgePacket(ibc_events::AcknowledgePacket {
+                    vec![IbcEvent::AcknowledgePacket(events::AcknowledgePacket {
                         packet_timeout_height: packet.timeout_height,
                         packet_timeout_timestamp: packet.timeout_timestamp,
                         packet_sequence: packet.sequence,
@@ -743,3 +761,231 @@ impl<T: IbcHost> Runnable<T> for Acknowledgement {
         Ok(res)
     }
 }
+
+#[derive(Serialize, Deserialize, Debug)]
+pub enum TimeoutPacket {
+    Init {
+        packet: Packet,
+        proof_unreceived: Vec<u8>,
+        proof_height: Height,
+    },
+
+    NonMembershipVerified {
+        packet: Packet,
+        connection_id: ConnectionId,
+        proof_height: Height,
+    },
+
+    CallbackCalled {
+        packet: Packet,
+        connection_id: ConnectionId,
+    },
+}
+
+impl<T: IbcHost> Runnable<T> for TimeoutPacket {
+    fn process(
+        self,
+        host: &mut T,
+        resp: &[IbcResponse],
+    ) -> Result<Either<(Self, IbcAction), (Vec<IbcEvent>, IbcVmResponse)>, <T as IbcHost>::Error>
+    {
+        let res = match (self, &resp) {
+            (
+                TimeoutPacket::Init {
+                    packet,
+                    proof_unreceived,
+                    proof_height,
+                },
+                &[IbcResponse::Empty],
+            ) => {
+                let channel: Channel = host
+                    .read_decode(
+                        &ChannelEndPath {
+                            port_id: packet.source_port.clone(),
+                            channel_id: packet.source_channel.clone(),
+                        }
+                        .into(),
+                    )?
+                    .ok_or(IbcError::ChannelNotFound(
+                        packet.source_port.clone(),
+                        packet.source_channel.clone(),
+                    ))?;
+
+                if channel.state != channel::state::State::Open {
+                    return Err(IbcError::IncorrectChannelState(
+                        channel.state,
+                        channel::state::State::Open,
+                    )
+                    .into());
+                }
+
+                if packet.destination_port != channel.counterparty.port_id {
+                    return Err(IbcError::DestinationPortMismatch(
+                        packet.destination_port,
+                        channel.counterparty.port_id,
+                    )
+                    .into());
+                }
+
+                if Some(&packet.destination_channel) != channel.counterparty.channel_id.as_ref() {
+                    return Err(IbcError::DestinationChannelMismatch(
+                        packet.destination_channel,
+                        channel.counterparty.channel_id.unwrap(),
+                    )
+                    .into());
+                }
+
+                let connection: ConnectionEnd = host
+                    .read_decode(
+                        &ConnectionPath {
+                            connection_id: channel.connection_hops[0].clone(),
+                        }
+                        .into(),
+                    )?
+                    .ok_or(IbcError::ConnectionNotFound(
+                        channel.connection_hops[0].to_string(),
+                    ))?;
+
+                if connection.state != connection::state::State::Open {
+                    return Err(IbcError::IncorrectConnectionState(
+                        connection.state,
+                        connection::state::State::Open,
+                    )
+                    .into());
+                }
+
+                let Some(commitment) = host.read_raw(
+                    &CommitmentPath {
+                        port_id: packet.source_port.clone(),
+                        channel_id: packet.source_channel.clone(),
+                        sequence: packet.sequence,
+                    }
+                    .into(),
+                ) else {
+                    // the packet was already acknowledged or timed out
+                    return Ok(Either::Right((
+                        vec![timeout_packet_event(
+                            packet,
+                            channel.connection_hops[0].clone(),
+                        )],
+                        IbcVmResponse::Empty,
+                    )));
+                };
+
+                let packet_commitment = packet_commitment(host, &packet)?;
+                if commitment != packet_commitment {
+                    return Err(
+                        IbcError::PacketCommitmentMismatch(commitment, packet_commitment).into(),
+                    );
+                }
+
+                Either::Left((
+                    TimeoutPacket::NonMembershipVerified {
+                        packet: packet.clone(),
+                        connection_id: channel.connection_hops[0].clone(),
+                        proof_height,
+                    },
+                    (
+                        connection.client_id,
+                        vec![
+                            IbcQuery::TimestampAtHeight(proof_height),
+                            IbcQuery::VerifyNonMembership {
+                                height: proof_height,
+                                delay_time_period: 0,
+                                delay_block_period: 0,
+                                proof: proof_unreceived,
+                                path: MerklePath {
+                                    key_path: vec![
+                                        "ibc".into(),
+                                        ReceiptPath {
+                                            port_id: packet.destination_port,
+                                            channel_id: packet.destination_channel,
+                                            sequence: packet.sequence,
+                                        }
+                                        .to_string(),
+                                    ],
+                                },
+                            },
+                        ],
+                    )
+                        .into(),
+                ))
+            }
+            (
+                TimeoutPacket::NonMembershipVerified { .. },
+                &[IbcResponse::TimestampAtHeight { .. }, IbcResponse::NonMembershipUnsupported],
+            ) => return Err(IbcError::NonMembershipUnsupported.into()),
+            (
+                TimeoutPacket::NonMembershipVerified {
+                    packet,
+                    connection_id,
+                    proof_height,
+                },
+                &[IbcResponse::TimestampAtHeight { timestamp }, IbcResponse::VerifyNonMembership { valid }],
+            ) => {
+                let height_timed_out = packet.timeout_height != Default::default()
+                    && proof_height >= packet.timeout_height;
+                let timestamp_timed_out =
+                    packet.timeout_timestamp != 0 && *timestamp >= packet.timeout_timestamp;
+
+                if !height_timed_out && !timestamp_timed_out {
+                    return Err(IbcError::PacketNotTimedOut(proof_height, *timestamp).into());
+                }
+
+                if !valid {
+                    return Err(IbcError::NonMembershipVerificationFailure.into());
+                }
+
+                Either::Left((
+                    TimeoutPacket::CallbackCalled {
+                        packet: packet.clone(),
+                        connection_id,
+                    },
+                    IbcMsg::OnTimeoutPacket { packet }.into(),
+                ))
+            }
+            (
+                TimeoutPacket::CallbackCalled {
+                    packet,
+                    connection_id,
+                },
+                &[IbcResponse::OnTimeoutPacket { err }],
+            ) => {
+                if let Some(err) = err {
+                    return Err(IbcError::IbcAppCallbackFailed(err.clone()).into());
+                }
+
+                host.delete(
+                    &CommitmentPath {
+                        port_id: packet.source_port.clone(),
+                        channel_id: packet.source_channel.clone(),
+                        sequence: packet.sequence,
+                    }
+                    .into(),
+                )?;
+
+                Either::Right((
+                    vec![timeout_packet_event(packet, connection_id)],
+                    IbcVmResponse::Empty,
+                ))
+            }
+            _ => return Err(IbcError::UnexpectedAction.into()),
+        };
+
+        Ok(res)
+    }
+}
+
+fn timeout_packet_event(packet: Packet, connection_id: ConnectionId) -> IbcEvent {
+    IbcEvent::TimeoutPacket(events::TimeoutPacket {
+        packet_timeout_height: packet.timeout_height,
+        packet_timeout_timestamp: packet.timeout_timestamp,
+        packet_sequence: packet.sequence,
+        packet_src_port: packet.source_port,
+        packet_src_channel: packet.source_channel,
+        packet_dst_port: packet.destination_port,
+        packet_dst_channel: packet.destination_channel,
+        packet_channel_ordering: Order::Unordered,
+        connection_id,
+    })
+}
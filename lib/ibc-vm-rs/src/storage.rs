@@ -0,0 +1,145 @@
+//! Decoding and versioning of the values stored by an [`IbcHost`].
+//!
+//! The values read by the state machines are decoded with [`IbcHost::read_decode`], which fails
+//! with a [`StorageError`] describing the key and type of the value if it can't be decoded.
+//!
+//! The encoding of the stored values is versioned with [`IbcHost::schema_version`]. The version is
+//! committed under [`SCHEMA_VERSION_KEY`] when the first client or connection is created, and
+//! checked before every step of a state machine: if the store was written with a lower version
+//! than the one supported by the host, the [`Migration`]s registered by the host are run with
+//! [`migrate`] before anything is read. Stores written before the schema version was introduced
+//! are at version 1.
+
+use core::{any::type_name, fmt::Debug};
+
+use crate::{IbcError, IbcHost};
+
+/// The reserved key the schema version of the store is committed under, as a big endian `u32`.
+///
+/// This is not an ics24 path, so it never collides with the values committed by the state
+/// machines.
+pub const SCHEMA_VERSION_KEY: &str = "ibc-vm/schemaVersion";
+
+/// The schema version of stores that don't have one committed.
+pub const INITIAL_SCHEMA_VERSION: u32 = 1;
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq)]
+#[error("unable to decode {type_name} stored at {key}: {error}")]
+pub struct StorageError {
+    /// The hex encoded key the value is stored at.
+    pub key: String,
+    /// The type the value was decoded as.
+    pub type_name: &'static str,
+    /// The [`Debug`] representation of the decoding error.
+    pub error: String,
+}
+
+impl StorageError {
+    pub fn new<T>(key: impl AsRef<[u8]>, error: impl Debug) -> Self {
+        Self {
+            key: serde_utils::to_hex(key),
+            type_name: type_name::<T>(),
+            error: format!("{error:?}"),
+        }
+    }
+}
+
+/// Upgrades the stored values of a type from the encoding of schema version `to - 1` to the
+/// encoding of version `to`.
+pub struct Migration<H: IbcHost> {
+    pub to: u32,
+    pub type_name: &'static str,
+    pub migrate: fn(&mut H) -> Result<(), H::Error>,
+}
+
+/// The migrations registered by a host, see [`IbcHost::migrations`].
+pub struct Migrations<H: IbcHost>(Vec<Migration<H>>);
+
+impl<H: IbcHost> Default for Migrations<H> {
+    fn default() -> Self {
+        Self(vec![])
+    }
+}
+
+impl<H: IbcHost> Migrations<H> {
+    /// Register `migrate` to upgrade the stored values of type `T` to the encoding of schema
+    /// version `to`. Migrations to the same version are run in the order they are registered in.
+    #[must_use]
+    pub fn register<T>(mut self, to: u32, migrate: fn(&mut H) -> Result<(), H::Error>) -> Self {
+        self.0.push(Migration {
+            to,
+            type_name: type_name::<T>(),
+            migrate,
+        });
+        self
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Migration<H>> {
+        self.0.iter()
+    }
+}
+
+/// The schema version committed in the store of `host`, or [`INITIAL_SCHEMA_VERSION`] if there is
+/// none.
+pub fn stored_schema_version<H: IbcHost>(host: &H) -> Result<u32, H::Error> {
+    let Some(raw) = host.read_reserved(SCHEMA_VERSION_KEY) else {
+        return Ok(INITIAL_SCHEMA_VERSION);
+    };
+
+    <[u8; 4]>::try_from(raw.as_slice())
+        .map(u32::from_be_bytes)
+        .map_err(|err| IbcError::Storage(StorageError::new::<u32>(SCHEMA_VERSION_KEY, err)).into())
+}
+
+/// Upgrade the store of `host` from schema version `from` to `to` by running the migrations
+/// registered for every version in between, in order, and commit `to` as the schema version of
+/// the store.
+///
+/// Downgrading the store is not supported.
+pub fn migrate<H: IbcHost>(host: &mut H, from: u32, to: u32) -> Result<(), H::Error> {
+    if from > to {
+        return Err(IbcError::UnsupportedSchemaVersion {
+            stored: from,
+            supported: to,
+        }
+        .into());
+    }
+
+    let migrations = host.migrations();
+
+    for version in (from + 1)..=to {
+        for migration in migrations
+            .iter()
+            .filter(|migration| migration.to == version)
+        {
+            (migration.migrate)(host)?;
+        }
+    }
+
+    host.commit_reserved(SCHEMA_VERSION_KEY, to.to_be_bytes().to_vec())
+}
+
+/// Migrate the store of `host` if it was written with a different schema version than the one
+/// supported by the host.
+pub(crate) fn ensure_schema_version<H: IbcHost>(host: &mut H) -> Result<(), H::Error> {
+    let stored = stored_schema_version(host)?;
+    let supported = host.schema_version();
+
+    if stored != supported {
+        migrate(host, stored, supported)?;
+    }
+
+    Ok(())
+}
+
+/// Commit the schema version of `host` if the store doesn't have one yet.
+pub(crate) fn init_schema_version<H: IbcHost>(host: &mut H) -> Result<(), H::Error> {
+    if host.read_reserved(SCHEMA_VERSION_KEY).is_none() {
+        host.commit_reserved(
+            SCHEMA_VERSION_KEY,
+            host.schema_version().to_be_bytes().to_vec(),
+        )?;
+    }
+
+    Ok(())
+}
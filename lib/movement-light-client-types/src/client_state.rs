@@ -10,6 +10,41 @@ pub struct ClientState {
     pub table_handle: AccountAddress,
     pub frozen_height: Height,
     pub latest_block_num: u64,
+    /// The trusting period of this client, in seconds. Once the latest consensus state is older
+    /// than this, the client is expired.
+    ///
+    /// A trusting period of 0 disables expiry, which is the case for clients created before this
+    /// field was introduced.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub trusting_period: u64,
+}
+
+const MICROS_PER_SECOND: u64 = 1_000_000;
+
+impl ClientState {
+    #[must_use]
+    pub fn is_frozen(&self) -> bool {
+        self.frozen_height.height() != 0
+    }
+
+    /// Whether a consensus state with the timestamp `consensus_state_timestamp` is outside of the
+    /// trusting period at `current_timestamp`. Both timestamps are in microseconds, matching the
+    /// movement block timestamps.
+    #[must_use]
+    pub fn is_expired(&self, consensus_state_timestamp: u64, current_timestamp: u64) -> bool {
+        if self.trusting_period == 0 {
+            return false;
+        }
+
+        match self
+            .trusting_period
+            .checked_mul(MICROS_PER_SECOND)
+            .and_then(|trusting_period| consensus_state_timestamp.checked_add(trusting_period))
+        {
+            Some(expires_at) => expires_at < current_timestamp,
+            None => true,
+        }
+    }
 }
 
 #[cfg(feature = "proto")]
@@ -35,6 +70,7 @@ pub mod proto {
                 table_handle: value.table_handle.0.into_bytes().to_vec(),
                 frozen_height: Some(value.frozen_height.into()),
                 latest_block_num: value.latest_block_num,
+                trusting_period: value.trusting_period,
             }
         }
     }
@@ -80,8 +116,50 @@ pub mod proto {
                 ),
                 frozen_height: value.frozen_height.unwrap_or_default().into(),
                 latest_block_num: value.latest_block_num,
+                trusting_period: value.trusting_period,
                 chain_id: value.chain_id,
             })
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONSENSUS_STATE_TIMESTAMP: u64 = 1_726_663_664_141_191;
+
+    fn client_state(trusting_period: u64) -> ClientState {
+        ClientState {
+            chain_id: "movement".to_owned(),
+            l1_client_id: 1,
+            l1_contract_address: H160::default(),
+            l2_contract_address: AccountAddress(Default::default()),
+            table_handle: AccountAddress(Default::default()),
+            frozen_height: Height::new(0),
+            latest_block_num: 100,
+            trusting_period,
+        }
+    }
+
+    #[test]
+    fn expiry_boundary() {
+        let client_state = client_state(60);
+        let expires_at = CONSENSUS_STATE_TIMESTAMP + 60 * MICROS_PER_SECOND;
+
+        assert!(!client_state.is_expired(CONSENSUS_STATE_TIMESTAMP, CONSENSUS_STATE_TIMESTAMP));
+        assert!(!client_state.is_expired(CONSENSUS_STATE_TIMESTAMP, expires_at - 1));
+        assert!(!client_state.is_expired(CONSENSUS_STATE_TIMESTAMP, expires_at));
+        assert!(client_state.is_expired(CONSENSUS_STATE_TIMESTAMP, expires_at + 1));
+    }
+
+    #[test]
+    fn zero_trusting_period_never_expires() {
+        assert!(!client_state(0).is_expired(CONSENSUS_STATE_TIMESTAMP, u64::MAX));
+    }
+
+    #[test]
+    fn trusting_period_overflow_is_expired() {
+        assert!(client_state(u64::MAX).is_expired(CONSENSUS_STATE_TIMESTAMP, 0));
+    }
+}
@@ -148,7 +148,7 @@ async fn connection_open_init(
                 key_prefix: b"ibc".into(),
             },
         },
-        version: DEFAULT_IBC_VERSION[0].clone(),
+        version: Some(DEFAULT_IBC_VERSION[0].clone()),
         delay_period: 0,
     };
 
@@ -408,6 +408,7 @@ async fn channel_open_init(
 ) {
     let port_id = ibc_app.id().to_string().validate().unwrap();
     let channel_init = ChannelOpenInit {
+        ordering: channel::order::Order::Unordered,
         connection_hops: vec![connection_id.to_string().validate().unwrap()],
         port_id: port_id.clone(),
         counterparty: channel::counterparty::Counterparty {
@@ -450,6 +451,7 @@ async fn channel_open_try(
     .await;
 
     let open_try = ChannelOpenTry {
+        ordering: channel::order::Order::Unordered,
         connection_hops: vec![connection_id.to_string().validate().unwrap()],
         port_id: port_id.clone(),
         counterparty: channel::counterparty::Counterparty {
@@ -34,7 +34,7 @@ pub struct CreateClient {
 pub struct ConnectionOpenInit {
     pub client_id: String,
     pub counterparty: connection::counterparty::Counterparty,
-    pub version: Version,
+    pub version: Option<Version>,
     pub delay_period: u64,
 }
 
@@ -66,6 +66,7 @@ pub struct ConnectionOpenConfirm {
 
 #[derive(serde::Serialize)]
 pub struct ChannelOpenInit {
+    pub ordering: channel::order::Order,
     pub connection_hops: Vec<ConnectionId>,
     pub port_id: PortId,
     pub counterparty: channel::counterparty::Counterparty,
@@ -74,6 +75,7 @@ pub struct ChannelOpenInit {
 
 #[derive(serde::Serialize)]
 pub struct ChannelOpenTry {
+    pub ordering: channel::order::Order,
     pub connection_hops: Vec<ConnectionId>,
     pub port_id: PortId,
     pub counterparty: channel::counterparty::Counterparty,
@@ -1,11 +1,12 @@
 use ibc_vm_rs::{
+    commitment::{CommitmentHasher, HashScheme},
     states::{
         channel_handshake::{ChannelOpenAck, ChannelOpenConfirm, ChannelOpenInit, ChannelOpenTry},
         client_state::UpdateClient,
         connection_handshake::{
             ConnectionOpenAck, ConnectionOpenConfirm, ConnectionOpenInit, ConnectionOpenTry,
         },
-        packet::{Acknowledgement, RecvPacket, SendPacket},
+        packet::{Acknowledgement, RecvPacket, SendPacket, TimeoutPacket},
         CreateClient,
     },
     CallbackError, IbcHost, IbcQuery, IbcResponse, IbcState, IbcVmResponse, Runnable, Status,
@@ -51,6 +52,10 @@ impl IbcHost for Contract {
         ))
     }
 
+    fn allowed_client_types(&self) -> Option<Vec<String>> {
+        None
+    }
+
     fn commit_raw(&mut self, key: Path, value: Vec<u8>) -> Result<(), Error> {
         self.commitments.insert(&key.to_string(), &value);
         Ok(())
@@ -88,6 +93,15 @@ impl IbcHost for Contract {
         self.commitments.get(&key.to_string())
     }
 
+    fn read_reserved(&self, key: &str) -> Option<Vec<u8>> {
+        self.commitments.get(&key.to_owned())
+    }
+
+    fn commit_reserved(&mut self, key: &str, value: Vec<u8>) -> Result<(), Error> {
+        self.commitments.insert(&key.to_owned(), &value);
+        Ok(())
+    }
+
     fn current_height(&self) -> Height {
         Height::new(env::block_height())
     }
@@ -97,16 +111,21 @@ impl IbcHost for Contract {
         env::block_timestamp()
     }
 
-    fn sha256(&self, data: Vec<u8>) -> Vec<u8> {
-        env::sha256(&data)
-    }
-
     fn delete(&mut self, key: &Path) -> Result<(), Self::Error> {
         let _ = self.commitments.remove(&key.to_string());
         Ok(())
     }
 }
 
+impl CommitmentHasher for Contract {
+    fn hash(&self, scheme: HashScheme, data: &[u8]) -> Option<Vec<u8>> {
+        Some(match scheme {
+            HashScheme::Sha256 => env::sha256(data),
+            HashScheme::Keccak256 => env::keccak256(data),
+        })
+    }
+}
+
 #[near_bindgen]
 #[derive(BorshDeserialize, BorshSerialize, Owner)]
 pub struct Contract {
@@ -166,7 +185,7 @@ impl Contract {
         &mut self,
         client_id: ClientId,
         counterparty: connection::counterparty::Counterparty,
-        version: connection::version::Version,
+        version: Option<connection::version::Version>,
         delay_period: u64,
     ) -> PromiseOrValue<IbcVmResponse> {
         self.init(
@@ -240,6 +259,7 @@ impl Contract {
 
     pub fn channel_open_init(
         &mut self,
+        ordering: channel::order::Order,
         connection_hops: Vec<ConnectionId>,
         port_id: PortId,
         counterparty: channel::counterparty::Counterparty,
@@ -247,6 +267,7 @@ impl Contract {
     ) -> PromiseOrValue<IbcVmResponse> {
         self.init(
             ChannelOpenInit::Init {
+                ordering,
                 connection_hops,
                 port_id,
                 counterparty,
@@ -258,6 +279,7 @@ impl Contract {
 
     pub fn channel_open_try(
         &mut self,
+        ordering: channel::order::Order,
         connection_hops: Vec<ConnectionId>,
         port_id: PortId,
         counterparty: channel::counterparty::Counterparty,
@@ -268,6 +290,7 @@ impl Contract {
     ) -> PromiseOrValue<IbcVmResponse> {
         self.init(
             ChannelOpenTry::Init {
+                ordering,
                 connection_hops,
                 port_id,
                 counterparty,
@@ -394,6 +417,22 @@ impl Contract {
         )
     }
 
+    pub fn timeout_packet(
+        &mut self,
+        packet: Packet,
+        proof_unreceived: Vec<u8>,
+        proof_height: Height,
+    ) -> PromiseOrValue<IbcVmResponse> {
+        self.init(
+            TimeoutPacket::Init {
+                packet,
+                proof_unreceived,
+                proof_height,
+            }
+            .into(),
+        )
+    }
+
     #[private]
     pub fn callback_query(
         &mut self,
@@ -503,6 +542,15 @@ impl Contract {
         self.step(current_state, &[IbcResponse::OnAcknowledgePacket { err }])
     }
 
+    #[private]
+    pub fn callback_on_timeout_packet(
+        &mut self,
+        current_state: IbcState,
+        #[callback_unwrap] err: CallbackError,
+    ) -> PromiseOrValue<IbcVmResponse> {
+        self.step(current_state, &[IbcResponse::OnTimeoutPacket { err }])
+    }
+
     fn init(&mut self, runnable: IbcState) -> PromiseOrValue<IbcVmResponse> {
         self.step(runnable, &[IbcResponse::Empty])
     }
@@ -683,6 +731,16 @@ impl Contract {
                             ),
                     )
                 }
+                ibc_vm_rs::IbcMsg::OnTimeoutPacket { packet } => {
+                    let account_id =
+                        AccountId::try_from(packet.source_port.clone().to_string()).unwrap();
+                    PromiseOrValue::Promise(
+                        ibc_app::ext(account_id).on_timeout_packet(packet).then(
+                            Contract::ext(env::current_account_id())
+                                .callback_on_timeout_packet(runnable),
+                        ),
+                    )
+                }
             },
         }
     }
@@ -766,5 +824,7 @@ pub trait IbcApp {
 
     fn on_acknowledge_packet(packet: Packet, ack: Vec<u8>) -> bool;
 
+    fn on_timeout_packet(packet: Packet) -> bool;
+
     fn recv_packet(packet: Packet) -> Vec<u8>;
 }
@@ -23,6 +23,16 @@ use crate::{
     ClientState, ConsensusState,
 };
 
+/// The states of the client are already decoded at most once per contract call, so there is no
+/// separate cache for them:
+///
+/// - `client_state` is deserialized together with the rest of the contract state when the call
+///   starts, and is written back once when it ends.
+/// - [`LookupMap`] (unlike `near_sdk::collections::LookupMap`) keeps every entry it reads or
+///   writes in memory until it is flushed at the end of the call. Repeated reads of the same
+///   consensus state (i.e. multiple `VerifyMembership` queries at the same height in a single
+///   [`query`](Self::query) call) don't touch storage again, and reads after an insert (as in
+///   [`update_client`](Self::update_client)) see the inserted value.
 #[near_bindgen]
 #[derive(PanicOnDefault, BorshDeserialize, BorshSerialize)]
 pub struct Contract {
@@ -82,6 +92,8 @@ impl Contract {
                         value,
                     ),
                 },
+                // absence proofs are not implemented for near yet
+                IbcQuery::VerifyNonMembership { .. } => IbcResponse::NonMembershipUnsupported,
                 IbcQuery::VerifyClientMessage(msg) => IbcResponse::VerifyClientMessage {
                     valid: self.verify_client_message(msg),
                 },
@@ -8,12 +8,15 @@ use futures_util::TryStreamExt;
 use itertools::Itertools;
 use schemars::JsonSchema;
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
 use sqlx::{postgres::PgPoolOptions, prelude::FromRow, types::Json, Either, Executor, PgPool};
 use tracing::{debug, debug_span, info_span, instrument, trace, Instrument};
 use voyager_vm::{
-    filter::{FilterResult, InterestFilter},
+    filter::{route, Routed},
     pass::{Pass, PassResult},
-    Captures, Op, QueueMessage,
+    wire::{self, VersionedOp},
+    Captures, InspectQueue, LaneStats, Op, QueueMessage, QueueStats, QueuedOp, ScanFilter,
+    ScanPage, UndecodableOp, READY_LANE,
 };
 
 use crate::metrics::{ITEM_PROCESSING_DURATION, OPTIMIZE_ITEM_COUNT, OPTIMIZE_PROCESSING_DURATION};
@@ -71,12 +74,19 @@ struct Record {
     created_at: sqlx::types::time::OffsetDateTime,
 }
 
+#[derive(Debug, FromRow)]
+struct LaneRecord {
+    lane: String,
+    depth: i64,
+    oldest: sqlx::types::time::OffsetDateTime,
+}
+
 #[derive(Debug, FromRow, Serialize)]
 #[serde(bound(serialize = ""))]
 pub struct FailedRecord<T: QueueMessage> {
     pub id: i64,
     pub parents: Vec<i64>,
-    pub item: Json<Op<T>>,
+    pub item: Json<VersionedOp<T>>,
     pub message: String,
     // pub created_at: sqlx::types::time::OffsetDateTime,
 }
@@ -226,13 +236,7 @@ impl<T: QueueMessage> voyager_vm::Queue<T> for PgQueue<T> {
     async fn enqueue<'a>(&'a self, op: Op<T>, filter: &'a T::Filter) -> Result<(), Self::Error> {
         trace!("enqueue");
 
-        let (optimize, ready): (Vec<_>, Vec<_>) =
-            op.normalize()
-                .into_iter()
-                .partition_map(|op| match filter.check_interest(&op) {
-                    FilterResult::Interest(tag) => Either::Left((op, tag)),
-                    FilterResult::NoInterest => Either::Right(op),
-                });
+        let (optimize, ready) = partition_routed(route(filter, op.normalize()));
 
         let mut tx = self.client.begin().await?;
 
@@ -243,7 +247,7 @@ impl<T: QueueMessage> voyager_vm::Queue<T> for PgQueue<T> {
             RETURNING id
             ",
         )
-        .bind(ready.into_iter().map(Json).collect::<Vec<_>>())
+        .bind(ready.into_iter().map(versioned).collect::<Vec<_>>())
         .try_map(|x| Id::from_row(&x))
         .fetch_all(tx.as_mut())
         .await?;
@@ -263,7 +267,7 @@ impl<T: QueueMessage> voyager_vm::Queue<T> for PgQueue<T> {
             optimize
                 .clone()
                 .into_iter()
-                .map(|x| Json(x.0))
+                .map(|x| versioned(x.0))
                 .collect::<Vec<_>>(),
         )
         .bind(optimize.into_iter().map(|x| x.1).collect::<Vec<_>>())
@@ -328,7 +332,7 @@ impl<T: QueueMessage> voyager_vm::Queue<T> for PgQueue<T> {
                 trace!(%row.item);
 
                 // really don't feel like defining a new error type right now
-                let op = de(&row.item).map_err(|e| sqlx::Error::Decode(Box::new(e)))?;
+                let op = de_op(&row.item).map_err(|e| sqlx::Error::Decode(Box::new(e)))?;
 
                 let timer = ITEM_PROCESSING_DURATION.start_timer();
                 let (r, res) = f(op).instrument(span).await;
@@ -374,13 +378,10 @@ impl<T: QueueMessage> voyager_vm::Queue<T> for PgQueue<T> {
                                 break 'block;
                             }
 
-                            let (optimize, ready): (Vec<_>, Vec<_>) = ops
-                                .into_iter()
-                                .flat_map(Op::normalize)
-                                .partition_map(|op| match filter.check_interest(&op) {
-                                    FilterResult::Interest(tag) => Either::Left((op, tag)),
-                                    FilterResult::NoInterest => Either::Right(op),
-                                });
+                            let (optimize, ready) = partition_routed(route(
+                                filter,
+                                ops.into_iter().flat_map(Op::normalize),
+                            ));
 
                             sqlx::query(
                                 "
@@ -388,7 +389,7 @@ impl<T: QueueMessage> voyager_vm::Queue<T> for PgQueue<T> {
                                 SELECT * FROM UNNEST($1::JSONB[])
                                 ",
                             )
-                            .bind(ready.into_iter().map(Json).collect::<Vec<_>>())
+                            .bind(ready.into_iter().map(versioned).collect::<Vec<_>>())
                             .execute(tx.as_mut())
                             .await?;
 
@@ -398,7 +399,12 @@ impl<T: QueueMessage> voyager_vm::Queue<T> for PgQueue<T> {
                                 SELECT * FROM UNNEST($1::JSONB[], $2::TEXT[])
                                 ",
                             )
-                            .bind(optimize.iter().map(|(op, _)| Json(op)).collect::<Vec<_>>())
+                            .bind(
+                                optimize
+                                    .iter()
+                                    .map(|(op, _)| versioned(op.clone()))
+                                    .collect::<Vec<_>>(),
+                            )
                             .bind(optimize.iter().map(|(_, tag)| *tag).collect::<Vec<_>>())
                             .execute(tx.as_mut())
                             .await?;
@@ -476,7 +482,7 @@ impl<T: QueueMessage> voyager_vm::Queue<T> for PgQueue<T> {
             .map(|r| {
                 Ok((
                     r.id,
-                    de(&r.item).map_err(|e| sqlx::Error::Decode(Box::new(e)))?,
+                    de_op(&r.item).map_err(|e| sqlx::Error::Decode(Box::new(e)))?,
                 ))
             })
             .collect::<Result<(Vec<_>, Vec<_>), sqlx::Error>>()
@@ -527,7 +533,7 @@ impl<T: QueueMessage> voyager_vm::Queue<T> for PgQueue<T> {
                 RETURNING id
                 ",
             )
-            .bind(Json(new_msg))
+            .bind(versioned(new_msg))
             .bind(&parents)
             .bind(tag)
             .try_map(|row| Id::from_row(&row))
@@ -550,7 +556,7 @@ impl<T: QueueMessage> voyager_vm::Queue<T> for PgQueue<T> {
                 RETURNING id
                 ",
             )
-            .bind(Json(new_msg))
+            .bind(versioned(new_msg))
             .bind(&parents)
             .try_map(|x| Id::from_row(&x))
             .fetch_one(tx.as_mut())
@@ -566,6 +572,233 @@ impl<T: QueueMessage> voyager_vm::Queue<T> for PgQueue<T> {
     }
 }
 
+impl<T: QueueMessage> InspectQueue<T> for PgQueue<T> {
+    #[instrument(skip_all, fields(?filter, ?after, limit))]
+    async fn scan<'a>(
+        &'a self,
+        filter: &'a ScanFilter,
+        after: Option<i64>,
+        limit: usize,
+    ) -> Result<ScanPage<T>, Self::Error> {
+        trace!("scan");
+
+        // plain reads don't take row locks, so this doesn't block process or optimize. both tables
+        // draw their ids from the same sequence, so the union can be paged on id.
+        let records = sqlx::query(
+            r#"
+            SELECT
+              id,
+              parents,
+              item::text,
+              created_at
+            FROM
+              (
+                SELECT
+                  id,
+                  parents,
+                  item,
+                  created_at
+                FROM
+                  queue
+                UNION ALL
+                SELECT
+                  id,
+                  parents,
+                  item,
+                  created_at
+                FROM
+                  optimize
+              ) ops
+            WHERE
+              id > $1
+              AND (
+                $2::TEXT IS NULL
+                OR jsonb_path_exists(
+                  item,
+                  '$.**.chain_id ? (@ == $chain_id)',
+                  jsonb_build_object('chain_id', $2::TEXT)
+                )
+                OR EXISTS(
+                  SELECT
+                    1
+                  FROM
+                    jsonb_path_query(item, '$.**.plugin') AS plugin
+                  WHERE
+                    plugin #>> '{}' LIKE '%/' || $2::TEXT
+                )
+              )
+              AND (
+                $3::BIGINT IS NULL
+                OR created_at < to_timestamp($3::BIGINT + 1)
+              )
+            ORDER BY
+              id ASC
+            LIMIT
+              $4
+            "#,
+        )
+        .bind(after.unwrap_or(0))
+        .bind(filter.chain_id.as_deref())
+        .bind(
+            filter
+                .enqueued_before
+                .map(|t| i64::try_from(t).unwrap_or(i64::MAX - 1)),
+        )
+        .bind(i64::try_from(limit).unwrap_or(i64::MAX))
+        .try_map(|x| Record::from_row(&x))
+        .fetch_all(&self.client)
+        .await?;
+
+        let next = (records.len() == limit)
+            .then(|| records.last().map(|record| record.id))
+            .flatten();
+
+        let mut page = ScanPage {
+            ops: vec![],
+            undecodable: vec![],
+            next,
+        };
+
+        for record in records {
+            let enqueued_at = record.created_at.unix_timestamp().try_into().unwrap_or(0);
+
+            // the item is jsonb and the recursion limit is disabled, so this is always valid json
+            let item = de::<Value>(&record.item).unwrap_or(Value::String(record.item));
+
+            match wire::decode_op(item.clone()) {
+                Ok(op) => page.ops.push(QueuedOp {
+                    id: record.id,
+                    enqueued_at,
+                    op,
+                }),
+                Err(error) => {
+                    let mut message = error.to_string();
+                    let mut source = std::error::Error::source(&error);
+                    while let Some(e) = source {
+                        message.push_str(&format!(": {e}"));
+                        source = e.source();
+                    }
+
+                    debug!(id = record.id, error = %message, "undecodable op");
+
+                    page.undecodable.push(UndecodableOp {
+                        id: record.id,
+                        enqueued_at,
+                        item,
+                        error: message,
+                    })
+                }
+            }
+        }
+
+        Ok(page)
+    }
+
+    #[instrument(skip_all, fields(ids = ids.len()))]
+    async fn remove<'a>(&'a self, ids: &'a [i64]) -> Result<Vec<i64>, Self::Error> {
+        trace!("remove");
+
+        // rows locked by an ongoing process or optimize are skipped instead of waited on
+        let removed = sqlx::query(
+            r#"
+            WITH removed_ready AS (
+              DELETE FROM
+                queue
+              WHERE
+                id = ANY(
+                  SELECT
+                    id
+                  FROM
+                    queue
+                  WHERE
+                    id = ANY($1)
+                  FOR UPDATE
+                    SKIP LOCKED
+                )
+              RETURNING
+                id
+            ),
+            removed_optimize AS (
+              DELETE FROM
+                optimize
+              WHERE
+                id = ANY(
+                  SELECT
+                    id
+                  FROM
+                    optimize
+                  WHERE
+                    id = ANY($1)
+                  FOR UPDATE
+                    SKIP LOCKED
+                )
+              RETURNING
+                id
+            )
+            SELECT id FROM removed_ready
+            UNION ALL
+            SELECT id FROM removed_optimize
+            "#,
+        )
+        .bind(ids)
+        .try_map(|x| Id::from_row(&x))
+        .fetch_all(&self.client)
+        .await?
+        .into_iter()
+        .map(|row| row.id)
+        .collect::<Vec<_>>();
+
+        debug!(removed = removed.len(), "removed ops");
+
+        Ok(removed)
+    }
+
+    #[instrument(skip_all)]
+    async fn stats(&self) -> Result<QueueStats, Self::Error> {
+        trace!("stats");
+
+        // only aggregates over the rows are read, so this is cheap even for large queues
+        let lanes = sqlx::query(
+            r#"
+            SELECT
+              $1::TEXT AS lane,
+              count(*) AS depth,
+              min(created_at) AS oldest
+            FROM
+              queue
+            HAVING
+              count(*) > 0
+            UNION ALL
+            SELECT
+              tag AS lane,
+              count(*) AS depth,
+              min(created_at) AS oldest
+            FROM
+              optimize
+            GROUP BY
+              tag
+            "#,
+        )
+        .bind(READY_LANE)
+        .try_map(|x| LaneRecord::from_row(&x))
+        .fetch_all(&self.client)
+        .await?
+        .into_iter()
+        .map(|record| {
+            (
+                record.lane,
+                LaneStats {
+                    depth: record.depth.try_into().unwrap_or(0),
+                    oldest_enqueued_at: record.oldest.unix_timestamp().try_into().unwrap_or(0),
+                },
+            )
+        })
+        .collect();
+
+        Ok(QueueStats { lanes })
+    }
+}
+
 #[derive(sqlx::Type)]
 #[sqlx(type_name = "status", rename_all = "lowercase")]
 pub enum EnqueueStatus {
@@ -581,6 +814,30 @@ fn de<T: DeserializeOwned>(s: &str) -> Result<T, serde_json::Error> {
     Ok(json)
 }
 
+/// Decode a stored op, which may have been written with an older version of the wire format. See
+/// [`voyager_vm::wire`].
+fn de_op<T: QueueMessage>(s: &str) -> Result<Op<T>, wire::DecodeOpError> {
+    de::<Value>(s)
+        .map_err(|source| wire::DecodeOpError::Decode { version: 0, source })
+        .and_then(wire::decode_op)
+}
+
+/// Split routed ops into the ops to optimize, along with their tags, and the ready ops.
+#[allow(clippy::type_complexity)]
+fn partition_routed<'a, T: QueueMessage>(
+    routed: Vec<Routed<'a, T>>,
+) -> (Vec<(Op<T>, &'a str)>, Vec<Op<T>>) {
+    routed.into_iter().partition_map(|routed| match routed {
+        Routed::Optimize(op, tag) => Either::Left((op, tag)),
+        Routed::Ready(op) => Either::Right(op),
+    })
+}
+
+/// Ops are always stored with the current version of the wire format.
+fn versioned<T: QueueMessage>(op: Op<T>) -> Json<VersionedOp<T>> {
+    Json(VersionedOp(op))
+}
+
 pub trait MapExt<K, V> {
     fn get_many<'a, Q>(&'a self, ks: impl IntoIterator<Item = &'a Q>) -> Vec<&'a V>
     where
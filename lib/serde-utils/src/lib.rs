@@ -1,4 +1,4 @@
-#![no_std]
+#![cfg_attr(not(test), no_std)]
 
 extern crate alloc;
 
@@ -87,6 +87,134 @@ where
     }
 }
 
+/// Parse hex bytes that may or may not be `0x`-prefixed.
+///
+/// Unlike [`parse_hex`], a missing prefix is not an error. The empty string is still rejected,
+/// since it is ambiguous whether it was intended to be empty bytes or is the result of a missing
+/// value.
+pub fn parse_hex_allow_unprefixed<T>(string: impl AsRef<[u8]>) -> Result<T, FromHexStringError>
+where
+    T: TryFrom<Vec<u8>, Error: Debug + 'static>,
+{
+    let s = string.as_ref();
+
+    if s.is_empty() {
+        return Err(FromHexStringError::EmptyString);
+    }
+
+    match s.strip_prefix(HEX_ENCODING_PREFIX.as_bytes()) {
+        Some(_) => parse_hex(s),
+        None => hex::decode(s)
+            .map_err(FromHexStringError::Hex)?
+            .try_into()
+            .map_err(|err| FromHexStringError::TryFromBytes(format!("{err:?}"))),
+    }
+}
+
+/// The amount of characters of an invalid value that are included in deserialization errors.
+/// Byte fields can be arbitrarily large, and the start of the value is usually enough to spot
+/// the problem.
+const ERROR_VALUE_PREFIX_LEN: usize = 32;
+
+const EXPECTED_HEX: &str = "`0x`-prefixed or bare hex with an even number of digits";
+const EXPECTED_HEX_STRICT: &str = "`0x`-prefixed hex with an even number of digits";
+const EXPECTED_BASE64: &str = "standard (padded) base64";
+
+/// Build a deserialization error for the invalid `value`, naming the expected format and
+/// including the start of the value.
+fn invalid_value<E: serde::de::Error>(
+    expected: &str,
+    value: &str,
+    err: impl core::fmt::Display,
+) -> E {
+    let prefix = match value.char_indices().nth(ERROR_VALUE_PREFIX_LEN) {
+        Some((idx, _)) => format!("{}...", &value[..idx]),
+        None => value.to_string(),
+    };
+
+    E::custom(format!(
+        "{err} (expected {expected}, found \"{prefix}\" of length {})",
+        value.len()
+    ))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FromHexOrBase64StringError {
+    /// The string is `0x`-prefixed, but is not valid hex.
+    Hex(FromHexStringError),
+    /// The string is neither valid bare hex nor valid base64.
+    Invalid(String),
+    // NOTE: Contains the stringified error
+    TryFromBytes(String),
+}
+
+impl core::error::Error for FromHexOrBase64StringError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            FromHexOrBase64StringError::Hex(hex) => Some(hex),
+            FromHexOrBase64StringError::Invalid(_) => None,
+            FromHexOrBase64StringError::TryFromBytes(_) => None,
+        }
+    }
+}
+
+impl core::fmt::Display for FromHexOrBase64StringError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            FromHexOrBase64StringError::Hex(e) => {
+                write!(f, "invalid `{HEX_ENCODING_PREFIX}`-prefixed hex: {e}")
+            }
+            FromHexOrBase64StringError::Invalid(data) => write!(
+                f,
+                "'{data}' is neither valid hex (`{HEX_ENCODING_PREFIX}`-prefixed or bare) nor \
+                valid standard base64"
+            ),
+            FromHexOrBase64StringError::TryFromBytes(err) => {
+                write!(f, "unable to convert from bytes: {err:?}")
+            }
+        }
+    }
+}
+
+/// Parse bytes encoded as either hex or base64.
+///
+/// The format is chosen as follows:
+///
+/// 1. `0x`-prefixed strings are always parsed as hex (see [`parse_hex`]).
+/// 2. Strings that consist of an even number of hex digits are parsed as bare
+///    hex. Note that such strings may also be valid base64 (i.e. `deadbeef`),
+///    in which case hex takes precedence. Base64 encoded values whose length is
+///    not a multiple of 3 bytes always contain padding, and as such are never
+///    ambiguous.
+/// 3. All other strings are parsed as standard (padded) base64.
+///
+/// The empty string is parsed as empty bytes.
+pub fn parse_hex_or_base64<T>(string: impl AsRef<str>) -> Result<T, FromHexOrBase64StringError>
+where
+    T: TryFrom<Vec<u8>, Error: Debug + 'static>,
+{
+    use ::base64::prelude::*;
+
+    let s = string.as_ref();
+
+    if s.starts_with(HEX_ENCODING_PREFIX) {
+        return parse_hex(s).map_err(|err| match err {
+            FromHexStringError::TryFromBytes(err) => FromHexOrBase64StringError::TryFromBytes(err),
+            err => FromHexOrBase64StringError::Hex(err),
+        });
+    }
+
+    let bz = if s.len() % 2 == 0 && s.bytes().all(|b| b.is_ascii_hexdigit()) {
+        hex::decode(s).ok()
+    } else {
+        BASE64_STANDARD.decode(s).ok()
+    }
+    .ok_or_else(|| FromHexOrBase64StringError::Invalid(s.to_string()))?;
+
+    bz.try_into()
+        .map_err(|err| FromHexOrBase64StringError::TryFromBytes(format!("{err:?}")))
+}
+
 pub mod base64 {
     use alloc::{format, string::String, vec::Vec};
     use core::fmt::Debug;
@@ -106,19 +234,21 @@ pub mod base64 {
         D: Deserializer<'de>,
         T: TryFrom<Vec<u8>, Error: Debug + 'static>,
     {
+        let s = String::deserialize(deserializer)?;
+
         BASE64_STANDARD
-            .decode(String::deserialize(deserializer)?.as_bytes())
-            .map_err(de::Error::custom)?
+            .decode(s.as_bytes())
+            .map_err(|err| crate::invalid_value(crate::EXPECTED_BASE64, &s, err))?
             .try_into()
             .map_err(|err| de::Error::custom(format!("{err:?}")))
     }
 }
 
 pub mod inner_base64 {
-    use alloc::{string::String, vec::Vec};
+    use alloc::{format, string::String, vec::Vec};
 
     use base64::prelude::*;
-    use serde::{de, Deserialize, Deserializer, Serializer};
+    use serde::{Deserialize, Deserializer, Serializer};
 
     pub fn serialize<S: Serializer>(
         #[allow(clippy::ptr_arg)] // required by serde
@@ -133,9 +263,17 @@ pub mod inner_base64 {
     ) -> Result<Vec<Vec<u8>>, D::Error> {
         Vec::<String>::deserialize(deserializer)?
             .into_iter()
-            .map(|item| BASE64_STANDARD.decode(item))
-            .collect::<Result<Vec<_>, _>>()
-            .map_err(de::Error::custom)
+            .enumerate()
+            .map(|(idx, item)| {
+                BASE64_STANDARD.decode(&item).map_err(|err| {
+                    crate::invalid_value(
+                        crate::EXPECTED_BASE64,
+                        &item,
+                        format!("invalid item at index {idx}: {err}"),
+                    )
+                })
+            })
+            .collect()
     }
 }
 
@@ -251,13 +389,18 @@ pub mod fixed_size_array {
     }
 }
 
+/// Serializes as `0x`-prefixed hex, and accepts both `0x`-prefixed and bare hex when
+/// deserializing (see [`parse_hex_allow_unprefixed`](crate::parse_hex_allow_unprefixed)).
+///
+/// Use [`hex_string_strict`](crate::hex_string_strict) where a missing prefix indicates that the
+/// value is not what it is expected to be.
 pub mod hex_string {
     use alloc::{format, string::String, vec::Vec};
     use core::fmt::Debug;
 
     use serde::{de, Deserialize, Deserializer, Serializer};
 
-    use crate::{parse_hex, to_hex};
+    use crate::{invalid_value, parse_hex_allow_unprefixed, to_hex, EXPECTED_HEX};
 
     pub fn serialize<S, T: AsRef<[u8]>>(data: T, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -276,8 +419,44 @@ pub mod hex_string {
         T: TryFrom<Vec<u8>, Error: Debug + 'static>,
     {
         if deserializer.is_human_readable() {
-            String::deserialize(deserializer)
-                .and_then(|x| parse_hex::<T>(x).map_err(de::Error::custom))
+            String::deserialize(deserializer).and_then(|s| {
+                parse_hex_allow_unprefixed::<T>(&s)
+                    .map_err(|err| invalid_value(EXPECTED_HEX, &s, err))
+            })
+        } else {
+            <Vec<u8>>::deserialize(deserializer).and_then(|t| {
+                t.try_into()
+                    .map_err(|e| de::Error::custom(format!("{e:?}")))
+            })
+        }
+    }
+}
+
+/// [`hex_string`](crate::hex_string), but values without the `0x` prefix are rejected.
+pub mod hex_string_strict {
+    use alloc::{format, string::String, vec::Vec};
+    use core::fmt::Debug;
+
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    use crate::{invalid_value, parse_hex, EXPECTED_HEX_STRICT};
+
+    pub fn serialize<S, T: AsRef<[u8]>>(data: T, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        crate::hex_string::serialize(data, serializer)
+    }
+
+    pub fn deserialize<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+    where
+        D: Deserializer<'de>,
+        T: TryFrom<Vec<u8>, Error: Debug + 'static>,
+    {
+        if deserializer.is_human_readable() {
+            String::deserialize(deserializer).and_then(|s| {
+                parse_hex::<T>(&s).map_err(|err| invalid_value(EXPECTED_HEX_STRICT, &s, err))
+            })
         } else {
             <Vec<u8>>::deserialize(deserializer).and_then(|t| {
                 t.try_into()
@@ -369,6 +548,72 @@ pub mod hex_allow_unprefixed {
     }
 }
 
+/// Accepts `0x`-prefixed hex, bare hex, or standard base64 when deserializing
+/// (see [`parse_hex_or_base64`](crate::parse_hex_or_base64)), and always
+/// serializes as `0x`-prefixed hex.
+pub mod hex_or_base64 {
+    use alloc::{format, string::String, vec::Vec};
+    use core::fmt::Debug;
+
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    use crate::parse_hex_or_base64;
+
+    pub fn serialize<S, T: AsRef<[u8]>>(data: T, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        crate::hex_string::serialize(data, serializer)
+    }
+
+    pub fn deserialize<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+    where
+        D: Deserializer<'de>,
+        T: TryFrom<Vec<u8>, Error: Debug + 'static>,
+    {
+        if deserializer.is_human_readable() {
+            String::deserialize(deserializer)
+                .and_then(|s| parse_hex_or_base64(s).map_err(de::Error::custom))
+        } else {
+            <Vec<u8>>::deserialize(deserializer).and_then(|t| {
+                t.try_into()
+                    .map_err(|e| de::Error::custom(format!("{e:?}")))
+            })
+        }
+    }
+}
+
+/// [`hex_or_base64`](crate::hex_or_base64), for optional values.
+pub mod hex_or_base64_opt {
+    use alloc::vec::Vec;
+    use core::fmt::Debug;
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    #[derive(Deserialize)]
+    #[serde(bound(deserialize = "T: TryFrom<Vec<u8>, Error: Debug + 'static>"))]
+    struct HexOrBase64<T>(#[serde(with = "crate::hex_or_base64")] T);
+
+    pub fn serialize<S, T>(option: &Option<T>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+        T: AsRef<[u8]>,
+    {
+        match option.as_ref() {
+            Some(t) => serializer.serialize_some(&crate::Hex(t)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+    where
+        D: Deserializer<'de>,
+        T: TryFrom<Vec<u8>, Error: Debug + 'static>,
+    {
+        Option::<HexOrBase64<T>>::deserialize(deserializer).map(|t| t.map(|HexOrBase64(t)| t))
+    }
+}
+
 pub mod hex_allow_unprefixed_list {
     use alloc::{format, string::String, vec::Vec};
     use core::fmt::Debug;
@@ -867,6 +1112,8 @@ impl<T: AsRef<[u8]>> core::fmt::Display for Hex<T> {
 
 #[cfg(test)]
 mod tests {
+    use ::base64::prelude::*;
+
     use super::*;
 
     #[test]
@@ -876,4 +1123,287 @@ mod tests {
         let bz = parse_hex::<alloc::vec::Vec<u8>>(string).unwrap();
         assert_eq!(bz, []);
     }
+
+    #[test]
+    fn hex_or_base64() {
+        let invalid = |s: &str| Err(FromHexOrBase64StringError::Invalid(s.to_string()));
+
+        let cases: &[(&str, Result<Vec<u8>, FromHexOrBase64StringError>)] = &[
+            // prefixed hex
+            ("0x", Ok(vec![])),
+            ("0x0", Ok(vec![])),
+            ("0xdeadbeef", Ok(vec![0xde, 0xad, 0xbe, 0xef])),
+            ("0xDEADBEEF", Ok(vec![0xde, 0xad, 0xbe, 0xef])),
+            (
+                "0xabc",
+                Err(FromHexOrBase64StringError::Hex(FromHexStringError::Hex(
+                    FromHexError::OddLength,
+                ))),
+            ),
+            // prefixed strings are never parsed as base64, even if valid
+            (
+                "0x3q2+7w==",
+                Err(FromHexOrBase64StringError::Hex(FromHexStringError::Hex(
+                    FromHexError::InvalidHexCharacter { c: 'q', index: 1 },
+                ))),
+            ),
+            // bare hex
+            ("", Ok(vec![])),
+            ("deadbeef", Ok(vec![0xde, 0xad, 0xbe, 0xef])),
+            ("DEADBEEF", Ok(vec![0xde, 0xad, 0xbe, 0xef])),
+            ("abcd", Ok(vec![0xab, 0xcd])),
+            // base64
+            ("3q2+7w==", Ok(vec![0xde, 0xad, 0xbe, 0xef])),
+            ("aGVsbG8=", Ok(b"hello".to_vec())),
+            ("AAA=", Ok(vec![0, 0])),
+            ("3q2/", Ok(vec![0xde, 0xad, 0xbf])),
+            // odd length hex digits are not valid base64 either
+            ("abc", invalid("abc")),
+            // unpadded and url-safe base64 are not accepted
+            ("aGVsbG8", invalid("aGVsbG8")),
+            ("3q2-7w==", invalid("3q2-7w==")),
+            ("not valid!", invalid("not valid!")),
+        ];
+
+        for (input, expected) in cases {
+            assert_eq!(
+                &parse_hex_or_base64::<Vec<u8>>(input),
+                expected,
+                "input: {input:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn hex_or_base64_ambiguous_is_hex() {
+        // valid as both bare hex and base64, hex takes precedence
+        for input in ["deadbeef", "abcd", "1234", "00000000"] {
+            assert!(BASE64_STANDARD.decode(input).is_ok());
+            assert_eq!(
+                parse_hex_or_base64::<Vec<u8>>(input).unwrap(),
+                hex::decode(input).unwrap()
+            );
+        }
+
+        // padded base64 is never ambiguous
+        let bz = [0xaa_u8; 32];
+        let encoded = BASE64_STANDARD.encode(bz);
+        assert!(encoded.ends_with('='));
+        assert_eq!(parse_hex_or_base64::<[u8; 32]>(encoded).unwrap(), bz);
+    }
+
+    #[test]
+    fn hex_or_base64_try_from_bytes() {
+        assert!(matches!(
+            parse_hex_or_base64::<[u8; 32]>("0xdeadbeef"),
+            Err(FromHexOrBase64StringError::TryFromBytes(_))
+        ));
+        assert!(matches!(
+            parse_hex_or_base64::<[u8; 32]>("3q2+7w=="),
+            Err(FromHexOrBase64StringError::TryFromBytes(_))
+        ));
+    }
+
+    #[test]
+    fn hex_or_base64_error_names_both_formats() {
+        let err = parse_hex_or_base64::<Vec<u8>>("not valid!")
+            .unwrap_err()
+            .to_string();
+
+        assert!(err.contains("hex"), "{err}");
+        assert!(err.contains("base64"), "{err}");
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Bytes {
+        #[serde(with = "crate::hex_or_base64")]
+        bytes: Vec<u8>,
+        #[serde(default, with = "crate::hex_or_base64_opt")]
+        maybe_bytes: Option<Vec<u8>>,
+    }
+
+    #[test]
+    fn hex_or_base64_serde() {
+        let expected = Bytes {
+            bytes: vec![0xde, 0xad, 0xbe, 0xef],
+            maybe_bytes: Some(vec![0xde, 0xad, 0xbe, 0xef]),
+        };
+
+        for input in ["0xdeadbeef", "deadbeef", "3q2+7w=="] {
+            let json = format!(r#"{{"bytes":"{input}","maybe_bytes":"{input}"}}"#);
+
+            assert_eq!(serde_json::from_str::<Bytes>(&json).unwrap(), expected);
+        }
+
+        // always serialized as prefixed hex
+        assert_eq!(
+            serde_json::to_string(&expected).unwrap(),
+            r#"{"bytes":"0xdeadbeef","maybe_bytes":"0xdeadbeef"}"#
+        );
+
+        assert_eq!(
+            serde_json::from_str::<Bytes>(r#"{"bytes":"","maybe_bytes":null}"#).unwrap(),
+            Bytes {
+                bytes: vec![],
+                maybe_bytes: None
+            }
+        );
+        assert_eq!(
+            serde_json::from_str::<Bytes>(r#"{"bytes":"0x0"}"#).unwrap(),
+            Bytes {
+                bytes: vec![],
+                maybe_bytes: None
+            }
+        );
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct HexBytes {
+        #[serde(with = "crate::hex_string")]
+        bytes: Vec<u8>,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct StrictHexBytes {
+        #[serde(with = "crate::hex_string_strict")]
+        bytes: Vec<u8>,
+    }
+
+    fn hex_string_error(value: &str) -> String {
+        serde_json::from_value::<HexBytes>(serde_json::json!({ "bytes": value }))
+            .unwrap_err()
+            .to_string()
+    }
+
+    #[test]
+    fn hex_string_accepts_unprefixed() {
+        for input in ["0xdeadbeef", "deadbeef", "DEADBEEF"] {
+            assert_eq!(
+                serde_json::from_value::<HexBytes>(serde_json::json!({ "bytes": input })).unwrap(),
+                HexBytes {
+                    bytes: vec![0xde, 0xad, 0xbe, 0xef]
+                }
+            );
+        }
+
+        assert_eq!(
+            serde_json::from_str::<HexBytes>(r#"{"bytes":"0x0"}"#).unwrap(),
+            HexBytes { bytes: vec![] }
+        );
+    }
+
+    #[test]
+    fn hex_string_errors_name_format_and_value() {
+        let err = hex_string_error("0xabc");
+        assert!(err.contains("Odd number of digits"), "{err}");
+        assert!(err.contains(EXPECTED_HEX), "{err}");
+        assert!(err.contains(r#"found "0xabc" of length 5"#), "{err}");
+
+        let err = hex_string_error("");
+        assert!(err.contains("empty string"), "{err}");
+
+        // only the start of large values is included
+        let value = "z".repeat(100);
+        let err = hex_string_error(&value);
+        assert!(
+            err.contains(&format!(r#"found "{}..." of length 100"#, "z".repeat(32))),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn hex_string_strict_requires_prefix() {
+        assert_eq!(
+            serde_json::from_str::<StrictHexBytes>(r#"{"bytes":"0xdeadbeef"}"#).unwrap(),
+            StrictHexBytes {
+                bytes: vec![0xde, 0xad, 0xbe, 0xef]
+            }
+        );
+
+        let err = serde_json::from_str::<StrictHexBytes>(r#"{"bytes":"deadbeef"}"#)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("missing prefix"), "{err}");
+        assert!(err.contains(EXPECTED_HEX_STRICT), "{err}");
+    }
+
+    #[test]
+    fn base64_errors_name_format_and_value() {
+        #[derive(Debug, Deserialize)]
+        #[allow(dead_code)]
+        struct Base64Bytes {
+            #[serde(with = "crate::base64")]
+            bytes: Vec<u8>,
+            #[serde(with = "crate::inner_base64")]
+            list: Vec<Vec<u8>>,
+        }
+
+        let err = serde_json::from_str::<Base64Bytes>(r#"{"bytes":"0xdeadbeef","list":[]}"#)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains(EXPECTED_BASE64), "{err}");
+        assert!(err.contains(r#"found "0xdeadbeef""#), "{err}");
+
+        let err = serde_json::from_str::<Base64Bytes>(
+            r#"{"bytes":"3q2+7w==","list":["3q2+7w==","abc"]}"#,
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(err.contains("invalid item at index 1"), "{err}");
+        assert!(err.contains(r#"found "abc""#), "{err}");
+    }
+
+    mod proptests {
+        use proptest::prelude::*;
+
+        use super::*;
+
+        proptest! {
+            #[test]
+            fn lenient_hex_roundtrips(bz in prop::collection::vec(any::<u8>(), 0..64)) {
+                prop_assert_eq!(parse_hex_allow_unprefixed::<Vec<u8>>(to_hex(&bz)), Ok(bz.clone()));
+
+                if !bz.is_empty() {
+                    prop_assert_eq!(
+                        parse_hex_allow_unprefixed::<Vec<u8>>(hex::encode(&bz)),
+                        Ok(bz.clone())
+                    );
+                    prop_assert_eq!(
+                        parse_hex_allow_unprefixed::<Vec<u8>>(hex::encode_upper(&bz)),
+                        Ok(bz)
+                    );
+                }
+            }
+
+            #[test]
+            fn lenient_hex_rejects_odd_length(s in "([0-9a-f]{2}){0,16}[0-9a-f]") {
+                prop_assert_eq!(
+                    parse_hex_allow_unprefixed::<Vec<u8>>(&s),
+                    Err(FromHexStringError::Hex(FromHexError::OddLength))
+                );
+
+                // `0x0` is the encoding of empty bytes
+                if s != "0" {
+                    prop_assert_eq!(
+                        parse_hex_allow_unprefixed::<Vec<u8>>(format!("0x{s}")),
+                        Err(FromHexStringError::Hex(FromHexError::OddLength))
+                    );
+                }
+            }
+
+            #[test]
+            fn lenient_hex_agrees_with_strict_on_prefixed(s in "0x.*") {
+                prop_assert_eq!(
+                    parse_hex_allow_unprefixed::<Vec<u8>>(&s),
+                    parse_hex::<Vec<u8>>(&s)
+                );
+            }
+
+            #[test]
+            fn arbitrary_strings_do_not_panic(s in ".*") {
+                let _ = serde_json::from_value::<HexBytes>(serde_json::json!({ "bytes": s }));
+                let _ = serde_json::from_value::<StrictHexBytes>(serde_json::json!({ "bytes": s }));
+            }
+        }
+    }
 }
@@ -2,6 +2,8 @@ pub mod client_state;
 pub mod consensus_state;
 pub mod fraction;
 pub mod header;
+#[cfg(feature = "proto")]
+pub mod validation;
 
 pub use crate::{
     client_state::ClientState, consensus_state::ConsensusState, fraction::Fraction, header::Header,
@@ -0,0 +1,404 @@
+//! Sanity checks for a [`ClientState`] before it is used to create a client.
+//!
+//! A client created with the wrong proof specs or parameters is accepted by the host chain, and
+//! only fails once the first proof is verified against it.
+
+use std::borrow::Cow;
+
+use unionlabs::{
+    cosmos::ics23::{
+        hash_op::HashOp,
+        inner_spec::{InnerSpec, PositiveI32AsUsize},
+        leaf_op::LeafOp,
+        length_op::LengthOp,
+        proof_spec::ProofSpec,
+    },
+    encoding::{EncodeAs, Proto},
+    google::protobuf::duration::Duration,
+    result_unwrap,
+};
+
+use crate::{ClientState, Fraction};
+
+/// The proof spec of the IAVL tree of the cosmos-sdk multistore.
+pub const IAVL_PROOF_SPEC: ProofSpec = ProofSpec {
+    leaf_spec: LeafOp {
+        hash: HashOp::Sha256,
+        prehash_key: HashOp::NoHash,
+        prehash_value: HashOp::Sha256,
+        length: LengthOp::VarProto,
+        prefix: Cow::Borrowed(&[0]),
+    },
+    inner_spec: InnerSpec {
+        child_order: Cow::Borrowed(
+            const {
+                &[
+                    result_unwrap!(PositiveI32AsUsize::new_const(0)),
+                    result_unwrap!(PositiveI32AsUsize::new_const(1)),
+                ]
+            },
+        ),
+        child_size: result_unwrap!(PositiveI32AsUsize::new_const(33)),
+        min_prefix_length: result_unwrap!(PositiveI32AsUsize::new_const(4)),
+        max_prefix_length: result_unwrap!(PositiveI32AsUsize::new_const(12)),
+        empty_child: Cow::Borrowed(&[]),
+        hash: HashOp::Sha256,
+    },
+    max_depth: None,
+    min_depth: None,
+    prehash_key_before_comparison: false,
+};
+
+/// The proof spec of the simple merkle tree the cosmos-sdk app hash is built from.
+pub const TENDERMINT_PROOF_SPEC: ProofSpec = ProofSpec {
+    leaf_spec: LeafOp {
+        hash: HashOp::Sha256,
+        prehash_key: HashOp::NoHash,
+        prehash_value: HashOp::Sha256,
+        length: LengthOp::VarProto,
+        prefix: Cow::Borrowed(&[0]),
+    },
+    inner_spec: InnerSpec {
+        child_order: Cow::Borrowed(
+            const {
+                &[
+                    result_unwrap!(PositiveI32AsUsize::new_const(0)),
+                    result_unwrap!(PositiveI32AsUsize::new_const(1)),
+                ]
+            },
+        ),
+        child_size: result_unwrap!(PositiveI32AsUsize::new_const(32)),
+        min_prefix_length: result_unwrap!(PositiveI32AsUsize::new_const(1)),
+        max_prefix_length: result_unwrap!(PositiveI32AsUsize::new_const(1)),
+        empty_child: Cow::Borrowed(&[]),
+        hash: HashOp::Sha256,
+    },
+    max_depth: None,
+    min_depth: None,
+    prehash_key_before_comparison: false,
+};
+
+/// The proof specs of a cosmos-sdk chain, in the order they are applied. This is the value of
+/// `commitmenttypes.GetSDKSpecs()` in ibc-go.
+pub const CANONICAL_PROOF_SPECS: [ProofSpec; 2] = [IAVL_PROOF_SPEC, TENDERMINT_PROOF_SPEC];
+
+/// The trust levels that are used in practice. Any other value in `(0, 1]` is valid, but almost
+/// certainly a mistake.
+pub const COMMON_TRUST_LEVELS: [(u64, u64); 2] = [(1, 3), (2, 3)];
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(tag = "violation", rename_all = "snake_case")
+)]
+pub enum ClientStateViolation {
+    #[error("the proof specs are not the canonical iavl and tendermint proof specs")]
+    NonCanonicalProofSpecs,
+    #[error(
+        "trust level {}/{} is not in (0, 1]",
+        .trust_level.numerator,
+        .trust_level.denominator
+    )]
+    TrustLevelOutOfRange { trust_level: Fraction },
+    #[error(
+        "trust level {}/{} is unusual, it is typically 1/3 or 2/3",
+        .trust_level.numerator,
+        .trust_level.denominator
+    )]
+    UncommonTrustLevel { trust_level: Fraction },
+    #[error(
+        "trusting period {trusting_period} is not shorter than the unbonding period \
+        {unbonding_period}"
+    )]
+    TrustingPeriodTooLong {
+        trusting_period: Duration,
+        unbonding_period: Duration,
+    },
+    #[error(
+        "the unbonding period of the client state ({client_state}) does not match the \
+        unbonding period of the chain ({chain})"
+    )]
+    UnbondingPeriodMismatch {
+        client_state: Duration,
+        chain: Duration,
+    },
+    #[error("max clock drift {max_clock_drift} is not positive")]
+    NonPositiveMaxClockDrift { max_clock_drift: Duration },
+}
+
+impl ClientStateViolation {
+    /// Whether a client can still be created with this violation, i.e. the value is valid, but
+    /// unusual.
+    #[must_use]
+    pub fn is_warning(&self) -> bool {
+        matches!(self, Self::UncommonTrustLevel { .. })
+    }
+}
+
+impl ClientState {
+    /// Check this client state for values that are invalid or almost certainly a mistake.
+    ///
+    /// `chain_unbonding_period` is the unbonding period of the chain this client tracks, as
+    /// reported by the chain itself. If it is `None`, only the checks that don't require it are
+    /// run.
+    #[must_use]
+    pub fn validate(&self, chain_unbonding_period: Option<Duration>) -> Vec<ClientStateViolation> {
+        let mut violations = vec![];
+
+        if !is_canonical(&self.proof_specs) {
+            violations.push(ClientStateViolation::NonCanonicalProofSpecs);
+        }
+
+        let Fraction {
+            numerator,
+            denominator,
+        } = self.trust_level;
+
+        if numerator == 0 || numerator > denominator.get() {
+            violations.push(ClientStateViolation::TrustLevelOutOfRange {
+                trust_level: self.trust_level.clone(),
+            });
+        } else if !COMMON_TRUST_LEVELS
+            .iter()
+            // compare the ratio, such that e.g. 2/6 is still recognized as 1/3
+            .any(|(n, d)| {
+                u128::from(numerator) * u128::from(*d)
+                    == u128::from(*n) * u128::from(denominator.get())
+            })
+        {
+            violations.push(ClientStateViolation::UncommonTrustLevel {
+                trust_level: self.trust_level.clone(),
+            });
+        }
+
+        if self.trusting_period >= self.unbonding_period {
+            violations.push(ClientStateViolation::TrustingPeriodTooLong {
+                trusting_period: self.trusting_period,
+                unbonding_period: self.unbonding_period,
+            });
+        }
+
+        if let Some(chain_unbonding_period) = chain_unbonding_period {
+            if chain_unbonding_period != self.unbonding_period {
+                violations.push(ClientStateViolation::UnbondingPeriodMismatch {
+                    client_state: self.unbonding_period,
+                    chain: chain_unbonding_period,
+                });
+
+                if self.trusting_period >= chain_unbonding_period {
+                    violations.push(ClientStateViolation::TrustingPeriodTooLong {
+                        trusting_period: self.trusting_period,
+                        unbonding_period: chain_unbonding_period,
+                    });
+                }
+            }
+        }
+
+        if self.max_clock_drift.as_nanos().inner() <= 0 {
+            violations.push(ClientStateViolation::NonPositiveMaxClockDrift {
+                max_clock_drift: self.max_clock_drift,
+            });
+        }
+
+        violations
+    }
+}
+
+/// Compare the proof specs against [`CANONICAL_PROOF_SPECS`] by their proto encoding.
+fn is_canonical(proof_specs: &[ProofSpec]) -> bool {
+    proof_specs.len() == CANONICAL_PROOF_SPECS.len()
+        && proof_specs
+            .iter()
+            .zip(CANONICAL_PROOF_SPECS)
+            .all(|(spec, canonical)| {
+                spec.clone().encode_as::<Proto>() == canonical.encode_as::<Proto>()
+            })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU64;
+
+    use hex_literal::hex;
+    use unionlabs::ibc::core::client::height::Height;
+
+    use super::*;
+
+    fn duration(seconds: i64) -> Duration {
+        Duration::new(seconds, 0).unwrap()
+    }
+
+    fn trust_level(numerator: u64, denominator: u64) -> Fraction {
+        Fraction {
+            numerator,
+            denominator: NonZeroU64::new(denominator).unwrap(),
+        }
+    }
+
+    fn client_state() -> ClientState {
+        ClientState {
+            chain_id: "union-testnet-9".to_owned(),
+            trust_level: trust_level(1, 3),
+            trusting_period: duration(1_468_800),
+            unbonding_period: duration(1_728_000),
+            max_clock_drift: duration(600),
+            frozen_height: None,
+            latest_height: Height::new_with_revision(9, 100),
+            proof_specs: CANONICAL_PROOF_SPECS.into(),
+            upgrade_path: vec!["upgrade".into(), "upgradedIBCState".into()],
+        }
+    }
+
+    #[test]
+    fn canonical_proof_specs_match_ibc_go() {
+        // proto encoding of `commitmenttypes.GetSDKSpecs()`, i.e. `ics23.IavlSpec` and
+        // `ics23.TendermintSpec`
+        assert_eq!(
+            IAVL_PROOF_SPEC.encode_as::<Proto>(),
+            hex!("0a090801180120012a0100120c0a02000110211804200c3001")
+        );
+        assert_eq!(
+            TENDERMINT_PROOF_SPEC.encode_as::<Proto>(),
+            hex!("0a090801180120012a0100120c0a0200011020180120013001")
+        );
+
+        assert_eq!(CANONICAL_PROOF_SPECS, ics23::ibc_api::SDK_SPECS);
+    }
+
+    #[test]
+    fn valid() {
+        assert_eq!(client_state().validate(None), []);
+        assert_eq!(client_state().validate(Some(duration(1_728_000))), []);
+        assert_eq!(
+            ClientState {
+                trust_level: trust_level(2, 3),
+                ..client_state()
+            }
+            .validate(None),
+            []
+        );
+        assert_eq!(
+            ClientState {
+                trust_level: trust_level(2, 6),
+                ..client_state()
+            }
+            .validate(None),
+            []
+        );
+    }
+
+    #[test]
+    fn non_canonical_proof_specs() {
+        for proof_specs in [
+            vec![],
+            vec![IAVL_PROOF_SPEC],
+            vec![TENDERMINT_PROOF_SPEC, IAVL_PROOF_SPEC],
+            vec![
+                IAVL_PROOF_SPEC,
+                ProofSpec {
+                    prehash_key_before_comparison: true,
+                    ..TENDERMINT_PROOF_SPEC
+                },
+            ],
+        ] {
+            assert_eq!(
+                ClientState {
+                    proof_specs,
+                    ..client_state()
+                }
+                .validate(None),
+                [ClientStateViolation::NonCanonicalProofSpecs]
+            );
+        }
+    }
+
+    #[test]
+    fn trust_level_out_of_range() {
+        for trust_level in [trust_level(0, 3), trust_level(4, 3)] {
+            assert_eq!(
+                ClientState {
+                    trust_level: trust_level.clone(),
+                    ..client_state()
+                }
+                .validate(None),
+                [ClientStateViolation::TrustLevelOutOfRange { trust_level }]
+            );
+        }
+    }
+
+    #[test]
+    fn uncommon_trust_level() {
+        let violations = ClientState {
+            trust_level: trust_level(1, 1),
+            ..client_state()
+        }
+        .validate(None);
+
+        assert_eq!(
+            violations,
+            [ClientStateViolation::UncommonTrustLevel {
+                trust_level: trust_level(1, 1)
+            }]
+        );
+        assert!(violations[0].is_warning());
+    }
+
+    #[test]
+    fn trusting_period_too_long() {
+        assert_eq!(
+            ClientState {
+                trusting_period: duration(1_728_000),
+                ..client_state()
+            }
+            .validate(None),
+            [ClientStateViolation::TrustingPeriodTooLong {
+                trusting_period: duration(1_728_000),
+                unbonding_period: duration(1_728_000),
+            }]
+        );
+    }
+
+    #[test]
+    fn unbonding_period_mismatch() {
+        assert_eq!(
+            client_state().validate(Some(duration(1_814_400))),
+            [ClientStateViolation::UnbondingPeriodMismatch {
+                client_state: duration(1_728_000),
+                chain: duration(1_814_400),
+            }]
+        );
+
+        // the chain's unbonding period is shorter than the trusting period of the client
+        assert_eq!(
+            client_state().validate(Some(duration(86_400))),
+            [
+                ClientStateViolation::UnbondingPeriodMismatch {
+                    client_state: duration(1_728_000),
+                    chain: duration(86_400),
+                },
+                ClientStateViolation::TrustingPeriodTooLong {
+                    trusting_period: duration(1_468_800),
+                    unbonding_period: duration(86_400),
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn non_positive_max_clock_drift() {
+        for max_clock_drift in [duration(0), duration(-1)] {
+            let violations = ClientState {
+                max_clock_drift,
+                ..client_state()
+            }
+            .validate(None);
+
+            assert_eq!(
+                violations,
+                [ClientStateViolation::NonPositiveMaxClockDrift { max_clock_drift }]
+            );
+            assert!(!violations[0].is_warning());
+        }
+    }
+}
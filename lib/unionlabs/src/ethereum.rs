@@ -21,6 +21,9 @@ pub const IBC_HANDLER_COMMITMENTS_SLOT: U256 = U256::from_limbs([0, 0, 0, 0]);
 /// Calculates the slot for a `path` at saved in the commitment map in `slot`
 ///
 /// key: `keccak256(keccak256(abi.encode_packed(path)) || slot)`
+///
+/// This does not allocate, and as such is usable in `no_std` contexts.
+#[inline]
 #[must_use = "calculating the commitment key has no effect"]
 pub fn ibc_commitment_key(path: H256) -> U256 {
     Slot::Mapping(
@@ -2,3 +2,4 @@ pub mod genesis_metadata;
 pub mod height;
 pub mod msg_create_client;
 pub mod msg_update_client;
+pub mod msg_upgrade_client;
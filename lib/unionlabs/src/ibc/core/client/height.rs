@@ -74,6 +74,22 @@ impl Height {
         }
     }
 
+    /// This height with the revision number removed, for contexts where heights are plain
+    /// numbers.
+    #[must_use]
+    pub const fn without_revision(self) -> Self {
+        Self::new(self.height)
+    }
+
+    /// Display only the height, without the revision number. Unlike the [`Display`] impl of
+    /// [`Height`], this never prints a revision number, even with the alternate flag.
+    ///
+    /// [`Display`]: fmt::Display
+    #[must_use]
+    pub const fn display_bare(&self) -> DisplayBare {
+        DisplayBare(self.height)
+    }
+
     #[must_use]
     pub const fn increment(self) -> Self {
         Self {
@@ -152,6 +168,16 @@ impl fmt::Display for Height {
     }
 }
 
+/// See [`Height::display_bare`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DisplayBare(u64);
+
+impl fmt::Display for DisplayBare {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
 // #[cfg(feature = "serde")]
 impl serde::Serialize for Height {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
@@ -209,6 +235,34 @@ impl<'de> serde::Deserialize<'de> for Height {
     }
 }
 
+/// A bare representation of [`Height`], without the revision number. Use with
+/// `#[serde(with = "...")]` for heights in contexts where heights are plain numbers.
+///
+/// Both bare and revision-prefixed heights are accepted when deserializing, the revision number is
+/// dropped.
+// #[cfg(feature = "serde")]
+pub mod bare {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::Height;
+
+    pub fn serialize<S: Serializer>(height: &Height, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.collect_str(&height.display_bare())
+        } else {
+            height.height().serialize(serializer)
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Height, D::Error> {
+        if deserializer.is_human_readable() {
+            Height::deserialize(deserializer).map(Height::without_revision)
+        } else {
+            u64::deserialize(deserializer).map(Height::new)
+        }
+    }
+}
+
 #[cfg(feature = "schemars")]
 impl ::schemars::JsonSchema for Height {
     fn schema_name() -> String {
@@ -352,6 +406,58 @@ mod tests {
         );
     }
 
+    #[test]
+    fn display_bare() {
+        assert_eq!(
+            Height::new_with_revision(1, 12345)
+                .display_bare()
+                .to_string(),
+            "12345"
+        );
+        assert_eq!(Height::new(12345).display_bare().to_string(), "12345");
+        assert_eq!(format!("{:#}", Height::new(12345).display_bare()), "12345");
+    }
+
+    #[test]
+    fn bare_serde_round_trip() {
+        #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+        struct Bare(#[serde(with = "bare")] Height);
+
+        assert_eq!(
+            serde_json::to_string(&Bare(Height::new_with_revision(1, 12345))).unwrap(),
+            r#""12345""#
+        );
+        assert_eq!(
+            serde_json::to_string(&Bare(Height::new(12345))).unwrap(),
+            r#""12345""#
+        );
+
+        for json in [r#""12345""#, r#""1-12345""#] {
+            assert_eq!(
+                serde_json::from_str::<Bare>(json).unwrap(),
+                Bare(Height::new(12345))
+            );
+        }
+
+        // non human readable formats encode the height as a plain number
+        let bz = bincode::serialize(&Bare(Height::new_with_revision(1, 12345))).unwrap();
+        assert_eq!(bz, 12345_u64.to_le_bytes());
+        assert_eq!(
+            bincode::deserialize::<Bare>(&bz).unwrap(),
+            Bare(Height::new(12345))
+        );
+    }
+
+    #[test]
+    fn serde_round_trip() {
+        for height in [Height::new(12345), Height::new_with_revision(1, 12345)] {
+            let json = serde_json::to_string(&height).unwrap();
+
+            assert_eq!(json, format!(r#""{height}""#));
+            assert_eq!(serde_json::from_str::<Height>(&json).unwrap(), height);
+        }
+    }
+
     #[test]
     fn from_str() {
         assert_eq!(
@@ -0,0 +1,12 @@
+use macros::model;
+
+use crate::{bytes::Bytes, id::ClientId};
+
+#[model(proto(raw(protos::ibc::core::client::v1::MsgUpgradeClient)))]
+pub struct MsgUpgradeClient {
+    pub client_id: ClientId,
+    pub client_state: Bytes,
+    pub consensus_state: Bytes,
+    pub proof_upgrade_client: Bytes,
+    pub proof_upgrade_consensus_state: Bytes,
+}
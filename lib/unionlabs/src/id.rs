@@ -30,6 +30,9 @@ pub struct ClientId {
 }
 
 impl ClientId {
+    pub const MIN_LEN: usize = 9;
+    pub const MAX_LEN: usize = 64;
+
     #[must_use]
     pub fn new(prefix: impl Into<Cow<'static, str>>, id: u32) -> Self {
         Self {
@@ -70,17 +73,17 @@ impl FromStr for ClientId {
     type Err = ParsePrefixedIdError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        // NOTE: rsplit bc prefixes can contain `-`
-        let Some((prefix, id)) = s.rsplit_once(DELIMITER) else {
-            return Err(ParsePrefixedIdError::MissingPrefix);
-        };
-
-        id.parse()
-            .map(|id| Self {
-                prefix: prefix.to_owned().into(),
-                id,
-            })
-            .map_err(ParsePrefixedIdError::ParseIntError)
+        validate_id(s, Self::MIN_LEN, Self::MAX_LEN).map_err(|violation| Ics24IdParseError {
+            id: s.to_owned(),
+            violation,
+        })?;
+
+        let (prefix, id) = try_parse_numeric_suffix(s)?;
+
+        Ok(Self {
+            prefix: prefix.to_owned().into(),
+            id,
+        })
     }
 }
 
@@ -109,20 +112,17 @@ impl ConnectionId {
     }
 
     pub fn from_str_prefixed(s: &str) -> Result<Self, ParsePrefixedIdError> {
-        let Some((prefix, id)) = s.split_once(DELIMITER) else {
-            return Err(ParsePrefixedIdError::MissingPrefix);
-        };
+        let (prefix, id) = try_parse_numeric_suffix(s)?;
 
         if prefix != CONNECTION_ID_PREFIX {
             return Err(ParsePrefixedIdError::UnexpectedPrefix {
+                id: s.to_owned(),
                 expected: CONNECTION_ID_PREFIX,
-                found: s.to_owned(),
+                found: prefix.to_owned(),
             });
         }
 
-        id.parse()
-            .map(Self)
-            .map_err(ParsePrefixedIdError::ParseIntError)
+        Ok(Self(id))
     }
 
     /// Formats this [`ConnectionId`] with it's alternate [`Display`] formatting, prefixing the ID with [`CONNECTION_ID_PREFIX`].
@@ -179,20 +179,17 @@ impl ChannelId {
     }
 
     pub fn from_str_prefixed(s: &str) -> Result<Self, ParsePrefixedIdError> {
-        let Some((prefix, id)) = s.split_once(DELIMITER) else {
-            return Err(ParsePrefixedIdError::MissingPrefix);
-        };
+        let (prefix, id) = try_parse_numeric_suffix(s)?;
 
         if prefix != CHANNEL_ID_PREFIX {
             return Err(ParsePrefixedIdError::UnexpectedPrefix {
+                id: s.to_owned(),
                 expected: CHANNEL_ID_PREFIX,
-                found: s.to_owned(),
+                found: prefix.to_owned(),
             });
         }
 
-        id.parse()
-            .map(Self)
-            .map_err(ParsePrefixedIdError::ParseIntError)
+        Ok(Self(id))
     }
 
     /// Formats this [`ChannelId`] with it's alternate [`Display`] formatting, prefixing the ID with [`CHANNEL_ID_PREFIX`].
@@ -240,15 +237,62 @@ pub struct PortId(#[doc(hidden)] Cow<'static, str>);
 
 #[derive(Debug, Clone, PartialEq, thiserror::Error)]
 pub enum ParsePrefixedIdError {
-    #[error("missing prefix")]
-    MissingPrefix,
-    #[error("expected prefix `{expected}` but found `{found}`")]
+    #[error("identifier `{id}` is not of the form `<prefix>-<number>`")]
+    MissingPrefix { id: String },
+    #[error("identifier `{id}` has prefix `{found}`, expected `{expected}`")]
     UnexpectedPrefix {
+        id: String,
         expected: &'static str,
         found: String,
     },
-    #[error("error parsing integer portion of id")]
-    ParseIntError(#[from] ParseIntError),
+    #[error(
+        "identifier `{id}` has a non-canonical numeric suffix, expected decimal digits with no \
+        sign or leading zeros"
+    )]
+    NonCanonicalSuffix { id: String },
+    #[error("identifier `{id}` has a numeric suffix that is out of range for a u32")]
+    ParseIntError {
+        id: String,
+        #[source]
+        source: ParseIntError,
+    },
+    #[error(transparent)]
+    InvalidIdentifier(#[from] Ics24IdParseError),
+}
+
+/// Split an identifier of the form `<prefix>-<number>` into its prefix and numeric suffix.
+///
+/// The suffix must be the canonical decimal representation of a `u32` (no sign and no leading
+/// zeros), such that formatting the parsed id reproduces the input exactly. The prefix may itself
+/// contain `-`, but must not be empty.
+///
+/// ```rust
+/// # use unionlabs::id::try_parse_numeric_suffix;
+/// assert_eq!(try_parse_numeric_suffix("08-wasm-12"), Ok(("08-wasm", 12)));
+/// assert!(try_parse_numeric_suffix("channel-012").is_err());
+/// assert!(try_parse_numeric_suffix("channel-+1").is_err());
+/// ```
+pub fn try_parse_numeric_suffix(s: &str) -> Result<(&str, u32), ParsePrefixedIdError> {
+    // NOTE: rsplit bc prefixes can contain `-`
+    let Some((prefix, suffix)) = s.rsplit_once(DELIMITER).filter(|(p, _)| !p.is_empty()) else {
+        return Err(ParsePrefixedIdError::MissingPrefix { id: s.to_owned() });
+    };
+
+    // u32::from_str accepts a leading `+`, and both that and leading zeros would not roundtrip
+    if suffix.is_empty()
+        || !suffix.bytes().all(|b| b.is_ascii_digit())
+        || (suffix.len() > 1 && suffix.starts_with('0'))
+    {
+        return Err(ParsePrefixedIdError::NonCanonicalSuffix { id: s.to_owned() });
+    }
+
+    suffix
+        .parse()
+        .map(|id| (prefix, id))
+        .map_err(|source| ParsePrefixedIdError::ParseIntError {
+            id: s.to_owned(),
+            source,
+        })
 }
 
 impl PortId {
@@ -258,12 +302,18 @@ impl PortId {
     pub fn new(s: impl Into<Cow<'static, str>>) -> Result<Self, Ics24IdParseError> {
         let s: Cow<'_, str> = s.into();
 
-        validate_id(&s, Self::MIN_LEN, Self::MAX_LEN)?;
-
-        Ok(Self(s))
+        match validate_id(&s, Self::MIN_LEN, Self::MAX_LEN) {
+            Ok(()) => Ok(Self(s)),
+            Err(violation) => Err(Ics24IdParseError {
+                id: s.into_owned(),
+                violation,
+            }),
+        }
     }
 
-    pub const fn new_static(s: &'static str) -> Result<Self, Ics24IdParseError> {
+    /// Const version of [`Self::new`]. The error does not contain the identifier, since it can't be
+    /// allocated in a const context.
+    pub const fn new_static(s: &'static str) -> Result<Self, Ics24IdViolation> {
         if let Err(e) = validate_id(s, Self::MIN_LEN, Self::MAX_LEN) {
             return Err(e);
         }
@@ -277,25 +327,21 @@ impl PortId {
     }
 }
 
-const fn validate_id(s: &str, min_len: usize, max_len: usize) -> Result<(), Ics24IdParseError> {
+const fn validate_id(s: &str, min_len: usize, max_len: usize) -> Result<(), Ics24IdViolation> {
     let len = s.len();
 
     if len < min_len || len > max_len {
-        return Err(Ics24IdParseError::InvalidLength(InvalidLength {
+        return Err(Ics24IdViolation::InvalidLength(InvalidLength {
             expected: ExpectedLength::Between(min_len, max_len),
             found: len,
         }));
     }
 
-    let mut i = len - 1;
+    let mut i = 0;
     let bz = s.as_bytes();
 
     // https://github.com/cosmos/ibc/tree/main/spec/core/ics-024-host-requirements#paths-identifiers-separators
-    loop {
-        if i == 0 {
-            break;
-        }
-
+    while i < len {
         let c = bz[i];
         match c {
             b'a'..=b'z'
@@ -311,13 +357,13 @@ const fn validate_id(s: &str, min_len: usize, max_len: usize) -> Result<(), Ics2
             | b'<'
             | b'>' => {}
             _ => {
-                return Err(Ics24IdParseError::InvalidCharacter(
+                return Err(Ics24IdViolation::InvalidCharacter(
                     InvalidIcs024IdentifierCharacter(c),
                 ))
             }
         }
 
-        i -= 1;
+        i += 1;
     }
 
     Ok(())
@@ -352,7 +398,17 @@ impl fmt::Display for PortId {
 }
 
 #[derive(Debug, Clone, PartialEq, thiserror::Error)]
-pub enum Ics24IdParseError {
+#[error("invalid identifier `{id}`: {violation}")]
+pub struct Ics24IdParseError {
+    pub id: String,
+    pub violation: Ics24IdViolation,
+}
+
+/// The [ICS-024] rule that an identifier violates.
+///
+/// [ICS-024]: https://github.com/cosmos/ibc/tree/main/spec/core/ics-024-host-requirements#paths-identifiers-separators
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum Ics24IdViolation {
     #[error(transparent)]
     InvalidCharacter(InvalidIcs024IdentifierCharacter),
     #[error(transparent)]
@@ -363,28 +419,41 @@ pub enum Ics24IdParseError {
 // static_assertions::assert_impl_all!(ClientId: schemars::JsonSchema);
 
 #[derive(Debug, Clone, PartialEq, thiserror::Error)]
-#[error("invalid ics-024 identifier character: `{0:x}`")]
+#[error(
+    "invalid character {:?} (0x{:02x}), ics-024 identifiers may only contain alphanumerics and \
+    `._+-#[]<>`",
+    char::from(*.0),
+    .0
+)]
 pub struct InvalidIcs024IdentifierCharacter(u8);
 
 #[cfg(test)]
 mod tests {
+    use proptest::prelude::*;
+
     use super::*;
 
+    const ICS024_CHARSET: &str = "[a-zA-Z0-9._+#<>\\[\\]-]";
+
+    fn invalid_length(id: &str, min: usize, max: usize) -> Ics24IdParseError {
+        Ics24IdParseError {
+            id: id.to_owned(),
+            violation: Ics24IdViolation::InvalidLength(InvalidLength {
+                expected: ExpectedLength::Between(min, max),
+                found: id.len(),
+            }),
+        }
+    }
+
     #[test]
     fn port_id() {
         assert_eq!(
             PortId::new(""),
-            Err(Ics24IdParseError::InvalidLength(InvalidLength {
-                expected: ExpectedLength::Between(PortId::MIN_LEN, PortId::MAX_LEN),
-                found: 0
-            }))
+            Err(invalid_length("", PortId::MIN_LEN, PortId::MAX_LEN))
         );
         assert_eq!(
             PortId::new("a"),
-            Err(Ics24IdParseError::InvalidLength(InvalidLength {
-                expected: ExpectedLength::Between(PortId::MIN_LEN, PortId::MAX_LEN),
-                found: 1
-            }))
+            Err(invalid_length("a", PortId::MIN_LEN, PortId::MAX_LEN))
         );
         assert_eq!(PortId::new("aa").as_ref().map(PortId::as_str), Ok("aa"));
         assert_eq!(
@@ -393,18 +462,202 @@ mod tests {
                 .map(PortId::as_str),
             Ok(&*"a".repeat(PortId::MAX_LEN))
         );
+        assert_eq!(
+            PortId::new("abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ1234567890._+-#[]<>")
+                .map(|port_id| port_id.to_string()),
+            Ok(
+                "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ1234567890._+-#[]<>"
+                    .to_owned()
+            )
+        );
+        assert_eq!(
+            PortId::new("a/b"),
+            Err(Ics24IdParseError {
+                id: "a/b".to_owned(),
+                violation: Ics24IdViolation::InvalidCharacter(InvalidIcs024IdentifierCharacter(
+                    b'/'
+                )),
+            })
+        );
+    }
+
+    #[test]
+    fn port_id_first_character_is_validated() {
+        assert_eq!(
+            PortId::new("/transfer"),
+            Err(Ics24IdParseError {
+                id: "/transfer".to_owned(),
+                violation: Ics24IdViolation::InvalidCharacter(InvalidIcs024IdentifierCharacter(
+                    b'/'
+                )),
+            })
+        );
+        assert!(PortId::new_static(" transfer").is_err());
+    }
+
+    #[test]
+    fn client_id() {
+        assert_eq!(
+            "07-tendermint-0".parse(),
+            Ok(ClientId::new("07-tendermint", 0))
+        );
+        assert_eq!("08-wasm-12".parse(), Ok(ClientId::new("08-wasm", 12)));
+
+        assert_eq!(
+            "08-wasm-".parse::<ClientId>(),
+            Err(ParsePrefixedIdError::NonCanonicalSuffix {
+                id: "08-wasm-".to_owned()
+            })
+        );
+        assert_eq!(
+            "08-wasm-01".parse::<ClientId>(),
+            Err(ParsePrefixedIdError::NonCanonicalSuffix {
+                id: "08-wasm-01".to_owned()
+            })
+        );
+        assert_eq!(
+            "08-wasm-+1".parse::<ClientId>(),
+            Err(ParsePrefixedIdError::NonCanonicalSuffix {
+                id: "08-wasm-+1".to_owned()
+            })
+        );
+        assert!(matches!(
+            "08-wasm-4294967296".parse::<ClientId>(),
+            Err(ParsePrefixedIdError::ParseIntError { .. })
+        ));
+        assert_eq!(
+            "tendermint".parse::<ClientId>(),
+            Err(ParsePrefixedIdError::MissingPrefix {
+                id: "tendermint".to_owned()
+            })
+        );
+        assert_eq!(
+            "wasm-1".parse::<ClientId>(),
+            Err(ParsePrefixedIdError::InvalidIdentifier(invalid_length(
+                "wasm-1",
+                ClientId::MIN_LEN,
+                ClientId::MAX_LEN
+            )))
+        );
+        assert_eq!(
+            "08/wasm-1".parse::<ClientId>(),
+            Err(ParsePrefixedIdError::InvalidIdentifier(Ics24IdParseError {
+                id: "08/wasm-1".to_owned(),
+                violation: Ics24IdViolation::InvalidCharacter(InvalidIcs024IdentifierCharacter(
+                    b'/'
+                )),
+            }))
+        );
+    }
 
-        // assert_eq!(ics024("".into()), Ok("".into()));
-        // assert_eq!(ics024("valid".into()), Ok("valid".into()));
-        // assert_eq!(
-        //     ics024(
-        //         "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ1234567890._+-#[]<>".into()
-        //     ),
-        //     Ok("abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ1234567890._+-#[]<>".into())
-        // );
-        // assert_eq!(
-        //     ics024("/".into()),
-        //     Err(InvalidIcs024IdentifierCharacter(b'/'))
-        // );
+    #[test]
+    fn prefixed_ids() {
+        assert_eq!(
+            ConnectionId::from_str_prefixed("connection-3"),
+            Ok(ConnectionId::new(3))
+        );
+        assert_eq!(
+            ChannelId::from_str_prefixed("channel-0"),
+            Ok(ChannelId::new(0))
+        );
+
+        assert_eq!(
+            ChannelId::from_str_prefixed("connection-3"),
+            Err(ParsePrefixedIdError::UnexpectedPrefix {
+                id: "connection-3".to_owned(),
+                expected: CHANNEL_ID_PREFIX,
+                found: "connection".to_owned(),
+            })
+        );
+        assert_eq!(
+            ConnectionId::from_str_prefixed("-3"),
+            Err(ParsePrefixedIdError::MissingPrefix {
+                id: "-3".to_owned()
+            })
+        );
+        assert_eq!(
+            ChannelId::from_str_prefixed("channel-007"),
+            Err(ParsePrefixedIdError::NonCanonicalSuffix {
+                id: "channel-007".to_owned()
+            })
+        );
+    }
+
+    #[test]
+    fn deserialization_error_names_identifier_and_rule() {
+        let err = serde_json::from_str::<PortId>(r#""tr/nsfer""#)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("`tr/nsfer`"), "{err}");
+        assert!(err.contains("invalid character '/'"), "{err}");
+
+        let err = serde_json::from_str::<ClientId>(r#""08-wasm-01""#)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("`08-wasm-01`"), "{err}");
+        assert!(err.contains("non-canonical numeric suffix"), "{err}");
+
+        let err = serde_json::from_str::<ClientId>(r#""wasm-1""#)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("`wasm-1`"), "{err}");
+        assert!(err.contains("invalid length"), "{err}");
+    }
+
+    proptest! {
+        #[test]
+        fn arbitrary_strings_do_not_panic(s in ".*") {
+            let _ = s.parse::<ClientId>();
+            let _ = s.parse::<PortId>();
+            let _ = ConnectionId::from_str_prefixed(&s);
+            let _ = ChannelId::from_str_prefixed(&s);
+            let _ = try_parse_numeric_suffix(&s);
+        }
+
+        #[test]
+        fn valid_port_ids_are_accepted(s in format!("{ICS024_CHARSET}{{2,128}}")) {
+            prop_assert_eq!(PortId::new(s.clone()).map(|port_id| port_id.to_string()), Ok(s));
+        }
+
+        #[test]
+        fn port_ids_with_invalid_characters_are_rejected(
+            (prefix, c, suffix) in (
+                format!("{ICS024_CHARSET}{{0,10}}"),
+                "[^a-zA-Z0-9._+#<>\\[\\]-]",
+                format!("{ICS024_CHARSET}{{1,10}}"),
+            )
+        ) {
+            let s = format!("{prefix}{c}{suffix}");
+            prop_assert!(PortId::new(s).is_err());
+        }
+
+        #[test]
+        fn valid_client_ids_roundtrip(
+            (prefix, id) in (format!("{ICS024_CHARSET}{{8,53}}"), any::<u32>())
+        ) {
+            let s = format!("{prefix}-{id}");
+            let client_id = s.parse::<ClientId>();
+
+            prop_assert_eq!(
+                client_id.as_ref().map(ToString::to_string),
+                Ok(s.clone()),
+            );
+            prop_assert_eq!(client_id.map(|client_id| client_id.id()), Ok(id));
+        }
+
+        #[test]
+        fn prefixed_ids_roundtrip(id in any::<u32>()) {
+            let connection_id = ConnectionId::new(id);
+            prop_assert_eq!(
+                ConnectionId::from_str_prefixed(&connection_id.to_string_prefixed()),
+                Ok(connection_id)
+            );
+
+            let channel_id = ChannelId::new(id);
+            prop_assert_eq!(
+                ChannelId::from_str_prefixed(&channel_id.to_string_prefixed()),
+                Ok(channel_id)
+            );
+        }
     }
 }
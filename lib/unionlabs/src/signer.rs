@@ -39,6 +39,12 @@ impl CosmosSigner {
         self.signing_key.public_key().to_bytes()
     }
 
+    /// The bech32 prefix of the address of this signer.
+    #[must_use]
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
     /// Attempt to sign the given bytes.
     ///
     /// # Errors
@@ -0,0 +1,416 @@
+//! A typed client for the voyager JSON-RPC and REST apis.
+//!
+//! This is intended for tooling that needs to talk to a running voyager instance (dashboards,
+//! scripts, tests) without depending on any of the plugin or module server machinery. All
+//! request and response types are the ones voyager itself uses, re-exported from
+//! [`voyager_message`].
+//!
+//! ```no_run
+//! use std::time::Duration;
+//!
+//! use voyager_client::{
+//!     core::{ChainId, QueryHeight},
+//!     ops::{call, seq},
+//!     voyager_message::call::WaitForHeight,
+//!     ClientBuilder,
+//! };
+//!
+//! # async fn run() -> Result<(), voyager_client::Error> {
+//! let client = ClientBuilder::new("http://localhost:7178")
+//!     .rest_url("http://localhost:7177")
+//!     .auth_header("Bearer hunter2")
+//!     .timeout(Duration::from_secs(10))
+//!     .build()?;
+//!
+//! let chain_id = ChainId::new("union-testnet-9");
+//!
+//! let height = client.query_latest_height(chain_id.clone(), true).await?;
+//!
+//! client
+//!     .enqueue(seq([call(WaitForHeight {
+//!         chain_id,
+//!         height: height.increment(),
+//!         finalized: true,
+//!     })]))
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::time::Duration;
+
+use ibc_classic_spec::{AcknowledgementPath, CommitmentPath, NextSequenceRecvPath, ReceiptPath};
+use ibc_union_spec::{BatchPacketsPath, BatchReceiptsPath, COMMITMENT_MAGIC, COMMITMENT_NULL};
+use serde::{Deserialize, Serialize};
+use unionlabs::{
+    hash::H256,
+    ibc::core::{channel::order::Order, client::height::Height},
+};
+use voyager_message::{
+    core::{ChainId, ClientInfo, ClientStateMeta, IbcSpec, IbcStorePathKey, QueryHeight},
+    into_value,
+    module::LoadedModulesInfo,
+    rpc::{IbcProof, IbcState, VoyagerRpcClient},
+    RawClientId, VoyagerMessage,
+};
+use voyager_vm::Op;
+
+pub use crate::transport::{HttpTransport, Transport};
+
+pub mod mock;
+mod transport;
+
+pub use voyager_message::{self, core};
+
+/// Helpers for constructing [`Op`]s to submit with [`Client::enqueue`].
+///
+/// ```
+/// use voyager_client::{
+///     core::ChainId,
+///     ops::{call, conc, seq, Op},
+///     voyager_message::{call::WaitForHeight, VoyagerMessage},
+/// };
+/// use unionlabs::ibc::core::client::height::Height;
+///
+/// let wait = |chain_id: &str| {
+///     call(WaitForHeight {
+///         chain_id: ChainId::new(chain_id.to_owned()),
+///         height: Height::new(100),
+///         finalized: true,
+///     })
+/// };
+///
+/// let op: Op<VoyagerMessage> = seq([conc([wait("a"), wait("b")]), wait("c")]);
+///
+/// assert!(matches!(op, Op::Seq(_)));
+/// ```
+pub mod ops {
+    pub use voyager_vm::{call, conc, data, defer, noop, now, promise, seq, void, Op};
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("invalid auth header")]
+    InvalidAuthHeader,
+    #[error("rpc error")]
+    Rpc(#[from] jsonrpsee::core::client::Error),
+    #[error("error decoding IBC state")]
+    Decode(#[from] serde_json::Error),
+    #[error("rest error")]
+    Rest(#[from] reqwest::Error),
+    #[error("no rest url configured, ops cannot be enqueued")]
+    NoRestUrl,
+}
+
+/// Builder for a [`Client`] talking to voyager over http.
+#[derive(Debug, Clone)]
+pub struct ClientBuilder {
+    rpc_url: String,
+    rest_url: Option<String>,
+    auth_header: Option<String>,
+    timeout: Option<Duration>,
+}
+
+impl ClientBuilder {
+    /// `rpc_url` is the JSON-RPC endpoint of voyager (`rpc_laddr` in the voyager config).
+    pub fn new(rpc_url: impl Into<String>) -> Self {
+        Self {
+            rpc_url: rpc_url.into(),
+            rest_url: None,
+            auth_header: None,
+            timeout: None,
+        }
+    }
+
+    /// The REST endpoint of voyager (`rest_laddr` in the voyager config). This is required for
+    /// [`Client::enqueue`].
+    #[must_use]
+    pub fn rest_url(mut self, rest_url: impl Into<String>) -> Self {
+        self.rest_url = Some(rest_url.into());
+        self
+    }
+
+    /// Value of the `Authorization` header sent with every request, for voyager instances
+    /// running behind an authenticating proxy.
+    #[must_use]
+    pub fn auth_header(mut self, auth_header: impl Into<String>) -> Self {
+        self.auth_header = Some(auth_header.into());
+        self
+    }
+
+    /// Timeout applied to every request.
+    #[must_use]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn build(self) -> Result<Client<HttpTransport>, Error> {
+        HttpTransport::new(
+            &self.rpc_url,
+            self.rest_url,
+            self.auth_header.as_deref(),
+            self.timeout,
+        )
+        .map(Client::new)
+    }
+}
+
+/// The status of a packet, as seen from both ends of its channel.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PacketStatus {
+    /// The packet commitment on the source chain. This is `None` if the packet has not been
+    /// sent, or if the commitment has since been cleared.
+    pub commitment: Option<H256>,
+    /// Whether the packet has been received on the destination chain.
+    pub received: bool,
+    /// The acknowledgement commitment on the destination chain, if the packet has been
+    /// acknowledged.
+    pub acknowledgement: Option<H256>,
+}
+
+/// A typed voyager client.
+///
+/// ```
+/// use voyager_client::{core::ChainId, mock::MockTransport, Client};
+/// use unionlabs::ibc::core::client::height::Height;
+///
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() {
+/// let transport = MockTransport::new();
+/// transport.respond_with("voyager_queryLatestHeight", Height::new(10));
+///
+/// let client = Client::new(transport);
+///
+/// let height = client
+///     .query_latest_height(ChainId::new("union-testnet-9"), true)
+///     .await
+///     .unwrap();
+///
+/// assert_eq!(height, Height::new(10));
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Client<T = HttpTransport> {
+    transport: T,
+}
+
+impl<T: Transport> Client<T> {
+    pub fn new(transport: T) -> Self {
+        Self { transport }
+    }
+
+    pub fn transport(&self) -> &T {
+        &self.transport
+    }
+
+    /// Info about all of the modules and plugins loaded by voyager.
+    pub async fn info(&self) -> Result<LoadedModulesInfo, Error> {
+        Ok(self.transport.rpc().info().await?)
+    }
+
+    pub async fn query_latest_height(
+        &self,
+        chain_id: ChainId,
+        finalized: bool,
+    ) -> Result<Height, Error> {
+        Ok(self
+            .transport
+            .rpc()
+            .query_latest_height(chain_id, finalized)
+            .await?)
+    }
+
+    pub async fn query_latest_timestamp(
+        &self,
+        chain_id: ChainId,
+        finalized: bool,
+    ) -> Result<i64, Error> {
+        Ok(self
+            .transport
+            .rpc()
+            .query_latest_timestamp(chain_id, finalized)
+            .await?)
+    }
+
+    pub async fn client_info<V: IbcSpec>(
+        &self,
+        chain_id: ChainId,
+        client_id: V::ClientId,
+    ) -> Result<ClientInfo, Error> {
+        Ok(self
+            .transport
+            .rpc()
+            .client_info(chain_id, V::ID, RawClientId::new(client_id))
+            .await?)
+    }
+
+    pub async fn client_meta<V: IbcSpec>(
+        &self,
+        chain_id: ChainId,
+        at: QueryHeight,
+        client_id: V::ClientId,
+    ) -> Result<ClientStateMeta, Error> {
+        Ok(self
+            .transport
+            .rpc()
+            .client_meta(chain_id, V::ID, at, RawClientId::new(client_id))
+            .await?)
+    }
+
+    pub async fn query_spec_ibc_state<P: IbcStorePathKey>(
+        &self,
+        chain_id: ChainId,
+        height: QueryHeight,
+        path: P,
+    ) -> Result<IbcState<P::Value>, Error> {
+        let ibc_state = self
+            .transport
+            .rpc()
+            .query_ibc_state(
+                chain_id,
+                P::Spec::ID,
+                height,
+                into_value(<P::Spec as IbcSpec>::StorePath::from(path.into())),
+            )
+            .await?;
+
+        Ok(IbcState {
+            height: ibc_state.height,
+            state: serde_json::from_value(ibc_state.state)?,
+        })
+    }
+
+    pub async fn query_ibc_proof<P: IbcStorePathKey>(
+        &self,
+        chain_id: ChainId,
+        height: QueryHeight,
+        path: P,
+    ) -> Result<IbcProof, Error> {
+        Ok(self
+            .transport
+            .rpc()
+            .query_ibc_proof(
+                chain_id,
+                P::Spec::ID,
+                height,
+                into_value(<P::Spec as IbcSpec>::StorePath::from(path.into())),
+            )
+            .await?)
+    }
+
+    /// Query the status of an IBC classic packet, sent from `source_chain_id` to
+    /// `destination_chain_id`. All state is read at the latest height of the respective chain.
+    pub async fn v1_packet_status(
+        &self,
+        source_chain_id: ChainId,
+        destination_chain_id: ChainId,
+        packet: &ibc_classic_spec::PacketMetadata,
+    ) -> Result<PacketStatus, Error> {
+        let source = &packet.source_channel;
+        let destination = &packet.destination_channel;
+
+        let commitment = self
+            .query_spec_ibc_state(
+                source_chain_id,
+                QueryHeight::Latest,
+                CommitmentPath {
+                    port_id: source.port_id.clone(),
+                    channel_id: source.channel_id.clone(),
+                    sequence: packet.sequence,
+                },
+            )
+            .await?
+            .state;
+
+        // ordered channels don't write receipts
+        let received = if packet.channel_ordering == Order::Ordered {
+            self.query_spec_ibc_state(
+                destination_chain_id.clone(),
+                QueryHeight::Latest,
+                NextSequenceRecvPath {
+                    port_id: destination.port_id.clone(),
+                    channel_id: destination.channel_id.clone(),
+                },
+            )
+            .await?
+            .state
+                > packet.sequence.get()
+        } else {
+            self.query_spec_ibc_state(
+                destination_chain_id.clone(),
+                QueryHeight::Latest,
+                ReceiptPath {
+                    port_id: destination.port_id.clone(),
+                    channel_id: destination.channel_id.clone(),
+                    sequence: packet.sequence,
+                },
+            )
+            .await?
+            .state
+        };
+
+        let acknowledgement = self
+            .query_spec_ibc_state(
+                destination_chain_id,
+                QueryHeight::Latest,
+                AcknowledgementPath {
+                    port_id: destination.port_id.clone(),
+                    channel_id: destination.channel_id.clone(),
+                    sequence: packet.sequence,
+                },
+            )
+            .await?
+            .state;
+
+        Ok(PacketStatus {
+            commitment,
+            received,
+            acknowledgement,
+        })
+    }
+
+    /// Query the status of an IBC union packet, sent from `source_chain_id` to
+    /// `destination_chain_id`. All state is read at the latest height of the respective chain.
+    pub async fn union_packet_status(
+        &self,
+        source_chain_id: ChainId,
+        destination_chain_id: ChainId,
+        packet: &ibc_solidity::Packet,
+    ) -> Result<PacketStatus, Error> {
+        let commitment = self
+            .query_spec_ibc_state(
+                source_chain_id,
+                QueryHeight::Latest,
+                BatchPacketsPath {
+                    channel_id: packet.source_channel,
+                    batch_hash: ibc_union_spec::commit_packet(packet),
+                },
+            )
+            .await?
+            .state;
+
+        // the receipt is COMMITMENT_MAGIC once the packet is received, and is overwritten with
+        // the acknowledgement commitment once the packet is acknowledged
+        let receipt = self
+            .query_spec_ibc_state(
+                destination_chain_id,
+                QueryHeight::Latest,
+                BatchReceiptsPath::from_packet(packet),
+            )
+            .await?
+            .state;
+
+        Ok(PacketStatus {
+            commitment: (commitment != COMMITMENT_NULL).then_some(commitment),
+            received: receipt != COMMITMENT_NULL,
+            acknowledgement: (receipt != COMMITMENT_NULL && receipt != COMMITMENT_MAGIC)
+                .then_some(receipt),
+        })
+    }
+
+    /// Submit `op` to the voyager queue.
+    pub async fn enqueue(&self, op: Op<VoyagerMessage>) -> Result<(), Error> {
+        self.transport.enqueue(op).await
+    }
+}
@@ -0,0 +1,163 @@
+//! An in-memory [`Transport`] for testing code built on [`Client`](crate::Client), without a
+//! running voyager instance.
+
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+use jsonrpsee::{
+    core::{
+        async_trait,
+        client::{BatchResponse, ClientT, Error as ClientError},
+        params::BatchRequestBuilder,
+        traits::ToRpcParams,
+    },
+    types::{error::METHOD_NOT_FOUND_CODE, ErrorObject, ErrorObjectOwned},
+};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use voyager_message::VoyagerMessage;
+use voyager_vm::Op;
+
+use crate::{transport::Transport, Error};
+
+type Handler = Arc<dyn Fn(Value) -> Result<Value, ErrorObjectOwned> + Send + Sync>;
+
+/// A [`Transport`] that answers JSON-RPC requests with registered handlers, and records all
+/// requests and enqueued ops.
+///
+/// Methods are registered by their full name, including the namespace (i.e.
+/// `voyager_clientInfo`). Requests to methods without a handler fail with a "method not found"
+/// error, as they would against a real server.
+#[derive(Debug, Default)]
+pub struct MockTransport {
+    rpc: MockRpc,
+    enqueued: Mutex<Vec<Op<VoyagerMessage>>>,
+}
+
+#[derive(Default)]
+pub struct MockRpc {
+    handlers: Mutex<HashMap<String, Handler>>,
+    requests: Mutex<Vec<(String, Value)>>,
+}
+
+impl fmt::Debug for MockRpc {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MockRpc")
+            .field(
+                "handlers",
+                &self.handlers.lock().unwrap().keys().collect::<Vec<_>>(),
+            )
+            .field("requests", &self.requests.lock().unwrap())
+            .finish()
+    }
+}
+
+impl MockTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Answer requests to `method` with `handler`, which is passed the (positional) params of
+    /// the request as a json array.
+    pub fn respond(
+        &self,
+        method: impl Into<String>,
+        handler: impl Fn(Value) -> Result<Value, ErrorObjectOwned> + Send + Sync + 'static,
+    ) -> &Self {
+        self.rpc
+            .handlers
+            .lock()
+            .unwrap()
+            .insert(method.into(), Arc::new(handler));
+        self
+    }
+
+    /// Answer all requests to `method` with `response`.
+    pub fn respond_with(&self, method: impl Into<String>, response: impl Serialize) -> &Self {
+        let response = serde_json::to_value(response).unwrap();
+        self.respond(method, move |_| Ok(response.clone()))
+    }
+
+    /// All requests made so far, as `(method, params)`.
+    pub fn requests(&self) -> Vec<(String, Value)> {
+        self.rpc.requests.lock().unwrap().clone()
+    }
+
+    /// All ops enqueued so far.
+    pub fn enqueued(&self) -> Vec<Op<VoyagerMessage>> {
+        self.enqueued.lock().unwrap().clone()
+    }
+}
+
+impl Transport for MockTransport {
+    type Rpc = MockRpc;
+
+    fn rpc(&self) -> &Self::Rpc {
+        &self.rpc
+    }
+
+    async fn enqueue(&self, op: Op<VoyagerMessage>) -> Result<(), Error> {
+        self.enqueued.lock().unwrap().push(op);
+
+        Ok(())
+    }
+}
+
+impl MockRpc {
+    fn call(&self, method: &str, params: impl ToRpcParams) -> Result<Value, ClientError> {
+        let params = params
+            .to_rpc_params()?
+            .map(|params| serde_json::from_str(params.get()))
+            .transpose()?
+            .unwrap_or(Value::Array(vec![]));
+
+        self.requests
+            .lock()
+            .unwrap()
+            .push((method.to_owned(), params.clone()));
+
+        let handler = self.handlers.lock().unwrap().get(method).cloned();
+
+        match handler {
+            Some(handler) => handler(params).map_err(ClientError::Call),
+            None => Err(ClientError::Call(ErrorObject::owned(
+                METHOD_NOT_FOUND_CODE,
+                format!("method not found: {method}"),
+                None::<()>,
+            ))),
+        }
+    }
+}
+
+#[async_trait]
+impl ClientT for MockRpc {
+    async fn notification<Params>(&self, method: &str, params: Params) -> Result<(), ClientError>
+    where
+        Params: ToRpcParams + Send,
+    {
+        self.call(method, params).map(|_| ())
+    }
+
+    async fn request<R, Params>(&self, method: &str, params: Params) -> Result<R, ClientError>
+    where
+        R: DeserializeOwned,
+        Params: ToRpcParams + Send,
+    {
+        Ok(serde_json::from_value(self.call(method, params)?)?)
+    }
+
+    async fn batch_request<'a, R>(
+        &self,
+        _batch: BatchRequestBuilder<'a>,
+    ) -> Result<BatchResponse<'a, R>, ClientError>
+    where
+        R: DeserializeOwned + fmt::Debug + 'a,
+    {
+        Err(ClientError::Custom(
+            "batch requests are not supported by the mock transport".to_owned(),
+        ))
+    }
+}
@@ -0,0 +1,92 @@
+use std::{future::Future, time::Duration};
+
+use jsonrpsee::{
+    core::client::ClientT,
+    http_client::{HeaderMap, HeaderValue, HttpClient},
+};
+use voyager_message::VoyagerMessage;
+use voyager_vm::Op;
+
+use crate::Error;
+
+/// The connection to voyager used by a [`Client`](crate::Client).
+///
+/// JSON-RPC requests are made through [`Transport::rpc`], and ops are submitted to the queue
+/// through [`Transport::enqueue`]. See [`MockTransport`](crate::mock::MockTransport) for an
+/// in-memory implementation for use in tests.
+pub trait Transport: Send + Sync {
+    type Rpc: ClientT + Send + Sync;
+
+    fn rpc(&self) -> &Self::Rpc;
+
+    fn enqueue(&self, op: Op<VoyagerMessage>) -> impl Future<Output = Result<(), Error>> + Send;
+}
+
+/// Talks to voyager over http. Constructed with [`ClientBuilder`](crate::ClientBuilder).
+#[derive(Debug, Clone)]
+pub struct HttpTransport {
+    rpc: HttpClient,
+    rest: reqwest::Client,
+    rest_url: Option<String>,
+}
+
+impl HttpTransport {
+    pub(crate) fn new(
+        rpc_url: &str,
+        rest_url: Option<String>,
+        auth_header: Option<&str>,
+        timeout: Option<Duration>,
+    ) -> Result<Self, Error> {
+        let mut rpc = HttpClient::builder();
+        let mut rest = reqwest::Client::builder();
+
+        if let Some(auth_header) = auth_header {
+            let mut headers = HeaderMap::new();
+            headers.insert(
+                "authorization",
+                HeaderValue::from_str(auth_header).map_err(|_| Error::InvalidAuthHeader)?,
+            );
+            rpc = rpc.set_headers(headers);
+
+            let mut headers = reqwest::header::HeaderMap::new();
+            headers.insert(
+                reqwest::header::AUTHORIZATION,
+                reqwest::header::HeaderValue::from_str(auth_header)
+                    .map_err(|_| Error::InvalidAuthHeader)?,
+            );
+            rest = rest.default_headers(headers);
+        }
+
+        if let Some(timeout) = timeout {
+            rpc = rpc.request_timeout(timeout);
+            rest = rest.timeout(timeout);
+        }
+
+        Ok(Self {
+            rpc: rpc.build(rpc_url)?,
+            rest: rest.build()?,
+            rest_url: rest_url.map(|url| url.trim_end_matches('/').to_owned()),
+        })
+    }
+}
+
+impl Transport for HttpTransport {
+    type Rpc = HttpClient;
+
+    fn rpc(&self) -> &Self::Rpc {
+        &self.rpc
+    }
+
+    async fn enqueue(&self, op: Op<VoyagerMessage>) -> Result<(), Error> {
+        let rest_url = self.rest_url.as_ref().ok_or(Error::NoRestUrl)?;
+
+        self.rest
+            .post(format!("{rest_url}/enqueue"))
+            .json(&op)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,334 @@
+use std::num::NonZeroU64;
+
+use ibc_classic_spec::{ChannelMetadata, ConnectionMetadata, IbcClassic, PacketMetadata};
+use ibc_union_spec::{IbcUnion, UnionId, COMMITMENT_MAGIC, COMMITMENT_NULL};
+use jsonrpsee::{server::Server, RpcModule};
+use serde_json::{json, Value};
+use unionlabs::{
+    hash::H256,
+    ibc::core::{channel::order::Order, client::height::Height},
+    id::{ChannelId, ClientId, ConnectionId, PortId},
+};
+use voyager_client::{
+    core::{ChainId, ClientInfo, ClientType, IbcInterface, QueryHeight},
+    mock::MockTransport,
+    ops::{call, seq},
+    voyager_message::{call::WaitForHeight, rpc::IbcState},
+    Client, ClientBuilder, Error, PacketStatus,
+};
+
+const SOURCE: &str = "source-1";
+const DESTINATION: &str = "destination-1";
+
+fn chain_id(params: &Value) -> &str {
+    params[0].as_str().unwrap()
+}
+
+fn ibc_state(state: impl serde::Serialize) -> Value {
+    serde_json::to_value(IbcState {
+        height: Height::new(1),
+        state,
+    })
+    .unwrap()
+}
+
+fn v1_packet(channel_ordering: Order) -> PacketMetadata {
+    let channel = |channel_id, client_id, connection_id| ChannelMetadata {
+        port_id: PortId::new("transfer").unwrap(),
+        channel_id: ChannelId::new(channel_id),
+        version: "ics20-1".to_owned(),
+        connection_hops: vec![ConnectionMetadata {
+            client_id: ClientId::new("07-tendermint", client_id),
+            connection_id: ConnectionId::new(connection_id),
+        }],
+    };
+
+    PacketMetadata {
+        sequence: NonZeroU64::new(5).unwrap(),
+        source_channel: channel(0, 1, 2),
+        destination_channel: channel(3, 4, 5),
+        channel_ordering,
+        timeout_height: Height::new(0),
+        timeout_timestamp: 100,
+    }
+}
+
+/// A v1 packet that has been received but not yet acknowledged on the destination chain.
+fn v1_received(transport: &MockTransport) {
+    transport.respond("voyager_queryIbcState", |params| {
+        let path =
+            serde_json::from_value::<ibc_classic_spec::StorePath>(params[3].clone()).unwrap();
+
+        Ok(match (chain_id(&params), path) {
+            (SOURCE, ibc_classic_spec::StorePath::Commitment(path)) => {
+                assert_eq!(path.sequence.get(), 5);
+                ibc_state(Some(H256::new([0xAA; 32])))
+            }
+            (DESTINATION, ibc_classic_spec::StorePath::Receipt(_)) => ibc_state(true),
+            (DESTINATION, ibc_classic_spec::StorePath::NextSequenceRecv(_)) => ibc_state(6),
+            (DESTINATION, ibc_classic_spec::StorePath::Acknowledgement(_)) => {
+                ibc_state(None::<H256>)
+            }
+            (chain_id, path) => panic!("unexpected query for {path} on {chain_id}"),
+        })
+    });
+}
+
+#[tokio::test]
+async fn v1_packet_status_unordered() {
+    let transport = MockTransport::new();
+    v1_received(&transport);
+
+    let client = Client::new(transport);
+
+    let status = client
+        .v1_packet_status(
+            ChainId::new(SOURCE),
+            ChainId::new(DESTINATION),
+            &v1_packet(Order::Unordered),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(
+        status,
+        PacketStatus {
+            commitment: Some(H256::new([0xAA; 32])),
+            received: true,
+            acknowledgement: None,
+        }
+    );
+
+    assert!(client
+        .transport()
+        .requests()
+        .iter()
+        .all(|(method, _)| method == "voyager_queryIbcState"));
+}
+
+#[tokio::test]
+async fn v1_packet_status_ordered_uses_next_sequence_recv() {
+    let transport = MockTransport::new();
+    v1_received(&transport);
+
+    let client = Client::new(transport);
+
+    let status = client
+        .v1_packet_status(
+            ChainId::new(SOURCE),
+            ChainId::new(DESTINATION),
+            &v1_packet(Order::Ordered),
+        )
+        .await
+        .unwrap();
+
+    assert!(status.received);
+
+    assert!(!client.transport().requests().iter().any(|(_, params)| {
+        matches!(
+            serde_json::from_value(params[3].clone()),
+            Ok(ibc_classic_spec::StorePath::Receipt(_))
+        )
+    }));
+}
+
+#[tokio::test]
+async fn union_packet_status() {
+    let packet = ibc_solidity::Packet {
+        source_channel: 1,
+        destination_channel: 2,
+        data: Default::default(),
+        timeout_height: 0,
+        timeout_timestamp: 100,
+    };
+
+    let ack = H256::new([0xBB; 32]);
+
+    for (receipt, received, acknowledgement) in [
+        (COMMITMENT_NULL, false, None),
+        (COMMITMENT_MAGIC, true, None),
+        (ack, true, Some(ack)),
+    ] {
+        let transport = MockTransport::new();
+        transport.respond("voyager_queryIbcState", move |params| {
+            let path =
+                serde_json::from_value::<ibc_union_spec::StorePath>(params[3].clone()).unwrap();
+
+            Ok(match (chain_id(&params), path) {
+                (SOURCE, ibc_union_spec::StorePath::BatchPackets(path)) => {
+                    assert_eq!(path.channel_id, 1);
+                    ibc_state(COMMITMENT_MAGIC)
+                }
+                (DESTINATION, ibc_union_spec::StorePath::BatchReceipts(path)) => {
+                    assert_eq!(path.channel_id, 2);
+                    ibc_state(receipt)
+                }
+                (chain_id, path) => panic!("unexpected query for {path:?} on {chain_id}"),
+            })
+        });
+
+        let status = Client::new(transport)
+            .union_packet_status(ChainId::new(SOURCE), ChainId::new(DESTINATION), &packet)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            status,
+            PacketStatus {
+                commitment: Some(COMMITMENT_MAGIC),
+                received,
+                acknowledgement,
+            }
+        );
+    }
+}
+
+#[tokio::test]
+async fn client_info_sends_raw_client_id() {
+    let transport = MockTransport::new();
+    transport.respond_with(
+        "voyager_clientInfo",
+        ClientInfo {
+            client_type: ClientType::new("cometbls"),
+            ibc_interface: IbcInterface::new("ibc-solidity"),
+            metadata: Value::Null,
+        },
+    );
+
+    let client = Client::new(transport);
+
+    let info = client
+        .client_info::<IbcUnion>(ChainId::new(SOURCE), UnionId::new(7).unwrap())
+        .await
+        .unwrap();
+
+    assert_eq!(info.client_type, ClientType::new("cometbls"));
+
+    assert_eq!(
+        client.transport().requests(),
+        [(
+            "voyager_clientInfo".to_owned(),
+            json!([SOURCE, "ibc-union", 7])
+        )]
+    );
+}
+
+#[tokio::test]
+async fn unmocked_method_is_an_rpc_error() {
+    let client = Client::new(MockTransport::new());
+
+    let err = client.info().await.unwrap_err();
+
+    assert!(
+        matches!(err, Error::Rpc(jsonrpsee::core::client::Error::Call(_))),
+        "{err:?}"
+    );
+}
+
+#[tokio::test]
+async fn enqueue_records_op() {
+    let client = Client::new(MockTransport::new());
+
+    let op = seq([call(WaitForHeight {
+        chain_id: ChainId::new(SOURCE),
+        height: Height::new(10),
+        finalized: true,
+    })]);
+
+    client.enqueue(op.clone()).await.unwrap();
+
+    assert_eq!(client.transport().enqueued(), [op]);
+}
+
+#[tokio::test]
+async fn enqueue_without_rest_url() {
+    let client = ClientBuilder::new("http://127.0.0.1:1").build().unwrap();
+
+    let err = client.enqueue(seq([])).await.unwrap_err();
+
+    assert!(matches!(err, Error::NoRestUrl), "{err:?}");
+}
+
+#[tokio::test]
+async fn invalid_auth_header() {
+    let err = ClientBuilder::new("http://127.0.0.1:1")
+        .auth_header("invalid\nheader")
+        .build()
+        .unwrap_err();
+
+    assert!(matches!(err, Error::InvalidAuthHeader), "{err:?}");
+}
+
+#[tokio::test]
+async fn http_against_mocked_server() {
+    let mut module = RpcModule::new(());
+    module
+        .register_method("voyager_queryLatestHeight", |params, _, _| {
+            let (chain_id, finalized) = params.parse::<(ChainId, bool)>().unwrap();
+            assert_eq!(chain_id.as_str(), SOURCE);
+            Height::new(if finalized { 10 } else { 11 })
+        })
+        .unwrap();
+    module
+        .register_method("voyager_queryIbcState", |params, _, _| {
+            let (chain_id, ibc_spec_id, height, path) = params
+                .parse::<(ChainId, String, QueryHeight, ibc_classic_spec::StorePath)>()
+                .unwrap();
+            assert_eq!(chain_id.as_str(), SOURCE);
+            assert_eq!(ibc_spec_id, "ibc-classic");
+            assert_eq!(height, QueryHeight::Specific(Height::new(3)));
+            assert!(matches!(
+                path,
+                ibc_classic_spec::StorePath::NextSequenceSend(_)
+            ));
+            ibc_state(42)
+        })
+        .unwrap();
+
+    let server = Server::builder().build("127.0.0.1:0").await.unwrap();
+    let addr = server.local_addr().unwrap();
+    let handle = server.start(module);
+
+    let client = ClientBuilder::new(format!("http://{addr}"))
+        .auth_header("Bearer token")
+        .build()
+        .unwrap();
+
+    assert_eq!(
+        client
+            .query_latest_height(ChainId::new(SOURCE), true)
+            .await
+            .unwrap(),
+        Height::new(10)
+    );
+
+    let next_sequence_send = client
+        .query_spec_ibc_state(
+            ChainId::new(SOURCE),
+            QueryHeight::Specific(Height::new(3)),
+            ibc_classic_spec::NextSequenceSendPath {
+                port_id: PortId::new("transfer").unwrap(),
+                channel_id: ChannelId::new(0),
+            },
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(next_sequence_send.state, 42);
+
+    let err = client
+        .client_meta::<IbcClassic>(
+            ChainId::new(SOURCE),
+            QueryHeight::Latest,
+            ClientId::new("07-tendermint", 0),
+        )
+        .await
+        .unwrap_err();
+
+    assert!(
+        matches!(err, Error::Rpc(jsonrpsee::core::client::Error::Call(_))),
+        "{err:?}"
+    );
+
+    handle.stop().unwrap();
+}
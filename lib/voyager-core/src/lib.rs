@@ -13,6 +13,10 @@ use unionlabs::{
     traits::Member,
 };
 
+pub use crate::timeout::{TimeoutHeight, TimeoutSpec, Timestamp};
+
+mod timeout;
+
 /// Represents the IBC interface of a chain.
 ///
 /// Since multiple chains with different consensus mechanisms can have the same
@@ -173,6 +177,9 @@ impl ConsensusType {
 pub trait IbcSpec {
     const ID: IbcSpecId;
 
+    /// How heights are represented in this spec.
+    const HEIGHT_FORMAT: HeightFormat = HeightFormat::WithRevision;
+
     type ClientId: Display + Member;
 
     /// The type used to index into the IBC store.
@@ -190,6 +197,29 @@ pub trait IbcSpec {
     fn consensus_state_path(client_id: Self::ClientId, height: Height) -> Self::StorePath;
 }
 
+/// How heights are represented by an [`IbcSpec`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum HeightFormat {
+    /// Heights are a revision number and a height, as per [ICS-02].
+    ///
+    /// [ICS-02]: https://github.com/cosmos/ibc/tree/main/spec/core/ics-002-client-semantics
+    #[default]
+    WithRevision,
+    /// Heights are plain numbers, without a revision number.
+    Bare,
+}
+
+impl HeightFormat {
+    /// Convert `height` into this format.
+    #[must_use]
+    pub const fn apply(self, height: Height) -> Height {
+        match self {
+            Self::WithRevision => height,
+            Self::Bare => height.without_revision(),
+        }
+    }
+}
+
 /// A subset of [`IbcSpec::StorePath`]. This should be implemented by all variants of the `StorePath` enum for an `IbcSpec` implementation.
 pub trait IbcStorePathKey:
     Member
@@ -268,6 +298,46 @@ pub struct ClientStateMeta {
 
     /// The chain id of the counterparty chain this client tracks.
     pub chain_id: ChainId,
+
+    /// The status of this client.
+    ///
+    /// This is populated by voyager through [`client_status`] on the client
+    /// module, client modules should return [`ClientStatus::Unknown`] when
+    /// decoding the client state meta.
+    ///
+    /// [`client_status`]: https://docs.rs/voyager-message/latest/voyager_message/module/trait.ClientModuleServer.html#tymethod.client_status
+    #[serde(default)]
+    pub status: ClientStatus,
+
+    /// The height of the chain the client is on that this meta was resolved
+    /// at.
+    ///
+    /// This is populated by voyager, client modules should return [`None`]
+    /// when decoding the client state meta. If the requested height has been
+    /// pruned by the node, voyager falls back to the latest height, in which
+    /// case this will be greater than the requested height.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolved_at: Option<Height>,
+}
+
+/// The status of a light client, as per [ICS-02].
+///
+/// [ICS-02]: https://github.com/cosmos/ibc/tree/main/spec/core/ics-002-client-semantics#client-status
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClientStatus {
+    /// The client can be updated and used to verify proofs.
+    Active,
+    /// The client has been frozen due to misbehaviour, and can not be used
+    /// until it is recovered.
+    Frozen,
+    /// The latest consensus state of the client is outside of the trusting
+    /// period, and the client can not be updated until it is recovered.
+    Expired,
+    /// The status of the client could not be determined, either because the
+    /// client module does not support it or because it failed to do so.
+    #[default]
+    Unknown,
 }
 
 #[model]
@@ -279,9 +349,105 @@ pub struct ConsensusStateMeta {
 
 #[model]
 pub struct IbcGo08WasmClientMetadata {
+    /// The checksum of the wasm blob of the client. Accepts both hex and base64, since the latter
+    /// is what ibc-go returns from the wasm module queries.
+    #[serde(with = "::serde_utils::hex_or_base64")]
     pub checksum: H256,
 }
 
+/// The encoding used for the client state of a client, as determined by its
+/// [`ClientInfo`].
+///
+/// Note that the encoding itself is still performed by the client module, this
+/// is only used to select (and validate) the inputs to the client module.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SupportedEncoding {
+    /// Protobuf, wrapped in [`Any`](https://protobuf.dev/programming-guides/proto3/#any).
+    Proto,
+    /// Protobuf, wrapped in the 08-wasm client state (with the provided
+    /// checksum) and then in `Any`.
+    Wasm { checksum: H256 },
+    /// Solidity ABI encoding.
+    EthAbi,
+    /// Binary Canonical Serialization, as used by move.
+    Bcs,
+    /// Bincode, as used by union's cosmwasm IBC implementation.
+    Bincode,
+}
+
+impl SupportedEncoding {
+    /// The metadata to pass to the client module when encoding a client state
+    /// with this encoding.
+    ///
+    /// For all encodings other than [`SupportedEncoding::Wasm`], the metadata
+    /// provided in the [`ClientInfo`] is passed through as-is (client modules
+    /// are expected to reject any metadata they don't require).
+    #[must_use]
+    pub fn client_state_metadata(&self, client_info: &ClientInfo) -> Value {
+        match self {
+            Self::Wasm { checksum } => serde_json::to_value(IbcGo08WasmClientMetadata {
+                checksum: *checksum,
+            })
+            .expect("serialization is infallible; qed;"),
+            _ => client_info.metadata.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum EncodingError {
+    #[error("no known encoding for client type {client_type} on IBC interface {ibc_interface}")]
+    Unknown {
+        client_type: ClientType,
+        ibc_interface: IbcInterface,
+    },
+    #[error(
+        "client type {client_type} is on the {} IBC interface, but no \
+        checksum was provided in the client metadata (found {metadata})",
+        IbcInterface::IBC_GO_V8_08_WASM
+    )]
+    MissingWasmChecksum {
+        client_type: ClientType,
+        metadata: Value,
+    },
+}
+
+/// Resolve the encoding of the client state of the client described by
+/// `client_info`.
+///
+/// # Errors
+///
+/// This will fail if the IBC interface is not known, or if the client is an
+/// ibc-go 08-wasm client and no valid [`IbcGo08WasmClientMetadata`] was
+/// provided in [`ClientInfo::metadata`].
+pub fn encoding_for(client_info: &ClientInfo) -> Result<SupportedEncoding, EncodingError> {
+    match (
+        client_info.client_type.as_str(),
+        client_info.ibc_interface.as_str(),
+    ) {
+        (_, IbcInterface::IBC_GO_V8_NATIVE) => Ok(SupportedEncoding::Proto),
+        // the movement client is deployed as a wasm contract, but encodes its states directly
+        (ClientType::MOVEMENT, IbcInterface::IBC_GO_V8_08_WASM)
+        | (_, IbcInterface::IBC_COSMWASM) => Ok(SupportedEncoding::Bincode),
+        (_, IbcInterface::IBC_GO_V8_08_WASM) => {
+            serde_json::from_value::<IbcGo08WasmClientMetadata>(client_info.metadata.clone())
+                .map(|metadata| SupportedEncoding::Wasm {
+                    checksum: metadata.checksum,
+                })
+                .map_err(|_| EncodingError::MissingWasmChecksum {
+                    client_type: client_info.client_type.clone(),
+                    metadata: client_info.metadata.clone(),
+                })
+        }
+        (_, IbcInterface::IBC_SOLIDITY) => Ok(SupportedEncoding::EthAbi),
+        (_, IbcInterface::IBC_MOVE_APTOS) => Ok(SupportedEncoding::Bcs),
+        _ => Err(EncodingError::Unknown {
+            client_type: client_info.client_type.clone(),
+            ibc_interface: client_info.ibc_interface.clone(),
+        }),
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum QueryHeight {
     /// The latest, potentially unfinalized block (the head of the chain).
@@ -301,6 +467,17 @@ impl From<Height> for QueryHeight {
     }
 }
 
+impl QueryHeight {
+    /// Convert the height of [`QueryHeight::Specific`] into `format`.
+    #[must_use]
+    pub fn with_format(self, format: HeightFormat) -> Self {
+        match self {
+            Self::Specific(height) => Self::Specific(format.apply(height)),
+            other => other,
+        }
+    }
+}
+
 impl fmt::Display for QueryHeight {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -317,6 +494,7 @@ impl FromStr for QueryHeight {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
             "latest" => Ok(Self::Latest),
+            "finalized" => Ok(Self::Finalized),
             _ => s.parse().map(Self::Specific),
         }
     }
@@ -388,3 +566,225 @@ macro_rules! str_newtype {
         }
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn client_state_meta_status_defaults_to_unknown() {
+        let meta = serde_json::from_value::<ClientStateMeta>(json!({
+            "height": "1-100",
+            "chain_id": "union-devnet-1",
+        }))
+        .unwrap();
+
+        assert_eq!(meta.status, ClientStatus::Unknown);
+        assert_eq!(meta.resolved_at, None);
+    }
+
+    #[test]
+    fn client_state_meta_status_round_trip() {
+        for status in [
+            ClientStatus::Active,
+            ClientStatus::Frozen,
+            ClientStatus::Expired,
+            ClientStatus::Unknown,
+        ] {
+            let meta = ClientStateMeta {
+                height: Height::new_with_revision(1, 100),
+                chain_id: ChainId::new("union-devnet-1"),
+                status,
+                resolved_at: Some(Height::new_with_revision(2, 5)),
+            };
+
+            let json = serde_json::to_value(&meta).unwrap();
+
+            assert_eq!(json["status"], serde_json::to_value(status).unwrap());
+            assert_eq!(json["resolved_at"], "2-5");
+            assert_eq!(
+                serde_json::from_value::<ClientStateMeta>(json).unwrap(),
+                meta
+            );
+        }
+    }
+
+    #[test]
+    fn query_height_string_round_trip() {
+        for (query_height, format, expected) in [
+            (QueryHeight::Latest, HeightFormat::Bare, "latest"),
+            (QueryHeight::Finalized, HeightFormat::Bare, "finalized"),
+            (
+                QueryHeight::Specific(Height::new_with_revision(1, 12345)),
+                HeightFormat::WithRevision,
+                "1-12345",
+            ),
+            (
+                QueryHeight::Specific(Height::new_with_revision(1, 12345)),
+                HeightFormat::Bare,
+                "12345",
+            ),
+            (
+                QueryHeight::Specific(Height::new(12345)),
+                HeightFormat::Bare,
+                "12345",
+            ),
+        ] {
+            let query_height = query_height.with_format(format);
+
+            assert_eq!(query_height.to_string(), expected);
+            assert_eq!(expected.parse::<QueryHeight>().unwrap(), query_height);
+            assert_eq!(
+                serde_json::from_value::<QueryHeight>(serde_json::to_value(&query_height).unwrap())
+                    .unwrap(),
+                query_height
+            );
+        }
+    }
+
+    #[test]
+    fn bare_client_state_meta() {
+        let meta = ClientStateMeta {
+            height: HeightFormat::Bare.apply(Height::new_with_revision(1, 100)),
+            chain_id: ChainId::new("union-devnet-1"),
+            status: ClientStatus::Active,
+            resolved_at: Some(HeightFormat::Bare.apply(Height::new_with_revision(2, 5))),
+        };
+
+        let json = serde_json::to_value(&meta).unwrap();
+
+        assert_eq!(json["height"], "100");
+        assert_eq!(json["resolved_at"], "5");
+        assert_eq!(
+            serde_json::from_value::<ClientStateMeta>(json).unwrap(),
+            meta
+        );
+    }
+
+    fn client_info(client_type: &'static str, ibc_interface: &'static str) -> ClientInfo {
+        ClientInfo {
+            client_type: ClientType::new_static(client_type),
+            ibc_interface: IbcInterface::new_static(ibc_interface),
+            metadata: Value::Null,
+        }
+    }
+
+    #[test]
+    fn encoding_for_registered_clients() {
+        for (client_type, ibc_interface, encoding) in [
+            (
+                ClientType::TENDERMINT,
+                IbcInterface::IBC_GO_V8_NATIVE,
+                SupportedEncoding::Proto,
+            ),
+            (
+                ClientType::COMETBLS,
+                IbcInterface::IBC_SOLIDITY,
+                SupportedEncoding::EthAbi,
+            ),
+            (
+                ClientType::COMETBLS,
+                IbcInterface::IBC_MOVE_APTOS,
+                SupportedEncoding::Bcs,
+            ),
+            (
+                ClientType::ETHEREUM,
+                IbcInterface::IBC_COSMWASM,
+                SupportedEncoding::Bincode,
+            ),
+            (
+                ClientType::MOVEMENT,
+                IbcInterface::IBC_GO_V8_08_WASM,
+                SupportedEncoding::Bincode,
+            ),
+        ] {
+            let client_info = client_info(client_type, ibc_interface);
+
+            assert_eq!(
+                encoding_for(&client_info).unwrap(),
+                encoding,
+                "{client_type} on {ibc_interface}"
+            );
+            assert_eq!(
+                encoding.client_state_metadata(&client_info),
+                Value::Null,
+                "{client_type} on {ibc_interface}"
+            );
+        }
+    }
+
+    #[test]
+    fn encoding_for_wasm_client() {
+        let checksum = H256::new([0xaa; 32]);
+
+        let client_info = ClientInfo {
+            metadata: json!({ "checksum": checksum }),
+            ..client_info(ClientType::COMETBLS, IbcInterface::IBC_GO_V8_08_WASM)
+        };
+
+        let encoding = encoding_for(&client_info).unwrap();
+
+        assert_eq!(encoding, SupportedEncoding::Wasm { checksum });
+        assert_eq!(
+            serde_json::from_value::<IbcGo08WasmClientMetadata>(
+                encoding.client_state_metadata(&client_info)
+            )
+            .unwrap(),
+            IbcGo08WasmClientMetadata { checksum }
+        );
+    }
+
+    #[test]
+    fn wasm_client_metadata_checksum_formats() {
+        let checksum = H256::new([0xaa; 32]);
+
+        for encoded in [
+            "0xaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
+            "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
+            "qqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqo=",
+        ] {
+            assert_eq!(
+                serde_json::from_value::<IbcGo08WasmClientMetadata>(json!({ "checksum": encoded }))
+                    .unwrap(),
+                IbcGo08WasmClientMetadata { checksum },
+                "{encoded}"
+            );
+        }
+
+        assert_eq!(
+            serde_json::to_value(IbcGo08WasmClientMetadata { checksum }).unwrap(),
+            json!({ "checksum": checksum.to_string() })
+        );
+    }
+
+    #[test]
+    fn encoding_for_wasm_client_without_checksum() {
+        for metadata in [Value::Null, json!({}), json!({ "checksum": "0x1234" })] {
+            let client_info = ClientInfo {
+                metadata: metadata.clone(),
+                ..client_info(ClientType::COMETBLS, IbcInterface::IBC_GO_V8_08_WASM)
+            };
+
+            assert_eq!(
+                encoding_for(&client_info),
+                Err(EncodingError::MissingWasmChecksum {
+                    client_type: ClientType::new_static(ClientType::COMETBLS),
+                    metadata,
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn encoding_for_unknown_interface() {
+        assert_eq!(
+            encoding_for(&client_info(ClientType::COMETBLS, "ibc-fuel")),
+            Err(EncodingError::Unknown {
+                client_type: ClientType::new_static(ClientType::COMETBLS),
+                ibc_interface: IbcInterface::new_static("ibc-fuel"),
+            })
+        );
+    }
+}
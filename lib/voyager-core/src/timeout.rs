@@ -0,0 +1,296 @@
+//! Typed packet timeouts.
+//!
+//! Both IBC specs encode the timeout of a packet as a `(timeout_height,
+//! timeout_timestamp)` pair, where a value of `0` disables that component.
+//! However, they differ in how the components are interpreted:
+//!
+//! - ibc-classic: The timeout height is a revisioned height, compared with
+//!   `GTE` as defined by ibc-go (i.e. a higher revision number has elapsed the
+//!   timeout regardless of the revision height). A packet with both components
+//!   set times out once *either* of them has elapsed.
+//! - ibc-union: The timeout height is a plain block number, with no revision
+//!   number. A packet with both components set is received only if *neither*
+//!   of them has elapsed, but can only be timed out once *both* of them have
+//!   elapsed.
+//!
+//! In both specs, the timeout timestamp is in unix nanoseconds and is elapsed
+//! once the current timestamp is greater than or equal to it.
+
+use core::fmt;
+
+use serde::{Deserialize, Serialize};
+use unionlabs::ibc::core::client::height::Height;
+
+/// A unix timestamp, in nanoseconds.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize,
+)]
+#[serde(transparent)]
+pub struct Timestamp(u64);
+
+impl Timestamp {
+    #[must_use]
+    pub const fn from_nanos(nanos: u64) -> Self {
+        Self(nanos)
+    }
+
+    #[must_use]
+    pub const fn as_nanos(&self) -> u64 {
+        self.0
+    }
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+/// The height component of a packet timeout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeoutHeight {
+    /// A revisioned ibc-classic height.
+    Classic(Height),
+    /// A revisionless ibc-union height.
+    Union(u64),
+}
+
+impl TimeoutHeight {
+    /// Whether this timeout height has been reached at `current_height`.
+    #[must_use]
+    pub fn is_reached(&self, current_height: Height) -> bool {
+        match *self {
+            // ibc-go's Height.GTE, where a missing revision number is 0
+            Self::Classic(height) => {
+                (current_height.revision(), current_height.height())
+                    >= (height.revision(), height.height())
+            }
+            Self::Union(height) => current_height.height() >= height,
+        }
+    }
+}
+
+/// The timeout of a packet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeoutSpec {
+    /// The packet never times out.
+    Never,
+    /// The packet times out once the counterparty timestamp is greater than or
+    /// equal to the timestamp.
+    AtTimestamp(Timestamp),
+    /// The packet times out once the counterparty height has reached the
+    /// height.
+    AtHeight(TimeoutHeight),
+    /// Both a height and a timestamp timeout are set.
+    ///
+    /// ibc-classic packets time out once either has elapsed, whereas ibc-union
+    /// packets only time out once both have elapsed.
+    Both {
+        height: TimeoutHeight,
+        timestamp: Timestamp,
+    },
+}
+
+impl TimeoutSpec {
+    /// Construct the timeout of an ibc-classic packet.
+    ///
+    /// A zero height (revision number and revision height both `0`) or a zero
+    /// timestamp disables the respective timeout.
+    #[must_use]
+    pub fn from_v1(timeout_height: Height, timeout_timestamp: u64) -> Self {
+        let height = (timeout_height.revision() != 0 || timeout_height.height() != 0)
+            .then_some(TimeoutHeight::Classic(timeout_height));
+
+        Self::new(height, timeout_timestamp)
+    }
+
+    /// Construct the timeout of an ibc-union packet.
+    ///
+    /// A zero height or a zero timestamp disables the respective timeout. The
+    /// height is revisionless, and as such is only compared against the
+    /// revision height of the counterparty.
+    #[must_use]
+    pub fn from_union(timeout_height: u64, timeout_timestamp: u64) -> Self {
+        let height = (timeout_height != 0).then_some(TimeoutHeight::Union(timeout_height));
+
+        Self::new(height, timeout_timestamp)
+    }
+
+    fn new(height: Option<TimeoutHeight>, timestamp: u64) -> Self {
+        let timestamp = (timestamp != 0).then_some(Timestamp::from_nanos(timestamp));
+
+        match (height, timestamp) {
+            (None, None) => Self::Never,
+            (None, Some(timestamp)) => Self::AtTimestamp(timestamp),
+            (Some(height), None) => Self::AtHeight(height),
+            (Some(height), Some(timestamp)) => Self::Both { height, timestamp },
+        }
+    }
+
+    /// Whether this timeout has elapsed at the provided counterparty height
+    /// and timestamp, i.e. whether the packet can be timed out with a proof at
+    /// this height.
+    #[must_use]
+    pub fn is_elapsed(&self, current_height: Height, current_timestamp: Timestamp) -> bool {
+        match *self {
+            Self::Never => false,
+            Self::AtTimestamp(timestamp) => current_timestamp >= timestamp,
+            Self::AtHeight(height) => height.is_reached(current_height),
+            Self::Both { height, timestamp } => {
+                let height_elapsed = height.is_reached(current_height);
+                let timestamp_elapsed = current_timestamp >= timestamp;
+
+                match height {
+                    TimeoutHeight::Classic(_) => height_elapsed || timestamp_elapsed,
+                    TimeoutHeight::Union(_) => height_elapsed && timestamp_elapsed,
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const fn ts(nanos: u64) -> Timestamp {
+        Timestamp::from_nanos(nanos)
+    }
+
+    #[test]
+    fn v1_constructor() {
+        let cases = [
+            (Height::new_with_revision(0, 0), 0, TimeoutSpec::Never),
+            (Height::new(0), 0, TimeoutSpec::Never),
+            (
+                Height::new_with_revision(0, 0),
+                100,
+                TimeoutSpec::AtTimestamp(ts(100)),
+            ),
+            (
+                Height::new_with_revision(1, 0),
+                0,
+                TimeoutSpec::AtHeight(TimeoutHeight::Classic(Height::new_with_revision(1, 0))),
+            ),
+            (
+                Height::new_with_revision(0, 10),
+                0,
+                TimeoutSpec::AtHeight(TimeoutHeight::Classic(Height::new(10))),
+            ),
+            (
+                Height::new_with_revision(1, 10),
+                100,
+                TimeoutSpec::Both {
+                    height: TimeoutHeight::Classic(Height::new_with_revision(1, 10)),
+                    timestamp: ts(100),
+                },
+            ),
+        ];
+
+        for (height, timestamp, expected) in cases {
+            assert_eq!(
+                TimeoutSpec::from_v1(height, timestamp),
+                expected,
+                "{height}, {timestamp}"
+            );
+        }
+    }
+
+    #[test]
+    fn union_constructor() {
+        let cases = [
+            (0, 0, TimeoutSpec::Never),
+            (0, 100, TimeoutSpec::AtTimestamp(ts(100))),
+            (10, 0, TimeoutSpec::AtHeight(TimeoutHeight::Union(10))),
+            (
+                10,
+                100,
+                TimeoutSpec::Both {
+                    height: TimeoutHeight::Union(10),
+                    timestamp: ts(100),
+                },
+            ),
+        ];
+
+        for (height, timestamp, expected) in cases {
+            assert_eq!(
+                TimeoutSpec::from_union(height, timestamp),
+                expected,
+                "{height}, {timestamp}"
+            );
+        }
+    }
+
+    #[test]
+    fn v1_is_elapsed() {
+        let h = Height::new_with_revision;
+
+        let cases = [
+            // disabled
+            (h(0, 0), 0, h(1, u64::MAX), u64::MAX, false),
+            // timestamp only
+            (h(0, 0), 100, h(1, 1), 99, false),
+            (h(0, 0), 100, h(1, 1), 100, true),
+            (h(0, 0), 100, h(1, 1), 101, true),
+            // height only
+            (h(1, 10), 0, h(1, 9), u64::MAX, false),
+            (h(1, 10), 0, h(1, 10), 0, true),
+            (h(1, 10), 0, h(1, 11), 0, true),
+            // a higher revision number elapses the timeout regardless of the revision height
+            (h(1, 10), 0, h(2, 1), 0, true),
+            (h(2, 1), 0, h(1, 10), 0, false),
+            (h(0, 10), 0, h(1, 1), 0, true),
+            (h(1, 10), 0, Height::new(10), 0, false),
+            // both, either elapsing is sufficient
+            (h(1, 10), 100, h(1, 9), 99, false),
+            (h(1, 10), 100, h(1, 10), 99, true),
+            (h(1, 10), 100, h(1, 9), 100, true),
+            (h(1, 10), 100, h(1, 10), 100, true),
+        ];
+
+        for (timeout_height, timeout_timestamp, height, timestamp, elapsed) in cases {
+            assert_eq!(
+                TimeoutSpec::from_v1(timeout_height, timeout_timestamp)
+                    .is_elapsed(height, ts(timestamp)),
+                elapsed,
+                "timeout ({timeout_height}, {timeout_timestamp}) at ({height}, {timestamp})"
+            );
+        }
+    }
+
+    #[test]
+    fn union_is_elapsed() {
+        let h = Height::new_with_revision;
+
+        let cases = [
+            // disabled
+            (0, 0, h(1, u64::MAX), u64::MAX, false),
+            // timestamp only
+            (0, 100, h(1, 1), 99, false),
+            (0, 100, h(1, 1), 100, true),
+            (0, 100, h(1, 1), 101, true),
+            // height only, the revision number of the counterparty is ignored
+            (10, 0, h(1, 9), u64::MAX, false),
+            (10, 0, h(1, 10), 0, true),
+            (10, 0, h(1, 11), 0, true),
+            (10, 0, h(2, 9), 0, false),
+            (10, 0, Height::new(10), 0, true),
+            // both, both must have elapsed
+            (10, 100, h(1, 9), 99, false),
+            (10, 100, h(1, 10), 99, false),
+            (10, 100, h(1, 9), 100, false),
+            (10, 100, h(1, 10), 100, true),
+        ];
+
+        for (timeout_height, timeout_timestamp, height, timestamp, elapsed) in cases {
+            assert_eq!(
+                TimeoutSpec::from_union(timeout_height, timeout_timestamp)
+                    .is_elapsed(height, ts(timestamp)),
+                elapsed,
+                "timeout ({timeout_height}, {timeout_timestamp}) at ({height}, {timestamp})"
+            );
+        }
+    }
+}
@@ -0,0 +1,405 @@
+//! Backpressure for event sources, based on the depth of the queue.
+//!
+//! Event sources fetch blocks as fast as the chain produces them, regardless of
+//! whether the rest of voyager is able to keep up with the events they emit. If
+//! the downstream plugins are slower than the chain (or stalled entirely), the
+//! queue grows without bound.
+//!
+//! [`Backpressure`] slows an event source down while the lanes of the queue that
+//! it feeds into are too deep (see [`QueueStats`]). Once the depth exceeds the
+//! [high-water mark](BackpressureConfig::high_water_mark), an extra defer is
+//! inserted before the next fetch, proportional to the amount of ops over the
+//! [low-water mark](BackpressureConfig::low_water_mark). The normal pace is only
+//! resumed once the depth has dropped below the low-water mark, such that the
+//! event source doesn't flap between the two states around a single threshold.
+
+use std::{
+    num::NonZeroU64,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use jsonrpsee::core::RpcResult;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+use unionlabs::{option_unwrap, ErrorReporter};
+use voyager_vm::{defer, seq, Op, QueueMessage, QueueStats, READY_LANE};
+
+/// A source of [`QueueStats`].
+#[allow(async_fn_in_trait)]
+pub trait QueueStatsSource {
+    async fn queue_stats(&self) -> RpcResult<QueueStats>;
+}
+
+#[cfg(feature = "server")]
+impl QueueStatsSource for crate::VoyagerClient {
+    async fn queue_stats(&self) -> RpcResult<QueueStats> {
+        crate::VoyagerClient::queue_stats(self).await
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BackpressureConfig {
+    /// The lanes of the queue that are considered downstream of the event
+    /// source. The depth is summed over all of these lanes.
+    ///
+    /// Defaults to the [ready lane](READY_LANE).
+    #[serde(default = "default_lanes")]
+    pub lanes: Vec<String>,
+    /// Start slowing down once the depth exceeds this amount of ops.
+    pub high_water_mark: u64,
+    /// Resume the normal pace once the depth drops below this amount of ops.
+    pub low_water_mark: u64,
+    /// The amount of ops the downstream is expected to process per second.
+    /// The inserted defer is the time it takes to process the ops over the
+    /// low-water mark at this rate.
+    #[serde(default = "default_drain_rate")]
+    pub drain_rate: NonZeroU64,
+    /// The maximum defer that is inserted before a single fetch.
+    #[serde(default = "default_max_defer_seconds")]
+    pub max_defer_seconds: u64,
+    /// Also slow down if the oldest op in any of the lanes has been queued for
+    /// longer than this, regardless of the depth.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_op_age_seconds: Option<u64>,
+}
+
+fn default_lanes() -> Vec<String> {
+    vec![READY_LANE.to_owned()]
+}
+
+fn default_drain_rate() -> NonZeroU64 {
+    const { option_unwrap!(NonZeroU64::new(100)) }
+}
+
+fn default_max_defer_seconds() -> u64 {
+    60
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error(
+    "the low-water mark ({low_water_mark}) must not be greater than the \
+    high-water mark ({high_water_mark})"
+)]
+pub struct InvalidWaterMarks {
+    pub low_water_mark: u64,
+    pub high_water_mark: u64,
+}
+
+impl BackpressureConfig {
+    pub fn validate(&self) -> Result<(), InvalidWaterMarks> {
+        if self.low_water_mark > self.high_water_mark {
+            Err(InvalidWaterMarks {
+                low_water_mark: self.low_water_mark,
+                high_water_mark: self.high_water_mark,
+            })
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// The backpressure state of a single event source.
+#[derive(Debug, Default)]
+pub struct Backpressure {
+    engaged: AtomicBool,
+}
+
+impl Backpressure {
+    /// Whether the event source is currently being slowed down.
+    #[must_use]
+    pub fn engaged(&self) -> bool {
+        self.engaged.load(Ordering::Relaxed)
+    }
+
+    /// Update the state with the latest `stats`, returning the amount of
+    /// seconds to defer the next fetch by, if any.
+    pub fn defer_seconds(
+        &self,
+        config: &BackpressureConfig,
+        stats: &QueueStats,
+        now: u64,
+    ) -> Option<u64> {
+        let lanes = config.lanes.iter().map(|lane| stats.lane(lane));
+
+        let depth = lanes.clone().map(|lane| lane.depth).sum::<u64>();
+        let oldest_age = lanes.map(|lane| lane.oldest_age(now)).max().unwrap_or(0);
+
+        let too_old = config
+            .max_op_age_seconds
+            .is_some_and(|max_op_age_seconds| oldest_age > max_op_age_seconds);
+
+        let engaged = if self.engaged() {
+            depth >= config.low_water_mark || too_old
+        } else {
+            depth > config.high_water_mark || too_old
+        };
+
+        if self.engaged.swap(engaged, Ordering::Relaxed) != engaged {
+            if engaged {
+                info!(depth, oldest_age, "queue is backed up, slowing down");
+            } else {
+                info!(depth, oldest_age, "queue has drained, resuming normal pace");
+            }
+        }
+
+        engaged.then(|| {
+            depth
+                .saturating_sub(config.low_water_mark)
+                .div_ceil(config.drain_rate.get())
+                .clamp(1, config.max_defer_seconds.max(1))
+        })
+    }
+
+    /// Defer `op` if the queue is backed up.
+    ///
+    /// Backpressure is best effort; if the stats can't be read, `op` is
+    /// returned as is.
+    pub async fn apply<T: QueueMessage>(
+        &self,
+        config: &BackpressureConfig,
+        source: &impl QueueStatsSource,
+        op: Op<T>,
+        now: u64,
+    ) -> Op<T> {
+        let stats = match source.queue_stats().await {
+            Ok(stats) => stats,
+            Err(err) => {
+                warn!(
+                    "error querying queue stats, not applying backpressure: {}",
+                    ErrorReporter(err)
+                );
+                return op;
+            }
+        };
+
+        match self.defer_seconds(config, &stats, now) {
+            Some(seconds) => seq([defer(now + seconds), op]),
+            None => op,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::VecDeque, sync::Mutex};
+
+    use jsonrpsee::types::ErrorObject;
+    use voyager_vm::{noop, LaneStats};
+
+    use super::*;
+    use crate::VoyagerMessage;
+
+    const NOW: u64 = 1_000_000;
+
+    /// Returns the queued responses in order.
+    #[derive(Default)]
+    struct MockStatsSource(Mutex<VecDeque<RpcResult<QueueStats>>>);
+
+    impl MockStatsSource {
+        fn push(&self, stats: RpcResult<QueueStats>) {
+            self.0.lock().unwrap().push_back(stats);
+        }
+    }
+
+    impl QueueStatsSource for MockStatsSource {
+        async fn queue_stats(&self) -> RpcResult<QueueStats> {
+            self.0.lock().unwrap().pop_front().unwrap()
+        }
+    }
+
+    fn config() -> BackpressureConfig {
+        BackpressureConfig {
+            lanes: vec![READY_LANE.to_owned(), "plugin/batch".to_owned()],
+            high_water_mark: 1000,
+            low_water_mark: 200,
+            drain_rate: NonZeroU64::new(100).unwrap(),
+            max_defer_seconds: 30,
+            max_op_age_seconds: None,
+        }
+    }
+
+    fn stats(ready: u64, batch: u64) -> QueueStats {
+        QueueStats {
+            lanes: [
+                (READY_LANE, ready),
+                ("plugin/batch", batch),
+                // not downstream of this event source
+                ("plugin/other", 100_000),
+            ]
+            .into_iter()
+            .filter(|(_, depth)| *depth > 0)
+            .map(|(lane, depth)| {
+                (
+                    lane.to_owned(),
+                    LaneStats {
+                        depth,
+                        oldest_enqueued_at: NOW,
+                    },
+                )
+            })
+            .collect(),
+        }
+    }
+
+    async fn apply(
+        backpressure: &Backpressure,
+        config: &BackpressureConfig,
+        stats: RpcResult<QueueStats>,
+    ) -> Op<VoyagerMessage> {
+        let source = MockStatsSource::default();
+        source.push(stats);
+
+        backpressure.apply(config, &source, noop(), NOW).await
+    }
+
+    #[tokio::test]
+    async fn no_defer_below_high_water_mark() {
+        let backpressure = Backpressure::default();
+
+        assert_eq!(
+            apply(&backpressure, &config(), Ok(stats(0, 0))).await,
+            noop()
+        );
+        assert_eq!(
+            apply(&backpressure, &config(), Ok(stats(500, 500))).await,
+            noop()
+        );
+        assert!(!backpressure.engaged());
+    }
+
+    #[tokio::test]
+    async fn defer_is_proportional_to_overflow() {
+        let backpressure = Backpressure::default();
+
+        // 1200 - 200 = 1000 ops over the low-water mark, at 100 ops/s
+        assert_eq!(
+            apply(&backpressure, &config(), Ok(stats(700, 500))).await,
+            seq([defer(NOW + 10), noop()])
+        );
+        assert!(backpressure.engaged());
+
+        assert_eq!(
+            apply(&backpressure, &config(), Ok(stats(1401, 500))).await,
+            seq([defer(NOW + 18), noop()])
+        );
+
+        // clamped to the max defer
+        assert_eq!(
+            apply(&backpressure, &config(), Ok(stats(100_000, 0))).await,
+            seq([defer(NOW + 30), noop()])
+        );
+    }
+
+    #[test]
+    fn hysteresis() {
+        let backpressure = Backpressure::default();
+        let config = config();
+
+        assert_eq!(
+            backpressure.defer_seconds(&config, &stats(1001, 0), NOW),
+            Some(9)
+        );
+
+        // still engaged between the water marks
+        assert_eq!(
+            backpressure.defer_seconds(&config, &stats(600, 0), NOW),
+            Some(4)
+        );
+        assert_eq!(
+            backpressure.defer_seconds(&config, &stats(200, 0), NOW),
+            Some(1)
+        );
+
+        // resumed below the low-water mark
+        assert_eq!(
+            backpressure.defer_seconds(&config, &stats(199, 0), NOW),
+            None
+        );
+        assert!(!backpressure.engaged());
+
+        // not engaged again until the high-water mark is exceeded
+        assert_eq!(
+            backpressure.defer_seconds(&config, &stats(600, 0), NOW),
+            None
+        );
+        assert_eq!(
+            backpressure.defer_seconds(&config, &stats(1000, 0), NOW),
+            None
+        );
+        assert_eq!(
+            backpressure.defer_seconds(&config, &stats(1001, 0), NOW),
+            Some(9)
+        );
+    }
+
+    #[test]
+    fn max_op_age() {
+        let backpressure = Backpressure::default();
+        let config = BackpressureConfig {
+            max_op_age_seconds: Some(60),
+            ..config()
+        };
+
+        let mut stats = stats(10, 0);
+
+        assert_eq!(backpressure.defer_seconds(&config, &stats, NOW + 60), None);
+        assert_eq!(
+            backpressure.defer_seconds(&config, &stats, NOW + 61),
+            Some(1)
+        );
+
+        stats.lanes.get_mut(READY_LANE).unwrap().oldest_enqueued_at = NOW + 61;
+
+        assert_eq!(backpressure.defer_seconds(&config, &stats, NOW + 61), None);
+    }
+
+    #[tokio::test]
+    async fn stats_error_does_not_block() {
+        let backpressure = Backpressure::default();
+
+        assert_eq!(
+            apply(
+                &backpressure,
+                &config(),
+                Err(ErrorObject::owned(-2, "queue is not available", None::<()>))
+            )
+            .await,
+            noop()
+        );
+    }
+
+    #[test]
+    fn validate() {
+        assert_eq!(config().validate(), Ok(()));
+        assert_eq!(
+            BackpressureConfig {
+                low_water_mark: 1001,
+                ..config()
+            }
+            .validate(),
+            Err(InvalidWaterMarks {
+                low_water_mark: 1001,
+                high_water_mark: 1000,
+            })
+        );
+    }
+
+    #[test]
+    fn config_defaults() {
+        assert_eq!(
+            serde_json::from_str::<BackpressureConfig>(
+                r#"{"high_water_mark":1000,"low_water_mark":200}"#
+            )
+            .unwrap(),
+            BackpressureConfig {
+                lanes: vec![READY_LANE.to_owned()],
+                high_water_mark: 1000,
+                low_water_mark: 200,
+                drain_rate: NonZeroU64::new(100).unwrap(),
+                max_defer_seconds: 60,
+                max_op_age_seconds: None,
+            }
+        );
+    }
+}
@@ -0,0 +1,363 @@
+//! Monitoring of the balances of the signers of transaction plugins.
+//!
+//! A signer that runs out of gas tokens only shows up as failing transactions.
+//! Transaction plugins configured with a [`BalanceMonitorConfig`] periodically
+//! query the spendable balance of every key in their keyring, by requeueing a
+//! [`CheckBalances`] call every
+//! [`check_interval`](BalanceMonitorConfig::check_interval) seconds. Once the
+//! balance of a key drops below
+//! [`warn_balance`](BalanceMonitorConfig::warn_balance) or
+//! [`min_balance`](BalanceMonitorConfig::min_balance), a [`SignerBalanceLow`]
+//! is emitted.
+//!
+//! With [`pause_below_min`](BalanceMonitorConfig::pause_below_min), keys below
+//! the minimum balance are also [paused](ConcurrentKeyring::pause) in the
+//! keyring, such that the remaining funded keys carry the load. They are
+//! resumed automatically once they have been topped up.
+
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    hash::Hash,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use chain_utils::keyring::ConcurrentKeyring;
+use macros::model;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+use voyager_core::ChainId;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BalanceMonitorConfig {
+    /// How often (in seconds) to check the balances of the keyring.
+    #[serde(default = "default_check_interval")]
+    pub check_interval: u64,
+    /// Emit a [`SignerBalanceLow`] once the balance of a key drops below this
+    /// amount (in the smallest denomination of the gas token).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub warn_balance: Option<u128>,
+    /// Emit a [`SignerBalanceLow`] once the balance of a key drops below this
+    /// amount (in the smallest denomination of the gas token), and pause the
+    /// key if [`pause_below_min`](Self::pause_below_min) is set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_balance: Option<u128>,
+    /// Don't use keys whose balance is below
+    /// [`min_balance`](Self::min_balance) to submit transactions, until they
+    /// are topped up.
+    #[serde(default)]
+    pub pause_below_min: bool,
+}
+
+fn default_check_interval() -> u64 {
+    5 * 60
+}
+
+/// How the balance of a key compares to the thresholds of a
+/// [`BalanceMonitorConfig`], ordered from best to worst.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum BalanceLevel {
+    Funded,
+    /// The balance is below [`BalanceMonitorConfig::warn_balance`].
+    Low,
+    /// The balance is below [`BalanceMonitorConfig::min_balance`].
+    BelowMin,
+}
+
+impl BalanceMonitorConfig {
+    #[must_use]
+    pub fn level(&self, balance: u128) -> BalanceLevel {
+        if self.min_balance.is_some_and(|min| balance < min) {
+            BalanceLevel::BelowMin
+        } else if self.warn_balance.is_some_and(|warn| balance < warn) {
+            BalanceLevel::Low
+        } else {
+            BalanceLevel::Funded
+        }
+    }
+
+    /// The threshold that a balance at `level` is below, if any.
+    #[must_use]
+    pub fn threshold(&self, level: BalanceLevel) -> Option<u128> {
+        match level {
+            BalanceLevel::Funded => None,
+            BalanceLevel::Low => self.warn_balance,
+            BalanceLevel::BelowMin => self.min_balance,
+        }
+    }
+}
+
+/// Check the balances of the keyring of a transaction plugin, and requeue
+/// this call after [`BalanceMonitorConfig::check_interval`].
+#[model]
+pub struct CheckBalances {
+    /// The [`BalanceMonitor`] instance that queued this check. Checks queued by
+    /// a previous run of the plugin are dropped, since a new check is started
+    /// on the first submission after a restart.
+    pub instance: u64,
+}
+
+/// The balance of a signer dropped below one of the thresholds of the
+/// [`BalanceMonitorConfig`].
+#[model]
+pub struct SignerBalanceLow {
+    pub chain_id: ChainId,
+    pub address: String,
+    #[serde(with = "::serde_utils::string")]
+    pub balance: u128,
+    #[serde(with = "::serde_utils::string")]
+    pub threshold: u128,
+}
+
+#[derive(Debug, Clone)]
+pub struct BalanceMonitor {
+    pub chain_id: ChainId,
+    pub config: BalanceMonitorConfig,
+    instance: u64,
+    started: Arc<AtomicBool>,
+    /// The level of each address as of the last check, keyed by the display
+    /// representation of the address.
+    levels: Arc<Mutex<HashMap<String, BalanceLevel>>>,
+}
+
+impl BalanceMonitor {
+    #[must_use]
+    pub fn new(chain_id: ChainId, config: BalanceMonitorConfig) -> Self {
+        Self {
+            chain_id,
+            config,
+            instance: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .expect("system time is after the unix epoch")
+                .as_nanos() as u64,
+            started: Arc::new(AtomicBool::new(false)),
+            levels: Arc::default(),
+        }
+    }
+
+    /// The first [`CheckBalances`] of this instance, if it has not been
+    /// started yet.
+    #[must_use]
+    pub fn start(&self) -> Option<CheckBalances> {
+        (!self.started.swap(true, Ordering::SeqCst)).then_some(CheckBalances {
+            instance: self.instance,
+        })
+    }
+
+    /// Whether `check` was queued by this instance.
+    #[must_use]
+    pub fn is_current(&self, check: &CheckBalances) -> bool {
+        check.instance == self.instance
+    }
+
+    /// Process the `balances` of the keys in `keyring`, pausing or resuming the
+    /// keys as configured.
+    ///
+    /// A [`SignerBalanceLow`] is returned for every key whose balance dropped
+    /// below a threshold since the previous check, such that a low balance is
+    /// only reported once per threshold until the key is topped up.
+    pub fn update<A: Hash + Eq + Clone + Display, S: 'static>(
+        &self,
+        keyring: &ConcurrentKeyring<A, S>,
+        balances: impl IntoIterator<Item = (A, u128)>,
+    ) -> Vec<SignerBalanceLow> {
+        let mut levels = self.levels.lock().expect("lock is not poisoned");
+
+        let mut low = vec![];
+
+        for (address, balance) in balances {
+            let level = self.config.level(balance);
+
+            let previous = levels
+                .insert(address.to_string(), level)
+                .unwrap_or(BalanceLevel::Funded);
+
+            if level > previous {
+                let threshold = self
+                    .config
+                    .threshold(level)
+                    .expect("level is below a threshold; qed;");
+
+                warn!(
+                    keyring = %keyring.name,
+                    %address,
+                    balance,
+                    threshold,
+                    "signer balance is low"
+                );
+
+                low.push(SignerBalanceLow {
+                    chain_id: self.chain_id.clone(),
+                    address: address.to_string(),
+                    balance,
+                    threshold,
+                });
+            } else if level == BalanceLevel::Funded && previous != BalanceLevel::Funded {
+                info!(keyring = %keyring.name, %address, balance, "signer has been topped up");
+            }
+
+            if self.config.pause_below_min && level == BalanceLevel::BelowMin {
+                if keyring.pause(&address) {
+                    warn!(
+                        keyring = %keyring.name,
+                        %address,
+                        balance,
+                        "pausing signer until it is topped up"
+                    );
+                }
+            } else if keyring.resume(&address) {
+                info!(keyring = %keyring.name, %address, balance, "resuming signer");
+            }
+        }
+
+        low
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chain_utils::keyring::KeyringEntry;
+
+    use super::*;
+
+    fn config(pause_below_min: bool) -> BalanceMonitorConfig {
+        BalanceMonitorConfig {
+            check_interval: 60,
+            warn_balance: Some(1_000),
+            min_balance: Some(100),
+            pause_below_min,
+        }
+    }
+
+    fn keyring() -> ConcurrentKeyring<u8, ()> {
+        ConcurrentKeyring::new(
+            "keyring",
+            [1, 2].into_iter().map(|address| KeyringEntry {
+                name: address.to_string(),
+                address,
+                signer: (),
+            }),
+        )
+    }
+
+    fn low(address: u8, balance: u128, threshold: u128) -> SignerBalanceLow {
+        SignerBalanceLow {
+            chain_id: ChainId::new("chain"),
+            address: address.to_string(),
+            balance,
+            threshold,
+        }
+    }
+
+    #[test]
+    fn threshold_evaluation() {
+        let config = config(false);
+
+        assert_eq!(config.level(1_000), BalanceLevel::Funded);
+        assert_eq!(config.level(999), BalanceLevel::Low);
+        assert_eq!(config.level(100), BalanceLevel::Low);
+        assert_eq!(config.level(99), BalanceLevel::BelowMin);
+        assert_eq!(config.level(0), BalanceLevel::BelowMin);
+
+        let warn_only = BalanceMonitorConfig {
+            min_balance: None,
+            ..config.clone()
+        };
+        assert_eq!(warn_only.level(0), BalanceLevel::Low);
+
+        let unset = BalanceMonitorConfig {
+            warn_balance: None,
+            min_balance: None,
+            ..config
+        };
+        assert_eq!(unset.level(0), BalanceLevel::Funded);
+    }
+
+    #[test]
+    fn low_balance_is_reported_once_per_threshold() {
+        let monitor = BalanceMonitor::new(ChainId::new("chain"), config(false));
+        let keyring = keyring();
+
+        assert!(monitor
+            .update(&keyring, [(1, 5_000), (2, 5_000)])
+            .is_empty());
+        assert_eq!(
+            monitor.update(&keyring, [(1, 500), (2, 5_000)]),
+            [low(1, 500, 1_000)]
+        );
+        assert!(monitor.update(&keyring, [(1, 400), (2, 5_000)]).is_empty());
+        assert_eq!(
+            monitor.update(&keyring, [(1, 50), (2, 5_000)]),
+            [low(1, 50, 100)]
+        );
+        assert!(monitor.update(&keyring, [(1, 50), (2, 5_000)]).is_empty());
+
+        // topped up, and reported again once it drops below a threshold again
+        assert!(monitor
+            .update(&keyring, [(1, 5_000), (2, 5_000)])
+            .is_empty());
+        assert_eq!(
+            monitor.update(&keyring, [(1, 10), (2, 5_000)]),
+            [low(1, 10, 100)]
+        );
+
+        // keys are only paused if configured
+        assert!(!keyring.is_paused(&1));
+    }
+
+    #[test]
+    fn pause_and_resume() {
+        let monitor = BalanceMonitor::new(ChainId::new("chain"), config(true));
+        let keyring = keyring();
+
+        monitor.update(&keyring, [(1, 500), (2, 5_000)]);
+        assert!(!keyring.is_paused(&1));
+
+        monitor.update(&keyring, [(1, 50), (2, 5_000)]);
+        assert!(keyring.is_paused(&1));
+        assert!(!keyring.is_paused(&2));
+
+        // still low, but above the minimum
+        monitor.update(&keyring, [(1, 500), (2, 5_000)]);
+        assert!(!keyring.is_paused(&1));
+
+        monitor.update(&keyring, [(1, 0), (2, 0)]);
+        assert!(keyring.is_paused(&1));
+        assert!(keyring.is_paused(&2));
+
+        monitor.update(&keyring, [(1, 5_000), (2, 5_000)]);
+        assert!(!keyring.is_paused(&1));
+        assert!(!keyring.is_paused(&2));
+    }
+
+    #[test]
+    fn only_started_once() {
+        let monitor = BalanceMonitor::new(ChainId::new("chain"), config(false));
+
+        let check = monitor.start().unwrap();
+        assert!(monitor.is_current(&check));
+        assert_eq!(monitor.start(), None);
+
+        assert!(!monitor.is_current(&CheckBalances {
+            instance: check.instance.wrapping_add(1),
+        }));
+    }
+
+    #[test]
+    fn signer_balance_low_json() {
+        assert_eq!(
+            serde_json::to_value(low(1, 500, 1_000)).unwrap(),
+            serde_json::json!({
+                "chain_id": "chain",
+                "address": "1",
+                "balance": "500",
+                "threshold": "1000",
+            })
+        );
+    }
+}
@@ -0,0 +1,474 @@
+//! Export and import of plugin caches, to warm up a standby voyager instance.
+//!
+//! Caches that are expensive to rebuild (i.e. the checksum to client type cache of the cosmos
+//! event source) are wrapped in a [`TimestampedCache`], which records when each entry was last
+//! written. A running plugin exports its caches with [`PluginClient::export_caches`] as a
+//! [`CacheSnapshot`], which can be imported into another instance with
+//! [`PluginClient::import_caches`].
+//!
+//! Importing a snapshot merges it into the local caches: an entry is only written if there is no
+//! local entry for its key, or if the local entry is older than the imported one (see
+//! [`merge_entry`]). Local data is never overwritten with older (or equally old) data, so a
+//! snapshot can be imported into an instance that is already running without losing anything.
+//!
+//! Snapshots are versioned with [`CACHE_SNAPSHOT_VERSION`]. Snapshots written with a version that
+//! is not in [`MIN_CACHE_SNAPSHOT_VERSION`]`..=`[`CACHE_SNAPSHOT_VERSION`] are rejected as a
+//! whole, and caches that are not known to the importing plugin are ignored.
+//!
+//! [`PluginClient::export_caches`]: crate::module::PluginClient::export_caches
+//! [`PluginClient::import_caches`]: crate::module::PluginClient::import_caches
+
+use std::{collections::BTreeMap, fmt::Debug, hash::Hash};
+
+use chain_utils::bounded::{BoundedMap, BoundsConfig};
+use jsonrpsee::types::{error::INVALID_PARAMS_CODE, ErrorObject, ErrorObjectOwned};
+use macros::model;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use unionlabs::ErrorReporter;
+
+/// The version of the snapshots written by this version of voyager.
+pub const CACHE_SNAPSHOT_VERSION: u32 = 1;
+
+/// The oldest snapshot version that can still be imported.
+pub const MIN_CACHE_SNAPSHOT_VERSION: u32 = 1;
+
+/// The caches of a plugin, as returned by [`PluginClient::export_caches`].
+///
+/// Unknown fields are ignored (unlike most types exchanged between plugins), such that snapshots
+/// of newer versions are rejected with [`SnapshotError::UnsupportedVersion`] instead of failing to
+/// decode.
+///
+/// [`PluginClient::export_caches`]: crate::module::PluginClient::export_caches
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CacheSnapshot {
+    pub version: u32,
+    /// The name of the plugin the snapshot was exported from. A snapshot can only be imported into
+    /// a plugin with the same name.
+    pub source: String,
+    /// The entries of each cache, by the name of the cache.
+    pub caches: BTreeMap<String, Vec<CacheEntry>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CacheEntry {
+    pub key: Value,
+    pub value: Value,
+    /// The unix timestamp (in seconds) of when this entry was last written.
+    pub updated_at: u64,
+}
+
+/// The result of importing a [`CacheSnapshot`].
+#[model]
+#[derive(Default)]
+pub struct ImportReport {
+    pub caches: BTreeMap<String, MergeReport>,
+    /// The caches in the snapshot that are not known to the plugin.
+    pub ignored_caches: Vec<String>,
+}
+
+/// The result of merging the entries of a single cache.
+#[model]
+#[derive(Default, Copy, Eq)]
+pub struct MergeReport {
+    /// Entries that did not exist locally.
+    pub inserted: u64,
+    /// Entries that replaced an older local entry.
+    pub replaced: u64,
+    /// Entries that were not imported since the local entry is at least as new.
+    pub kept_local: u64,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SnapshotError {
+    #[error(
+        "unsupported cache snapshot version {found}, only versions \
+        {MIN_CACHE_SNAPSHOT_VERSION}..={CACHE_SNAPSHOT_VERSION} are supported"
+    )]
+    UnsupportedVersion { found: u32 },
+    #[error("cache snapshot was exported from {found}, expected {expected}")]
+    SourceMismatch { expected: String, found: String },
+    #[error("invalid entry in cache {cache}")]
+    InvalidEntry {
+        cache: String,
+        #[source]
+        error: serde_json::Error,
+    },
+}
+
+impl From<SnapshotError> for ErrorObjectOwned {
+    fn from(value: SnapshotError) -> Self {
+        ErrorObject::owned(
+            INVALID_PARAMS_CODE,
+            ErrorReporter(value).with_message("error importing cache snapshot"),
+            None::<()>,
+        )
+    }
+}
+
+impl CacheSnapshot {
+    /// Create an empty snapshot of the current version.
+    #[must_use]
+    pub fn new(source: impl Into<String>) -> Self {
+        Self {
+            version: CACHE_SNAPSHOT_VERSION,
+            source: source.into(),
+            caches: BTreeMap::new(),
+        }
+    }
+
+    #[must_use]
+    pub fn with_cache(mut self, name: impl Into<String>, entries: Vec<CacheEntry>) -> Self {
+        self.caches.insert(name.into(), entries);
+        self
+    }
+
+    /// Check that this snapshot can be imported into the plugin `expected_source`.
+    pub fn validate(&self, expected_source: &str) -> Result<(), SnapshotError> {
+        if !(MIN_CACHE_SNAPSHOT_VERSION..=CACHE_SNAPSHOT_VERSION).contains(&self.version) {
+            return Err(SnapshotError::UnsupportedVersion {
+                found: self.version,
+            });
+        }
+
+        if self.source != expected_source {
+            return Err(SnapshotError::SourceMismatch {
+                expected: expected_source.to_owned(),
+                found: self.source.clone(),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// What to do with an imported entry, given the timestamp of the local entry for the same key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeOutcome {
+    Insert,
+    Replace,
+    KeepLocal,
+}
+
+/// Decide whether an imported entry written at `imported` replaces the local entry written at
+/// `local`, if any. On equal timestamps, the local entry is kept.
+#[must_use]
+pub fn merge_entry(local: Option<u64>, imported: u64) -> MergeOutcome {
+    match local {
+        None => MergeOutcome::Insert,
+        Some(local) if imported > local => MergeOutcome::Replace,
+        Some(_) => MergeOutcome::KeepLocal,
+    }
+}
+
+impl MergeReport {
+    pub fn record(&mut self, outcome: MergeOutcome) {
+        match outcome {
+            MergeOutcome::Insert => self.inserted += 1,
+            MergeOutcome::Replace => self.replaced += 1,
+            MergeOutcome::KeepLocal => self.kept_local += 1,
+        }
+    }
+}
+
+/// A cache that records when each of its entries was last written, such that it can be exported
+/// and merged into another instance of the cache.
+///
+/// The cache is bounded as described in [`chain_utils::bounded`]. The TTL of an entry starts when
+/// it is written to this instance, including when it is imported, regardless of its timestamp.
+#[derive(Debug)]
+pub struct TimestampedCache<K, V> {
+    entries: BoundedMap<K, (V, u64)>,
+}
+
+impl<K: Eq + Hash + Clone + Debug, V: Clone> Default for TimestampedCache<K, V> {
+    /// An unbounded cache.
+    fn default() -> Self {
+        Self {
+            entries: BoundedMap::new("timestamped_cache", usize::MAX, None),
+        }
+    }
+}
+
+impl<K: Eq + Hash + Clone + Debug, V: Clone> TimestampedCache<K, V> {
+    /// A cache bounded by `bounds`. `name` is used to identify the cache in logs.
+    #[must_use]
+    pub fn new(name: &'static str, bounds: &BoundsConfig) -> Self {
+        Self {
+            entries: BoundedMap::from_config(name, bounds),
+        }
+    }
+
+    #[must_use]
+    pub fn get(&self, key: &K) -> Option<V> {
+        self.entries.get(key).map(|(value, _)| value)
+    }
+
+    /// Insert `value` for `key`, written now.
+    pub fn insert(&self, key: K, value: V) {
+        self.insert_at(key, value, voyager_vm::now());
+    }
+
+    /// Insert `value` for `key`, written at the unix timestamp `updated_at`.
+    pub fn insert_at(&self, key: K, value: V, updated_at: u64) {
+        self.entries.insert(key, (value, updated_at));
+    }
+
+    /// All entries in the cache, without their timestamps.
+    #[must_use]
+    pub fn to_vec(&self) -> Vec<(K, V)> {
+        self.entries
+            .to_vec()
+            .into_iter()
+            .map(|(key, (value, _))| (key, value))
+            .collect()
+    }
+
+    /// The amount of entries in the cache.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl<K, V> TimestampedCache<K, V>
+where
+    K: Eq + Hash + Clone + Debug + Serialize + DeserializeOwned,
+    V: Clone + Debug + Serialize + DeserializeOwned,
+{
+    /// All entries in the cache, in the format of a [`CacheSnapshot`].
+    #[must_use]
+    pub fn export(&self) -> Vec<CacheEntry> {
+        self.entries
+            .to_vec()
+            .into_iter()
+            .map(|(key, (value, updated_at))| CacheEntry {
+                key: crate::into_value(key),
+                value: crate::into_value(value),
+                updated_at,
+            })
+            .collect()
+    }
+
+    /// Merge `entries` into the cache, keeping the timestamps of the imported entries.
+    ///
+    /// All entries are decoded before any of them are written, so if any entry is invalid, the
+    /// cache is left unchanged.
+    pub fn import(
+        &self,
+        cache: &str,
+        entries: Vec<CacheEntry>,
+    ) -> Result<MergeReport, SnapshotError> {
+        let entries = entries
+            .into_iter()
+            .map(|entry| {
+                Ok((
+                    serde_json::from_value::<K>(entry.key)?,
+                    serde_json::from_value::<V>(entry.value)?,
+                    entry.updated_at,
+                ))
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|error| SnapshotError::InvalidEntry {
+                cache: cache.to_owned(),
+                error,
+            })?;
+
+        let mut report = MergeReport::default();
+
+        for (key, value, updated_at) in entries {
+            let outcome = self.entries.update(key, |local| {
+                let outcome = merge_entry(local.map(|(_, t)| *t), updated_at);
+
+                (
+                    (outcome != MergeOutcome::KeepLocal).then_some((value, updated_at)),
+                    outcome,
+                )
+            });
+
+            report.record(outcome);
+        }
+
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn cache(entries: &[(u32, &str, u64)]) -> TimestampedCache<u32, String> {
+        let cache = TimestampedCache::default();
+
+        for (key, value, updated_at) in entries {
+            cache.insert_at(*key, (*value).to_owned(), *updated_at);
+        }
+
+        cache
+    }
+
+    fn sorted(cache: &TimestampedCache<u32, String>) -> Vec<CacheEntry> {
+        let mut entries = cache.export();
+        entries.sort_by_key(|entry| entry.key.as_u64());
+        entries
+    }
+
+    #[test]
+    fn merge_outcomes() {
+        assert_eq!(merge_entry(None, 10), MergeOutcome::Insert);
+        assert_eq!(merge_entry(Some(9), 10), MergeOutcome::Replace);
+        assert_eq!(merge_entry(Some(10), 10), MergeOutcome::KeepLocal);
+        assert_eq!(merge_entry(Some(11), 10), MergeOutcome::KeepLocal);
+    }
+
+    #[test]
+    fn merge_conflicts() {
+        let local = cache(&[
+            (1, "local-newer", 20),
+            (2, "local-older", 5),
+            (3, "local", 10),
+        ]);
+        let imported = cache(&[
+            (1, "imported-older", 10),
+            (2, "imported-newer", 15),
+            (3, "imported", 10),
+            (4, "imported-new", 1),
+        ]);
+
+        let report = local.import("test", imported.export()).unwrap();
+
+        assert_eq!(
+            report,
+            MergeReport {
+                inserted: 1,
+                replaced: 1,
+                kept_local: 2,
+            }
+        );
+
+        assert_eq!(local.get(&1).unwrap(), "local-newer");
+        assert_eq!(local.get(&2).unwrap(), "imported-newer");
+        // equal timestamps keep the local entry
+        assert_eq!(local.get(&3).unwrap(), "local");
+        assert_eq!(local.get(&4).unwrap(), "imported-new");
+
+        // the imported timestamps are kept
+        assert_eq!(
+            sorted(&local)
+                .into_iter()
+                .map(|entry| entry.updated_at)
+                .collect::<Vec<_>>(),
+            [20, 15, 10, 1]
+        );
+    }
+
+    #[test]
+    fn bounded() {
+        let cache = TimestampedCache::<u32, String>::new(
+            "test",
+            &BoundsConfig {
+                max_entries: 100,
+                ttl_seconds: None,
+            },
+        );
+
+        for key in 0..10_000 {
+            cache.insert_at(key, key.to_string(), u64::from(key));
+            assert!(cache.len() <= 100);
+        }
+
+        // the most recently written entries are kept
+        assert_eq!(cache.get(&9_999).unwrap(), "9999");
+        assert_eq!(cache.get(&9_899), None);
+        assert_eq!(cache.export().len(), 100);
+
+        // imports are bounded as well
+        let imported = TimestampedCache::<u32, String>::default();
+        for key in 10_000..20_000 {
+            imported.insert_at(key, key.to_string(), 1);
+        }
+
+        let report = cache.import("test", imported.export()).unwrap();
+
+        assert_eq!(report.inserted, 10_000);
+        assert_eq!(cache.len(), 100);
+    }
+
+    #[test]
+    fn invalid_entries_are_rejected() {
+        let local = cache(&[(1, "local", 1)]);
+
+        let err = local
+            .import(
+                "test",
+                vec![
+                    CacheEntry {
+                        key: json!(2),
+                        value: json!("valid"),
+                        updated_at: 10,
+                    },
+                    CacheEntry {
+                        key: json!("not a number"),
+                        value: json!("invalid"),
+                        updated_at: 10,
+                    },
+                ],
+            )
+            .unwrap_err();
+
+        assert!(matches!(err, SnapshotError::InvalidEntry { cache, .. } if cache == "test"));
+
+        // nothing was imported
+        assert_eq!(local.get(&2), None);
+    }
+
+    #[test]
+    fn version_mismatch_is_rejected() {
+        let mut snapshot = CacheSnapshot::new("plugin/union-1");
+
+        assert!(snapshot.validate("plugin/union-1").is_ok());
+
+        for version in [0, CACHE_SNAPSHOT_VERSION + 1] {
+            snapshot.version = version;
+
+            assert!(matches!(
+                snapshot.validate("plugin/union-1"),
+                Err(SnapshotError::UnsupportedVersion { found }) if found == version
+            ));
+        }
+
+        snapshot.version = CACHE_SNAPSHOT_VERSION;
+
+        assert!(matches!(
+            snapshot.validate("plugin/union-2"),
+            Err(SnapshotError::SourceMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn round_trip() {
+        let exported = cache(&[(1, "a", 10), (2, "b", 20)]);
+
+        let snapshot = CacheSnapshot::new("plugin/union-1").with_cache("test", exported.export());
+
+        let snapshot =
+            serde_json::from_str::<CacheSnapshot>(&serde_json::to_string(&snapshot).unwrap())
+                .unwrap();
+
+        snapshot.validate("plugin/union-1").unwrap();
+
+        let imported = TimestampedCache::default();
+        imported
+            .import("test", snapshot.caches["test"].clone())
+            .unwrap();
+
+        assert_eq!(sorted(&imported), sorted(&exported));
+    }
+}
@@ -1,20 +1,41 @@
+use std::{fmt, str::FromStr};
+
 use enumorph::Enumorph;
+#[cfg(feature = "server")]
+use ibc_union_spec::IbcUnion;
 use macros::model;
-use serde::de::DeserializeOwned;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+#[cfg(feature = "server")]
 use tracing::{debug, error, info};
 use unionlabs::{ibc::core::client::height::Height, traits::Member};
-use voyager_core::{IbcSpecId, QueryHeight};
-use voyager_vm::{call, defer, noop, now, seq, CallT, Op, QueueError};
+use voyager_core::IbcSpecId;
+#[cfg(feature = "server")]
+use voyager_core::{ClientStatus, IbcSpec, QueryHeight};
+#[cfg(feature = "server")]
+use voyager_vm::{call, data, defer, noop, now, seq};
+use voyager_vm::{
+    op_graph::{Summarize, Summary},
+    CallT, Op, QueueError,
+};
 
 use crate::{
-    core::ChainId, error_object_to_queue_error, json_rpc_error_to_queue_error,
-    module::PluginClient, Context, PluginMessage, RawClientId, VoyagerMessage,
+    core::ChainId, freeze::ClientRef, into_value, PluginMessage, RawClientId, VoyagerMessage,
+};
+#[cfg(feature = "server")]
+use crate::{
+    data::Data,
+    error_object_to_queue_error,
+    freeze::{FreezeError, WATCH_INTERVAL_SECONDS},
+    json_rpc_error_to_queue_error,
+    module::PluginClient,
+    Context,
 };
 
 #[model]
 #[derive(Enumorph)]
 pub enum Call {
     FetchBlocks(FetchBlocks),
+    FetchBlockRange(FetchBlockRange),
 
     FetchUpdateHeaders(FetchUpdateHeaders),
 
@@ -23,6 +44,8 @@ pub enum Call {
     WaitForTimestamp(WaitForTimestamp),
     WaitForTrustedHeight(WaitForTrustedHeight),
 
+    WatchClientFreeze(WatchClientFreeze),
+
     Plugin(PluginMessage),
 }
 
@@ -38,11 +61,89 @@ impl Call {
     }
 }
 
+impl Summarize for Call {
+    fn summarize(&self) -> Summary {
+        match self {
+            Self::Plugin(plugin_message) => plugin_message.summarize(),
+            this => Summary::from_value(&into_value(this)),
+        }
+    }
+}
+
+/// Fetch the blocks on a chain from `from_height` up to and including `to_height`.
+///
+/// This is the bounded counterpart of [`FetchBlocks`], and must likewise be picked up by a
+/// plugin. If it is not handled by a plugin, this will return with a fatal error.
+///
+/// # Implementor's Note
+///
+/// The range is intended to be fetched in chunks, with the size of each chunk chosen by the
+/// plugin. `hint_events_per_block` is the expected density of IBC events in the range (if known,
+/// i.e. from a previous fetch of the same range), which can be used to size the first chunk.
 #[model]
 pub struct FetchBlockRange {
     pub chain_id: ChainId,
     pub from_height: Height,
     pub to_height: Height,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hint_events_per_block: Option<EventDensity>,
+}
+
+/// An amount of IBC events per block. This is always finite and non-negative.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Serialize, Deserialize)]
+#[serde(try_from = "f64", into = "f64")]
+pub struct EventDensity(f64);
+
+// NaN is rejected on construction
+impl Eq for EventDensity {}
+
+impl EventDensity {
+    pub const ZERO: Self = Self(0.0);
+
+    #[must_use]
+    pub fn new(events_per_block: f64) -> Option<Self> {
+        (events_per_block.is_finite() && events_per_block >= 0.0).then_some(Self(events_per_block))
+    }
+
+    #[must_use]
+    pub const fn get(self) -> f64 {
+        self.0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+#[error("invalid event density {0}, expected a finite non-negative number")]
+pub struct InvalidEventDensity(String);
+
+impl TryFrom<f64> for EventDensity {
+    type Error = InvalidEventDensity;
+
+    fn try_from(value: f64) -> Result<Self, Self::Error> {
+        Self::new(value).ok_or_else(|| InvalidEventDensity(value.to_string()))
+    }
+}
+
+impl From<EventDensity> for f64 {
+    fn from(value: EventDensity) -> Self {
+        value.0
+    }
+}
+
+impl FromStr for EventDensity {
+    type Err = InvalidEventDensity;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse::<f64>()
+            .ok()
+            .and_then(Self::new)
+            .ok_or_else(|| InvalidEventDensity(s.to_owned()))
+    }
+}
+
+impl fmt::Display for EventDensity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
 }
 
 /// Fetch blocks on a chain, starting at height `start_height`.
@@ -112,6 +213,16 @@ pub struct WaitForTrustedHeight {
     pub height: Height,
 }
 
+/// Poll the status of the ibc-union client `.client` until it is frozen, then suppress the
+/// relaying over its connections and emit [`Data::RelayingFrozen`]. See [`crate::freeze`].
+///
+/// [`Data::RelayingFrozen`]: crate::data::Data::RelayingFrozen
+#[model]
+pub struct WatchClientFreeze {
+    pub client: ClientRef,
+}
+
+#[cfg(feature = "server")]
 impl CallT<VoyagerMessage> for Call {
     // #[instrument(skip_all, fields(chain_id = %self.chain_id))]
     async fn process(self, ctx: &Context) -> Result<Op<VoyagerMessage>, QueueError> {
@@ -137,6 +248,22 @@ impl CallT<VoyagerMessage> for Call {
                 Err(QueueError::Fatal(message.into()))
             }
 
+            Call::FetchBlockRange(FetchBlockRange {
+                chain_id,
+                from_height,
+                to_height,
+                hint_events_per_block: _,
+            }) => {
+                let message = format!(
+                    "fetch block range request received for chain `{chain_id}` from height \
+                    {from_height} to {to_height} but it was not picked up by a plugin"
+                );
+
+                error!(%message);
+
+                Err(QueueError::Fatal(message.into()))
+            }
+
             Call::FetchUpdateHeaders(FetchUpdateHeaders {
                 chain_id,
                 counterparty_chain_id,
@@ -279,6 +406,38 @@ impl CallT<VoyagerMessage> for Call {
                     ]))
                 }
             }
+            Call::WatchClientFreeze(WatchClientFreeze { client }) => {
+                let meta = ctx
+                    .rpc_server
+                    .client_meta(
+                        &client.chain_id,
+                        &IbcUnion::ID,
+                        QueryHeight::Latest,
+                        RawClientId::new(client.client_id),
+                    )
+                    .await
+                    .map_err(error_object_to_queue_error)?;
+
+                let watch_again = |client| {
+                    seq([
+                        defer(now() + WATCH_INTERVAL_SECONDS),
+                        call(WatchClientFreeze { client }),
+                    ])
+                };
+
+                if meta.status != ClientStatus::Frozen {
+                    return Ok(watch_again(client));
+                }
+
+                match ctx.rpc_server.freeze_relaying(client.clone()).await {
+                    Ok(frozen) => Ok(data(Data::RelayingFrozen(frozen))),
+                    // the freeze is read at the latest finalized height, which may not include it
+                    // yet
+                    Err(FreezeError::NotFrozen { .. }) => Ok(watch_again(client)),
+                    Err(FreezeError::Rpc(err)) => Err(error_object_to_queue_error(err)),
+                    Err(err) => Err(QueueError::Retry(Box::new(err))),
+                }
+            }
             Call::Plugin(PluginMessage { plugin, message }) => Ok(ctx
                 .plugin(plugin)?
                 .call(message)
@@ -287,3 +446,32 @@ impl CallT<VoyagerMessage> for Call {
         }
     }
 }
+
+#[cfg(not(feature = "server"))]
+impl CallT<VoyagerMessage> for Call {
+    async fn process(self, _: &()) -> Result<Op<VoyagerMessage>, QueueError> {
+        Err(QueueError::Fatal(
+            "processing calls requires the `server` feature".into(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn event_density() {
+        assert_eq!(EventDensity::new(2.5).map(EventDensity::get), Some(2.5));
+        assert_eq!(EventDensity::new(0.0), Some(EventDensity::ZERO));
+
+        for invalid in [-1.0, f64::NAN, f64::INFINITY] {
+            assert_eq!(EventDensity::new(invalid), None);
+        }
+
+        assert_eq!("0.5".parse(), Ok(EventDensity(0.5)));
+        assert!("-0.5".parse::<EventDensity>().is_err());
+        assert!(serde_json::from_str::<EventDensity>("-0.5").is_err());
+        assert_eq!(serde_json::to_string(&EventDensity(0.5)).unwrap(), "0.5");
+    }
+}
@@ -1,20 +1,28 @@
 use std::collections::VecDeque;
 
 use enumorph::Enumorph;
+#[cfg(feature = "server")]
 use futures::{stream, StreamExt, TryFutureExt, TryStreamExt};
+#[cfg(feature = "server")]
 use itertools::Itertools;
 use macros::model;
 use serde::de::DeserializeOwned;
 use unionlabs::traits::Member;
-use voyager_core::{ClientInfo, IbcSpecId};
-use voyager_vm::{CallbackT, Op, QueueError};
+#[cfg(feature = "server")]
+use voyager_core::ClientInfo;
+use voyager_core::IbcSpecId;
+use voyager_vm::{
+    op_graph::{Summarize, Summary},
+    CallbackT, Op, QueueError,
+};
 
+use crate::{core::ChainId, data::Data, into_value, PluginMessage, RawClientId, VoyagerMessage};
+#[cfg(feature = "server")]
 use crate::{
-    core::ChainId,
-    data::{ClientUpdate, Data, OrderedClientUpdates, OrderedHeaders},
+    data::{ClientUpdate, OrderedClientUpdates, OrderedHeaders},
     error_object_to_queue_error, json_rpc_error_to_queue_error,
     module::{ClientModuleClient, PluginClient},
-    Context, PluginMessage, RawClientId, VoyagerMessage,
+    Context,
 };
 
 #[model]
@@ -37,6 +45,16 @@ impl Callback {
     }
 }
 
+impl Summarize for Callback {
+    fn summarize(&self) -> Summary {
+        match self {
+            Self::Plugin(plugin_message) => plugin_message.summarize(),
+            this => Summary::from_value(&into_value(this)),
+        }
+    }
+}
+
+#[cfg(feature = "server")]
 impl CallbackT<VoyagerMessage> for Callback {
     async fn process(
         self,
@@ -117,6 +135,15 @@ impl CallbackT<VoyagerMessage> for Callback {
     }
 }
 
+#[cfg(not(feature = "server"))]
+impl CallbackT<VoyagerMessage> for Callback {
+    async fn process(self, _: &(), _: VecDeque<Data>) -> Result<Op<VoyagerMessage>, QueueError> {
+        Err(QueueError::Fatal(
+            "processing callbacks requires the `server` feature".into(),
+        ))
+    }
+}
+
 /// Required data: [`OrderedHeaders`]
 #[model]
 pub struct AggregateMsgUpdateClientsFromOrderedHeaders {
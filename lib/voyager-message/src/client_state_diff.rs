@@ -0,0 +1,761 @@
+//! Comparison of the client state of a client against the current parameters of the chain it
+//! tracks.
+//!
+//! A client is created with a snapshot of the parameters of the tracked chain (its unbonding
+//! period, fork schedule, ibc contract address, etc). When these change on the tracked chain (i.e.
+//! through a governance proposal), the client keeps verifying against the old parameters until it
+//! is migrated, and the drift is usually only discovered once the client breaks. [`diff_client_state`]
+//! compares the client state stored on the host chain against the self client state of the tracked
+//! chain at its latest height, which is the client state that the client would be created with
+//! today.
+//!
+//! Only the fields that are expected to be equal are compared (see [`comparable_fields`]). Heights
+//! and timestamps always differ between the two, and are only compared if requested. Mismatches
+//! that are known to break the client (or to make it unsafe) are flagged as [`Danger`]s.
+
+use std::collections::BTreeSet;
+
+use jsonrpsee::types::{error::INVALID_PARAMS_CODE, ErrorObject, ErrorObjectOwned};
+use macros::model;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use unionlabs::{ibc::core::client::height::Height, ErrorReporter};
+use voyager_core::{ChainId, ClientType, IbcSpecId, QueryHeight};
+
+use crate::{query::VoyagerQuery, RawClientId, FATAL_JSONRPC_ERROR_CODE};
+
+/// The difference between the client state of a client and the self client state of the chain it
+/// tracks.
+#[model]
+pub struct ClientStateDiff {
+    pub client_type: ClientType,
+    /// The chain tracked by the client.
+    pub counterparty_chain_id: ChainId,
+    /// The height of the tracked chain that [`Self::expected`] was generated at.
+    pub expected_height: Height,
+    /// The decoded client state, as stored on the host chain.
+    pub on_chain: Value,
+    /// The self client state of the tracked chain.
+    pub expected: Value,
+    /// The comparable fields that differ, in the order they are defined in the client state.
+    pub fields: Vec<FieldDiff>,
+    /// The mismatches that are known to break the client.
+    pub dangers: BTreeSet<Danger>,
+}
+
+/// A field that differs between the two client states. A field that is missing in one of the
+/// client states is `null`.
+#[model]
+pub struct FieldDiff {
+    pub field: String,
+    pub kind: FieldKind,
+    pub on_chain: Value,
+    pub expected: Value,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FieldKind {
+    /// A parameter of the tracked chain, which is expected to be equal.
+    Parameter,
+    /// A height, which is only compared if requested.
+    Height,
+    /// A timestamp, which is only compared if requested.
+    Timestamp,
+}
+
+/// A mismatch that is known to break the client, or to make it unsafe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Danger {
+    /// The trusting period of the client is not shorter than the unbonding period of the tracked
+    /// chain. Misbehaviour can no longer be punished for the entire trusting period.
+    UnbondingPeriodShorterThanTrustingPeriod,
+    /// The ibc contract of the tracked chain has changed. State proofs are verified against the
+    /// storage of the old contract.
+    IbcContractAddressChanged,
+    /// The fork schedule of the tracked chain has changed. Headers after the changed fork are
+    /// verified with the wrong fork version.
+    ForkScheduleChanged,
+}
+
+/// The fields of the client state of `client_type` that are compared, along with their kind.
+///
+/// Fields that are not listed here (i.e. the `zk_verifying_key_hash` of cometbls, which is pinned
+/// on migration and never set in the self client state) are not compared.
+#[must_use]
+pub fn comparable_fields(client_type: &ClientType) -> Option<&'static [(&'static str, FieldKind)]> {
+    match client_type.as_str() {
+        ClientType::COMETBLS_GROTH16 => Some(&[
+            ("chain_id", FieldKind::Parameter),
+            ("trusting_period", FieldKind::Parameter),
+            ("max_clock_drift", FieldKind::Parameter),
+            ("frozen_height", FieldKind::Height),
+            ("latest_height", FieldKind::Height),
+            ("contract_address", FieldKind::Parameter),
+        ]),
+        ClientType::TENDERMINT => Some(&[
+            ("chain_id", FieldKind::Parameter),
+            ("trust_level", FieldKind::Parameter),
+            ("trusting_period", FieldKind::Parameter),
+            ("unbonding_period", FieldKind::Parameter),
+            ("max_clock_drift", FieldKind::Parameter),
+            ("frozen_height", FieldKind::Height),
+            ("latest_height", FieldKind::Height),
+            ("proof_specs", FieldKind::Parameter),
+            ("upgrade_path", FieldKind::Parameter),
+        ]),
+        ClientType::ETHEREUM => Some(&[
+            ("chain_id", FieldKind::Parameter),
+            ("chain_spec", FieldKind::Parameter),
+            ("genesis_validators_root", FieldKind::Parameter),
+            ("genesis_time", FieldKind::Timestamp),
+            ("fork_parameters", FieldKind::Parameter),
+            ("latest_height", FieldKind::Height),
+            ("frozen_height", FieldKind::Height),
+            ("ibc_contract_address", FieldKind::Parameter),
+        ]),
+        _ => None,
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ClientStateDiffError {
+    #[error("client type `{0}` is not supported")]
+    UnsupportedClientType(ClientType),
+    #[error("invalid {which} client state")]
+    InvalidClientState {
+        which: &'static str,
+        #[source]
+        source: serde_json::Error,
+    },
+    #[error(transparent)]
+    Rpc(#[from] ErrorObjectOwned),
+}
+
+impl From<ClientStateDiffError> for ErrorObjectOwned {
+    fn from(value: ClientStateDiffError) -> Self {
+        match value {
+            ClientStateDiffError::Rpc(err) => err,
+            err => ErrorObject::owned(
+                INVALID_PARAMS_CODE,
+                ErrorReporter(err).to_string(),
+                None::<()>,
+            ),
+        }
+    }
+}
+
+/// Compare the `on_chain` client state of `client_type` against the `expected` self client state,
+/// both provided as JSON. Heights and timestamps are only compared if `include_heights` is set.
+pub fn diff_client_states(
+    client_type: &ClientType,
+    on_chain: &Value,
+    expected: &Value,
+    include_heights: bool,
+) -> Result<(Vec<FieldDiff>, BTreeSet<Danger>), ClientStateDiffError> {
+    let fields = comparable_fields(client_type)
+        .ok_or_else(|| ClientStateDiffError::UnsupportedClientType(client_type.clone()))?;
+
+    let dangers = match client_type.as_str() {
+        ClientType::COMETBLS_GROTH16 => cometbls_dangers(
+            &deserialize("on chain", on_chain)?,
+            &deserialize("expected", expected)?,
+        ),
+        ClientType::TENDERMINT => tendermint_dangers(
+            &deserialize("on chain", on_chain)?,
+            &deserialize("expected", expected)?,
+        ),
+        ClientType::ETHEREUM => ethereum_dangers(
+            &deserialize("on chain", on_chain)?,
+            &deserialize("expected", expected)?,
+        ),
+        _ => unreachable!("comparable fields are defined for this client type; qed;"),
+    };
+
+    let fields = fields
+        .iter()
+        .filter(|(_, kind)| include_heights || matches!(kind, FieldKind::Parameter))
+        .filter_map(|&(field, kind)| {
+            let on_chain = on_chain.get(field).cloned().unwrap_or_default();
+            let expected = expected.get(field).cloned().unwrap_or_default();
+
+            (on_chain != expected).then(|| FieldDiff {
+                field: field.to_owned(),
+                kind,
+                on_chain,
+                expected,
+            })
+        })
+        .collect();
+
+    Ok((fields, dangers))
+}
+
+fn deserialize<T: DeserializeOwned>(
+    which: &'static str,
+    client_state: &Value,
+) -> Result<T, ClientStateDiffError> {
+    T::deserialize(client_state)
+        .map_err(|source| ClientStateDiffError::InvalidClientState { which, source })
+}
+
+/// The cometbls client state has no unbonding period, so it is recovered from the trusting period
+/// of the self client state, which is 85% of the unbonding period of the tracked chain.
+fn cometbls_dangers(
+    on_chain: &cometbls_light_client_types::ClientState,
+    expected: &cometbls_light_client_types::ClientState,
+) -> BTreeSet<Danger> {
+    let mut dangers = BTreeSet::new();
+
+    let unbonding_period = u128::from(expected.trusting_period) * 100 / 85;
+
+    if u128::from(on_chain.trusting_period) >= unbonding_period {
+        dangers.insert(Danger::UnbondingPeriodShorterThanTrustingPeriod);
+    }
+
+    // the contract address is not known to the self client state of chains that are not connected
+    // to the cosmwasm implementation of ibc-union
+    if !expected.contract_address.is_zero()
+        && on_chain.contract_address != expected.contract_address
+    {
+        dangers.insert(Danger::IbcContractAddressChanged);
+    }
+
+    dangers
+}
+
+fn tendermint_dangers(
+    on_chain: &tendermint_light_client_types::ClientState,
+    expected: &tendermint_light_client_types::ClientState,
+) -> BTreeSet<Danger> {
+    let mut dangers = BTreeSet::new();
+
+    // the unbonding period stored in the client is checked as well, since it is what the client
+    // uses to expire
+    if on_chain.trusting_period >= expected.unbonding_period
+        || on_chain.trusting_period >= on_chain.unbonding_period
+    {
+        dangers.insert(Danger::UnbondingPeriodShorterThanTrustingPeriod);
+    }
+
+    dangers
+}
+
+fn ethereum_dangers(
+    on_chain: &ethereum_light_client_types::ClientState,
+    expected: &ethereum_light_client_types::ClientState,
+) -> BTreeSet<Danger> {
+    let mut dangers = BTreeSet::new();
+
+    if on_chain.fork_parameters != expected.fork_parameters {
+        dangers.insert(Danger::ForkScheduleChanged);
+    }
+
+    if on_chain.ibc_contract_address != expected.ibc_contract_address {
+        dangers.insert(Danger::IbcContractAddressChanged);
+    }
+
+    dangers
+}
+
+/// Compare the client state of `client_id` on `chain_id` against the self client state of the
+/// chain it tracks, at the latest height of both chains. See the [module documentation](self).
+pub async fn diff_client_state(
+    client: &impl VoyagerQuery,
+    chain_id: &ChainId,
+    ibc_spec_id: &IbcSpecId,
+    client_id: RawClientId,
+    include_heights: bool,
+) -> Result<ClientStateDiff, ClientStateDiffError> {
+    let client_info = client
+        .client_info_raw(chain_id, ibc_spec_id, client_id.clone())
+        .await?;
+
+    if comparable_fields(&client_info.client_type).is_none() {
+        return Err(ClientStateDiffError::UnsupportedClientType(
+            client_info.client_type,
+        ));
+    }
+
+    let meta = client
+        .client_meta_raw(
+            chain_id,
+            ibc_spec_id,
+            QueryHeight::Latest,
+            client_id.clone(),
+        )
+        .await?;
+
+    let client_state = client
+        .query_ibc_state_raw(
+            chain_id,
+            ibc_spec_id,
+            QueryHeight::Latest,
+            client.client_state_path(ibc_spec_id, client_id.clone())?,
+        )
+        .await?;
+
+    let Some(encoded_client_state) = client_state.state.as_str() else {
+        return Err(ErrorObject::owned(
+            FATAL_JSONRPC_ERROR_CODE,
+            format!(
+                "client {} not found at height {}",
+                client_id.0, client_state.height
+            ),
+            None::<()>,
+        )
+        .into());
+    };
+
+    let on_chain = client
+        .decode_client_state(
+            &client_info.client_type,
+            &client_info.ibc_interface,
+            ibc_spec_id,
+            encoded_client_state.parse().map_err(|err| {
+                ErrorObject::owned(
+                    FATAL_JSONRPC_ERROR_CODE,
+                    format!("invalid client state: {}", ErrorReporter(err)),
+                    None::<()>,
+                )
+            })?,
+        )
+        .await?;
+
+    let expected = client
+        .self_client_state(&meta.chain_id, QueryHeight::Finalized)
+        .await?;
+
+    let expected_height = expected.height;
+    let expected = expected.state;
+
+    let (fields, dangers) = diff_client_states(
+        &client_info.client_type,
+        &on_chain,
+        &expected,
+        include_heights,
+    )?;
+
+    Ok(ClientStateDiff {
+        client_type: client_info.client_type,
+        counterparty_chain_id: meta.chain_id,
+        expected_height,
+        on_chain,
+        expected,
+        fields,
+        dangers,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU64;
+
+    use ibc_classic_spec::IbcClassic;
+    use serde_json::json;
+    use unionlabs::{google::protobuf::duration::Duration, hash::H256, id::ClientId};
+    use voyager_core::{ClientInfo, ClientStateMeta, ClientStatus, IbcInterface};
+
+    use super::*;
+    use crate::testing::MockVoyager;
+
+    const UNBONDING_PERIOD_SECONDS: i64 = 21 * 24 * 60 * 60;
+
+    fn names(diff: &[FieldDiff]) -> Vec<&str> {
+        diff.iter().map(|field| field.field.as_str()).collect()
+    }
+
+    fn diff(
+        client_type: &str,
+        on_chain: &Value,
+        expected: &Value,
+        include_heights: bool,
+    ) -> (Vec<FieldDiff>, BTreeSet<Danger>) {
+        diff_client_states(
+            &ClientType::new(client_type),
+            on_chain,
+            expected,
+            include_heights,
+        )
+        .unwrap()
+    }
+
+    fn cometbls(height: u64) -> cometbls_light_client_types::ClientState {
+        let unbonding_period = u64::try_from(UNBONDING_PERIOD_SECONDS).unwrap() * 1_000_000_000;
+
+        cometbls_light_client_types::ClientState {
+            chain_id: cometbls_light_client_types::ChainId::from_string("union-devnet-1").unwrap(),
+            trusting_period: unbonding_period * 85 / 100,
+            max_clock_drift: 600_000_000_000,
+            frozen_height: Height::new(0),
+            latest_height: Height::new_with_revision(1, height),
+            contract_address: H256::new([0xaa; 32]),
+            zk_verifying_key_hash: None,
+        }
+    }
+
+    fn tendermint(height: u64) -> tendermint_light_client_types::ClientState {
+        tendermint_light_client_types::ClientState {
+            chain_id: "stargaze-1".to_owned(),
+            trust_level: tendermint_light_client_types::Fraction {
+                numerator: 1,
+                denominator: NonZeroU64::new(3).unwrap(),
+            },
+            trusting_period: Duration::new(UNBONDING_PERIOD_SECONDS * 85 / 100, 0).unwrap(),
+            unbonding_period: Duration::new(UNBONDING_PERIOD_SECONDS, 0).unwrap(),
+            max_clock_drift: Duration::new(600, 0).unwrap(),
+            frozen_height: None,
+            latest_height: Height::new_with_revision(1, height),
+            proof_specs: vec![],
+            upgrade_path: vec!["upgrade".to_owned(), "upgradedIBCState".to_owned()],
+        }
+    }
+
+    fn ethereum(height: u64) -> Value {
+        let fork = |version: &str, epoch: &str| json!({ "version": version, "epoch": epoch });
+
+        json!({
+            "chain_id": "11155111",
+            "chain_spec": "mainnet",
+            "genesis_validators_root": format!("0x{}", "d8".repeat(32)),
+            "genesis_time": 1_655_733_600,
+            "fork_parameters": {
+                "genesis_fork_version": "0x90000069",
+                "genesis_slot": "0",
+                "altair": fork("0x90000070", "50"),
+                "bellatrix": fork("0x90000071", "100"),
+                "capella": fork("0x90000072", "56832"),
+                "deneb": fork("0x90000073", "132608"),
+            },
+            "latest_height": height,
+            "frozen_height": "0",
+            "ibc_contract_address": format!("0x{}", "ee".repeat(20)),
+        })
+    }
+
+    fn to_value(client_state: impl Serialize) -> Value {
+        serde_json::to_value(client_state).unwrap()
+    }
+
+    #[test]
+    fn unchanged_client_has_no_diff() {
+        for (client_type, on_chain, expected) in [
+            (
+                ClientType::COMETBLS_GROTH16,
+                to_value(cometbls(10)),
+                to_value(cometbls(20)),
+            ),
+            (
+                ClientType::TENDERMINT,
+                to_value(tendermint(10)),
+                to_value(tendermint(20)),
+            ),
+            (ClientType::ETHEREUM, ethereum(10), ethereum(20)),
+        ] {
+            assert_eq!(
+                diff(client_type, &on_chain, &expected, false),
+                (vec![], BTreeSet::new()),
+                "{client_type}"
+            );
+
+            let (fields, dangers) = diff(client_type, &on_chain, &expected, true);
+            assert_eq!(
+                fields
+                    .iter()
+                    .map(|field| (field.field.as_str(), field.kind))
+                    .collect::<Vec<_>>(),
+                [("latest_height", FieldKind::Height)],
+                "{client_type}"
+            );
+            assert_eq!(dangers, BTreeSet::new(), "{client_type}");
+        }
+    }
+
+    #[test]
+    fn cometbls_unbonding_period_shortened() {
+        // the unbonding period of the tracked chain was halved by governance
+        let mut expected = cometbls(20);
+        expected.trusting_period /= 2;
+
+        let (fields, dangers) = diff(
+            ClientType::COMETBLS_GROTH16,
+            &to_value(cometbls(10)),
+            &to_value(expected),
+            false,
+        );
+
+        assert_eq!(names(&fields), ["trusting_period"]);
+        assert_eq!(
+            dangers,
+            [Danger::UnbondingPeriodShorterThanTrustingPeriod].into()
+        );
+    }
+
+    #[test]
+    fn cometbls_unbonding_period_extended() {
+        let mut expected = cometbls(20);
+        expected.trusting_period *= 2;
+
+        let (fields, dangers) = diff(
+            ClientType::COMETBLS_GROTH16,
+            &to_value(cometbls(10)),
+            &to_value(expected),
+            false,
+        );
+
+        assert_eq!(names(&fields), ["trusting_period"]);
+        assert_eq!(dangers, BTreeSet::new());
+    }
+
+    #[test]
+    fn cometbls_contract_address_changed() {
+        let mut expected = cometbls(20);
+        expected.contract_address = H256::new([0xbb; 32]);
+
+        let (fields, dangers) = diff(
+            ClientType::COMETBLS_GROTH16,
+            &to_value(cometbls(10)),
+            &to_value(expected),
+            false,
+        );
+
+        assert_eq!(names(&fields), ["contract_address"]);
+        assert_eq!(dangers, [Danger::IbcContractAddressChanged].into());
+
+        // not dangerous if the contract address is not known to the tracked chain
+        let mut expected = cometbls(20);
+        expected.contract_address = H256::default();
+
+        let (fields, dangers) = diff(
+            ClientType::COMETBLS_GROTH16,
+            &to_value(cometbls(10)),
+            &to_value(expected),
+            false,
+        );
+
+        assert_eq!(names(&fields), ["contract_address"]);
+        assert_eq!(fields[0].expected, Value::Null);
+        assert_eq!(dangers, BTreeSet::new());
+    }
+
+    #[test]
+    fn tendermint_unbonding_period_shortened() {
+        let mut expected = tendermint(20);
+        expected.trusting_period = Duration::new(UNBONDING_PERIOD_SECONDS / 4, 0).unwrap();
+        expected.unbonding_period = Duration::new(UNBONDING_PERIOD_SECONDS / 2, 0).unwrap();
+
+        let (fields, dangers) = diff(
+            ClientType::TENDERMINT,
+            &to_value(tendermint(10)),
+            &to_value(expected),
+            false,
+        );
+
+        assert_eq!(names(&fields), ["trusting_period", "unbonding_period"]);
+        assert_eq!(
+            dangers,
+            [Danger::UnbondingPeriodShorterThanTrustingPeriod].into()
+        );
+    }
+
+    #[test]
+    fn tendermint_invalid_on_chain_client() {
+        // the client was created with a trusting period longer than its own unbonding period
+        let mut on_chain = tendermint(10);
+        on_chain.trusting_period = Duration::new(UNBONDING_PERIOD_SECONDS, 0).unwrap();
+
+        let (fields, dangers) = diff(
+            ClientType::TENDERMINT,
+            &to_value(on_chain),
+            &to_value(tendermint(20)),
+            false,
+        );
+
+        assert_eq!(names(&fields), ["trusting_period"]);
+        assert_eq!(
+            dangers,
+            [Danger::UnbondingPeriodShorterThanTrustingPeriod].into()
+        );
+    }
+
+    #[test]
+    fn tendermint_harmless_changes() {
+        let mut expected = tendermint(20);
+        expected.max_clock_drift = Duration::new(10, 0).unwrap();
+        expected.upgrade_path = vec![];
+
+        let mut on_chain = tendermint(10);
+        on_chain.frozen_height = Some(Height::new_with_revision(1, 5));
+
+        let (fields, dangers) = diff(
+            ClientType::TENDERMINT,
+            &to_value(&on_chain),
+            &to_value(&expected),
+            false,
+        );
+
+        assert_eq!(names(&fields), ["max_clock_drift", "upgrade_path"]);
+        assert_eq!(dangers, BTreeSet::new());
+
+        let (fields, _) = diff(
+            ClientType::TENDERMINT,
+            &to_value(&on_chain),
+            &to_value(&expected),
+            true,
+        );
+
+        assert_eq!(
+            names(&fields),
+            [
+                "max_clock_drift",
+                "frozen_height",
+                "latest_height",
+                "upgrade_path"
+            ]
+        );
+    }
+
+    #[test]
+    fn ethereum_fork_scheduled() {
+        let mut expected = ethereum(20);
+        expected["fork_parameters"]["deneb"]["epoch"] = json!("132000");
+
+        let (fields, dangers) = diff(ClientType::ETHEREUM, &ethereum(10), &expected, false);
+
+        assert_eq!(names(&fields), ["fork_parameters"]);
+        assert_eq!(dangers, [Danger::ForkScheduleChanged].into());
+    }
+
+    #[test]
+    fn ethereum_contract_address_changed() {
+        let mut expected = ethereum(20);
+        expected["ibc_contract_address"] = json!(format!("0x{}", "ff".repeat(20)));
+        expected["fork_parameters"]["genesis_fork_version"] = json!("0x90000068");
+
+        let (fields, dangers) = diff(ClientType::ETHEREUM, &ethereum(10), &expected, false);
+
+        assert_eq!(names(&fields), ["fork_parameters", "ibc_contract_address"]);
+        assert_eq!(
+            dangers,
+            [
+                Danger::IbcContractAddressChanged,
+                Danger::ForkScheduleChanged
+            ]
+            .into()
+        );
+    }
+
+    #[test]
+    fn ethereum_timestamps_are_excluded() {
+        let mut expected = ethereum(20);
+        expected["genesis_time"] = json!(1_606_824_023);
+
+        let (fields, dangers) = diff(ClientType::ETHEREUM, &ethereum(10), &expected, false);
+
+        assert!(fields.is_empty());
+        assert_eq!(dangers, BTreeSet::new());
+
+        let (fields, _) = diff(ClientType::ETHEREUM, &ethereum(10), &expected, true);
+
+        assert_eq!(names(&fields), ["genesis_time", "latest_height"]);
+        assert_eq!(fields[0].kind, FieldKind::Timestamp);
+    }
+
+    #[test]
+    fn invalid_client_states() {
+        let err = diff_client_states(
+            &ClientType::new(ClientType::ETHEREUM),
+            &ethereum(10),
+            &to_value(cometbls(20)),
+            false,
+        )
+        .unwrap_err();
+
+        assert!(
+            matches!(
+                err,
+                ClientStateDiffError::InvalidClientState {
+                    which: "expected",
+                    ..
+                }
+            ),
+            "{err:?}"
+        );
+
+        let err = diff_client_states(&ClientType::new("movement"), &json!({}), &json!({}), false)
+            .unwrap_err();
+
+        assert!(
+            matches!(err, ClientStateDiffError::UnsupportedClientType(_)),
+            "{err:?}"
+        );
+    }
+
+    const HOST: &str = "union-devnet-1";
+    const TRACKED: &str = "stargaze-1";
+
+    /// A tendermint client on union, tracking a chain whose unbonding period has been shortened.
+    fn chains() -> MockVoyager {
+        let host = ChainId::new(HOST);
+        let tracked = ChainId::new(TRACKED);
+        let client_id = ClientId::new("07-tendermint", 1);
+
+        let mut expected = tendermint(20);
+        expected.unbonding_period = Duration::new(UNBONDING_PERIOD_SECONDS / 2, 0).unwrap();
+
+        let voyager = MockVoyager::new();
+
+        voyager
+            .set_latest_height(&host, Height::new_with_revision(1, 10))
+            .set_latest_height(&tracked, Height::new_with_revision(1, 20))
+            .set_client_info::<IbcClassic>(
+                &host,
+                client_id.clone(),
+                ClientInfo {
+                    client_type: ClientType::new(ClientType::TENDERMINT),
+                    ibc_interface: IbcInterface::new(IbcInterface::IBC_COSMWASM),
+                    metadata: Value::Null,
+                },
+            )
+            .set_client_meta::<IbcClassic>(
+                &host,
+                client_id.clone(),
+                ClientStateMeta {
+                    height: Height::new_with_revision(1, 10),
+                    chain_id: tracked.clone(),
+                    status: ClientStatus::Active,
+                    resolved_at: None,
+                },
+            )
+            .set_client_state::<IbcClassic>(
+                &host,
+                client_id,
+                MockVoyager::encoded(&to_value(tendermint(10))),
+            )
+            .set_self_client_state(&tracked, to_value(expected));
+
+        voyager
+    }
+
+    #[tokio::test]
+    async fn diff_against_tracked_chain() {
+        let diff = diff_client_state(
+            &chains(),
+            &ChainId::new(HOST),
+            &IbcSpecId::new(IbcSpecId::CLASSIC),
+            RawClientId::new(ClientId::new("07-tendermint", 1)),
+            false,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(diff.counterparty_chain_id.as_str(), TRACKED);
+        assert_eq!(diff.expected_height, Height::new_with_revision(1, 20));
+        assert_eq!(names(&diff.fields), ["unbonding_period"]);
+        assert_eq!(
+            diff.dangers,
+            [Danger::UnbondingPeriodShorterThanTrustingPeriod].into()
+        );
+    }
+}
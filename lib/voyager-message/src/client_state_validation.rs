@@ -0,0 +1,375 @@
+//! Validation of client states before a client is created with them.
+//!
+//! A client created with invalid parameters (i.e. a trusting period that is longer than the
+//! unbonding period of the tracked chain) is accepted by the host chain, and is only discovered to
+//! be broken once the first proof fails to verify against it. For the client types that support it
+//! (see [`ClientStateValidator`]), the client state is checked before the `MsgCreateClient` is
+//! built.
+//!
+//! Some of the checks require the actual unbonding period of the tracked chain, which is queried
+//! with [`UnbondingPeriod`]. When building messages offline, these checks are skipped.
+
+use std::time::Duration;
+
+use jsonrpsee::{
+    core::RpcResult,
+    types::{ErrorObject, ErrorObjectOwned},
+};
+use protos::cosmos::staking::v1beta1::{query_client::QueryClient, QueryParamsRequest};
+use serde::Serialize;
+use serde_json::{json, Value};
+use unionlabs::ErrorReporter;
+use voyager_core::ClientType;
+
+use crate::FATAL_JSONRPC_ERROR_CODE;
+
+/// A source of the unbonding period of a chain.
+#[allow(async_fn_in_trait)]
+pub trait UnbondingPeriod {
+    async fn unbonding_period(&self) -> RpcResult<Duration>;
+}
+
+/// Query the unbonding period from the staking params with `cosmos.staking.v1beta1.Query/Params`.
+#[derive(Debug, Clone)]
+pub struct GrpcUnbondingPeriod {
+    pub grpc_url: String,
+}
+
+impl UnbondingPeriod for GrpcUnbondingPeriod {
+    async fn unbonding_period(&self) -> RpcResult<Duration> {
+        let unbonding_time = QueryClient::connect(self.grpc_url.clone())
+            .await
+            .map_err(|err| self.error("error connecting to grpc server", err))?
+            .params(QueryParamsRequest {})
+            .await
+            .map_err(|err| self.error("error querying staking params", err))?
+            .into_inner()
+            .params
+            .and_then(|params| params.unbonding_time)
+            .ok_or_else(|| {
+                ErrorObject::owned(
+                    -1,
+                    "staking params do not contain an unbonding time",
+                    Some(json!({ "grpc_url": self.grpc_url })),
+                )
+            })?;
+
+        match (
+            u64::try_from(unbonding_time.seconds),
+            u32::try_from(unbonding_time.nanos),
+        ) {
+            (Ok(seconds), Ok(nanos)) => Ok(Duration::new(seconds, nanos)),
+            _ => Err(ErrorObject::owned(
+                FATAL_JSONRPC_ERROR_CODE,
+                format!(
+                    "negative unbonding time: {}s {}ns",
+                    unbonding_time.seconds, unbonding_time.nanos
+                ),
+                Some(json!({ "grpc_url": self.grpc_url })),
+            )),
+        }
+    }
+}
+
+impl GrpcUnbondingPeriod {
+    fn error(&self, message: &str, err: impl std::error::Error) -> ErrorObjectOwned {
+        ErrorObject::owned(
+            -1,
+            format!("{message}: {}", ErrorReporter(err)),
+            Some(json!({ "grpc_url": self.grpc_url })),
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error, Serialize)]
+#[serde(untagged)]
+pub enum ClientStateViolation {
+    #[error(transparent)]
+    Tendermint(tendermint_light_client_types::validation::ClientStateViolation),
+    #[error(transparent)]
+    Cometbls(cometbls_light_client_types::validation::ClientStateViolation),
+}
+
+impl ClientStateViolation {
+    /// Whether a client can still be created with this violation.
+    #[must_use]
+    pub fn is_warning(&self) -> bool {
+        match self {
+            ClientStateViolation::Tendermint(violation) => violation.is_warning(),
+            ClientStateViolation::Cometbls(_) => false,
+        }
+    }
+}
+
+/// The client types that client states can be validated for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClientStateValidator {
+    Tendermint,
+    Cometbls,
+}
+
+impl ClientStateValidator {
+    /// The validator for `client_type`, if there is one.
+    #[must_use]
+    pub fn for_client_type(client_type: &ClientType) -> Option<Self> {
+        match client_type.as_str() {
+            ClientType::TENDERMINT => Some(Self::Tendermint),
+            ClientType::COMETBLS_GROTH16 => Some(Self::Cometbls),
+            _ => None,
+        }
+    }
+
+    /// Validate `client_state`, provided as JSON.
+    ///
+    /// `unbonding_period` is the unbonding period of the chain the client tracks. If it is `None`,
+    /// the checks that require it are skipped.
+    pub fn validate(
+        self,
+        client_state: Value,
+        unbonding_period: Option<Duration>,
+    ) -> RpcResult<ClientStateValidation> {
+        let violations = match self {
+            ClientStateValidator::Tendermint => {
+                let client_state =
+                    deserialize::<tendermint_light_client_types::ClientState>(client_state)?;
+
+                let unbonding_period = unbonding_period
+                    .map(|unbonding_period| {
+                        i64::try_from(unbonding_period.as_secs())
+                            .ok()
+                            .and_then(|seconds| {
+                                unionlabs::google::protobuf::duration::Duration::new(
+                                    seconds,
+                                    unbonding_period
+                                        .subsec_nanos()
+                                        .try_into()
+                                        .expect("nanos are < 1_000_000_000; qed;"),
+                                )
+                                .ok()
+                            })
+                            .ok_or_else(|| {
+                                ErrorObject::owned(
+                                    FATAL_JSONRPC_ERROR_CODE,
+                                    format!(
+                                        "unbonding period {unbonding_period:?} is out of range"
+                                    ),
+                                    None::<()>,
+                                )
+                            })
+                    })
+                    .transpose()?;
+
+                client_state
+                    .validate(unbonding_period)
+                    .into_iter()
+                    .map(ClientStateViolation::Tendermint)
+                    .collect()
+            }
+            ClientStateValidator::Cometbls => {
+                let client_state =
+                    deserialize::<cometbls_light_client_types::ClientState>(client_state)?;
+
+                let unbonding_period = unbonding_period
+                    .map(|unbonding_period| {
+                        u64::try_from(unbonding_period.as_nanos()).map_err(|_| {
+                            ErrorObject::owned(
+                                FATAL_JSONRPC_ERROR_CODE,
+                                format!("unbonding period {unbonding_period:?} is out of range"),
+                                None::<()>,
+                            )
+                        })
+                    })
+                    .transpose()?;
+
+                client_state
+                    .validate(unbonding_period)
+                    .into_iter()
+                    .map(ClientStateViolation::Cometbls)
+                    .collect()
+            }
+        };
+
+        Ok(ClientStateValidation {
+            unbonding_period_checked: unbonding_period.is_some(),
+            violations,
+        })
+    }
+}
+
+fn deserialize<T: serde::de::DeserializeOwned>(client_state: Value) -> RpcResult<T> {
+    serde_json::from_value(client_state).map_err(|err| {
+        ErrorObject::owned(
+            FATAL_JSONRPC_ERROR_CODE,
+            format!("unable to deserialize client state: {}", ErrorReporter(err)),
+            None::<()>,
+        )
+    })
+}
+
+/// The result of validating a client state.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ClientStateValidation {
+    /// Whether the checks that require the unbonding period of the tracked chain were run.
+    pub unbonding_period_checked: bool,
+    pub violations: Vec<ClientStateViolation>,
+}
+
+impl ClientStateValidation {
+    pub fn errors(&self) -> impl Iterator<Item = &ClientStateViolation> {
+        self.violations.iter().filter(|v| !v.is_warning())
+    }
+
+    pub fn warnings(&self) -> impl Iterator<Item = &ClientStateViolation> {
+        self.violations.iter().filter(|v| v.is_warning())
+    }
+
+    /// Whether a client can be created with this client state.
+    #[must_use]
+    pub fn is_valid(&self) -> bool {
+        self.errors().next().is_none()
+    }
+
+    /// Returns an error listing all of the violations if the client state is not valid.
+    pub fn ensure_valid(&self) -> RpcResult<()> {
+        if self.is_valid() {
+            return Ok(());
+        }
+
+        Err(ErrorObject::owned(
+            FATAL_JSONRPC_ERROR_CODE,
+            format!(
+                "invalid client state: {}",
+                self.errors()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join("; ")
+            ),
+            Some(json!({ "violations": self.violations })),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UNBONDING_PERIOD: Duration = Duration::from_secs(1_728_000);
+
+    fn tendermint_client_state(trusting_period: &str, trust_level: u64) -> Value {
+        json!({
+            "chain_id": "union-testnet-9",
+            "trust_level": {
+                "numerator": trust_level,
+                "denominator": 3,
+            },
+            "trusting_period": trusting_period,
+            "unbonding_period": "1728000s",
+            "max_clock_drift": "600s",
+            "frozen_height": null,
+            "latest_height": "9-100",
+            "proof_specs": tendermint_light_client_types::validation::CANONICAL_PROOF_SPECS,
+            "upgrade_path": ["upgrade", "upgradedIBCState"],
+        })
+    }
+
+    fn cometbls_client_state(trusting_period: u64) -> Value {
+        json!({
+            "chain_id": "union-testnet-9",
+            "trusting_period": trusting_period,
+            "max_clock_drift": 600_000_000_000_u64,
+            "frozen_height": "0",
+            "latest_height": "9-100",
+        })
+    }
+
+    #[test]
+    fn validator_for_client_type() {
+        assert_eq!(
+            ClientStateValidator::for_client_type(&ClientType::new(ClientType::TENDERMINT)),
+            Some(ClientStateValidator::Tendermint)
+        );
+        assert_eq!(
+            ClientStateValidator::for_client_type(&ClientType::new(ClientType::COMETBLS_GROTH16)),
+            Some(ClientStateValidator::Cometbls)
+        );
+        assert_eq!(
+            ClientStateValidator::for_client_type(&ClientType::new(ClientType::ETHEREUM)),
+            None
+        );
+    }
+
+    #[test]
+    fn tendermint() {
+        let validation = ClientStateValidator::Tendermint
+            .validate(
+                tendermint_client_state("1468800s", 1),
+                Some(UNBONDING_PERIOD),
+            )
+            .unwrap();
+
+        assert!(validation.unbonding_period_checked);
+        assert_eq!(validation.violations, []);
+        assert!(validation.ensure_valid().is_ok());
+
+        // valid, but uncommon
+        let validation = ClientStateValidator::Tendermint
+            .validate(tendermint_client_state("1468800s", 3), None)
+            .unwrap();
+
+        assert!(!validation.unbonding_period_checked);
+        assert_eq!(validation.warnings().count(), 1);
+        assert!(validation.ensure_valid().is_ok());
+
+        let validation = ClientStateValidator::Tendermint
+            .validate(tendermint_client_state("1728000s", 1), None)
+            .unwrap();
+
+        assert_eq!(validation.errors().count(), 1);
+
+        let err = validation.ensure_valid().unwrap_err();
+
+        assert_eq!(err.code(), FATAL_JSONRPC_ERROR_CODE);
+        assert!(err.message().contains("trusting period"));
+    }
+
+    #[test]
+    fn cometbls() {
+        let trusting_period = u64::try_from(UNBONDING_PERIOD.as_nanos()).unwrap();
+
+        let validation = ClientStateValidator::Cometbls
+            .validate(cometbls_client_state(trusting_period), None)
+            .unwrap();
+
+        // the trusting period can only be checked against the unbonding period of the chain
+        assert_eq!(validation.violations, []);
+
+        let validation = ClientStateValidator::Cometbls
+            .validate(
+                cometbls_client_state(trusting_period),
+                Some(UNBONDING_PERIOD),
+            )
+            .unwrap();
+
+        assert_eq!(
+            validation.violations,
+            [ClientStateViolation::Cometbls(
+                cometbls_light_client_types::validation::ClientStateViolation::TrustingPeriodTooLong {
+                    trusting_period,
+                    unbonding_period: trusting_period,
+                }
+            )]
+        );
+        assert!(!validation.is_valid());
+    }
+
+    #[test]
+    fn invalid_json() {
+        assert_eq!(
+            ClientStateValidator::Tendermint
+                .validate(json!({}), None)
+                .unwrap_err()
+                .code(),
+            FATAL_JSONRPC_ERROR_CODE
+        );
+    }
+}
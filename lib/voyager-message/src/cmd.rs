@@ -0,0 +1,224 @@
+//! Output of the plugin `cmd` subcommands.
+//!
+//! Plugin subcommands return their result as a [`CmdOutput`] instead of printing it, and the
+//! shared CLI layer (`Plugin::run`) prints it in the format selected with `--output`. In json
+//! mode, the result is printed as a single json value on stdout, and errors are printed as a json
+//! object on stderr, such that the output can be parsed by scripts.
+
+use std::error::Error;
+
+use chain_utils::{relay_progress::RelayProgressReport, spend::SpendReport};
+use serde::Serialize;
+use serde_json::{json, Value};
+use unionlabs::{ibc::core::client::height::Height, ErrorReporter};
+use voyager_core::ChainId;
+
+use crate::into_value;
+
+/// The exit code of a plugin subcommand that failed.
+pub const CMD_ERROR_EXIT_CODE: u8 = 1;
+
+/// The format plugin subcommands print their output in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// Human readable output, which may differ between plugins and versions.
+    #[default]
+    Text,
+    /// A single json value.
+    Json,
+}
+
+impl OutputFormat {
+    pub fn format(self, output: &dyn CmdOutput) -> String {
+        match self {
+            OutputFormat::Text => output.to_text(),
+            OutputFormat::Json => output.to_json().to_string(),
+        }
+    }
+
+    pub fn format_error(self, err: &(dyn Error + 'static)) -> String {
+        match self {
+            OutputFormat::Text => ErrorReporter(err).to_string(),
+            OutputFormat::Json => error_json(err).to_string(),
+        }
+    }
+
+    /// Print `output` to stdout.
+    pub fn print(self, output: &dyn CmdOutput) {
+        println!("{}", self.format(output));
+    }
+
+    /// Print `err` to stderr, and exit with [`CMD_ERROR_EXIT_CODE`].
+    pub fn exit_with_error(self, err: &(dyn Error + 'static)) -> ! {
+        eprintln!("{}", self.format_error(err));
+        std::process::exit(CMD_ERROR_EXIT_CODE.into());
+    }
+}
+
+/// The result of a plugin subcommand.
+pub trait CmdOutput {
+    fn to_json(&self) -> Value;
+
+    fn to_text(&self) -> String;
+}
+
+/// The json representation of an error: the message of the error itself, and the messages of each
+/// of its sources, outermost first.
+#[must_use]
+pub fn error_json(err: &(dyn Error + 'static)) -> Value {
+    let mut sources = vec![];
+    let mut source = err.source();
+
+    while let Some(err) = source {
+        sources.push(err.to_string());
+        source = err.source();
+    }
+
+    json!({
+        "error": err.to_string(),
+        "sources": sources,
+    })
+}
+
+/// The chain id of the chain a plugin is configured for.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ChainIdOutput {
+    pub chain_id: ChainId,
+}
+
+impl CmdOutput for ChainIdOutput {
+    fn to_json(&self) -> Value {
+        into_value(self)
+    }
+
+    fn to_text(&self) -> String {
+        self.chain_id.to_string()
+    }
+}
+
+/// The latest height of the chain a plugin is configured for.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LatestHeightOutput {
+    pub latest_height: Height,
+}
+
+impl CmdOutput for LatestHeightOutput {
+    fn to_json(&self) -> Value {
+        into_value(self)
+    }
+
+    fn to_text(&self) -> String {
+        self.latest_height.to_string()
+    }
+}
+
+impl CmdOutput for SpendReport {
+    fn to_json(&self) -> Value {
+        into_value(self)
+    }
+
+    fn to_text(&self) -> String {
+        serde_json::to_string_pretty(self).expect("serialization is infallible; qed;")
+    }
+}
+
+impl CmdOutput for RelayProgressReport {
+    fn to_json(&self) -> Value {
+        into_value(self)
+    }
+
+    fn to_text(&self) -> String {
+        serde_json::to_string_pretty(self).expect("serialization is infallible; qed;")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chain_utils::spend::SpendEntry;
+
+    use super::*;
+
+    #[derive(Debug, thiserror::Error)]
+    #[error("outer")]
+    struct Outer(#[source] Inner);
+
+    #[derive(Debug, thiserror::Error)]
+    #[error("inner")]
+    struct Inner;
+
+    #[test]
+    fn error_output() {
+        assert_eq!(
+            OutputFormat::Json.format_error(&Outer(Inner)),
+            r#"{"error":"outer","sources":["inner"]}"#
+        );
+        assert_eq!(
+            OutputFormat::Json.format_error(&Inner),
+            r#"{"error":"inner","sources":[]}"#
+        );
+        assert_eq!(
+            OutputFormat::Text.format_error(&Outer(Inner)),
+            "outer: inner"
+        );
+    }
+
+    #[test]
+    fn chain_id_output() {
+        let output = ChainIdOutput {
+            chain_id: ChainId::new("union-testnet-9"),
+        };
+
+        assert_eq!(
+            output.to_json(),
+            json!({
+                "chain_id": "union-testnet-9",
+            })
+        );
+        assert_eq!(OutputFormat::Text.format(&output), "union-testnet-9");
+    }
+
+    #[test]
+    fn latest_height_output() {
+        let output = LatestHeightOutput {
+            latest_height: Height::new_with_revision(9, 1234),
+        };
+
+        assert_eq!(
+            output.to_json(),
+            json!({
+                "latest_height": "9-1234",
+            })
+        );
+        assert_eq!(OutputFormat::Text.format(&output), "9-1234");
+    }
+
+    #[test]
+    fn spend_report_output() {
+        let report = SpendReport {
+            chain_id: "union-testnet-9".to_owned(),
+            day: 20_000,
+            spent: 1_500,
+            daily_budget: Some(10_000),
+            history: vec![SpendEntry {
+                chain_id: "union-testnet-9".to_owned(),
+                day: 20_000,
+                spent: 1_500,
+            }],
+        };
+
+        assert_eq!(
+            report.to_json(),
+            json!({
+                "chain_id": "union-testnet-9",
+                "day": 20_000,
+                "spent": "1500",
+                "daily_budget": "10000",
+                "history": [{
+                    "chain_id": "union-testnet-9",
+                    "day": 20_000,
+                    "spent": "1500",
+                }],
+            })
+        );
+    }
+}
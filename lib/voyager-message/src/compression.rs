@@ -0,0 +1,458 @@
+//! Optional compression of large payloads in queue messages.
+//!
+//! Some payloads (encoded headers with entire sync committees, state proofs of
+//! large tries) easily reach several megabytes once hex encoded, all of which
+//! has to be stored in the queue and sent back and forth between voyager and
+//! the plugins. Fields using one of the codecs in this module are compressed
+//! once they exceed the configured threshold, and are then serialized as:
+//!
+//! ```json
+//! { "enc": "zstd+base64", "data": "..." }
+//! ```
+//!
+//! Deserialization always accepts both the compressed and the plain form,
+//! regardless of the local configuration.
+//!
+//! Compression is configured with the [`COMPRESSION_ENV_VAR`] environment
+//! variable, in the form `<algorithm>[:<threshold>]`, where the algorithm is
+//! one of `zstd`, `gzip` or `none` and the threshold is the uncompressed size
+//! of the payload in bytes (defaults to [`DEFAULT_COMPRESSION_THRESHOLD`]). If
+//! unset, payloads are never compressed.
+
+use std::{
+    io::{self, Read, Write},
+    str::FromStr,
+    sync::OnceLock,
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{json, Value};
+use tracing::warn;
+use unionlabs::{bytes::Bytes, ErrorReporter};
+
+/// The environment variable that compression is configured with.
+pub const COMPRESSION_ENV_VAR: &str = "VOYAGER_PAYLOAD_COMPRESSION";
+
+pub const DEFAULT_COMPRESSION_THRESHOLD: usize = 64 * 1024;
+
+const ZSTD_LEVEL: i32 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompressionAlgorithm {
+    Zstd,
+    Gzip,
+}
+
+impl CompressionAlgorithm {
+    /// The value of the `enc` field for payloads compressed with this
+    /// algorithm.
+    #[must_use]
+    pub const fn encoding(&self) -> &'static str {
+        match self {
+            Self::Zstd => "zstd+base64",
+            Self::Gzip => "gzip+base64",
+        }
+    }
+
+    fn from_encoding(encoding: &str) -> Option<Self> {
+        [Self::Zstd, Self::Gzip]
+            .into_iter()
+            .find(|algorithm| algorithm.encoding() == encoding)
+    }
+
+    fn compress(&self, payload: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            Self::Zstd => zstd::encode_all(payload, ZSTD_LEVEL),
+            Self::Gzip => {
+                let mut encoder =
+                    flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(payload)?;
+                encoder.finish()
+            }
+        }
+    }
+
+    fn decompress(&self, compressed: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            Self::Zstd => zstd::decode_all(compressed),
+            Self::Gzip => {
+                let mut payload = vec![];
+                flate2::read::GzDecoder::new(compressed).read_to_end(&mut payload)?;
+                Ok(payload)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompressionConfig {
+    pub algorithm: CompressionAlgorithm,
+    /// Payloads larger than this many bytes (before compression) are
+    /// compressed.
+    pub threshold: usize,
+}
+
+impl CompressionConfig {
+    /// The compression config read from [`COMPRESSION_ENV_VAR`].
+    ///
+    /// The environment variable is only read once, on first use. An invalid
+    /// value is logged and disables compression.
+    pub fn from_env() -> Option<Self> {
+        static CONFIG: OnceLock<Option<CompressionConfig>> = OnceLock::new();
+
+        *CONFIG.get_or_init(|| {
+            let value = std::env::var(COMPRESSION_ENV_VAR).ok()?;
+
+            value
+                .parse::<ParsedCompressionConfig>()
+                .inspect_err(|err| {
+                    warn!(
+                        %value,
+                        err = %ErrorReporter(err),
+                        "invalid {COMPRESSION_ENV_VAR}, payloads will not be compressed"
+                    );
+                })
+                .ok()?
+                .0
+        })
+    }
+}
+
+/// A parsed [`COMPRESSION_ENV_VAR`] value, where `None` disables compression.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParsedCompressionConfig(pub Option<CompressionConfig>);
+
+impl FromStr for ParsedCompressionConfig {
+    type Err = ParseCompressionConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (algorithm, threshold) = match s.split_once(':') {
+            Some((algorithm, threshold)) => (
+                algorithm,
+                threshold
+                    .parse()
+                    .map_err(ParseCompressionConfigError::Threshold)?,
+            ),
+            None => (s, DEFAULT_COMPRESSION_THRESHOLD),
+        };
+
+        let algorithm = match algorithm {
+            "zstd" => CompressionAlgorithm::Zstd,
+            "gzip" => CompressionAlgorithm::Gzip,
+            "none" | "" => return Ok(Self(None)),
+            _ => {
+                return Err(ParseCompressionConfigError::UnknownAlgorithm(
+                    algorithm.to_owned(),
+                ))
+            }
+        };
+
+        Ok(Self(Some(CompressionConfig {
+            algorithm,
+            threshold,
+        })))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ParseCompressionConfigError {
+    #[error("unknown compression algorithm `{0}`, expected one of `zstd`, `gzip` or `none`")]
+    UnknownAlgorithm(String),
+    #[error("invalid compression threshold")]
+    Threshold(#[source] std::num::ParseIntError),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Compressed {
+    enc: String,
+    data: String,
+}
+
+impl Compressed {
+    fn new(config: CompressionConfig, payload: &[u8]) -> Option<Self> {
+        if payload.len() <= config.threshold {
+            return None;
+        }
+
+        match config.algorithm.compress(payload) {
+            Ok(compressed) => Some(Self {
+                enc: config.algorithm.encoding().to_owned(),
+                data: STANDARD.encode(compressed),
+            }),
+            Err(err) => {
+                // compression is best effort, the plain payload is always valid
+                warn!(err = %ErrorReporter(err), "unable to compress payload");
+                None
+            }
+        }
+    }
+
+    fn decompress<E: de::Error>(&self) -> Result<Vec<u8>, E> {
+        let algorithm = CompressionAlgorithm::from_encoding(&self.enc)
+            .ok_or_else(|| E::custom(format!("unknown payload encoding `{}`", self.enc)))?;
+
+        let compressed = STANDARD
+            .decode(&*self.data)
+            .map_err(|err| E::custom(format!("invalid {} payload: {err}", self.enc)))?;
+
+        algorithm
+            .decompress(&compressed)
+            .map_err(|err| E::custom(format!("invalid {} payload: {err}", self.enc)))
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum MaybeCompressed<T> {
+    Compressed(Compressed),
+    Plain(T),
+}
+
+fn serialize_bytes<S: Serializer>(
+    config: Option<CompressionConfig>,
+    bytes: &Bytes,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match config
+        .filter(|_| serializer.is_human_readable())
+        .and_then(|config| Compressed::new(config, bytes))
+    {
+        Some(compressed) => compressed.serialize(serializer),
+        None => bytes.serialize(serializer),
+    }
+}
+
+fn deserialize_bytes<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Bytes, D::Error> {
+    if !deserializer.is_human_readable() {
+        return Bytes::deserialize(deserializer);
+    }
+
+    match MaybeCompressed::<Bytes>::deserialize(deserializer)? {
+        MaybeCompressed::Compressed(compressed) => compressed.decompress().map(Bytes::new),
+        MaybeCompressed::Plain(bytes) => Ok(bytes),
+    }
+}
+
+fn serialize_value<S: Serializer>(
+    config: Option<CompressionConfig>,
+    value: &Value,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let compressed = config
+        .filter(|_| serializer.is_human_readable())
+        .and_then(|config| {
+            Compressed::new(
+                config,
+                &serde_json::to_vec(value).expect("serialization of a value is infallible; qed;"),
+            )
+        });
+
+    match compressed {
+        Some(compressed) => compressed.serialize(serializer),
+        None => value.serialize(serializer),
+    }
+}
+
+fn deserialize_value<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Value, D::Error> {
+    if !deserializer.is_human_readable() {
+        return Value::deserialize(deserializer);
+    }
+
+    match MaybeCompressed::<Value>::deserialize(deserializer)? {
+        MaybeCompressed::Compressed(compressed)
+            if CompressionAlgorithm::from_encoding(&compressed.enc).is_some() =>
+        {
+            serde_json::from_slice(&compressed.decompress::<D::Error>()?).map_err(|err| {
+                de::Error::custom(format!("invalid {} payload: {err}", compressed.enc))
+            })
+        }
+        // a plain value that happens to have the same shape as a compressed payload
+        MaybeCompressed::Compressed(Compressed { enc, data }) => {
+            Ok(json!({ "enc": enc, "data": data }))
+        }
+        MaybeCompressed::Plain(value) => Ok(value),
+    }
+}
+
+/// `#[serde(with)]` codec for [`Bytes`] fields.
+pub mod compressed_bytes {
+    use serde::{Deserializer, Serializer};
+    use unionlabs::bytes::Bytes;
+
+    use super::CompressionConfig;
+
+    pub fn serialize<S: Serializer>(bytes: &Bytes, serializer: S) -> Result<S::Ok, S::Error> {
+        super::serialize_bytes(CompressionConfig::from_env(), bytes, serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Bytes, D::Error> {
+        super::deserialize_bytes(deserializer)
+    }
+}
+
+/// `#[serde(with)]` codec for [`Value`] fields. The payload that is
+/// compressed is the JSON serialization of the value.
+///
+/// Note that a plain value that is itself an object of the compressed form
+/// (containing only the `enc` and `data` fields, where `enc` is a known
+/// encoding) will be decompressed when deserialized.
+pub mod compressed_value {
+    use serde::{Deserializer, Serializer};
+    use serde_json::Value;
+
+    use super::CompressionConfig;
+
+    pub fn serialize<S: Serializer>(value: &Value, serializer: S) -> Result<S::Ok, S::Error> {
+        super::serialize_value(CompressionConfig::from_env(), value, serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Value, D::Error> {
+        super::deserialize_value(deserializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    const ETHEREUM_UPDATE: &str =
+        include_str!("../../ethereum-sync-protocol/src/test/updates/3579969.json");
+
+    fn config(algorithm: CompressionAlgorithm) -> CompressionConfig {
+        CompressionConfig {
+            algorithm,
+            threshold: 1024,
+        }
+    }
+
+    fn to_json_bytes(config: Option<CompressionConfig>, bytes: &Bytes) -> Value {
+        serialize_bytes(config, bytes, serde_json::value::Serializer).unwrap()
+    }
+
+    fn from_json_bytes(json: Value) -> Result<Bytes, serde_json::Error> {
+        deserialize_bytes(json)
+    }
+
+    #[test]
+    fn parse_config() {
+        assert_eq!(
+            "zstd".parse::<ParsedCompressionConfig>(),
+            Ok(ParsedCompressionConfig(Some(CompressionConfig {
+                algorithm: CompressionAlgorithm::Zstd,
+                threshold: DEFAULT_COMPRESSION_THRESHOLD
+            })))
+        );
+        assert_eq!(
+            "gzip:100".parse::<ParsedCompressionConfig>(),
+            Ok(ParsedCompressionConfig(Some(CompressionConfig {
+                algorithm: CompressionAlgorithm::Gzip,
+                threshold: 100
+            })))
+        );
+        assert_eq!(
+            "none".parse::<ParsedCompressionConfig>(),
+            Ok(ParsedCompressionConfig(None))
+        );
+        assert!("lz4".parse::<ParsedCompressionConfig>().is_err());
+        assert!("zstd:big".parse::<ParsedCompressionConfig>().is_err());
+    }
+
+    #[test]
+    fn small_payloads_are_plain() {
+        let bytes = Bytes::from(vec![1, 2, 3]);
+
+        let json = to_json_bytes(Some(config(CompressionAlgorithm::Zstd)), &bytes);
+
+        assert_eq!(json, json!("0x010203"));
+        assert_eq!(from_json_bytes(json).unwrap(), bytes);
+    }
+
+    #[test]
+    fn round_trip_plain() {
+        let bytes = Bytes::from(ETHEREUM_UPDATE.as_bytes().to_vec());
+
+        let json = to_json_bytes(None, &bytes);
+
+        assert!(json.is_string());
+        assert_eq!(from_json_bytes(json).unwrap(), bytes);
+    }
+
+    #[test]
+    fn round_trip_compressed() {
+        for algorithm in [CompressionAlgorithm::Zstd, CompressionAlgorithm::Gzip] {
+            let bytes = Bytes::from(ETHEREUM_UPDATE.as_bytes().to_vec());
+
+            let json = to_json_bytes(Some(config(algorithm)), &bytes);
+
+            assert_eq!(json["enc"], algorithm.encoding());
+            assert_eq!(from_json_bytes(json).unwrap(), bytes);
+        }
+    }
+
+    #[test]
+    fn round_trip_value() {
+        let value = serde_json::from_str::<Value>(ETHEREUM_UPDATE).unwrap();
+
+        for config in [None, Some(config(CompressionAlgorithm::Zstd))] {
+            let json = serialize_value(config, &value, serde_json::value::Serializer).unwrap();
+
+            assert_eq!(json.get("enc").is_some(), config.is_some());
+            assert_eq!(deserialize_value(json).unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn plain_value_with_compressed_shape() {
+        let value = json!({ "enc": "not an encoding", "data": "some data" });
+
+        let json = serialize_value(None, &value, serde_json::value::Serializer).unwrap();
+
+        assert_eq!(deserialize_value(json).unwrap(), value);
+    }
+
+    #[test]
+    fn corrupt_payload() {
+        let bytes = Bytes::from(ETHEREUM_UPDATE.as_bytes().to_vec());
+
+        let mut json = to_json_bytes(Some(config(CompressionAlgorithm::Zstd)), &bytes);
+
+        // valid base64, but not a valid zstd frame
+        json["data"] = json!(STANDARD.encode(b"not zstd"));
+        assert!(from_json_bytes(json.clone())
+            .unwrap_err()
+            .to_string()
+            .contains("invalid zstd+base64 payload"));
+
+        json["data"] = json!("not base64!");
+        assert!(from_json_bytes(json.clone()).is_err());
+
+        json["enc"] = json!("lz4+base64");
+        assert!(from_json_bytes(json)
+            .unwrap_err()
+            .to_string()
+            .contains("unknown payload encoding"));
+    }
+
+    #[test]
+    fn compression_reduces_size() {
+        let bytes = Bytes::from(ETHEREUM_UPDATE.as_bytes().to_vec());
+
+        let plain = serde_json::to_string(&to_json_bytes(None, &bytes)).unwrap();
+        let compressed = serde_json::to_string(&to_json_bytes(
+            Some(config(CompressionAlgorithm::Zstd)),
+            &bytes,
+        ))
+        .unwrap();
+
+        assert!(
+            compressed.len() * 2 < plain.len(),
+            "compressed: {}, plain: {}",
+            compressed.len(),
+            plain.len()
+        );
+    }
+}
@@ -0,0 +1,490 @@
+//! Listing the heights of the consensus states stored for a client.
+//!
+//! ibc-go exposes the heights of the consensus states of a client directly
+//! (`ConsensusStateHeights`), paginated over the keys of the client store.
+//! These keys are ordered lexicographically (`1-10` sorts before `1-9`), so all
+//! of the pages are fetched and the heights sorted before the requested page is
+//! taken; see [`collect_pages`].
+//!
+//! ibc-union stores consensus states in a mapping keyed by height, which can
+//! only be read key by key. Heights are found by walking down from the latest
+//! height of the client in windows of at most [`MAX_QUERY_RANGE`] heights, via
+//! [`StateModule::query_range`]; see [`walk_heights`]. Only the
+//! [`MAX_SCANNED_HEIGHTS`] heights below the latest height are scanned, so the
+//! total count is not reported for such clients.
+//!
+//! [`StateModule::query_range`]: crate::module::StateModuleClient::query_range
+
+use jsonrpsee::{
+    core::RpcResult,
+    types::{error::INVALID_PARAMS_CODE, ErrorObject, ErrorObjectOwned},
+};
+use macros::model;
+use serde_json::Value;
+use unionlabs::{ibc::core::client::height::Height, ErrorReporter};
+
+/// The maximum [`Pagination::limit`].
+pub const MAX_PAGE_LIMIT: u64 = 1000;
+
+/// The maximum number of keys that can be read with a single [`QueryRange`].
+pub const MAX_QUERY_RANGE: u64 = 256;
+
+/// The maximum number of heights below the latest height of a client that are
+/// scanned by [`walk_heights`].
+pub const MAX_SCANNED_HEIGHTS: u64 = 64 * MAX_QUERY_RANGE;
+
+/// The maximum number of pages fetched by [`collect_pages`].
+pub const MAX_PAGES: usize = 100;
+
+/// The page of heights to return, counted from the highest height.
+#[model]
+#[derive(Copy)]
+pub struct Pagination {
+    #[serde(default)]
+    pub offset: u64,
+    #[serde(default = "Pagination::default_limit")]
+    pub limit: u64,
+}
+
+impl Pagination {
+    const fn default_limit() -> u64 {
+        100
+    }
+
+    pub fn validate(&self) -> Result<(), ConsensusHeightsError> {
+        match self.limit {
+            0 => Err(ConsensusHeightsError::ZeroLimit),
+            limit if limit > MAX_PAGE_LIMIT => Err(ConsensusHeightsError::LimitTooLarge { limit }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Sort `heights` descending, and take the page out of them.
+    #[must_use]
+    pub fn apply(&self, mut heights: Vec<Height>) -> Vec<Height> {
+        heights.sort_unstable_by(|a, b| b.cmp(a));
+        heights.dedup();
+
+        heights
+            .into_iter()
+            .skip(self.offset.try_into().unwrap_or(usize::MAX))
+            .take(self.limit.try_into().unwrap_or(usize::MAX))
+            .collect()
+    }
+}
+
+impl Default for Pagination {
+    fn default() -> Self {
+        Self {
+            offset: 0,
+            limit: Self::default_limit(),
+        }
+    }
+}
+
+/// A page of the heights of the consensus states of a client, sorted
+/// descending.
+#[model]
+pub struct ConsensusStateHeights {
+    pub heights: Vec<Height>,
+    /// The total number of consensus states of the client, if it is cheaply
+    /// available.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total: Option<u64>,
+}
+
+/// The keys `start..end` of a mapping in the store.
+#[model]
+#[derive(Copy)]
+pub struct QueryRange {
+    pub start: u64,
+    pub end: u64,
+}
+
+impl QueryRange {
+    pub fn validate(&self) -> Result<(), ConsensusHeightsError> {
+        if self.start > self.end {
+            Err(ConsensusHeightsError::InvalidRange {
+                start: self.start,
+                end: self.end,
+            })
+        } else if self.end - self.start > MAX_QUERY_RANGE {
+            Err(ConsensusHeightsError::RangeTooLarge {
+                start: self.start,
+                end: self.end,
+            })
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = u64> {
+        self.start..self.end
+    }
+}
+
+/// A value found by [`StateModule::query_range`].
+///
+/// [`StateModule::query_range`]: crate::module::StateModuleClient::query_range
+#[model]
+pub struct RangeEntry {
+    pub key: u64,
+    pub state: Value,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConsensusHeightsError {
+    #[error("limit must be greater than 0")]
+    ZeroLimit,
+    #[error("limit {limit} is larger than the maximum of {MAX_PAGE_LIMIT}")]
+    LimitTooLarge { limit: u64 },
+    #[error("invalid range {start}..{end}")]
+    InvalidRange { start: u64, end: u64 },
+    #[error("range {start}..{end} is larger than the maximum of {MAX_QUERY_RANGE} keys")]
+    RangeTooLarge { start: u64, end: u64 },
+    #[error("more than {MAX_PAGES} pages of consensus state heights")]
+    TooManyPages,
+    #[error(transparent)]
+    Rpc(#[from] ErrorObjectOwned),
+}
+
+impl From<ConsensusHeightsError> for ErrorObjectOwned {
+    fn from(value: ConsensusHeightsError) -> Self {
+        match value {
+            ConsensusHeightsError::Rpc(err) => err,
+            ConsensusHeightsError::TooManyPages => {
+                ErrorObject::owned(-1, ErrorReporter(value).to_string(), None::<()>)
+            }
+            err => ErrorObject::owned(
+                INVALID_PARAMS_CODE,
+                ErrorReporter(err).to_string(),
+                None::<()>,
+            ),
+        }
+    }
+}
+
+/// A page of heights, as returned by the ibc-go `ConsensusStateHeights` query.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeightsPage {
+    pub heights: Vec<Height>,
+    /// The key to fetch the next page with, empty if this is the last page.
+    pub next_key: Vec<u8>,
+}
+
+/// Paginated access to the consensus state heights of a client.
+#[allow(async_fn_in_trait)]
+pub trait HeightsPageClient {
+    /// Fetch the page starting at `key`, or the first page if `key` is empty.
+    async fn heights_page(&self, key: Vec<u8>) -> RpcResult<HeightsPage>;
+}
+
+/// Fetch all pages of consensus state heights from `client`, and take the page
+/// requested by `pagination` out of them.
+pub async fn collect_pages(
+    client: &impl HeightsPageClient,
+    pagination: Pagination,
+) -> Result<ConsensusStateHeights, ConsensusHeightsError> {
+    pagination.validate()?;
+
+    let mut heights = vec![];
+    let mut key = vec![];
+
+    for _ in 0..MAX_PAGES {
+        let page = client.heights_page(key).await?;
+
+        heights.extend(page.heights);
+
+        if page.next_key.is_empty() {
+            return Ok(ConsensusStateHeights {
+                total: Some(heights.len().try_into().expect("usize fits in u64; qed;")),
+                heights: pagination.apply(heights),
+            });
+        }
+
+        key = page.next_key;
+    }
+
+    Err(ConsensusHeightsError::TooManyPages)
+}
+
+/// Read access to the keys of the consensus state mapping of a client.
+#[allow(async_fn_in_trait)]
+pub trait RangeClient {
+    /// The keys in `range` that have a value.
+    async fn query_range(&self, range: QueryRange) -> RpcResult<Vec<u64>>;
+}
+
+/// Walk down from `latest_height` in windows of [`MAX_QUERY_RANGE`] keys until
+/// the page requested by `pagination` is filled, or [`MAX_SCANNED_HEIGHTS`]
+/// heights have been scanned. Returns the heights sorted descending.
+pub async fn walk_heights(
+    client: &impl RangeClient,
+    latest_height: u64,
+    pagination: Pagination,
+) -> Result<Vec<u64>, ConsensusHeightsError> {
+    pagination.validate()?;
+
+    let limit = usize::try_from(pagination.limit).expect("limit is bounded; qed;");
+
+    let mut end = latest_height.saturating_add(1);
+    let floor = end.saturating_sub(MAX_SCANNED_HEIGHTS);
+
+    let mut skipped = 0;
+    let mut heights = vec![];
+
+    while end > floor && heights.len() < limit {
+        let start = end.saturating_sub(MAX_QUERY_RANGE).max(floor);
+
+        let mut keys = client.query_range(QueryRange { start, end }).await?;
+        keys.sort_unstable_by(|a, b| b.cmp(a));
+
+        for key in keys {
+            if skipped < pagination.offset {
+                skipped += 1;
+            } else if heights.len() < limit {
+                heights.push(key);
+            }
+        }
+
+        end = start;
+    }
+
+    Ok(heights)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        cell::RefCell,
+        collections::{BTreeSet, VecDeque},
+    };
+
+    use super::*;
+
+    struct MockPages(RefCell<VecDeque<(Vec<u8>, HeightsPage)>>);
+
+    impl MockPages {
+        fn new(pages: Vec<Vec<u64>>) -> Self {
+            let count = pages.len();
+
+            Self(RefCell::new(
+                pages
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, heights)| {
+                        let key = if i == 0 { vec![] } else { vec![i as u8] };
+                        let next_key = if i + 1 == count {
+                            vec![]
+                        } else {
+                            vec![i as u8 + 1]
+                        };
+
+                        (
+                            key,
+                            HeightsPage {
+                                heights: heights
+                                    .into_iter()
+                                    .map(|h| Height::new_with_revision(1, h))
+                                    .collect(),
+                                next_key,
+                            },
+                        )
+                    })
+                    .collect(),
+            ))
+        }
+    }
+
+    impl HeightsPageClient for MockPages {
+        async fn heights_page(&self, key: Vec<u8>) -> RpcResult<HeightsPage> {
+            let (expected_key, page) = self.0.borrow_mut().pop_front().expect("no more pages");
+
+            assert_eq!(key, expected_key);
+
+            Ok(page)
+        }
+    }
+
+    struct MockMapping {
+        keys: BTreeSet<u64>,
+        queried: RefCell<Vec<QueryRange>>,
+    }
+
+    impl MockMapping {
+        fn new(keys: impl IntoIterator<Item = u64>) -> Self {
+            Self {
+                keys: keys.into_iter().collect(),
+                queried: RefCell::new(vec![]),
+            }
+        }
+    }
+
+    impl RangeClient for MockMapping {
+        async fn query_range(&self, range: QueryRange) -> RpcResult<Vec<u64>> {
+            range.validate()?;
+
+            self.queried.borrow_mut().push(range);
+
+            Ok(self.keys.range(range.start..range.end).copied().collect())
+        }
+    }
+
+    fn heights(heights: impl IntoIterator<Item = u64>) -> Vec<Height> {
+        heights
+            .into_iter()
+            .map(|h| Height::new_with_revision(1, h))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn pages_are_stitched() {
+        // lexicographic order, as returned by ibc-go
+        let client = MockPages::new(vec![vec![1, 10, 11], vec![2, 3], vec![9]]);
+
+        assert_eq!(
+            collect_pages(
+                &client,
+                Pagination {
+                    offset: 1,
+                    limit: 3
+                }
+            )
+            .await
+            .unwrap(),
+            ConsensusStateHeights {
+                heights: heights([10, 9, 3]),
+                total: Some(6),
+            }
+        );
+
+        assert!(client.0.borrow().is_empty());
+    }
+
+    #[tokio::test]
+    async fn offset_past_the_end_is_empty() {
+        let client = MockPages::new(vec![vec![1, 2]]);
+
+        let page = collect_pages(
+            &client,
+            Pagination {
+                offset: 2,
+                limit: 10,
+            },
+        )
+        .await
+        .unwrap();
+
+        assert!(page.heights.is_empty());
+        assert_eq!(page.total, Some(2));
+    }
+
+    #[tokio::test]
+    async fn page_count_is_bounded() {
+        let client = MockPages::new(vec![vec![1]; MAX_PAGES + 1]);
+
+        assert!(matches!(
+            collect_pages(&client, Pagination::default()).await,
+            Err(ConsensusHeightsError::TooManyPages)
+        ));
+    }
+
+    #[tokio::test]
+    async fn range_is_walked_down() {
+        let mapping = MockMapping::new([5, 100, 300, 301, 600]);
+
+        assert_eq!(
+            walk_heights(
+                &mapping,
+                600,
+                Pagination {
+                    offset: 1,
+                    limit: 3
+                }
+            )
+            .await
+            .unwrap(),
+            [301, 300, 100]
+        );
+
+        assert_eq!(
+            *mapping.queried.borrow(),
+            [
+                QueryRange {
+                    start: 345,
+                    end: 601
+                },
+                QueryRange {
+                    start: 89,
+                    end: 345
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn walk_stops_at_zero() {
+        let mapping = MockMapping::new([0, 1, 2]);
+
+        assert_eq!(
+            walk_heights(&mapping, 2, Pagination::default())
+                .await
+                .unwrap(),
+            [2, 1, 0]
+        );
+
+        assert_eq!(mapping.queried.borrow().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn walk_is_bounded() {
+        let mapping = MockMapping::new([1, 1_000_000]);
+
+        assert_eq!(
+            walk_heights(&mapping, 1_000_000, Pagination::default())
+                .await
+                .unwrap(),
+            [1_000_000]
+        );
+
+        let queried = mapping.queried.borrow();
+
+        assert_eq!(
+            queried.len(),
+            usize::try_from(MAX_SCANNED_HEIGHTS / MAX_QUERY_RANGE).unwrap()
+        );
+        assert_eq!(
+            queried.last().unwrap().start,
+            1_000_001 - MAX_SCANNED_HEIGHTS
+        );
+    }
+
+    #[test]
+    fn bounds() {
+        assert!(Pagination {
+            offset: 0,
+            limit: 0
+        }
+        .validate()
+        .is_err());
+        assert!(Pagination {
+            offset: 0,
+            limit: MAX_PAGE_LIMIT + 1
+        }
+        .validate()
+        .is_err());
+        assert!(QueryRange { start: 2, end: 1 }.validate().is_err());
+        assert!(QueryRange {
+            start: 0,
+            end: MAX_QUERY_RANGE + 1
+        }
+        .validate()
+        .is_err());
+        assert!(QueryRange {
+            start: 0,
+            end: MAX_QUERY_RANGE
+        }
+        .validate()
+        .is_ok());
+    }
+}
@@ -21,7 +21,7 @@ use tokio::time::sleep;
 use tokio_util::sync::CancellationToken;
 use tracing::{debug, debug_span, error, info, instrument, trace, warn, Instrument};
 use unionlabs::{ethereum::keccak256, hash::hash_v2::HexUnprefixed, traits::Member, ErrorReporter};
-use voyager_core::{ConsensusType, IbcSpecId};
+use voyager_core::{ConsensusType, HeightFormat, IbcSpecId};
 use voyager_vm::QueueError;
 
 use crate::{
@@ -29,10 +29,13 @@ use crate::{
     into_value,
     module::{
         ClientModuleClient, ClientModuleInfo, ConsensusModuleClient, ConsensusModuleInfo,
-        PluginClient, PluginInfo, ProofModuleInfo, RawProofModuleClient, RawStateModuleClient,
-        StateModuleInfo,
+        LoadedModulesInfo, PluginClient, PluginInfo, PluginKind, ProofModuleInfo,
+        RawProofModuleClient, RawStateModuleClient, StateModuleInfo,
+    },
+    rpc::{
+        server::{cache::CacheConfig, Server},
+        VoyagerRpcServer,
     },
-    rpc::{server::Server, VoyagerRpcServer},
     RawClientId, FATAL_JSONRPC_ERROR_CODE,
 };
 
@@ -53,6 +56,13 @@ pub struct Context {
 
 #[derive(macros::Debug)]
 pub struct Modules {
+    /// map of plugin name to plugin.
+    plugins: HashMap<String, ModuleRpcClient>,
+
+    /// map of (chain id, IBC spec) to the name of the transaction plugin
+    /// serving it.
+    transaction_plugins: HashMap<(ChainId, IbcSpecId), String>,
+
     state_modules: HashMap<(ChainId, IbcSpecId), ModuleRpcClient>,
     proof_modules: HashMap<(ChainId, IbcSpecId), ModuleRpcClient>,
 
@@ -85,6 +95,7 @@ impl IbcSpecHandlers {
 pub struct IbcSpecHandler {
     pub client_state_path: fn(RawClientId) -> anyhow::Result<Value>,
     pub consensus_state_path: fn(RawClientId, String) -> anyhow::Result<Value>,
+    pub height_format: HeightFormat,
 }
 
 impl IbcSpecHandler {
@@ -101,6 +112,7 @@ impl IbcSpecHandler {
                     height.parse()?,
                 )))
             },
+            height_format: T::HEIGHT_FORMAT,
         }
     }
 }
@@ -198,6 +210,9 @@ pub struct PluginConfig {
     pub config: Value,
     #[serde(default = "default_enabled")]
     pub enabled: bool,
+    /// Allow this plugin to serve the same (chain, IBC spec) pairs as another plugin of the same kind.
+    #[serde(default)]
+    pub allow_overlap: bool,
 }
 
 #[model]
@@ -233,6 +248,7 @@ impl Context {
     pub async fn new(
         plugin_configs: Vec<PluginConfig>,
         module_configs: ModulesConfig,
+        cache_config: CacheConfig,
         register_ibc_spec_handlers: fn(&mut IbcSpecHandlers),
     ) -> anyhow::Result<Self> {
         let cancellation_token = CancellationToken::new();
@@ -244,6 +260,8 @@ impl Context {
         register_ibc_spec_handlers(&mut ibc_spec_handlers);
 
         let mut modules = Modules {
+            plugins: Default::default(),
+            transaction_plugins: Default::default(),
             state_modules: Default::default(),
             proof_modules: Default::default(),
             client_modules: Default::default(),
@@ -257,11 +275,11 @@ impl Context {
 
         let mut interest_filters = HashMap::default();
 
-        let main_rpc_server = Server::new();
+        let main_rpc_server = Server::new(&cache_config);
 
         info!("spawning {} plugins", plugin_configs.len());
 
-        stream::iter(plugin_configs)
+        let plugins_with_info = stream::iter(plugin_configs)
             .filter(|plugin_config| {
                 future::ready(if !plugin_config.enabled {
                     info!(
@@ -274,7 +292,7 @@ impl Context {
                 })
             })
             .zip(stream::repeat(main_rpc_server.clone()))
-            .map(Ok)
+            .map(Ok::<_, anyhow::Error>)
             .try_filter_map(|(plugin_config, server)| async move {
                 if !plugin_config.enabled {
                     info!(
@@ -291,41 +309,56 @@ impl Context {
                     Ok(Some((plugin_config, plugin_info)))
                 }
             })
-            .try_for_each_concurrent(
-                None,
-                |(
-                    plugin_config,
-                    PluginInfo {
-                        name,
-                        interest_filter,
-                    },
-                )| {
-                    info!("registering plugin {}", name);
-
-                    tokio::spawn(plugin_child_process(
-                        name.clone(),
-                        plugin_config.clone(),
-                        cancellation_token.clone(),
-                    ));
-
-                    let rpc_client = ModuleRpcClient::new(&name);
+            .try_collect::<Vec<_>>()
+            .await?;
 
-                    let prev = plugins.insert(name.clone(), rpc_client.clone());
+        check_plugin_overlap(&plugins_with_info)?;
 
-                    if prev.is_some() {
-                        return future::ready(Err(anyhow!(
-                            "multiple plugins configured with name `{name}`"
-                        )));
+        for (
+            plugin_config,
+            PluginInfo {
+                name,
+                interest_filter,
+                kind,
+                chains,
+                ibc_specs,
+            },
+        ) in plugins_with_info
+        {
+            info!("registering plugin {}", name);
+
+            if kind == Some(PluginKind::Transaction) {
+                for chain_id in &chains {
+                    for ibc_spec_id in &ibc_specs {
+                        // with `allow_overlap`, the first configured plugin is used
+                        modules
+                            .transaction_plugins
+                            .entry((chain_id.clone(), ibc_spec_id.clone()))
+                            .or_insert_with(|| name.clone());
                     }
+                }
+            }
 
-                    info!("registered plugin {name}");
+            tokio::spawn(plugin_child_process(
+                name.clone(),
+                plugin_config.clone(),
+                cancellation_token.clone(),
+            ));
 
-                    interest_filters.insert(name, interest_filter);
+            let rpc_client = ModuleRpcClient::new(&name);
 
-                    future::ready(Ok(()))
-                },
-            )
-            .await?;
+            let prev = plugins.insert(name.clone(), rpc_client.clone());
+
+            if prev.is_some() {
+                return Err(anyhow!("multiple plugins configured with name `{name}`"));
+            }
+
+            info!("registered plugin {name}");
+
+            interest_filters.insert(name, interest_filter);
+        }
+
+        modules.plugins = plugins.clone();
 
         module_startup(
             module_configs.state,
@@ -546,6 +579,37 @@ impl Context {
 }
 
 impl Modules {
+    pub fn plugin<'a>(
+        &'a self,
+        name: &str,
+    ) -> Result<&'a (impl PluginClient<Value, Value> + 'a), PluginNotFound> {
+        Ok(self
+            .plugins
+            .get(name)
+            .ok_or_else(|| PluginNotFound { name: name.into() })?
+            .client())
+    }
+
+    /// The transaction plugin that submits transactions for `ibc_spec_id` on
+    /// `chain_id`.
+    pub fn transaction_plugin<'a>(
+        &'a self,
+        chain_id: &ChainId,
+        ibc_spec_id: &IbcSpecId,
+    ) -> Result<&'a (impl PluginClient<Value, Value> + 'a), TransactionPluginNotFound> {
+        let not_found = || TransactionPluginNotFound {
+            chain_id: chain_id.clone(),
+            ibc_spec_id: ibc_spec_id.clone(),
+        };
+
+        let name = self
+            .transaction_plugins
+            .get(&(chain_id.clone(), ibc_spec_id.clone()))
+            .ok_or_else(not_found)?;
+
+        Ok(self.plugins.get(name).ok_or_else(not_found)?.client())
+    }
+
     pub fn info(&self) -> LoadedModulesInfo {
         let state = self
             .state_modules
@@ -680,14 +744,6 @@ impl Modules {
     }
 }
 
-#[model]
-pub struct LoadedModulesInfo {
-    pub state: Vec<StateModuleInfo>,
-    pub proof: Vec<ProofModuleInfo>,
-    pub consensus: Vec<ConsensusModuleInfo>,
-    pub client: Vec<ClientModuleInfo>,
-}
-
 #[instrument(skip_all, fields(%name))]
 async fn plugin_child_process(
     name: String,
@@ -893,6 +949,43 @@ pub struct PluginNotFound {
 
 module_error!(PluginNotFound);
 
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+#[error("no transaction plugin loaded for chain `{chain_id}` and IBC version `{ibc_spec_id}`")]
+pub struct TransactionPluginNotFound {
+    pub chain_id: ChainId,
+    pub ibc_spec_id: IbcSpecId,
+}
+
+module_error!(TransactionPluginNotFound);
+
+/// Ensure that no two plugins of the same kind serve the same (chain, IBC spec) pair, since this would result in (for example) the same transactions being submitted multiple times. Plugins with [`PluginConfig::allow_overlap`] set are not checked.
+fn check_plugin_overlap(plugins: &[(PluginConfig, PluginInfo)]) -> anyhow::Result<()> {
+    let mut served = HashMap::<(PluginKind, &ChainId, &IbcSpecId), &str>::new();
+
+    for (plugin_config, plugin_info) in plugins {
+        if plugin_config.allow_overlap {
+            continue;
+        }
+
+        let Some(kind) = plugin_info.kind else {
+            continue;
+        };
+
+        for (chain_id, ibc_spec_id) in plugin_info.served() {
+            if let Some(prev) = served.insert((kind, chain_id, ibc_spec_id), &plugin_info.name) {
+                return Err(anyhow!(
+                    "{kind} plugins `{prev}` and `{name}` are both configured to serve \
+                    {ibc_spec_id} on {chain_id}; set `allow_overlap` in the plugin \
+                    config if this is intended",
+                    name = plugin_info.name
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
+
 pub fn get_plugin_info(module_config: &PluginConfig) -> anyhow::Result<PluginInfo> {
     debug!(
         "querying module info from plugin at {}",
@@ -996,3 +1089,80 @@ async fn module_startup<Info: Serialize + Clone + Unpin + Send + 'static>(
             Ok(())
         })
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn plugin(
+        name: &str,
+        kind: Option<PluginKind>,
+        chains: &[&'static str],
+        allow_overlap: bool,
+    ) -> (PluginConfig, PluginInfo) {
+        (
+            PluginConfig {
+                path: name.into(),
+                config: json!({}),
+                enabled: true,
+                allow_overlap,
+            },
+            PluginInfo {
+                name: name.to_owned(),
+                interest_filter: "true".to_owned(),
+                kind,
+                chains: chains.iter().map(|c| ChainId::new(*c)).collect(),
+                ibc_specs: vec![IbcSpecId::new(IbcSpecId::UNION)],
+            },
+        )
+    }
+
+    #[test]
+    fn plugin_overlap_same_kind_errors() {
+        let res = check_plugin_overlap(&[
+            plugin("a", Some(PluginKind::Transaction), &["union-1"], false),
+            plugin("b", Some(PluginKind::Transaction), &["union-1"], false),
+        ]);
+
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn plugin_overlap_allowed() {
+        check_plugin_overlap(&[
+            plugin("a", Some(PluginKind::Transaction), &["union-1"], false),
+            plugin("b", Some(PluginKind::Transaction), &["union-1"], true),
+        ])
+        .unwrap();
+    }
+
+    #[test]
+    fn plugin_overlap_different_kinds_or_chains() {
+        check_plugin_overlap(&[
+            plugin("a", Some(PluginKind::Transaction), &["union-1"], false),
+            plugin("b", Some(PluginKind::EventSource), &["union-1"], false),
+            plugin(
+                "c",
+                Some(PluginKind::Transaction),
+                &["sepolia-11155111"],
+                false,
+            ),
+            plugin("d", None, &["union-1"], false),
+        ])
+        .unwrap();
+    }
+
+    #[test]
+    fn plugin_config_legacy_decode() {
+        let config = serde_json::from_value::<PluginConfig>(json!({
+            "path": "voyager-plugin",
+            "config": {},
+        }))
+        .unwrap();
+
+        assert!(config.enabled);
+        assert!(!config.allow_overlap);
+    }
+}
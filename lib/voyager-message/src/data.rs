@@ -5,9 +5,12 @@ use serde_json::Value;
 use subset_of::SubsetOf;
 use unionlabs::{bytes::Bytes, hash::H256, ibc::core::client::height::Height, traits::Member};
 use voyager_core::IbcSpecId;
+use voyager_vm::op_graph::{type_tag, Summarize, Summary};
 
 use crate::{
     core::{ChainId, ClientInfo, ClientStateMeta, IbcSpec},
+    denom::DenomTrace,
+    freeze::FrozenRelaying,
     into_value, PluginMessage, RawClientId,
 };
 
@@ -24,6 +27,14 @@ pub enum Data {
     OrderedHeaders(OrderedHeaders),
     OrderedMsgUpdateClients(OrderedClientUpdates),
 
+    /// Emitted by [`WatchClientFreeze`](crate::call::WatchClientFreeze) when a client is found to
+    /// be frozen, after relaying over its connections has been suppressed.
+    RelayingFrozen(FrozenRelaying),
+
+    /// Emitted by event sources that track the finality of a chain out of band, i.e. by watching
+    /// the settlement of a rollup on its L1.
+    LatestHeight(LatestHeight),
+
     Plugin(PluginMessage),
 }
 
@@ -39,6 +50,45 @@ impl Data {
     }
 }
 
+impl Summarize for Data {
+    fn summarize(&self) -> Summary {
+        let summary = match self {
+            Self::Plugin(plugin_message) => return plugin_message.summarize(),
+            this => Summary::from_value(&into_value(this)),
+        };
+
+        // the events and datagrams are opaque values, include their type as well
+        match self {
+            Self::IbcEvent(chain_event) => match type_tag(&chain_event.event) {
+                Some(ty) => summary.with_field("event", ty),
+                None => summary,
+            },
+            Self::IbcDatagram(IbcDatagram { datagram, .. })
+            | Self::IdentifiedIbcDatagram(WithChainId {
+                message: IbcDatagram { datagram, .. },
+                ..
+            }) => match type_tag(datagram) {
+                Some(ty) => summary.with_field("datagram", ty),
+                None => summary,
+            },
+            Self::IdentifiedIbcDatagramBatch(WithChainId { message, .. }) => {
+                summary.with_field("datagrams", message.len())
+            }
+            _ => summary,
+        }
+    }
+}
+
+/// The latest height of a chain. Scheduling that depends on the finality of the chain (client
+/// updates, timeout checks, ...) should be keyed off of this rather than the head of the chain.
+#[model]
+pub struct LatestHeight {
+    pub chain_id: ChainId,
+    pub height: Height,
+    /// Whether [`Self::height`] is finalized.
+    pub finalized: bool,
+}
+
 #[model]
 pub struct ChainEvent {
     /// The chain where this event was emitted.
@@ -57,6 +107,22 @@ pub struct ChainEvent {
     /// The full IBC event, encoded as JSON value. This is really [`IbcSpec::Event`],
     /// and will be interpreted based on the implementation defined by [`Self::ibc_spec_id`].
     pub event: Value,
+    /// The raw events emitted in the same transaction as this event that are correlated with it,
+    /// including the raw form of the event itself. This is only populated by event sources that
+    /// support it, and only if enabled in their configuration.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw_events: Option<Vec<RawTmEvent>>,
+    /// The trace of the transferred denom, for packet events of ics20 transfers. This is only
+    /// populated by event sources that support it, and only if enabled in their configuration.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub denom_trace: Option<DenomTrace>,
+}
+
+/// A raw cometbft event, as emitted by the chain.
+#[model]
+pub struct RawTmEvent {
+    pub ty: String,
+    pub attributes: Vec<(String, String)>,
 }
 
 impl ChainEvent {
@@ -122,6 +188,7 @@ pub struct OrderedClientUpdates {
 pub struct ClientUpdate {
     pub client_id: RawClientId,
     pub ibc_spec_id: IbcSpecId,
+    #[serde(with = "crate::compression::compressed_bytes")]
     pub client_message: Bytes,
 }
 
@@ -130,3 +197,6 @@ pub struct WithChainId<T> {
     pub chain_id: ChainId,
     pub message: T,
 }
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,372 @@
+//! Round trip tests for all [`Data`] variants, using arbitrary instances
+//! generated with [`proptest`].
+//!
+//! The tests are run with a fixed seed, such that they are deterministic.
+
+use std::fmt::Debug;
+
+use proptest::{
+    collection::{btree_map, vec},
+    prelude::*,
+    test_runner::{Config, RngAlgorithm, TestCaseError, TestRng, TestRunner},
+};
+use serde::Serialize;
+use voyager_core::{ClientType, IbcInterface, QueryHeight};
+use voyager_vm::Op;
+
+use super::*;
+use crate::{
+    freeze::{ChannelRef, ClientRef},
+    rpc::{IbcProof, IbcState},
+    VoyagerMessage,
+};
+
+const PLUGIN_NAME: &str = "test-plugin";
+
+fn runner() -> TestRunner {
+    TestRunner::new_with_rng(
+        Config {
+            cases: 512,
+            failure_persistence: None,
+            ..Config::default()
+        },
+        TestRng::deterministic_rng(RngAlgorithm::ChaCha),
+    )
+}
+
+fn assert_round_trips<T>(t: &T) -> Result<(), TestCaseError>
+where
+    T: Serialize + DeserializeOwned + PartialEq + Debug,
+{
+    let json = serde_json::to_string(t).unwrap();
+
+    let decoded = serde_json::from_str::<T>(&json)
+        .map_err(|err| TestCaseError::fail(format!("{json}: {err}")))?;
+
+    prop_assert_eq!(&decoded, t, "{}", json);
+
+    Ok(())
+}
+
+fn arb_value() -> impl Strategy<Value = Value> {
+    let leaf = prop_oneof![
+        Just(Value::Null),
+        any::<bool>().prop_map(Value::from),
+        any::<i64>().prop_map(Value::from),
+        any::<u64>().prop_map(Value::from),
+        any::<f64>()
+            .prop_filter("json numbers must be finite", |f| f.is_finite())
+            .prop_map(Value::from),
+        ".*".prop_map(Value::from),
+    ];
+
+    leaf.prop_recursive(4, 64, 8, |inner| {
+        prop_oneof![
+            vec(inner.clone(), 0..8).prop_map(Value::Array),
+            btree_map(arb_key(), inner, 0..8)
+                .prop_map(|map| Value::Object(map.into_iter().collect())),
+        ]
+    })
+}
+
+/// Object keys, biased towards the keys that have special meaning somewhere in
+/// voyager (enum tags and compressed payloads).
+fn arb_key() -> impl Strategy<Value = String> {
+    prop_oneof![
+        Just("@type".to_owned()),
+        Just("@value".to_owned()),
+        Just("enc".to_owned()),
+        Just("data".to_owned()),
+        "[a-z_]{0,8}",
+    ]
+}
+
+fn arb_chain_id() -> impl Strategy<Value = ChainId> {
+    "[a-z0-9-]{1,16}".prop_map(ChainId::new)
+}
+
+fn arb_ibc_spec_id() -> impl Strategy<Value = IbcSpecId> {
+    prop_oneof![
+        Just(IbcSpecId::new_static(IbcSpecId::CLASSIC)),
+        Just(IbcSpecId::new_static(IbcSpecId::UNION)),
+        "[a-z-]{1,16}".prop_map(IbcSpecId::new),
+    ]
+}
+
+fn arb_height() -> impl Strategy<Value = Height> {
+    (0..4_u64, any::<u64>())
+        .prop_map(|(revision, height)| Height::new_with_revision(revision, height))
+}
+
+fn arb_h256() -> impl Strategy<Value = H256> {
+    any::<[u8; 32]>().prop_map(H256::new)
+}
+
+fn arb_bytes() -> impl Strategy<Value = Bytes> {
+    vec(any::<u8>(), 0..64).prop_map(Bytes::from)
+}
+
+fn arb_client_info() -> impl Strategy<Value = ClientInfo> {
+    (
+        prop_oneof![
+            Just(ClientType::new_static(ClientType::COMETBLS)),
+            Just(ClientType::new_static(ClientType::TENDERMINT)),
+            "[a-z0-9-]{1,16}".prop_map(ClientType::new),
+        ],
+        prop_oneof![
+            Just(IbcInterface::new_static(IbcInterface::IBC_GO_V8_NATIVE)),
+            Just(IbcInterface::new_static(IbcInterface::IBC_GO_V8_08_WASM)),
+            "[a-z0-9/-]{1,16}".prop_map(IbcInterface::new),
+        ],
+        arb_value(),
+    )
+        .prop_map(|(client_type, ibc_interface, metadata)| ClientInfo {
+            client_type,
+            ibc_interface,
+            metadata,
+        })
+}
+
+fn arb_raw_tm_event() -> impl Strategy<Value = RawTmEvent> {
+    ("[a-z_]{1,16}", vec((".*", ".*"), 0..4))
+        .prop_map(|(ty, attributes)| RawTmEvent { ty, attributes })
+}
+
+fn arb_chain_event() -> impl Strategy<Value = ChainEvent> {
+    (
+        arb_chain_id(),
+        arb_client_info(),
+        arb_chain_id(),
+        arb_h256(),
+        arb_height(),
+        arb_ibc_spec_id(),
+        arb_value(),
+        proptest::option::of(vec(arb_raw_tm_event(), 0..4)),
+    )
+        .prop_map(
+            |(
+                chain_id,
+                client_info,
+                counterparty_chain_id,
+                tx_hash,
+                provable_height,
+                ibc_spec_id,
+                event,
+                raw_events,
+            )| ChainEvent {
+                chain_id,
+                client_info,
+                counterparty_chain_id,
+                tx_hash,
+                provable_height,
+                ibc_spec_id,
+                event,
+                raw_events,
+                denom_trace: None,
+            },
+        )
+}
+
+fn arb_ibc_datagram() -> impl Strategy<Value = IbcDatagram> {
+    (arb_ibc_spec_id(), arb_value()).prop_map(|(ibc_spec_id, datagram)| IbcDatagram {
+        ibc_spec_id,
+        datagram,
+    })
+}
+
+fn arb_decoded_header_meta() -> impl Strategy<Value = DecodedHeaderMeta> {
+    arb_height().prop_map(|height| DecodedHeaderMeta { height })
+}
+
+fn arb_client_update() -> impl Strategy<Value = ClientUpdate> {
+    (arb_value(), arb_ibc_spec_id(), arb_bytes()).prop_map(
+        |(client_id, ibc_spec_id, client_message)| ClientUpdate {
+            client_id: RawClientId::new(client_id),
+            ibc_spec_id,
+            client_message,
+        },
+    )
+}
+
+fn arb_plugin_message() -> impl Strategy<Value = PluginMessage> {
+    ("[a-z0-9/-]{1,16}", arb_value())
+        .prop_map(|(plugin, message)| PluginMessage { plugin, message })
+}
+
+fn arb_client_ref() -> impl Strategy<Value = ClientRef> {
+    (arb_chain_id(), any::<u32>()).prop_map(|(chain_id, client_id)| ClientRef {
+        chain_id,
+        client_id,
+    })
+}
+
+fn arb_frozen_relaying() -> impl Strategy<Value = FrozenRelaying> {
+    (
+        arb_client_ref(),
+        arb_height(),
+        vec(arb_client_ref(), 0..4),
+        vec((arb_chain_id(), any::<u32>()), 0..4),
+    )
+        .prop_map(
+            |(client, frozen_at, paired_clients, channels)| FrozenRelaying {
+                client,
+                frozen_at,
+                paired_clients,
+                channels: channels
+                    .into_iter()
+                    .map(|(chain_id, channel_id)| ChannelRef {
+                        chain_id,
+                        channel_id,
+                    })
+                    .collect(),
+            },
+        )
+}
+
+fn arb_data() -> impl Strategy<Value = Data> {
+    prop_oneof![
+        arb_chain_event().prop_map(Data::IbcEvent),
+        arb_ibc_datagram().prop_map(Data::IbcDatagram),
+        (arb_chain_id(), arb_ibc_datagram()).prop_map(|(chain_id, message)| {
+            Data::IdentifiedIbcDatagram(WithChainId { chain_id, message })
+        }),
+        (arb_chain_id(), vec(arb_ibc_datagram(), 0..4)).prop_map(|(chain_id, message)| {
+            Data::IdentifiedIbcDatagramBatch(WithChainId { chain_id, message })
+        }),
+        vec((arb_decoded_header_meta(), arb_value()), 0..4)
+            .prop_map(|headers| Data::OrderedHeaders(OrderedHeaders { headers })),
+        vec((arb_decoded_header_meta(), arb_client_update()), 0..4)
+            .prop_map(|updates| Data::OrderedMsgUpdateClients(OrderedClientUpdates { updates })),
+        arb_frozen_relaying().prop_map(Data::RelayingFrozen),
+        (arb_chain_id(), arb_height(), any::<bool>()).prop_map(|(chain_id, height, finalized)| {
+            Data::LatestHeight(LatestHeight {
+                chain_id,
+                height,
+                finalized,
+            })
+        }),
+        arb_plugin_message().prop_map(Data::Plugin),
+    ]
+}
+
+#[test]
+fn data_round_trip() {
+    runner()
+        .run(&arb_data(), |data| assert_round_trips(&data))
+        .unwrap();
+}
+
+#[test]
+fn data_round_trip_in_op() {
+    runner()
+        .run(&arb_data(), |data| {
+            assert_round_trips(&Op::<VoyagerMessage>::Data(data))
+        })
+        .unwrap();
+}
+
+#[test]
+fn data_round_trip_in_plugin_message() {
+    runner()
+        .run(&arb_data(), |data| {
+            let wrapped = Data::Plugin(PluginMessage::new(PLUGIN_NAME, &data));
+
+            assert_round_trips(&wrapped)?;
+            assert_round_trips(&Op::<VoyagerMessage>::Data(wrapped.clone()))?;
+
+            prop_assert_eq!(wrapped.as_plugin::<Data>(PLUGIN_NAME), Ok(data));
+
+            Ok(())
+        })
+        .unwrap();
+}
+
+#[test]
+fn ibc_state_round_trip() {
+    runner()
+        .run(&(arb_height(), arb_value()), |(height, state)| {
+            assert_round_trips(&IbcState { height, state })
+        })
+        .unwrap();
+
+    runner()
+        .run(&(arb_height(), arb_bytes()), |(height, state)| {
+            assert_round_trips(&IbcState { height, state })
+        })
+        .unwrap();
+
+    runner()
+        .run(
+            &(arb_height(), proptest::option::of(arb_h256())),
+            |(height, state)| assert_round_trips(&IbcState { height, state }),
+        )
+        .unwrap();
+}
+
+#[test]
+fn ibc_proof_round_trip() {
+    runner()
+        .run(&(arb_height(), arb_value()), |(height, proof)| {
+            assert_round_trips(&IbcProof { height, proof })
+        })
+        .unwrap();
+}
+
+#[test]
+fn query_height_round_trip() {
+    runner()
+        .run(
+            &prop_oneof![
+                Just(QueryHeight::Latest),
+                Just(QueryHeight::Finalized),
+                arb_height().prop_map(QueryHeight::Specific),
+            ],
+            |query_height| assert_round_trips(&query_height),
+        )
+        .unwrap();
+}
+
+#[test]
+fn empty_client_message_round_trips() {
+    let data = Data::OrderedMsgUpdateClients(OrderedClientUpdates {
+        updates: vec![(
+            DecodedHeaderMeta {
+                height: Height::new(1),
+            },
+            ClientUpdate {
+                client_id: RawClientId::new(1),
+                ibc_spec_id: IbcSpecId::new_static(IbcSpecId::UNION),
+                client_message: Bytes::default(),
+            },
+        )],
+    });
+
+    assert_round_trips(&data).unwrap();
+}
+
+#[test]
+fn chain_event_without_raw_events() {
+    let event = ChainEvent {
+        chain_id: ChainId::new("chain"),
+        client_info: ClientInfo {
+            client_type: ClientType::new_static(ClientType::COMETBLS),
+            ibc_interface: IbcInterface::new_static(IbcInterface::IBC_GO_V8_NATIVE),
+            metadata: Value::Null,
+        },
+        counterparty_chain_id: ChainId::new("counterparty"),
+        tx_hash: H256::default(),
+        provable_height: Height::new(1),
+        ibc_spec_id: IbcSpecId::new_static(IbcSpecId::CLASSIC),
+        event: Value::Null,
+        raw_events: None,
+        denom_trace: None,
+    };
+
+    let mut json = serde_json::to_value(&event).unwrap();
+
+    assert!(json.get("raw_events").is_none());
+
+    // messages from before the field was added are still valid
+    json.as_object_mut().unwrap().remove("raw_events");
+    assert_eq!(serde_json::from_value::<ChainEvent>(json).unwrap(), event);
+}
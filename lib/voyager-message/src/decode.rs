@@ -0,0 +1,114 @@
+//! Decoding of ops from JSON.
+//!
+//! Ops are deeply nested, and the errors returned by `serde_json` only contain the line and column
+//! of the failure, which is not very useful for a single line of JSON. Ops decoded with
+//! [`decode_op`] report the [JSON pointer](https://www.rfc-editor.org/rfc/rfc6901) to the field
+//! that failed to deserialize instead.
+
+use serde::de::DeserializeOwned;
+use serde_path_to_error::Segment;
+use voyager_vm::Op;
+
+use crate::VoyagerMessage;
+
+#[derive(Debug, thiserror::Error)]
+#[error("invalid op at `{pointer}`: {error}")]
+pub struct DecodeError {
+    /// The JSON pointer to the field that failed to deserialize. This is the empty string if the
+    /// failure is at the root of the document.
+    pub pointer: String,
+    pub error: serde_json::Error,
+}
+
+/// Decode an op from JSON, reporting the path to the offending field on failure.
+pub fn decode_op(json: &str) -> Result<Op<VoyagerMessage>, DecodeError> {
+    decode(json)
+}
+
+pub(crate) fn decode<T: DeserializeOwned>(json: &str) -> Result<T, DecodeError> {
+    let mut deserializer = serde_json::Deserializer::from_str(json);
+
+    let t = serde_path_to_error::deserialize(&mut deserializer).map_err(|err| DecodeError {
+        pointer: json_pointer(err.path()),
+        error: err.into_inner(),
+    })?;
+
+    // trailing characters are not part of any field
+    deserializer.end().map_err(|error| DecodeError {
+        pointer: String::new(),
+        error,
+    })?;
+
+    Ok(t)
+}
+
+fn json_pointer(path: &serde_path_to_error::Path) -> String {
+    path.iter()
+        .filter_map(|segment| match segment {
+            Segment::Seq { index } => Some(index.to_string()),
+            Segment::Map { key } => Some(key.replace('~', "~0").replace('/', "~1")),
+            // externally tagged enum variants are keys in the JSON
+            Segment::Enum { variant } => Some(variant.replace('~', "~0").replace('/', "~1")),
+            Segment::Unknown => None,
+        })
+        .fold(String::new(), |pointer, segment| pointer + "/" + &segment)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use unionlabs::ibc::core::client::height::Height;
+    use voyager_vm::call;
+
+    use super::*;
+    use crate::{call::WaitForHeight, core::ChainId};
+
+    #[test]
+    fn valid_op() {
+        let op = call(WaitForHeight {
+            chain_id: ChainId::new("union-1"),
+            height: Height::new_with_revision(1, 100),
+            finalized: true,
+        });
+
+        assert_eq!(decode_op(&serde_json::to_string(&op).unwrap()).unwrap(), op);
+    }
+
+    #[test]
+    fn pointer_to_nested_field() {
+        let json = json!({
+            "@type": "call",
+            "@value": {
+                "@type": "wait_for_height",
+                "@value": {
+                    "chain_id": "union-1",
+                    "height": "not a height",
+                    "finalized": true,
+                }
+            }
+        });
+
+        let err = decode_op(&json.to_string()).unwrap_err();
+
+        assert_eq!(err.pointer, "/@value/@value/height");
+        assert!(
+            err.to_string()
+                .starts_with("invalid op at `/@value/@value/height`: "),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn pointer_is_escaped() {
+        #[derive(Debug, serde::Deserialize)]
+        #[allow(dead_code)]
+        struct Nested {
+            #[serde(rename = "a/b~c")]
+            inner: Vec<u64>,
+        }
+
+        let err = decode::<Nested>(r#"{"a/b~c":[1,"2"]}"#).unwrap_err();
+
+        assert_eq!(err.pointer, "/a~1b~0c/1");
+    }
+}
@@ -0,0 +1,554 @@
+//! Resolution of ics20 denoms to their trace.
+//!
+//! Tokens received over ics20 are represented on the receiving chain as
+//! `ibc/{hash}`, where the hash is the sha256 of the full path of the denom
+//! (i.e. `transfer/channel-1/uatom`). The full path can only be recovered by
+//! asking the transfer module of the chain that the denom is on, which is
+//! abstracted over by [`DenomResolver`].
+
+use std::{collections::BTreeMap, fmt::Debug};
+
+use chain_utils::{
+    auth::GrpcAuth,
+    bounded::{BoundedMap, BoundsConfig},
+};
+use futures::future::BoxFuture;
+use macros::model;
+use sha2::{Digest, Sha256};
+use tonic::{codegen::http::uri::PathAndQuery, Code};
+use tracing::debug;
+use unionlabs::{encoding::HexUnprefixed, hash::H256};
+use voyager_core::ChainId;
+use voyager_vm::BoxDynError;
+
+/// The prefix of the denoms of tokens received over ics20.
+pub const IBC_DENOM_PREFIX: &str = "ibc/";
+
+/// The trace of an ics20 denom.
+#[model]
+pub struct DenomTrace {
+    /// The port and channel pairs that the token was transferred through, i.e.
+    /// `transfer/channel-1/transfer/channel-2`. Empty for native tokens.
+    pub trace_path: String,
+    /// The denom of the token on the chain it is native to.
+    pub base_denom: String,
+}
+
+impl DenomTrace {
+    /// Split a full denom path into the trace path and the base denom.
+    ///
+    /// This follows ibc-go's `ExtractPathAndBaseFromFullDenom`: leading
+    /// `{port}/{channel}` pairs are part of the trace for as long as the
+    /// channel is a valid ibc-go channel identifier, and everything after is
+    /// the base denom (which may itself contain `/`).
+    #[must_use]
+    pub fn parse(full_denom: &str) -> Self {
+        let items = full_denom.split('/').collect::<Vec<_>>();
+
+        let mut trace_len = 0;
+
+        while trace_len + 1 < items.len() && items.len() > 2 && is_channel_id(items[trace_len + 1])
+        {
+            trace_len += 2;
+        }
+
+        Self {
+            trace_path: items[..trace_len].join("/"),
+            base_denom: items[trace_len..].join("/"),
+        }
+    }
+
+    /// The full path of the denom, i.e. `transfer/channel-1/uatom`.
+    #[must_use]
+    pub fn full_path(&self) -> String {
+        if self.trace_path.is_empty() {
+            self.base_denom.clone()
+        } else {
+            format!("{}/{}", self.trace_path, self.base_denom)
+        }
+    }
+
+    /// The hash of the full path of the denom.
+    #[must_use]
+    pub fn hash(&self) -> H256 {
+        H256::new(Sha256::digest(self.full_path()).into())
+    }
+
+    /// The denom of the token on the chain at the end of the trace, i.e.
+    /// `ibc/{hash}`. Native tokens are denoted by their base denom.
+    #[must_use]
+    pub fn ibc_denom(&self) -> String {
+        if self.trace_path.is_empty() {
+            self.base_denom.clone()
+        } else {
+            format!("{IBC_DENOM_PREFIX}{}", hex::encode_upper(self.hash().get()))
+        }
+    }
+}
+
+/// Whether `s` is a channel identifier as assigned by ibc-go (`channel-{n}`).
+fn is_channel_id(s: &str) -> bool {
+    s.strip_prefix("channel-").is_some_and(|n| {
+        !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()) && n.parse::<u64>().is_ok()
+    })
+}
+
+/// The hash of an `ibc/{hash}` denom, or `None` if `denom` is not one.
+#[must_use]
+pub fn ibc_denom_hash(denom: &str) -> Option<H256> {
+    denom
+        .strip_prefix(IBC_DENOM_PREFIX)?
+        .parse::<H256<HexUnprefixed>>()
+        .ok()
+        .map(|hash| hash.into_encoding())
+}
+
+/// Access to the denom traces known to the transfer modules of chains.
+pub trait DenomResolver: Debug + Send + Sync {
+    /// The trace of the denom with `hash` on `chain_id`, or `None` if the
+    /// chain does not know of it.
+    fn resolve<'a>(
+        &'a self,
+        chain_id: &'a ChainId,
+        hash: H256,
+    ) -> BoxFuture<'a, Result<Option<DenomTrace>, BoxDynError>>;
+}
+
+/// Resolve `denom` on `chain_id` to its trace.
+///
+/// `ibc/{hash}` denoms are looked up with `resolver`, all other denoms are
+/// parsed as a full denom path.
+pub async fn resolve_denom(
+    resolver: &dyn DenomResolver,
+    chain_id: &ChainId,
+    denom: &str,
+) -> Result<Option<DenomTrace>, BoxDynError> {
+    if !denom.starts_with(IBC_DENOM_PREFIX) {
+        return Ok(Some(DenomTrace::parse(denom)));
+    }
+
+    match ibc_denom_hash(denom) {
+        Some(hash) => resolver.resolve(chain_id, hash).await,
+        None => Ok(None),
+    }
+}
+
+/// A [`DenomResolver`] that caches the traces resolved by another resolver.
+///
+/// The hash of a denom commits to its trace, so resolved traces never go
+/// stale, but the cache is still bounded by `bounds` to not grow with every
+/// denom ever seen. Denoms that are not known are not cached, since they may
+/// be created later.
+#[derive(Debug)]
+pub struct CachingDenomResolver<R> {
+    inner: R,
+    cache: BoundedMap<(ChainId, H256), DenomTrace>,
+}
+
+impl<R: DenomResolver> CachingDenomResolver<R> {
+    pub fn new(inner: R, bounds: &BoundsConfig) -> Self {
+        Self {
+            inner,
+            cache: BoundedMap::from_config("denom_trace_cache", bounds),
+        }
+    }
+}
+
+impl<R: DenomResolver> DenomResolver for CachingDenomResolver<R> {
+    fn resolve<'a>(
+        &'a self,
+        chain_id: &'a ChainId,
+        hash: H256,
+    ) -> BoxFuture<'a, Result<Option<DenomTrace>, BoxDynError>> {
+        Box::pin(async move {
+            let key = (chain_id.clone(), hash);
+
+            if let Some(trace) = self.cache.get(&key) {
+                debug!(%chain_id, %hash, "cache hit for denom trace");
+
+                return Ok(Some(trace));
+            }
+
+            let trace = self.inner.resolve(chain_id, hash).await?;
+
+            if let Some(trace) = &trace {
+                self.cache.insert(key, trace.clone());
+            }
+
+            Ok(trace)
+        })
+    }
+}
+
+/// A [`DenomResolver`] that queries the transfer module of chains over grpc.
+///
+/// ibc-go v9 replaced the `DenomTrace` query with the `Denom` query of
+/// `ibc.applications.transfer.v2`, so the latter is used if the chain does not
+/// implement the former.
+#[derive(Debug, Clone)]
+pub struct GrpcDenomResolver {
+    grpc_urls: BTreeMap<ChainId, (String, GrpcAuth)>,
+}
+
+impl GrpcDenomResolver {
+    pub fn new(grpc_urls: impl IntoIterator<Item = (ChainId, String, GrpcAuth)>) -> Self {
+        Self {
+            grpc_urls: grpc_urls
+                .into_iter()
+                .map(|(chain_id, grpc_url, auth)| (chain_id, (grpc_url, auth)))
+                .collect(),
+        }
+    }
+
+    async fn denom_trace(
+        grpc_url: &str,
+        auth: &GrpcAuth,
+        hash: H256,
+    ) -> Result<Option<DenomTrace>, tonic::Status> {
+        use protos::ibc::applications::transfer::v1::{
+            query_client::QueryClient, QueryDenomTraceRequest,
+        };
+
+        let res = QueryClient::new(
+            auth.connect(grpc_url)
+                .await
+                .map_err(|err| tonic::Status::unavailable(err.to_string()))?,
+        )
+        .denom_trace(QueryDenomTraceRequest {
+            hash: hex::encode_upper(hash.get()),
+        })
+        .await?;
+
+        Ok(res.into_inner().denom_trace.map(|trace| DenomTrace {
+            trace_path: trace.path,
+            base_denom: trace.base_denom,
+        }))
+    }
+
+    async fn denom(
+        grpc_url: &str,
+        auth: &GrpcAuth,
+        hash: H256,
+    ) -> Result<Option<DenomTrace>, tonic::Status> {
+        let channel = auth
+            .connect(grpc_url)
+            .await
+            .map_err(|err| tonic::Status::unavailable(err.to_string()))?;
+
+        let mut client = tonic::client::Grpc::new(channel);
+
+        client
+            .ready()
+            .await
+            .map_err(|err| tonic::Status::unavailable(err.to_string()))?;
+
+        let res = client
+            .unary::<_, transfer_v2::QueryDenomResponse, _>(
+                tonic::Request::new(transfer_v2::QueryDenomRequest {
+                    hash: hex::encode_upper(hash.get()),
+                }),
+                PathAndQuery::from_static("/ibc.applications.transfer.v2.Query/Denom"),
+                tonic::codec::ProstCodec::default(),
+            )
+            .await?;
+
+        Ok(res.into_inner().denom.map(|denom| DenomTrace {
+            trace_path: denom
+                .trace
+                .into_iter()
+                .map(|hop| format!("{}/{}", hop.port_id, hop.channel_id))
+                .collect::<Vec<_>>()
+                .join("/"),
+            base_denom: denom.base,
+        }))
+    }
+}
+
+impl DenomResolver for GrpcDenomResolver {
+    fn resolve<'a>(
+        &'a self,
+        chain_id: &'a ChainId,
+        hash: H256,
+    ) -> BoxFuture<'a, Result<Option<DenomTrace>, BoxDynError>> {
+        Box::pin(async move {
+            let (grpc_url, auth) = self
+                .grpc_urls
+                .get(chain_id)
+                .ok_or_else(|| format!("no grpc endpoint configured for chain {chain_id}"))?;
+
+            let res = match Self::denom_trace(grpc_url, auth, hash).await {
+                Err(status) if status.code() == Code::Unimplemented => {
+                    debug!(%chain_id, "DenomTrace query is not implemented, using Denom");
+
+                    Self::denom(grpc_url, auth, hash).await
+                }
+                res => res,
+            };
+
+            match res {
+                Ok(trace) => Ok(trace),
+                Err(status) if status.code() == Code::NotFound => Ok(None),
+                Err(status) => Err(format!(
+                    "error querying denom {hash} on {chain_id}: {}",
+                    status.message()
+                )
+                .into()),
+            }
+        })
+    }
+}
+
+/// `ibc.applications.transfer.v2` queries, which are not included in the
+/// generated protos.
+mod transfer_v2 {
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct QueryDenomRequest {
+        #[prost(string, tag = "1")]
+        pub hash: String,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct QueryDenomResponse {
+        #[prost(message, optional, tag = "1")]
+        pub denom: Option<Denom>,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct Denom {
+        #[prost(string, tag = "1")]
+        pub base: String,
+        #[prost(message, repeated, tag = "3")]
+        pub trace: Vec<Hop>,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct Hop {
+        #[prost(string, tag = "1")]
+        pub port_id: String,
+        #[prost(string, tag = "2")]
+        pub channel_id: String,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    use super::*;
+
+    const BOUNDS: BoundsConfig = BoundsConfig {
+        max_entries: 100,
+        ttl_seconds: None,
+    };
+
+    fn trace(trace_path: &str, base_denom: &str) -> DenomTrace {
+        DenomTrace {
+            trace_path: trace_path.to_owned(),
+            base_denom: base_denom.to_owned(),
+        }
+    }
+
+    // https://github.com/cosmos/ibc-go/blob/main/modules/apps/transfer/types/trace_test.go
+    #[test]
+    fn parse() {
+        for (full_denom, expected) in [
+            ("", trace("", "")),
+            ("uatom", trace("", "uatom")),
+            ("uatom/", trace("", "uatom/")),
+            ("gamm/pool/1", trace("", "gamm/pool/1")),
+            ("gamm//pool//1", trace("", "gamm//pool//1")),
+            (
+                "transfer/channel-1/uatom",
+                trace("transfer/channel-1", "uatom"),
+            ),
+            (
+                "customtransfer/channel-1/uatom",
+                trace("customtransfer/channel-1", "uatom"),
+            ),
+            (
+                "transfer/channel-1/uatom/",
+                trace("transfer/channel-1", "uatom/"),
+            ),
+            (
+                "transfer/channel-1/erc20/0x85bcBCd7e79Ec36f4fBBDc54F90C643d921151AA",
+                trace(
+                    "transfer/channel-1",
+                    "erc20/0x85bcBCd7e79Ec36f4fBBDc54F90C643d921151AA",
+                ),
+            ),
+            (
+                "transfer/channel-1/gamm/pool/1",
+                trace("transfer/channel-1", "gamm/pool/1"),
+            ),
+            (
+                "transfer/channel-1/transfer/channel-2/uatom",
+                trace("transfer/channel-1/transfer/channel-2", "uatom"),
+            ),
+            ("transfer/uatom", trace("", "transfer/uatom")),
+            ("transfer//uatom", trace("", "transfer//uatom")),
+            (
+                "channel-1/transfer/uatom",
+                trace("", "channel-1/transfer/uatom"),
+            ),
+            ("uatom/transfer", trace("", "uatom/transfer")),
+            ("transfer/channel-1", trace("", "transfer/channel-1")),
+            ("transfer/channel-1/", trace("transfer/channel-1", "")),
+            (
+                "transfer/channel-1/transfer",
+                trace("transfer/channel-1", "transfer"),
+            ),
+            (
+                "transfer/channel-1/transfer/channel-2",
+                trace("transfer/channel-1/transfer/channel-2", ""),
+            ),
+        ] {
+            assert_eq!(DenomTrace::parse(full_denom), expected, "{full_denom}");
+        }
+    }
+
+    #[test]
+    fn ibc_denom() {
+        assert_eq!(trace("", "uatom").ibc_denom(), "uatom");
+        assert_eq!(
+            trace("transfer/channel-1", "uatom").ibc_denom(),
+            "ibc/C4CFF46FD6DE35CA4CF4CE031E643C8FDC9BA4B99AE598E9B0ED98FE3A2319F9"
+        );
+        // atom on osmosis
+        assert_eq!(
+            trace("transfer/channel-0", "uatom").ibc_denom(),
+            "ibc/27394FB092D2ECCD56123C74F36E4C1F926001CEADA9CA97EA622B25F41E5EB2"
+        );
+    }
+
+    #[test]
+    fn ibc_denom_hash_roundtrip() {
+        let trace = trace("transfer/channel-1", "uatom");
+
+        assert_eq!(ibc_denom_hash(&trace.ibc_denom()), Some(trace.hash()));
+        // lowercase hashes are accepted as well
+        assert_eq!(
+            ibc_denom_hash(&trace.ibc_denom().to_lowercase()),
+            Some(trace.hash())
+        );
+
+        assert_eq!(ibc_denom_hash("uatom"), None);
+        assert_eq!(ibc_denom_hash("ibc/not-a-hash"), None);
+    }
+
+    #[derive(Debug, Default)]
+    struct MockResolver {
+        traces: HashMap<H256, DenomTrace>,
+        calls: AtomicUsize,
+    }
+
+    impl DenomResolver for MockResolver {
+        fn resolve<'a>(
+            &'a self,
+            _: &'a ChainId,
+            hash: H256,
+        ) -> BoxFuture<'a, Result<Option<DenomTrace>, BoxDynError>> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+
+            Box::pin(async move { Ok(self.traces.get(&hash).cloned()) })
+        }
+    }
+
+    #[tokio::test]
+    async fn caching_resolver() {
+        let chain_id = ChainId::new("osmosis-1");
+        let known = trace("transfer/channel-0", "uatom");
+        let unknown = trace("transfer/channel-1", "uatom");
+
+        let resolver = CachingDenomResolver::new(
+            MockResolver {
+                traces: [(known.hash(), known.clone())].into_iter().collect(),
+                ..Default::default()
+            },
+            &BOUNDS,
+        );
+
+        for _ in 0..3 {
+            assert_eq!(
+                resolve_denom(&resolver, &chain_id, &known.ibc_denom())
+                    .await
+                    .unwrap(),
+                Some(known.clone())
+            );
+        }
+        assert_eq!(resolver.inner.calls.load(Ordering::SeqCst), 1);
+
+        // unknown denoms are queried again
+        for _ in 0..2 {
+            assert_eq!(
+                resolve_denom(&resolver, &chain_id, &unknown.ibc_denom())
+                    .await
+                    .unwrap(),
+                None
+            );
+        }
+        assert_eq!(resolver.inner.calls.load(Ordering::SeqCst), 3);
+
+        // the cache is per chain
+        resolve_denom(&resolver, &ChainId::new("cosmoshub-4"), &known.ibc_denom())
+            .await
+            .unwrap();
+        assert_eq!(resolver.inner.calls.load(Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test]
+    async fn caching_resolver_is_bounded() {
+        let traces = (0..10_000)
+            .map(|i| trace("transfer/channel-0", &format!("denom{i}")))
+            .collect::<Vec<_>>();
+
+        let resolver = CachingDenomResolver::new(
+            MockResolver {
+                traces: traces.iter().map(|t| (t.hash(), t.clone())).collect(),
+                ..Default::default()
+            },
+            &BOUNDS,
+        );
+
+        let chain_id = ChainId::new("osmosis-1");
+
+        for trace in &traces {
+            resolve_denom(&resolver, &chain_id, &trace.ibc_denom())
+                .await
+                .unwrap();
+        }
+        assert_eq!(resolver.cache.len(), BOUNDS.max_entries);
+
+        // the most recently resolved traces are still cached
+        let calls = resolver.inner.calls.load(Ordering::SeqCst);
+        resolve_denom(&resolver, &chain_id, &traces[9_999].ibc_denom())
+            .await
+            .unwrap();
+        assert_eq!(resolver.inner.calls.load(Ordering::SeqCst), calls);
+
+        // the oldest were evicted
+        resolve_denom(&resolver, &chain_id, &traces[0].ibc_denom())
+            .await
+            .unwrap();
+        assert_eq!(resolver.inner.calls.load(Ordering::SeqCst), calls + 1);
+    }
+
+    #[tokio::test]
+    async fn full_paths_are_not_queried() {
+        let resolver = MockResolver::default();
+
+        assert_eq!(
+            resolve_denom(
+                &resolver,
+                &ChainId::new("osmosis-1"),
+                "transfer/channel-0/uatom"
+            )
+            .await
+            .unwrap(),
+            Some(trace("transfer/channel-0", "uatom"))
+        );
+        assert_eq!(resolver.calls.load(Ordering::SeqCst), 0);
+    }
+}
@@ -0,0 +1,347 @@
+//! Encoding of client states based on the [`ClientInfo`] of the client they
+//! are for.
+//!
+//! Client modules are registered per `(client_type, ibc_interface)`, and are
+//! responsible for the actual encoding. This module resolves the
+//! [`SupportedEncoding`] for a client and threads the required metadata (the
+//! 08-wasm checksum) through to the client module, so that misconfigured
+//! clients are caught before any client module is called.
+//!
+//! Before an 08-wasm client is created, [`verify_wasm_checksum`] checks that
+//! the checksum is actually stored on the host chain, since creating a client
+//! with an unknown checksum only fails once the transaction is executed.
+
+use std::str::FromStr;
+
+use jsonrpsee::{
+    core::RpcResult,
+    types::{ErrorObject, ErrorObjectOwned},
+};
+use prost::{Message, Name};
+use protos::{
+    cosmos::base::query::v1beta1::PageRequest,
+    ibc::lightclients::wasm::v1::{
+        query_client::QueryClient, ClientState as WasmClientState, QueryChecksumsRequest,
+    },
+};
+use serde_json::{json, Value};
+use unionlabs::{
+    bytes::Bytes,
+    hash::{hash_v2::HexUnprefixed, H256},
+    ErrorReporter,
+};
+use voyager_core::{encoding_for, ClientInfo, SupportedEncoding};
+
+use crate::{
+    module::ClientModuleClient, rpc::json_rpc_error_to_error_object, FATAL_JSONRPC_ERROR_CODE,
+};
+
+/// Encode the client state, provided as JSON, for the client described by
+/// `client_info`.
+pub async fn encode_client_state(
+    client_module: &(impl ClientModuleClient + Sync),
+    client_info: &ClientInfo,
+    client_state: Value,
+) -> RpcResult<Bytes> {
+    let encoding = resolve_encoding(client_info)?;
+
+    client_module
+        .encode_client_state(client_state, encoding.client_state_metadata(client_info))
+        .await
+        .map_err(json_rpc_error_to_error_object)
+}
+
+/// Encode the consensus state, provided as JSON, for the client described by
+/// `client_info`.
+pub async fn encode_consensus_state(
+    client_module: &(impl ClientModuleClient + Sync),
+    client_info: &ClientInfo,
+    consensus_state: Value,
+) -> RpcResult<Bytes> {
+    resolve_encoding(client_info)?;
+
+    client_module
+        .encode_consensus_state(consensus_state)
+        .await
+        .map_err(json_rpc_error_to_error_object)
+}
+
+/// Encode the header, provided as JSON, for the client described by
+/// `client_info`.
+pub async fn encode_header(
+    client_module: &(impl ClientModuleClient + Sync),
+    client_info: &ClientInfo,
+    header: Value,
+) -> RpcResult<Bytes> {
+    resolve_encoding(client_info)?;
+
+    client_module
+        .encode_header(header)
+        .await
+        .map_err(json_rpc_error_to_error_object)
+}
+
+/// A source of the 08-wasm checksums stored on a chain.
+#[allow(async_fn_in_trait)]
+pub trait WasmChecksums {
+    async fn stored_checksums(&self) -> RpcResult<Vec<H256>>;
+}
+
+/// Query the stored checksums with `ibc.lightclients.wasm.v1.Query/Checksums`.
+#[derive(Debug, Clone)]
+pub struct GrpcWasmChecksums {
+    pub grpc_url: String,
+}
+
+impl WasmChecksums for GrpcWasmChecksums {
+    async fn stored_checksums(&self) -> RpcResult<Vec<H256>> {
+        let mut client = QueryClient::connect(self.grpc_url.clone())
+            .await
+            .map_err(|err| self.error("error connecting to grpc server", err))?;
+
+        let mut checksums = vec![];
+        let mut next_key = vec![];
+
+        loop {
+            let response = client
+                .checksums(QueryChecksumsRequest {
+                    pagination: Some(PageRequest {
+                        key: next_key,
+                        ..Default::default()
+                    }),
+                })
+                .await
+                .map_err(|err| self.error("error querying wasm checksums", err))?
+                .into_inner();
+
+            for checksum in response.checksums {
+                checksums.push(
+                    H256::<HexUnprefixed>::from_str(&checksum)
+                        .map_err(|err| self.error("invalid wasm checksum", err))?
+                        .into_encoding(),
+                );
+            }
+
+            match response.pagination {
+                Some(pagination) if !pagination.next_key.is_empty() => {
+                    next_key = pagination.next_key;
+                }
+                _ => break,
+            }
+        }
+
+        Ok(checksums)
+    }
+}
+
+impl GrpcWasmChecksums {
+    fn error(&self, message: &str, err: impl std::error::Error) -> ErrorObjectOwned {
+        ErrorObject::owned(
+            -1,
+            format!("{message}: {}", ErrorReporter(err)),
+            Some(json!({ "grpc_url": self.grpc_url })),
+        )
+    }
+}
+
+/// Verify that the 08-wasm checksum of the client described by `client_info`
+/// is stored on the host chain, and that it is the checksum embedded in the
+/// encoded `client_state`. This is a no-op for all other encodings.
+///
+/// Both failures are fatal errors, since retrying will never succeed.
+pub async fn verify_wasm_checksum(
+    checksums: &impl WasmChecksums,
+    client_info: &ClientInfo,
+    client_state: &[u8],
+) -> RpcResult<()> {
+    let SupportedEncoding::Wasm { checksum } = resolve_encoding(client_info)? else {
+        return Ok(());
+    };
+
+    let embedded = embedded_wasm_checksum(client_state)?;
+
+    if embedded != checksum {
+        return Err(ErrorObject::owned(
+            FATAL_JSONRPC_ERROR_CODE,
+            format!(
+                "the checksum embedded in the client state ({embedded}) does not match \
+                the checksum in the client metadata ({checksum})"
+            ),
+            Some(json!({ "client_info": client_info })),
+        ));
+    }
+
+    let stored = checksums.stored_checksums().await?;
+
+    if !stored.contains(&checksum) {
+        return Err(ErrorObject::owned(
+            FATAL_JSONRPC_ERROR_CODE,
+            format!(
+                "checksum {checksum} is not stored on the host chain, available checksums are [{}]",
+                stored
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Some(json!({ "client_info": client_info, "available": stored })),
+        ));
+    }
+
+    Ok(())
+}
+
+/// The checksum of an `Any<ibc.lightclients.wasm.v1.ClientState>`.
+fn embedded_wasm_checksum(client_state: &[u8]) -> RpcResult<H256> {
+    let invalid = |message: String| {
+        ErrorObject::owned(
+            FATAL_JSONRPC_ERROR_CODE,
+            message,
+            Some(json!({ "client_state": Bytes::from(client_state.to_vec()) })),
+        )
+    };
+
+    let any = protos::google::protobuf::Any::decode(client_state)
+        .map_err(|err| invalid(format!("invalid client state: {}", ErrorReporter(err))))?;
+
+    if any.type_url != WasmClientState::type_url() {
+        return Err(invalid(format!(
+            "expected a client state of type {}, found {}",
+            WasmClientState::type_url(),
+            any.type_url
+        )));
+    }
+
+    WasmClientState::decode(&*any.value)
+        .map_err(|err| invalid(format!("invalid wasm client state: {}", ErrorReporter(err))))?
+        .checksum
+        .try_into()
+        .map_err(|err| invalid(format!("invalid wasm checksum: {}", ErrorReporter(err))))
+}
+
+/// Resolve the [`SupportedEncoding`] for `client_info`, as per
+/// [`encoding_for`].
+///
+/// Misconfigured clients are fatal errors, since retrying will never succeed.
+pub fn resolve_encoding(client_info: &ClientInfo) -> RpcResult<SupportedEncoding> {
+    encoding_for(client_info).map_err(|err| {
+        ErrorObject::owned(
+            FATAL_JSONRPC_ERROR_CODE,
+            ErrorReporter(&err).to_string(),
+            Some(json!({ "client_info": client_info })),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use voyager_core::{ClientType, IbcInterface};
+
+    use super::*;
+    use crate::error::VoyagerError;
+
+    #[test]
+    fn missing_wasm_checksum_is_fatal() {
+        let err = resolve_encoding(&ClientInfo {
+            client_type: ClientType::new_static(ClientType::COMETBLS),
+            ibc_interface: IbcInterface::new_static(IbcInterface::IBC_GO_V8_08_WASM),
+            metadata: Value::Null,
+        })
+        .unwrap_err();
+
+        assert!(VoyagerError::from_error_object(&err).is_fatal());
+        assert!(err.message().contains("no checksum was provided"));
+    }
+
+    struct MockChecksums(Vec<H256>);
+
+    impl WasmChecksums for MockChecksums {
+        async fn stored_checksums(&self) -> RpcResult<Vec<H256>> {
+            Ok(self.0.clone())
+        }
+    }
+
+    fn wasm_client_info(checksum: H256) -> ClientInfo {
+        ClientInfo {
+            client_type: ClientType::new_static(ClientType::COMETBLS),
+            ibc_interface: IbcInterface::new_static(IbcInterface::IBC_GO_V8_08_WASM),
+            metadata: json!({ "checksum": checksum }),
+        }
+    }
+
+    fn wasm_client_state(checksum: H256) -> Vec<u8> {
+        protos::google::protobuf::Any {
+            type_url: WasmClientState::type_url(),
+            value: WasmClientState {
+                data: b"client state".to_vec(),
+                checksum: checksum.get().to_vec(),
+                latest_height: None,
+            }
+            .encode_to_vec()
+            .into(),
+        }
+        .encode_to_vec()
+    }
+
+    #[tokio::test]
+    async fn wasm_checksum_present() {
+        let checksum = H256::new([1; 32]);
+
+        verify_wasm_checksum(
+            &MockChecksums(vec![H256::new([0; 32]), checksum]),
+            &wasm_client_info(checksum),
+            &wasm_client_state(checksum),
+        )
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn wasm_checksum_absent() {
+        let checksum = H256::new([1; 32]);
+        let available = H256::new([2; 32]);
+
+        let err = verify_wasm_checksum(
+            &MockChecksums(vec![available]),
+            &wasm_client_info(checksum),
+            &wasm_client_state(checksum),
+        )
+        .await
+        .unwrap_err();
+
+        assert!(VoyagerError::from_error_object(&err).is_fatal());
+        assert!(err.message().contains("is not stored on the host chain"));
+        assert!(err.message().contains(&available.to_string()));
+    }
+
+    #[tokio::test]
+    async fn wasm_checksum_mismatch() {
+        let checksum = H256::new([1; 32]);
+
+        let err = verify_wasm_checksum(
+            &MockChecksums(vec![checksum]),
+            &wasm_client_info(checksum),
+            &wasm_client_state(H256::new([2; 32])),
+        )
+        .await
+        .unwrap_err();
+
+        assert!(VoyagerError::from_error_object(&err).is_fatal());
+        assert!(err.message().contains("does not match"));
+    }
+
+    #[tokio::test]
+    async fn non_wasm_clients_are_not_verified() {
+        verify_wasm_checksum(
+            &MockChecksums(vec![]),
+            &ClientInfo {
+                client_type: ClientType::new_static(ClientType::TENDERMINT),
+                ibc_interface: IbcInterface::new_static(IbcInterface::IBC_GO_V8_NATIVE),
+                metadata: Value::Null,
+            },
+            b"not a wasm client state",
+        )
+        .await
+        .unwrap();
+    }
+}
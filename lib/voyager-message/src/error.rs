@@ -0,0 +1,453 @@
+//! Typed classification of errors returned by plugins and modules.
+//!
+//! Plugins and modules communicate with voyager over JSON-RPC, so the only
+//! thing the coordinator sees of an error is an [`ErrorObject`]. Historically
+//! the classification of an error was encoded purely in the error code (see
+//! [`FATAL_JSONRPC_ERROR_CODE`]), which only allows for a binary fatal/retry
+//! decision. [`VoyagerError`] is the structured form of this classification,
+//! and is carried in the `data` field of the error object so that it survives
+//! the round trip through JSON-RPC.
+
+use std::time::Duration;
+
+use chain_utils::{
+    light_block::LightBlockError, timeout::TimedOut, upgrade_plan::UpgradePlanError,
+};
+use ibc_solidity::Connection;
+use ibc_union_spec::{ChannelConnection, InvalidUnionId, UnionId};
+use jsonrpsee::types::{
+    error::{INVALID_PARAMS_CODE, METHOD_NOT_FOUND_CODE, PARSE_ERROR_CODE},
+    ErrorObject, ErrorObjectOwned,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use unionlabs::{ibc::core::client::height::Height, ErrorReporter};
+use voyager_vm::QueueError;
+
+use crate::FATAL_JSONRPC_ERROR_CODE;
+
+/// The error code used for all non-fatal [`VoyagerError`]s.
+pub const RETRYABLE_JSONRPC_ERROR_CODE: i32 = -1;
+
+/// How long to wait before retrying a message that failed with
+/// [`VoyagerError::RateLimited`].
+pub const RATE_LIMITED_RETRY_DELAY: Duration = Duration::from_secs(10);
+
+/// The key in the [`ErrorObject`] data field that the [`VoyagerError`] is
+/// stored under.
+const VOYAGER_ERROR_DATA_KEY: &str = "voyager_error";
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error, Serialize, Deserialize)]
+#[serde(
+    tag = "@type",
+    content = "@value",
+    rename_all = "snake_case",
+    deny_unknown_fields
+)]
+pub enum VoyagerError {
+    /// The message can never succeed, and must not be retried.
+    #[error("{reason}")]
+    Fatal {
+        reason: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        data: Option<Value>,
+    },
+    /// The message failed due to a transient error and can be retried,
+    /// optionally after the specified delay.
+    #[error("{reason}")]
+    Retryable {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        after: Option<Duration>,
+        reason: String,
+    },
+    /// Some required state (connection/channel end, packet commitment, ..) was
+    /// not found.
+    #[error("missing state at {path}")]
+    MissingState { path: String },
+    /// The state was requested at a height that has been pruned by the node,
+    /// and will never be available from it again.
+    #[error("height {height} has been pruned")]
+    Pruned { height: Height },
+    /// The upstream rpc rate limited the request.
+    #[error("rate limited")]
+    RateLimited,
+}
+
+impl VoyagerError {
+    pub fn fatal(reason: impl Into<String>) -> Self {
+        Self::Fatal {
+            reason: reason.into(),
+            data: None,
+        }
+    }
+
+    pub fn fatal_with_data(reason: impl Into<String>, data: Value) -> Self {
+        Self::Fatal {
+            reason: reason.into(),
+            data: Some(data),
+        }
+    }
+
+    pub fn retryable(reason: impl Into<String>) -> Self {
+        Self::Retryable {
+            after: None,
+            reason: reason.into(),
+        }
+    }
+
+    pub fn retry_after(after: Duration, reason: impl Into<String>) -> Self {
+        Self::Retryable {
+            after: Some(after),
+            reason: reason.into(),
+        }
+    }
+
+    pub fn missing_state(path: impl Into<String>) -> Self {
+        Self::MissingState { path: path.into() }
+    }
+
+    pub fn pruned(height: Height) -> Self {
+        Self::Pruned { height }
+    }
+
+    /// Whether this error is not retryable.
+    pub fn is_fatal(&self) -> bool {
+        matches!(
+            self,
+            Self::Fatal { .. } | Self::MissingState { .. } | Self::Pruned { .. }
+        )
+    }
+
+    /// The JSON-RPC error code for this error.
+    ///
+    /// Fatal errors use [`FATAL_JSONRPC_ERROR_CODE`] so that they are still
+    /// classified correctly by consumers that only inspect the error code.
+    pub fn code(&self) -> i32 {
+        if self.is_fatal() {
+            FATAL_JSONRPC_ERROR_CODE
+        } else {
+            RETRYABLE_JSONRPC_ERROR_CODE
+        }
+    }
+
+    /// The delay to apply before retrying the message, if any.
+    pub fn retry_delay(&self) -> Option<Duration> {
+        match self {
+            Self::Retryable { after, .. } => *after,
+            Self::RateLimited => Some(RATE_LIMITED_RETRY_DELAY),
+            Self::Fatal { .. } | Self::MissingState { .. } | Self::Pruned { .. } => None,
+        }
+    }
+
+    /// Decode the classification of an [`ErrorObject`].
+    ///
+    /// If the error object was produced from a [`VoyagerError`], the original
+    /// error is returned. Otherwise, the error is classified based on its error
+    /// code:
+    ///
+    /// - [`FATAL_JSONRPC_ERROR_CODE`]: Custom error code that can be returned
+    ///   by plugin and modules to denote that a fatal error has occurred, and
+    ///   this message is not retryable.
+    /// - [`METHOD_NOT_FOUND_CODE`]: The plugin or module does not expose the
+    ///   method that was attempted to be called. This indicates a bug in the
+    ///   plugin or module.
+    /// - [`PARSE_ERROR_CODE`] or [`INVALID_PARAMS_CODE`]: The custom message
+    ///   sent to the plugin or module could not be deserialized. This could
+    ///   either be due a bug in the plugin or module (JSON serialization not
+    ///   roundtripping correctly) or a message that was manually inserted into
+    ///   the queue via `/enqueue`.
+    ///
+    /// All other errors are treated as retryable.
+    pub fn from_error_object(error: &ErrorObject<'_>) -> Self {
+        let data = error
+            .data()
+            .and_then(|data| serde_json::from_str::<Value>(data.get()).ok());
+
+        if let Some(voyager_error) = data
+            .as_ref()
+            .and_then(|data| data.get(VOYAGER_ERROR_DATA_KEY))
+            .and_then(|e| Self::deserialize(e).ok())
+        {
+            return voyager_error;
+        }
+
+        if error.code() == FATAL_JSONRPC_ERROR_CODE
+            || error.code() == METHOD_NOT_FOUND_CODE
+            || error.code() == INVALID_PARAMS_CODE
+            || error.code() == PARSE_ERROR_CODE
+        {
+            Self::Fatal {
+                reason: error.message().to_owned(),
+                data,
+            }
+        } else {
+            Self::retryable(error.message())
+        }
+    }
+
+    /// Convert this error into a [`QueueError`], with `source` as the
+    /// underlying error.
+    pub fn into_queue_error(
+        self,
+        source: impl std::error::Error + Send + Sync + 'static,
+    ) -> QueueError {
+        if self.is_fatal() {
+            QueueError::Fatal(Box::new(source))
+        } else if let Some(after) = self.retry_delay() {
+            QueueError::RetryAfter {
+                after,
+                error: Box::new(source),
+            }
+        } else {
+            QueueError::Retry(Box::new(source))
+        }
+    }
+}
+
+impl From<VoyagerError> for ErrorObjectOwned {
+    fn from(value: VoyagerError) -> Self {
+        ErrorObject::owned(
+            value.code(),
+            value.to_string(),
+            Some(json!({ VOYAGER_ERROR_DATA_KEY: value })),
+        )
+    }
+}
+
+/// A call to an endpoint that timed out is transient, and can be retried.
+impl From<TimedOut> for VoyagerError {
+    fn from(value: TimedOut) -> Self {
+        Self::retryable(value.to_string())
+    }
+}
+
+/// A light block that could not be fetched can be retried, but one that is inconsistent with its
+/// header will not become consistent on retry.
+impl From<LightBlockError> for VoyagerError {
+    fn from(value: LightBlockError) -> Self {
+        match value {
+            LightBlockError::Rpc(_) | LightBlockError::IncompleteValidatorSet { .. } => {
+                Self::retryable(ErrorReporter(value).to_string())
+            }
+            LightBlockError::MissingProposer { .. }
+            | LightBlockError::ValidatorsHashMismatch(_)
+            | LightBlockError::NonBn254Validator { .. } => {
+                Self::fatal(ErrorReporter(value).to_string())
+            }
+        }
+    }
+}
+
+impl From<UpgradePlanError> for VoyagerError {
+    fn from(value: UpgradePlanError) -> Self {
+        match value {
+            UpgradePlanError::Rpc(_) => Self::retryable(ErrorReporter(value).to_string()),
+            UpgradePlanError::Query { .. }
+            | UpgradePlanError::Decode(_)
+            | UpgradePlanError::InvalidHeight { .. } => {
+                Self::fatal(ErrorReporter(value).to_string())
+            }
+        }
+    }
+}
+
+/// An ibc-union id read from chain that is not a valid
+/// [`UnionId`](ibc_union_spec::UnionId) will never become valid.
+impl From<InvalidUnionId> for VoyagerError {
+    fn from(value: InvalidUnionId) -> Self {
+        Self::fatal(value.to_string())
+    }
+}
+
+/// Check an ibc-union id read from chain, which is never `0` for an existing client, connection
+/// or channel.
+pub fn union_id(id: u32) -> Result<UnionId, VoyagerError> {
+    UnionId::try_from(id).map_err(Into::into)
+}
+
+/// Check the ids of a connection read from chain that a channel handshake event is emitted on.
+pub fn channel_connection(connection: Connection) -> Result<ChannelConnection, VoyagerError> {
+    ChannelConnection::try_from(connection).map_err(Into::into)
+}
+
+impl From<ErrorObject<'_>> for VoyagerError {
+    fn from(value: ErrorObject<'_>) -> Self {
+        Self::from_error_object(&value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[track_caller]
+    fn assert_round_trip(error: VoyagerError) {
+        let error_object = ErrorObjectOwned::from(error.clone());
+
+        assert_eq!(error_object.code(), error.code());
+        assert_eq!(error_object.message(), error.to_string());
+        assert_eq!(VoyagerError::from_error_object(&error_object), error);
+
+        // ensure the classification also survives serialization to the wire
+        let json = serde_json::to_string(&error_object).expect("serialization is infallible; qed;");
+        let error_object =
+            serde_json::from_str::<ErrorObject<'_>>(&json).expect("error object roundtrips");
+
+        assert_eq!(VoyagerError::from_error_object(&error_object), error);
+    }
+
+    #[test]
+    fn round_trip_fatal() {
+        assert_round_trip(VoyagerError::fatal("bad"));
+        assert_round_trip(VoyagerError::fatal_with_data(
+            "bad",
+            json!({ "raw_state": "0x1234" }),
+        ));
+    }
+
+    #[test]
+    fn round_trip_retryable() {
+        assert_round_trip(VoyagerError::retryable("try again"));
+        assert_round_trip(VoyagerError::retry_after(
+            Duration::from_secs(12),
+            "try again later",
+        ));
+    }
+
+    #[test]
+    fn round_trip_missing_state() {
+        assert_round_trip(VoyagerError::missing_state("connections/1"));
+    }
+
+    #[test]
+    fn round_trip_pruned() {
+        assert_round_trip(VoyagerError::pruned(Height::new_with_revision(1, 100)));
+    }
+
+    #[test]
+    fn round_trip_rate_limited() {
+        assert_round_trip(VoyagerError::RateLimited);
+    }
+
+    #[test]
+    fn timed_out_is_retryable() {
+        let error = VoyagerError::from(TimedOut {
+            kind: chain_utils::timeout::CallKind::Proof,
+            elapsed: Duration::from_secs(20),
+        });
+
+        assert_eq!(
+            error,
+            VoyagerError::retryable("proof call timed out after 20s")
+        );
+        assert_round_trip(error);
+    }
+
+    #[test]
+    fn light_block_errors() {
+        assert!(
+            !VoyagerError::from(LightBlockError::IncompleteValidatorSet {
+                height: 10,
+                total: 150,
+                found: 100,
+            })
+            .is_fatal()
+        );
+
+        assert!(VoyagerError::from(LightBlockError::ValidatorsHashMismatch(
+            chain_utils::light_block::ValidatorsHashMismatch {
+                height: 10,
+                expected: Default::default(),
+                found: Default::default(),
+            }
+        ))
+        .is_fatal());
+    }
+
+    #[test]
+    fn upgrade_plan_errors() {
+        let error = VoyagerError::from(UpgradePlanError::InvalidHeight {
+            name: "v1.1.0".to_owned(),
+            height: -1,
+        });
+
+        assert!(error.is_fatal());
+        assert_eq!(
+            error,
+            VoyagerError::fatal("the upgrade plan `v1.1.0` has an invalid height -1")
+        );
+    }
+
+    #[test]
+    fn legacy_error_codes() {
+        assert_eq!(
+            VoyagerError::from_error_object(&ErrorObject::owned(
+                FATAL_JSONRPC_ERROR_CODE,
+                "fatal",
+                None::<()>
+            )),
+            VoyagerError::fatal("fatal")
+        );
+
+        assert_eq!(
+            VoyagerError::from_error_object(&ErrorObject::owned(
+                FATAL_JSONRPC_ERROR_CODE,
+                "fatal",
+                Some(json!({ "raw_state": 1 }))
+            )),
+            VoyagerError::fatal_with_data("fatal", json!({ "raw_state": 1 }))
+        );
+
+        for code in [METHOD_NOT_FOUND_CODE, INVALID_PARAMS_CODE, PARSE_ERROR_CODE] {
+            assert!(VoyagerError::from_error_object(&ErrorObject::owned(
+                code, "fatal", None::<()>
+            ))
+            .is_fatal());
+        }
+
+        assert_eq!(
+            VoyagerError::from_error_object(&ErrorObject::owned(-1, "retry", None::<()>)),
+            VoyagerError::retryable("retry")
+        );
+    }
+
+    #[test]
+    fn queue_error_classification() {
+        let source = || ErrorObject::owned(-1, "error", None::<()>);
+
+        assert!(matches!(
+            VoyagerError::fatal("fatal").into_queue_error(source()),
+            QueueError::Fatal(_)
+        ));
+        assert!(matches!(
+            VoyagerError::missing_state("channels/1").into_queue_error(source()),
+            QueueError::Fatal(_)
+        ));
+        assert!(matches!(
+            VoyagerError::retryable("retry").into_queue_error(source()),
+            QueueError::Retry(_)
+        ));
+        assert!(matches!(
+            VoyagerError::retry_after(Duration::from_secs(5), "retry").into_queue_error(source()),
+            QueueError::RetryAfter { after, .. } if after == Duration::from_secs(5)
+        ));
+        assert!(matches!(
+            VoyagerError::RateLimited.into_queue_error(source()),
+            QueueError::RetryAfter { after, .. } if after == RATE_LIMITED_RETRY_DELAY
+        ));
+    }
+
+    #[test]
+    fn null_union_id_is_fatal() {
+        assert_eq!(union_id(1), Ok(UnionId::new(1).unwrap()));
+        assert!(union_id(0).unwrap_err().is_fatal());
+
+        let connection = Connection {
+            state: ibc_solidity::ConnectionState::Open,
+            client_id: 1,
+            counterparty_client_id: 2,
+            counterparty_connection_id: 0,
+        };
+        assert!(channel_connection(connection).unwrap_err().is_fatal());
+    }
+}
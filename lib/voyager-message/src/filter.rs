@@ -11,6 +11,9 @@ use voyager_vm::{
 
 use crate::{module::PluginInfo, VoyagerMessage};
 
+/// The value an interest filter returns to observe a message, see [`PluginInfo::interest_filter`].
+pub const OBSERVE: &str = "observe";
+
 #[derive(Debug, Clone)]
 pub struct JaqInterestFilter {
     pub filters: Vec<(Filter, String)>,
@@ -31,6 +34,7 @@ pub fn make_filter(
     PluginInfo {
         name,
         interest_filter,
+        ..
     }: PluginInfo,
 ) -> anyhow::Result<(Filter, String)> {
     let mut ctx = ParseCtx::new(["PLUGIN_NAME".to_owned()].into());
@@ -80,15 +84,22 @@ impl InterestFilter<VoyagerMessage> for JaqInterestFilter {
     fn check_interest<'a>(&'a self, op: &Op<VoyagerMessage>) -> FilterResult<'a> {
         let msg_json = Val::from(serde_json::to_value(op.clone()).unwrap());
 
+        let mut result = FilterResult::NO_INTEREST;
+
+        // the first plugin to express interest claims the message, but all of the filters are run
+        // such that plugins observing it receive it regardless of their position
         for (filter, plugin_name) in &self.filters {
-            match run_filter(filter, plugin_name, msg_json.clone()) {
-                Ok(interest @ FilterResult::Interest(_)) => return interest,
-                Ok(FilterResult::NoInterest) => {}
-                Err(_) => {}
+            if let Ok(FilterResult {
+                interest,
+                observers,
+            }) = run_filter(filter, plugin_name, msg_json.clone())
+            {
+                result.interest = result.interest.or(interest);
+                result.observers.extend(observers);
             }
         }
 
-        FilterResult::NoInterest
+        result
     }
 }
 
@@ -137,7 +148,7 @@ pub fn run_filter<'a>(
 
         error!(
             additional_items = %tail,
-            "filter returned multiple values, only a single boolean value is valid"
+            "filter returned multiple values, only a single value is valid"
         );
         Err(())
     } else {
@@ -145,15 +156,23 @@ pub fn run_filter<'a>(
             Val::Bool(true) => {
                 trace!("interest");
 
-                Ok(FilterResult::Interest(plugin_name))
+                Ok(FilterResult::interest(plugin_name))
             }
             Val::Bool(false) => {
                 trace!("no interest");
 
-                Ok(FilterResult::NoInterest)
+                Ok(FilterResult::NO_INTEREST)
+            }
+            Val::Str(s) if *s == OBSERVE => {
+                trace!("observe");
+
+                Ok(FilterResult {
+                    interest: None,
+                    observers: vec![plugin_name],
+                })
             }
             _ => {
-                error!("filter returned a non-boolean value: {result:?}");
+                error!("filter returned a value that is neither a boolean nor \"{OBSERVE}\": {result:?}");
 
                 Err(())
             }
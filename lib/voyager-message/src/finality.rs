@@ -0,0 +1,577 @@
+//! Tracking of the finalized height of a chain.
+//!
+//! Every chain has its own notion of finality (commit canonicity for cometbft
+//! chains, beacon finality for ethereum, settlement lag for rollups, ...).
+//! [`FinalityTracker`] abstracts over this, such that consumers (timeout
+//! detection, [`WaitForHeight`] scheduling, provable height checks, ...) don't
+//! need to reimplement it for every chain.
+//!
+//! [`WaitForHeight`]: crate::call::WaitForHeight
+
+use std::{
+    collections::VecDeque,
+    future::Future,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use jsonrpsee::{
+    core::RpcResult,
+    types::{ErrorObject, ErrorObjectOwned},
+};
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+use unionlabs::{ibc::core::client::height::Height, ErrorReporter};
+
+/// The default amount of blocks that the block time is averaged over.
+pub const DEFAULT_BLOCK_TIME_WINDOW: usize = 32;
+
+pub trait FinalityTracker: Send + Sync {
+    /// The latest finalized height of the chain.
+    fn latest_finalized(&self) -> impl Future<Output = RpcResult<Height>> + Send;
+
+    /// Whether `height` has been finalized.
+    fn is_finalized(&self, height: Height) -> impl Future<Output = RpcResult<bool>> + Send {
+        async move { Ok(self.latest_finalized().await? >= height) }
+    }
+
+    /// Estimate how long it will take for `height` to be finalized, based on
+    /// the observed block time of the chain.
+    ///
+    /// Returns [`Duration::ZERO`] if `height` is already finalized, and
+    /// [`None`] if not enough blocks have been observed yet to make an
+    /// estimate.
+    fn estimate_finalization(
+        &self,
+        height: Height,
+    ) -> impl Future<Output = RpcResult<Option<Duration>>> + Send;
+}
+
+/// Estimates the block time of a chain by averaging over a sliding window of
+/// the most recently observed blocks.
+#[derive(Debug)]
+pub struct BlockTimeEstimator {
+    window: usize,
+    /// `(height, timestamp_nanos)`, ordered by height.
+    samples: Mutex<VecDeque<(u64, u64)>>,
+}
+
+impl BlockTimeEstimator {
+    /// Create a new estimator, averaging over the last `window` blocks.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `window` is less than 2, since at least two blocks are
+    /// required to calculate a block time.
+    pub fn new(window: usize) -> Self {
+        assert!(window >= 2, "block time window must be at least 2");
+
+        Self {
+            window,
+            samples: Mutex::new(VecDeque::with_capacity(window)),
+        }
+    }
+
+    /// Record an observed block. Blocks that are not newer than the latest
+    /// observed block are ignored.
+    pub fn observe(&self, height: u64, timestamp_nanos: u64) {
+        let mut samples = self.samples.lock().expect("poisoned");
+
+        if samples
+            .back()
+            .is_some_and(|(h, ts)| height <= *h || timestamp_nanos < *ts)
+        {
+            return;
+        }
+
+        samples.push_back((height, timestamp_nanos));
+
+        if samples.len() > self.window {
+            samples.pop_front();
+        }
+    }
+
+    /// The average block time over the current window, if at least two blocks
+    /// have been observed.
+    pub fn average_block_time(&self) -> Option<Duration> {
+        let samples = self.samples.lock().expect("poisoned");
+
+        let (first_height, first_timestamp) = samples.front()?;
+        let (last_height, last_timestamp) = samples.back()?;
+
+        let blocks = last_height.checked_sub(*first_height).filter(|b| *b > 0)?;
+
+        Some(Duration::from_nanos(
+            (last_timestamp - first_timestamp) / blocks,
+        ))
+    }
+
+    /// Estimate how long it will take for the chain to progress from `from`
+    /// to `to`.
+    pub fn estimate(&self, from: u64, to: u64) -> Option<Duration> {
+        if to <= from {
+            return Some(Duration::ZERO);
+        }
+
+        self.average_block_time()?
+            .checked_mul((to - from).try_into().ok()?)
+    }
+}
+
+/// A [`FinalityTracker`] for cometbft chains.
+///
+/// A block is considered finalized once its commit is canonical, i.e. if the
+/// commit for the latest block is not yet canonical, the previous block is the
+/// latest finalized block.
+#[derive(Debug, Clone)]
+pub struct CometbftFinalityTracker {
+    client: cometbft_rpc::Client,
+    revision: Arc<AtomicU64>,
+    latest_finalized: Arc<AtomicU64>,
+    estimator: Arc<BlockTimeEstimator>,
+}
+
+impl CometbftFinalityTracker {
+    pub fn new(client: cometbft_rpc::Client, revision: u64, block_time_window: usize) -> Self {
+        Self {
+            client,
+            revision: Arc::new(AtomicU64::new(revision)),
+            latest_finalized: Arc::new(AtomicU64::new(0)),
+            estimator: Arc::new(BlockTimeEstimator::new(block_time_window)),
+        }
+    }
+
+    /// Set the revision of the returned heights, i.e. after the chain id of the chain changed in
+    /// an upgrade. The finalized height is tracked anew, since the chain may have restarted from a
+    /// lower height.
+    pub fn set_revision(&self, revision: u64) {
+        self.revision.store(revision, Ordering::Relaxed);
+        self.latest_finalized.store(0, Ordering::Relaxed);
+    }
+}
+
+impl FinalityTracker for CometbftFinalityTracker {
+    async fn latest_finalized(&self) -> RpcResult<Height> {
+        let commit_response = self.client.commit(None).await.map_err(rpc_error)?;
+
+        let header = commit_response.signed_header.header;
+
+        let mut height = header
+            .height
+            .inner()
+            .try_into()
+            .expect("value is >= 0; qed;");
+
+        self.estimator.observe(height, header.time.as_unix_nanos());
+
+        if !commit_response.canonical {
+            debug!("commit is not canonical, latest finalized height is the previous block");
+            height -= 1;
+        }
+
+        debug!(height, "latest finalized height");
+
+        self.latest_finalized.fetch_max(height, Ordering::Relaxed);
+
+        Ok(Height::new_with_revision(
+            self.revision.load(Ordering::Relaxed),
+            height,
+        ))
+    }
+
+    async fn is_finalized(&self, height: Height) -> RpcResult<bool> {
+        // finalized heights can't become unfinalized, so there's no need to query the chain again
+        if height.height() <= self.latest_finalized.load(Ordering::Relaxed) {
+            return Ok(true);
+        }
+
+        Ok(self.latest_finalized().await? >= height)
+    }
+
+    async fn estimate_finalization(&self, height: Height) -> RpcResult<Option<Duration>> {
+        let latest_finalized = self.latest_finalized().await?;
+
+        Ok(self
+            .estimator
+            .estimate(latest_finalized.height(), height.height()))
+    }
+}
+
+/// The head of a chain, as returned by a [`HeadSource`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Head {
+    pub height: Height,
+    pub timestamp_nanos: u64,
+}
+
+/// A source for the latest (potentially unfinalized) block of a chain.
+pub trait HeadSource: Send + Sync {
+    fn head(&self) -> impl Future<Output = RpcResult<Head>> + Send;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FixedLagConfig {
+    /// The amount of blocks behind the head of the chain that a block is
+    /// considered finalized.
+    pub lag: u64,
+    /// The amount of blocks to average the block time over.
+    #[serde(default = "default_block_time_window")]
+    pub block_time_window: usize,
+}
+
+fn default_block_time_window() -> usize {
+    DEFAULT_BLOCK_TIME_WINDOW
+}
+
+/// A [`FinalityTracker`] for chains where a block is considered finalized
+/// once it is a fixed amount of blocks behind the head of the chain.
+#[derive(Debug)]
+pub struct FixedLagFinalityTracker<S> {
+    source: S,
+    lag: u64,
+    estimator: BlockTimeEstimator,
+}
+
+impl<S: HeadSource> FixedLagFinalityTracker<S> {
+    pub fn new(source: S, config: &FixedLagConfig) -> Self {
+        Self {
+            source,
+            lag: config.lag,
+            estimator: BlockTimeEstimator::new(config.block_time_window),
+        }
+    }
+}
+
+impl<S: HeadSource> FinalityTracker for FixedLagFinalityTracker<S> {
+    async fn latest_finalized(&self) -> RpcResult<Height> {
+        let head = self.source.head().await?;
+
+        self.estimator
+            .observe(head.height.height(), head.timestamp_nanos);
+
+        Ok(Height::new_with_revision(
+            head.height.revision(),
+            head.height.height().saturating_sub(self.lag),
+        ))
+    }
+
+    async fn estimate_finalization(&self, height: Height) -> RpcResult<Option<Duration>> {
+        let latest_finalized = self.latest_finalized().await?;
+
+        Ok(self
+            .estimator
+            .estimate(latest_finalized.height(), height.height()))
+    }
+}
+
+/// A [`FinalityTracker`] for rollups, where a block is considered finalized
+/// once it has been settled on the L1 (i.e. once the batch or assertion
+/// containing it has been finalized by the rollup contract).
+///
+/// Settlement is not queried by the tracker itself, but reported with
+/// [`Self::settle`] by whatever is watching the rollup contract. Until the
+/// first settlement is reported, no height is considered finalized.
+#[derive(Debug, Clone)]
+pub struct SettlementFinalityTracker {
+    revision: u64,
+    settled: Arc<AtomicU64>,
+    estimator: Arc<BlockTimeEstimator>,
+}
+
+impl SettlementFinalityTracker {
+    pub fn new(revision: u64, block_time_window: usize) -> Self {
+        Self {
+            revision,
+            settled: Arc::new(AtomicU64::new(0)),
+            estimator: Arc::new(BlockTimeEstimator::new(block_time_window)),
+        }
+    }
+
+    /// Record that all blocks up to and including `height` have been settled,
+    /// by an L1 block with the timestamp `l1_timestamp_nanos`.
+    ///
+    /// Returns `false` if `height` was already settled.
+    pub fn settle(&self, height: u64, l1_timestamp_nanos: u64) -> bool {
+        let previous = self.settled.fetch_max(height, Ordering::Relaxed);
+
+        if height <= previous {
+            return false;
+        }
+
+        // the estimate is of the rate that blocks are settled at, not of the
+        // block time of the rollup itself
+        self.estimator.observe(height, l1_timestamp_nanos);
+
+        true
+    }
+}
+
+impl FinalityTracker for SettlementFinalityTracker {
+    async fn latest_finalized(&self) -> RpcResult<Height> {
+        Ok(Height::new_with_revision(
+            self.revision,
+            self.settled.load(Ordering::Relaxed),
+        ))
+    }
+
+    async fn estimate_finalization(&self, height: Height) -> RpcResult<Option<Duration>> {
+        Ok(self
+            .estimator
+            .estimate(self.settled.load(Ordering::Relaxed), height.height()))
+    }
+}
+
+fn rpc_error(err: cometbft_rpc::JsonRpcError) -> ErrorObjectOwned {
+    ErrorObject::owned(
+        -1,
+        format!("error fetching latest commit: {}", ErrorReporter(err)),
+        None::<()>,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECOND: u64 = 1_000_000_000;
+
+    #[test]
+    fn estimator_requires_two_blocks() {
+        let estimator = BlockTimeEstimator::new(4);
+
+        assert_eq!(estimator.average_block_time(), None);
+        assert_eq!(estimator.estimate(1, 2), None);
+
+        estimator.observe(1, SECOND);
+
+        assert_eq!(estimator.average_block_time(), None);
+
+        estimator.observe(2, 3 * SECOND);
+
+        assert_eq!(estimator.average_block_time(), Some(Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn estimator_sliding_window() {
+        let estimator = BlockTimeEstimator::new(3);
+
+        // 10s block times
+        estimator.observe(1, 0);
+        estimator.observe(2, 10 * SECOND);
+        estimator.observe(3, 20 * SECOND);
+
+        assert_eq!(
+            estimator.average_block_time(),
+            Some(Duration::from_secs(10))
+        );
+
+        // the chain speeds up to 1s block times, the window only contains (3, 20), (4, 21), (5, 22)
+        estimator.observe(4, 21 * SECOND);
+        estimator.observe(5, 22 * SECOND);
+
+        assert_eq!(estimator.average_block_time(), Some(Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn estimator_skipped_blocks() {
+        let estimator = BlockTimeEstimator::new(3);
+
+        // blocks are not necessarily observed contiguously
+        estimator.observe(10, 0);
+        estimator.observe(20, 50 * SECOND);
+
+        assert_eq!(estimator.average_block_time(), Some(Duration::from_secs(5)));
+    }
+
+    #[test]
+    fn estimator_ignores_stale_blocks() {
+        let estimator = BlockTimeEstimator::new(3);
+
+        estimator.observe(1, 0);
+        estimator.observe(2, 2 * SECOND);
+        // already observed
+        estimator.observe(2, 2 * SECOND);
+        // older than the latest observed block
+        estimator.observe(1, 100 * SECOND);
+        // timestamp went backwards
+        estimator.observe(3, SECOND);
+
+        assert_eq!(estimator.average_block_time(), Some(Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn estimator_estimate() {
+        let estimator = BlockTimeEstimator::new(3);
+
+        estimator.observe(1, 0);
+        estimator.observe(2, 6 * SECOND);
+
+        assert_eq!(estimator.estimate(2, 2), Some(Duration::ZERO));
+        assert_eq!(estimator.estimate(3, 2), Some(Duration::ZERO));
+        assert_eq!(estimator.estimate(2, 5), Some(Duration::from_secs(18)));
+    }
+
+    struct MockHeadSource(Mutex<VecDeque<Head>>);
+
+    impl MockHeadSource {
+        fn new(heads: impl IntoIterator<Item = (u64, u64)>) -> Self {
+            Self(Mutex::new(
+                heads
+                    .into_iter()
+                    .map(|(height, timestamp)| Head {
+                        height: Height::new_with_revision(1, height),
+                        timestamp_nanos: timestamp * SECOND,
+                    })
+                    .collect(),
+            ))
+        }
+    }
+
+    impl HeadSource for MockHeadSource {
+        async fn head(&self) -> RpcResult<Head> {
+            Ok(self.0.lock().unwrap().pop_front().expect("no more heads"))
+        }
+    }
+
+    fn fixed_lag(
+        lag: u64,
+        heads: impl IntoIterator<Item = (u64, u64)>,
+    ) -> FixedLagFinalityTracker<MockHeadSource> {
+        FixedLagFinalityTracker::new(
+            MockHeadSource::new(heads),
+            &FixedLagConfig {
+                lag,
+                block_time_window: DEFAULT_BLOCK_TIME_WINDOW,
+            },
+        )
+    }
+
+    #[tokio::test]
+    async fn fixed_lag_latest_finalized() {
+        let tracker = fixed_lag(5, [(100, 0), (3, 0)]);
+
+        assert_eq!(
+            tracker.latest_finalized().await.unwrap(),
+            Height::new_with_revision(1, 95)
+        );
+
+        // saturates at 0
+        assert_eq!(
+            tracker.latest_finalized().await.unwrap(),
+            Height::new_with_revision(1, 0)
+        );
+    }
+
+    #[tokio::test]
+    async fn fixed_lag_is_finalized() {
+        let tracker = fixed_lag(5, [(100, 0), (100, 0)]);
+
+        assert!(tracker
+            .is_finalized(Height::new_with_revision(1, 95))
+            .await
+            .unwrap());
+        assert!(!tracker
+            .is_finalized(Height::new_with_revision(1, 96))
+            .await
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn fixed_lag_estimate_finalization() {
+        let tracker = fixed_lag(5, [(100, 0), (100, 0), (102, 4), (102, 4)]);
+
+        // only one block observed
+        assert_eq!(
+            tracker
+                .estimate_finalization(Height::new_with_revision(1, 100))
+                .await
+                .unwrap(),
+            None
+        );
+
+        // already finalized
+        assert_eq!(
+            tracker
+                .estimate_finalization(Height::new_with_revision(1, 90))
+                .await
+                .unwrap(),
+            Some(Duration::ZERO)
+        );
+
+        // 2s block time, 97 is finalized once the head is at 102
+        assert_eq!(
+            tracker
+                .estimate_finalization(Height::new_with_revision(1, 97))
+                .await
+                .unwrap(),
+            Some(Duration::ZERO)
+        );
+
+        // 100 is finalized once the head is at 105, 3 blocks after 102
+        assert_eq!(
+            tracker
+                .estimate_finalization(Height::new_with_revision(1, 100))
+                .await
+                .unwrap(),
+            Some(Duration::from_secs(6))
+        );
+    }
+
+    #[tokio::test]
+    async fn settlement_is_monotonic() {
+        let tracker = SettlementFinalityTracker::new(0, DEFAULT_BLOCK_TIME_WINDOW);
+
+        assert_eq!(tracker.latest_finalized().await.unwrap(), Height::new(0));
+        assert!(!tracker.is_finalized(Height::new(1)).await.unwrap());
+
+        assert!(tracker.settle(100, 0));
+
+        assert_eq!(tracker.latest_finalized().await.unwrap(), Height::new(100));
+        assert!(tracker.is_finalized(Height::new(100)).await.unwrap());
+        assert!(!tracker.is_finalized(Height::new(101)).await.unwrap());
+
+        // already settled, i.e. the same batch observed twice or an older batch observed late
+        assert!(!tracker.settle(100, 0));
+        assert!(!tracker.settle(50, 60 * SECOND));
+
+        assert_eq!(tracker.latest_finalized().await.unwrap(), Height::new(100));
+    }
+
+    #[tokio::test]
+    async fn settlement_estimate_finalization() {
+        let tracker = SettlementFinalityTracker::new(0, DEFAULT_BLOCK_TIME_WINDOW);
+
+        tracker.settle(100, 0);
+
+        // only one settlement observed
+        assert_eq!(
+            tracker
+                .estimate_finalization(Height::new(150))
+                .await
+                .unwrap(),
+            None
+        );
+
+        // 100 blocks are settled every 60s
+        tracker.settle(200, 60 * SECOND);
+
+        assert_eq!(
+            tracker
+                .estimate_finalization(Height::new(150))
+                .await
+                .unwrap(),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(
+            tracker
+                .estimate_finalization(Height::new(250))
+                .await
+                .unwrap(),
+            Some(Duration::from_secs(30))
+        );
+    }
+}
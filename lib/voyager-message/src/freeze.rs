@@ -0,0 +1,488 @@
+//! Propagation of client freezes to the relaying that depends on the frozen client.
+//!
+//! A client on chain A tracking chain B is frozen when misbehaviour of B is submitted to it. Until
+//! the freeze has been reviewed, relaying over any connection of the client is unsafe in both
+//! directions: the counterparty client of A on B (the *paired* client) verifies the datagrams that
+//! are relayed from A to B, and depending on which chain misbehaved, either client may be tracking
+//! a forked chain.
+//!
+//! [`propagate_freeze`] finds the paired clients through the connection ends on A that reference
+//! the frozen client, and the channels on both chains that are opened over those connections. The
+//! result is recorded in a [`FreezeList`] file, which is shared with the transaction plugins: a
+//! [`SuppressionList`] with a [`freeze_list`](SuppressionList::freeze_list) drops the datagrams for
+//! every frozen channel on its chain, in addition to its configured channels (see
+//! [`SuppressionList::with_frozen_channels`]). Relaying stays suppressed until it is cleared with
+//! the `unfreezeRelaying` rpc method.
+//!
+//! The freeze is observed by [`WatchClientFreeze`](crate::call::WatchClientFreeze), which polls
+//! the status of a client until it is frozen. Only ibc-union clients are supported.
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use ibc_union_spec::{ChannelPath, ConnectionPath, IbcUnion};
+use jsonrpsee::types::{error::INVALID_PARAMS_CODE, ErrorObject, ErrorObjectOwned};
+use macros::model;
+use unionlabs::{ibc::core::client::height::Height, ErrorReporter};
+use voyager_core::{ChainId, ClientStatus, IbcSpec, IbcSpecId};
+
+use crate::{
+    query::VoyagerQuery,
+    suppression::{SuppressedChannel, SuppressionList},
+    RawClientId,
+};
+
+/// The interval (in seconds) at which [`WatchClientFreeze`](crate::call::WatchClientFreeze) polls
+/// the status of a client.
+pub const WATCH_INTERVAL_SECONDS: u64 = 60;
+
+/// The maximum number of connections and channels that are scanned on the chain of a frozen
+/// client. ibc-union ids are assigned sequentially starting at 1, so the scan stops at the first id
+/// that does not exist.
+pub const MAX_SCANNED_IDS: u32 = 10_000;
+
+/// An ibc-union client on a chain.
+#[model]
+pub struct ClientRef {
+    pub chain_id: ChainId,
+    pub client_id: u32,
+}
+
+/// An ibc-union channel on a chain.
+#[model]
+pub struct ChannelRef {
+    pub chain_id: ChainId,
+    pub channel_id: u32,
+}
+
+/// A frozen client, and the relaying that is suppressed because of it.
+#[model]
+pub struct FrozenRelaying {
+    pub client: ClientRef,
+    /// The height of the chain of [`Self::client`] at which the client was found to be frozen.
+    pub frozen_at: Height,
+    /// The clients on the counterparty chain that are paired with the frozen client through a
+    /// connection.
+    pub paired_clients: Vec<ClientRef>,
+    /// The channels over the connections of the frozen client, on both chains.
+    pub channels: Vec<ChannelRef>,
+}
+
+/// The persisted set of frozen clients, shared between voyager and the transaction plugins.
+#[model]
+#[derive(Default)]
+pub struct FreezeList {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub frozen: Vec<FrozenRelaying>,
+}
+
+impl FreezeList {
+    /// Load the list from `path`. A missing file is treated as an empty list.
+    pub fn load(path: &Path) -> Result<Self, FreezeListError> {
+        match std::fs::read(path) {
+            Ok(bz) => serde_json::from_slice(&bz).map_err(|source| FreezeListError::Decode {
+                path: path.to_owned(),
+                source,
+            }),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(source) => Err(FreezeListError::Io {
+                path: path.to_owned(),
+                source,
+            }),
+        }
+    }
+
+    /// Atomically write the list to `path`.
+    pub fn store(&self, path: &Path) -> Result<(), FreezeListError> {
+        let io_err = |source| FreezeListError::Io {
+            path: path.to_owned(),
+            source,
+        };
+
+        let tmp_path = path.with_extension(format!("{}.tmp", std::process::id()));
+
+        std::fs::write(
+            &tmp_path,
+            serde_json::to_vec_pretty(self).expect("serialization is infallible; qed;"),
+        )
+        .map_err(io_err)?;
+
+        std::fs::rename(&tmp_path, path).map_err(io_err)
+    }
+
+    /// Add `frozen` to the list, replacing any previous entry for the same client.
+    pub fn insert(&mut self, frozen: FrozenRelaying) {
+        self.remove(&frozen.client);
+        self.frozen.push(frozen);
+    }
+
+    /// Remove the entry for `client`, if any.
+    pub fn remove(&mut self, client: &ClientRef) -> Option<FrozenRelaying> {
+        let idx = self
+            .frozen
+            .iter()
+            .position(|frozen| &frozen.client == client)?;
+
+        Some(self.frozen.remove(idx))
+    }
+
+    /// The channels on `chain_id` that are suppressed by any of the frozen clients.
+    pub fn suppressed_channels<'a>(
+        &'a self,
+        chain_id: &'a ChainId,
+    ) -> impl Iterator<Item = SuppressedChannel> + 'a {
+        self.frozen.iter().flat_map(move |frozen| {
+            frozen
+                .channels
+                .iter()
+                .filter(move |channel| &channel.chain_id == chain_id)
+                .map(|channel| SuppressedChannel {
+                    ibc_spec_id: IbcSpecId::new(IbcSpecId::UNION),
+                    channel_id: channel.channel_id,
+                    reason: Some(format!(
+                        "client {} on {} is frozen",
+                        frozen.client.client_id, frozen.client.chain_id
+                    )),
+                })
+        })
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum FreezeListError {
+    #[error("error accessing freeze list at {}", path.display())]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    #[error("invalid freeze list at {}", path.display())]
+    Decode {
+        path: PathBuf,
+        #[source]
+        source: serde_json::Error,
+    },
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum FreezeError {
+    #[error("client {} on {} is not frozen (status: {status:?})", client.client_id, client.chain_id)]
+    NotFrozen {
+        client: ClientRef,
+        status: ClientStatus,
+    },
+    #[error("client {} on {} has no frozen relaying", client.client_id, client.chain_id)]
+    NotInFreezeList { client: ClientRef },
+    #[error(transparent)]
+    FreezeList(#[from] FreezeListError),
+    #[error(transparent)]
+    Rpc(#[from] ErrorObjectOwned),
+}
+
+impl From<FreezeError> for ErrorObjectOwned {
+    fn from(value: FreezeError) -> Self {
+        match value {
+            FreezeError::Rpc(err) => err,
+            err => ErrorObject::owned(
+                INVALID_PARAMS_CODE,
+                ErrorReporter(err).to_string(),
+                None::<()>,
+            ),
+        }
+    }
+}
+
+/// Find the relaying that depends on the frozen `client`: the clients paired with it on the
+/// counterparty chain, and the channels over its connections on both chains.
+///
+/// The client must be frozen at the latest finalized height of its chain.
+pub async fn propagate_freeze(
+    client: &impl VoyagerQuery,
+    frozen: ClientRef,
+) -> Result<FrozenRelaying, FreezeError> {
+    let height = client.query_latest_height(&frozen.chain_id, true).await?;
+
+    let meta = client
+        .client_meta_raw(
+            &frozen.chain_id,
+            &IbcUnion::ID,
+            height.into(),
+            RawClientId::new(frozen.client_id),
+        )
+        .await?;
+
+    if meta.status != ClientStatus::Frozen {
+        return Err(FreezeError::NotFrozen {
+            client: frozen,
+            status: meta.status,
+        });
+    }
+
+    let counterparty_chain_id = meta.chain_id;
+
+    let mut connection_ids = vec![];
+    let mut paired_clients = vec![];
+
+    for connection_id in 1..=MAX_SCANNED_IDS {
+        let Some(connection) = client
+            .query_ibc_state(
+                &frozen.chain_id,
+                height.into(),
+                ConnectionPath { connection_id },
+            )
+            .await?
+            .state
+        else {
+            break;
+        };
+
+        if connection.client_id != frozen.client_id {
+            continue;
+        }
+
+        connection_ids.push(connection_id);
+
+        let paired = ClientRef {
+            chain_id: counterparty_chain_id.clone(),
+            client_id: connection.counterparty_client_id,
+        };
+
+        if !paired_clients.contains(&paired) {
+            paired_clients.push(paired);
+        }
+    }
+
+    let mut channels = vec![];
+
+    if !connection_ids.is_empty() {
+        for channel_id in 1..=MAX_SCANNED_IDS {
+            let Some(channel) = client
+                .query_ibc_state(&frozen.chain_id, height.into(), ChannelPath { channel_id })
+                .await?
+                .state
+            else {
+                break;
+            };
+
+            if !connection_ids.contains(&channel.connection_id) {
+                continue;
+            }
+
+            channels.push(ChannelRef {
+                chain_id: frozen.chain_id.clone(),
+                channel_id,
+            });
+
+            // the counterparty channel is not known until the handshake has progressed
+            if channel.counterparty_channel_id != 0 {
+                channels.push(ChannelRef {
+                    chain_id: counterparty_chain_id.clone(),
+                    channel_id: channel.counterparty_channel_id,
+                });
+            }
+        }
+    }
+
+    Ok(FrozenRelaying {
+        client: frozen,
+        frozen_at: height,
+        paired_clients,
+        channels,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use ibc_solidity::{Channel, ChannelState, Connection, ConnectionState};
+    use ibc_union_spec::{Datagram, MsgPacketRecv, UnionId};
+    use voyager_core::ClientStateMeta;
+
+    use super::*;
+    use crate::{data::IbcDatagram, into_value, testing::MockVoyager};
+
+    const CHAIN: &str = "union-devnet-1";
+    const COUNTERPARTY: &str = "32382";
+
+    /// The chain of the frozen client, with client 1 tracking the counterparty.
+    fn chain(status: ClientStatus) -> MockVoyager {
+        let chain_id = ChainId::new(CHAIN);
+
+        let connection = |client_id, counterparty_client_id| Connection {
+            state: ConnectionState::Open,
+            client_id,
+            counterparty_client_id,
+            counterparty_connection_id: 1,
+        };
+        let channel = |connection_id, counterparty_channel_id| Channel {
+            state: ChannelState::Open,
+            connection_id,
+            counterparty_channel_id,
+            counterparty_port_id: Default::default(),
+            version: "ucs03-zkgm-0".to_owned(),
+        };
+
+        let voyager = MockVoyager::new();
+
+        voyager
+            .set_latest_height(&chain_id, Height::new(100))
+            .set_client_meta::<IbcUnion>(
+                &chain_id,
+                UnionId::new(1).unwrap(),
+                ClientStateMeta {
+                    height: Height::new(90),
+                    chain_id: ChainId::new(COUNTERPARTY),
+                    status,
+                    resolved_at: None,
+                },
+            );
+
+        for (connection_id, connection) in [
+            (1, connection(1, 7)),
+            // a connection of another client
+            (2, connection(2, 8)),
+            // a second connection to the same counterparty client
+            (3, connection(1, 7)),
+        ] {
+            voyager.set_state(
+                &chain_id,
+                ConnectionPath { connection_id },
+                Some(connection),
+            );
+        }
+
+        for (channel_id, channel) in [
+            (1, channel(1, 10)),
+            (2, channel(2, 11)),
+            (3, channel(3, 12)),
+            // still in init, the counterparty channel doesn't exist yet
+            (4, channel(1, 0)),
+        ] {
+            voyager.set_state(&chain_id, ChannelPath { channel_id }, Some(channel));
+        }
+
+        voyager
+    }
+
+    fn client(chain_id: &str, client_id: u32) -> ClientRef {
+        ClientRef {
+            chain_id: ChainId::new(chain_id),
+            client_id,
+        }
+    }
+
+    fn channel(chain_id: &str, channel_id: u32) -> ChannelRef {
+        ChannelRef {
+            chain_id: ChainId::new(chain_id),
+            channel_id,
+        }
+    }
+
+    fn recv(destination_channel: u32) -> IbcDatagram {
+        IbcDatagram::new::<IbcUnion>(Datagram::from(MsgPacketRecv {
+            packets: vec![ibc_solidity::Packet {
+                source_channel: 1,
+                destination_channel,
+                data: Default::default(),
+                timeout_height: 0,
+                timeout_timestamp: 100,
+            }],
+            relayer_msgs: vec![Default::default()],
+            proof: Default::default(),
+            proof_height: 1,
+        }))
+    }
+
+    #[tokio::test]
+    async fn pairs_across_connections() {
+        let voyager = chain(ClientStatus::Frozen);
+
+        let frozen = propagate_freeze(&voyager, client(CHAIN, 1)).await.unwrap();
+
+        // the status is checked at the height the connections and channels are read at
+        assert_eq!(
+            voyager.calls("client_meta")[0]["height"],
+            into_value(Height::new(100))
+        );
+
+        assert_eq!(
+            frozen,
+            FrozenRelaying {
+                client: client(CHAIN, 1),
+                frozen_at: Height::new(100),
+                paired_clients: vec![client(COUNTERPARTY, 7)],
+                channels: vec![
+                    channel(CHAIN, 1),
+                    channel(COUNTERPARTY, 10),
+                    channel(CHAIN, 3),
+                    channel(COUNTERPARTY, 12),
+                    channel(CHAIN, 4),
+                ],
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn active_client_is_not_propagated() {
+        let err = propagate_freeze(&chain(ClientStatus::Active), client(CHAIN, 1))
+            .await
+            .unwrap_err();
+
+        assert!(
+            matches!(
+                err,
+                FreezeError::NotFrozen {
+                    status: ClientStatus::Active,
+                    ..
+                }
+            ),
+            "{err:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn frozen_channels_are_suppressed() {
+        let path = std::env::temp_dir().join(format!(
+            "voyager-message-freeze-list-{}.json",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+
+        let frozen = propagate_freeze(&chain(ClientStatus::Frozen), client(CHAIN, 1))
+            .await
+            .unwrap();
+
+        let mut list = FreezeList::default();
+        list.insert(frozen.clone());
+        list.store(&path).unwrap();
+
+        let suppression = SuppressionList {
+            channels: vec![],
+            freeze_list: Some(path.clone()),
+        };
+
+        // suppressed on both ends of the channel
+        let (submit, suppressed) = suppression
+            .with_frozen_channels(&ChainId::new(CHAIN))
+            .partition(&ChainId::new(CHAIN), vec![recv(1), recv(2)]);
+        assert_eq!(submit, [recv(2)]);
+        assert_eq!(suppressed.len(), 1);
+
+        let (submit, suppressed) = suppression
+            .with_frozen_channels(&ChainId::new(COUNTERPARTY))
+            .partition(&ChainId::new(COUNTERPARTY), vec![recv(10), recv(11)]);
+        assert_eq!(submit, [recv(11)]);
+        assert_eq!(suppressed[0].channel_id, 10);
+
+        // relaying resumes once the freeze is cleared
+        let mut list = FreezeList::load(&path).unwrap();
+        assert_eq!(list.remove(&client(CHAIN, 1)), Some(frozen));
+        list.store(&path).unwrap();
+
+        let (submit, _) = suppression
+            .with_frozen_channels(&ChainId::new(CHAIN))
+            .partition(&ChainId::new(CHAIN), vec![recv(1)]);
+        assert_eq!(submit, [recv(1)]);
+    }
+}
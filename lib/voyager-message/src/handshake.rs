@@ -0,0 +1,1009 @@
+//! Initiation of connection and channel handshakes.
+//!
+//! Voyager relays every step of a handshake after the first, but the initial
+//! `ConnectionOpenInit`/`ChannelOpenInit` has to be submitted by someone. The
+//! functions in this module validate that the client or connection that the
+//! new handshake builds on can actually be used, and only then build the
+//! datagram. The datagram is emitted as an
+//! [`IdentifiedIbcDatagram`](crate::data::Data::IdentifiedIbcDatagram), which
+//! is picked up by the transaction plugin for the chain.
+//!
+//! All validation happens before anything is enqueued, such that an invalid
+//! request never results in an op that can't be submitted.
+//!
+//! Channels to ibc-classic chains with the ICS-29 fee middleware have to be
+//! opened with the fee wrapped version (see [`ibc_classic_spec::fee`]) to
+//! support relayer incentivization. This is requested with
+//! [`InitChannel::wrap_fee_version`], which can be determined from the
+//! counterparty chain with [`detect_fee_support`].
+
+use ibc_classic_spec::{fee::wrap_fee_version, IbcClassic};
+use ibc_union_spec::{IbcUnion, UnionId};
+use jsonrpsee::{
+    core::RpcResult,
+    types::{error::INVALID_PARAMS_CODE, ErrorObject, ErrorObjectOwned},
+};
+use macros::model;
+use protos::{
+    cosmos::base::query::v1beta1::PageRequest,
+    ibc::applications::fee::v1::{query_client::QueryClient, QueryFeeEnabledChannelsRequest},
+};
+use serde_json::{json, Value};
+use tonic::Code;
+use unionlabs::{
+    bytes::Bytes,
+    ibc::core::{
+        channel::{self, order::Order},
+        commitment::merkle_prefix::MerklePrefix,
+        connection,
+    },
+    id::{ConnectionId, PortId},
+    ErrorReporter,
+};
+use voyager_core::{ChainId, ClientStatus, IbcSpec, IbcSpecId, QueryHeight};
+use voyager_vm::{data, Op};
+
+use crate::{
+    data::{IbcDatagram, WithChainId},
+    query::VoyagerQuery,
+    RawClientId, VoyagerMessage,
+};
+
+/// The version that ibc-classic connections are opened with, as per ibc-go.
+pub const IBC_CLASSIC_CONNECTION_VERSION: &str = "1";
+
+/// The prefix of the IBC store on ibc-classic chains.
+// TODO: Make configurable
+pub const IBC_CLASSIC_KEY_PREFIX: &[u8] = b"ibc";
+
+/// Open a new connection on `chain_id` from `client_id` to
+/// `counterparty_client_id`.
+#[model]
+pub struct InitConnection {
+    pub chain_id: ChainId,
+    pub ibc_spec_id: IbcSpecId,
+    pub client_id: RawClientId,
+    pub counterparty_client_id: RawClientId,
+    /// Only supported by ibc-classic; must be `0` for ibc-union.
+    pub delay_period: u64,
+}
+
+/// Open a new channel on `chain_id` on top of the connection `connection_id`.
+#[model]
+pub struct InitChannel {
+    pub chain_id: ChainId,
+    pub ibc_spec_id: IbcSpecId,
+    pub connection_id: u32,
+    /// For ibc-union, either the hex encoded port (`0x`-prefixed), or the port
+    /// as a string (i.e. the bech32 address of the app contract).
+    pub port_id: String,
+    pub counterparty_port_id: String,
+    pub version: String,
+    pub ordering: Order,
+    /// Wrap `version` with the version of the ICS-29 fee middleware. Versions
+    /// that are already wrapped are used as is. Only supported by
+    /// ibc-classic.
+    #[serde(default)]
+    pub wrap_fee_version: bool,
+}
+
+/// A spec agnostic view of a connection end.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConnectionSummary {
+    pub client_id: RawClientId,
+    pub state: ConnectionState,
+    /// The channel orderings supported by this connection.
+    pub orderings: Vec<Order>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ConnectionState {
+    Init,
+    TryOpen,
+    Open,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum InitError {
+    #[error("unsupported IBC spec `{0}`")]
+    UnsupportedIbcSpec(IbcSpecId),
+    #[error("invalid {field}")]
+    InvalidField {
+        field: &'static str,
+        #[source]
+        source: Box<dyn core::error::Error + Send + Sync>,
+    },
+    #[error("client {client_id} does not exist on {chain_id}")]
+    ClientNotFound { chain_id: ChainId, client_id: Value },
+    #[error("client {client_id} on {chain_id} is not active (status: {status:?})")]
+    ClientNotActive {
+        chain_id: ChainId,
+        client_id: Value,
+        status: ClientStatus,
+    },
+    #[error("connection {connection_id} does not exist on {chain_id}")]
+    ConnectionNotFound {
+        chain_id: ChainId,
+        connection_id: u32,
+    },
+    #[error("connection {connection_id} on {chain_id} is not open (state: {state:?})")]
+    ConnectionNotOpen {
+        chain_id: ChainId,
+        connection_id: u32,
+        state: ConnectionState,
+    },
+    #[error("connection {connection_id} on {chain_id} does not support {ordering} channels")]
+    OrderingNotSupported {
+        chain_id: ChainId,
+        connection_id: u32,
+        ordering: Order,
+    },
+    #[error("the delay period is not supported by {0}")]
+    DelayPeriodNotSupported(IbcSpecId),
+    #[error("fee middleware versions are not supported by {0}")]
+    FeeVersionNotSupported(IbcSpecId),
+    #[error(transparent)]
+    Rpc(#[from] ErrorObjectOwned),
+}
+
+impl From<InitError> for ErrorObjectOwned {
+    fn from(value: InitError) -> Self {
+        match value {
+            InitError::Rpc(err) => err,
+            err => ErrorObject::owned(
+                INVALID_PARAMS_CODE,
+                ErrorReporter(err).to_string(),
+                None::<()>,
+            ),
+        }
+    }
+}
+
+/// Validate and build the `ConnectionOpenInit` datagram for `msg`.
+///
+/// The client must exist and be active.
+pub async fn init_connection(
+    client: &impl VoyagerQuery,
+    msg: InitConnection,
+) -> Result<Op<VoyagerMessage>, InitError> {
+    ensure_client_active(client, &msg.chain_id, &msg.ibc_spec_id, &msg.client_id).await?;
+
+    let datagram = match msg.ibc_spec_id.as_str() {
+        IbcSpecId::CLASSIC => IbcDatagram::new::<IbcClassic>(ibc_classic_spec::Datagram::from(
+            connection::msg_connection_open_init::MsgConnectionOpenInit {
+                client_id: decode_client_id::<IbcClassic>(msg.client_id, "client_id")?,
+                counterparty: connection::counterparty::Counterparty {
+                    client_id: decode_client_id::<IbcClassic>(
+                        msg.counterparty_client_id,
+                        "counterparty_client_id",
+                    )?,
+                    connection_id: None,
+                    prefix: MerklePrefix {
+                        key_prefix: IBC_CLASSIC_KEY_PREFIX.into(),
+                    },
+                },
+                version: connection::version::Version {
+                    identifier: IBC_CLASSIC_CONNECTION_VERSION.to_owned(),
+                    features: vec![Order::Ordered, Order::Unordered],
+                },
+                delay_period: msg.delay_period,
+            },
+        )),
+        IbcSpecId::UNION => {
+            if msg.delay_period != 0 {
+                return Err(InitError::DelayPeriodNotSupported(msg.ibc_spec_id));
+            }
+
+            IbcDatagram::new::<IbcUnion>(ibc_union_spec::Datagram::from(
+                ibc_union_spec::MsgConnectionOpenInit {
+                    client_id: decode_client_id::<IbcUnion>(msg.client_id, "client_id")?,
+                    counterparty_client_id: decode_client_id::<IbcUnion>(
+                        msg.counterparty_client_id,
+                        "counterparty_client_id",
+                    )?,
+                },
+            ))
+        }
+        _ => return Err(InitError::UnsupportedIbcSpec(msg.ibc_spec_id)),
+    };
+
+    Ok(data(WithChainId {
+        chain_id: msg.chain_id,
+        message: datagram,
+    }))
+}
+
+/// Validate and build the `ChannelOpenInit` datagram for `msg`.
+///
+/// The connection must exist, be open, and support the requested ordering.
+/// The client of the connection must be active.
+pub async fn init_channel(
+    client: &impl VoyagerQuery,
+    msg: InitChannel,
+) -> Result<Op<VoyagerMessage>, InitError> {
+    let connection = connection(client, &msg.chain_id, &msg.ibc_spec_id, msg.connection_id)
+        .await?
+        .ok_or_else(|| InitError::ConnectionNotFound {
+            chain_id: msg.chain_id.clone(),
+            connection_id: msg.connection_id,
+        })?;
+
+    if connection.state != ConnectionState::Open {
+        return Err(InitError::ConnectionNotOpen {
+            chain_id: msg.chain_id,
+            connection_id: msg.connection_id,
+            state: connection.state,
+        });
+    }
+
+    if !connection.orderings.contains(&msg.ordering) {
+        return Err(InitError::OrderingNotSupported {
+            chain_id: msg.chain_id,
+            connection_id: msg.connection_id,
+            ordering: msg.ordering,
+        });
+    }
+
+    ensure_client_active(
+        client,
+        &msg.chain_id,
+        &msg.ibc_spec_id,
+        &connection.client_id,
+    )
+    .await?;
+
+    let datagram = match msg.ibc_spec_id.as_str() {
+        IbcSpecId::CLASSIC => IbcDatagram::new::<IbcClassic>(ibc_classic_spec::Datagram::from(
+            channel::msg_channel_open_init::MsgChannelOpenInit {
+                port_id: parse_port_id(msg.port_id, "port_id")?,
+                channel: channel::channel::Channel {
+                    state: channel::state::State::Init,
+                    ordering: msg.ordering,
+                    counterparty: channel::counterparty::Counterparty {
+                        port_id: parse_port_id(msg.counterparty_port_id, "counterparty_port_id")?,
+                        channel_id: None,
+                    },
+                    connection_hops: vec![ConnectionId::new(msg.connection_id)],
+                    version: if msg.wrap_fee_version {
+                        wrap_fee_version(msg.version)
+                    } else {
+                        msg.version
+                    },
+                    upgrade_sequence: 0,
+                },
+            },
+        )),
+        IbcSpecId::UNION => {
+            if msg.wrap_fee_version {
+                return Err(InitError::FeeVersionNotSupported(msg.ibc_spec_id));
+            }
+
+            IbcDatagram::new::<IbcUnion>(ibc_union_spec::Datagram::from(
+                ibc_union_spec::MsgChannelOpenInit {
+                    port_id: union_port_id(msg.port_id),
+                    counterparty_port_id: union_port_id(msg.counterparty_port_id),
+                    connection_id: UnionId::try_from(msg.connection_id).map_err(|err| {
+                        InitError::InvalidField {
+                            field: "connection_id",
+                            source: err.into(),
+                        }
+                    })?,
+                    version: msg.version,
+                },
+            ))
+        }
+        _ => return Err(InitError::UnsupportedIbcSpec(msg.ibc_spec_id)),
+    };
+
+    Ok(data(WithChainId {
+        chain_id: msg.chain_id,
+        message: datagram,
+    }))
+}
+
+/// Whether the ICS-29 fee middleware is enabled on a port, as detected by
+/// [`detect_fee_support`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeeSupport {
+    /// The port has fee enabled channels.
+    Enabled,
+    /// The chain runs the fee module, but there are no fee enabled channels
+    /// on the port.
+    Disabled,
+    /// The chain does not run the fee module.
+    Unsupported,
+}
+
+impl FeeSupport {
+    /// Whether a channel to this port should be opened with the fee wrapped
+    /// version.
+    #[must_use]
+    pub fn wrap_fee_version(self) -> bool {
+        self == FeeSupport::Enabled
+    }
+}
+
+/// A source of the fee enabled channels of a chain.
+#[allow(async_fn_in_trait)]
+pub trait FeeEnabledChannels {
+    /// The `(port_id, channel_id)` of all fee enabled channels, or `None` if
+    /// the chain does not run the fee module.
+    async fn fee_enabled_channels(&self) -> RpcResult<Option<Vec<(String, String)>>>;
+}
+
+/// Query the fee enabled channels with
+/// `ibc.applications.fee.v1.Query/FeeEnabledChannels`.
+#[derive(Debug, Clone)]
+pub struct GrpcFeeEnabledChannels {
+    pub grpc_url: String,
+}
+
+impl FeeEnabledChannels for GrpcFeeEnabledChannels {
+    async fn fee_enabled_channels(&self) -> RpcResult<Option<Vec<(String, String)>>> {
+        let mut client = QueryClient::connect(self.grpc_url.clone())
+            .await
+            .map_err(|err| self.error("error connecting to grpc server", err))?;
+
+        let mut channels = vec![];
+        let mut next_key = vec![];
+
+        loop {
+            let response = match client
+                .fee_enabled_channels(QueryFeeEnabledChannelsRequest {
+                    pagination: Some(PageRequest {
+                        key: next_key,
+                        ..Default::default()
+                    }),
+                    query_height: 0,
+                })
+                .await
+            {
+                Ok(response) => response.into_inner(),
+                Err(status) if is_unsupported(&status) => return Ok(None),
+                Err(status) => {
+                    return Err(self.error("error querying fee enabled channels", status))
+                }
+            };
+
+            channels.extend(
+                response
+                    .fee_enabled_channels
+                    .into_iter()
+                    .map(|channel| (channel.port_id, channel.channel_id)),
+            );
+
+            match response.pagination {
+                Some(pagination) if !pagination.next_key.is_empty() => {
+                    next_key = pagination.next_key;
+                }
+                _ => break,
+            }
+        }
+
+        Ok(Some(channels))
+    }
+}
+
+impl GrpcFeeEnabledChannels {
+    fn error(&self, message: &str, err: impl std::error::Error) -> ErrorObjectOwned {
+        ErrorObject::owned(
+            -1,
+            format!("{message}: {}", ErrorReporter(err)),
+            Some(json!({ "grpc_url": self.grpc_url })),
+        )
+    }
+}
+
+/// Chains without the fee module don't serve its query service at all.
+fn is_unsupported(status: &tonic::Status) -> bool {
+    status.code() == Code::Unimplemented
+}
+
+/// Detect whether the fee middleware is enabled on `port_id`, from the fee
+/// enabled channels of the chain. A port is only considered to be fee enabled
+/// if it already has a fee enabled channel, since the fee module being present
+/// does not mean that the port is wrapped by the middleware.
+pub async fn detect_fee_support(
+    channels: &impl FeeEnabledChannels,
+    port_id: &str,
+) -> RpcResult<FeeSupport> {
+    Ok(match channels.fee_enabled_channels().await? {
+        None => FeeSupport::Unsupported,
+        Some(channels) if channels.iter().any(|(port, _)| port == port_id) => FeeSupport::Enabled,
+        Some(_) => FeeSupport::Disabled,
+    })
+}
+
+async fn ensure_client_active(
+    client: &impl VoyagerQuery,
+    chain_id: &ChainId,
+    ibc_spec_id: &IbcSpecId,
+    client_id: &RawClientId,
+) -> Result<(), InitError> {
+    match client_status(client, chain_id, ibc_spec_id, client_id.clone()).await? {
+        None => Err(InitError::ClientNotFound {
+            chain_id: chain_id.clone(),
+            client_id: client_id.as_raw().clone(),
+        }),
+        Some(ClientStatus::Active) => Ok(()),
+        Some(status) => Err(InitError::ClientNotActive {
+            chain_id: chain_id.clone(),
+            client_id: client_id.as_raw().clone(),
+            status,
+        }),
+    }
+}
+
+/// The status of the client, or `None` if the client does not exist.
+async fn client_status(
+    client: &impl VoyagerQuery,
+    chain_id: &ChainId,
+    ibc_spec_id: &IbcSpecId,
+    client_id: RawClientId,
+) -> Result<Option<ClientStatus>, InitError> {
+    if ![IbcSpecId::CLASSIC, IbcSpecId::UNION].contains(&ibc_spec_id.as_str()) {
+        return Err(InitError::UnsupportedIbcSpec(ibc_spec_id.clone()));
+    }
+
+    let client_state_path = client
+        .client_state_path(ibc_spec_id, client_id.clone())
+        .map_err(|err| InitError::InvalidField {
+            field: "client_id",
+            source: err.into(),
+        })?;
+
+    let client_state = client
+        .query_ibc_state_raw(
+            chain_id,
+            ibc_spec_id,
+            QueryHeight::Latest,
+            client_state_path,
+        )
+        .await?;
+
+    if client_state.state.is_null() {
+        return Ok(None);
+    }
+
+    let meta = client
+        .client_meta_raw(chain_id, ibc_spec_id, QueryHeight::Latest, client_id)
+        .await?;
+
+    Ok(Some(meta.status))
+}
+
+/// The connection, or `None` if the connection does not exist.
+async fn connection(
+    client: &impl VoyagerQuery,
+    chain_id: &ChainId,
+    ibc_spec_id: &IbcSpecId,
+    connection_id: u32,
+) -> Result<Option<ConnectionSummary>, InitError> {
+    match ibc_spec_id.as_str() {
+        IbcSpecId::CLASSIC => {
+            let connection = client
+                .query_ibc_state(
+                    chain_id,
+                    QueryHeight::Latest,
+                    ibc_classic_spec::ConnectionPath {
+                        connection_id: ConnectionId::new(connection_id),
+                    },
+                )
+                .await?
+                .state;
+
+            Ok(connection.and_then(|connection| {
+                Some(ConnectionSummary {
+                    client_id: RawClientId::new(connection.client_id),
+                    state: match connection.state {
+                        connection::state::State::UninitializedUnspecified => return None,
+                        connection::state::State::Init => ConnectionState::Init,
+                        connection::state::State::Tryopen => ConnectionState::TryOpen,
+                        connection::state::State::Open => ConnectionState::Open,
+                    },
+                    orderings: connection
+                        .versions
+                        .into_iter()
+                        .flat_map(|version| version.features)
+                        .collect(),
+                })
+            }))
+        }
+        IbcSpecId::UNION => {
+            let connection = client
+                .query_ibc_state(
+                    chain_id,
+                    QueryHeight::Latest,
+                    ibc_union_spec::ConnectionPath { connection_id },
+                )
+                .await?
+                .state;
+
+            Ok(connection.and_then(|connection| {
+                Some(ConnectionSummary {
+                    client_id: RawClientId::new(connection.client_id),
+                    state: match connection.state {
+                        ibc_solidity::ConnectionState::Init => ConnectionState::Init,
+                        ibc_solidity::ConnectionState::TryOpen => ConnectionState::TryOpen,
+                        ibc_solidity::ConnectionState::Open => ConnectionState::Open,
+                        _ => return None,
+                    },
+                    // ibc-union channels are always unordered
+                    orderings: vec![Order::Unordered],
+                })
+            }))
+        }
+        _ => Err(InitError::UnsupportedIbcSpec(ibc_spec_id.clone())),
+    }
+}
+
+fn decode_client_id<V: IbcSpec>(
+    client_id: RawClientId,
+    field: &'static str,
+) -> Result<V::ClientId, InitError> {
+    client_id
+        .decode_spec::<V>()
+        .map_err(|err| InitError::InvalidField {
+            field,
+            source: err.into(),
+        })
+}
+
+fn parse_port_id(port_id: String, field: &'static str) -> Result<PortId, InitError> {
+    PortId::new(port_id).map_err(|err| InitError::InvalidField {
+        field,
+        source: err.into(),
+    })
+}
+
+/// ibc-union ports are arbitrary bytes; accept them either hex encoded or as a
+/// plain string.
+fn union_port_id(port_id: String) -> Bytes {
+    match port_id.parse::<Bytes>() {
+        Ok(bytes) if port_id.starts_with("0x") => bytes,
+        _ => port_id.into_bytes().into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ibc_solidity::Connection;
+    use unionlabs::{ibc::core::client::height::Height, id::ClientId};
+    use voyager_core::ClientStateMeta;
+
+    use super::*;
+    use crate::{data::Data, testing::MockVoyager};
+
+    fn chain_id() -> ChainId {
+        ChainId::new("union-devnet-1")
+    }
+
+    fn voyager() -> MockVoyager {
+        let voyager = MockVoyager::new();
+        voyager.set_latest_height(&chain_id(), Height::new(100));
+        voyager
+    }
+
+    /// Create the client `client_id` with `status` on [`chain_id`].
+    fn set_client<V: IbcSpec>(voyager: &MockVoyager, client_id: V::ClientId, status: ClientStatus) {
+        voyager
+            .set_client_state::<V>(&chain_id(), client_id.clone(), b"client state".into())
+            .set_client_meta::<V>(
+                &chain_id(),
+                client_id,
+                ClientStateMeta {
+                    height: Height::new(1),
+                    chain_id: ChainId::new("counterparty-1"),
+                    status,
+                    resolved_at: None,
+                },
+            );
+    }
+
+    fn set_union_connection(voyager: &MockVoyager, state: ibc_solidity::ConnectionState) {
+        voyager.set_state(
+            &chain_id(),
+            ibc_union_spec::ConnectionPath { connection_id: 1 },
+            Some(Connection {
+                state,
+                client_id: 1,
+                counterparty_client_id: 7,
+                counterparty_connection_id: 0,
+            }),
+        );
+    }
+
+    /// The union client 1 with `status`, and the open connection 1 on top of it.
+    fn union_client(status: ClientStatus) -> MockVoyager {
+        let voyager = voyager();
+        set_client::<IbcUnion>(&voyager, UnionId::new(1).unwrap(), status);
+        set_union_connection(&voyager, ibc_solidity::ConnectionState::Open);
+        voyager
+    }
+
+    fn init_union_connection() -> InitConnection {
+        InitConnection {
+            chain_id: chain_id(),
+            ibc_spec_id: IbcUnion::ID,
+            client_id: RawClientId::new(1),
+            counterparty_client_id: RawClientId::new(7),
+            delay_period: 0,
+        }
+    }
+
+    fn init_union_channel() -> InitChannel {
+        InitChannel {
+            chain_id: chain_id(),
+            ibc_spec_id: IbcUnion::ID,
+            connection_id: 1,
+            port_id: "union1port".to_owned(),
+            counterparty_port_id: "0x1234".to_owned(),
+            version: "ucs01-relay-1".to_owned(),
+            ordering: Order::Unordered,
+            wrap_fee_version: false,
+        }
+    }
+
+    fn datagram(op: Op<VoyagerMessage>) -> IbcDatagram {
+        match op {
+            Op::Data(Data::IdentifiedIbcDatagram(WithChainId {
+                chain_id: c,
+                message,
+            })) => {
+                assert_eq!(c, chain_id());
+                message
+            }
+            op => panic!("unexpected op: {op:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn init_union_connection_is_enqueued_as_datagram() {
+        let op = init_connection(&union_client(ClientStatus::Active), init_union_connection())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            datagram(op).decode_datagram::<IbcUnion>().unwrap().unwrap(),
+            ibc_union_spec::Datagram::from(ibc_union_spec::MsgConnectionOpenInit {
+                client_id: UnionId::new(1).unwrap(),
+                counterparty_client_id: UnionId::new(7).unwrap(),
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn init_connection_on_missing_client() {
+        let err = init_connection(&voyager(), init_union_connection())
+            .await
+            .unwrap_err();
+
+        assert!(matches!(err, InitError::ClientNotFound { .. }), "{err:?}");
+    }
+
+    #[tokio::test]
+    async fn init_connection_on_frozen_client() {
+        let err = init_connection(&union_client(ClientStatus::Frozen), init_union_connection())
+            .await
+            .unwrap_err();
+
+        assert!(
+            matches!(
+                err,
+                InitError::ClientNotActive {
+                    status: ClientStatus::Frozen,
+                    ..
+                }
+            ),
+            "{err:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn init_union_connection_with_delay_period() {
+        let err = init_connection(
+            &union_client(ClientStatus::Active),
+            InitConnection {
+                delay_period: 10,
+                ..init_union_connection()
+            },
+        )
+        .await
+        .unwrap_err();
+
+        assert!(
+            matches!(err, InitError::DelayPeriodNotSupported(_)),
+            "{err:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn init_classic_connection() {
+        let voyager = voyager();
+        set_client::<IbcClassic>(
+            &voyager,
+            ClientId::new("07-tendermint", 0),
+            ClientStatus::Active,
+        );
+
+        let op = init_connection(
+            &voyager,
+            InitConnection {
+                chain_id: chain_id(),
+                ibc_spec_id: IbcClassic::ID,
+                client_id: RawClientId::new(ClientId::new("07-tendermint", 0)),
+                counterparty_client_id: RawClientId::new(ClientId::new("08-wasm", 3)),
+                delay_period: 0,
+            },
+        )
+        .await
+        .unwrap();
+
+        let ibc_classic_spec::Datagram::ConnectionOpenInit(msg) = datagram(op)
+            .decode_datagram::<IbcClassic>()
+            .unwrap()
+            .unwrap()
+        else {
+            panic!("expected ConnectionOpenInit");
+        };
+
+        assert_eq!(msg.client_id, ClientId::new("07-tendermint", 0));
+        assert_eq!(msg.counterparty.client_id, ClientId::new("08-wasm", 3));
+        assert_eq!(msg.counterparty.connection_id, None);
+        assert_eq!(msg.version.identifier, IBC_CLASSIC_CONNECTION_VERSION);
+    }
+
+    #[tokio::test]
+    async fn init_union_channel_is_enqueued_as_datagram() {
+        let op = init_channel(&union_client(ClientStatus::Active), init_union_channel())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            datagram(op).decode_datagram::<IbcUnion>().unwrap().unwrap(),
+            ibc_union_spec::Datagram::from(ibc_union_spec::MsgChannelOpenInit {
+                port_id: b"union1port".into(),
+                counterparty_port_id: [0x12, 0x34].into(),
+                connection_id: UnionId::new(1).unwrap(),
+                version: "ucs01-relay-1".to_owned(),
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn init_channel_on_missing_connection() {
+        let err = init_channel(&voyager(), init_union_channel())
+            .await
+            .unwrap_err();
+
+        assert!(
+            matches!(err, InitError::ConnectionNotFound { .. }),
+            "{err:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn init_channel_on_unopened_connection() {
+        let voyager = union_client(ClientStatus::Active);
+        set_union_connection(&voyager, ibc_solidity::ConnectionState::TryOpen);
+
+        let err = init_channel(&voyager, init_union_channel())
+            .await
+            .unwrap_err();
+
+        assert!(
+            matches!(
+                err,
+                InitError::ConnectionNotOpen {
+                    state: ConnectionState::TryOpen,
+                    ..
+                }
+            ),
+            "{err:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn init_channel_on_frozen_client() {
+        let err = init_channel(&union_client(ClientStatus::Frozen), init_union_channel())
+            .await
+            .unwrap_err();
+
+        assert!(matches!(err, InitError::ClientNotActive { .. }), "{err:?}");
+    }
+
+    #[tokio::test]
+    async fn init_ordered_union_channel() {
+        let err = init_channel(
+            &union_client(ClientStatus::Active),
+            InitChannel {
+                ordering: Order::Ordered,
+                ..init_union_channel()
+            },
+        )
+        .await
+        .unwrap_err();
+
+        assert!(
+            matches!(err, InitError::OrderingNotSupported { .. }),
+            "{err:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn init_union_channel_with_fee_version() {
+        let err = init_channel(
+            &union_client(ClientStatus::Active),
+            InitChannel {
+                wrap_fee_version: true,
+                ..init_union_channel()
+            },
+        )
+        .await
+        .unwrap_err();
+
+        assert!(
+            matches!(err, InitError::FeeVersionNotSupported(_)),
+            "{err:?}"
+        );
+    }
+
+    async fn init_classic_channel(version: &str, wrap_fee_version: bool) -> String {
+        let client_id = ClientId::new("07-tendermint", 0);
+
+        let voyager = voyager();
+        set_client::<IbcClassic>(&voyager, client_id.clone(), ClientStatus::Active);
+        voyager.set_state(
+            &chain_id(),
+            ibc_classic_spec::ConnectionPath {
+                connection_id: ConnectionId::new(0),
+            },
+            Some(connection::connection_end::ConnectionEnd {
+                client_id,
+                versions: vec![connection::version::Version {
+                    identifier: IBC_CLASSIC_CONNECTION_VERSION.to_owned(),
+                    features: vec![Order::Ordered, Order::Unordered],
+                }],
+                state: connection::state::State::Open,
+                counterparty: connection::counterparty::Counterparty {
+                    client_id: ClientId::new("08-wasm", 3),
+                    connection_id: Some(ConnectionId::new(5)),
+                    prefix: MerklePrefix {
+                        key_prefix: IBC_CLASSIC_KEY_PREFIX.into(),
+                    },
+                },
+                delay_period: 0,
+            }),
+        );
+
+        let op = init_channel(
+            &voyager,
+            InitChannel {
+                chain_id: chain_id(),
+                ibc_spec_id: IbcClassic::ID,
+                connection_id: 0,
+                port_id: "transfer".to_owned(),
+                counterparty_port_id: "transfer".to_owned(),
+                version: version.to_owned(),
+                ordering: Order::Unordered,
+                wrap_fee_version,
+            },
+        )
+        .await
+        .unwrap();
+
+        let ibc_classic_spec::Datagram::ChannelOpenInit(msg) = datagram(op)
+            .decode_datagram::<IbcClassic>()
+            .unwrap()
+            .unwrap()
+        else {
+            panic!("expected ChannelOpenInit");
+        };
+
+        msg.channel.version
+    }
+
+    #[tokio::test]
+    async fn init_classic_channel_fee_version() {
+        const WRAPPED: &str = r#"{"fee_version":"ics29-1","app_version":"ics20-1"}"#;
+
+        assert_eq!(init_classic_channel("ics20-1", false).await, "ics20-1");
+        assert_eq!(init_classic_channel("ics20-1", true).await, WRAPPED);
+        // an already wrapped version is not wrapped twice
+        assert_eq!(init_classic_channel(WRAPPED, true).await, WRAPPED);
+        assert_eq!(init_classic_channel(WRAPPED, false).await, WRAPPED);
+    }
+
+    #[test]
+    fn init_channel_without_fee_version_is_accepted() {
+        let msg = serde_json::from_value::<InitChannel>(json!({
+            "chain_id": "union-devnet-1",
+            "ibc_spec_id": "ibc-classic",
+            "connection_id": 0,
+            "port_id": "transfer",
+            "counterparty_port_id": "transfer",
+            "version": "ics20-1",
+            "ordering": "unordered",
+        }))
+        .unwrap();
+
+        assert!(!msg.wrap_fee_version);
+    }
+
+    /// The fee enabled channels of a chain, or `None` if it doesn't run the
+    /// fee module.
+    struct MockFeeEnabledChannels(Option<Vec<(&'static str, &'static str)>>);
+
+    impl FeeEnabledChannels for MockFeeEnabledChannels {
+        async fn fee_enabled_channels(&self) -> RpcResult<Option<Vec<(String, String)>>> {
+            Ok(self.0.as_ref().map(|channels| {
+                channels
+                    .iter()
+                    .map(|(port_id, channel_id)| ((*port_id).to_owned(), (*channel_id).to_owned()))
+                    .collect()
+            }))
+        }
+    }
+
+    #[tokio::test]
+    async fn detect_fee_enabled() {
+        let channels = MockFeeEnabledChannels(Some(vec![
+            ("icahost", "channel-0"),
+            ("transfer", "channel-1"),
+        ]));
+
+        let support = detect_fee_support(&channels, "transfer").await.unwrap();
+
+        assert_eq!(support, FeeSupport::Enabled);
+        assert!(support.wrap_fee_version());
+    }
+
+    #[tokio::test]
+    async fn detect_fee_disabled() {
+        for channels in [
+            MockFeeEnabledChannels(Some(vec![])),
+            // fees are only enabled for a different port
+            MockFeeEnabledChannels(Some(vec![("icahost", "channel-0")])),
+        ] {
+            let support = detect_fee_support(&channels, "transfer").await.unwrap();
+
+            assert_eq!(support, FeeSupport::Disabled);
+            assert!(!support.wrap_fee_version());
+        }
+    }
+
+    #[tokio::test]
+    async fn detect_fee_unsupported() {
+        let support = detect_fee_support(&MockFeeEnabledChannels(None), "transfer")
+            .await
+            .unwrap();
+
+        assert_eq!(support, FeeSupport::Unsupported);
+        assert!(!support.wrap_fee_version());
+    }
+
+    #[test]
+    fn unimplemented_fee_query_is_unsupported() {
+        assert!(is_unsupported(&tonic::Status::unimplemented(
+            "unknown service ibc.applications.fee.v1.Query"
+        )));
+        assert!(!is_unsupported(&tonic::Status::unavailable(
+            "connection refused"
+        )));
+        assert!(!is_unsupported(&tonic::Status::not_found("not found")));
+    }
+
+    #[test]
+    fn validation_errors_are_invalid_params() {
+        let err = ErrorObjectOwned::from(InitError::ConnectionNotFound {
+            chain_id: chain_id(),
+            connection_id: 1,
+        });
+
+        assert_eq!(err.code(), INVALID_PARAMS_CODE);
+        assert_eq!(
+            err.message(),
+            "connection 1 does not exist on union-devnet-1"
+        );
+    }
+}
@@ -1,18 +1,27 @@
 use std::collections::VecDeque;
 
-use jsonrpsee::{core::RpcResult, proc_macros::rpc};
+use chain_utils::relay_progress::RelayProgress;
+use jsonrpsee::{
+    core::RpcResult,
+    proc_macros::rpc,
+    types::{error::METHOD_NOT_FOUND_CODE, ErrorObject},
+};
 use macros::model;
 use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use unionlabs::{bytes::Bytes, ibc::core::client::height::Height, traits::Member};
+use unionlabs::{bytes::Bytes, hash::H256, ibc::core::client::height::Height, traits::Member};
 use voyager_core::{ConsensusType, IbcSpecId};
 use voyager_vm::{pass::PassResult, BoxDynError, Op};
 
 use crate::{
+    cache_snapshot::{CacheSnapshot, ImportReport},
+    consensus_heights::{ConsensusStateHeights, Pagination, QueryRange, RangeEntry},
     core::{
-        ChainId, ClientInfo, ClientStateMeta, ClientType, ConsensusStateMeta, IbcInterface, IbcSpec,
+        ChainId, ClientInfo, ClientStateMeta, ClientStatus, ClientType, ConsensusStateMeta,
+        IbcInterface, IbcSpec,
     },
-    data::Data,
+    data::{Data, IbcDatagram},
     RawClientId, VoyagerMessage,
 };
 
@@ -36,14 +45,7 @@ impl StateModuleInfo {
 
     // TODO: Add this for ibc_spec_id
     pub fn ensure_chain_id(&self, chain_id: impl AsRef<str>) -> Result<(), UnexpectedChainIdError> {
-        if chain_id.as_ref() != self.chain_id.as_str() {
-            Err(UnexpectedChainIdError {
-                expected: self.chain_id.clone(),
-                found: chain_id.as_ref().to_owned(),
-            })
-        } else {
-            Ok(())
-        }
+        ensure_chain_id(&self.chain_id, chain_id)
     }
 }
 
@@ -63,14 +65,7 @@ impl ProofModuleInfo {
 
     // TODO: Add this for ibc_spec_id
     pub fn ensure_chain_id(&self, chain_id: impl AsRef<str>) -> Result<(), UnexpectedChainIdError> {
-        if chain_id.as_ref() != self.chain_id.as_str() {
-            Err(UnexpectedChainIdError {
-                expected: self.chain_id.clone(),
-                found: chain_id.as_ref().to_owned(),
-            })
-        } else {
-            Ok(())
-        }
+        ensure_chain_id(&self.chain_id, chain_id)
     }
 }
 
@@ -93,14 +88,7 @@ impl ConsensusModuleInfo {
     }
 
     pub fn ensure_chain_id(&self, chain_id: impl AsRef<str>) -> Result<(), UnexpectedChainIdError> {
-        if chain_id.as_ref() != self.chain_id.as_str() {
-            Err(UnexpectedChainIdError {
-                expected: self.chain_id.clone(),
-                found: chain_id.as_ref().to_owned(),
-            })
-        } else {
-            Ok(())
-        }
+        ensure_chain_id(&self.chain_id, chain_id)
     }
 
     pub fn ensure_consensus_type(
@@ -203,6 +191,16 @@ impl ClientModuleInfo {
     }
 }
 
+/// The modules currently loaded by voyager, as returned by the `info` rpc
+/// method.
+#[model]
+pub struct LoadedModulesInfo {
+    pub state: Vec<StateModuleInfo>,
+    pub proof: Vec<ProofModuleInfo>,
+    pub consensus: Vec<ConsensusModuleInfo>,
+    pub client: Vec<ClientModuleInfo>,
+}
+
 #[derive(Debug, Clone, thiserror::Error)]
 #[error("invalid chain id: expected `{expected}` but the rpc responded with `{found}`")]
 pub struct UnexpectedChainIdError {
@@ -210,6 +208,22 @@ pub struct UnexpectedChainIdError {
     pub found: String,
 }
 
+/// Check that the chain id reported by an rpc endpoint, `found`, is the `expected` (configured)
+/// one.
+pub fn ensure_chain_id(
+    expected: &ChainId,
+    found: impl AsRef<str>,
+) -> Result<(), UnexpectedChainIdError> {
+    if found.as_ref() != expected.as_str() {
+        Err(UnexpectedChainIdError {
+            expected: expected.clone(),
+            found: found.as_ref().to_owned(),
+        })
+    } else {
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone, thiserror::Error)]
 #[error("invalid consensus type: this module provides functionality for consensus type `{expected}`, but the config specifies `{found}`")]
 pub struct UnexpectedConsensusTypeError {
@@ -245,13 +259,135 @@ pub struct PluginInfo {
     /// routed to this plugin.
     pub name: String,
     /// A jaq filter to run on every message before pushing them to the queue.
-    /// This ***MUST*** return a bool. If this returns `true`, the message will
-    /// be pushed to the optimization queue with this plugin's name as the tag,
-    /// otherwise it will be passed on to the next plugin to be filtered.
+    /// This ***MUST*** return a bool or `"observe"`. If this returns `true`,
+    /// the message will be pushed to the optimization queue with this plugin's
+    /// name as the tag, otherwise it will be passed on to the next plugin to be
+    /// filtered. If this returns `"observe"`, a copy of the message will be
+    /// pushed to the optimization queue with this plugin's name as the tag, and
+    /// the message itself is passed on to the next plugin to be filtered (i.e.
+    /// for plugins that only record messages). Messages are observed regardless
+    /// of whether a plugin before this one already expressed interest in them.
     pub interest_filter: String,
+    /// The kind of this plugin. Plugins of the same kind may not serve the same
+    /// (chain, IBC spec) pair, unless explicitly allowed in the plugin config.
+    #[arg(long, value_enum)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<PluginKind>,
+    /// The chains that this plugin serves.
+    #[arg(long, value_parser(|s: &str| ok(ChainId::new(s.to_owned()))))]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub chains: Vec<ChainId>,
+    /// The IBC specs that this plugin serves.
+    #[arg(long, value_parser(|s: &str| ok(IbcSpecId::new(s.to_owned()))))]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ibc_specs: Vec<IbcSpecId>,
 }
 
-#[rpc(client, server, namespace = "plugin")]
+impl PluginInfo {
+    /// All of the (chain, IBC spec) pairs that this plugin serves.
+    pub fn served(&self) -> impl Iterator<Item = (&ChainId, &IbcSpecId)> + '_ {
+        self.chains.iter().flat_map(|chain_id| {
+            self.ibc_specs
+                .iter()
+                .map(move |ibc_spec_id| (chain_id, ibc_spec_id))
+        })
+    }
+}
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, clap::ValueEnum, JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum PluginKind {
+    EventSource,
+    Transaction,
+}
+
+impl std::fmt::Display for PluginKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            PluginKind::EventSource => "event_source",
+            PluginKind::Transaction => "transaction",
+        })
+    }
+}
+
+/// The result of reloading the config of a plugin.
+#[model]
+#[derive(JsonSchema, Default)]
+pub struct ReloadReport {
+    /// The top level config fields that were changed and are now in effect.
+    pub changed: Vec<String>,
+    /// The top level config fields that were changed but could not be applied
+    /// to the running plugin.
+    pub rejected: Vec<RejectedField>,
+}
+
+#[model]
+#[derive(JsonSchema)]
+pub struct RejectedField {
+    pub field: String,
+    pub reason: String,
+}
+
+impl ReloadReport {
+    /// Diff the top level fields of two configs. Fields listed in `reloadable`
+    /// are reported as changed, all other differing fields are rejected as
+    /// requiring a restart.
+    ///
+    /// Both configs are expected to serialize to JSON objects.
+    pub fn diff(old: &impl Serialize, new: &impl Serialize, reloadable: &[&str]) -> Self {
+        fn as_object(t: &impl Serialize) -> serde_json::Map<String, Value> {
+            match serde_json::to_value(t) {
+                Ok(Value::Object(map)) => map,
+                _ => Default::default(),
+            }
+        }
+
+        let old = as_object(old);
+        let new = as_object(new);
+
+        let mut report = Self::default();
+
+        let mut fields = old.keys().chain(new.keys()).collect::<Vec<_>>();
+        fields.sort();
+        fields.dedup();
+
+        for field in fields {
+            if old.get(field) == new.get(field) {
+                continue;
+            }
+
+            if reloadable.contains(&field.as_str()) {
+                report.changed.push(field.clone());
+            } else {
+                report.rejected.push(RejectedField {
+                    field: field.clone(),
+                    reason: "requires restart".to_owned(),
+                });
+            }
+        }
+
+        report
+    }
+}
+
+/// The estimated cost of submitting a transaction, as returned by
+/// [`PluginClient::estimate`].
+#[model]
+pub struct TxEstimate {
+    /// The gas used by the transaction when simulated.
+    pub gas: u64,
+    /// The fee that would be paid for the transaction at the current gas
+    /// price, in the smallest unit of [`denom`](Self::denom).
+    #[serde(with = "::serde_utils::string")]
+    pub fee: u128,
+    /// The native denom of the chain, i.e. `muno` or `wei`.
+    pub denom: String,
+}
+
+#[cfg_attr(feature = "server", rpc(client, server, namespace = "plugin"))]
+#[cfg_attr(not(feature = "server"), rpc(client, namespace = "plugin"))]
 pub trait Plugin<C: Member, Cb: Member> {
     #[method(name = "runPass", with_extensions)]
     async fn run_pass(
@@ -266,14 +402,117 @@ pub trait Plugin<C: Member, Cb: Member> {
     /// Handle a custom `Callback` message for this module.
     #[method(name = "callback", with_extensions)]
     async fn callback(&self, aggregate: Cb, data: VecDeque<Data>) -> RpcResult<Op<VoyagerMessage>>;
+
+    /// Apply a new config to the running plugin, without restarting it.
+    ///
+    /// Only settings that are read at the time of use can be reloaded; changes
+    /// to any other settings are rejected and reported in the returned
+    /// [`ReloadReport`]. Plugins that do not support reloading their config
+    /// return an error.
+    #[method(name = "reload")]
+    async fn reload(&self, new_config: Value) -> RpcResult<ReloadReport> {
+        let _ = new_config;
+
+        Err(ErrorObject::owned(
+            METHOD_NOT_FOUND_CODE,
+            "reloading the config is not supported by this plugin",
+            None::<()>,
+        ))
+    }
+
+    /// Introspect the current state of the running plugin, for debugging. The
+    /// shape of the returned value is plugin specific, and any sensitive
+    /// configuration is redacted. Plugins that do not support this return an
+    /// error.
+    #[method(name = "debugState")]
+    async fn debug_state(&self) -> RpcResult<Value> {
+        Err(ErrorObject::owned(
+            METHOD_NOT_FOUND_CODE,
+            "debug state is not supported by this plugin",
+            None::<()>,
+        ))
+    }
+
+    /// Re-run the event extraction of an event source plugin for the
+    /// transaction `tx_hash`, returning the parse results. This has no side
+    /// effects, and is only supported by event source plugins.
+    #[method(name = "debugParseTx")]
+    async fn debug_parse_tx(&self, tx_hash: H256) -> RpcResult<Value> {
+        let _ = tx_hash;
+
+        Err(ErrorObject::owned(
+            METHOD_NOT_FOUND_CODE,
+            "parsing transactions is not supported by this plugin",
+            None::<()>,
+        ))
+    }
+
+    /// Estimate the cost of submitting `msgs` in a single transaction, without
+    /// broadcasting it. Only supported by transaction plugins.
+    #[method(name = "estimate")]
+    async fn estimate(&self, msgs: Vec<IbcDatagram>) -> RpcResult<TxEstimate> {
+        let _ = msgs;
+
+        Err(ErrorObject::owned(
+            METHOD_NOT_FOUND_CODE,
+            "estimating transactions is not supported by this plugin",
+            None::<()>,
+        ))
+    }
+
+    /// Export the caches of this plugin, such that they can be imported into another instance of
+    /// the plugin with [`import_caches`](Self::import_caches). Plugins without any exportable
+    /// caches return an error.
+    #[method(name = "exportCaches")]
+    async fn export_caches(&self) -> RpcResult<CacheSnapshot> {
+        Err(ErrorObject::owned(
+            METHOD_NOT_FOUND_CODE,
+            "exporting caches is not supported by this plugin",
+            None::<()>,
+        ))
+    }
+
+    /// Merge a snapshot exported with [`export_caches`](Self::export_caches) into the caches of
+    /// this plugin. Local entries are never overwritten with older entries, see the
+    /// [`cache_snapshot`](crate::cache_snapshot) module.
+    #[method(name = "importCaches")]
+    async fn import_caches(&self, snapshot: CacheSnapshot) -> RpcResult<ImportReport> {
+        let _ = snapshot;
+
+        Err(ErrorObject::owned(
+            METHOD_NOT_FOUND_CODE,
+            "importing caches is not supported by this plugin",
+            None::<()>,
+        ))
+    }
+
+    /// The highest packet sequences relayed on `channel` of this chain, see
+    /// [`relay_progress`](chain_utils::relay_progress). Only supported by transaction plugins.
+    #[method(name = "relayProgress")]
+    async fn relay_progress(&self, channel: String) -> RpcResult<RelayProgress> {
+        let _ = channel;
+
+        Err(ErrorObject::owned(
+            METHOD_NOT_FOUND_CODE,
+            "relay progress is not tracked by this plugin",
+            None::<()>,
+        ))
+    }
 }
 
-#[rpc(
-    client,
-    server,
-    client_bounds(Self:),
-    server_bounds(Self:),
-    namespace = "state",
+#[cfg_attr(
+    feature = "server",
+    rpc(
+        client,
+        server,
+        client_bounds(Self:),
+        server_bounds(Self:),
+        namespace = "state",
+    )
+)]
+#[cfg_attr(
+    not(feature = "server"),
+    rpc(client, client_bounds(Self:), namespace = "state")
 )]
 pub trait StateModule<V: IbcSpec> {
     /// Query a proof of IBC state on this chain, at the specified [`Height`],
@@ -284,6 +523,47 @@ pub trait StateModule<V: IbcSpec> {
     /// Fetch the client info of a client on this chain.
     #[method(name = "clientInfo", with_extensions)]
     async fn client_info(&self, client_id: V::ClientId) -> RpcResult<ClientInfo>;
+
+    /// Query the values of the keys in `range` of the mapping that `path` is
+    /// in, at the specified [`Height`]. The key that `path` is for is ignored.
+    /// Keys without a value are not returned.
+    ///
+    /// `range` must not be larger than [`MAX_QUERY_RANGE`] keys.
+    ///
+    /// [`MAX_QUERY_RANGE`]: crate::consensus_heights::MAX_QUERY_RANGE
+    #[method(name = "queryRange")]
+    async fn query_range(
+        &self,
+        at: Height,
+        path: V::StorePath,
+        range: QueryRange,
+    ) -> RpcResult<Vec<RangeEntry>> {
+        let _ = (at, path, range);
+
+        Err(ErrorObject::owned(
+            METHOD_NOT_FOUND_CODE,
+            "range queries are not supported by this module",
+            None::<()>,
+        ))
+    }
+
+    /// List the heights of the consensus states of a client on this chain, as
+    /// stored at the specified [`Height`], sorted descending.
+    #[method(name = "consensusStateHeights")]
+    async fn consensus_state_heights(
+        &self,
+        at: Height,
+        client_id: V::ClientId,
+        pagination: Pagination,
+    ) -> RpcResult<ConsensusStateHeights> {
+        let _ = (at, client_id, pagination);
+
+        Err(ErrorObject::owned(
+            METHOD_NOT_FOUND_CODE,
+            "listing consensus state heights is not supported by this module",
+            None::<()>,
+        ))
+    }
 }
 
 /// Type-erased version of [`StateModuleClient`].
@@ -294,13 +574,37 @@ pub trait RawStateModule {
 
     #[method(name = "clientInfo")]
     async fn client_info_raw(&self, client_id: RawClientId) -> RpcResult<ClientInfo>;
+
+    #[method(name = "queryRange")]
+    async fn query_range_raw(
+        &self,
+        at: Height,
+        path: Value,
+        range: QueryRange,
+    ) -> RpcResult<Vec<RangeEntry>>;
+
+    #[method(name = "consensusStateHeights")]
+    async fn consensus_state_heights_raw(
+        &self,
+        at: Height,
+        client_id: RawClientId,
+        pagination: Pagination,
+    ) -> RpcResult<ConsensusStateHeights>;
 }
 
-#[rpc(client,
-    server,
-    client_bounds(Self:),
-    server_bounds(Self:),
-    namespace = "proof",
+#[cfg_attr(
+    feature = "server",
+    rpc(
+        client,
+        server,
+        client_bounds(Self:),
+        server_bounds(Self:),
+        namespace = "proof",
+    )
+)]
+#[cfg_attr(
+    not(feature = "server"),
+    rpc(client, client_bounds(Self:), namespace = "proof")
 )]
 pub trait ProofModule<V: IbcSpec> {
     /// Query a proof of IBC state on this chain, at the specified [`Height`],
@@ -320,7 +624,8 @@ pub trait RawProofModule {
 /// type, on a single IBC interface. This can also be thought of as a "client
 /// codec", as all of the endpoints it exposes are related to encoding and
 /// decoding state.
-#[rpc(client, server, namespace = "client")]
+#[cfg_attr(feature = "server", rpc(client, server, namespace = "client"))]
+#[cfg_attr(not(feature = "server"), rpc(client, namespace = "client"))]
 // TODO: Rename to client codec module
 pub trait ClientModule {
     /// Decode the raw client state, returning the decoded metadata common
@@ -328,6 +633,19 @@ pub trait ClientModule {
     #[method(name = "decodeClientStateMeta", with_extensions)]
     async fn decode_client_state_meta(&self, client_state: Bytes) -> RpcResult<ClientStateMeta>;
 
+    /// Compute the status of a client from its raw client state and the raw
+    /// consensus state at the latest height of the client.
+    ///
+    /// `timestamp_nanos` is the current time, which the trusting period of
+    /// the client (if any) is compared against.
+    #[method(name = "clientStatus", with_extensions)]
+    async fn client_status(
+        &self,
+        client_state: Bytes,
+        consensus_state: Bytes,
+        timestamp_nanos: u64,
+    ) -> RpcResult<ClientStatus>;
+
     /// Decode the raw consensus state, returning the decoded metadata common
     /// between all consensus state types.
     #[method(name = "decodeConsensusStateMeta", with_extensions)]
@@ -387,7 +705,8 @@ pub trait ClientModule {
 
 /// Client modules provide functionality for interacting with a specific chain
 /// consensus and finality.
-#[rpc(client, server, namespace = "consensus")]
+#[cfg_attr(feature = "server", rpc(client, server, namespace = "consensus"))]
+#[cfg_attr(not(feature = "server"), rpc(client, namespace = "consensus"))]
 pub trait ConsensusModule {
     /// Query the latest finalized height of this chain.
     #[method(name = "queryLatestHeight", with_extensions)]
@@ -398,6 +717,38 @@ pub trait ConsensusModule {
     // TODO: Make this return a better type than i64
     async fn query_latest_timestamp(&self, finalized: bool) -> RpcResult<i64>;
 
+    /// Query the timestamp of the block at the specified [`Height`] of this
+    /// chain, in nanoseconds since the unix epoch.
+    ///
+    /// Returns an error if there is no block at `height` (yet).
+    #[method(name = "timestampAtHeight")]
+    async fn timestamp_at_height(&self, height: Height) -> RpcResult<i64> {
+        let _ = height;
+
+        Err(ErrorObject::owned(
+            METHOD_NOT_FOUND_CODE,
+            "timestamp queries are not supported by this module",
+            None::<()>,
+        ))
+    }
+
+    /// Translate a consensus [`Height`] of this chain to the execution block
+    /// number that was finalized at it.
+    ///
+    /// This is only supported by modules for chains where the two differ (i.e.
+    /// beacon-kit chains), where the state proven at a consensus height must
+    /// be queried from the execution layer at the translated height.
+    #[method(name = "consensusToExecutionHeight")]
+    async fn consensus_to_execution_height(&self, height: Height) -> RpcResult<u64> {
+        let _ = height;
+
+        Err(ErrorObject::owned(
+            METHOD_NOT_FOUND_CODE,
+            "height translation is not supported by this module",
+            None::<()>,
+        ))
+    }
+
     /// The client state of this chain at the specified [`Height`].
     ///
     /// Returns the client state value as JSON, which will then be encoded to
@@ -412,3 +763,105 @@ pub trait ConsensusModule {
     #[method(name = "selfConsensusState", with_extensions)]
     async fn self_consensus_state(&self, height: Height) -> RpcResult<Value>;
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn plugin_info_legacy_decode() {
+        let info = serde_json::from_value::<PluginInfo>(json!({
+            "name": "plugin",
+            "interest_filter": "true",
+        }))
+        .unwrap();
+
+        assert_eq!(
+            info,
+            PluginInfo {
+                name: "plugin".to_owned(),
+                interest_filter: "true".to_owned(),
+                kind: None,
+                chains: vec![],
+                ibc_specs: vec![],
+            }
+        );
+
+        // new fields are not serialized if they are empty
+        assert_eq!(
+            serde_json::to_value(&info).unwrap(),
+            json!({
+                "name": "plugin",
+                "interest_filter": "true",
+            })
+        );
+    }
+
+    #[test]
+    fn chain_id_mismatch() {
+        let expected = ChainId::new("union-1");
+
+        ensure_chain_id(&expected, "union-1").unwrap();
+
+        let err = ensure_chain_id(&expected, "union-testnet-9").unwrap_err();
+
+        assert_eq!(err.found, "union-testnet-9");
+        assert_eq!(
+            err.to_string(),
+            "invalid chain id: expected `union-1` but the rpc responded with `union-testnet-9`"
+        );
+    }
+
+    #[test]
+    fn reload_report_diff() {
+        let old = json!({ "chain_id": "union-1", "gas": 1, "memo": "a", "url": "x" });
+        let new = json!({ "chain_id": "union-2", "gas": 2, "memo": "a", "url": "x" });
+
+        let report = ReloadReport::diff(&old, &new, &["gas", "memo"]);
+
+        assert_eq!(
+            report,
+            ReloadReport {
+                changed: vec!["gas".to_owned()],
+                rejected: vec![RejectedField {
+                    field: "chain_id".to_owned(),
+                    reason: "requires restart".to_owned(),
+                }],
+            }
+        );
+
+        assert_eq!(ReloadReport::diff(&old, &old, &[]), ReloadReport::default());
+    }
+
+    #[test]
+    fn plugin_info_served() {
+        let info = PluginInfo {
+            name: "plugin".to_owned(),
+            interest_filter: "true".to_owned(),
+            kind: Some(PluginKind::EventSource),
+            chains: vec![ChainId::new("union-1")],
+            ibc_specs: vec![
+                IbcSpecId::new(IbcSpecId::CLASSIC),
+                IbcSpecId::new(IbcSpecId::UNION),
+            ],
+        };
+
+        assert_eq!(
+            serde_json::to_value(&info).unwrap()["kind"],
+            json!("event_source")
+        );
+
+        assert_eq!(
+            info.served().collect::<Vec<_>>(),
+            [
+                (
+                    &ChainId::new("union-1"),
+                    &IbcSpecId::new(IbcSpecId::CLASSIC)
+                ),
+                (&ChainId::new("union-1"), &IbcSpecId::new(IbcSpecId::UNION)),
+            ]
+        );
+    }
+}
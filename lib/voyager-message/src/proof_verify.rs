@@ -0,0 +1,936 @@
+//! Local verification of IBC proofs against a consensus state.
+//!
+//! Light clients verify the proofs in packet datagrams on chain, so a proof
+//! that was generated at the wrong height (or against the wrong path) is only
+//! noticed once the transaction reverts. [`verify_ibc_proof`] runs the same
+//! verification off chain, against the JSON representation of the consensus
+//! state as returned by the client module, such that these datagrams can be
+//! rejected before they are submitted.
+//!
+//! Verification is dispatched on the client type to a [`VerifierBackend`]:
+//!
+//! - [`VerifierBackend::Ics23`]: tendermint and cometbls clients, verifying
+//!   the chained ics23 proof against the app hash.
+//! - [`VerifierBackend::Mpt`]: ethereum clients, verifying the storage proof
+//!   against the storage root of the IBC handler.
+//!
+//! Clients without a backend are reported as
+//! [`VerifyError::UnsupportedClient`].
+
+use ethereum_light_client_types::StorageProof;
+use evm_storage_verifier::{verify_storage_absence, verify_storage_proof};
+use ibc_union_spec::{
+    BatchPacketsPath, BatchReceiptsPath, ChannelPath, ClientStatePath, ConnectionPath,
+    ConsensusStatePath, Datagram, IbcUnion, COMMITMENT_MAGIC,
+};
+use ics23::ibc_api::{VerifyMembershipError, SDK_SPECS};
+use itertools::Itertools;
+use jsonrpsee::{
+    core::RpcResult,
+    types::{error::INVALID_PARAMS_CODE, ErrorObject, ErrorObjectOwned},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::debug;
+use unionlabs::{
+    bytes::Bytes,
+    ethereum::ibc_commitment_key,
+    hash::H256,
+    ibc::core::{
+        client::height::Height,
+        commitment::{merkle_proof::MerkleProof, merkle_root::MerkleRoot},
+    },
+    uint::U256,
+    ErrorReporter,
+};
+use voyager_core::{ChainId, ClientType, IbcInterface, IbcSpec};
+
+use crate::{error::VoyagerError, query::VoyagerQuery, RawClientId};
+
+/// The verification scheme used for the proofs of a client type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VerifierBackend {
+    /// Chained ics23 proofs against the app hash of a cosmos-sdk chain.
+    Ics23,
+    /// Merkle-patricia trie storage proofs against the storage root of a
+    /// contract.
+    Mpt,
+}
+
+/// The verification backend for proofs verified by a client of type
+/// `client_type` on `ibc_interface`, if there is one.
+pub fn backend_for(
+    client_type: &ClientType,
+    ibc_interface: &IbcInterface,
+) -> Option<VerifierBackend> {
+    match (client_type.as_str(), ibc_interface.as_str()) {
+        (ClientType::TENDERMINT | ClientType::COMETBLS, _) => Some(VerifierBackend::Ics23),
+        (ClientType::ETHEREUM, _) => Some(VerifierBackend::Mpt),
+        _ => None,
+    }
+}
+
+/// A successfully verified proof.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Verified {
+    pub backend: VerifierBackend,
+    /// Whether the proof was verified as a membership proof (as opposed to a
+    /// non-membership proof).
+    pub membership: bool,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum VerifyError {
+    #[error("no proof verifier for client type {client_type} on {ibc_interface}")]
+    UnsupportedClient {
+        client_type: ClientType,
+        ibc_interface: IbcInterface,
+    },
+    #[error("invalid consensus state")]
+    ConsensusState(#[source] serde_json::Error),
+    #[error("invalid client state")]
+    ClientState(#[source] serde_json::Error),
+    #[error("invalid proof")]
+    Proof(#[source] serde_json::Error),
+    #[error("expected a key path of a single 32 byte storage slot, found {0:?}")]
+    InvalidPath(Vec<Bytes>),
+    #[error("expected a 32 byte value, found {0}")]
+    InvalidValueLength(Bytes),
+    #[error("ics23 proof verification failed")]
+    Ics23(#[source] VerifyMembershipError),
+    #[error("storage proof verification failed")]
+    Mpt(#[source] evm_storage_verifier::error::Error),
+    #[error("storage proof is for slot {found}, expected {expected}")]
+    SlotMismatch { expected: U256, found: U256 },
+    #[error("storage proof is for value {found}, expected {expected}")]
+    ValueMismatch { expected: H256, found: H256 },
+    #[error("a value is stored at slot {0}")]
+    NotAbsent(U256),
+    #[error("channel {channel_id} does not exist on {chain_id}")]
+    ChannelNotFound { chain_id: ChainId, channel_id: u32 },
+    #[error("connection {connection_id} does not exist on {chain_id}")]
+    ConnectionNotFound {
+        chain_id: ChainId,
+        connection_id: u32,
+    },
+    #[error("client {client_id} on {chain_id} has no consensus state at height {height}")]
+    ConsensusStateNotFound {
+        chain_id: ChainId,
+        client_id: u32,
+        height: u64,
+    },
+    #[error(
+        "the proof in the datagram does not match the proof of {path} on {chain_id} at {height}"
+    )]
+    ProofMismatch {
+        chain_id: ChainId,
+        path: String,
+        height: Height,
+    },
+    #[error(transparent)]
+    Rpc(#[from] ErrorObjectOwned),
+}
+
+impl From<VerifyError> for ErrorObjectOwned {
+    fn from(value: VerifyError) -> Self {
+        match value {
+            VerifyError::Rpc(err) => err,
+            err => ErrorObject::owned(
+                INVALID_PARAMS_CODE,
+                ErrorReporter(err).to_string(),
+                None::<()>,
+            ),
+        }
+    }
+}
+
+/// Verify `proof` of `path` against `consensus_state`, for a client of type
+/// `client_type` on `ibc_interface`.
+///
+/// If `value` is set, `proof` is verified as a membership proof of `value`,
+/// otherwise as a non-membership proof. The meaning of `path` depends on the
+/// backend:
+///
+/// - [`VerifierBackend::Ics23`]: the full key path, one key per proof in the
+///   chain (i.e. the store prefix followed by the key).
+/// - [`VerifierBackend::Mpt`]: a single 32 byte storage slot. Values are 32
+///   byte storage words.
+pub fn verify_ibc_proof(
+    client_type: &ClientType,
+    ibc_interface: &IbcInterface,
+    consensus_state: &Value,
+    proof: &Value,
+    path: &[Vec<u8>],
+    value: Option<Vec<u8>>,
+) -> Result<Verified, VerifyError> {
+    let backend =
+        backend_for(client_type, ibc_interface).ok_or_else(|| VerifyError::UnsupportedClient {
+            client_type: client_type.clone(),
+            ibc_interface: ibc_interface.clone(),
+        })?;
+
+    let membership = value.is_some();
+
+    match backend {
+        VerifierBackend::Ics23 => verify_ics23(consensus_state, proof, path, value)?,
+        VerifierBackend::Mpt => verify_mpt(consensus_state, proof, path, value)?,
+    }
+
+    Ok(Verified {
+        backend,
+        membership,
+    })
+}
+
+/// The commitment root of tendermint (`root`) and cometbls (`app_hash`)
+/// consensus states.
+#[derive(Deserialize)]
+struct Ics23ConsensusState {
+    #[serde(alias = "app_hash")]
+    root: MerkleRoot,
+}
+
+fn verify_ics23(
+    consensus_state: &Value,
+    proof: &Value,
+    path: &[Vec<u8>],
+    value: Option<Vec<u8>>,
+) -> Result<(), VerifyError> {
+    let Ics23ConsensusState { root } =
+        Ics23ConsensusState::deserialize(consensus_state).map_err(VerifyError::ConsensusState)?;
+
+    let proof = MerkleProof::deserialize(proof).map_err(VerifyError::Proof)?;
+
+    match value {
+        Some(value) => ics23::ibc_api::verify_membership(&proof, &SDK_SPECS, &root, path, value),
+        None => ics23::ibc_api::verify_non_membership(&proof, &SDK_SPECS, &root, path),
+    }
+    .map_err(VerifyError::Ics23)
+}
+
+/// The storage root of the IBC handler in ethereum consensus states.
+#[derive(Deserialize)]
+struct MptConsensusState {
+    storage_root: H256,
+}
+
+fn verify_mpt(
+    consensus_state: &Value,
+    proof: &Value,
+    path: &[Vec<u8>],
+    value: Option<Vec<u8>>,
+) -> Result<(), VerifyError> {
+    let MptConsensusState { storage_root } =
+        MptConsensusState::deserialize(consensus_state).map_err(VerifyError::ConsensusState)?;
+
+    let proof = StorageProof::deserialize(proof).map_err(VerifyError::Proof)?;
+
+    let slot = match path {
+        [slot] => U256::try_from_be_bytes(slot).ok(),
+        _ => None,
+    }
+    .ok_or_else(|| VerifyError::InvalidPath(path.iter().cloned().map(Into::into).collect()))?;
+
+    if proof.key != slot {
+        return Err(VerifyError::SlotMismatch {
+            expected: slot,
+            found: proof.key,
+        });
+    }
+
+    match value {
+        Some(value) => {
+            let value = H256::try_from(&value)
+                .map_err(|_| VerifyError::InvalidValueLength(value.into()))?;
+
+            let proof_value = H256::new(proof.value.to_be_bytes());
+
+            if value != proof_value {
+                return Err(VerifyError::ValueMismatch {
+                    expected: value,
+                    found: proof_value,
+                });
+            }
+
+            verify_storage_proof(
+                storage_root,
+                proof.key,
+                &rlp::encode(&proof.value),
+                &proof.proof,
+            )
+            .map_err(VerifyError::Mpt)
+        }
+        None => {
+            if verify_storage_absence(storage_root, proof.key, &proof.proof)
+                .map_err(VerifyError::Mpt)?
+            {
+                Ok(())
+            } else {
+                Err(VerifyError::NotAbsent(proof.key))
+            }
+        }
+    }
+}
+
+/// The key path that the counterparty commitment `key` is proven under, for a
+/// client of type `client_type` on `ibc_interface` with the (decoded) client
+/// state `client_state`.
+///
+/// Only the clients that track an ibc-union deployment whose commitment
+/// prefix can be derived from the client state are supported:
+///
+/// - ethereum clients prove the slot of `key` in the commitments mapping of
+///   the IBC handler.
+/// - cometbls clients prove `key` in the storage of the ibc-union contract,
+///   under the wasm module store.
+pub fn union_commitment_path(
+    client_type: &ClientType,
+    ibc_interface: &IbcInterface,
+    client_state: &Value,
+    key: H256,
+) -> Result<Vec<Vec<u8>>, VerifyError> {
+    /// The address of the ibc-union contract in cometbls client states.
+    #[derive(Deserialize)]
+    struct ContractAddress {
+        contract_address: H256,
+    }
+
+    match client_type.as_str() {
+        ClientType::ETHEREUM => Ok(vec![ibc_commitment_key(key).to_be_bytes().to_vec()]),
+        ClientType::COMETBLS => {
+            let ContractAddress { contract_address } =
+                ContractAddress::deserialize(client_state).map_err(VerifyError::ClientState)?;
+
+            // see `CometblsClient.verifyMembership`
+            Ok(vec![
+                b"wasm".to_vec(),
+                [
+                    &[0x03],
+                    contract_address.get().as_slice(),
+                    key.get().as_slice(),
+                ]
+                .concat(),
+            ])
+        }
+        _ => Err(VerifyError::UnsupportedClient {
+            client_type: client_type.clone(),
+            ibc_interface: ibc_interface.clone(),
+        }),
+    }
+}
+
+/// The commitment proven by a packet datagram.
+struct Commitment {
+    /// The channel on the chain the datagram is submitted to.
+    channel_id: u32,
+    proof_height: u64,
+    proof: Bytes,
+    /// The path of the commitment on the counterparty chain.
+    path: CommitmentPath,
+    /// The committed value, or `None` if the absence of the commitment is
+    /// proven.
+    value: Option<H256>,
+}
+
+enum CommitmentPath {
+    BatchPackets(BatchPacketsPath),
+    BatchReceipts(BatchReceiptsPath),
+}
+
+impl Commitment {
+    /// The commitment proven by `datagram`, if it is a packet datagram whose
+    /// commitment can be computed locally.
+    ///
+    /// Acknowledgements of batches are committed to with the hash of all
+    /// acknowledgements in the batch, which is not computed here, so these
+    /// are not verified.
+    fn from_datagram(datagram: &Datagram) -> Option<Self> {
+        match datagram {
+            Datagram::PacketRecv(msg) => {
+                let packet = msg.packets.first()?;
+
+                Some(Self {
+                    channel_id: packet.destination_channel,
+                    proof_height: msg.proof_height,
+                    proof: msg.proof.clone(),
+                    path: CommitmentPath::BatchPackets(BatchPacketsPath {
+                        channel_id: packet.source_channel,
+                        batch_hash: match &*msg.packets {
+                            [packet] => ibc_union_spec::commit_packet(packet),
+                            packets => ibc_union_spec::commit_packets(packets),
+                        },
+                    }),
+                    value: Some(COMMITMENT_MAGIC),
+                })
+            }
+            Datagram::PacketAcknowledgement(msg) => {
+                let ([packet], [ack]) = (&*msg.packets, &*msg.acknowledgements) else {
+                    return None;
+                };
+
+                Some(Self {
+                    channel_id: packet.source_channel,
+                    proof_height: msg.proof_height,
+                    proof: msg.proof.clone(),
+                    path: CommitmentPath::BatchReceipts(BatchReceiptsPath::from_packet(packet)),
+                    value: Some(ibc_union_spec::commit_ack(ack)),
+                })
+            }
+            Datagram::PacketTimeout(msg) => Some(Self {
+                channel_id: msg.packet.source_channel,
+                proof_height: msg.proof_height,
+                proof: msg.proof.clone(),
+                path: CommitmentPath::BatchReceipts(BatchReceiptsPath::from_packet(&msg.packet)),
+                value: None,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Verify the proof in the ibc-union packet `datagram` that is to be
+/// submitted on `chain_id`, against the consensus state of the client that
+/// will verify it on chain.
+///
+/// The proof is regenerated on the counterparty chain at the proof height of
+/// the datagram and must encode to the exact proof in the datagram, which is
+/// then verified with [`verify_ibc_proof`].
+///
+/// Returns `None` if the proof of the datagram can't be verified locally,
+/// either because the datagram is not a packet datagram, or the client has no
+/// [`VerifierBackend`] (or no known commitment path, see
+/// [`union_commitment_path`]).
+pub async fn verify_union_datagram(
+    client: &impl VoyagerQuery,
+    chain_id: &ChainId,
+    datagram: &Datagram,
+) -> Result<Option<Verified>, VerifyError> {
+    let Some(commitment) = Commitment::from_datagram(datagram) else {
+        return Ok(None);
+    };
+
+    let height = client.query_latest_height(chain_id, false).await?;
+
+    let channel = client
+        .query_ibc_state(
+            chain_id,
+            height.into(),
+            ChannelPath {
+                channel_id: commitment.channel_id,
+            },
+        )
+        .await?
+        .state
+        .ok_or_else(|| VerifyError::ChannelNotFound {
+            chain_id: chain_id.clone(),
+            channel_id: commitment.channel_id,
+        })?;
+
+    let client_id = client
+        .query_ibc_state(
+            chain_id,
+            height.into(),
+            ConnectionPath {
+                connection_id: channel.connection_id,
+            },
+        )
+        .await?
+        .state
+        .ok_or_else(|| VerifyError::ConnectionNotFound {
+            chain_id: chain_id.clone(),
+            connection_id: channel.connection_id,
+        })?
+        .client_id;
+
+    let client_info = client
+        .client_info_raw(chain_id, &IbcUnion::ID, RawClientId::new(client_id))
+        .await?;
+
+    if backend_for(&client_info.client_type, &client_info.ibc_interface).is_none() {
+        debug!(
+            %client_id,
+            client_type = %client_info.client_type,
+            ibc_interface = %client_info.ibc_interface,
+            "no proof verifier for client, not verifying proof"
+        );
+
+        return Ok(None);
+    }
+
+    let client_state = client
+        .query_ibc_state(chain_id, height.into(), ClientStatePath { client_id })
+        .await?
+        .state;
+    let client_state = client
+        .decode_client_state(
+            &client_info.client_type,
+            &client_info.ibc_interface,
+            &IbcUnion::ID,
+            client_state,
+        )
+        .await?;
+
+    let key = match &commitment.path {
+        CommitmentPath::BatchPackets(path) => path.key(),
+        CommitmentPath::BatchReceipts(path) => path.key(),
+    };
+
+    let path = match union_commitment_path(
+        &client_info.client_type,
+        &client_info.ibc_interface,
+        &client_state,
+        key,
+    ) {
+        Ok(path) => path,
+        Err(VerifyError::UnsupportedClient { .. }) => {
+            debug!(
+                %client_id,
+                client_type = %client_info.client_type,
+                "unknown commitment path for client, not verifying proof"
+            );
+
+            return Ok(None);
+        }
+        Err(err) => return Err(err),
+    };
+
+    let consensus_state = client
+        .query_ibc_state(
+            chain_id,
+            height.into(),
+            ConsensusStatePath {
+                client_id,
+                height: commitment.proof_height,
+            },
+        )
+        .await?
+        .state;
+
+    if consensus_state.is_empty() {
+        return Err(VerifyError::ConsensusStateNotFound {
+            chain_id: chain_id.clone(),
+            client_id,
+            height: commitment.proof_height,
+        });
+    }
+
+    let consensus_state = client
+        .decode_consensus_state(
+            &client_info.client_type,
+            &client_info.ibc_interface,
+            &IbcUnion::ID,
+            consensus_state,
+        )
+        .await?;
+
+    let counterparty_chain_id = client
+        .client_meta_raw(
+            chain_id,
+            &IbcUnion::ID,
+            height.into(),
+            RawClientId::new(client_id),
+        )
+        .await?
+        .chain_id;
+
+    let proof_height = Height::new(commitment.proof_height);
+
+    let (proof, path_display) = match commitment.path {
+        CommitmentPath::BatchPackets(path) => (
+            client
+                .query_ibc_proof(&counterparty_chain_id, proof_height.into(), path.clone())
+                .await?
+                .proof,
+            path.to_string(),
+        ),
+        CommitmentPath::BatchReceipts(path) => (
+            client
+                .query_ibc_proof(&counterparty_chain_id, proof_height.into(), path.clone())
+                .await?
+                .proof,
+            path.to_string(),
+        ),
+    };
+
+    if client
+        .encode_proof::<IbcUnion>(&client_info, proof.clone())
+        .await?
+        != commitment.proof
+    {
+        return Err(VerifyError::ProofMismatch {
+            chain_id: counterparty_chain_id,
+            path: path_display,
+            height: proof_height,
+        });
+    }
+
+    verify_ibc_proof(
+        &client_info.client_type,
+        &client_info.ibc_interface,
+        &consensus_state,
+        &proof,
+        &path,
+        commitment.value.map(|value| value.get().to_vec()),
+    )
+    .map(Some)
+}
+
+/// Split `msgs` into the ones that can be submitted, and the ones whose proof
+/// failed to verify with [`verify_union_datagram`], along with the error.
+///
+/// Messages that aren't ibc-union datagrams (as determined by `datagram`), or
+/// whose proof can't be verified locally, can be submitted. Rpc errors are not
+/// a verification failure, and are returned as is.
+pub async fn partition_verified<T>(
+    client: &impl VoyagerQuery,
+    chain_id: &ChainId,
+    msgs: Vec<T>,
+    datagram: impl Fn(&T) -> Option<&Datagram>,
+) -> RpcResult<(Vec<T>, Vec<(T, VerifyError)>)> {
+    let mut verified = vec![];
+    let mut rejected = vec![];
+
+    for msg in msgs {
+        let res = match datagram(&msg) {
+            Some(datagram) => verify_union_datagram(client, chain_id, datagram).await,
+            None => Ok(None),
+        };
+
+        match res {
+            Ok(_) => verified.push(msg),
+            Err(VerifyError::Rpc(err)) => return Err(err),
+            Err(err) => rejected.push((msg, err)),
+        }
+    }
+
+    Ok((verified, rejected))
+}
+
+/// The error to fail the datagrams rejected by [`partition_verified`] with.
+///
+/// This is fatal, since the proof of a rejected datagram would be rejected by
+/// the light client on chain as well.
+pub fn rejected_error<T>(rejected: &[(T, VerifyError)]) -> VoyagerError {
+    VoyagerError::fatal(format!(
+        "{} datagram(s) failed local proof verification: {}",
+        rejected.len(),
+        rejected
+            .iter()
+            .map(|(_, err)| ErrorReporter(err).to_string())
+            .join("; ")
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use hex_literal::hex;
+    use serde_json::json;
+    use unionlabs::encoding::{DecodeAs, Proto};
+
+    use super::*;
+
+    // connection-1 on union-testnet-8, see the `connection_exists` test in ics23
+    const ICS23_ROOT: [u8; 32] =
+        hex!("899CD0B55A4FEDE9AF3C959C43ED3AE6805293642590A81CD95B4C97F89CC424");
+    const ICS23_PROOF: &[u8] = &hex!("0abc020ab9020a18636f6e6e656374696f6e732f636f6e6e656374696f6e2d31125b0a0930382d7761736d2d3112230a0131120d4f524445525f4f524445524544120f4f524445525f554e4f524445524544180222250a0e636f6d6574626c732d6e65772d30120c636f6e6e656374696f6e2d301a050a0369626328061a0c0801180120012a040002f006222c080112050204f006201a212075c4910f51207d3c65960120fe931f138e2624668d75869f51b8442593dd5eab222a080112260408de0a2002b6fcf07091245d162f1196b003c555c564980e02c4d4a9fa0a249798f4b25e20222c08011205060ede0a201a2120ff6b0a04e076eecbabfee4e751c0523cbedba898211b5847404e2d954a2203e3222a08011226081ede0a20635053419cfb6a81c839860d99f3ed002840124a790ddd9f066d8bce63f9df54200afc010af9010a03696263122024b15e198bcf648dee62c7ca1fd8c3950c85c3d898833180c3e3c412ccbc559d1a090801180120012a01002225080112210106b99c0d8119ff1edbcbe165d0f19337dbbc080e677c88e57aa2ae767ebf0f0f222708011201011a20aa650406ea0d76e39dd43d2ea6a91e3fdaa1c908fc21a7ca68e5e62cc8115639222508011221016ac3182364d7cdaa1f52a77b6081e070aa29b2d253f3642169693cde336e2bdc222508011221016376cbd7b917c7105ddac35bdeddd79e6c9cbbc66dd227941599de2b9bc8b3de222708011201011a200d68ac7c3e8daf94c65ccdfe5b7397f50e80325240ef9b2a0ec483afaea30544");
+    const ICS23_VALUE: &[u8] = &hex!("0a0930382d7761736d2d3112230a0131120d4f524445525f4f524445524544120f4f524445525f554e4f524445524544180222250a0e636f6d6574626c732d6e65772d30120c636f6e6e656374696f6e2d301a050a036962632806");
+
+    fn ics23_fixture() -> (Value, Value, Vec<Vec<u8>>) {
+        let consensus_state = json!({
+            "app_hash": MerkleRoot {
+                hash: H256::new(ICS23_ROOT),
+            },
+        });
+
+        let proof =
+            serde_json::to_value(MerkleProof::decode_as::<Proto>(ICS23_PROOF).unwrap()).unwrap();
+
+        let path = vec![b"ibc".to_vec(), b"connections/connection-1".to_vec()];
+
+        (consensus_state, proof, path)
+    }
+
+    /// A storage proof fixture from the ethereum light client tests.
+    #[derive(Deserialize)]
+    struct MptFixture {
+        key: String,
+        value: String,
+        proof: Vec<Bytes>,
+        storage_root: H256,
+    }
+
+    fn mpt_fixture(json: &str) -> (Value, Value, Vec<Vec<u8>>, U256) {
+        let fixture = serde_json::from_str::<MptFixture>(json).unwrap();
+
+        let key = U256::from_be_hex(&fixture.key).unwrap();
+        let value = U256::from_be_hex(&fixture.value).unwrap();
+
+        let proof = serde_json::to_value(StorageProof {
+            key,
+            value,
+            proof: fixture.proof.into_iter().map(Into::into).collect(),
+        })
+        .unwrap();
+
+        (
+            json!({ "storage_root": fixture.storage_root }),
+            proof,
+            vec![key.to_be_bytes().to_vec()],
+            value,
+        )
+    }
+
+    const MPT_MEMBERSHIP: &str = include_str!(
+        "../../../cosmwasm/union-ibc/light-clients/ethereum/src/test/memberships/valid_connection_end.json"
+    );
+    const MPT_NON_MEMBERSHIP: &str = include_str!(
+        "../../../cosmwasm/union-ibc/light-clients/ethereum/src/test/memberships/valid_non_membership_proof.json"
+    );
+
+    fn cometbls() -> (ClientType, IbcInterface) {
+        (
+            ClientType::new(ClientType::COMETBLS),
+            IbcInterface::new(IbcInterface::IBC_SOLIDITY),
+        )
+    }
+
+    fn ethereum() -> (ClientType, IbcInterface) {
+        (
+            ClientType::new(ClientType::ETHEREUM),
+            IbcInterface::new(IbcInterface::IBC_COSMWASM),
+        )
+    }
+
+    #[test]
+    fn backend_dispatch() {
+        let (client_type, ibc_interface) = cometbls();
+        assert_eq!(
+            backend_for(&client_type, &ibc_interface),
+            Some(VerifierBackend::Ics23)
+        );
+
+        assert_eq!(
+            backend_for(
+                &ClientType::new(ClientType::TENDERMINT),
+                &IbcInterface::new(IbcInterface::IBC_GO_V8_NATIVE)
+            ),
+            Some(VerifierBackend::Ics23)
+        );
+
+        let (client_type, ibc_interface) = ethereum();
+        assert_eq!(
+            backend_for(&client_type, &ibc_interface),
+            Some(VerifierBackend::Mpt)
+        );
+
+        assert!(matches!(
+            verify_ibc_proof(
+                &ClientType::new(ClientType::MOVEMENT),
+                &IbcInterface::new(IbcInterface::IBC_COSMWASM),
+                &json!({}),
+                &json!({}),
+                &[],
+                None,
+            ),
+            Err(VerifyError::UnsupportedClient { .. })
+        ));
+    }
+
+    #[test]
+    fn ics23_membership() {
+        let (client_type, ibc_interface) = cometbls();
+        let (consensus_state, proof, path) = ics23_fixture();
+
+        assert_eq!(
+            verify_ibc_proof(
+                &client_type,
+                &ibc_interface,
+                &consensus_state,
+                &proof,
+                &path,
+                Some(ICS23_VALUE.to_vec()),
+            )
+            .unwrap(),
+            Verified {
+                backend: VerifierBackend::Ics23,
+                membership: true,
+            }
+        );
+    }
+
+    #[test]
+    fn ics23_membership_fails_for_wrong_value() {
+        let (client_type, ibc_interface) = cometbls();
+        let (consensus_state, proof, path) = ics23_fixture();
+
+        let mut value = ICS23_VALUE.to_vec();
+        value[0] ^= 0xff;
+
+        assert!(matches!(
+            verify_ibc_proof(
+                &client_type,
+                &ibc_interface,
+                &consensus_state,
+                &proof,
+                &path,
+                Some(value),
+            ),
+            Err(VerifyError::Ics23(_))
+        ));
+    }
+
+    #[test]
+    fn ics23_non_membership_fails_for_existing_key() {
+        let (client_type, ibc_interface) = cometbls();
+        let (consensus_state, proof, path) = ics23_fixture();
+
+        assert!(matches!(
+            verify_ibc_proof(
+                &client_type,
+                &ibc_interface,
+                &consensus_state,
+                &proof,
+                &path,
+                None,
+            ),
+            Err(VerifyError::Ics23(_))
+        ));
+    }
+
+    #[test]
+    fn mpt_membership() {
+        let (client_type, ibc_interface) = ethereum();
+        let (consensus_state, proof, path, value) = mpt_fixture(MPT_MEMBERSHIP);
+
+        assert_eq!(
+            verify_ibc_proof(
+                &client_type,
+                &ibc_interface,
+                &consensus_state,
+                &proof,
+                &path,
+                Some(value.to_be_bytes().to_vec()),
+            )
+            .unwrap(),
+            Verified {
+                backend: VerifierBackend::Mpt,
+                membership: true,
+            }
+        );
+    }
+
+    #[test]
+    fn mpt_membership_fails_for_wrong_value() {
+        let (client_type, ibc_interface) = ethereum();
+        let (consensus_state, proof, path, _) = mpt_fixture(MPT_MEMBERSHIP);
+
+        assert!(matches!(
+            verify_ibc_proof(
+                &client_type,
+                &ibc_interface,
+                &consensus_state,
+                &proof,
+                &path,
+                Some(vec![0xaa; 32]),
+            ),
+            Err(VerifyError::ValueMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn mpt_membership_fails_for_wrong_storage_root() {
+        let (client_type, ibc_interface) = ethereum();
+        let (_, proof, path, value) = mpt_fixture(MPT_MEMBERSHIP);
+
+        assert!(matches!(
+            verify_ibc_proof(
+                &client_type,
+                &ibc_interface,
+                &json!({ "storage_root": H256::new([0xaa; 32]) }),
+                &proof,
+                &path,
+                Some(value.to_be_bytes().to_vec()),
+            ),
+            Err(VerifyError::Mpt(_))
+        ));
+    }
+
+    #[test]
+    fn mpt_membership_fails_for_wrong_slot() {
+        let (client_type, ibc_interface) = ethereum();
+        let (consensus_state, proof, _, value) = mpt_fixture(MPT_MEMBERSHIP);
+
+        assert!(matches!(
+            verify_ibc_proof(
+                &client_type,
+                &ibc_interface,
+                &consensus_state,
+                &proof,
+                &[vec![0xaa; 32]],
+                Some(value.to_be_bytes().to_vec()),
+            ),
+            Err(VerifyError::SlotMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn mpt_non_membership() {
+        let (client_type, ibc_interface) = ethereum();
+        let (consensus_state, proof, path, _) = mpt_fixture(MPT_NON_MEMBERSHIP);
+
+        assert_eq!(
+            verify_ibc_proof(
+                &client_type,
+                &ibc_interface,
+                &consensus_state,
+                &proof,
+                &path,
+                None,
+            )
+            .unwrap(),
+            Verified {
+                backend: VerifierBackend::Mpt,
+                membership: false,
+            }
+        );
+    }
+
+    #[test]
+    fn mpt_non_membership_fails_for_existing_slot() {
+        let (client_type, ibc_interface) = ethereum();
+        let (consensus_state, proof, path, _) = mpt_fixture(MPT_MEMBERSHIP);
+
+        assert!(matches!(
+            verify_ibc_proof(
+                &client_type,
+                &ibc_interface,
+                &consensus_state,
+                &proof,
+                &path,
+                None,
+            ),
+            Err(VerifyError::NotAbsent(_))
+        ));
+    }
+
+    #[test]
+    fn cometbls_commitment_path() {
+        let (client_type, ibc_interface) = cometbls();
+        let contract_address = H256::new([0x11; 32]);
+        let key = H256::new([0x22; 32]);
+
+        let path = union_commitment_path(
+            &client_type,
+            &ibc_interface,
+            &json!({ "contract_address": contract_address }),
+            key,
+        )
+        .unwrap();
+
+        assert_eq!(path[0], b"wasm");
+        assert_eq!(path[1][0], 0x03);
+        assert_eq!(&path[1][1..33], contract_address.get());
+        assert_eq!(&path[1][33..], key.get());
+    }
+}
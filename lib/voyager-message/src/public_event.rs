@@ -0,0 +1,921 @@
+//! A stable, versioned JSON representation of [`ChainEvent`]s, for consumers outside of voyager.
+//!
+//! The internal representation of chain events (the `@type`/`@value` encoded
+//! [`IbcSpec::Event`](voyager_core::IbcSpec::Event) of each IBC spec) changes between releases.
+//! [`PublicEvent`] instead flattens the identifiers of the packet lifecycle and handshake events
+//! into plain strings that have the same shape for all IBC specs:
+//!
+//! - the numeric ids of ibc-union are rendered as decimal strings (`1`),
+//! - the channel and connection ids of ibc-classic are rendered in their prefixed form
+//!   (`channel-1`, `connection-1`),
+//! - bytes (hashes, packet data and acknowledgements) are rendered as 0x-prefixed hex,
+//! - all other numbers (sequences and timeouts) are rendered as decimal strings, since they
+//!   commonly exceed the range of integers that can be represented exactly in JSON.
+//!
+//! Within a [`PUBLIC_EVENT_VERSION`], fields are only ever added, never removed or changed, so
+//! consumers must ignore fields they don't know. The JSON schema of [`PublicEvent`] is derived
+//! with [`schemars`].
+
+use ibc_classic_spec::IbcClassic;
+use ibc_solidity::Packet;
+use ibc_union_spec::IbcUnion;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use unionlabs::bytes::Bytes;
+use voyager_core::IbcSpec;
+
+use crate::data::ChainEvent;
+
+/// The current version of the [`PublicEvent`] schema.
+pub const PUBLIC_EVENT_VERSION: u32 = 1;
+
+/// An IBC event observed by voyager.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct PublicEvent {
+    /// The version of this schema, see [`PUBLIC_EVENT_VERSION`].
+    pub version: u32,
+    /// The chain that the event was emitted on.
+    pub chain_id: String,
+    /// The chain on the other end of the event.
+    pub counterparty_chain_id: String,
+    /// The IBC spec of the event, either `ibc-classic` or `ibc-union`.
+    pub ibc_spec_id: String,
+    /// The hash of the transaction that emitted the event.
+    pub tx_hash: String,
+    /// The minimum height on `chain_id` at which the effects of the event are provable.
+    pub provable_height: String,
+    /// The unix timestamp (in milliseconds) at which voyager observed the event, if known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub observed_at: Option<u64>,
+    #[serde(flatten)]
+    pub event: PublicEventKind,
+}
+
+impl PublicEvent {
+    #[must_use]
+    pub fn with_observed_at(mut self, observed_at: u64) -> Self {
+        self.observed_at = Some(observed_at);
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "event_type", rename_all = "snake_case")]
+pub enum PublicEventKind {
+    ConnectionOpenInit(ConnectionHandshake),
+    ConnectionOpenTry(ConnectionHandshake),
+    ConnectionOpenAck(ConnectionHandshake),
+    ConnectionOpenConfirm(ConnectionHandshake),
+
+    ChannelOpenInit(ChannelHandshake),
+    ChannelOpenTry(ChannelHandshake),
+    ChannelOpenAck(ChannelHandshake),
+    ChannelOpenConfirm(ChannelHandshake),
+
+    SendPacket(PacketEvent),
+    /// The packet was received on the destination chain. This includes packets received by a
+    /// market maker (ibc-union intent packets).
+    RecvPacket(PacketEvent),
+    WriteAcknowledgement(PacketEvent),
+    AcknowledgePacket(PacketEvent),
+    TimeoutPacket(PacketEvent),
+
+    /// An event that is not covered by this schema (i.e. client events), or that could not be
+    /// decoded.
+    Other {
+        /// The internal name of the event, if known.
+        name: Option<String>,
+    },
+}
+
+impl PublicEventKind {
+    /// The `event_type`s of all kinds of events.
+    pub const NAMES: &'static [&'static str] = &[
+        "connection_open_init",
+        "connection_open_try",
+        "connection_open_ack",
+        "connection_open_confirm",
+        "channel_open_init",
+        "channel_open_try",
+        "channel_open_ack",
+        "channel_open_confirm",
+        "send_packet",
+        "recv_packet",
+        "write_acknowledgement",
+        "acknowledge_packet",
+        "timeout_packet",
+        "other",
+    ];
+
+    /// The `event_type` of this event.
+    #[must_use]
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::ConnectionOpenInit(_) => "connection_open_init",
+            Self::ConnectionOpenTry(_) => "connection_open_try",
+            Self::ConnectionOpenAck(_) => "connection_open_ack",
+            Self::ConnectionOpenConfirm(_) => "connection_open_confirm",
+            Self::ChannelOpenInit(_) => "channel_open_init",
+            Self::ChannelOpenTry(_) => "channel_open_try",
+            Self::ChannelOpenAck(_) => "channel_open_ack",
+            Self::ChannelOpenConfirm(_) => "channel_open_confirm",
+            Self::SendPacket(_) => "send_packet",
+            Self::RecvPacket(_) => "recv_packet",
+            Self::WriteAcknowledgement(_) => "write_acknowledgement",
+            Self::AcknowledgePacket(_) => "acknowledge_packet",
+            Self::TimeoutPacket(_) => "timeout_packet",
+            Self::Other { .. } => "other",
+        }
+    }
+
+    /// The ids of all channels referenced by this event, on either end.
+    #[must_use]
+    pub fn channel_ids(&self) -> Vec<&str> {
+        match self {
+            Self::ChannelOpenInit(event)
+            | Self::ChannelOpenTry(event)
+            | Self::ChannelOpenAck(event)
+            | Self::ChannelOpenConfirm(event) => {
+                let mut channel_ids = vec![event.channel_id.as_str()];
+                channel_ids.extend(event.counterparty_channel_id.as_deref());
+                channel_ids
+            }
+            Self::SendPacket(event)
+            | Self::RecvPacket(event)
+            | Self::WriteAcknowledgement(event)
+            | Self::AcknowledgePacket(event)
+            | Self::TimeoutPacket(event) => {
+                vec![&event.source_channel_id, &event.destination_channel_id]
+            }
+            Self::ConnectionOpenInit(_)
+            | Self::ConnectionOpenTry(_)
+            | Self::ConnectionOpenAck(_)
+            | Self::ConnectionOpenConfirm(_)
+            | Self::Other { .. } => vec![],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct ConnectionHandshake {
+    pub client_id: String,
+    pub connection_id: String,
+    pub counterparty_client_id: String,
+    /// Not yet known in `connection_open_init`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub counterparty_connection_id: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct ChannelHandshake {
+    /// The port of the channel. For ibc-union, this is the address of the app, which is rendered
+    /// as a string if it is printable (i.e. a bech32 address) and as hex otherwise.
+    pub port_id: String,
+    pub channel_id: String,
+    pub counterparty_port_id: String,
+    /// Not yet known in `channel_open_init`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub counterparty_channel_id: Option<String>,
+    /// The client of the connection that the channel is built on.
+    pub client_id: String,
+    pub counterparty_client_id: String,
+    pub version: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct PacketEvent {
+    /// The sequence of the packet on its channel. Only set for ibc-classic, ibc-union packets
+    /// are identified by their `packet_hash`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sequence: Option<String>,
+    /// The commitment hash of the packet. Only set for ibc-union.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub packet_hash: Option<String>,
+
+    /// Only set for ibc-classic, ibc-union channels are not bound to a port in packets.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_port_id: Option<String>,
+    pub source_channel_id: String,
+    pub source_connection_id: String,
+    pub source_client_id: String,
+
+    /// Only set for ibc-classic, ibc-union channels are not bound to a port in packets.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub destination_port_id: Option<String>,
+    pub destination_channel_id: String,
+    pub destination_connection_id: String,
+    pub destination_client_id: String,
+
+    pub timeout_height: String,
+    /// The timeout of the packet, as a unix timestamp in nanoseconds. `0` if the packet only has
+    /// a timeout height.
+    pub timeout_timestamp: String,
+
+    /// Not included in the `acknowledge_packet` and `timeout_packet` events of ibc-classic.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub packet_data: Option<String>,
+    /// Only set for `write_acknowledgement` and `acknowledge_packet`, and only included in the
+    /// latter for ibc-union.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub acknowledgement: Option<String>,
+}
+
+impl From<ChainEvent> for PublicEvent {
+    fn from(chain_event: ChainEvent) -> Self {
+        let event = if chain_event.ibc_spec_id == IbcClassic::ID {
+            chain_event
+                .decode_event::<IbcClassic>()
+                .and_then(Result::ok)
+                .and_then(classic::event)
+        } else if chain_event.ibc_spec_id == IbcUnion::ID {
+            chain_event
+                .decode_event::<IbcUnion>()
+                .and_then(Result::ok)
+                .and_then(union::event)
+        } else {
+            None
+        };
+
+        Self {
+            version: PUBLIC_EVENT_VERSION,
+            chain_id: chain_event.chain_id.to_string(),
+            counterparty_chain_id: chain_event.counterparty_chain_id.to_string(),
+            ibc_spec_id: chain_event.ibc_spec_id.to_string(),
+            tx_hash: chain_event.tx_hash.to_string(),
+            provable_height: chain_event.provable_height.to_string(),
+            observed_at: None,
+            event: event.unwrap_or_else(|| PublicEventKind::Other {
+                name: chain_event
+                    .event
+                    .get("@type")
+                    .and_then(Value::as_str)
+                    .map(ToOwned::to_owned),
+            }),
+        }
+    }
+}
+
+mod classic {
+    use ibc_classic_spec::{ChannelMetadata, FullEvent, PacketMetadata};
+    use unionlabs::{
+        ibc::core::connection::connection_end::ConnectionEnd,
+        id::{ChannelId, ClientId, ConnectionId, PortId},
+    };
+
+    use super::*;
+
+    pub(super) fn event(event: FullEvent) -> Option<PublicEventKind> {
+        Some(match event {
+            FullEvent::CreateClient(_) | FullEvent::UpdateClient(_) => return None,
+
+            FullEvent::ConnectionOpenInit(event) => {
+                PublicEventKind::ConnectionOpenInit(connection(
+                    &event.client_id,
+                    &event.connection_id,
+                    &event.counterparty_client_id,
+                    None,
+                ))
+            }
+            FullEvent::ConnectionOpenTry(event) => PublicEventKind::ConnectionOpenTry(connection(
+                &event.client_id,
+                &event.connection_id,
+                &event.counterparty_client_id,
+                Some(&event.counterparty_connection_id),
+            )),
+            FullEvent::ConnectionOpenAck(event) => PublicEventKind::ConnectionOpenAck(connection(
+                &event.client_id,
+                &event.connection_id,
+                &event.counterparty_client_id,
+                Some(&event.counterparty_connection_id),
+            )),
+            FullEvent::ConnectionOpenConfirm(event) => {
+                PublicEventKind::ConnectionOpenConfirm(connection(
+                    &event.client_id,
+                    &event.connection_id,
+                    &event.counterparty_client_id,
+                    Some(&event.counterparty_connection_id),
+                ))
+            }
+
+            FullEvent::ChannelOpenInit(event) => PublicEventKind::ChannelOpenInit(channel(
+                &event.port_id,
+                &event.channel_id,
+                &event.counterparty_port_id,
+                None,
+                &event.connection,
+                event.version,
+            )),
+            FullEvent::ChannelOpenTry(event) => PublicEventKind::ChannelOpenTry(channel(
+                &event.port_id,
+                &event.channel_id,
+                &event.counterparty_port_id,
+                Some(&event.counterparty_channel_id),
+                &event.connection,
+                event.version,
+            )),
+            FullEvent::ChannelOpenAck(event) => PublicEventKind::ChannelOpenAck(channel(
+                &event.port_id,
+                &event.channel_id,
+                &event.counterparty_port_id,
+                Some(&event.counterparty_channel_id),
+                &event.connection,
+                event.version,
+            )),
+            FullEvent::ChannelOpenConfirm(event) => PublicEventKind::ChannelOpenConfirm(channel(
+                &event.port_id,
+                &event.channel_id,
+                &event.counterparty_port_id,
+                Some(&event.counterparty_channel_id),
+                &event.connection,
+                event.version,
+            )),
+
+            FullEvent::SendPacket(event) => {
+                PublicEventKind::SendPacket(packet(&event.packet, Some(&event.packet_data), None))
+            }
+            FullEvent::RecvPacket(event) => {
+                PublicEventKind::RecvPacket(packet(&event.packet, Some(&event.packet_data), None))
+            }
+            FullEvent::WriteAcknowledgement(event) => {
+                PublicEventKind::WriteAcknowledgement(packet(
+                    &event.packet,
+                    Some(&event.packet_data),
+                    Some(&event.packet_ack),
+                ))
+            }
+            FullEvent::AcknowledgePacket(event) => {
+                PublicEventKind::AcknowledgePacket(packet(&event.packet, None, None))
+            }
+            FullEvent::TimeoutPacket(event) => {
+                PublicEventKind::TimeoutPacket(packet(&event.packet, None, None))
+            }
+        })
+    }
+
+    fn connection(
+        client_id: &ClientId,
+        connection_id: &ConnectionId,
+        counterparty_client_id: &ClientId,
+        counterparty_connection_id: Option<&ConnectionId>,
+    ) -> ConnectionHandshake {
+        ConnectionHandshake {
+            client_id: client_id.to_string(),
+            connection_id: format!("{connection_id:#}"),
+            counterparty_client_id: counterparty_client_id.to_string(),
+            counterparty_connection_id: counterparty_connection_id
+                .map(|connection_id| format!("{connection_id:#}")),
+        }
+    }
+
+    fn channel(
+        port_id: &PortId,
+        channel_id: &ChannelId,
+        counterparty_port_id: &PortId,
+        counterparty_channel_id: Option<&ChannelId>,
+        connection: &ConnectionEnd,
+        version: String,
+    ) -> ChannelHandshake {
+        ChannelHandshake {
+            port_id: port_id.to_string(),
+            channel_id: format!("{channel_id:#}"),
+            counterparty_port_id: counterparty_port_id.to_string(),
+            counterparty_channel_id: counterparty_channel_id
+                .map(|channel_id| format!("{channel_id:#}")),
+            client_id: connection.client_id.to_string(),
+            counterparty_client_id: connection.counterparty.client_id.to_string(),
+            version,
+        }
+    }
+
+    fn packet(
+        packet: &PacketMetadata,
+        packet_data: Option<&Bytes>,
+        acknowledgement: Option<&Bytes>,
+    ) -> PacketEvent {
+        let ChannelMetadata {
+            port_id: source_port_id,
+            channel_id: source_channel_id,
+            ..
+        } = &packet.source_channel;
+        let source_connection = packet.source_channel.primary_connection();
+
+        let ChannelMetadata {
+            port_id: destination_port_id,
+            channel_id: destination_channel_id,
+            ..
+        } = &packet.destination_channel;
+        let destination_connection = packet.destination_channel.primary_connection();
+
+        PacketEvent {
+            sequence: Some(packet.sequence.to_string()),
+            packet_hash: None,
+            source_port_id: Some(source_port_id.to_string()),
+            source_channel_id: format!("{source_channel_id:#}"),
+            source_connection_id: format!("{:#}", source_connection.connection_id),
+            source_client_id: source_connection.client_id.to_string(),
+            destination_port_id: Some(destination_port_id.to_string()),
+            destination_channel_id: format!("{destination_channel_id:#}"),
+            destination_connection_id: format!("{:#}", destination_connection.connection_id),
+            destination_client_id: destination_connection.client_id.to_string(),
+            timeout_height: packet.timeout_height.to_string(),
+            timeout_timestamp: packet.timeout_timestamp.to_string(),
+            packet_data: packet_data.map(ToString::to_string),
+            acknowledgement: acknowledgement.map(ToString::to_string),
+        }
+    }
+}
+
+mod union {
+    use ibc_union_spec::{commit_packet, ChannelConnection, FullEvent, PacketMetadata, UnionId};
+
+    use super::*;
+
+    pub(super) fn event(event: FullEvent) -> Option<PublicEventKind> {
+        Some(match event {
+            FullEvent::CreateClient(_)
+            | FullEvent::UpdateClient(_)
+            | FullEvent::ChannelCloseInit(_)
+            | FullEvent::ChannelCloseConfirm(_) => return None,
+
+            FullEvent::ConnectionOpenInit(event) => {
+                PublicEventKind::ConnectionOpenInit(ConnectionHandshake {
+                    client_id: event.client_id.to_string(),
+                    connection_id: event.connection_id.to_string(),
+                    counterparty_client_id: event.counterparty_client_id.to_string(),
+                    counterparty_connection_id: None,
+                })
+            }
+            FullEvent::ConnectionOpenTry(event) => {
+                PublicEventKind::ConnectionOpenTry(ConnectionHandshake {
+                    client_id: event.client_id.to_string(),
+                    connection_id: event.connection_id.to_string(),
+                    counterparty_client_id: event.counterparty_client_id.to_string(),
+                    counterparty_connection_id: Some(event.counterparty_connection_id.to_string()),
+                })
+            }
+            FullEvent::ConnectionOpenAck(event) => {
+                PublicEventKind::ConnectionOpenAck(ConnectionHandshake {
+                    client_id: event.client_id.to_string(),
+                    connection_id: event.connection_id.to_string(),
+                    counterparty_client_id: event.counterparty_client_id.to_string(),
+                    counterparty_connection_id: Some(event.counterparty_connection_id.to_string()),
+                })
+            }
+            FullEvent::ConnectionOpenConfirm(event) => {
+                PublicEventKind::ConnectionOpenConfirm(ConnectionHandshake {
+                    client_id: event.client_id.to_string(),
+                    connection_id: event.connection_id.to_string(),
+                    counterparty_client_id: event.counterparty_client_id.to_string(),
+                    counterparty_connection_id: Some(event.counterparty_connection_id.to_string()),
+                })
+            }
+
+            FullEvent::ChannelOpenInit(event) => PublicEventKind::ChannelOpenInit(channel(
+                &event.port_id,
+                event.channel_id,
+                &event.counterparty_port_id,
+                None,
+                &event.connection,
+                event.version,
+            )),
+            FullEvent::ChannelOpenTry(event) => PublicEventKind::ChannelOpenTry(channel(
+                &event.port_id,
+                event.channel_id,
+                &event.counterparty_port_id,
+                Some(event.counterparty_channel_id),
+                &event.connection,
+                event.version,
+            )),
+            FullEvent::ChannelOpenAck(event) => PublicEventKind::ChannelOpenAck(channel(
+                &event.port_id,
+                event.channel_id,
+                &event.counterparty_port_id,
+                Some(event.counterparty_channel_id),
+                &event.connection,
+                event.version,
+            )),
+            FullEvent::ChannelOpenConfirm(event) => PublicEventKind::ChannelOpenConfirm(channel(
+                &event.port_id,
+                event.channel_id,
+                &event.counterparty_port_id,
+                Some(event.counterparty_channel_id),
+                &event.connection,
+                event.version,
+            )),
+
+            FullEvent::SendPacket(event) => {
+                PublicEventKind::SendPacket(packet(&event.packet, &event.packet_data, None))
+            }
+            FullEvent::RecvPacket(event) => {
+                PublicEventKind::RecvPacket(packet(&event.packet, &event.packet_data, None))
+            }
+            FullEvent::RecvIntentPacket(event) => {
+                PublicEventKind::RecvPacket(packet(&event.packet, &event.packet_data, None))
+            }
+            FullEvent::WriteAcknowledgement(event) => {
+                PublicEventKind::WriteAcknowledgement(packet(
+                    &event.packet,
+                    &event.packet_data,
+                    Some(&event.acknowledgement),
+                ))
+            }
+            FullEvent::AcknowledgePacket(event) => PublicEventKind::AcknowledgePacket(packet(
+                &event.packet,
+                &event.packet_data,
+                Some(&event.acknowledgement),
+            )),
+            FullEvent::TimeoutPacket(event) => {
+                PublicEventKind::TimeoutPacket(packet(&event.packet, &event.packet_data, None))
+            }
+        })
+    }
+
+    fn channel(
+        port_id: &Bytes,
+        channel_id: UnionId,
+        counterparty_port_id: &Bytes,
+        counterparty_channel_id: Option<UnionId>,
+        connection: &ChannelConnection,
+        version: String,
+    ) -> ChannelHandshake {
+        ChannelHandshake {
+            port_id: port(port_id),
+            channel_id: channel_id.to_string(),
+            counterparty_port_id: port(counterparty_port_id),
+            counterparty_channel_id: counterparty_channel_id
+                .map(|channel_id| channel_id.to_string()),
+            client_id: connection.client_id.to_string(),
+            counterparty_client_id: connection.counterparty_client_id.to_string(),
+            version,
+        }
+    }
+
+    /// Ports are the addresses of the apps, which are printable on cosmwasm chains (bech32) but
+    /// raw bytes on evm chains.
+    fn port(port_id: &Bytes) -> String {
+        match std::str::from_utf8(port_id) {
+            Ok(port_id) if !port_id.is_empty() && port_id.bytes().all(|b| b.is_ascii_graphic()) => {
+                port_id.to_owned()
+            }
+            _ => port_id.to_string(),
+        }
+    }
+
+    fn packet(
+        packet: &PacketMetadata,
+        packet_data: &Bytes,
+        acknowledgement: Option<&Bytes>,
+    ) -> PacketEvent {
+        let packet_hash = commit_packet(&Packet {
+            source_channel: packet.source_channel.channel_id.get(),
+            destination_channel: packet.destination_channel.channel_id.get(),
+            data: packet_data.clone().into(),
+            timeout_height: packet.timeout_height,
+            timeout_timestamp: packet.timeout_timestamp,
+        });
+
+        PacketEvent {
+            sequence: None,
+            packet_hash: Some(packet_hash.to_string()),
+            source_port_id: None,
+            source_channel_id: packet.source_channel.channel_id.to_string(),
+            source_connection_id: packet.source_channel.connection.connection_id.to_string(),
+            source_client_id: packet.source_channel.connection.client_id.to_string(),
+            destination_port_id: None,
+            destination_channel_id: packet.destination_channel.channel_id.to_string(),
+            destination_connection_id: packet
+                .destination_channel
+                .connection
+                .connection_id
+                .to_string(),
+            destination_client_id: packet.destination_channel.connection.client_id.to_string(),
+            timeout_height: packet.timeout_height.to_string(),
+            timeout_timestamp: packet.timeout_timestamp.to_string(),
+            packet_data: Some(packet_data.to_string()),
+            acknowledgement: acknowledgement.map(ToString::to_string),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU64;
+
+    use serde_json::json;
+    use unionlabs::{
+        hash::H256,
+        ibc::core::{channel::order::Order, client::height::Height},
+        id::{ChannelId, ClientId, ConnectionId, PortId},
+    };
+    use voyager_core::{ChainId, ClientInfo, ClientType, IbcInterface, IbcSpecId};
+
+    use super::*;
+
+    fn chain_event(ibc_spec_id: &'static str, event: impl Serialize) -> ChainEvent {
+        ChainEvent {
+            chain_id: ChainId::new("union-testnet-9"),
+            client_info: ClientInfo {
+                client_type: ClientType::new_static(ClientType::ETHEREUM),
+                ibc_interface: IbcInterface::new_static(IbcInterface::IBC_COSMWASM),
+                metadata: Value::Null,
+            },
+            counterparty_chain_id: ChainId::new("11155111"),
+            tx_hash: H256::new([0x11; 32]),
+            provable_height: Height::new_with_revision(9, 100),
+            ibc_spec_id: IbcSpecId::new_static(ibc_spec_id),
+            event: serde_json::to_value(event).unwrap(),
+            raw_events: None,
+            denom_trace: None,
+        }
+    }
+
+    fn to_json(chain_event: ChainEvent) -> Value {
+        let event = PublicEvent::from(chain_event);
+
+        let json = serde_json::to_value(&event).unwrap();
+
+        assert_eq!(json["event_type"], event.event.name());
+        assert!(PublicEventKind::NAMES.contains(&event.event.name()));
+        assert_eq!(
+            serde_json::from_value::<PublicEvent>(json.clone()).unwrap(),
+            event
+        );
+
+        json
+    }
+
+    fn tx_hash() -> String {
+        format!("0x{}", "11".repeat(32))
+    }
+
+    fn classic_channel(
+        port_id: &'static str,
+        channel_id: u32,
+    ) -> ibc_classic_spec::ChannelMetadata {
+        ibc_classic_spec::ChannelMetadata {
+            port_id: PortId::new_static(port_id).unwrap(),
+            channel_id: ChannelId::new(channel_id),
+            version: "ics20-1".to_owned(),
+            connection_hops: vec![ibc_classic_spec::ConnectionMetadata {
+                client_id: ClientId::new_static("07-tendermint", channel_id),
+                connection_id: ConnectionId::new(channel_id + 10),
+            }],
+        }
+    }
+
+    #[test]
+    fn classic_packet() {
+        let packet = ibc_classic_spec::PacketMetadata {
+            sequence: NonZeroU64::new(42).unwrap(),
+            source_channel: classic_channel("transfer", 1),
+            destination_channel: classic_channel("transfer", 2),
+            channel_ordering: Order::Unordered,
+            timeout_height: Height::new_with_revision(1, 1_000),
+            timeout_timestamp: 1_700_000_000_000_000_000,
+        };
+
+        assert_eq!(
+            to_json(chain_event(
+                IbcSpecId::CLASSIC,
+                ibc_classic_spec::FullEvent::WriteAcknowledgement(
+                    ibc_classic_spec::WriteAcknowledgement {
+                        packet_data: b"data".into(),
+                        packet_ack: b"ack".into(),
+                        packet: packet.clone(),
+                    }
+                ),
+            )),
+            json!({
+                "version": 1,
+                "chain_id": "union-testnet-9",
+                "counterparty_chain_id": "11155111",
+                "ibc_spec_id": "ibc-classic",
+                "tx_hash": tx_hash(),
+                "provable_height": "9-100",
+                "event_type": "write_acknowledgement",
+                "sequence": "42",
+                "source_port_id": "transfer",
+                "source_channel_id": "channel-1",
+                "source_connection_id": "connection-11",
+                "source_client_id": "07-tendermint-1",
+                "destination_port_id": "transfer",
+                "destination_channel_id": "channel-2",
+                "destination_connection_id": "connection-12",
+                "destination_client_id": "07-tendermint-2",
+                "timeout_height": "1-1000",
+                "timeout_timestamp": "1700000000000000000",
+                "packet_data": "0x64617461",
+                "acknowledgement": "0x61636b",
+            })
+        );
+
+        // the packet data is not part of classic acknowledgements
+        let json = to_json(chain_event(
+            IbcSpecId::CLASSIC,
+            ibc_classic_spec::FullEvent::AcknowledgePacket(ibc_classic_spec::AcknowledgePacket {
+                packet,
+            }),
+        ));
+
+        assert_eq!(json["event_type"], "acknowledge_packet");
+        assert!(json.get("packet_data").is_none());
+        assert!(json.get("acknowledgement").is_none());
+    }
+
+    #[test]
+    fn classic_handshake() {
+        assert_eq!(
+            to_json(chain_event(
+                IbcSpecId::CLASSIC,
+                ibc_classic_spec::FullEvent::ConnectionOpenInit(
+                    ibc_classic_spec::ConnectionOpenInit {
+                        connection_id: ConnectionId::new(3),
+                        client_id: ClientId::new_static("08-wasm", 1),
+                        counterparty_client_id: ClientId::new_static("07-tendermint", 7),
+                    }
+                ),
+            )),
+            json!({
+                "version": 1,
+                "chain_id": "union-testnet-9",
+                "counterparty_chain_id": "11155111",
+                "ibc_spec_id": "ibc-classic",
+                "tx_hash": tx_hash(),
+                "provable_height": "9-100",
+                "event_type": "connection_open_init",
+                "client_id": "08-wasm-1",
+                "connection_id": "connection-3",
+                "counterparty_client_id": "07-tendermint-7",
+            })
+        );
+    }
+
+    fn union_id(id: u32) -> ibc_union_spec::UnionId {
+        ibc_union_spec::UnionId::new(id).unwrap()
+    }
+
+    fn union_channel(channel_id: u32) -> ibc_union_spec::ChannelMetadata {
+        ibc_union_spec::ChannelMetadata {
+            channel_id: union_id(channel_id),
+            version: "ucs03-zkgm-0".to_owned(),
+            connection: ibc_union_spec::ConnectionMetadata {
+                client_id: union_id(channel_id + 10),
+                connection_id: union_id(channel_id + 20),
+            },
+        }
+    }
+
+    #[test]
+    fn union_packet() {
+        let packet = ibc_union_spec::PacketMetadata {
+            source_channel: union_channel(1),
+            destination_channel: union_channel(2),
+            timeout_height: 0,
+            timeout_timestamp: 1_700_000_000_000_000_000,
+        };
+
+        let packet_hash = ibc_union_spec::commit_packet(&Packet {
+            source_channel: 1,
+            destination_channel: 2,
+            data: b"data".to_vec().into(),
+            timeout_height: 0,
+            timeout_timestamp: 1_700_000_000_000_000_000,
+        });
+
+        assert_eq!(
+            to_json(chain_event(
+                IbcSpecId::UNION,
+                ibc_union_spec::FullEvent::SendPacket(ibc_union_spec::SendPacket {
+                    packet_data: b"data".into(),
+                    packet: packet.clone(),
+                    batch: None,
+                }),
+            )),
+            json!({
+                "version": 1,
+                "chain_id": "union-testnet-9",
+                "counterparty_chain_id": "11155111",
+                "ibc_spec_id": "ibc-union",
+                "tx_hash": tx_hash(),
+                "provable_height": "9-100",
+                "event_type": "send_packet",
+                "packet_hash": packet_hash.to_string(),
+                "source_channel_id": "1",
+                "source_connection_id": "21",
+                "source_client_id": "11",
+                "destination_channel_id": "2",
+                "destination_connection_id": "22",
+                "destination_client_id": "12",
+                "timeout_height": "0",
+                "timeout_timestamp": "1700000000000000000",
+                "packet_data": "0x64617461",
+            })
+        );
+
+        // intent packets are received packets
+        let json = to_json(chain_event(
+            IbcSpecId::UNION,
+            ibc_union_spec::FullEvent::RecvIntentPacket(ibc_union_spec::RecvIntentPacket {
+                packet_data: b"data".into(),
+                packet,
+                market_maker_msg: b"msg".into(),
+            }),
+        ));
+
+        assert_eq!(json["event_type"], "recv_packet");
+        assert_eq!(json["packet_hash"], packet_hash.to_string());
+    }
+
+    #[test]
+    fn union_handshake() {
+        let event = |port_id: &[u8]| {
+            chain_event(
+                IbcSpecId::UNION,
+                ibc_union_spec::FullEvent::ChannelOpenTry(ibc_union_spec::ChannelOpenTry {
+                    port_id: port_id.to_vec().into(),
+                    channel_id: union_id(2),
+                    counterparty_port_id: [0xab; 20].to_vec().into(),
+                    counterparty_channel_id: union_id(4),
+                    connection: ibc_union_spec::ChannelConnection {
+                        state: ibc_solidity::ConnectionState::Open,
+                        client_id: union_id(1),
+                        counterparty_client_id: union_id(7),
+                        counterparty_connection_id: union_id(5),
+                    },
+                    version: "ucs03-zkgm-0".to_owned(),
+                }),
+            )
+        };
+
+        assert_eq!(
+            to_json(event(b"union1port")),
+            json!({
+                "version": 1,
+                "chain_id": "union-testnet-9",
+                "counterparty_chain_id": "11155111",
+                "ibc_spec_id": "ibc-union",
+                "tx_hash": tx_hash(),
+                "provable_height": "9-100",
+                "event_type": "channel_open_try",
+                "port_id": "union1port",
+                "channel_id": "2",
+                "counterparty_port_id": format!("0x{}", "ab".repeat(20)),
+                "counterparty_channel_id": "4",
+                "client_id": "1",
+                "counterparty_client_id": "7",
+                "version": "ucs03-zkgm-0",
+            })
+        );
+
+        // non-printable ports are rendered as hex
+        assert_eq!(to_json(event(&[0x00, 0x01]))["port_id"], "0x0001");
+    }
+
+    #[test]
+    fn other_events() {
+        // client events are not part of the schema
+        assert_eq!(
+            PublicEvent::from(chain_event(
+                IbcSpecId::UNION,
+                ibc_union_spec::FullEvent::CreateClient(ibc_union_spec::CreateClient {
+                    client_type: ClientType::new_static(ClientType::ETHEREUM),
+                    client_id: union_id(1),
+                }),
+            ))
+            .event,
+            PublicEventKind::Other {
+                name: Some("create_client".to_owned())
+            }
+        );
+
+        // undecodable events
+        assert_eq!(
+            PublicEvent::from(chain_event(
+                IbcSpecId::CLASSIC,
+                json!({ "@type": "send_packet", "@value": {} }),
+            ))
+            .event,
+            PublicEventKind::Other {
+                name: Some("send_packet".to_owned())
+            }
+        );
+
+        // unknown ibc specs
+        assert_eq!(
+            PublicEvent::from(chain_event("ibc-unknown", json!({}))).event,
+            PublicEventKind::Other { name: None }
+        );
+    }
+
+    #[test]
+    fn schema() {
+        let schema = serde_json::to_value(schemars::schema_for!(PublicEvent)).unwrap();
+
+        let schema = schema.to_string();
+
+        for name in PublicEventKind::NAMES {
+            assert!(schema.contains(&format!("\"{name}\"")), "{name}");
+        }
+    }
+}
@@ -0,0 +1,741 @@
+//! Removal of queued ops that reference an abandoned chain, client, or channel.
+//!
+//! When a channel is abandoned (i.e. the counterparty chain halted
+//! permanently), the ops referencing it keep failing and being requeued.
+//! [`purge_ops`] scans the queue page by page (see [`InspectQueue::scan`]) and
+//! removes the ops matching a [`PurgeFilter`], without blocking ongoing
+//! processing.
+//!
+//! Ops are arbitrarily nested and mostly opaque to voyager, so they are matched
+//! structurally on their JSON representation; see [`PurgeFilter`] for the exact
+//! rules. Ops that can't be decoded (i.e. ones enqueued by an incompatible
+//! version of voyager) are matched on their stored JSON instead. Use the dry
+//! run mode to check what would be removed first.
+
+use std::{collections::BTreeMap, fmt::Debug};
+
+use futures::future::BoxFuture;
+use jsonrpsee::types::{ErrorObject, ErrorObjectOwned};
+use macros::model;
+use serde_json::Value;
+use tracing::info;
+use unionlabs::ErrorReporter;
+use voyager_core::ChainId;
+use voyager_vm::{BoxDynError, InspectQueue, Op, QueueStats, QueuedOp, ScanFilter, ScanPage};
+
+use crate::{RawClientId, VoyagerMessage, FATAL_JSONRPC_ERROR_CODE};
+
+/// The amount of ops read from the queue at once.
+const SCAN_PAGE_SIZE: usize = 1000;
+
+/// The op type that undecodable ops are reported as in the [`PurgeSummary`].
+pub const UNDECODABLE_OP_TYPE: &str = "<undecodable>";
+
+/// Object keys that contain a chain id.
+const CHAIN_ID_KEYS: &[&str] = &["chain_id"];
+
+/// Object keys that contain a client id.
+const CLIENT_ID_KEYS: &[&str] = &["client_id", "counterparty_client_id"];
+
+/// Object keys that contain a channel id.
+const CHANNEL_ID_KEYS: &[&str] = &[
+    "channel_id",
+    "counterparty_channel_id",
+    "source_channel",
+    "destination_channel",
+];
+
+/// The ops to remove from the queue.
+///
+/// An op matches if it references `chain_id`, and all of the other provided
+/// fields:
+///
+/// - `chain_id` is referenced by any `chain_id` field, or by a plugin message
+///   addressed to a plugin for the chain (`<plugin>/<chain_id>`).
+/// - `client_id` is referenced by any `client_id` or `counterparty_client_id`
+///   field.
+/// - `channel_id` is referenced by any `channel_id`, `counterparty_channel_id`,
+///   `source_channel`, or `destination_channel` field. Note that this includes
+///   the counterparty end of channels and packets.
+/// - `older_than` matches ops that were enqueued at least this many seconds
+///   ago.
+#[model]
+pub struct PurgeFilter {
+    pub chain_id: ChainId,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<RawClientId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel_id: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub older_than: Option<u64>,
+}
+
+impl PurgeFilter {
+    /// Whether `op` matches this filter, with `now` being the current unix
+    /// timestamp in seconds.
+    pub fn matches(&self, op: &QueuedOp<VoyagerMessage>, now: u64) -> bool {
+        serde_json::to_value(&op.op)
+            .is_ok_and(|value| self.matches_json(&value, op.enqueued_at, now))
+    }
+
+    /// Whether the JSON representation of an op, enqueued at `enqueued_at`,
+    /// matches this filter, with `now` being the current unix timestamp in
+    /// seconds.
+    pub fn matches_json(&self, value: &Value, enqueued_at: u64, now: u64) -> bool {
+        if self
+            .older_than
+            .is_some_and(|older_than| now.saturating_sub(enqueued_at) < older_than)
+        {
+            return false;
+        }
+
+        let chain_id = Value::String(self.chain_id.to_string());
+        let plugin_suffix = format!("/{}", self.chain_id);
+
+        let references_chain = references(value, &mut |key: &str, v: &Value| {
+            (CHAIN_ID_KEYS.contains(&key) && *v == chain_id)
+                || (key == "plugin" && v.as_str().is_some_and(|p| p.ends_with(&plugin_suffix)))
+        });
+
+        references_chain
+            && self.client_id.as_ref().map_or(true, |client_id| {
+                references(value, &mut |key: &str, v: &Value| {
+                    CLIENT_ID_KEYS.contains(&key) && v == client_id.as_raw()
+                })
+            })
+            && self.channel_id.map_or(true, |channel_id| {
+                let channel_id = Value::from(channel_id);
+
+                references(value, &mut |key: &str, v: &Value| {
+                    CHANNEL_ID_KEYS.contains(&key) && *v == channel_id
+                })
+            })
+    }
+
+    /// The filter to scan the queue with, such that all ops matching this
+    /// filter are read.
+    pub fn scan_filter(&self, now: u64) -> ScanFilter {
+        ScanFilter {
+            chain_id: Some(self.chain_id.to_string()),
+            enqueued_before: self
+                .older_than
+                .map(|older_than| now.saturating_sub(older_than)),
+        }
+    }
+}
+
+/// Whether any object in `value` (recursively) contains a field for which `f`
+/// returns `true`.
+fn references(value: &Value, f: &mut impl FnMut(&str, &Value) -> bool) -> bool {
+    match value {
+        Value::Object(map) => map
+            .iter()
+            .any(|(key, value)| f(key, value) || references(value, f)),
+        Value::Array(values) => values.iter().any(|value| references(value, f)),
+        _ => false,
+    }
+}
+
+/// The ops that were (or, in a dry run, would be) removed by [`purge_ops`].
+#[model]
+pub struct PurgeSummary {
+    pub dry_run: bool,
+    pub total: usize,
+    /// The amount of removed ops per op type (see [`op_type`]). Ops that can't
+    /// be decoded are counted as [`UNDECODABLE_OP_TYPE`].
+    pub removed: BTreeMap<String, usize>,
+}
+
+/// The `@type` of `op`, including the `@type` of the contained data or call
+/// for data and call ops, and the plugin for plugin messages (i.e.
+/// `call/plugin/<plugin-name>`).
+pub fn op_type(op: &Op<VoyagerMessage>) -> String {
+    let value = serde_json::to_value(op).unwrap_or_default();
+
+    let ty = |value: &Value| {
+        value
+            .get("@type")
+            .and_then(Value::as_str)
+            .unwrap_or("<unknown>")
+            .to_owned()
+    };
+
+    match op {
+        Op::Data(_) | Op::Call(_) => {
+            let inner = &value["@value"];
+
+            match inner["@value"]["plugin"].as_str() {
+                Some(plugin) => format!("{}/{}/{plugin}", ty(&value), ty(inner)),
+                None => format!("{}/{}", ty(&value), ty(inner)),
+            }
+        }
+        _ => ty(&value),
+    }
+}
+
+/// Object safe access to the queue, for the rpc server. This is implemented for
+/// all [`InspectQueue`]s.
+pub trait QueueInspector: Debug + Send + Sync + 'static {
+    fn scan<'a>(
+        &'a self,
+        filter: &'a ScanFilter,
+        after: Option<i64>,
+        limit: usize,
+    ) -> BoxFuture<'a, Result<ScanPage<VoyagerMessage>, BoxDynError>>;
+
+    fn remove<'a>(&'a self, ids: &'a [i64]) -> BoxFuture<'a, Result<Vec<i64>, BoxDynError>>;
+
+    fn stats(&self) -> BoxFuture<'_, Result<QueueStats, BoxDynError>>;
+}
+
+impl<Q: InspectQueue<VoyagerMessage>> QueueInspector for Q {
+    fn scan<'a>(
+        &'a self,
+        filter: &'a ScanFilter,
+        after: Option<i64>,
+        limit: usize,
+    ) -> BoxFuture<'a, Result<ScanPage<VoyagerMessage>, BoxDynError>> {
+        Box::pin(async move {
+            InspectQueue::scan(self, filter, after, limit)
+                .await
+                .map_err(|e| Box::new(e) as BoxDynError)
+        })
+    }
+
+    fn remove<'a>(&'a self, ids: &'a [i64]) -> BoxFuture<'a, Result<Vec<i64>, BoxDynError>> {
+        Box::pin(async move {
+            InspectQueue::remove(self, ids)
+                .await
+                .map_err(|e| Box::new(e) as BoxDynError)
+        })
+    }
+
+    fn stats(&self) -> BoxFuture<'_, Result<QueueStats, BoxDynError>> {
+        Box::pin(async move {
+            InspectQueue::stats(self)
+                .await
+                .map_err(|e| Box::new(e) as BoxDynError)
+        })
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("error accessing the queue")]
+pub struct PurgeError(#[source] pub BoxDynError);
+
+impl From<PurgeError> for ErrorObjectOwned {
+    fn from(value: PurgeError) -> Self {
+        ErrorObject::owned(
+            FATAL_JSONRPC_ERROR_CODE,
+            ErrorReporter(value).to_string(),
+            None::<()>,
+        )
+    }
+}
+
+/// Remove all ops in the queue that match `filter`. If `dry_run` is set, the
+/// ops that would be removed are summarized, but not removed.
+///
+/// The queue is read and purged one page at a time, so ops that are picked up
+/// for processing in the meantime are not removed, and are not included in the
+/// summary.
+pub async fn purge_ops(
+    queue: &dyn QueueInspector,
+    filter: &PurgeFilter,
+    dry_run: bool,
+    now: u64,
+) -> Result<PurgeSummary, PurgeError> {
+    let scan_filter = filter.scan_filter(now);
+
+    let mut summary = PurgeSummary {
+        dry_run,
+        total: 0,
+        removed: BTreeMap::new(),
+    };
+
+    let mut after = None;
+
+    loop {
+        let page = queue
+            .scan(&scan_filter, after, SCAN_PAGE_SIZE)
+            .await
+            .map_err(PurgeError)?;
+
+        let matching = page
+            .ops
+            .iter()
+            .filter(|op| filter.matches(op, now))
+            .map(|op| (op.id, op_type(&op.op)))
+            .chain(
+                page.undecodable
+                    .iter()
+                    .filter(|op| filter.matches_json(&op.item, op.enqueued_at, now))
+                    .map(|op| (op.id, UNDECODABLE_OP_TYPE.to_owned())),
+            )
+            .collect::<Vec<_>>();
+
+        let removed = if dry_run || matching.is_empty() {
+            matching
+        } else {
+            let ids = matching.iter().map(|(id, _)| *id).collect::<Vec<_>>();
+
+            let removed_ids = queue.remove(&ids).await.map_err(PurgeError)?;
+
+            matching
+                .into_iter()
+                .filter(|(id, _)| removed_ids.contains(id))
+                .collect()
+        };
+
+        summary.total += removed.len();
+
+        for (_, op_type) in removed {
+            *summary.removed.entry(op_type).or_default() += 1;
+        }
+
+        match page.next {
+            Some(next) => after = Some(next),
+            None => break,
+        }
+    }
+
+    info!(
+        dry_run,
+        total = summary.total,
+        removed = ?summary.removed,
+        "purged ops"
+    );
+
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use serde_json::json;
+    use voyager_core::IbcSpecId;
+    use voyager_vm::{call, data, in_memory::InMemoryQueue, noop, seq, Queue, UndecodableOp};
+
+    use super::*;
+    use crate::{
+        call::WaitForHeight,
+        data::{IbcDatagram, WithChainId},
+        filter::JaqInterestFilter,
+        PluginMessage,
+    };
+
+    const NOW: u64 = 1_000_000;
+
+    fn queued(op: Op<VoyagerMessage>, enqueued_at: u64) -> QueuedOp<VoyagerMessage> {
+        QueuedOp {
+            id: 1,
+            enqueued_at,
+            op,
+        }
+    }
+
+    fn datagram(chain_id: &str, datagram: Value) -> Op<VoyagerMessage> {
+        data(WithChainId {
+            chain_id: ChainId::new(chain_id.to_owned()),
+            message: IbcDatagram {
+                ibc_spec_id: IbcSpecId::new(IbcSpecId::UNION),
+                datagram,
+            },
+        })
+    }
+
+    fn recv(chain_id: &str, source_channel: u32, destination_channel: u32) -> Op<VoyagerMessage> {
+        datagram(
+            chain_id,
+            json!({
+                "@type": "packet_recv",
+                "@value": {
+                    "packets": [{
+                        "source_channel": source_channel,
+                        "destination_channel": destination_channel,
+                    }],
+                },
+            }),
+        )
+    }
+
+    fn filter(chain_id: &str) -> PurgeFilter {
+        PurgeFilter {
+            chain_id: ChainId::new(chain_id.to_owned()),
+            client_id: None,
+            channel_id: None,
+            older_than: None,
+        }
+    }
+
+    #[test]
+    fn matches_chain_id() {
+        let filter = filter("union-1");
+
+        assert!(filter.matches(&queued(recv("union-1", 1, 2), NOW), NOW));
+        assert!(!filter.matches(&queued(recv("union-2", 1, 2), NOW), NOW));
+
+        // nested ops are matched as well
+        assert!(filter.matches(&queued(seq([noop(), recv("union-1", 1, 2)]), NOW), NOW));
+
+        // chain ids are only matched by key, not by value anywhere in the op
+        assert!(!filter.matches(
+            &queued(datagram("union-2", json!({ "memo": "union-1" })), NOW),
+            NOW
+        ));
+    }
+
+    #[test]
+    fn matches_plugin_messages_for_chain() {
+        let op = call(PluginMessage::new(
+            "voyager-transaction-plugin-cosmos-sdk/union-1",
+            json!({ "submit_transaction": [] }),
+        ));
+
+        assert!(filter("union-1").matches(&queued(op.clone(), NOW), NOW));
+        assert!(!filter("union-2").matches(&queued(op, NOW), NOW));
+    }
+
+    #[test]
+    fn matches_channel_id() {
+        let filter = PurgeFilter {
+            channel_id: Some(2),
+            ..filter("union-1")
+        };
+
+        assert!(filter.matches(&queued(recv("union-1", 1, 2), NOW), NOW));
+        assert!(filter.matches(&queued(recv("union-1", 2, 3), NOW), NOW));
+        assert!(!filter.matches(&queued(recv("union-1", 3, 4), NOW), NOW));
+        assert!(!filter.matches(&queued(recv("union-2", 1, 2), NOW), NOW));
+    }
+
+    #[test]
+    fn matches_client_id() {
+        let op = datagram(
+            "union-1",
+            json!({ "@type": "update_client", "@value": { "client_id": 5 } }),
+        );
+
+        let matching = PurgeFilter {
+            client_id: Some(RawClientId::new(5)),
+            ..filter("union-1")
+        };
+        let not_matching = PurgeFilter {
+            client_id: Some(RawClientId::new(6)),
+            ..filter("union-1")
+        };
+
+        assert!(matching.matches(&queued(op.clone(), NOW), NOW));
+        assert!(!not_matching.matches(&queued(op, NOW), NOW));
+    }
+
+    #[test]
+    fn matches_older_than() {
+        let filter = PurgeFilter {
+            older_than: Some(60),
+            ..filter("union-1")
+        };
+
+        assert!(filter.matches(&queued(recv("union-1", 1, 2), NOW - 60), NOW));
+        assert!(!filter.matches(&queued(recv("union-1", 1, 2), NOW - 59), NOW));
+        // enqueued in the future (i.e. clock skew between voyager instances)
+        assert!(!filter.matches(&queued(recv("union-1", 1, 2), NOW + 1), NOW));
+    }
+
+    #[test]
+    fn op_types() {
+        assert_eq!(
+            op_type(&recv("union-1", 1, 2)),
+            "data/identified_ibc_datagram"
+        );
+        assert_eq!(
+            op_type(&call(PluginMessage::new("plugin/union-1", json!({})))),
+            "call/plugin/plugin/union-1"
+        );
+        assert_eq!(
+            op_type(&call(WaitForHeight {
+                chain_id: ChainId::new("union-1"),
+                height: unionlabs::ibc::core::client::height::Height::new(1),
+                finalized: true,
+            })),
+            "call/wait_for_height"
+        );
+        assert_eq!(op_type(&noop()), "noop");
+    }
+
+    #[test]
+    fn scan_filter_includes_matching_ops() {
+        assert_eq!(
+            filter("union-1").scan_filter(NOW),
+            ScanFilter {
+                chain_id: Some("union-1".to_owned()),
+                enqueued_before: None,
+            }
+        );
+
+        let filter = PurgeFilter {
+            older_than: Some(60),
+            ..filter("union-1")
+        };
+
+        // the newest op that matches the filter is enqueued exactly at the bound
+        assert_eq!(filter.scan_filter(NOW).enqueued_before, Some(NOW - 60));
+        assert!(filter.matches(&queued(recv("union-1", 1, 2), NOW - 60), NOW));
+    }
+
+    /// A queue that records the ids it was asked to remove, and only removes
+    /// the ones in `removable`. Pages contain at most `page_size` ops.
+    #[derive(Debug)]
+    struct MockQueue {
+        ops: Vec<QueuedOp<VoyagerMessage>>,
+        undecodable: Vec<UndecodableOp>,
+        page_size: usize,
+        removable: Vec<i64>,
+        removed: Mutex<Vec<i64>>,
+        scanned_pages: Mutex<Vec<Option<i64>>>,
+    }
+
+    impl QueueInspector for MockQueue {
+        fn scan<'a>(
+            &'a self,
+            _filter: &'a ScanFilter,
+            after: Option<i64>,
+            limit: usize,
+        ) -> BoxFuture<'a, Result<ScanPage<VoyagerMessage>, BoxDynError>> {
+            Box::pin(async move {
+                self.scanned_pages.lock().unwrap().push(after);
+
+                let limit = limit.min(self.page_size);
+                let after = after.unwrap_or(0);
+
+                let mut ids = self
+                    .ops
+                    .iter()
+                    .map(|op| op.id)
+                    .chain(self.undecodable.iter().map(|op| op.id))
+                    .filter(|id| *id > after)
+                    .collect::<Vec<_>>();
+                ids.sort();
+                ids.truncate(limit);
+
+                Ok(ScanPage {
+                    ops: self
+                        .ops
+                        .iter()
+                        .filter(|op| ids.contains(&op.id))
+                        .cloned()
+                        .collect(),
+                    undecodable: self
+                        .undecodable
+                        .iter()
+                        .filter(|op| ids.contains(&op.id))
+                        .cloned()
+                        .collect(),
+                    next: (ids.len() == limit).then(|| ids.last().copied()).flatten(),
+                })
+            })
+        }
+
+        fn remove<'a>(&'a self, ids: &'a [i64]) -> BoxFuture<'a, Result<Vec<i64>, BoxDynError>> {
+            Box::pin(async move {
+                self.removed.lock().unwrap().extend_from_slice(ids);
+
+                Ok(ids
+                    .iter()
+                    .copied()
+                    .filter(|id| self.removable.contains(id))
+                    .collect())
+            })
+        }
+
+        fn stats(&self) -> BoxFuture<'_, Result<QueueStats, BoxDynError>> {
+            Box::pin(async move { Ok(QueueStats::default()) })
+        }
+    }
+
+    fn mock_queue() -> MockQueue {
+        MockQueue {
+            ops: [
+                recv("union-1", 1, 2),
+                recv("union-1", 3, 4),
+                recv("union-1", 2, 5),
+                recv("union-2", 1, 2),
+            ]
+            .into_iter()
+            .zip(1..)
+            .map(|(op, id)| QueuedOp {
+                id,
+                enqueued_at: NOW,
+                op,
+            })
+            .collect(),
+            undecodable: vec![],
+            page_size: SCAN_PAGE_SIZE,
+            removable: vec![1, 2, 3, 4],
+            removed: Mutex::new(vec![]),
+            scanned_pages: Mutex::new(vec![]),
+        }
+    }
+
+    #[tokio::test]
+    async fn dry_run_does_not_remove() {
+        let queue = mock_queue();
+
+        let summary = purge_ops(
+            &queue,
+            &PurgeFilter {
+                channel_id: Some(2),
+                ..filter("union-1")
+            },
+            true,
+            NOW,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            summary,
+            PurgeSummary {
+                dry_run: true,
+                total: 2,
+                removed: [("data/identified_ibc_datagram".to_owned(), 2)].into(),
+            }
+        );
+        assert!(queue.removed.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn only_removed_ops_are_reported() {
+        let queue = MockQueue {
+            // op 3 was picked up for processing after its page was scanned
+            removable: vec![1, 2, 4],
+            ..mock_queue()
+        };
+
+        let summary = purge_ops(
+            &queue,
+            &PurgeFilter {
+                channel_id: Some(2),
+                ..filter("union-1")
+            },
+            false,
+            NOW,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(*queue.removed.lock().unwrap(), [1, 3]);
+        assert_eq!(summary.total, 1);
+    }
+
+    #[tokio::test]
+    async fn all_pages_are_purged() {
+        let queue = MockQueue {
+            page_size: 2,
+            ..mock_queue()
+        };
+
+        let summary = purge_ops(&queue, &filter("union-1"), false, NOW)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            *queue.scanned_pages.lock().unwrap(),
+            [None, Some(2), Some(4)]
+        );
+        assert_eq!(*queue.removed.lock().unwrap(), [1, 2, 3]);
+        assert_eq!(summary.total, 3);
+    }
+
+    #[tokio::test]
+    async fn undecodable_ops_are_matched_on_json() {
+        let undecodable = |id, item: Value| UndecodableOp {
+            id,
+            enqueued_at: NOW,
+            item,
+            error: "op was written with wire format version 99, but only versions up to 1 are \
+                supported"
+                .to_owned(),
+        };
+
+        let queue = MockQueue {
+            undecodable: vec![
+                undecodable(
+                    5,
+                    json!({ "v": 99, "@type": "data", "@value": { "chain_id": "union-1" } }),
+                ),
+                undecodable(
+                    6,
+                    json!({ "v": 99, "@type": "data", "@value": { "chain_id": "union-2" } }),
+                ),
+            ],
+            removable: vec![1, 2, 3, 4, 5, 6],
+            ..mock_queue()
+        };
+
+        let summary = purge_ops(&queue, &filter("union-1"), false, NOW)
+            .await
+            .unwrap();
+
+        assert_eq!(*queue.removed.lock().unwrap(), [1, 2, 3, 5]);
+        assert_eq!(
+            summary,
+            PurgeSummary {
+                dry_run: false,
+                total: 4,
+                removed: [
+                    ("data/identified_ibc_datagram".to_owned(), 3),
+                    (UNDECODABLE_OP_TYPE.to_owned(), 1)
+                ]
+                .into(),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn in_memory_queue() {
+        let interest_filter = JaqInterestFilter::new(vec![]).unwrap();
+
+        let queue = InMemoryQueue::<VoyagerMessage>::new(()).await.unwrap();
+        queue
+            .enqueue(recv("union-1", 1, 2), &interest_filter)
+            .await
+            .unwrap();
+        queue
+            .enqueue(recv("union-2", 1, 2), &interest_filter)
+            .await
+            .unwrap();
+
+        let stats = InspectQueue::stats(&queue).await.unwrap();
+        assert_eq!(stats.lanes.len(), 1);
+        assert_eq!(stats.lane(voyager_vm::READY_LANE).depth, 2);
+
+        let summary = purge_ops(&queue, &filter("union-1"), false, voyager_vm::now())
+            .await
+            .unwrap();
+        assert_eq!(summary.total, 1);
+
+        assert_eq!(
+            InspectQueue::stats(&queue)
+                .await
+                .unwrap()
+                .lane(voyager_vm::READY_LANE)
+                .depth,
+            1
+        );
+
+        let remaining = InspectQueue::scan(&queue, &ScanFilter::default(), None, 10)
+            .await
+            .unwrap();
+        assert_eq!(remaining.next, None);
+        assert_eq!(
+            remaining
+                .ops
+                .into_iter()
+                .map(|op| op.op)
+                .collect::<Vec<_>>(),
+            [recv("union-2", 1, 2)]
+        );
+    }
+}
@@ -0,0 +1,209 @@
+//! The queries into voyager that the helpers in this crate, plugins, and modules are written
+//! against.
+//!
+//! [`VoyagerQuery`] is implemented by [`VoyagerClient`] (for plugins and modules, over rpc) and by
+//! the voyager rpc server itself, and by [`MockVoyager`] in tests. Only the raw (untyped) queries
+//! of the voyager rpc need to be implemented, the typed queries are provided on top of them.
+//!
+//! [`VoyagerClient`]: crate::VoyagerClient
+//! [`MockVoyager`]: crate::testing::MockVoyager
+
+use ibc_classic_spec::IbcClassic;
+use ibc_union_spec::IbcUnion;
+use jsonrpsee::{core::RpcResult, types::ErrorObject};
+use serde_json::{json, Value};
+use unionlabs::{bytes::Bytes, ibc::core::client::height::Height, ErrorReporter};
+use voyager_core::{
+    ChainId, ClientInfo, ClientStateMeta, ClientType, IbcInterface, IbcSpec, IbcSpecId,
+    IbcStorePathKey, QueryHeight,
+};
+
+use crate::{
+    into_value,
+    rpc::{IbcProof, IbcState, SelfClientState},
+    RawClientId, FATAL_JSONRPC_ERROR_CODE,
+};
+
+#[allow(async_fn_in_trait)]
+pub trait VoyagerQuery {
+    async fn query_latest_height(&self, chain_id: &ChainId, finalized: bool) -> RpcResult<Height>;
+
+    /// The execution height of `chain_id` at the consensus `height`, as translated by its
+    /// consensus module.
+    async fn consensus_to_execution_height(
+        &self,
+        chain_id: &ChainId,
+        height: Height,
+    ) -> RpcResult<u64>;
+
+    async fn client_info_raw(
+        &self,
+        chain_id: &ChainId,
+        ibc_spec_id: &IbcSpecId,
+        client_id: RawClientId,
+    ) -> RpcResult<ClientInfo>;
+
+    async fn client_meta_raw(
+        &self,
+        chain_id: &ChainId,
+        ibc_spec_id: &IbcSpecId,
+        at: QueryHeight,
+        client_id: RawClientId,
+    ) -> RpcResult<ClientStateMeta>;
+
+    async fn query_ibc_state_raw(
+        &self,
+        chain_id: &ChainId,
+        ibc_spec_id: &IbcSpecId,
+        height: QueryHeight,
+        path: Value,
+    ) -> RpcResult<IbcState<Value>>;
+
+    async fn query_ibc_proof_raw(
+        &self,
+        chain_id: &ChainId,
+        ibc_spec_id: &IbcSpecId,
+        height: QueryHeight,
+        path: Value,
+    ) -> RpcResult<IbcProof>;
+
+    async fn self_client_state(
+        &self,
+        chain_id: &ChainId,
+        height: QueryHeight,
+    ) -> RpcResult<SelfClientState>;
+
+    async fn encode_proof_raw(
+        &self,
+        client_type: &ClientType,
+        ibc_interface: &IbcInterface,
+        ibc_spec_id: &IbcSpecId,
+        proof: Value,
+    ) -> RpcResult<Bytes>;
+
+    async fn decode_client_state(
+        &self,
+        client_type: &ClientType,
+        ibc_interface: &IbcInterface,
+        ibc_spec_id: &IbcSpecId,
+        client_state: Bytes,
+    ) -> RpcResult<Value>;
+
+    async fn decode_consensus_state(
+        &self,
+        client_type: &ClientType,
+        ibc_interface: &IbcInterface,
+        ibc_spec_id: &IbcSpecId,
+        consensus_state: Bytes,
+    ) -> RpcResult<Value>;
+
+    /// The path of the client state of `client_id` in the store of `ibc_spec_id`.
+    ///
+    /// This only knows the IBC specs built into voyager, implementors that know of more specs
+    /// should override it.
+    fn client_state_path(
+        &self,
+        ibc_spec_id: &IbcSpecId,
+        client_id: RawClientId,
+    ) -> RpcResult<Value> {
+        fn path<V: IbcSpec>(client_id: RawClientId) -> RpcResult<Value> {
+            let client_id = client_id.decode_spec::<V>().map_err(|err| {
+                ErrorObject::owned(
+                    FATAL_JSONRPC_ERROR_CODE,
+                    format!("invalid {} client id: {}", V::ID, ErrorReporter(err)),
+                    None::<()>,
+                )
+            })?;
+
+            Ok(into_value(V::client_state_path(client_id)))
+        }
+
+        match ibc_spec_id.as_str() {
+            IbcSpecId::CLASSIC => path::<IbcClassic>(client_id),
+            IbcSpecId::UNION => path::<IbcUnion>(client_id),
+            _ => Err(ErrorObject::owned(
+                FATAL_JSONRPC_ERROR_CODE,
+                format!("unknown IBC spec `{ibc_spec_id}`"),
+                None::<()>,
+            )),
+        }
+    }
+
+    async fn query_ibc_state<P: IbcStorePathKey>(
+        &self,
+        chain_id: &ChainId,
+        height: QueryHeight,
+        path: P,
+    ) -> RpcResult<IbcState<P::Value>> {
+        let ibc_state = self
+            .query_ibc_state_raw(
+                chain_id,
+                &P::Spec::ID,
+                height,
+                into_value(<P::Spec as IbcSpec>::StorePath::from(path.into())),
+            )
+            .await?;
+
+        Ok(IbcState {
+            height: ibc_state.height,
+            state: serde_json::from_value(ibc_state.state.clone()).map_err(|e| {
+                ErrorObject::owned(
+                    FATAL_JSONRPC_ERROR_CODE,
+                    format!("error decoding IBC state: {}", ErrorReporter(e)),
+                    Some(json!({
+                        "raw_state": ibc_state.state
+                    })),
+                )
+            })?,
+        })
+    }
+
+    async fn query_ibc_proof<P: IbcStorePathKey>(
+        &self,
+        chain_id: &ChainId,
+        height: QueryHeight,
+        path: P,
+    ) -> RpcResult<IbcProof> {
+        self.query_ibc_proof_raw(
+            chain_id,
+            &P::Spec::ID,
+            height,
+            into_value(<P::Spec as IbcSpec>::StorePath::from(path.into())),
+        )
+        .await
+    }
+
+    async fn client_info<V: IbcSpec>(
+        &self,
+        chain_id: &ChainId,
+        client_id: V::ClientId,
+    ) -> RpcResult<ClientInfo> {
+        self.client_info_raw(chain_id, &V::ID, RawClientId::new(client_id))
+            .await
+    }
+
+    async fn client_meta<V: IbcSpec>(
+        &self,
+        chain_id: &ChainId,
+        at: QueryHeight,
+        client_id: V::ClientId,
+    ) -> RpcResult<ClientStateMeta> {
+        self.client_meta_raw(chain_id, &V::ID, at, RawClientId::new(client_id))
+            .await
+    }
+
+    /// Encode `proof` for verification by a client of type `client_info`.
+    async fn encode_proof<V: IbcSpec>(
+        &self,
+        client_info: &ClientInfo,
+        proof: Value,
+    ) -> RpcResult<Bytes> {
+        self.encode_proof_raw(
+            &client_info.client_type,
+            &client_info.ibc_interface,
+            &V::ID,
+            proof,
+        )
+        .await
+    }
+}
@@ -0,0 +1,600 @@
+//! Estimation of the cost of relaying a packet.
+//!
+//! Relaying a packet takes two transactions: the `RecvPacket` on the
+//! destination chain, and the `AcknowledgePacket` on the source chain once the
+//! acknowledgement has been written. [`estimate_relay_cost`] builds both
+//! datagrams the same way they would be built when relaying, and estimates
+//! them with the transaction plugins of the respective chains (see
+//! [`PluginClient::estimate`]), without submitting anything.
+//!
+//! The estimates do not include the client updates required to verify the
+//! proofs, since these are shared between all packets relayed in the same
+//! batch.
+//!
+//! [`PluginClient::estimate`]: crate::module::PluginClient::estimate
+
+use ibc_solidity::Packet;
+use ibc_union_spec::{
+    BatchPacketsPath, BatchReceiptsPath, ChannelPath, ConnectionPath, IbcUnion, COMMITMENT_NULL,
+};
+use jsonrpsee::{
+    core::RpcResult,
+    types::{error::INVALID_PARAMS_CODE, ErrorObject, ErrorObjectOwned},
+};
+use macros::model;
+use unionlabs::{bytes::Bytes, hash::H256, ibc::core::client::height::Height, ErrorReporter};
+use voyager_core::{ChainId, IbcSpec};
+
+use crate::{data::IbcDatagram, module::TxEstimate, query::VoyagerQuery, RawClientId};
+
+/// A packet that has been sent, but not yet relayed.
+///
+/// ibc-union packets do not have sequences; a packet is identified by its
+/// source channel and its contents, which are committed to under
+/// [`BatchPacketsPath`].
+#[model]
+pub struct PacketRef {
+    /// The chain the packet was sent on.
+    pub chain_id: ChainId,
+    pub packet: Packet,
+    /// The acknowledgement that the packet is expected to be acknowledged
+    /// with.
+    ///
+    /// Acknowledgements are only committed to by hash, so they can't be read
+    /// back from the destination chain. Without this, the acknowledgement leg
+    /// is not estimated.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub acknowledgement: Option<Bytes>,
+}
+
+/// The estimated cost of relaying a packet, per leg.
+#[model]
+pub struct RelayCost {
+    /// The height of the source chain that the packet commitment was proven
+    /// at.
+    pub proof_height: Height,
+    /// The `RecvPacket` on the destination chain.
+    pub recv: LegCost,
+    /// The `AcknowledgePacket` on the source chain.
+    pub ack: LegCost,
+}
+
+/// The estimated cost of a single leg of a relay. Exactly one of `estimate`
+/// and `error` is set.
+#[model]
+pub struct LegCost {
+    /// The chain that the transaction for this leg is submitted on.
+    pub chain_id: ChainId,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub estimate: Option<TxEstimate>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl LegCost {
+    fn new(chain_id: ChainId, res: RpcResult<TxEstimate>) -> Self {
+        match res {
+            Ok(estimate) => Self {
+                chain_id,
+                estimate: Some(estimate),
+                error: None,
+            },
+            Err(err) => Self {
+                chain_id,
+                estimate: None,
+                error: Some(err.message().to_owned()),
+            },
+        }
+    }
+}
+
+/// Estimation of transactions, through the transaction plugin for the chain they're submitted on.
+#[allow(async_fn_in_trait)]
+pub trait TxEstimator {
+    /// Estimate the cost of submitting `msgs` on `chain_id`.
+    async fn estimate(&self, chain_id: &ChainId, msgs: Vec<IbcDatagram>) -> RpcResult<TxEstimate>;
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum RelayCostError {
+    #[error("channel {channel_id} does not exist on {chain_id}")]
+    ChannelNotFound { chain_id: ChainId, channel_id: u32 },
+    #[error("connection {connection_id} does not exist on {chain_id}")]
+    ConnectionNotFound {
+        chain_id: ChainId,
+        connection_id: u32,
+    },
+    #[error(
+        "packet is for channel {found} on the destination chain, but the counterparty of \
+        channel {channel_id} is {expected}"
+    )]
+    DestinationChannelMismatch {
+        channel_id: u32,
+        expected: u32,
+        found: u32,
+    },
+    #[error("packet {packet_hash} is not committed on {chain_id} at {height}")]
+    PacketNotFound {
+        chain_id: ChainId,
+        packet_hash: H256,
+        height: Height,
+    },
+    #[error(transparent)]
+    Rpc(#[from] ErrorObjectOwned),
+}
+
+impl From<RelayCostError> for ErrorObjectOwned {
+    fn from(value: RelayCostError) -> Self {
+        match value {
+            RelayCostError::Rpc(err) => err,
+            err => ErrorObject::owned(
+                INVALID_PARAMS_CODE,
+                ErrorReporter(err).to_string(),
+                None::<()>,
+            ),
+        }
+    }
+}
+
+/// Estimate the cost of relaying the packet referenced by `packet_ref`.
+///
+/// The packet must still be committed on the source chain at its latest
+/// finalized height, which is also the height the commitment is proven at.
+/// Failures to build or estimate either leg are reported in the respective
+/// [`LegCost`], such that one failing leg does not hide the cost of the other.
+pub async fn estimate_relay_cost(
+    client: &impl VoyagerQuery,
+    estimator: &impl TxEstimator,
+    packet_ref: PacketRef,
+) -> Result<RelayCost, RelayCostError> {
+    let PacketRef {
+        chain_id: source_chain_id,
+        packet,
+        acknowledgement,
+    } = packet_ref;
+
+    let proof_height = client.query_latest_height(&source_chain_id, true).await?;
+
+    let channel = client
+        .query_ibc_state(
+            &source_chain_id,
+            proof_height.into(),
+            ChannelPath {
+                channel_id: packet.source_channel,
+            },
+        )
+        .await?
+        .state
+        .ok_or_else(|| RelayCostError::ChannelNotFound {
+            chain_id: source_chain_id.clone(),
+            channel_id: packet.source_channel,
+        })?;
+
+    if channel.counterparty_channel_id != packet.destination_channel {
+        return Err(RelayCostError::DestinationChannelMismatch {
+            channel_id: packet.source_channel,
+            expected: channel.counterparty_channel_id,
+            found: packet.destination_channel,
+        });
+    }
+
+    let connection = client
+        .query_ibc_state(
+            &source_chain_id,
+            proof_height.into(),
+            ConnectionPath {
+                connection_id: channel.connection_id,
+            },
+        )
+        .await?
+        .state
+        .ok_or_else(|| RelayCostError::ConnectionNotFound {
+            chain_id: source_chain_id.clone(),
+            connection_id: channel.connection_id,
+        })?;
+
+    let packet_path = BatchPacketsPath {
+        channel_id: packet.source_channel,
+        batch_hash: ibc_union_spec::commit_packet(&packet),
+    };
+
+    let commitment = client
+        .query_ibc_state(&source_chain_id, proof_height.into(), packet_path.clone())
+        .await?
+        .state;
+
+    if commitment == COMMITMENT_NULL {
+        return Err(RelayCostError::PacketNotFound {
+            chain_id: source_chain_id,
+            packet_hash: packet_path.batch_hash,
+            height: proof_height,
+        });
+    }
+
+    let destination_chain_id = client
+        .client_meta_raw(
+            &source_chain_id,
+            &IbcUnion::ID,
+            proof_height.into(),
+            RawClientId::new(connection.client_id),
+        )
+        .await?
+        .chain_id;
+
+    let recv: RpcResult<TxEstimate> = async {
+        let proof = client
+            .query_ibc_proof(&source_chain_id, proof_height.into(), packet_path)
+            .await?
+            .proof;
+
+        let client_info = client
+            .client_info_raw(
+                &destination_chain_id,
+                &IbcUnion::ID,
+                RawClientId::new(connection.counterparty_client_id),
+            )
+            .await?;
+
+        let proof = client.encode_proof::<IbcUnion>(&client_info, proof).await?;
+
+        estimator
+            .estimate(
+                &destination_chain_id,
+                vec![IbcDatagram::new::<IbcUnion>(
+                    ibc_union_spec::MsgPacketRecv {
+                        packets: vec![packet.clone()],
+                        relayer_msgs: vec![vec![].into()],
+                        proof,
+                        proof_height: proof_height.height(),
+                    }
+                    .into(),
+                )],
+            )
+            .await
+    }
+    .await;
+
+    let ack: RpcResult<TxEstimate> = async {
+        let acknowledgement = acknowledgement.ok_or_else(|| {
+            ErrorObject::owned(
+                INVALID_PARAMS_CODE,
+                "no acknowledgement provided",
+                None::<()>,
+            )
+        })?;
+
+        let ack_proof_height = client
+            .query_latest_height(&destination_chain_id, true)
+            .await?;
+
+        let proof = client
+            .query_ibc_proof(
+                &destination_chain_id,
+                ack_proof_height.into(),
+                BatchReceiptsPath::from_packet(&packet),
+            )
+            .await?
+            .proof;
+
+        let client_info = client
+            .client_info_raw(
+                &source_chain_id,
+                &IbcUnion::ID,
+                RawClientId::new(connection.client_id),
+            )
+            .await?;
+
+        let proof = client.encode_proof::<IbcUnion>(&client_info, proof).await?;
+
+        estimator
+            .estimate(
+                &source_chain_id,
+                vec![IbcDatagram::new::<IbcUnion>(
+                    ibc_union_spec::MsgPacketAcknowledgement {
+                        packets: vec![packet.clone()],
+                        acknowledgements: vec![acknowledgement],
+                        proof,
+                        proof_height: ack_proof_height.height(),
+                    }
+                    .into(),
+                )],
+            )
+            .await
+    }
+    .await;
+
+    Ok(RelayCost {
+        proof_height,
+        recv: LegCost::new(destination_chain_id, recv),
+        ack: LegCost::new(source_chain_id, ack),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, sync::Mutex};
+
+    use ibc_solidity::{Channel, ChannelState, Connection, ConnectionState};
+    use ibc_union_spec::{Datagram, UnionId, COMMITMENT_MAGIC};
+    use serde_json::json;
+    use voyager_core::{ClientInfo, ClientStateMeta, ClientStatus, ClientType, IbcInterface};
+
+    use super::*;
+    use crate::{into_value, testing::MockVoyager};
+
+    const SOURCE: &str = "union-devnet-1";
+    const DESTINATION: &str = "32382";
+
+    /// Two chains connected by channel 1 on the source and channel 2 on the
+    /// destination, with `commitment` committed for the packet on the source.
+    fn chains(commitment: H256) -> MockVoyager {
+        let source = ChainId::new(SOURCE);
+        let destination = ChainId::new(DESTINATION);
+
+        let client_info = ClientInfo {
+            client_type: ClientType::new(ClientType::COMETBLS_GROTH16),
+            ibc_interface: IbcInterface::new(IbcInterface::IBC_SOLIDITY),
+            metadata: Default::default(),
+        };
+
+        let voyager = MockVoyager::new();
+
+        voyager
+            .set_latest_finalized_height(&source, Height::new_with_revision(1, 100))
+            .set_latest_finalized_height(&destination, Height::new(2000))
+            .set_state(
+                &source,
+                ChannelPath { channel_id: 1 },
+                Some(Channel {
+                    state: ChannelState::Open,
+                    connection_id: 3,
+                    counterparty_channel_id: 2,
+                    counterparty_port_id: Default::default(),
+                    version: "ucs03-zkgm-0".to_owned(),
+                }),
+            )
+            .set_state(
+                &source,
+                ConnectionPath { connection_id: 3 },
+                Some(Connection {
+                    state: ConnectionState::Open,
+                    client_id: 4,
+                    counterparty_client_id: 5,
+                    counterparty_connection_id: 6,
+                }),
+            )
+            .set_state(
+                &source,
+                BatchPacketsPath {
+                    channel_id: 1,
+                    batch_hash: ibc_union_spec::commit_packet(&packet()),
+                },
+                commitment,
+            )
+            .set_client_meta::<IbcUnion>(
+                &source,
+                UnionId::new(4).unwrap(),
+                ClientStateMeta {
+                    height: Height::new(1990),
+                    chain_id: destination.clone(),
+                    status: ClientStatus::Active,
+                    resolved_at: None,
+                },
+            )
+            .set_client_info::<IbcUnion>(&source, UnionId::new(4).unwrap(), client_info.clone())
+            .set_client_info::<IbcUnion>(&destination, UnionId::new(5).unwrap(), client_info);
+
+        voyager
+    }
+
+    /// The estimate endpoints of the transaction plugins of both chains.
+    struct MockEstimator {
+        estimates: HashMap<ChainId, RpcResult<TxEstimate>>,
+        estimated: Mutex<Vec<(ChainId, Datagram)>>,
+    }
+
+    impl MockEstimator {
+        fn new(recv: RpcResult<TxEstimate>, ack: RpcResult<TxEstimate>) -> Self {
+            Self {
+                estimates: [
+                    (ChainId::new(DESTINATION), recv),
+                    (ChainId::new(SOURCE), ack),
+                ]
+                .into_iter()
+                .collect(),
+                estimated: Mutex::default(),
+            }
+        }
+    }
+
+    impl TxEstimator for MockEstimator {
+        async fn estimate(
+            &self,
+            chain_id: &ChainId,
+            msgs: Vec<IbcDatagram>,
+        ) -> RpcResult<TxEstimate> {
+            for msg in msgs {
+                self.estimated.lock().unwrap().push((
+                    chain_id.clone(),
+                    msg.decode_datagram::<IbcUnion>().unwrap().unwrap(),
+                ));
+            }
+
+            self.estimates[chain_id].clone()
+        }
+    }
+
+    fn packet() -> Packet {
+        Packet {
+            source_channel: 1,
+            destination_channel: 2,
+            data: b"data".to_vec().into(),
+            timeout_height: 0,
+            timeout_timestamp: 100,
+        }
+    }
+
+    fn packet_ref() -> PacketRef {
+        PacketRef {
+            chain_id: ChainId::new(SOURCE),
+            packet: packet(),
+            acknowledgement: Some(b"ack".into()),
+        }
+    }
+
+    fn tx_estimate(gas: u64, fee: u128, denom: &str) -> TxEstimate {
+        TxEstimate {
+            gas,
+            fee,
+            denom: denom.to_owned(),
+        }
+    }
+
+    #[tokio::test]
+    async fn estimates_both_legs() {
+        let estimator = MockEstimator::new(
+            Ok(tx_estimate(150_000, 3_000_000_000_000_000, "wei")),
+            Ok(tx_estimate(400_000, 600_000, "muno")),
+        );
+
+        let cost = estimate_relay_cost(&chains(COMMITMENT_MAGIC), &estimator, packet_ref())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            cost,
+            RelayCost {
+                proof_height: Height::new_with_revision(1, 100),
+                recv: LegCost {
+                    chain_id: ChainId::new(DESTINATION),
+                    estimate: Some(tx_estimate(150_000, 3_000_000_000_000_000, "wei")),
+                    error: None,
+                },
+                ack: LegCost {
+                    chain_id: ChainId::new(SOURCE),
+                    estimate: Some(tx_estimate(400_000, 600_000, "muno")),
+                    error: None,
+                },
+            }
+        );
+
+        let estimated = estimator.estimated.into_inner().unwrap();
+
+        let [(recv_chain_id, Datagram::PacketRecv(recv)), (ack_chain_id, Datagram::PacketAcknowledgement(ack))] =
+            &*estimated
+        else {
+            panic!("unexpected datagrams: {estimated:?}");
+        };
+
+        assert_eq!(recv_chain_id.as_str(), DESTINATION);
+        assert_eq!(recv.packets, [packet()]);
+        assert_eq!(recv.proof_height, 100);
+        assert_eq!(
+            recv.proof,
+            MockVoyager::encoded(&MockVoyager::proof(
+                Height::new_with_revision(1, 100),
+                BatchPacketsPath {
+                    channel_id: 1,
+                    batch_hash: ibc_union_spec::commit_packet(&packet()),
+                }
+            ))
+        );
+
+        assert_eq!(ack_chain_id.as_str(), SOURCE);
+        assert_eq!(ack.packets, [packet()]);
+        assert_eq!(ack.acknowledgements, [Bytes::from(b"ack")]);
+        assert_eq!(ack.proof_height, 2000);
+        assert_eq!(
+            ack.proof,
+            MockVoyager::encoded(&MockVoyager::proof(
+                Height::new(2000),
+                BatchReceiptsPath::from_packet(&packet())
+            ))
+        );
+    }
+
+    #[tokio::test]
+    async fn failing_leg_is_reported_per_leg() {
+        let estimator = MockEstimator::new(
+            Ok(tx_estimate(150_000, 3_000_000_000_000_000, "wei")),
+            Err(ErrorObject::owned(
+                -1,
+                "tx simulation failed: packet not received",
+                None::<()>,
+            )),
+        );
+
+        let cost = estimate_relay_cost(&chains(COMMITMENT_MAGIC), &estimator, packet_ref())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            cost.recv.estimate,
+            Some(tx_estimate(150_000, 3_000_000_000_000_000, "wei"))
+        );
+        assert_eq!(cost.recv.error, None);
+
+        assert_eq!(cost.ack.chain_id.as_str(), SOURCE);
+        assert_eq!(cost.ack.estimate, None);
+        assert_eq!(
+            cost.ack.error.as_deref(),
+            Some("tx simulation failed: packet not received")
+        );
+    }
+
+    #[tokio::test]
+    async fn ack_leg_requires_acknowledgement() {
+        let estimator = MockEstimator::new(
+            Ok(tx_estimate(150_000, 3_000_000_000_000_000, "wei")),
+            Ok(tx_estimate(400_000, 600_000, "muno")),
+        );
+
+        let cost = estimate_relay_cost(
+            &chains(COMMITMENT_MAGIC),
+            &estimator,
+            PacketRef {
+                acknowledgement: None,
+                ..packet_ref()
+            },
+        )
+        .await
+        .unwrap();
+
+        assert!(cost.recv.estimate.is_some());
+        assert_eq!(
+            cost.ack.error.as_deref(),
+            Some("no acknowledgement provided")
+        );
+
+        // only the recv leg is estimated
+        assert_eq!(estimator.estimated.into_inner().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn packet_not_committed() {
+        let estimator =
+            MockEstimator::new(Ok(tx_estimate(0, 0, "wei")), Ok(tx_estimate(0, 0, "muno")));
+
+        let err = estimate_relay_cost(&chains(COMMITMENT_NULL), &estimator, packet_ref())
+            .await
+            .unwrap_err();
+
+        assert!(
+            matches!(err, RelayCostError::PacketNotFound { .. }),
+            "{err:?}"
+        );
+        assert_eq!(ErrorObjectOwned::from(err).code(), INVALID_PARAMS_CODE);
+    }
+
+    #[test]
+    fn tx_estimate_fee_is_string() {
+        assert_eq!(
+            into_value(tx_estimate(1, u128::MAX, "wei")),
+            json!({
+                "gas": 1,
+                "fee": u128::MAX.to_string(),
+                "denom": "wei",
+            })
+        );
+    }
+}
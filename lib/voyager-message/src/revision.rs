@@ -0,0 +1,327 @@
+//! Cross-checking the IBC revision number of cosmos chains.
+//!
+//! The revision number of the heights of a cosmos chain is parsed from its
+//! chain id (`union-testnet-9` -> `9`). Some chains have a trailing number in
+//! their chain id that is not their IBC revision number, in which case the
+//! plugins for the chain can be configured with a `revision_number_override`.
+//! Heights with the wrong revision don't match what the clients tracking the
+//! chain expect, which breaks proof verification on the counterparty, so the
+//! revision that is used is cross-checked once:
+//!
+//! - against the latest height of a client tracking the chain on a
+//!   counterparty, if a [`RevisionCheckConfig`] is provided, or otherwise
+//! - against the revision that ibc-go derives from the chain id reported by the
+//!   node (see [`ibc_go_revision`]).
+//!
+//! A mismatch is logged as a warning, and does not stop the plugin.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+use voyager_core::{ChainId, IbcSpecId, QueryHeight};
+
+use crate::{query::VoyagerQuery, RawClientId};
+
+/// A client tracking this chain on a counterparty, to check the revision number against.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RevisionCheckConfig {
+    /// The chain that the client is on.
+    pub counterparty_chain_id: ChainId,
+    pub ibc_spec_id: IbcSpecId,
+    pub client_id: RawClientId,
+}
+
+/// The revision number that ibc-go derives from `chain_id` (`ParseChainID`).
+///
+/// Only chain ids of the form `{chain}-{revision}` have a revision, where
+/// `{revision}` is a number without leading zeros. The revision of all other
+/// chain ids is `0`.
+#[must_use]
+pub fn ibc_go_revision(chain_id: &str) -> u64 {
+    let Some((name, revision)) = chain_id.rsplit_once('-') else {
+        return 0;
+    };
+
+    if name.is_empty()
+        || name.ends_with('-')
+        || revision.starts_with('0')
+        || !revision.bytes().all(|b| b.is_ascii_digit())
+    {
+        return 0;
+    }
+
+    revision.parse().unwrap_or(0)
+}
+
+/// Where the revision that was checked against was observed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RevisionSource {
+    /// The latest height of a client tracking the chain on a counterparty.
+    CounterpartyClient {
+        chain_id: ChainId,
+        ibc_spec_id: IbcSpecId,
+        client_id: RawClientId,
+    },
+    /// The chain id reported by the node, as parsed by ibc-go.
+    NodeChainId { network: String },
+}
+
+impl fmt::Display for RevisionSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CounterpartyClient {
+                chain_id,
+                ibc_spec_id,
+                client_id,
+            } => write!(
+                f,
+                "the latest height of {ibc_spec_id} client {} on {chain_id}",
+                client_id.as_raw()
+            ),
+            Self::NodeChainId { network } => write!(f, "the chain id {network} of the node"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error(
+    "the heights of {chain_id} are created with revision {revision}, but {observed_from} \
+    has revision {observed}"
+)]
+pub struct RevisionMismatch {
+    pub chain_id: ChainId,
+    /// The revision that is used for the heights of the chain.
+    pub revision: u64,
+    pub observed: u64,
+    pub observed_from: RevisionSource,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum RevisionCheckError {
+    #[error("unable to query the client to check the revision against")]
+    Query(#[from] jsonrpsee::types::ErrorObjectOwned),
+    #[error(
+        "client {} on {counterparty_chain_id} tracks {tracked_chain_id}, not {chain_id}",
+        client_id.as_raw()
+    )]
+    WrongChain {
+        chain_id: ChainId,
+        counterparty_chain_id: ChainId,
+        client_id: RawClientId,
+        tracked_chain_id: ChainId,
+    },
+}
+
+/// Check `revision` against the revision ibc-go derives from `network`, the chain id reported by
+/// the node of `chain_id`.
+pub fn check_node_revision(
+    chain_id: &ChainId,
+    network: &str,
+    revision: u64,
+) -> Result<(), RevisionMismatch> {
+    report(RevisionMismatch {
+        chain_id: chain_id.clone(),
+        revision,
+        observed: ibc_go_revision(network),
+        observed_from: RevisionSource::NodeChainId {
+            network: network.to_owned(),
+        },
+    })
+}
+
+/// Check `revision` against the latest height of the client tracking `chain_id` configured in
+/// `config`.
+pub async fn check_counterparty_revision(
+    client: &impl VoyagerQuery,
+    chain_id: &ChainId,
+    revision: u64,
+    config: &RevisionCheckConfig,
+) -> Result<Result<(), RevisionMismatch>, RevisionCheckError> {
+    let meta = client
+        .client_meta_raw(
+            &config.counterparty_chain_id,
+            &config.ibc_spec_id,
+            QueryHeight::Latest,
+            config.client_id.clone(),
+        )
+        .await?;
+
+    if &meta.chain_id != chain_id {
+        return Err(RevisionCheckError::WrongChain {
+            chain_id: chain_id.clone(),
+            counterparty_chain_id: config.counterparty_chain_id.clone(),
+            client_id: config.client_id.clone(),
+            tracked_chain_id: meta.chain_id,
+        });
+    }
+
+    Ok(report(RevisionMismatch {
+        chain_id: chain_id.clone(),
+        revision,
+        observed: meta.height.revision(),
+        observed_from: RevisionSource::CounterpartyClient {
+            chain_id: config.counterparty_chain_id.clone(),
+            ibc_spec_id: config.ibc_spec_id.clone(),
+            client_id: config.client_id.clone(),
+        },
+    }))
+}
+
+/// Log the outcome of a check, returning the mismatch if the revisions differ.
+fn report(check: RevisionMismatch) -> Result<(), RevisionMismatch> {
+    if check.revision == check.observed {
+        info!(
+            chain_id = %check.chain_id,
+            revision = check.revision,
+            "revision matches {}",
+            check.observed_from
+        );
+
+        Ok(())
+    } else {
+        warn!(
+            chain_id = %check.chain_id,
+            revision = check.revision,
+            observed = check.observed,
+            "REVISION MISMATCH: {check}. Proofs at these heights will fail to verify on the \
+            counterparty; set (or correct) `revision_number_override` in the plugin config"
+        );
+
+        Err(check)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ibc_classic_spec::IbcClassic;
+    use unionlabs::{ibc::core::client::height::Height, id::ClientId};
+    use voyager_core::{ClientStateMeta, ClientStatus};
+
+    use super::*;
+    use crate::testing::MockVoyager;
+
+    #[test]
+    fn ibc_go_revision_from_chain_id() {
+        assert_eq!(ibc_go_revision("union-testnet-9"), 9);
+        assert_eq!(ibc_go_revision("axelar-dojo-1"), 1);
+        assert_eq!(ibc_go_revision("cosmoshub-4"), 4);
+
+        assert_eq!(ibc_go_revision("union"), 0);
+        assert_eq!(ibc_go_revision("union-testnet"), 0);
+        assert_eq!(ibc_go_revision("union-01"), 0);
+        assert_eq!(ibc_go_revision("union-0"), 0);
+        assert_eq!(ibc_go_revision("union--1"), 0);
+        assert_eq!(ibc_go_revision("-1"), 0);
+        assert_eq!(ibc_go_revision("union-1a"), 0);
+        assert_eq!(ibc_go_revision("union-99999999999999999999"), 0);
+    }
+
+    #[test]
+    fn node_revision() {
+        let chain_id = ChainId::new("axelar-dojo-1");
+
+        assert_eq!(check_node_revision(&chain_id, "axelar-dojo-1", 1), Ok(()));
+
+        assert_eq!(
+            check_node_revision(&chain_id, "axelar-dojo-1", 2),
+            Err(RevisionMismatch {
+                chain_id: chain_id.clone(),
+                revision: 2,
+                observed: 1,
+                observed_from: RevisionSource::NodeChainId {
+                    network: "axelar-dojo-1".to_owned(),
+                },
+            })
+        );
+    }
+
+    fn config() -> RevisionCheckConfig {
+        RevisionCheckConfig {
+            counterparty_chain_id: ChainId::new("union-1"),
+            ibc_spec_id: IbcSpecId::new_static(IbcSpecId::CLASSIC),
+            client_id: RawClientId::new("07-tendermint-3"),
+        }
+    }
+
+    /// The client of [`config`], tracking `chain_id` at `height`.
+    fn client(chain_id: &str, height: Height) -> MockVoyager {
+        let voyager = MockVoyager::new();
+        voyager.set_client_meta::<IbcClassic>(
+            &ChainId::new("union-1"),
+            ClientId::new("07-tendermint", 3),
+            ClientStateMeta {
+                height,
+                chain_id: ChainId::new(chain_id.to_owned()),
+                status: ClientStatus::Active,
+                resolved_at: None,
+            },
+        );
+        voyager
+    }
+
+    #[tokio::test]
+    async fn counterparty_revision() {
+        let chain_id = ChainId::new("legacy-chain-1");
+
+        // the client was created with the correct revision, 0
+        let client = client("legacy-chain-1", Height::new_with_revision(0, 100));
+
+        assert_eq!(
+            check_counterparty_revision(&client, &chain_id, 0, &config())
+                .await
+                .unwrap(),
+            Ok(())
+        );
+
+        // the revision parsed from the chain id does not match
+        assert_eq!(
+            check_counterparty_revision(&client, &chain_id, 1, &config())
+                .await
+                .unwrap(),
+            Err(RevisionMismatch {
+                chain_id: chain_id.clone(),
+                revision: 1,
+                observed: 0,
+                observed_from: RevisionSource::CounterpartyClient {
+                    chain_id: ChainId::new("union-1"),
+                    ibc_spec_id: IbcSpecId::new_static(IbcSpecId::CLASSIC),
+                    client_id: RawClientId::new("07-tendermint-3"),
+                },
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn counterparty_client_for_another_chain() {
+        let client = client("other-1", Height::new_with_revision(1, 100));
+
+        assert!(matches!(
+            check_counterparty_revision(&client, &ChainId::new("legacy-chain-1"), 1, &config())
+                .await,
+            Err(RevisionCheckError::WrongChain { tracked_chain_id, .. })
+                if tracked_chain_id == ChainId::new("other-1")
+        ));
+    }
+
+    #[test]
+    fn mismatch_message() {
+        let mismatch = RevisionMismatch {
+            chain_id: ChainId::new("legacy-chain-1"),
+            revision: 1,
+            observed: 0,
+            observed_from: RevisionSource::CounterpartyClient {
+                chain_id: ChainId::new("union-1"),
+                ibc_spec_id: IbcSpecId::new_static(IbcSpecId::CLASSIC),
+                client_id: RawClientId::new("07-tendermint-3"),
+            },
+        };
+
+        assert_eq!(
+            mismatch.to_string(),
+            "the heights of legacy-chain-1 are created with revision 1, but the latest height of \
+            ibc-classic client \"07-tendermint-3\" on union-1 has revision 0"
+        );
+    }
+}
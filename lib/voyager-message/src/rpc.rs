@@ -1,3 +1,4 @@
+use chain_utils::relay_progress::RelayProgress;
 use jsonrpsee::{
     self,
     core::RpcResult,
@@ -7,23 +8,40 @@ use jsonrpsee::{
 use macros::model;
 use serde::de::DeserializeOwned;
 use serde_json::{json, Value};
-use unionlabs::{bytes::Bytes, ibc::core::client::height::Height, ErrorReporter};
+use unionlabs::{bytes::Bytes, hash::H256, ibc::core::client::height::Height, ErrorReporter};
 use voyager_core::IbcSpecId;
+use voyager_vm::{Op, QueueStats};
 
 use crate::{
-    context::LoadedModulesInfo,
+    cache_snapshot::{CacheSnapshot, ImportReport},
+    client_state_diff::ClientStateDiff,
+    consensus_heights::{ConsensusStateHeights, Pagination},
     core::{ChainId, ClientInfo, ClientStateMeta, ClientType, IbcInterface, QueryHeight},
-    RawClientId, FATAL_JSONRPC_ERROR_CODE,
+    error::VoyagerError,
+    freeze::{ClientRef, FrozenRelaying},
+    handshake::{InitChannel, InitConnection},
+    module::{LoadedModulesInfo, ReloadReport},
+    purge::{PurgeFilter, PurgeSummary},
+    relay_cost::{PacketRef, RelayCost},
+    RawClientId, VoyagerMessage, FATAL_JSONRPC_ERROR_CODE,
 };
 
+#[cfg(feature = "server")]
 pub mod server;
 
-#[rpc(
-    client,
-    server,
-    client_bounds(Self: Send + Sync),
-    server_bounds(Self:),
-    namespace = "voyager",
+#[cfg_attr(
+    feature = "server",
+    rpc(
+        client,
+        server,
+        client_bounds(Self: Send + Sync),
+        server_bounds(Self:),
+        namespace = "voyager",
+    )
+)]
+#[cfg_attr(
+    not(feature = "server"),
+    rpc(client, client_bounds(Self: Send + Sync), namespace = "voyager")
 )]
 // TODO: Ensure that height is always the last parameter for consistency
 pub trait VoyagerRpc {
@@ -41,6 +59,31 @@ pub trait VoyagerRpc {
     // TODO: Make this return a better type than i64
     async fn query_latest_timestamp(&self, chain_id: ChainId, finalized: bool) -> RpcResult<i64>;
 
+    /// The timestamp of the block at `height` on `chain_id`, in nanoseconds
+    /// since the unix epoch.
+    #[method(name = "timestampAtHeight")]
+    async fn timestamp_at_height(&self, chain_id: ChainId, height: Height) -> RpcResult<i64>;
+
+    /// The timestamps of the blocks at all of `heights` on `chain_id`, in the
+    /// same order as `heights`. See [`Self::timestamp_at_height`].
+    #[method(name = "timestampsAtHeights")]
+    async fn timestamps_at_heights(
+        &self,
+        chain_id: ChainId,
+        heights: Vec<Height>,
+    ) -> RpcResult<Vec<i64>>;
+
+    /// The execution block number that was finalized at the consensus
+    /// `height` of `chain_id`, as translated by the consensus module of
+    /// `chain_id`. This is only supported for chains where the two differ
+    /// (i.e. beacon-kit chains).
+    #[method(name = "consensusToExecutionHeight")]
+    async fn consensus_to_execution_height(
+        &self,
+        chain_id: ChainId,
+        height: Height,
+    ) -> RpcResult<u64>;
+
     // =================
     // IBC state queries
     // =================
@@ -80,6 +123,30 @@ pub trait VoyagerRpc {
         path: Value,
     ) -> RpcResult<IbcProof>;
 
+    /// The heights of the consensus states of `client_id`, as stored at the
+    /// latest height of the chain, sorted descending.
+    #[method(name = "consensusStateHeights")]
+    async fn consensus_state_heights(
+        &self,
+        chain_id: ChainId,
+        ibc_spec_id: IbcSpecId,
+        client_id: RawClientId,
+        pagination: Pagination,
+    ) -> RpcResult<ConsensusStateHeights>;
+
+    /// Compare the client state of `client_id` against the self client state
+    /// of the chain it tracks, flagging mismatches that are known to break the
+    /// client. Heights and timestamps are only compared if `include_heights`
+    /// is set. See [`diff_client_state`](crate::client_state_diff::diff_client_state).
+    #[method(name = "diffClientState")]
+    async fn diff_client_state(
+        &self,
+        chain_id: ChainId,
+        ibc_spec_id: IbcSpecId,
+        client_id: RawClientId,
+        include_heights: bool,
+    ) -> RpcResult<ClientStateDiff>;
+
     // ========================================
     // self state queries, for creating clients
     // ========================================
@@ -137,6 +204,95 @@ pub trait VoyagerRpc {
         ibc_spec_id: IbcSpecId,
         consensus_state: Bytes,
     ) -> RpcResult<Value>;
+
+    // ==========
+    // handshakes
+    // ==========
+
+    /// Build the op to open a new connection. See
+    /// [`init_connection`](crate::handshake::init_connection).
+    #[method(name = "initConnection")]
+    async fn init_connection(&self, msg: InitConnection) -> RpcResult<Op<VoyagerMessage>>;
+
+    /// Build the op to open a new channel. See
+    /// [`init_channel`](crate::handshake::init_channel).
+    #[method(name = "initChannel")]
+    async fn init_channel(&self, msg: InitChannel) -> RpcResult<Op<VoyagerMessage>>;
+
+    // =======
+    // packets
+    // =======
+
+    /// Estimate the cost of relaying a pending packet, without submitting
+    /// anything. See
+    /// [`estimate_relay_cost`](crate::relay_cost::estimate_relay_cost).
+    #[method(name = "estimateRelayCost")]
+    async fn estimate_relay_cost(&self, packet_ref: PacketRef) -> RpcResult<RelayCost>;
+
+    /// The highest packet sequences relayed on `channel` of `chain_id`, as recorded by the
+    /// transaction plugin of the chain. See [`PluginClient::relay_progress`].
+    ///
+    /// [`PluginClient::relay_progress`]: crate::module::PluginClient::relay_progress
+    #[method(name = "relayProgress")]
+    async fn relay_progress(&self, chain_id: ChainId, channel: String) -> RpcResult<RelayProgress>;
+
+    /// Resume relaying over the connections of a frozen client, once the freeze has been
+    /// reviewed. The client is removed from the freeze list, and the relaying that was suppressed
+    /// because of it is returned. See [`crate::freeze`].
+    #[method(name = "unfreezeRelaying")]
+    async fn unfreeze_relaying(&self, client: ClientRef) -> RpcResult<FrozenRelaying>;
+
+    /// Apply a new config to a running plugin. See [`PluginClient::reload`].
+    ///
+    /// [`PluginClient::reload`]: crate::module::PluginClient::reload
+    #[method(name = "reloadPlugin")]
+    async fn reload_plugin(&self, plugin_name: String, config: Value) -> RpcResult<ReloadReport>;
+
+    /// Introspect the state of a running plugin. See
+    /// [`PluginClient::debug_state`].
+    ///
+    /// [`PluginClient::debug_state`]: crate::module::PluginClient::debug_state
+    #[method(name = "pluginDebugState")]
+    async fn plugin_debug_state(&self, plugin_name: String) -> RpcResult<Value>;
+
+    /// Re-run the event extraction of a running event source plugin for a
+    /// transaction. See [`PluginClient::debug_parse_tx`].
+    ///
+    /// [`PluginClient::debug_parse_tx`]: crate::module::PluginClient::debug_parse_tx
+    #[method(name = "pluginDebugParseTx")]
+    async fn plugin_debug_parse_tx(&self, plugin_name: String, tx_hash: H256) -> RpcResult<Value>;
+
+    /// Export the caches of a running plugin, or of voyager itself if `plugin_name` is not set.
+    /// See [`PluginClient::export_caches`].
+    ///
+    /// [`PluginClient::export_caches`]: crate::module::PluginClient::export_caches
+    #[method(name = "exportCaches")]
+    async fn export_caches(&self, plugin_name: Option<String>) -> RpcResult<CacheSnapshot>;
+
+    /// Import a cache snapshot into a running plugin, or into voyager itself if `plugin_name` is
+    /// not set. See [`PluginClient::import_caches`].
+    ///
+    /// [`PluginClient::import_caches`]: crate::module::PluginClient::import_caches
+    #[method(name = "importCaches")]
+    async fn import_caches(
+        &self,
+        plugin_name: Option<String>,
+        snapshot: CacheSnapshot,
+    ) -> RpcResult<ImportReport>;
+
+    // =====
+    // queue
+    // =====
+
+    /// Remove the queued ops matching `filter`, or only summarize them if
+    /// `dry_run` is set. See [`purge_ops`](crate::purge::purge_ops).
+    #[method(name = "purgeOps")]
+    async fn purge_ops(&self, filter: PurgeFilter, dry_run: bool) -> RpcResult<PurgeSummary>;
+
+    /// The depth and the age of the oldest op of each lane of the queue. See
+    /// [`InspectQueue::stats`](voyager_vm::InspectQueue::stats).
+    #[method(name = "queueStats")]
+    async fn queue_stats(&self) -> RpcResult<QueueStats>;
 }
 
 #[model]
@@ -164,6 +320,7 @@ impl IbcState<Value> {
 pub struct IbcProof {
     /// The height that the proof was read at.
     pub height: Height,
+    #[serde(with = "crate::compression::compressed_value")]
     pub proof: Value,
 }
 
@@ -197,5 +354,11 @@ pub fn missing_state(
     message: impl Into<String>,
     data: Option<Value>,
 ) -> impl FnOnce() -> ErrorObjectOwned {
-    move || ErrorObject::owned(FATAL_JSONRPC_ERROR_CODE, message, data)
+    move || {
+        VoyagerError::Fatal {
+            reason: message.into(),
+            data,
+        }
+        .into()
+    }
 }
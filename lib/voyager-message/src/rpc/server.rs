@@ -1,33 +1,56 @@
 use std::{
     fmt::Debug,
-    sync::{Arc, OnceLock},
+    future::Future,
+    path::PathBuf,
+    sync::{Arc, Mutex, OnceLock},
+    time::{SystemTime, UNIX_EPOCH},
 };
 
+use chain_utils::relay_progress::RelayProgress;
+use ibc_union_spec::IbcUnion;
 use jsonrpsee::{
     core::{async_trait, RpcResult},
-    types::{ErrorObject, ErrorObjectOwned},
+    types::{error::METHOD_NOT_FOUND_CODE, ErrorObject, ErrorObjectOwned},
 };
 use serde_json::Value;
-use tracing::{debug, instrument, trace};
-use unionlabs::{bytes::Bytes, ibc::core::client::height::Height, ErrorReporter};
-use voyager_core::IbcSpecId;
+use tracing::{debug, info, instrument, trace, warn};
+use unionlabs::{bytes::Bytes, hash::H256, ibc::core::client::height::Height, ErrorReporter};
+use voyager_core::{HeightFormat, IbcSpecId};
+use voyager_vm::{Op, QueueStats};
 
 // use valuable::Valuable;
 // use voyager_core::IbcStoreFormat;
 use crate::{
-    context::{LoadedModulesInfo, Modules},
-    core::{ChainId, ClientInfo, ClientStateMeta, ClientType, IbcInterface, QueryHeight},
+    cache_snapshot::{CacheSnapshot, ImportReport},
+    client_state_diff::{self, ClientStateDiff},
+    consensus_heights::{ConsensusStateHeights, Pagination},
+    context::Modules,
+    core::{
+        ChainId, ClientInfo, ClientStateMeta, ClientStatus, ClientType, IbcInterface, IbcSpec,
+        IbcStorePathKey, QueryHeight,
+    },
+    data::IbcDatagram,
+    error::VoyagerError,
+    freeze::{self, ClientRef, FreezeError, FreezeList, FrozenRelaying},
+    handshake::{self, InitChannel, InitConnection},
     into_value,
     module::{
-        ClientModuleClient, ConsensusModuleClient, RawProofModuleClient, RawStateModuleClient,
+        ClientModuleClient, ConsensusModuleClient, LoadedModulesInfo, PluginClient,
+        RawProofModuleClient, RawStateModuleClient, ReloadReport, TxEstimate,
     },
+    purge::{self, PurgeError, PurgeFilter, PurgeSummary, QueueInspector},
+    query::VoyagerQuery,
+    relay_cost::{self, PacketRef, RelayCost, TxEstimator},
     rpc::{
-        json_rpc_error_to_error_object, IbcProof, IbcState, SelfClientState, SelfConsensusState,
-        VoyagerRpcServer,
+        json_rpc_error_to_error_object,
+        server::cache::{Cache, CacheConfig},
+        IbcProof, IbcState, SelfClientState, SelfConsensusState, VoyagerRpcServer,
     },
-    IbcSpec, IbcStorePathKey, RawClientId, FATAL_JSONRPC_ERROR_CODE,
+    RawClientId, VoyagerMessage, FATAL_JSONRPC_ERROR_CODE,
 };
 
+pub mod cache;
+
 #[derive(Debug, Clone)]
 pub struct Server {
     inner: Arc<ServerInner>,
@@ -36,33 +59,22 @@ pub struct Server {
 #[derive(Debug, Clone)]
 pub struct ServerInner {
     modules: OnceLock<Arc<Modules>>,
-    // ibc_state_cache: Cache,
+    queue: OnceLock<Arc<dyn QueueInspector>>,
+    freeze_list: OnceLock<PathBuf>,
+    /// Serializes updates of the freeze list file.
+    freeze_list_lock: Mutex<()>,
+    cache: Cache,
 }
 
-// #[derive(Clone)]
-// struct Cache(moka::future::Cache<StateQuery, Value>);
-
-// impl Debug for Cache {
-//     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-//         write!(f, "Cache({:?}, {})", self.0.name(), self.0.entry_count())
-//     }
-// }
-
 impl Server {
-    #[allow(clippy::new_without_default)]
-    pub fn new() -> Self {
+    pub fn new(cache_config: &CacheConfig) -> Self {
         Server {
             inner: Arc::new(ServerInner {
                 modules: OnceLock::new(),
-                // ibc_state_cache: Cache(
-                //     moka::future::Cache::builder()
-                //         .eviction_listener(|k, v, why| {
-                //             error!(?k, ?v, ?why, "value evicted from the cache")
-                //         })
-                //         .max_capacity(10_000)
-                //         .name("ibc_state_cache")
-                //         .build(),
-                // ),
+                queue: OnceLock::new(),
+                freeze_list: OnceLock::new(),
+                freeze_list_lock: Mutex::new(()),
+                cache: Cache::new(cache_config),
             }),
         }
     }
@@ -78,6 +90,66 @@ impl Server {
         self.inner.modules()
     }
 
+    /// Provide access to the queue, for the queue administration methods.
+    pub fn set_queue(&self, queue: Arc<dyn QueueInspector>) {
+        let was_not_already_set = self.inner.queue.set(queue).is_ok();
+
+        assert!(was_not_already_set, "queue has already been set");
+    }
+
+    fn queue(&self) -> RpcResult<&dyn QueueInspector> {
+        self.inner
+            .queue
+            .get()
+            .map(|x| &**x)
+            .ok_or_else(|| ErrorObject::owned(-2, "queue is not available", None::<()>))
+    }
+
+    /// Set the path of the [`FreezeList`] that frozen clients are recorded in.
+    pub fn set_freeze_list(&self, path: PathBuf) {
+        let was_not_already_set = self.inner.freeze_list.set(path).is_ok();
+
+        assert!(was_not_already_set, "freeze list has already been set");
+    }
+
+    fn freeze_list(&self) -> RpcResult<&PathBuf> {
+        self.inner
+            .freeze_list
+            .get()
+            .ok_or_else(|| ErrorObject::owned(-2, "freeze list is not available", None::<()>))
+    }
+
+    /// Suppress the relaying that depends on the frozen `client`, recording it in the freeze
+    /// list. See [`freeze::propagate_freeze`].
+    #[instrument(skip_all, fields(chain_id = %client.chain_id, client_id = client.client_id))]
+    pub async fn freeze_relaying(&self, client: ClientRef) -> Result<FrozenRelaying, FreezeError> {
+        let path = self.freeze_list()?;
+
+        let frozen = freeze::propagate_freeze(self, client).await?;
+
+        let _guard = self
+            .inner
+            .freeze_list_lock
+            .lock()
+            .expect("lock is not poisoned");
+
+        let mut freeze_list = FreezeList::load(path)?;
+        freeze_list.insert(frozen.clone());
+        freeze_list.store(path)?;
+
+        warn!(
+            paired_clients = ?frozen.paired_clients,
+            channels = ?frozen.channels,
+            "client is frozen, suppressing relaying over its connections"
+        );
+
+        Ok(frozen)
+    }
+
+    pub fn cache(&self) -> &Cache {
+        &self.inner.cache
+    }
+
     #[instrument(skip_all, fields(%height, %chain_id))]
     pub async fn query_height(&self, chain_id: &ChainId, height: QueryHeight) -> RpcResult<Height> {
         match height {
@@ -105,6 +177,10 @@ impl Server {
 
                 debug!(%latest_height, finalized = true, "queried latest height");
 
+                self.inner
+                    .cache
+                    .record_finalized_height(chain_id, latest_height);
+
                 Ok(latest_height)
             }
             QueryHeight::Specific(height) => Ok(height),
@@ -145,6 +221,12 @@ impl Server {
             "queried latest height"
         );
 
+        if finalized {
+            self.inner
+                .cache
+                .record_finalized_height(chain_id, latest_height);
+        }
+
         Ok(latest_height)
     }
 
@@ -170,6 +252,74 @@ impl Server {
         Ok(latest_timestamp)
     }
 
+    #[instrument(skip_all, fields(%chain_id, %height))]
+    pub async fn timestamp_at_height(&self, chain_id: &ChainId, height: Height) -> RpcResult<i64> {
+        trace!("fetching timestamp");
+
+        let timestamp = self
+            .inner
+            .cache
+            .timestamp(chain_id, height, self.fetch_timestamp(chain_id, height))
+            .await?;
+
+        trace!(timestamp, "fetched timestamp");
+
+        Ok(timestamp)
+    }
+
+    #[instrument(skip_all, fields(%chain_id, heights = heights.len()))]
+    pub async fn timestamps_at_heights(
+        &self,
+        chain_id: &ChainId,
+        heights: &[Height],
+    ) -> RpcResult<Vec<i64>> {
+        trace!("fetching timestamps");
+
+        let timestamps = self
+            .inner
+            .cache
+            .timestamps(chain_id, heights, |height| {
+                self.fetch_timestamp(chain_id, height)
+            })
+            .await?;
+
+        trace!("fetched timestamps");
+
+        Ok(timestamps)
+    }
+
+    #[instrument(skip_all, fields(%chain_id, %height))]
+    pub async fn consensus_to_execution_height(
+        &self,
+        chain_id: &ChainId,
+        height: Height,
+    ) -> RpcResult<u64> {
+        trace!("translating consensus height");
+
+        let execution_height = self
+            .inner
+            .modules()?
+            .consensus_module(chain_id)
+            .map_err(fatal_error)?
+            .consensus_to_execution_height(height)
+            .await
+            .map_err(json_rpc_error_to_error_object)?;
+
+        trace!(execution_height, "translated consensus height");
+
+        Ok(execution_height)
+    }
+
+    async fn fetch_timestamp(&self, chain_id: &ChainId, height: Height) -> RpcResult<i64> {
+        self.inner
+            .modules()?
+            .consensus_module(chain_id)
+            .map_err(fatal_error)?
+            .timestamp_at_height(height)
+            .await
+            .map_err(json_rpc_error_to_error_object)
+    }
+
     #[instrument(skip_all, fields(%chain_id, %ibc_spec_id, client_id = %client_id.0))]
     pub async fn client_info(
         &self,
@@ -181,12 +331,17 @@ impl Server {
 
         let client_info = self
             .inner
-            .modules()?
-            .state_module(chain_id, ibc_spec_id)
-            .map_err(fatal_error)?
-            .client_info_raw(client_id.clone())
-            .await
-            .map_err(json_rpc_error_to_error_object)?;
+            .cache
+            .client_info(chain_id, ibc_spec_id, &client_id, async {
+                self.inner
+                    .modules()?
+                    .state_module(chain_id, ibc_spec_id)
+                    .map_err(fatal_error)?
+                    .client_info_raw(client_id.clone())
+                    .await
+                    .map_err(json_rpc_error_to_error_object)
+            })
+            .await?;
 
         trace!(
             %client_info.ibc_interface,
@@ -197,6 +352,23 @@ impl Server {
         Ok(client_info)
     }
 
+    /// The format that heights are returned in for `ibc_spec_id` in RPC responses.
+    pub fn height_format(&self, ibc_spec_id: &IbcSpecId) -> RpcResult<HeightFormat> {
+        self.inner
+            .modules()?
+            .ibc_spec_handlers
+            .handlers
+            .get(ibc_spec_id)
+            .map(|handler| handler.height_format)
+            .ok_or_else(|| {
+                ErrorObject::owned(
+                    FATAL_JSONRPC_ERROR_CODE,
+                    format!("unknown IBC spec `{ibc_spec_id}`"),
+                    None::<()>,
+                )
+            })
+    }
+
     #[instrument(skip_all, fields(%chain_id, %ibc_spec_id, height = %at, client_id = %client_id.0))]
     pub async fn client_meta(
         &self,
@@ -211,44 +383,121 @@ impl Server {
 
         let modules = self.inner.modules()?;
 
-        let state_module = modules.state_module(chain_id, ibc_spec_id)?;
-
-        let client_info = state_module
-            .client_info_raw(client_id.clone())
-            .await
-            .map_err(json_rpc_error_to_error_object)?;
-
-        let client_state = state_module
-            .query_ibc_state_raw(
-                height,
-                (self
-                    .modules()?
-                    .ibc_spec_handlers
-                    .handlers
-                    .get(ibc_spec_id)
-                    .unwrap()
-                    .client_state_path)(client_id.clone())
-                .unwrap(),
-            )
-            .await
-            .map_err(fatal_error)?;
-
-        trace!(%client_state);
-
-        let meta = modules
+        let client_info = self
+            .client_info(chain_id, ibc_spec_id, client_id.clone())
+            .await?;
+
+        let client_state_path = (modules
+            .ibc_spec_handlers
+            .handlers
+            .get(ibc_spec_id)
+            .unwrap()
+            .client_state_path)(client_id.clone())
+        .unwrap();
+
+        // NOTE: The client state is resolved at the requested height (and not the latest height)
+        // so that the counterparty of clients that have since been substituted or migrated is
+        // resolved correctly for historical events. For 08-wasm clients, this is the wrapped
+        // client state at this height.
+        let (height, client_state) = query_at_or_latest(
+            height,
+            || self.query_height(chain_id, QueryHeight::Latest),
+            |height| {
+                let client_state_path = &client_state_path;
+
+                self.inner.cache.state(
+                    chain_id,
+                    ibc_spec_id,
+                    height,
+                    client_state_path,
+                    async move {
+                        modules
+                            .state_module(chain_id, ibc_spec_id)?
+                            .query_ibc_state_raw(height, client_state_path.clone())
+                            .await
+                            .map_err(json_rpc_error_to_error_object)
+                    },
+                )
+            },
+        )
+        .await?;
+
+        trace!(%height, %client_state);
+
+        let client_state = client_state.as_str().unwrap().parse::<Bytes>().unwrap();
+
+        let client_module = modules
             .client_module(
                 &client_info.client_type,
                 &client_info.ibc_interface,
                 ibc_spec_id,
             )
-            .map_err(fatal_error)?
-            .decode_client_state_meta(client_state.as_str().unwrap().parse().unwrap())
+            .map_err(fatal_error)?;
+
+        let mut meta = client_module
+            .decode_client_state_meta(client_state.clone())
             .await
             .map_err(json_rpc_error_to_error_object)?;
 
+        let status = async {
+            let consensus_state_path = (modules
+                .ibc_spec_handlers
+                .handlers
+                .get(ibc_spec_id)
+                .unwrap()
+                .consensus_state_path)(
+                client_id.clone(), meta.height.to_string()
+            )
+            .map_err(|err| fatal_error(&*err))?;
+
+            let consensus_state = self
+                .inner
+                .cache
+                .state(
+                    chain_id,
+                    ibc_spec_id,
+                    height,
+                    &consensus_state_path,
+                    async {
+                        modules
+                            .state_module(chain_id, ibc_spec_id)?
+                            .query_ibc_state_raw(height, consensus_state_path.clone())
+                            .await
+                            .map_err(fatal_error)
+                    },
+                )
+                .await?;
+
+            let Some(consensus_state) = consensus_state.as_str() else {
+                return Err(ErrorObject::owned(
+                    FATAL_JSONRPC_ERROR_CODE,
+                    format!(
+                        "no consensus state found at the latest height {}",
+                        meta.height
+                    ),
+                    None::<()>,
+                ));
+            };
+
+            client_module
+                .client_status(
+                    client_state,
+                    consensus_state.parse().map_err(fatal_error)?,
+                    now_nanos(),
+                )
+                .await
+                .map_err(json_rpc_error_to_error_object)
+        }
+        .await;
+
+        meta.status = client_status_or_unknown(status);
+        meta.resolved_at = Some(height);
+
         trace!(
             client_state_meta.height = %meta.height,
             client_state_meta.chain_id = %meta.chain_id,
+            client_state_meta.status = ?meta.status,
+            client_state_meta.resolved_at = %height,
             %client_info.ibc_interface,
             %client_info.client_type,
             "fetched client meta"
@@ -266,16 +515,21 @@ impl Server {
     ) -> RpcResult<IbcState<P::Value>> {
         trace!("fetching ibc state");
 
-        let state_module = self
-            .inner
-            .modules()?
-            .state_module(chain_id, &P::Spec::ID)
-            .map_err(fatal_error)?;
+        let path = into_value(path);
 
-        let state = state_module
-            .query_ibc_state_raw(height, into_value(path.clone()))
-            .await
-            .map_err(json_rpc_error_to_error_object)?;
+        let state = self
+            .inner
+            .cache
+            .state(chain_id, &P::Spec::ID, height, &path, async {
+                self.inner
+                    .modules()?
+                    .state_module(chain_id, &P::Spec::ID)
+                    .map_err(fatal_error)?
+                    .query_ibc_state_raw(height, path.clone())
+                    .await
+                    .map_err(json_rpc_error_to_error_object)
+            })
+            .await?;
 
         // TODO: Use valuable here
         trace!(%state, "fetched ibc state");
@@ -455,6 +709,94 @@ impl Server {
             .await
             .map_err(json_rpc_error_to_error_object)
     }
+
+    #[instrument(skip_all, fields(%plugin_name))]
+    pub async fn reload_plugin(&self, plugin_name: &str, config: Value) -> RpcResult<ReloadReport> {
+        let report = self
+            .inner
+            .modules()?
+            .plugin(plugin_name)
+            .map_err(fatal_error)?
+            .reload(config)
+            .await
+            .map_err(json_rpc_error_to_error_object)?;
+
+        info!(
+            changed = ?report.changed,
+            rejected = report.rejected.len(),
+            "reloaded plugin config"
+        );
+
+        Ok(report)
+    }
+
+    #[instrument(skip_all, fields(%plugin_name))]
+    pub async fn plugin_debug_state(&self, plugin_name: &str) -> RpcResult<Value> {
+        self.inner
+            .modules()?
+            .plugin(plugin_name)
+            .map_err(fatal_error)?
+            .debug_state()
+            .await
+            .map_err(json_rpc_error_to_error_object)
+    }
+
+    #[instrument(skip_all, fields(%plugin_name, %tx_hash))]
+    pub async fn plugin_debug_parse_tx(
+        &self,
+        plugin_name: &str,
+        tx_hash: H256,
+    ) -> RpcResult<Value> {
+        self.inner
+            .modules()?
+            .plugin(plugin_name)
+            .map_err(fatal_error)?
+            .debug_parse_tx(tx_hash)
+            .await
+            .map_err(json_rpc_error_to_error_object)
+    }
+
+    #[instrument(skip_all, fields(?plugin_name))]
+    pub async fn export_caches(&self, plugin_name: Option<&str>) -> RpcResult<CacheSnapshot> {
+        match plugin_name {
+            Some(plugin_name) => self
+                .inner
+                .modules()?
+                .plugin(plugin_name)
+                .map_err(fatal_error)?
+                .export_caches()
+                .await
+                .map_err(json_rpc_error_to_error_object),
+            None => Ok(self.inner.cache.export()),
+        }
+    }
+
+    #[instrument(skip_all, fields(?plugin_name))]
+    pub async fn import_caches(
+        &self,
+        plugin_name: Option<&str>,
+        snapshot: CacheSnapshot,
+    ) -> RpcResult<ImportReport> {
+        let report = match plugin_name {
+            Some(plugin_name) => self
+                .inner
+                .modules()?
+                .plugin(plugin_name)
+                .map_err(fatal_error)?
+                .import_caches(snapshot)
+                .await
+                .map_err(json_rpc_error_to_error_object)?,
+            None => self.inner.cache.import(snapshot).await?,
+        };
+
+        info!(
+            caches = ?report.caches,
+            ignored_caches = ?report.ignored_caches,
+            "imported cache snapshot"
+        );
+
+        Ok(report)
+    }
 }
 
 /// rpc impl
@@ -476,6 +818,26 @@ impl VoyagerRpcServer for Server {
         self.query_latest_timestamp(&chain_id, finalized).await
     }
 
+    async fn timestamp_at_height(&self, chain_id: ChainId, height: Height) -> RpcResult<i64> {
+        self.timestamp_at_height(&chain_id, height).await
+    }
+
+    async fn timestamps_at_heights(
+        &self,
+        chain_id: ChainId,
+        heights: Vec<Height>,
+    ) -> RpcResult<Vec<i64>> {
+        self.timestamps_at_heights(&chain_id, &heights).await
+    }
+
+    async fn consensus_to_execution_height(
+        &self,
+        chain_id: ChainId,
+        height: Height,
+    ) -> RpcResult<u64> {
+        self.consensus_to_execution_height(&chain_id, height).await
+    }
+
     // =====
     // STATE
     // =====
@@ -496,8 +858,17 @@ impl VoyagerRpcServer for Server {
         at: QueryHeight,
         client_id: RawClientId,
     ) -> RpcResult<ClientStateMeta> {
-        self.client_meta(&chain_id, &ibc_spec_id, at, client_id)
-            .await
+        let height_format = self.height_format(&ibc_spec_id)?;
+
+        let meta = self
+            .client_meta(&chain_id, &ibc_spec_id, at, client_id)
+            .await?;
+
+        Ok(ClientStateMeta {
+            height: height_format.apply(meta.height),
+            resolved_at: meta.resolved_at.map(|height| height_format.apply(height)),
+            ..meta
+        })
     }
 
     // async fn query_client_state(
@@ -541,7 +912,6 @@ impl VoyagerRpcServer for Server {
     //         .map_err(json_rpc_error_to_error_object)
     // }
 
-    #[instrument(skip_all, fields(%chain_id, %height))]
     async fn query_ibc_state(
         &self,
         chain_id: ChainId,
@@ -549,28 +919,15 @@ impl VoyagerRpcServer for Server {
         height: QueryHeight,
         path: Value,
     ) -> RpcResult<IbcState<Value>> {
-        let height = self.query_height(&chain_id, height).await?;
-
-        debug!("fetching ibc state");
-
-        let state_module = self
-            .inner
-            .modules()?
-            .state_module(&chain_id, &ibc_spec_id)
-            .map_err(fatal_error)?;
-
-        let state = state_module
-            .query_ibc_state_raw(height, path)
-            .await
-            .map_err(json_rpc_error_to_error_object)?;
-
-        // TODO: Use valuable here
-        debug!(%state, "fetched ibc state");
+        let state =
+            VoyagerQuery::query_ibc_state_raw(self, &chain_id, &ibc_spec_id, height, path).await?;
 
-        Ok(IbcState { height, state })
+        Ok(IbcState {
+            height: self.height_format(&ibc_spec_id)?.apply(state.height),
+            ..state
+        })
     }
 
-    #[instrument(skip_all, fields(%chain_id, %height))]
     async fn query_ibc_proof(
         &self,
         chain_id: ChainId,
@@ -578,25 +935,80 @@ impl VoyagerRpcServer for Server {
         height: QueryHeight,
         path: Value,
     ) -> RpcResult<IbcProof> {
-        let height = self.query_height(&chain_id, height).await?;
+        let proof =
+            VoyagerQuery::query_ibc_proof_raw(self, &chain_id, &ibc_spec_id, height, path).await?;
 
-        debug!("fetching ibc proof");
+        Ok(IbcProof {
+            height: self.height_format(&ibc_spec_id)?.apply(proof.height),
+            ..proof
+        })
+    }
 
-        let proof_module = self
+    #[instrument(skip_all, fields(%chain_id, %ibc_spec_id, client_id = %client_id.0))]
+    async fn consensus_state_heights(
+        &self,
+        chain_id: ChainId,
+        ibc_spec_id: IbcSpecId,
+        client_id: RawClientId,
+        pagination: Pagination,
+    ) -> RpcResult<ConsensusStateHeights> {
+        pagination.validate()?;
+
+        let height_format = self.height_format(&ibc_spec_id)?;
+
+        let height = self.query_height(&chain_id, QueryHeight::Latest).await?;
+
+        debug!(%height, "fetching consensus state heights");
+
+        let consensus_state_heights = self
             .inner
             .modules()?
-            .proof_module(&chain_id, &ibc_spec_id)
-            .map_err(fatal_error)?;
-
-        let proof = proof_module
-            .query_ibc_proof_raw(height, path)
+            .state_module(&chain_id, &ibc_spec_id)
+            .map_err(fatal_error)?
+            .consensus_state_heights_raw(height, client_id, pagination)
             .await
             .map_err(json_rpc_error_to_error_object)?;
 
-        // TODO: Use valuable here
-        debug!(%proof, "fetched ibc proof");
+        debug!(
+            count = consensus_state_heights.heights.len(),
+            total = consensus_state_heights.total,
+            "fetched consensus state heights"
+        );
 
-        Ok(IbcProof { height, proof })
+        Ok(ConsensusStateHeights {
+            heights: consensus_state_heights
+                .heights
+                .into_iter()
+                .map(|height| height_format.apply(height))
+                .collect(),
+            ..consensus_state_heights
+        })
+    }
+
+    #[instrument(skip_all, fields(%chain_id, %ibc_spec_id, client_id = %client_id.0, include_heights))]
+    async fn diff_client_state(
+        &self,
+        chain_id: ChainId,
+        ibc_spec_id: IbcSpecId,
+        client_id: RawClientId,
+        include_heights: bool,
+    ) -> RpcResult<ClientStateDiff> {
+        let diff = client_state_diff::diff_client_state(
+            self,
+            &chain_id,
+            &ibc_spec_id,
+            client_id,
+            include_heights,
+        )
+        .await?;
+
+        debug!(
+            fields = diff.fields.len(),
+            dangers = ?diff.dangers,
+            "diffed client state"
+        );
+
+        Ok(diff)
     }
 
     async fn self_client_state(
@@ -662,6 +1074,337 @@ impl VoyagerRpcServer for Server {
         self.decode_consensus_state(&client_type, &ibc_interface, &ibc_spec_id, consensus_state)
             .await
     }
+
+    // ==========
+    // HANDSHAKES
+    // ==========
+
+    async fn init_connection(&self, msg: InitConnection) -> RpcResult<Op<VoyagerMessage>> {
+        Ok(handshake::init_connection(self, msg).await?)
+    }
+
+    async fn init_channel(&self, msg: InitChannel) -> RpcResult<Op<VoyagerMessage>> {
+        Ok(handshake::init_channel(self, msg).await?)
+    }
+
+    // =======
+    // PACKETS
+    // =======
+
+    async fn estimate_relay_cost(&self, packet_ref: PacketRef) -> RpcResult<RelayCost> {
+        Ok(relay_cost::estimate_relay_cost(self, self, packet_ref).await?)
+    }
+
+    #[instrument(skip_all, fields(%chain_id, %channel))]
+    async fn relay_progress(&self, chain_id: ChainId, channel: String) -> RpcResult<RelayProgress> {
+        self.inner
+            .modules()?
+            .transaction_plugin(&chain_id, &IbcUnion::ID)
+            .map_err(fatal_error)?
+            .relay_progress(channel)
+            .await
+            .map_err(json_rpc_error_to_error_object)
+    }
+
+    #[instrument(skip_all, fields(chain_id = %client.chain_id, client_id = client.client_id))]
+    async fn unfreeze_relaying(&self, client: ClientRef) -> RpcResult<FrozenRelaying> {
+        let path = self.freeze_list()?;
+
+        let _guard = self
+            .inner
+            .freeze_list_lock
+            .lock()
+            .expect("lock is not poisoned");
+
+        let mut freeze_list = FreezeList::load(path).map_err(FreezeError::from)?;
+
+        let frozen = freeze_list
+            .remove(&client)
+            .ok_or(FreezeError::NotInFreezeList { client })?;
+
+        freeze_list.store(path).map_err(FreezeError::from)?;
+
+        info!(
+            paired_clients = ?frozen.paired_clients,
+            channels = ?frozen.channels,
+            "resumed relaying"
+        );
+
+        Ok(frozen)
+    }
+
+    // =======
+    // PLUGINS
+    // =======
+
+    async fn reload_plugin(&self, plugin_name: String, config: Value) -> RpcResult<ReloadReport> {
+        self.reload_plugin(&plugin_name, config).await
+    }
+
+    async fn plugin_debug_state(&self, plugin_name: String) -> RpcResult<Value> {
+        self.plugin_debug_state(&plugin_name).await
+    }
+
+    async fn plugin_debug_parse_tx(&self, plugin_name: String, tx_hash: H256) -> RpcResult<Value> {
+        self.plugin_debug_parse_tx(&plugin_name, tx_hash).await
+    }
+
+    async fn export_caches(&self, plugin_name: Option<String>) -> RpcResult<CacheSnapshot> {
+        self.export_caches(plugin_name.as_deref()).await
+    }
+
+    async fn import_caches(
+        &self,
+        plugin_name: Option<String>,
+        snapshot: CacheSnapshot,
+    ) -> RpcResult<ImportReport> {
+        self.import_caches(plugin_name.as_deref(), snapshot).await
+    }
+
+    // =====
+    // QUEUE
+    // =====
+
+    #[instrument(skip_all, fields(chain_id = %filter.chain_id, dry_run))]
+    async fn purge_ops(&self, filter: PurgeFilter, dry_run: bool) -> RpcResult<PurgeSummary> {
+        Ok(purge::purge_ops(self.queue()?, &filter, dry_run, voyager_vm::now()).await?)
+    }
+
+    #[instrument(skip_all)]
+    async fn queue_stats(&self) -> RpcResult<QueueStats> {
+        Ok(self.queue()?.stats().await.map_err(PurgeError)?)
+    }
+}
+
+impl VoyagerQuery for Server {
+    async fn query_latest_height(&self, chain_id: &ChainId, finalized: bool) -> RpcResult<Height> {
+        self.query_latest_height(chain_id, finalized).await
+    }
+
+    async fn consensus_to_execution_height(
+        &self,
+        chain_id: &ChainId,
+        height: Height,
+    ) -> RpcResult<u64> {
+        self.consensus_to_execution_height(chain_id, height).await
+    }
+
+    async fn client_info_raw(
+        &self,
+        chain_id: &ChainId,
+        ibc_spec_id: &IbcSpecId,
+        client_id: RawClientId,
+    ) -> RpcResult<ClientInfo> {
+        self.client_info(chain_id, ibc_spec_id, client_id).await
+    }
+
+    async fn client_meta_raw(
+        &self,
+        chain_id: &ChainId,
+        ibc_spec_id: &IbcSpecId,
+        at: QueryHeight,
+        client_id: RawClientId,
+    ) -> RpcResult<ClientStateMeta> {
+        self.client_meta(chain_id, ibc_spec_id, at, client_id).await
+    }
+
+    #[instrument(skip_all, fields(%chain_id, %height))]
+    async fn query_ibc_state_raw(
+        &self,
+        chain_id: &ChainId,
+        ibc_spec_id: &IbcSpecId,
+        height: QueryHeight,
+        path: Value,
+    ) -> RpcResult<IbcState<Value>> {
+        let height = self.query_height(chain_id, height).await?;
+
+        debug!("fetching ibc state");
+
+        let state = self
+            .inner
+            .cache
+            .state(chain_id, ibc_spec_id, height, &path, async {
+                self.inner
+                    .modules()?
+                    .state_module(chain_id, ibc_spec_id)
+                    .map_err(fatal_error)?
+                    .query_ibc_state_raw(height, path.clone())
+                    .await
+                    .map_err(json_rpc_error_to_error_object)
+            })
+            .await?;
+
+        // TODO: Use valuable here
+        debug!(%state, "fetched ibc state");
+
+        Ok(IbcState { height, state })
+    }
+
+    #[instrument(skip_all, fields(%chain_id, %height))]
+    async fn query_ibc_proof_raw(
+        &self,
+        chain_id: &ChainId,
+        ibc_spec_id: &IbcSpecId,
+        height: QueryHeight,
+        path: Value,
+    ) -> RpcResult<IbcProof> {
+        let height = self.query_height(chain_id, height).await?;
+
+        debug!("fetching ibc proof");
+
+        let proof = self
+            .inner
+            .modules()?
+            .proof_module(chain_id, ibc_spec_id)
+            .map_err(fatal_error)?
+            .query_ibc_proof_raw(height, path)
+            .await
+            .map_err(json_rpc_error_to_error_object)?;
+
+        // TODO: Use valuable here
+        debug!(%proof, "fetched ibc proof");
+
+        Ok(IbcProof { height, proof })
+    }
+
+    async fn self_client_state(
+        &self,
+        chain_id: &ChainId,
+        height: QueryHeight,
+    ) -> RpcResult<SelfClientState> {
+        let height = self.query_height(chain_id, height).await?;
+
+        self.self_client_state(chain_id.clone(), height).await
+    }
+
+    async fn encode_proof_raw(
+        &self,
+        client_type: &ClientType,
+        ibc_interface: &IbcInterface,
+        ibc_spec_id: &IbcSpecId,
+        proof: Value,
+    ) -> RpcResult<Bytes> {
+        self.encode_proof(client_type, ibc_interface, ibc_spec_id, proof)
+            .await
+    }
+
+    async fn decode_client_state(
+        &self,
+        client_type: &ClientType,
+        ibc_interface: &IbcInterface,
+        ibc_spec_id: &IbcSpecId,
+        client_state: Bytes,
+    ) -> RpcResult<Value> {
+        self.decode_client_state(client_type, ibc_interface, ibc_spec_id, client_state)
+            .await
+    }
+
+    async fn decode_consensus_state(
+        &self,
+        client_type: &ClientType,
+        ibc_interface: &IbcInterface,
+        ibc_spec_id: &IbcSpecId,
+        consensus_state: Bytes,
+    ) -> RpcResult<Value> {
+        self.decode_consensus_state(client_type, ibc_interface, ibc_spec_id, consensus_state)
+            .await
+    }
+
+    /// Unlike the default, this knows every IBC spec that has a handler loaded.
+    fn client_state_path(
+        &self,
+        ibc_spec_id: &IbcSpecId,
+        client_id: RawClientId,
+    ) -> RpcResult<Value> {
+        (self
+            .modules()?
+            .ibc_spec_handlers
+            .handlers
+            .get(ibc_spec_id)
+            .ok_or_else(|| {
+                ErrorObject::owned(
+                    FATAL_JSONRPC_ERROR_CODE,
+                    format!("unknown IBC spec `{ibc_spec_id}`"),
+                    None::<()>,
+                )
+            })?
+            .client_state_path)(client_id)
+        .map_err(|err| fatal_error(&*err))
+    }
+}
+
+impl TxEstimator for Server {
+    #[instrument(skip_all, fields(%chain_id))]
+    async fn estimate(&self, chain_id: &ChainId, msgs: Vec<IbcDatagram>) -> RpcResult<TxEstimate> {
+        self.inner
+            .modules()?
+            .transaction_plugin(chain_id, &IbcUnion::ID)
+            .map_err(fatal_error)?
+            .estimate(msgs)
+            .await
+            .map_err(json_rpc_error_to_error_object)
+    }
+}
+
+/// Not all client modules support [`ClientModuleClient::client_status`], and
+/// the status is purely informational, so any errors result in
+/// [`ClientStatus::Unknown`].
+fn client_status_or_unknown(status: RpcResult<ClientStatus>) -> ClientStatus {
+    match status {
+        Ok(status) => status,
+        Err(err) if err.code() == METHOD_NOT_FOUND_CODE => ClientStatus::Unknown,
+        Err(err) => {
+            debug!(
+                error = %ErrorReporter(err),
+                "unable to determine client status"
+            );
+
+            ClientStatus::Unknown
+        }
+    }
+}
+
+fn now_nanos() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("the current timestamp must be greater than the unix epoch")
+        .as_nanos()
+        .try_into()
+        .expect("the current timestamp fits in a u64")
+}
+
+/// Run `query` at `height`, falling back to the height returned by `latest` if `height` has been
+/// pruned by the node. The height that the query was ultimately run at is returned along with the
+/// result.
+async fn query_at_or_latest<T, Q, L>(
+    height: Height,
+    latest: impl FnOnce() -> L,
+    query: impl Fn(Height) -> Q,
+) -> RpcResult<(Height, T)>
+where
+    Q: Future<Output = RpcResult<T>>,
+    L: Future<Output = RpcResult<Height>>,
+{
+    match query(height).await {
+        Ok(t) => Ok((height, t)),
+        Err(err)
+            if matches!(
+                VoyagerError::from_error_object(&err),
+                VoyagerError::Pruned { .. }
+            ) =>
+        {
+            let latest = latest().await?;
+
+            warn!(
+                %height,
+                %latest,
+                "height has been pruned, falling back to the latest height"
+            );
+
+            query(latest).await.map(|t| (latest, t))
+        }
+        Err(err) => Err(err),
+    }
 }
 
 pub(crate) fn fatal_error(t: impl core::error::Error) -> ErrorObjectOwned {
@@ -738,3 +1481,120 @@ pub(crate) fn fatal_error(t: impl core::error::Error) -> ErrorObjectOwned {
 //         sequence: NonZeroU64,
 //     },
 // }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn client_status_errors_are_unknown() {
+        assert_eq!(
+            client_status_or_unknown(Ok(ClientStatus::Expired)),
+            ClientStatus::Expired
+        );
+
+        assert_eq!(
+            client_status_or_unknown(Err(ErrorObject::owned(
+                METHOD_NOT_FOUND_CODE,
+                "Method not found",
+                None::<()>
+            ))),
+            ClientStatus::Unknown
+        );
+
+        assert_eq!(
+            client_status_or_unknown(Err(ErrorObject::owned(-1, "error", None::<()>))),
+            ClientStatus::Unknown
+        );
+    }
+    /// A node that has pruned all heights before `earliest`, and tracks a client that was
+    /// substituted at `substituted_at` (i.e. the counterparty chain id changed).
+    struct MockNode {
+        earliest: Height,
+        latest: Height,
+        substituted_at: Height,
+    }
+
+    impl MockNode {
+        async fn latest(&self) -> RpcResult<Height> {
+            Ok(self.latest)
+        }
+
+        async fn counterparty_chain_id(&self, height: Height) -> RpcResult<&'static str> {
+            if height < self.earliest {
+                Err(VoyagerError::pruned(height).into())
+            } else if height < self.substituted_at {
+                Ok("old-counterparty-1")
+            } else {
+                Ok("new-counterparty-1")
+            }
+        }
+    }
+
+    const NODE: MockNode = MockNode {
+        earliest: Height::new_with_revision(1, 100),
+        latest: Height::new_with_revision(1, 300),
+        substituted_at: Height::new_with_revision(1, 200),
+    };
+
+    #[tokio::test]
+    async fn historical_client_meta_is_not_latest() {
+        let historical = Height::new_with_revision(1, 150);
+
+        assert_eq!(
+            query_at_or_latest(
+                historical,
+                || NODE.latest(),
+                |height| NODE.counterparty_chain_id(height)
+            )
+            .await
+            .unwrap(),
+            (historical, "old-counterparty-1")
+        );
+
+        assert_eq!(
+            query_at_or_latest(
+                NODE.latest,
+                || NODE.latest(),
+                |height| NODE.counterparty_chain_id(height)
+            )
+            .await
+            .unwrap(),
+            (NODE.latest, "new-counterparty-1")
+        );
+    }
+
+    #[tokio::test]
+    async fn pruned_height_falls_back_to_latest() {
+        assert_eq!(
+            query_at_or_latest(
+                Height::new_with_revision(1, 50),
+                || NODE.latest(),
+                |height| NODE.counterparty_chain_id(height)
+            )
+            .await
+            .unwrap(),
+            (NODE.latest, "new-counterparty-1")
+        );
+    }
+
+    #[tokio::test]
+    async fn other_errors_do_not_fall_back() {
+        let err = query_at_or_latest(
+            Height::new_with_revision(1, 150),
+            || async { panic!("latest height should not be queried") },
+            |height| async move {
+                Err::<(), _>(ErrorObject::from(VoyagerError::missing_state(format!(
+                    "clients/07-tendermint-0/clientState@{height}"
+                ))))
+            },
+        )
+        .await
+        .unwrap_err();
+
+        assert!(matches!(
+            VoyagerError::from_error_object(&err),
+            VoyagerError::MissingState { .. }
+        ));
+    }
+}
@@ -0,0 +1,724 @@
+use std::{
+    collections::{HashMap, HashSet},
+    future::Future,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use futures::{stream, StreamExt, TryStreamExt};
+use jsonrpsee::{core::RpcResult, types::ErrorObjectOwned};
+use macros::model;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::{debug, trace};
+use unionlabs::ibc::core::client::height::Height;
+use voyager_core::IbcSpecId;
+
+use crate::{
+    cache_snapshot::{
+        merge_entry, CacheEntry, CacheSnapshot, ImportReport, MergeOutcome, MergeReport,
+        SnapshotError,
+    },
+    core::{ChainId, ClientInfo},
+    into_value, RawClientId,
+};
+
+/// The source of the snapshots of the caches of voyager itself, see [`Cache::export`].
+pub const VOYAGER_CACHE_SNAPSHOT_SOURCE: &str = "voyager";
+
+const CLIENT_INFO_CACHE: &str = "client_info";
+
+/// The maximum amount of timestamps that are fetched concurrently by [`Cache::timestamps`].
+const TIMESTAMP_FETCH_CONCURRENCY: usize = 16;
+
+#[model]
+#[derive(JsonSchema, Default)]
+pub struct CacheConfig {
+    #[serde(default)]
+    pub state: StateCacheConfig,
+    #[serde(default)]
+    pub client_info: ClientInfoCacheConfig,
+    #[serde(default)]
+    pub timestamp: TimestampCacheConfig,
+}
+
+#[model]
+#[derive(JsonSchema)]
+pub struct StateCacheConfig {
+    /// The maximum amount of IBC state entries to keep in the cache.
+    #[serde(default = "default_state_capacity")]
+    pub capacity: u64,
+    /// If set, entries will be evicted this many seconds after they were inserted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_to_live: Option<u64>,
+}
+
+impl Default for StateCacheConfig {
+    fn default() -> Self {
+        Self {
+            capacity: default_state_capacity(),
+            time_to_live: None,
+        }
+    }
+}
+
+#[model]
+#[derive(JsonSchema)]
+pub struct ClientInfoCacheConfig {
+    /// The maximum amount of client info entries to keep in the cache.
+    #[serde(default = "default_client_info_capacity")]
+    pub capacity: u64,
+    /// If set, entries will be evicted this many seconds after they were inserted. The client type and IBC interface of a client never change once it has been created, so by default entries are cached until they are evicted due to capacity.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_to_live: Option<u64>,
+}
+
+impl Default for ClientInfoCacheConfig {
+    fn default() -> Self {
+        Self {
+            capacity: default_client_info_capacity(),
+            time_to_live: None,
+        }
+    }
+}
+
+#[model]
+#[derive(JsonSchema)]
+pub struct TimestampCacheConfig {
+    /// The maximum amount of block timestamps to keep in the cache.
+    #[serde(default = "default_timestamp_capacity")]
+    pub capacity: u64,
+}
+
+impl Default for TimestampCacheConfig {
+    fn default() -> Self {
+        Self {
+            capacity: default_timestamp_capacity(),
+        }
+    }
+}
+
+const fn default_state_capacity() -> u64 {
+    10_000
+}
+
+const fn default_client_info_capacity() -> u64 {
+    1_000
+}
+
+const fn default_timestamp_capacity() -> u64 {
+    10_000
+}
+
+/// Caches for immutable (or effectively immutable) data queried through the voyager rpc server.
+///
+/// IBC state and block timestamps are only cached if they were queried at a concrete height that is known to be finalized on the chain they were queried on, since they can never change at such a height. The finalized height of each chain is tracked as it is queried through the server; if it is ever observed to go backwards (for example, if a devnet is restarted), all cached state and timestamps for that chain are invalidated.
+#[derive(macros::Debug, Clone)]
+pub struct Cache {
+    #[debug(skip)]
+    state: moka::future::Cache<StateCacheKey, Value>,
+    #[debug(skip)]
+    /// The cached client info, along with the unix timestamp of when it was inserted.
+    client_info: moka::future::Cache<ClientInfoCacheKey, (ClientInfo, u64)>,
+    #[debug(skip)]
+    timestamps: moka::future::Cache<(ChainId, Height), i64>,
+    #[debug(skip)]
+    finalized_heights: moka::sync::Cache<ChainId, Height>,
+    stats: Arc<CacheStats>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct StateCacheKey {
+    chain_id: ChainId,
+    ibc_spec_id: IbcSpecId,
+    /// The path, JSON encoded. [`Value`] is not [`Hash`], and the JSON encoding of a value is deterministic.
+    path: String,
+    height: Height,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+struct ClientInfoCacheKey {
+    chain_id: ChainId,
+    ibc_spec_id: IbcSpecId,
+    client_id: String,
+}
+
+#[derive(Debug, Default)]
+pub struct CacheStats {
+    pub state_hits: AtomicU64,
+    pub state_misses: AtomicU64,
+    pub client_info_hits: AtomicU64,
+    pub client_info_misses: AtomicU64,
+    pub timestamp_hits: AtomicU64,
+    pub timestamp_misses: AtomicU64,
+}
+
+impl Cache {
+    pub fn new(config: &CacheConfig) -> Self {
+        let mut state = moka::future::Cache::builder()
+            .max_capacity(config.state.capacity)
+            .support_invalidation_closures()
+            .name("ibc_state_cache");
+
+        if let Some(time_to_live) = config.state.time_to_live {
+            state = state.time_to_live(Duration::from_secs(time_to_live));
+        }
+
+        let mut client_info = moka::future::Cache::builder()
+            .max_capacity(config.client_info.capacity)
+            .name("client_info_cache");
+
+        if let Some(time_to_live) = config.client_info.time_to_live {
+            client_info = client_info.time_to_live(Duration::from_secs(time_to_live));
+        }
+
+        Self {
+            state: state.build(),
+            client_info: client_info.build(),
+            timestamps: moka::future::Cache::builder()
+                .max_capacity(config.timestamp.capacity)
+                .support_invalidation_closures()
+                .name("timestamp_cache")
+                .build(),
+            finalized_heights: moka::sync::Cache::builder()
+                .name("finalized_height_cache")
+                .build(),
+            stats: Arc::new(CacheStats::default()),
+        }
+    }
+
+    pub fn stats(&self) -> &CacheStats {
+        &self.stats
+    }
+
+    /// Record the latest finalized height of a chain. State at or below this height will be cached.
+    pub fn record_finalized_height(&self, chain_id: &ChainId, height: Height) {
+        match self.finalized_heights.get(chain_id) {
+            Some(prev) if height < prev => {
+                debug!(
+                    %chain_id,
+                    %prev,
+                    %height,
+                    "finalized height went backwards, invalidating cached state"
+                );
+
+                let state_chain_id = chain_id.clone();
+                self.state
+                    .invalidate_entries_if(move |k, _| k.chain_id == state_chain_id)
+                    .expect("invalidation closures are supported; qed;");
+
+                let timestamp_chain_id = chain_id.clone();
+                self.timestamps
+                    .invalidate_entries_if(move |(chain_id, _), _| *chain_id == timestamp_chain_id)
+                    .expect("invalidation closures are supported; qed;");
+            }
+            Some(prev) if height == prev => return,
+            _ => {}
+        }
+
+        self.finalized_heights.insert(chain_id.clone(), height);
+    }
+
+    fn is_finalized(&self, chain_id: &ChainId, height: Height) -> bool {
+        self.finalized_heights
+            .get(chain_id)
+            .is_some_and(|finalized_height| height <= finalized_height)
+    }
+
+    /// Fetch the IBC state at `path`, returning the cached value if it exists. The value returned by `fetch` will only be cached if `height` is finalized.
+    pub async fn state<F: Future<Output = RpcResult<Value>>>(
+        &self,
+        chain_id: &ChainId,
+        ibc_spec_id: &IbcSpecId,
+        height: Height,
+        path: &Value,
+        fetch: F,
+    ) -> RpcResult<Value> {
+        let key = StateCacheKey {
+            chain_id: chain_id.clone(),
+            ibc_spec_id: ibc_spec_id.clone(),
+            path: path.to_string(),
+            height,
+        };
+
+        if let Some(state) = self.state.get(&key).await {
+            self.stats.state_hits.fetch_add(1, Ordering::Relaxed);
+            trace!(%chain_id, %ibc_spec_id, %height, %path, "ibc state cache hit");
+            return Ok(state);
+        }
+
+        self.stats.state_misses.fetch_add(1, Ordering::Relaxed);
+        trace!(%chain_id, %ibc_spec_id, %height, %path, "ibc state cache miss");
+
+        let state = fetch.await?;
+
+        if self.is_finalized(chain_id, height) {
+            self.state.insert(key, state.clone()).await;
+        }
+
+        Ok(state)
+    }
+
+    /// Fetch the client info of `client_id`, returning the cached value if it exists.
+    pub async fn client_info<F: Future<Output = RpcResult<ClientInfo>>>(
+        &self,
+        chain_id: &ChainId,
+        ibc_spec_id: &IbcSpecId,
+        client_id: &RawClientId,
+        fetch: F,
+    ) -> RpcResult<ClientInfo> {
+        let key = ClientInfoCacheKey {
+            chain_id: chain_id.clone(),
+            ibc_spec_id: ibc_spec_id.clone(),
+            client_id: client_id.0.to_string(),
+        };
+
+        if let Some((client_info, _)) = self.client_info.get(&key).await {
+            self.stats.client_info_hits.fetch_add(1, Ordering::Relaxed);
+            trace!(%chain_id, %ibc_spec_id, client_id = %client_id.0, "client info cache hit");
+            return Ok(client_info);
+        }
+
+        self.stats
+            .client_info_misses
+            .fetch_add(1, Ordering::Relaxed);
+        trace!(%chain_id, %ibc_spec_id, client_id = %client_id.0, "client info cache miss");
+
+        let client_info = fetch.await?;
+
+        self.client_info
+            .insert(key, (client_info.clone(), voyager_vm::now()))
+            .await;
+
+        Ok(client_info)
+    }
+
+    /// Fetch the timestamp of the block at `height`, returning the cached value if it exists. The
+    /// value returned by `fetch` will only be cached if `height` is finalized.
+    pub async fn timestamp<F: Future<Output = RpcResult<i64>>>(
+        &self,
+        chain_id: &ChainId,
+        height: Height,
+        fetch: F,
+    ) -> RpcResult<i64> {
+        let key = (chain_id.clone(), height);
+
+        if let Some(timestamp) = self.timestamps.get(&key).await {
+            self.stats.timestamp_hits.fetch_add(1, Ordering::Relaxed);
+            trace!(%chain_id, %height, "timestamp cache hit");
+            return Ok(timestamp);
+        }
+
+        self.stats.timestamp_misses.fetch_add(1, Ordering::Relaxed);
+        trace!(%chain_id, %height, "timestamp cache miss");
+
+        let timestamp = fetch.await?;
+
+        if self.is_finalized(chain_id, height) {
+            self.timestamps.insert(key, timestamp).await;
+        }
+
+        Ok(timestamp)
+    }
+
+    /// Fetch the timestamps of the blocks at all of `heights`, in the same order as `heights`.
+    ///
+    /// Each distinct height is looked up in the cache once, and the heights that aren't cached are
+    /// fetched concurrently. If any fetch fails, the error is returned; any timestamps that were
+    /// already fetched are still cached.
+    pub async fn timestamps<F, Fut>(
+        &self,
+        chain_id: &ChainId,
+        heights: &[Height],
+        fetch: F,
+    ) -> RpcResult<Vec<i64>>
+    where
+        F: Fn(Height) -> Fut,
+        Fut: Future<Output = RpcResult<i64>>,
+    {
+        let fetch = &fetch;
+
+        let unique_heights = heights.iter().copied().collect::<HashSet<_>>();
+
+        let timestamps = stream::iter(unique_heights)
+            .map(|height| async move {
+                Ok::<_, ErrorObjectOwned>((
+                    height,
+                    self.timestamp(chain_id, height, fetch(height)).await?,
+                ))
+            })
+            .buffer_unordered(TIMESTAMP_FETCH_CONCURRENCY)
+            .try_collect::<HashMap<_, _>>()
+            .await?;
+
+        Ok(heights.iter().map(|height| timestamps[height]).collect())
+    }
+
+    /// Export the client info cache. IBC state is not exported, since it is only cached up to the
+    /// finalized height that this instance has observed.
+    pub fn export(&self) -> CacheSnapshot {
+        CacheSnapshot::new(VOYAGER_CACHE_SNAPSHOT_SOURCE).with_cache(
+            CLIENT_INFO_CACHE,
+            self.client_info
+                .iter()
+                .map(|(key, (client_info, updated_at))| CacheEntry {
+                    key: into_value(&*key),
+                    value: into_value(client_info),
+                    updated_at,
+                })
+                .collect(),
+        )
+    }
+
+    /// Merge a snapshot exported with [`Self::export`] into the client info cache.
+    pub async fn import(&self, snapshot: CacheSnapshot) -> Result<ImportReport, SnapshotError> {
+        snapshot.validate(VOYAGER_CACHE_SNAPSHOT_SOURCE)?;
+
+        let mut report = ImportReport::default();
+
+        for (cache, entries) in snapshot.caches {
+            if cache != CLIENT_INFO_CACHE {
+                report.ignored_caches.push(cache);
+                continue;
+            }
+
+            let entries = entries
+                .into_iter()
+                .map(|entry| {
+                    Ok((
+                        serde_json::from_value::<ClientInfoCacheKey>(entry.key)?,
+                        serde_json::from_value::<ClientInfo>(entry.value)?,
+                        entry.updated_at,
+                    ))
+                })
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|error| SnapshotError::InvalidEntry {
+                    cache: cache.clone(),
+                    error,
+                })?;
+
+            let mut merge_report = MergeReport::default();
+
+            for (key, client_info, updated_at) in entries {
+                let local = self.client_info.get(&key).await.map(|(_, t)| t);
+                let outcome = merge_entry(local, updated_at);
+
+                if outcome != MergeOutcome::KeepLocal {
+                    self.client_info
+                        .insert(key, (client_info, updated_at))
+                        .await;
+                }
+
+                merge_report.record(outcome);
+            }
+
+            report.caches.insert(cache, merge_report);
+        }
+
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+
+    use jsonrpsee::types::ErrorObject;
+    use serde_json::json;
+
+    use super::*;
+    use crate::core::{ClientType, IbcInterface};
+
+    fn chain_id() -> ChainId {
+        ChainId::new("union-devnet-1")
+    }
+
+    fn ibc_spec_id() -> IbcSpecId {
+        IbcSpecId::new("ibc-union")
+    }
+
+    async fn query_state(cache: &Cache, calls: &AtomicUsize, height: u64) -> Value {
+        cache
+            .state(
+                &chain_id(),
+                &ibc_spec_id(),
+                Height::new(height),
+                &json!({ "client_state": { "client_id": 1 } }),
+                async {
+                    calls.fetch_add(1, Ordering::SeqCst);
+                    Ok(json!(height))
+                },
+            )
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn finalized_state_is_cached() {
+        let cache = Cache::new(&CacheConfig::default());
+        let calls = AtomicUsize::new(0);
+
+        cache.record_finalized_height(&chain_id(), Height::new(10));
+
+        assert_eq!(query_state(&cache, &calls, 10).await, json!(10));
+        assert_eq!(query_state(&cache, &calls, 10).await, json!(10));
+
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(cache.stats().state_hits.load(Ordering::Relaxed), 1);
+        assert_eq!(cache.stats().state_misses.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn unfinalized_state_is_not_cached() {
+        let cache = Cache::new(&CacheConfig::default());
+        let calls = AtomicUsize::new(0);
+
+        // finalized height is not yet known
+        query_state(&cache, &calls, 10).await;
+        query_state(&cache, &calls, 10).await;
+
+        cache.record_finalized_height(&chain_id(), Height::new(10));
+
+        // above the finalized height
+        query_state(&cache, &calls, 11).await;
+        query_state(&cache, &calls, 11).await;
+
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+        assert_eq!(cache.stats().state_hits.load(Ordering::Relaxed), 0);
+    }
+
+    #[tokio::test]
+    async fn finalized_height_rewind_invalidates_state() {
+        let cache = Cache::new(&CacheConfig::default());
+        let calls = AtomicUsize::new(0);
+
+        cache.record_finalized_height(&chain_id(), Height::new(10));
+
+        query_state(&cache, &calls, 5).await;
+        query_state(&cache, &calls, 5).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        cache.record_finalized_height(&chain_id(), Height::new(3));
+
+        query_state(&cache, &calls, 5).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn errors_are_not_cached() {
+        let cache = Cache::new(&CacheConfig::default());
+
+        cache.record_finalized_height(&chain_id(), Height::new(10));
+
+        let res = cache
+            .state(
+                &chain_id(),
+                &ibc_spec_id(),
+                Height::new(1),
+                &json!(null),
+                async { Err(ErrorObject::owned(-1, "error", None::<()>)) },
+            )
+            .await;
+        assert!(res.is_err());
+
+        let res = cache
+            .state(
+                &chain_id(),
+                &ibc_spec_id(),
+                Height::new(1),
+                &json!(null),
+                async { Ok(json!(1)) },
+            )
+            .await;
+        assert_eq!(res.unwrap(), json!(1));
+    }
+
+    async fn query_timestamps(
+        cache: &Cache,
+        calls: &AtomicUsize,
+        heights: &[u64],
+    ) -> RpcResult<Vec<i64>> {
+        cache
+            .timestamps(
+                &chain_id(),
+                &heights.iter().copied().map(Height::new).collect::<Vec<_>>(),
+                |height| async move {
+                    calls.fetch_add(1, Ordering::SeqCst);
+
+                    // heights above 100 don't exist yet
+                    if height.height() > 100 {
+                        Err(ErrorObject::owned(
+                            -1,
+                            format!("height {height} is not yet available"),
+                            None::<()>,
+                        ))
+                    } else {
+                        Ok(height.height() as i64 * 1_000)
+                    }
+                },
+            )
+            .await
+    }
+
+    #[tokio::test]
+    async fn finalized_timestamp_is_cached() {
+        let cache = Cache::new(&CacheConfig::default());
+        let calls = AtomicUsize::new(0);
+
+        cache.record_finalized_height(&chain_id(), Height::new(10));
+
+        assert_eq!(
+            query_timestamps(&cache, &calls, &[10]).await.unwrap(),
+            [10_000]
+        );
+        assert_eq!(
+            query_timestamps(&cache, &calls, &[10]).await.unwrap(),
+            [10_000]
+        );
+
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(cache.stats().timestamp_hits.load(Ordering::Relaxed), 1);
+        assert_eq!(cache.stats().timestamp_misses.load(Ordering::Relaxed), 1);
+
+        // above the finalized height
+        query_timestamps(&cache, &calls, &[11]).await.unwrap();
+        query_timestamps(&cache, &calls, &[11]).await.unwrap();
+
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn bulk_timestamps_are_ordered() {
+        let cache = Cache::new(&CacheConfig::default());
+        let calls = AtomicUsize::new(0);
+
+        cache.record_finalized_height(&chain_id(), Height::new(100));
+
+        // cache some of the heights
+        query_timestamps(&cache, &calls, &[3, 1]).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        assert_eq!(
+            query_timestamps(&cache, &calls, &[5, 1, 4, 3, 5, 2])
+                .await
+                .unwrap(),
+            [5_000, 1_000, 4_000, 3_000, 5_000, 2_000]
+        );
+
+        // only the uncached heights are fetched, and duplicate heights are only fetched once
+        assert_eq!(calls.load(Ordering::SeqCst), 5);
+        assert_eq!(cache.stats().timestamp_hits.load(Ordering::Relaxed), 2);
+    }
+
+    #[tokio::test]
+    async fn missing_height_is_an_error() {
+        let cache = Cache::new(&CacheConfig::default());
+        let calls = AtomicUsize::new(0);
+
+        cache.record_finalized_height(&chain_id(), Height::new(100));
+
+        let err = query_timestamps(&cache, &calls, &[1, 101, 2])
+            .await
+            .unwrap_err();
+        assert_eq!(err.message(), "height 101 is not yet available");
+
+        // the error is not cached
+        calls.store(0, Ordering::SeqCst);
+        query_timestamps(&cache, &calls, &[101]).await.unwrap_err();
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        assert_eq!(
+            query_timestamps(&cache, &calls, &[1, 2]).await.unwrap(),
+            [1_000, 2_000]
+        );
+    }
+
+    #[tokio::test]
+    async fn finalized_height_rewind_invalidates_timestamps() {
+        let cache = Cache::new(&CacheConfig::default());
+        let calls = AtomicUsize::new(0);
+
+        cache.record_finalized_height(&chain_id(), Height::new(10));
+
+        query_timestamps(&cache, &calls, &[5]).await.unwrap();
+        query_timestamps(&cache, &calls, &[5]).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        cache.record_finalized_height(&chain_id(), Height::new(3));
+
+        query_timestamps(&cache, &calls, &[5]).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn client_info_is_cached() {
+        let cache = Cache::new(&CacheConfig::default());
+        let calls = AtomicUsize::new(0);
+
+        let client_info = ClientInfo {
+            client_type: ClientType::new(ClientType::COMETBLS_GROTH16),
+            ibc_interface: IbcInterface::new(IbcInterface::IBC_COSMWASM),
+            metadata: Default::default(),
+        };
+
+        for _ in 0..3 {
+            let res = cache
+                .client_info(&chain_id(), &ibc_spec_id(), &RawClientId::new(1), async {
+                    calls.fetch_add(1, Ordering::SeqCst);
+                    Ok(client_info.clone())
+                })
+                .await
+                .unwrap();
+
+            assert_eq!(res, client_info);
+        }
+
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(cache.stats().client_info_hits.load(Ordering::Relaxed), 2);
+        assert_eq!(cache.stats().client_info_misses.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn client_info_snapshot_round_trip() {
+        let exported = Cache::new(&CacheConfig::default());
+
+        let client_info = ClientInfo {
+            client_type: ClientType::new(ClientType::COMETBLS_GROTH16),
+            ibc_interface: IbcInterface::new(IbcInterface::IBC_COSMWASM),
+            metadata: Default::default(),
+        };
+
+        exported
+            .client_info(&chain_id(), &ibc_spec_id(), &RawClientId::new(1), async {
+                Ok(client_info.clone())
+            })
+            .await
+            .unwrap();
+
+        let snapshot = exported.export();
+
+        let imported = Cache::new(&CacheConfig::default());
+        let report = imported.import(snapshot.clone()).await.unwrap();
+
+        assert_eq!(report.caches[CLIENT_INFO_CACHE].inserted, 1);
+
+        // the imported entry is a cache hit
+        let res = imported
+            .client_info(&chain_id(), &ibc_spec_id(), &RawClientId::new(1), async {
+                panic!("client info is cached")
+            })
+            .await
+            .unwrap();
+        assert_eq!(res, client_info);
+
+        assert_eq!(imported.export(), snapshot);
+
+        // importing the same snapshot again keeps the local entries
+        let report = imported.import(snapshot).await.unwrap();
+        assert_eq!(report.caches[CLIENT_INFO_CACHE].kept_local, 1);
+    }
+}
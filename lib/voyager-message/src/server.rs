@@ -0,0 +1,700 @@
+//! Runtime for plugins and modules: the `run` entrypoints, the server they
+//! expose to voyager, and the [`VoyagerClient`] they use to call back into it.
+
+use std::{env::VarError, future::Future, time::Duration};
+
+use chain_utils::BoxDynError;
+use jsonrpsee::{
+    core::RpcResult, server::middleware::rpc::RpcServiceT, types::ErrorObject, Extensions,
+    RpcModule,
+};
+use reth_ipc::{client::IpcClientBuilder, server::RpcServiceBuilder};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use tracing::{debug, debug_span, error, info, instrument, trace, Instrument};
+use unionlabs::{bytes::Bytes, ibc::core::client::height::Height, traits::Member, ErrorReporter};
+use voyager_core::{
+    ChainId, ClientInfo, ClientStateMeta, ClientType, IbcInterface, IbcSpec, IbcSpecId,
+    IbcStorePathKey, QueryHeight,
+};
+use voyager_vm::QueueStats;
+
+use crate::{
+    cmd::{CmdOutput, OutputFormat},
+    context::{INVALID_CONFIG_EXIT_CODE, STARTUP_ERROR_EXIT_CODE},
+    module::{
+        ClientModuleInfo, ClientModuleServer, ConsensusModuleInfo, ConsensusModuleServer,
+        PluginInfo, PluginServer, ProofModuleInfo, ProofModuleServer, StateModuleInfo,
+        StateModuleServer,
+    },
+    query::VoyagerQuery,
+    rpc::{json_rpc_error_to_error_object, IbcProof, IbcState, SelfClientState, VoyagerRpcClient},
+    RawClientId,
+};
+
+fn init_log() {
+    enum LogFormat {
+        Text,
+        Json,
+    }
+
+    let format = match std::env::var("RUST_LOG_FORMAT").as_deref() {
+        Err(VarError::NotPresent) | Ok("text") => LogFormat::Text,
+        Ok("json") => LogFormat::Json,
+        Err(VarError::NotUnicode(invalid)) => {
+            eprintln!("invalid non-utf8 log format {invalid:?}, defaulting to text");
+            LogFormat::Text
+        }
+        Ok(invalid) => {
+            eprintln!("invalid log format {invalid}, defaulting to text");
+            LogFormat::Text
+        }
+    };
+
+    match format {
+        LogFormat::Text => {
+            tracing_subscriber::fmt()
+                .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+                // .with_span_events(FmtSpan::CLOSE)
+                .init();
+        }
+        LogFormat::Json => {
+            tracing_subscriber::fmt()
+                .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+                // .with_span_events(FmtSpan::CLOSE)
+                .json()
+                .init();
+        }
+    }
+}
+
+#[allow(async_fn_in_trait)]
+pub trait Plugin: PluginServer<Self::Call, Self::Callback> + Sized {
+    type Call: Member;
+    type Callback: Member;
+
+    type Config: DeserializeOwned + Clone;
+    type Cmd: clap::Subcommand;
+
+    async fn new(config: Self::Config) -> Result<Self, BoxDynError>;
+
+    fn info(config: Self::Config) -> PluginInfo;
+
+    /// Run `cmd`. The output is printed by [`Self::run`], in the format selected with `--output`.
+    async fn cmd(config: Self::Config, cmd: Self::Cmd) -> Result<Box<dyn CmdOutput>, BoxDynError>;
+
+    async fn run() {
+        init_log();
+
+        let app = <PluginApp<Self::Cmd> as clap::Parser>::parse();
+
+        match app {
+            PluginApp::Run {
+                socket,
+                voyager_socket,
+                config,
+            } => {
+                let config = must_parse::<Self::Config>(&config);
+
+                let info = Self::info(config.clone());
+
+                let name = info.name;
+
+                run_server(
+                    name.clone(),
+                    voyager_socket,
+                    config,
+                    socket,
+                    Self::new,
+                    Self::into_rpc,
+                )
+                .instrument(debug_span!("run_plugin_server", %name))
+                .await
+            }
+            PluginApp::Info { config } => {
+                let info = Self::info(must_parse(&config));
+
+                print!("{}", serde_json::to_string(&info).unwrap())
+            }
+            PluginApp::Cmd {
+                cmd,
+                config,
+                output,
+            } => match Self::cmd(must_parse(&config), cmd).await {
+                Ok(res) => output.print(&*res),
+                Err(err) => output.exit_with_error(&*err),
+            },
+        }
+    }
+}
+
+#[allow(async_fn_in_trait)]
+pub trait StateModule<V: IbcSpec>: StateModuleServer<V> + Sized {
+    type Config: DeserializeOwned + Clone;
+
+    async fn new(config: Self::Config, info: StateModuleInfo) -> Result<Self, BoxDynError>;
+
+    async fn run() {
+        init_log();
+
+        match <ModuleApp as clap::Parser>::parse() {
+            ModuleApp::Run {
+                socket,
+                voyager_socket,
+                config,
+                info,
+            } => {
+                let config = must_parse::<Self::Config>(&config);
+
+                let info = must_parse::<StateModuleInfo>(&info);
+
+                let name = info.id();
+
+                run_server(
+                    name.clone(),
+                    voyager_socket,
+                    (config, info),
+                    socket,
+                    |(config, info)| Self::new(config, info),
+                    Self::into_rpc,
+                )
+                .instrument(debug_span!("run_state_module_server", %name))
+                .await
+            }
+        }
+    }
+}
+
+#[allow(async_fn_in_trait)]
+pub trait ProofModule<V: IbcSpec>: ProofModuleServer<V> + Sized {
+    type Config: DeserializeOwned + Clone;
+
+    async fn new(config: Self::Config, info: ProofModuleInfo) -> Result<Self, BoxDynError>;
+
+    async fn run() {
+        init_log();
+
+        match <ModuleApp as clap::Parser>::parse() {
+            ModuleApp::Run {
+                socket,
+                voyager_socket,
+                config,
+                info,
+            } => {
+                let config = must_parse::<Self::Config>(&config);
+
+                let info = must_parse::<ProofModuleInfo>(&info);
+
+                let name = info.id();
+
+                run_server(
+                    name.clone(),
+                    voyager_socket,
+                    (config, info),
+                    socket,
+                    |(config, info)| Self::new(config, info),
+                    Self::into_rpc,
+                )
+                .instrument(debug_span!("run_proof_module_server", %name))
+                .await
+            }
+        }
+    }
+}
+
+#[allow(async_fn_in_trait)]
+pub trait ConsensusModule: ConsensusModuleServer + Sized {
+    type Config: DeserializeOwned + Clone;
+
+    async fn new(config: Self::Config, info: ConsensusModuleInfo) -> Result<Self, BoxDynError>;
+
+    async fn run() {
+        init_log();
+
+        match <ModuleApp as clap::Parser>::parse() {
+            ModuleApp::Run {
+                socket,
+                voyager_socket,
+                config,
+                info,
+            } => {
+                let config = must_parse::<Self::Config>(&config);
+
+                let info = must_parse::<ConsensusModuleInfo>(&info);
+
+                let name = info.id();
+
+                run_server(
+                    name.clone(),
+                    voyager_socket,
+                    (config, info),
+                    socket,
+                    |(config, info)| Self::new(config, info),
+                    Self::into_rpc,
+                )
+                .instrument(debug_span!("run_consensus_module_server", %name))
+                .await
+            }
+        }
+    }
+}
+
+#[allow(async_fn_in_trait)]
+pub trait ClientModule: ClientModuleServer + Sized {
+    type Config: DeserializeOwned + Clone;
+
+    async fn new(config: Self::Config, info: ClientModuleInfo) -> Result<Self, BoxDynError>;
+
+    async fn run() {
+        init_log();
+
+        match <ModuleApp as clap::Parser>::parse() {
+            ModuleApp::Run {
+                socket,
+                voyager_socket,
+                config,
+                info,
+            } => {
+                let config = must_parse::<Self::Config>(&config);
+
+                let info = must_parse::<ClientModuleInfo>(&info);
+
+                let name = info.id();
+
+                run_server(
+                    name.clone(),
+                    voyager_socket,
+                    (config, info),
+                    socket,
+                    |(config, info)| Self::new(config, info),
+                    Self::into_rpc,
+                )
+                .instrument(debug_span!("run_client_module_server", %name))
+                .await
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct VoyagerClient(pub(crate) reconnecting_jsonrpc_ws_client::Client);
+
+impl VoyagerClient {
+    pub fn new(name: String, socket: String) -> Self {
+        let client = reconnecting_jsonrpc_ws_client::Client::new({
+            let voyager_socket: &'static str = socket.leak();
+            let name = name.clone();
+            move || {
+                async move {
+                    trace!("connecting to socket at {voyager_socket}");
+                    IpcClientBuilder::default().build(voyager_socket).await
+                }
+                .instrument(debug_span!("voyager_ipc_client", %name))
+            }
+        });
+        Self(client)
+    }
+
+    pub async fn query_latest_height(
+        &self,
+        chain_id: ChainId,
+        finalized: bool,
+    ) -> RpcResult<Height> {
+        let latest_height = self
+            .0
+            .query_latest_height(chain_id, finalized)
+            .await
+            .map_err(json_rpc_error_to_error_object)?;
+
+        Ok(latest_height)
+    }
+
+    pub async fn query_latest_timestamp(
+        &self,
+        chain_id: ChainId,
+        finalized: bool,
+    ) -> RpcResult<i64> {
+        let latest_timestamp = self
+            .0
+            .query_latest_timestamp(chain_id, finalized)
+            .await
+            .map_err(json_rpc_error_to_error_object)?;
+
+        Ok(latest_timestamp)
+    }
+
+    pub async fn timestamp_at_height(&self, chain_id: ChainId, height: Height) -> RpcResult<i64> {
+        let timestamp = self
+            .0
+            .timestamp_at_height(chain_id, height)
+            .await
+            .map_err(json_rpc_error_to_error_object)?;
+
+        Ok(timestamp)
+    }
+
+    pub async fn timestamps_at_heights(
+        &self,
+        chain_id: ChainId,
+        heights: Vec<Height>,
+    ) -> RpcResult<Vec<i64>> {
+        let timestamps = self
+            .0
+            .timestamps_at_heights(chain_id, heights)
+            .await
+            .map_err(json_rpc_error_to_error_object)?;
+
+        Ok(timestamps)
+    }
+
+    pub async fn consensus_to_execution_height(
+        &self,
+        chain_id: ChainId,
+        height: Height,
+    ) -> RpcResult<u64> {
+        let execution_height = self
+            .0
+            .consensus_to_execution_height(chain_id, height)
+            .await
+            .map_err(json_rpc_error_to_error_object)?;
+
+        Ok(execution_height)
+    }
+
+    #[instrument(
+        skip_all,
+        name = "voyager_client_encode_proof",
+        fields(
+            %client_type,
+            %ibc_interface,
+            %proof
+        )
+    )]
+    pub async fn encode_proof<V: IbcSpec>(
+        &self,
+        client_type: ClientType,
+        ibc_interface: IbcInterface,
+        proof: Value,
+    ) -> RpcResult<Bytes> {
+        self.encode_proof_raw(&client_type, &ibc_interface, &V::ID, proof)
+            .await
+    }
+
+    pub async fn query_ibc_state<P: IbcStorePathKey>(
+        &self,
+        chain_id: ChainId,
+        height: QueryHeight,
+        path: P,
+    ) -> RpcResult<IbcState<P::Value>> {
+        VoyagerQuery::query_ibc_state(self, &chain_id, height, path).await
+    }
+
+    pub async fn query_ibc_proof<P: IbcStorePathKey>(
+        &self,
+        chain_id: ChainId,
+        height: QueryHeight,
+        path: P,
+    ) -> RpcResult<IbcProof> {
+        VoyagerQuery::query_ibc_proof(self, &chain_id, height, path).await
+    }
+
+    pub async fn client_info<V: IbcSpec>(
+        &self,
+        chain_id: ChainId,
+        client_id: V::ClientId,
+    ) -> RpcResult<ClientInfo> {
+        VoyagerQuery::client_info::<V>(self, &chain_id, client_id).await
+    }
+
+    pub async fn client_meta<V: IbcSpec>(
+        &self,
+        chain_id: ChainId,
+        at: QueryHeight,
+        client_id: V::ClientId,
+    ) -> RpcResult<ClientStateMeta> {
+        VoyagerQuery::client_meta::<V>(self, &chain_id, at, client_id).await
+    }
+
+    pub async fn queue_stats(&self) -> RpcResult<QueueStats> {
+        self.0
+            .queue_stats()
+            .await
+            .map_err(json_rpc_error_to_error_object)
+    }
+}
+
+impl VoyagerQuery for VoyagerClient {
+    async fn query_latest_height(&self, chain_id: &ChainId, finalized: bool) -> RpcResult<Height> {
+        self.query_latest_height(chain_id.clone(), finalized).await
+    }
+
+    async fn consensus_to_execution_height(
+        &self,
+        chain_id: &ChainId,
+        height: Height,
+    ) -> RpcResult<u64> {
+        self.consensus_to_execution_height(chain_id.clone(), height)
+            .await
+    }
+
+    async fn client_info_raw(
+        &self,
+        chain_id: &ChainId,
+        ibc_spec_id: &IbcSpecId,
+        client_id: RawClientId,
+    ) -> RpcResult<ClientInfo> {
+        self.0
+            .client_info(chain_id.clone(), ibc_spec_id.clone(), client_id)
+            .await
+            .map_err(json_rpc_error_to_error_object)
+    }
+
+    async fn client_meta_raw(
+        &self,
+        chain_id: &ChainId,
+        ibc_spec_id: &IbcSpecId,
+        at: QueryHeight,
+        client_id: RawClientId,
+    ) -> RpcResult<ClientStateMeta> {
+        self.0
+            .client_meta(chain_id.clone(), ibc_spec_id.clone(), at, client_id)
+            .await
+            .map_err(json_rpc_error_to_error_object)
+    }
+
+    async fn query_ibc_state_raw(
+        &self,
+        chain_id: &ChainId,
+        ibc_spec_id: &IbcSpecId,
+        height: QueryHeight,
+        path: Value,
+    ) -> RpcResult<IbcState<Value>> {
+        self.0
+            .query_ibc_state(chain_id.clone(), ibc_spec_id.clone(), height, path)
+            .await
+            .map_err(json_rpc_error_to_error_object)
+    }
+
+    async fn query_ibc_proof_raw(
+        &self,
+        chain_id: &ChainId,
+        ibc_spec_id: &IbcSpecId,
+        height: QueryHeight,
+        path: Value,
+    ) -> RpcResult<IbcProof> {
+        self.0
+            .query_ibc_proof(chain_id.clone(), ibc_spec_id.clone(), height, path)
+            .await
+            .map_err(json_rpc_error_to_error_object)
+    }
+
+    async fn self_client_state(
+        &self,
+        chain_id: &ChainId,
+        height: QueryHeight,
+    ) -> RpcResult<SelfClientState> {
+        self.0
+            .self_client_state(chain_id.clone(), height)
+            .await
+            .map_err(json_rpc_error_to_error_object)
+    }
+
+    async fn encode_proof_raw(
+        &self,
+        client_type: &ClientType,
+        ibc_interface: &IbcInterface,
+        ibc_spec_id: &IbcSpecId,
+        proof: Value,
+    ) -> RpcResult<Bytes> {
+        self.0
+            .encode_proof(
+                client_type.clone(),
+                ibc_interface.clone(),
+                ibc_spec_id.clone(),
+                proof,
+            )
+            .await
+            .map_err(json_rpc_error_to_error_object)
+    }
+
+    async fn decode_client_state(
+        &self,
+        client_type: &ClientType,
+        ibc_interface: &IbcInterface,
+        ibc_spec_id: &IbcSpecId,
+        client_state: Bytes,
+    ) -> RpcResult<Value> {
+        self.0
+            .decode_client_state(
+                client_type.clone(),
+                ibc_interface.clone(),
+                ibc_spec_id.clone(),
+                client_state,
+            )
+            .await
+            .map_err(json_rpc_error_to_error_object)
+    }
+
+    async fn decode_consensus_state(
+        &self,
+        client_type: &ClientType,
+        ibc_interface: &IbcInterface,
+        ibc_spec_id: &IbcSpecId,
+        consensus_state: Bytes,
+    ) -> RpcResult<Value> {
+        self.0
+            .decode_consensus_state(
+                client_type.clone(),
+                ibc_interface.clone(),
+                ibc_spec_id.clone(),
+                consensus_state,
+            )
+            .await
+            .map_err(json_rpc_error_to_error_object)
+    }
+}
+
+pub trait ExtensionsExt {
+    /// Retrieve a value from this [`Extensions`], returning an [`RpcResult`] for more
+    /// convenient handling in rpc server implementations.
+    fn try_get<T: Send + Sync + 'static>(&self) -> RpcResult<&T>;
+}
+
+impl ExtensionsExt for Extensions {
+    fn try_get<T: Send + Sync + 'static>(&self) -> RpcResult<&T> {
+        match self.get() {
+            Some(t) => Ok(t),
+            None => Err(ErrorObject::owned(
+                -1,
+                format!(
+                    "failed to retrieve value of type {} from extensions",
+                    std::any::type_name::<T>(),
+                ),
+                None::<()>,
+            )),
+        }
+    }
+}
+
+#[derive(clap::Parser)]
+enum PluginApp<Cmd: clap::Subcommand> {
+    Run {
+        socket: String,
+        voyager_socket: String,
+        config: String,
+    },
+    Info {
+        config: String,
+    },
+    Cmd {
+        #[command(subcommand)]
+        cmd: Cmd,
+        #[arg(long)]
+        config: String,
+        /// The format to print the output of the command in.
+        #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Text)]
+        output: OutputFormat,
+    },
+}
+
+#[derive(clap::Parser)]
+enum ModuleApp {
+    Run {
+        socket: String,
+        voyager_socket: String,
+        config: String,
+        info: String,
+    },
+}
+
+#[instrument(level = "debug", fields(%config_str))]
+fn must_parse<T: DeserializeOwned>(config_str: &str) -> T {
+    // track the path to the field that failed to deserialize, so that errors name the offending
+    // field (i.e. `invalid config at ws_url: ...`)
+    match serde_path_to_error::deserialize::<_, T>(&mut serde_json::Deserializer::from_str(
+        config_str,
+    )) {
+        Ok(ok) => ok,
+        Err(err) => {
+            error!(
+                "invalid config at {}: {}",
+                err.path(),
+                ErrorReporter(err.inner())
+            );
+            std::process::exit(INVALID_CONFIG_EXIT_CODE as i32);
+        }
+    }
+}
+
+async fn run_server<
+    T,
+    NewF: FnOnce(NewT) -> Fut,
+    NewT,
+    Fut: Future<Output = Result<T, BoxDynError>>,
+    IntoRpcF: FnOnce(T) -> RpcModule<T>,
+>(
+    id: String,
+    voyager_socket: String,
+    new_t: NewT,
+    socket: String,
+    new: NewF,
+    into_rpc: IntoRpcF,
+) {
+    let voyager_client = VoyagerClient::new(id.clone(), voyager_socket);
+    if let Err(err) = voyager_client
+        .0
+        .wait_until_connected(Duration::from_millis(500))
+        .await
+    {
+        error!("unable to connect to voyager socket: {err}");
+        std::process::exit(STARTUP_ERROR_EXIT_CODE as i32);
+    };
+
+    debug!("connected to voyager socket");
+
+    let module_server = match new(new_t).await {
+        Ok(ctx) => ctx,
+        Err(err) => {
+            error!("startup error: {err:?}");
+            std::process::exit(STARTUP_ERROR_EXIT_CODE as i32);
+        }
+    };
+
+    let ipc_server = reth_ipc::server::Builder::default()
+        .set_rpc_middleware(
+            RpcServiceBuilder::new().layer_fn(move |service| InjectClient {
+                client: voyager_client.clone(),
+                service,
+            }),
+        )
+        .build(socket);
+
+    let rpcs = into_rpc(module_server);
+
+    trace!(methods = ?*rpcs, "registered methods");
+    let addr = ipc_server.endpoint();
+    let server_handle = ipc_server.start(rpcs).await.unwrap();
+    info!("listening on {addr}");
+
+    tokio::spawn(
+        server_handle
+            .stopped()
+            .instrument(debug_span!("module_server", %id)),
+    )
+    .await
+    .unwrap()
+}
+
+struct InjectClient<S> {
+    client: VoyagerClient,
+    service: S,
+}
+
+impl<'a, S: RpcServiceT<'a> + Send + Sync> RpcServiceT<'a> for InjectClient<S> {
+    type Future = S::Future;
+
+    fn call(&self, mut request: jsonrpsee::types::Request<'a>) -> Self::Future {
+        request.extensions.insert(self.client.clone());
+        self.service.call(request)
+    }
+}
@@ -0,0 +1,294 @@
+//! Standing suppression of datagrams for abandoned channels.
+//!
+//! Purging the queue (see [`purge_ops`]) only removes the ops that are
+//! currently queued; events on the counterparty chain will keep producing new
+//! datagrams for an abandoned channel. Transaction plugins consult their
+//! [`SuppressionList`] before submitting, and drop datagrams for suppressed
+//! channels, emitting a [`SuppressedDatagram`] for each one instead.
+//!
+//! Channels can also be suppressed because a client that they depend on is
+//! frozen, through the [`FreezeList`] file in
+//! [`freeze_list`](SuppressionList::freeze_list) (see [`crate::freeze`]).
+//!
+//! [`purge_ops`]: crate::purge::purge_ops
+
+use std::path::PathBuf;
+
+use ibc_classic_spec::IbcClassic;
+use ibc_union_spec::IbcUnion;
+use macros::model;
+use tracing::{error, warn};
+use unionlabs::ErrorReporter;
+use voyager_core::{ChainId, IbcSpecId};
+
+use crate::{data::IbcDatagram, freeze::FreezeList};
+
+/// The channels on a chain for which datagrams are dropped instead of
+/// submitted.
+#[model]
+#[derive(Default)]
+pub struct SuppressionList {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub channels: Vec<SuppressedChannel>,
+    /// The [`FreezeList`] maintained by voyager, if relaying on this chain
+    /// should be paused when a client is frozen.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub freeze_list: Option<PathBuf>,
+}
+
+/// A channel on the chain that the [`SuppressionList`] is configured for.
+#[model]
+pub struct SuppressedChannel {
+    pub ibc_spec_id: IbcSpecId,
+    pub channel_id: u32,
+    /// Why the channel is suppressed, for operators.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// A datagram that was dropped instead of submitted, since it is for a
+/// suppressed channel.
+#[model]
+pub struct SuppressedDatagram {
+    pub chain_id: ChainId,
+    pub channel_id: u32,
+    pub datagram: IbcDatagram,
+}
+
+impl SuppressionList {
+    /// This list, with the channels on `chain_id` that are currently frozen in
+    /// the [`freeze_list`](Self::freeze_list) added to it.
+    ///
+    /// The freeze list is read on every call, since it is updated by voyager
+    /// while the plugin is running. If it can't be read, only the configured
+    /// channels are suppressed.
+    pub fn with_frozen_channels(&self, chain_id: &ChainId) -> SuppressionList {
+        let mut list = self.clone();
+
+        let Some(path) = &self.freeze_list else {
+            return list;
+        };
+
+        match FreezeList::load(path) {
+            Ok(freeze_list) => list
+                .channels
+                .extend(freeze_list.suppressed_channels(chain_id)),
+            Err(err) => error!(
+                %chain_id,
+                err = %ErrorReporter(err),
+                "unable to read freeze list, only suppressing configured channels"
+            ),
+        }
+
+        list
+    }
+
+    /// The suppressed channel that `datagram` is for, if any.
+    ///
+    /// A datagram is for a channel if it is handled by that channel on the chain
+    /// it is submitted to (i.e. the destination channel of a received packet, or
+    /// the source channel of an acknowledged or timed out packet). A batched
+    /// datagram is suppressed if any of the packets in it are for a suppressed
+    /// channel, since the packets are proven together.
+    pub fn suppressed_channel(&self, datagram: &IbcDatagram) -> Option<&SuppressedChannel> {
+        if self.channels.is_empty() {
+            return None;
+        }
+
+        let channel_ids = local_channel_ids(datagram);
+
+        self.channels.iter().find(|channel| {
+            channel.ibc_spec_id == datagram.ibc_spec_id && channel_ids.contains(&channel.channel_id)
+        })
+    }
+
+    /// Split `datagrams` to be submitted on `chain_id` into the ones to submit,
+    /// and the ones for suppressed channels.
+    pub fn partition(
+        &self,
+        chain_id: &ChainId,
+        datagrams: Vec<IbcDatagram>,
+    ) -> (Vec<IbcDatagram>, Vec<SuppressedDatagram>) {
+        let mut submit = vec![];
+        let mut suppressed = vec![];
+
+        for datagram in datagrams {
+            match self.suppressed_channel(&datagram) {
+                Some(channel) => {
+                    warn!(
+                        %chain_id,
+                        ibc_spec_id = %channel.ibc_spec_id,
+                        channel_id = channel.channel_id,
+                        reason = channel.reason.as_deref().unwrap_or_default(),
+                        "datagram is for a suppressed channel, dropping it"
+                    );
+
+                    suppressed.push(SuppressedDatagram {
+                        chain_id: chain_id.clone(),
+                        channel_id: channel.channel_id,
+                        datagram,
+                    });
+                }
+                None => submit.push(datagram),
+            }
+        }
+
+        (submit, suppressed)
+    }
+}
+
+/// The channels on the chain that `datagram` is submitted to that it is
+/// handled by. Datagrams that can't be decoded are not for any channel.
+fn local_channel_ids(datagram: &IbcDatagram) -> Vec<u32> {
+    if let Some(Ok(datagram)) = datagram.decode_datagram::<IbcUnion>() {
+        use ibc_union_spec::Datagram;
+
+        return match datagram {
+            Datagram::ChannelOpenAck(msg) => vec![msg.channel_id.get()],
+            Datagram::ChannelOpenConfirm(msg) => vec![msg.channel_id.get()],
+            Datagram::PacketRecv(msg) => msg
+                .packets
+                .iter()
+                .map(|packet| packet.destination_channel)
+                .collect(),
+            Datagram::PacketAcknowledgement(msg) => msg
+                .packets
+                .iter()
+                .map(|packet| packet.source_channel)
+                .collect(),
+            Datagram::PacketTimeout(msg) => vec![msg.packet.source_channel],
+            _ => vec![],
+        };
+    }
+
+    if let Some(Ok(datagram)) = datagram.decode_datagram::<IbcClassic>() {
+        use ibc_classic_spec::Datagram;
+
+        return match datagram {
+            Datagram::ChannelOpenAck(msg) => vec![msg.channel_id.id()],
+            Datagram::ChannelOpenConfirm(msg) => vec![msg.channel_id.id()],
+            Datagram::RecvPacket(msg) => vec![msg.packet.destination_channel.id()],
+            Datagram::AcknowledgePacket(msg) => vec![msg.packet.source_channel.id()],
+            Datagram::TimeoutPacket(msg) => vec![msg.packet.source_channel.id()],
+            _ => vec![],
+        };
+    }
+
+    vec![]
+}
+
+#[cfg(test)]
+mod tests {
+    use ibc_solidity::Packet;
+    use ibc_union_spec::{
+        Datagram, MsgChannelOpenConfirm, MsgPacketRecv, MsgPacketTimeout, UnionId,
+    };
+
+    use super::*;
+
+    fn packet(source_channel: u32, destination_channel: u32) -> Packet {
+        Packet {
+            source_channel,
+            destination_channel,
+            data: Default::default(),
+            timeout_height: 0,
+            timeout_timestamp: 100,
+        }
+    }
+
+    fn recv(packets: Vec<Packet>) -> IbcDatagram {
+        IbcDatagram::new::<IbcUnion>(Datagram::from(MsgPacketRecv {
+            relayer_msgs: vec![Default::default(); packets.len()],
+            packets,
+            proof: Default::default(),
+            proof_height: 1,
+        }))
+    }
+
+    fn list(ibc_spec_id: &'static str, channel_id: u32) -> SuppressionList {
+        SuppressionList {
+            channels: vec![SuppressedChannel {
+                ibc_spec_id: IbcSpecId::new(ibc_spec_id),
+                channel_id,
+                reason: Some("counterparty halted".to_owned()),
+            }],
+            freeze_list: None,
+        }
+    }
+
+    #[test]
+    fn matches_local_channel_only() {
+        let list = list(IbcSpecId::UNION, 2);
+
+        // received on channel 2
+        assert!(list.suppressed_channel(&recv(vec![packet(1, 2)])).is_some());
+        // received on channel 1, sent from the counterparty's channel 2
+        assert!(list.suppressed_channel(&recv(vec![packet(2, 1)])).is_none());
+
+        // timed out on channel 2
+        assert!(list
+            .suppressed_channel(&IbcDatagram::new::<IbcUnion>(Datagram::from(
+                MsgPacketTimeout {
+                    packet: packet(2, 1),
+                    proof: Default::default(),
+                    proof_height: 1,
+                }
+            )))
+            .is_some());
+
+        assert!(list
+            .suppressed_channel(&IbcDatagram::new::<IbcUnion>(Datagram::from(
+                MsgChannelOpenConfirm {
+                    channel_id: UnionId::new(2).unwrap(),
+                    proof_ack: Default::default(),
+                    proof_height: 1,
+                }
+            )))
+            .is_some());
+    }
+
+    #[test]
+    fn batch_with_any_suppressed_packet_is_suppressed() {
+        let list = list(IbcSpecId::UNION, 2);
+
+        assert!(list
+            .suppressed_channel(&recv(vec![packet(1, 3), packet(1, 2)]))
+            .is_some());
+    }
+
+    #[test]
+    fn ibc_spec_must_match() {
+        assert!(list(IbcSpecId::CLASSIC, 2)
+            .suppressed_channel(&recv(vec![packet(1, 2)]))
+            .is_none());
+    }
+
+    #[test]
+    fn partition() {
+        let chain_id = ChainId::new("union-1");
+
+        let (submit, suppressed) = list(IbcSpecId::UNION, 2).partition(
+            &chain_id,
+            vec![recv(vec![packet(1, 2)]), recv(vec![packet(1, 3)])],
+        );
+
+        assert_eq!(submit, [recv(vec![packet(1, 3)])]);
+        assert_eq!(
+            suppressed,
+            [SuppressedDatagram {
+                chain_id,
+                channel_id: 2,
+                datagram: recv(vec![packet(1, 2)]),
+            }]
+        );
+    }
+
+    #[test]
+    fn empty_list_suppresses_nothing() {
+        let (submit, suppressed) = SuppressionList::default()
+            .partition(&ChainId::new("union-1"), vec![recv(vec![packet(1, 2)])]);
+
+        assert_eq!(submit.len(), 1);
+        assert!(suppressed.is_empty());
+    }
+}
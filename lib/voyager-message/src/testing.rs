@@ -0,0 +1,340 @@
+//! Test utilities for plugins and modules: golden tests for plugin passes, and
+//! [`MockVoyager`] to test code written against [`VoyagerQuery`].
+//!
+//! [`assert_pass_snapshot!`] runs a pass over the ops in a JSON fixture file, and
+//! compares the resulting [`PassResult`] against the snapshot checked in next to
+//! the fixture (`<fixture>.snap.json`). Set [`UPDATE_SNAPSHOTS_ENV`] to write
+//! the snapshots instead, and review the diff before committing them.
+//!
+//! The snapshot is rendered deterministically: entries are sorted by their
+//! parent indexes (entries with the same parents keep the order they were
+//! emitted in), object keys are sorted, and the JSON is pretty printed.
+//!
+//! [`assert_pass_snapshot!`]: crate::assert_pass_snapshot
+//! [`VoyagerQuery`]: crate::query::VoyagerQuery
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use jsonrpsee::core::RpcResult;
+#[cfg(feature = "server")]
+use jsonrpsee::Extensions;
+use serde_json::{json, Map, Value};
+use voyager_vm::{pass::PassResult, Op};
+
+pub use self::voyager::MockVoyager;
+use crate::VoyagerMessage;
+
+mod voyager;
+
+/// Set this environment variable to (re)generate the snapshots instead of
+/// comparing against them.
+pub const UPDATE_SNAPSHOTS_ENV: &str = "VOYAGER_UPDATE_SNAPSHOTS";
+
+/// Run a pass over the ops in `fixture`, comparing the result against the
+/// snapshot for it. Prefer [`assert_pass_snapshot!`], which resolves `fixture`
+/// relative to the crate being tested.
+///
+/// [`assert_pass_snapshot!`]: crate::assert_pass_snapshot
+pub async fn assert_pass_snapshot(pass: &impl RunPass, fixture: impl AsRef<Path>) {
+    let fixture = fixture.as_ref();
+
+    let ops = load_ops(fixture);
+    let len = ops.len();
+
+    let result = pass
+        .run_pass(ops)
+        .await
+        .unwrap_or_else(|err| panic!("pass failed on {}: {err:?}", fixture.display()));
+
+    result.debug_assert_parents(len);
+
+    let actual = render(result);
+    let snapshot = snapshot_path(fixture);
+
+    if std::env::var_os(UPDATE_SNAPSHOTS_ENV).is_some() {
+        fs::write(&snapshot, &actual)
+            .unwrap_or_else(|err| panic!("unable to write snapshot {}: {err}", snapshot.display()));
+
+        return;
+    }
+
+    let expected = fs::read_to_string(&snapshot).unwrap_or_else(|err| {
+        panic!(
+            "unable to read snapshot {}: {err}; run with {UPDATE_SNAPSHOTS_ENV}=1 to create it",
+            snapshot.display()
+        )
+    });
+
+    assert!(
+        expected == actual,
+        "pass output for {} does not match the snapshot; run with {UPDATE_SNAPSHOTS_ENV}=1 to \
+        update it\n\n--- expected\n{expected}\n--- actual\n{actual}",
+        fixture.display(),
+    );
+}
+
+/// A pass to snapshot the output of.
+///
+/// This is implemented for closures returning a [`PassResult`] (for passes
+/// that are implemented as free functions), and for [`PluginPass`].
+#[allow(async_fn_in_trait)]
+pub trait RunPass {
+    async fn run_pass(&self, ops: Vec<Op<VoyagerMessage>>)
+        -> RpcResult<PassResult<VoyagerMessage>>;
+}
+
+impl<F> RunPass for F
+where
+    F: Fn(Vec<Op<VoyagerMessage>>) -> PassResult<VoyagerMessage>,
+{
+    async fn run_pass(
+        &self,
+        ops: Vec<Op<VoyagerMessage>>,
+    ) -> RpcResult<PassResult<VoyagerMessage>> {
+        Ok(self(ops))
+    }
+}
+
+/// The `run_pass` of a plugin server, called with empty [`Extensions`].
+///
+/// Passes only rewrite ops and must not call back into voyager, so no
+/// [`VoyagerClient`](crate::VoyagerClient) is provided; a pass that tries to
+/// retrieve one fails with an error instead.
+#[cfg(feature = "server")]
+pub struct PluginPass<'a, P, C, Cb> {
+    plugin: &'a P,
+    __marker: std::marker::PhantomData<fn() -> (C, Cb)>,
+}
+
+#[cfg(feature = "server")]
+impl<'a, P, C, Cb> PluginPass<'a, P, C, Cb> {
+    pub fn new(plugin: &'a P) -> Self {
+        Self {
+            plugin,
+            __marker: std::marker::PhantomData,
+        }
+    }
+}
+
+#[cfg(feature = "server")]
+impl<P, C, Cb> RunPass for PluginPass<'_, P, C, Cb>
+where
+    P: crate::module::PluginServer<C, Cb>,
+    C: unionlabs::traits::Member,
+    Cb: unionlabs::traits::Member,
+{
+    async fn run_pass(
+        &self,
+        ops: Vec<Op<VoyagerMessage>>,
+    ) -> RpcResult<PassResult<VoyagerMessage>> {
+        self.plugin.run_pass(&Extensions::new(), ops).await
+    }
+}
+
+/// Read the ops in `fixture`, a JSON array of [`Op`]s.
+pub fn load_ops(fixture: &Path) -> Vec<Op<VoyagerMessage>> {
+    let raw = fs::read_to_string(fixture)
+        .unwrap_or_else(|err| panic!("unable to read fixture {}: {err}", fixture.display()));
+
+    serde_json::from_str(&raw)
+        .unwrap_or_else(|err| panic!("invalid fixture {}: {err}", fixture.display()))
+}
+
+/// The snapshot file for `fixture`, i.e. `pass/batch.json` ->
+/// `pass/batch.snap.json`.
+#[must_use]
+pub fn snapshot_path(fixture: &Path) -> PathBuf {
+    fixture.with_extension("snap.json")
+}
+
+/// Render `result` deterministically, as described in the [module
+/// docs](self).
+#[must_use]
+pub fn render(result: PassResult<VoyagerMessage>) -> String {
+    let mut optimize_further = result
+        .optimize_further
+        .into_iter()
+        .map(|(mut parents, op, tag)| {
+            parents.sort_unstable();
+            (parents, tag, op)
+        })
+        .collect::<Vec<_>>();
+    optimize_further.sort_by(|a, b| (&a.0, &a.1).cmp(&(&b.0, &b.1)));
+
+    let mut ready = result
+        .ready
+        .into_iter()
+        .map(|(mut parents, op)| {
+            parents.sort_unstable();
+            (parents, op)
+        })
+        .collect::<Vec<_>>();
+    ready.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let snapshot = json!({
+        "optimize_further": optimize_further
+            .into_iter()
+            .map(|(parents, tag, op)| json!({ "parents": parents, "tag": tag, "op": op }))
+            .collect::<Vec<_>>(),
+        "ready": ready
+            .into_iter()
+            .map(|(parents, op)| json!({ "parents": parents, "op": op }))
+            .collect::<Vec<_>>(),
+    });
+
+    let mut rendered = serde_json::to_string_pretty(&sort_keys(snapshot))
+        .expect("serializing a value is infallible; qed;");
+    rendered.push('\n');
+
+    rendered
+}
+
+fn sort_keys(value: Value) -> Value {
+    match value {
+        Value::Array(values) => Value::Array(values.into_iter().map(sort_keys).collect()),
+        Value::Object(map) => {
+            let mut entries = map.into_iter().collect::<Vec<_>>();
+            entries.sort_by(|a, b| a.0.cmp(&b.0));
+
+            Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (key, sort_keys(value)))
+                    .collect::<Map<_, _>>(),
+            )
+        }
+        value => value,
+    }
+}
+
+/// Run the pass `$pass` over the ops in `$fixture` (a path relative to the
+/// crate root) and compare the result against the snapshot checked in next to
+/// it. See the [`testing`](crate::testing) module for details.
+///
+/// `$pass` is anything implementing [`RunPass`](crate::testing::RunPass),
+/// i.e. a closure calling the pass function of the plugin:
+///
+/// ```ignore
+/// assert_pass_snapshot!(|ops| run_pass(&chain_id, ops), "testdata/pass/fetch_blocks.json");
+/// ```
+#[macro_export]
+macro_rules! assert_pass_snapshot {
+    ($pass:expr, $fixture:expr $(,)?) => {
+        $crate::testing::assert_pass_snapshot(
+            &$pass,
+            ::std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join($fixture),
+        )
+        .await
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use voyager_vm::{data, defer};
+
+    use super::*;
+    use crate::PluginMessage;
+
+    #[test]
+    fn render_is_deterministic() {
+        let result = PassResult::<VoyagerMessage> {
+            optimize_further: vec![
+                (vec![3], defer(2), "b".to_owned()),
+                (vec![3], defer(1), "a".to_owned()),
+            ],
+            ready: vec![
+                (
+                    vec![2, 0],
+                    data(PluginMessage::new("p", json!({ "b": 1, "a": 2 }))),
+                ),
+                (vec![1], defer(3)),
+            ],
+        };
+
+        assert_eq!(
+            render(result),
+            r#"{
+  "optimize_further": [
+    {
+      "op": {
+        "@type": "defer",
+        "@value": {
+          "until": 1
+        }
+      },
+      "parents": [
+        3
+      ],
+      "tag": "a"
+    },
+    {
+      "op": {
+        "@type": "defer",
+        "@value": {
+          "until": 2
+        }
+      },
+      "parents": [
+        3
+      ],
+      "tag": "b"
+    }
+  ],
+  "ready": [
+    {
+      "op": {
+        "@type": "data",
+        "@value": {
+          "@type": "plugin",
+          "@value": {
+            "message": {
+              "a": 2,
+              "b": 1
+            },
+            "plugin": "p"
+          }
+        }
+      },
+      "parents": [
+        0,
+        2
+      ]
+    },
+    {
+      "op": {
+        "@type": "defer",
+        "@value": {
+          "until": 3
+        }
+      },
+      "parents": [
+        1
+      ]
+    }
+  ]
+}
+"#
+        );
+    }
+
+    #[test]
+    fn ready_ops_with_the_same_parents_keep_their_order() {
+        let rendered = render(PassResult {
+            optimize_further: vec![],
+            ready: vec![(vec![0], defer(2)), (vec![0], defer(1))],
+        });
+
+        assert!(rendered.find("\"until\": 2") < rendered.find("\"until\": 1"));
+    }
+
+    #[test]
+    fn snapshot_is_next_to_fixture() {
+        assert_eq!(
+            snapshot_path(Path::new("testdata/pass/batch.json")),
+            Path::new("testdata/pass/batch.snap.json")
+        );
+    }
+}
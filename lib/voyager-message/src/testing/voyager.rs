@@ -0,0 +1,598 @@
+use std::sync::Mutex;
+
+use jsonrpsee::{
+    core::RpcResult,
+    types::{ErrorObject, ErrorObjectOwned},
+};
+use serde_json::{json, Value};
+use unionlabs::{bytes::Bytes, ibc::core::client::height::Height, ErrorReporter};
+use voyager_core::{
+    ChainId, ClientInfo, ClientStateMeta, ClientType, IbcInterface, IbcSpec, IbcSpecId,
+    IbcStorePathKey, QueryHeight,
+};
+
+use crate::{
+    into_value,
+    query::VoyagerQuery,
+    rpc::{IbcProof, IbcState, SelfClientState},
+    RawClientId, FATAL_JSONRPC_ERROR_CODE,
+};
+
+/// An in memory [`VoyagerQuery`] implementation, to test code written against it without running
+/// voyager.
+///
+/// - Values are set for a chain either at all heights, or starting from a specific height (see
+///   [`MockVoyager::set_state_at`]); a query returns the value set at the greatest height not
+///   after the queried height.
+/// - Unset IBC state is `null`, as returned by voyager for state that does not exist. Unset proofs
+///   are generated from the path and the height they're read at (see [`MockVoyager::proof`]),
+///   and all other unset values are an error.
+/// - Proofs are "encoded" as their JSON (see [`MockVoyager::encoded`]), and client and consensus
+///   states are "decoded" from JSON.
+/// - Every query is recorded, see [`MockVoyager::calls`].
+#[derive(Debug, Default)]
+pub struct MockVoyager {
+    inner: Mutex<Inner>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    latest_heights: Vec<(ChainId, bool, Height)>,
+    proof_heights: Vec<(ChainId, Height, Height)>,
+    execution_heights: Vec<(ChainId, Height, u64)>,
+    states: Entries,
+    proofs: Entries,
+    client_infos: Entries,
+    client_metas: Entries,
+    self_client_states: Entries,
+    failures: Vec<(&'static str, ErrorObjectOwned)>,
+    calls: Vec<(&'static str, Value)>,
+}
+
+#[derive(Debug, Default)]
+struct Entries(Vec<Entry>);
+
+#[derive(Debug)]
+struct Entry {
+    chain_id: ChainId,
+    key: Value,
+    from: Option<Height>,
+    value: Value,
+}
+
+impl Entries {
+    fn set(&mut self, chain_id: &ChainId, key: Value, from: Option<Height>, value: Value) {
+        self.0
+            .retain(|e| !(&e.chain_id == chain_id && e.key == key && e.from == from));
+
+        self.0.push(Entry {
+            chain_id: chain_id.clone(),
+            key,
+            from,
+            value,
+        });
+    }
+
+    /// The value set at the greatest height not after `height`, or at any height if `height` is
+    /// `None`.
+    fn get(&self, chain_id: &ChainId, key: &Value, height: Option<Height>) -> Option<&Value> {
+        self.0
+            .iter()
+            .filter(|e| &e.chain_id == chain_id && &e.key == key)
+            .filter(|e| {
+                e.from
+                    .zip(height)
+                    .is_none_or(|(from, height)| from <= height)
+            })
+            .max_by_key(|e| e.from)
+            .map(|e| &e.value)
+    }
+}
+
+impl MockVoyager {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the latest height of `chain_id`, both finalized and unfinalized.
+    pub fn set_latest_height(&self, chain_id: &ChainId, height: Height) -> &Self {
+        let mut inner = self.inner();
+        inner.latest_heights.retain(|(c, _, _)| c != chain_id);
+        inner.latest_heights.push((chain_id.clone(), false, height));
+        inner.latest_heights.push((chain_id.clone(), true, height));
+        drop(inner);
+        self
+    }
+
+    /// Set the latest finalized height of `chain_id`, leaving the latest unfinalized height as is.
+    pub fn set_latest_finalized_height(&self, chain_id: &ChainId, height: Height) -> &Self {
+        let mut inner = self.inner();
+        inner
+            .latest_heights
+            .retain(|(c, finalized, _)| !(c == chain_id && *finalized));
+        inner.latest_heights.push((chain_id.clone(), true, height));
+        drop(inner);
+        self
+    }
+
+    /// Set the execution height of `chain_id` at the consensus `height`.
+    pub fn set_execution_height(
+        &self,
+        chain_id: &ChainId,
+        height: Height,
+        execution_height: u64,
+    ) -> &Self {
+        let mut inner = self.inner();
+        inner
+            .execution_heights
+            .retain(|(c, h, _)| !(c == chain_id && *h == height));
+        inner
+            .execution_heights
+            .push((chain_id.clone(), height, execution_height));
+        drop(inner);
+        self
+    }
+
+    /// Set the state at `path` on `chain_id`, at all heights.
+    pub fn set_state<P: IbcStorePathKey>(
+        &self,
+        chain_id: &ChainId,
+        path: P,
+        state: P::Value,
+    ) -> &Self {
+        self.inner()
+            .states
+            .set(chain_id, store_path(path), None, into_value(state));
+        self
+    }
+
+    /// Set the state at `path` on `chain_id`, at `height` and all heights after it.
+    pub fn set_state_at<P: IbcStorePathKey>(
+        &self,
+        chain_id: &ChainId,
+        height: Height,
+        path: P,
+        state: P::Value,
+    ) -> &Self {
+        self.inner()
+            .states
+            .set(chain_id, store_path(path), Some(height), into_value(state));
+        self
+    }
+
+    /// Set the (encoded) client state of `client_id` on `chain_id`, at all heights.
+    pub fn set_client_state<V: IbcSpec>(
+        &self,
+        chain_id: &ChainId,
+        client_id: V::ClientId,
+        client_state: Bytes,
+    ) -> &Self {
+        self.inner().states.set(
+            chain_id,
+            into_value(V::client_state_path(client_id)),
+            None,
+            into_value(client_state),
+        );
+        self
+    }
+
+    /// Set the proof of `path` on `chain_id`, at all heights.
+    pub fn set_proof<P: IbcStorePathKey>(
+        &self,
+        chain_id: &ChainId,
+        path: P,
+        proof: Value,
+    ) -> &Self {
+        self.inner()
+            .proofs
+            .set(chain_id, store_path(path), None, proof);
+        self
+    }
+
+    /// Report the proofs of `chain_id` queried at `queried` as read at `reported`, as a proof
+    /// module that can't prove every height does.
+    pub fn set_proof_height(&self, chain_id: &ChainId, queried: Height, reported: Height) -> &Self {
+        self.inner()
+            .proof_heights
+            .push((chain_id.clone(), queried, reported));
+        self
+    }
+
+    pub fn set_client_info<V: IbcSpec>(
+        &self,
+        chain_id: &ChainId,
+        client_id: V::ClientId,
+        client_info: ClientInfo,
+    ) -> &Self {
+        self.inner().client_infos.set(
+            chain_id,
+            client_key(&V::ID, RawClientId::new(client_id)),
+            None,
+            into_value(client_info),
+        );
+        self
+    }
+
+    /// Set the meta of `client_id` on `chain_id`, at all heights.
+    pub fn set_client_meta<V: IbcSpec>(
+        &self,
+        chain_id: &ChainId,
+        client_id: V::ClientId,
+        meta: ClientStateMeta,
+    ) -> &Self {
+        self.inner().client_metas.set(
+            chain_id,
+            client_key(&V::ID, RawClientId::new(client_id)),
+            None,
+            into_value(meta),
+        );
+        self
+    }
+
+    /// Set the meta of `client_id` on `chain_id`, at `height` and all heights after it.
+    pub fn set_client_meta_at<V: IbcSpec>(
+        &self,
+        chain_id: &ChainId,
+        height: Height,
+        client_id: V::ClientId,
+        meta: ClientStateMeta,
+    ) -> &Self {
+        self.inner().client_metas.set(
+            chain_id,
+            client_key(&V::ID, RawClientId::new(client_id)),
+            Some(height),
+            into_value(meta),
+        );
+        self
+    }
+
+    /// Set the state of a client tracking `chain_id`, at all heights.
+    pub fn set_self_client_state(&self, chain_id: &ChainId, state: Value) -> &Self {
+        self.inner()
+            .self_client_states
+            .set(chain_id, Value::Null, None, state);
+        self
+    }
+
+    /// Fail all calls of `method` with `error`. Methods are named as in the voyager rpc, i.e. as
+    /// the [`VoyagerQuery`] method without the `_raw` suffix.
+    pub fn fail(&self, method: &'static str, error: ErrorObjectOwned) -> &Self {
+        self.inner().failures.push((method, error));
+        self
+    }
+
+    /// The parameters of all calls of `method` (named as in [`MockVoyager::fail`]), in the order
+    /// they were made. The parameters are an object of the arguments, keyed by their names.
+    #[must_use]
+    pub fn calls(&self, method: &str) -> Vec<Value> {
+        self.inner()
+            .calls
+            .iter()
+            .filter(|(m, _)| *m == method)
+            .map(|(_, params)| params.clone())
+            .collect()
+    }
+
+    /// The proof returned for `path` at `height` if none is set with [`MockVoyager::set_proof`].
+    pub fn proof<P: IbcStorePathKey>(height: Height, path: P) -> Value {
+        json!({
+            "height": height,
+            "path": store_path(path),
+        })
+    }
+
+    /// `proof`, as encoded by [`VoyagerQuery::encode_proof_raw`].
+    #[must_use]
+    pub fn encoded(proof: &Value) -> Bytes {
+        serde_json::to_vec(proof).unwrap().into()
+    }
+
+    fn inner(&self) -> std::sync::MutexGuard<'_, Inner> {
+        self.inner.lock().unwrap()
+    }
+
+    fn call(&self, method: &'static str, params: Value) -> RpcResult<()> {
+        let mut inner = self.inner();
+
+        inner.calls.push((method, params));
+
+        match inner.failures.iter().find(|(m, _)| *m == method) {
+            Some((_, error)) => Err(error.clone()),
+            None => Ok(()),
+        }
+    }
+
+    /// Resolve `height` on `chain_id`, or `None` if the latest height of the chain is not set.
+    fn resolve(&self, chain_id: &ChainId, height: QueryHeight) -> Option<Height> {
+        let finalized = match height {
+            QueryHeight::Latest => false,
+            QueryHeight::Finalized => true,
+            QueryHeight::Specific(height) => return Some(height),
+        };
+
+        self.inner()
+            .latest_heights
+            .iter()
+            .find(|(c, f, _)| c == chain_id && *f == finalized)
+            .map(|(_, _, height)| *height)
+    }
+
+    fn height(&self, chain_id: &ChainId, height: QueryHeight) -> RpcResult<Height> {
+        self.resolve(chain_id, height)
+            .ok_or_else(|| not_set(format!("the latest height of {chain_id}")))
+    }
+}
+
+impl VoyagerQuery for MockVoyager {
+    async fn query_latest_height(&self, chain_id: &ChainId, finalized: bool) -> RpcResult<Height> {
+        self.call(
+            "query_latest_height",
+            json!({ "chain_id": chain_id, "finalized": finalized }),
+        )?;
+
+        self.height(
+            chain_id,
+            if finalized {
+                QueryHeight::Finalized
+            } else {
+                QueryHeight::Latest
+            },
+        )
+    }
+
+    async fn consensus_to_execution_height(
+        &self,
+        chain_id: &ChainId,
+        height: Height,
+    ) -> RpcResult<u64> {
+        self.call(
+            "consensus_to_execution_height",
+            json!({ "chain_id": chain_id, "height": height }),
+        )?;
+
+        self.inner()
+            .execution_heights
+            .iter()
+            .find(|(c, h, _)| c == chain_id && *h == height)
+            .map(|(_, _, execution_height)| *execution_height)
+            .ok_or_else(|| not_set(format!("the execution height of {chain_id} at {height}")))
+    }
+
+    async fn client_info_raw(
+        &self,
+        chain_id: &ChainId,
+        ibc_spec_id: &IbcSpecId,
+        client_id: RawClientId,
+    ) -> RpcResult<ClientInfo> {
+        self.call(
+            "client_info",
+            json!({ "chain_id": chain_id, "ibc_spec_id": ibc_spec_id, "client_id": client_id }),
+        )?;
+
+        let key = client_key(ibc_spec_id, client_id);
+
+        let info = self
+            .inner()
+            .client_infos
+            .get(chain_id, &key, None)
+            .cloned()
+            .ok_or_else(|| not_set(format!("the info of client {key} on {chain_id}")))?;
+
+        decode(info)
+    }
+
+    async fn client_meta_raw(
+        &self,
+        chain_id: &ChainId,
+        ibc_spec_id: &IbcSpecId,
+        at: QueryHeight,
+        client_id: RawClientId,
+    ) -> RpcResult<ClientStateMeta> {
+        self.call(
+            "client_meta",
+            json!({
+                "chain_id": chain_id,
+                "ibc_spec_id": ibc_spec_id,
+                "height": at,
+                "client_id": client_id,
+            }),
+        )?;
+
+        // metas set at all heights don't require the latest height to be set
+        let height = self.resolve(chain_id, at);
+        let key = client_key(ibc_spec_id, client_id);
+
+        let meta = self
+            .inner()
+            .client_metas
+            .get(chain_id, &key, height)
+            .cloned()
+            .ok_or_else(|| not_set(format!("the meta of client {key} on {chain_id}")))?;
+
+        decode(meta)
+    }
+
+    async fn query_ibc_state_raw(
+        &self,
+        chain_id: &ChainId,
+        ibc_spec_id: &IbcSpecId,
+        height: QueryHeight,
+        path: Value,
+    ) -> RpcResult<IbcState<Value>> {
+        self.call(
+            "query_ibc_state",
+            json!({
+                "chain_id": chain_id,
+                "ibc_spec_id": ibc_spec_id,
+                "height": height,
+                "path": path,
+            }),
+        )?;
+
+        let height = self.height(chain_id, height)?;
+
+        let state = self
+            .inner()
+            .states
+            .get(chain_id, &path, Some(height))
+            .cloned()
+            .unwrap_or(Value::Null);
+
+        Ok(IbcState { height, state })
+    }
+
+    async fn query_ibc_proof_raw(
+        &self,
+        chain_id: &ChainId,
+        ibc_spec_id: &IbcSpecId,
+        height: QueryHeight,
+        path: Value,
+    ) -> RpcResult<IbcProof> {
+        self.call(
+            "query_ibc_proof",
+            json!({
+                "chain_id": chain_id,
+                "ibc_spec_id": ibc_spec_id,
+                "height": height,
+                "path": path,
+            }),
+        )?;
+
+        let height = self.height(chain_id, height)?;
+
+        let inner = self.inner();
+
+        let height = inner
+            .proof_heights
+            .iter()
+            .find(|(c, queried, _)| c == chain_id && *queried == height)
+            .map_or(height, |(_, _, reported)| *reported);
+
+        let proof = inner
+            .proofs
+            .get(chain_id, &path, Some(height))
+            .cloned()
+            .unwrap_or_else(|| json!({ "height": height, "path": path }));
+
+        Ok(IbcProof { height, proof })
+    }
+
+    async fn self_client_state(
+        &self,
+        chain_id: &ChainId,
+        height: QueryHeight,
+    ) -> RpcResult<SelfClientState> {
+        self.call(
+            "self_client_state",
+            json!({ "chain_id": chain_id, "height": height }),
+        )?;
+
+        let height = self.height(chain_id, height)?;
+
+        let state = self
+            .inner()
+            .self_client_states
+            .get(chain_id, &Value::Null, Some(height))
+            .cloned()
+            .ok_or_else(|| not_set(format!("the self client state of {chain_id}")))?;
+
+        Ok(SelfClientState { height, state })
+    }
+
+    async fn encode_proof_raw(
+        &self,
+        client_type: &ClientType,
+        ibc_interface: &IbcInterface,
+        ibc_spec_id: &IbcSpecId,
+        proof: Value,
+    ) -> RpcResult<Bytes> {
+        self.call(
+            "encode_proof",
+            json!({
+                "client_type": client_type,
+                "ibc_interface": ibc_interface,
+                "ibc_spec_id": ibc_spec_id,
+                "proof": proof,
+            }),
+        )?;
+
+        Ok(Self::encoded(&proof))
+    }
+
+    async fn decode_client_state(
+        &self,
+        client_type: &ClientType,
+        ibc_interface: &IbcInterface,
+        ibc_spec_id: &IbcSpecId,
+        client_state: Bytes,
+    ) -> RpcResult<Value> {
+        self.call(
+            "decode_client_state",
+            json!({
+                "client_type": client_type,
+                "ibc_interface": ibc_interface,
+                "ibc_spec_id": ibc_spec_id,
+                "client_state": client_state,
+            }),
+        )?;
+
+        decode_json(&client_state)
+    }
+
+    async fn decode_consensus_state(
+        &self,
+        client_type: &ClientType,
+        ibc_interface: &IbcInterface,
+        ibc_spec_id: &IbcSpecId,
+        consensus_state: Bytes,
+    ) -> RpcResult<Value> {
+        self.call(
+            "decode_consensus_state",
+            json!({
+                "client_type": client_type,
+                "ibc_interface": ibc_interface,
+                "ibc_spec_id": ibc_spec_id,
+                "consensus_state": consensus_state,
+            }),
+        )?;
+
+        decode_json(&consensus_state)
+    }
+}
+
+fn store_path<P: IbcStorePathKey>(path: P) -> Value {
+    into_value(<P::Spec as IbcSpec>::StorePath::from(path.into()))
+}
+
+fn client_key(ibc_spec_id: &IbcSpecId, client_id: RawClientId) -> Value {
+    json!([ibc_spec_id, client_id])
+}
+
+fn not_set(what: String) -> ErrorObjectOwned {
+    ErrorObject::owned(
+        FATAL_JSONRPC_ERROR_CODE,
+        format!("{what} is not set in the mock"),
+        None::<()>,
+    )
+}
+
+fn decode<T: serde::de::DeserializeOwned>(value: Value) -> RpcResult<T> {
+    serde_json::from_value(value).map_err(|err| {
+        ErrorObject::owned(
+            FATAL_JSONRPC_ERROR_CODE,
+            ErrorReporter(err).to_string(),
+            None::<()>,
+        )
+    })
+}
+
+fn decode_json(bytes: &[u8]) -> RpcResult<Value> {
+    serde_json::from_slice(bytes).map_err(|err| {
+        ErrorObject::owned(
+            FATAL_JSONRPC_ERROR_CODE,
+            format!("mock states are JSON: {}", ErrorReporter(err)),
+            None::<()>,
+        )
+    })
+}
@@ -0,0 +1,133 @@
+//! Upgrades of [`VoyagerMessage`](crate::VoyagerMessage) ops written with an older version of the
+//! wire format. See [`voyager_vm::wire`] for how the version is stored.
+//!
+//! # Versions
+//!
+//! - `0`: Ops written before the wire format was versioned, by any previous release. These may
+//!   contain the older serialized forms of the following types:
+//!   - [`Order`] was serialized with its protobuf name (`"ORDER_UNORDERED"`), and is now
+//!     serialized in snake case (`"unordered"`).
+//!   - The `state` of a
+//!     [`ConnectionEnd`](unionlabs::ibc::core::connection::connection_end::ConnectionEnd) was
+//!     serialized with its protobuf name (`"STATE_OPEN"`), and the `connection_id` of its
+//!     counterparty was the prefixed id (`"connection-1"`, or `""` if it was not yet known). The
+//!     state is now serialized in snake case (`"open"`), and the connection id is the numeric id
+//!     (or `null`).
+//! - `1`: The current version.
+//!
+//! Most of the payloads in an op are opaque JSON values (events, datagrams, IBC state, plugin
+//! messages), so the types can't be found by their position in the op. Instead, the entire op is
+//! walked and the types are recognized by their shape: connection ends are objects with exactly
+//! the fields of a connection end, and [`Order`]s are the values of `ordering` and
+//! `channel_ordering` fields and the `features` of connection versions. Only values that are in
+//! the old form are rewritten. Compressed payloads (see [`compression`](crate::compression)) are not upgraded.
+//!
+//! # Fixtures
+//!
+//! `testdata/wire/v<version>` contains an op of every [`Call`](crate::call::Call) and
+//! [`Data`](crate::data::Data) variant as written by the release that introduced it (or the first
+//! release of the wire format version), which the tests ensure can still be decoded. When a new
+//! release is cut, the fixtures of any new variants (or of a new wire format version) are written
+//! with:
+//!
+//! ```sh
+//! cargo test -p voyager-message -- wire::tests::regenerate_fixtures --ignored
+//! ```
+//!
+//! Existing fixtures are never overwritten.
+
+use core::str::FromStr;
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+use unionlabs::{
+    ibc::core::{channel::order::Order, connection::state::State},
+    id::ConnectionId,
+};
+
+const CONNECTION_END_FIELDS: [&str; 5] = [
+    "client_id",
+    "versions",
+    "state",
+    "counterparty",
+    "delay_period",
+];
+
+const VERSION_FIELDS: [&str; 2] = ["identifier", "features"];
+
+const ORDER_FIELDS: [&str; 2] = ["ordering", "channel_ordering"];
+
+/// Upgrade `op` from the wire format `version` to the current version, in place.
+pub fn upgrade_op(op: &mut Value, version: u32) {
+    if version == 0 {
+        upgrade_v0(op);
+    }
+}
+
+fn upgrade_v0(value: &mut Value) {
+    match value {
+        Value::Array(values) => values.iter_mut().for_each(upgrade_v0),
+        Value::Object(map) => {
+            if has_fields(map, &CONNECTION_END_FIELDS) {
+                upgrade_v0_connection_end(map);
+            }
+
+            if has_fields(map, &VERSION_FIELDS) {
+                if let Some(Value::Array(features)) = map.get_mut("features") {
+                    features.iter_mut().for_each(rename_proto_variant::<Order>);
+                }
+            }
+
+            for (key, value) in map.iter_mut() {
+                if ORDER_FIELDS.contains(&key.as_str()) {
+                    rename_proto_variant::<Order>(value);
+                }
+
+                upgrade_v0(value);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn upgrade_v0_connection_end(connection_end: &mut Map<String, Value>) {
+    if let Some(state) = connection_end.get_mut("state") {
+        rename_proto_variant::<State>(state);
+    }
+
+    let Some(connection_id) = connection_end
+        .get_mut("counterparty")
+        .and_then(|counterparty| counterparty.get_mut("connection_id"))
+    else {
+        return;
+    };
+
+    let upgraded = match connection_id.as_str() {
+        Some("") => Some(Value::Null),
+        Some(prefixed) => ConnectionId::from_str_prefixed(prefixed).ok().map(to_value),
+        None => None,
+    };
+
+    if let Some(upgraded) = upgraded {
+        *connection_id = upgraded;
+    }
+}
+
+/// Rewrite an enum `value` that is serialized with its protobuf name to its current form. Values
+/// that are not a protobuf name of `E` are left as is.
+fn rename_proto_variant<E: FromStr + Serialize>(value: &mut Value) {
+    if let Some(variant) = value.as_str().and_then(|name| name.parse::<E>().ok()) {
+        *value = to_value(variant);
+    }
+}
+
+fn has_fields(map: &Map<String, Value>, fields: &[&str]) -> bool {
+    map.len() == fields.len() && fields.iter().all(|field| map.contains_key(*field))
+}
+
+fn to_value(value: impl Serialize) -> Value {
+    serde_json::to_value(value).expect("serialization is infallible; qed;")
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,507 @@
+//! Decoding of the fixtures in `testdata/wire`, see the [module documentation](super).
+
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use ibc_classic_spec::{FullEvent, IbcClassic};
+use serde_json::json;
+use unionlabs::{
+    bytes::Bytes,
+    hash::H256,
+    ibc::core::{
+        client::height::Height,
+        connection::{connection_end::ConnectionEnd, version::Version},
+    },
+};
+use voyager_core::{ClientInfo, ClientType, IbcInterface, IbcSpecId};
+use voyager_vm::{
+    wire::{decode_op, encode_op, WIRE_VERSION},
+    Op,
+};
+
+use super::*;
+use crate::{
+    call::{
+        Call, EventDensity, FetchBlockRange, FetchBlocks, FetchUpdateHeaders, WaitForHeight,
+        WaitForTimestamp, WaitForTrustedHeight, WatchClientFreeze,
+    },
+    core::ChainId,
+    data::{
+        ChainEvent, ClientUpdate, Data, DecodedHeaderMeta, IbcDatagram, LatestHeight,
+        OrderedClientUpdates, OrderedHeaders, WithChainId,
+    },
+    freeze::{ChannelRef, ClientRef, FrozenRelaying},
+    rpc::IbcState,
+    PluginMessage, RawClientId, VoyagerMessage,
+};
+
+fn fixtures_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata/wire")
+}
+
+/// The directories of the fixtures of each wire format version.
+fn version_dirs() -> Vec<PathBuf> {
+    let mut dirs = fs::read_dir(fixtures_dir())
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.strip_prefix('v'))
+                .is_some_and(|version| version.parse::<u32>().is_ok())
+        })
+        .collect::<Vec<_>>();
+
+    dirs.sort();
+
+    dirs
+}
+
+fn read_fixture(path: &Path) -> Value {
+    serde_json::from_str(&fs::read_to_string(path).unwrap())
+        .unwrap_or_else(|err| panic!("invalid json in {}: {err}", path.display()))
+}
+
+fn decode_fixture(path: &Path) -> Op<VoyagerMessage> {
+    decode_op(read_fixture(path))
+        .unwrap_or_else(|err| panic!("unable to decode {}: {err:?}", path.display()))
+}
+
+/// The name of the fixture of `op`, after the variant of the call or data it contains.
+///
+/// When adding a new variant here, also add it to [`samples`] and regenerate the fixtures.
+fn fixture_name(op: &Op<VoyagerMessage>) -> String {
+    match op {
+        Op::Call(call) => format!(
+            "call-{}",
+            match call {
+                Call::FetchBlocks(_) => "fetch_blocks",
+                Call::FetchBlockRange(_) => "fetch_block_range",
+                Call::FetchUpdateHeaders(_) => "fetch_update_headers",
+                Call::WaitForHeight(_) => "wait_for_height",
+                Call::WaitForTimestamp(_) => "wait_for_timestamp",
+                Call::WaitForTrustedHeight(_) => "wait_for_trusted_height",
+                Call::WatchClientFreeze(_) => "watch_client_freeze",
+                Call::Plugin(_) => "plugin",
+            }
+        ),
+        Op::Data(data) => format!(
+            "data-{}",
+            match data {
+                Data::IbcEvent(_) => "ibc_event",
+                Data::IbcDatagram(_) => "ibc_datagram",
+                Data::IdentifiedIbcDatagram(_) => "identified_ibc_datagram",
+                Data::IdentifiedIbcDatagramBatch(_) => "identified_ibc_datagram_batch",
+                Data::OrderedHeaders(_) => "ordered_headers",
+                Data::OrderedMsgUpdateClients(_) => "ordered_msg_update_clients",
+                Data::RelayingFrozen(_) => "relaying_frozen",
+                Data::LatestHeight(_) => "latest_height",
+                Data::Plugin(_) => "plugin",
+            }
+        ),
+        op => panic!("fixtures are only written for calls and data, found {op:?}"),
+    }
+}
+
+/// An op of every [`Call`] and [`Data`] variant, as written to the fixtures by
+/// [`regenerate_fixtures`].
+fn samples() -> Vec<Op<VoyagerMessage>> {
+    let union = || ChainId::new("union-testnet-9");
+    let sepolia = || ChainId::new("11155111");
+
+    let union_height = Height::new_with_revision(9, 2_823_301);
+    let sepolia_height = Height::new(7_000_000);
+
+    let plugin = "voyager-transaction-plugin-ethereum/11155111";
+
+    let datagram = IbcDatagram {
+        ibc_spec_id: IbcSpecId::new_static(IbcSpecId::UNION),
+        datagram: json!({
+            "@type": "update_client",
+            "@value": { "client_id": 1, "client_message": "0x0102" }
+        }),
+    };
+
+    vec![
+        Op::Call(Call::FetchBlocks(FetchBlocks {
+            chain_id: union(),
+            start_height: union_height,
+        })),
+        Op::Call(Call::FetchBlockRange(FetchBlockRange {
+            chain_id: union(),
+            from_height: union_height,
+            to_height: Height::new_with_revision(9, 2_824_300),
+            hint_events_per_block: EventDensity::new(2.5),
+        })),
+        Op::Call(Call::FetchUpdateHeaders(FetchUpdateHeaders {
+            chain_id: union(),
+            counterparty_chain_id: sepolia(),
+            update_from: union_height,
+            update_to: Height::new_with_revision(9, 2_823_400),
+        })),
+        Op::Call(Call::WaitForHeight(WaitForHeight {
+            chain_id: sepolia(),
+            height: sepolia_height,
+            finalized: true,
+        })),
+        Op::Call(Call::WaitForTimestamp(WaitForTimestamp {
+            chain_id: sepolia(),
+            timestamp: 1_729_036_800,
+            finalized: false,
+        })),
+        Op::Call(Call::WaitForTrustedHeight(WaitForTrustedHeight {
+            chain_id: sepolia(),
+            ibc_spec_id: IbcSpecId::new_static(IbcSpecId::UNION),
+            client_id: RawClientId::new(1),
+            height: union_height,
+        })),
+        Op::Call(Call::WatchClientFreeze(WatchClientFreeze {
+            client: ClientRef {
+                chain_id: sepolia(),
+                client_id: 1,
+            },
+        })),
+        Op::Call(Call::Plugin(PluginMessage::new(
+            plugin,
+            json!({ "@type": "submit_multicall", "@value": [] }),
+        ))),
+        Op::Data(Data::IbcEvent(ChainEvent {
+            chain_id: union(),
+            client_info: ClientInfo {
+                client_type: ClientType::new_static(ClientType::ETHEREUM),
+                ibc_interface: IbcInterface::new_static(IbcInterface::IBC_COSMWASM),
+                metadata: Value::Null,
+            },
+            counterparty_chain_id: sepolia(),
+            tx_hash: H256::new([0x11; 32]),
+            provable_height: union_height,
+            ibc_spec_id: IbcSpecId::new_static(IbcSpecId::UNION),
+            event: json!({
+                "@type": "create_client",
+                "@value": {
+                    "client_type": "ethereum",
+                    "client_id": 1,
+                    "counterparty_chain_id": "11155111"
+                }
+            }),
+            raw_events: None,
+            denom_trace: None,
+        })),
+        Op::Data(Data::IbcDatagram(datagram.clone())),
+        Op::Data(Data::IdentifiedIbcDatagram(WithChainId {
+            chain_id: sepolia(),
+            message: datagram.clone(),
+        })),
+        Op::Data(Data::IdentifiedIbcDatagramBatch(WithChainId {
+            chain_id: sepolia(),
+            message: vec![datagram.clone(), datagram],
+        })),
+        Op::Data(Data::OrderedHeaders(OrderedHeaders {
+            headers: vec![(
+                DecodedHeaderMeta {
+                    height: union_height,
+                },
+                json!({ "header": "0x0102" }),
+            )],
+        })),
+        Op::Data(Data::OrderedMsgUpdateClients(OrderedClientUpdates {
+            updates: vec![(
+                DecodedHeaderMeta {
+                    height: union_height,
+                },
+                ClientUpdate {
+                    client_id: RawClientId::new(1),
+                    ibc_spec_id: IbcSpecId::new_static(IbcSpecId::UNION),
+                    client_message: Bytes::from(vec![1, 2]),
+                },
+            )],
+        })),
+        Op::Data(Data::RelayingFrozen(FrozenRelaying {
+            client: ClientRef {
+                chain_id: sepolia(),
+                client_id: 1,
+            },
+            frozen_at: sepolia_height,
+            paired_clients: vec![ClientRef {
+                chain_id: union(),
+                client_id: 4,
+            }],
+            channels: vec![ChannelRef {
+                chain_id: sepolia(),
+                channel_id: 2,
+            }],
+        })),
+        Op::Data(Data::LatestHeight(LatestHeight {
+            chain_id: ChainId::new("534351"),
+            height: Height::new(7_500_000),
+            finalized: true,
+        })),
+        Op::Data(Data::Plugin(PluginMessage::new(
+            plugin,
+            json!({ "@type": "suppressed_datagram", "@value": { "channel_id": 2 } }),
+        ))),
+    ]
+}
+
+/// Write the fixtures of the [`samples`] that don't have a fixture for the current wire format
+/// version yet. Run this when cutting a release, see the [module documentation](super).
+#[test]
+#[ignore = "only run when cutting a release"]
+fn regenerate_fixtures() {
+    let dir = fixtures_dir().join(format!("v{WIRE_VERSION}"));
+
+    fs::create_dir_all(&dir).unwrap();
+
+    for op in samples() {
+        let path = dir.join(format!("{}.json", fixture_name(&op)));
+
+        let file = OpenOptions::new().write(true).create_new(true).open(&path);
+
+        match file {
+            Ok(mut file) => {
+                serde_json::to_writer_pretty(&mut file, &encode_op(&op).unwrap()).unwrap();
+                writeln!(file).unwrap();
+
+                println!("wrote {}", path.display());
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists => {}
+            Err(err) => panic!("unable to create {}: {err}", path.display()),
+        }
+    }
+}
+
+#[test]
+fn fixtures_decode() {
+    let dirs = version_dirs();
+
+    assert!(!dirs.is_empty());
+
+    for dir in dirs {
+        for entry in fs::read_dir(&dir).unwrap() {
+            let path = entry.unwrap().path();
+
+            let op = decode_fixture(&path);
+
+            assert_eq!(
+                path.file_stem().unwrap().to_str().unwrap(),
+                fixture_name(&op),
+                "{} decoded as a different variant",
+                path.display()
+            );
+
+            // and can be written again with the current version
+            assert_eq!(decode_op(encode_op(&op).unwrap()).unwrap(), op);
+        }
+    }
+}
+
+#[test]
+fn all_variants_have_fixtures() {
+    let dirs = version_dirs();
+
+    for op in samples() {
+        let name = fixture_name(&op);
+
+        assert!(
+            dirs.iter()
+                .any(|dir| dir.join(format!("{name}.json")).exists()),
+            "no fixture for {name}, run the regenerate_fixtures test"
+        );
+    }
+}
+
+#[test]
+fn samples_round_trip() {
+    for op in samples() {
+        let encoded = encode_op(&op).unwrap();
+
+        assert_eq!(encoded["v"], WIRE_VERSION);
+        assert_eq!(decode_op::<VoyagerMessage>(encoded).unwrap(), op);
+    }
+}
+
+fn legacy_fixture(name: &str) -> Op<VoyagerMessage> {
+    decode_fixture(&fixtures_dir().join(format!("legacy-v0/{name}.json")))
+}
+
+fn classic_event(op: Op<VoyagerMessage>) -> FullEvent {
+    let Op::Data(Data::IbcEvent(event)) = op else {
+        panic!("expected an ibc event, found {op:?}");
+    };
+
+    event.decode_event::<IbcClassic>().unwrap().unwrap()
+}
+
+fn assert_upgraded_connection_end(connection_end: &ConnectionEnd) {
+    assert_eq!(
+        connection_end.versions,
+        [Version {
+            identifier: "1".to_owned(),
+            features: vec![Order::Ordered, Order::Unordered],
+        }]
+    );
+}
+
+#[test]
+fn legacy_connection_end() {
+    let FullEvent::ChannelOpenInit(event) = classic_event(legacy_fixture("channel_open_init"))
+    else {
+        panic!("expected channel_open_init");
+    };
+
+    assert_upgraded_connection_end(&event.connection);
+    assert_eq!(event.connection.state, State::Open);
+    assert_eq!(
+        event.connection.counterparty.connection_id,
+        Some(ConnectionId::new(3))
+    );
+}
+
+#[test]
+fn legacy_channel_ordering() {
+    let FullEvent::SendPacket(event) = classic_event(legacy_fixture("send_packet")) else {
+        panic!("expected send_packet");
+    };
+
+    assert_eq!(event.packet.channel_ordering, Order::Unordered);
+}
+
+#[test]
+fn legacy_ibc_state() {
+    let Op::Data(Data::Plugin(message)) = legacy_fixture("connection_state") else {
+        panic!("expected a plugin message");
+    };
+
+    let state = serde_json::from_value::<IbcState<ConnectionEnd>>(message.message).unwrap();
+
+    assert_upgraded_connection_end(&state.state);
+    assert_eq!(state.state.state, State::Init);
+    assert_eq!(state.state.counterparty.connection_id, None);
+}
+
+#[test]
+fn legacy_fixtures_require_upgrade() {
+    let without_upgrade = |name: &str| {
+        let mut op = read_fixture(&fixtures_dir().join(format!("legacy-v0/{name}.json")));
+
+        // claim that the op was written with the current version
+        op["v"] = WIRE_VERSION.into();
+
+        decode_op::<VoyagerMessage>(op).unwrap()
+    };
+
+    for name in ["channel_open_init", "send_packet"] {
+        let Op::Data(Data::IbcEvent(event)) = without_upgrade(name) else {
+            panic!("expected an ibc event");
+        };
+
+        assert!(event.decode_event::<IbcClassic>().unwrap().is_err());
+    }
+
+    let Op::Data(Data::Plugin(message)) = without_upgrade("connection_state") else {
+        panic!("expected a plugin message");
+    };
+
+    assert!(serde_json::from_value::<IbcState<ConnectionEnd>>(message.message).is_err());
+}
+
+#[test]
+fn upgrade_v0() {
+    let mut op = json!({
+        "ordering": "ORDER_ORDERED",
+        "channel_ordering": "unordered",
+        "nested": [{
+            "identifier": "1",
+            "features": ["ORDER_UNORDERED", "not_an_order"],
+        }],
+        "connection": {
+            "client_id": "07-tendermint-0",
+            "versions": [],
+            "state": "STATE_TRYOPEN",
+            "counterparty": {
+                "client_id": "08-wasm-1",
+                "connection_id": "connection-3",
+                "prefix": { "key_prefix": "0x" },
+            },
+            "delay_period": 0,
+        },
+        // not a connection end
+        "state": "STATE_OPEN",
+    });
+
+    upgrade_op(&mut op, 0);
+
+    assert_eq!(
+        op,
+        json!({
+            "ordering": "ordered",
+            "channel_ordering": "unordered",
+            "nested": [{
+                "identifier": "1",
+                "features": ["unordered", "not_an_order"],
+            }],
+            "connection": {
+                "client_id": "07-tendermint-0",
+                "versions": [],
+                "state": "tryopen",
+                "counterparty": {
+                    "client_id": "08-wasm-1",
+                    "connection_id": 3,
+                    "prefix": { "key_prefix": "0x" },
+                },
+                "delay_period": 0,
+            },
+            "state": "STATE_OPEN",
+        })
+    );
+
+    // the current version is left as is
+    let mut current = json!({ "ordering": "ORDER_ORDERED" });
+    upgrade_op(&mut current, WIRE_VERSION);
+    assert_eq!(current, json!({ "ordering": "ORDER_ORDERED" }));
+}
+
+#[test]
+fn recognized_shapes_match_types() {
+    let connection_end = serde_json::to_value(
+        serde_json::from_value::<ConnectionEnd>(json!({
+            "client_id": "07-tendermint-0",
+            "versions": [{ "identifier": "1", "features": ["ordered"] }],
+            "state": "open",
+            "counterparty": {
+                "client_id": "08-wasm-1",
+                "connection_id": null,
+                "prefix": { "key_prefix": "0x" },
+            },
+            "delay_period": 0,
+        }))
+        .unwrap(),
+    )
+    .unwrap();
+
+    let keys = |value: &Value| {
+        let mut keys = value
+            .as_object()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>();
+        keys.sort();
+        keys
+    };
+
+    let sorted = |fields: &[&str]| {
+        let mut fields = fields.iter().map(|f| (*f).to_owned()).collect::<Vec<_>>();
+        fields.sort();
+        fields
+    };
+
+    assert_eq!(keys(&connection_end), sorted(&CONNECTION_END_FIELDS));
+    assert_eq!(
+        keys(&connection_end["versions"][0]),
+        sorted(&VERSION_FIELDS)
+    );
+}
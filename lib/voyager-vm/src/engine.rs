@@ -54,6 +54,14 @@ impl<'a, T: QueueMessage, Q: Queue<T>> Engine<'a, T, Q> {
                             error!(error = %full_err, "retryable error");
                             (None, Ok(vec![seq([defer(now() + 3), op])]))
                         }
+                        Err(QueueError::RetryAfter { after, error }) => {
+                            let full_err = ErrorReporter(&*error);
+                            error!(error = %full_err, ?after, "retryable error");
+                            (
+                                None,
+                                Ok(vec![seq([defer(now() + after.as_secs().max(1)), op])]),
+                            )
+                        }
                     })
                 })
                 .map(|data| match data {
@@ -6,11 +6,65 @@ pub trait InterestFilter<T: QueueMessage>: Send + Sync + Sized + 'static {
 }
 
 /// The result of running an [`InterestFilter`] on an [`Op`].
-pub enum FilterResult<'a> {
-    /// Interest has been expressed in this Op, with the contained tag. It will be inserted into the optimization queue under this tag.
-    Interest(&'a str),
-    /// No interest.
-    NoInterest,
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct FilterResult<'a> {
+    /// Interest has been expressed in this Op, with the contained tag. It will be inserted into the
+    /// optimization queue under this tag. If there is no interest, it is ready to be processed.
+    pub interest: Option<&'a str>,
+    /// This Op is observed with the contained tags. A copy of it will be inserted into the
+    /// optimization queue under each of these tags, without affecting where the Op itself goes.
+    pub observers: Vec<&'a str>,
+}
+
+impl<'a> FilterResult<'a> {
+    /// No interest, and no observers.
+    pub const NO_INTEREST: Self = Self {
+        interest: None,
+        observers: vec![],
+    };
+
+    /// Interest with `tag`, and no observers.
+    pub fn interest(tag: &'a str) -> Self {
+        Self {
+            interest: Some(tag),
+            observers: vec![],
+        }
+    }
+}
+
+/// An [`Op`] routed with an [`InterestFilter`], see [`route`].
+pub enum Routed<'a, T: QueueMessage> {
+    /// The op is to be inserted into the optimization queue under the contained tag.
+    Optimize(Op<T>, &'a str),
+    /// The op is ready to be processed.
+    Ready(Op<T>),
+}
+
+/// Route all of `ops` with `filter`. Every op is routed exactly once as per the interest in it, and
+/// an additional copy of it is routed for optimization under the tag of each of its observers (see
+/// [`FilterResult`]).
+pub fn route<'a, T: QueueMessage, F: InterestFilter<T>>(
+    filter: &'a F,
+    ops: impl IntoIterator<Item = Op<T>>,
+) -> Vec<Routed<'a, T>> {
+    ops.into_iter()
+        .flat_map(|op| {
+            let FilterResult {
+                interest,
+                observers,
+            } = filter.check_interest(&op);
+
+            observers
+                .into_iter()
+                .map(|tag| Routed::Optimize(op.clone(), tag))
+                .collect::<Vec<_>>()
+                .into_iter()
+                .chain([match interest {
+                    Some(tag) => Routed::Optimize(op, tag),
+                    None => Routed::Ready(op),
+                }])
+        })
+        .collect()
 }
 
 /// A noop implementation of an interest filter that never expresses interest in any messages.
@@ -18,6 +72,6 @@ impl<T: QueueMessage> InterestFilter<T> for () {
     fn check_interest<'a>(&'a self, op: &Op<T>) -> FilterResult<'a> {
         let _ = op;
 
-        FilterResult::NoInterest
+        FilterResult::NO_INTEREST
     }
 }
@@ -12,9 +12,11 @@ use frame_support_procedural::{CloneNoBound, DebugNoBound};
 use tracing::{debug, info_span, warn, Instrument};
 
 use crate::{
-    filter::{FilterResult, InterestFilter},
+    filter::{route, Routed},
+    now,
     pass::Pass,
-    Captures, Op, Queue, QueueMessage,
+    Captures, InspectQueue, LaneStats, Op, Queue, QueueMessage, QueueStats, QueuedOp, ScanFilter,
+    ScanPage, READY_LANE,
 };
 
 #[derive(DebugNoBound, CloneNoBound)]
@@ -30,6 +32,8 @@ pub struct InMemoryQueue<T: QueueMessage> {
 pub(crate) struct Item<T: QueueMessage> {
     #[allow(dead_code)] // used in debug
     parents: Vec<u32>,
+    /// Unix timestamp (seconds) of when this item was enqueued.
+    enqueued_at: u64,
     op: Op<T>,
 }
 
@@ -56,22 +60,24 @@ impl<T: QueueMessage> Queue<T> for InMemoryQueue<T> {
         let mut optimizer_queue = self.optimizer_queue.lock().expect("mutex is poisoned");
         let mut ready = self.ready.lock().expect("mutex is poisoned");
 
-        for op in op.normalize() {
-            match filter.check_interest(&op) {
-                FilterResult::Interest(tag) => {
+        for routed in route(filter, op.normalize()) {
+            match routed {
+                Routed::Optimize(op, tag) => {
                     optimizer_queue.entry(tag.to_owned()).or_default().insert(
                         self.idx.fetch_add(1, Ordering::SeqCst),
                         Item {
                             parents: vec![],
+                            enqueued_at: now(),
                             op,
                         },
                     );
                 }
-                FilterResult::NoInterest => {
+                Routed::Ready(op) => {
                     ready.insert(
                         self.idx.fetch_add(1, Ordering::SeqCst),
                         Item {
                             parents: vec![],
+                            enqueued_at: now(),
                             op,
                         },
                     );
@@ -119,22 +125,24 @@ impl<T: QueueMessage> Queue<T> for InMemoryQueue<T> {
                             self.optimizer_queue.lock().expect("mutex is poisoned");
                         let mut ready = self.ready.lock().expect("mutex is poisoned");
 
-                        for op in ops.into_iter().flat_map(Op::normalize) {
-                            match filter.check_interest(&op) {
-                                FilterResult::Interest(tag) => {
+                        for routed in route(filter, ops.into_iter().flat_map(Op::normalize)) {
+                            match routed {
+                                Routed::Optimize(op, tag) => {
                                     optimizer_queue.entry(tag.to_owned()).or_default().insert(
                                         self.idx.fetch_add(1, Ordering::SeqCst),
                                         Item {
                                             parents: vec![id],
+                                            enqueued_at: now(),
                                             op,
                                         },
                                     );
                                 }
-                                FilterResult::NoInterest => {
+                                Routed::Ready(op) => {
                                     ready.insert(
                                         self.idx.fetch_add(1, Ordering::SeqCst),
                                         Item {
                                             parents: vec![id],
+                                            enqueued_at: now(),
                                             op,
                                         },
                                     );
@@ -194,6 +202,7 @@ impl<T: QueueMessage> Queue<T> for InMemoryQueue<T> {
                     self.idx.fetch_add(1, Ordering::SeqCst),
                     Item {
                         parents: parents_idxs.iter().map(|&i| &ids[i]).copied().collect(),
+                        enqueued_at: now(),
                         op,
                     },
                 );
@@ -204,6 +213,7 @@ impl<T: QueueMessage> Queue<T> for InMemoryQueue<T> {
                     self.idx.fetch_add(1, Ordering::SeqCst),
                     Item {
                         parents: parents_idxs.iter().map(|&i| &ids[i]).copied().collect(),
+                        enqueued_at: now(),
                         op,
                     },
                 );
@@ -213,3 +223,103 @@ impl<T: QueueMessage> Queue<T> for InMemoryQueue<T> {
         }
     }
 }
+
+impl<T: QueueMessage> InspectQueue<T> for InMemoryQueue<T> {
+    fn scan<'a>(
+        &'a self,
+        // ops are not serialized in memory, so there is nothing cheaper to filter on than the
+        // decoded ops the caller matches anyways
+        _filter: &'a ScanFilter,
+        after: Option<i64>,
+        limit: usize,
+    ) -> impl Future<Output = Result<ScanPage<T>, Self::Error>> + Send + 'a {
+        let after = |id: &u32| after.is_none_or(|after| i64::from(*id) > after);
+
+        let to_queued = |(id, item): (&u32, &Item<T>)| QueuedOp {
+            id: (*id).into(),
+            enqueued_at: item.enqueued_at,
+            op: item.op.clone(),
+        };
+
+        let mut ops = self
+            .ready
+            .lock()
+            .expect("mutex is poisoned")
+            .iter()
+            .filter(|(id, _)| after(id))
+            .map(to_queued)
+            .collect::<Vec<_>>();
+
+        ops.extend(
+            self.optimizer_queue
+                .lock()
+                .expect("mutex is poisoned")
+                .values()
+                .flat_map(|tagged| tagged.iter().filter(|(id, _)| after(id)).map(to_queued)),
+        );
+
+        ops.sort_by_key(|op| op.id);
+        ops.truncate(limit);
+
+        let next = (ops.len() == limit)
+            .then(|| ops.last().map(|op| op.id))
+            .flatten();
+
+        futures::future::ok(ScanPage {
+            ops,
+            // ops in memory are always decodable
+            undecodable: vec![],
+            next,
+        })
+    }
+
+    fn remove<'a>(
+        &'a self,
+        ids: &'a [i64],
+    ) -> impl Future<Output = Result<Vec<i64>, Self::Error>> + Send + 'a {
+        // same lock order as enqueue and process
+        let mut optimizer_queue = self.optimizer_queue.lock().expect("mutex is poisoned");
+        let mut ready = self.ready.lock().expect("mutex is poisoned");
+
+        let removed = ids
+            .iter()
+            .copied()
+            .filter(|&id| {
+                let Ok(id) = u32::try_from(id) else {
+                    return false;
+                };
+
+                ready.remove(&id).is_some()
+                    || optimizer_queue
+                        .values_mut()
+                        .any(|tagged| tagged.remove(&id).is_some())
+            })
+            .collect();
+
+        futures::future::ok(removed)
+    }
+
+    fn stats(&self) -> impl Future<Output = Result<QueueStats, Self::Error>> + Send + '_ {
+        // ids are assigned in enqueue order, so the first op in a lane is also the oldest one
+        let lane_stats = |items: &BTreeMap<u32, Item<T>>| {
+            items.first_key_value().map(|(_, item)| LaneStats {
+                depth: items.len() as u64,
+                oldest_enqueued_at: item.enqueued_at,
+            })
+        };
+
+        let mut lanes = self
+            .optimizer_queue
+            .lock()
+            .expect("mutex is poisoned")
+            .iter()
+            .filter_map(|(tag, tagged)| Some((tag.clone(), lane_stats(tagged)?)))
+            .collect::<BTreeMap<_, _>>();
+
+        if let Some(ready) = lane_stats(&self.ready.lock().expect("mutex is poisoned")) {
+            lanes.insert(READY_LANE.to_owned(), ready);
+        }
+
+        futures::future::ok(QueueStats { lanes })
+    }
+}
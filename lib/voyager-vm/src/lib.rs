@@ -5,7 +5,7 @@
 
 use std::{
     self,
-    collections::VecDeque,
+    collections::{BTreeMap, VecDeque},
     error::Error,
     fmt::Debug,
     future::Future,
@@ -25,7 +25,10 @@ use crate::{filter::InterestFilter, pass::Pass};
 pub mod engine;
 pub mod filter;
 pub mod in_memory;
+pub mod normalize;
+pub mod op_graph;
 pub mod pass;
+pub mod wire;
 
 #[cfg(test)]
 mod tests;
@@ -68,6 +71,128 @@ pub trait Queue<T: QueueMessage>: Debug + Clone + Send + Sync + Sized + 'static
     ) -> impl Future<Output = Result<(), Either<Self::Error, O::Error>>> + Send + 'a;
 }
 
+/// Read and remove access to the ops waiting in a [`Queue`], for administrative tooling.
+///
+/// Neither method blocks ongoing processing or optimization passes; ops that are currently being
+/// handled are neither returned nor removed.
+pub trait InspectQueue<T: QueueMessage>: Queue<T> {
+    /// Read a page of at most `limit` of the ops that are ready to be processed or waiting to be
+    /// optimized, ordered by id, starting after the id `after`.
+    ///
+    /// Queues may skip ops that don't match `filter` before decoding them, but are not required
+    /// to, so the returned ops must still be matched by the caller. Ops that can't be decoded are
+    /// returned separately instead of failing the whole page.
+    fn scan<'a>(
+        &'a self,
+        filter: &'a ScanFilter,
+        after: Option<i64>,
+        limit: usize,
+    ) -> impl Future<Output = Result<ScanPage<T>, Self::Error>> + Send + 'a;
+
+    /// Remove the ops with the provided ids from the queue, returning the ids that were removed.
+    ///
+    /// Ids that are no longer in the queue (i.e. they were picked up for processing after the
+    /// page they were read in was scanned) are skipped.
+    fn remove<'a>(
+        &'a self,
+        ids: &'a [i64],
+    ) -> impl Future<Output = Result<Vec<i64>, Self::Error>> + Send + 'a;
+
+    /// The depth and the age of the oldest op of each non-empty lane of the queue.
+    ///
+    /// Unlike [`scan`](InspectQueue::scan), this does not read the ops themselves and is
+    /// cheap enough to be polled frequently.
+    fn stats(&self) -> impl Future<Output = Result<QueueStats, Self::Error>> + Send + '_;
+}
+
+/// The lane that ops which are ready to be processed are queued in. All other lanes are the
+/// optimization queues, keyed by the tag of the plugin that optimizes them.
+pub const READY_LANE: &str = "ready";
+
+/// Statistics of the ops waiting in a queue, as returned by [`InspectQueue::stats`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct QueueStats {
+    /// Only non-empty lanes are included.
+    pub lanes: BTreeMap<String, LaneStats>,
+}
+
+impl QueueStats {
+    /// The stats of `lane`, or an empty lane if there are no ops queued in it.
+    #[must_use]
+    pub fn lane(&self, lane: &str) -> LaneStats {
+        self.lanes.get(lane).copied().unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LaneStats {
+    /// The amount of ops queued in this lane.
+    pub depth: u64,
+    /// Unix timestamp (seconds) of when the oldest op in this lane was enqueued, or `0` if the
+    /// lane is empty.
+    pub oldest_enqueued_at: u64,
+}
+
+impl LaneStats {
+    /// The age in seconds of the oldest op in this lane as of `now`.
+    #[must_use]
+    pub fn oldest_age(&self, now: u64) -> u64 {
+        if self.depth == 0 {
+            0
+        } else {
+            now.saturating_sub(self.oldest_enqueued_at)
+        }
+    }
+}
+
+/// A coarse filter of the ops read by [`InspectQueue::scan`], on their JSON representation.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScanFilter {
+    /// Only read ops that contain a `chain_id` field with this value, or a `plugin` field ending in
+    /// `/<chain_id>` (a plugin message addressed to a plugin for the chain), at any depth.
+    pub chain_id: Option<String>,
+    /// Only read ops enqueued at or before this unix timestamp (seconds).
+    pub enqueued_before: Option<u64>,
+}
+
+/// A page of the ops read by [`InspectQueue::scan`].
+#[derive(::macros::Debug, ::frame_support_procedural::CloneNoBound)]
+#[debug(bound())]
+pub struct ScanPage<T: QueueMessage> {
+    pub ops: Vec<QueuedOp<T>>,
+    pub undecodable: Vec<UndecodableOp>,
+    /// The id to pass as `after` to read the next page, or `None` if this is the last page.
+    pub next: Option<i64>,
+}
+
+/// An op waiting in a queue that can't be decoded, i.e. because it was enqueued by an
+/// incompatible version.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UndecodableOp {
+    pub id: i64,
+    /// Unix timestamp (seconds) of when this op was enqueued.
+    pub enqueued_at: u64,
+    /// The op as it is stored in the queue.
+    pub item: serde_json::Value,
+    pub error: String,
+}
+
+/// An op waiting in a queue, as returned by [`InspectQueue::scan`].
+#[derive(
+    ::macros::Debug,
+    ::frame_support_procedural::CloneNoBound,
+    ::frame_support_procedural::PartialEqNoBound,
+)]
+#[debug(bound())]
+pub struct QueuedOp<T: QueueMessage> {
+    pub id: i64,
+    /// Unix timestamp (seconds) of when this op was enqueued.
+    pub enqueued_at: u64,
+    pub op: Op<T>,
+}
+
 #[derive(
     ::macros::Debug,
     ::frame_support_procedural::CloneNoBound,
@@ -178,6 +303,15 @@ pub trait QueueMessage: Sized + 'static {
     type Filter: InterestFilter<Self>;
 
     type Context: Context;
+
+    /// Rewrite a top level `op` of the wire format `version` into the shape of the current
+    /// [`WIRE_VERSION`](wire::WIRE_VERSION), before it is deserialized. `version` is always less
+    /// than the current version. See [`wire`] for more information.
+    ///
+    /// Ops that can't be upgraded should be left as is, and will then fail to deserialize.
+    fn upgrade_op(op: &mut serde_json::Value, version: u32) {
+        let _ = (op, version);
+    }
 }
 
 pub trait Context: Send + Sync {}
@@ -303,6 +437,8 @@ impl<T: QueueMessage> Op<T> {
         Box::pin(fut)
     }
 
+    /// [Normalize](normalize::normalize) this op and split it into the top level ops to be
+    /// enqueued, hoisting any data that is ready to be emitted out of it.
     pub fn normalize(self) -> Vec<Op<T>> {
         pub fn go<T: QueueMessage>(op: Op<T>) -> Vec<Op<T>> {
             match op {
@@ -381,7 +517,7 @@ impl<T: QueueMessage> Op<T> {
             }
         }
 
-        go(self)
+        go(normalize::normalize(self))
             .into_iter()
             .flat_map(|op| {
                 // flatten conc to multiple messages
@@ -400,6 +536,12 @@ pub enum QueueError {
     Fatal(#[source] BoxDynError),
     #[error("error while handling message")]
     Retry(#[source] BoxDynError),
+    #[error("error while handling message, retrying after {after:?}")]
+    RetryAfter {
+        after: Duration,
+        #[source]
+        error: BoxDynError,
+    },
 }
 
 impl QueueError {
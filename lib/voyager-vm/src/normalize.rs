@@ -0,0 +1,119 @@
+//! A pure rewrite pass over [`Op`]s, run by the queue on every op before it is enqueued.
+//!
+//! Plugins often build ops out of smaller ops without regard for their final shape, which results
+//! in deeply nested [`Op::Seq`]s and [`Op::Conc`]s, and in structurally identical calls being
+//! queued multiple times (i.e. when the same fetch is requested by multiple events in the same
+//! block). [`normalize`] rewrites an op bottom up with the following rules:
+//!
+//! 1. `seq([a, seq([b, c]), d])` => `seq([a, b, c, d])`
+//! 2. `conc([a, conc([b, c]), d])` => `conc([a, b, c, d])`
+//! 3. `conc([a, b, a])` => `conc([a, b])`, where the siblings are compared by their serialized
+//!    form
+//! 4. `seq([a])` => `a` and `conc([a])` => `a`
+//! 5. `noop` children of a `seq` or `conc` are dropped, and `seq([])`, `conc([])` and
+//!    `void(noop)` => `noop`
+//!
+//! The nested ops of [`Op::Promise`] and [`Op::Void`] are normalized as well, and the
+//! [`queue`](crate::Promise::queue) of a promise is flattened like a `conc`. Duplicate siblings
+//! are never dropped within the queue of a promise however, since the data they resolve to is
+//! aggregated by the callback.
+//!
+//! None of the rules change the ordering guarantees of an op: every op in a `seq` still runs after
+//! all of the ops before it, and the ops of a `conc` remain independent of each other.
+
+use std::collections::{HashSet, VecDeque};
+
+use crate::{Op, Promise, QueueMessage};
+
+/// Normalize `op`, as described in the [module documentation](self).
+///
+/// Normalization is idempotent; `normalize(normalize(op)) == normalize(op)`.
+#[must_use = "normalizing an op has no side effects"]
+pub fn normalize<T: QueueMessage>(op: Op<T>) -> Op<T> {
+    go(op, true)
+}
+
+fn go<T: QueueMessage>(op: Op<T>, dedup: bool) -> Op<T> {
+    match op {
+        Op::Data(_) | Op::Call(_) | Op::Defer { .. } | Op::Noop => op,
+        Op::Seq(seq) => collapse(
+            Op::Seq,
+            flatten(seq, dedup, |op| match op {
+                Op::Seq(seq) => Ok(seq),
+                op => Err(op),
+            }),
+        ),
+        Op::Conc(conc) => {
+            let mut ops = flatten(conc, dedup, |op| match op {
+                Op::Conc(conc) => Ok(conc),
+                op => Err(op),
+            });
+
+            if dedup {
+                dedup_siblings(&mut ops);
+            }
+
+            collapse(Op::Conc, ops)
+        }
+        Op::Promise(Promise {
+            queue,
+            data,
+            receiver,
+        }) => Op::Promise(Promise {
+            queue: flatten(queue, false, |op| match op {
+                Op::Conc(conc) => Ok(conc),
+                op => Err(op),
+            }),
+            data,
+            receiver,
+        }),
+        Op::Void(op) => match go(*op, dedup) {
+            Op::Noop => Op::Noop,
+            op => Op::Void(Box::new(op)),
+        },
+    }
+}
+
+/// Normalize `ops`, splicing the children of any op matched by `nested` into `ops` and dropping
+/// [`Op::Noop`]s. Since the ops are normalized first, the spliced children never need to be
+/// flattened again.
+fn flatten<T: QueueMessage>(
+    ops: VecDeque<Op<T>>,
+    dedup: bool,
+    nested: impl Fn(Op<T>) -> Result<VecDeque<Op<T>>, Op<T>>,
+) -> VecDeque<Op<T>> {
+    let mut flattened = VecDeque::with_capacity(ops.len());
+
+    for op in ops {
+        match nested(go(op, dedup)) {
+            Ok(children) => flattened.extend(children),
+            Err(Op::Noop) => {}
+            Err(op) => flattened.push_back(op),
+        }
+    }
+
+    flattened
+}
+
+/// Drop every op that serializes to the same bytes as a previous op in `ops`.
+fn dedup_siblings<T: QueueMessage>(ops: &mut VecDeque<Op<T>>) {
+    let mut seen = HashSet::with_capacity(ops.len());
+
+    ops.retain(|op| {
+        seen.insert(serde_json::to_vec(op).expect("serialization is infallible; qed;"))
+    });
+}
+
+fn collapse<T: QueueMessage>(
+    wrap: impl FnOnce(VecDeque<Op<T>>) -> Op<T>,
+    mut ops: VecDeque<Op<T>>,
+) -> Op<T> {
+    match ops.len() {
+        0 => Op::Noop,
+        1 => ops.pop_front().expect("length is 1; qed;"),
+        _ => wrap(ops),
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,357 @@
+//! Unit tests for each of the rewrite rules of [`normalize`], and property tests checking that
+//! normalization is idempotent and does not change the behaviour of an op, as observed by a
+//! reference interpreter over [`ToyMessage`].
+//!
+//! The property tests are run with a fixed seed, such that they are deterministic.
+
+use std::collections::{BTreeSet, VecDeque};
+
+use proptest::{
+    collection::vec_deque,
+    prelude::*,
+    test_runner::{Config, RngAlgorithm, TestRng, TestRunner},
+};
+
+use super::*;
+use crate::{call, conc, data, defer, noop, promise, seq, void, CallT, CallbackT, QueueError};
+
+/// Every call `n` resolves to `data(n)`, and every callback resolves to `noop()`.
+enum ToyMessage {}
+
+impl QueueMessage for ToyMessage {
+    type Data = u8;
+    type Call = u8;
+    type Callback = u8;
+
+    type Filter = ();
+
+    type Context = ();
+}
+
+impl CallT<ToyMessage> for u8 {
+    async fn process(self, (): &()) -> Result<Op<ToyMessage>, QueueError> {
+        Ok(data(self))
+    }
+}
+
+impl CallbackT<ToyMessage> for u8 {
+    async fn process(self, (): &(), _: VecDeque<u8>) -> Result<Op<ToyMessage>, QueueError> {
+        Ok(noop())
+    }
+}
+
+#[test]
+fn flatten_seq() {
+    let op = seq::<ToyMessage>([call(1), seq([call(2), seq([call(3)]), call(4)]), call(5)]);
+    assert_eq!(
+        normalize(op),
+        seq([call(1), call(2), call(3), call(4), call(5)])
+    );
+
+    // conc in seq is not flattened
+    let op = seq::<ToyMessage>([call(1), conc([call(2), call(3)])]);
+    assert_eq!(normalize(op.clone()), op);
+}
+
+#[test]
+fn flatten_conc() {
+    let op = conc::<ToyMessage>([call(1), conc([call(2), conc([call(3)]), call(4)]), call(5)]);
+    assert_eq!(
+        normalize(op),
+        conc([call(1), call(2), call(3), call(4), call(5)])
+    );
+
+    // seq in conc is not flattened
+    let op = conc::<ToyMessage>([call(1), seq([call(2), call(3)])]);
+    assert_eq!(normalize(op.clone()), op);
+}
+
+#[test]
+fn dedup_conc_siblings() {
+    let op = conc::<ToyMessage>([call(1), call(2), call(1), data(3), data(3)]);
+    assert_eq!(normalize(op), conc([call(1), call(2), data(3)]));
+
+    // siblings are compared after they are flattened
+    let op = conc::<ToyMessage>([conc([call(1), call(2)]), call(1)]);
+    assert_eq!(normalize(op), conc([call(1), call(2)]));
+
+    let op = conc::<ToyMessage>([seq([call(1), call(2)]), seq([call(1), call(2)])]);
+    assert_eq!(normalize(op), seq([call(1), call(2)]));
+
+    // ...and after they are normalized
+    let op = conc::<ToyMessage>([seq([call(1), seq([call(2)])]), seq([call(1), call(2)])]);
+    assert_eq!(normalize(op), seq([call(1), call(2)]));
+}
+
+#[test]
+fn seq_siblings_are_not_deduped() {
+    let op = seq::<ToyMessage>([call(1), call(1)]);
+    assert_eq!(normalize(op.clone()), op);
+}
+
+#[test]
+fn promise_queue_is_not_deduped() {
+    // the callback receives the data of both calls
+    let op = promise::<ToyMessage>([call(1), call(1)], [], 0);
+    assert_eq!(normalize(op.clone()), op);
+
+    let op = promise::<ToyMessage>([conc([call(1), call(1)])], [], 0);
+    assert_eq!(normalize(op), promise([call(1), call(1)], [], 0));
+
+    let op = promise::<ToyMessage>([seq([conc([call(1), call(1)]), call(2)])], [], 0);
+    assert_eq!(normalize(op.clone()), op);
+
+    // a promise in a conc is deduped as a whole
+    let op = conc::<ToyMessage>([promise([call(1)], [], 0), promise([call(1)], [], 0)]);
+    assert_eq!(normalize(op), promise([call(1)], [], 0));
+}
+
+#[test]
+fn collapse_single_child() {
+    assert_eq!(normalize(seq::<ToyMessage>([call(1)])), call(1));
+    assert_eq!(normalize(conc::<ToyMessage>([call(1)])), call(1));
+    assert_eq!(
+        normalize(conc::<ToyMessage>([seq([conc([seq([call(1)])])])])),
+        call(1)
+    );
+    assert_eq!(normalize(conc::<ToyMessage>([call(1), call(1)])), call(1));
+
+    // a collapsed conc in a seq is flattened into the seq
+    assert_eq!(
+        normalize(seq::<ToyMessage>([
+            call(1),
+            conc([seq([call(2), call(3)])]),
+            call(4)
+        ])),
+        seq([call(1), call(2), call(3), call(4)])
+    );
+}
+
+#[test]
+fn drop_noop() {
+    assert_eq!(normalize(seq::<ToyMessage>([])), noop());
+    assert_eq!(normalize(conc::<ToyMessage>([])), noop());
+    assert_eq!(normalize(seq::<ToyMessage>([noop(), noop()])), noop());
+    assert_eq!(normalize(void::<ToyMessage>(seq([noop()]))), noop());
+    assert_eq!(
+        normalize(conc::<ToyMessage>([
+            noop(),
+            call(1),
+            seq([noop()]),
+            call(2)
+        ])),
+        conc([call(1), call(2)])
+    );
+    assert_eq!(
+        normalize(promise::<ToyMessage>([noop(), call(1)], [], 0)),
+        promise([call(1)], [], 0)
+    );
+
+    // an empty promise still runs its callback
+    let op = promise::<ToyMessage>([], [], 0);
+    assert_eq!(normalize(op.clone()), op);
+}
+
+#[test]
+fn nested_ops_are_normalized() {
+    assert_eq!(
+        normalize(void::<ToyMessage>(conc([
+            call(1),
+            conc([call(1), call(2)])
+        ]))),
+        void(conc([call(1), call(2)]))
+    );
+    assert_eq!(
+        normalize(promise::<ToyMessage>(
+            [seq([call(1), seq([call(2)])]), conc([conc([call(3)])])],
+            [4],
+            0
+        )),
+        promise([seq([call(1), call(2)]), call(3)], [4], 0)
+    );
+}
+
+#[test]
+fn leaves_are_unchanged() {
+    for op in [data::<ToyMessage>(1), call(1), defer(1), noop()] {
+        assert_eq!(normalize(op.clone()), op);
+    }
+}
+
+#[test]
+fn queue_normalizes_before_splitting() {
+    // top level conc is deduped before it is split into individual ops
+    let op = conc::<ToyMessage>([call(1), conc([call(2), call(1)]), call(2)]);
+    assert_eq!(op.normalize(), vec![call(1), call(2)]);
+}
+
+/// An effect performed by an op, as observed by [`run`].
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Effect {
+    Data(u8),
+    Call(u8),
+    Defer(u64),
+    /// A callback, along with the data it was called with in sorted order.
+    Callback(u8, Vec<u8>),
+}
+
+/// The behaviour of an op under the reference interpreter.
+#[derive(Debug, Default, PartialEq)]
+struct Run {
+    /// All of the distinct effects that the op performs.
+    effects: BTreeSet<Effect>,
+    /// `(a, b)` if an occurrence of `a` is guaranteed to run before an occurrence of `b`.
+    happens_before: BTreeSet<(Effect, Effect)>,
+    /// The data that the op resolves to, in no particular order.
+    data: Vec<u8>,
+}
+
+impl Run {
+    fn effect(effect: Effect) -> Self {
+        Self {
+            effects: [effect].into(),
+            ..Self::default()
+        }
+    }
+
+    fn merge(mut self, other: Self) -> Self {
+        self.effects.extend(other.effects);
+        self.happens_before.extend(other.happens_before);
+        self.data.extend(other.data);
+        self
+    }
+
+    /// The data resolved by the op, ignoring duplicates. Dropping a duplicate sibling resolves the
+    /// same data fewer times, which is only observable within a promise.
+    fn distinct_data(&self) -> BTreeSet<u8> {
+        self.data.iter().copied().collect()
+    }
+}
+
+/// Interpret `op`, following the semantics of [`Op::process`]: ops in a seq run strictly after
+/// all previous ops in the seq, ops in a conc (and the queue of a promise) run independently of
+/// each other, and a callback runs after its entire queue with the aggregated data.
+fn run(op: &Op<ToyMessage>) -> Run {
+    match op {
+        Op::Data(data) => Run {
+            data: vec![*data],
+            ..Run::effect(Effect::Data(*data))
+        },
+        Op::Call(call) => Run {
+            data: vec![*call],
+            ..Run::effect(Effect::Call(*call))
+        },
+        Op::Defer { until } => Run::effect(Effect::Defer(*until)),
+        Op::Seq(seq) => seq
+            .iter()
+            .map(run)
+            .fold(Run::default(), |mut before, next| {
+                for a in &before.effects {
+                    for b in &next.effects {
+                        before.happens_before.insert((a.clone(), b.clone()));
+                    }
+                }
+                before.merge(next)
+            }),
+        Op::Conc(conc) => conc.iter().map(run).fold(Run::default(), Run::merge),
+        Op::Promise(Promise {
+            queue,
+            data,
+            receiver,
+        }) => {
+            let queue = queue.iter().map(run).fold(Run::default(), Run::merge);
+
+            let mut aggregated = data.iter().copied().chain(queue.data).collect::<Vec<_>>();
+            aggregated.sort_unstable();
+
+            let callback = Effect::Callback(*receiver, aggregated);
+
+            Run {
+                happens_before: queue
+                    .happens_before
+                    .into_iter()
+                    .chain(
+                        queue
+                            .effects
+                            .iter()
+                            .map(|effect| (effect.clone(), callback.clone())),
+                    )
+                    .collect(),
+                effects: queue.effects.into_iter().chain([callback]).collect(),
+                data: vec![],
+            }
+        }
+        Op::Void(op) => Run {
+            data: vec![],
+            ..run(op)
+        },
+        Op::Noop => Run::default(),
+    }
+}
+
+fn runner() -> TestRunner {
+    TestRunner::new_with_rng(
+        Config {
+            cases: 1024,
+            failure_persistence: None,
+            ..Config::default()
+        },
+        TestRng::deterministic_rng(RngAlgorithm::ChaCha),
+    )
+}
+
+/// Arbitrary ops with a small domain of leaves, such that duplicate siblings are common.
+fn arb_op() -> impl Strategy<Value = Op<ToyMessage>> {
+    let leaf = prop_oneof![
+        (0..3_u8).prop_map(Op::Data),
+        (0..3_u8).prop_map(Op::Call),
+        (0..2_u64).prop_map(|until| Op::Defer { until }),
+        Just(Op::Noop),
+    ];
+
+    leaf.prop_recursive(5, 64, 4, |inner| {
+        prop_oneof![
+            vec_deque(inner.clone(), 0..4).prop_map(Op::Seq),
+            vec_deque(inner.clone(), 0..4).prop_map(Op::Conc),
+            (
+                vec_deque(inner.clone(), 0..4),
+                vec_deque(0..3_u8, 0..2),
+                0..2_u8
+            )
+                .prop_map(|(queue, data, receiver)| Op::Promise(Promise {
+                    queue,
+                    data,
+                    receiver
+                })),
+            inner.prop_map(|op| Op::Void(Box::new(op))),
+        ]
+    })
+}
+
+#[test]
+fn normalize_is_idempotent() {
+    runner()
+        .run(&arb_op(), |op| {
+            let normalized = normalize(op);
+            prop_assert_eq!(normalize(normalized.clone()), normalized);
+
+            Ok(())
+        })
+        .unwrap();
+}
+
+#[test]
+fn normalize_preserves_semantics() {
+    runner()
+        .run(&arb_op(), |op| {
+            let expected = run(&op);
+            let normalized = run(&normalize(op));
+
+            prop_assert_eq!(&normalized.effects, &expected.effects);
+            prop_assert_eq!(&normalized.happens_before, &expected.happens_before);
+            prop_assert_eq!(normalized.distinct_data(), expected.distinct_data());
+
+            Ok(())
+        })
+        .unwrap();
+}
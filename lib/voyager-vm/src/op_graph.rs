@@ -0,0 +1,378 @@
+//! Rendering of op trees for debugging.
+//!
+//! Ops in the queue are deeply nested, and the JSON representation of even a small op is hard to
+//! follow. [`OpNode`] is a simplified tree of an op, with a short [`Summary`] of every message
+//! instead of the full message, which can be serialized as JSON or rendered as a
+//! [Graphviz](https://graphviz.org/) DOT graph with [`OpNode::to_dot`].
+
+use std::{collections::BTreeMap, fmt::Display};
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::{Op, Promise, QueueMessage};
+
+/// The fields of a message that are included in its [`Summary`] by default, if they are scalars.
+pub const SUMMARY_FIELDS: &[&str] = &[
+    "plugin",
+    "chain_id",
+    "counterparty_chain_id",
+    "ibc_spec_id",
+    "client_id",
+    "connection_id",
+    "channel_id",
+    "port_id",
+    "height",
+    "sequence",
+];
+
+/// A short description of a message, used as the label of its node in an [`OpNode`] tree.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Summary {
+    /// The type of the message. For nested enums, this is the type of every level joined with
+    /// `/`, i.e. `plugin/fetch_block`.
+    #[serde(rename = "type")]
+    pub ty: String,
+    /// The identifying fields of the message, such as the chain id.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub fields: BTreeMap<String, String>,
+}
+
+impl Summary {
+    #[must_use]
+    pub fn new(ty: impl Into<String>) -> Self {
+        Self {
+            ty: ty.into(),
+            fields: BTreeMap::new(),
+        }
+    }
+
+    #[must_use]
+    pub fn with_field(mut self, key: impl Into<String>, value: impl Display) -> Self {
+        self.fields.insert(key.into(), value.to_string());
+        self
+    }
+
+    /// Summarize a message from its JSON representation.
+    ///
+    /// The type is read from the `@type` tag of every level of adjacently tagged enums, and any of
+    /// the [`SUMMARY_FIELDS`] found along the way are included.
+    #[must_use]
+    pub fn from_value(mut value: &Value) -> Self {
+        let mut types = vec![];
+        let mut fields = BTreeMap::new();
+
+        loop {
+            if let Some(object) = value.as_object() {
+                for key in SUMMARY_FIELDS {
+                    match object.get(*key) {
+                        Some(Value::String(s)) => {
+                            fields.insert((*key).to_owned(), s.clone());
+                        }
+                        Some(v @ (Value::Number(_) | Value::Bool(_))) => {
+                            fields.insert((*key).to_owned(), v.to_string());
+                        }
+                        _ => {}
+                    }
+                }
+            }
+
+            match type_tag(value) {
+                Some(ty) => {
+                    types.push(ty);
+                    value = value.get("@value").unwrap_or(&Value::Null);
+                }
+                None => break,
+            }
+        }
+
+        Self {
+            ty: if types.is_empty() {
+                "<unknown>".to_owned()
+            } else {
+                types.join("/")
+            },
+            fields,
+        }
+    }
+
+    /// The type followed by every field on its own line.
+    #[must_use]
+    pub fn label(&self) -> String {
+        let mut label = self.ty.clone();
+
+        for (key, value) in &self.fields {
+            label.push('\n');
+            label.push_str(key);
+            label.push_str(": ");
+            label.push_str(value);
+        }
+
+        label
+    }
+}
+
+/// The `@type` tag of an adjacently tagged enum.
+#[must_use]
+pub fn type_tag(value: &Value) -> Option<&str> {
+    value.as_object()?.get("@type")?.as_str()
+}
+
+/// Messages that can be summarized in an [`OpNode`] tree.
+///
+/// The default implementation summarizes the serialized message with [`Summary::from_value`].
+pub trait Summarize: Serialize {
+    fn summarize(&self) -> Summary {
+        Summary::from_value(
+            &serde_json::to_value(self).expect("serialization of messages is infallible; qed;"),
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OpKind {
+    Data,
+    Call,
+    Defer,
+    Seq,
+    Conc,
+    Promise,
+    Void,
+    Noop,
+}
+
+impl OpKind {
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            OpKind::Data => "data",
+            OpKind::Call => "call",
+            OpKind::Defer => "defer",
+            OpKind::Seq => "seq",
+            OpKind::Conc => "conc",
+            OpKind::Promise => "promise",
+            OpKind::Void => "void",
+            OpKind::Noop => "noop",
+        }
+    }
+}
+
+/// A simplified tree of an [`Op`].
+///
+/// For a [`Promise`], the summary is the summary of the receiver and the children are the queue.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OpNode {
+    pub kind: OpKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary: Option<Summary>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<OpNode>,
+    /// The data that has already been resolved, for a [`Promise`].
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub data: Vec<Summary>,
+}
+
+impl OpNode {
+    #[must_use]
+    pub fn new<T: QueueMessage>(op: &Op<T>) -> Self
+    where
+        T::Data: Summarize,
+        T::Call: Summarize,
+        T::Callback: Summarize,
+    {
+        let node = |kind, summary, children| Self {
+            kind,
+            summary,
+            children,
+            data: vec![],
+        };
+
+        match op {
+            Op::Data(data) => node(OpKind::Data, Some(data.summarize()), vec![]),
+            Op::Call(call) => node(OpKind::Call, Some(call.summarize()), vec![]),
+            Op::Defer { until } => node(
+                OpKind::Defer,
+                Some(Summary::new("defer").with_field("until", until)),
+                vec![],
+            ),
+            Op::Seq(seq) => node(OpKind::Seq, None, seq.iter().map(Self::new).collect()),
+            Op::Conc(conc) => node(OpKind::Conc, None, conc.iter().map(Self::new).collect()),
+            Op::Promise(Promise {
+                queue,
+                data,
+                receiver,
+            }) => Self {
+                data: data.iter().map(Summarize::summarize).collect(),
+                ..node(
+                    OpKind::Promise,
+                    Some(receiver.summarize()),
+                    queue.iter().map(Self::new).collect(),
+                )
+            },
+            Op::Void(op) => node(OpKind::Void, None, vec![Self::new(op)]),
+            Op::Noop => node(OpKind::Noop, None, vec![]),
+        }
+    }
+
+    /// Render this tree as a Graphviz DOT graph.
+    ///
+    /// The children of a `seq` are connected with solid edges labelled with their position, and
+    /// are laid out in order. The children of a `conc` and the queue of a `promise` are connected
+    /// with dashed edges, since they are executed in parallel. The resolved data of a `promise` is
+    /// connected with dotted edges.
+    #[must_use]
+    pub fn to_dot(&self) -> String {
+        let mut dot = "digraph op {\n    node [fontname=\"monospace\"];\n".to_owned();
+        self.write_dot(&mut dot, &mut 0);
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Write this node and all of its children, returning the id of this node.
+    fn write_dot(&self, dot: &mut String, next_id: &mut usize) -> usize {
+        let id = *next_id;
+        *next_id += 1;
+
+        let label = match &self.summary {
+            // i.e. `defer`, the type is already in the kind
+            Some(summary) if summary.ty == self.kind.as_str() => summary.label(),
+            Some(summary) => format!("{}\n{}", self.kind.as_str(), summary.label()),
+            None => self.kind.as_str().to_owned(),
+        };
+
+        let attrs = match self.kind {
+            OpKind::Data => ", shape=note",
+            OpKind::Call => ", shape=box",
+            OpKind::Defer => ", shape=octagon",
+            OpKind::Promise => ", shape=box, style=rounded",
+            OpKind::Seq => ", shape=oval, ordering=out",
+            OpKind::Conc | OpKind::Void | OpKind::Noop => ", shape=oval",
+        };
+
+        dot.push_str(&format!(
+            "    n{id} [label=\"{}\"{attrs}];\n",
+            escape(&label)
+        ));
+
+        for (idx, child) in self.children.iter().enumerate() {
+            let child_id = child.write_dot(dot, next_id);
+
+            let edge_attrs = match self.kind {
+                OpKind::Seq => format!(" [label=\"{}\"]", idx + 1),
+                OpKind::Conc | OpKind::Promise => " [style=dashed]".to_owned(),
+                _ => String::new(),
+            };
+
+            dot.push_str(&format!("    n{id} -> n{child_id}{edge_attrs};\n"));
+        }
+
+        for data in &self.data {
+            let data_id = *next_id;
+            *next_id += 1;
+
+            dot.push_str(&format!(
+                "    n{data_id} [label=\"data\\n{}\", shape=note];\n",
+                escape(&data.label())
+            ));
+            dot.push_str(&format!("    n{id} -> n{data_id} [style=dotted];\n"));
+        }
+
+        id
+    }
+}
+
+fn escape(label: &str) -> String {
+    label
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        call, conc, data, defer, noop, promise, seq,
+        tests::utils::{
+            BuildPrintAbc, DataA, DataB, FetchA, FetchB, FetchC, SimpleAggregate, SimpleCall,
+            SimpleData, SimpleMessage,
+        },
+        void,
+    };
+
+    impl Summarize for SimpleData {}
+    impl Summarize for SimpleCall {}
+    impl Summarize for SimpleAggregate {}
+
+    #[test]
+    fn summary_from_value() {
+        let value = json!({
+            "@type": "plugin",
+            "@value": {
+                "plugin": "voyager-event-source-cosmos-sdk",
+                "message": {
+                    "@type": "fetch_blocks",
+                    "@value": {
+                        "chain_id": "union-1",
+                        "height": 100,
+                        "nested": { "chain_id": "not included" }
+                    }
+                }
+            }
+        });
+
+        assert_eq!(
+            Summary::from_value(&value),
+            Summary::new("plugin").with_field("plugin", "voyager-event-source-cosmos-sdk")
+        );
+
+        assert_eq!(
+            Summary::from_value(&value["@value"]["message"]),
+            Summary::new("fetch_blocks")
+                .with_field("chain_id", "union-1")
+                .with_field("height", 100)
+        );
+
+        assert_eq!(Summary::from_value(&json!(1)).ty, "<unknown>");
+    }
+
+    #[test]
+    fn json_tree() {
+        let op = seq::<SimpleMessage>([call(FetchA {}), void(noop())]);
+
+        assert_eq!(
+            serde_json::to_value(OpNode::new(&op)).unwrap(),
+            json!({
+                "kind": "seq",
+                "children": [
+                    { "kind": "call", "summary": { "type": "a" } },
+                    { "kind": "void", "children": [{ "kind": "noop" }] },
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn dot_snapshot() {
+        let op = seq::<SimpleMessage>([
+            call(FetchA {}),
+            conc([
+                promise(
+                    [call(FetchB {}), call(FetchC {})],
+                    [SimpleData::from(DataA {})],
+                    BuildPrintAbc {},
+                ),
+                seq([defer(10), data(DataB {})]),
+            ]),
+            void(noop()),
+        ]);
+
+        assert_eq!(
+            OpNode::new(&op).to_dot(),
+            include_str!("tests/op_graph.dot")
+        );
+    }
+}
@@ -29,3 +29,82 @@ pub struct PassResult<T: QueueMessage> {
     /// on these [`Op`]s, and they will be requeued as "ready" in the queue.
     pub ready: Vec<(Vec<usize>, Op<T>)>,
 }
+
+/// What a pass does with a single [`Op`], as returned by the closure passed to
+/// [`PassResult::map_claimed`].
+#[derive(DebugNoBound, CloneNoBound)]
+pub enum Claim<T: QueueMessage> {
+    /// The op is complete, and will be requeued as ready.
+    Ready(Op<T>),
+    /// The op is not claimed by this pass, and will not be emitted.
+    Unclaimed,
+    /// The op is to be optimized further, under the provided tag.
+    OptimizeFurther(Op<T>, String),
+}
+
+impl<T: QueueMessage> PassResult<T> {
+    /// Return all of `ops` as ready, unchanged and in their original order.
+    pub fn passthrough(ops: Vec<Op<T>>) -> Self {
+        Self::map_claimed(ops, Claim::Ready)
+    }
+
+    /// Map each of `ops` individually, keeping track of the original index of each op.
+    ///
+    /// Since the parent of every emitted op is the op it was mapped from, it is not possible to
+    /// emit an op without a parent, or with a parent that was not in the input.
+    pub fn map_claimed(ops: Vec<Op<T>>, mut f: impl FnMut(Op<T>) -> Claim<T>) -> Self {
+        Self::try_map_claimed(ops, |op| Ok::<_, std::convert::Infallible>(f(op)))
+            .unwrap_or_else(|never| match never {})
+    }
+
+    /// Fallible version of [`Self::map_claimed`]. The first error returned by `f` is returned.
+    pub fn try_map_claimed<E>(
+        ops: Vec<Op<T>>,
+        mut f: impl FnMut(Op<T>) -> Result<Claim<T>, E>,
+    ) -> Result<Self, E> {
+        let len = ops.len();
+
+        let mut result = Self::default();
+
+        for (idx, op) in ops.into_iter().enumerate() {
+            match f(op)? {
+                Claim::Ready(op) => result.ready.push((vec![idx], op)),
+                Claim::Unclaimed => {}
+                Claim::OptimizeFurther(op, tag) => {
+                    result.optimize_further.push((vec![idx], op, tag))
+                }
+            }
+        }
+
+        result.debug_assert_parents(len);
+
+        Ok(result)
+    }
+
+    /// Assert that every parent index in this result refers to one of the `input_len` input ops,
+    /// and that every input op is the parent of at most one output op across both `ready` and
+    /// `optimize_further`.
+    ///
+    /// This is a no-op in release builds.
+    #[track_caller]
+    pub fn debug_assert_parents(&self, input_len: usize) {
+        if cfg!(debug_assertions) {
+            let mut seen = vec![false; input_len];
+
+            for idx in self.ready.iter().flat_map(|(parents, _)| parents).chain(
+                self.optimize_further
+                    .iter()
+                    .flat_map(|(parents, _, _)| parents),
+            ) {
+                assert!(
+                    *idx < input_len,
+                    "parent index {idx} is out of bounds for {input_len} input ops"
+                );
+                assert!(
+                    !std::mem::replace(&mut seen[*idx], true),
+                    "parent index {idx} appears more than once"
+                );
+            }
+        }
+    }
+}
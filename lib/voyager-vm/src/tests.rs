@@ -1,9 +1,13 @@
 use macros::model;
 
 use crate::{
-    call, conc, data, defer, noop, now, promise, seq,
+    call, conc, data, defer,
+    filter::{route, FilterResult, InterestFilter, Routed},
+    noop, now,
+    pass::{Claim, PassResult},
+    promise, seq,
     tests::utils::{BuildPrintAbc, DataA, DataB, DataC, FetchA, FetchB, PrintAbc, SimpleMessage},
-    CallT, CallbackT, Op, QueueError, QueueMessage, VecDeque,
+    wire, CallT, CallbackT, Op, QueueError, QueueMessage, VecDeque,
 };
 
 pub mod utils;
@@ -255,14 +259,9 @@ fn seq_conc_conc() {
     ]);
 
     let expected_output = vec![seq::<SimpleMessage>([
-        conc([
-            promise([], [], BuildPrintAbc {}),
-            promise([], [], BuildPrintAbc {}),
-        ]),
-        conc([
-            promise([], [], BuildPrintAbc {}),
-            promise([], [], BuildPrintAbc {}),
-        ]),
+        // identical siblings of a conc are deduplicated
+        promise([], [], BuildPrintAbc {}),
+        promise([], [], BuildPrintAbc {}),
         conc([
             seq([call(FetchA {}), defer(now() + 10)]),
             seq([call(FetchB {}), defer(now() + 10)]),
@@ -283,3 +282,210 @@ fn seq_conc_conc() {
 
     assert_eq!(op.normalize(), expected_output);
 }
+
+/// Deterministic xorshift rng, so that the random claim patterns below are reproducible.
+fn xorshift(state: &mut u64) -> u64 {
+    *state ^= *state << 13;
+    *state ^= *state >> 7;
+    *state ^= *state << 17;
+    *state
+}
+
+#[test]
+fn pass_result_passthrough() {
+    let ops = (0..5).map(defer::<UnitMessage>).collect::<Vec<_>>();
+
+    let res = PassResult::passthrough(ops.clone());
+
+    assert!(res.optimize_further.is_empty());
+    assert_eq!(
+        res.ready,
+        ops.into_iter()
+            .enumerate()
+            .map(|(idx, op)| (vec![idx], op))
+            .collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn pass_result_map_claimed_preserves_indices() {
+    for seed in 1..=500_u64 {
+        let mut rng = seed;
+
+        let len = (xorshift(&mut rng) % 32) as usize;
+        // the op at index i is `defer(i)`, so the op identifies its original index
+        let ops = (0..len as u64)
+            .map(defer::<UnitMessage>)
+            .collect::<Vec<_>>();
+        let claims = (0..len).map(|_| xorshift(&mut rng) % 3).collect::<Vec<_>>();
+
+        let mut i = 0;
+        let res = PassResult::map_claimed(ops, |op| {
+            let claim = match claims[i] {
+                0 => Claim::Ready(op),
+                1 => Claim::Unclaimed,
+                _ => Claim::OptimizeFurther(op, "tag".to_owned()),
+            };
+            i += 1;
+            claim
+        });
+
+        res.debug_assert_parents(len);
+
+        let expected = |claim| {
+            claims
+                .iter()
+                .enumerate()
+                .filter(|(_, c)| **c == claim)
+                .map(|(idx, _)| idx)
+                .collect::<Vec<_>>()
+        };
+
+        // every ready op keeps its original index, in the original order
+        assert_eq!(
+            res.ready
+                .iter()
+                .map(|(parents, op)| {
+                    assert_eq!(op, &defer(parents[0] as u64));
+                    parents.clone()
+                })
+                .collect::<Vec<_>>(),
+            expected(0)
+                .into_iter()
+                .map(|idx| vec![idx])
+                .collect::<Vec<_>>(),
+            "seed {seed}"
+        );
+
+        // as does every op to be optimized further
+        assert_eq!(
+            res.optimize_further
+                .iter()
+                .map(|(parents, op, tag)| {
+                    assert_eq!(op, &defer(parents[0] as u64));
+                    assert_eq!(tag, "tag");
+                    parents.clone()
+                })
+                .collect::<Vec<_>>(),
+            expected(2)
+                .into_iter()
+                .map(|idx| vec![idx])
+                .collect::<Vec<_>>(),
+            "seed {seed}"
+        );
+    }
+}
+
+#[test]
+fn pass_result_try_map_claimed_returns_first_error() {
+    let ops = (0..5).map(defer::<UnitMessage>).collect::<Vec<_>>();
+
+    let res = PassResult::try_map_claimed(ops, |op| match op {
+        Op::Defer { until } if until >= 2 => Err(until),
+        op => Ok(Claim::Ready(op)),
+    });
+
+    assert_eq!(res.unwrap_err(), 2);
+}
+
+#[test]
+#[should_panic = "parent index 1 appears more than once"]
+fn pass_result_duplicate_parent() {
+    PassResult::<UnitMessage> {
+        optimize_further: vec![(vec![1], noop(), "tag".to_owned())],
+        ready: vec![(vec![0, 1], noop())],
+    }
+    .debug_assert_parents(2);
+}
+
+#[test]
+#[should_panic = "parent index 2 is out of bounds for 2 input ops"]
+fn pass_result_out_of_bounds_parent() {
+    PassResult::<UnitMessage> {
+        optimize_further: vec![],
+        ready: vec![(vec![2], noop())],
+    }
+    .debug_assert_parents(2);
+}
+
+#[test]
+fn wire_format_envelope() {
+    let op = seq::<UnitMessage>([defer(1), conc([data(()), noop()])]);
+
+    let encoded = wire::encode_op(&op).unwrap();
+
+    assert_eq!(encoded["v"], wire::WIRE_VERSION);
+    assert_eq!(wire::decode_op::<UnitMessage>(encoded.clone()).unwrap(), op);
+
+    // the envelope is only added at the top level
+    assert!(encoded["@value"][0].get("v").is_none());
+
+    // ops from before the version was introduced are version 0
+    let unversioned = serde_json::to_value(&op).unwrap();
+    assert!(unversioned.get("v").is_none());
+    assert_eq!(wire::decode_op::<UnitMessage>(unversioned).unwrap(), op);
+
+    let versioned = serde_json::to_string(&wire::VersionedOp(op.clone())).unwrap();
+    assert_eq!(
+        serde_json::from_str::<wire::VersionedOp<UnitMessage>>(&versioned)
+            .unwrap()
+            .0,
+        op
+    );
+}
+
+#[test]
+fn wire_format_unsupported_version() {
+    let mut encoded = wire::encode_op(&defer::<UnitMessage>(1)).unwrap();
+
+    encoded["v"] = (wire::WIRE_VERSION + 1).into();
+    assert!(matches!(
+        wire::decode_op::<UnitMessage>(encoded.clone()),
+        Err(wire::DecodeOpError::UnsupportedVersion(version)) if version == wire::WIRE_VERSION + 1
+    ));
+
+    encoded["v"] = "1".into();
+    assert!(matches!(
+        wire::decode_op::<UnitMessage>(encoded),
+        Err(wire::DecodeOpError::InvalidVersion(_))
+    ));
+}
+
+#[test]
+fn route_copies_observed_ops() {
+    /// Claims `defer(1)` and observes all defers.
+    struct DeferFilter;
+
+    impl InterestFilter<UnitMessage> for DeferFilter {
+        fn check_interest<'a>(&'a self, op: &Op<UnitMessage>) -> FilterResult<'a> {
+            match op {
+                Op::Defer { until } => FilterResult {
+                    interest: (*until == 1).then_some("claim"),
+                    observers: vec!["observe-a", "observe-b"],
+                },
+                _ => FilterResult::NO_INTEREST,
+            }
+        }
+    }
+
+    let routed = route(&DeferFilter, [defer(1), defer(2), noop()])
+        .into_iter()
+        .map(|routed| match routed {
+            Routed::Optimize(op, tag) => (op, Some(tag)),
+            Routed::Ready(op) => (op, None),
+        })
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        routed,
+        [
+            (defer(1), Some("observe-a")),
+            (defer(1), Some("observe-b")),
+            (defer(1), Some("claim")),
+            (defer(2), Some("observe-a")),
+            (defer(2), Some("observe-b")),
+            (defer(2), None),
+            (noop(), None),
+        ]
+    );
+}
@@ -0,0 +1,146 @@
+//! Versioning of the serialized form of queued [`Op`]s.
+//!
+//! Ops are persisted by the queue and read back by whichever release of voyager is running at the
+//! time, which is not necessarily the release that wrote them. The serialized form of an op
+//! depends on the serde implementations of every type contained in it, some of which have changed
+//! shape between releases, which would otherwise cause ops queued before an upgrade to fail to
+//! decode after it. To detect this, top level ops are written with the version of the wire format
+//! in the `v` field of the op envelope:
+//!
+//! ```json
+//! { "@type": "call", "@value": { ... }, "v": 1 }
+//! ```
+//!
+//! Ops without a version were written before the field was introduced, and are version `0`. When
+//! an op of an older version is read, [`QueueMessage::upgrade_op`] is called to rewrite it into
+//! the current shape before it is deserialized.
+//!
+//! Only the top level op carries the version, since nested ops are always written together with
+//! the op containing them. All objects are written with their keys sorted, such that the encoding
+//! of an op does not depend on the field order of the types in it.
+
+use serde::{de, ser, Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{Map, Value};
+
+use crate::{Op, QueueMessage};
+
+/// The current version of the wire format.
+///
+/// This must be bumped whenever the serialized form of an existing op changes, along with an
+/// adapter for the previous version in [`QueueMessage::upgrade_op`].
+pub const WIRE_VERSION: u32 = 1;
+
+/// The field of the op envelope containing the wire format version.
+pub const VERSION_FIELD: &str = "v";
+
+#[derive(Debug, thiserror::Error)]
+pub enum DecodeOpError {
+    #[error("op is not an object")]
+    NotAnObject,
+    #[error("invalid wire format version {0}")]
+    InvalidVersion(Value),
+    #[error(
+        "op was written with wire format version {0}, but only versions up to \
+        {WIRE_VERSION} are supported"
+    )]
+    UnsupportedVersion(u32),
+    #[error("error decoding op of wire format version {version}")]
+    Decode {
+        version: u32,
+        #[source]
+        source: serde_json::Error,
+    },
+}
+
+/// Encode `op` with the current [`WIRE_VERSION`].
+///
+/// # Errors
+///
+/// Returns an error if `op` can't be serialized as JSON.
+pub fn encode_op<T: QueueMessage>(op: &Op<T>) -> Result<Value, serde_json::Error> {
+    let mut value = serde_json::to_value(op)?;
+
+    sort_keys(&mut value);
+
+    value
+        .as_object_mut()
+        .expect("ops are serialized as objects; qed;")
+        .insert(VERSION_FIELD.to_owned(), WIRE_VERSION.into());
+
+    Ok(value)
+}
+
+/// Decode an op of any supported wire format version, upgrading it to the current version first
+/// if required.
+///
+/// # Errors
+///
+/// Returns an error if the version is invalid or unsupported, or if the (upgraded) op can't be
+/// deserialized.
+pub fn decode_op<T: QueueMessage>(mut value: Value) -> Result<Op<T>, DecodeOpError> {
+    let version = match value
+        .as_object_mut()
+        .ok_or(DecodeOpError::NotAnObject)?
+        .remove(VERSION_FIELD)
+    {
+        None => 0,
+        Some(version) => version
+            .as_u64()
+            .and_then(|version| u32::try_from(version).ok())
+            .ok_or(DecodeOpError::InvalidVersion(version))?,
+    };
+
+    if version > WIRE_VERSION {
+        return Err(DecodeOpError::UnsupportedVersion(version));
+    }
+
+    if version < WIRE_VERSION {
+        T::upgrade_op(&mut value, version);
+    }
+
+    serde_json::from_value(value).map_err(|source| DecodeOpError::Decode { version, source })
+}
+
+/// An [`Op`] that is (de)serialized with [`encode_op`] and [`decode_op`], for use at the boundary
+/// of the queue.
+#[derive(
+    ::frame_support_procedural::DebugNoBound,
+    ::frame_support_procedural::CloneNoBound,
+    ::frame_support_procedural::PartialEqNoBound,
+)]
+pub struct VersionedOp<T: QueueMessage>(pub Op<T>);
+
+impl<T: QueueMessage> Serialize for VersionedOp<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        encode_op(&self.0)
+            .map_err(ser::Error::custom)?
+            .serialize(serializer)
+    }
+}
+
+impl<'de, T: QueueMessage> Deserialize<'de> for VersionedOp<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        decode_op(Value::deserialize(deserializer)?)
+            .map(Self)
+            .map_err(de::Error::custom)
+    }
+}
+
+fn sort_keys(value: &mut Value) {
+    match value {
+        Value::Array(values) => values.iter_mut().for_each(sort_keys),
+        Value::Object(map) => {
+            let mut entries = std::mem::take(map).into_iter().collect::<Vec<_>>();
+            entries.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+
+            *map = entries
+                .into_iter()
+                .map(|(key, mut value)| {
+                    sort_keys(&mut value);
+                    (key, value)
+                })
+                .collect::<Map<_, _>>();
+        }
+        _ => {}
+    }
+}
@@ -40,6 +40,9 @@ type ClientState struct {
 	FrozenHeight types.Height `protobuf:"bytes,5,opt,name=frozen_height,json=frozenHeight,proto3" json:"frozen_height"`
 	// Latest height the client was updated to
 	LatestHeight types.Height `protobuf:"bytes,6,opt,name=latest_height,json=latestHeight,proto3" json:"latest_height"`
+	// Hash of the groth16 verifying key that proofs for this client must be
+	// verified with. Empty if any verifying key is accepted.
+	ZkVerifyingKeyHash []byte `protobuf:"bytes,7,opt,name=zk_verifying_key_hash,json=zkVerifyingKeyHash,proto3" json:"zk_verifying_key_hash,omitempty"`
 }
 
 func (m *ClientState) Reset()         { *m = ClientState{} }
@@ -241,45 +244,46 @@ func init() {
 }
 
 var fileDescriptor_6e4c33c744877a4e = []byte{
-	// 609 bytes of a gzipped FileDescriptorProto
-	0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0xff, 0x9c, 0x54, 0x3f, 0x6f, 0xd4, 0x3e,
-	0x18, 0xbe, 0xf4, 0x77, 0xbf, 0xfe, 0xf1, 0xdd, 0xb5, 0x28, 0xea, 0x70, 0x54, 0x55, 0x5a, 0xdd,
-	0x40, 0x0b, 0x12, 0x49, 0x13, 0x36, 0xc4, 0xd2, 0x1e, 0x95, 0x8a, 0x50, 0xa5, 0x2a, 0x95, 0x18,
-	0x58, 0x22, 0x27, 0x79, 0x9b, 0x58, 0x97, 0xd8, 0x91, 0xed, 0x0b, 0xa5, 0x9f, 0x80, 0x91, 0x0f,
-	0xc0, 0xc0, 0xc0, 0x87, 0x60, 0x66, 0xea, 0xd8, 0x91, 0x09, 0xa1, 0xde, 0x17, 0x41, 0xb1, 0x9d,
-	0xbb, 0xdb, 0x8a, 0x58, 0x22, 0xfb, 0x79, 0x9f, 0xe7, 0xb1, 0xf3, 0xd8, 0xaf, 0x91, 0x3f, 0xa5,
-	0x84, 0x51, 0x8f, 0xc4, 0x89, 0x57, 0x90, 0x2c, 0x97, 0x49, 0x41, 0x80, 0x4a, 0xe1, 0x25, 0xac,
-	0x04, 0x19, 0x17, 0xc2, 0xab, 0xfd, 0xf9, 0xd8, 0xad, 0x38, 0x93, 0xcc, 0x1e, 0x29, 0x89, 0x4b,
-	0xe2, 0xc4, 0x5d, 0x96, 0xb8, 0x73, 0x5a, 0xed, 0xef, 0xec, 0x4a, 0xa0, 0x29, 0xf0, 0x92, 0x50,
-	0xe9, 0xc9, 0x8f, 0x15, 0x08, 0xfd, 0xd5, 0x0e, 0x3b, 0x7b, 0xcd, 0x72, 0x09, 0xe3, 0xe0, 0x69,
-	0xad, 0x5a, 0x44, 0x8d, 0x0c, 0xe1, 0x60, 0x41, 0x60, 0x65, 0x49, 0x64, 0xd9, 0x92, 0xe6, 0x33,
-	0x43, 0xdc, 0xce, 0x58, 0xc6, 0xd4, 0xd0, 0x6b, 0x46, 0x1a, 0x1d, 0x7d, 0x5f, 0x41, 0xbd, 0xb1,
-	0xf2, 0xbb, 0x94, 0x58, 0x82, 0xfd, 0x18, 0xad, 0x27, 0x39, 0x26, 0x34, 0x22, 0xe9, 0xd0, 0xda,
-	0xb7, 0x0e, 0x37, 0xc2, 0x35, 0x35, 0x7f, 0x93, 0xda, 0x07, 0x68, 0x4b, 0xf2, 0xa9, 0x90, 0x84,
-	0x66, 0x51, 0x05, 0x9c, 0xb0, 0x74, 0xb8, 0xb2, 0x6f, 0x1d, 0x76, 0xc3, 0xcd, 0x16, 0xbe, 0x50,
-	0xa8, 0xfd, 0x14, 0x3d, 0x9a, 0xd2, 0x98, 0xd1, 0x74, 0x89, 0xf9, 0x9f, 0x62, 0x6e, 0xcd, 0x71,
-	0x43, 0x7d, 0x82, 0xb6, 0x4a, 0x7c, 0x1d, 0x25, 0x05, 0x4b, 0x26, 0x51, 0xca, 0xc9, 0x95, 0x1c,
-	0x76, 0x15, 0x73, 0x50, 0xe2, 0xeb, 0x71, 0x83, 0xbe, 0x6e, 0x40, 0xfb, 0x14, 0x0d, 0xae, 0x38,
-	0xbb, 0x01, 0x1a, 0xe5, 0xd0, 0x04, 0x39, 0xfc, 0x7f, 0xdf, 0x3a, 0xec, 0x05, 0x3b, 0x2a, 0xda,
-	0xe6, 0xef, 0x5d, 0x13, 0x4a, 0xed, 0xbb, 0x67, 0x8a, 0x71, 0xd2, 0xbd, 0xfd, 0xb5, 0xd7, 0x09,
-	0xfb, 0x5a, 0xa6, 0xb1, 0xc6, 0xa6, 0xc0, 0x12, 0x84, 0x6c, 0x6d, 0x56, 0xff, 0xd6, 0x46, 0xcb,
-	0x34, 0xf6, 0xb2, 0xfb, 0xe9, 0xeb, 0x5e, 0x67, 0xf4, 0xcd, 0x42, 0x9b, 0x63, 0x46, 0x05, 0x50,
-	0x31, 0x15, 0x3a, 0xbd, 0x5d, 0xb4, 0x21, 0x49, 0x09, 0x42, 0xe2, 0xb2, 0x52, 0xf1, 0x75, 0xc3,
-	0x05, 0x60, 0xbf, 0x42, 0x5d, 0xce, 0x98, 0x54, 0xa9, 0xf5, 0x82, 0xd1, 0xd2, 0xa2, 0x8b, 0xb3,
-	0xaa, 0x7d, 0xf7, 0x1c, 0xf8, 0xa4, 0x80, 0x90, 0xb1, 0x76, 0x71, 0xa5, 0xb2, 0x8f, 0xd0, 0x36,
-	0x85, 0x6b, 0x19, 0xd5, 0xb8, 0x20, 0x29, 0x96, 0x8c, 0x8b, 0x28, 0xc7, 0x22, 0x57, 0xc9, 0xf6,
-	0x43, 0xbb, 0xa9, 0xbd, 0x9b, 0x97, 0xce, 0xb0, 0xc8, 0xcd, 0x36, 0xbf, 0x58, 0xa8, 0x7f, 0x4e,
-	0x44, 0x0c, 0x39, 0xae, 0x09, 0x9b, 0x72, 0xfb, 0x14, 0xad, 0xe7, 0x80, 0x53, 0xe0, 0x91, 0xaf,
-	0xf6, 0xd8, 0x0b, 0x9e, 0xb9, 0x0f, 0xdf, 0x53, 0xf7, 0x4c, 0x69, 0xc2, 0x35, 0xad, 0xf5, 0x97,
-	0x6c, 0x02, 0xf3, 0x47, 0xff, 0x60, 0x13, 0x8c, 0x7e, 0x58, 0x68, 0x55, 0x63, 0xf6, 0x18, 0x0d,
-	0x04, 0xc9, 0x28, 0xa4, 0x91, 0x2e, 0x9a, 0xdd, 0x39, 0xee, 0xa2, 0x43, 0x5c, 0xdd, 0x1b, 0x97,
-	0x8a, 0x66, 0xac, 0xfa, 0x62, 0x69, 0x66, 0x1f, 0x23, 0x7d, 0x1d, 0x95, 0x8b, 0x3a, 0xe3, 0x95,
-	0x87, 0xce, 0x38, 0x1c, 0x18, 0x85, 0xb9, 0x25, 0x47, 0x68, 0xfb, 0x06, 0x38, 0x8b, 0x26, 0x94,
-	0x7d, 0x28, 0x20, 0xcd, 0x20, 0xaa, 0x38, 0x63, 0x57, 0x6d, 0xd2, 0x4d, 0xed, 0x6d, 0x5b, 0xba,
-	0x68, 0x2a, 0x27, 0xc7, 0xb7, 0xf7, 0x8e, 0x75, 0x77, 0xef, 0x58, 0xbf, 0xef, 0x1d, 0xeb, 0xf3,
-	0xcc, 0xe9, 0xdc, 0xcd, 0x9c, 0xce, 0xcf, 0x99, 0xd3, 0x79, 0x7f, 0xa0, 0x1f, 0x0d, 0x5c, 0x55,
-	0x9e, 0x6e, 0x54, 0xf3, 0x56, 0x1c, 0x05, 0xcf, 0x4d, 0x43, 0x4f, 0x00, 0x2a, 0xe0, 0xf1, 0xaa,
-	0xea, 0xc7, 0x17, 0x7f, 0x02, 0x00, 0x00, 0xff, 0xff, 0xbf, 0x44, 0x80, 0x76, 0x66, 0x04, 0x00,
+	// 625 bytes of a gzipped FileDescriptorProto
+	0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0xff, 0x9d, 0x54, 0x41, 0x6f, 0xd3, 0x30,
+	0x14, 0x6e, 0xba, 0xd2, 0x6e, 0x6e, 0xbb, 0xa2, 0x68, 0x48, 0xa5, 0x9a, 0x5a, 0xd4, 0x03, 0x65,
+	0x48, 0x24, 0x4b, 0xb8, 0x21, 0x2e, 0x5b, 0x41, 0x1a, 0x9a, 0x26, 0x4d, 0x99, 0xb4, 0x03, 0x97,
+	0x28, 0x4d, 0xdc, 0xc6, 0x4a, 0x62, 0x47, 0xb1, 0x1b, 0xd6, 0xfe, 0x02, 0x8e, 0xfc, 0x00, 0x0e,
+	0x1c, 0xf8, 0x25, 0x9c, 0x76, 0xdc, 0x91, 0x13, 0x42, 0x70, 0xe4, 0x4f, 0xe0, 0xd8, 0x4e, 0x9b,
+	0xdb, 0x10, 0x07, 0x57, 0xf6, 0xf7, 0xbe, 0xef, 0xcb, 0xf3, 0xeb, 0x7b, 0x06, 0xd6, 0x12, 0x23,
+	0x82, 0x4d, 0x34, 0xf3, 0xcd, 0x18, 0x2d, 0x42, 0xe6, 0xc7, 0x08, 0x62, 0x46, 0x4d, 0x9f, 0x24,
+	0x90, 0xcd, 0x62, 0x6a, 0xe6, 0xd6, 0x66, 0x6f, 0xa4, 0x19, 0x61, 0x44, 0x1f, 0x0b, 0x89, 0xc1,
+	0x25, 0x46, 0x55, 0x62, 0x6c, 0x68, 0xb9, 0x35, 0x38, 0x64, 0x10, 0x07, 0x30, 0x4b, 0x10, 0x66,
+	0x26, 0x5b, 0xa5, 0x90, 0xca, 0x5f, 0xe9, 0x30, 0x18, 0x15, 0x9f, 0xf3, 0x49, 0x06, 0x4d, 0xa9,
+	0x15, 0x1f, 0x11, 0x3b, 0x45, 0x98, 0x6c, 0x09, 0x24, 0x49, 0x10, 0x4b, 0x4a, 0xd2, 0xe6, 0xa4,
+	0x88, 0x07, 0x0b, 0xb2, 0x20, 0x62, 0x6b, 0x16, 0x3b, 0x89, 0x8e, 0xff, 0xd4, 0x41, 0x7b, 0x2a,
+	0xfc, 0xae, 0x98, 0xc7, 0xa0, 0xfe, 0x18, 0xec, 0xfa, 0xa1, 0x87, 0xb0, 0x8b, 0x82, 0xbe, 0xf6,
+	0x44, 0x7b, 0xb6, 0xe7, 0xb4, 0xc4, 0xf9, 0x5d, 0xa0, 0x4f, 0x40, 0x8f, 0x65, 0x4b, 0xca, 0x10,
+	0x5e, 0xb8, 0x29, 0xcc, 0x10, 0x09, 0xfa, 0x75, 0xce, 0x68, 0x38, 0xfb, 0x25, 0x7c, 0x29, 0x50,
+	0xfd, 0x08, 0x3c, 0x5c, 0xe2, 0x19, 0xc1, 0x41, 0x85, 0xb9, 0x23, 0x98, 0xbd, 0x0d, 0xae, 0xa8,
+	0x4f, 0x41, 0x2f, 0xf1, 0x6e, 0x5c, 0x3f, 0x26, 0x7e, 0xe4, 0x06, 0x19, 0x9a, 0xb3, 0x7e, 0x43,
+	0x30, 0xbb, 0x1c, 0x9e, 0x16, 0xe8, 0x9b, 0x02, 0xd4, 0xdf, 0x82, 0xee, 0x3c, 0x23, 0x6b, 0x88,
+	0xdd, 0x10, 0x16, 0x85, 0xec, 0x3f, 0xe0, 0xac, 0xb6, 0x3d, 0x10, 0xa5, 0x2d, 0x6e, 0x6f, 0xa8,
+	0xa2, 0xe4, 0x96, 0x71, 0x26, 0x18, 0xa7, 0x8d, 0xdb, 0x1f, 0xa3, 0x9a, 0xd3, 0x91, 0x32, 0x89,
+	0x15, 0x36, 0x31, 0xbf, 0x25, 0x65, 0xa5, 0x4d, 0xf3, 0x5f, 0x6d, 0xa4, 0x4c, 0xd9, 0x58, 0xe0,
+	0xd1, 0x3a, 0x72, 0x73, 0x7e, 0x85, 0xf9, 0xaa, 0xb8, 0x63, 0x04, 0x57, 0x6e, 0xe8, 0xd1, 0xb0,
+	0xdf, 0xe2, 0x76, 0x1d, 0x47, 0x5f, 0x47, 0xd7, 0x65, 0xec, 0x1c, 0xae, 0xce, 0x78, 0xe4, 0x55,
+	0xe3, 0xe3, 0x97, 0x51, 0x6d, 0xfc, 0x55, 0x03, 0xfb, 0x53, 0x82, 0x29, 0xc4, 0x74, 0x49, 0x65,
+	0xc1, 0x0f, 0xc1, 0x1e, 0x43, 0x09, 0xf7, 0xf6, 0x92, 0x54, 0x54, 0xbc, 0xe1, 0x6c, 0x01, 0xfd,
+	0x35, 0x68, 0x64, 0x84, 0x30, 0x51, 0xe8, 0xb6, 0x3d, 0xae, 0xe4, 0xb9, 0xfd, 0x7b, 0x79, 0xae,
+	0x17, 0x30, 0x8b, 0x62, 0xe8, 0x70, 0xa6, 0xca, 0x57, 0xa8, 0xf4, 0x63, 0x70, 0x80, 0xe1, 0x0d,
+	0x73, 0x73, 0x2f, 0x46, 0x81, 0xc7, 0x48, 0x46, 0x65, 0x9a, 0x3b, 0x32, 0xcd, 0x22, 0x76, 0xbd,
+	0x09, 0x55, 0xd2, 0xfc, 0xac, 0x81, 0xce, 0x05, 0xa2, 0x33, 0x18, 0x7a, 0x39, 0x22, 0xcb, 0x8c,
+	0xd7, 0x6d, 0x37, 0x84, 0x1e, 0xef, 0x52, 0xd7, 0x12, 0x39, 0xb6, 0xed, 0xe7, 0xc6, 0xfd, 0xad,
+	0xcd, 0x4b, 0x58, 0x68, 0x9c, 0x96, 0xd4, 0x5a, 0x15, 0x1b, 0x5b, 0xdd, 0xe8, 0x3f, 0x6c, 0xec,
+	0xf1, 0x37, 0x0d, 0x34, 0x25, 0xa6, 0x4f, 0x41, 0x97, 0xa2, 0x05, 0x86, 0x81, 0x2b, 0x83, 0x2a,
+	0xbb, 0xa1, 0xb1, 0x1d, 0x2a, 0x43, 0x8e, 0xd3, 0x95, 0xa0, 0x29, 0xab, 0x0e, 0xad, 0x9c, 0xf4,
+	0x13, 0x20, 0x3b, 0x58, 0xb8, 0x88, 0xb6, 0xa8, 0xdf, 0xd7, 0x16, 0x4e, 0x57, 0x29, 0x54, 0x47,
+	0xf0, 0x4a, 0xaf, 0x61, 0x46, 0xdc, 0x08, 0x93, 0x0f, 0x31, 0x0c, 0x16, 0xd0, 0xe5, 0xe3, 0x45,
+	0xe6, 0x65, 0xa5, 0x8b, 0xd8, 0x79, 0x19, 0xba, 0x2c, 0x22, 0xa7, 0x27, 0xb7, 0xbf, 0x86, 0xda,
+	0x1d, 0x5f, 0x3f, 0xf9, 0xfa, 0xf4, 0x7b, 0x58, 0xbb, 0xe3, 0xeb, 0x3b, 0x5f, 0xef, 0x27, 0xf2,
+	0x9d, 0xf1, 0xd2, 0xd4, 0x94, 0xb3, 0xad, 0x9e, 0x97, 0x63, 0xfb, 0x85, 0x7a, 0x03, 0x22, 0x08,
+	0xf9, 0x58, 0xcd, 0x9a, 0x62, 0x84, 0x5f, 0xfe, 0x05, 0x2e, 0xbc, 0x05, 0x1f, 0x99, 0x04, 0x00,
 	0x00,
 }
 
@@ -303,6 +307,13 @@ func (m *ClientState) MarshalToSizedBuffer(dAtA []byte) (int, error) {
 	_ = i
 	var l int
 	_ = l
+	if len(m.ZkVerifyingKeyHash) > 0 {
+		i -= len(m.ZkVerifyingKeyHash)
+		copy(dAtA[i:], m.ZkVerifyingKeyHash)
+		i = encodeVarintCometbls(dAtA, i, uint64(len(m.ZkVerifyingKeyHash)))
+		i--
+		dAtA[i] = 0x3a
+	}
 	{
 		size, err := m.LatestHeight.MarshalToSizedBuffer(dAtA[:i])
 		if err != nil {
@@ -528,6 +539,10 @@ func (m *ClientState) Size() (n int) {
 	n += 1 + l + sovCometbls(uint64(l))
 	l = m.LatestHeight.Size()
 	n += 1 + l + sovCometbls(uint64(l))
+	l = len(m.ZkVerifyingKeyHash)
+	if l > 0 {
+		n += 1 + l + sovCometbls(uint64(l))
+	}
 	return n
 }
 
@@ -777,6 +792,40 @@ func (m *ClientState) Unmarshal(dAtA []byte) error {
 				return err
 			}
 			iNdEx = postIndex
+		case 7:
+			if wireType != 2 {
+				return fmt.Errorf("proto: wrong wireType = %d for field ZkVerifyingKeyHash", wireType)
+			}
+			var byteLen int
+			for shift := uint(0); ; shift += 7 {
+				if shift >= 64 {
+					return ErrIntOverflowCometbls
+				}
+				if iNdEx >= l {
+					return io.ErrUnexpectedEOF
+				}
+				b := dAtA[iNdEx]
+				iNdEx++
+				byteLen |= int(b&0x7F) << shift
+				if b < 0x80 {
+					break
+				}
+			}
+			if byteLen < 0 {
+				return ErrInvalidLengthCometbls
+			}
+			postIndex := iNdEx + byteLen
+			if postIndex < 0 {
+				return ErrInvalidLengthCometbls
+			}
+			if postIndex > l {
+				return io.ErrUnexpectedEOF
+			}
+			m.ZkVerifyingKeyHash = append(m.ZkVerifyingKeyHash[:0], dAtA[iNdEx:postIndex]...)
+			if m.ZkVerifyingKeyHash == nil {
+				m.ZkVerifyingKeyHash = []byte{}
+			}
+			iNdEx = postIndex
 		default:
 			iNdEx = preIndex
 			skippy, err := skipCometbls(dAtA[iNdEx:])
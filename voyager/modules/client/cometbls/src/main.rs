@@ -21,7 +21,7 @@ use unionlabs::{
 };
 use voyager_message::{
     core::{
-        ChainId, ClientStateMeta, ClientType, ConsensusStateMeta, ConsensusType,
+        ChainId, ClientStateMeta, ClientStatus, ClientType, ConsensusStateMeta, ConsensusType,
         IbcGo08WasmClientMetadata, IbcInterface,
     },
     module::{ClientModuleInfo, ClientModuleServer},
@@ -178,9 +178,32 @@ impl ClientModuleServer for Module {
         Ok(ClientStateMeta {
             chain_id: ChainId::new(cs.chain_id.as_str().to_owned()),
             height: cs.latest_height,
+            status: ClientStatus::Unknown,
+            resolved_at: None,
         })
     }
 
+    #[instrument(skip_all)]
+    async fn client_status(
+        &self,
+        _: &Extensions,
+        client_state: Bytes,
+        consensus_state: Bytes,
+        timestamp_nanos: u64,
+    ) -> RpcResult<ClientStatus> {
+        let cs = self.decode_client_state(&client_state)?;
+        let consensus_state = self.decode_consensus_state(&consensus_state)?;
+
+        if cs.frozen_height.height() != 0 {
+            return Ok(ClientStatus::Frozen);
+        }
+
+        match consensus_state.timestamp.checked_add(cs.trusting_period) {
+            Some(expires_at) if expires_at >= timestamp_nanos => Ok(ClientStatus::Active),
+            _ => Ok(ClientStatus::Expired),
+        }
+    }
+
     #[instrument(skip_all)]
     async fn decode_consensus_state_meta(
         &self,
@@ -16,7 +16,10 @@ use unionlabs::{
     ErrorReporter,
 };
 use voyager_message::{
-    core::{ChainId, ClientStateMeta, ClientType, ConsensusStateMeta, ConsensusType, IbcInterface},
+    core::{
+        ChainId, ClientStateMeta, ClientStatus, ClientType, ConsensusStateMeta, ConsensusType,
+        IbcInterface,
+    },
     module::{ClientModuleInfo, ClientModuleServer},
     ClientModule, FATAL_JSONRPC_ERROR_CODE,
 };
@@ -93,9 +96,29 @@ impl ClientModuleServer for Module {
         Ok(ClientStateMeta {
             chain_id: ChainId::new(cs.chain_id.to_string()),
             height: Module::make_height(cs.latest_height),
+            status: ClientStatus::Unknown,
+            resolved_at: None,
         })
     }
 
+    #[instrument]
+    async fn client_status(
+        &self,
+        _: &Extensions,
+        client_state: Bytes,
+        _consensus_state: Bytes,
+        _timestamp_nanos: u64,
+    ) -> RpcResult<ClientStatus> {
+        let cs = Module::decode_client_state(&client_state)?;
+
+        // the ethereum client has no trusting period, it can only be frozen
+        if cs.frozen_height.height() != 0 {
+            Ok(ClientStatus::Frozen)
+        } else {
+            Ok(ClientStatus::Active)
+        }
+    }
+
     #[instrument]
     async fn decode_consensus_state_meta(
         &self,
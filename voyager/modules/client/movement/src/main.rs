@@ -18,7 +18,10 @@ use unionlabs::{
     ErrorReporter,
 };
 use voyager_message::{
-    core::{ChainId, ClientStateMeta, ClientType, ConsensusStateMeta, ConsensusType, IbcInterface},
+    core::{
+        ChainId, ClientStateMeta, ClientStatus, ClientType, ConsensusStateMeta, ConsensusType,
+        IbcInterface,
+    },
     module::{ClientModuleInfo, ClientModuleServer},
     ClientModule, FATAL_JSONRPC_ERROR_CODE,
 };
@@ -96,9 +99,32 @@ impl ClientModuleServer for Module {
         Ok(ClientStateMeta {
             chain_id: ChainId::new(cs.0.data.chain_id.to_string()),
             height: Module::make_height(cs.0.data.latest_block_num),
+            status: ClientStatus::Unknown,
+            resolved_at: None,
         })
     }
 
+    #[instrument]
+    async fn client_status(
+        &self,
+        _: &Extensions,
+        client_state: Bytes,
+        consensus_state: Bytes,
+        timestamp_nanos: u64,
+    ) -> RpcResult<ClientStatus> {
+        let cs = Module::decode_client_state(&client_state)?.0.data;
+        let consensus_state = Module::decode_consensus_state(&consensus_state)?.0.data;
+
+        if cs.is_frozen() {
+            Ok(ClientStatus::Frozen)
+        } else if cs.is_expired(consensus_state.timestamp, timestamp_nanos / 1_000) {
+            // movement timestamps are in microseconds
+            Ok(ClientStatus::Expired)
+        } else {
+            Ok(ClientStatus::Active)
+        }
+    }
+
     #[instrument]
     async fn decode_consensus_state_meta(
         &self,
@@ -0,0 +1,404 @@
+use std::num::NonZeroU64;
+
+use beacon_api_types::{execution_payload_header::ExecutionPayloadHeaderSsz, Mainnet};
+use chain_utils::{
+    auth::{self, AuthorizationHeader, EndpointAuth},
+    endpoint::{WsUrl, DEFAULT_PROBE_TIMEOUT},
+    net::NetworkConfig,
+    timeout::{CallKind, TimeoutConfig},
+    upgrade_plan::AbciQueryClient,
+};
+use cometbft_rpc::rpc_types::CommitResponse;
+use jsonrpsee::{
+    core::{async_trait, RpcResult},
+    types::{error::METHOD_NOT_FOUND_CODE, ErrorObject},
+    Extensions,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::{debug, instrument};
+use unionlabs::{
+    berachain::LATEST_EXECUTION_PAYLOAD_HEADER_PREFIX, ibc::core::client::height::Height,
+    ErrorReporter,
+};
+use voyager_message::{
+    core::{ChainId, ConsensusType},
+    error::VoyagerError,
+    module::{ConsensusModuleInfo, ConsensusModuleServer},
+    rpc::json_rpc_error_to_error_object,
+    ConsensusModule,
+};
+use voyager_vm::BoxDynError;
+
+/// The abci query path of the beacon-kit `beacon` store.
+const BEACON_STORE_PATH: &str = "store/beacon/key";
+
+#[tokio::main(flavor = "multi_thread")]
+async fn main() {
+    Module::run().await
+}
+
+#[derive(Debug, Clone)]
+pub struct Module {
+    pub chain_id: ChainId,
+
+    pub tm_client: cometbft_rpc::Client,
+
+    pub timeouts: TimeoutConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// The cometbft RPC endpoint of the beacon-kit consensus layer.
+    pub comet_ws_url: WsUrl,
+
+    /// Authentication for `comet_ws_url`, if the rpc provider requires it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comet_ws_auth: Option<EndpointAuth>,
+
+    /// Don't check that the configured endpoint is reachable on startup.
+    #[serde(default)]
+    pub skip_startup_probe: bool,
+
+    /// How connections to the configured endpoint are opened, i.e. through a proxy.
+    #[serde(default)]
+    pub network: NetworkConfig,
+
+    /// Timeouts of the calls to the configured endpoint.
+    #[serde(default)]
+    pub timeouts: TimeoutConfig,
+}
+
+impl ConsensusModule for Module {
+    type Config = Config;
+
+    async fn new(config: Self::Config, info: ConsensusModuleInfo) -> Result<Self, BoxDynError> {
+        if !config.skip_startup_probe {
+            config
+                .comet_ws_url
+                .probe_via(&config.network, DEFAULT_PROBE_TIMEOUT)
+                .await?;
+        }
+
+        let tm_client = cometbft_rpc::Client::new_with_connector(
+            config.comet_ws_url,
+            AuthorizationHeader::jsonrpsee_headers(
+                auth::resolve(config.comet_ws_auth.as_ref())?.as_ref(),
+            ),
+            config.network.cometbft_connector(),
+        )
+        .await?;
+
+        let chain_id = tm_client.status().await?.node_info.network.to_string();
+
+        info.ensure_chain_id(&chain_id)?;
+        info.ensure_consensus_type(ConsensusType::BEACON_KIT)?;
+
+        Ok(Self {
+            chain_id: ChainId::new(chain_id),
+            tm_client,
+            timeouts: config.timeouts,
+        })
+    }
+}
+
+impl Module {
+    /// The latest commit of the consensus layer. If `finalized` is set and the latest commit is
+    /// not canonical yet, this is the commit of the previous block.
+    async fn latest_commit(&self, finalized: bool) -> RpcResult<CommitResponse> {
+        let commit_response = self
+            .tm_client
+            .commit(None)
+            .await
+            .map_err(json_rpc_error_to_error_object)?;
+
+        if !finalized || commit_response.canonical {
+            return Ok(commit_response);
+        }
+
+        debug!(
+            "commit is not canonical and finalized commit was requested, fetching commit at \
+            previous block"
+        );
+
+        let previous_height = u64::try_from(commit_response.signed_header.header.height.inner())
+            .ok()
+            .and_then(|height| NonZeroU64::new(height - 1))
+            .ok_or_else(|| {
+                ErrorObject::owned(-1, "there is no finalized commit yet", None::<()>)
+            })?;
+
+        self.tm_client
+            .commit(Some(previous_height))
+            .await
+            .map_err(json_rpc_error_to_error_object)
+    }
+}
+
+#[async_trait]
+impl ConsensusModuleServer for Module {
+    /// Query the latest finalized height of this chain.
+    #[instrument(skip_all, fields(chain_id = %self.chain_id))]
+    async fn query_latest_height(&self, _: &Extensions, finalized: bool) -> RpcResult<Height> {
+        let height = self
+            .latest_commit(finalized)
+            .await?
+            .signed_header
+            .header
+            .height
+            .inner()
+            .try_into()
+            .expect("value is >= 0; qed;");
+
+        debug!(height, "latest height");
+
+        Ok(Height::new(height))
+    }
+
+    /// Query the latest finalized timestamp of this chain.
+    #[instrument(skip_all, fields(chain_id = %self.chain_id))]
+    async fn query_latest_timestamp(&self, _: &Extensions, finalized: bool) -> RpcResult<i64> {
+        Ok(self
+            .latest_commit(finalized)
+            .await?
+            .signed_header
+            .header
+            .time
+            .as_unix_nanos()
+            .try_into()
+            .expect("should be fine"))
+    }
+
+    #[instrument(skip_all, fields(chain_id = %self.chain_id, %height))]
+    async fn timestamp_at_height(&self, height: Height) -> RpcResult<i64> {
+        let block_height = NonZeroU64::new(height.height())
+            .ok_or_else(|| ErrorObject::owned(-1, "there is no block at height 0", None::<()>))?;
+
+        let block_response = self
+            .tm_client
+            .block(Some(block_height))
+            .await
+            .map_err(json_rpc_error_to_error_object)?;
+
+        Ok(block_response
+            .block
+            .header
+            .time
+            .as_unix_nanos()
+            .try_into()
+            .expect("should be fine"))
+    }
+
+    #[instrument(skip_all, fields(chain_id = %self.chain_id, %height))]
+    async fn consensus_to_execution_height(&self, height: Height) -> RpcResult<u64> {
+        let execution_height =
+            consensus_to_execution_height(&self.tm_client, &self.timeouts, height.height()).await?;
+
+        debug!(%execution_height, "translated consensus height to execution height");
+
+        Ok(execution_height)
+    }
+
+    async fn self_client_state(&self, _: &Extensions, _: Height) -> RpcResult<Value> {
+        Err(ErrorObject::owned(
+            METHOD_NOT_FOUND_CODE,
+            "creating clients of beacon-kit chains is not supported by this module",
+            None::<()>,
+        ))
+    }
+
+    async fn self_consensus_state(&self, _: &Extensions, _: Height) -> RpcResult<Value> {
+        Err(ErrorObject::owned(
+            METHOD_NOT_FOUND_CODE,
+            "creating clients of beacon-kit chains is not supported by this module",
+            None::<()>,
+        ))
+    }
+}
+
+/// Read the execution block number from the latest execution payload header stored in the
+/// `beacon` store at the provided consensus height.
+async fn consensus_to_execution_height(
+    client: &impl AbciQueryClient,
+    timeouts: &TimeoutConfig,
+    consensus_height: u64,
+) -> RpcResult<u64> {
+    // the beacon store is queried at the height before the consensus height, see
+    // https://github.com/berachain/beacon-kit/blob/8b706f49705b4ca5e88d1551c58db91b98d7eee7/mod/consensus/pkg/cometbft/service/abci.go
+    let query_height = consensus_height
+        .checked_sub(1)
+        .and_then(NonZeroU64::new)
+        .ok_or_else(|| {
+            ErrorObject::owned(
+                -1,
+                format!("invalid consensus height {consensus_height}"),
+                None::<()>,
+            )
+        })?;
+
+    let response = timeouts
+        .with_timeout(
+            CallKind::Query,
+            client.abci_query(
+                BEACON_STORE_PATH,
+                vec![LATEST_EXECUTION_PAYLOAD_HEADER_PREFIX],
+                Some(query_height),
+            ),
+        )
+        .await
+        .map_err(VoyagerError::from)?
+        .map_err(|e| {
+            ErrorObject::owned(
+                -1,
+                format!(
+                    "error fetching execution payload header: {}",
+                    ErrorReporter(e)
+                ),
+                None::<()>,
+            )
+        })?
+        .response;
+
+    if response.code != 0 {
+        return Err(ErrorObject::owned(
+            -1,
+            format!(
+                "execution payload header query failed with code {}: {}",
+                response.code, response.log
+            ),
+            None::<()>,
+        ));
+    }
+
+    execution_block_number_from_payload_header(response.value.as_deref().unwrap_or_default())
+}
+
+fn execution_block_number_from_payload_header(bz: &[u8]) -> RpcResult<u64> {
+    <ExecutionPayloadHeaderSsz<Mainnet> as ssz::Ssz>::from_ssz_bytes(bz)
+        .map(|header| header.block_number)
+        .map_err(|e| {
+            ErrorObject::owned(
+                -1,
+                format!("unable to decode execution payload header: {e:?}"),
+                None::<()>,
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use cometbft_rpc::{rpc_types::AbciQueryResponse, JsonRpcError};
+    use serde_json::json;
+    use ssz::Ssz;
+
+    use super::*;
+
+    /// Returns `response` for queries of the latest execution payload header at `height`.
+    struct MockClient {
+        height: u64,
+        response: AbciQueryResponse,
+    }
+
+    impl MockClient {
+        fn new(height: u64, code: u32, value: Option<Vec<u8>>) -> Self {
+            let mut response = serde_json::from_value::<AbciQueryResponse>(json!({
+                "response": {
+                    "code": code,
+                    "log": "",
+                    "info": "",
+                    "index": "0",
+                    "key": null,
+                    "value": null,
+                    "proofOps": null,
+                    "height": height.to_string(),
+                    "codespace": "",
+                }
+            }))
+            .unwrap();
+
+            response.response.value = value.map(Into::into);
+
+            Self { height, response }
+        }
+    }
+
+    impl AbciQueryClient for MockClient {
+        async fn abci_query(
+            &self,
+            path: &str,
+            data: Vec<u8>,
+            height: Option<NonZeroU64>,
+        ) -> Result<AbciQueryResponse, JsonRpcError> {
+            assert_eq!(path, BEACON_STORE_PATH);
+            assert_eq!(data, [LATEST_EXECUTION_PAYLOAD_HEADER_PREFIX]);
+            assert_eq!(height.map(NonZeroU64::get), Some(self.height));
+
+            Ok(self.response.clone())
+        }
+    }
+
+    fn payload_header(block_number: u64) -> Vec<u8> {
+        ExecutionPayloadHeaderSsz::<Mainnet> {
+            parent_hash: Default::default(),
+            fee_recipient: Default::default(),
+            state_root: Default::default(),
+            receipts_root: Default::default(),
+            logs_bloom: Default::default(),
+            prev_randao: Default::default(),
+            block_number,
+            gas_limit: 30_000_000,
+            gas_used: 0,
+            timestamp: 1_700_000_000,
+            extra_data: Default::default(),
+            base_fee_per_gas: Default::default(),
+            block_hash: Default::default(),
+            transactions_root: Default::default(),
+            withdrawals_root: Default::default(),
+            blob_gas_used: 0,
+            excess_blob_gas: 0,
+        }
+        .as_ssz_bytes()
+    }
+
+    #[tokio::test]
+    async fn translates_consensus_height() {
+        // the header is read from the state at the previous height
+        let client = MockClient::new(99, 0, Some(payload_header(4321)));
+
+        assert_eq!(
+            consensus_to_execution_height(&client, &TimeoutConfig::default(), 100).await,
+            Ok(4321)
+        );
+    }
+
+    #[tokio::test]
+    async fn invalid_consensus_height() {
+        let client = MockClient::new(0, 0, Some(payload_header(4321)));
+
+        for height in [0, 1] {
+            assert!(
+                consensus_to_execution_height(&client, &TimeoutConfig::default(), height)
+                    .await
+                    .is_err()
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn failed_query() {
+        let client = MockClient::new(99, 26, None);
+
+        let err = consensus_to_execution_height(&client, &TimeoutConfig::default(), 100)
+            .await
+            .unwrap_err();
+
+        assert!(err.message().contains("failed with code 26"), "{err:?}");
+    }
+
+    #[test]
+    fn execution_block_number_from_payload_header_invalid_bytes() {
+        assert!(execution_block_number_from_payload_header(&[]).is_err());
+        assert!(execution_block_number_from_payload_header(&[0; 32]).is_err());
+    }
+}
@@ -1,11 +1,15 @@
 use std::{
     fmt::Debug,
     num::{NonZeroU64, ParseIntError},
+    time::Duration,
 };
 
+use cometbft_rpc::rpc_types::CommitResponse;
+use cometbft_types::types::header::Header;
 use cometbls_light_client_types::{ClientState, ConsensusState};
 use jsonrpsee::{
     core::{async_trait, RpcResult},
+    types::ErrorObject,
     Extensions,
 };
 use serde::{Deserialize, Serialize};
@@ -13,9 +17,11 @@ use serde_json::Value;
 use tracing::{debug, error, instrument};
 use unionlabs::{
     bech32::Bech32,
+    errors::InvalidLength,
     hash::H256,
     ibc::core::{client::height::Height, commitment::merkle_root::MerkleRoot},
     traits::Member,
+    ErrorReporter,
 };
 use voyager_message::{
     core::{ChainId, ConsensusType},
@@ -180,69 +186,298 @@ impl ConsensusModuleServer for Module {
             .expect("should be fine"))
     }
 
+    #[instrument(skip_all, fields(chain_id = %self.chain_id, %height))]
+    async fn timestamp_at_height(&self, height: Height) -> RpcResult<i64> {
+        let block_height = NonZeroU64::new(height.height())
+            .ok_or_else(|| ErrorObject::owned(-1, "there is no block at height 0", None::<()>))?;
+
+        let block_response = self
+            .tm_client
+            .block(Some(block_height))
+            .await
+            .map_err(json_rpc_error_to_error_object)?;
+
+        Ok(block_response
+            .block
+            .header
+            .time
+            .as_unix_nanos()
+            .try_into()
+            .expect("should be fine"))
+    }
+
     #[instrument(skip_all, fields(chain_id = %self.chain_id))]
     async fn self_client_state(&self, _: &Extensions, height: Height) -> RpcResult<Value> {
         let params = protos::cosmos::staking::v1beta1::query_client::QueryClient::connect(
             self.grpc_url.clone(),
         )
         .await
-        .unwrap()
+        .map_err(|e| {
+            ErrorObject::owned(
+                -1,
+                format!("error connecting to grpc server: {}", ErrorReporter(e)),
+                None::<()>,
+            )
+        })?
         .params(protos::cosmos::staking::v1beta1::QueryParamsRequest {})
         .await
-        .unwrap()
+        .map_err(|e| {
+            ErrorObject::owned(
+                -1,
+                format!("error fetching staking params: {}", ErrorReporter(e)),
+                None::<()>,
+            )
+        })?
         .into_inner()
         .params
-        .unwrap();
+        .ok_or_else(|| ErrorObject::owned(-1, "staking params response is empty", None::<()>))?;
 
-        let commit = self
-            .tm_client
-            .commit(Some(NonZeroU64::new(height.height()).unwrap()))
-            .await
-            .unwrap();
-
-        let height = commit.signed_header.header.height;
-
-        // Expected to be nanos
-        let unbonding_period =
-            u64::try_from(params.unbonding_time.clone().unwrap().seconds).unwrap() * 1_000_000_000;
-
-        Ok(serde_json::to_value(ClientState {
-            chain_id: cometbls_light_client_types::ChainId::from_string(self.chain_id.to_string())
-                .unwrap(),
-            trusting_period: unbonding_period * 85 / 100,
-            max_clock_drift: (60 * 20) * 1_000_000_000,
-            frozen_height: Height::new(0),
-            latest_height: Height::new_with_revision(
-                self.chain_id
-                    .as_str()
-                    .split('-')
-                    .last()
-                    .unwrap()
-                    .parse()
-                    .unwrap(),
-                height.inner().try_into().expect("value is >= 0; qed;"),
-            ),
-            contract_address: self.ibc_host_contract_address,
-        })
-        .unwrap())
+        let unbonding_period = params
+            .unbonding_time
+            .and_then(|unbonding_time| {
+                unbonding_period(unbonding_time.seconds, unbonding_time.nanos)
+            })
+            .ok_or_else(|| {
+                ErrorObject::owned(
+                    -1,
+                    "staking params contain no or an invalid unbonding time",
+                    None::<()>,
+                )
+            })?;
+
+        let commit = self.commit(height).await?;
+
+        let client_state = make_client_state(
+            &commit.signed_header.header,
+            self.chain_revision,
+            unbonding_period,
+            self.ibc_host_contract_address,
+        )
+        .map_err(|e| {
+            ErrorObject::owned(
+                -1,
+                format!("error building client state: {}", ErrorReporter(e)),
+                None::<()>,
+            )
+        })?;
+
+        Ok(serde_json::to_value(client_state).expect("serialization is infallible; qed;"))
     }
 
     /// The consensus state on this chain at the specified `Height`.
     #[instrument(skip_all, fields(chain_id = %self.chain_id))]
     async fn self_consensus_state(&self, _: &Extensions, height: Height) -> RpcResult<Value> {
-        let commit = self
-            .tm_client
-            .commit(Some(NonZeroU64::new(height.height()).unwrap()))
-            .await
-            .unwrap();
-
-        Ok(serde_json::to_value(ConsensusState {
-            timestamp: commit.signed_header.header.time.as_unix_nanos(),
-            app_hash: MerkleRoot {
-                hash: commit.signed_header.header.app_hash.into_encoding(),
-            },
-            next_validators_hash: commit.signed_header.header.next_validators_hash,
+        let commit = self.commit(height).await?;
+
+        Ok(
+            serde_json::to_value(make_consensus_state(&commit.signed_header.header))
+                .expect("serialization is infallible; qed;"),
+        )
+    }
+}
+
+impl Module {
+    async fn commit(&self, height: Height) -> RpcResult<CommitResponse> {
+        let height = NonZeroU64::new(height.height())
+            .ok_or_else(|| ErrorObject::owned(-1, "there is no commit at height 0", None::<()>))?;
+
+        self.tm_client.commit(Some(height)).await.map_err(|e| {
+            ErrorObject::owned(
+                -1,
+                format!("error fetching commit: {}", ErrorReporter(e)),
+                None::<()>,
+            )
         })
-        .unwrap())
+    }
+}
+
+// https://github.com/cosmos/relayer/blob/23d1e5c864b35d133cad6a0ef06970a2b1e1b03f/relayer/chains/cosmos/provider.go#L177
+const MAX_CLOCK_DRIFT: Duration = Duration::from_secs(60 * 20);
+
+#[derive(Debug, thiserror::Error)]
+pub enum MakeClientStateError {
+    #[error("invalid chain id")]
+    ChainId(#[from] InvalidLength),
+    #[error("trusting period of {0:?} does not fit in u64 nanoseconds")]
+    TrustingPeriodOverflow(Duration),
+}
+
+/// Convert the unbonding time of the staking params (a protobuf `Duration`) into a [`Duration`].
+fn unbonding_period(seconds: i64, nanos: i32) -> Option<Duration> {
+    Some(Duration::new(
+        seconds.try_into().ok()?,
+        nanos.try_into().ok()?,
+    ))
+}
+
+/// Build the client state of the chain at `header`.
+///
+/// The trusting period is 85% of the `unbonding_period` of the chain. Unlike the tendermint client
+/// state, the cometbls client state has neither a trust level (the zk circuit verifies a 2/3 quorum)
+/// nor an unbonding period.
+pub fn make_client_state(
+    header: &Header,
+    chain_revision: u64,
+    unbonding_period: Duration,
+    contract_address: H256,
+) -> Result<ClientState, MakeClientStateError> {
+    // https://github.com/cosmos/relayer/blob/23d1e5c864b35d133cad6a0ef06970a2b1e1b03f/relayer/chains/cosmos/provider.go#L177
+    let trusting_period = unbonding_period * 85 / 100;
+
+    Ok(ClientState {
+        chain_id: cometbls_light_client_types::ChainId::from_string(header.chain_id.clone())?,
+        trusting_period: trusting_period
+            .as_nanos()
+            .try_into()
+            .map_err(|_| MakeClientStateError::TrustingPeriodOverflow(trusting_period))?,
+        max_clock_drift: MAX_CLOCK_DRIFT
+            .as_nanos()
+            .try_into()
+            .expect("value is within bounds; qed;"),
+        frozen_height: Height::new(0),
+        latest_height: Height::new_with_revision(
+            chain_revision,
+            header
+                .height
+                .inner()
+                .try_into()
+                .expect("value is >= 0; qed;"),
+        ),
+        contract_address,
+        zk_verifying_key_hash: None,
+    })
+}
+
+/// Build the consensus state of the chain at `header`.
+pub fn make_consensus_state(header: &Header) -> ConsensusState {
+    ConsensusState {
+        timestamp: header.time.as_unix_nanos(),
+        app_hash: MerkleRoot {
+            hash: header.app_hash.into_encoding(),
+        },
+        // the next header is verified against the validator set that signs it, which is committed
+        // to in this header as the *next* validators hash (not `validators_hash`, which is the
+        // validator set of this header)
+        next_validators_hash: header.next_validators_hash,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hex_literal::hex;
+    use unionlabs::encoding::{EncodeAs, EthAbi};
+
+    use super::*;
+
+    #[derive(Deserialize)]
+    struct JsonRpcResponse<T> {
+        result: T,
+    }
+
+    fn commit() -> CommitResponse {
+        serde_json::from_str::<JsonRpcResponse<CommitResponse>>(include_str!(
+            "../testdata/commit-union-testnet-9-2823301.json"
+        ))
+        .unwrap()
+        .result
+    }
+
+    const CONTRACT_ADDRESS: H256 = H256::new(hex!(
+        "a6e1ba2b6b3c8fbd0a3a2f52e0e2c4f2f4c2b0dd17bd2d97c6dcaad0de3a0a5f"
+    ));
+
+    // 21 days, the default of the staking module
+    const UNBONDING_PERIOD: Duration = Duration::from_secs(21 * 24 * 60 * 60);
+
+    #[test]
+    fn client_state() {
+        let client_state = make_client_state(
+            &commit().signed_header.header,
+            9,
+            UNBONDING_PERIOD,
+            CONTRACT_ADDRESS,
+        )
+        .unwrap();
+
+        assert_eq!(
+            client_state,
+            ClientState {
+                chain_id: cometbls_light_client_types::ChainId::from_string("union-testnet-9")
+                    .unwrap(),
+                trusting_period: 1_542_240_000_000_000,
+                max_clock_drift: 1_200_000_000_000,
+                frozen_height: Height::new(0),
+                latest_height: Height::new_with_revision(9, 2_823_301),
+                contract_address: CONTRACT_ADDRESS,
+                zk_verifying_key_hash: None,
+            }
+        );
+
+        assert_eq!(
+            client_state.encode_as::<EthAbi>(),
+            hex!(
+                "00000000000000000000000000000000756e696f6e2d746573746e65742d3900"
+                "00000000000000000000000000000000000000000000000000057aa8bb5bc000"
+                "000000000000000000000000000000000000000000000000000001176592e000"
+                "0000000000000000000000000000000000000000000000000000000000000000"
+                "00000000000000000000000000000000000000000000000000000000002b1485"
+                "a6e1ba2b6b3c8fbd0a3a2f52e0e2c4f2f4c2b0dd17bd2d97c6dcaad0de3a0a5f"
+            )
+        );
+    }
+
+    #[test]
+    fn client_state_invalid_chain_id() {
+        let mut header = commit().signed_header.header;
+        header.chain_id = "a-chain-id-that-is-longer-than-31-bytes-1".to_owned();
+
+        assert!(matches!(
+            make_client_state(&header, 1, UNBONDING_PERIOD, CONTRACT_ADDRESS),
+            Err(MakeClientStateError::ChainId(_))
+        ));
+    }
+
+    #[test]
+    fn consensus_state() {
+        let header = commit().signed_header.header;
+
+        let consensus_state = make_consensus_state(&header);
+
+        assert_eq!(
+            consensus_state,
+            ConsensusState {
+                timestamp: 1_733_400_847_593_911_474,
+                app_hash: MerkleRoot {
+                    hash: H256::new(hex!(
+                        "a172cedcae47474b615c54d510a5d84a8dea3032e958587430b413538be3f333"
+                    )),
+                },
+                next_validators_hash: H256::new(hex!(
+                    "4c76aa59f716a49db53697991a7e2f71276a75c75e25f23f5852e25896689d58"
+                )),
+            }
+        );
+
+        // the validator set changes at this height
+        assert_ne!(consensus_state.next_validators_hash, header.validators_hash);
+
+        assert_eq!(
+            consensus_state.encode_as::<EthAbi>(),
+            hex!(
+                "000000000000000000000000000000000000000000000000180e46e30e3234b2"
+                "a172cedcae47474b615c54d510a5d84a8dea3032e958587430b413538be3f333"
+                "4c76aa59f716a49db53697991a7e2f71276a75c75e25f23f5852e25896689d58"
+            )
+        );
+    }
+
+    #[test]
+    fn unbonding_period_from_proto() {
+        assert_eq!(
+            unbonding_period(1_814_400, 500),
+            Some(Duration::new(1_814_400, 500))
+        );
+        assert_eq!(unbonding_period(-1, 0), None);
+        assert_eq!(unbonding_period(1, -1), None);
     }
 }
@@ -52,6 +52,8 @@ pub struct Module {
     pub aptos_client: aptos_rest_client::Client,
 
     pub movement_rest_url: String,
+
+    pub trusting_period: u64,
 }
 
 impl ConsensusModule for Module {
@@ -75,6 +77,7 @@ impl ConsensusModule for Module {
             l1_settlement_address: config.l1_settlement_address,
             l1_client_id: config.l1_client_id,
             movement_rest_url: config.movement_rest_url,
+            trusting_period: config.trusting_period,
         })
     }
 }
@@ -101,6 +104,15 @@ pub struct Config {
 
     /// The RPC endpoint for custom movement apis.
     pub movement_rest_url: String,
+
+    /// The trusting period (in seconds) of clients tracking this chain.
+    #[serde(default = "default_trusting_period")]
+    pub trusting_period: u64,
+}
+
+const fn default_trusting_period() -> u64 {
+    // 14 days
+    14 * 24 * 60 * 60
 }
 
 impl Module {
@@ -167,6 +179,7 @@ impl ConsensusModuleServer for Module {
             )),
             frozen_height: Height::new(0),
             latest_block_num: height.height(),
+            trusting_period: self.trusting_period,
         })
         .expect("infallible"))
     }
@@ -172,6 +172,26 @@ impl ConsensusModuleServer for Module {
             .expect("should be fine"))
     }
 
+    #[instrument(skip_all, fields(chain_id = %self.chain_id, %height))]
+    async fn timestamp_at_height(&self, height: Height) -> RpcResult<i64> {
+        let block_height = NonZeroU64::new(height.height())
+            .ok_or_else(|| ErrorObject::owned(-1, "there is no block at height 0", None::<()>))?;
+
+        let block_response = self
+            .tm_client
+            .block(Some(block_height))
+            .await
+            .map_err(json_rpc_error_to_error_object)?;
+
+        Ok(block_response
+            .block
+            .header
+            .time
+            .as_unix_nanos()
+            .try_into()
+            .expect("should be fine"))
+    }
+
     #[instrument(skip_all, fields(chain_id = %self.chain_id))]
     async fn self_client_state(&self, _: &Extensions, height: Height) -> RpcResult<Value> {
         let params = protos::cosmos::staking::v1beta1::query_client::QueryClient::connect(
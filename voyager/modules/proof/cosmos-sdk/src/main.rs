@@ -116,14 +116,16 @@ impl ProofModuleServer<IbcClassic> for Module {
         at: Height,
         path: StorePath,
     ) -> RpcResult<Value> {
-        const IBC_STORE_PATH: &str = "store/ibc/key";
+        // the upgraded client and consensus states are committed in the upgrade store, everything
+        // else is committed in the ibc store
+        let store_path = format!("store/{}/key", path.store_key());
 
         let path_string = path.to_string();
 
         let query_result = self
             .tm_client
             .abci_query(
-                IBC_STORE_PATH,
+                &store_path,
                 &path_string,
                 // a proof at height H is provable at height H + 1
                 // we assume that the height passed in to this function is the intended height to prove against, thus we have to query the height - 1
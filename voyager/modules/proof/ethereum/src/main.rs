@@ -1,9 +1,17 @@
 #![warn(clippy::unwrap_used)]
 
+use std::future::IntoFuture;
+
 use alloy::{
-    providers::{Provider, ProviderBuilder, RootProvider},
+    providers::{Provider, RootProvider},
     transports::BoxTransport,
 };
+use chain_utils::{
+    auth::{self, EndpointAuth},
+    endpoint::{HttpUrl, DEFAULT_PROBE_TIMEOUT},
+    net::NetworkConfig,
+    timeout::{CallKind, TimeoutConfig},
+};
 use ethereum_light_client_types::StorageProof;
 use ibc_union_spec::{IbcUnion, StorePath};
 use jsonrpsee::{
@@ -13,15 +21,17 @@ use jsonrpsee::{
 };
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use tracing::instrument;
+use tracing::{debug, instrument};
 use unionlabs::{
     ethereum::ibc_commitment_key, hash::H160, ibc::core::client::height::Height, uint::U256,
     ErrorReporter,
 };
 use voyager_message::{
     core::ChainId,
+    error::VoyagerError,
     module::{ProofModuleInfo, ProofModuleServer},
-    ProofModule,
+    query::VoyagerQuery,
+    ExtensionsExt, ProofModule, VoyagerClient,
 };
 use voyager_vm::BoxDynError;
 
@@ -37,6 +47,12 @@ pub struct Module {
     pub ibc_handler_address: H160,
 
     pub provider: RootProvider<BoxTransport>,
+
+    pub timeouts: TimeoutConfig,
+
+    /// The beacon-kit consensus chain that heights are translated with, if any. See
+    /// [`HeightTranslatorConfig`].
+    pub height_translator: Option<ChainId>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -46,16 +62,55 @@ pub struct Config {
     pub ibc_handler_address: H160,
 
     /// The RPC endpoint for the execution chain.
-    pub eth_rpc_api: String,
+    pub eth_rpc_api: HttpUrl,
+
+    /// Authentication for `eth_rpc_api`, if the rpc provider requires it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub eth_rpc_api_auth: Option<EndpointAuth>,
+
+    /// Don't check that the configured endpoints are reachable on startup.
+    #[serde(default)]
+    pub skip_startup_probe: bool,
+
+    /// How connections to the configured endpoints are opened, i.e. through a proxy.
+    #[serde(default)]
+    pub network: NetworkConfig,
+
+    /// Timeouts of the calls to the configured endpoints. `eth_getProof` is bounded by the `proof`
+    /// timeout.
+    #[serde(default)]
+    pub timeouts: TimeoutConfig,
+
+    /// If set, the heights that proofs are queried at are treated as consensus heights, and are translated to the corresponding execution height before querying `eth_getProof`.
+    ///
+    /// This is required for chains where the consensus height is not the execution block number, such as beacon-kit chains. If this is not set, heights are used as execution heights as-is.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub height_translator: Option<HeightTranslatorConfig>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HeightTranslatorConfig {
+    /// The chain id of the beacon-kit consensus layer. Heights are translated with the `consensusToExecutionHeight` rpc of its consensus module, which reads the execution block number from the latest execution payload header stored in the beacon-kit `beacon` store at the consensus height.
+    pub consensus_chain_id: ChainId,
 }
 
 impl ProofModule<IbcUnion> for Module {
     type Config = Config;
 
     async fn new(config: Self::Config, info: ProofModuleInfo) -> Result<Self, BoxDynError> {
-        let provider = ProviderBuilder::new()
-            .on_builtin(&config.eth_rpc_api)
-            .await?;
+        if !config.skip_startup_probe {
+            config
+                .eth_rpc_api
+                .probe_via(&config.network, DEFAULT_PROBE_TIMEOUT)
+                .await?;
+        }
+
+        let provider = auth::http_provider(
+            &config.eth_rpc_api,
+            auth::resolve(config.eth_rpc_api_auth.as_ref())?.as_ref(),
+            &config.network,
+        )?;
 
         let chain_id = provider.get_chain_id().await?;
 
@@ -65,6 +120,10 @@ impl ProofModule<IbcUnion> for Module {
             chain_id: ChainId::new(chain_id.to_string()),
             ibc_handler_address: config.ibc_handler_address,
             provider,
+            timeouts: config.timeouts,
+            height_translator: config
+                .height_translator
+                .map(|height_translator| height_translator.consensus_chain_id),
         })
     }
 }
@@ -74,29 +133,53 @@ impl Module {
     pub fn make_height(&self, height: u64) -> Height {
         Height::new(height)
     }
-}
 
-#[async_trait]
-impl ProofModuleServer<IbcUnion> for Module {
-    #[instrument(skip_all, fields(chain_id = %self.chain_id))]
-    async fn query_ibc_proof(
+    /// The execution height to query proofs at for the provided height. If no height translator is configured, this is the provided height.
+    pub async fn execution_height(
+        &self,
+        voyager: &impl VoyagerQuery,
+        at: Height,
+    ) -> RpcResult<u64> {
+        match &self.height_translator {
+            Some(consensus_chain_id) => {
+                let execution_height = voyager
+                    .consensus_to_execution_height(consensus_chain_id, at)
+                    .await?;
+
+                debug!(consensus_height = %at, %execution_height, "translated consensus height to execution height");
+
+                Ok(execution_height)
+            }
+            None => Ok(at.height()),
+        }
+    }
+
+    /// Query the proof of `path` at `at`, translating the height with `voyager` if a height
+    /// translator is configured.
+    pub async fn query_proof(
         &self,
-        _: &Extensions,
+        voyager: &impl VoyagerQuery,
         at: Height,
         path: StorePath,
     ) -> RpcResult<Value> {
         let location = ibc_commitment_key(path.key());
 
-        let execution_height = at.height();
+        let execution_height = self.execution_height(voyager, at).await?;
 
         let proof = self
-            .provider
-            .get_proof(
-                self.ibc_handler_address.get().into(),
-                vec![location.to_be_bytes().into()],
+            .timeouts
+            .with_timeout(
+                CallKind::Proof,
+                self.provider
+                    .get_proof(
+                        self.ibc_handler_address.get().into(),
+                        vec![location.to_be_bytes().into()],
+                    )
+                    .block_id(execution_height.into())
+                    .into_future(),
             )
-            .block_id(execution_height.into())
             .await
+            .map_err(VoyagerError::from)?
             .map_err(|e| {
                 ErrorObject::owned(
                     -1,
@@ -125,3 +208,221 @@ impl ProofModuleServer<IbcUnion> for Module {
         Ok(serde_json::to_value(proof).expect("serialization is infallible; qed;"))
     }
 }
+
+#[async_trait]
+impl ProofModuleServer<IbcUnion> for Module {
+    #[instrument(skip_all, fields(chain_id = %self.chain_id, %at, %path))]
+    async fn query_ibc_proof(
+        &self,
+        e: &Extensions,
+        at: Height,
+        path: StorePath,
+    ) -> RpcResult<Value> {
+        self.query_proof(e.try_get::<VoyagerClient>()?, at, path)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ibc_union_spec::ClientStatePath;
+    use serde_json::json;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+    use voyager_message::testing::MockVoyager;
+
+    use super::*;
+
+    fn module(url: &str, height_translator: Option<ChainId>) -> Module {
+        Module {
+            chain_id: ChainId::new("1"),
+            ibc_handler_address: H160::default(),
+            provider: auth::http_provider(
+                &url.parse().expect("url is valid"),
+                None,
+                &NetworkConfig::default(),
+            )
+            .expect("provider is valid"),
+            timeouts: TimeoutConfig::default(),
+            height_translator,
+        }
+    }
+
+    fn path() -> StorePath {
+        StorePath::ClientState(ClientStatePath { client_id: 1 })
+    }
+
+    /// Serve a single `eth_getProof` request on `listener` with a proof of `slot`, returning the
+    /// request.
+    async fn serve_proof(listener: TcpListener, slot: U256) -> Value {
+        let (mut stream, _) = listener.accept().await.expect("able to accept");
+
+        let mut request = vec![];
+        let mut chunk = [0; 1024];
+        let body_start = loop {
+            let read = stream.read(&mut chunk).await.expect("able to read");
+            assert_ne!(read, 0, "connection closed before the request was read");
+            request.extend_from_slice(&chunk[..read]);
+
+            if let Some(end) = request.windows(4).position(|window| window == b"\r\n\r\n") {
+                break end + 4;
+            }
+        };
+
+        let headers = String::from_utf8_lossy(&request[..body_start]).to_ascii_lowercase();
+        let content_length = headers
+            .lines()
+            .find_map(|line| line.strip_prefix("content-length:"))
+            .expect("request has a content length")
+            .trim()
+            .parse::<usize>()
+            .expect("content length is valid");
+
+        while request.len() < body_start + content_length {
+            let read = stream.read(&mut chunk).await.expect("able to read");
+            assert_ne!(read, 0, "connection closed before the request was read");
+            request.extend_from_slice(&chunk[..read]);
+        }
+
+        let request =
+            serde_json::from_slice::<Value>(&request[body_start..]).expect("request is valid json");
+
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": request["id"],
+            "result": {
+                "address": H160::default(),
+                "balance": "0x0",
+                "codeHash": format!("0x{}", "00".repeat(32)),
+                "nonce": "0x0",
+                "storageHash": format!("0x{}", "00".repeat(32)),
+                "accountProof": [],
+                "storageProof": [{
+                    "key": format!("0x{}", hex_slot(slot)),
+                    "value": "0x1",
+                    "proof": ["0xc0"],
+                }],
+            },
+        })
+        .to_string();
+
+        stream
+            .write_all(
+                format!(
+                    "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\n\
+                    content-length: {}\r\n\r\n{body}",
+                    body.len()
+                )
+                .as_bytes(),
+            )
+            .await
+            .expect("able to write");
+
+        request
+    }
+
+    fn hex_slot(slot: U256) -> String {
+        slot.to_be_bytes()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn execution_height_passes_through_without_translator() {
+        let voyager = MockVoyager::new();
+
+        assert_eq!(
+            module("http://127.0.0.1:1", None)
+                .execution_height(&voyager, Height::new(1337))
+                .await,
+            Ok(1337)
+        );
+        assert!(voyager.calls("consensus_to_execution_height").is_empty());
+    }
+
+    #[tokio::test]
+    async fn execution_height_is_translated_by_consensus_module() {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("able to bind");
+        let url = format!(
+            "http://{}",
+            listener.local_addr().expect("listener is bound")
+        );
+
+        let server = tokio::spawn(serve_proof(listener, ibc_commitment_key(path().key())));
+
+        // the beacon-kit consensus module reports the execution block of consensus height 1337
+        let voyager = MockVoyager::new();
+        voyager.set_execution_height(&ChainId::new("beacon-kit-1"), Height::new(1337), 1400);
+
+        let proof = module(&url, Some(ChainId::new("beacon-kit-1")))
+            .query_proof(&voyager, Height::new(1337), path())
+            .await
+            .expect("proof is queried");
+
+        let request = server.await.expect("server does not panic");
+
+        assert_eq!(request["method"], "eth_getProof");
+        // the block of the proof is the translated execution height
+        assert_eq!(request["params"][2], "0x578");
+
+        assert_eq!(
+            voyager.calls("consensus_to_execution_height"),
+            [json!({
+                "chain_id": "beacon-kit-1",
+                "height": Height::new(1337),
+            })]
+        );
+
+        assert_eq!(
+            serde_json::from_value::<StorageProof>(proof)
+                .expect("proof is valid")
+                .key,
+            ibc_commitment_key(path().key())
+        );
+    }
+
+    #[tokio::test]
+    async fn untranslatable_height_is_an_error() {
+        let voyager = MockVoyager::new();
+
+        module("http://127.0.0.1:1", Some(ChainId::new("beacon-kit-1")))
+            .query_proof(&voyager, Height::new(1337), path())
+            .await
+            .expect_err("the consensus module did not translate the height");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn query_ibc_proof_times_out() {
+        // accepts connections, but never responds to requests
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("able to bind");
+        let url = format!(
+            "http://{}",
+            listener.local_addr().expect("listener is bound")
+        );
+        tokio::spawn(async move {
+            let mut connections = vec![];
+            loop {
+                connections.push(listener.accept().await.expect("able to accept"));
+            }
+        });
+
+        let module = module(&url, None);
+
+        let err = module
+            .query_proof(&MockVoyager::new(), Height::new(100), path())
+            .await
+            .expect_err("proof query times out");
+
+        assert_eq!(
+            VoyagerError::from_error_object(&err),
+            VoyagerError::retryable("proof call timed out after 20s")
+        );
+    }
+}
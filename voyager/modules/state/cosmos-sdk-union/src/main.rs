@@ -9,11 +9,12 @@ use std::{
 
 use cometbft_rpc::types::abci::response_query::QueryResponse;
 use dashmap::DashMap;
+use futures::{stream, StreamExt, TryStreamExt};
 use ibc_solidity::{Channel, Connection};
-use ibc_union_spec::{IbcUnion, StorePath};
+use ibc_union_spec::{IbcUnion, StorePath, UnionId};
 use jsonrpsee::{
     core::{async_trait, RpcResult},
-    types::{ErrorObject, ErrorObjectOwned},
+    types::{error::INVALID_PARAMS_CODE, ErrorObject, ErrorObjectOwned},
     Extensions,
 };
 use prost::Message;
@@ -29,6 +30,9 @@ use unionlabs::{
     ErrorReporter, WasmClientType,
 };
 use voyager_message::{
+    consensus_heights::{
+        walk_heights, ConsensusStateHeights, Pagination, QueryRange, RangeClient, RangeEntry,
+    },
     core::{ChainId, ClientInfo, ClientType, IbcInterface},
     into_value,
     module::{StateModuleInfo, StateModuleServer},
@@ -36,6 +40,9 @@ use voyager_message::{
 };
 use voyager_vm::BoxDynError;
 
+/// The number of keys of a range query that are queried concurrently.
+const RANGE_QUERY_CONCURRENCY: usize = 16;
+
 #[tokio::main(flavor = "multi_thread")]
 async fn main() {
     <Module as StateModule<IbcUnion>>::run().await;
@@ -194,6 +201,32 @@ impl Module {
         Ok(client_state.map(Bytes::into_encoding))
     }
 
+    /// The heights in `range` that `client_id` has a consensus state at, at
+    /// `height`.
+    ///
+    /// The consensus states are stored in a mapping in the ibc-union contract,
+    /// which is queried key by key.
+    #[instrument(skip_all, fields(chain_id = %self.chain_id, %height, %client_id, start = range.start, end = range.end))]
+    async fn query_consensus_state_range(
+        &self,
+        height: Height,
+        client_id: u32,
+        range: QueryRange,
+    ) -> RpcResult<Vec<(u64, Bytes)>> {
+        range.validate()?;
+
+        stream::iter(range.keys())
+            .map(|trusted_height| async move {
+                self.query_consensus_state(height, client_id, trusted_height)
+                    .await
+                    .map(|state| state.map(|state| (trusted_height, state)))
+            })
+            .buffered(RANGE_QUERY_CONCURRENCY)
+            .try_filter_map(|entry| async move { Ok(entry) })
+            .try_collect()
+            .await
+    }
+
     #[instrument(skip_all, fields(chain_id = %self.chain_id, %height, %connection_id))]
     async fn query_connection(
         &self,
@@ -263,6 +296,25 @@ impl Module {
     }
 }
 
+/// The consensus states of a client, at a height.
+struct ConsensusStates<'a> {
+    module: &'a Module,
+    at: Height,
+    client_id: u32,
+}
+
+impl RangeClient for ConsensusStates<'_> {
+    async fn query_range(&self, range: QueryRange) -> RpcResult<Vec<u64>> {
+        Ok(self
+            .module
+            .query_consensus_state_range(self.at, self.client_id, range)
+            .await?
+            .into_iter()
+            .map(|(height, _)| height)
+            .collect())
+    }
+}
+
 #[derive(Debug, thiserror::Error)]
 #[error("unable to parse chain id: expected format `<chain>-<revision-number>`, found `{found}`")]
 pub struct ChainIdParseError {
@@ -274,10 +326,12 @@ pub struct ChainIdParseError {
 #[async_trait]
 impl StateModuleServer<IbcUnion> for Module {
     #[instrument(skip_all, fields(chain_id = %self.chain_id))]
-    async fn client_info(&self, _: &Extensions, client_id: u32) -> RpcResult<ClientInfo> {
+    async fn client_info(&self, _: &Extensions, client_id: UnionId) -> RpcResult<ClientInfo> {
         let client_type = self
             .query_smart::<_, String>(
-                &union_ibc_msg::query::QueryMsg::GetClientType { client_id },
+                &union_ibc_msg::query::QueryMsg::GetClientType {
+                    client_id: client_id.get(),
+                },
                 None,
             )
             .await?
@@ -294,6 +348,69 @@ impl StateModuleServer<IbcUnion> for Module {
         })
     }
 
+    #[instrument(skip_all, fields(chain_id = %self.chain_id, %at))]
+    async fn query_range(
+        &self,
+        at: Height,
+        path: StorePath,
+        range: QueryRange,
+    ) -> RpcResult<Vec<RangeEntry>> {
+        match path {
+            StorePath::ConsensusState(path) => Ok(self
+                .query_consensus_state_range(at, path.client_id, range)
+                .await?
+                .into_iter()
+                .map(|(key, state)| RangeEntry {
+                    key,
+                    state: into_value(state),
+                })
+                .collect()),
+            _ => Err(ErrorObject::owned(
+                INVALID_PARAMS_CODE,
+                "range queries are only supported for consensus states",
+                Some(json!({ "path": path })),
+            )),
+        }
+    }
+
+    #[instrument(skip_all, fields(chain_id = %self.chain_id, %at, %client_id))]
+    async fn consensus_state_heights(
+        &self,
+        at: Height,
+        client_id: UnionId,
+        pagination: Pagination,
+    ) -> RpcResult<ConsensusStateHeights> {
+        let latest_height = self
+            .query_smart::<_, u64>(
+                &union_ibc_msg::query::QueryMsg::GetLatestHeight {
+                    client_id: client_id.get(),
+                },
+                Some(at),
+            )
+            .await?
+            .ok_or(ErrorObject::owned(
+                FATAL_JSONRPC_ERROR_CODE,
+                format!("client `{client_id}` not found"),
+                None::<()>,
+            ))?;
+
+        let heights = walk_heights(
+            &ConsensusStates {
+                module: self,
+                at,
+                client_id: client_id.get(),
+            },
+            latest_height,
+            pagination,
+        )
+        .await?;
+
+        Ok(ConsensusStateHeights {
+            heights: heights.into_iter().map(Height::new).collect(),
+            total: None,
+        })
+    }
+
     #[instrument(skip_all, fields(chain_id = %self.chain_id))]
     async fn query_ibc_state(
         &self,
@@ -19,6 +19,13 @@ use jsonrpsee::{
     types::{ErrorObject, ErrorObjectOwned},
     Extensions,
 };
+use prost::Message;
+use protos::{
+    cosmos::base::query::v1beta1::PageRequest,
+    ibc::core::client::v1::{
+        QueryConsensusStateHeightsRequest, QueryConsensusStateHeightsResponse,
+    },
+};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use tracing::{debug, error, info, instrument, warn};
@@ -34,7 +41,11 @@ use unionlabs::{
     parse_wasm_client_type, ErrorReporter, WasmClientType,
 };
 use voyager_message::{
+    consensus_heights::{
+        collect_pages, ConsensusStateHeights, HeightsPage, HeightsPageClient, Pagination,
+    },
     core::{ChainId, ClientInfo, ClientType, IbcGo08WasmClientMetadata, IbcInterface},
+    error::VoyagerError,
     into_value,
     module::{StateModuleInfo, StateModuleServer},
     StateModule, FATAL_JSONRPC_ERROR_CODE,
@@ -43,6 +54,14 @@ use voyager_vm::BoxDynError;
 
 const IBC_STORE_PATH: &str = "store/ibc/key";
 
+/// The store that `x/upgrade` commits the upgraded client and consensus states in.
+const UPGRADE_STORE_PATH: &str = "store/upgrade/key";
+
+const CONSENSUS_STATE_HEIGHTS_PATH: &str = "/ibc.core.client.v1.Query/ConsensusStateHeights";
+
+/// The number of heights requested per `ConsensusStateHeights` query.
+const CONSENSUS_STATE_HEIGHTS_PAGE_SIZE: u64 = 500;
+
 #[tokio::main(flavor = "multi_thread")]
 async fn main() {
     <Module as StateModule<IbcClassic>>::run().await;
@@ -228,9 +247,20 @@ impl Module {
     }
 
     async fn abci_query(&self, path_string: &str, height: Height) -> RpcResult<QueryResponse> {
-        self.tm_client
+        self.abci_query_store(IBC_STORE_PATH, path_string, height)
+            .await
+    }
+
+    async fn abci_query_store(
+        &self,
+        store_path: &str,
+        path_string: &str,
+        height: Height,
+    ) -> RpcResult<QueryResponse> {
+        let response = self
+            .tm_client
             .abci_query(
-                IBC_STORE_PATH,
+                store_path,
                 &path_string,
                 Some(
                     i64::try_from(height.height())
@@ -241,11 +271,45 @@ impl Module {
                 false,
             )
             .await
-            .map_err(rpc_error(
-                format_args!("error fetching abci query"),
-                Some(json!({ "height": height, "path": path_string })),
+            .map_err(|err| {
+                if is_pruned_height_error(&ErrorReporter(&err).to_string()) {
+                    VoyagerError::pruned(height).into()
+                } else {
+                    rpc_error(
+                        "error fetching abci query",
+                        Some(json!({ "height": height, "path": path_string })),
+                    )(err)
+                }
+            })?
+            .response;
+
+        if response.code != 0 && is_pruned_height_error(&response.log) {
+            debug!(%height, log = %response.log, "height has been pruned");
+            return Err(VoyagerError::pruned(height).into());
+        }
+
+        Ok(response)
+    }
+
+    /// Query one of the upgraded client or consensus states committed by `x/upgrade` for a scheduled
+    /// upgrade. These are only set from when the upgrade is scheduled until it is applied, so an
+    /// empty query is retried rather than treated as fatal.
+    #[instrument(skip_all, fields(chain_id = %self.chain_id, %height, %path))]
+    async fn query_upgraded_state(&self, height: Height, path: StorePath) -> RpcResult<Bytes> {
+        let path_string = path.to_string();
+
+        let query_result = self
+            .abci_query_store(UPGRADE_STORE_PATH, &path_string, height)
+            .await?;
+
+        match query_result.value {
+            Some(value) if !value.is_empty() => Ok(value.into_encoding()),
+            _ => Err(VoyagerError::retryable(format!(
+                "{path_string} is not set at height {height}, the upgrade may not be scheduled \
+                yet or may already be applied"
             ))
-            .map(|response| response.response)
+            .into()),
+        }
     }
 
     #[instrument(skip_all, fields(chain_id = %self.chain_id, %height, %client_id))]
@@ -495,6 +559,80 @@ impl Module {
     }
 }
 
+/// The pages of the `ConsensusStateHeights` query for a client, at a height.
+struct ConsensusStateHeightsPages<'a> {
+    module: &'a Module,
+    at: Height,
+    client_id: &'a ClientId,
+}
+
+impl HeightsPageClient for ConsensusStateHeightsPages<'_> {
+    async fn heights_page(&self, key: Vec<u8>) -> RpcResult<HeightsPage> {
+        let response = self
+            .module
+            .tm_client
+            .abci_query(
+                CONSENSUS_STATE_HEIGHTS_PATH,
+                QueryConsensusStateHeightsRequest {
+                    client_id: self.client_id.to_string(),
+                    pagination: Some(PageRequest {
+                        key,
+                        offset: 0,
+                        limit: CONSENSUS_STATE_HEIGHTS_PAGE_SIZE,
+                        count_total: false,
+                        reverse: false,
+                    }),
+                }
+                .encode_to_vec(),
+                Some(
+                    i64::try_from(self.at.height())
+                        .expect("should be fine")
+                        .try_into()
+                        .expect("invalid height"),
+                ),
+                false,
+            )
+            .await
+            .map_err(rpc_error(
+                "error fetching consensus state heights",
+                Some(json!({ "height": self.at, "client_id": self.client_id })),
+            ))?
+            .response;
+
+        if response.code != 0 {
+            if is_pruned_height_error(&response.log) {
+                return Err(VoyagerError::pruned(self.at).into());
+            }
+
+            return Err(ErrorObject::owned(
+                -1,
+                format!("error fetching consensus state heights: {}", response.log),
+                Some(json!({ "height": self.at, "client_id": self.client_id })),
+            ));
+        }
+
+        let response = QueryConsensusStateHeightsResponse::decode(
+            response.value.as_deref().unwrap_or_default(),
+        )
+        .map_err(rpc_error(
+            "error decoding consensus state heights",
+            Some(json!({ "height": self.at, "client_id": self.client_id })),
+        ))?;
+
+        Ok(HeightsPage {
+            heights: response
+                .consensus_state_heights
+                .into_iter()
+                .map(Into::into)
+                .collect(),
+            next_key: response
+                .pagination
+                .map(|pagination| pagination.next_key)
+                .unwrap_or_default(),
+        })
+    }
+}
+
 #[derive(Debug, thiserror::Error)]
 #[error("unable to parse chain id: expected format `<chain>-<revision-number>`, found `{found}`")]
 pub struct ChainIdParseError {
@@ -550,6 +688,24 @@ impl StateModuleServer<IbcClassic> for Module {
         }
     }
 
+    #[instrument(skip_all, fields(chain_id = %self.chain_id, %at, %client_id))]
+    async fn consensus_state_heights(
+        &self,
+        at: Height,
+        client_id: ClientId,
+        pagination: Pagination,
+    ) -> RpcResult<ConsensusStateHeights> {
+        Ok(collect_pages(
+            &ConsensusStateHeightsPages {
+                module: self,
+                at,
+                client_id: &client_id,
+            },
+            pagination,
+        )
+        .await?)
+    }
+
     #[instrument(skip_all, fields(chain_id = %self.chain_id))]
     async fn query_ibc_state(
         &self,
@@ -605,6 +761,9 @@ impl StateModuleServer<IbcClassic> for Module {
             StorePath::NextClientSequence(_path) => {
                 self.query_next_client_sequence(at).await.map(into_value)
             }
+            path @ (StorePath::UpgradedClientState(_) | StorePath::UpgradedConsensusState(_)) => {
+                self.query_upgraded_state(at, path).await.map(into_value)
+            }
         }
     }
 
@@ -614,6 +773,11 @@ impl StateModuleServer<IbcClassic> for Module {
 
     //     const IBC_STORE_PATH: &str = "store/ibc/key";
 
+    const CONSENSUS_STATE_HEIGHTS_PATH: &str = "/ibc.core.client.v1.Query/ConsensusStateHeights";
+
+    /// The number of heights requested per `ConsensusStateHeights` query.
+    const CONSENSUS_STATE_HEIGHTS_PAGE_SIZE: u64 = 500;
+
     //     let path_string = path.to_string();
 
     //     let query_result = self
@@ -659,6 +823,16 @@ impl StateModuleServer<IbcClassic> for Module {
 
 // NOTE: For both of the below functions, `message` as a field will override any actual message put in (i.e. `error!("foo", message = "bar")` will print as "bar", not "foo" with an extra field `message = "bar"`.
 
+/// Whether the error returned by the node for a query indicates that the queried height has been
+/// pruned (as opposed to it not existing yet, or any other error).
+fn is_pruned_height_error(message: &str) -> bool {
+    // iavl: "version does not exist"
+    // cometbft: "height 1 is not available, lowest height is 100"
+    message.contains("version does not exist")
+        || message.contains("has been pruned")
+        || message.contains("lowest height is")
+}
+
 fn rpc_error<E: Error>(
     message: impl Display,
     data: Option<Value>,
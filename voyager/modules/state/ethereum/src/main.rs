@@ -11,7 +11,7 @@ use ibc_solidity::{
     Channel, Connection, ILightClient,
     Ibc::{self, IbcInstance},
 };
-use ibc_union_spec::{BatchPacketsPath, BatchReceiptsPath, IbcUnion, StorePath};
+use ibc_union_spec::{BatchPacketsPath, BatchReceiptsPath, IbcUnion, StorePath, UnionId};
 use jsonrpsee::{
     core::{async_trait, RpcResult},
     types::ErrorObject,
@@ -350,9 +350,14 @@ impl StateModuleServer<IbcUnion> for Module {
     }
 
     #[instrument(skip_all, fields(chain_id = %self.chain_id))]
-    async fn client_info(&self, _: &Extensions, client_id: u32) -> RpcResult<ClientInfo> {
+    async fn client_info(&self, _: &Extensions, client_id: UnionId) -> RpcResult<ClientInfo> {
         let ibc_handler = self.ibc_handler();
-        let client_type = ibc_handler.clientTypes(client_id).call().await.unwrap()._0;
+        let client_type = ibc_handler
+            .clientTypes(client_id.get())
+            .call()
+            .await
+            .unwrap()
+            ._0;
         Ok(ClientInfo {
             client_type: ClientType::new(client_type),
             ibc_interface: IbcInterface::new(IbcInterface::IBC_SOLIDITY),
@@ -3,7 +3,7 @@ use std::fmt::Debug;
 use aptos_move_ibc::ibc::ClientExt as _;
 use aptos_rest_client::{aptos_api_types::Address, error::RestError};
 use aptos_types::state_store::state_value::PersistedStateValueMetadata;
-use ibc_union_spec::{IbcUnion, StorePath};
+use ibc_union_spec::{IbcUnion, StorePath, UnionId};
 use jsonrpsee::{
     core::{async_trait, RpcResult},
     types::{ErrorObject, ErrorObjectOwned},
@@ -158,7 +158,7 @@ impl Module {
 #[async_trait]
 impl StateModuleServer<IbcUnion> for Module {
     #[instrument(skip_all, fields(chain_id = %self.chain_id))]
-    async fn client_info(&self, _: &Extensions, client_id: u32) -> RpcResult<ClientInfo> {
+    async fn client_info(&self, _: &Extensions, client_id: UnionId) -> RpcResult<ClientInfo> {
         match client_id.to_string().rsplit_once('-') {
             Some(("cometbls", _)) => Ok(ClientInfo {
                 client_type: ClientType::new(ClientType::COMETBLS_GROTH16),
@@ -1,5 +1,5 @@
+use chain_utils::light_block::build_cometbls_update_header;
 use cometbft_types::types::signed_header::SignedHeader;
-use cometbls_light_client_types::{header::Header, light_header::LightHeader};
 use enumorph::Enumorph;
 use macros::model;
 use subset_of::SubsetOf;
@@ -55,20 +55,11 @@ impl Module {
                         signed_header.header.height.inner().try_into().unwrap(),
                     ),
                 },
-                serde_json::to_value(Header {
-                    signed_header: LightHeader {
-                        height: signed_header.header.height,
-                        time: signed_header.header.time,
-                        validators_hash: signed_header.header.validators_hash.into_encoding(),
-                        next_validators_hash: signed_header
-                            .header
-                            .next_validators_hash
-                            .into_encoding(),
-                        app_hash: signed_header.header.app_hash.into_encoding(),
-                    },
-                    trusted_height: update_from,
-                    zero_knowledge_proof: response.proof.evm_proof,
-                })
+                serde_json::to_value(build_cometbls_update_header(
+                    update_from,
+                    &signed_header,
+                    response.proof.evm_proof,
+                ))
                 .unwrap(),
             )],
         })
@@ -1,37 +1,24 @@
-use std::{
-    collections::{HashMap, VecDeque},
-    num::ParseIntError,
-};
+use std::{collections::VecDeque, num::ParseIntError};
 
-use cometbft_types::{
-    crypto::public_key::PublicKey,
-    types::{
-        canonical_block_id::CanonicalBlockId, canonical_part_set_header::CanonicalPartSetHeader,
-        commit_sig::CommitSig, signed_msg_type::SignedMsgType, simple_validator::SimpleValidator,
-        validator::Validator,
-    },
-};
+use chain_utils::light_block::{build_cometbls_prove_request, fetch_light_block, fetch_validators};
 use galois_rpc::{
-    canonical_vote::CanonicalVote,
     poll_request::PollRequest,
     poll_response::{PollResponse, ProveRequestDone, ProveRequestFailed},
-    prove_request::ProveRequest,
-    validator_set_commit::ValidatorSetCommit,
 };
 use itertools::Itertools;
 use jsonrpsee::{
     core::{async_trait, RpcResult},
     Extensions,
 };
-use num_bigint::BigUint;
 use protos::union::galois::api::v3::union_prover_api_client;
 use serde::{Deserialize, Serialize};
-use tracing::{debug, error, info, instrument, trace};
-use unionlabs::bounded::BoundedI64;
+use tracing::{debug, error, info, instrument};
 use voyager_message::{
     call::{Call, WaitForHeight},
+    cmd::CmdOutput,
     core::ChainId,
     data::Data,
+    error::VoyagerError,
     hook::UpdateHook,
     module::{PluginInfo, PluginServer},
     DefaultCmd, Plugin, PluginMessage, VoyagerMessage,
@@ -122,10 +109,13 @@ impl Plugin for Module {
         PluginInfo {
             name: plugin_name(&config.chain_id),
             interest_filter: UpdateHook::filter(&config.chain_id),
+            kind: None,
+            chains: vec![],
+            ibc_specs: vec![],
         }
     }
 
-    async fn cmd(_config: Self::Config, cmd: Self::Cmd) {
+    async fn cmd(_config: Self::Config, cmd: Self::Cmd) -> Result<Box<dyn CmdOutput>, BoxDynError> {
         match cmd {}
     }
 }
@@ -189,123 +179,18 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
                 update_from,
                 update_to,
             }) => {
-                let trusted_validators = self
-                    .tm_client
-                    .all_validators(Some(update_from.height().try_into().unwrap()))
-                    .await
-                    .unwrap()
-                    .validators;
-
-                let untrusted_validators = self
-                    .tm_client
-                    .all_validators(Some(update_to.height().try_into().unwrap()))
-                    .await
-                    .unwrap()
-                    .validators;
-
-                let signed_header = self
-                    .tm_client
-                    .commit(Some(update_to.height().try_into().unwrap()))
-                    .await
-                    .unwrap()
-                    .signed_header;
-
-                let make_validators_commit = |mut validators: Vec<Validator>| {
-                    // Validators must be sorted to match the root, by token then address
-                    validators.sort_by(|a, b| {
-                        // TODO: Double check how these comparisons are supposed to work
-                        #[allow(clippy::collapsible_else_if)]
-                        if a.voting_power == b.voting_power {
-                            if a.address < b.address {
-                                std::cmp::Ordering::Less
-                            } else {
-                                std::cmp::Ordering::Greater
-                            }
-                        } else {
-                            if a.voting_power > b.voting_power {
-                                std::cmp::Ordering::Less
-                            } else {
-                                std::cmp::Ordering::Greater
-                            }
-                        }
-                    });
-
-                    // The bitmap is a public input of the circuit, it must fit in Fr (scalar field) bn254
-                    let mut bitmap = BigUint::default();
-                    // REVIEW: This will over-allocate for the trusted validators; should be benchmarked
-                    let mut signatures = Vec::<Vec<u8>>::with_capacity(validators.len());
-
-                    let validators_map = validators
-                        .iter()
-                        .enumerate()
-                        .map(|(i, v)| (v.address, i))
-                        .collect::<HashMap<_, _>>();
-
-                    // For each validator signature, we search for the actual validator
-                    // in the set and set it's signed bit to 1. We then push the
-                    // signature only if the validator signed. It's possible that we
-                    // don't find a validator for a given signature as the validator set
-                    // may have drifted (trusted validator set).
-                    for sig in signed_header.commit.signatures.iter() {
-                        match sig {
-                            CommitSig::Absent => {
-                                debug!("validator did not sign");
-                            }
-                            CommitSig::Commit {
-                                validator_address,
-                                timestamp: _,
-                                signature,
-                            } => {
-                                if let Some(validator_index) =
-                                    validators_map.get(validator_address.as_encoding())
-                                {
-                                    bitmap.set_bit(*validator_index as u64, true);
-                                    signatures.push(signature.clone().into());
-                                    trace!(
-                                        %validator_address,
-                                        %validator_index,
-                                        "validator signed"
-                                    );
-                                } else {
-                                    trace!(
-                                        %validator_address,
-                                        "validator set drifted, could not find validator signature"
-                                    );
-                                }
-                            }
-                            CommitSig::Nil {
-                                validator_address, ..
-                            } => {
-                                trace!(
-                                    %validator_address,
-                                    "validator commit is nil"
-                                );
-                            }
-                        }
-                    }
+                let trusted_validators =
+                    fetch_validators(&self.tm_client, update_from.height().try_into().unwrap())
+                        .await
+                        .map_err(VoyagerError::from)?;
 
-                    let simple_validators = validators
-                        .iter()
-                        .map(|v| {
-                            let PublicKey::Bn254(ref key) = v.pub_key else {
-                                panic!("must be bn254")
-                            };
-                            SimpleValidator {
-                                pub_key: PublicKey::Bn254(key.clone()),
-                                voting_power: v.voting_power.into(),
-                            }
-                        })
-                        .collect::<Vec<_>>();
-
-                    ValidatorSetCommit {
-                        validators: simple_validators,
-                        signatures,
-                        bitmap: bitmap.to_bytes_be(),
-                    }
-                };
+                let target =
+                    fetch_light_block(&self.tm_client, update_to.height().try_into().unwrap())
+                        .await
+                        .map_err(VoyagerError::from)?;
 
-                let trusted_validators_commit = make_validators_commit(trusted_validators);
-                let untrusted_validators_commit = make_validators_commit(untrusted_validators);
+                let request = build_cometbls_prove_request(trusted_validators, &target)
+                    .map_err(VoyagerError::from)?;
 
                 Ok(seq([
                     void(call(WaitForHeight {
@@ -316,52 +201,14 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
                     promise(
                         [call(PluginMessage::new(
                             self.plugin_name(),
-                            ModuleCall::from(FetchProveRequest {
-                                request: ProveRequest {
-                                    vote: CanonicalVote {
-                                        // REVIEW: Should this be hardcoded to precommit?
-                                        ty: SignedMsgType::Precommit,
-                                        height: signed_header.commit.height,
-                                        round: BoundedI64::new_const(
-                                            signed_header.commit.round.inner().into(),
-                                        )
-                                        .expect(
-                                            "0..=i32::MAX can be converted to 0..=i64::MAX safely",
-                                        ),
-                                        block_id: CanonicalBlockId {
-                                            hash: signed_header
-                                                .commit
-                                                .block_id
-                                                .hash
-                                                .unwrap_or_default(),
-                                            part_set_header: CanonicalPartSetHeader {
-                                                total: signed_header
-                                                    .commit
-                                                    .block_id
-                                                    .part_set_header
-                                                    .total,
-                                                hash: signed_header
-                                                    .commit
-                                                    .block_id
-                                                    .part_set_header
-                                                    .hash
-                                                    .unwrap_or_default(),
-                                            },
-                                        },
-                                        chain_id: signed_header.header.chain_id.clone(),
-                                    },
-                                    untrusted_header: signed_header.header.clone(),
-                                    trusted_commit: trusted_validators_commit,
-                                    untrusted_commit: untrusted_validators_commit,
-                                },
-                            }),
+                            ModuleCall::from(FetchProveRequest { request }),
                         ))],
                         [],
                         PluginMessage::new(
                             self.plugin_name(),
                             ModuleCallback::from(AggregateHeader {
                                 chain_id: self.chain_id.clone(),
-                                signed_header,
+                                signed_header: target.signed_header,
                                 update_from,
                                 update_to,
                             }),
@@ -11,7 +11,7 @@ use beacon_api::{client::BeaconApiClient, types::Spec};
 use beacon_api_types::{
     light_client_update::NextSyncCommitteeBranch, PresetBaseKind, SyncCommittee,
 };
-use bitvec::{order::Msb0, vec::BitVec};
+use bitvec::{order::Msb0, slice::BitSlice, vec::BitVec};
 use ethereum_light_client_types::{
     AccountProof, EpochChangeUpdate, Header, LightClientUpdate, LightClientUpdateData,
     WithinEpochUpdate,
@@ -32,6 +32,7 @@ use unionlabs::{
 };
 use voyager_message::{
     call::{Call, FetchUpdateHeaders, WaitForTimestamp},
+    cmd::CmdOutput,
     core::ChainId,
     data::{Data, DecodedHeaderMeta, OrderedHeaders},
     hook::UpdateHook,
@@ -179,10 +180,13 @@ impl Plugin for Module {
         PluginInfo {
             name: plugin_name(&config.chain_id),
             interest_filter: UpdateHook::filter(&config.chain_id),
+            kind: None,
+            chains: vec![],
+            ibc_specs: vec![],
         }
     }
 
-    async fn cmd(_config: Self::Config, cmd: Self::Cmd) {
+    async fn cmd(_config: Self::Config, cmd: Self::Cmd) -> Result<Box<dyn CmdOutput>, BoxDynError> {
         match cmd {}
     }
 }
@@ -348,11 +352,9 @@ impl Module {
             )
             .expect("sync committee bits should be valid");
 
-            let sync_committee_size = spec.sync_committee_size;
+            assert_eq!(sync_committee_bits.len() as u64, spec.sync_committee_size);
 
-            assert_eq!(sync_committee_bits.len() as u64, sync_committee_size);
-
-            sync_committee_bits.count_ones() * 3 < sync_committee_bits.len() * 2
+            !has_supermajority(&sync_committee_bits)
         };
 
         if does_not_have_has_supermajority {
@@ -396,9 +398,11 @@ impl Module {
 
         // let target_period = sync_committee_period(finality_update.signature_slot, spec.period());
 
+        let (start_period, count) = epoch_change_update_range(trusted_period, target_period);
+
         let light_client_updates = self
             .beacon_api_client
-            .light_client_updates(trusted_period + 1, target_period - trusted_period)
+            .light_client_updates(start_period, count)
             .await
             .map_err(|e| {
                 ErrorObject::owned(
@@ -467,7 +471,7 @@ impl Module {
         };
 
         let does_not_have_finality_update =
-            last_update_block_number >= update_to_block_number.height();
+            !requires_finality_update(last_update_block_number, update_to_block_number.height());
 
         debug!(last_update_block_number, %update_to_block_number);
 
@@ -578,10 +582,10 @@ impl Module {
     ) -> RpcResult<Header> {
         // When we fetch the update at this height, the `next_sync_committee` will
         // be the current sync committee of the period that we want to update to.
-        let previous_period = u64::max(
-            1,
-            light_client_update_data.finalized_header.beacon.slot / spec.period(),
-        ) - 1;
+        let previous_period = previous_sync_committee_period(
+            light_client_update_data.finalized_header.beacon.slot,
+            spec.period(),
+        );
 
         let ibc_account_proof = self
             .fetch_account_update(
@@ -640,3 +644,94 @@ impl Module {
 fn sync_committee_period(slot: u64, period: u64) -> u64 {
     slot.div(period)
 }
+
+/// The sync committee period before the period of `slot`, saturating at period 0.
+fn previous_sync_committee_period(slot: u64, period: u64) -> u64 {
+    u64::max(1, sync_committee_period(slot, period)) - 1
+}
+
+/// The start period and count of the light client updates required to go from `trusted_period` to
+/// `target_period`. One epoch change update is required for every period boundary crossed.
+fn epoch_change_update_range(trusted_period: u64, target_period: u64) -> (u64, u64) {
+    (trusted_period + 1, target_period - trusted_period)
+}
+
+/// Whether a finality update is required on top of the epoch change updates, i.e. whether the last
+/// epoch change update does not already reach the requested block number.
+fn requires_finality_update(last_update_block_number: u64, update_to_block_number: u64) -> bool {
+    last_update_block_number < update_to_block_number
+}
+
+/// Whether at least 2/3 of the sync committee participated in the sync aggregate.
+fn has_supermajority(sync_committee_bits: &BitSlice<u8, Msb0>) -> bool {
+    sync_committee_bits.count_ones() * 3 >= sync_committee_bits.len() * 2
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // slots per sync committee period on mainnet
+    const PERIOD: u64 = 8192;
+
+    #[test]
+    fn sync_committee_period_boundaries() {
+        assert_eq!(sync_committee_period(0, PERIOD), 0);
+        assert_eq!(sync_committee_period(PERIOD - 1, PERIOD), 0);
+        assert_eq!(sync_committee_period(PERIOD, PERIOD), 1);
+        assert_eq!(sync_committee_period(2 * PERIOD + 1, PERIOD), 2);
+    }
+
+    #[test]
+    fn previous_sync_committee_period_saturates() {
+        assert_eq!(previous_sync_committee_period(0, PERIOD), 0);
+        assert_eq!(previous_sync_committee_period(PERIOD - 1, PERIOD), 0);
+        assert_eq!(previous_sync_committee_period(PERIOD, PERIOD), 0);
+        assert_eq!(previous_sync_committee_period(2 * PERIOD, PERIOD), 1);
+    }
+
+    #[test]
+    fn epoch_change_update_range_is_minimal() {
+        // same period, no epoch change updates are required
+        assert_eq!(epoch_change_update_range(5, 5), (6, 0));
+        // one period boundary, one epoch change update
+        assert_eq!(epoch_change_update_range(5, 6), (6, 1));
+        assert_eq!(epoch_change_update_range(5, 8), (6, 3));
+    }
+
+    #[test]
+    fn finality_update_only_if_not_reached() {
+        assert!(requires_finality_update(100, 101));
+        assert!(!requires_finality_update(101, 101));
+        assert!(!requires_finality_update(102, 101));
+    }
+
+    #[test]
+    fn sync_committee_bits_supermajority() {
+        // all but the last 8 members participated
+        let mut sync_committee_bits = vec![0xff; 64];
+        sync_committee_bits[63] = 0;
+
+        let sync_committee_bits = BitVec::<u8, Msb0>::from_vec(sync_committee_bits);
+
+        assert_eq!(sync_committee_bits.len(), 512);
+        assert!(has_supermajority(&sync_committee_bits));
+
+        let sync_committee_bits = BitVec::<u8, Msb0>::from_vec(vec![0x0f; 64]);
+
+        assert!(!has_supermajority(&sync_committee_bits));
+    }
+
+    #[test]
+    fn supermajority_threshold() {
+        let mut bits = BitVec::<u8, Msb0>::repeat(false, 512);
+
+        // 341 * 3 = 1023 < 512 * 2
+        bits[..341].fill(true);
+        assert!(!has_supermajority(&bits));
+
+        // 342 * 3 = 1026 >= 512 * 2
+        bits[..342].fill(true);
+        assert!(has_supermajority(&bits));
+    }
+}
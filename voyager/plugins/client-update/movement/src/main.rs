@@ -19,6 +19,7 @@ use unionlabs::{
 };
 use voyager_message::{
     call::Call,
+    cmd::CmdOutput,
     core::ChainId,
     data::{Data, DecodedHeaderMeta, OrderedHeaders},
     hook::UpdateHook,
@@ -96,10 +97,16 @@ impl Plugin for Module {
         PluginInfo {
             name: plugin_name(&config.chain_id),
             interest_filter: UpdateHook::filter(&config.chain_id),
+            kind: None,
+            chains: vec![],
+            ibc_specs: vec![],
         }
     }
 
-    async fn cmd(_config: Self::Config, cmd: Self::Cmd) {
+    async fn cmd(
+        _config: Self::Config,
+        cmd: Self::Cmd,
+    ) -> Result<Box<dyn CmdOutput>, chain_utils::BoxDynError> {
         match cmd {}
     }
 }
@@ -1,18 +1,18 @@
 use std::{collections::VecDeque, fmt::Debug, num::ParseIntError};
 
-use cometbft_types::types::{validator::Validator, validator_set::ValidatorSet};
+use chain_utils::light_block::{build_tm_update_header, fetch_light_block};
 use jsonrpsee::{
     core::{async_trait, RpcResult},
     Extensions,
 };
 use serde::{Deserialize, Serialize};
-use tendermint_light_client_types::Header;
 use tracing::instrument;
-use unionlabs::hash::{hash_v2::HexUnprefixed, H160};
 use voyager_message::{
     call::Call,
+    cmd::CmdOutput,
     core::ChainId,
     data::{Data, DecodedHeaderMeta, OrderedHeaders},
+    error::VoyagerError,
     hook::UpdateHook,
     module::{PluginInfo, PluginServer},
     DefaultCmd, Plugin, PluginMessage, VoyagerMessage,
@@ -94,10 +94,13 @@ impl Plugin for Module {
         PluginInfo {
             name: plugin_name(&config.chain_id),
             interest_filter: UpdateHook::filter(&config.chain_id),
+            kind: None,
+            chains: vec![],
+            ibc_specs: vec![],
         }
     }
 
-    async fn cmd(_config: Self::Config, cmd: Self::Cmd) {
+    async fn cmd(_config: Self::Config, cmd: Self::Cmd) -> Result<Box<dyn CmdOutput>, BoxDynError> {
         match cmd {}
     }
 }
@@ -161,42 +164,18 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
                 update_from,
                 update_to,
             }) => {
-                let trusted_commit = self
-                    .tm_client
-                    .commit(Some(update_from.height().try_into().unwrap()))
-                    .await
-                    .unwrap();
-
-                let untrusted_commit = self
-                    .tm_client
-                    .commit(Some(update_to.height().try_into().unwrap()))
-                    .await
-                    .unwrap();
-
-                let trusted_validators = self
-                    .tm_client
-                    .all_validators(Some(update_from.height().try_into().unwrap()))
-                    .await
-                    .unwrap();
-
-                let untrusted_validators = self
-                    .tm_client
-                    .all_validators(Some(update_to.height().try_into().unwrap()))
-                    .await
-                    .unwrap();
-
-                let header = Header {
-                    validator_set: mk_validator_set(
-                        untrusted_validators.validators,
-                        untrusted_commit.signed_header.header.proposer_address,
-                    ),
-                    signed_header: untrusted_commit.signed_header,
-                    trusted_height: update_from,
-                    trusted_validators: mk_validator_set(
-                        trusted_validators.validators,
-                        trusted_commit.signed_header.header.proposer_address,
-                    ),
-                };
+                let trusted =
+                    fetch_light_block(&self.tm_client, update_from.height().try_into().unwrap())
+                        .await
+                        .map_err(VoyagerError::from)?;
+
+                let target =
+                    fetch_light_block(&self.tm_client, update_to.height().try_into().unwrap())
+                        .await
+                        .map_err(VoyagerError::from)?;
+
+                let header = build_tm_update_header(update_from, trusted.validator_set, target)
+                    .map_err(VoyagerError::from)?;
 
                 Ok(data(OrderedHeaders {
                     headers: vec![(
@@ -218,25 +197,3 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
         match callback {}
     }
 }
-
-fn mk_validator_set(
-    validators: Vec<Validator>,
-    proposer_address: H160<HexUnprefixed>,
-) -> ValidatorSet {
-    let proposer = validators
-        .iter()
-        .find(|val| val.address == proposer_address)
-        .unwrap()
-        .clone();
-
-    let total_voting_power = validators
-        .iter()
-        .map(|v| v.voting_power.inner())
-        .sum::<i64>();
-
-    ValidatorSet {
-        validators,
-        proposer,
-        total_voting_power,
-    }
-}
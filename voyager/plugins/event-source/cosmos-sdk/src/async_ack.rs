@@ -0,0 +1,400 @@
+//! Tracking of packets that are received without an acknowledgement being
+//! written in the same transaction.
+//!
+//! Some IBC apps acknowledge packets asynchronously, in which case the
+//! `recv_packet` event is not accompanied by a `write_acknowledgement` event.
+//! Such packets are re-checked periodically by querying the acknowledgement
+//! commitment on this chain. Once the acknowledgement has been written, the
+//! corresponding `write_acknowledgement` event is emitted so that the normal
+//! acknowledgement relaying flow picks it up. If it is still missing after
+//! [`AsyncAckConfig::max_wait`], an alert is raised instead.
+
+use std::num::NonZeroU64;
+
+use jsonrpsee::core::RpcResult;
+use macros::model;
+use serde::{Deserialize, Serialize};
+use unionlabs::{
+    ibc::core::client::height::Height,
+    id::{ChannelId, PortId},
+};
+use voyager_message::{core::ChainId, query::VoyagerQuery};
+
+use crate::ibc_events::IbcEvent;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AsyncAckConfig {
+    /// How long to wait (in seconds) between checks for an acknowledgement.
+    #[serde(default = "AsyncAckConfig::default_recheck_delay")]
+    pub recheck_delay: u64,
+    /// How long to wait (in seconds) after the packet was first seen before
+    /// the acknowledgement is considered missing.
+    #[serde(default = "AsyncAckConfig::default_max_wait")]
+    pub max_wait: u64,
+}
+
+impl AsyncAckConfig {
+    const fn default_recheck_delay() -> u64 {
+        10 * 60
+    }
+
+    const fn default_max_wait() -> u64 {
+        24 * 60 * 60
+    }
+}
+
+impl Default for AsyncAckConfig {
+    fn default() -> Self {
+        Self {
+            recheck_delay: Self::default_recheck_delay(),
+            max_wait: Self::default_max_wait(),
+        }
+    }
+}
+
+/// A packet received on this chain that is awaiting an acknowledgement.
+///
+/// All identifiers are from the perspective of the destination chain (i.e.
+/// this chain).
+#[model]
+pub enum PendingAck {
+    V1 {
+        port_id: PortId,
+        channel_id: ChannelId,
+        sequence: NonZeroU64,
+    },
+    Union {
+        packet: ibc_solidity::Packet,
+    },
+}
+
+impl PendingAck {
+    #[must_use]
+    pub fn v1_path(
+        port_id: &PortId,
+        channel_id: &ChannelId,
+        sequence: NonZeroU64,
+    ) -> ibc_classic_spec::AcknowledgementPath {
+        ibc_classic_spec::AcknowledgementPath {
+            port_id: port_id.clone(),
+            channel_id: channel_id.clone(),
+            sequence,
+        }
+    }
+
+    #[must_use]
+    pub fn union_path(packet: &ibc_solidity::Packet) -> ibc_union_spec::BatchReceiptsPath {
+        ibc_union_spec::BatchReceiptsPath::from_packet(packet)
+    }
+
+    /// Whether the acknowledgement for this packet has been written on
+    /// `chain_id` as of `height`.
+    pub async fn is_written(
+        &self,
+        client: &impl VoyagerQuery,
+        chain_id: &ChainId,
+        height: Height,
+    ) -> RpcResult<bool> {
+        match self {
+            PendingAck::V1 {
+                port_id,
+                channel_id,
+                sequence,
+            } => Ok(client
+                .query_ibc_state(
+                    chain_id,
+                    height.into(),
+                    Self::v1_path(port_id, channel_id, *sequence),
+                )
+                .await?
+                .state
+                .is_some()),
+            PendingAck::Union { packet } => {
+                let commitment = client
+                    .query_ibc_state(chain_id, height.into(), Self::union_path(packet))
+                    .await?
+                    .state;
+
+                // the receipt is set to the magic value when the packet has been received but not
+                // yet acknowledged
+                Ok(commitment != ibc_union_spec::COMMITMENT_NULL
+                    && commitment != ibc_union_spec::COMMITMENT_MAGIC)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AckStatus {
+    /// The acknowledgement has been written as of this finalized height.
+    Written { height: Height },
+    /// The acknowledgement has not been written yet, and should be checked
+    /// again at the contained unix timestamp (in seconds).
+    Pending { recheck_at: u64 },
+    /// The acknowledgement has not been written within
+    /// [`AsyncAckConfig::max_wait`].
+    Missing,
+}
+
+/// Check whether the acknowledgement for `pending` has been written at the
+/// latest finalized height of `chain_id`, and decide on the next step.
+///
+/// `first_seen` and `now` are unix timestamps in seconds.
+pub async fn check(
+    client: &impl VoyagerQuery,
+    chain_id: &ChainId,
+    config: &AsyncAckConfig,
+    pending: &PendingAck,
+    first_seen: u64,
+    now: u64,
+) -> RpcResult<AckStatus> {
+    let height = client.query_latest_height(chain_id, true).await?;
+
+    if pending.is_written(client, chain_id, height).await? {
+        Ok(AckStatus::Written { height })
+    } else if now.saturating_sub(first_seen) >= config.max_wait {
+        Ok(AckStatus::Missing)
+    } else {
+        Ok(AckStatus::Pending {
+            recheck_at: now + config.recheck_delay,
+        })
+    }
+}
+
+/// Find all received packets in a single transaction's events that do not
+/// have a corresponding acknowledgement written in the same transaction.
+#[must_use]
+pub fn pending_acks(tx_events: &[IbcEvent]) -> Vec<PendingAck> {
+    tx_events
+        .iter()
+        .filter_map(|event| match event {
+            IbcEvent::RecvPacket(recv) => (!tx_events.iter().any(|event| {
+                matches!(
+                    event,
+                    IbcEvent::WriteAcknowledgement(ack)
+                        if ack.packet_dst_port == recv.packet_dst_port
+                            && ack.packet_dst_channel == recv.packet_dst_channel
+                            && ack.packet_sequence == recv.packet_sequence
+                )
+            }))
+            .then(|| PendingAck::V1 {
+                port_id: recv.packet_dst_port.clone(),
+                channel_id: recv.packet_dst_channel.clone(),
+                sequence: recv.packet_sequence,
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use unionlabs::{bytes::Bytes, hash::H256, ibc::core::channel::order::Order, id::ConnectionId};
+    use voyager_message::testing::MockVoyager;
+
+    use super::*;
+    use crate::ibc_events::{RecvPacket, WriteAcknowledgement};
+
+    const FIRST_SEEN: u64 = 1_000_000;
+
+    fn chain_id() -> ChainId {
+        ChainId::new("union-devnet-1")
+    }
+
+    /// A chain on which the acknowledgement is written at `written_at` (committing to
+    /// `union_ack` for the union packet), if ever. The packet is received at height 1.
+    fn voyager(written_at: Option<u64>, union_ack: H256) -> MockVoyager {
+        let voyager = MockVoyager::new();
+
+        // the receipt is set to the magic value on receipt, which is in the same block as the
+        // packet was first seen
+        voyager
+            .set_latest_finalized_height(&chain_id(), Height::new(1))
+            .set_state_at(
+                &chain_id(),
+                Height::new(1),
+                PendingAck::union_path(&union_packet()),
+                ibc_union_spec::COMMITMENT_MAGIC,
+            );
+
+        if let Some(written_at) = written_at {
+            voyager
+                .set_state_at(
+                    &chain_id(),
+                    Height::new(written_at),
+                    PendingAck::v1_path(&port_id(), &channel_id(), sequence()),
+                    Some(H256::new([0xaa; 32])),
+                )
+                .set_state_at(
+                    &chain_id(),
+                    Height::new(written_at),
+                    PendingAck::union_path(&union_packet()),
+                    union_ack,
+                );
+        }
+
+        voyager
+    }
+
+    fn port_id() -> PortId {
+        PortId::new("wasm.union1app".to_owned()).unwrap()
+    }
+
+    fn channel_id() -> ChannelId {
+        ChannelId::new(7)
+    }
+
+    fn sequence() -> NonZeroU64 {
+        NonZeroU64::new(3).unwrap()
+    }
+
+    fn union_packet() -> ibc_solidity::Packet {
+        ibc_solidity::Packet {
+            source_channel: 1,
+            destination_channel: 7,
+            data: b"data".to_vec().into(),
+            timeout_height: 0,
+            timeout_timestamp: 100,
+        }
+    }
+
+    fn pending() -> [PendingAck; 2] {
+        [
+            PendingAck::V1 {
+                port_id: port_id(),
+                channel_id: channel_id(),
+                sequence: sequence(),
+            },
+            PendingAck::Union {
+                packet: union_packet(),
+            },
+        ]
+    }
+
+    fn config() -> AsyncAckConfig {
+        AsyncAckConfig {
+            recheck_delay: 600,
+            max_wait: 3600,
+        }
+    }
+
+    #[tokio::test]
+    async fn ack_appears_later() {
+        for pending in pending() {
+            let voyager = voyager(Some(3), H256::new([0xaa; 32]));
+            let config = config();
+
+            let mut now = FIRST_SEEN + config.recheck_delay;
+
+            for height in 1..3 {
+                voyager.set_latest_finalized_height(&chain_id(), Height::new(height));
+
+                let status = check(&voyager, &chain_id(), &config, &pending, FIRST_SEEN, now)
+                    .await
+                    .unwrap();
+
+                assert_eq!(
+                    status,
+                    AckStatus::Pending {
+                        recheck_at: now + config.recheck_delay
+                    }
+                );
+
+                now += config.recheck_delay;
+            }
+
+            voyager.set_latest_finalized_height(&chain_id(), Height::new(3));
+
+            assert_eq!(
+                check(&voyager, &chain_id(), &config, &pending, FIRST_SEEN, now)
+                    .await
+                    .unwrap(),
+                AckStatus::Written {
+                    height: Height::new(3)
+                }
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn ack_never_appears() {
+        for pending in pending() {
+            let voyager = voyager(None, H256::new([0xaa; 32]));
+            let config = config();
+
+            let mut now = FIRST_SEEN + config.recheck_delay;
+            let mut checks = 0;
+
+            let status = loop {
+                checks += 1;
+
+                match check(&voyager, &chain_id(), &config, &pending, FIRST_SEEN, now)
+                    .await
+                    .unwrap()
+                {
+                    AckStatus::Pending { recheck_at } => now = recheck_at,
+                    status => break status,
+                }
+            };
+
+            assert_eq!(status, AckStatus::Missing);
+            assert_eq!(now, FIRST_SEEN + config.max_wait);
+            assert_eq!(checks, config.max_wait / config.recheck_delay);
+        }
+    }
+
+    #[tokio::test]
+    async fn union_null_commitment_is_not_an_ack() {
+        let voyager = voyager(Some(1), ibc_union_spec::COMMITMENT_NULL);
+
+        assert!(!PendingAck::Union {
+            packet: union_packet(),
+        }
+        .is_written(&voyager, &chain_id(), Height::new(1))
+        .await
+        .unwrap());
+    }
+
+    #[test]
+    fn pending_acks_in_tx() {
+        let recv = |sequence: u64| {
+            IbcEvent::RecvPacket(RecvPacket {
+                packet_data_hex: Bytes::default(),
+                packet_timeout_height: Height::new(0),
+                packet_timeout_timestamp: 0,
+                packet_sequence: NonZeroU64::new(sequence).unwrap(),
+                packet_src_port: PortId::new("transfer".to_owned()).unwrap(),
+                packet_src_channel: ChannelId::new(1),
+                packet_dst_port: port_id(),
+                packet_dst_channel: channel_id(),
+                packet_channel_ordering: Order::Unordered,
+                connection_id: ConnectionId::new(0),
+            })
+        };
+
+        let write_ack = |sequence: u64| {
+            IbcEvent::WriteAcknowledgement(WriteAcknowledgement {
+                packet_data_hex: Bytes::default(),
+                packet_timeout_height: Height::new(0),
+                packet_timeout_timestamp: 0,
+                packet_sequence: NonZeroU64::new(sequence).unwrap(),
+                packet_src_port: PortId::new("transfer".to_owned()).unwrap(),
+                packet_src_channel: ChannelId::new(1),
+                packet_dst_port: port_id(),
+                packet_dst_channel: channel_id(),
+                packet_ack_hex: Bytes::default(),
+                connection_id: ConnectionId::new(0),
+            })
+        };
+
+        assert_eq!(
+            pending_acks(&[recv(1), write_ack(1), recv(3), recv(4), write_ack(4)]),
+            vec![PendingAck::V1 {
+                port_id: port_id(),
+                channel_id: channel_id(),
+                sequence: sequence(),
+            }]
+        );
+    }
+}
@@ -0,0 +1,501 @@
+//! Fetching of bounded block ranges in chunks sized by the density of IBC events.
+//!
+//! A [`FetchBlockRange`] is fetched one chunk at a time, with the rest of the range requeued after
+//! each chunk. The work done per chunk is dominated by the IBC events in it (each event is turned
+//! into a chain event, which queries the chain), so rather than a fixed amount of blocks, each
+//! chunk targets [`BlockRangeConfig::events_per_chunk`] events: busy stretches of the chain are
+//! fetched in small chunks, empty stretches in large ones.
+//!
+//! The density of events is not known ahead of time, so it is estimated from the chunks fetched so
+//! far as an exponential moving average of the observed events per block ([`update_estimate`]),
+//! carried along in the requeued range. Without an estimate (and without a hint from the
+//! originating [`Call::FetchBlockRange`](voyager_message::call::Call::FetchBlockRange)), the first
+//! chunk is [`BlockRangeConfig::min_chunk_size`] blocks.
+
+use std::{
+    num::{NonZeroU32, NonZeroU64},
+    ops::RangeInclusive,
+};
+
+use cometbft_rpc::rpc_types::TxResponse;
+use jsonrpsee::core::RpcResult;
+use serde::{Deserialize, Serialize};
+use unionlabs::{ibc::core::client::height::Height, option_unwrap};
+use voyager_message::call::EventDensity;
+
+use crate::{
+    call::FetchBlockRange,
+    ibc_events::union_ibc,
+    tx_search::{self, TxSearchClient},
+};
+
+/// The weight of the most recently observed chunk in the estimated event density. Higher values
+/// adapt faster to bursts, lower values are less sensitive to single outliers.
+pub const ESTIMATE_WEIGHT: f64 = 0.5;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BlockRangeConfig {
+    /// The amount of IBC events each chunk of a block range should contain.
+    #[serde(default = "BlockRangeConfig::default_events_per_chunk")]
+    pub events_per_chunk: NonZeroU64,
+    /// The minimum amount of blocks fetched per chunk, also used for the first chunk if the
+    /// density of events is not known yet.
+    #[serde(default = "BlockRangeConfig::default_min_chunk_size")]
+    pub min_chunk_size: NonZeroU64,
+    /// The maximum amount of blocks fetched per chunk.
+    #[serde(default = "BlockRangeConfig::default_max_chunk_size")]
+    pub max_chunk_size: NonZeroU64,
+}
+
+impl BlockRangeConfig {
+    const fn default_events_per_chunk() -> NonZeroU64 {
+        option_unwrap!(NonZeroU64::new(100))
+    }
+
+    const fn default_min_chunk_size() -> NonZeroU64 {
+        NonZeroU64::MIN
+    }
+
+    const fn default_max_chunk_size() -> NonZeroU64 {
+        option_unwrap!(NonZeroU64::new(50))
+    }
+
+    pub fn validate(&self) -> Result<(), InvalidChunkSizes> {
+        if self.min_chunk_size > self.max_chunk_size {
+            Err(InvalidChunkSizes {
+                min_chunk_size: self.min_chunk_size,
+                max_chunk_size: self.max_chunk_size,
+            })
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Default for BlockRangeConfig {
+    fn default() -> Self {
+        Self {
+            events_per_chunk: Self::default_events_per_chunk(),
+            min_chunk_size: Self::default_min_chunk_size(),
+            max_chunk_size: Self::default_max_chunk_size(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error(
+    "invalid block range chunk sizes: min_chunk_size ({min_chunk_size}) must not be greater \
+    than max_chunk_size ({max_chunk_size})"
+)]
+pub struct InvalidChunkSizes {
+    pub min_chunk_size: NonZeroU64,
+    pub max_chunk_size: NonZeroU64,
+}
+
+/// Update the estimated event density with a chunk of `blocks` blocks containing `events` IBC
+/// events. Without a previous estimate, the observed density is the new estimate.
+#[must_use]
+pub fn update_estimate(
+    previous: Option<EventDensity>,
+    events: u64,
+    blocks: NonZeroU64,
+) -> EventDensity {
+    let observed = events as f64 / blocks.get() as f64;
+
+    let estimate = match previous {
+        Some(previous) => ESTIMATE_WEIGHT * observed + (1.0 - ESTIMATE_WEIGHT) * previous.get(),
+        None => observed,
+    };
+
+    EventDensity::new(estimate).expect("a weighted average of densities is a valid density; qed;")
+}
+
+/// The amount of blocks to fetch in the next chunk, given the estimated event density.
+#[must_use]
+pub fn chunk_size(estimate: Option<EventDensity>, config: &BlockRangeConfig) -> NonZeroU64 {
+    let Some(estimate) = estimate else {
+        return config.min_chunk_size;
+    };
+
+    // a density of 0 results in infinity, which saturates to u64::MAX
+    let size = (config.events_per_chunk.get() as f64 / estimate.get()) as u64;
+
+    // rather than clamp, which panics if min_chunk_size > max_chunk_size
+    NonZeroU64::new(size)
+        .unwrap_or(NonZeroU64::MIN)
+        .min(config.max_chunk_size)
+        .max(config.min_chunk_size)
+}
+
+/// The heights of the next chunk of `range`, or `None` if its first block is not finalized yet.
+/// The chunk never extends beyond `finalized`.
+#[must_use]
+pub fn next_chunk(
+    range: &FetchBlockRange,
+    finalized: Height,
+    config: &BlockRangeConfig,
+) -> Option<RangeInclusive<u64>> {
+    // all blocks of previous revisions are finalized
+    let finalized = if finalized.revision() > range.from.revision() {
+        u64::MAX
+    } else if finalized.revision() < range.from.revision() {
+        return None;
+    } else {
+        finalized.height()
+    };
+
+    let from = range.from.height();
+
+    let to = from
+        .saturating_add(chunk_size(range.events_per_block, config).get() - 1)
+        .min(range.to.height())
+        .min(finalized);
+
+    (from <= to).then_some(from..=to)
+}
+
+/// A fetched chunk of a block range.
+#[derive(Debug)]
+pub struct FetchedChunk {
+    /// The transactions in each block of the chunk, in ascending order of height.
+    pub blocks: Vec<(Height, Vec<TxResponse>)>,
+    /// The amount of IBC events in the chunk.
+    pub events: u64,
+    /// The rest of the range, if any, with the updated event density estimate.
+    pub next: Option<FetchBlockRange>,
+}
+
+/// Fetch the transactions in the blocks at `heights` (as returned by [`next_chunk`]) of `range`.
+pub async fn fetch_chunk(
+    client: &impl TxSearchClient,
+    range: &FetchBlockRange,
+    heights: RangeInclusive<u64>,
+) -> RpcResult<FetchedChunk> {
+    let revision = range.from.revision();
+
+    let mut blocks = vec![];
+    let mut events = 0;
+
+    for height in heights.clone() {
+        let height = Height::new_with_revision(revision, height);
+
+        let txs = tx_search::fetch_txs(client, height, NonZeroU32::MIN).await?;
+
+        events += txs
+            .iter()
+            .flat_map(|tx| &tx.tx_result.events)
+            .filter(|event| union_ibc::parse((*event).clone()).is_some())
+            .count() as u64;
+
+        blocks.push((height, txs));
+    }
+
+    let estimate = update_estimate(
+        range.events_per_block,
+        events,
+        NonZeroU64::new(blocks.len() as u64).expect("chunks are never empty; qed;"),
+    );
+
+    let next = (*heights.end() < range.to.height()).then(|| FetchBlockRange {
+        from: Height::new_with_revision(revision, heights.end() + 1),
+        to: range.to,
+        events_per_block: Some(estimate),
+    });
+
+    Ok(FetchedChunk {
+        blocks,
+        events,
+        next,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use cometbft_rpc::{
+        rpc_types::TxSearchResponse,
+        types::abci::{
+            event::Event, event_attribute::EventAttribute, exec_tx_result::ExecTxResult,
+        },
+    };
+    use unionlabs::{bounded::BoundedI64, hash::H256};
+
+    use super::*;
+
+    fn density(events_per_block: f64) -> Option<EventDensity> {
+        Some(EventDensity::new(events_per_block).unwrap())
+    }
+
+    fn config(events_per_chunk: u64, min_chunk_size: u64, max_chunk_size: u64) -> BlockRangeConfig {
+        BlockRangeConfig {
+            events_per_chunk: NonZeroU64::new(events_per_chunk).unwrap(),
+            min_chunk_size: NonZeroU64::new(min_chunk_size).unwrap(),
+            max_chunk_size: NonZeroU64::new(max_chunk_size).unwrap(),
+        }
+    }
+
+    fn range(from: u64, to: u64, events_per_block: Option<EventDensity>) -> FetchBlockRange {
+        FetchBlockRange {
+            from: Height::new_with_revision(1, from),
+            to: Height::new_with_revision(1, to),
+            events_per_block,
+        }
+    }
+
+    #[test]
+    fn config_defaults() {
+        let config = serde_json::from_str::<BlockRangeConfig>("{}").unwrap();
+
+        assert_eq!(config, BlockRangeConfig::default());
+        assert_eq!(config.validate(), Ok(()));
+    }
+
+    #[test]
+    fn config_min_greater_than_max() {
+        assert_eq!(config(100, 10, 10).validate(), Ok(()));
+        assert_eq!(
+            config(100, 11, 10).validate(),
+            Err(InvalidChunkSizes {
+                min_chunk_size: NonZeroU64::new(11).unwrap(),
+                max_chunk_size: NonZeroU64::new(10).unwrap(),
+            })
+        );
+    }
+
+    #[test]
+    fn estimate() {
+        let blocks = NonZeroU64::new(4).unwrap();
+
+        assert_eq!(update_estimate(None, 10, blocks), density(2.5).unwrap());
+        assert_eq!(
+            update_estimate(density(0.5), 10, blocks),
+            density(1.5).unwrap()
+        );
+        assert_eq!(
+            update_estimate(density(3.0), 0, blocks),
+            density(1.5).unwrap()
+        );
+        assert_eq!(update_estimate(None, 0, blocks), EventDensity::ZERO);
+    }
+
+    #[test]
+    fn chunk_size_targets_events_per_chunk() {
+        let config = config(100, 2, 50);
+
+        assert_eq!(chunk_size(density(10.0), &config).get(), 10);
+        assert_eq!(chunk_size(density(3.0), &config).get(), 33);
+    }
+
+    #[test]
+    fn chunk_size_is_clamped() {
+        let config = config(100, 2, 50);
+
+        // no estimate yet
+        assert_eq!(chunk_size(None, &config).get(), 2);
+        // busy
+        assert_eq!(chunk_size(density(1_000.0), &config).get(), 2);
+        // empty
+        assert_eq!(chunk_size(density(0.1), &config).get(), 50);
+        assert_eq!(chunk_size(Some(EventDensity::ZERO), &config).get(), 50);
+    }
+
+    #[test]
+    fn next_chunk_bounds() {
+        let config = config(100, 1, 50);
+        let finalized = Height::new_with_revision(1, 1_000);
+
+        assert_eq!(
+            next_chunk(&range(10, 500, density(0.0)), finalized, &config),
+            Some(10..=59)
+        );
+        // the end of the range
+        assert_eq!(
+            next_chunk(&range(10, 20, density(0.0)), finalized, &config),
+            Some(10..=20)
+        );
+        // the latest finalized block
+        assert_eq!(
+            next_chunk(&range(990, 2_000, density(0.0)), finalized, &config),
+            Some(990..=1_000)
+        );
+        // all blocks of a previous revision are finalized
+        assert_eq!(
+            next_chunk(
+                &range(990, 2_000, density(0.0)),
+                Height::new_with_revision(2, 1),
+                &config
+            ),
+            Some(990..=1_039)
+        );
+    }
+
+    #[test]
+    fn next_chunk_waits_for_finalization() {
+        let config = config(100, 1, 50);
+
+        assert_eq!(
+            next_chunk(
+                &range(1_001, 2_000, None),
+                Height::new_with_revision(1, 1_000),
+                &config
+            ),
+            None
+        );
+        assert_eq!(
+            next_chunk(
+                &range(10, 20, None),
+                Height::new_with_revision(0, 1_000),
+                &config
+            ),
+            None
+        );
+    }
+
+    /// A chain with one transaction in every block that contains IBC events, containing the
+    /// amount of IBC events given by `events_at` for the block.
+    struct MockChain {
+        events_at: fn(u64) -> usize,
+        fetched: RefCell<Vec<u64>>,
+    }
+
+    impl TxSearchClient for MockChain {
+        async fn txs_at_height(
+            &self,
+            height: Height,
+            page: NonZeroU32,
+        ) -> RpcResult<TxSearchResponse> {
+            assert_eq!(page, NonZeroU32::MIN);
+
+            self.fetched.borrow_mut().push(height.height());
+
+            let txs = match (self.events_at)(height.height()) {
+                0 => vec![],
+                events => vec![tx(height, events)],
+            };
+
+            Ok(TxSearchResponse {
+                total_count: txs.len() as u32,
+                txs,
+            })
+        }
+    }
+
+    fn tx(height: Height, events: usize) -> TxResponse {
+        let connection_open_try = Event {
+            ty: "wasm-connection_open_try".to_owned(),
+            attributes: [
+                ("_contract_address", "union1contract"),
+                ("connection_id", "3"),
+                ("client_id", "1"),
+                ("counterparty_client_id", "7"),
+                ("counterparty_connection_id", "5"),
+                ("msg_index", "0"),
+            ]
+            .into_iter()
+            .map(|(key, value)| EventAttribute {
+                key: key.to_owned(),
+                value: value.to_owned(),
+                index: true,
+            })
+            .collect(),
+        };
+
+        // not an IBC event, and as such not counted
+        let message = Event {
+            ty: "message".to_owned(),
+            attributes: vec![],
+        };
+
+        TxResponse {
+            hash: H256::new([1; 32]),
+            height: height.height().try_into().ok(),
+            index: 0,
+            tx_result: ExecTxResult {
+                code: 0,
+                data: None,
+                log: String::new(),
+                info: String::new(),
+                gas_wanted: BoundedI64::new_const(0).unwrap(),
+                gas_used: BoundedI64::new_const(0).unwrap(),
+                events: [message]
+                    .into_iter()
+                    .chain(vec![connection_open_try; events])
+                    .collect(),
+                codespace: String::new(),
+            },
+            tx: Default::default(),
+            proof: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn chunk_size_adapts_to_event_density() {
+        // a burst of 10 events per block in an otherwise empty range
+        let chain = MockChain {
+            events_at: |height| if (101..=300).contains(&height) { 10 } else { 0 },
+            fetched: RefCell::default(),
+        };
+
+        let config = config(20, 1, 50);
+        let finalized = Height::new_with_revision(1, 1_000);
+
+        let mut chunks = vec![];
+        let mut next = Some(range(1, 500, None));
+
+        while let Some(range) = next {
+            let heights = next_chunk(&range, finalized, &config).unwrap();
+
+            let chunk = fetch_chunk(&chain, &range, heights.clone()).await.unwrap();
+
+            assert_eq!(
+                chunk
+                    .blocks
+                    .iter()
+                    .map(|(height, _)| height.height())
+                    .collect::<Vec<_>>(),
+                heights.clone().collect::<Vec<_>>()
+            );
+
+            chunks.push((heights, chunk.events));
+            next = chunk.next;
+        }
+
+        // every block is fetched exactly once, in order
+        assert_eq!(*chain.fetched.borrow(), (1..=500).collect::<Vec<_>>());
+
+        let size = |heights: &RangeInclusive<u64>| heights.end() - heights.start() + 1;
+
+        // without an estimate, the first chunk is as small as possible, after which the empty
+        // blocks are fetched in chunks as large as possible
+        assert_eq!(size(&chunks[0].0), 1);
+        assert_eq!(size(&chunks[1].0), 50);
+
+        // once the burst is observed, the chunks shrink to contain about 20 events each
+        let burst = chunks
+            .iter()
+            .filter(|(heights, _)| *heights.start() > 150 && *heights.end() <= 300)
+            .collect::<Vec<_>>();
+
+        assert!(!burst.is_empty());
+        assert!(burst
+            .iter()
+            .all(|(heights, events)| size(heights) <= 3 && *events <= 30));
+        assert_eq!(size(&burst.last().unwrap().0), 2);
+
+        // after the burst, the chunks grow back to the maximum size
+        let after = chunks
+            .iter()
+            .filter(|(heights, _)| *heights.start() > 300)
+            .map(|(heights, _)| size(heights))
+            .collect::<Vec<_>>();
+
+        assert!(after[0] <= 3);
+        // the last chunk is cut off at the end of the range
+        assert!(after[..after.len() - 1]
+            .windows(2)
+            .all(|sizes| sizes[0] < sizes[1] || sizes[1] == 50));
+        assert!(after.contains(&50));
+    }
+}
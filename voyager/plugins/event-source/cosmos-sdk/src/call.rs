@@ -3,14 +3,18 @@ use std::num::NonZeroU32;
 use enumorph::Enumorph;
 use macros::model;
 use unionlabs::{hash::H256, ibc::core::client::height::Height};
+use voyager_message::{call::EventDensity, data::RawTmEvent};
 
 #[model]
 #[derive(Enumorph)]
 #[allow(clippy::large_enum_variant)]
 pub enum ModuleCall {
     FetchBlocks(FetchBlocks),
+    FetchBlockRange(FetchBlockRange),
+    WaitForBlock(WaitForBlock),
     FetchTransactions(FetchTransactions),
     MakeChainEvent(MakeChainEvent),
+    CheckAsyncAck(CheckAsyncAck),
 }
 
 /// Fetch a block at the specified height, requeuing a seq(wait(H+1), fetch(H+1)).
@@ -19,9 +23,31 @@ pub struct FetchBlocks {
     pub height: Height,
 }
 
+/// Fetch the next chunk of the blocks from `from` up to and including `to`, requeuing the rest of
+/// the range. See [`crate::block_range`] for how the size of the chunk is chosen.
+#[model]
+pub struct FetchBlockRange {
+    pub from: Height,
+    pub to: Height,
+    /// The estimated density of IBC events in the range, as observed in the previous chunks.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub events_per_block: Option<EventDensity>,
+}
+
+/// Wait for the block at the specified height to be finalized and then fetch it, checking for
+/// upgrades and halts of the chain while waiting.
+#[model]
+pub struct WaitForBlock {
+    pub height: Height,
+}
+
+/// Fetch all transactions in the block at the specified height, emitting a [`MakeChainEvent`] for
+/// each IBC event in them.
 #[model]
 pub struct FetchTransactions {
     pub height: Height,
+    /// The first page to fetch. All following pages are fetched in the same call, so this is
+    /// always 1 unless the call was queued by a version that fetched each page in a separate call.
     pub page: NonZeroU32,
 }
 
@@ -30,4 +56,24 @@ pub struct MakeChainEvent {
     pub height: Height,
     pub tx_hash: H256,
     pub event: crate::ibc_events::IbcEvent,
+    /// The raw events in the same transaction that are correlated with [`Self::event`], if
+    /// enabled.
+    #[serde(default)]
+    pub raw_events: Option<Vec<RawTmEvent>>,
+    /// The attribute schema [`Self::event`] was parsed with, if it was emitted by the union IBC
+    /// contract.
+    #[serde(default)]
+    pub union_schema: Option<crate::ibc_events::union_ibc::SchemaVersion>,
+}
+
+/// Check whether the acknowledgement for a packet received without one in the
+/// same transaction has been written yet.
+#[model]
+pub struct CheckAsyncAck {
+    pub pending: crate::async_ack::PendingAck,
+    /// The height and transaction that the packet was received in.
+    pub height: Height,
+    pub tx_hash: H256,
+    /// The unix timestamp (in seconds) at which the packet was first seen.
+    pub first_seen: u64,
 }
@@ -0,0 +1,277 @@
+//! Resolution of the connection hops of a channel.
+//!
+//! A channel is built on one connection per hop (more than one only for
+//! ICS-033 multi-hop channels). The client of each hop tracks the chain the
+//! next hop is on, and the client of the last hop tracks the counterparty
+//! chain. The counterparty channel is built on the same connections, in
+//! reverse order, as seen from the other end.
+
+use ibc_classic_spec::{ConnectionMetadata, ConnectionPath, IbcClassic};
+use jsonrpsee::core::RpcResult;
+use unionlabs::{ibc::core::client::height::Height, id::ConnectionId};
+use voyager_message::{
+    core::{ChainId, QueryHeight},
+    error::VoyagerError,
+    query::VoyagerQuery,
+    rpc::missing_state,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConnectionHops {
+    /// The chain at the end of the last hop.
+    pub counterparty_chain_id: ChainId,
+    /// The hops as seen from the chain the walk started on.
+    pub source: Vec<ConnectionMetadata>,
+    /// The hops as seen from the counterparty chain.
+    pub destination: Vec<ConnectionMetadata>,
+}
+
+/// Walk `connection_hops`, starting on `chain_id`. The first hop is queried at
+/// `height`, all further hops on the other chains at their latest height.
+pub async fn walk(
+    client: &impl VoyagerQuery,
+    chain_id: ChainId,
+    height: Height,
+    connection_hops: &[ConnectionId],
+) -> RpcResult<ConnectionHops> {
+    if connection_hops.is_empty() {
+        return Err(VoyagerError::fatal("channel has no connection hops").into());
+    }
+
+    let mut source = vec![];
+    let mut destination = vec![];
+
+    let mut hop_chain_id = chain_id;
+    let mut hop_height = QueryHeight::from(height);
+
+    for connection_id in connection_hops {
+        let connection = client
+            .query_ibc_state(
+                &hop_chain_id,
+                hop_height.clone(),
+                ConnectionPath {
+                    connection_id: connection_id.clone(),
+                },
+            )
+            .await?
+            .state
+            .ok_or_else(missing_state("connection must exist", None))?;
+
+        // the client of the hop tracks the chain the next hop is on
+        let next_chain_id = client
+            .client_meta::<IbcClassic>(&hop_chain_id, hop_height, connection.client_id.clone())
+            .await?
+            .chain_id;
+
+        destination.push(ConnectionMetadata {
+            client_id: connection.counterparty.client_id,
+            connection_id: connection
+                .counterparty
+                .connection_id
+                .expect("counterparty connection id should be set"),
+        });
+        source.push(ConnectionMetadata {
+            client_id: connection.client_id,
+            connection_id: connection_id.clone(),
+        });
+
+        hop_chain_id = next_chain_id;
+        hop_height = QueryHeight::Latest;
+    }
+
+    destination.reverse();
+
+    Ok(ConnectionHops {
+        counterparty_chain_id: hop_chain_id,
+        source,
+        destination,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use unionlabs::{
+        ibc::core::{
+            channel::{self, channel::Channel, order::Order},
+            commitment::merkle_prefix::MerklePrefix,
+            connection::{self, connection_end::ConnectionEnd, version::Version},
+        },
+        id::{ChannelId, ClientId, PortId},
+    };
+    use voyager_message::{
+        core::{ClientStateMeta, ClientStatus},
+        into_value,
+        testing::MockVoyager,
+    };
+
+    use super::*;
+
+    /// Add a connection `connection_id` on `chain_id` to `voyager`, whose client `client_id`
+    /// tracks `counterparty_chain_id`.
+    fn set_connection(
+        voyager: &MockVoyager,
+        chain_id: &str,
+        connection_id: u32,
+        client_id: u32,
+        counterparty_chain_id: &str,
+        counterparty_connection_id: u32,
+        counterparty_client_id: u32,
+    ) {
+        let chain_id = ChainId::new(chain_id.to_owned());
+
+        voyager
+            .set_state(
+                &chain_id,
+                ConnectionPath {
+                    connection_id: ConnectionId::new(connection_id),
+                },
+                Some(ConnectionEnd {
+                    client_id: ClientId::new("07-tendermint", client_id),
+                    versions: vec![Version {
+                        identifier: "1".to_owned(),
+                        features: vec![Order::Ordered, Order::Unordered],
+                    }],
+                    state: connection::state::State::Open,
+                    counterparty: connection::counterparty::Counterparty {
+                        client_id: ClientId::new("07-tendermint", counterparty_client_id),
+                        connection_id: Some(ConnectionId::new(counterparty_connection_id)),
+                        prefix: MerklePrefix {
+                            key_prefix: b"ibc".into(),
+                        },
+                    },
+                    delay_period: 0,
+                }),
+            )
+            .set_client_meta::<IbcClassic>(
+                &chain_id,
+                ClientId::new("07-tendermint", client_id),
+                ClientStateMeta {
+                    height: Height::new(1),
+                    chain_id: ChainId::new(counterparty_chain_id.to_owned()),
+                    status: ClientStatus::Active,
+                    resolved_at: None,
+                },
+            )
+            .set_latest_height(&chain_id, Height::new(10));
+    }
+
+    fn connection_metadata(client_id: u32, connection_id: u32) -> ConnectionMetadata {
+        ConnectionMetadata {
+            client_id: ClientId::new("07-tendermint", client_id),
+            connection_id: ConnectionId::new(connection_id),
+        }
+    }
+
+    /// A channel end on chain `a` of a channel `a -> b -> c`.
+    fn multi_hop_channel_end() -> Channel {
+        Channel {
+            state: channel::state::State::Open,
+            ordering: Order::Unordered,
+            counterparty: channel::counterparty::Counterparty {
+                port_id: PortId::new("transfer").unwrap(),
+                channel_id: Some(ChannelId::new(9)),
+            },
+            connection_hops: vec![ConnectionId::new(0), ConnectionId::new(4)],
+            version: "ics20-1".to_owned(),
+            upgrade_sequence: 0,
+        }
+    }
+
+    fn chains() -> MockVoyager {
+        let voyager = MockVoyager::new();
+
+        // a <-> b
+        set_connection(&voyager, "a", 0, 1, "b", 2, 3);
+        set_connection(&voyager, "b", 2, 3, "a", 0, 1);
+        // b <-> c
+        set_connection(&voyager, "b", 4, 5, "c", 6, 7);
+        set_connection(&voyager, "c", 6, 7, "b", 4, 5);
+
+        voyager
+    }
+
+    #[tokio::test]
+    async fn single_hop() {
+        let hops = walk(
+            &chains(),
+            ChainId::new("a"),
+            Height::new(1),
+            &[ConnectionId::new(0)],
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            hops,
+            ConnectionHops {
+                counterparty_chain_id: ChainId::new("b"),
+                source: vec![connection_metadata(1, 0)],
+                destination: vec![connection_metadata(3, 2)],
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn multi_hop() {
+        let channel = multi_hop_channel_end();
+
+        let voyager = chains();
+
+        let hops = walk(
+            &voyager,
+            ChainId::new("a"),
+            Height::new(1),
+            &channel.connection_hops,
+        )
+        .await
+        .unwrap();
+
+        // only the first hop is read at the height of the event
+        assert_eq!(
+            voyager
+                .calls("query_ibc_state")
+                .iter()
+                .map(|params| (params["chain_id"].clone(), params["height"].clone()))
+                .collect::<Vec<_>>(),
+            [
+                ("a".into(), into_value(QueryHeight::from(Height::new(1)))),
+                ("b".into(), into_value(QueryHeight::Latest)),
+            ]
+        );
+
+        assert_eq!(
+            hops,
+            ConnectionHops {
+                counterparty_chain_id: ChainId::new("c"),
+                source: vec![connection_metadata(1, 0), connection_metadata(5, 4)],
+                destination: vec![connection_metadata(7, 6), connection_metadata(3, 2)],
+            }
+        );
+
+        // walking the counterparty channel's hops from the other end yields the same hops,
+        // from the other side
+        let reverse = walk(
+            &chains(),
+            ChainId::new("c"),
+            Height::new(1),
+            &hops
+                .destination
+                .iter()
+                .map(|hop| hop.connection_id.clone())
+                .collect::<Vec<_>>(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(reverse.counterparty_chain_id, ChainId::new("a"));
+        assert_eq!(reverse.source, hops.destination);
+        assert_eq!(reverse.destination, hops.source);
+    }
+
+    #[tokio::test]
+    async fn no_hops() {
+        walk(&chains(), ChainId::new("a"), Height::new(1), &[])
+            .await
+            .unwrap_err();
+    }
+}
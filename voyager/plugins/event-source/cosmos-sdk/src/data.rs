@@ -1,6 +1,103 @@
 use enumorph::Enumorph;
 use macros::model;
+use serde_json::Value;
+use unionlabs::{
+    hash::H256,
+    ibc::core::client::height::Height,
+    id::{ChannelId, PortId},
+};
+use voyager_message::core::{ChainId, IbcSpecId};
+
+use crate::{async_ack::PendingAck, payload_filter::FilterReason};
 
 #[model]
 #[derive(Enumorph)]
-pub enum ModuleData {}
+pub enum ModuleData {
+    AsyncAckMissing(AsyncAckMissing),
+    PacketFiltered(PacketFiltered),
+    SequenceGapDetected(SequenceGapDetected),
+    ChainUpgradeDetected(ChainUpgradeDetected),
+    ChainHalted(ChainHalted),
+    PacketRoundTripCompleted(PacketRoundTripCompleted),
+}
+
+/// A packet was received, but no acknowledgement was written for it within the
+/// configured maximum wait time.
+#[model]
+pub struct AsyncAckMissing {
+    pub chain_id: ChainId,
+    pub pending: PendingAck,
+    /// The height and transaction that the packet was received in.
+    pub height: Height,
+    pub tx_hash: H256,
+    /// How long (in seconds) the acknowledgement has been waited for.
+    pub waited: u64,
+}
+
+/// A packet was sent, but will not be relayed since it exceeds the configured
+/// payload limits.
+#[model]
+pub struct PacketFiltered {
+    pub chain_id: ChainId,
+    pub tx_hash: H256,
+    pub reason: FilterReason,
+    pub ibc_spec_id: IbcSpecId,
+    /// The `send_packet` event, as it would have been emitted in the chain
+    /// event.
+    pub packet: Value,
+}
+
+/// Sequences were skipped on a channel for longer than the configured duration,
+/// i.e. a packet was sent but never observed by this event source.
+#[model]
+pub struct SequenceGapDetected {
+    pub chain_id: ChainId,
+    pub port_id: PortId,
+    pub channel: ChannelId,
+    /// The missing sequences, capped at
+    /// [`MAX_REPORTED_SEQUENCES`](crate::sequence_gaps::MAX_REPORTED_SEQUENCES).
+    pub missing: Vec<u64>,
+    /// The total amount of missing sequences in this gap.
+    pub missing_count: u64,
+    /// The unix timestamp (in seconds) at which the gap was first observed.
+    pub since: u64,
+}
+
+/// The network id reported by the node changed from the one being fetched
+/// from, i.e. the chain was upgraded to a new chain id.
+#[model]
+pub struct ChainUpgradeDetected {
+    pub old_chain_id: ChainId,
+    pub new_chain_id: ChainId,
+    /// The latest height of the node when the new chain id was observed.
+    pub height: u64,
+}
+
+/// The latest height of the chain did not advance within the configured halt
+/// detection window.
+#[model]
+pub struct ChainHalted {
+    pub chain_id: ChainId,
+    /// The latest height of the chain.
+    pub height: Height,
+    /// The unix timestamp (in seconds) at which the latest height was first
+    /// observed.
+    pub since: u64,
+}
+
+/// A packet sent from this chain was acknowledged. All timestamps are unix
+/// timestamps in milliseconds, taken when the events were observed by this
+/// event source.
+#[model]
+pub struct PacketRoundTripCompleted {
+    pub chain_id: ChainId,
+    pub port_id: PortId,
+    pub channel: ChannelId,
+    pub sequence: u64,
+    pub send_observed_at: u64,
+    /// Only set if the receipt of the packet was observed by this event
+    /// source, i.e. both ends of the channel are on this chain.
+    pub recv_observed_at: Option<u64>,
+    pub ack_observed_at: u64,
+    pub total_ms: u64,
+}
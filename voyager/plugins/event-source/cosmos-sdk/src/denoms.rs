@@ -0,0 +1,180 @@
+//! Enrichment of ics20 packet events with the trace of the transferred denom.
+//!
+//! Consumers of packet events commonly need the base denom of a transfer to
+//! make filtering and reporting decisions, which requires resolving `ibc/{hash}`
+//! denoms with the transfer module of the chain the denom is on. If enabled, the
+//! trace is attached to the emitted [`ChainEvent`] as
+//! [`ChainEvent::denom_trace`].
+//!
+//! [`ChainEvent`]: voyager_message::data::ChainEvent
+//! [`ChainEvent::denom_trace`]: voyager_message::data::ChainEvent::denom_trace
+
+use tracing::warn;
+use unionlabs::ErrorReporter;
+use voyager_message::{
+    core::ChainId,
+    denom::{resolve_denom, DenomResolver, DenomTrace},
+};
+
+use crate::payload_filter::Ics20PacketData;
+
+/// The trace of the denom transferred in a packet sent on a channel with
+/// `channel_version`, where the denom in the packet data is a denom on
+/// `chain_id`.
+///
+/// Returns `None` for packets that are not ics20 transfers. Failing to resolve
+/// the denom is logged, but does not fail the event.
+pub async fn packet_denom_trace(
+    resolver: &dyn DenomResolver,
+    chain_id: &ChainId,
+    channel_version: &str,
+    packet_data: &[u8],
+) -> Option<DenomTrace> {
+    let transfer = Ics20PacketData::parse(channel_version, packet_data)?;
+
+    match resolve_denom(resolver, chain_id, &transfer.denom).await {
+        Ok(trace) => trace,
+        Err(err) => {
+            warn!(
+                %chain_id,
+                denom = %transfer.denom,
+                "error resolving denom trace: {}",
+                ErrorReporter(&*err)
+            );
+
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use futures::future::BoxFuture;
+    use serde_json::json;
+    use unionlabs::hash::H256;
+    use voyager_vm::BoxDynError;
+
+    use super::*;
+
+    #[derive(Debug, Default)]
+    struct MockResolver {
+        trace: Option<DenomTrace>,
+        fail: bool,
+        calls: AtomicUsize,
+    }
+
+    impl DenomResolver for MockResolver {
+        fn resolve<'a>(
+            &'a self,
+            _: &'a ChainId,
+            _: H256,
+        ) -> BoxFuture<'a, Result<Option<DenomTrace>, BoxDynError>> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+
+            Box::pin(async move {
+                if self.fail {
+                    Err("connection refused".into())
+                } else {
+                    Ok(self.trace.clone())
+                }
+            })
+        }
+    }
+
+    fn transfer(denom: &str) -> Vec<u8> {
+        serde_json::to_vec(&json!({
+            "denom": denom,
+            "amount": "100",
+            "sender": "union1sender",
+            "receiver": "osmo1receiver",
+        }))
+        .unwrap()
+    }
+
+    fn atom() -> DenomTrace {
+        DenomTrace {
+            trace_path: "transfer/channel-0".to_owned(),
+            base_denom: "uatom".to_owned(),
+        }
+    }
+
+    #[tokio::test]
+    async fn non_ics20_packets_pass_through() {
+        let resolver = MockResolver {
+            trace: Some(atom()),
+            ..Default::default()
+        };
+        let chain_id = ChainId::new("union-1");
+
+        // not an ics20 channel
+        assert_eq!(
+            packet_denom_trace(&resolver, &chain_id, "ucs03-zkgm-0", &transfer("muno")).await,
+            None
+        );
+        // not ics20 packet data
+        assert_eq!(
+            packet_denom_trace(&resolver, &chain_id, "ics20-1", b"\xde\xad\xbe\xef").await,
+            None
+        );
+
+        assert_eq!(resolver.calls.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn ibc_denoms_are_resolved() {
+        let resolver = MockResolver {
+            trace: Some(atom()),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            packet_denom_trace(
+                &resolver,
+                &ChainId::new("osmosis-1"),
+                "ics20-1",
+                &transfer(&atom().ibc_denom()),
+            )
+            .await,
+            Some(atom())
+        );
+        assert_eq!(resolver.calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn full_paths_are_parsed() {
+        let resolver = MockResolver::default();
+
+        assert_eq!(
+            packet_denom_trace(
+                &resolver,
+                &ChainId::new("osmosis-1"),
+                "ics20-1",
+                &transfer("transfer/channel-0/uatom"),
+            )
+            .await,
+            Some(atom())
+        );
+        assert_eq!(resolver.calls.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn resolver_errors_do_not_fail_the_event() {
+        let resolver = MockResolver {
+            fail: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            packet_denom_trace(
+                &resolver,
+                &ChainId::new("osmosis-1"),
+                "ics20-1",
+                &transfer(&atom().ibc_denom()),
+            )
+            .await,
+            None
+        );
+    }
+}
@@ -8,6 +8,8 @@ use unionlabs::{
     id::{ChannelId, ClientId, ConnectionId, PortId},
 };
 
+pub mod union_ibc;
+
 event! {
     pub enum IbcEvent {
         // standard ibc-go events for IBC classic
@@ -391,6 +393,14 @@ event! {
             packet: ibc_solidity::Packet,
         },
 
+        #[event(tag = "wasm-batch_send")]
+        UnionBatchSend {
+            #[parse(u32::from_str)]
+            channel_id: u32,
+            #[parse(serde_json::from_str)]
+            packets: Vec<ibc_solidity::Packet>,
+        },
+
         // #[event(
         //     tag = "write_acknowledgement",
         //     deprecated("packet_data", "packet_ack", "packet_connection")
@@ -549,6 +559,7 @@ impl IbcEvent {
             // IbcEvent::UnionWriteAcknowledgement(_) => "write_acknowledgement",
             // IbcEvent::UnionRecvPacket(_) => "recv_packet",
             IbcEvent::UnionSendPacket(_) => "send_packet",
+            IbcEvent::UnionBatchSend(_) => "batch_send",
             // IbcEvent::UnionAcknowledgePacket(_) => "acknowledge_packet",
             // IbcEvent::UnionTimeoutPacket(_) => "timeout_packet",
         }
@@ -0,0 +1,478 @@
+//! Parsing of the events emitted by the union IBC cosmwasm contract, tolerant of the attribute
+//! schemas of previous contract versions.
+//!
+//! The contract has changed the shape of its event attributes between versions (the names of the
+//! attributes and the encoding of numeric ids). Since the contract can be migrated independently
+//! of the relayer, events are parsed with the [current schema](SCHEMAS) first, and then with each
+//! of the previous schemas in order, normalizing the attributes into the shape expected by
+//! [`IbcEvent`]. A warning is logged (at most once every [`FALLBACK_WARNING_INTERVAL`]) whenever
+//! a previous schema matches, since this means that the relayer is out of date with the contract.
+//!
+//! If an event is valid under multiple schemas, the first one in [`SCHEMAS`] wins.
+
+use std::{
+    fmt,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use cosmos_sdk_event::{cometbft_types::abci::event::Event, TryFromTendermintEventError};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::ibc_events::IbcEvent;
+
+/// The version of the attribute schema of a union IBC contract event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct SchemaVersion(pub u32);
+
+impl fmt::Display for SchemaVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "v{}", self.0)
+    }
+}
+
+/// How the numeric ids ([`ID_ATTRIBUTES`]) of an event are encoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdEncoding {
+    /// The id is a plain integer, i.e. `1`.
+    Integer,
+    /// The id is a json string, i.e. `"1"`.
+    String,
+}
+
+/// The attribute schema of a version of the union IBC contract.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnionEventSchema {
+    pub version: SchemaVersion,
+    /// The attributes whose names differ from the current schema, as `(name, current name)`.
+    pub renames: &'static [(&'static str, &'static str)],
+    pub id_encoding: IdEncoding,
+}
+
+/// The known attribute schemas, ordered from the current schema to the oldest. Adding a schema
+/// for a new contract version only requires prepending it here (and updating the previously
+/// current schema to map onto it).
+pub const SCHEMAS: &[UnionEventSchema] = &[
+    UnionEventSchema {
+        version: SchemaVersion(2),
+        renames: &[],
+        id_encoding: IdEncoding::Integer,
+    },
+    UnionEventSchema {
+        version: SchemaVersion(1),
+        renames: &[
+            ("client-id", "client_id"),
+            ("client-type", "client_type"),
+            ("connection-id", "connection_id"),
+            ("counterparty-client-id", "counterparty_client_id"),
+            ("counterparty-connection-id", "counterparty_connection_id"),
+            ("port-id", "port_id"),
+            ("channel-id", "channel_id"),
+            ("counterparty-port-id", "counterparty_port_id"),
+            ("counterparty-channel-id", "counterparty_channel_id"),
+            ("counterparty-version", "counterparty_version"),
+        ],
+        id_encoding: IdEncoding::String,
+    },
+];
+
+/// The attributes (by their current name) that contain numeric ids.
+pub const ID_ATTRIBUTES: &[&str] = &[
+    "client_id",
+    "connection_id",
+    "counterparty_client_id",
+    "counterparty_connection_id",
+    "channel_id",
+    "counterparty_channel_id",
+];
+
+/// The minimum interval between two warnings about a previous schema being matched.
+pub const FALLBACK_WARNING_INTERVAL: Duration = Duration::from_secs(60);
+
+static FALLBACK_WARNINGS: FallbackWarnings = FallbackWarnings::new(FALLBACK_WARNING_INTERVAL);
+
+/// An [`IbcEvent`], along with the schema it was parsed with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsedIbcEvent {
+    pub event: IbcEvent,
+    /// The schema the event was parsed with, if it was emitted by the union IBC contract.
+    pub schema_version: Option<SchemaVersion>,
+}
+
+/// Parse an [`IbcEvent`] from a tendermint event, falling back to the previous [`SCHEMAS`] for
+/// events emitted by the union IBC contract.
+///
+/// Returns `None` if the event is not an IBC event. If the event can't be parsed with any schema,
+/// the error from the current schema is returned.
+#[must_use]
+pub fn parse(event: Event) -> Option<Result<ParsedIbcEvent, TryFromTendermintEventError>> {
+    if !event.ty.starts_with("wasm-") {
+        return IbcEvent::try_from_tendermint_event(event).map(|res| {
+            res.map(|event| ParsedIbcEvent {
+                event,
+                schema_version: None,
+            })
+        });
+    }
+
+    let mut current_schema_err = None;
+
+    for (idx, schema) in SCHEMAS.iter().enumerate() {
+        let res = match normalize(schema, event.clone()) {
+            // the event type is the same in every schema, so this only returns early for the
+            // current schema
+            Ok(normalized) => IbcEvent::try_from_tendermint_event(normalized)?,
+            Err(err) => Err(err),
+        };
+
+        match res {
+            Ok(parsed) => {
+                if idx > 0 {
+                    FALLBACK_WARNINGS.warn(Instant::now(), &event.ty, schema.version);
+                }
+
+                return Some(Ok(ParsedIbcEvent {
+                    event: parsed,
+                    schema_version: Some(schema.version),
+                }));
+            }
+            Err(err) => {
+                current_schema_err.get_or_insert(err);
+            }
+        }
+    }
+
+    current_schema_err.map(Err)
+}
+
+/// Normalize the attributes of `event` from `schema` into the current schema.
+fn normalize(
+    schema: &UnionEventSchema,
+    mut event: Event,
+) -> Result<Event, TryFromTendermintEventError> {
+    for attr in &mut event.attributes {
+        if let Some((_, current)) = schema.renames.iter().find(|(name, _)| *name == attr.key) {
+            (*current).clone_into(&mut attr.key);
+        }
+
+        let id_field = ID_ATTRIBUTES.iter().find(|field| **field == attr.key);
+
+        if let (IdEncoding::String, Some(field)) = (schema.id_encoding, id_field) {
+            attr.value = serde_json::from_str::<String>(&attr.value).map_err(|err| {
+                TryFromTendermintEventError::AttributeValueParse {
+                    field: *field,
+                    error: format!("expected a string encoded id: {err}"),
+                }
+            })?;
+        }
+    }
+
+    Ok(event)
+}
+
+/// Rate limits the warnings logged when an event is parsed with a previous schema.
+struct FallbackWarnings {
+    interval: Duration,
+    state: Mutex<FallbackWarningsState>,
+}
+
+struct FallbackWarningsState {
+    last_warning: Option<Instant>,
+    suppressed: u64,
+}
+
+impl FallbackWarnings {
+    const fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            state: Mutex::new(FallbackWarningsState {
+                last_warning: None,
+                suppressed: 0,
+            }),
+        }
+    }
+
+    /// Log a warning unless one has been logged within the interval, returning whether the
+    /// warning was logged.
+    fn warn(&self, now: Instant, event_type: &str, version: SchemaVersion) -> bool {
+        let mut state = self.state.lock().expect("lock is poisoned");
+
+        if state
+            .last_warning
+            .is_some_and(|last_warning| now.duration_since(last_warning) < self.interval)
+        {
+            state.suppressed += 1;
+            return false;
+        }
+
+        warn!(
+            %event_type,
+            schema_version = %version,
+            current_schema_version = %SCHEMAS[0].version,
+            suppressed = state.suppressed,
+            "union IBC contract event was parsed with a previous attribute schema, the relayer is \
+            out of date with the contract and should be upgraded"
+        );
+
+        state.last_warning = Some(now);
+        state.suppressed = 0;
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmos_sdk_event::cometbft_types::abci::event_attribute::EventAttribute;
+    use unionlabs::{bytes::Bytes, hash::hash_v2::HexUnprefixed};
+
+    use super::*;
+    use crate::ibc_events::{
+        UnionBatchSend, UnionChannelOpenTry, UnionConnectionOpenTry, UnionSendPacket,
+    };
+
+    fn event(ty: &str, attributes: &[(&str, &str)]) -> Event {
+        Event {
+            ty: ty.to_owned(),
+            attributes: attributes
+                .iter()
+                .map(|(key, value)| EventAttribute {
+                    key: (*key).to_owned(),
+                    value: (*value).to_owned(),
+                    index: true,
+                })
+                .collect(),
+        }
+    }
+
+    fn connection_open_try() -> IbcEvent {
+        IbcEvent::UnionConnectionOpenTry(UnionConnectionOpenTry {
+            connection_id: 3,
+            client_id: 1,
+            counterparty_client_id: 7,
+            counterparty_connection_id: 5,
+        })
+    }
+
+    fn channel_open_try() -> IbcEvent {
+        IbcEvent::UnionChannelOpenTry(UnionChannelOpenTry {
+            port_id: "union1port".to_owned(),
+            channel_id: 2,
+            counterparty_port_id: <Bytes<HexUnprefixed>>::new(vec![0xab, 0xcd]),
+            counterparty_channel_id: 4,
+            connection_id: 3,
+            counterparty_version: "ucs01-relay-1".to_owned(),
+        })
+    }
+
+    #[test]
+    fn schemas_are_ordered() {
+        assert!(SCHEMAS
+            .windows(2)
+            .all(|schemas| schemas[0].version > schemas[1].version));
+    }
+
+    #[test]
+    fn current_schema() {
+        assert_eq!(
+            parse(event(
+                "wasm-connection_open_try",
+                &[
+                    ("_contract_address", "union1contract"),
+                    ("connection_id", "3"),
+                    ("client_id", "1"),
+                    ("counterparty_client_id", "7"),
+                    ("counterparty_connection_id", "5"),
+                    ("msg_index", "0"),
+                ],
+            )),
+            Some(Ok(ParsedIbcEvent {
+                event: connection_open_try(),
+                schema_version: Some(SchemaVersion(2)),
+            }))
+        );
+
+        assert_eq!(
+            parse(event(
+                "wasm-channel_open_try",
+                &[
+                    ("port_id", "union1port"),
+                    ("channel_id", "2"),
+                    ("counterparty_port_id", "abcd"),
+                    ("counterparty_channel_id", "4"),
+                    ("connection_id", "3"),
+                    ("counterparty_version", "ucs01-relay-1"),
+                ],
+            )),
+            Some(Ok(ParsedIbcEvent {
+                event: channel_open_try(),
+                schema_version: Some(SchemaVersion(2)),
+            }))
+        );
+    }
+
+    #[test]
+    fn previous_schema() {
+        assert_eq!(
+            parse(event(
+                "wasm-connection_open_try",
+                &[
+                    ("_contract_address", "union1contract"),
+                    ("connection-id", "\"3\""),
+                    ("client-id", "\"1\""),
+                    ("counterparty-client-id", "\"7\""),
+                    ("counterparty-connection-id", "\"5\""),
+                    ("msg_index", "0"),
+                ],
+            )),
+            Some(Ok(ParsedIbcEvent {
+                event: connection_open_try(),
+                schema_version: Some(SchemaVersion(1)),
+            }))
+        );
+
+        // non-id attributes are only renamed
+        assert_eq!(
+            parse(event(
+                "wasm-channel_open_try",
+                &[
+                    ("port-id", "union1port"),
+                    ("channel-id", "\"2\""),
+                    ("counterparty-port-id", "abcd"),
+                    ("counterparty-channel-id", "\"4\""),
+                    ("connection-id", "\"3\""),
+                    ("counterparty-version", "ucs01-relay-1"),
+                ],
+            )),
+            Some(Ok(ParsedIbcEvent {
+                event: channel_open_try(),
+                schema_version: Some(SchemaVersion(1)),
+            }))
+        );
+    }
+
+    #[test]
+    fn ambiguous_event_uses_current_schema() {
+        // send_packet has the same shape in every schema
+        let packet = ibc_solidity::Packet {
+            source_channel: 1,
+            destination_channel: 2,
+            data: Default::default(),
+            timeout_height: 0,
+            timeout_timestamp: 1_000,
+        };
+
+        assert_eq!(
+            parse(event(
+                "wasm-send_packet",
+                &[("packet", &serde_json::to_string(&packet).unwrap())],
+            )),
+            Some(Ok(ParsedIbcEvent {
+                event: IbcEvent::UnionSendPacket(UnionSendPacket { packet }),
+                schema_version: Some(SchemaVersion(2)),
+            }))
+        );
+    }
+
+    #[test]
+    fn batch_send() {
+        let packets = vec![
+            ibc_solidity::Packet {
+                source_channel: 1,
+                destination_channel: 2,
+                data: vec![0xde, 0xad].into(),
+                timeout_height: 0,
+                timeout_timestamp: 1_000,
+            },
+            ibc_solidity::Packet {
+                source_channel: 1,
+                destination_channel: 2,
+                data: vec![0xbe, 0xef].into(),
+                timeout_height: 0,
+                timeout_timestamp: 2_000,
+            },
+        ];
+        let packets_json = serde_json::to_string(&packets).unwrap();
+
+        let batch_send = IbcEvent::UnionBatchSend(UnionBatchSend {
+            channel_id: 1,
+            packets,
+        });
+
+        assert_eq!(
+            parse(event(
+                "wasm-batch_send",
+                &[("channel_id", "1"), ("packets", &packets_json)],
+            )),
+            Some(Ok(ParsedIbcEvent {
+                event: batch_send.clone(),
+                schema_version: Some(SchemaVersion(2)),
+            }))
+        );
+
+        assert_eq!(
+            parse(event(
+                "wasm-batch_send",
+                &[("channel-id", "\"1\""), ("packets", &packets_json)],
+            )),
+            Some(Ok(ParsedIbcEvent {
+                event: batch_send,
+                schema_version: Some(SchemaVersion(1)),
+            }))
+        );
+    }
+
+    #[test]
+    fn mixed_schemas_are_rejected() {
+        // attributes from multiple schemas are never merged, the error from the current schema is
+        // reported
+        assert_eq!(
+            parse(event(
+                "wasm-client_update",
+                &[("client_id", "1"), ("client-id", "\"1\""), ("height", "10")],
+            )),
+            Some(Err(TryFromTendermintEventError::UnknownAttribute(
+                "client-id".to_owned()
+            )))
+        );
+
+        // ids must be encoded as per the schema of the attribute names
+        assert_eq!(
+            parse(event(
+                "wasm-client_update",
+                &[("client-id", "1"), ("height", "10")],
+            )),
+            Some(Err(TryFromTendermintEventError::UnknownAttribute(
+                "client-id".to_owned()
+            )))
+        );
+    }
+
+    #[test]
+    fn not_an_ibc_event() {
+        assert_eq!(parse(event("wasm-transfer", &[("amount", "1")])), None);
+        assert_eq!(parse(event("message", &[("module", "bank")])), None);
+    }
+
+    #[test]
+    fn fallback_warnings_are_rate_limited() {
+        let warnings = FallbackWarnings::new(Duration::from_secs(60));
+        let start = Instant::now();
+
+        assert!(warnings.warn(start, "wasm-client_update", SchemaVersion(1)));
+        assert!(!warnings.warn(
+            start + Duration::from_secs(30),
+            "wasm-client_update",
+            SchemaVersion(1)
+        ));
+        assert_eq!(warnings.state.lock().unwrap().suppressed, 1);
+        assert!(warnings.warn(
+            start + Duration::from_secs(60),
+            "wasm-client_update",
+            SchemaVersion(1)
+        ));
+        assert_eq!(warnings.state.lock().unwrap().suppressed, 0);
+    }
+}
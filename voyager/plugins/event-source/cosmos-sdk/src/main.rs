@@ -5,55 +5,109 @@ use std::{
     error::Error,
     fmt::{Debug, Display},
     num::{NonZeroU32, NonZeroU8, ParseIntError},
-    sync::Arc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, RwLock,
+    },
+    time::{SystemTime, UNIX_EPOCH},
 };
 
-use dashmap::DashMap;
+use chain_utils::{
+    auth::{self, AuthorizationHeader, EndpointAuth, GrpcAuth},
+    bounded::BoundsConfig,
+    endpoint::{GrpcUrl, WsUrl, DEFAULT_PROBE_TIMEOUT},
+    net::NetworkConfig,
+    timeout::{CallKind, TimeoutConfig},
+};
+use cometbft_rpc::{rpc_types::TxResponse, types::abci::event::Event};
 use ibc_classic_spec::IbcClassic;
-use ibc_union_spec::IbcUnion;
+use ibc_union_spec::{
+    batch::{BatchMember, PacketBatch},
+    IbcUnion,
+};
 use jsonrpsee::{
     core::{async_trait, RpcResult},
-    types::{ErrorObject, ErrorObjectOwned},
+    types::{error::INVALID_PARAMS_CODE, ErrorObject, ErrorObjectOwned},
     Extensions,
 };
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
-use tracing::{debug, error, info, instrument};
+use tonic::{codegen::InterceptedService, transport::Channel};
+use tracing::{debug, error, field, info, instrument, warn, Span};
 use unionlabs::{
     hash::{hash_v2::HexUnprefixed, H256},
     ibc::core::{
         channel::{self},
         client::height::Height,
     },
-    id::{ChannelId, ClientId, ConnectionId, PortId},
+    id::{ChannelId, ClientId, PortId},
     option_unwrap, parse_wasm_client_type, ErrorReporter, WasmClientType,
 };
 use voyager_message::{
-    call::{Call, WaitForHeight},
-    core::{ChainId, ClientInfo, ClientType, IbcSpec, QueryHeight},
-    data::{ChainEvent, Data},
+    backpressure::{Backpressure, BackpressureConfig},
+    cache_snapshot::{CacheSnapshot, ImportReport, TimestampedCache},
+    call::Call,
+    cmd::{ChainIdOutput, CmdOutput, LatestHeightOutput},
+    core::{ChainId, ClientInfo, ClientType, IbcSpec, IbcSpecId, QueryHeight},
+    data::{ChainEvent, Data, RawTmEvent},
+    denom::{CachingDenomResolver, DenomResolver, DenomTrace, GrpcDenomResolver},
+    error::{channel_connection, union_id, VoyagerError},
+    finality::{CometbftFinalityTracker, FinalityTracker, DEFAULT_BLOCK_TIME_WINDOW},
     into_value,
-    module::{PluginInfo, PluginServer},
+    module::{ensure_chain_id, PluginInfo, PluginKind, PluginServer, ReloadReport},
+    revision::{check_counterparty_revision, check_node_revision, RevisionCheckConfig},
     rpc::missing_state,
     ExtensionsExt, Plugin, PluginMessage, VoyagerClient, VoyagerMessage,
 };
-use voyager_vm::{call, conc, data, pass::PassResult, seq, BoxDynError, Op};
+use voyager_vm::{
+    call, conc, data, defer, now,
+    pass::{Claim, PassResult},
+    seq, BoxDynError, Op,
+};
 
 use crate::{
-    call::{FetchBlocks, FetchTransactions, MakeChainEvent, ModuleCall},
+    async_ack::{AckStatus, AsyncAckConfig, PendingAck},
+    block_range::BlockRangeConfig,
+    call::{
+        CheckAsyncAck, FetchBlockRange, FetchBlocks, FetchTransactions, MakeChainEvent, ModuleCall,
+        WaitForBlock,
+    },
     callback::ModuleCallback,
+    data::{AsyncAckMissing, ModuleData, PacketFiltered},
+    debug::{DebugState, RecentHeights, RECENT_HEIGHTS_CAPACITY},
+    denoms::packet_denom_trace,
     ibc_events::{
+        union_ibc::{self, SchemaVersion},
         ChannelOpenAck, ChannelOpenConfirm, ChannelOpenInit, ChannelOpenTry, ClientMisbehaviour,
         ConnectionOpenAck, ConnectionOpenConfirm, ConnectionOpenInit, ConnectionOpenTry,
         CreateClient, IbcEvent, SubmitEvidence, UpdateClient,
     },
+    packet_latency::{PacketLatencyConfig, PacketLatencyTracker},
+    payload_filter::PayloadFilterConfig,
+    sequence_gaps::{GapLedger, GapsOutput, SequenceGapConfig, SequenceGapTracker},
+    upgrades::{NodeStatus, NodeStatusClient, UpgradeConfig, UpgradeMonitor},
 };
 
+pub mod async_ack;
+pub mod block_range;
 pub mod ibc_events;
 
 pub mod call;
 pub mod callback;
+pub mod connection_hops;
 pub mod data;
+pub mod debug;
+pub mod denoms;
+pub mod packet_latency;
+pub mod payload_filter;
+pub mod raw_events;
+pub mod sequence_gaps;
+pub mod tx_search;
+pub mod union_events;
+pub mod upgrades;
+
+/// The name of the checksum to client type cache in cache snapshots.
+const CHECKSUM_CACHE: &str = "checksum_cache";
 
 const PER_PAGE_LIMIT: NonZeroU8 = option_unwrap!(NonZeroU8::new(10));
 
@@ -66,25 +120,244 @@ async fn main() {
 pub enum Cmd {
     ChainId,
     LatestHeight,
+    /// Print all sequence gaps currently tracked for this chain, one channel
+    /// per line.
+    Gaps,
 }
 
 #[derive(Debug, Clone)]
 pub struct Module {
     pub chain_id: ChainId,
-    pub chain_revision: u64,
 
     pub tm_client: cometbft_rpc::Client,
     pub grpc_url: String,
+    pub grpc_auth: GrpcAuth,
+
+    pub timeouts: TimeoutConfig,
+
+    pub checksum_cache: Arc<TimestampedCache<H256, WasmClientType>>,
+
+    pub finality: CometbftFinalityTracker,
+
+    /// The config this plugin is running with, including any changes applied
+    /// with [`PluginServer::reload`].
+    pub config: LiveConfig,
+
+    pub sequence_gaps: Option<Arc<SequenceGapTracker>>,
+
+    pub packet_latency: Option<Arc<PacketLatencyTracker>>,
+
+    /// The most recently processed heights, for [`PluginServer::debug_state`].
+    pub recent_heights: Arc<RecentHeights>,
+
+    /// Resolves the denoms of ics20 packets, if [`Config::resolve_denoms`] is enabled.
+    pub denom_resolver: Arc<dyn DenomResolver>,
+
+    /// Tracks the chain id and revision of the chain across upgrades.
+    pub upgrades: Arc<UpgradeMonitor>,
+
+    pub backpressure: Option<BackpressureConfig>,
+    /// Whether fetching blocks is currently being slowed down, if [`Config::backpressure`] is
+    /// set.
+    pub backpressure_state: Arc<Backpressure>,
+
+    pub block_range: BlockRangeConfig,
 
-    pub checksum_cache: Arc<DashMap<H256, WasmClientType>>,
+    /// The client to check the revision of this chain against, if any. See
+    /// [`voyager_message::revision`].
+    pub revision_check: Option<RevisionCheckConfig>,
+    /// Set once the revision has been checked against [`Self::revision_check`].
+    pub revision_checked: Arc<AtomicBool>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct Config {
     pub chain_id: ChainId,
-    pub ws_url: String,
-    pub grpc_url: String,
+    pub ws_url: WsUrl,
+    pub grpc_url: GrpcUrl,
+    /// Authentication for `ws_url`, if the rpc provider requires it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ws_auth: Option<EndpointAuth>,
+    /// Authentication for `grpc_url`, if the grpc provider requires it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub grpc_auth: Option<EndpointAuth>,
+    /// Don't check that `ws_url` and `grpc_url` are reachable on startup.
+    #[serde(default)]
+    pub skip_startup_probe: bool,
+    /// How connections to `ws_url` and `grpc_url` are opened, i.e. through a proxy.
+    #[serde(default)]
+    pub network: NetworkConfig,
+    /// Timeouts of the calls to `ws_url` and `grpc_url`.
+    #[serde(default)]
+    pub timeouts: TimeoutConfig,
+    #[serde(default)]
+    pub async_ack: AsyncAckConfig,
+    /// The amount of blocks to average the block time over when estimating
+    /// when a block will be finalized.
+    #[serde(default = "default_block_time_window")]
+    pub block_time_window: usize,
+    /// Attach the raw events correlated with each packet event to the emitted
+    /// [`ChainEvent`]s.
+    #[serde(default)]
+    pub include_raw_events: bool,
+    /// Attach the trace of the transferred denom to the emitted [`ChainEvent`]s of ics20
+    /// `SendPacket` and `RecvPacket` events. `ibc/{hash}` denoms are resolved with the transfer
+    /// module of the chain.
+    #[serde(default)]
+    pub resolve_denoms: bool,
+    /// Limits on the payload of sent packets. Packets exceeding these limits
+    /// are not relayed.
+    #[serde(default)]
+    pub payload_filter: PayloadFilterConfig,
+    /// Track the sequences of sent packets, and report any sequences that are
+    /// skipped. Disabled if not set.
+    #[serde(default)]
+    pub sequence_gaps: Option<SequenceGapConfig>,
+    /// Measure the time between sending a packet and its acknowledgement, and
+    /// report it once the acknowledgement is observed. Disabled if not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub packet_latency: Option<PacketLatencyConfig>,
+    /// Detection of chain upgrades and halts while fetching blocks.
+    #[serde(default)]
+    pub upgrades: UpgradeConfig,
+    /// Slow down fetching blocks while the queue is backed up. Disabled if not
+    /// set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backpressure: Option<BackpressureConfig>,
+    /// The sizing of the chunks that block ranges are fetched in.
+    #[serde(default)]
+    pub block_range: BlockRangeConfig,
+    /// The IBC revision number of this chain, used for all heights instead of the revision parsed
+    /// from the chain id. Only set this if the trailing number of the chain id is not the
+    /// revision number that the clients tracking this chain expect.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revision_number_override: Option<u64>,
+    /// A client tracking this chain on a counterparty, whose latest height the revision of this
+    /// chain is checked against before fetching the first blocks. If not set, the revision is
+    /// checked against the chain id reported by the node instead.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revision_check: Option<RevisionCheckConfig>,
+    /// Bounds of the in-memory caches.
+    #[serde(default)]
+    pub caches: CachesConfig,
+}
+
+fn default_block_time_window() -> usize {
+    DEFAULT_BLOCK_TIME_WINDOW
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CachesConfig {
+    /// The client types of wasm checksums. Code is immutable once stored, so entries never go
+    /// stale and there is no ttl by default.
+    #[serde(default = "default_checksum_cache")]
+    pub checksum: BoundsConfig,
+    /// The traces of resolved `ibc/{hash}` denoms, if [`Config::resolve_denoms`] is set.
+    #[serde(default = "default_denom_cache")]
+    pub denom: BoundsConfig,
+}
+
+impl Default for CachesConfig {
+    fn default() -> Self {
+        Self {
+            checksum: default_checksum_cache(),
+            denom: default_denom_cache(),
+        }
+    }
+}
+
+fn default_checksum_cache() -> BoundsConfig {
+    BoundsConfig {
+        max_entries: 1_024,
+        ttl_seconds: None,
+    }
+}
+
+fn default_denom_cache() -> BoundsConfig {
+    BoundsConfig {
+        max_entries: 10_000,
+        ttl_seconds: Some(24 * 60 * 60),
+    }
+}
+
+/// A [`Config`] that can be updated while the plugin is running.
+///
+/// Only the fields in [`LiveConfig::RELOADABLE`] are updated on reload, all
+/// other fields are used to construct the plugin and as such require a restart
+/// to change.
+#[derive(Debug, Clone)]
+pub struct LiveConfig(Arc<RwLock<Config>>);
+
+impl LiveConfig {
+    pub const RELOADABLE: &'static [&'static str] = &[
+        "async_ack",
+        "include_raw_events",
+        "resolve_denoms",
+        "payload_filter",
+        "upgrades",
+    ];
+
+    pub fn new(config: Config) -> Self {
+        Self(Arc::new(RwLock::new(config)))
+    }
+
+    pub fn async_ack(&self) -> AsyncAckConfig {
+        self.0
+            .read()
+            .expect("lock is not poisoned")
+            .async_ack
+            .clone()
+    }
+
+    pub fn include_raw_events(&self) -> bool {
+        self.0
+            .read()
+            .expect("lock is not poisoned")
+            .include_raw_events
+    }
+
+    pub fn resolve_denoms(&self) -> bool {
+        self.0.read().expect("lock is not poisoned").resolve_denoms
+    }
+
+    pub fn payload_filter(&self) -> PayloadFilterConfig {
+        self.0
+            .read()
+            .expect("lock is not poisoned")
+            .payload_filter
+            .clone()
+    }
+
+    pub fn upgrades(&self) -> UpgradeConfig {
+        self.0
+            .read()
+            .expect("lock is not poisoned")
+            .upgrades
+            .clone()
+    }
+
+    /// The currently active config.
+    pub fn get(&self) -> Config {
+        self.0.read().expect("lock is not poisoned").clone()
+    }
+
+    /// Apply the reloadable fields of `new_config`, and report any other
+    /// changed fields as rejected.
+    pub fn reload(&self, new_config: Config) -> ReloadReport {
+        let mut config = self.0.write().expect("lock is not poisoned");
+
+        let report = ReloadReport::diff(&*config, &new_config, Self::RELOADABLE);
+
+        config.async_ack = new_config.async_ack;
+        config.include_raw_events = new_config.include_raw_events;
+        config.resolve_denoms = new_config.resolve_denoms;
+        config.payload_filter = new_config.payload_filter;
+        config.upgrades = new_config.upgrades;
+
+        report
+    }
 }
 
 impl Plugin for Module {
@@ -95,29 +368,97 @@ impl Plugin for Module {
     type Cmd = Cmd;
 
     async fn new(config: Self::Config) -> Result<Self, BoxDynError> {
-        let tm_client = cometbft_rpc::Client::new(config.ws_url).await?;
-
-        let chain_id = tm_client.status().await?.node_info.network;
-
-        let chain_revision = chain_id
-            .split('-')
-            .last()
-            .ok_or_else(|| ChainIdParseError {
-                found: chain_id.clone(),
-                source: None,
-            })?
-            .parse()
-            .map_err(|err| ChainIdParseError {
-                found: chain_id.clone(),
-                source: Some(err),
-            })?;
+        let live_config = LiveConfig::new(config.clone());
+
+        if let Some(backpressure) = &config.backpressure {
+            backpressure.validate()?;
+        }
+
+        config.block_range.validate()?;
+
+        if !config.skip_startup_probe {
+            config
+                .ws_url
+                .probe_via(&config.network, DEFAULT_PROBE_TIMEOUT)
+                .await?;
+            config
+                .grpc_url
+                .probe_via(&config.network, DEFAULT_PROBE_TIMEOUT)
+                .await?;
+        }
+
+        let ws_auth = auth::resolve(config.ws_auth.as_ref())?;
+        let grpc_auth = GrpcAuth::new(auth::resolve(config.grpc_auth.as_ref())?.as_ref())
+            .with_network(config.network.clone());
+
+        let tm_client = cometbft_rpc::Client::new_with_connector(
+            config.ws_url,
+            AuthorizationHeader::jsonrpsee_headers(ws_auth.as_ref()),
+            config.network.cometbft_connector(),
+        )
+        .await?;
+
+        let network = tm_client.status().await?.node_info.network;
+
+        let chain_revision =
+            chain_revision(&config.chain_id, &network, config.revision_number_override)?;
+
+        if config.revision_check.is_none() {
+            // a mismatch is logged, and is not fatal
+            let _ = check_node_revision(&config.chain_id, &network, chain_revision);
+        }
+
+        let upgrades = UpgradeMonitor::new(config.chain_id.clone(), network, chain_revision, now());
+
+        let chain_id = config.chain_id.clone();
+
+        let sequence_gaps = config
+            .sequence_gaps
+            .map(|sequence_gaps| SequenceGapTracker::new(chain_id.clone(), sequence_gaps))
+            .transpose()?
+            .map(Arc::new);
+
+        let packet_latency = config.packet_latency.map(|packet_latency| {
+            Arc::new(PacketLatencyTracker::new(chain_id.clone(), packet_latency))
+        });
 
         Ok(Self {
+            finality: CometbftFinalityTracker::new(
+                tm_client.clone(),
+                chain_revision,
+                config.block_time_window,
+            ),
             tm_client,
-            chain_id: ChainId::new(chain_id),
-            chain_revision,
-            grpc_url: config.grpc_url,
-            checksum_cache: Arc::new(DashMap::default()),
+            upgrades: Arc::new(if config.revision_number_override.is_some() {
+                upgrades.with_pinned_revision()
+            } else {
+                upgrades
+            }),
+            chain_id,
+            denom_resolver: Arc::new(CachingDenomResolver::new(
+                GrpcDenomResolver::new([(
+                    config.chain_id.clone(),
+                    config.grpc_url.to_string(),
+                    grpc_auth.clone(),
+                )]),
+                &config.caches.denom,
+            )),
+            grpc_url: config.grpc_url.into(),
+            grpc_auth,
+            timeouts: config.timeouts,
+            checksum_cache: Arc::new(TimestampedCache::new(
+                CHECKSUM_CACHE,
+                &config.caches.checksum,
+            )),
+            config: live_config,
+            sequence_gaps,
+            packet_latency,
+            recent_heights: Arc::new(RecentHeights::new(RECENT_HEIGHTS_CAPACITY)),
+            backpressure: config.backpressure,
+            backpressure_state: Arc::default(),
+            block_range: config.block_range,
+            revision_check: config.revision_check,
+            revision_checked: Arc::default(),
         })
     }
 
@@ -128,17 +469,61 @@ impl Plugin for Module {
                 r#"[.. | ."@type"? == "fetch_blocks" and ."@value".chain_id == "{}"] | any"#,
                 config.chain_id
             ),
+            kind: Some(PluginKind::EventSource),
+            chains: vec![config.chain_id.clone()],
+            ibc_specs: vec![IbcClassic::ID, IbcUnion::ID],
         }
     }
 
-    async fn cmd(config: Self::Config, cmd: Self::Cmd) {
-        let module = Self::new(config).await.unwrap();
+    async fn cmd(config: Self::Config, cmd: Self::Cmd) -> Result<Box<dyn CmdOutput>, BoxDynError> {
+        // the ledger is read directly, as printing it doesn't require a connection to the chain
+        if let Cmd::Gaps = cmd {
+            let ledger = GapLedger::load(&config.sequence_gaps.unwrap_or_default().ledger_path)?;
 
-        match cmd {
-            Cmd::ChainId => println!("{}", module.chain_id),
-            Cmd::LatestHeight => println!("{}", module.latest_height().await.unwrap()),
+            return Ok(Box::new(GapsOutput::new(&ledger, &config.chain_id)));
         }
+
+        let module = Self::new(config).await?;
+
+        let output: Box<dyn CmdOutput> = match cmd {
+            Cmd::ChainId => Box::new(ChainIdOutput {
+                chain_id: module.chain_id,
+            }),
+            Cmd::LatestHeight => Box::new(LatestHeightOutput {
+                latest_height: module.latest_height().await?,
+            }),
+            Cmd::Gaps => unreachable!(),
+        };
+
+        Ok(output)
+    }
+}
+
+/// Check that the node is for the configured chain, and parse the revision number from the chain
+/// id, unless it is overridden.
+fn chain_revision(
+    chain_id: &ChainId,
+    network: &str,
+    revision_number_override: Option<u64>,
+) -> Result<u64, BoxDynError> {
+    ensure_chain_id(chain_id, network)?;
+
+    if let Some(revision) = revision_number_override {
+        return Ok(revision);
     }
+
+    Ok(network
+        .split('-')
+        .last()
+        .ok_or_else(|| ChainIdParseError {
+            found: network.to_owned(),
+            source: None,
+        })?
+        .parse()
+        .map_err(|err| ChainIdParseError {
+            found: network.to_owned(),
+            source: Some(err),
+        })?)
 }
 
 fn plugin_name(chain_id: &ChainId) -> String {
@@ -147,6 +532,15 @@ fn plugin_name(chain_id: &ChainId) -> String {
     format!("{PLUGIN_NAME}/{}", chain_id)
 }
 
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("the current timestamp must be greater than the unix epoch")
+        .as_millis()
+        .try_into()
+        .expect("millisecond timestamp overflowed a u64")
+}
+
 impl Module {
     fn plugin_name(&self) -> String {
         plugin_name(&self.chain_id)
@@ -154,18 +548,234 @@ impl Module {
 
     #[must_use]
     pub fn make_height(&self, height: u64) -> Height {
-        Height::new_with_revision(self.chain_revision, height)
+        Height::new_with_revision(self.upgrades.revision(), height)
+    }
+
+    /// Refuse to fetch blocks once fetching was stopped at an upgrade.
+    fn ensure_not_stopped(&self) -> RpcResult<()> {
+        match self.upgrades.stopped() {
+            Some(upgrade) => Err(VoyagerError::fatal(format!(
+                "the chain was upgraded from {} to {} at height {}, restart the plugin with the \
+                new chain id to continue fetching blocks",
+                upgrade.old_chain_id, upgrade.new_chain_id, upgrade.height
+            ))
+            .into()),
+            None => Ok(()),
+        }
+    }
+
+    /// Check the revision of this chain against [`Self::revision_check`], once. Neither a
+    /// mismatch nor a failed check is fatal, as they are only logged.
+    async fn check_revision_once(&self, voyager_client: &VoyagerClient) {
+        let Some(revision_check) = &self.revision_check else {
+            return;
+        };
+
+        if self.revision_checked.swap(true, Ordering::Relaxed) {
+            return;
+        }
+
+        if let Err(err) = check_counterparty_revision(
+            voyager_client,
+            &self.chain_id,
+            self.upgrades.revision(),
+            revision_check,
+        )
+        .await
+        {
+            warn!(error = %ErrorReporter(err), "unable to check the revision of this chain");
+        }
+    }
+
+    /// Check the node for upgrades and halts, returning the data to report. If an upgrade is
+    /// followed, the finality tracker is switched to the new revision.
+    async fn check_upgrades(&self) -> RpcResult<Vec<Op<VoyagerMessage>>> {
+        let revision = self.upgrades.revision();
+
+        let alerts = self
+            .upgrades
+            .check(&self.tm_client, &self.config.upgrades(), now())
+            .await?;
+
+        if self.upgrades.revision() != revision {
+            self.finality.set_revision(self.upgrades.revision());
+        }
+
+        Ok(alerts
+            .into_iter()
+            .map(|alert| data(PluginMessage::new(self.plugin_name(), alert)))
+            .collect())
+    }
+
+    /// Check a sent packet against the configured payload limits, returning
+    /// the [`PacketFiltered`] to emit in place of the chain event if any limit
+    /// is exceeded.
+    fn filter_packet(
+        &self,
+        tx_hash: H256,
+        ibc_spec_id: IbcSpecId,
+        channel_version: &str,
+        packet_data: &[u8],
+        packet: impl FnOnce() -> Value,
+    ) -> Option<Op<VoyagerMessage>> {
+        let reason = self
+            .config
+            .payload_filter()
+            .check(channel_version, packet_data)
+            .err()?;
+
+        warn!(%tx_hash, ?reason, "packet exceeds the payload limits and will not be relayed");
+
+        Some(data(PluginMessage::new(
+            self.plugin_name(),
+            ModuleData::from(PacketFiltered {
+                chain_id: self.chain_id.clone(),
+                tx_hash,
+                reason,
+                ibc_spec_id,
+                packet: packet(),
+            }),
+        )))
+    }
+
+    /// Record the sequence of a sent packet, if sequence gap tracking is
+    /// enabled. Failing to persist the ledger does not fail the event.
+    fn observe_sequence(&self, port_id: &PortId, channel_id: &ChannelId, sequence: u64) {
+        if let Some(sequence_gaps) = &self.sequence_gaps {
+            if let Err(err) = sequence_gaps.observe(port_id, channel_id, sequence) {
+                error!(
+                    %port_id,
+                    %channel_id,
+                    sequence,
+                    "error recording packet sequence: {}",
+                    ErrorReporter(err)
+                );
+            }
+        }
+    }
+
+    /// Record the receipt of a packet sent from `source_chain_id`, if packet
+    /// latency measurement is enabled.
+    fn observe_recv(
+        &self,
+        source_chain_id: &ChainId,
+        port_id: &PortId,
+        channel_id: &ChannelId,
+        sequence: u64,
+    ) {
+        if let Some(packet_latency) = &self.packet_latency {
+            packet_latency.observe_recv(
+                now_millis(),
+                source_chain_id,
+                port_id,
+                channel_id,
+                sequence,
+            );
+        }
+    }
+
+    /// The completed round trip of an acknowledged packet, if packet latency
+    /// measurement is enabled and the packet was sent while it was.
+    fn packet_round_trip(
+        &self,
+        port_id: &PortId,
+        channel_id: &ChannelId,
+        sequence: u64,
+    ) -> Option<Op<VoyagerMessage>> {
+        let completed = self.packet_latency.as_ref()?.observe_ack(
+            now_millis(),
+            port_id,
+            channel_id,
+            sequence,
+        )?;
+
+        info!(
+            %port_id,
+            %channel_id,
+            sequence,
+            total_ms = completed.total_ms,
+            "packet round trip completed"
+        );
+
+        Some(data(PluginMessage::new(
+            self.plugin_name(),
+            ModuleData::from(completed),
+        )))
+    }
+
+    /// All sequence gaps that have persisted for longer than the configured
+    /// duration, as [`SequenceGapDetected`](crate::data::SequenceGapDetected) data.
+    fn sequence_gap_alerts(&self) -> Vec<Op<VoyagerMessage>> {
+        let Some(sequence_gaps) = &self.sequence_gaps else {
+            return vec![];
+        };
+
+        match sequence_gaps.take_overdue() {
+            Ok(overdue) => overdue
+                .into_iter()
+                .map(|gap| {
+                    warn!(
+                        port_id = %gap.port_id,
+                        channel_id = %gap.channel,
+                        missing_count = gap.missing_count,
+                        since = gap.since,
+                        "sequences were skipped: {:?}",
+                        gap.missing
+                    );
+
+                    data(PluginMessage::new(
+                        self.plugin_name(),
+                        ModuleData::from(gap),
+                    ))
+                })
+                .collect(),
+            Err(err) => {
+                error!("error checking for sequence gaps: {}", ErrorReporter(err));
+
+                vec![]
+            }
+        }
+    }
+
+    /// The raw events correlated with `event`, if enabled.
+    fn raw_events(&self, event: &IbcEvent, tx_events: &[Event]) -> Option<Vec<RawTmEvent>> {
+        if self.config.include_raw_events() {
+            raw_events::correlated_events(event, tx_events)
+        } else {
+            None
+        }
+    }
+
+    /// The trace of the denom transferred in a packet, if enabled and the packet is an ics20
+    /// transfer. `chain_id` is the chain that the denom in the packet data is on.
+    async fn denom_trace(
+        &self,
+        chain_id: &ChainId,
+        channel_version: &str,
+        packet_data: &[u8],
+    ) -> Option<DenomTrace> {
+        if !self.config.resolve_denoms() {
+            return None;
+        }
+
+        packet_denom_trace(
+            &*self.denom_resolver,
+            chain_id,
+            channel_version,
+            packet_data,
+        )
+        .await
     }
 
     async fn client_type_of_checksum(&self, checksum: H256) -> RpcResult<Option<WasmClientType>> {
         if let Some(ty) = self.checksum_cache.get(&checksum) {
             debug!(
                 %checksum,
-                ty = ?*ty,
+                ?ty,
                 "cache hit for checksum"
             );
 
-            return Ok(Some(*ty));
+            return Ok(Some(ty));
         };
 
         info!(
@@ -173,29 +783,29 @@ impl Module {
             "cache miss for checksum"
         );
 
-        let bz = protos::ibc::lightclients::wasm::v1::query_client::QueryClient::connect(
-            self.grpc_url.clone(),
-        )
-        .await
-        .map_err(rpc_error(
-            "error connecting to grpc server",
-            Some(json!({
-                "grpc_url": self.grpc_url
-            })),
-        ))?
-        .code(protos::ibc::lightclients::wasm::v1::QueryCodeRequest {
-            checksum: checksum.into_encoding::<HexUnprefixed>().to_string(),
-        })
-        .await
-        .map_err(rpc_error(
-            "error querying wasm code",
-            Some(json!({
-                "checksum": checksum,
-                "grpc_url": self.grpc_url
-            })),
-        ))?
-        .into_inner()
-        .data;
+        let mut client = protos::ibc::lightclients::wasm::v1::query_client::QueryClient::new(
+            connect_grpc(&self.timeouts, &self.grpc_auth, &self.grpc_url).await?,
+        );
+
+        let bz = self
+            .timeouts
+            .with_timeout(
+                CallKind::Query,
+                client.code(protos::ibc::lightclients::wasm::v1::QueryCodeRequest {
+                    checksum: checksum.into_encoding::<HexUnprefixed>().to_string(),
+                }),
+            )
+            .await
+            .map_err(VoyagerError::from)?
+            .map_err(rpc_error(
+                "error querying wasm code",
+                Some(json!({
+                    "checksum": checksum,
+                    "grpc_url": self.grpc_url
+                })),
+            ))?
+            .into_inner()
+            .data;
 
         match parse_wasm_client_type(bz) {
             Ok(Some(ty)) => {
@@ -226,31 +836,33 @@ impl Module {
     async fn checksum_of_client_id(&self, client_id: ClientId) -> RpcResult<H256> {
         type WasmClientState = protos::ibc::lightclients::wasm::v1::ClientState;
 
-        let client_state = protos::ibc::core::client::v1::query_client::QueryClient::connect(
-            self.grpc_url.clone(),
-        )
-        .await
-        .map_err(rpc_error(
-            "error connecting to grpc server",
-            Some(json!({ "client_id": client_id })),
-        ))?
-        .client_state(protos::ibc::core::client::v1::QueryClientStateRequest {
-            client_id: client_id.to_string(),
-        })
-        .await
-        .map_err(rpc_error(
-            "error querying client state",
-            Some(json!({ "client_id": client_id })),
-        ))?
-        .into_inner()
-        .client_state
-        .ok_or_else(|| {
-            // lol
-            rpc_error(
-                "error fetching client state",
+        let mut client = protos::ibc::core::client::v1::query_client::QueryClient::new(
+            connect_grpc(&self.timeouts, &self.grpc_auth, &self.grpc_url).await?,
+        );
+
+        let client_state = self
+            .timeouts
+            .with_timeout(
+                CallKind::Query,
+                client.client_state(protos::ibc::core::client::v1::QueryClientStateRequest {
+                    client_id: client_id.to_string(),
+                }),
+            )
+            .await
+            .map_err(VoyagerError::from)?
+            .map_err(rpc_error(
+                "error querying client state",
                 Some(json!({ "client_id": client_id })),
-            )(&*Box::<dyn Error>::from("client state field is empty"))
-        })?;
+            ))?
+            .into_inner()
+            .client_state
+            .ok_or_else(|| {
+                // lol
+                rpc_error(
+                    "error fetching client state",
+                    Some(json!({ "client_id": client_id })),
+                )(&*Box::<dyn Error>::from("client state field is empty"))
+            })?;
 
         assert!(
             client_state.type_url == <WasmClientState as prost::Name>::type_url(),
@@ -311,32 +923,241 @@ impl Module {
     //     )
     // }
 
-    async fn latest_height(&self) -> Result<Height, cometbft_rpc::JsonRpcError> {
-        let commit_response = self.tm_client.commit(None).await?;
+    async fn latest_height(&self) -> RpcResult<Height> {
+        self.finality.latest_finalized().await
+    }
 
-        let mut height = commit_response
-            .signed_header
-            .header
-            .height
-            .inner()
-            .try_into()
-            .expect("value is >= 0; qed;");
+    /// Fetch the blocks at `height` once it is finalized. If `height` is already finalized (i.e.
+    /// when backfilling), there is no need to wait for it.
+    async fn fetch_blocks_when_finalized(&self, height: Height) -> RpcResult<Op<VoyagerMessage>> {
+        let fetch_blocks = call(PluginMessage::new(
+            self.plugin_name(),
+            ModuleCall::from(FetchBlocks { height }),
+        ));
+
+        if self.finality.is_finalized(height).await? {
+            return Ok(fetch_blocks);
+        }
+
+        let wait_for_block = call(PluginMessage::new(
+            self.plugin_name(),
+            ModuleCall::from(WaitForBlock { height }),
+        ));
+
+        // avoid polling for the height before it's expected to be finalized
+        Ok(match self.finality.estimate_finalization(height).await? {
+            Some(estimate) if estimate.as_secs() > 0 => {
+                seq([defer(now() + estimate.as_secs()), wait_for_block])
+            }
+            _ => wait_for_block,
+        })
+    }
+
+    async fn wait_for_block(&self, height: Height) -> RpcResult<Op<VoyagerMessage>> {
+        self.ensure_not_stopped()?;
+
+        let alerts = self.check_upgrades().await?;
+
+        if self.upgrades.stopped().is_some() {
+            return Ok(conc(alerts));
+        }
+
+        // the revision changes if an upgrade was followed
+        let height = self.make_height(height.height());
+
+        let next = if self.finality.is_finalized(height).await? {
+            call(PluginMessage::new(
+                self.plugin_name(),
+                ModuleCall::from(FetchBlocks { height }),
+            ))
+        } else {
+            debug!(%height, "waiting for block to be finalized");
+
+            let delay = self
+                .finality
+                .estimate_finalization(height)
+                .await?
+                .map_or(0, |estimate| estimate.as_secs());
+
+            seq([
+                defer(now() + delay.max(1)),
+                call(PluginMessage::new(
+                    self.plugin_name(),
+                    ModuleCall::from(WaitForBlock { height }),
+                )),
+            ])
+        };
 
-        if !commit_response.canonical {
-            debug!("commit is not canonical, latest finalized height is the previous block");
-            height -= 1;
+        Ok(conc(alerts.into_iter().chain([next])))
+    }
+
+    /// Emit a [`MakeChainEvent`] for each IBC event in `txs`, the transactions at `height`
+    /// starting at `page`.
+    fn tx_events(
+        &self,
+        height: Height,
+        page: NonZeroU32,
+        txs: Vec<TxResponse>,
+    ) -> RpcResult<Op<VoyagerMessage>> {
+        let include_raw_events = self.config.include_raw_events();
+
+        let txs = txs
+            .into_iter()
+            .map(|txr| {
+                // only keep the raw events around if they're needed
+                let tx_events = if include_raw_events {
+                    txr.tx_result.events.clone()
+                } else {
+                    vec![]
+                };
+
+                txr.tx_result
+                    .events
+                    .into_iter()
+                    .filter_map(|event| {
+                        debug!(%event.ty, "observed event");
+                        union_ibc::parse(event)
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+                    .map(|events| {
+                        let (events, union_schemas): (Vec<_>, Vec<_>) = events
+                            .into_iter()
+                            .map(|parsed| (parsed.event, parsed.schema_version))
+                            .unzip();
+
+                        (txr.hash.into_encoding(), events, union_schemas, tx_events)
+                    })
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| {
+                ErrorObject::owned(
+                    -1,
+                    ErrorReporter(err).to_string(),
+                    Some(json!({
+                        "height": height,
+                        "page": page
+                    })),
+                )
+            })?;
+
+        let first_seen = now();
+        let recheck_delay = self.config.async_ack().recheck_delay;
+
+        Ok(conc(txs.into_iter().flat_map(
+            |(tx_hash, events, union_schemas, tx_events)| {
+                // packets received without an acknowledgement being written in the
+                // same tx are acknowledged asynchronously, check back later
+                let pending_acks = async_ack::pending_acks(&events)
+                    .into_iter()
+                    .map(|pending| {
+                        debug!(?pending, "packet received without acknowledgement");
+
+                        seq([
+                            defer(first_seen + recheck_delay),
+                            call(PluginMessage::new(
+                                self.plugin_name(),
+                                ModuleCall::from(CheckAsyncAck {
+                                    pending,
+                                    height,
+                                    tx_hash,
+                                    first_seen,
+                                }),
+                            )),
+                        ])
+                    })
+                    .collect::<Vec<_>>();
+
+                events
+                    .into_iter()
+                    .zip(union_schemas)
+                    .map(move |(ibc_event, union_schema)| {
+                        debug!(
+                            event = %ibc_event.name(),
+                            ?union_schema,
+                            "observed IBC event"
+                        );
+                        call(PluginMessage::new(
+                            self.plugin_name(),
+                            ModuleCall::from(MakeChainEvent {
+                                height,
+                                tx_hash,
+                                raw_events: self.raw_events(&ibc_event, &tx_events),
+                                event: ibc_event,
+                                union_schema,
+                            }),
+                        ))
+                    })
+                    .chain(pending_acks)
+            },
+        )))
+    }
+
+    /// Fetch the next chunk of `range` once its first block is finalized, requeuing the rest of
+    /// the range. See [`block_range`] for how the size of the chunk is chosen.
+    async fn fetch_block_range(&self, range: FetchBlockRange) -> RpcResult<Op<VoyagerMessage>> {
+        self.ensure_not_stopped()?;
+
+        if range.from.revision() != range.to.revision() || range.from.height() > range.to.height() {
+            return Err(VoyagerError::fatal(format!(
+                "invalid block range from height {} to {}",
+                range.from, range.to
+            ))
+            .into());
+        }
+
+        let finalized = self.finality.latest_finalized().await?;
+
+        let Some(heights) = block_range::next_chunk(&range, finalized, &self.block_range) else {
+            debug!(from = %range.from, "waiting for block range to be finalized");
+
+            let delay = self
+                .finality
+                .estimate_finalization(range.from)
+                .await?
+                .map_or(0, |estimate| estimate.as_secs());
+
+            return Ok(seq([
+                defer(now() + delay.max(1)),
+                call(PluginMessage::new(
+                    self.plugin_name(),
+                    ModuleCall::from(range),
+                )),
+            ]));
+        };
+
+        let chunk = block_range::fetch_chunk(&self.tm_client, &range, heights.clone()).await?;
+
+        info!(
+            from = heights.start(),
+            to = heights.end(),
+            range_to = %range.to,
+            events = chunk.events,
+            events_per_block = ?chunk.next.as_ref().and_then(|next| next.events_per_block),
+            "fetched chunk of block range"
+        );
+
+        let mut ops = vec![];
+
+        for (height, txs) in chunk.blocks {
+            self.recent_heights.push(height);
+
+            ops.push(self.tx_events(height, NonZeroU32::MIN, txs)?);
         }
 
-        debug!(height, "latest height");
+        ops.extend(chunk.next.map(|next| {
+            call(PluginMessage::new(
+                self.plugin_name(),
+                ModuleCall::from(next),
+            ))
+        }));
 
-        Ok(self.make_height(height))
+        Ok(conc(ops))
     }
 
     #[allow(clippy::too_many_arguments)] // pls
     async fn make_packet_metadata(
         &self,
         event_height: Height,
-        self_connection_id: ConnectionId,
         self_port_id: PortId,
         self_channel_id: ChannelId,
         other_port_id: PortId,
@@ -349,50 +1170,43 @@ impl Module {
         ibc_classic_spec::ChannelMetadata,
         channel::order::Order,
     )> {
-        let self_connection = voyager_rpc_client
+        let this_channel = voyager_rpc_client
             .query_ibc_state(
                 self.chain_id.clone(),
                 event_height.into(),
-                ibc_classic_spec::ConnectionPath {
-                    connection_id: self_connection_id.clone(),
+                ibc_classic_spec::ChannelEndPath {
+                    port_id: self_port_id.clone(),
+                    channel_id: self_channel_id.clone(),
                 },
             )
             .await?
             .state
-            .ok_or_else(missing_state("connection must exist", None))?;
+            .ok_or_else(missing_state("channel must exist", None))?;
 
-        let client_info = voyager_rpc_client
-            .client_info::<IbcClassic>(self.chain_id.clone(), self_connection.client_id.clone())
-            .await?;
+        let connection_hops = connection_hops::walk(
+            voyager_rpc_client,
+            self.chain_id.clone(),
+            event_height,
+            &this_channel.connection_hops,
+        )
+        .await?;
 
-        let client_meta = voyager_rpc_client
-            .client_meta::<IbcClassic>(
+        let client_info = voyager_rpc_client
+            .client_info::<IbcClassic>(
                 self.chain_id.clone(),
-                event_height.into(),
-                self_connection.client_id.clone(),
+                connection_hops.source[0].client_id.clone(),
             )
             .await?;
 
-        let this_channel = voyager_rpc_client
+        let counterparty_chain_id = connection_hops.counterparty_chain_id;
+
+        let counterparty_channel = voyager_rpc_client
             .query_ibc_state(
-                self.chain_id.clone(),
-                event_height.into(),
+                counterparty_chain_id.clone(),
+                QueryHeight::Latest,
                 ibc_classic_spec::ChannelEndPath {
-                    port_id: self_port_id.clone(),
-                    channel_id: self_channel_id.clone(),
-                },
-            )
-            .await?
-            .state
-            .ok_or_else(missing_state("channel must exist", None))?;
-
-        let counterparty_channel = voyager_rpc_client
-            .query_ibc_state(
-                client_meta.chain_id.clone(),
-                QueryHeight::Latest,
-                ibc_classic_spec::ChannelEndPath {
-                    port_id: other_port_id.clone(),
-                    channel_id: other_channel_id.clone(),
+                    port_id: other_port_id.clone(),
+                    channel_id: other_channel_id.clone(),
                 },
             )
             .await?
@@ -403,26 +1217,17 @@ impl Module {
             port_id: self_port_id.clone(),
             channel_id: self_channel_id.clone(),
             version: this_channel.version,
-            connection: ibc_classic_spec::ConnectionMetadata {
-                client_id: self_connection.client_id,
-                connection_id: self_connection_id.clone(),
-            },
+            connection_hops: connection_hops.source,
         };
         let destination_channel = ibc_classic_spec::ChannelMetadata {
             port_id: other_port_id.clone(),
             channel_id: other_channel_id.clone(),
             version: counterparty_channel.version,
-            connection: ibc_classic_spec::ConnectionMetadata {
-                client_id: self_connection.counterparty.client_id,
-                connection_id: self_connection
-                    .counterparty
-                    .connection_id
-                    .expect("counterparty connection id should be set"),
-            },
+            connection_hops: connection_hops.destination,
         };
 
         Ok((
-            client_meta.chain_id,
+            counterparty_chain_id,
             client_info,
             source_channel,
             destination_channel,
@@ -431,6 +1236,672 @@ impl Module {
     }
 }
 
+impl Module {
+    async fn check_async_ack(
+        &self,
+        e: &Extensions,
+        check: CheckAsyncAck,
+    ) -> RpcResult<Op<VoyagerMessage>> {
+        let voyager_client = e.try_get::<VoyagerClient>()?;
+
+        let now = now();
+
+        let status = async_ack::check(
+            voyager_client,
+            &self.chain_id,
+            &self.config.async_ack(),
+            &check.pending,
+            check.first_seen,
+            now,
+        )
+        .await?;
+
+        match status {
+            AckStatus::Written { height } => {
+                info!(pending = ?check.pending, %height, "asynchronous acknowledgement written");
+
+                self.find_write_acknowledgement(&check.pending).await
+            }
+            AckStatus::Pending { recheck_at } => {
+                debug!(pending = ?check.pending, recheck_at, "acknowledgement not yet written");
+
+                Ok(seq([
+                    defer(recheck_at),
+                    call(PluginMessage::new(
+                        self.plugin_name(),
+                        ModuleCall::from(check),
+                    )),
+                ]))
+            }
+            AckStatus::Missing => {
+                let waited = now.saturating_sub(check.first_seen);
+
+                error!(
+                    pending = ?check.pending,
+                    height = %check.height,
+                    tx_hash = %check.tx_hash,
+                    waited,
+                    "packet was received but no acknowledgement has been written"
+                );
+
+                Ok(data(PluginMessage::new(
+                    self.plugin_name(),
+                    ModuleData::from(AsyncAckMissing {
+                        chain_id: self.chain_id.clone(),
+                        pending: check.pending,
+                        height: check.height,
+                        tx_hash: check.tx_hash,
+                        waited,
+                    }),
+                )))
+            }
+        }
+    }
+
+    /// Find the `write_acknowledgement` event for a packet whose acknowledgement has been written,
+    /// and emit it as a chain event.
+    async fn find_write_acknowledgement(
+        &self,
+        pending: &PendingAck,
+    ) -> RpcResult<Op<VoyagerMessage>> {
+        let PendingAck::V1 {
+            port_id,
+            channel_id,
+            sequence,
+        } = pending
+        else {
+            return Err(VoyagerError::fatal(
+                "union write_acknowledgement events are not indexed by this plugin",
+            )
+            .into());
+        };
+
+        let response = self
+            .timeouts
+            .with_timeout(
+                CallKind::Query,
+                self.tm_client.tx_search(
+                    format!(
+                        "write_acknowledgement.packet_dst_port='{port_id}' AND \
+                        write_acknowledgement.packet_dst_channel='{}' AND \
+                        write_acknowledgement.packet_sequence='{sequence}'",
+                        channel_id.to_string_prefixed()
+                    ),
+                    false,
+                    const { option_unwrap!(NonZeroU32::new(1)) },
+                    PER_PAGE_LIMIT,
+                    cometbft_rpc::rpc_types::Order::Desc,
+                ),
+            )
+            .await
+            .map_err(VoyagerError::from)?
+            .map_err(rpc_error(
+                "error searching for write_acknowledgement event",
+                Some(json!({ "pending": pending })),
+            ))?;
+
+        response
+            .txs
+            .into_iter()
+            .find_map(|txr| {
+                let height = txr.height?;
+
+                let tx_events = txr.tx_result.events;
+
+                tx_events
+                    .iter()
+                    .cloned()
+                    .filter_map(IbcEvent::try_from_tendermint_event)
+                    .filter_map(Result::ok)
+                    .find(|event| {
+                        matches!(
+                            event,
+                            IbcEvent::WriteAcknowledgement(ack)
+                                if ack.packet_dst_port == *port_id
+                                    && ack.packet_dst_channel == *channel_id
+                                    && ack.packet_sequence == *sequence
+                        )
+                    })
+                    .map(|event| {
+                        call(PluginMessage::new(
+                            self.plugin_name(),
+                            ModuleCall::from(MakeChainEvent {
+                                height: self.make_height(height.get()),
+                                tx_hash: txr.hash.into_encoding(),
+                                raw_events: self.raw_events(&event, &tx_events),
+                                event,
+                                union_schema: None,
+                            }),
+                        ))
+                    })
+            })
+            .ok_or_else(|| {
+                // the acknowledgement is committed at a finalized height, so the event may not be
+                // indexed yet
+                VoyagerError::retryable(format!(
+                    "acknowledgement for packet {sequence} on {port_id}/{channel_id} \
+                    is written but no write_acknowledgement event was found"
+                ))
+                .into()
+            })
+    }
+
+    /// Make the chain event for an event emitted by the union IBC contract.
+    #[instrument(
+        skip_all,
+        fields(
+            chain_id = %self.chain_id,
+            %height,
+            %tx_hash,
+            event = event.name(),
+            union_schema = union_schema.map(field::display),
+            client_id = field::Empty,
+            connection_id = field::Empty,
+        )
+    )]
+    async fn make_union_chain_event(
+        &self,
+        voyager_client: &VoyagerClient,
+        height: Height,
+        tx_hash: H256,
+        event: IbcEvent,
+        raw_events: Option<Vec<RawTmEvent>>,
+        union_schema: Option<SchemaVersion>,
+    ) -> RpcResult<Op<VoyagerMessage>> {
+        // events at height N are provable at height N+k where k<0
+        let provable_height = height.increment();
+
+        let chain_event = match event {
+            IbcEvent::UnionCreateClient(create_client) => {
+                Span::current().record("client_id", create_client.client_id);
+
+                union_events::create_client(
+                    voyager_client,
+                    self.chain_id.clone(),
+                    height,
+                    provable_height,
+                    tx_hash,
+                    create_client,
+                    raw_events,
+                )
+                .await?
+            }
+            IbcEvent::UnionUpdateClient(update_client) => {
+                Span::current().record("client_id", update_client.client_id);
+
+                let client_id = union_id(update_client.client_id)?;
+
+                let client_info = voyager_client
+                    .client_info::<IbcUnion>(self.chain_id.clone(), client_id)
+                    .await?;
+
+                let client_meta = voyager_client
+                    .client_meta::<IbcUnion>(self.chain_id.clone(), height.into(), client_id)
+                    .await?;
+
+                ChainEvent {
+                    chain_id: self.chain_id.clone(),
+                    client_info: client_info.clone(),
+                    counterparty_chain_id: client_meta.chain_id,
+                    tx_hash,
+                    provable_height,
+                    ibc_spec_id: IbcUnion::ID,
+                    event: into_value::<ibc_union_spec::FullEvent>(
+                        ibc_union_spec::UpdateClient {
+                            client_id,
+                            client_type: client_info.client_type,
+                            height: update_client.height,
+                        }
+                        .into(),
+                    ),
+                    raw_events,
+                    denom_trace: None,
+                }
+            }
+            IbcEvent::UnionConnectionOpenInit(connection_open_init) => {
+                Span::current().record("client_id", connection_open_init.client_id);
+                Span::current().record("connection_id", connection_open_init.connection_id);
+
+                let client_id = union_id(connection_open_init.client_id)?;
+
+                let client_info = voyager_client
+                    .client_info::<IbcUnion>(self.chain_id.clone(), client_id)
+                    .await?;
+
+                let client_meta = voyager_client
+                    .client_meta::<IbcUnion>(self.chain_id.clone(), height.into(), client_id)
+                    .await?;
+
+                ChainEvent {
+                    chain_id: self.chain_id.clone(),
+                    client_info,
+                    counterparty_chain_id: client_meta.chain_id,
+                    tx_hash,
+                    provable_height,
+                    ibc_spec_id: IbcUnion::ID,
+                    event: into_value::<ibc_union_spec::FullEvent>(
+                        ibc_union_spec::ConnectionOpenInit {
+                            client_id,
+                            connection_id: union_id(connection_open_init.connection_id)?,
+                            counterparty_client_id: union_id(
+                                connection_open_init.counterparty_client_id,
+                            )?,
+                        }
+                        .into(),
+                    ),
+                    raw_events,
+                    denom_trace: None,
+                }
+            }
+            IbcEvent::UnionConnectionOpenTry(connection_open_try) => {
+                Span::current().record("client_id", connection_open_try.client_id);
+                Span::current().record("connection_id", connection_open_try.connection_id);
+
+                let client_id = union_id(connection_open_try.client_id)?;
+
+                let client_info = voyager_client
+                    .client_info::<IbcUnion>(self.chain_id.clone(), client_id)
+                    .await?;
+
+                let client_meta = voyager_client
+                    .client_meta::<IbcUnion>(self.chain_id.clone(), height.into(), client_id)
+                    .await?;
+
+                ChainEvent {
+                    chain_id: self.chain_id.clone(),
+                    client_info,
+                    counterparty_chain_id: client_meta.chain_id,
+                    tx_hash,
+                    provable_height,
+                    ibc_spec_id: IbcUnion::ID,
+                    event: into_value::<ibc_union_spec::FullEvent>(
+                        ibc_union_spec::ConnectionOpenTry {
+                            connection_id: union_id(connection_open_try.connection_id)?,
+                            counterparty_connection_id: union_id(
+                                connection_open_try.counterparty_connection_id,
+                            )?,
+                            client_id,
+                            counterparty_client_id: union_id(
+                                connection_open_try.counterparty_client_id,
+                            )?,
+                        }
+                        .into(),
+                    ),
+                    raw_events,
+                    denom_trace: None,
+                }
+            }
+            IbcEvent::UnionConnectionOpenAck(connection_open_ack) => {
+                Span::current().record("client_id", connection_open_ack.client_id);
+                Span::current().record("connection_id", connection_open_ack.connection_id);
+
+                let client_id = union_id(connection_open_ack.client_id)?;
+
+                let client_info = voyager_client
+                    .client_info::<IbcUnion>(self.chain_id.clone(), client_id)
+                    .await?;
+
+                let client_meta = voyager_client
+                    .client_meta::<IbcUnion>(self.chain_id.clone(), height.into(), client_id)
+                    .await?;
+
+                ChainEvent {
+                    chain_id: self.chain_id.clone(),
+                    client_info,
+                    counterparty_chain_id: client_meta.chain_id,
+                    tx_hash,
+                    provable_height,
+                    ibc_spec_id: IbcUnion::ID,
+                    event: into_value::<ibc_union_spec::FullEvent>(
+                        ibc_union_spec::ConnectionOpenAck {
+                            connection_id: union_id(connection_open_ack.connection_id)?,
+                            counterparty_connection_id: union_id(
+                                connection_open_ack.counterparty_connection_id,
+                            )?,
+                            client_id,
+                            counterparty_client_id: union_id(
+                                connection_open_ack.counterparty_client_id,
+                            )?,
+                        }
+                        .into(),
+                    ),
+                    raw_events,
+                    denom_trace: None,
+                }
+            }
+            IbcEvent::UnionConnectionOpenConfirm(connection_open_confirm) => {
+                Span::current().record("client_id", connection_open_confirm.client_id);
+                Span::current().record("connection_id", connection_open_confirm.connection_id);
+
+                let client_id = union_id(connection_open_confirm.client_id)?;
+
+                let client_info = voyager_client
+                    .client_info::<IbcUnion>(self.chain_id.clone(), client_id)
+                    .await?;
+
+                let client_meta = voyager_client
+                    .client_meta::<IbcUnion>(self.chain_id.clone(), height.into(), client_id)
+                    .await?;
+
+                ChainEvent {
+                    chain_id: self.chain_id.clone(),
+                    client_info,
+                    counterparty_chain_id: client_meta.chain_id,
+                    tx_hash,
+                    provable_height,
+                    ibc_spec_id: IbcUnion::ID,
+                    event: into_value::<ibc_union_spec::FullEvent>(
+                        ibc_union_spec::ConnectionOpenConfirm {
+                            connection_id: union_id(connection_open_confirm.connection_id)?,
+                            counterparty_connection_id: union_id(
+                                connection_open_confirm.counterparty_connection_id,
+                            )?,
+                            client_id,
+                            counterparty_client_id: union_id(
+                                connection_open_confirm.counterparty_client_id,
+                            )?,
+                        }
+                        .into(),
+                    ),
+                    raw_events,
+                    denom_trace: None,
+                }
+            }
+            IbcEvent::UnionChannelOpenTry(channel_open_try) => {
+                Span::current().record("connection_id", channel_open_try.connection_id);
+
+                let connection = voyager_client
+                    .query_ibc_state(
+                        self.chain_id.clone(),
+                        QueryHeight::Specific(height),
+                        ibc_union_spec::ConnectionPath {
+                            connection_id: channel_open_try.connection_id,
+                        },
+                    )
+                    .await?
+                    .state
+                    .ok_or_else(missing_state("connection must exist", None))?;
+
+                Span::current().record("client_id", connection.client_id);
+
+                let client_id = union_id(connection.client_id)?;
+
+                let client_info = voyager_client
+                    .client_info::<IbcUnion>(self.chain_id.clone(), client_id)
+                    .await?;
+
+                let client_meta = voyager_client
+                    .client_meta::<IbcUnion>(self.chain_id.clone(), height.into(), client_id)
+                    .await?;
+
+                ChainEvent {
+                    chain_id: self.chain_id.clone(),
+                    client_info,
+                    counterparty_chain_id: client_meta.chain_id,
+                    tx_hash,
+                    provable_height,
+                    ibc_spec_id: IbcUnion::ID,
+                    event: into_value::<ibc_union_spec::FullEvent>(
+                        ibc_union_spec::ChannelOpenTry {
+                            port_id: channel_open_try.port_id.into_bytes().into(),
+                            channel_id: union_id(channel_open_try.channel_id)?,
+                            counterparty_port_id: channel_open_try
+                                .counterparty_port_id
+                                .into_encoding(),
+                            counterparty_channel_id: union_id(
+                                channel_open_try.counterparty_channel_id,
+                            )?,
+                            connection: channel_connection(connection)?,
+                            version: channel_open_try.counterparty_version,
+                        }
+                        .into(),
+                    ),
+                    raw_events,
+                    denom_trace: None,
+                }
+            }
+            IbcEvent::UnionChannelOpenConfirm(channel_open_confirm) => {
+                Span::current().record("connection_id", channel_open_confirm.connection_id);
+
+                let channel = voyager_client
+                    .query_ibc_state(
+                        self.chain_id.clone(),
+                        QueryHeight::Specific(height),
+                        ibc_union_spec::ChannelPath {
+                            channel_id: channel_open_confirm.channel_id,
+                        },
+                    )
+                    .await?
+                    .state
+                    .ok_or_else(missing_state("channel must exist", None))?;
+
+                let connection = voyager_client
+                    .query_ibc_state(
+                        self.chain_id.clone(),
+                        QueryHeight::Specific(height),
+                        ibc_union_spec::ConnectionPath {
+                            connection_id: channel_open_confirm.connection_id,
+                        },
+                    )
+                    .await?
+                    .state
+                    .ok_or_else(missing_state("connection must exist", None))?;
+
+                Span::current().record("client_id", connection.client_id);
+
+                let client_id = union_id(connection.client_id)?;
+
+                let client_info = voyager_client
+                    .client_info::<IbcUnion>(self.chain_id.clone(), client_id)
+                    .await?;
+
+                let client_meta = voyager_client
+                    .client_meta::<IbcUnion>(self.chain_id.clone(), height.into(), client_id)
+                    .await?;
+
+                ChainEvent {
+                    chain_id: self.chain_id.clone(),
+                    client_info,
+                    counterparty_chain_id: client_meta.chain_id,
+                    tx_hash,
+                    provable_height,
+                    ibc_spec_id: IbcUnion::ID,
+                    event: into_value::<ibc_union_spec::FullEvent>(
+                        ibc_union_spec::ChannelOpenConfirm {
+                            port_id: channel_open_confirm.port_id.into_bytes().into(),
+                            channel_id: union_id(channel_open_confirm.channel_id)?,
+                            counterparty_port_id: channel_open_confirm
+                                .counterparty_port_id
+                                .into_encoding(),
+                            counterparty_channel_id: union_id(
+                                channel_open_confirm.counterparty_channel_id,
+                            )?,
+                            connection: channel_connection(connection)?,
+                            version: channel.version,
+                        }
+                        .into(),
+                    ),
+                    raw_events,
+                    denom_trace: None,
+                }
+            }
+            IbcEvent::UnionSendPacket(send_packet) => {
+                return self
+                    .make_union_send_packet(
+                        voyager_client,
+                        height,
+                        tx_hash,
+                        send_packet.packet,
+                        None,
+                        raw_events,
+                    )
+                    .await;
+            }
+            IbcEvent::UnionBatchSend(batch_send) => {
+                // every packet in the batch was already sent individually, so this emits a full,
+                // duplicate send_packet event for each of them with its position in the batch set.
+                // consumers that relay packets must skip events where `batch` is set, otherwise
+                // the packets would be relayed twice
+                let batch = PacketBatch::new(batch_send.channel_id, &batch_send.packets);
+
+                debug!(
+                    batch_hash = %batch.batch_hash,
+                    packets = batch_send.packets.len(),
+                    "packets were batched"
+                );
+
+                let mut ops = Vec::with_capacity(batch_send.packets.len());
+
+                for (packet, member) in batch_send.packets.into_iter().zip(batch.members()) {
+                    ops.push(
+                        self.make_union_send_packet(
+                            voyager_client,
+                            height,
+                            tx_hash,
+                            packet,
+                            Some(member),
+                            raw_events.clone(),
+                        )
+                        .await?,
+                    );
+                }
+
+                return Ok(conc(ops));
+            }
+            _ => unreachable!("only union events are passed to make_union_chain_event"),
+        };
+
+        debug!(
+            counterparty_chain_id = %chain_event.counterparty_chain_id,
+            client_type = %chain_event.client_info.client_type,
+            "made union chain event"
+        );
+
+        Ok(data(chain_event))
+    }
+
+    /// Make the chain event for a packet sent on this chain, or for a packet that was batched if
+    /// `batch` is set.
+    async fn make_union_send_packet(
+        &self,
+        voyager_client: &VoyagerClient,
+        height: Height,
+        tx_hash: H256,
+        packet: ibc_solidity::Packet,
+        batch: Option<BatchMember>,
+        raw_events: Option<Vec<RawTmEvent>>,
+    ) -> RpcResult<Op<VoyagerMessage>> {
+        let provable_height = height.increment();
+
+        let source_channel = voyager_client
+            .query_ibc_state(
+                self.chain_id.clone(),
+                QueryHeight::Specific(height),
+                ibc_union_spec::ChannelPath {
+                    channel_id: packet.source_channel,
+                },
+            )
+            .await?
+            .state
+            .ok_or_else(missing_state("channel must exist", None))?;
+
+        Span::current().record("connection_id", source_channel.connection_id);
+
+        let source_connection = voyager_client
+            .query_ibc_state(
+                self.chain_id.clone(),
+                QueryHeight::Specific(height),
+                ibc_union_spec::ConnectionPath {
+                    connection_id: source_channel.connection_id,
+                },
+            )
+            .await?
+            .state
+            .ok_or_else(missing_state("connection must exist", None))?;
+
+        Span::current().record("client_id", source_connection.client_id);
+
+        let client_id = union_id(source_connection.client_id)?;
+
+        let client_info = voyager_client
+            .client_info::<IbcUnion>(self.chain_id.clone(), client_id)
+            .await?;
+
+        let client_meta = voyager_client
+            .client_meta::<IbcUnion>(self.chain_id.clone(), height.into(), client_id)
+            .await?;
+
+        let send_packet = ibc_union_spec::SendPacket {
+            packet_data: packet.data.into(),
+            packet: ibc_union_spec::PacketMetadata {
+                source_channel: ibc_union_spec::ChannelMetadata {
+                    channel_id: union_id(packet.source_channel)?,
+                    version: source_channel.version.clone(),
+                    connection: ibc_union_spec::ConnectionMetadata {
+                        client_id,
+                        connection_id: union_id(source_channel.connection_id)?,
+                    },
+                },
+                destination_channel: ibc_union_spec::ChannelMetadata {
+                    channel_id: union_id(packet.destination_channel)?,
+                    version: source_channel.version,
+                    connection: ibc_union_spec::ConnectionMetadata {
+                        client_id: union_id(source_connection.counterparty_client_id)?,
+                        connection_id: union_id(source_connection.counterparty_connection_id)?,
+                    },
+                },
+                timeout_height: packet.timeout_height,
+                timeout_timestamp: packet.timeout_timestamp,
+            },
+            batch,
+        };
+
+        if let Some(filtered) = self.filter_packet(
+            tx_hash,
+            IbcUnion::ID,
+            &send_packet.packet.source_channel.version,
+            &send_packet.packet_data,
+            || into_value::<ibc_union_spec::FullEvent>(send_packet.clone().into()),
+        ) {
+            return Ok(filtered);
+        }
+
+        let chain_event = ChainEvent {
+            chain_id: self.chain_id.clone(),
+            client_info,
+            counterparty_chain_id: client_meta.chain_id,
+            tx_hash,
+            provable_height,
+            ibc_spec_id: IbcUnion::ID,
+            event: into_value::<ibc_union_spec::FullEvent>(send_packet.into()),
+            raw_events,
+            denom_trace: None,
+        };
+
+        debug!(
+            counterparty_chain_id = %chain_event.counterparty_chain_id,
+            client_type = %chain_event.client_info.client_type,
+            "made union chain event"
+        );
+
+        Ok(data(chain_event))
+    }
+}
+
+impl NodeStatusClient for cometbft_rpc::Client {
+    async fn node_status(&self) -> RpcResult<NodeStatus> {
+        let status = self
+            .status()
+            .await
+            .map_err(rpc_error("error fetching node status", None))?;
+
+        Ok(NodeStatus {
+            network: status.node_info.network,
+            latest_height: status.sync_info.latest_block_height,
+        })
+    }
+}
+
 #[derive(Debug, thiserror::Error)]
 #[error("unable to parse chain id: expected format `<chain>-<revision-number>`, found `{found}`")]
 pub struct ChainIdParseError {
@@ -439,6 +1910,33 @@ pub struct ChainIdParseError {
     source: Option<ParseIntError>,
 }
 
+/// Claim the [`FetchBlocks`](voyager_message::call::FetchBlocks) and
+/// [`FetchBlockRange`](voyager_message::call::FetchBlockRange) calls for `chain_id`, passing
+/// through all other ops unchanged.
+fn run_pass(chain_id: &ChainId, msgs: Vec<Op<VoyagerMessage>>) -> PassResult<VoyagerMessage> {
+    PassResult::map_claimed(msgs, |op| match op {
+        Op::Call(Call::FetchBlocks(fetch)) if &fetch.chain_id == chain_id => {
+            Claim::Ready(call(PluginMessage::new(
+                plugin_name(chain_id),
+                ModuleCall::from(FetchBlocks {
+                    height: fetch.start_height,
+                }),
+            )))
+        }
+        Op::Call(Call::FetchBlockRange(range)) if &range.chain_id == chain_id => {
+            Claim::Ready(call(PluginMessage::new(
+                plugin_name(chain_id),
+                ModuleCall::from(FetchBlockRange {
+                    from: range.from_height,
+                    to: range.to_height,
+                    events_per_block: range.hint_events_per_block,
+                }),
+            )))
+        }
+        op => Claim::Ready(op),
+    })
+}
+
 #[async_trait]
 impl PluginServer<ModuleCall, ModuleCallback> for Module {
     #[instrument(skip_all, fields(chain_id = %self.chain_id))]
@@ -447,25 +1945,7 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
         _: &Extensions,
         msgs: Vec<Op<VoyagerMessage>>,
     ) -> RpcResult<PassResult<VoyagerMessage>> {
-        Ok(PassResult {
-            optimize_further: vec![],
-            ready: msgs
-                .into_iter()
-                .map(|op| match op {
-                    Op::Call(Call::FetchBlocks(fetch)) if fetch.chain_id == self.chain_id => {
-                        call(PluginMessage::new(
-                            self.plugin_name(),
-                            ModuleCall::from(FetchBlocks {
-                                height: fetch.start_height,
-                            }),
-                        ))
-                    }
-                    op => op,
-                })
-                .enumerate()
-                .map(|(i, op)| (vec![i], op))
-                .collect(),
-        })
+        Ok(run_pass(&self.chain_id, msgs))
     }
 
     #[instrument(skip_all, fields(chain_id = %self.chain_id))]
@@ -478,102 +1958,146 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
         match cb {}
     }
 
+    #[instrument(skip_all, fields(chain_id = %self.chain_id))]
+    async fn reload(&self, new_config: Value) -> RpcResult<ReloadReport> {
+        let new_config = serde_json::from_value::<Config>(new_config).map_err(|err| {
+            ErrorObject::owned(
+                INVALID_PARAMS_CODE,
+                format!("invalid config: {}", ErrorReporter(err)),
+                None::<()>,
+            )
+        })?;
+
+        let report = self.config.reload(new_config);
+
+        info!(?report, "reloaded config");
+
+        Ok(report)
+    }
+
+    #[instrument(skip_all, fields(chain_id = %self.chain_id))]
+    async fn debug_state(&self) -> RpcResult<Value> {
+        Ok(into_value(DebugState::new(
+            self.chain_id.clone(),
+            self.upgrades.revision(),
+            self.config.get(),
+            self.checksum_cache.to_vec(),
+            self.recent_heights.to_vec(),
+            self.packet_latency
+                .as_ref()
+                .map(|packet_latency| packet_latency.in_flight()),
+        )))
+    }
+
+    #[instrument(skip_all, fields(chain_id = %self.chain_id, %tx_hash))]
+    async fn debug_parse_tx(&self, tx_hash: H256) -> RpcResult<Value> {
+        let tx = self
+            .timeouts
+            .with_timeout(CallKind::Query, self.tm_client.tx(tx_hash, false))
+            .await
+            .map_err(VoyagerError::from)?
+            .map_err(rpc_error(
+                format_args!("error fetching transaction {tx_hash}"),
+                Some(json!({ "tx_hash": tx_hash })),
+            ))?;
+
+        Ok(into_value(debug::parse_tx(
+            tx_hash,
+            tx.height.map(|height| self.make_height(height.get())),
+            tx.tx_result.events,
+        )))
+    }
+
+    #[instrument(skip_all, fields(chain_id = %self.chain_id))]
+    async fn export_caches(&self) -> RpcResult<CacheSnapshot> {
+        Ok(CacheSnapshot::new(self.plugin_name())
+            .with_cache(CHECKSUM_CACHE, self.checksum_cache.export()))
+    }
+
+    #[instrument(skip_all, fields(chain_id = %self.chain_id))]
+    async fn import_caches(&self, snapshot: CacheSnapshot) -> RpcResult<ImportReport> {
+        snapshot.validate(&self.plugin_name())?;
+
+        let mut report = ImportReport::default();
+
+        for (cache, entries) in snapshot.caches {
+            if cache == CHECKSUM_CACHE {
+                let merge_report = self.checksum_cache.import(&cache, entries)?;
+                report.caches.insert(cache, merge_report);
+            } else {
+                report.ignored_caches.push(cache);
+            }
+        }
+
+        info!(?report, "imported cache snapshot");
+
+        Ok(report)
+    }
+
     #[instrument(skip_all, fields(chain_id = %self.chain_id))]
     async fn call(&self, e: &Extensions, msg: ModuleCall) -> RpcResult<Op<VoyagerMessage>> {
         match msg {
             ModuleCall::FetchTransactions(FetchTransactions { height, page }) => {
                 info!(%height, %page, "fetching events in block");
 
-                let response = self
-                    .tm_client
-                    .tx_search(
-                        format!("tx.height={}", height.height()),
-                        false,
-                        page,
-                        PER_PAGE_LIMIT,
-                        cometbft_rpc::rpc_types::Order::Desc,
-                    )
-                    .await
-                    .map_err(rpc_error(
-                        format_args!("error fetching transactions at height {height}"),
-                        Some(json!({ "height": height })),
-                    ))?;
+                let txs = tx_search::fetch_txs(&self.tm_client, height, page).await?;
+
+                self.tx_events(height, page, txs)
+            }
+            ModuleCall::FetchBlockRange(range) => self.fetch_block_range(range).await,
+            ModuleCall::CheckAsyncAck(check) => self.check_async_ack(e, check).await,
+            ModuleCall::FetchBlocks(FetchBlocks { height }) => {
+                self.ensure_not_stopped()?;
+
+                self.check_revision_once(e.try_get::<VoyagerClient>()?)
+                    .await;
+
+                let alerts = self.check_upgrades().await?;
+
+                if self.upgrades.stopped().is_some() {
+                    return Ok(conc(alerts));
+                }
+
+                self.recent_heights.push(height);
+
+                let mut fetch_next_blocks =
+                    self.fetch_blocks_when_finalized(height.increment()).await?;
+
+                if let Some(backpressure) = &self.backpressure {
+                    fetch_next_blocks = self
+                        .backpressure_state
+                        .apply(
+                            backpressure,
+                            e.try_get::<VoyagerClient>()?,
+                            fetch_next_blocks,
+                            now(),
+                        )
+                        .await;
+                }
 
                 Ok(conc(
-                    response
-                        .txs
-                        .into_iter()
-                        .flat_map(|txr| {
-                            txr.tx_result.events.into_iter().filter_map(move |event| {
-                                debug!(%event.ty, "observed event");
-                                IbcEvent::try_from_tendermint_event(event.clone())
-                                    .map(|event| event.map(|event| (event, txr.hash)))
-                            })
-                        })
-                        .collect::<Result<Vec<_>, _>>()
-                        .map_err(|err| {
-                            ErrorObject::owned(
-                                -1,
-                                ErrorReporter(err).to_string(),
-                                Some(json!({
-                                    "height": height,
-                                    "page": page
-                                })),
-                            )
-                        })?
-                        .into_iter()
-                        .map(|(ibc_event, tx_hash)| {
-                            debug!(event = %ibc_event.name(), "observed IBC event");
-                            call(PluginMessage::new(
-                                self.plugin_name(),
-                                ModuleCall::from(MakeChainEvent {
-                                    height,
-                                    tx_hash: tx_hash.into_encoding(),
-                                    event: ibc_event,
-                                }),
-                            ))
-                        })
-                        .chain(
-                            ((page.get() * PER_PAGE_LIMIT.get() as u32) < response.total_count)
-                                .then(|| {
-                                    call(PluginMessage::new(
-                                        self.plugin_name(),
-                                        ModuleCall::from(FetchTransactions {
-                                            height,
-                                            page: page.checked_add(1).expect("too many pages?"),
-                                        }),
-                                    ))
-                                }),
-                        ),
+                    [
+                        call(PluginMessage::new(
+                            self.plugin_name(),
+                            ModuleCall::from(FetchTransactions {
+                                height,
+                                page: const { option_unwrap!(NonZeroU32::new(1_u32)) },
+                            }),
+                        )),
+                        fetch_next_blocks,
+                    ]
+                    .into_iter()
+                    .chain(self.sequence_gap_alerts())
+                    .chain(alerts),
                 ))
             }
-            ModuleCall::FetchBlocks(FetchBlocks { height }) => Ok(conc([
-                call(PluginMessage::new(
-                    self.plugin_name(),
-                    ModuleCall::from(FetchTransactions {
-                        height,
-                        page: const { option_unwrap!(NonZeroU32::new(1_u32)) },
-                    }),
-                )),
-                seq([
-                    // TODO: Make this a config param
-                    call(WaitForHeight {
-                        chain_id: self.chain_id.clone(),
-                        height: height.increment(),
-                        finalized: true,
-                    }),
-                    call(PluginMessage::new(
-                        self.plugin_name(),
-                        ModuleCall::from(FetchBlocks {
-                            height: height.increment(),
-                        }),
-                    )),
-                ]),
-            ])),
+            ModuleCall::WaitForBlock(WaitForBlock { height }) => self.wait_for_block(height).await,
             ModuleCall::MakeChainEvent(MakeChainEvent {
                 height,
                 tx_hash,
                 event,
+                raw_events,
+                union_schema,
             }) => {
                 // events at height N are provable at height N+k where k<0
                 let provable_height = height.increment();
@@ -667,6 +2191,8 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
                                 .into(),
                                 _ => unreachable!("who needs flow typing"),
                             }),
+                            raw_events,
+                            denom_trace: None,
                         }))
                     }
 
@@ -734,6 +2260,8 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
                                 }
                                 _ => unreachable!("who needs flow typing"),
                             }),
+                            raw_events,
+                            denom_trace: None,
                         }))
                     }
 
@@ -821,6 +2349,8 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
                                 }
                                 _ => unreachable!("who needs flow typing"),
                             }),
+                            raw_events,
+                            denom_trace: None,
                         }))
                     }
                     // packet origin is this chain
@@ -834,7 +2364,6 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
                         ) = self
                             .make_packet_metadata(
                                 height,
-                                event.connection_id.to_owned(),
                                 event.packet_src_port.to_owned(),
                                 event.packet_src_channel.to_owned(),
                                 event.packet_dst_port.to_owned(),
@@ -843,6 +2372,56 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
                             )
                             .await?;
 
+                        self.observe_sequence(
+                            &event.packet_src_port,
+                            &event.packet_src_channel,
+                            event.packet_sequence.get(),
+                        );
+
+                        let send_packet = ibc_classic_spec::SendPacket {
+                            packet_data: event.packet_data_hex,
+                            packet: ibc_classic_spec::PacketMetadata {
+                                sequence: event.packet_sequence,
+                                source_channel,
+                                destination_channel,
+                                channel_ordering,
+                                timeout_height: event.packet_timeout_height,
+                                timeout_timestamp: event.packet_timeout_timestamp,
+                            },
+                        };
+
+                        if let Some(filtered) = self.filter_packet(
+                            tx_hash,
+                            IbcClassic::ID,
+                            &send_packet.packet.source_channel.version,
+                            &send_packet.packet_data,
+                            || {
+                                into_value::<ibc_classic_spec::FullEvent>(
+                                    send_packet.clone().into(),
+                                )
+                            },
+                        ) {
+                            return Ok(filtered);
+                        }
+
+                        if let Some(packet_latency) = &self.packet_latency {
+                            packet_latency.observe_send(
+                                now_millis(),
+                                &event.packet_src_port,
+                                &event.packet_src_channel,
+                                event.packet_sequence.get(),
+                                send_packet.packet.timeout_timestamp,
+                            );
+                        }
+
+                        let denom_trace = self
+                            .denom_trace(
+                                &self.chain_id,
+                                &send_packet.packet.source_channel.version,
+                                &send_packet.packet_data,
+                            )
+                            .await;
+
                         Ok(data(ChainEvent {
                             chain_id: self.chain_id.clone(),
                             client_info,
@@ -850,20 +2429,9 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
                             tx_hash,
                             provable_height,
                             ibc_spec_id: IbcClassic::ID,
-                            event: into_value::<ibc_classic_spec::FullEvent>(
-                                ibc_classic_spec::SendPacket {
-                                    packet_data: event.packet_data_hex,
-                                    packet: ibc_classic_spec::PacketMetadata {
-                                        sequence: event.packet_sequence,
-                                        source_channel,
-                                        destination_channel,
-                                        channel_ordering,
-                                        timeout_height: event.packet_timeout_height,
-                                        timeout_timestamp: event.packet_timeout_timestamp,
-                                    },
-                                }
-                                .into(),
-                            ),
+                            event: into_value::<ibc_classic_spec::FullEvent>(send_packet.into()),
+                            raw_events,
+                            denom_trace,
                         }))
                     }
                     IbcEvent::TimeoutPacket(event) => {
@@ -876,7 +2444,6 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
                         ) = self
                             .make_packet_metadata(
                                 height,
-                                event.connection_id.to_owned(),
                                 event.packet_src_port.to_owned(),
                                 event.packet_src_channel.to_owned(),
                                 event.packet_dst_port.to_owned(),
@@ -885,6 +2452,14 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
                             )
                             .await?;
 
+                        if let Some(packet_latency) = &self.packet_latency {
+                            packet_latency.observe_timeout(
+                                &event.packet_src_port,
+                                &event.packet_src_channel,
+                                event.packet_sequence.get(),
+                            );
+                        }
+
                         Ok(data(ChainEvent {
                             chain_id: self.chain_id.clone(),
                             client_info,
@@ -905,6 +2480,8 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
                                 }
                                 .into(),
                             ),
+                            raw_events,
+                            denom_trace: None,
                         }))
                     }
                     IbcEvent::AcknowledgePacket(event) => {
@@ -917,7 +2494,6 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
                         ) = self
                             .make_packet_metadata(
                                 height,
-                                event.connection_id.to_owned(),
                                 event.packet_src_port.to_owned(),
                                 event.packet_src_channel.to_owned(),
                                 event.packet_dst_port.to_owned(),
@@ -926,7 +2502,13 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
                             )
                             .await?;
 
-                        Ok(data(ChainEvent {
+                        let round_trip = self.packet_round_trip(
+                            &event.packet_src_port,
+                            &event.packet_src_channel,
+                            event.packet_sequence.get(),
+                        );
+
+                        let chain_event = data(ChainEvent {
                             chain_id: self.chain_id.clone(),
                             client_info,
                             counterparty_chain_id,
@@ -946,7 +2528,14 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
                                 }
                                 .into(),
                             ),
-                        }))
+                            raw_events,
+                            denom_trace: None,
+                        });
+
+                        Ok(match round_trip {
+                            Some(round_trip) => conc([chain_event, round_trip]),
+                            None => chain_event,
+                        })
                     }
                     // packet origin is the counterparty chain (if i put this comment above this pattern rustfmt explodes)
                     IbcEvent::WriteAcknowledgement(event) => {
@@ -959,7 +2548,6 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
                         ) = self
                             .make_packet_metadata(
                                 height,
-                                event.connection_id.to_owned(),
                                 event.packet_dst_port.to_owned(),
                                 event.packet_dst_channel.to_owned(),
                                 event.packet_src_port.to_owned(),
@@ -968,6 +2556,13 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
                             )
                             .await?;
 
+                        self.observe_recv(
+                            &counterparty_chain_id,
+                            &event.packet_src_port,
+                            &event.packet_src_channel,
+                            event.packet_sequence.get(),
+                        );
+
                         Ok(data(ChainEvent {
                             chain_id: self.chain_id.clone(),
                             client_info,
@@ -990,6 +2585,8 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
                                 }
                                 .into(),
                             ),
+                            raw_events,
+                            denom_trace: None,
                         }))
                     }
                     IbcEvent::RecvPacket(event) => {
@@ -1002,7 +2599,6 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
                         ) = self
                             .make_packet_metadata(
                                 height,
-                                event.connection_id.to_owned(),
                                 event.packet_dst_port.to_owned(),
                                 event.packet_dst_channel.to_owned(),
                                 event.packet_src_port.to_owned(),
@@ -1011,6 +2607,22 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
                             )
                             .await?;
 
+                        self.observe_recv(
+                            &counterparty_chain_id,
+                            &event.packet_src_port,
+                            &event.packet_src_channel,
+                            event.packet_sequence.get(),
+                        );
+
+                        // the denom in the packet data is the denom on the sending chain
+                        let denom_trace = self
+                            .denom_trace(
+                                &counterparty_chain_id,
+                                &destination_channel.version,
+                                &event.packet_data_hex,
+                            )
+                            .await;
+
                         Ok(data(ChainEvent {
                             chain_id: self.chain_id.clone(),
                             client_info,
@@ -1032,428 +2644,176 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
                                 }
                                 .into(),
                             ),
+                            raw_events,
+                            denom_trace,
                         }))
                     }
-                    IbcEvent::UnionCreateClient(create_client) => {
-                        dbg!(&create_client);
-
-                        let client_info = voyager_client
-                            .client_info::<IbcUnion>(self.chain_id.clone(), create_client.client_id)
-                            .await?;
-
-                        let client_meta = voyager_client
-                            .client_meta::<IbcUnion>(
-                                self.chain_id.clone(),
-                                height.into(),
-                                create_client.client_id,
-                            )
-                            .await?;
-
-                        Ok(data(ChainEvent {
-                            chain_id: self.chain_id.clone(),
-                            client_info,
-                            counterparty_chain_id: client_meta.chain_id,
+                    event @ (IbcEvent::UnionCreateClient(_)
+                    | IbcEvent::UnionUpdateClient(_)
+                    | IbcEvent::UnionConnectionOpenInit(_)
+                    | IbcEvent::UnionConnectionOpenTry(_)
+                    | IbcEvent::UnionConnectionOpenAck(_)
+                    | IbcEvent::UnionConnectionOpenConfirm(_)
+                    | IbcEvent::UnionChannelOpenTry(_)
+                    | IbcEvent::UnionChannelOpenConfirm(_)
+                    | IbcEvent::UnionSendPacket(_)
+                    | IbcEvent::UnionBatchSend(_)) => {
+                        self.make_union_chain_event(
+                            voyager_client,
+                            height,
                             tx_hash,
-                            provable_height,
-                            ibc_spec_id: IbcUnion::ID,
-                            event: into_value::<ibc_union_spec::FullEvent>(
-                                ibc_union_spec::CreateClient {
-                                    client_id: create_client.client_id,
-                                    client_type: ClientType::new(create_client.client_type),
-                                }
-                                .into(),
-                            ),
-                        }))
-                    }
-                    IbcEvent::UnionUpdateClient(update_client) => {
-                        dbg!(&update_client);
-
-                        let client_info = voyager_client
-                            .client_info::<IbcUnion>(self.chain_id.clone(), update_client.client_id)
-                            .await?;
-
-                        let client_meta = voyager_client
-                            .client_meta::<IbcUnion>(
-                                self.chain_id.clone(),
-                                height.into(),
-                                update_client.client_id,
-                            )
-                            .await?;
-
-                        Ok(data(ChainEvent {
-                            chain_id: self.chain_id.clone(),
-                            client_info: client_info.clone(),
-                            counterparty_chain_id: client_meta.chain_id,
-                            tx_hash,
-                            provable_height,
-                            ibc_spec_id: IbcUnion::ID,
-                            event: into_value::<ibc_union_spec::FullEvent>(
-                                ibc_union_spec::UpdateClient {
-                                    client_id: update_client.client_id,
-                                    client_type: client_info.client_type,
-                                    height: update_client.height,
-                                }
-                                .into(),
-                            ),
-                        }))
-                    }
-                    IbcEvent::UnionConnectionOpenInit(connection_open_init) => {
-                        dbg!(&connection_open_init);
-
-                        let client_info = voyager_client
-                            .client_info::<IbcUnion>(
-                                self.chain_id.clone(),
-                                connection_open_init.client_id,
-                            )
-                            .await?;
-
-                        let client_meta = voyager_client
-                            .client_meta::<IbcUnion>(
-                                self.chain_id.clone(),
-                                height.into(),
-                                connection_open_init.client_id,
-                            )
-                            .await?;
-
-                        Ok(data(ChainEvent {
-                            chain_id: self.chain_id.clone(),
-                            client_info,
-                            counterparty_chain_id: client_meta.chain_id,
-                            tx_hash,
-                            provable_height,
-                            ibc_spec_id: IbcUnion::ID,
-                            event: into_value::<ibc_union_spec::FullEvent>(
-                                ibc_union_spec::ConnectionOpenInit {
-                                    client_id: connection_open_init.client_id,
-                                    connection_id: connection_open_init.connection_id,
-                                    counterparty_client_id: connection_open_init
-                                        .counterparty_client_id,
-                                }
-                                .into(),
-                            ),
-                        }))
+                            event,
+                            raw_events,
+                            union_schema,
+                        )
+                        .await
                     }
-                    IbcEvent::UnionConnectionOpenTry(connection_open_try) => {
-                        dbg!(&connection_open_try);
-
-                        let client_info = voyager_client
-                            .client_info::<IbcUnion>(
-                                self.chain_id.clone(),
-                                connection_open_try.client_id,
-                            )
-                            .await?;
-
-                        let client_meta = voyager_client
-                            .client_meta::<IbcUnion>(
-                                self.chain_id.clone(),
-                                height.into(),
-                                connection_open_try.client_id,
-                            )
-                            .await?;
+                }
+            }
+        }
+    }
+}
 
-                        Ok(data(ChainEvent {
-                            chain_id: self.chain_id.clone(),
-                            client_info,
-                            counterparty_chain_id: client_meta.chain_id,
-                            tx_hash,
-                            provable_height,
-                            ibc_spec_id: IbcUnion::ID,
-                            event: into_value::<ibc_union_spec::FullEvent>(
-                                ibc_union_spec::ConnectionOpenTry {
-                                    connection_id: connection_open_try.connection_id,
-                                    counterparty_connection_id: connection_open_try
-                                        .counterparty_connection_id,
-                                    client_id: connection_open_try.client_id,
-                                    counterparty_client_id: connection_open_try
-                                        .counterparty_client_id,
-                                }
-                                .into(),
-                            ),
-                        }))
-                    }
-                    IbcEvent::UnionConnectionOpenAck(connection_open_ack) => {
-                        dbg!(&connection_open_ack);
+// NOTE: For both of the below functions, `message` as a field will override any actual message put in (i.e. `error!("foo", message = "bar")` will print as "bar", not "foo" with an extra field `message = "bar"`.
 
-                        let client_info = voyager_client
-                            .client_info::<IbcUnion>(
-                                self.chain_id.clone(),
-                                connection_open_ack.client_id,
-                            )
-                            .await?;
+/// Connect to the grpc server at `grpc_url`, within the connect timeout.
+async fn connect_grpc(
+    timeouts: &TimeoutConfig,
+    grpc_auth: &GrpcAuth,
+    grpc_url: &str,
+) -> RpcResult<InterceptedService<Channel, GrpcAuth>> {
+    timeouts
+        .with_timeout(CallKind::Connect, grpc_auth.connect(grpc_url))
+        .await
+        .map_err(VoyagerError::from)?
+        .map_err(rpc_error(
+            "error connecting to grpc server",
+            Some(json!({ "grpc_url": grpc_url })),
+        ))
+}
 
-                        let client_meta = voyager_client
-                            .client_meta::<IbcUnion>(
-                                self.chain_id.clone(),
-                                height.into(),
-                                connection_open_ack.client_id,
-                            )
-                            .await?;
+fn rpc_error<E: Error>(
+    message: impl Display,
+    data: Option<Value>,
+) -> impl FnOnce(E) -> ErrorObjectOwned {
+    move |e| {
+        let message = format!("{message}: {}", ErrorReporter(e));
+        error!(%message, data = %data.as_ref().unwrap_or(&serde_json::Value::Null));
+        ErrorObject::owned(-1, message, data)
+    }
+}
 
-                        Ok(data(ChainEvent {
-                            chain_id: self.chain_id.clone(),
-                            client_info,
-                            counterparty_chain_id: client_meta.chain_id,
-                            tx_hash,
-                            provable_height,
-                            ibc_spec_id: IbcUnion::ID,
-                            event: into_value::<ibc_union_spec::FullEvent>(
-                                ibc_union_spec::ConnectionOpenAck {
-                                    connection_id: connection_open_ack.connection_id,
-                                    counterparty_connection_id: connection_open_ack
-                                        .counterparty_connection_id,
-                                    client_id: connection_open_ack.client_id,
-                                    counterparty_client_id: connection_open_ack
-                                        .counterparty_client_id,
-                                }
-                                .into(),
-                            ),
-                        }))
-                    }
-                    IbcEvent::UnionConnectionOpenConfirm(connection_open_confirm) => {
-                        dbg!(&connection_open_confirm);
+#[cfg(test)]
+mod tests {
+    use voyager_message::{assert_pass_snapshot, module::UnexpectedChainIdError};
 
-                        let client_info = voyager_client
-                            .client_info::<IbcUnion>(
-                                self.chain_id.clone(),
-                                connection_open_confirm.client_id,
-                            )
-                            .await?;
+    use super::*;
 
-                        let client_meta = voyager_client
-                            .client_meta::<IbcUnion>(
-                                self.chain_id.clone(),
-                                height.into(),
-                                connection_open_confirm.client_id,
-                            )
-                            .await?;
+    #[test]
+    fn chain_revision_from_network() {
+        assert_eq!(
+            chain_revision(&ChainId::new("union-testnet-9"), "union-testnet-9", None).unwrap(),
+            9
+        );
 
-                        Ok(data(ChainEvent {
-                            chain_id: self.chain_id.clone(),
-                            client_info,
-                            counterparty_chain_id: client_meta.chain_id,
-                            tx_hash,
-                            provable_height,
-                            ibc_spec_id: IbcUnion::ID,
-                            event: into_value::<ibc_union_spec::FullEvent>(
-                                ibc_union_spec::ConnectionOpenConfirm {
-                                    connection_id: connection_open_confirm.connection_id,
-                                    counterparty_connection_id: connection_open_confirm
-                                        .counterparty_connection_id,
-                                    client_id: connection_open_confirm.client_id,
-                                    counterparty_client_id: connection_open_confirm
-                                        .counterparty_client_id,
-                                }
-                                .into(),
-                            ),
-                        }))
-                    }
-                    IbcEvent::UnionChannelOpenTry(channel_open_try) => {
-                        dbg!(&channel_open_try);
+        let err = chain_revision(&ChainId::new("union"), "union", None).unwrap_err();
+        assert!(err.is::<ChainIdParseError>(), "{err}");
+    }
 
-                        let connection = voyager_client
-                            .query_ibc_state(
-                                self.chain_id.clone(),
-                                QueryHeight::Specific(height),
-                                ibc_union_spec::ConnectionPath {
-                                    connection_id: channel_open_try.connection_id,
-                                },
-                            )
-                            .await?
-                            .state
-                            .unwrap();
+    #[test]
+    fn chain_revision_override() {
+        // the override takes precedence over the parsed revision
+        assert_eq!(
+            chain_revision(&ChainId::new("axelar-dojo-1"), "axelar-dojo-1", Some(0)).unwrap(),
+            0
+        );
 
-                        let client_info = voyager_client
-                            .client_info::<IbcUnion>(self.chain_id.clone(), connection.client_id)
-                            .await?;
+        // chain ids without a revision can be used with an override
+        assert_eq!(
+            chain_revision(&ChainId::new("union"), "union", Some(3)).unwrap(),
+            3
+        );
 
-                        let client_meta = voyager_client
-                            .client_meta::<IbcUnion>(
-                                self.chain_id.clone(),
-                                height.into(),
-                                connection.client_id,
-                            )
-                            .await?;
+        // the node is still checked
+        let err = chain_revision(&ChainId::new("union-1"), "union-2", Some(1)).unwrap_err();
+        assert!(err.is::<UnexpectedChainIdError>(), "{err}");
+    }
 
-                        Ok(data(ChainEvent {
-                            chain_id: self.chain_id.clone(),
-                            client_info,
-                            counterparty_chain_id: client_meta.chain_id,
-                            tx_hash,
-                            provable_height,
-                            ibc_spec_id: IbcUnion::ID,
-                            event: into_value::<ibc_union_spec::FullEvent>(
-                                ibc_union_spec::ChannelOpenTry {
-                                    port_id: channel_open_try.port_id.into_bytes().into(),
-                                    channel_id: channel_open_try.channel_id,
-                                    counterparty_port_id: channel_open_try
-                                        .counterparty_port_id
-                                        .into_encoding(),
-                                    counterparty_channel_id: channel_open_try
-                                        .counterparty_channel_id,
-                                    connection,
-                                    version: channel_open_try.counterparty_version,
-                                }
-                                .into(),
-                            ),
-                        }))
-                    }
-                    IbcEvent::UnionChannelOpenConfirm(channel_open_confirm) => {
-                        dbg!(&channel_open_confirm);
+    #[test]
+    fn chain_revision_network_mismatch() {
+        let err = chain_revision(&ChainId::new("union-1"), "union-testnet-9", None).unwrap_err();
 
-                        let channel = voyager_client
-                            .query_ibc_state(
-                                self.chain_id.clone(),
-                                QueryHeight::Specific(height),
-                                ibc_union_spec::ChannelPath {
-                                    channel_id: channel_open_confirm.channel_id,
-                                },
-                            )
-                            .await?
-                            .state
-                            .unwrap();
+        assert!(err.is::<UnexpectedChainIdError>(), "{err}");
 
-                        let connection = voyager_client
-                            .query_ibc_state(
-                                self.chain_id.clone(),
-                                QueryHeight::Specific(height),
-                                ibc_union_spec::ConnectionPath {
-                                    connection_id: channel_open_confirm.connection_id,
-                                },
-                            )
-                            .await?
-                            .state
-                            .unwrap();
+        let err = err.to_string();
+        assert!(err.contains("`union-1`"), "{err}");
+        assert!(err.contains("`union-testnet-9`"), "{err}");
+    }
 
-                        let client_info = voyager_client
-                            .client_info::<IbcUnion>(self.chain_id.clone(), connection.client_id)
-                            .await?;
+    #[test]
+    fn checksum_cache_is_bounded() {
+        let bounds = CachesConfig::default().checksum;
+        let cache = TimestampedCache::new(CHECKSUM_CACHE, &bounds);
 
-                        let client_meta = voyager_client
-                            .client_meta::<IbcUnion>(
-                                self.chain_id.clone(),
-                                height.into(),
-                                connection.client_id,
-                            )
-                            .await?;
+        for i in 0..(bounds.max_entries as u64 * 10) {
+            let mut checksum = [0; 32];
+            checksum[..8].copy_from_slice(&i.to_be_bytes());
 
-                        Ok(data(ChainEvent {
-                            chain_id: self.chain_id.clone(),
-                            client_info,
-                            counterparty_chain_id: client_meta.chain_id,
-                            tx_hash,
-                            provable_height,
-                            ibc_spec_id: IbcUnion::ID,
-                            event: into_value::<ibc_union_spec::FullEvent>(
-                                ibc_union_spec::ChannelOpenConfirm {
-                                    port_id: channel_open_confirm.port_id.into_bytes().into(),
-                                    channel_id: channel_open_confirm.channel_id,
-                                    counterparty_port_id: channel_open_confirm
-                                        .counterparty_port_id
-                                        .into_encoding(),
-                                    counterparty_channel_id: channel_open_confirm
-                                        .counterparty_channel_id,
-                                    connection,
-                                    version: channel.version,
-                                }
-                                .into(),
-                            ),
-                        }))
-                    }
-                    IbcEvent::UnionSendPacket(send_packet) => {
-                        dbg!(&send_packet);
+            cache.insert(H256::new(checksum), WasmClientType::Cometbls);
+        }
 
-                        let packet = send_packet.packet;
+        assert_eq!(cache.len(), bounds.max_entries);
+        assert_eq!(cache.export().len(), bounds.max_entries);
+    }
 
-                        let source_channel = voyager_client
-                            .query_ibc_state(
-                                self.chain_id.clone(),
-                                QueryHeight::Specific(height),
-                                ibc_union_spec::ChannelPath {
-                                    channel_id: packet.source_channel,
-                                },
-                            )
-                            .await?
-                            .state
-                            .unwrap();
+    #[tokio::test]
+    async fn run_pass_fetch_blocks() {
+        let chain_id = ChainId::new("union-devnet-1");
 
-                        let source_connection = voyager_client
-                            .query_ibc_state(
-                                self.chain_id.clone(),
-                                QueryHeight::Specific(height),
-                                ibc_union_spec::ConnectionPath {
-                                    connection_id: source_channel.connection_id,
-                                },
-                            )
-                            .await?
-                            .state
-                            .unwrap();
+        assert_pass_snapshot!(
+            |ops| run_pass(&chain_id, ops),
+            "testdata/pass/fetch_blocks.json"
+        );
+    }
 
-                        let client_info = voyager_client
-                            .client_info::<IbcUnion>(
-                                self.chain_id.clone(),
-                                source_connection.client_id,
-                            )
-                            .await?;
+    #[tokio::test]
+    async fn run_pass_fetch_block_range() {
+        let chain_id = ChainId::new("union-devnet-1");
 
-                        let client_meta = voyager_client
-                            .client_meta::<IbcUnion>(
-                                self.chain_id.clone(),
-                                height.into(),
-                                source_connection.client_id,
-                            )
-                            .await?;
+        assert_pass_snapshot!(
+            |ops| run_pass(&chain_id, ops),
+            "testdata/pass/fetch_block_range.json"
+        );
+    }
 
-                        Ok(data(ChainEvent {
-                            chain_id: self.chain_id.clone(),
-                            client_info,
-                            counterparty_chain_id: client_meta.chain_id,
-                            tx_hash,
-                            provable_height,
-                            ibc_spec_id: IbcUnion::ID,
-                            event: into_value::<ibc_union_spec::FullEvent>(
-                                ibc_union_spec::SendPacket {
-                                    packet_data: packet.data.into(),
-                                    packet: ibc_union_spec::PacketMetadata {
-                                        source_channel: ibc_union_spec::ChannelMetadata {
-                                            channel_id: packet.source_channel,
-                                            version: source_channel.version.clone(),
-                                            connection: ibc_union_spec::ConnectionMetadata {
-                                                client_id: source_connection.client_id,
-                                                connection_id: source_channel.connection_id,
-                                            },
-                                        },
-                                        destination_channel: ibc_union_spec::ChannelMetadata {
-                                            channel_id: packet.destination_channel,
-                                            version: source_channel.version,
-                                            connection: ibc_union_spec::ConnectionMetadata {
-                                                client_id: source_connection.counterparty_client_id,
-                                                connection_id: source_connection
-                                                    .counterparty_connection_id,
-                                            },
-                                        },
-                                        timeout_height: packet.timeout_height,
-                                        timeout_timestamp: packet.timeout_timestamp,
-                                    },
-                                }
-                                .into(),
-                            ),
-                        }))
-                    }
-                }
+    #[tokio::test(start_paused = true)]
+    async fn grpc_connect_times_out() {
+        // a proxy that accepts connections, but never responds to the `CONNECT` request
+        let proxy = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let proxy_addr = proxy.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut connections = vec![];
+            loop {
+                connections.push(proxy.accept().await.unwrap());
             }
-        }
-    }
-}
+        });
 
-// NOTE: For both of the below functions, `message` as a field will override any actual message put in (i.e. `error!("foo", message = "bar")` will print as "bar", not "foo" with an extra field `message = "bar"`.
+        let grpc_auth = GrpcAuth::new(None).with_network(NetworkConfig {
+            proxy: Some(format!("http://{proxy_addr}").parse().unwrap()),
+            prefer_ipv6: false,
+        });
 
-fn rpc_error<E: Error>(
-    message: impl Display,
-    data: Option<Value>,
-) -> impl FnOnce(E) -> ErrorObjectOwned {
-    move |e| {
-        let message = format!("{message}: {}", ErrorReporter(e));
-        error!(%message, data = %data.as_ref().unwrap_or(&serde_json::Value::Null));
-        ErrorObject::owned(-1, message, data)
+        let err = connect_grpc(
+            &TimeoutConfig::default(),
+            &grpc_auth,
+            "http://grpc.union.build:9090",
+        )
+        .await
+        .unwrap_err();
+
+        assert_eq!(
+            VoyagerError::from_error_object(&err),
+            VoyagerError::retryable("connect call timed out after 5s")
+        );
     }
 }
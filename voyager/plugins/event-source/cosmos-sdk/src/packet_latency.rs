@@ -0,0 +1,508 @@
+//! Measurement of the end-to-end latency of packets sent from this chain.
+//!
+//! The wall-clock time at which each `send_packet` event is observed is
+//! recorded per `(port_id, channel_id, sequence)`. Once the corresponding
+//! `acknowledge_packet` event is observed, the round trip is reported as
+//! [`PacketRoundTripCompleted`] data, and the entry is removed.
+//!
+//! The receipt of the packet happens on the counterparty chain, and as such is
+//! only observed by this event source if both ends of the channel are on this
+//! chain. In all other cases, the round trip is reported without a receive
+//! time.
+//!
+//! Packets that are never acknowledged (i.e. timed out packets whose timeout
+//! was relayed by another relayer) would otherwise be tracked forever. Entries
+//! expire once the timeout timestamp of the packet has passed by more than
+//! [`PacketLatencyConfig::timeout_margin_seconds`], and the total amount of
+//! tracked packets is capped by [`PacketLatencyConfig::max_entries`], evicting
+//! the packets that expire first.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::{LazyLock, Mutex},
+};
+
+use prometheus::{register_histogram_vec, HistogramVec};
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+use unionlabs::id::{ChannelId, PortId};
+use voyager_message::core::ChainId;
+
+use crate::data::PacketRoundTripCompleted;
+
+pub static PACKET_ROUND_TRIP_DURATION: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register_histogram_vec!(
+        "voyager_packet_round_trip_duration_seconds",
+        "The time between observing a packet being sent and its acknowledgement.",
+        &["chain_id"],
+        vec![5.0, 15.0, 30.0, 60.0, 120.0, 300.0, 600.0, 1800.0, 3600.0, 21600.0],
+    )
+    .unwrap()
+});
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PacketLatencyConfig {
+    /// How long (in seconds) after the timeout timestamp of a packet it is
+    /// still tracked.
+    #[serde(default = "PacketLatencyConfig::default_timeout_margin_seconds")]
+    pub timeout_margin_seconds: u64,
+    /// How long (in seconds) packets without a timeout timestamp (i.e. only
+    /// a timeout height) are tracked.
+    #[serde(default = "PacketLatencyConfig::default_ttl_seconds")]
+    pub default_ttl_seconds: u64,
+    /// The maximum amount of in-flight packets that are tracked at once.
+    #[serde(default = "PacketLatencyConfig::default_max_entries")]
+    pub max_entries: usize,
+    /// Record the round trip durations in the
+    /// [`voyager_packet_round_trip_duration_seconds`](PACKET_ROUND_TRIP_DURATION)
+    /// histogram.
+    #[serde(default)]
+    pub metrics: bool,
+}
+
+impl PacketLatencyConfig {
+    const fn default_timeout_margin_seconds() -> u64 {
+        10 * 60
+    }
+
+    const fn default_ttl_seconds() -> u64 {
+        24 * 60 * 60
+    }
+
+    const fn default_max_entries() -> usize {
+        100_000
+    }
+}
+
+impl Default for PacketLatencyConfig {
+    fn default() -> Self {
+        Self {
+            timeout_margin_seconds: Self::default_timeout_margin_seconds(),
+            default_ttl_seconds: Self::default_ttl_seconds(),
+            max_entries: Self::default_max_entries(),
+            metrics: false,
+        }
+    }
+}
+
+type PacketKey = (PortId, ChannelId, u64);
+
+/// A packet that has been sent, but not yet acknowledged.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InFlightPacket {
+    /// The unix timestamp (in milliseconds) at which the packet was sent.
+    pub send_observed_at: u64,
+    /// The unix timestamp (in milliseconds) at which the packet was received,
+    /// if observed.
+    pub recv_observed_at: Option<u64>,
+    /// The unix timestamp (in milliseconds) after which the packet is no
+    /// longer tracked.
+    pub expires_at: u64,
+}
+
+/// The in-flight packets, indexed by the time they expire at.
+#[derive(Debug, Default)]
+struct InFlightPackets {
+    packets: BTreeMap<PacketKey, InFlightPacket>,
+    expiry: BTreeSet<(u64, PacketKey)>,
+}
+
+impl InFlightPackets {
+    fn insert(&mut self, key: PacketKey, packet: InFlightPacket) {
+        self.expiry.insert((packet.expires_at, key.clone()));
+        self.packets.insert(key, packet);
+    }
+
+    fn remove(&mut self, key: &PacketKey) -> Option<InFlightPacket> {
+        let packet = self.packets.remove(key)?;
+        self.expiry.remove(&(packet.expires_at, key.clone()));
+        Some(packet)
+    }
+
+    /// Remove all packets that expired before `now`, and then the packets that expire first until
+    /// at most `max_entries` remain.
+    fn prune(&mut self, now: u64, max_entries: usize) {
+        while let Some((expires_at, key)) = self.expiry.first().cloned() {
+            if expires_at >= now && self.packets.len() <= max_entries {
+                break;
+            }
+
+            debug!(
+                port_id = %key.0,
+                channel_id = %key.1,
+                sequence = key.2,
+                expires_at,
+                "no longer tracking the latency of packet"
+            );
+
+            self.remove(&key);
+        }
+    }
+}
+
+/// Tracks the in-flight packets sent from a single chain.
+#[derive(Debug)]
+pub struct PacketLatencyTracker {
+    chain_id: ChainId,
+    config: PacketLatencyConfig,
+    in_flight: Mutex<InFlightPackets>,
+}
+
+impl PacketLatencyTracker {
+    #[must_use]
+    pub fn new(chain_id: ChainId, config: PacketLatencyConfig) -> Self {
+        Self {
+            chain_id,
+            config,
+            in_flight: Mutex::default(),
+        }
+    }
+
+    /// Record a `send_packet` event. `timeout_timestamp` is the timeout of the packet in
+    /// nanoseconds, or 0 if it only has a timeout height. If the packet is already being tracked
+    /// (i.e. the block was fetched again), the first observation is kept.
+    pub fn observe_send(
+        &self,
+        now: u64,
+        port_id: &PortId,
+        channel_id: &ChannelId,
+        sequence: u64,
+        timeout_timestamp: u64,
+    ) {
+        let expires_at = if timeout_timestamp == 0 {
+            now.saturating_add(self.config.default_ttl_seconds.saturating_mul(1000))
+        } else {
+            (timeout_timestamp / 1_000_000)
+                .saturating_add(self.config.timeout_margin_seconds.saturating_mul(1000))
+        };
+
+        let key = (port_id.clone(), channel_id.clone(), sequence);
+
+        let mut in_flight = self.in_flight.lock().expect("lock is not poisoned");
+
+        if in_flight.packets.contains_key(&key) {
+            return;
+        }
+
+        in_flight.insert(
+            key,
+            InFlightPacket {
+                send_observed_at: now,
+                recv_observed_at: None,
+                expires_at,
+            },
+        );
+
+        in_flight.prune(now, self.config.max_entries);
+    }
+
+    /// Record a `recv_packet` or `write_acknowledgement` event. `source_chain_id` is the chain
+    /// that the packet was sent from; packets not sent from this chain are ignored. Only the first
+    /// observation is kept.
+    pub fn observe_recv(
+        &self,
+        now: u64,
+        source_chain_id: &ChainId,
+        port_id: &PortId,
+        channel_id: &ChannelId,
+        sequence: u64,
+    ) {
+        if *source_chain_id != self.chain_id {
+            return;
+        }
+
+        let mut in_flight = self.in_flight.lock().expect("lock is not poisoned");
+
+        in_flight.prune(now, self.config.max_entries);
+
+        if let Some(packet) =
+            in_flight
+                .packets
+                .get_mut(&(port_id.clone(), channel_id.clone(), sequence))
+        {
+            packet.recv_observed_at.get_or_insert(now);
+        }
+    }
+
+    /// Record an `acknowledge_packet` event, returning the completed round trip if the packet was
+    /// being tracked.
+    pub fn observe_ack(
+        &self,
+        now: u64,
+        port_id: &PortId,
+        channel_id: &ChannelId,
+        sequence: u64,
+    ) -> Option<PacketRoundTripCompleted> {
+        let mut in_flight = self.in_flight.lock().expect("lock is not poisoned");
+
+        in_flight.prune(now, self.config.max_entries);
+
+        let packet = in_flight.remove(&(port_id.clone(), channel_id.clone(), sequence))?;
+
+        let completed = PacketRoundTripCompleted {
+            chain_id: self.chain_id.clone(),
+            port_id: port_id.clone(),
+            channel: channel_id.clone(),
+            sequence,
+            send_observed_at: packet.send_observed_at,
+            recv_observed_at: packet.recv_observed_at,
+            ack_observed_at: now,
+            total_ms: now.saturating_sub(packet.send_observed_at),
+        };
+
+        if self.config.metrics {
+            PACKET_ROUND_TRIP_DURATION
+                .with_label_values(&[self.chain_id.as_str()])
+                .observe(completed.total_ms as f64 / 1000.0);
+        }
+
+        Some(completed)
+    }
+
+    /// Record a `timeout_packet` event. The packet will never be acknowledged, so it is no longer
+    /// tracked.
+    pub fn observe_timeout(&self, port_id: &PortId, channel_id: &ChannelId, sequence: u64) {
+        self.in_flight
+            .lock()
+            .expect("lock is not poisoned")
+            .remove(&(port_id.clone(), channel_id.clone(), sequence));
+    }
+
+    /// The amount of packets currently being tracked.
+    pub fn in_flight(&self) -> usize {
+        self.in_flight
+            .lock()
+            .expect("lock is not poisoned")
+            .packets
+            .len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The current time, in milliseconds.
+    const NOW: u64 = 1_700_000_000_000;
+
+    fn tracker(config: PacketLatencyConfig) -> PacketLatencyTracker {
+        PacketLatencyTracker::new(ChainId::new("union-1"), config)
+    }
+
+    fn port() -> PortId {
+        PortId::new("transfer".to_owned()).unwrap()
+    }
+
+    fn channel() -> ChannelId {
+        ChannelId::new(0)
+    }
+
+    /// A timeout timestamp (in nanoseconds) `seconds` after [`NOW`].
+    fn timeout_in(seconds: u64) -> u64 {
+        (NOW + seconds * 1000) * 1_000_000
+    }
+
+    #[test]
+    fn round_trip() {
+        let tracker = tracker(PacketLatencyConfig::default());
+
+        tracker.observe_send(NOW, &port(), &channel(), 1, timeout_in(3600));
+        tracker.observe_recv(
+            NOW + 4_000,
+            &ChainId::new("union-1"),
+            &port(),
+            &channel(),
+            1,
+        );
+        // the write_acknowledgement of the same packet does not overwrite the receive time
+        tracker.observe_recv(
+            NOW + 5_000,
+            &ChainId::new("union-1"),
+            &port(),
+            &channel(),
+            1,
+        );
+
+        assert_eq!(
+            tracker.observe_ack(NOW + 9_500, &port(), &channel(), 1),
+            Some(PacketRoundTripCompleted {
+                chain_id: ChainId::new("union-1"),
+                port_id: port(),
+                channel: channel(),
+                sequence: 1,
+                send_observed_at: NOW,
+                recv_observed_at: Some(NOW + 4_000),
+                ack_observed_at: NOW + 9_500,
+                total_ms: 9_500,
+            })
+        );
+
+        // only reported once
+        assert_eq!(
+            tracker.observe_ack(NOW + 10_000, &port(), &channel(), 1),
+            None
+        );
+        assert_eq!(tracker.in_flight(), 0);
+    }
+
+    #[test]
+    fn recv_not_observed() {
+        let tracker = tracker(PacketLatencyConfig::default());
+
+        tracker.observe_send(NOW, &port(), &channel(), 1, timeout_in(3600));
+        tracker.observe_send(NOW, &port(), &channel(), 2, timeout_in(3600));
+
+        // received on another chain, with the same channel and sequence
+        tracker.observe_recv(
+            NOW + 4_000,
+            &ChainId::new("osmosis-1"),
+            &port(),
+            &channel(),
+            1,
+        );
+        // a different sequence
+        tracker.observe_recv(
+            NOW + 4_000,
+            &ChainId::new("union-1"),
+            &port(),
+            &channel(),
+            2,
+        );
+
+        let completed = tracker
+            .observe_ack(NOW + 9_000, &port(), &channel(), 1)
+            .unwrap();
+
+        assert_eq!(completed.recv_observed_at, None);
+        assert_eq!(completed.total_ms, 9_000);
+    }
+
+    #[test]
+    fn ack_without_send() {
+        let tracker = tracker(PacketLatencyConfig::default());
+
+        assert_eq!(tracker.observe_ack(NOW, &port(), &channel(), 1), None);
+    }
+
+    #[test]
+    fn timeout() {
+        let tracker = tracker(PacketLatencyConfig::default());
+
+        tracker.observe_send(NOW, &port(), &channel(), 1, timeout_in(60));
+        tracker.observe_timeout(&port(), &channel(), 1);
+
+        assert_eq!(tracker.in_flight(), 0);
+        assert_eq!(
+            tracker.observe_ack(NOW + 1_000, &port(), &channel(), 1),
+            None
+        );
+    }
+
+    #[test]
+    fn expires_after_timeout_and_margin() {
+        let tracker = tracker(PacketLatencyConfig {
+            timeout_margin_seconds: 30,
+            ..Default::default()
+        });
+
+        tracker.observe_send(NOW, &port(), &channel(), 1, timeout_in(60));
+        tracker.observe_send(NOW, &port(), &channel(), 2, timeout_in(60));
+
+        // still tracked within the margin
+        assert!(tracker
+            .observe_ack(NOW + 90_000, &port(), &channel(), 1)
+            .is_some());
+
+        assert!(tracker
+            .observe_ack(NOW + 90_001, &port(), &channel(), 2)
+            .is_none());
+        assert_eq!(tracker.in_flight(), 0);
+    }
+
+    #[test]
+    fn expires_after_default_ttl_without_timeout_timestamp() {
+        let tracker = tracker(PacketLatencyConfig {
+            default_ttl_seconds: 60,
+            ..Default::default()
+        });
+
+        tracker.observe_send(NOW, &port(), &channel(), 1, 0);
+        tracker.observe_send(NOW + 1, &port(), &channel(), 2, timeout_in(3600));
+
+        tracker.observe_recv(
+            NOW + 60_001,
+            &ChainId::new("union-1"),
+            &port(),
+            &channel(),
+            3,
+        );
+
+        assert_eq!(tracker.in_flight(), 1);
+        assert!(tracker
+            .observe_ack(NOW + 60_001, &port(), &channel(), 2)
+            .is_some());
+    }
+
+    #[test]
+    fn max_entries_evicts_earliest_expiry() {
+        let tracker = tracker(PacketLatencyConfig {
+            max_entries: 2,
+            ..Default::default()
+        });
+
+        tracker.observe_send(NOW, &port(), &channel(), 1, timeout_in(300));
+        tracker.observe_send(NOW, &port(), &channel(), 2, timeout_in(100));
+        tracker.observe_send(NOW, &port(), &channel(), 3, timeout_in(200));
+
+        assert_eq!(tracker.in_flight(), 2);
+        assert!(tracker.observe_ack(NOW, &port(), &channel(), 2).is_none());
+        assert!(tracker.observe_ack(NOW, &port(), &channel(), 1).is_some());
+        assert!(tracker.observe_ack(NOW, &port(), &channel(), 3).is_some());
+    }
+
+    #[test]
+    fn duplicate_send_keeps_first_observation() {
+        let tracker = tracker(PacketLatencyConfig {
+            max_entries: 1,
+            ..Default::default()
+        });
+
+        // i.e. the same block is processed twice
+        tracker.observe_send(NOW, &port(), &channel(), 1, timeout_in(60));
+        tracker.observe_send(NOW + 1_000, &port(), &channel(), 1, timeout_in(60));
+
+        assert_eq!(tracker.in_flight(), 1);
+        assert_eq!(
+            tracker
+                .observe_ack(NOW + 2_000, &port(), &channel(), 1)
+                .unwrap()
+                .total_ms,
+            2_000
+        );
+    }
+
+    #[test]
+    fn metrics() {
+        let tracker = tracker(PacketLatencyConfig {
+            metrics: true,
+            ..Default::default()
+        });
+
+        tracker.observe_send(NOW, &port(), &channel(), 1, timeout_in(60));
+        tracker.observe_ack(NOW + 2_000, &port(), &channel(), 1);
+
+        let histogram = PACKET_ROUND_TRIP_DURATION.with_label_values(&["union-1"]);
+
+        assert_eq!(histogram.get_sample_count(), 1);
+        assert_eq!(histogram.get_sample_sum(), 2.0);
+    }
+
+    #[test]
+    fn config_defaults() {
+        assert_eq!(
+            serde_json::from_str::<PacketLatencyConfig>("{}").unwrap(),
+            PacketLatencyConfig::default()
+        );
+    }
+}
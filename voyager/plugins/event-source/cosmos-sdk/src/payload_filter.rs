@@ -0,0 +1,308 @@
+//! Filtering of packets by their payload, to avoid relaying spam.
+//!
+//! Packets with a large payload (commonly a large memo in an ics20 transfer)
+//! cost a disproportionate amount of gas to relay. Packets that exceed any of
+//! the configured limits are not emitted as chain events, and as such are never
+//! relayed. A [`PacketFiltered`](crate::data::PacketFiltered) is emitted in
+//! their place instead.
+//!
+//! The transfer specific limits only apply to packets on ics20 channels whose
+//! data can be parsed as ics20 packet data; all other packets are only subject
+//! to [`PayloadFilterConfig::max_packet_data_bytes`].
+
+use macros::model;
+use serde::{Deserialize, Serialize};
+
+/// The channel versions whose packets are parsed as ics20 packet data.
+pub const ICS20_VERSIONS: &[&str] = &["ics20-1", "ics20-2"];
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PayloadFilterConfig {
+    /// The maximum size (in bytes) of the data of a packet.
+    #[serde(default)]
+    pub max_packet_data_bytes: Option<usize>,
+    /// The maximum size (in bytes) of the memo of an ics20 transfer.
+    #[serde(default)]
+    pub max_memo_bytes: Option<usize>,
+    /// The minimum amount of an ics20 transfer, per denom. Transfers of denoms
+    /// that are not listed here are not filtered by amount.
+    #[serde(default)]
+    pub min_transfer_amount: Vec<MinTransferAmount>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MinTransferAmount {
+    pub denom: String,
+    #[serde(with = "::serde_utils::string")]
+    pub amount: u128,
+}
+
+/// Why a packet was not relayed.
+#[model]
+pub enum FilterReason {
+    PacketDataTooLarge {
+        size: usize,
+        max: usize,
+    },
+    MemoTooLarge {
+        size: usize,
+        max: usize,
+    },
+    TransferAmountTooSmall {
+        denom: String,
+        #[serde(with = "::serde_utils::string")]
+        amount: u128,
+        #[serde(with = "::serde_utils::string")]
+        min: u128,
+    },
+}
+
+/// The fields of ics20 `FungibleTokenPacketData` that are relevant for
+/// filtering.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ics20PacketData {
+    pub denom: String,
+    pub amount: u128,
+    pub memo: String,
+}
+
+impl Ics20PacketData {
+    /// Parse `data` as ics20 packet data, if the channel is an ics20 channel.
+    ///
+    /// Returns `None` for any data that is not valid ics20 packet data, such
+    /// that it is treated as an opaque payload.
+    #[must_use]
+    pub fn parse(channel_version: &str, data: &[u8]) -> Option<Self> {
+        #[derive(Deserialize)]
+        struct Raw {
+            denom: String,
+            amount: String,
+            #[serde(default)]
+            memo: String,
+        }
+
+        if !ICS20_VERSIONS.contains(&channel_version) {
+            return None;
+        }
+
+        let raw = serde_json::from_slice::<Raw>(data).ok()?;
+
+        Some(Self {
+            denom: raw.denom,
+            amount: raw.amount.parse().ok()?,
+            memo: raw.memo,
+        })
+    }
+}
+
+impl PayloadFilterConfig {
+    /// Check the data of a packet sent on a channel with `channel_version`
+    /// against the configured limits.
+    pub fn check(&self, channel_version: &str, packet_data: &[u8]) -> Result<(), FilterReason> {
+        if let Some(max) = self.max_packet_data_bytes {
+            if packet_data.len() > max {
+                return Err(FilterReason::PacketDataTooLarge {
+                    size: packet_data.len(),
+                    max,
+                });
+            }
+        }
+
+        let Some(transfer) = Ics20PacketData::parse(channel_version, packet_data) else {
+            return Ok(());
+        };
+
+        if let Some(max) = self.max_memo_bytes {
+            if transfer.memo.len() > max {
+                return Err(FilterReason::MemoTooLarge {
+                    size: transfer.memo.len(),
+                    max,
+                });
+            }
+        }
+
+        if let Some(min) = self
+            .min_transfer_amount
+            .iter()
+            .find(|min| min.denom == transfer.denom)
+        {
+            if transfer.amount < min.amount {
+                return Err(FilterReason::TransferAmountTooSmall {
+                    denom: transfer.denom,
+                    amount: transfer.amount,
+                    min: min.amount,
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use unionlabs::hash::H256;
+    use voyager_message::{
+        core::{ChainId, IbcSpecId},
+        PluginMessage,
+    };
+
+    use super::*;
+    use crate::data::{ModuleData, PacketFiltered};
+
+    fn transfer(denom: &str, amount: &str, memo: &str) -> Vec<u8> {
+        serde_json::to_vec(&json!({
+            "denom": denom,
+            "amount": amount,
+            "sender": "union1sender",
+            "receiver": "osmo1receiver",
+            "memo": memo,
+        }))
+        .unwrap()
+    }
+
+    fn config() -> PayloadFilterConfig {
+        PayloadFilterConfig {
+            max_packet_data_bytes: Some(512),
+            max_memo_bytes: Some(32),
+            min_transfer_amount: vec![MinTransferAmount {
+                denom: "muno".to_owned(),
+                amount: 1000,
+            }],
+        }
+    }
+
+    #[test]
+    fn unlimited_by_default() {
+        let data = transfer("muno", "1", &"a".repeat(10_000));
+
+        assert_eq!(
+            PayloadFilterConfig::default().check("ics20-1", &data),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn packet_data_too_large() {
+        let data = transfer("muno", "1000", &"a".repeat(1024));
+
+        assert_eq!(
+            config().check("ics20-1", &data),
+            Err(FilterReason::PacketDataTooLarge {
+                size: data.len(),
+                max: 512,
+            })
+        );
+    }
+
+    #[test]
+    fn memo_too_large() {
+        assert_eq!(
+            config().check("ics20-1", &transfer("muno", "1000", &"a".repeat(33))),
+            Err(FilterReason::MemoTooLarge { size: 33, max: 32 })
+        );
+
+        assert_eq!(
+            config().check("ics20-2", &transfer("muno", "1000", &"a".repeat(32))),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn transfer_amount_too_small() {
+        assert_eq!(
+            config().check("ics20-1", &transfer("muno", "999", "")),
+            Err(FilterReason::TransferAmountTooSmall {
+                denom: "muno".to_owned(),
+                amount: 999,
+                min: 1000,
+            })
+        );
+
+        assert_eq!(
+            config().check("ics20-1", &transfer("muno", "1000", "")),
+            Ok(())
+        );
+
+        // denoms without a minimum are not filtered
+        assert_eq!(
+            config().check("ics20-1", &transfer("uosmo", "1", "")),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn non_ics20_fallback() {
+        let large_memo = transfer("muno", "1", &"a".repeat(64));
+
+        // not an ics20 channel
+        assert_eq!(Ics20PacketData::parse("ucs01-relay-1", &large_memo), None);
+        assert_eq!(config().check("ucs01-relay-1", &large_memo), Ok(()));
+
+        // not ics20 packet data
+        for data in [
+            &b"\xde\xad\xbe\xef"[..],
+            &br#"{"denom":"muno"}"#[..],
+            &br#"{"denom":"muno","amount":"-1","memo":""}"#[..],
+            &br#"{"denom":"muno","amount":1,"memo":""}"#[..],
+        ] {
+            assert_eq!(Ics20PacketData::parse("ics20-1", data), None);
+            assert_eq!(config().check("ics20-1", data), Ok(()));
+        }
+
+        // the raw size limit still applies
+        assert!(matches!(
+            config().check("ucs01-relay-1", &[0; 513]),
+            Err(FilterReason::PacketDataTooLarge { size: 513, .. })
+        ));
+    }
+
+    #[test]
+    fn memo_is_optional() {
+        assert_eq!(
+            Ics20PacketData::parse("ics20-1", br#"{"denom":"muno","amount":"5"}"#),
+            Some(Ics20PacketData {
+                denom: "muno".to_owned(),
+                amount: 5,
+                memo: String::new(),
+            })
+        );
+    }
+
+    #[test]
+    fn filtered_event_shape() {
+        let filtered = PluginMessage::new(
+            "voyager-event-source-plugin-cosmos-sdk/union-1",
+            ModuleData::from(PacketFiltered {
+                chain_id: ChainId::new("union-1"),
+                tx_hash: H256::default(),
+                reason: FilterReason::MemoTooLarge { size: 33, max: 32 },
+                ibc_spec_id: IbcSpecId::new_static(IbcSpecId::CLASSIC),
+                packet: json!({ "packet_data": "0x" }),
+            }),
+        );
+
+        assert_eq!(
+            serde_json::to_value(&filtered).unwrap(),
+            json!({
+                "plugin": "voyager-event-source-plugin-cosmos-sdk/union-1",
+                "message": {
+                    "@type": "packet_filtered",
+                    "@value": {
+                        "chain_id": "union-1",
+                        "tx_hash": H256::default(),
+                        "reason": {
+                            "@type": "memo_too_large",
+                            "@value": { "size": 33, "max": 32 }
+                        },
+                        "ibc_spec_id": "ibc-classic",
+                        "packet": { "packet_data": "0x" }
+                    }
+                }
+            })
+        );
+    }
+}
@@ -0,0 +1,277 @@
+//! Correlating the raw events of a transaction with the IBC events emitted in
+//! it.
+//!
+//! Middleware (such as ics29 fee middleware) emits additional events alongside
+//! the core IBC packet events, which are not part of the typed events. Instead
+//! of attaching the entire transaction, only the events that refer to the same
+//! packet are attached to the [`ChainEvent`](voyager_message::data::ChainEvent).
+
+use cometbft_rpc::types::abci::event::Event;
+use voyager_message::data::RawTmEvent;
+
+use crate::ibc_events::IbcEvent;
+
+/// The attribute keys that contain a packet sequence.
+const SEQUENCE_KEYS: &[&str] = &["packet_sequence", "sequence"];
+
+/// The attribute keys that contain a channel id.
+const CHANNEL_KEYS: &[&str] = &[
+    "packet_src_channel",
+    "packet_dst_channel",
+    "channel_id",
+    "src_channel",
+    "dst_channel",
+];
+
+/// Identifies a packet within a transaction by its sequence and the channels on
+/// either end.
+struct PacketKey {
+    sequence: String,
+    channels: [String; 2],
+}
+
+impl PacketKey {
+    fn of(event: &IbcEvent) -> Option<Self> {
+        let (sequence, src_channel, dst_channel) = match event {
+            IbcEvent::SendPacket(event) => (
+                event.packet_sequence,
+                &event.packet_src_channel,
+                &event.packet_dst_channel,
+            ),
+            IbcEvent::RecvPacket(event) => (
+                event.packet_sequence,
+                &event.packet_src_channel,
+                &event.packet_dst_channel,
+            ),
+            IbcEvent::WriteAcknowledgement(event) => (
+                event.packet_sequence,
+                &event.packet_src_channel,
+                &event.packet_dst_channel,
+            ),
+            IbcEvent::AcknowledgePacket(event) => (
+                event.packet_sequence,
+                &event.packet_src_channel,
+                &event.packet_dst_channel,
+            ),
+            IbcEvent::TimeoutPacket(event) => (
+                event.packet_sequence,
+                &event.packet_src_channel,
+                &event.packet_dst_channel,
+            ),
+            _ => return None,
+        };
+
+        Some(Self {
+            sequence: sequence.to_string(),
+            channels: [
+                src_channel.to_string_prefixed(),
+                dst_channel.to_string_prefixed(),
+            ],
+        })
+    }
+
+    /// An event refers to this packet if it contains both the sequence and one
+    /// of the channels of the packet.
+    fn matches(&self, event: &Event) -> bool {
+        let has_attribute = |keys: &[&str], matches: &dyn Fn(&str) -> bool| {
+            event
+                .attributes
+                .iter()
+                .any(|attr| keys.contains(&attr.key.as_str()) && matches(&attr.value))
+        };
+
+        has_attribute(SEQUENCE_KEYS, &|value| value == self.sequence)
+            && has_attribute(CHANNEL_KEYS, &|value| {
+                self.channels.iter().any(|channel| channel == value)
+            })
+    }
+}
+
+/// Collect the events out of `tx_events` that are correlated with `event`,
+/// including the raw form of `event` itself.
+///
+/// Only packet events are correlated, for all other events `None` is returned.
+/// Union packet events are not correlated either, since they have no sequence.
+#[must_use]
+pub fn correlated_events(event: &IbcEvent, tx_events: &[Event]) -> Option<Vec<RawTmEvent>> {
+    let key = PacketKey::of(event)?;
+
+    Some(
+        tx_events
+            .iter()
+            .filter(|tx_event| key.matches(tx_event))
+            .map(|tx_event| RawTmEvent {
+                ty: tx_event.ty.clone(),
+                attributes: tx_event
+                    .attributes
+                    .iter()
+                    .map(|attr| (attr.key.clone(), attr.value.clone()))
+                    .collect(),
+            })
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU64;
+
+    use cometbft_rpc::types::abci::event_attribute::EventAttribute;
+    use unionlabs::{
+        ibc::core::{channel::order::Order, client::height::Height},
+        id::{ChannelId, ClientId, ConnectionId, PortId},
+    };
+
+    use super::*;
+    use crate::ibc_events::{SendPacket, UpdateClient};
+
+    fn event(ty: &str, attributes: &[(&str, &str)]) -> Event {
+        Event {
+            ty: ty.to_owned(),
+            attributes: attributes
+                .iter()
+                .map(|(key, value)| EventAttribute {
+                    key: (*key).to_owned(),
+                    value: (*value).to_owned(),
+                    index: true,
+                })
+                .collect(),
+        }
+    }
+
+    fn send_packet_event(sequence: &str, src_channel: &str, dst_channel: &str) -> Event {
+        event(
+            "send_packet",
+            &[
+                ("packet_data_hex", "7b7d"),
+                ("packet_timeout_height", "0-100"),
+                ("packet_timeout_timestamp", "0"),
+                ("packet_sequence", sequence),
+                ("packet_src_port", "transfer"),
+                ("packet_src_channel", src_channel),
+                ("packet_dst_port", "transfer"),
+                ("packet_dst_channel", dst_channel),
+                ("packet_channel_ordering", "ORDER_UNORDERED"),
+                ("connection_id", "connection-0"),
+                ("msg_index", "0"),
+            ],
+        )
+    }
+
+    fn incentivized_packet_event(sequence: &str, channel: &str) -> Event {
+        event(
+            "incentivized_ibc_packet",
+            &[
+                ("port_id", "transfer"),
+                ("channel_id", channel),
+                ("packet_sequence", sequence),
+                ("recv_fee", "100muno"),
+                ("ack_fee", "50muno"),
+                ("timeout_fee", "50muno"),
+                ("msg_index", "1"),
+            ],
+        )
+    }
+
+    /// A transaction sending two packets on a fee enabled channel, and a packet
+    /// with the same sequence on another channel.
+    fn fixture_tx() -> Vec<Event> {
+        vec![
+            event(
+                "message",
+                &[
+                    ("action", "/ibc.applications.transfer.v1.MsgTransfer"),
+                    ("sender", "union1sender"),
+                ],
+            ),
+            event(
+                "transfer",
+                &[
+                    ("recipient", "union1escrow"),
+                    ("sender", "union1sender"),
+                    ("amount", "1000muno"),
+                ],
+            ),
+            send_packet_event("12", "channel-0", "channel-7"),
+            event(
+                "ibc_transfer",
+                &[
+                    ("sender", "union1sender"),
+                    ("receiver", "osmo1receiver"),
+                    ("memo", "hello"),
+                ],
+            ),
+            incentivized_packet_event("12", "channel-0"),
+            send_packet_event("13", "channel-0", "channel-7"),
+            incentivized_packet_event("13", "channel-0"),
+            send_packet_event("12", "channel-1", "channel-9"),
+            incentivized_packet_event("12", "channel-1"),
+        ]
+    }
+
+    fn send_packet(sequence: u64) -> IbcEvent {
+        IbcEvent::SendPacket(SendPacket {
+            packet_data_hex: b"{}".to_vec().into(),
+            packet_timeout_height: Height::new(100),
+            packet_timeout_timestamp: 0,
+            packet_sequence: NonZeroU64::new(sequence).unwrap(),
+            packet_src_port: PortId::new("transfer".to_owned()).unwrap(),
+            packet_src_channel: ChannelId::new(0),
+            packet_dst_port: PortId::new("transfer".to_owned()).unwrap(),
+            packet_dst_channel: ChannelId::new(7),
+            packet_channel_ordering: Order::Unordered,
+            connection_id: ConnectionId::new(0),
+        })
+    }
+
+    fn raw(event: &Event) -> RawTmEvent {
+        RawTmEvent {
+            ty: event.ty.clone(),
+            attributes: event
+                .attributes
+                .iter()
+                .map(|attr| (attr.key.clone(), attr.value.clone()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn fee_events_are_correlated() {
+        let tx = fixture_tx();
+
+        assert_eq!(
+            correlated_events(&send_packet(12), &tx),
+            Some(vec![
+                raw(&send_packet_event("12", "channel-0", "channel-7")),
+                raw(&incentivized_packet_event("12", "channel-0")),
+            ])
+        );
+
+        assert_eq!(
+            correlated_events(&send_packet(13), &tx),
+            Some(vec![
+                raw(&send_packet_event("13", "channel-0", "channel-7")),
+                raw(&incentivized_packet_event("13", "channel-0")),
+            ])
+        );
+    }
+
+    #[test]
+    fn fixture_send_packet_parses() {
+        assert_eq!(
+            IbcEvent::try_from_tendermint_event(send_packet_event("12", "channel-0", "channel-7")),
+            Some(Ok(send_packet(12)))
+        );
+    }
+
+    #[test]
+    fn non_packet_events_are_not_correlated() {
+        let update_client = IbcEvent::UpdateClient(UpdateClient {
+            client_id: ClientId::new("07-tendermint", 0),
+            client_type: "07-tendermint".to_owned(),
+            consensus_heights: vec![Height::new(100)],
+        });
+
+        assert_eq!(correlated_events(&update_client, &fixture_tx()), None);
+    }
+}
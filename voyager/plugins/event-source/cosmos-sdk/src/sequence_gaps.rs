@@ -0,0 +1,812 @@
+//! Detection of gaps in the sequences of sent packets.
+//!
+//! The sequences of all `send_packet` events are tracked per channel. Since
+//! packets on a channel are sequenced contiguously, a sequence that is never
+//! observed (i.e. 101 and 103 are seen, but 102 is not) usually means that an
+//! op was dropped somewhere. Sequences may be observed out of order (for
+//! example while backfilling), so a gap is only reported once it has persisted
+//! for [`SequenceGapConfig::alert_after`] seconds. Gaps that are filled before
+//! then are cleared silently.
+//!
+//! Tracking starts at the first sequence observed on a channel, as there is no
+//! way to know whether the sequences before it were relayed by another
+//! relayer. The observed sequences are stored as ranges, such that memory is
+//! bounded by the amount of gaps rather than the amount of packets.
+//!
+//! The tracked state is persisted to a small JSON file, which can be shared
+//! between the event source plugins of multiple chains.
+
+use std::{
+    collections::BTreeMap,
+    io,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use macros::model;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use unionlabs::id::{ChannelId, PortId};
+use voyager_message::{cmd::CmdOutput, core::ChainId, into_value};
+
+use crate::data::SequenceGapDetected;
+
+/// The maximum amount of missing sequences listed in a single
+/// [`SequenceGapDetected`].
+pub const MAX_REPORTED_SEQUENCES: u64 = 1000;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SequenceGapConfig {
+    /// The file that the observed sequences are persisted to.
+    #[serde(default = "SequenceGapConfig::default_ledger_path")]
+    pub ledger_path: PathBuf,
+    /// How long (in seconds) a gap must persist before it is reported.
+    #[serde(default = "SequenceGapConfig::default_alert_after")]
+    pub alert_after: u64,
+}
+
+impl SequenceGapConfig {
+    fn default_ledger_path() -> PathBuf {
+        "sequence-gaps.json".into()
+    }
+
+    const fn default_alert_after() -> u64 {
+        10 * 60
+    }
+}
+
+impl Default for SequenceGapConfig {
+    fn default() -> Self {
+        Self {
+            ledger_path: Self::default_ledger_path(),
+            alert_after: Self::default_alert_after(),
+        }
+    }
+}
+
+/// A set of integers, stored as sorted, non-overlapping and non-adjacent
+/// inclusive ranges.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct RangeSet(Vec<(u64, u64)>);
+
+impl RangeSet {
+    #[must_use]
+    pub fn ranges(&self) -> &[(u64, u64)] {
+        &self.0
+    }
+
+    #[must_use]
+    pub fn contains(&self, n: u64) -> bool {
+        let idx = self.0.partition_point(|(start, _)| *start <= n);
+
+        idx > 0 && self.0[idx - 1].1 >= n
+    }
+
+    /// Insert `n` into the set, merging it with any adjacent ranges. Returns
+    /// `false` if `n` was already in the set.
+    pub fn insert(&mut self, n: u64) -> bool {
+        // the index of the first range that starts after n
+        let idx = self.0.partition_point(|(start, _)| *start <= n);
+
+        let extends_prev = match idx.checked_sub(1).map(|prev| self.0[prev]) {
+            Some((_, end)) if end >= n => return false,
+            Some((_, end)) => end + 1 == n,
+            None => false,
+        };
+
+        let extends_next = self
+            .0
+            .get(idx)
+            .is_some_and(|(start, _)| n.checked_add(1) == Some(*start));
+
+        match (extends_prev, extends_next) {
+            (true, true) => {
+                self.0[idx - 1].1 = self.0[idx].1;
+                self.0.remove(idx);
+            }
+            (true, false) => self.0[idx - 1].1 = n,
+            (false, true) => self.0[idx].0 = n,
+            (false, false) => self.0.insert(idx, (n, n)),
+        }
+
+        true
+    }
+
+    /// The ranges missing between the first and last integer in the set.
+    pub fn gaps(&self) -> impl Iterator<Item = (u64, u64)> + '_ {
+        self.0
+            .windows(2)
+            .map(|window| (window[0].1 + 1, window[1].0 - 1))
+    }
+}
+
+/// A range of sequences that has not been observed.
+#[model]
+pub struct Gap {
+    pub start: u64,
+    pub end: u64,
+    /// The unix timestamp (in seconds) at which the gap was first observed.
+    pub since: u64,
+    /// Whether the gap has already been reported.
+    pub alerted: bool,
+}
+
+impl Gap {
+    /// The missing sequences, capped at [`MAX_REPORTED_SEQUENCES`].
+    #[must_use]
+    pub fn missing(&self) -> Vec<u64> {
+        (self.start..=self.end)
+            .take(MAX_REPORTED_SEQUENCES as usize)
+            .collect()
+    }
+}
+
+/// The observed sequences of a single channel.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChannelSequences {
+    pub observed: RangeSet,
+    pub gaps: Vec<Gap>,
+}
+
+impl ChannelSequences {
+    /// Record `sequence` as observed at `now`, returning `false` if it had
+    /// already been observed.
+    pub fn observe(&mut self, sequence: u64, now: u64) -> bool {
+        if !self.observed.insert(sequence) {
+            return false;
+        }
+
+        // a gap that is split by a newly observed sequence keeps the time it was first observed at
+        // and whether it has been reported
+        self.gaps = self
+            .observed
+            .gaps()
+            .map(|(start, end)| {
+                let overlapping = self
+                    .gaps
+                    .iter()
+                    .filter(|gap| gap.start <= end && start <= gap.end);
+
+                Gap {
+                    start,
+                    end,
+                    since: overlapping
+                        .clone()
+                        .map(|gap| gap.since)
+                        .min()
+                        .unwrap_or(now),
+                    alerted: overlapping.clone().any(|gap| gap.alerted),
+                }
+            })
+            .collect();
+
+        true
+    }
+
+    /// The highest sequence up to which all sequences have been observed,
+    /// starting from the first observed sequence.
+    #[must_use]
+    pub fn high_water_mark(&self) -> Option<u64> {
+        self.observed.ranges().first().map(|(_, end)| *end)
+    }
+
+    /// Mark all gaps that have persisted for at least `alert_after` seconds and
+    /// haven't been reported yet as reported, returning them.
+    pub fn take_overdue(&mut self, now: u64, alert_after: u64) -> Vec<Gap> {
+        self.gaps
+            .iter_mut()
+            .filter(|gap| !gap.alerted && now.saturating_sub(gap.since) >= alert_after)
+            .map(|gap| {
+                gap.alerted = true;
+                gap.clone()
+            })
+            .collect()
+    }
+}
+
+/// The gaps of a single channel, see [`GapsOutput`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ChannelGaps {
+    pub port_id: PortId,
+    pub channel_id: ChannelId,
+    pub high_water_mark: Option<u64>,
+    pub gaps: Vec<Gap>,
+}
+
+/// The output of [`Cmd::Gaps`](crate::Cmd::Gaps): all channels of a chain that currently have
+/// gaps.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(transparent)]
+pub struct GapsOutput(pub Vec<ChannelGaps>);
+
+impl GapsOutput {
+    #[must_use]
+    pub fn new(ledger: &GapLedger, chain_id: &ChainId) -> Self {
+        Self(
+            ledger
+                .gaps(chain_id)
+                .map(|(port_id, channel_id, sequences)| ChannelGaps {
+                    port_id: port_id.clone(),
+                    channel_id: channel_id.clone(),
+                    high_water_mark: sequences.high_water_mark(),
+                    gaps: sequences.gaps.clone(),
+                })
+                .collect(),
+        )
+    }
+}
+
+impl CmdOutput for GapsOutput {
+    fn to_json(&self) -> Value {
+        into_value(self)
+    }
+
+    /// One json encoded channel per line.
+    fn to_text(&self) -> String {
+        self.0
+            .iter()
+            .map(|channel| {
+                serde_json::to_string(channel).expect("serialization is infallible; qed;")
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GapLedgerEntry {
+    pub chain_id: String,
+    pub port_id: PortId,
+    pub channel_id: ChannelId,
+    pub observed: RangeSet,
+    pub gaps: Vec<Gap>,
+}
+
+type ChannelKey = (String, PortId, ChannelId);
+
+/// The observed sequences per `(chain_id, port_id, channel_id)`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "Vec<GapLedgerEntry>", into = "Vec<GapLedgerEntry>")]
+pub struct GapLedger {
+    channels: BTreeMap<ChannelKey, ChannelSequences>,
+}
+
+impl GapLedger {
+    /// Load the ledger from `path`. A missing file is treated as an empty
+    /// ledger.
+    pub fn load(path: &Path) -> Result<Self, GapLedgerError> {
+        match std::fs::read(path) {
+            Ok(bz) => serde_json::from_slice(&bz).map_err(|source| GapLedgerError::Decode {
+                path: path.to_owned(),
+                source,
+            }),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(source) => Err(GapLedgerError::Io {
+                path: path.to_owned(),
+                source,
+            }),
+        }
+    }
+
+    /// Atomically write the ledger to `path`.
+    pub fn store(&self, path: &Path) -> Result<(), GapLedgerError> {
+        let io_err = |source| GapLedgerError::Io {
+            path: path.to_owned(),
+            source,
+        };
+
+        // the ledger may be shared between processes, so each writes to its own temporary file
+        let tmp_path = path.with_extension(format!("{}.tmp", std::process::id()));
+
+        std::fs::write(
+            &tmp_path,
+            serde_json::to_vec_pretty(self).expect("serialization is infallible; qed;"),
+        )
+        .map_err(io_err)?;
+
+        std::fs::rename(&tmp_path, path).map_err(io_err)
+    }
+
+    pub fn channel_mut(
+        &mut self,
+        chain_id: &ChainId,
+        port_id: &PortId,
+        channel_id: &ChannelId,
+    ) -> &mut ChannelSequences {
+        self.channels
+            .entry((chain_id.to_string(), port_id.clone(), channel_id.clone()))
+            .or_default()
+    }
+
+    /// All channels of `chain_id` that currently have gaps.
+    pub fn gaps(
+        &self,
+        chain_id: &ChainId,
+    ) -> impl Iterator<Item = (&PortId, &ChannelId, &ChannelSequences)> {
+        let chain_id = chain_id.to_string();
+
+        self.channels
+            .iter()
+            .filter(move |((entry_chain_id, _, _), sequences)| {
+                *entry_chain_id == chain_id && !sequences.gaps.is_empty()
+            })
+            .map(|((_, port_id, channel_id), sequences)| (port_id, channel_id, sequences))
+    }
+
+    /// Replace all entries for `chain_id` with the entries in `other`.
+    fn merge_chain(&mut self, chain_id: &ChainId, other: &Self) {
+        let chain_id = chain_id.to_string();
+
+        self.channels
+            .retain(|(entry_chain_id, _, _), _| *entry_chain_id != chain_id);
+        self.channels.extend(
+            other
+                .channels
+                .iter()
+                .filter(|((entry_chain_id, _, _), _)| *entry_chain_id == chain_id)
+                .map(|(k, v)| (k.clone(), v.clone())),
+        );
+    }
+}
+
+impl From<Vec<GapLedgerEntry>> for GapLedger {
+    fn from(value: Vec<GapLedgerEntry>) -> Self {
+        Self {
+            channels: value
+                .into_iter()
+                .map(|entry| {
+                    (
+                        (entry.chain_id, entry.port_id, entry.channel_id),
+                        ChannelSequences {
+                            observed: entry.observed,
+                            gaps: entry.gaps,
+                        },
+                    )
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<GapLedger> for Vec<GapLedgerEntry> {
+    fn from(value: GapLedger) -> Self {
+        value
+            .channels
+            .into_iter()
+            .map(
+                |((chain_id, port_id, channel_id), sequences)| GapLedgerEntry {
+                    chain_id,
+                    port_id,
+                    channel_id,
+                    observed: sequences.observed,
+                    gaps: sequences.gaps,
+                },
+            )
+            .collect()
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum GapLedgerError {
+    #[error("error accessing sequence gap ledger at {}", path.display())]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    #[error("invalid sequence gap ledger at {}", path.display())]
+    Decode {
+        path: PathBuf,
+        #[source]
+        source: serde_json::Error,
+    },
+}
+
+/// Tracks the sequences of the packets sent on a single chain, persisting them
+/// to the configured ledger.
+#[derive(Debug)]
+pub struct SequenceGapTracker {
+    chain_id: ChainId,
+    config: SequenceGapConfig,
+    ledger: Mutex<GapLedger>,
+}
+
+impl SequenceGapTracker {
+    pub fn new(chain_id: ChainId, config: SequenceGapConfig) -> Result<Self, GapLedgerError> {
+        let ledger = GapLedger::load(&config.ledger_path)?;
+
+        Ok(Self {
+            chain_id,
+            config,
+            ledger: Mutex::new(ledger),
+        })
+    }
+
+    pub fn observe(
+        &self,
+        port_id: &PortId,
+        channel_id: &ChannelId,
+        sequence: u64,
+    ) -> Result<(), GapLedgerError> {
+        self.observe_at(voyager_vm::now(), port_id, channel_id, sequence)
+    }
+
+    pub fn observe_at(
+        &self,
+        now: u64,
+        port_id: &PortId,
+        channel_id: &ChannelId,
+        sequence: u64,
+    ) -> Result<(), GapLedgerError> {
+        let mut ledger = self.ledger.lock().expect("lock is not poisoned");
+
+        if ledger
+            .channel_mut(&self.chain_id, port_id, channel_id)
+            .observe(sequence, now)
+        {
+            self.persist(&ledger)?;
+        }
+
+        Ok(())
+    }
+
+    /// All gaps that have persisted for longer than the configured duration
+    /// and have not been reported yet. Returned gaps are not returned again.
+    pub fn take_overdue(&self) -> Result<Vec<SequenceGapDetected>, GapLedgerError> {
+        self.take_overdue_at(voyager_vm::now())
+    }
+
+    pub fn take_overdue_at(&self, now: u64) -> Result<Vec<SequenceGapDetected>, GapLedgerError> {
+        let mut ledger = self.ledger.lock().expect("lock is not poisoned");
+
+        let chain_id = self.chain_id.to_string();
+
+        let overdue = ledger
+            .channels
+            .iter_mut()
+            .filter(|((entry_chain_id, _, _), _)| *entry_chain_id == chain_id)
+            .flat_map(|((_, port_id, channel_id), sequences)| {
+                sequences
+                    .take_overdue(now, self.config.alert_after)
+                    .into_iter()
+                    .map(|gap| SequenceGapDetected {
+                        chain_id: self.chain_id.clone(),
+                        port_id: port_id.clone(),
+                        channel: channel_id.clone(),
+                        missing: gap.missing(),
+                        missing_count: gap.end - gap.start + 1,
+                        since: gap.since,
+                    })
+            })
+            .collect::<Vec<_>>();
+
+        if !overdue.is_empty() {
+            self.persist(&ledger)?;
+        }
+
+        Ok(overdue)
+    }
+
+    /// The current state of the ledger.
+    pub fn ledger(&self) -> GapLedger {
+        self.ledger.lock().expect("lock is not poisoned").clone()
+    }
+
+    /// Write the entries of this chain to the ledger, preserving the entries
+    /// of other chains that share the same file.
+    fn persist(&self, ledger: &GapLedger) -> Result<(), GapLedgerError> {
+        let mut on_disk = GapLedger::load(&self.config.ledger_path)?;
+        on_disk.merge_chain(&self.chain_id, ledger);
+        on_disk.store(&self.config.ledger_path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    const NOW: u64 = 1_700_000_000;
+
+    fn range_set(ns: impl IntoIterator<Item = u64>) -> RangeSet {
+        let mut set = RangeSet::default();
+        for n in ns {
+            set.insert(n);
+        }
+        set
+    }
+
+    fn port() -> PortId {
+        PortId::new("transfer".to_owned()).unwrap()
+    }
+
+    fn tracker(name: &str, alert_after: u64) -> SequenceGapTracker {
+        let ledger_path = std::env::temp_dir().join(format!(
+            "cosmos-sdk-event-source-gaps-{name}-{}.json",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&ledger_path);
+
+        SequenceGapTracker::new(
+            ChainId::new("union-1"),
+            SequenceGapConfig {
+                ledger_path,
+                alert_after,
+            },
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn range_set_compaction() {
+        let mut set = range_set([1, 2, 3, 5, 7, 8]);
+        assert_eq!(set.ranges(), [(1, 3), (5, 5), (7, 8)]);
+        assert_eq!(set.gaps().collect::<Vec<_>>(), [(4, 4), (6, 6)]);
+
+        // already present
+        assert!(!set.insert(2));
+        assert!(!set.insert(8));
+
+        // filling a gap merges both neighbours
+        assert!(set.insert(4));
+        assert_eq!(set.ranges(), [(1, 5), (7, 8)]);
+
+        assert!(set.insert(6));
+        assert_eq!(set.ranges(), [(1, 8)]);
+        assert_eq!(set.gaps().count(), 0);
+
+        // extending either side
+        assert!(set.insert(0));
+        assert!(set.insert(9));
+        assert_eq!(set.ranges(), [(0, 9)]);
+
+        assert!(set.insert(u64::MAX));
+        assert!(set.insert(u64::MAX - 1));
+        assert_eq!(set.ranges(), [(0, 9), (u64::MAX - 1, u64::MAX)]);
+
+        assert!(set.contains(5));
+        assert!(!set.contains(10));
+        assert!(set.contains(u64::MAX));
+    }
+
+    #[test]
+    fn range_set_out_of_order() {
+        let set = range_set(
+            (1..=1000)
+                .rev()
+                .filter(|n| n % 2 == 0)
+                .chain((1..=1000).filter(|n| n % 2 == 1)),
+        );
+
+        assert_eq!(set.ranges(), [(1, 1000)]);
+    }
+
+    #[test]
+    fn gaps_keep_their_age_when_split() {
+        let mut sequences = ChannelSequences::default();
+
+        sequences.observe(100, NOW);
+        sequences.observe(110, NOW);
+        assert_eq!(sequences.high_water_mark(), Some(100));
+        assert_eq!(
+            sequences.gaps,
+            [Gap {
+                start: 101,
+                end: 109,
+                since: NOW,
+                alerted: false,
+            }]
+        );
+
+        sequences.observe(105, NOW + 60);
+        assert_eq!(
+            sequences.gaps,
+            [
+                Gap {
+                    start: 101,
+                    end: 104,
+                    since: NOW,
+                    alerted: false,
+                },
+                Gap {
+                    start: 106,
+                    end: 109,
+                    since: NOW,
+                    alerted: false,
+                },
+            ]
+        );
+
+        // a new gap after the last sequence is new
+        sequences.observe(112, NOW + 120);
+        assert_eq!(sequences.gaps[2].since, NOW + 120);
+    }
+
+    #[test]
+    fn alert_threshold() {
+        let tracker = tracker("threshold", 600);
+
+        let channel_id = ChannelId::new(3);
+
+        tracker.observe_at(NOW, &port(), &channel_id, 101).unwrap();
+        tracker.observe_at(NOW, &port(), &channel_id, 103).unwrap();
+
+        // not yet overdue
+        assert_eq!(tracker.take_overdue_at(NOW + 599).unwrap(), []);
+
+        assert_eq!(
+            tracker.take_overdue_at(NOW + 600).unwrap(),
+            [SequenceGapDetected {
+                chain_id: ChainId::new("union-1"),
+                port_id: port(),
+                channel: channel_id.clone(),
+                missing: vec![102],
+                missing_count: 1,
+                since: NOW,
+            }]
+        );
+
+        // only reported once
+        assert_eq!(tracker.take_overdue_at(NOW + 6000).unwrap(), []);
+
+        // filled gaps are cleared
+        tracker
+            .observe_at(NOW + 6000, &port(), &channel_id, 102)
+            .unwrap();
+        assert_eq!(tracker.ledger().gaps(&ChainId::new("union-1")).count(), 0);
+    }
+
+    #[test]
+    fn gaps_filled_during_backfill_are_not_reported() {
+        let tracker = tracker("backfill", 600);
+
+        let channel_id = ChannelId::new(0);
+
+        for sequence in [10, 14, 11, 13, 12] {
+            tracker
+                .observe_at(NOW, &port(), &channel_id, sequence)
+                .unwrap();
+        }
+
+        assert_eq!(tracker.take_overdue_at(NOW + 6000).unwrap(), []);
+        assert_eq!(
+            tracker
+                .ledger()
+                .channel_mut(&ChainId::new("union-1"), &port(), &channel_id)
+                .observed
+                .ranges(),
+            [(10, 14)]
+        );
+    }
+
+    #[test]
+    fn large_gaps_are_capped() {
+        let tracker = tracker("large", 0);
+
+        let channel_id = ChannelId::new(0);
+
+        tracker.observe_at(NOW, &port(), &channel_id, 1).unwrap();
+        tracker
+            .observe_at(NOW, &port(), &channel_id, 1_000_000)
+            .unwrap();
+
+        let [detected] = &tracker.take_overdue_at(NOW).unwrap()[..] else {
+            panic!()
+        };
+
+        assert_eq!(detected.missing.len(), MAX_REPORTED_SEQUENCES as usize);
+        assert_eq!(detected.missing[0], 2);
+        assert_eq!(detected.missing_count, 999_998);
+    }
+
+    #[test]
+    fn persistence_format() {
+        let tracker = tracker("format", 600);
+
+        tracker
+            .observe_at(NOW, &port(), &ChannelId::new(1), 1)
+            .unwrap();
+        tracker
+            .observe_at(NOW, &port(), &ChannelId::new(1), 3)
+            .unwrap();
+        tracker.take_overdue_at(NOW + 600).unwrap();
+
+        let json = serde_json::from_slice::<serde_json::Value>(
+            &std::fs::read(&tracker.config.ledger_path).unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            json,
+            json!([{
+                "chain_id": "union-1",
+                "port_id": "transfer",
+                "channel_id": 1,
+                "observed": [[1, 1], [3, 3]],
+                "gaps": [{ "start": 2, "end": 2, "since": NOW, "alerted": true }],
+            }])
+        );
+
+        // the ledger is restored on restart, including whether gaps have been reported
+        let restored =
+            SequenceGapTracker::new(ChainId::new("union-1"), tracker.config.clone()).unwrap();
+        assert_eq!(restored.ledger(), tracker.ledger());
+        assert_eq!(restored.take_overdue_at(NOW + 6000).unwrap(), []);
+    }
+
+    #[test]
+    fn ledger_is_shared_between_chains() {
+        let tracker = tracker("shared", 600);
+
+        let other =
+            SequenceGapTracker::new(ChainId::new("osmosis-1"), tracker.config.clone()).unwrap();
+
+        tracker
+            .observe_at(NOW, &port(), &ChannelId::new(1), 1)
+            .unwrap();
+        other
+            .observe_at(NOW, &port(), &ChannelId::new(1), 7)
+            .unwrap();
+
+        let ledger = GapLedger::load(&tracker.config.ledger_path).unwrap();
+
+        assert_eq!(ledger.channels.len(), 2);
+    }
+
+    #[test]
+    fn gaps_output() {
+        let mut ledger = GapLedger::default();
+
+        *ledger.channel_mut(&ChainId::new("union-1"), &port(), &ChannelId::new(1)) =
+            ChannelSequences {
+                observed: range_set([1, 2, 5]),
+                gaps: vec![Gap {
+                    start: 3,
+                    end: 4,
+                    since: NOW,
+                    alerted: false,
+                }],
+            };
+        // channels without gaps and channels of other chains are not included
+        *ledger.channel_mut(&ChainId::new("union-1"), &port(), &ChannelId::new(2)) =
+            ChannelSequences {
+                observed: range_set([1, 2]),
+                gaps: vec![],
+            };
+        *ledger.channel_mut(&ChainId::new("stargaze-1"), &port(), &ChannelId::new(1)) =
+            ChannelSequences {
+                observed: range_set([1, 3]),
+                gaps: vec![Gap {
+                    start: 2,
+                    end: 2,
+                    since: NOW,
+                    alerted: true,
+                }],
+            };
+
+        let output = GapsOutput::new(&ledger, &ChainId::new("union-1"));
+
+        assert_eq!(
+            output.to_json(),
+            json!([{
+                "port_id": "transfer",
+                "channel_id": 1,
+                "high_water_mark": 2,
+                "gaps": [{
+                    "start": 3,
+                    "end": 4,
+                    "since": NOW,
+                    "alerted": false,
+                }],
+            }])
+        );
+        assert_eq!(
+            output.to_text(),
+            serde_json::to_string(&output.0[0]).unwrap()
+        );
+    }
+}
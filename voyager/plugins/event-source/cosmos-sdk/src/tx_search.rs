@@ -0,0 +1,287 @@
+//! Fetching of all transactions in a block.
+//!
+//! `tx_search` is paginated, and the pages are fetched with separate requests. If the node is
+//! still indexing the block while it is being paginated (or indexes lazily), the `total_count`
+//! can change between pages, shifting transactions across page boundaries. To avoid skipping or
+//! emitting transactions twice, all pages of a height are fetched in ascending order and
+//! deduplicated by transaction hash, which also deduplicates the events in them by
+//! `(tx_hash, event index)`. If the `total_count` changed during pagination or does not match the
+//! number of transactions seen, the pages are fetched once more from the start.
+
+use std::{collections::HashSet, num::NonZeroU32};
+
+use cometbft_rpc::rpc_types::{Order, TxResponse, TxSearchResponse};
+use jsonrpsee::{core::RpcResult, types::ErrorObject};
+use serde_json::json;
+use tracing::{debug, warn};
+use unionlabs::{hash::H256, ibc::core::client::height::Height};
+
+use crate::{rpc_error, PER_PAGE_LIMIT};
+
+/// Read access to the indexed transactions of this chain.
+#[allow(async_fn_in_trait)]
+pub trait TxSearchClient {
+    /// Fetch the transactions at `height`, in ascending order of their index in the block.
+    async fn txs_at_height(&self, height: Height, page: NonZeroU32) -> RpcResult<TxSearchResponse>;
+}
+
+impl TxSearchClient for cometbft_rpc::Client {
+    async fn txs_at_height(&self, height: Height, page: NonZeroU32) -> RpcResult<TxSearchResponse> {
+        self.tx_search(
+            format!("tx.height={}", height.height()),
+            false,
+            page,
+            PER_PAGE_LIMIT,
+            Order::Asc,
+        )
+        .await
+        .map_err(rpc_error(
+            format_args!("error fetching transactions at height {height}"),
+            Some(json!({ "height": height, "page": page })),
+        ))
+    }
+}
+
+/// Fetch all transactions at `height`, starting at `first_page`.
+///
+/// Each transaction is returned exactly once. An error is returned if the number of transactions
+/// still does not match the `total_count` after fetching the pages a second time, in which case
+/// the call should be retried.
+pub async fn fetch_txs(
+    client: &impl TxSearchClient,
+    height: Height,
+    first_page: NonZeroU32,
+) -> RpcResult<Vec<TxResponse>> {
+    // scoped to this height, transactions are only ever deduplicated within a single block
+    let mut seen = HashSet::<H256>::new();
+    let mut txs = vec![];
+
+    let skipped = (first_page.get() - 1) * u32::from(PER_PAGE_LIMIT.get());
+
+    for attempt in 1..=2 {
+        let mut page = first_page;
+        let mut total_count = None;
+        let mut total_count_changed = false;
+
+        loop {
+            let response = client.txs_at_height(height, page).await?;
+
+            debug!(
+                %height,
+                %page,
+                total_count = response.total_count,
+                txs = response.txs.len(),
+                "fetched page of transactions"
+            );
+
+            if total_count.is_some_and(|total_count| total_count != response.total_count) {
+                warn!(
+                    %height,
+                    %page,
+                    previous_total_count = total_count,
+                    total_count = response.total_count,
+                    "total count of transactions changed during pagination"
+                );
+
+                total_count_changed = true;
+            }
+            total_count = Some(response.total_count);
+
+            let is_empty = response.txs.is_empty();
+
+            txs.extend(
+                response
+                    .txs
+                    .into_iter()
+                    .filter(|tx| seen.insert(tx.hash.into_encoding())),
+            );
+
+            if is_empty || page.get() * u32::from(PER_PAGE_LIMIT.get()) >= response.total_count {
+                break;
+            }
+
+            page = page.checked_add(1).expect("too many pages?");
+        }
+
+        let expected = total_count
+            .expect("at least one page is fetched; qed;")
+            .saturating_sub(skipped);
+
+        if !total_count_changed && txs.len() == expected as usize {
+            return Ok(txs);
+        }
+
+        warn!(
+            %height,
+            attempt,
+            expected,
+            found = txs.len(),
+            "transactions at height do not match the total count"
+        );
+    }
+
+    Err(ErrorObject::owned(
+        -1,
+        format!("the transactions at height {height} changed during pagination"),
+        Some(json!({ "height": height, "first_page": first_page })),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, collections::VecDeque};
+
+    use cometbft_rpc::types::abci::exec_tx_result::ExecTxResult;
+    use unionlabs::bounded::BoundedI64;
+
+    use super::*;
+
+    /// A node that returns the queued pages in order, asserting that they are requested in the
+    /// expected order.
+    struct MockNode(RefCell<VecDeque<(u32, TxSearchResponse)>>);
+
+    impl MockNode {
+        fn new(pages: impl IntoIterator<Item = (u32, u32, Vec<u8>)>) -> Self {
+            Self(RefCell::new(
+                pages
+                    .into_iter()
+                    .map(|(page, total_count, txs)| {
+                        (
+                            page,
+                            TxSearchResponse {
+                                txs: txs.into_iter().map(tx).collect(),
+                                total_count,
+                            },
+                        )
+                    })
+                    .collect(),
+            ))
+        }
+    }
+
+    impl TxSearchClient for MockNode {
+        async fn txs_at_height(
+            &self,
+            height: Height,
+            page: NonZeroU32,
+        ) -> RpcResult<TxSearchResponse> {
+            assert_eq!(height, HEIGHT);
+
+            let (expected_page, response) = self.0.borrow_mut().pop_front().expect("no more pages");
+            assert_eq!(page.get(), expected_page);
+
+            Ok(response)
+        }
+    }
+
+    const HEIGHT: Height = Height::new(100);
+
+    const FIRST_PAGE: NonZeroU32 = NonZeroU32::MIN;
+
+    fn tx(index: u8) -> TxResponse {
+        TxResponse {
+            hash: H256::new([index; 32]),
+            height: HEIGHT.height().try_into().ok(),
+            index: index.into(),
+            tx_result: ExecTxResult {
+                code: 0,
+                data: None,
+                log: String::new(),
+                info: String::new(),
+                gas_wanted: BoundedI64::new_const(0).unwrap(),
+                gas_used: BoundedI64::new_const(0).unwrap(),
+                events: vec![],
+                codespace: String::new(),
+            },
+            tx: Default::default(),
+            proof: None,
+        }
+    }
+
+    fn indices(txs: &[TxResponse]) -> Vec<u32> {
+        txs.iter().map(|tx| tx.index).collect()
+    }
+
+    #[tokio::test]
+    async fn single_page() {
+        let node = MockNode::new([(1, 3, vec![0, 1, 2])]);
+
+        let txs = fetch_txs(&node, HEIGHT, FIRST_PAGE).await.unwrap();
+
+        assert_eq!(indices(&txs), [0, 1, 2]);
+        assert!(node.0.borrow().is_empty());
+    }
+
+    #[tokio::test]
+    async fn multiple_pages() {
+        let node = MockNode::new([(1, 12, (0..10).collect()), (2, 12, vec![10, 11])]);
+
+        let txs = fetch_txs(&node, HEIGHT, FIRST_PAGE).await.unwrap();
+
+        assert_eq!(indices(&txs), (0..12).collect::<Vec<_>>());
+        assert!(node.0.borrow().is_empty());
+    }
+
+    #[tokio::test]
+    async fn total_count_changes_between_pages() {
+        let node = MockNode::new([
+            // the node has only indexed 11 transactions when the first page is fetched
+            (1, 11, (0..10).collect()),
+            // two more transactions are indexed, shifting the second page
+            (2, 13, vec![10, 11, 12]),
+            // the pages are fetched once more from the start, the transactions that were already
+            // seen are not returned twice
+            (1, 13, (0..10).collect()),
+            (2, 13, vec![10, 11, 12]),
+        ]);
+
+        let txs = fetch_txs(&node, HEIGHT, FIRST_PAGE).await.unwrap();
+
+        assert_eq!(indices(&txs), (0..13).collect::<Vec<_>>());
+        assert!(node.0.borrow().is_empty());
+    }
+
+    #[tokio::test]
+    async fn shifted_page_is_recovered_on_refetch() {
+        let node = MockNode::new([
+            (1, 12, (0..10).collect()),
+            // a transaction from the first page is returned again, and one is missing
+            (2, 12, vec![9, 11]),
+            (1, 12, (0..10).collect()),
+            (2, 12, vec![10, 11]),
+        ]);
+
+        let txs = fetch_txs(&node, HEIGHT, FIRST_PAGE).await.unwrap();
+
+        let mut indices = indices(&txs);
+        indices.sort_unstable();
+
+        assert_eq!(indices, (0..12).collect::<Vec<_>>());
+        assert!(node.0.borrow().is_empty());
+    }
+
+    #[tokio::test]
+    async fn total_count_changes_twice() {
+        let node = MockNode::new([
+            (1, 11, (0..10).collect()),
+            (2, 12, vec![10, 11]),
+            (1, 12, (0..10).collect()),
+            (2, 13, vec![10, 11, 12]),
+        ]);
+
+        assert!(fetch_txs(&node, HEIGHT, FIRST_PAGE).await.is_err());
+        assert!(node.0.borrow().is_empty());
+    }
+
+    #[tokio::test]
+    async fn starts_at_page() {
+        let node = MockNode::new([(2, 12, vec![10, 11])]);
+
+        let txs = fetch_txs(&node, HEIGHT, NonZeroU32::new(2).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(indices(&txs), [10, 11]);
+        assert!(node.0.borrow().is_empty());
+    }
+}
@@ -0,0 +1,271 @@
+//! Construction of chain events for the events emitted by the union IBC
+//! cosmwasm contract.
+
+use ibc_union_spec::{IbcUnion, UnionId};
+use jsonrpsee::core::RpcResult;
+use tracing::debug;
+use unionlabs::{hash::H256, ibc::core::client::height::Height};
+use voyager_message::{
+    core::{ChainId, ClientType, IbcSpec},
+    data::{ChainEvent, RawTmEvent},
+    error::union_id,
+    into_value,
+    query::VoyagerQuery,
+};
+
+use crate::ibc_events::UnionCreateClient;
+
+/// Build the chain event for a `CreateClient` event emitted at `height`.
+///
+/// The contract does not emit the counterparty chain or the height of the
+/// initial consensus state, so both are read from the client state at the
+/// height the client was created at.
+pub async fn create_client(
+    client: &impl VoyagerQuery,
+    chain_id: ChainId,
+    height: Height,
+    provable_height: Height,
+    tx_hash: H256,
+    event: UnionCreateClient,
+    raw_events: Option<Vec<RawTmEvent>>,
+) -> RpcResult<ChainEvent> {
+    let client_id = union_id(event.client_id)?;
+
+    let client_info = client.client_info::<IbcUnion>(&chain_id, client_id).await?;
+
+    let client_meta = client
+        .client_meta::<IbcUnion>(&chain_id, height.into(), client_id)
+        .await?;
+
+    debug!(
+        client_type = %event.client_type,
+        counterparty_chain_id = %client_meta.chain_id,
+        consensus_height = %client_meta.height,
+        "resolved created client"
+    );
+
+    Ok(ChainEvent {
+        chain_id,
+        client_info,
+        counterparty_chain_id: client_meta.chain_id,
+        tx_hash,
+        provable_height,
+        ibc_spec_id: IbcUnion::ID,
+        event: into_value::<ibc_union_spec::FullEvent>(
+            ibc_union_spec::CreateClient {
+                client_id,
+                client_type: ClientType::new(event.client_type),
+            }
+            .into(),
+        ),
+        raw_events,
+        denom_trace: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io,
+        sync::{Arc, Mutex},
+    };
+
+    use tracing_subscriber::fmt::MakeWriter;
+    use voyager_message::{
+        core::{ClientInfo, ClientStateMeta, ClientStatus, IbcInterface},
+        testing::MockVoyager,
+    };
+
+    use super::*;
+
+    const CHAIN_ID: &str = "union-devnet-1";
+    const COUNTERPARTY_CHAIN_ID: &str = "32382";
+
+    /// The client is created at height 1-100, its meta can't be read before that.
+    fn voyager() -> MockVoyager {
+        let chain_id = ChainId::new(CHAIN_ID);
+        let client_id = UnionId::new(3).unwrap();
+
+        let voyager = MockVoyager::new();
+
+        voyager
+            .set_client_info::<IbcUnion>(
+                &chain_id,
+                client_id,
+                ClientInfo {
+                    client_type: ClientType::new(ClientType::ETHEREUM),
+                    ibc_interface: IbcInterface::new(IbcInterface::IBC_COSMWASM),
+                    metadata: Default::default(),
+                },
+            )
+            .set_client_meta_at::<IbcUnion>(
+                &chain_id,
+                Height::new_with_revision(1, 100),
+                client_id,
+                ClientStateMeta {
+                    height: Height::new(2048),
+                    chain_id: ChainId::new(COUNTERPARTY_CHAIN_ID),
+                    status: ClientStatus::Unknown,
+                    resolved_at: None,
+                },
+            );
+
+        voyager
+    }
+
+    /// Collects everything written by the subscriber it is installed in.
+    #[derive(Clone, Default)]
+    struct Logs(Arc<Mutex<Vec<u8>>>);
+
+    impl Logs {
+        fn contents(&self) -> String {
+            String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+        }
+    }
+
+    impl io::Write for Logs {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl<'a> MakeWriter<'a> for Logs {
+        type Writer = Self;
+
+        fn make_writer(&'a self) -> Self::Writer {
+            self.clone()
+        }
+    }
+
+    async fn create_client_chain_event() -> ChainEvent {
+        create_client(
+            &voyager(),
+            ChainId::new(CHAIN_ID),
+            Height::new_with_revision(1, 100),
+            Height::new_with_revision(1, 101),
+            H256::new([0xaa; 32]),
+            UnionCreateClient {
+                client_id: 3,
+                client_type: ClientType::ETHEREUM.to_owned(),
+            },
+            None,
+        )
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn create_client_event() {
+        let logs = Logs::default();
+
+        let _guard = tracing::subscriber::set_default(
+            tracing_subscriber::fmt()
+                .with_max_level(tracing::Level::DEBUG)
+                .with_ansi(false)
+                .with_writer(logs.clone())
+                .finish(),
+        );
+
+        let chain_event = create_client_chain_event().await;
+
+        assert_eq!(chain_event.chain_id, ChainId::new(CHAIN_ID));
+        assert_eq!(
+            chain_event.counterparty_chain_id,
+            ChainId::new(COUNTERPARTY_CHAIN_ID)
+        );
+        assert_eq!(chain_event.ibc_spec_id, IbcUnion::ID);
+        assert_eq!(
+            chain_event.provable_height,
+            Height::new_with_revision(1, 101)
+        );
+        assert_eq!(
+            serde_json::from_value::<ibc_union_spec::FullEvent>(chain_event.event).unwrap(),
+            ibc_union_spec::CreateClient {
+                client_id: UnionId::new(3).unwrap(),
+                client_type: ClientType::new(ClientType::ETHEREUM),
+            }
+            .into()
+        );
+
+        // the event is reported through the subscriber
+        let logs = logs.contents();
+        assert!(logs.contains("resolved created client"), "{logs}");
+        assert!(
+            logs.contains(&format!("counterparty_chain_id={COUNTERPARTY_CHAIN_ID}")),
+            "{logs}"
+        );
+        assert!(logs.contains("consensus_height=2048"), "{logs}");
+    }
+
+    /// Set in the process spawned by [`create_client_event_does_not_write_to_stdout`], which runs
+    /// the event construction instead of spawning it.
+    const STDOUT_CHILD_ENV: &str = "UNION_EVENTS_STDOUT_CHILD";
+
+    const BEGIN_MARKER: &str = "--- begin create_client ---";
+    const END_MARKER: &str = "--- end create_client ---";
+
+    /// libtest captures everything printed in a test in-process, so the event is constructed in a
+    /// separate run of this test binary with capturing disabled, and its real stdout and stderr
+    /// are inspected.
+    #[test]
+    fn create_client_event_does_not_write_to_stdout() {
+        if std::env::var_os(STDOUT_CHILD_ENV).is_some() {
+            let logs = Logs::default();
+
+            let _guard = tracing::subscriber::set_default(
+                tracing_subscriber::fmt()
+                    .with_max_level(tracing::Level::DEBUG)
+                    .with_writer(logs.clone())
+                    .finish(),
+            );
+
+            println!("{BEGIN_MARKER}");
+
+            tokio::runtime::Builder::new_current_thread()
+                .build()
+                .unwrap()
+                .block_on(create_client_chain_event());
+
+            println!("{END_MARKER}");
+
+            assert!(logs.contents().contains("resolved created client"));
+
+            return;
+        }
+
+        // test names don't include the crate name
+        let (_, module_path) = module_path!().split_once("::").unwrap();
+
+        let output = std::process::Command::new(std::env::current_exe().unwrap())
+            .args([
+                &format!("{module_path}::create_client_event_does_not_write_to_stdout"),
+                "--exact",
+                "--nocapture",
+                "--test-threads=1",
+            ])
+            .env(STDOUT_CHILD_ENV, "1")
+            .output()
+            .unwrap();
+
+        assert!(output.status.success(), "{output:?}");
+
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        let written = stdout
+            .split_once(BEGIN_MARKER)
+            .and_then(|(_, rest)| rest.split_once(END_MARKER))
+            .map(|(written, _)| written)
+            .unwrap_or_else(|| panic!("the event was not constructed: {stdout}"));
+
+        assert_eq!(written.trim(), "", "stdout was written to");
+        assert_eq!(
+            String::from_utf8(output.stderr).unwrap(),
+            "",
+            "stderr was written to"
+        );
+    }
+}
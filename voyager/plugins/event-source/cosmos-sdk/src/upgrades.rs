@@ -0,0 +1,424 @@
+//! Detection of chain upgrades and halts.
+//!
+//! When a chain halts for a coordinated upgrade, no new blocks are produced
+//! until the upgraded binary is running. If the upgrade changes the chain id
+//! (i.e. `union-testnet-8` -> `union-testnet-9`), the revision of all heights
+//! changes with it. The network id reported by the node is checked
+//! periodically while fetching blocks, and once it changes, fetching is
+//! stopped until the plugin is restarted with the new chain id, unless
+//! [`UpgradeConfig::auto_follow_upgrade`] is set, in which case the new
+//! revision is used from then on. Upgrades are never followed if the revision
+//! is overridden in the config, since the revision of the new chain id can't be
+//! known.
+//!
+//! If the latest height of the chain does not advance within
+//! [`UpgradeConfig::halt_detection_window`], the chain is reported as halted.
+
+use std::sync::Mutex;
+
+use jsonrpsee::core::RpcResult;
+use serde::{Deserialize, Serialize};
+use tracing::{error, warn};
+use unionlabs::ibc::core::client::height::Height;
+use voyager_message::core::ChainId;
+
+use crate::data::{ChainHalted, ChainUpgradeDetected, ModuleData};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct UpgradeConfig {
+    /// Continue fetching with the revision of the new chain id after an
+    /// upgrade, instead of stopping until the plugin is restarted.
+    #[serde(default)]
+    pub auto_follow_upgrade: bool,
+    /// How often (in seconds) to check the network id and latest height of the
+    /// node.
+    #[serde(default = "UpgradeConfig::default_check_interval")]
+    pub check_interval: u64,
+    /// How long (in seconds) the latest height of the chain can stay the same
+    /// before the chain is reported as halted.
+    #[serde(default = "UpgradeConfig::default_halt_detection_window")]
+    pub halt_detection_window: u64,
+}
+
+impl UpgradeConfig {
+    const fn default_check_interval() -> u64 {
+        60
+    }
+
+    const fn default_halt_detection_window() -> u64 {
+        10 * 60
+    }
+}
+
+impl Default for UpgradeConfig {
+    fn default() -> Self {
+        Self {
+            auto_follow_upgrade: false,
+            check_interval: Self::default_check_interval(),
+            halt_detection_window: Self::default_halt_detection_window(),
+        }
+    }
+}
+
+/// The status of the node, as relevant for upgrade and halt detection.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NodeStatus {
+    pub network: String,
+    pub latest_height: u64,
+}
+
+/// Read access to the status of the node.
+#[allow(async_fn_in_trait)]
+pub trait NodeStatusClient {
+    async fn node_status(&self) -> RpcResult<NodeStatus>;
+}
+
+/// Tracks the network id and latest height of the node across checks.
+#[derive(Debug)]
+pub struct UpgradeMonitor {
+    chain_id: ChainId,
+    /// Set if the revision is overridden in the config, in which case upgrades are never
+    /// followed.
+    revision_pinned: bool,
+    state: Mutex<MonitorState>,
+}
+
+#[derive(Debug)]
+struct MonitorState {
+    /// The network id of the chain that is currently being fetched from.
+    network: String,
+    revision: u64,
+    last_checked: Option<u64>,
+    /// The latest height of the node, and the unix timestamp (in seconds) at
+    /// which it was first observed.
+    latest_height: u64,
+    latest_height_since: u64,
+    halt_reported: bool,
+    /// Set once an upgrade is detected that is not followed.
+    stopped: Option<ChainUpgradeDetected>,
+}
+
+impl UpgradeMonitor {
+    /// Create a monitor for `chain_id`, where `network` and `revision` are as
+    /// reported by the node on startup.
+    #[must_use]
+    pub fn new(chain_id: ChainId, network: String, revision: u64, now: u64) -> Self {
+        Self {
+            chain_id,
+            revision_pinned: false,
+            state: Mutex::new(MonitorState {
+                network,
+                revision,
+                last_checked: None,
+                latest_height: 0,
+                latest_height_since: now,
+                halt_reported: false,
+                stopped: None,
+            }),
+        }
+    }
+
+    /// Never follow upgrades, as the revision is overridden in the config.
+    #[must_use]
+    pub fn with_pinned_revision(mut self) -> Self {
+        self.revision_pinned = true;
+        self
+    }
+
+    /// The revision of the chain that is currently being fetched from.
+    pub fn revision(&self) -> u64 {
+        self.state.lock().expect("lock is not poisoned").revision
+    }
+
+    /// The upgrade that fetching was stopped at, if any.
+    pub fn stopped(&self) -> Option<ChainUpgradeDetected> {
+        self.state
+            .lock()
+            .expect("lock is not poisoned")
+            .stopped
+            .clone()
+    }
+
+    /// Check the status of the node, if the last check was at least
+    /// [`UpgradeConfig::check_interval`] ago, returning the upgrades and halts
+    /// to report.
+    ///
+    /// `now` is a unix timestamp in seconds.
+    pub async fn check(
+        &self,
+        client: &impl NodeStatusClient,
+        config: &UpgradeConfig,
+        now: u64,
+    ) -> RpcResult<Vec<ModuleData>> {
+        {
+            let state = self.state.lock().expect("lock is not poisoned");
+
+            if state.stopped.is_some()
+                || state
+                    .last_checked
+                    .is_some_and(|last_checked| now < last_checked + config.check_interval)
+            {
+                return Ok(vec![]);
+            }
+        }
+
+        let status = client.node_status().await?;
+
+        let mut state = self.state.lock().expect("lock is not poisoned");
+
+        state.last_checked = Some(now);
+
+        if status.network != state.network {
+            let upgrade = ChainUpgradeDetected {
+                old_chain_id: ChainId::new(state.network.clone()),
+                new_chain_id: ChainId::new(status.network.clone()),
+                height: status.latest_height,
+            };
+
+            match revision(&status.network) {
+                Some(revision) if config.auto_follow_upgrade && !self.revision_pinned => {
+                    warn!(
+                        chain_id = %self.chain_id,
+                        old_chain_id = %upgrade.old_chain_id,
+                        new_chain_id = %upgrade.new_chain_id,
+                        height = upgrade.height,
+                        revision,
+                        "chain was upgraded, continuing with the new revision"
+                    );
+
+                    state.network = status.network;
+                    state.revision = revision;
+                    state.latest_height = status.latest_height;
+                    state.latest_height_since = now;
+                    state.halt_reported = false;
+                }
+                _ => {
+                    error!(
+                        chain_id = %self.chain_id,
+                        old_chain_id = %upgrade.old_chain_id,
+                        new_chain_id = %upgrade.new_chain_id,
+                        height = upgrade.height,
+                        "chain was upgraded, no more blocks will be fetched until the plugin is \
+                        restarted with the new chain id"
+                    );
+
+                    state.stopped = Some(upgrade.clone());
+                }
+            }
+
+            return Ok(vec![upgrade.into()]);
+        }
+
+        if status.latest_height > state.latest_height {
+            state.latest_height = status.latest_height;
+            state.latest_height_since = now;
+            state.halt_reported = false;
+
+            return Ok(vec![]);
+        }
+
+        if state.halt_reported
+            || now.saturating_sub(state.latest_height_since) < config.halt_detection_window
+        {
+            return Ok(vec![]);
+        }
+
+        state.halt_reported = true;
+
+        let halted = ChainHalted {
+            chain_id: self.chain_id.clone(),
+            height: Height::new_with_revision(state.revision, state.latest_height),
+            since: state.latest_height_since,
+        };
+
+        error!(
+            chain_id = %self.chain_id,
+            height = %halted.height,
+            since = halted.since,
+            "chain has not produced a new block within the halt detection window"
+        );
+
+        Ok(vec![halted.into()])
+    }
+}
+
+/// Parse the revision number from a chain id of the form
+/// `<chain>-<revision-number>`.
+#[must_use]
+pub fn revision(network: &str) -> Option<u64> {
+    network.rsplit_once('-')?.1.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, collections::VecDeque};
+
+    use super::*;
+
+    struct MockNode(RefCell<VecDeque<NodeStatus>>);
+
+    impl MockNode {
+        fn new<'a>(statuses: impl IntoIterator<Item = (&'a str, u64)>) -> Self {
+            Self(RefCell::new(
+                statuses
+                    .into_iter()
+                    .map(|(network, latest_height)| NodeStatus {
+                        network: network.to_owned(),
+                        latest_height,
+                    })
+                    .collect(),
+            ))
+        }
+    }
+
+    impl NodeStatusClient for MockNode {
+        async fn node_status(&self) -> RpcResult<NodeStatus> {
+            Ok(self.0.borrow_mut().pop_front().expect("no more statuses"))
+        }
+    }
+
+    fn monitor() -> UpgradeMonitor {
+        UpgradeMonitor::new(
+            ChainId::new("union-testnet-8"),
+            "union-testnet-8".to_owned(),
+            8,
+            0,
+        )
+    }
+
+    fn config(auto_follow_upgrade: bool) -> UpgradeConfig {
+        UpgradeConfig {
+            auto_follow_upgrade,
+            check_interval: 10,
+            halt_detection_window: 300,
+        }
+    }
+
+    async fn check(
+        monitor: &UpgradeMonitor,
+        node: &MockNode,
+        auto_follow_upgrade: bool,
+        now: u64,
+    ) -> Vec<ModuleData> {
+        monitor
+            .check(node, &config(auto_follow_upgrade), now)
+            .await
+            .unwrap()
+    }
+
+    fn upgrade() -> ModuleData {
+        ChainUpgradeDetected {
+            old_chain_id: ChainId::new("union-testnet-8"),
+            new_chain_id: ChainId::new("union-testnet-9"),
+            height: 101,
+        }
+        .into()
+    }
+
+    #[tokio::test]
+    async fn upgrade_stops_fetching() {
+        let monitor = monitor();
+        let node = MockNode::new([("union-testnet-8", 100), ("union-testnet-9", 101)]);
+
+        assert!(check(&monitor, &node, false, 0).await.is_empty());
+        assert_eq!(monitor.stopped(), None);
+
+        assert_eq!(check(&monitor, &node, false, 10).await, [upgrade()]);
+        assert_eq!(monitor.stopped().map(ModuleData::from), Some(upgrade()));
+        assert_eq!(monitor.revision(), 8);
+
+        // the node is not queried again once stopped
+        assert!(check(&monitor, &node, false, 20).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn upgrade_is_followed() {
+        let monitor = monitor();
+        let node = MockNode::new([
+            ("union-testnet-8", 100),
+            ("union-testnet-9", 101),
+            ("union-testnet-9", 102),
+        ]);
+
+        assert!(check(&monitor, &node, true, 0).await.is_empty());
+        assert_eq!(check(&monitor, &node, true, 10).await, [upgrade()]);
+        assert_eq!(monitor.stopped(), None);
+        assert_eq!(monitor.revision(), 9);
+
+        // the new chain id is not reported again
+        assert!(check(&monitor, &node, true, 20).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn unparseable_upgrade_is_not_followed() {
+        let monitor = monitor();
+        let node = MockNode::new([("union", 101)]);
+
+        assert_eq!(check(&monitor, &node, true, 0).await.len(), 1);
+        assert!(monitor.stopped().is_some());
+        assert_eq!(monitor.revision(), 8);
+    }
+
+    #[tokio::test]
+    async fn pinned_revision_is_not_followed() {
+        let monitor = monitor().with_pinned_revision();
+        let node = MockNode::new([("union-testnet-9", 101)]);
+
+        assert_eq!(check(&monitor, &node, true, 0).await, [upgrade()]);
+        assert!(monitor.stopped().is_some());
+        assert_eq!(monitor.revision(), 8);
+    }
+
+    #[tokio::test]
+    async fn checks_are_rate_limited() {
+        let monitor = monitor();
+        let node = MockNode::new([("union-testnet-8", 100)]);
+
+        assert!(check(&monitor, &node, false, 0).await.is_empty());
+        // would panic if the node was queried again
+        assert!(check(&monitor, &node, false, 9).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn halt_is_reported_once_per_halt() {
+        let monitor = monitor();
+        let node = MockNode::new([
+            ("union-testnet-8", 100),
+            ("union-testnet-8", 100),
+            ("union-testnet-8", 100),
+            ("union-testnet-8", 100),
+            ("union-testnet-8", 101),
+            ("union-testnet-8", 101),
+        ]);
+
+        let halted = ModuleData::from(ChainHalted {
+            chain_id: ChainId::new("union-testnet-8"),
+            height: Height::new_with_revision(8, 100),
+            since: 0,
+        });
+
+        assert!(check(&monitor, &node, false, 0).await.is_empty());
+        assert!(check(&monitor, &node, false, 299).await.is_empty());
+        assert_eq!(check(&monitor, &node, false, 300).await, [halted]);
+        assert!(check(&monitor, &node, false, 600).await.is_empty());
+
+        // the chain resumed, and a later halt is reported again
+        assert!(check(&monitor, &node, false, 700).await.is_empty());
+        assert_eq!(
+            check(&monitor, &node, false, 1000).await,
+            [ModuleData::from(ChainHalted {
+                chain_id: ChainId::new("union-testnet-8"),
+                height: Height::new_with_revision(8, 101),
+                since: 700,
+            })]
+        );
+    }
+
+    #[test]
+    fn revision_from_network() {
+        assert_eq!(revision("union-testnet-9"), Some(9));
+        assert_eq!(revision("union"), None);
+        assert_eq!(revision("union-testnet"), None);
+    }
+}
@@ -4,11 +4,15 @@ use macros::model;
 use subset_of::SubsetOf;
 use unionlabs::hash::H256;
 
+use crate::settlement::SettlementEvent;
+
 #[model]
 #[derive(Enumorph, SubsetOf)]
 pub enum ModuleCall {
     FetchGetLogs(FetchGetLogs),
     MakeFullEvent(MakeFullEvent),
+    FetchSettlementLogs(FetchSettlementLogs),
+    MakeLatestHeight(MakeLatestHeight),
 }
 
 /// Fetch all events in `block_number` emitted by the `IBCHandler` via [`eth_getLogs`].
@@ -32,6 +36,26 @@ pub struct MakeFullEvent {
     pub event: IbcEvents,
 }
 
+/// Fetch the events of the rollup contract on the L1 from `from_block` up to the latest finalized
+/// L1 block, and then continue with the next blocks once they are finalized. See
+/// [`crate::settlement`].
+#[model]
+pub struct FetchSettlementLogs {
+    /// If not set, start at the configured start block or the latest finalized L1 block.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from_block: Option<u64>,
+}
+
+/// Resolve the rollup height settled by `event` and emit it as the latest finalized height.
+#[model]
+pub struct MakeLatestHeight {
+    /// The L1 block number that this event was emitted at.
+    pub l1_block_number: u64,
+    /// The timestamp of the L1 block, in seconds.
+    pub l1_timestamp: u64,
+    pub event: SettlementEvent,
+}
+
 #[model]
 pub enum IbcEvents {
     ClientRegistered(Ibc::ClientRegistered),
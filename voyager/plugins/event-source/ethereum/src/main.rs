@@ -18,7 +18,7 @@ use ibc_union_spec::{
 };
 use jsonrpsee::{
     core::{async_trait, RpcResult},
-    types::ErrorObject,
+    types::{ErrorObject, ErrorObjectOwned},
     Extensions,
 };
 use serde::{Deserialize, Serialize};
@@ -26,10 +26,12 @@ use tracing::{debug, info, instrument, trace, warn};
 use unionlabs::{hash::H160, ibc::core::client::height::Height, ErrorReporter};
 use voyager_message::{
     call::Call,
+    cmd::CmdOutput,
     core::{ChainId, ClientInfo, IbcSpec, QueryHeight},
-    data::{ChainEvent, Data},
+    data::{ChainEvent, Data, LatestHeight},
+    error::{channel_connection, union_id},
     into_value,
-    module::{PluginInfo, PluginServer},
+    module::{PluginInfo, PluginKind, PluginServer},
     rpc::missing_state,
     DefaultCmd, ExtensionsExt, Plugin, PluginMessage, VoyagerClient, VoyagerMessage,
     FATAL_JSONRPC_ERROR_CODE,
@@ -37,13 +39,17 @@ use voyager_message::{
 use voyager_vm::{call, conc, data, defer, noop, now, pass::PassResult, seq, BoxDynError, Op};
 
 use crate::{
-    call::{FetchGetLogs, IbcEvents, MakeFullEvent, ModuleCall},
+    call::{
+        FetchGetLogs, FetchSettlementLogs, IbcEvents, MakeFullEvent, MakeLatestHeight, ModuleCall,
+    },
     callback::ModuleCallback,
+    settlement::{Settlement, SettlementConfig, SettlementError, SettlementEvent},
 };
 
 pub mod call;
 pub mod callback;
 pub mod data;
+pub mod settlement;
 
 #[tokio::main(flavor = "multi_thread")]
 async fn main() {
@@ -58,6 +64,9 @@ pub struct Module {
 
     pub provider: RootProvider<BoxTransport>,
     pub beacon_api_client: BeaconApiClient,
+
+    /// Set if this chain is a rollup whose settlement on the L1 is tracked.
+    pub settlement: Option<Settlement>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -73,6 +82,11 @@ pub struct Config {
     pub eth_rpc_api: String,
     /// The RPC endpoint for the beacon chain.
     pub eth_beacon_rpc_api: String,
+
+    /// If this chain is a rollup, track its settlement on the L1 and emit the settled heights as
+    /// the latest finalized heights of the chain. See [`settlement`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub settlement: Option<SettlementConfig>,
 }
 
 impl Plugin for Module {
@@ -93,10 +107,13 @@ impl Plugin for Module {
                 r#"[.. | ."@type"? == "fetch_blocks" and ."@value".chain_id == "{}"] | any"#,
                 config.chain_id
             ),
+            kind: Some(PluginKind::EventSource),
+            chains: vec![config.chain_id.clone()],
+            ibc_specs: vec![IbcUnion::ID],
         }
     }
 
-    async fn cmd(_config: Self::Config, cmd: Self::Cmd) {
+    async fn cmd(_config: Self::Config, cmd: Self::Cmd) -> Result<Box<dyn CmdOutput>, BoxDynError> {
         match cmd {}
     }
 }
@@ -118,16 +135,184 @@ impl Module {
             .await?;
 
         // TODO: Assert chain id is correct
-        let chain_id = provider.get_chain_id().await?;
+        let chain_id = ChainId::new(provider.get_chain_id().await?.to_string());
+
+        let settlement = match config.settlement {
+            Some(settlement) => Some(Settlement::new(settlement, chain_id.clone()).await?),
+            None => None,
+        };
 
         Ok(Self {
-            chain_id: ChainId::new(chain_id.to_string()),
+            chain_id,
             ibc_handler_address: config.ibc_handler_address,
             provider,
             beacon_api_client: BeaconApiClient::new(config.eth_beacon_rpc_api).await?,
+            settlement,
         })
     }
 
+    fn settlement(&self) -> RpcResult<&Settlement> {
+        self.settlement.as_ref().ok_or_else(|| {
+            ErrorObject::owned(
+                FATAL_JSONRPC_ERROR_CODE,
+                "settlement is not configured for this chain",
+                None::<()>,
+            )
+        })
+    }
+
+    async fn fetch_settlement_logs(
+        &self,
+        from_block: Option<u64>,
+    ) -> RpcResult<Op<VoyagerMessage>> {
+        let settlement = self.settlement()?;
+
+        let l1_finalized = settlement
+            .l1_finalized_block()
+            .await
+            .map_err(settlement_error("error fetching the finalized l1 block"))?
+            .number
+            .to::<u64>();
+
+        let from_block = from_block
+            .or(settlement.start_block)
+            .unwrap_or(l1_finalized);
+
+        let fetch_from = |from_block| {
+            call(PluginMessage::new(
+                self.plugin_name(),
+                ModuleCall::from(FetchSettlementLogs {
+                    from_block: Some(from_block),
+                }),
+            ))
+        };
+
+        if from_block > l1_finalized {
+            debug!(from_block, l1_finalized, "l1 block is not yet finalized");
+
+            return Ok(seq([
+                defer(now() + settlement.poll_interval),
+                fetch_from(from_block),
+            ]));
+        }
+
+        let to_block = l1_finalized.min(from_block + settlement.max_block_range - 1);
+
+        debug!(from_block, to_block, "fetching settlement logs");
+
+        let logs = settlement
+            .l1_provider
+            .get_logs(
+                &Filter::new()
+                    .address(settlement.rollup.contract_address())
+                    .from_block(from_block)
+                    .to_block(to_block),
+            )
+            .await
+            .map_err(|e| {
+                ErrorObject::owned(
+                    -1,
+                    format!(
+                        "error fetching settlement logs in blocks {from_block}..={to_block}: {}",
+                        ErrorReporter(e)
+                    ),
+                    None::<()>,
+                )
+            })?;
+
+        // settlement is monotonic, so only the latest finalization in the range is relevant
+        let latest = logs
+            .iter()
+            .filter_map(|log| {
+                match crate::settlement::decode(&settlement.rollup, &log.inner.data) {
+                    Ok(Some(event)) => {
+                        trace!(?event, "found settlement event");
+                        Some((log, event))
+                    }
+                    Ok(None) => None,
+                    Err(e) => {
+                        warn!(
+                            ?log,
+                            "could not decode settlement event: {}",
+                            ErrorReporter(e)
+                        );
+                        None
+                    }
+                }
+            })
+            .filter(|(_, event)| event.is_finalization())
+            .last();
+
+        let make_latest_height = match latest {
+            Some((log, event)) => {
+                let l1_block_number = log.block_number.expect("log should have block_number");
+
+                let l1_timestamp = match log.block_timestamp {
+                    Some(l1_timestamp) => l1_timestamp,
+                    None => settlement
+                        .l1_block(l1_block_number)
+                        .await
+                        .map_err(settlement_error("error fetching l1 block"))?
+                        .timestamp
+                        .to(),
+                };
+
+                Some(call(PluginMessage::new(
+                    self.plugin_name(),
+                    ModuleCall::from(MakeLatestHeight {
+                        l1_block_number,
+                        l1_timestamp,
+                        event,
+                    }),
+                )))
+            }
+            None => None,
+        };
+
+        let next_fetch = if to_block == l1_finalized {
+            seq([
+                defer(now() + settlement.poll_interval),
+                fetch_from(to_block + 1),
+            ])
+        } else {
+            fetch_from(to_block + 1)
+        };
+
+        Ok(conc(make_latest_height.into_iter().chain([next_fetch])))
+    }
+
+    async fn make_latest_height(
+        &self,
+        l1_block_number: u64,
+        l1_timestamp: u64,
+        event: SettlementEvent,
+    ) -> RpcResult<Op<VoyagerMessage>> {
+        let settlement = self.settlement()?;
+
+        let height = settlement
+            .l2_height(&self.provider, &event)
+            .await
+            .map_err(settlement_error("error fetching the settled height"))?;
+
+        // the settlement of a later l1 block may have been processed first
+        if !settlement
+            .finality
+            .settle(height, l1_timestamp * 1_000_000_000)
+        {
+            debug!(height, l1_block_number, "height is already settled");
+
+            return Ok(noop());
+        }
+
+        info!(height, l1_block_number, "settled");
+
+        Ok(data(LatestHeight {
+            chain_id: settlement.l2_chain_id.clone(),
+            height: Height::new(height),
+            finalized: true,
+        }))
+    }
+
     async fn make_packet_metadata(
         &self,
         event_height: Height,
@@ -161,16 +346,14 @@ impl Module {
             .state
             .ok_or_else(missing_state("connection must exist", None))?;
 
+        let self_client_id = union_id(self_connection_state.client_id)?;
+
         let client_info = voyager_rpc_client
-            .client_info::<IbcUnion>(self.chain_id.clone(), self_connection_state.client_id)
+            .client_info::<IbcUnion>(self.chain_id.clone(), self_client_id)
             .await?;
 
         let client_meta = voyager_rpc_client
-            .client_meta::<IbcUnion>(
-                self.chain_id.clone(),
-                event_height.into(),
-                self_connection_state.client_id,
-            )
+            .client_meta::<IbcUnion>(self.chain_id.clone(), event_height.into(), self_client_id)
             .await?;
 
         let other_channel_id = self_channel.counterparty_channel_id;
@@ -190,19 +373,19 @@ impl Module {
             .ok_or_else(missing_state("channel must exist", None))?;
 
         let source_channel = ChannelMetadata {
-            channel_id: self_channel_id,
+            channel_id: union_id(self_channel_id)?,
             version: self_channel.version,
             connection: ConnectionMetadata {
-                client_id: self_connection_state.client_id,
-                connection_id: self_connection_id,
+                client_id: self_client_id,
+                connection_id: union_id(self_connection_id)?,
             },
         };
         let destination_channel = ChannelMetadata {
-            channel_id: other_channel_id,
+            channel_id: union_id(other_channel_id)?,
             version: other_channel_state.version,
             connection: ConnectionMetadata {
-                client_id: self_connection_state.counterparty_client_id,
-                connection_id: self_connection_state.counterparty_connection_id,
+                client_id: union_id(self_connection_state.counterparty_client_id)?,
+                connection_id: union_id(self_connection_state.counterparty_connection_id)?,
             },
         };
 
@@ -215,6 +398,10 @@ impl Module {
     }
 }
 
+fn settlement_error(message: &'static str) -> impl Fn(SettlementError) -> ErrorObjectOwned {
+    move |e| ErrorObject::owned(-1, format!("{message}: {}", ErrorReporter(e)), None::<()>)
+}
+
 #[async_trait]
 impl PluginServer<ModuleCall, ModuleCallback> for Module {
     #[instrument(skip_all, fields(chain_id = %self.chain_id))]
@@ -229,13 +416,26 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
                 .into_iter()
                 .map(|op| match op {
                     Op::Call(Call::FetchBlocks(fetch)) if fetch.chain_id == self.chain_id => {
-                        call(PluginMessage::new(
+                        let fetch_get_logs = call(PluginMessage::new(
                             self.plugin_name(),
                             ModuleCall::from(FetchGetLogs {
                                 block_number: fetch.start_height.height(),
                                 up_to: None,
                             }),
-                        ))
+                        ));
+
+                        // settlement is watched alongside the blocks of the chain
+                        if self.settlement.is_some() {
+                            conc([
+                                fetch_get_logs,
+                                call(PluginMessage::new(
+                                    self.plugin_name(),
+                                    ModuleCall::from(FetchSettlementLogs { from_block: None }),
+                                )),
+                            ])
+                        } else {
+                            fetch_get_logs
+                        }
                     }
                     op => op,
                 })
@@ -268,15 +468,17 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
 
                 match event {
                     IbcEvents::ClientCreated(raw_event) => {
+                        let client_id = union_id(raw_event.client_id)?;
+
                         let client_info = voyager_client
-                            .client_info::<IbcUnion>(self.chain_id.clone(), raw_event.client_id)
+                            .client_info::<IbcUnion>(self.chain_id.clone(), client_id)
                             .await?;
 
                         let client_meta = voyager_client
                             .client_meta::<IbcUnion>(
                                 self.chain_id.clone(),
                                 provable_height.into(),
-                                raw_event.client_id,
+                                client_id,
                             )
                             .await?;
 
@@ -289,11 +491,13 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
                             ibc_spec_id: IbcUnion::ID,
                             event: into_value::<FullEvent>(
                                 CreateClient {
-                                    client_id: raw_event.client_id,
+                                    client_id,
                                     client_type: client_info.client_type,
                                 }
                                 .into(),
                             ),
+                            raw_events: None,
+                            denom_trace: None,
                         }))
                     }
                     IbcEvents::ClientRegistered(raw_event) => {
@@ -302,15 +506,17 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
                         Ok(noop())
                     }
                     IbcEvents::ClientUpdated(raw_event) => {
+                        let client_id = union_id(raw_event.client_id)?;
+
                         let client_info = voyager_client
-                            .client_info::<IbcUnion>(self.chain_id.clone(), raw_event.client_id)
+                            .client_info::<IbcUnion>(self.chain_id.clone(), client_id)
                             .await?;
 
                         let client_meta = voyager_client
                             .client_meta::<IbcUnion>(
                                 self.chain_id.clone(),
                                 provable_height.into(),
-                                raw_event.client_id,
+                                client_id,
                             )
                             .await?;
 
@@ -324,24 +530,28 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
                             event: into_value::<FullEvent>(
                                 UpdateClient {
                                     client_type: client_info.client_type,
-                                    client_id: raw_event.client_id,
+                                    client_id,
                                     height: raw_event.height,
                                 }
                                 .into(),
                             ),
+                            raw_events: None,
+                            denom_trace: None,
                         }))
                     }
 
                     IbcEvents::ConnectionOpenInit(raw_event) => {
+                        let client_id = union_id(raw_event.client_id)?;
+
                         let client_info = voyager_client
-                            .client_info::<IbcUnion>(self.chain_id.clone(), raw_event.client_id)
+                            .client_info::<IbcUnion>(self.chain_id.clone(), client_id)
                             .await?;
 
                         let client_meta = voyager_client
                             .client_meta::<IbcUnion>(
                                 self.chain_id.clone(),
                                 provable_height.into(),
-                                raw_event.client_id,
+                                client_id,
                             )
                             .await?;
 
@@ -354,24 +564,30 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
                             provable_height,
                             event: into_value::<FullEvent>(
                                 ConnectionOpenInit {
-                                    client_id: raw_event.client_id,
-                                    connection_id: raw_event.connection_id,
-                                    counterparty_client_id: raw_event.counterparty_client_id,
+                                    client_id,
+                                    connection_id: union_id(raw_event.connection_id)?,
+                                    counterparty_client_id: union_id(
+                                        raw_event.counterparty_client_id,
+                                    )?,
                                 }
                                 .into(),
                             ),
+                            raw_events: None,
+                            denom_trace: None,
                         }))
                     }
                     IbcEvents::ConnectionOpenTry(raw_event) => {
+                        let client_id = union_id(raw_event.client_id)?;
+
                         let client_info = voyager_client
-                            .client_info::<IbcUnion>(self.chain_id.clone(), raw_event.client_id)
+                            .client_info::<IbcUnion>(self.chain_id.clone(), client_id)
                             .await?;
 
                         let client_meta = voyager_client
                             .client_meta::<IbcUnion>(
                                 self.chain_id.clone(),
                                 provable_height.into(),
-                                raw_event.client_id,
+                                client_id,
                             )
                             .await?;
 
@@ -384,26 +600,33 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
                             ibc_spec_id: IbcUnion::ID,
                             event: into_value::<FullEvent>(
                                 ConnectionOpenTry {
-                                    client_id: raw_event.client_id,
-                                    connection_id: raw_event.connection_id,
-                                    counterparty_client_id: raw_event.counterparty_client_id,
-                                    counterparty_connection_id: raw_event
-                                        .counterparty_connection_id,
+                                    client_id,
+                                    connection_id: union_id(raw_event.connection_id)?,
+                                    counterparty_client_id: union_id(
+                                        raw_event.counterparty_client_id,
+                                    )?,
+                                    counterparty_connection_id: union_id(
+                                        raw_event.counterparty_connection_id,
+                                    )?,
                                 }
                                 .into(),
                             ),
+                            raw_events: None,
+                            denom_trace: None,
                         }))
                     }
                     IbcEvents::ConnectionOpenAck(raw_event) => {
+                        let client_id = union_id(raw_event.client_id)?;
+
                         let client_info = voyager_client
-                            .client_info::<IbcUnion>(self.chain_id.clone(), raw_event.client_id)
+                            .client_info::<IbcUnion>(self.chain_id.clone(), client_id)
                             .await?;
 
                         let client_meta = voyager_client
                             .client_meta::<IbcUnion>(
                                 self.chain_id.clone(),
                                 provable_height.into(),
-                                raw_event.client_id,
+                                client_id,
                             )
                             .await?;
 
@@ -416,26 +639,33 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
                             ibc_spec_id: IbcUnion::ID,
                             event: into_value::<FullEvent>(
                                 ConnectionOpenAck {
-                                    client_id: raw_event.client_id,
-                                    connection_id: raw_event.connection_id,
-                                    counterparty_client_id: raw_event.counterparty_client_id,
-                                    counterparty_connection_id: raw_event
-                                        .counterparty_connection_id,
+                                    client_id,
+                                    connection_id: union_id(raw_event.connection_id)?,
+                                    counterparty_client_id: union_id(
+                                        raw_event.counterparty_client_id,
+                                    )?,
+                                    counterparty_connection_id: union_id(
+                                        raw_event.counterparty_connection_id,
+                                    )?,
                                 }
                                 .into(),
                             ),
+                            raw_events: None,
+                            denom_trace: None,
                         }))
                     }
                     IbcEvents::ConnectionOpenConfirm(raw_event) => {
+                        let client_id = union_id(raw_event.client_id)?;
+
                         let client_info = voyager_client
-                            .client_info::<IbcUnion>(self.chain_id.clone(), raw_event.client_id)
+                            .client_info::<IbcUnion>(self.chain_id.clone(), client_id)
                             .await?;
 
                         let client_meta = voyager_client
                             .client_meta::<IbcUnion>(
                                 self.chain_id.clone(),
                                 provable_height.into(),
-                                raw_event.client_id,
+                                client_id,
                             )
                             .await?;
 
@@ -448,13 +678,19 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
                             ibc_spec_id: IbcUnion::ID,
                             event: into_value::<FullEvent>(
                                 ConnectionOpenConfirm {
-                                    client_id: raw_event.client_id,
-                                    connection_id: raw_event.connection_id,
-                                    counterparty_client_id: raw_event.counterparty_client_id,
-                                    counterparty_connection_id: raw_event.counterparty_client_id,
+                                    client_id,
+                                    connection_id: union_id(raw_event.connection_id)?,
+                                    counterparty_client_id: union_id(
+                                        raw_event.counterparty_client_id,
+                                    )?,
+                                    counterparty_connection_id: union_id(
+                                        raw_event.counterparty_client_id,
+                                    )?,
                                 }
                                 .into(),
                             ),
+                            raw_events: None,
+                            denom_trace: None,
                         }))
                     }
                     IbcEvents::ChannelOpenInit(raw_event) => {
@@ -470,15 +706,17 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
                             .state
                             .ok_or_else(missing_state("connection must exist", None))?;
 
+                        let client_id = union_id(connection.client_id)?;
+
                         let client_info = voyager_client
-                            .client_info::<IbcUnion>(self.chain_id.clone(), connection.client_id)
+                            .client_info::<IbcUnion>(self.chain_id.clone(), client_id)
                             .await?;
 
                         let client_meta = voyager_client
                             .client_meta::<IbcUnion>(
                                 self.chain_id.clone(),
                                 provable_height.into(),
-                                connection.client_id,
+                                client_id,
                             )
                             .await?;
 
@@ -504,13 +742,15 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
                             event: into_value::<FullEvent>(
                                 ChannelOpenInit {
                                     port_id: raw_event.port_id.into(),
-                                    channel_id,
+                                    channel_id: union_id(channel_id)?,
                                     counterparty_port_id: raw_event.counterparty_port_id.into(),
-                                    connection,
+                                    connection: channel_connection(connection)?,
                                     version: channel.version,
                                 }
                                 .into(),
                             ),
+                            raw_events: None,
+                            denom_trace: None,
                         }))
                     }
                     IbcEvents::ChannelOpenTry(raw_event) => {
@@ -526,15 +766,17 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
                             .state
                             .ok_or_else(missing_state("connection must exist", None))?;
 
+                        let client_id = union_id(connection.client_id)?;
+
                         let client_info = voyager_client
-                            .client_info::<IbcUnion>(self.chain_id.clone(), connection.client_id)
+                            .client_info::<IbcUnion>(self.chain_id.clone(), client_id)
                             .await?;
 
                         let client_meta = voyager_client
                             .client_meta::<IbcUnion>(
                                 self.chain_id.clone(),
                                 provable_height.into(),
-                                connection.client_id,
+                                client_id,
                             )
                             .await?;
 
@@ -560,14 +802,18 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
                             event: into_value::<FullEvent>(
                                 ChannelOpenTry {
                                     port_id: raw_event.port_id.into(),
-                                    channel_id,
+                                    channel_id: union_id(channel_id)?,
                                     counterparty_port_id: raw_event.counterparty_port_id.into(),
-                                    counterparty_channel_id: raw_event.counterparty_channel_id,
-                                    connection,
+                                    counterparty_channel_id: union_id(
+                                        raw_event.counterparty_channel_id,
+                                    )?,
+                                    connection: channel_connection(connection)?,
                                     version: channel.version,
                                 }
                                 .into(),
                             ),
+                            raw_events: None,
+                            denom_trace: None,
                         }))
                     }
                     IbcEvents::ChannelOpenAck(raw_event) => {
@@ -583,15 +829,17 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
                             .state
                             .ok_or_else(missing_state("connection must exist", None))?;
 
+                        let client_id = union_id(connection.client_id)?;
+
                         let client_info = voyager_client
-                            .client_info::<IbcUnion>(self.chain_id.clone(), connection.client_id)
+                            .client_info::<IbcUnion>(self.chain_id.clone(), client_id)
                             .await?;
 
                         let client_meta = voyager_client
                             .client_meta::<IbcUnion>(
                                 self.chain_id.clone(),
                                 provable_height.into(),
-                                connection.client_id,
+                                client_id,
                             )
                             .await?;
 
@@ -617,14 +865,18 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
                             event: into_value::<FullEvent>(
                                 ChannelOpenAck {
                                     port_id: raw_event.port_id.into(),
-                                    channel_id,
+                                    channel_id: union_id(channel_id)?,
                                     counterparty_port_id: raw_event.counterparty_port_id.into(),
-                                    counterparty_channel_id: raw_event.counterparty_channel_id,
-                                    connection,
+                                    counterparty_channel_id: union_id(
+                                        raw_event.counterparty_channel_id,
+                                    )?,
+                                    connection: channel_connection(connection)?,
                                     version: channel.version,
                                 }
                                 .into(),
                             ),
+                            raw_events: None,
+                            denom_trace: None,
                         }))
                     }
                     IbcEvents::ChannelOpenConfirm(raw_event) => {
@@ -640,15 +892,17 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
                             .state
                             .ok_or_else(missing_state("connection must exist", None))?;
 
+                        let client_id = union_id(connection.client_id)?;
+
                         let client_info = voyager_client
-                            .client_info::<IbcUnion>(self.chain_id.clone(), connection.client_id)
+                            .client_info::<IbcUnion>(self.chain_id.clone(), client_id)
                             .await?;
 
                         let client_meta = voyager_client
                             .client_meta::<IbcUnion>(
                                 self.chain_id.clone(),
                                 provable_height.into(),
-                                connection.client_id,
+                                client_id,
                             )
                             .await?;
 
@@ -674,14 +928,18 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
                             event: into_value::<FullEvent>(
                                 ChannelOpenConfirm {
                                     port_id: raw_event.port_id.into(),
-                                    channel_id,
+                                    channel_id: union_id(channel_id)?,
                                     counterparty_port_id: channel.counterparty_port_id.into(),
-                                    counterparty_channel_id: channel.counterparty_channel_id,
-                                    connection,
+                                    counterparty_channel_id: union_id(
+                                        channel.counterparty_channel_id,
+                                    )?,
+                                    connection: channel_connection(connection)?,
                                     version: channel.version,
                                 }
                                 .into(),
                             ),
+                            raw_events: None,
+                            denom_trace: None,
                         }))
                     }
 
@@ -722,9 +980,12 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
                                         timeout_height: event.packet.timeout_height,
                                         timeout_timestamp: event.packet.timeout_timestamp,
                                     },
+                                    batch: None,
                                 }
                                 .into(),
                             ),
+                            raw_events: None,
+                            denom_trace: None,
                         }))
                     }
                     IbcEvents::TimeoutPacket(event) => {
@@ -760,6 +1021,8 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
                                 }
                                 .into(),
                             ),
+                            raw_events: None,
+                            denom_trace: None,
                         }))
                     }
                     IbcEvents::AcknowledgePacket(event) => {
@@ -796,6 +1059,8 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
                                 }
                                 .into(),
                             ),
+                            raw_events: None,
+                            denom_trace: None,
                         }))
                     }
                     // packet origin is the counterparty chain
@@ -833,6 +1098,8 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
                                 }
                                 .into(),
                             ),
+                            raw_events: None,
+                            denom_trace: None,
                         }))
                     }
                     IbcEvents::RecvPacket(event) => {
@@ -869,6 +1136,8 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
                                 }
                                 .into(),
                             ),
+                            raw_events: None,
+                            denom_trace: None,
                         }))
                     }
                     IbcEvents::RecvIntentPacket(_event) => {
@@ -1049,6 +1318,17 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
 
                 Ok(conc(next_fetch.into_iter().chain(events)))
             }
+            ModuleCall::FetchSettlementLogs(FetchSettlementLogs { from_block }) => {
+                self.fetch_settlement_logs(from_block).await
+            }
+            ModuleCall::MakeLatestHeight(MakeLatestHeight {
+                l1_block_number,
+                l1_timestamp,
+                event,
+            }) => {
+                self.make_latest_height(l1_block_number, l1_timestamp, event)
+                    .await
+            }
         }
     }
 }
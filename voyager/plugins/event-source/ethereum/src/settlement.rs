@@ -0,0 +1,498 @@
+//! Tracking of the finality of rollups by their settlement on the L1.
+//!
+//! The head of a rollup is not final until the batch (scroll) or assertion (arbitrum) containing
+//! it has been finalized by the rollup contract on the L1, so client updates and timeout checks
+//! for rollups are scheduled off of the settled height instead. The rollup contract is watched
+//! for finalization events, each of which is mapped to the height of the latest rollup block that
+//! it settles and emitted as [`LatestHeight`](voyager_message::data::LatestHeight) data.
+//!
+//! Only logs in finalized L1 blocks are read, such that a settlement can't be reorged out after
+//! its height has been emitted.
+
+use alloy::{
+    primitives::{Address, LogData, B256, U256, U64},
+    providers::{Provider, ProviderBuilder, RootProvider},
+    sol_types::SolEvent,
+    transports::{BoxTransport, TransportError},
+};
+use macros::model;
+use scroll_api::ScrollClient;
+use serde::{Deserialize, Serialize};
+use unionlabs::hash::{H160, H256};
+use voyager_message::{
+    core::ChainId,
+    finality::{SettlementFinalityTracker, DEFAULT_BLOCK_TIME_WINDOW},
+};
+use voyager_vm::BoxDynError;
+
+alloy::sol! {
+    /// The scroll rollup contract, `ScrollChain`.
+    interface ScrollChain {
+        event CommitBatch(uint256 indexed batchIndex, bytes32 indexed batchHash);
+        event FinalizeBatch(
+            uint256 indexed batchIndex,
+            bytes32 indexed batchHash,
+            bytes32 stateRoot,
+            bytes32 withdrawRoot
+        );
+    }
+
+    /// The arbitrum rollup contract, `RollupCore` before BOLD and `RollupUserLogic` after.
+    interface ArbitrumRollup {
+        event NodeConfirmed(uint64 indexed nodeNum, bytes32 blockHash, bytes32 sendRoot);
+        event AssertionConfirmed(bytes32 indexed assertionHash, bytes32 blockHash, bytes32 sendRoot);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SettlementConfig {
+    /// The RPC endpoint of the L1 that the rollup settles on.
+    pub l1_rpc_api: String,
+    pub rollup: Rollup,
+    /// The chain id that the settled heights are emitted for. Defaults to the chain id of this
+    /// plugin.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub l2_chain_id: Option<ChainId>,
+    /// The L1 block to start watching the rollup contract from. Defaults to the latest finalized
+    /// L1 block.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_block: Option<u64>,
+    /// The maximum amount of L1 blocks to fetch the logs of at once.
+    #[serde(default = "default_max_block_range")]
+    pub max_block_range: u64,
+    /// How long to wait for the L1 to finalize more blocks, in seconds.
+    #[serde(default = "default_poll_interval")]
+    pub poll_interval: u64,
+}
+
+const fn default_max_block_range() -> u64 {
+    1_000
+}
+
+const fn default_poll_interval() -> u64 {
+    12
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Rollup {
+    Scroll {
+        /// The address of the `ScrollChain` contract.
+        rollup_contract_address: H160,
+        /// The scroll API, used to look up the blocks of a batch.
+        scroll_api: String,
+    },
+    Arbitrum {
+        /// The address of the rollup contract.
+        rollup_contract_address: H160,
+    },
+}
+
+impl Rollup {
+    pub fn contract_address(&self) -> Address {
+        match self {
+            Rollup::Scroll {
+                rollup_contract_address,
+                ..
+            }
+            | Rollup::Arbitrum {
+                rollup_contract_address,
+            } => (*rollup_contract_address).into(),
+        }
+    }
+}
+
+/// A decoded event of a rollup contract.
+#[model]
+pub enum SettlementEvent {
+    /// A scroll batch was committed. The batch can still be reverted until it is finalized.
+    ScrollCommitBatch { batch_index: u64, batch_hash: H256 },
+    ScrollFinalizeBatch {
+        batch_index: u64,
+        batch_hash: H256,
+        state_root: H256,
+    },
+    /// An arbitrum node was confirmed by the legacy rollup contract.
+    ArbitrumNodeConfirmed { node_num: u64, block_hash: H256 },
+    /// An arbitrum assertion was confirmed by the BOLD rollup contract.
+    ArbitrumAssertionConfirmed {
+        assertion_hash: H256,
+        block_hash: H256,
+    },
+}
+
+impl SettlementEvent {
+    /// Whether this event settles rollup blocks.
+    pub fn is_finalization(&self) -> bool {
+        !matches!(self, SettlementEvent::ScrollCommitBatch { .. })
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum DecodeError {
+    #[error("invalid log")]
+    Abi(#[from] alloy::sol_types::Error),
+    #[error("batch index {0} does not fit in a u64")]
+    BatchIndexOverflow(U256),
+}
+
+/// Decode a log of the contract of `rollup`. Events that are not relevant for settlement are
+/// ignored.
+pub fn decode(rollup: &Rollup, log: &LogData) -> Result<Option<SettlementEvent>, DecodeError> {
+    let Some(selector) = log.topics().first() else {
+        return Ok(None);
+    };
+
+    let batch_index = |batch_index: U256| {
+        u64::try_from(batch_index).map_err(|_| DecodeError::BatchIndexOverflow(batch_index))
+    };
+
+    let event = match rollup {
+        Rollup::Scroll { .. } if *selector == ScrollChain::CommitBatch::SIGNATURE_HASH => {
+            let event = ScrollChain::CommitBatch::decode_log_data(log, true)?;
+
+            SettlementEvent::ScrollCommitBatch {
+                batch_index: batch_index(event.batchIndex)?,
+                batch_hash: event.batchHash.into(),
+            }
+        }
+        Rollup::Scroll { .. } if *selector == ScrollChain::FinalizeBatch::SIGNATURE_HASH => {
+            let event = ScrollChain::FinalizeBatch::decode_log_data(log, true)?;
+
+            SettlementEvent::ScrollFinalizeBatch {
+                batch_index: batch_index(event.batchIndex)?,
+                batch_hash: event.batchHash.into(),
+                state_root: event.stateRoot.into(),
+            }
+        }
+        Rollup::Arbitrum { .. } if *selector == ArbitrumRollup::NodeConfirmed::SIGNATURE_HASH => {
+            let event = ArbitrumRollup::NodeConfirmed::decode_log_data(log, true)?;
+
+            SettlementEvent::ArbitrumNodeConfirmed {
+                node_num: event.nodeNum,
+                block_hash: event.blockHash.into(),
+            }
+        }
+        Rollup::Arbitrum { .. }
+            if *selector == ArbitrumRollup::AssertionConfirmed::SIGNATURE_HASH =>
+        {
+            let event = ArbitrumRollup::AssertionConfirmed::decode_log_data(log, true)?;
+
+            SettlementEvent::ArbitrumAssertionConfirmed {
+                assertion_hash: event.assertionHash.into(),
+                block_hash: event.blockHash.into(),
+            }
+        }
+        _ => return Ok(None),
+    };
+
+    Ok(Some(event))
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SettlementError {
+    #[error("error querying the rpc")]
+    Transport(#[from] TransportError),
+    #[error("block {0} not found")]
+    BlockNotFound(String),
+}
+
+/// The header fields of a block that are required here, as returned by `eth_getBlockBy*`.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct BlockHeader {
+    pub number: U64,
+    pub timestamp: U64,
+}
+
+#[derive(Debug, Clone)]
+pub struct Settlement {
+    pub l1_provider: RootProvider<BoxTransport>,
+    pub rollup: Rollup,
+    pub scroll_api_client: Option<ScrollClient>,
+    pub l2_chain_id: ChainId,
+    pub start_block: Option<u64>,
+    pub max_block_range: u64,
+    pub poll_interval: u64,
+    pub finality: SettlementFinalityTracker,
+}
+
+impl Settlement {
+    pub async fn new(config: SettlementConfig, chain_id: ChainId) -> Result<Self, BoxDynError> {
+        let l1_provider = ProviderBuilder::new()
+            .on_builtin(&config.l1_rpc_api)
+            .await?;
+
+        Ok(Self {
+            l1_provider,
+            scroll_api_client: match &config.rollup {
+                Rollup::Scroll { scroll_api, .. } => Some(ScrollClient::new(scroll_api)),
+                Rollup::Arbitrum { .. } => None,
+            },
+            rollup: config.rollup,
+            l2_chain_id: config.l2_chain_id.unwrap_or(chain_id),
+            start_block: config.start_block,
+            max_block_range: config.max_block_range.max(1),
+            poll_interval: config.poll_interval,
+            finality: SettlementFinalityTracker::new(0, DEFAULT_BLOCK_TIME_WINDOW),
+        })
+    }
+
+    /// The latest finalized block of the L1.
+    pub async fn l1_finalized_block(&self) -> Result<BlockHeader, SettlementError> {
+        self.l1_provider
+            .raw_request::<_, Option<BlockHeader>>(
+                "eth_getBlockByNumber".into(),
+                ("finalized", false),
+            )
+            .await?
+            .ok_or_else(|| SettlementError::BlockNotFound("finalized".to_owned()))
+    }
+
+    pub async fn l1_block(&self, number: u64) -> Result<BlockHeader, SettlementError> {
+        self.l1_provider
+            .raw_request::<_, Option<BlockHeader>>(
+                "eth_getBlockByNumber".into(),
+                (U64::from(number), false),
+            )
+            .await?
+            .ok_or_else(|| SettlementError::BlockNotFound(number.to_string()))
+    }
+
+    /// The height of the latest rollup block that is settled by `event`. `l2_provider` is the
+    /// provider of the rollup itself.
+    pub async fn l2_height(
+        &self,
+        l2_provider: &RootProvider<BoxTransport>,
+        event: &SettlementEvent,
+    ) -> Result<u64, SettlementError> {
+        match event {
+            SettlementEvent::ScrollCommitBatch { batch_index, .. }
+            | SettlementEvent::ScrollFinalizeBatch { batch_index, .. } => Ok(self
+                .scroll_api_client
+                .as_ref()
+                .expect("scroll events are only decoded for scroll rollups; qed;")
+                .batch(*batch_index)
+                .await
+                .batch
+                .end_block_number),
+            SettlementEvent::ArbitrumNodeConfirmed { block_hash, .. }
+            | SettlementEvent::ArbitrumAssertionConfirmed { block_hash, .. } => Ok(l2_provider
+                .raw_request::<_, Option<BlockHeader>>(
+                    "eth_getBlockByHash".into(),
+                    (B256::from(*block_hash.get()), false),
+                )
+                .await?
+                .ok_or_else(|| SettlementError::BlockNotFound(block_hash.to_string()))?
+                .number
+                .to()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::{
+        primitives::{b256, Bytes},
+        rpc::types::Log,
+    };
+
+    use super::*;
+
+    fn scroll() -> Rollup {
+        Rollup::Scroll {
+            rollup_contract_address: alloy::primitives::address!(
+                "a13baf47339d63b743e7da8741db5456dac1e556"
+            )
+            .into(),
+            scroll_api: "https://mainnet-api-re.scroll.io".to_owned(),
+        }
+    }
+
+    fn arbitrum() -> Rollup {
+        Rollup::Arbitrum {
+            rollup_contract_address: alloy::primitives::address!(
+                "5ef0d09d1e6204141b4d37530808ed19f60fba35"
+            )
+            .into(),
+        }
+    }
+
+    fn fixture(json: &str) -> Log {
+        serde_json::from_str(json).expect("valid fixture")
+    }
+
+    fn decode_fixture(rollup: &Rollup, json: &str) -> Option<SettlementEvent> {
+        let log = fixture(json);
+
+        assert_eq!(log.inner.address, rollup.contract_address());
+
+        decode(rollup, &log.inner.data).expect("fixture decodes")
+    }
+
+    #[test]
+    fn scroll_commit_batch() {
+        let event = decode_fixture(
+            &scroll(),
+            include_str!("../testdata/settlement/scroll-commit_batch.json"),
+        )
+        .unwrap();
+
+        assert_eq!(
+            event,
+            SettlementEvent::ScrollCommitBatch {
+                batch_index: 318_231,
+                batch_hash: b256!(
+                    "9064d304908cc571c2a577617e00849036fc5071de3e36d9b15f9f6cf2a53934"
+                )
+                .into(),
+            }
+        );
+        assert!(!event.is_finalization());
+    }
+
+    #[test]
+    fn scroll_finalize_batch() {
+        let event = decode_fixture(
+            &scroll(),
+            include_str!("../testdata/settlement/scroll-finalize_batch.json"),
+        )
+        .unwrap();
+
+        assert_eq!(
+            event,
+            SettlementEvent::ScrollFinalizeBatch {
+                batch_index: 318_230,
+                batch_hash: b256!(
+                    "f77677ecff2672909d9b816712e4e885a68081763c72f6550e76e347c9e051c9"
+                )
+                .into(),
+                state_root: b256!(
+                    "cf6f6a3678bb95f7571bc43b56559cd10081d3668af7a5be1a66214526f75d93"
+                )
+                .into(),
+            }
+        );
+        assert!(event.is_finalization());
+    }
+
+    #[test]
+    fn arbitrum_node_confirmed() {
+        let event = decode_fixture(
+            &arbitrum(),
+            include_str!("../testdata/settlement/arbitrum-node_confirmed.json"),
+        )
+        .unwrap();
+
+        assert_eq!(
+            event,
+            SettlementEvent::ArbitrumNodeConfirmed {
+                node_num: 15_012,
+                block_hash: b256!(
+                    "546566315877094ba1f45af4dcfdb69e8934f726d0ff79e47efcf13a9a768d6e"
+                )
+                .into(),
+            }
+        );
+        assert!(event.is_finalization());
+    }
+
+    #[test]
+    fn arbitrum_assertion_confirmed() {
+        let event = decode_fixture(
+            &arbitrum(),
+            include_str!("../testdata/settlement/arbitrum-assertion_confirmed.json"),
+        )
+        .unwrap();
+
+        assert_eq!(
+            event,
+            SettlementEvent::ArbitrumAssertionConfirmed {
+                assertion_hash: b256!(
+                    "0af1496c3772b40201acf64b5cd8012cdc565a4cb97e364b9e893f0d04c779f8"
+                )
+                .into(),
+                block_hash: b256!(
+                    "a243d235c515908d0984ad19db87ee1713147c9e4f44adff9c814d0345f2dbe1"
+                )
+                .into(),
+            }
+        );
+        assert!(event.is_finalization());
+    }
+
+    #[test]
+    fn unrelated_events_are_ignored() {
+        // RevertBatch(uint256,bytes32)
+        assert_eq!(
+            decode_fixture(
+                &scroll(),
+                include_str!("../testdata/settlement/scroll-revert_batch.json"),
+            ),
+            None
+        );
+
+        // events are only decoded for the configured rollup
+        let log = fixture(include_str!(
+            "../testdata/settlement/scroll-finalize_batch.json"
+        ));
+        assert_eq!(decode(&arbitrum(), &log.inner.data).unwrap(), None);
+
+        let log = fixture(include_str!(
+            "../testdata/settlement/arbitrum-node_confirmed.json"
+        ));
+        assert_eq!(decode(&scroll(), &log.inner.data).unwrap(), None);
+
+        assert_eq!(decode(&scroll(), &LogData::default()).unwrap(), None);
+    }
+
+    #[test]
+    fn malformed_event() {
+        let mut log = fixture(include_str!(
+            "../testdata/settlement/scroll-finalize_batch.json"
+        ));
+
+        // drop the withdraw root
+        let (topics, data) = log.inner.data.split();
+        log.inner.data = LogData::new_unchecked(topics, Bytes::copy_from_slice(&data[..32]));
+
+        assert!(matches!(
+            decode(&scroll(), &log.inner.data),
+            Err(DecodeError::Abi(_))
+        ));
+    }
+
+    #[test]
+    fn batch_index_overflow() {
+        let mut log = fixture(include_str!(
+            "../testdata/settlement/scroll-finalize_batch.json"
+        ));
+
+        let (mut topics, data) = log.inner.data.split();
+        topics[1] = B256::repeat_byte(0xff);
+        log.inner.data = LogData::new_unchecked(topics, data);
+
+        assert!(matches!(
+            decode(&scroll(), &log.inner.data),
+            Err(DecodeError::BatchIndexOverflow(_))
+        ));
+    }
+
+    #[test]
+    fn config() {
+        let config = serde_json::from_value::<SettlementConfig>(serde_json::json!({
+            "l1_rpc_api": "https://eth.example.com",
+            "rollup": {
+                "type": "arbitrum",
+                "rollup_contract_address": "0x5ef0d09d1e6204141b4d37530808ed19f60fba35"
+            },
+            "l2_chain_id": "42161"
+        }))
+        .unwrap();
+
+        assert_eq!(config.rollup, arbitrum());
+        assert_eq!(config.l2_chain_id, Some(ChainId::new("42161")));
+        assert_eq!(config.max_block_range, default_max_block_range());
+        assert_eq!(config.poll_interval, default_poll_interval());
+    }
+}
@@ -13,7 +13,7 @@ use ibc_union_spec::{
     AcknowledgePacket, ChannelMetadata, ChannelOpenAck, ChannelOpenConfirm, ChannelOpenInit,
     ChannelOpenTry, ChannelPath, ConnectionMetadata, ConnectionOpenAck, ConnectionOpenConfirm,
     ConnectionOpenInit, ConnectionOpenTry, ConnectionPath, CreateClient, FullEvent, IbcUnion,
-    PacketMetadata, RecvPacket, SendPacket, UpdateClient, WriteAcknowledgement,
+    PacketMetadata, RecvPacket, SendPacket, UnionId, UpdateClient, WriteAcknowledgement,
 };
 use jsonrpsee::{
     core::{async_trait, RpcResult},
@@ -25,10 +25,12 @@ use tracing::{debug, info, instrument};
 use unionlabs::{hash::H256, ibc::core::client::height::Height, ErrorReporter};
 use voyager_message::{
     call::Call,
+    cmd::CmdOutput,
     core::{ChainId, ClientInfo, ClientType, IbcSpec, QueryHeight},
     data::{ChainEvent, Data},
+    error::{channel_connection, union_id},
     into_value,
-    module::{PluginInfo, PluginServer},
+    module::{PluginInfo, PluginKind, PluginServer},
     rpc::missing_state,
     DefaultCmd, ExtensionsExt, Plugin, PluginMessage, VoyagerClient, VoyagerMessage,
 };
@@ -95,10 +97,13 @@ impl Plugin for Module {
                 r#"[.. | ."@type"? == "fetch_blocks" and ."@value".chain_id == "{}"] | any"#,
                 config.chain_id
             ),
+            kind: Some(PluginKind::EventSource),
+            chains: vec![config.chain_id.clone()],
+            ibc_specs: vec![IbcUnion::ID],
         }
     }
 
-    async fn cmd(_config: Self::Config, cmd: Self::Cmd) {
+    async fn cmd(_config: Self::Config, cmd: Self::Cmd) -> Result<Box<dyn CmdOutput>, BoxDynError> {
         match cmd {}
     }
 }
@@ -183,19 +188,14 @@ impl Module {
             .state
             .ok_or_else(missing_state("connection must exist", None))?;
 
+        let self_client_id = union_id(self_connection_state.client_id)?;
+
         let client_info = voyager_rpc_client
-            .client_info::<IbcUnion>(
-                self.chain_id.clone(),
-                self_connection_state.client_id.clone(),
-            )
+            .client_info::<IbcUnion>(self.chain_id.clone(), self_client_id)
             .await?;
 
         let client_meta = voyager_rpc_client
-            .client_meta::<IbcUnion>(
-                self.chain_id.clone(),
-                event_height.into(),
-                self_connection_state.client_id.clone(),
-            )
+            .client_meta::<IbcUnion>(self.chain_id.clone(), event_height.into(), self_client_id)
             .await?;
 
         let other_channel_id = self_channel.counterparty_channel_id;
@@ -214,19 +214,19 @@ impl Module {
             .ok_or_else(missing_state("channel must exist", None))?;
 
         let source_channel = ChannelMetadata {
-            channel_id: self_channel_id.clone(),
+            channel_id: union_id(self_channel_id)?,
             version: self_channel.version,
             connection: ConnectionMetadata {
-                client_id: self_connection_state.client_id,
-                connection_id: self_connection_id,
+                client_id: self_client_id,
+                connection_id: union_id(self_connection_id)?,
             },
         };
         let destination_channel = ChannelMetadata {
-            channel_id: other_channel_id,
+            channel_id: union_id(other_channel_id)?,
             version: other_channel_state.version,
             connection: ConnectionMetadata {
-                client_id: self_connection_state.counterparty_client_id,
-                connection_id: self_connection_state.counterparty_connection_id,
+                client_id: union_id(self_connection_state.counterparty_client_id)?,
+                connection_id: union_id(self_connection_state.counterparty_connection_id)?,
             },
         };
 
@@ -441,62 +441,62 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
                 tx_hash,
                 height,
             }) => {
-                let (full_event, client_id): (FullEvent, u32) = match event {
+                let (full_event, client_id): (FullEvent, UnionId) = match event {
                     events::IbcEvent::CreateClient(event) => (
                         CreateClient {
-                            client_id: event.client_id,
+                            client_id: union_id(event.client_id)?,
                             client_type: ClientType::new(event.client_type),
                         }
                         .into(),
-                        event.client_id,
+                        union_id(event.client_id)?,
                     ),
                     events::IbcEvent::UpdateClient(event) => (
                         UpdateClient {
-                            client_id: event.client_id,
+                            client_id: union_id(event.client_id)?,
                             client_type: ClientType::new(event.client_type),
                             height: event.height,
                         }
                         .into(),
-                        event.client_id,
+                        union_id(event.client_id)?,
                     ),
                     events::IbcEvent::ConnectionOpenInit(event) => (
                         ConnectionOpenInit {
-                            client_id: event.client_id,
-                            connection_id: event.connection_id,
-                            counterparty_client_id: event.counterparty_client_id,
+                            client_id: union_id(event.client_id)?,
+                            connection_id: union_id(event.connection_id)?,
+                            counterparty_client_id: union_id(event.counterparty_client_id)?,
                         }
                         .into(),
-                        event.client_id,
+                        union_id(event.client_id)?,
                     ),
                     events::IbcEvent::ConnectionOpenTry(event) => (
                         ConnectionOpenTry {
-                            client_id: event.client_id,
-                            connection_id: event.connection_id,
-                            counterparty_client_id: event.counterparty_client_id,
-                            counterparty_connection_id: event.counterparty_connection_id,
+                            client_id: union_id(event.client_id)?,
+                            connection_id: union_id(event.connection_id)?,
+                            counterparty_client_id: union_id(event.counterparty_client_id)?,
+                            counterparty_connection_id: union_id(event.counterparty_connection_id)?,
                         }
                         .into(),
-                        event.client_id,
+                        union_id(event.client_id)?,
                     ),
                     events::IbcEvent::ConnectionOpenAck(event) => (
                         ConnectionOpenAck {
-                            client_id: event.client_id,
-                            connection_id: event.connection_id,
-                            counterparty_client_id: event.counterparty_client_id,
-                            counterparty_connection_id: event.counterparty_connection_id,
+                            client_id: union_id(event.client_id)?,
+                            connection_id: union_id(event.connection_id)?,
+                            counterparty_client_id: union_id(event.counterparty_client_id)?,
+                            counterparty_connection_id: union_id(event.counterparty_connection_id)?,
                         }
                         .into(),
-                        event.client_id,
+                        union_id(event.client_id)?,
                     ),
                     events::IbcEvent::ConnectionOpenConfirm(event) => (
                         ConnectionOpenConfirm {
-                            client_id: event.client_id,
-                            connection_id: event.connection_id,
-                            counterparty_client_id: event.counterparty_client_id,
-                            counterparty_connection_id: event.counterparty_connection_id,
+                            client_id: union_id(event.client_id)?,
+                            connection_id: union_id(event.connection_id)?,
+                            counterparty_client_id: union_id(event.counterparty_client_id)?,
+                            counterparty_connection_id: union_id(event.counterparty_connection_id)?,
                         }
                         .into(),
-                        event.client_id,
+                        union_id(event.client_id)?,
                     ),
                     events::IbcEvent::ChannelOpenInit(event) => {
                         let ledger_version = self.ledger_version_of_height(height).await;
@@ -512,14 +512,14 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
                             .unwrap();
 
                         let connection = convert_connection(connection);
-                        let client_id = connection.client_id;
+                        let client_id = union_id(connection.client_id)?;
 
                         (
                             ChannelOpenInit {
                                 port_id: event.port_id.parse().unwrap(),
-                                channel_id: event.channel_id,
+                                channel_id: union_id(event.channel_id)?,
                                 counterparty_port_id: event.counterparty_port_id.into(),
-                                connection,
+                                connection: channel_connection(connection)?,
                                 version: event.version,
                             }
                             .into(),
@@ -541,15 +541,15 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
 
                         let connection = convert_connection(connection);
 
-                        let client_id = connection.client_id;
+                        let client_id = union_id(connection.client_id)?;
 
                         (
                             ChannelOpenTry {
                                 port_id: event.port_id.parse().unwrap(),
-                                channel_id: event.channel_id,
+                                channel_id: union_id(event.channel_id)?,
                                 counterparty_port_id: event.counterparty_port_id.into(),
-                                counterparty_channel_id: event.counterparty_channel_id,
-                                connection,
+                                counterparty_channel_id: union_id(event.counterparty_channel_id)?,
+                                connection: channel_connection(connection)?,
                                 version: event.version,
                             }
                             .into(),
@@ -581,15 +581,15 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
 
                         let connection = convert_connection(connection);
 
-                        let client_id = connection.client_id.clone();
+                        let client_id = union_id(connection.client_id)?;
 
                         (
                             ChannelOpenAck {
                                 port_id: event.port_id.parse().unwrap(),
-                                channel_id: event.channel_id,
+                                channel_id: union_id(event.channel_id)?,
                                 counterparty_port_id: event.counterparty_port_id.into(),
-                                counterparty_channel_id: event.counterparty_channel_id,
-                                connection,
+                                counterparty_channel_id: union_id(event.counterparty_channel_id)?,
+                                connection: channel_connection(connection)?,
                                 version: channel.version,
                             }
                             .into(),
@@ -621,15 +621,15 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
 
                         let connection = convert_connection(connection);
 
-                        let client_id = connection.client_id;
+                        let client_id = union_id(connection.client_id)?;
 
                         (
                             ChannelOpenConfirm {
                                 port_id: event.port_id.parse().unwrap(),
-                                channel_id: event.channel_id,
+                                channel_id: union_id(event.channel_id)?,
                                 counterparty_port_id: event.counterparty_port_id.into(),
-                                counterparty_channel_id: event.counterparty_channel_id,
-                                connection,
+                                counterparty_channel_id: union_id(event.counterparty_channel_id)?,
+                                connection: channel_connection(connection)?,
                                 version: channel.version,
                             }
                             .into(),
@@ -650,7 +650,7 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
                             )
                             .await?;
 
-                        let client_id = destination_channel.connection.client_id.clone();
+                        let client_id = destination_channel.connection.client_id;
 
                         (
                             WriteAcknowledgement {
@@ -681,7 +681,7 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
                             )
                             .await?;
 
-                        let client_id = destination_channel.connection.client_id.clone();
+                        let client_id = destination_channel.connection.client_id;
 
                         (
                             RecvPacket {
@@ -712,7 +712,7 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
                             )
                             .await?;
 
-                        let client_id = source_channel.connection.client_id.clone();
+                        let client_id = source_channel.connection.client_id;
 
                         (
                             SendPacket {
@@ -723,6 +723,7 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
                                     timeout_height: event.timeout_height,
                                     timeout_timestamp: event.timeout_timestamp,
                                 },
+                                batch: None,
                             }
                             .into(),
                             client_id,
@@ -742,7 +743,7 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
                             )
                             .await?;
 
-                        let client_id = source_channel.connection.client_id.clone();
+                        let client_id = source_channel.connection.client_id;
 
                         (
                             AcknowledgePacket {
@@ -765,14 +766,14 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
                 let voyager_client = e.try_get::<VoyagerClient>()?;
 
                 let client_info = voyager_client
-                    .client_info::<IbcUnion>(self.chain_id.clone(), client_id.clone())
+                    .client_info::<IbcUnion>(self.chain_id.clone(), client_id)
                     .await?;
 
                 let client_meta = voyager_client
                     .client_meta::<IbcUnion>(
                         self.chain_id.clone(),
                         self.make_height(height).into(),
-                        client_id.clone(),
+                        client_id,
                     )
                     .await?;
 
@@ -785,6 +786,8 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
                     provable_height: self.make_height(height),
                     event: into_value::<FullEvent>(full_event),
                     ibc_spec_id: IbcUnion::ID,
+                    raw_events: None,
+                    denom_trace: None,
                 }))
             }
         }
@@ -0,0 +1,545 @@
+//! An append-only journal of ops, stored as size-rotated JSONL files.
+//!
+//! Every [`Record`] is assigned a sequence number that is monotonically increasing across all
+//! files of the journal. Files are named after the sequence number of the first record they
+//! contain (`journal-<seq>.jsonl`), such that sorting them by name also sorts them by sequence
+//! number.
+//!
+//! Only the newest file is ever written to. If the process crashes while a record is being
+//! written, the newest file may end with a partially written line; this line is truncated when the
+//! journal is next opened.
+
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::{debug, warn};
+use voyager_message::{core::ChainId, data::Data, VoyagerMessage};
+use voyager_vm::Op;
+
+const FILE_PREFIX: &str = "journal-";
+const FILE_EXTENSION: &str = "jsonl";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Record {
+    pub seq: u64,
+    /// Milliseconds since the unix epoch at which the op was journaled.
+    pub timestamp: u64,
+    pub chain_id: ChainId,
+    pub op: Op<VoyagerMessage>,
+}
+
+impl Record {
+    /// The chain that `op` was emitted on or is to be submitted to, if it is an op that is
+    /// journaled.
+    #[must_use]
+    pub fn chain_id_of(op: &Op<VoyagerMessage>) -> Option<&ChainId> {
+        match op {
+            Op::Data(Data::IbcEvent(event)) => Some(&event.chain_id),
+            Op::Data(Data::IdentifiedIbcDatagram(datagram)) => Some(&datagram.chain_id),
+            Op::Data(Data::IdentifiedIbcDatagramBatch(batch)) => Some(&batch.chain_id),
+            _ => None,
+        }
+    }
+
+    /// Whether any of the channel ids referenced by the op is `channel`.
+    ///
+    /// The op is not decoded into the types of its IBC spec, instead any field named `channel_id`
+    /// or ending in `_channel_id` is checked. Both string (ibc-classic) and numeric (ibc-union)
+    /// channel ids are supported.
+    #[must_use]
+    pub fn mentions_channel(&self, channel: &str) -> bool {
+        fn visit(value: &Value, channel: &str) -> bool {
+            match value {
+                Value::Object(map) => map.iter().any(|(key, value)| {
+                    let is_channel_id = (key == "channel_id" || key.ends_with("_channel_id"))
+                        && match value {
+                            Value::String(s) => s == channel,
+                            Value::Number(n) => n.to_string() == channel,
+                            _ => false,
+                        };
+
+                    is_channel_id || visit(value, channel)
+                }),
+                Value::Array(values) => values.iter().any(|value| visit(value, channel)),
+                _ => false,
+            }
+        }
+
+        visit(
+            &serde_json::to_value(&self.op).expect("serialization is infallible; qed;"),
+            channel,
+        )
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum JournalError {
+    #[error("error accessing journal file {}", path.display())]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    #[error("invalid record in journal file {} at line {line}", path.display())]
+    Decode {
+        path: PathBuf,
+        line: usize,
+        #[source]
+        source: serde_json::Error,
+    },
+}
+
+/// The writer side of the journal.
+#[derive(Debug)]
+pub struct Journal {
+    directory: PathBuf,
+    max_file_size: u64,
+    next_seq: u64,
+    current: Option<CurrentFile>,
+}
+
+#[derive(Debug)]
+struct CurrentFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+}
+
+impl Journal {
+    /// Open the journal in `directory`, creating the directory if it does not exist.
+    ///
+    /// A partially written record at the end of the newest file is truncated.
+    pub fn open(directory: &Path, max_file_size: u64) -> Result<Self, JournalError> {
+        fs::create_dir_all(directory).map_err(io_err(directory))?;
+
+        let Some((first_seq, path)) = files(directory)?.pop() else {
+            return Ok(Self {
+                directory: directory.to_owned(),
+                max_file_size,
+                next_seq: 0,
+                current: None,
+            });
+        };
+
+        let bz = fs::read(&path).map_err(io_err(&path))?;
+
+        // everything after the last newline was not completely written
+        let complete_len = bz
+            .iter()
+            .rposition(|b| *b == b'\n')
+            .map_or(0, |idx| idx + 1);
+
+        let file = OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .map_err(io_err(&path))?;
+
+        if complete_len < bz.len() {
+            warn!(
+                path = %path.display(),
+                truncated = bz.len() - complete_len,
+                "truncating partially written record"
+            );
+
+            file.set_len(complete_len as u64).map_err(io_err(&path))?;
+            file.sync_data().map_err(io_err(&path))?;
+        }
+
+        let next_seq = match bz[..complete_len]
+            .split(|b| *b == b'\n')
+            .enumerate()
+            .filter(|(_, line)| !line.is_empty())
+            .last()
+        {
+            Some((idx, line)) => {
+                serde_json::from_slice::<Record>(line)
+                    .map_err(|source| JournalError::Decode {
+                        path: path.clone(),
+                        line: idx + 1,
+                        source,
+                    })?
+                    .seq
+                    + 1
+            }
+            None => first_seq,
+        };
+
+        debug!(path = %path.display(), next_seq, "opened journal");
+
+        Ok(Self {
+            directory: directory.to_owned(),
+            max_file_size,
+            next_seq,
+            current: Some(CurrentFile {
+                path,
+                file,
+                size: complete_len as u64,
+            }),
+        })
+    }
+
+    /// The sequence number that will be assigned to the next record.
+    #[must_use]
+    pub fn next_seq(&self) -> u64 {
+        self.next_seq
+    }
+
+    /// Append all journaled ops out of `ops` to the journal, returning the amount of records
+    /// written. Ops that are not journaled (see [`Record::chain_id_of`]) are ignored.
+    ///
+    /// All records are synced to disk before this returns.
+    pub fn append<'a>(
+        &mut self,
+        ops: impl IntoIterator<Item = &'a Op<VoyagerMessage>>,
+    ) -> Result<usize, JournalError> {
+        let timestamp = now_millis();
+
+        let mut written = 0;
+
+        for op in ops {
+            let Some(chain_id) = Record::chain_id_of(op) else {
+                continue;
+            };
+
+            let mut line = serde_json::to_vec(&Record {
+                seq: self.next_seq,
+                timestamp,
+                chain_id: chain_id.clone(),
+                op: op.clone(),
+            })
+            .expect("serialization is infallible; qed;");
+            line.push(b'\n');
+
+            let current = self.file_for(line.len() as u64)?;
+
+            if let Err(err) = current.file.write_all(&line) {
+                // don't leave a partially written record in the middle of the file
+                let _ = current.file.set_len(current.size);
+
+                return Err(io_err(&current.path)(err));
+            }
+            current.size += line.len() as u64;
+
+            self.next_seq += 1;
+            written += 1;
+        }
+
+        if let Some(current) = &self.current {
+            current.file.sync_data().map_err(io_err(&current.path))?;
+        }
+
+        Ok(written)
+    }
+
+    /// The file that a record of `len` bytes is to be written to, rotating to a new file if the
+    /// current file would exceed the maximum file size. A record is always written to an empty
+    /// file, even if it exceeds the maximum file size by itself.
+    fn file_for(&mut self, len: u64) -> Result<&mut CurrentFile, JournalError> {
+        let rotate = match &self.current {
+            Some(current) => current.size > 0 && current.size + len > self.max_file_size,
+            None => true,
+        };
+
+        if rotate {
+            if let Some(current) = &self.current {
+                // ensure the previous file is fully synced before moving on
+                current.file.sync_data().map_err(io_err(&current.path))?;
+            }
+
+            let path = self.directory.join(file_name(self.next_seq));
+
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&path)
+                .map_err(io_err(&path))?;
+
+            debug!(path = %path.display(), "rotated journal file");
+
+            self.current = Some(CurrentFile {
+                path,
+                file,
+                size: 0,
+            });
+        }
+
+        Ok(self.current.as_mut().expect("file was opened above; qed;"))
+    }
+}
+
+/// Read all records with a sequence number of at least `from_seq` out of the journal in
+/// `directory`, in order, calling `f` with each of them.
+///
+/// A partially written record at the end of the newest file is skipped.
+pub fn scan(
+    directory: &Path,
+    from_seq: u64,
+    mut f: impl FnMut(Record),
+) -> Result<(), JournalError> {
+    let files = files(directory)?;
+
+    // skip all files that only contain records before `from_seq`
+    let start = files
+        .iter()
+        .rposition(|(first_seq, _)| *first_seq <= from_seq)
+        .unwrap_or(0);
+
+    let last = files.len().saturating_sub(1);
+
+    for (file_idx, (_, path)) in files.iter().enumerate().skip(start) {
+        let mut reader = BufReader::new(File::open(path).map_err(io_err(path))?);
+
+        let mut line = Vec::new();
+        let mut line_idx = 0;
+
+        loop {
+            line.clear();
+            line_idx += 1;
+
+            if reader.read_until(b'\n', &mut line).map_err(io_err(path))? == 0 {
+                break;
+            }
+
+            if line.last() != Some(&b'\n') && file_idx == last {
+                warn!(path = %path.display(), "skipping partially written record");
+                break;
+            }
+
+            let record =
+                serde_json::from_slice::<Record>(&line).map_err(|source| JournalError::Decode {
+                    path: path.clone(),
+                    line: line_idx,
+                    source,
+                })?;
+
+            if record.seq >= from_seq {
+                f(record);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// All journal files in `directory`, sorted by the sequence number of their first record.
+fn files(directory: &Path) -> Result<Vec<(u64, PathBuf)>, JournalError> {
+    let mut files = fs::read_dir(directory)
+        .map_err(io_err(directory))?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(io_err(directory))?
+        .into_iter()
+        .filter_map(|path| {
+            let first_seq = path
+                .file_name()?
+                .to_str()?
+                .strip_prefix(FILE_PREFIX)?
+                .strip_suffix(FILE_EXTENSION)?
+                .strip_suffix('.')?
+                .parse()
+                .ok()?;
+
+            Some((first_seq, path))
+        })
+        .collect::<Vec<_>>();
+
+    files.sort();
+
+    Ok(files)
+}
+
+fn file_name(first_seq: u64) -> String {
+    format!("{FILE_PREFIX}{first_seq:020}.{FILE_EXTENSION}")
+}
+
+fn io_err(path: &Path) -> impl FnOnce(io::Error) -> JournalError + '_ {
+    move |source| JournalError::Io {
+        path: path.to_owned(),
+        source,
+    }
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("the current timestamp must be greater than the unix epoch")
+        .as_millis()
+        .try_into()
+        .expect("millisecond timestamp overflowed a u64")
+}
+
+#[cfg(test)]
+mod tests {
+    use voyager_message::{
+        core::IbcSpecId,
+        data::{IbcDatagram, WithChainId},
+    };
+    use voyager_vm::{data, noop};
+
+    use super::*;
+
+    fn journal_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "voyager-plugin-journal-{name}-{}",
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn datagram(chain_id: &str, channel_id: u32) -> Op<VoyagerMessage> {
+        data(WithChainId {
+            chain_id: ChainId::new(chain_id.to_owned()),
+            message: IbcDatagram {
+                ibc_spec_id: IbcSpecId::new_static(IbcSpecId::UNION),
+                datagram: serde_json::json!({
+                    "@type": "packet_recv",
+                    "@value": { "packets": [{ "destination_channel_id": channel_id }] }
+                }),
+            },
+        })
+    }
+
+    fn read_all(dir: &Path, from_seq: u64) -> Vec<Record> {
+        let mut records = vec![];
+        scan(dir, from_seq, |record| records.push(record)).unwrap();
+        records
+    }
+
+    #[test]
+    fn records_are_sequenced() {
+        let dir = journal_dir("sequenced");
+
+        let ops = [datagram("a", 1), noop(), datagram("b", 2)];
+
+        let mut journal = Journal::open(&dir, u64::MAX).unwrap();
+        assert_eq!(journal.append(&ops).unwrap(), 2);
+        assert_eq!(journal.append(&ops[..1]).unwrap(), 1);
+
+        let records = read_all(&dir, 0);
+
+        assert_eq!(records.iter().map(|r| r.seq).collect::<Vec<_>>(), [0, 1, 2]);
+        assert_eq!(records[0].op, ops[0]);
+        assert_eq!(records[1].op, ops[2]);
+        assert_eq!(records[1].chain_id, ChainId::new("b".to_owned()));
+
+        // the sequence continues after reopening
+        drop(journal);
+        assert_eq!(Journal::open(&dir, u64::MAX).unwrap().next_seq(), 3);
+    }
+
+    #[test]
+    fn rotation() {
+        let dir = journal_dir("rotation");
+
+        let record_len = serde_json::to_vec(&Record {
+            seq: 0,
+            timestamp: now_millis(),
+            chain_id: ChainId::new("a".to_owned()),
+            op: datagram("a", 1),
+        })
+        .unwrap()
+        .len() as u64
+            + 1;
+
+        // room for two records per file
+        let mut journal = Journal::open(&dir, record_len * 2 + record_len / 2).unwrap();
+
+        for channel_id in 0..5 {
+            journal.append([&datagram("a", channel_id)]).unwrap();
+        }
+
+        assert_eq!(
+            files(&dir)
+                .unwrap()
+                .into_iter()
+                .map(|(first_seq, _)| first_seq)
+                .collect::<Vec<_>>(),
+            [0, 2, 4]
+        );
+
+        assert_eq!(
+            read_all(&dir, 0).iter().map(|r| r.seq).collect::<Vec<_>>(),
+            [0, 1, 2, 3, 4]
+        );
+        assert_eq!(
+            read_all(&dir, 3).iter().map(|r| r.seq).collect::<Vec<_>>(),
+            [3, 4]
+        );
+
+        // a record larger than the maximum file size still gets written
+        let mut journal = Journal::open(&dir, 1).unwrap();
+        journal.append([&datagram("a", 5)]).unwrap();
+        journal.append([&datagram("a", 6)]).unwrap();
+
+        assert_eq!(files(&dir).unwrap().len(), 5);
+        assert_eq!(read_all(&dir, 0).len(), 7);
+    }
+
+    #[test]
+    fn crash_truncated_file() {
+        let dir = journal_dir("truncated");
+
+        let mut journal = Journal::open(&dir, u64::MAX).unwrap();
+        journal
+            .append(&[datagram("a", 1), datagram("a", 2)])
+            .unwrap();
+        drop(journal);
+
+        // simulate a crash halfway through writing a record
+        let (_, path) = files(&dir).unwrap().pop().unwrap();
+        let full = fs::read(&path).unwrap();
+        let first_line_len = full.iter().position(|b| *b == b'\n').unwrap() + 1;
+        fs::write(&path, &full[..first_line_len + 10]).unwrap();
+
+        // readers skip the partial record
+        assert_eq!(
+            read_all(&dir, 0).iter().map(|r| r.seq).collect::<Vec<_>>(),
+            [0]
+        );
+
+        // the writer truncates it and reuses its sequence number
+        let mut journal = Journal::open(&dir, u64::MAX).unwrap();
+        assert_eq!(journal.next_seq(), 1);
+        assert_eq!(fs::read(&path).unwrap().len(), first_line_len);
+
+        journal.append([&datagram("a", 3)]).unwrap();
+
+        let records = read_all(&dir, 0);
+        assert_eq!(records.iter().map(|r| r.seq).collect::<Vec<_>>(), [0, 1]);
+        assert_eq!(records[1].op, datagram("a", 3));
+    }
+
+    #[test]
+    fn crash_before_first_record() {
+        let dir = journal_dir("empty");
+
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join(file_name(7)), b"{\"seq\":7,\"times").unwrap();
+
+        let journal = Journal::open(&dir, u64::MAX).unwrap();
+        assert_eq!(journal.next_seq(), 7);
+        assert!(read_all(&dir, 0).is_empty());
+    }
+
+    #[test]
+    fn channel_filter() {
+        let record = |op| Record {
+            seq: 0,
+            timestamp: 0,
+            chain_id: ChainId::new("a".to_owned()),
+            op,
+        };
+
+        assert!(record(datagram("a", 1)).mentions_channel("1"));
+        assert!(!record(datagram("a", 1)).mentions_channel("2"));
+        assert!(!record(datagram("a", 1)).mentions_channel("a"));
+    }
+}
@@ -0,0 +1,357 @@
+use std::{
+    collections::VecDeque,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use jsonrpsee::{
+    core::{async_trait, RpcResult},
+    types::ErrorObject,
+    Extensions,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::{debug, instrument};
+use unionlabs::{never::Never, ErrorReporter};
+use voyager_message::{
+    cmd::CmdOutput,
+    core::ChainId,
+    data::Data,
+    into_value,
+    module::{PluginInfo, PluginServer},
+    Plugin, VoyagerMessage, FATAL_JSONRPC_ERROR_CODE,
+};
+use voyager_vm::{pass::PassResult, BoxDynError, Op};
+
+use crate::journal::{Journal, JournalError, Record};
+
+pub mod journal;
+
+#[tokio::main(flavor = "multi_thread")]
+async fn main() {
+    Module::run().await
+}
+
+#[derive(Debug, Clone)]
+pub struct Module {
+    pub journal: Arc<Mutex<Journal>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// The directory that the journal files are written to.
+    pub directory: PathBuf,
+    /// The size (in bytes) after which a new journal file is started.
+    #[serde(default = "default_max_file_size")]
+    pub max_file_size: u64,
+}
+
+fn default_max_file_size() -> u64 {
+    // 64 MiB
+    64 * 1024 * 1024
+}
+
+#[derive(clap::Subcommand)]
+pub enum Cmd {
+    /// Print all journaled records matching the provided filters, one per line.
+    Query {
+        /// Only print records for this chain.
+        #[arg(long, value_parser(|s: &str| Ok::<_, BoxDynError>(ChainId::new(s.to_owned()))))]
+        chain_id: Option<ChainId>,
+        /// Only print records that reference this channel id.
+        #[arg(long)]
+        channel: Option<String>,
+        /// Only print records with a sequence number of at least this value.
+        #[arg(long, default_value_t = 0)]
+        from_seq: u64,
+    },
+}
+
+impl Plugin for Module {
+    type Call = Never;
+    type Callback = Never;
+
+    type Config = Config;
+    type Cmd = Cmd;
+
+    async fn new(config: Self::Config) -> Result<Self, BoxDynError> {
+        let journal = Journal::open(&config.directory, config.max_file_size)?;
+
+        Ok(Self {
+            journal: Arc::new(Mutex::new(journal)),
+        })
+    }
+
+    fn info(_config: Self::Config) -> PluginInfo {
+        PluginInfo {
+            name: plugin_name(),
+            interest_filter: include_str!("interest_filter.jq").to_owned(),
+            kind: None,
+            chains: vec![],
+            ibc_specs: vec![],
+        }
+    }
+
+    async fn cmd(config: Self::Config, cmd: Self::Cmd) -> Result<Box<dyn CmdOutput>, BoxDynError> {
+        match cmd {
+            Cmd::Query {
+                chain_id,
+                channel,
+                from_seq,
+            } => Ok(Box::new(query(
+                &config.directory,
+                chain_id.as_ref(),
+                channel.as_deref(),
+                from_seq,
+            )?)),
+        }
+    }
+}
+
+/// The output of [`Cmd::Query`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(transparent)]
+pub struct QueryOutput(pub Vec<Record>);
+
+impl CmdOutput for QueryOutput {
+    fn to_json(&self) -> Value {
+        into_value(self)
+    }
+
+    /// One json encoded record per line.
+    fn to_text(&self) -> String {
+        self.0
+            .iter()
+            .map(|record| serde_json::to_string(record).expect("serialization is infallible; qed;"))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+fn query(
+    directory: &Path,
+    chain_id: Option<&ChainId>,
+    channel: Option<&str>,
+    from_seq: u64,
+) -> Result<QueryOutput, JournalError> {
+    let mut records = vec![];
+
+    journal::scan(directory, from_seq, |record| {
+        if chain_id.is_some_and(|c| c != &record.chain_id) {
+            return;
+        }
+
+        if channel.is_some_and(|c| !record.mentions_channel(c)) {
+            return;
+        }
+
+        records.push(record);
+    })?;
+
+    Ok(QueryOutput(records))
+}
+
+fn plugin_name() -> String {
+    pub const PLUGIN_NAME: &str = env!("CARGO_PKG_NAME");
+
+    PLUGIN_NAME.to_owned()
+}
+
+/// Journal `msgs`.
+///
+/// The ops are only observed by this plugin (they are copies of ops that are handled by the other
+/// plugins as usual), so they are all dropped once they are journaled.
+pub fn journal_pass(
+    journal: &mut Journal,
+    msgs: Vec<Op<VoyagerMessage>>,
+) -> Result<PassResult<VoyagerMessage>, JournalError> {
+    let written = journal.append(&msgs)?;
+
+    debug!(written, next_seq = journal.next_seq(), "journaled ops");
+
+    Ok(PassResult::default())
+}
+
+#[async_trait]
+impl PluginServer<Never, Never> for Module {
+    #[instrument(skip_all)]
+    async fn run_pass(
+        &self,
+        _: &Extensions,
+        msgs: Vec<Op<VoyagerMessage>>,
+    ) -> RpcResult<PassResult<VoyagerMessage>> {
+        let journal = self.journal.clone();
+
+        // the journal is written synchronously, such that all records are on disk once the pass
+        // returns
+        tokio::task::spawn_blocking(move || {
+            let mut journal = journal.lock().expect("journal mutex is poisoned");
+
+            journal_pass(&mut journal, msgs)
+        })
+        .await
+        .expect("journal task panicked")
+        .map_err(|err| {
+            ErrorObject::owned(
+                FATAL_JSONRPC_ERROR_CODE,
+                ErrorReporter(err).with_message("error writing to the journal"),
+                None::<()>,
+            )
+        })
+    }
+
+    #[instrument]
+    async fn call(&self, _: &Extensions, msg: Never) -> RpcResult<Op<VoyagerMessage>> {
+        match msg {}
+    }
+
+    #[instrument]
+    async fn callback(
+        &self,
+        _: &Extensions,
+        cb: Never,
+        _data: VecDeque<Data>,
+    ) -> RpcResult<Op<VoyagerMessage>> {
+        match cb {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use voyager_message::filter::JaqInterestFilter;
+    use voyager_message::{
+        core::IbcSpecId,
+        data::{IbcDatagram, WithChainId},
+    };
+    use voyager_vm::{
+        data, defer, in_memory::InMemoryQueue, noop, seq, InspectQueue, Queue, READY_LANE,
+    };
+
+    use super::*;
+
+    fn datagram(chain_id: &str) -> Op<VoyagerMessage> {
+        data(WithChainId {
+            chain_id: ChainId::new(chain_id.to_owned()),
+            message: IbcDatagram {
+                ibc_spec_id: IbcSpecId::new_static(IbcSpecId::CLASSIC),
+                datagram: serde_json::json!({ "@type": "update_client", "@value": {} }),
+            },
+        })
+    }
+
+    #[test]
+    fn observed_ops_are_dropped() {
+        let dir = std::env::temp_dir().join(format!(
+            "voyager-plugin-journal-observed-ops-are-dropped-{}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let ops = vec![
+            datagram("a"),
+            noop(),
+            datagram("b"),
+            seq([defer(1), datagram("c")]),
+        ];
+
+        let mut journal = Journal::open(&dir, u64::MAX).unwrap();
+
+        let res = journal_pass(&mut journal, ops).unwrap();
+
+        assert!(res.optimize_further.is_empty());
+        assert!(res.ready.is_empty());
+
+        // only the two top level datagrams were journaled
+        assert_eq!(journal.next_seq(), 2);
+    }
+
+    #[tokio::test]
+    async fn observed_datagrams_are_still_submitted() {
+        let transaction_plugin = PluginInfo {
+            name: "voyager-transaction-plugin-cosmos-sdk/union-1".to_owned(),
+            interest_filter: r#"
+if ."@type" == "data" then
+    ."@value" as $data |
+
+    ($data."@type" == "identified_ibc_datagram_batch" or $data."@type" == "identified_ibc_datagram")
+        and $data."@value".chain_id == "union-1"
+else
+    false
+end
+"#
+            .to_owned(),
+            kind: None,
+            chains: vec![],
+            ibc_specs: vec![],
+        };
+
+        // the journal is ordered before the transaction plugin, which would have starved the
+        // transaction plugin if the journal claimed the datagrams
+        let filter = JaqInterestFilter::new(vec![
+            Module::info(Config {
+                directory: PathBuf::new(),
+                max_file_size: default_max_file_size(),
+            }),
+            transaction_plugin.clone(),
+        ])
+        .unwrap();
+
+        let queue = InMemoryQueue::<VoyagerMessage>::new(()).await.unwrap();
+        queue.enqueue(datagram("union-1"), &filter).await.unwrap();
+        queue.enqueue(datagram("union-2"), &filter).await.unwrap();
+
+        let stats = queue.stats().await.unwrap();
+
+        assert_eq!(stats.lane(&plugin_name()).depth, 2);
+        assert_eq!(stats.lane(&transaction_plugin.name).depth, 1);
+        // the datagram for the other chain is not claimed by any plugin
+        assert_eq!(stats.lane(READY_LANE).depth, 1);
+    }
+
+    #[test]
+    fn query_output() {
+        let op = data(WithChainId {
+            chain_id: ChainId::new("a"),
+            message: IbcDatagram {
+                ibc_spec_id: IbcSpecId::new_static(IbcSpecId::CLASSIC),
+                datagram: serde_json::json!({ "@type": "update_client", "@value": {} }),
+            },
+        });
+
+        let output = QueryOutput(vec![
+            Record {
+                seq: 0,
+                timestamp: 1_000,
+                chain_id: ChainId::new("a"),
+                op: op.clone(),
+            },
+            Record {
+                seq: 1,
+                timestamp: 2_000,
+                chain_id: ChainId::new("a"),
+                op: op.clone(),
+            },
+        ]);
+
+        let op = serde_json::to_value(&op).unwrap();
+
+        assert_eq!(
+            output.to_json(),
+            serde_json::json!([
+                { "seq": 0, "timestamp": 1_000, "chain_id": "a", "op": op },
+                { "seq": 1, "timestamp": 2_000, "chain_id": "a", "op": op },
+            ])
+        );
+
+        // one record per line
+        assert_eq!(
+            output.to_text().lines().collect::<Vec<_>>(),
+            output
+                .0
+                .iter()
+                .map(|record| serde_json::to_string(record).unwrap())
+                .collect::<Vec<_>>()
+        );
+    }
+}
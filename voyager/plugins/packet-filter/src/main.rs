@@ -11,6 +11,7 @@ use serde_with::{serde_as, DisplayFromStr};
 use tracing::{instrument, trace};
 use unionlabs::never::Never;
 use voyager_message::{
+    cmd::CmdOutput,
     core::IbcSpec,
     data::Data,
     module::{PluginInfo, PluginServer},
@@ -54,10 +55,13 @@ impl Plugin for Module {
         PluginInfo {
             name: module.plugin_name(),
             interest_filter: module.make_filter(),
+            kind: None,
+            chains: vec![],
+            ibc_specs: vec![],
         }
     }
 
-    async fn cmd(_config: Self::Config, cmd: Self::Cmd) {
+    async fn cmd(_config: Self::Config, cmd: Self::Cmd) -> Result<Box<dyn CmdOutput>, BoxDynError> {
         match cmd {}
     }
 }
@@ -194,12 +198,12 @@ impl PacketEventFilter {
                 and ($event.packet.source_channel.port_id | test("{source_port_id}"))
                 and ($event.packet.source_channel.channel_id | test("{source_channel_id}"))
                 and ($event.packet.source_channel.version | test("{source_channel_version}"))
-                and ($event.packet.source_channel.connection.connection_id | test("{source_connection_id}"))
+                and (($event.packet.source_channel | (.connection_hops[0] // .connection).connection_id) | test("{source_connection_id}"))
 
                 and ($event.packet.destination_channel.port_id | test("{destination_port_id}"))
                 and ($event.packet.destination_channel.channel_id | test("{destination_channel_id}"))
                 and ($event.packet.destination_channel.version | test("{destination_channel_version}"))
-                and ($event.packet.destination_channel.connection.connection_id | test("{destination_connection_id}"))
+                and (($event.packet.destination_channel | (.connection_hops[0] // .connection).connection_id) | test("{destination_connection_id}"))
             )"#
         )
     }
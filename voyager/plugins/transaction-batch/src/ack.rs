@@ -0,0 +1,287 @@
+//! Verification of acknowledgements before they are relayed.
+//!
+//! An acknowledgement datagram is only accepted by the source chain if the acknowledgement matches
+//! the commitment that the destination chain wrote for it. Before an acknowledgement is relayed,
+//! the commitment is read from the destination chain at the height the proof is built at, and
+//! compared against the commitment of the acknowledgement that is being relayed. On a mismatch (or
+//! if no acknowledgement is committed at all), the message is not built, instead of spending gas
+//! on a transaction that is guaranteed to revert.
+//!
+//! The commitments are computed with [`ibc_classic_spec::commit_acknowledgement`] and
+//! [`ibc_union_spec::commit_ack`], the same functions that are used everywhere else the
+//! commitments are needed.
+
+use ibc_solidity::Packet;
+use jsonrpsee::{core::RpcResult, types::ErrorObject};
+use serde_json::json;
+use unionlabs::{hash::H256, ibc::core::client::height::Height, ErrorReporter};
+use voyager_message::{core::ChainId, query::VoyagerQuery, FATAL_JSONRPC_ERROR_CODE};
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum AckVerificationError {
+    #[error(
+        "no acknowledgement is committed at {path} at height {height}, \
+        expected commitment {expected}"
+    )]
+    Missing {
+        path: String,
+        height: Height,
+        expected: H256,
+    },
+    #[error(
+        "acknowledgement commitment mismatch at {path} at height {height}: the relayed \
+        acknowledgement commits to {expected}, but {found} is committed"
+    )]
+    Mismatch {
+        path: String,
+        height: Height,
+        expected: H256,
+        found: H256,
+    },
+}
+
+impl From<AckVerificationError> for ErrorObject<'static> {
+    fn from(value: AckVerificationError) -> Self {
+        let data = match &value {
+            AckVerificationError::Missing { expected, .. } => json!({
+                "expected": expected,
+            }),
+            AckVerificationError::Mismatch {
+                expected, found, ..
+            } => json!({
+                "expected": expected,
+                "found": found,
+            }),
+        };
+
+        ErrorObject::owned(
+            FATAL_JSONRPC_ERROR_CODE,
+            ErrorReporter(value).with_message("refusing to relay acknowledgement"),
+            Some(data),
+        )
+    }
+}
+
+/// Check that `ack` is the acknowledgement committed at `path` on `chain_id` as of `height`.
+pub async fn verify_v1_ack(
+    client: &impl VoyagerQuery,
+    chain_id: &ChainId,
+    height: Height,
+    path: ibc_classic_spec::AcknowledgementPath,
+    ack: &[u8],
+) -> RpcResult<Result<(), AckVerificationError>> {
+    let expected = ibc_classic_spec::commit_acknowledgement(ack);
+
+    let found = client
+        .query_ibc_state(chain_id, height.into(), path.clone())
+        .await?
+        .state;
+
+    Ok(check(path.to_string(), height, expected, found))
+}
+
+/// Check that `ack` is the acknowledgement committed for `packet` on `chain_id` as of `height`.
+pub async fn verify_union_ack(
+    client: &impl VoyagerQuery,
+    chain_id: &ChainId,
+    height: Height,
+    packet: &Packet,
+    ack: &[u8],
+) -> RpcResult<Result<(), AckVerificationError>> {
+    let expected = ibc_union_spec::commit_ack(ack);
+
+    let path = ibc_union_spec::BatchReceiptsPath::from_packet(packet);
+
+    let found = client
+        .query_ibc_state(chain_id, height.into(), path.clone())
+        .await?
+        .state;
+
+    // the receipt is set to the magic value when the packet has been received but not yet
+    // acknowledged
+    let found = (found != ibc_union_spec::COMMITMENT_NULL
+        && found != ibc_union_spec::COMMITMENT_MAGIC)
+        .then_some(found);
+
+    Ok(check(path.to_string(), height, expected, found))
+}
+
+fn check(
+    path: String,
+    height: Height,
+    expected: H256,
+    found: Option<H256>,
+) -> Result<(), AckVerificationError> {
+    match found {
+        None => Err(AckVerificationError::Missing {
+            path,
+            height,
+            expected,
+        }),
+        Some(found) if found != expected => Err(AckVerificationError::Mismatch {
+            path,
+            height,
+            expected,
+            found,
+        }),
+        Some(_) => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU64;
+
+    use unionlabs::id::{ChannelId, PortId};
+    use voyager_message::testing::MockVoyager;
+
+    use super::*;
+
+    const ACK: &[u8] = br#"{"result":"AQ=="}"#;
+
+    fn chain_id() -> ChainId {
+        ChainId::new("union-devnet-1")
+    }
+
+    fn height() -> Height {
+        Height::new(10)
+    }
+
+    fn v1_path() -> ibc_classic_spec::AcknowledgementPath {
+        ibc_classic_spec::AcknowledgementPath {
+            port_id: PortId::new("transfer").unwrap(),
+            channel_id: ChannelId::new(0),
+            sequence: NonZeroU64::new(1).unwrap(),
+        }
+    }
+
+    fn packet() -> Packet {
+        Packet {
+            source_channel: 1,
+            destination_channel: 2,
+            data: b"data".into(),
+            timeout_height: 0,
+            timeout_timestamp: 1,
+        }
+    }
+
+    #[tokio::test]
+    async fn v1_ack() {
+        let voyager = MockVoyager::new();
+
+        assert_eq!(
+            verify_v1_ack(&voyager, &chain_id(), height(), v1_path(), ACK)
+                .await
+                .unwrap(),
+            Err(AckVerificationError::Missing {
+                path: v1_path().to_string(),
+                height: height(),
+                expected: ibc_classic_spec::commit_acknowledgement(ACK),
+            })
+        );
+
+        voyager.set_state_at(
+            &chain_id(),
+            height(),
+            v1_path(),
+            Some(ibc_classic_spec::commit_acknowledgement(ACK)),
+        );
+
+        assert_eq!(
+            verify_v1_ack(&voyager, &chain_id(), height(), v1_path(), ACK)
+                .await
+                .unwrap(),
+            Ok(())
+        );
+
+        // the commitment is read at the proof height, before it was written
+        assert!(matches!(
+            verify_v1_ack(&voyager, &chain_id(), Height::new(9), v1_path(), ACK)
+                .await
+                .unwrap(),
+            Err(AckVerificationError::Missing { .. })
+        ));
+
+        assert_eq!(
+            verify_v1_ack(&voyager, &chain_id(), height(), v1_path(), b"stale")
+                .await
+                .unwrap(),
+            Err(AckVerificationError::Mismatch {
+                path: v1_path().to_string(),
+                height: height(),
+                expected: ibc_classic_spec::commit_acknowledgement(b"stale"),
+                found: ibc_classic_spec::commit_acknowledgement(ACK),
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn union_ack() {
+        let voyager = MockVoyager::new();
+
+        let path = ibc_union_spec::BatchReceiptsPath::from_packet(&packet());
+
+        voyager.set_state(&chain_id(), path.clone(), ibc_union_spec::COMMITMENT_NULL);
+
+        let missing = Err(AckVerificationError::Missing {
+            path: path.to_string(),
+            height: height(),
+            expected: ibc_union_spec::commit_ack(ACK),
+        });
+
+        assert_eq!(
+            verify_union_ack(&voyager, &chain_id(), height(), &packet(), ACK)
+                .await
+                .unwrap(),
+            missing
+        );
+
+        // received, but not yet acknowledged
+        voyager.set_state(&chain_id(), path.clone(), ibc_union_spec::COMMITMENT_MAGIC);
+
+        assert_eq!(
+            verify_union_ack(&voyager, &chain_id(), height(), &packet(), ACK)
+                .await
+                .unwrap(),
+            missing
+        );
+
+        voyager.set_state(&chain_id(), path.clone(), ibc_union_spec::commit_ack(ACK));
+
+        assert_eq!(
+            verify_union_ack(&voyager, &chain_id(), height(), &packet(), ACK)
+                .await
+                .unwrap(),
+            Ok(())
+        );
+
+        assert_eq!(
+            verify_union_ack(&voyager, &chain_id(), height(), &packet(), b"stale")
+                .await
+                .unwrap(),
+            Err(AckVerificationError::Mismatch {
+                path: path.to_string(),
+                height: height(),
+                expected: ibc_union_spec::commit_ack(b"stale"),
+                found: ibc_union_spec::commit_ack(ACK),
+            })
+        );
+    }
+
+    #[test]
+    fn mismatch_error_contains_both_commitments() {
+        let expected = ibc_union_spec::commit_ack(b"stale");
+        let found = ibc_union_spec::commit_ack(ACK);
+
+        let err = ErrorObject::from(AckVerificationError::Mismatch {
+            path: "channels/2/batchReceipts/0x00".to_owned(),
+            height: height(),
+            expected,
+            found,
+        });
+
+        assert_eq!(err.code(), FATAL_JSONRPC_ERROR_CODE);
+        assert!(err.message().contains(&expected.to_string()));
+        assert!(err.message().contains(&found.to_string()));
+    }
+}
@@ -1,23 +1,26 @@
 use enumorph::Enumorph;
 use ibc_classic_spec::IbcClassic;
-use ibc_union_spec::IbcUnion;
+use ibc_solidity::Packet;
+use ibc_union_spec::{IbcUnion, UnionId};
 use jsonrpsee::{core::RpcResult, types::ErrorObject};
 use macros::model;
 use serde_json::json;
-use tracing::info;
-use unionlabs::ibc::core::client::height::Height;
+use tracing::{debug, info, warn};
+use unionlabs::{ibc::core::client::height::Height, id::ClientId};
 use voyager_message::{
-    call::FetchUpdateHeaders,
+    call::{FetchUpdateHeaders, WaitForHeight, WaitForTimestamp, WaitForTrustedHeight},
     callback::AggregateMsgUpdateClientsFromOrderedHeaders,
-    core::{ChainId, QueryHeight},
+    core::{ChainId, ClientStateMeta, ClientStatus, IbcSpec, QueryHeight, TimeoutSpec, Timestamp},
+    data::{IbcDatagram, WithChainId},
     PluginMessage, RawClientId, VoyagerClient, VoyagerMessage, FATAL_JSONRPC_ERROR_CODE,
 };
-use voyager_vm::{now, promise, Op};
+use voyager_vm::{data, noop, now, promise, seq, Op};
 
 use crate::{
     call,
     callback::{make_msgs, MakeBatchTransaction, MakeIbcMessagesFromUpdate, ModuleCallback},
     data::BatchableEvent,
+    upgrade::make_msg_upgrade_client,
     IbcSpecExt, Module,
 };
 
@@ -29,6 +32,10 @@ pub enum ModuleCall {
 
     MakeMsgV1(MakeMsg<IbcClassic>),
     MakeMsgUnion(MakeMsg<IbcUnion>),
+
+    MakeMsgTimeoutUnion(MakeMsgTimeout),
+
+    MakeMsgUpgradeClientV1(MakeMsgUpgradeClient),
 }
 
 /// Constructs multiple batch transactions, where all of the batches are provable at the new consensus height.
@@ -56,6 +63,8 @@ where
             )
             .await?;
 
+        ensure_client_updatable(&module.chain_id, &self.client_id, &client_meta)?;
+
         // let client_info = self
         //     .client
         //     .client_info(self.chain_id.clone(), client_id.clone())
@@ -152,3 +161,271 @@ pub struct MakeMsg<V: IbcSpecExt> {
     /// The original event that was emitted on the origin chain.
     pub event: V::BatchableEvent,
 }
+
+/// Time out packets that were sent from this chain, once their timeout has elapsed on the
+/// destination chain.
+///
+/// Packets that have been received on the destination chain in the meantime are skipped.
+#[model]
+pub struct MakeMsgTimeout {
+    /// The chain id of the chain that the packets were sent to.
+    pub destination_chain_id: ChainId,
+    /// The client on this chain tracking the destination chain.
+    pub client_id: UnionId,
+    /// The packets to time out. For packets sent with `BatchSend`, this is the original batch.
+    pub packets: Vec<Packet>,
+}
+
+impl MakeMsgTimeout {
+    /// Time out the packet of a union `SendPacket` event emitted on this chain.
+    #[must_use]
+    pub fn from_send_packet(
+        destination_chain_id: ChainId,
+        event: ibc_union_spec::SendPacket,
+    ) -> Self {
+        Self {
+            destination_chain_id,
+            client_id: event.packet.source_channel.connection.client_id,
+            packets: vec![Packet {
+                source_channel: event.packet.source_channel.channel_id.get(),
+                destination_channel: event.packet.destination_channel.channel_id.get(),
+                data: event.packet_data.into(),
+                timeout_height: event.packet.timeout_height,
+                timeout_timestamp: event.packet.timeout_timestamp,
+            }],
+        }
+    }
+
+    pub async fn call(
+        self,
+        module: &Module,
+        voyager_client: &VoyagerClient,
+    ) -> RpcResult<Op<VoyagerMessage>> {
+        if let Some(packet) = self
+            .packets
+            .iter()
+            .find(|packet| timeout(packet) == TimeoutSpec::Never)
+        {
+            return Err(ErrorObject::owned(
+                FATAL_JSONRPC_ERROR_CODE,
+                "packet has no timeout set",
+                Some(json!({ "packet": packet })),
+            ));
+        }
+
+        // the timestamp is queried before the height, such that it is at most the timestamp at
+        // the queried height. as such, if the timeout has elapsed at this timestamp, it has also
+        // elapsed at the proof height.
+        let timestamp = voyager_client
+            .query_latest_timestamp(self.destination_chain_id.clone(), true)
+            .await?;
+        let proof_height = voyager_client
+            .query_latest_height(self.destination_chain_id.clone(), true)
+            .await?;
+
+        let timestamp = Timestamp::from_nanos(timestamp.try_into().unwrap_or_default());
+
+        if !self
+            .packets
+            .iter()
+            .all(|packet| timeout(packet).is_elapsed(proof_height, timestamp))
+        {
+            debug!(
+                %proof_height,
+                %timestamp,
+                "timeout has not yet elapsed on the destination chain"
+            );
+
+            return Ok(self.wait_for_timeout(module, proof_height));
+        }
+
+        let client_info = voyager_client
+            .client_info::<IbcUnion>(module.chain_id.clone(), self.client_id)
+            .await?;
+
+        let mut msgs = vec![];
+
+        for packet in self.packets {
+            let path = ibc_union_spec::BatchReceiptsPath::from_packet(&packet);
+
+            let receipt = voyager_client
+                .query_ibc_state(
+                    self.destination_chain_id.clone(),
+                    QueryHeight::Specific(proof_height),
+                    path.clone(),
+                )
+                .await?
+                .state;
+
+            if receipt != ibc_union_spec::COMMITMENT_NULL {
+                info!(
+                    %path,
+                    "packet has been received on the destination chain, it cannot be timed out"
+                );
+
+                continue;
+            }
+
+            let proof = voyager_client
+                .query_ibc_proof(
+                    self.destination_chain_id.clone(),
+                    QueryHeight::Specific(proof_height),
+                    path,
+                )
+                .await?;
+
+            let encoded_proof = voyager_client
+                .encode_proof::<IbcUnion>(
+                    client_info.client_type.clone(),
+                    client_info.ibc_interface.clone(),
+                    proof.proof,
+                )
+                .await?;
+
+            msgs.push(IbcDatagram::new::<IbcUnion>(
+                ibc_union_spec::Datagram::from(ibc_union_spec::MsgPacketTimeout {
+                    packet,
+                    proof: encoded_proof,
+                    proof_height: proof_height.height(),
+                }),
+            ));
+        }
+
+        if msgs.is_empty() {
+            return Ok(noop());
+        }
+
+        Ok(seq([
+            call(WaitForTrustedHeight {
+                chain_id: module.chain_id.clone(),
+                ibc_spec_id: IbcUnion::ID,
+                client_id: RawClientId::new(self.client_id),
+                height: proof_height,
+            }),
+            data(WithChainId {
+                chain_id: module.chain_id.clone(),
+                message: msgs,
+            }),
+        ]))
+    }
+
+    /// Wait for the destination chain to reach the timeout of all of the packets, and then try
+    /// again.
+    fn wait_for_timeout(self, module: &Module, latest_height: Height) -> Op<VoyagerMessage> {
+        let timeout_height = self
+            .packets
+            .iter()
+            .map(|packet| packet.timeout_height)
+            .max()
+            .unwrap_or_default();
+        let timeout_timestamp = self
+            .packets
+            .iter()
+            .map(|packet| packet.timeout_timestamp)
+            .max()
+            .unwrap_or_default();
+
+        seq([
+            call(WaitForTimestamp {
+                chain_id: self.destination_chain_id.clone(),
+                timestamp: timeout_timestamp.try_into().unwrap_or(i64::MAX),
+                finalized: true,
+            }),
+            // union timeout heights are revisionless, so wait for the height in the current revision
+            call(WaitForHeight {
+                chain_id: self.destination_chain_id.clone(),
+                height: Height::new_with_revision(latest_height.revision(), timeout_height),
+                finalized: true,
+            }),
+            call(PluginMessage::new(
+                module.plugin_name(),
+                ModuleCall::from(self),
+            )),
+        ])
+    }
+}
+
+/// Upgrade a client on this chain across a planned upgrade of the chain it tracks, once the client
+/// has been updated to the upgrade height. See [`crate::upgrade`].
+#[model]
+pub struct MakeMsgUpgradeClient {
+    pub client_id: ClientId,
+    /// The height of the upgrade plan on the counterparty chain, in the revision before the
+    /// upgrade.
+    pub upgrade_height: Height,
+}
+
+impl MakeMsgUpgradeClient {
+    pub async fn call(
+        self,
+        module: &Module,
+        voyager_client: &VoyagerClient,
+    ) -> RpcResult<Op<VoyagerMessage>> {
+        let client_meta = voyager_client
+            .client_meta::<IbcClassic>(
+                module.chain_id.clone(),
+                QueryHeight::Latest,
+                self.client_id.clone(),
+            )
+            .await?;
+
+        ensure_client_updatable(&module.chain_id, &self.client_id, &client_meta)?;
+
+        let msg = make_msg_upgrade_client(
+            voyager_client,
+            &module.chain_id,
+            self.client_id.clone(),
+            &client_meta.chain_id,
+            self.upgrade_height,
+        )
+        .await?;
+
+        Ok(seq([
+            call(WaitForTrustedHeight {
+                chain_id: module.chain_id.clone(),
+                ibc_spec_id: IbcClassic::ID,
+                client_id: RawClientId::new(self.client_id),
+                height: self.upgrade_height,
+            }),
+            data(WithChainId {
+                chain_id: module.chain_id.clone(),
+                message: vec![IbcDatagram::new::<IbcClassic>(
+                    ibc_classic_spec::Datagram::from(msg),
+                )],
+            }),
+        ]))
+    }
+}
+
+fn timeout(packet: &Packet) -> TimeoutSpec {
+    TimeoutSpec::from_union(packet.timeout_height, packet.timeout_timestamp)
+}
+
+/// Refuse to build updates for frozen clients, since any update would be rejected on chain. Expired clients are only warned about, since whether they can still be updated depends on the light client (and they may be recovered out of band).
+pub(crate) fn ensure_client_updatable(
+    chain_id: &ChainId,
+    client_id: &impl std::fmt::Display,
+    client_meta: &ClientStateMeta,
+) -> RpcResult<()> {
+    match client_meta.status {
+        ClientStatus::Frozen => Err(ErrorObject::owned(
+            FATAL_JSONRPC_ERROR_CODE,
+            format!(
+                "client {client_id} on {chain_id} tracking {counterparty_chain_id} is frozen, \
+                refusing to update it",
+                counterparty_chain_id = client_meta.chain_id,
+            ),
+            None::<()>,
+        )),
+        ClientStatus::Expired => {
+            warn!(
+                %client_id,
+                %chain_id,
+                counterparty_chain_id = %client_meta.chain_id,
+                "client is expired, the update will likely fail"
+            );
+            Ok(())
+        }
+        ClientStatus::Active | ClientStatus::Unknown => Ok(()),
+    }
+}
@@ -3,7 +3,7 @@ use std::collections::VecDeque;
 use enumorph::Enumorph;
 use ibc_classic_spec::IbcClassic;
 use ibc_union_spec::IbcUnion;
-use itertools::Itertools;
+use itertools::{Either, Itertools};
 use jsonrpsee::{core::RpcResult, types::ErrorObject};
 use macros::model;
 use tracing::{debug, instrument, warn};
@@ -18,7 +18,8 @@ use voyager_vm::{call, conc, data, noop, promise, seq, Op};
 
 use crate::{
     call::{MakeMsg, ModuleCall},
-    data::BatchableEvent,
+    data::{BatchableEvent, ModuleData},
+    replay::guard_client_updates,
     IbcSpecExt, Module,
 };
 
@@ -77,6 +78,8 @@ where
             )
             .await?;
 
+        // the events are provable at the height of the last update, even if the client has already
+        // been updated past it
         let new_trusted_height = updates
             .updates
             .last()
@@ -84,14 +87,35 @@ where
             .0
             .height;
 
-        make_msgs(
+        let (updates, dropped) = guard_client_updates::<V>(
+            voyager_client,
+            &module_server.chain_id,
+            &self.client_id,
+            client_meta.height,
+            module_server.allow_historical_updates,
+            updates,
+        )
+        .await?;
+
+        let msgs = make_msgs(
             module_server,
             self.client_id,
             self.batches,
-            Some(updates),
+            (!updates.updates.is_empty()).then_some(updates),
             client_meta,
             new_trusted_height,
-        )
+        )?;
+
+        Ok(match dropped {
+            Some(dropped) => conc([
+                data(PluginMessage::new(
+                    module_server.plugin_name(),
+                    ModuleData::from(dropped),
+                )),
+                msgs,
+            ]),
+            None => msgs,
+        })
     }
 }
 
@@ -181,15 +205,18 @@ impl<V: IbcSpecExt> MakeBatchTransaction<V> {
             warn!("no IBC messages in queue! this likely means that all of the IBC messages that were queued to be sent were already sent to the destination chain");
         }
 
-        let mut msgs = datas
+        // handshake steps that were skipped (see the handshake module) are passed through as-is
+        let (datagrams, skipped): (Vec<_>, Vec<_>) =
+            datas
+                .into_iter()
+                .partition_map(|d| match IbcDatagram::try_from(d) {
+                    Ok(datagram) => Either::Left(datagram),
+                    Err(d) => Either::Right(d),
+                });
+
+        let mut msgs = datagrams
             .into_iter()
-            .map(|d| {
-                IbcDatagram::try_from(d)
-                    .unwrap()
-                    .decode_datagram::<V>()
-                    .unwrap()
-                    .unwrap()
-            })
+            .map(|d| d.decode_datagram::<V>().unwrap().unwrap())
             .peekable();
 
         // TODO: We may need to sort packet messages when we support ordered channels
@@ -205,7 +232,7 @@ impl<V: IbcSpecExt> MakeBatchTransaction<V> {
         //     (IbcMessage::TimeoutPacket(_), IbcMessage::TimeoutPacket(_)) => todo!(),
         // });
 
-        match self.updates {
+        let op = match self.updates {
             Some(updates) => data(WithChainId {
                 chain_id,
                 message: updates
@@ -246,6 +273,12 @@ impl<V: IbcSpecExt> MakeBatchTransaction<V> {
                     ])
                 }
             }
+        };
+
+        if skipped.is_empty() {
+            op
+        } else {
+            conc([op].into_iter().chain(skipped.into_iter().map(data)))
         }
     }
 }
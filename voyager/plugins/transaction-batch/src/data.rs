@@ -5,13 +5,22 @@ use macros::model;
 use subset_of::SubsetOf;
 use unionlabs::ibc::core::client::height::Height;
 
-use crate::IbcSpecExt;
+use crate::{
+    handshake::{HandshakeStepAlreadyCompleted, HandshakeStepConflict},
+    replay::DroppedClientUpdates,
+    IbcSpecExt,
+};
 
 #[model]
 #[derive(Enumorph, SubsetOf)]
 pub enum ModuleData {
     BatchEventsV1(EventBatch<IbcClassic>),
     BatchEventsUnion(EventBatch<IbcUnion>),
+
+    HandshakeStepAlreadyCompleted(HandshakeStepAlreadyCompleted),
+    HandshakeStepConflict(HandshakeStepConflict),
+
+    DroppedClientUpdates(DroppedClientUpdates),
 }
 
 #[model]
@@ -96,7 +105,11 @@ impl TryFrom<ibc_union_spec::FullEvent> for EventUnion {
             ibc_union_spec::FullEvent::ChannelOpenInit(e) => Ok(Self::ChannelOpenInit(e)),
             ibc_union_spec::FullEvent::ChannelOpenTry(e) => Ok(Self::ChannelOpenTry(e)),
             ibc_union_spec::FullEvent::ChannelOpenAck(e) => Ok(Self::ChannelOpenAck(e)),
-            ibc_union_spec::FullEvent::SendPacket(e) => Ok(Self::SendPacket(e)),
+            // packets are relayed individually from the event emitted when they were sent, so the
+            // events emitted when they are batched are not relayed again
+            ibc_union_spec::FullEvent::SendPacket(e) if e.batch.is_none() => {
+                Ok(Self::SendPacket(e))
+            }
             ibc_union_spec::FullEvent::WriteAcknowledgement(e) => Ok(Self::WriteAcknowledgement(e)),
             _ => Err(()),
         }
@@ -0,0 +1,919 @@
+//! Pre-submission checks for connection and channel handshake datagrams.
+//!
+//! A handshake datagram reverts if the destination end has already progressed past the step
+//! being relayed (i.e. the handshake was completed manually, or another relayer raced us),
+//! wasting the gas of the transaction. Before a handshake datagram is made, the destination end
+//! is read at the latest finalized height and compared against the state the step expects (see
+//! [`check_step`]). Steps that are already completed are skipped with a
+//! [`HandshakeStepAlreadyCompleted`], and steps that conflict with the destination state are
+//! skipped with a [`HandshakeStepConflict`].
+//!
+//! `*OpenTry` creates the destination end, so its identifier is not known when the step is
+//! relayed. For these steps the origin end is checked instead: if it is already open, the
+//! handshake was completed without us. A counterparty that ran `*OpenTry` but not yet `*OpenAck`
+//! can not be detected this way.
+
+use std::fmt::Display;
+
+use ibc_classic_spec::IbcClassic;
+use ibc_solidity::{ChannelState, ConnectionState};
+use ibc_union_spec::{IbcUnion, UnionId};
+use jsonrpsee::core::RpcResult;
+use macros::model;
+use tracing::{info, warn};
+use unionlabs::{
+    ibc::core::{
+        channel::{self, channel::Channel},
+        connection::{self, connection_end::ConnectionEnd},
+    },
+    id::{ChannelId, ConnectionId, PortId},
+};
+use voyager_message::{
+    core::{ChainId, IbcSpec, IbcSpecId, QueryHeight},
+    VoyagerClient,
+};
+
+use crate::data::{EventClassic, EventUnion, ModuleData};
+
+/// A step of a connection or channel handshake, named after the datagram that is relayed.
+#[model]
+#[derive(Copy, Eq)]
+pub enum HandshakeStep {
+    ConnectionOpenTry,
+    ConnectionOpenAck,
+    ConnectionOpenConfirm,
+    ChannelOpenTry,
+    ChannelOpenAck,
+    ChannelOpenConfirm,
+}
+
+impl HandshakeStep {
+    /// The state that the end checked for this step is expected to be in.
+    #[must_use]
+    pub const fn expected_state(self) -> EndState {
+        match self {
+            Self::ConnectionOpenTry
+            | Self::ConnectionOpenAck
+            | Self::ChannelOpenTry
+            | Self::ChannelOpenAck => EndState::Init,
+            Self::ConnectionOpenConfirm | Self::ChannelOpenConfirm => EndState::TryOpen,
+        }
+    }
+
+    const fn is_try(self) -> bool {
+        matches!(self, Self::ConnectionOpenTry | Self::ChannelOpenTry)
+    }
+}
+
+/// The handshake state of a connection or channel end, common to all IBC specs.
+#[model]
+#[derive(Copy, Eq)]
+pub enum EndState {
+    /// The end does not exist.
+    Missing,
+    Init,
+    TryOpen,
+    Open,
+    Closed,
+}
+
+/// A connection or channel end, as read from the chain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObservedEnd<Id> {
+    pub state: EndState,
+    /// The id of the end on the counterparty chain, if it is known to this end.
+    pub counterparty_id: Option<Id>,
+}
+
+impl<Id> ObservedEnd<Id> {
+    #[must_use]
+    pub const fn missing() -> Self {
+        Self {
+            state: EndState::Missing,
+            counterparty_id: None,
+        }
+    }
+}
+
+/// The outcome of [`check_step`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HandshakeCheck {
+    /// The step can be relayed.
+    Proceed,
+    /// The end has already progressed past this step.
+    AlreadyCompleted,
+    /// The end is in a state that this step can never succeed against.
+    Conflict(HandshakeConflict),
+}
+
+#[model]
+#[derive(Eq)]
+pub enum HandshakeConflict {
+    /// The end does not exist.
+    Missing,
+    /// The end is not in a state this step can be relayed to, nor past it.
+    UnexpectedState { expected: EndState, found: EndState },
+    /// The end has progressed past this step, but with a different counterparty.
+    CounterpartyMismatch {
+        expected: String,
+        found: Option<String>,
+    },
+}
+
+/// Compare `end` against the state expected by `step`.
+///
+/// For `*OpenTry`, `end` is the origin end (see the [module docs](self)). For all other steps,
+/// `end` is the destination end, and `counterparty_id` is the id of the origin end that the
+/// destination is expected to be paired with.
+pub fn check_step<Id: PartialEq + Display>(
+    step: HandshakeStep,
+    end: &ObservedEnd<Id>,
+    counterparty_id: &Id,
+) -> HandshakeCheck {
+    if step.is_try() {
+        return match end.state {
+            // the origin end may not be finalized yet
+            EndState::Missing | EndState::Init | EndState::TryOpen => HandshakeCheck::Proceed,
+            EndState::Open => HandshakeCheck::AlreadyCompleted,
+            found @ EndState::Closed => {
+                HandshakeCheck::Conflict(HandshakeConflict::UnexpectedState {
+                    expected: step.expected_state(),
+                    found,
+                })
+            }
+        };
+    }
+
+    let counterparty_matches = end.counterparty_id.as_ref() == Some(counterparty_id);
+
+    let mismatch = || {
+        HandshakeCheck::Conflict(HandshakeConflict::CounterpartyMismatch {
+            expected: counterparty_id.to_string(),
+            found: end.counterparty_id.as_ref().map(ToString::to_string),
+        })
+    };
+
+    match (step.expected_state(), end.state) {
+        (_, EndState::Missing) => HandshakeCheck::Conflict(HandshakeConflict::Missing),
+        // the counterparty is only set on the init end once the handshake is acknowledged
+        (EndState::Init, EndState::Init) => HandshakeCheck::Proceed,
+        (EndState::TryOpen, EndState::TryOpen) if counterparty_matches => HandshakeCheck::Proceed,
+        (_, EndState::Open) if counterparty_matches => HandshakeCheck::AlreadyCompleted,
+        (EndState::TryOpen, EndState::TryOpen) | (_, EndState::Open) => mismatch(),
+        (expected, found) => {
+            HandshakeCheck::Conflict(HandshakeConflict::UnexpectedState { expected, found })
+        }
+    }
+}
+
+/// The ends involved in a handshake step.
+#[model]
+pub struct HandshakeIds {
+    /// The chain that the event that triggered this step was emitted on.
+    pub origin_chain_id: ChainId,
+    pub origin_id: String,
+    /// The chain that this step is relayed to.
+    pub destination_chain_id: ChainId,
+    /// The id of the destination end, or `None` for `*OpenTry` steps (which create it).
+    pub destination_id: Option<String>,
+}
+
+/// A handshake step that was not relayed since the destination has already progressed past it.
+#[model]
+pub struct HandshakeStepAlreadyCompleted {
+    pub ibc_spec_id: IbcSpecId,
+    pub step: HandshakeStep,
+    pub ids: HandshakeIds,
+}
+
+/// A handshake step that was not relayed since it conflicts with the state of the destination.
+#[model]
+pub struct HandshakeStepConflict {
+    pub ibc_spec_id: IbcSpecId,
+    pub step: HandshakeStep,
+    pub ids: HandshakeIds,
+    pub conflict: HandshakeConflict,
+}
+
+/// Turn the outcome of a check into the data item to skip the step with, if any.
+fn skip<V: IbcSpec>(
+    step: HandshakeStep,
+    ids: HandshakeIds,
+    check: HandshakeCheck,
+) -> Option<ModuleData> {
+    match check {
+        HandshakeCheck::Proceed => None,
+        HandshakeCheck::AlreadyCompleted => {
+            info!(
+                ?step,
+                origin_chain_id = %ids.origin_chain_id,
+                origin_id = %ids.origin_id,
+                destination_chain_id = %ids.destination_chain_id,
+                destination_id = ?ids.destination_id,
+                "handshake step is already completed, skipping"
+            );
+
+            Some(
+                HandshakeStepAlreadyCompleted {
+                    ibc_spec_id: V::ID,
+                    step,
+                    ids,
+                }
+                .into(),
+            )
+        }
+        HandshakeCheck::Conflict(conflict) => {
+            warn!(
+                ?step,
+                origin_chain_id = %ids.origin_chain_id,
+                origin_id = %ids.origin_id,
+                destination_chain_id = %ids.destination_chain_id,
+                destination_id = ?ids.destination_id,
+                ?conflict,
+                "handshake step conflicts with the destination state, skipping"
+            );
+
+            Some(
+                HandshakeStepConflict {
+                    ibc_spec_id: V::ID,
+                    step,
+                    ids,
+                    conflict,
+                }
+                .into(),
+            )
+        }
+    }
+}
+
+#[must_use]
+pub fn union_connection_end(connection: Option<ibc_solidity::Connection>) -> ObservedEnd<UnionId> {
+    let Some(connection) = connection else {
+        return ObservedEnd::missing();
+    };
+
+    ObservedEnd {
+        state: match connection.state {
+            ConnectionState::Init => EndState::Init,
+            ConnectionState::TryOpen => EndState::TryOpen,
+            ConnectionState::Open => EndState::Open,
+            _ => EndState::Missing,
+        },
+        counterparty_id: UnionId::new(connection.counterparty_connection_id),
+    }
+}
+
+#[must_use]
+pub fn union_channel_end(channel: Option<ibc_solidity::Channel>) -> ObservedEnd<UnionId> {
+    let Some(channel) = channel else {
+        return ObservedEnd::missing();
+    };
+
+    ObservedEnd {
+        state: match channel.state {
+            ChannelState::Init => EndState::Init,
+            ChannelState::TryOpen => EndState::TryOpen,
+            ChannelState::Open => EndState::Open,
+            ChannelState::Closed => EndState::Closed,
+            _ => EndState::Missing,
+        },
+        counterparty_id: UnionId::new(channel.counterparty_channel_id),
+    }
+}
+
+#[must_use]
+pub fn classic_connection_end(connection: Option<ConnectionEnd>) -> ObservedEnd<ConnectionId> {
+    let Some(connection) = connection else {
+        return ObservedEnd::missing();
+    };
+
+    ObservedEnd {
+        state: match connection.state {
+            connection::state::State::UninitializedUnspecified => EndState::Missing,
+            connection::state::State::Init => EndState::Init,
+            connection::state::State::Tryopen => EndState::TryOpen,
+            connection::state::State::Open => EndState::Open,
+        },
+        counterparty_id: connection.counterparty.connection_id,
+    }
+}
+
+#[must_use]
+pub fn classic_channel_end(channel: Option<Channel>) -> ObservedEnd<ChannelId> {
+    let Some(channel) = channel else {
+        return ObservedEnd::missing();
+    };
+
+    ObservedEnd {
+        state: match channel.state {
+            channel::state::State::UninitializedUnspecified => EndState::Missing,
+            channel::state::State::Init => EndState::Init,
+            channel::state::State::Tryopen => EndState::TryOpen,
+            // channels that are being upgraded are still open
+            channel::state::State::Open
+            | channel::state::State::Flushing
+            | channel::state::State::Flushcomplete => EndState::Open,
+            channel::state::State::Closed => EndState::Closed,
+        },
+        counterparty_id: channel.counterparty.channel_id,
+    }
+}
+
+/// Check the handshake step triggered by `event`, returning the data item to skip the step with
+/// if it should not be relayed. Events that don't trigger a handshake step are not checked.
+pub async fn precheck_union(
+    voyager_client: &VoyagerClient,
+    origin_chain_id: &ChainId,
+    target_chain_id: &ChainId,
+    event: &EventUnion,
+) -> RpcResult<Option<ModuleData>> {
+    let connection = |chain_id: &ChainId, connection_id: UnionId| {
+        let chain_id = chain_id.clone();
+        async move {
+            voyager_client
+                .query_ibc_state(
+                    chain_id,
+                    QueryHeight::Finalized,
+                    ibc_union_spec::ConnectionPath {
+                        connection_id: connection_id.get(),
+                    },
+                )
+                .await
+                .map(|state| union_connection_end(state.state))
+        }
+    };
+
+    let channel = |chain_id: &ChainId, channel_id: UnionId| {
+        let chain_id = chain_id.clone();
+        async move {
+            voyager_client
+                .query_ibc_state(
+                    chain_id,
+                    QueryHeight::Finalized,
+                    ibc_union_spec::ChannelPath {
+                        channel_id: channel_id.get(),
+                    },
+                )
+                .await
+                .map(|state| union_channel_end(state.state))
+        }
+    };
+
+    let ids = |origin_id: UnionId, destination_id: Option<UnionId>| HandshakeIds {
+        origin_chain_id: origin_chain_id.clone(),
+        origin_id: origin_id.to_string(),
+        destination_chain_id: target_chain_id.clone(),
+        destination_id: destination_id.map(|id| id.to_string()),
+    };
+
+    let (step, ids, check) = match event {
+        EventUnion::ConnectionOpenInit(event) => {
+            let step = HandshakeStep::ConnectionOpenTry;
+            let end = connection(origin_chain_id, event.connection_id).await?;
+            (
+                step,
+                ids(event.connection_id, None),
+                check_step(step, &end, &event.connection_id),
+            )
+        }
+        EventUnion::ConnectionOpenTry(event) => {
+            let step = HandshakeStep::ConnectionOpenAck;
+            let end = connection(target_chain_id, event.counterparty_connection_id).await?;
+            (
+                step,
+                ids(event.connection_id, Some(event.counterparty_connection_id)),
+                check_step(step, &end, &event.connection_id),
+            )
+        }
+        EventUnion::ConnectionOpenAck(event) => {
+            let step = HandshakeStep::ConnectionOpenConfirm;
+            let end = connection(target_chain_id, event.counterparty_connection_id).await?;
+            (
+                step,
+                ids(event.connection_id, Some(event.counterparty_connection_id)),
+                check_step(step, &end, &event.connection_id),
+            )
+        }
+        EventUnion::ChannelOpenInit(event) => {
+            let step = HandshakeStep::ChannelOpenTry;
+            let end = channel(origin_chain_id, event.channel_id).await?;
+            (
+                step,
+                ids(event.channel_id, None),
+                check_step(step, &end, &event.channel_id),
+            )
+        }
+        EventUnion::ChannelOpenTry(event) => {
+            let step = HandshakeStep::ChannelOpenAck;
+            let end = channel(target_chain_id, event.counterparty_channel_id).await?;
+            (
+                step,
+                ids(event.channel_id, Some(event.counterparty_channel_id)),
+                check_step(step, &end, &event.channel_id),
+            )
+        }
+        EventUnion::ChannelOpenAck(event) => {
+            let step = HandshakeStep::ChannelOpenConfirm;
+            let end = channel(target_chain_id, event.counterparty_channel_id).await?;
+            (
+                step,
+                ids(event.channel_id, Some(event.counterparty_channel_id)),
+                check_step(step, &end, &event.channel_id),
+            )
+        }
+        EventUnion::SendPacket(_) | EventUnion::WriteAcknowledgement(_) => return Ok(None),
+    };
+
+    Ok(skip::<IbcUnion>(step, ids, check))
+}
+
+/// [`precheck_union`], for IBC classic.
+pub async fn precheck_classic(
+    voyager_client: &VoyagerClient,
+    origin_chain_id: &ChainId,
+    target_chain_id: &ChainId,
+    event: &EventClassic,
+) -> RpcResult<Option<ModuleData>> {
+    let connection = |chain_id: &ChainId, connection_id: ConnectionId| {
+        let chain_id = chain_id.clone();
+        async move {
+            voyager_client
+                .query_ibc_state(
+                    chain_id,
+                    QueryHeight::Finalized,
+                    ibc_classic_spec::ConnectionPath { connection_id },
+                )
+                .await
+                .map(|state| classic_connection_end(state.state))
+        }
+    };
+
+    let channel = |chain_id: &ChainId, port_id: PortId, channel_id: ChannelId| {
+        let chain_id = chain_id.clone();
+        async move {
+            voyager_client
+                .query_ibc_state(
+                    chain_id,
+                    QueryHeight::Finalized,
+                    ibc_classic_spec::ChannelEndPath {
+                        port_id,
+                        channel_id,
+                    },
+                )
+                .await
+                .map(|state| classic_channel_end(state.state))
+        }
+    };
+
+    let ids = |origin_id: String, destination_id: Option<String>| HandshakeIds {
+        origin_chain_id: origin_chain_id.clone(),
+        origin_id,
+        destination_chain_id: target_chain_id.clone(),
+        destination_id,
+    };
+
+    let (step, ids, check) = match event {
+        EventClassic::ConnectionOpenInit(event) => {
+            let step = HandshakeStep::ConnectionOpenTry;
+            let end = connection(origin_chain_id, event.connection_id.clone()).await?;
+            (
+                step,
+                ids(event.connection_id.to_string(), None),
+                check_step(step, &end, &event.connection_id),
+            )
+        }
+        EventClassic::ConnectionOpenTry(event) => {
+            let step = HandshakeStep::ConnectionOpenAck;
+            let end = connection(target_chain_id, event.counterparty_connection_id.clone()).await?;
+            (
+                step,
+                ids(
+                    event.connection_id.to_string(),
+                    Some(event.counterparty_connection_id.to_string()),
+                ),
+                check_step(step, &end, &event.connection_id),
+            )
+        }
+        EventClassic::ConnectionOpenAck(event) => {
+            let step = HandshakeStep::ConnectionOpenConfirm;
+            let end = connection(target_chain_id, event.counterparty_connection_id.clone()).await?;
+            (
+                step,
+                ids(
+                    event.connection_id.to_string(),
+                    Some(event.counterparty_connection_id.to_string()),
+                ),
+                check_step(step, &end, &event.connection_id),
+            )
+        }
+        EventClassic::ChannelOpenInit(event) => {
+            let step = HandshakeStep::ChannelOpenTry;
+            let end = channel(
+                origin_chain_id,
+                event.port_id.clone(),
+                event.channel_id.clone(),
+            )
+            .await?;
+            (
+                step,
+                ids(format!("{}/{}", event.port_id, event.channel_id), None),
+                check_step(step, &end, &event.channel_id),
+            )
+        }
+        EventClassic::ChannelOpenTry(event) => {
+            let step = HandshakeStep::ChannelOpenAck;
+            let end = channel(
+                target_chain_id,
+                event.counterparty_port_id.clone(),
+                event.counterparty_channel_id.clone(),
+            )
+            .await?;
+            (
+                step,
+                ids(
+                    format!("{}/{}", event.port_id, event.channel_id),
+                    Some(format!(
+                        "{}/{}",
+                        event.counterparty_port_id, event.counterparty_channel_id
+                    )),
+                ),
+                check_step(step, &end, &event.channel_id),
+            )
+        }
+        EventClassic::ChannelOpenAck(event) => {
+            let step = HandshakeStep::ChannelOpenConfirm;
+            let end = channel(
+                target_chain_id,
+                event.counterparty_port_id.clone(),
+                event.counterparty_channel_id.clone(),
+            )
+            .await?;
+            (
+                step,
+                ids(
+                    format!("{}/{}", event.port_id, event.channel_id),
+                    Some(format!(
+                        "{}/{}",
+                        event.counterparty_port_id, event.counterparty_channel_id
+                    )),
+                ),
+                check_step(step, &end, &event.channel_id),
+            )
+        }
+        EventClassic::SendPacket(_) | EventClassic::WriteAcknowledgement(_) => return Ok(None),
+    };
+
+    Ok(skip::<IbcClassic>(step, ids, check))
+}
+
+#[cfg(test)]
+mod tests {
+    use unionlabs::ibc::core::{
+        channel::order::Order, commitment::merkle_prefix::MerklePrefix,
+        connection::version::Version,
+    };
+
+    use super::*;
+
+    const STATES: [EndState; 5] = [
+        EndState::Missing,
+        EndState::Init,
+        EndState::TryOpen,
+        EndState::Open,
+        EndState::Closed,
+    ];
+
+    fn end(state: EndState, counterparty_id: Option<u32>) -> ObservedEnd<u32> {
+        ObservedEnd {
+            state,
+            counterparty_id,
+        }
+    }
+
+    fn unexpected(expected: EndState, found: EndState) -> HandshakeCheck {
+        HandshakeCheck::Conflict(HandshakeConflict::UnexpectedState { expected, found })
+    }
+
+    /// The outcome of relaying `step` against an end in `state`, with the expected counterparty.
+    fn expected_check(step: HandshakeStep, state: EndState) -> HandshakeCheck {
+        use EndState::*;
+        use HandshakeCheck::*;
+
+        match (step.expected_state(), step.is_try(), state) {
+            (_, true, Missing | Init | TryOpen) => Proceed,
+            (_, true, Open) => AlreadyCompleted,
+            (_, true, Closed) => unexpected(Init, Closed),
+
+            (_, false, Missing) => Conflict(HandshakeConflict::Missing),
+            (_, false, Open) => AlreadyCompleted,
+
+            (Init, false, Init) => Proceed,
+            (Init, false, found @ (TryOpen | Closed)) => unexpected(Init, found),
+
+            (TryOpen, false, TryOpen) => Proceed,
+            (TryOpen, false, found @ (Init | Closed)) => unexpected(TryOpen, found),
+
+            (expected, _, _) => panic!("no step expects {expected:?}"),
+        }
+    }
+
+    #[test]
+    fn step_matrix() {
+        for step in [
+            HandshakeStep::ConnectionOpenTry,
+            HandshakeStep::ConnectionOpenAck,
+            HandshakeStep::ConnectionOpenConfirm,
+            HandshakeStep::ChannelOpenTry,
+            HandshakeStep::ChannelOpenAck,
+            HandshakeStep::ChannelOpenConfirm,
+        ] {
+            for state in STATES {
+                // the init end only knows its counterparty once the handshake is acknowledged
+                let counterparty_id = (state != EndState::Init).then_some(1);
+
+                assert_eq!(
+                    check_step(step, &end(state, counterparty_id), &1),
+                    expected_check(step, state),
+                    "{step:?} against {state:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn counterparty_mismatch() {
+        let mismatch = |found: Option<u32>| {
+            HandshakeCheck::Conflict(HandshakeConflict::CounterpartyMismatch {
+                expected: "1".to_owned(),
+                found: found.map(|id| id.to_string()),
+            })
+        };
+
+        for (step, state) in [
+            (HandshakeStep::ConnectionOpenAck, EndState::Open),
+            (HandshakeStep::ConnectionOpenConfirm, EndState::TryOpen),
+            (HandshakeStep::ConnectionOpenConfirm, EndState::Open),
+            (HandshakeStep::ChannelOpenAck, EndState::Open),
+            (HandshakeStep::ChannelOpenConfirm, EndState::TryOpen),
+            (HandshakeStep::ChannelOpenConfirm, EndState::Open),
+        ] {
+            assert_eq!(
+                check_step(step, &end(state, Some(2)), &1),
+                mismatch(Some(2)),
+                "{step:?} against {state:?}"
+            );
+            assert_eq!(
+                check_step(step, &end(state, None), &1),
+                mismatch(None),
+                "{step:?} against {state:?}"
+            );
+        }
+
+        // the counterparty of the origin end is not checked for `*OpenTry`
+        assert_eq!(
+            check_step(
+                HandshakeStep::ConnectionOpenTry,
+                &end(EndState::Open, Some(2)),
+                &1
+            ),
+            HandshakeCheck::AlreadyCompleted
+        );
+    }
+
+    fn union_connection(
+        state: ConnectionState,
+        counterparty_connection_id: u32,
+    ) -> ibc_solidity::Connection {
+        ibc_solidity::Connection {
+            state,
+            client_id: 1,
+            counterparty_client_id: 2,
+            counterparty_connection_id,
+        }
+    }
+
+    fn union_channel(state: ChannelState, counterparty_channel_id: u32) -> ibc_solidity::Channel {
+        ibc_solidity::Channel {
+            state,
+            connection_id: 1,
+            counterparty_channel_id,
+            counterparty_port_id: b"port".to_vec().into(),
+            version: "ics20-1".to_owned(),
+        }
+    }
+
+    #[test]
+    fn union_connection_matrix() {
+        let check = |step, connection| {
+            check_step(
+                step,
+                &union_connection_end(connection),
+                &UnionId::new(7).unwrap(),
+            )
+        };
+
+        for (state, counterparty_connection_id, end_state) in [
+            (ConnectionState::Unspecified, 0, EndState::Missing),
+            (ConnectionState::Init, 0, EndState::Init),
+            (ConnectionState::TryOpen, 7, EndState::TryOpen),
+            (ConnectionState::Open, 7, EndState::Open),
+        ] {
+            for step in [
+                HandshakeStep::ConnectionOpenTry,
+                HandshakeStep::ConnectionOpenAck,
+                HandshakeStep::ConnectionOpenConfirm,
+            ] {
+                assert_eq!(
+                    check(
+                        step,
+                        Some(union_connection(state, counterparty_connection_id))
+                    ),
+                    expected_check(step, end_state),
+                    "{step:?} against {end_state:?}"
+                );
+            }
+        }
+
+        assert_eq!(
+            check(HandshakeStep::ConnectionOpenAck, None),
+            HandshakeCheck::Conflict(HandshakeConflict::Missing)
+        );
+    }
+
+    #[test]
+    fn union_channel_matrix() {
+        let check = |step, channel| {
+            check_step(step, &union_channel_end(channel), &UnionId::new(7).unwrap())
+        };
+
+        for (state, counterparty_channel_id, end_state) in [
+            (ChannelState::Unspecified, 0, EndState::Missing),
+            (ChannelState::Init, 0, EndState::Init),
+            (ChannelState::TryOpen, 7, EndState::TryOpen),
+            (ChannelState::Open, 7, EndState::Open),
+            (ChannelState::Closed, 7, EndState::Closed),
+        ] {
+            for step in [
+                HandshakeStep::ChannelOpenTry,
+                HandshakeStep::ChannelOpenAck,
+                HandshakeStep::ChannelOpenConfirm,
+            ] {
+                assert_eq!(
+                    check(step, Some(union_channel(state, counterparty_channel_id))),
+                    expected_check(step, end_state),
+                    "{step:?} against {end_state:?}"
+                );
+            }
+        }
+
+        // an unset counterparty is stored as 0
+        assert_eq!(
+            union_channel_end(Some(union_channel(ChannelState::Init, 0))).counterparty_id,
+            None
+        );
+    }
+
+    fn classic_connection(
+        state: connection::state::State,
+        counterparty_connection_id: Option<ConnectionId>,
+    ) -> ConnectionEnd {
+        ConnectionEnd {
+            client_id: unionlabs::id::ClientId::new("07-tendermint", 1),
+            versions: vec![Version {
+                identifier: "1".to_owned(),
+                features: vec![Order::Unordered],
+            }],
+            state,
+            counterparty: connection::counterparty::Counterparty {
+                client_id: unionlabs::id::ClientId::new("07-tendermint", 2),
+                connection_id: counterparty_connection_id,
+                prefix: MerklePrefix {
+                    key_prefix: b"ibc".to_vec().into(),
+                },
+            },
+            delay_period: 0,
+        }
+    }
+
+    fn classic_channel(
+        state: channel::state::State,
+        counterparty_channel_id: Option<ChannelId>,
+    ) -> Channel {
+        Channel {
+            state,
+            ordering: Order::Unordered,
+            counterparty: channel::counterparty::Counterparty {
+                port_id: PortId::new("transfer").unwrap(),
+                channel_id: counterparty_channel_id,
+            },
+            connection_hops: vec![ConnectionId::new(1)],
+            version: "ics20-1".to_owned(),
+            upgrade_sequence: 0,
+        }
+    }
+
+    #[test]
+    fn classic_connection_matrix() {
+        use connection::state::State;
+
+        let counterparty_id = ConnectionId::new(7);
+
+        for (state, counterparty_connection_id, end_state) in [
+            (State::UninitializedUnspecified, None, EndState::Missing),
+            (State::Init, None, EndState::Init),
+            (
+                State::Tryopen,
+                Some(counterparty_id.clone()),
+                EndState::TryOpen,
+            ),
+            (State::Open, Some(counterparty_id.clone()), EndState::Open),
+        ] {
+            for step in [
+                HandshakeStep::ConnectionOpenTry,
+                HandshakeStep::ConnectionOpenAck,
+                HandshakeStep::ConnectionOpenConfirm,
+            ] {
+                assert_eq!(
+                    check_step(
+                        step,
+                        &classic_connection_end(Some(classic_connection(
+                            state,
+                            counterparty_connection_id.clone()
+                        ))),
+                        &counterparty_id
+                    ),
+                    expected_check(step, end_state),
+                    "{step:?} against {end_state:?}"
+                );
+            }
+        }
+
+        assert_eq!(
+            check_step(
+                HandshakeStep::ConnectionOpenConfirm,
+                &classic_connection_end(Some(classic_connection(
+                    State::Open,
+                    Some(ConnectionId::new(8))
+                ))),
+                &counterparty_id
+            ),
+            HandshakeCheck::Conflict(HandshakeConflict::CounterpartyMismatch {
+                expected: "7".to_owned(),
+                found: Some("8".to_owned()),
+            })
+        );
+    }
+
+    #[test]
+    fn classic_channel_matrix() {
+        use channel::state::State;
+
+        let counterparty_id = ChannelId::new(7);
+
+        for (state, counterparty_channel_id, end_state) in [
+            (State::UninitializedUnspecified, None, EndState::Missing),
+            (State::Init, None, EndState::Init),
+            (
+                State::Tryopen,
+                Some(counterparty_id.clone()),
+                EndState::TryOpen,
+            ),
+            (State::Open, Some(counterparty_id.clone()), EndState::Open),
+            // channels being upgraded are open
+            (
+                State::Flushing,
+                Some(counterparty_id.clone()),
+                EndState::Open,
+            ),
+            (
+                State::Flushcomplete,
+                Some(counterparty_id.clone()),
+                EndState::Open,
+            ),
+            (
+                State::Closed,
+                Some(counterparty_id.clone()),
+                EndState::Closed,
+            ),
+        ] {
+            for step in [
+                HandshakeStep::ChannelOpenTry,
+                HandshakeStep::ChannelOpenAck,
+                HandshakeStep::ChannelOpenConfirm,
+            ] {
+                assert_eq!(
+                    check_step(
+                        step,
+                        &classic_channel_end(Some(classic_channel(
+                            state,
+                            counterparty_channel_id.clone()
+                        ))),
+                        &counterparty_id
+                    ),
+                    expected_check(step, end_state),
+                    "{step:?} against {end_state:?}"
+                );
+            }
+        }
+    }
+}
@@ -6,12 +6,10 @@ use std::{
     time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
-use alloy::sol_types::SolValue;
 use either::Either;
 use futures::{stream::FuturesOrdered, StreamExt, TryStreamExt};
 use ibc_classic_spec::IbcClassic;
-use ibc_solidity::Packet;
-use ibc_union_spec::IbcUnion;
+use ibc_union_spec::{IbcUnion, UnionId};
 use itertools::Itertools;
 use jsonrpsee::{
     core::{async_trait, RpcResult},
@@ -22,7 +20,6 @@ use serde::{Deserialize, Serialize};
 use tracing::{debug, error, info, instrument, trace, warn};
 use unionlabs::{
     bytes::Bytes,
-    ethereum::keccak256,
     ibc::core::{
         client::height::Height,
         commitment::merkle_prefix::MerklePrefix,
@@ -32,11 +29,12 @@ use unionlabs::{
     },
     id::{ClientId, ConnectionId},
     traits::Member,
-    DELAY_PERIOD,
+    ErrorReporter, DELAY_PERIOD,
 };
 use voyager_message::{
     call::WaitForHeight,
-    core::{ChainId, IbcSpec, QueryHeight},
+    cmd::CmdOutput,
+    core::{ChainId, IbcSpec, IbcStorePathKey, QueryHeight},
     data::{ChainEvent, Data, IbcDatagram},
     module::{PluginInfo, PluginServer},
     DefaultCmd, ExtensionsExt, Plugin, PluginMessage, RawClientId, VoyagerClient, VoyagerMessage,
@@ -50,9 +48,14 @@ use crate::{
     data::{BatchableEvent, EventBatch, EventClassic, EventUnion, ModuleData},
 };
 
+pub mod ack;
 pub mod call;
 pub mod callback;
 pub mod data;
+pub mod handshake;
+pub mod replay;
+pub mod union_msg;
+pub mod upgrade;
 
 #[tokio::main(flavor = "multi_thread")]
 async fn main() {
@@ -63,6 +66,7 @@ async fn main() {
 pub struct Module {
     pub chain_id: ChainId,
     pub client_configs: ClientConfigs,
+    pub allow_historical_updates: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -75,6 +79,11 @@ pub enum ClientConfigs {
 pub struct Config {
     pub chain_id: ChainId,
     pub client_configs: ClientConfigsSerde,
+    /// Submit client updates that are older than the latest height of the client, as long as the
+    /// client doesn't have a consensus state at the height of the update. By default, only updates
+    /// that are strictly newer than the latest height of the client are submitted.
+    #[serde(default)]
+    pub allow_historical_updates: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -115,14 +124,23 @@ impl SpecificClientConfig {
 pub trait IbcSpecExt: IbcSpec {
     type BatchableEvent: TryFrom<Self::Event, Error = ()> + Eq + Member;
 
+    type ConsensusStatePath: IbcStorePathKey<Spec = Self, Value = Bytes>;
+
     fn proof_height(msg: &Self::Datagram) -> Height;
 
     fn event_name(msg: &Self::BatchableEvent) -> &'static str;
+
+    fn consensus_state_path_key(
+        client_id: Self::ClientId,
+        height: Height,
+    ) -> Self::ConsensusStatePath;
 }
 
 impl IbcSpecExt for IbcClassic {
     type BatchableEvent = crate::data::EventClassic;
 
+    type ConsensusStatePath = ibc_classic_spec::ClientConsensusStatePath;
+
     fn proof_height(msg: &Self::Datagram) -> Height {
         msg.proof_height()
             .expect("all batchable messages have a proof")
@@ -140,11 +158,20 @@ impl IbcSpecExt for IbcClassic {
             EventClassic::WriteAcknowledgement(_) => "write_acknowledgement",
         }
     }
+
+    fn consensus_state_path_key(
+        client_id: Self::ClientId,
+        height: Height,
+    ) -> Self::ConsensusStatePath {
+        ibc_classic_spec::ClientConsensusStatePath { client_id, height }
+    }
 }
 
 impl IbcSpecExt for IbcUnion {
     type BatchableEvent = crate::data::EventUnion;
 
+    type ConsensusStatePath = ibc_union_spec::ConsensusStatePath;
+
     fn proof_height(msg: &Self::Datagram) -> Height {
         msg.proof_height()
             .expect("all batchable messages have a proof")
@@ -162,6 +189,16 @@ impl IbcSpecExt for IbcUnion {
             EventUnion::WriteAcknowledgement(_) => "write_acknowledgement",
         }
     }
+
+    fn consensus_state_path_key(
+        client_id: Self::ClientId,
+        height: Height,
+    ) -> Self::ConsensusStatePath {
+        ibc_union_spec::ConsensusStatePath {
+            client_id: client_id.get(),
+            height: height.height(),
+        }
+    }
 }
 
 impl ClientConfigs {
@@ -244,10 +281,10 @@ if ."@type" == "data" then
             and ($event_data.connection.counterparty.client_id as $client_id | {clients_filter})
         ) or (
             $event_type == "send_packet"
-            and ($event_data.packet.destination_channel.connection.client_id as $client_id | {clients_filter})
+            and ($event_data.packet.destination_channel.connection_hops[0].client_id as $client_id | {clients_filter})
         ) or (
             $event_type == "write_acknowledgement"
-            and ($event_data.packet.source_channel.connection.client_id as $client_id | {clients_filter})
+            and ($event_data.packet.source_channel.connection_hops[0].client_id as $client_id | {clients_filter})
         ) or ($data."@type" == "plugin"
             and $data."@value".plugin == "{plugin_name}"
             and $data."@value".message."@type" == "event_batch")
@@ -276,6 +313,8 @@ if ."@type" == "data" then
             and ($event_data.connection.counterparty_client_id as $client_id | {clients_filter})
         ) or (
             $event_type == "send_packet"
+            # packets that were batched were already sent individually
+            and $event_data.batch == null
             and ($event_data.packet.destination_channel.connection.client_id as $client_id | {clients_filter})
         ) or (
             $event_type == "write_acknowledgement"
@@ -296,10 +335,13 @@ end
                 ibc_v1_id = IbcClassic::ID,
                 ibc_union_id = IbcUnion::ID,
             ),
+            kind: None,
+            chains: vec![],
+            ibc_specs: vec![],
         }
     }
 
-    async fn cmd(_config: Self::Config, cmd: Self::Cmd) {
+    async fn cmd(_config: Self::Config, cmd: Self::Cmd) -> Result<Box<dyn CmdOutput>, BoxDynError> {
         match cmd {}
     }
 }
@@ -318,6 +360,7 @@ impl Module {
         Self {
             chain_id: config.chain_id,
             client_configs: ClientConfigs::new(config.client_configs),
+            allow_historical_updates: config.allow_historical_updates,
         }
     }
 }
@@ -342,10 +385,14 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
             ModuleCall::MakeTransactionBatchesWithUpdateUnion(mk) => {
                 mk.call(self, e.try_get()?).await
             }
-            ModuleCall::MakeMsgV1(make_msg_v1) => do_make_msg_v1(voyager_client, make_msg_v1).await,
+            ModuleCall::MakeMsgV1(make_msg_v1) => {
+                do_make_msg_v1(self, voyager_client, make_msg_v1).await
+            }
             ModuleCall::MakeMsgUnion(make_msg_union) => {
-                do_make_msg_union(voyager_client, make_msg_union).await
+                do_make_msg_union(self, voyager_client, make_msg_union).await
             }
+            ModuleCall::MakeMsgTimeoutUnion(mk) => mk.call(self, voyager_client).await,
+            ModuleCall::MakeMsgUpgradeClientV1(mk) => mk.call(self, voyager_client).await,
         }
     }
 
@@ -381,6 +428,7 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
     )
 )]
 async fn do_make_msg_union(
+    module: &Module,
     voyager_client: &VoyagerClient,
     MakeMsg {
         origin_chain_id,
@@ -389,447 +437,39 @@ async fn do_make_msg_union(
         event,
     }: MakeMsg<IbcUnion>,
 ) -> RpcResult<Op<VoyagerMessage>> {
-    match event {
-        EventUnion::ConnectionOpenInit(connection_open_init_event) => {
-            let client_id = connection_open_init_event.client_id;
-            let counterparty_client_id = connection_open_init_event.counterparty_client_id;
-            let connection_id = connection_open_init_event.connection_id;
-
-            // info of the client on the target chain that will verify the storage
-            // proofs
-            let target_client_info = voyager_client
-                // counterparty_client_id from open_init/try is the client on the target chain
-                .client_info::<IbcUnion>(target_chain_id.clone(), counterparty_client_id)
-                .await?;
-
-            debug!(
-                %counterparty_client_id,
-                %target_client_info.client_type,
-                %target_client_info.ibc_interface,
-                %target_client_info.metadata,
-            );
-
-            // info of the client on the origin chain, this is used to decode the stored
-            // client state
-            let origin_client_info = voyager_client
-                // client_id from open_init/try is the client on the origin chain
-                .client_info::<IbcUnion>(origin_chain_id.clone(), client_id)
-                .await?;
-
-            debug!(
-                %client_id,
-                %origin_client_info.client_type,
-                %origin_client_info.ibc_interface,
-                %origin_client_info.metadata,
-            );
-
-            // the connection end as stored by the origin chain after open_init/try
-            let connection_state = voyager_client
-                .query_ibc_state(
-                    origin_chain_id.clone(),
-                    origin_chain_proof_height.into(),
-                    ibc_union_spec::ConnectionPath { connection_id },
-                )
-                .await?
-                .state
-                .ok_or(ErrorObject::owned(
-                    FATAL_JSONRPC_ERROR_CODE,
-                    "connection must exist",
-                    None::<()>,
-                ))?;
-            debug!(
-                connection_state = %serde_json::to_string(&connection_state).unwrap(),
-            );
-
-            // proof of connection_state, encoded for the client on the target chain
-            let connection_proof = voyager_client
-                .query_ibc_proof(
-                    origin_chain_id.clone(),
-                    QueryHeight::Specific(origin_chain_proof_height),
-                    ibc_union_spec::ConnectionPath { connection_id },
-                )
-                .await?
-                .proof;
-            debug!(%connection_proof);
-
-            let encoded_connection_state_proof = voyager_client
-                .encode_proof::<IbcUnion>(
-                    target_client_info.client_type.clone(),
-                    target_client_info.ibc_interface.clone(),
-                    connection_proof,
-                )
-                .await?;
-            debug!(%encoded_connection_state_proof);
-
-            Ok(data(IbcDatagram::new::<IbcUnion>(
-                ibc_union_spec::Datagram::from(ibc_union_spec::MsgConnectionOpenTry {
-                    client_id: connection_open_init_event.counterparty_client_id,
-                    counterparty_client_id: connection_open_init_event.client_id,
-                    counterparty_connection_id: connection_open_init_event.connection_id,
-                    proof_height: origin_chain_proof_height.height(),
-                    proof_init: encoded_connection_state_proof,
-                }),
-            )))
-        }
-
-        EventUnion::ConnectionOpenTry(connection_open_try_event) => {
-            let client_id = connection_open_try_event.client_id;
-            let counterparty_client_id = connection_open_try_event.counterparty_client_id;
-            let connection_id = connection_open_try_event.connection_id;
-
-            // info of the client on the target chain that will verify the storage
-            // proofs
-            let target_client_info = voyager_client
-                // counterparty_client_id from open_init/try is the client on the target chain
-                .client_info::<IbcUnion>(target_chain_id.clone(), counterparty_client_id)
-                .await?;
-
-            debug!(
-                %counterparty_client_id,
-                %target_client_info.client_type,
-                %target_client_info.ibc_interface,
-                %target_client_info.metadata,
-            );
-
-            // info of the client on the origin chain, this is used to decode the stored
-            // client state
-            let origin_client_info = voyager_client
-                // client_id from open_init/try is the client on the origin chain
-                .client_info::<IbcUnion>(origin_chain_id.clone(), client_id)
-                .await?;
-
-            debug!(
-                %client_id,
-                %origin_client_info.client_type,
-                %origin_client_info.ibc_interface,
-                %origin_client_info.metadata,
-            );
-
-            // the connection end as stored by the origin chain after open_init/try
-            let connection_state = voyager_client
-                .query_ibc_state(
-                    origin_chain_id.clone(),
-                    origin_chain_proof_height.into(),
-                    ibc_union_spec::ConnectionPath { connection_id },
-                )
-                .await?
-                .state
-                .ok_or(ErrorObject::owned(
-                    FATAL_JSONRPC_ERROR_CODE,
-                    "connection must exist",
-                    None::<()>,
-                ))?;
-            debug!(
-                connection_state = %serde_json::to_string(&connection_state).unwrap(),
-            );
-
-            // proof of connection_state, encoded for the client on the target chain
-            let connection_proof = voyager_client
-                .query_ibc_proof(
-                    origin_chain_id.clone(),
-                    QueryHeight::Specific(origin_chain_proof_height),
-                    ibc_union_spec::ConnectionPath { connection_id },
-                )
-                .await?
-                .proof;
-            debug!(%connection_proof);
-
-            let encoded_connection_state_proof = voyager_client
-                .encode_proof::<IbcUnion>(
-                    target_client_info.client_type.clone(),
-                    target_client_info.ibc_interface.clone(),
-                    connection_proof,
-                )
-                .await?;
-            debug!(%encoded_connection_state_proof);
-
-            Ok(data(IbcDatagram::new::<IbcUnion>(
-                ibc_union_spec::Datagram::from(ibc_union_spec::MsgConnectionOpenAck {
-                    connection_id: connection_open_try_event.counterparty_connection_id,
-                    counterparty_connection_id: connection_open_try_event.connection_id,
-                    proof_height: origin_chain_proof_height.height(),
-                    proof_try: encoded_connection_state_proof,
-                }),
-            )))
-        }
-
-        EventUnion::ConnectionOpenAck(connection_open_ack_event) => {
-            let client_id = connection_open_ack_event.client_id;
-            let counterparty_client_id = connection_open_ack_event.counterparty_client_id;
-            let connection_id = connection_open_ack_event.connection_id;
-
-            // info of the client on the target chain that will verify the storage
-            // proofs
-            let target_client_info = voyager_client
-                // counterparty_client_id from open_init/ack is the client on the target chain
-                .client_info::<IbcUnion>(target_chain_id.clone(), counterparty_client_id)
-                .await?;
-
-            debug!(
-                %counterparty_client_id,
-                %target_client_info.client_type,
-                %target_client_info.ibc_interface,
-                %target_client_info.metadata,
-            );
-
-            // info of the client on the origin chain, this is used to decode the stored
-            // client state
-            let origin_client_info = voyager_client
-                // client_id from open_init/ack is the client on the origin chain
-                .client_info::<IbcUnion>(origin_chain_id.clone(), client_id)
-                .await?;
-
-            debug!(
-                %client_id,
-                %origin_client_info.client_type,
-                %origin_client_info.ibc_interface,
-                %origin_client_info.metadata,
-            );
-
-            // the connection end as stored by the origin chain after open_init/ack
-            let connection_state = voyager_client
-                .query_ibc_state(
-                    origin_chain_id.clone(),
-                    origin_chain_proof_height.into(),
-                    ibc_union_spec::ConnectionPath { connection_id },
-                )
-                .await?
-                .state
-                .ok_or(ErrorObject::owned(
-                    FATAL_JSONRPC_ERROR_CODE,
-                    "connection must exist",
-                    None::<()>,
-                ))?;
-            debug!(
-                connection_state = %serde_json::to_string(&connection_state).unwrap(),
-            );
-
-            // proof of connection_state, encoded for the client on the target chain
-            let connection_proof = voyager_client
-                .query_ibc_proof(
-                    origin_chain_id.clone(),
-                    QueryHeight::Specific(origin_chain_proof_height),
-                    ibc_union_spec::ConnectionPath { connection_id },
-                )
-                .await?
-                .proof;
-            debug!(%connection_proof);
-
-            let encoded_connection_state_proof = voyager_client
-                .encode_proof::<IbcUnion>(
-                    target_client_info.client_type.clone(),
-                    target_client_info.ibc_interface.clone(),
-                    connection_proof,
-                )
-                .await?;
-            debug!(%encoded_connection_state_proof);
-
-            Ok(data(IbcDatagram::new::<IbcUnion>(
-                ibc_union_spec::Datagram::from(ibc_union_spec::MsgConnectionOpenConfirm {
-                    connection_id: connection_open_ack_event.counterparty_connection_id,
-                    proof_height: origin_chain_proof_height.height(),
-                    proof_ack: encoded_connection_state_proof,
-                }),
-            )))
-        }
-
-        EventUnion::ChannelOpenInit(event) => {
-            let proof_init = voyager_client
-                .query_ibc_proof(
-                    origin_chain_id,
-                    QueryHeight::Specific(origin_chain_proof_height),
-                    ibc_union_spec::ChannelPath {
-                        channel_id: event.channel_id,
-                    },
-                )
-                .await?;
-
-            let client_info = voyager_client
-                .client_info::<IbcUnion>(target_chain_id, event.connection.counterparty_client_id)
-                .await?;
-
-            let encoded_proof_init = voyager_client
-                .encode_proof::<IbcUnion>(
-                    client_info.client_type,
-                    client_info.ibc_interface,
-                    proof_init.proof,
-                )
-                .await?;
-
-            Ok(data(IbcDatagram::new::<IbcUnion>(
-                ibc_union_spec::Datagram::from(ibc_union_spec::MsgChannelOpenTry {
-                    port_id: event.counterparty_port_id,
-                    channel: ibc_solidity::Channel {
-                        state: ibc_solidity::ChannelState::TryOpen,
-                        counterparty_channel_id: event.channel_id,
-                        counterparty_port_id: event.port_id.into(),
-                        connection_id: event.connection.counterparty_connection_id,
-                        version: event.version.clone(),
-                    },
-                    counterparty_version: event.version,
-                    proof_init: encoded_proof_init,
-                    proof_height: origin_chain_proof_height.height(),
-                }),
-            )))
-        }
-
-        EventUnion::ChannelOpenTry(event) => {
-            let proof_try = voyager_client
-                .query_ibc_proof(
-                    origin_chain_id,
-                    QueryHeight::Specific(origin_chain_proof_height),
-                    ibc_union_spec::ChannelPath {
-                        channel_id: event.channel_id,
-                    },
-                )
-                .await?;
-
-            let client_info = voyager_client
-                .client_info::<IbcUnion>(target_chain_id, event.connection.counterparty_client_id)
-                .await?;
-
-            let encoded_proof_try = voyager_client
-                .encode_proof::<IbcUnion>(
-                    client_info.client_type,
-                    client_info.ibc_interface,
-                    proof_try.proof,
-                )
-                .await?;
-
-            Ok(data(IbcDatagram::new::<IbcUnion>(
-                ibc_union_spec::Datagram::from(ibc_union_spec::MsgChannelOpenAck {
-                    channel_id: event.counterparty_channel_id,
-                    counterparty_channel_id: event.channel_id,
-                    counterparty_version: event.version,
-                    proof_try: encoded_proof_try,
-                    proof_height: origin_chain_proof_height.height(),
-                }),
-            )))
-        }
-
-        EventUnion::ChannelOpenAck(event) => {
-            let proof_try = voyager_client
-                .query_ibc_proof(
-                    origin_chain_id,
-                    QueryHeight::Specific(origin_chain_proof_height),
-                    ibc_union_spec::ChannelPath {
-                        channel_id: event.channel_id,
-                    },
-                )
-                .await?;
-
-            let client_info = voyager_client
-                .client_info::<IbcUnion>(target_chain_id, event.connection.counterparty_client_id)
-                .await?;
-
-            let encoded_proof_ack = voyager_client
-                .encode_proof::<IbcUnion>(
-                    client_info.client_type,
-                    client_info.ibc_interface,
-                    proof_try.proof,
-                )
-                .await?;
-
-            Ok(data(IbcDatagram::new::<IbcUnion>(
-                ibc_union_spec::Datagram::from(ibc_union_spec::MsgChannelOpenConfirm {
-                    channel_id: event.counterparty_channel_id,
-                    proof_ack: encoded_proof_ack,
-                    proof_height: origin_chain_proof_height.height(),
-                }),
-            )))
-        }
-
-        EventUnion::SendPacket(event) => {
-            let packet = Packet {
-                source_channel: event.packet.source_channel.channel_id,
-                destination_channel: event.packet.destination_channel.channel_id,
-                data: event.packet_data.into(),
-                timeout_height: event.packet.timeout_height,
-                timeout_timestamp: event.packet.timeout_timestamp,
-            };
-            let proof_try = voyager_client
-                .query_ibc_proof(
-                    origin_chain_id,
-                    QueryHeight::Specific(origin_chain_proof_height),
-                    ibc_union_spec::BatchPacketsPath {
-                        channel_id: event.packet.source_channel.channel_id,
-                        batch_hash: keccak256(packet.abi_encode()),
-                    },
-                )
-                .await?;
-
-            let client_info = voyager_client
-                .client_info::<IbcUnion>(
-                    target_chain_id,
-                    event.packet.destination_channel.connection.client_id,
-                )
-                .await?;
-
-            let encoded_proof_commitment = voyager_client
-                .encode_proof::<IbcUnion>(
-                    client_info.client_type,
-                    client_info.ibc_interface,
-                    proof_try.proof,
-                )
-                .await?;
-
-            Ok(data(IbcDatagram::new::<IbcUnion>(
-                ibc_union_spec::Datagram::from(ibc_union_spec::MsgPacketRecv {
-                    packets: vec![packet],
-                    relayer_msgs: vec![vec![].into()],
-                    proof: encoded_proof_commitment,
-                    proof_height: origin_chain_proof_height.height(),
-                }),
-            )))
-        }
-
-        EventUnion::WriteAcknowledgement(event) => {
-            let packet = Packet {
-                source_channel: event.packet.source_channel.channel_id,
-                destination_channel: event.packet.destination_channel.channel_id,
-                data: event.packet_data.into(),
-                timeout_height: event.packet.timeout_height,
-                timeout_timestamp: event.packet.timeout_timestamp,
-            };
-            let proof_try = voyager_client
-                .query_ibc_proof(
-                    origin_chain_id,
-                    QueryHeight::Specific(origin_chain_proof_height),
-                    ibc_union_spec::BatchReceiptsPath {
-                        channel_id: event.packet.destination_channel.channel_id,
-                        batch_hash: keccak256(packet.abi_encode()),
-                    },
-                )
-                .await?;
-
-            let client_info = voyager_client
-                .client_info::<IbcUnion>(
-                    target_chain_id,
-                    event.packet.source_channel.connection.client_id,
-                )
-                .await?;
+    if let Some(skipped) =
+        handshake::precheck_union(voyager_client, &origin_chain_id, &target_chain_id, &event)
+            .await?
+    {
+        return Ok(data(PluginMessage::new(module.plugin_name(), skipped)));
+    }
 
-            let encoded_proof_commitment = voyager_client
-                .encode_proof::<IbcUnion>(
-                    client_info.client_type,
-                    client_info.ibc_interface,
-                    proof_try.proof,
-                )
-                .await?;
-
-            Ok(data(IbcDatagram::new::<IbcUnion>(
-                ibc_union_spec::Datagram::from(ibc_union_spec::MsgPacketAcknowledgement {
-                    packets: vec![packet],
-                    acknowledgements: vec![event.acknowledgement],
-                    proof: encoded_proof_commitment,
-                    proof_height: origin_chain_proof_height.height(),
-                }),
-            )))
-        }
+    // the acknowledgement must be verified before the proof of it is built
+    if let EventUnion::WriteAcknowledgement(event) = &event {
+        ack::verify_union_ack(
+            voyager_client,
+            &origin_chain_id,
+            origin_chain_proof_height,
+            &union_msg::packet(event.packet_data.clone(), &event.packet),
+            &event.acknowledgement,
+        )
+        .await??;
     }
+
+    Ok(data(IbcDatagram::new::<IbcUnion>(
+        union_msg::make_datagram(
+            voyager_client,
+            &origin_chain_id,
+            origin_chain_proof_height,
+            &target_chain_id,
+            event,
+        )
+        .await?,
+    )))
 }
 
 async fn do_make_msg_v1(
+    module: &Module,
     voyager_client: &VoyagerClient,
     MakeMsg {
         origin_chain_id,
@@ -838,6 +478,13 @@ async fn do_make_msg_v1(
         event,
     }: MakeMsg<IbcClassic>,
 ) -> RpcResult<Op<VoyagerMessage>> {
+    if let Some(skipped) =
+        handshake::precheck_classic(voyager_client, &origin_chain_id, &target_chain_id, &event)
+            .await?
+    {
+        return Ok(data(PluginMessage::new(module.plugin_name(), skipped)));
+    }
+
     match event {
         EventClassic::ConnectionOpenInit(connection_open_init_event) => {
             let ConnectionHandshakeStateAndProof {
@@ -1118,6 +765,43 @@ async fn do_make_msg_v1(
         // }
 
         // MakeMsgV1::MakeMsgRecvPacket(msg) => make_msg_recv_packet(ctx, msg).await,
+        EventClassic::SendPacket(ibc_classic_spec::SendPacket { ref packet, .. })
+        | EventClassic::WriteAcknowledgement(ibc_classic_spec::WriteAcknowledgement {
+            ref packet,
+            ..
+        }) => {
+            // packet proofs can only be built over a single connection hop
+            for channel in [&packet.source_channel, &packet.destination_channel] {
+                channel.single_hop_connection().map_err(|err| {
+                    ErrorObject::owned(
+                        FATAL_JSONRPC_ERROR_CODE,
+                        ErrorReporter(err).to_string(),
+                        None::<()>,
+                    )
+                })?;
+            }
+
+            if let EventClassic::WriteAcknowledgement(ref event) = event {
+                ack::verify_v1_ack(
+                    voyager_client,
+                    &origin_chain_id,
+                    origin_chain_proof_height,
+                    ibc_classic_spec::AcknowledgementPath {
+                        port_id: packet.destination_channel.port_id.clone(),
+                        channel_id: packet.destination_channel.channel_id.clone(),
+                        sequence: packet.sequence,
+                    },
+                    &event.packet_ack,
+                )
+                .await??;
+            }
+
+            Err(ErrorObject::owned(
+                FATAL_JSONRPC_ERROR_CODE,
+                "classic packet relaying not implemented",
+                None::<()>,
+            ))
+        }
         _ => todo!(),
     }
 }
@@ -1132,7 +816,8 @@ impl Module {
         Box::pin(async move {
             let mut batchers_v1 =
                 HashMap::<ClientId, Vec<(usize, BatchableEvent<IbcClassic>)>>::new();
-            let mut batchers_union = HashMap::<u32, Vec<(usize, BatchableEvent<IbcUnion>)>>::new();
+            let mut batchers_union =
+                HashMap::<UnionId, Vec<(usize, BatchableEvent<IbcUnion>)>>::new();
 
             for (idx, msg) in msgs.into_iter().enumerate() {
                 let Op::Data(msg) = msg else {
@@ -1159,17 +844,23 @@ impl Module {
                                 .counterparty_client_id()
                                 .expect("all batchable messages have a counterparty");
 
-                            trace!(%client_id, "batching event");
-
-                            batchers_v1.entry(client_id.clone()).or_default().push((
-                                idx,
-                                BatchableEvent {
-                                    first_seen_at,
-                                    provable_height: chain_event.provable_height,
-                                    // TODO: Handle this more gracefully
-                                    event: full_ibc_event.try_into().unwrap(),
-                                },
-                            ));
+                            match EventClassic::try_from(full_ibc_event) {
+                                Ok(event) => {
+                                    trace!(%client_id, "batching event");
+
+                                    batchers_v1.entry(client_id.clone()).or_default().push((
+                                        idx,
+                                        BatchableEvent {
+                                            first_seen_at,
+                                            provable_height: chain_event.provable_height,
+                                            event,
+                                        },
+                                    ));
+                                }
+                                Err(()) => {
+                                    debug!(%client_id, "event is not relayed, dropping it");
+                                }
+                            }
                         }
 
                         if let Some(full_ibc_event) = chain_event.decode_event::<IbcUnion>() {
@@ -1179,17 +870,26 @@ impl Module {
                                 .counterparty_client_id()
                                 .expect("all batchable messages have a counterparty");
 
-                            trace!(%client_id, "batching event");
-
-                            batchers_union.entry(client_id).or_default().push((
-                                idx,
-                                BatchableEvent {
-                                    first_seen_at,
-                                    provable_height: chain_event.provable_height,
-                                    // TODO: Handle this more gracefully
-                                    event: full_ibc_event.try_into().unwrap(),
-                                },
-                            ));
+                            // the interest filter excludes events that are not relayed (i.e.
+                            // send_packet events for packets that were batched), but they can
+                            // still be passed to the plugin directly
+                            match EventUnion::try_from(full_ibc_event) {
+                                Ok(event) => {
+                                    trace!(%client_id, "batching event");
+
+                                    batchers_union.entry(client_id).or_default().push((
+                                        idx,
+                                        BatchableEvent {
+                                            first_seen_at,
+                                            provable_height: chain_event.provable_height,
+                                            event,
+                                        },
+                                    ));
+                                }
+                                Err(()) => {
+                                    debug!(%client_id, "event is not relayed, dropping it");
+                                }
+                            }
                         }
                     }
                     Err(msg) => {
@@ -1236,28 +936,39 @@ impl Module {
                 .flat_map(|(client_id, events)| split_ready(client_id, events, self))
                 .partition_map::<Vec<_>, Vec<_>, _, _, _>(convert::identity);
 
-            let voyager_client = e.try_get::<VoyagerClient>()?;
+            // the voyager client is only required to build the ready batches
+            let ready = if ready_v1.is_empty() && ready_union.is_empty() {
+                vec![]
+            } else {
+                let voyager_client = e.try_get::<VoyagerClient>()?;
 
-            let ready_v1 = ready_v1
-                .into_iter()
-                .into_group_map()
-                .into_iter()
-                .map(|(client_id, events)| mk_ready_ops(client_id, events, self, voyager_client))
-                .collect::<FuturesOrdered<_>>();
+                let ready_v1 = ready_v1
+                    .into_iter()
+                    .into_group_map()
+                    .into_iter()
+                    .map(|(client_id, events)| {
+                        mk_ready_ops(client_id, events, self, voyager_client)
+                    })
+                    .collect::<FuturesOrdered<_>>();
 
-            let ready_union = ready_union
-                .into_iter()
-                .into_group_map()
-                .into_iter()
-                .map(|(client_id, events)| mk_ready_ops(client_id, events, self, voyager_client))
-                .collect::<FuturesOrdered<_>>();
+                let ready_union = ready_union
+                    .into_iter()
+                    .into_group_map()
+                    .into_iter()
+                    .map(|(client_id, events)| {
+                        mk_ready_ops(client_id, events, self, voyager_client)
+                    })
+                    .collect::<FuturesOrdered<_>>();
+
+                ready_v1.chain(ready_union).map(|x| x).try_collect().await?
+            };
 
             Ok(PassResult {
                 optimize_further: optimize_further_v1
                     .into_iter()
                     .chain(optimize_further_union)
                     .collect(),
-                ready: ready_v1.chain(ready_union).map(|x| x).try_collect().await?,
+                ready,
             })
         })
     }
@@ -1521,6 +1232,267 @@ mod tests {
           }
         });
 
-        let _config = serde_json::from_value::<Config>(config_json).unwrap();
+        let config = serde_json::from_value::<Config>(config_json).unwrap();
+        assert!(!config.allow_historical_updates);
+    }
+
+    #[test]
+    fn frozen_clients_are_not_updated() {
+        use voyager_message::core::{ClientStateMeta, ClientStatus};
+
+        use crate::call::ensure_client_updatable;
+
+        let chain_id = ChainId::new("union-devnet-1");
+        let client_id = ClientId::new("07-tendermint", 1);
+
+        let meta = |status| ClientStateMeta {
+            chain_id: ChainId::new("32382"),
+            height: Height::new(1),
+            status,
+            resolved_at: None,
+        };
+
+        for status in [
+            ClientStatus::Active,
+            ClientStatus::Expired,
+            ClientStatus::Unknown,
+        ] {
+            assert!(ensure_client_updatable(&chain_id, &client_id, &meta(status)).is_ok());
+        }
+
+        let err = ensure_client_updatable(&chain_id, &client_id, &meta(ClientStatus::Frozen))
+            .unwrap_err();
+        assert_eq!(err.code(), FATAL_JSONRPC_ERROR_CODE);
+    }
+
+    /// An ibc-union send packet event from `32382` to `counterparty_chain_id`.
+    fn send_packet(
+        counterparty_chain_id: &ChainId,
+        batch: Option<ibc_union_spec::batch::BatchMember>,
+    ) -> Op<VoyagerMessage> {
+        use ibc_union_spec::{ChannelMetadata, ConnectionMetadata, PacketMetadata};
+        use voyager_message::core::{ClientInfo, ClientType, IbcInterface};
+
+        let channel = |channel_id, client_id| ChannelMetadata {
+            channel_id: UnionId::new(channel_id).unwrap(),
+            version: "ucs03-zkgm-0".to_owned(),
+            connection: ConnectionMetadata {
+                client_id: UnionId::new(client_id).unwrap(),
+                connection_id: UnionId::new(1).unwrap(),
+            },
+        };
+
+        data(ChainEvent {
+            chain_id: ChainId::new("32382"),
+            client_info: ClientInfo {
+                client_type: ClientType::new_static(ClientType::COMETBLS),
+                ibc_interface: IbcInterface::new_static(IbcInterface::IBC_SOLIDITY),
+                metadata: Default::default(),
+            },
+            counterparty_chain_id: counterparty_chain_id.clone(),
+            tx_hash: Default::default(),
+            provable_height: Height::new(10),
+            ibc_spec_id: IbcUnion::ID,
+            event: serde_json::to_value(ibc_union_spec::FullEvent::SendPacket(
+                ibc_union_spec::SendPacket {
+                    packet_data: b"packet".into(),
+                    packet: PacketMetadata {
+                        source_channel: channel(1, 2),
+                        destination_channel: channel(3, 4),
+                        timeout_height: 0,
+                        timeout_timestamp: 1,
+                    },
+                    batch,
+                },
+            ))
+            .unwrap(),
+            raw_events: None,
+            denom_trace: None,
+        })
+    }
+
+    fn batch_config() -> Config {
+        Config {
+            chain_id: ChainId::new("union-devnet-1"),
+            client_configs: ClientConfigsSerde::Any(ClientConfig {
+                min_batch_size: 2,
+                max_batch_size: 2,
+                max_wait_time: Duration::from_secs(10),
+            }),
+            allow_historical_updates: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn batched_send_packets_are_not_relayed() {
+        use ibc_union_spec::batch::BatchMember;
+        use voyager_message::filter::JaqInterestFilter;
+        use voyager_vm::filter::InterestFilter;
+
+        let config = batch_config();
+
+        let sent = send_packet(&config.chain_id, None);
+        let batched = send_packet(
+            &config.chain_id,
+            Some(BatchMember {
+                batch_hash: Default::default(),
+                index: 0,
+            }),
+        );
+
+        let filter = JaqInterestFilter::new(vec![Module::info(config.clone())]).unwrap();
+
+        assert!(filter.check_interest(&sent).interest.is_some());
+        assert!(filter.check_interest(&batched).interest.is_none());
+
+        // events that made it past the filter are dropped by the pass, instead of being relayed
+        // again
+        let result = Module::new(config)
+            .run_pass(&Extensions::new(), vec![batched, sent])
+            .await
+            .unwrap();
+
+        assert!(result.ready.is_empty());
+        assert_eq!(result.optimize_further.len(), 1);
+        assert_eq!(result.optimize_further[0].0, vec![1]);
+    }
+
+    #[tokio::test]
+    async fn observed_events_are_still_batched() {
+        use voyager_message::filter::JaqInterestFilter;
+        use voyager_vm::{in_memory::InMemoryQueue, InspectQueue, Queue, READY_LANE};
+
+        let observer = |name: &str, interest_filter: &str| PluginInfo {
+            name: name.to_owned(),
+            interest_filter: interest_filter.to_owned(),
+            kind: None,
+            chains: vec![],
+            ibc_specs: vec![],
+        };
+
+        let config = batch_config();
+        let module = Module::new(config.clone());
+
+        // the webhook and journal plugins are ordered first, which would have starved this plugin
+        // if they claimed the events they observe
+        let filter = JaqInterestFilter::new(vec![
+            observer(
+                "voyager-plugin-webhook",
+                include_str!("../../webhook/src/interest_filter.jq"),
+            ),
+            observer(
+                "voyager-plugin-journal",
+                include_str!("../../journal/src/interest_filter.jq"),
+            ),
+            Module::info(config.clone()),
+        ])
+        .unwrap();
+
+        let queue = InMemoryQueue::<VoyagerMessage>::new(()).await.unwrap();
+        queue
+            .enqueue(send_packet(&config.chain_id, None), &filter)
+            .await
+            .unwrap();
+
+        let stats = queue.stats().await.unwrap();
+
+        assert_eq!(stats.lane("voyager-plugin-webhook").depth, 1);
+        assert_eq!(stats.lane("voyager-plugin-journal").depth, 1);
+        assert_eq!(stats.lane(&module.plugin_name()).depth, 1);
+        assert_eq!(stats.lane(READY_LANE).depth, 0);
+    }
+
+    #[test]
+    fn update_client_datagram_round_trip() {
+        let classic = IbcDatagram::new::<IbcClassic>(IbcClassic::update_client_datagram(
+            ClientId::new("07-tendermint", 1),
+            b"header".into(),
+        ));
+        let client_id = UnionId::new(1).unwrap();
+
+        let union = IbcDatagram::new::<IbcUnion>(IbcUnion::update_client_datagram(
+            client_id,
+            b"header".into(),
+        ));
+
+        for datagram in [classic.clone(), union.clone()] {
+            let json = serde_json::to_value(Data::from(datagram.clone())).unwrap();
+            assert_eq!(
+                serde_json::from_value::<Data>(json).unwrap(),
+                datagram.into()
+            );
+        }
+
+        assert!(matches!(
+            classic.decode_datagram::<IbcClassic>().unwrap().unwrap(),
+            ibc_classic_spec::Datagram::UpdateClient(_)
+        ));
+        assert_eq!(
+            union.decode_datagram::<IbcUnion>().unwrap().unwrap(),
+            ibc_union_spec::Datagram::UpdateClient(ibc_union_spec::MsgUpdateClient {
+                client_id,
+                client_message: b"header".into(),
+            })
+        );
+    }
+
+    #[test]
+    fn updates_are_batched_before_messages() {
+        use voyager_message::data::{
+            ClientUpdate, DecodedHeaderMeta, OrderedClientUpdates, WithChainId,
+        };
+
+        use crate::callback::MakeBatchTransaction;
+
+        let chain_id = ChainId::new("union-devnet-1");
+        let client_id = UnionId::new(1).unwrap();
+
+        let update = |height, header: &[u8]| {
+            (
+                DecodedHeaderMeta {
+                    height: Height::new(height),
+                },
+                ClientUpdate {
+                    client_id: RawClientId::new(1_u32),
+                    ibc_spec_id: IbcUnion::ID,
+                    client_message: header.to_vec().into(),
+                },
+            )
+        };
+
+        let recv = ibc_union_spec::Datagram::PacketRecv(ibc_union_spec::MsgPacketRecv {
+            packets: vec![],
+            relayer_msgs: vec![],
+            proof: b"proof".into(),
+            proof_height: 11,
+        });
+
+        let op = MakeBatchTransaction::<IbcUnion> {
+            client_id,
+            updates: Some(OrderedClientUpdates {
+                updates: vec![update(10, b"header-10"), update(11, b"header-11")],
+            }),
+        }
+        .call(
+            chain_id.clone(),
+            [Data::from(IbcDatagram::new::<IbcUnion>(recv.clone()))].into(),
+        );
+
+        // the updates and the message they are required for are submitted in a single batch (i.e.
+        // one multicall or one cosmos tx), with the updates first
+        assert_eq!(
+            op,
+            data(WithChainId {
+                chain_id,
+                message: [
+                    IbcUnion::update_client_datagram(client_id, b"header-10".into()),
+                    IbcUnion::update_client_datagram(client_id, b"header-11".into()),
+                    recv,
+                ]
+                .into_iter()
+                .map(IbcDatagram::new::<IbcUnion>)
+                .collect::<Vec<_>>(),
+            })
+        );
     }
 }
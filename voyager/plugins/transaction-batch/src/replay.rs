@@ -0,0 +1,330 @@
+//! Replay protection for client updates.
+//!
+//! By the time the updates for a batch are fetched, the client on the destination may already have
+//! been updated past them (by another relayer, or by a previous batch of ours that raced this one).
+//! Submitting such an update would at best waste the gas of the transaction, and at worst rewind
+//! the latest height of clients that accept it. Before the updates are batched, the height of each
+//! update is compared against the latest height of the client (see [`check_update`]), and updates
+//! that are not strictly newer are dropped with a [`DroppedClientUpdates`].
+//!
+//! If `allow_historical_updates` is set, older updates are submitted anyway (i.e. to fill in a
+//! consensus state that was skipped), as long as the client doesn't already have a consensus state
+//! at the height of the update.
+
+use std::collections::HashSet;
+
+use jsonrpsee::core::RpcResult;
+use macros::model;
+use tracing::{info, warn};
+use unionlabs::ibc::core::client::height::Height;
+use voyager_message::{
+    core::{ChainId, IbcSpecId, QueryHeight},
+    data::OrderedClientUpdates,
+    RawClientId, VoyagerClient,
+};
+
+use crate::IbcSpecExt;
+
+/// Client updates that were not submitted since they would not advance the client.
+#[model]
+pub struct DroppedClientUpdates {
+    pub ibc_spec_id: IbcSpecId,
+    /// The chain the client is on.
+    pub chain_id: ChainId,
+    pub client_id: RawClientId,
+    /// The latest height of the client when the updates were checked.
+    pub client_height: Height,
+    pub dropped: Vec<DroppedClientUpdate>,
+}
+
+#[model]
+pub struct DroppedClientUpdate {
+    /// The height the update would have updated the client to.
+    pub height: Height,
+    pub reason: DroppedUpdateReason,
+}
+
+#[model]
+#[derive(Copy, Eq)]
+pub enum DroppedUpdateReason {
+    /// The update is not newer than the latest height of the client.
+    NotNewer,
+    /// Historical updates are allowed, but the client already has a consensus state at the height
+    /// of the update.
+    ConsensusStateExists,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpdateCheck {
+    Keep,
+    Drop(DroppedUpdateReason),
+    /// The update is older than the latest height of the client, and must only be kept if the
+    /// client doesn't have a consensus state at its height.
+    CheckConsensusState,
+}
+
+/// Compare the height of an update against the latest height of the client.
+#[must_use]
+pub fn check_update(
+    update_height: Height,
+    client_height: Height,
+    allow_historical_updates: bool,
+) -> UpdateCheck {
+    if update_height > client_height {
+        UpdateCheck::Keep
+    } else if allow_historical_updates && update_height < client_height {
+        UpdateCheck::CheckConsensusState
+    } else if allow_historical_updates {
+        // the client always has a consensus state at its latest height
+        UpdateCheck::Drop(DroppedUpdateReason::ConsensusStateExists)
+    } else {
+        UpdateCheck::Drop(DroppedUpdateReason::NotNewer)
+    }
+}
+
+/// Split `updates` into the updates to submit and the updates to drop.
+///
+/// `consensus_state_exists` is only called for the updates that resolve to
+/// [`UpdateCheck::CheckConsensusState`]. The order of the kept updates is preserved.
+pub fn filter_updates(
+    updates: OrderedClientUpdates,
+    client_height: Height,
+    allow_historical_updates: bool,
+    consensus_state_exists: impl Fn(Height) -> bool,
+) -> (OrderedClientUpdates, Vec<DroppedClientUpdate>) {
+    let mut dropped = vec![];
+
+    let updates = updates
+        .updates
+        .into_iter()
+        .filter(|(meta, _)| {
+            let reason = match check_update(meta.height, client_height, allow_historical_updates) {
+                UpdateCheck::Keep => return true,
+                UpdateCheck::Drop(reason) => reason,
+                UpdateCheck::CheckConsensusState if consensus_state_exists(meta.height) => {
+                    DroppedUpdateReason::ConsensusStateExists
+                }
+                UpdateCheck::CheckConsensusState => return true,
+            };
+
+            dropped.push(DroppedClientUpdate {
+                height: meta.height,
+                reason,
+            });
+
+            false
+        })
+        .collect();
+
+    (OrderedClientUpdates { updates }, dropped)
+}
+
+/// Drop the updates in `updates` that would not advance the client `client_id` on `chain_id`,
+/// returning the updates to submit and the data item describing the dropped updates, if any.
+pub async fn guard_client_updates<V: IbcSpecExt>(
+    voyager_client: &VoyagerClient,
+    chain_id: &ChainId,
+    client_id: &V::ClientId,
+    client_height: Height,
+    allow_historical_updates: bool,
+    updates: OrderedClientUpdates,
+) -> RpcResult<(OrderedClientUpdates, Option<DroppedClientUpdates>)> {
+    let mut existing_consensus_states = HashSet::new();
+
+    for (meta, _) in &updates.updates {
+        if check_update(meta.height, client_height, allow_historical_updates)
+            != UpdateCheck::CheckConsensusState
+        {
+            continue;
+        }
+
+        let consensus_state = voyager_client
+            .query_ibc_state(
+                chain_id.clone(),
+                QueryHeight::Latest,
+                V::consensus_state_path_key(client_id.clone(), meta.height),
+            )
+            .await?;
+
+        if !consensus_state.state.is_empty() {
+            existing_consensus_states.insert(meta.height);
+        }
+    }
+
+    let (updates, dropped) =
+        filter_updates(updates, client_height, allow_historical_updates, |height| {
+            existing_consensus_states.contains(&height)
+        });
+
+    if dropped.is_empty() {
+        return Ok((updates, None));
+    }
+
+    if updates.updates.is_empty() {
+        warn!(
+            %client_height,
+            dropped = dropped.len(),
+            "dropping all client updates, the client has already been updated past them"
+        );
+    } else {
+        info!(
+            %client_height,
+            dropped = dropped.len(),
+            kept = updates.updates.len(),
+            "dropping client updates that would not advance the client"
+        );
+    }
+
+    Ok((
+        updates,
+        Some(DroppedClientUpdates {
+            ibc_spec_id: V::ID,
+            chain_id: chain_id.clone(),
+            client_id: RawClientId::new(client_id.clone()),
+            client_height,
+            dropped,
+        }),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use voyager_message::{
+        core::IbcSpec,
+        data::{ClientUpdate, DecodedHeaderMeta},
+    };
+
+    use super::*;
+
+    fn updates(heights: &[u64]) -> OrderedClientUpdates {
+        OrderedClientUpdates {
+            updates: heights
+                .iter()
+                .map(|height| {
+                    (
+                        DecodedHeaderMeta {
+                            height: Height::new(*height),
+                        },
+                        ClientUpdate {
+                            client_id: RawClientId::new(1_u32),
+                            ibc_spec_id: ibc_union_spec::IbcUnion::ID,
+                            client_message: height.to_be_bytes().to_vec().into(),
+                        },
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    fn heights(updates: &OrderedClientUpdates) -> Vec<u64> {
+        updates
+            .updates
+            .iter()
+            .map(|(meta, _)| meta.height.height())
+            .collect()
+    }
+
+    fn dropped(height: u64, reason: DroppedUpdateReason) -> DroppedClientUpdate {
+        DroppedClientUpdate {
+            height: Height::new(height),
+            reason,
+        }
+    }
+
+    #[test]
+    fn check_matrix() {
+        use DroppedUpdateReason::*;
+        use UpdateCheck::*;
+
+        let client_height = Height::new(10);
+
+        for (update_height, allow_historical_updates, expected) in [
+            (11, false, Keep),
+            (10, false, Drop(NotNewer)),
+            (9, false, Drop(NotNewer)),
+            (11, true, Keep),
+            (10, true, Drop(ConsensusStateExists)),
+            (9, true, CheckConsensusState),
+        ] {
+            assert_eq!(
+                check_update(
+                    Height::new(update_height),
+                    client_height,
+                    allow_historical_updates
+                ),
+                expected,
+                "{update_height} against {client_height}, \
+                allow_historical_updates = {allow_historical_updates}"
+            );
+        }
+    }
+
+    #[test]
+    fn newer_revision_is_newer() {
+        assert_eq!(
+            check_update(
+                Height::new_with_revision(2, 1),
+                Height::new_with_revision(1, 100),
+                false
+            ),
+            UpdateCheck::Keep
+        );
+        assert_eq!(
+            check_update(
+                Height::new_with_revision(1, 100),
+                Height::new_with_revision(2, 1),
+                false
+            ),
+            UpdateCheck::Drop(DroppedUpdateReason::NotNewer)
+        );
+    }
+
+    #[test]
+    fn stale_updates_are_dropped() {
+        let (kept, dropped_updates) =
+            filter_updates(updates(&[9, 10, 11, 12]), Height::new(10), false, |_| {
+                panic!("consensus states are only checked for historical updates")
+            });
+
+        assert_eq!(heights(&kept), [11, 12]);
+        assert_eq!(
+            dropped_updates,
+            [
+                dropped(9, DroppedUpdateReason::NotNewer),
+                dropped(10, DroppedUpdateReason::NotNewer),
+            ]
+        );
+
+        let (kept, dropped_updates) =
+            filter_updates(updates(&[8, 9]), Height::new(10), false, |_| false);
+
+        assert!(kept.updates.is_empty());
+        assert_eq!(dropped_updates.len(), 2);
+    }
+
+    #[test]
+    fn historical_updates_require_missing_consensus_state() {
+        let (kept, dropped_updates) =
+            filter_updates(updates(&[7, 8, 10, 11]), Height::new(10), true, |height| {
+                height == Height::new(8)
+            });
+
+        assert_eq!(heights(&kept), [7, 11]);
+        assert_eq!(
+            dropped_updates,
+            [
+                dropped(8, DroppedUpdateReason::ConsensusStateExists),
+                dropped(10, DroppedUpdateReason::ConsensusStateExists),
+            ]
+        );
+    }
+
+    #[test]
+    fn newer_updates_are_kept() {
+        let (kept, dropped_updates) =
+            filter_updates(updates(&[11, 12]), Height::new(10), false, |_| true);
+
+        assert_eq!(kept, updates(&[11, 12]));
+        assert!(dropped_updates.is_empty());
+    }
+}
@@ -0,0 +1,591 @@
+//! Assembly of ibc-union datagrams from the events on the origin chain.
+//!
+//! A union spec connection may be between chains with different IBC interfaces, i.e. a cosmwasm
+//! chain and an EVM chain. The datagrams built here are the same for both: they are only encoded
+//! as a cosmwasm `ExecuteMsg` or as a solidity call by the transaction plugin of the target chain.
+//! Everything embedded in a datagram must therefore be for the target chain, and never depend on
+//! the IBC interface of the origin chain:
+//!
+//! - Proofs are read from the proof module of the origin chain, and encoded by the client module
+//!   of the client on the target chain that verifies them, as described by its
+//!   [`ClientInfo`](voyager_message::core::ClientInfo).
+//!   The proof is otherwise opaque, an ics23 proof is encoded for a solidity client the same way
+//!   an MPT proof is encoded for a cosmwasm client.
+//! - The proof height is the height reported by the proof module of the origin chain, in the
+//!   height format of the union spec.
+//! - Client update headers are encoded for the client on the target chain as well, see
+//!   [`AggregateMsgUpdateClientsFromOrderedHeaders`](voyager_message::callback::AggregateMsgUpdateClientsFromOrderedHeaders).
+
+use ibc_solidity::Packet;
+use ibc_union_spec::{IbcUnion, PacketMetadata, UnionId};
+use jsonrpsee::core::RpcResult;
+use tracing::debug;
+use unionlabs::{bytes::Bytes, ibc::core::client::height::Height};
+use voyager_message::{
+    core::{ChainId, IbcStorePathKey},
+    query::VoyagerQuery,
+    rpc::IbcProof,
+};
+
+use crate::data::EventUnion;
+
+/// A proof read on the origin chain, encoded for the client on the target chain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncodedProof {
+    pub proof: Bytes,
+    pub proof_height: u64,
+}
+
+/// The chains a datagram is relayed between.
+struct Route<'a, C> {
+    client: &'a C,
+    origin_chain_id: &'a ChainId,
+    origin_chain_proof_height: Height,
+    target_chain_id: &'a ChainId,
+}
+
+impl<C: VoyagerQuery> Route<'_, C> {
+    /// Read the proof of `path` on the origin chain, and encode it for `target_client_id` on the
+    /// target chain.
+    async fn proof<P: IbcStorePathKey<Spec = IbcUnion>>(
+        &self,
+        path: P,
+        target_client_id: UnionId,
+    ) -> RpcResult<EncodedProof> {
+        let target_client_info = self
+            .client
+            .client_info::<IbcUnion>(self.target_chain_id, target_client_id)
+            .await?;
+
+        debug!(
+            %target_client_id,
+            %target_client_info.client_type,
+            %target_client_info.ibc_interface,
+            %target_client_info.metadata,
+        );
+
+        let IbcProof { height, proof } = self
+            .client
+            .query_ibc_proof(
+                self.origin_chain_id,
+                self.origin_chain_proof_height.into(),
+                path,
+            )
+            .await?;
+
+        let proof = self
+            .client
+            .encode_proof::<IbcUnion>(&target_client_info, proof)
+            .await?;
+        debug!(%proof, %height);
+
+        Ok(EncodedProof {
+            proof,
+            proof_height: height.height(),
+        })
+    }
+}
+
+/// The packet of a packet event.
+#[must_use]
+pub fn packet(packet_data: Bytes, packet: &PacketMetadata) -> Packet {
+    Packet {
+        source_channel: packet.source_channel.channel_id.get(),
+        destination_channel: packet.destination_channel.channel_id.get(),
+        data: packet_data.into(),
+        timeout_height: packet.timeout_height,
+        timeout_timestamp: packet.timeout_timestamp,
+    }
+}
+
+/// Build the datagram to submit to `target_chain_id` in response to `event` on
+/// `origin_chain_id`, with proofs at `origin_chain_proof_height`.
+///
+/// Acknowledgements are not verified here, see [`crate::ack`].
+pub async fn make_datagram(
+    client: &impl VoyagerQuery,
+    origin_chain_id: &ChainId,
+    origin_chain_proof_height: Height,
+    target_chain_id: &ChainId,
+    event: EventUnion,
+) -> RpcResult<ibc_union_spec::Datagram> {
+    let route = Route {
+        client,
+        origin_chain_id,
+        origin_chain_proof_height,
+        target_chain_id,
+    };
+
+    Ok(match event {
+        // counterparty_client_id of connection events is the client on the target chain
+        EventUnion::ConnectionOpenInit(event) => {
+            let EncodedProof {
+                proof,
+                proof_height,
+            } = route
+                .proof(
+                    ibc_union_spec::ConnectionPath {
+                        connection_id: event.connection_id.get(),
+                    },
+                    event.counterparty_client_id,
+                )
+                .await?;
+
+            ibc_union_spec::MsgConnectionOpenTry {
+                client_id: event.counterparty_client_id,
+                counterparty_client_id: event.client_id,
+                counterparty_connection_id: event.connection_id,
+                proof_height,
+                proof_init: proof,
+            }
+            .into()
+        }
+        EventUnion::ConnectionOpenTry(event) => {
+            let EncodedProof {
+                proof,
+                proof_height,
+            } = route
+                .proof(
+                    ibc_union_spec::ConnectionPath {
+                        connection_id: event.connection_id.get(),
+                    },
+                    event.counterparty_client_id,
+                )
+                .await?;
+
+            ibc_union_spec::MsgConnectionOpenAck {
+                connection_id: event.counterparty_connection_id,
+                counterparty_connection_id: event.connection_id,
+                proof_height,
+                proof_try: proof,
+            }
+            .into()
+        }
+        EventUnion::ConnectionOpenAck(event) => {
+            let EncodedProof {
+                proof,
+                proof_height,
+            } = route
+                .proof(
+                    ibc_union_spec::ConnectionPath {
+                        connection_id: event.connection_id.get(),
+                    },
+                    event.counterparty_client_id,
+                )
+                .await?;
+
+            ibc_union_spec::MsgConnectionOpenConfirm {
+                connection_id: event.counterparty_connection_id,
+                proof_height,
+                proof_ack: proof,
+            }
+            .into()
+        }
+
+        EventUnion::ChannelOpenInit(event) => {
+            let EncodedProof {
+                proof,
+                proof_height,
+            } = route
+                .proof(
+                    ibc_union_spec::ChannelPath {
+                        channel_id: event.channel_id.get(),
+                    },
+                    event.connection.counterparty_client_id,
+                )
+                .await?;
+
+            ibc_union_spec::MsgChannelOpenTry {
+                port_id: event.counterparty_port_id,
+                channel: ibc_solidity::Channel {
+                    state: ibc_solidity::ChannelState::TryOpen,
+                    counterparty_channel_id: event.channel_id.get(),
+                    counterparty_port_id: event.port_id.into(),
+                    connection_id: event.connection.counterparty_connection_id.get(),
+                    version: event.version.clone(),
+                },
+                counterparty_version: event.version,
+                proof_init: proof,
+                proof_height,
+            }
+            .into()
+        }
+        EventUnion::ChannelOpenTry(event) => {
+            let EncodedProof {
+                proof,
+                proof_height,
+            } = route
+                .proof(
+                    ibc_union_spec::ChannelPath {
+                        channel_id: event.channel_id.get(),
+                    },
+                    event.connection.counterparty_client_id,
+                )
+                .await?;
+
+            ibc_union_spec::MsgChannelOpenAck {
+                channel_id: event.counterparty_channel_id,
+                counterparty_channel_id: event.channel_id,
+                counterparty_version: event.version,
+                proof_try: proof,
+                proof_height,
+            }
+            .into()
+        }
+        EventUnion::ChannelOpenAck(event) => {
+            let EncodedProof {
+                proof,
+                proof_height,
+            } = route
+                .proof(
+                    ibc_union_spec::ChannelPath {
+                        channel_id: event.channel_id.get(),
+                    },
+                    event.connection.counterparty_client_id,
+                )
+                .await?;
+
+            ibc_union_spec::MsgChannelOpenConfirm {
+                channel_id: event.counterparty_channel_id,
+                proof_ack: proof,
+                proof_height,
+            }
+            .into()
+        }
+
+        // the packet is received on the destination chain, by the client of the destination
+        // channel
+        EventUnion::SendPacket(event) => {
+            let packet = packet(event.packet_data, &event.packet);
+
+            let EncodedProof {
+                proof,
+                proof_height,
+            } = route
+                .proof(
+                    ibc_union_spec::BatchPacketsPath {
+                        channel_id: packet.source_channel,
+                        batch_hash: ibc_union_spec::commit_packet(&packet),
+                    },
+                    event.packet.destination_channel.connection.client_id,
+                )
+                .await?;
+
+            ibc_union_spec::MsgPacketRecv {
+                packets: vec![packet],
+                relayer_msgs: vec![vec![].into()],
+                proof,
+                proof_height,
+            }
+            .into()
+        }
+        // the acknowledgement is written on the destination chain and relayed back to the source
+        // chain, by the client of the source channel
+        EventUnion::WriteAcknowledgement(event) => {
+            let packet = packet(event.packet_data, &event.packet);
+
+            let EncodedProof {
+                proof,
+                proof_height,
+            } = route
+                .proof(
+                    ibc_union_spec::BatchReceiptsPath::from_packet(&packet),
+                    event.packet.source_channel.connection.client_id,
+                )
+                .await?;
+
+            ibc_union_spec::MsgPacketAcknowledgement {
+                packets: vec![packet],
+                acknowledgements: vec![event.acknowledgement],
+                proof,
+                proof_height,
+            }
+            .into()
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use ibc_union_spec::{ChannelMetadata, ConnectionMetadata, StorePath};
+    use serde_json::{json, Value};
+    use voyager_message::{
+        core::{ClientInfo, ClientType, IbcInterface, IbcSpec, QueryHeight},
+        into_value,
+        testing::MockVoyager,
+    };
+
+    use super::*;
+
+    /// A chain with the cosmwasm IBC interface, with ics23 proofs.
+    const COSMWASM: &str = "union-1";
+    /// A chain with the solidity IBC interface, with MPT proofs.
+    const SOLIDITY: &str = "1";
+
+    /// The client of each chain tracking the other chain.
+    const COSMWASM_CLIENT: u32 = 3;
+    const SOLIDITY_CLIENT: u32 = 7;
+
+    fn client_info(client_type: &str, ibc_interface: &str) -> ClientInfo {
+        ClientInfo {
+            client_type: ClientType::new(client_type),
+            ibc_interface: IbcInterface::new(ibc_interface),
+            metadata: Value::Null,
+        }
+    }
+
+    fn voyager() -> MockVoyager {
+        let voyager = MockVoyager::new();
+
+        voyager
+            .set_client_info::<IbcUnion>(
+                &ChainId::new(COSMWASM),
+                UnionId::new(COSMWASM_CLIENT).unwrap(),
+                client_info(ClientType::ETHEREUM, IbcInterface::IBC_COSMWASM),
+            )
+            .set_client_info::<IbcUnion>(
+                &ChainId::new(SOLIDITY),
+                UnionId::new(SOLIDITY_CLIENT).unwrap(),
+                client_info(ClientType::COMETBLS, IbcInterface::IBC_SOLIDITY),
+            );
+
+        voyager
+    }
+
+    struct Direction {
+        origin_chain_id: ChainId,
+        origin_chain_proof_height: Height,
+        target_chain_id: ChainId,
+        origin_client_id: UnionId,
+        target_client_id: UnionId,
+        /// The client on the target chain, that proofs are encoded for.
+        target_client_info: ClientInfo,
+    }
+
+    fn cosmwasm_to_solidity() -> Direction {
+        Direction {
+            origin_chain_id: ChainId::new(COSMWASM),
+            origin_chain_proof_height: Height::new_with_revision(1, 100),
+            target_chain_id: ChainId::new(SOLIDITY),
+            origin_client_id: UnionId::new(COSMWASM_CLIENT).unwrap(),
+            target_client_id: UnionId::new(SOLIDITY_CLIENT).unwrap(),
+            target_client_info: client_info(ClientType::COMETBLS, IbcInterface::IBC_SOLIDITY),
+        }
+    }
+
+    fn solidity_to_cosmwasm() -> Direction {
+        Direction {
+            origin_chain_id: ChainId::new(SOLIDITY),
+            origin_chain_proof_height: Height::new(100),
+            target_chain_id: ChainId::new(COSMWASM),
+            origin_client_id: UnionId::new(SOLIDITY_CLIENT).unwrap(),
+            target_client_id: UnionId::new(COSMWASM_CLIENT).unwrap(),
+            target_client_info: client_info(ClientType::ETHEREUM, IbcInterface::IBC_COSMWASM),
+        }
+    }
+
+    impl Direction {
+        fn proof<P: IbcStorePathKey<Spec = IbcUnion>>(&self, path: P) -> Value {
+            MockVoyager::proof(self.origin_chain_proof_height, path)
+        }
+
+        fn encoded_proof<P: IbcStorePathKey<Spec = IbcUnion>>(&self, path: P) -> Bytes {
+            MockVoyager::encoded(&self.proof(path))
+        }
+
+        /// Assemble the datagram for `event`, and check that the proof of `path` was read on the
+        /// origin chain and encoded for the client on the target chain.
+        async fn make_datagram<P: IbcStorePathKey<Spec = IbcUnion> + Clone>(
+            &self,
+            event: impl Into<EventUnion>,
+            path: P,
+        ) -> ibc_union_spec::Datagram {
+            let voyager = voyager();
+
+            let datagram = make_datagram(
+                &voyager,
+                &self.origin_chain_id,
+                self.origin_chain_proof_height,
+                &self.target_chain_id,
+                event.into(),
+            )
+            .await
+            .unwrap();
+
+            assert_eq!(
+                voyager.calls("query_ibc_proof"),
+                [json!({
+                    "chain_id": self.origin_chain_id,
+                    "ibc_spec_id": IbcUnion::ID,
+                    "height": QueryHeight::Specific(self.origin_chain_proof_height),
+                    "path": into_value(StorePath::from(path.clone().into())),
+                })]
+            );
+            assert_eq!(
+                voyager.calls("encode_proof"),
+                [json!({
+                    "client_type": self.target_client_info.client_type,
+                    "ibc_interface": self.target_client_info.ibc_interface,
+                    "ibc_spec_id": IbcUnion::ID,
+                    "proof": self.proof(path),
+                })]
+            );
+
+            datagram
+        }
+
+        fn packet(&self) -> (Bytes, PacketMetadata) {
+            (
+                b"packet data".into(),
+                PacketMetadata {
+                    source_channel: ChannelMetadata {
+                        channel_id: UnionId::new(1).unwrap(),
+                        version: "ucs03-zkgm-0".to_owned(),
+                        connection: ConnectionMetadata {
+                            client_id: self.origin_client_id,
+                            connection_id: UnionId::new(1).unwrap(),
+                        },
+                    },
+                    destination_channel: ChannelMetadata {
+                        channel_id: UnionId::new(2).unwrap(),
+                        version: "ucs03-zkgm-0".to_owned(),
+                        connection: ConnectionMetadata {
+                            client_id: self.target_client_id,
+                            connection_id: UnionId::new(2).unwrap(),
+                        },
+                    },
+                    timeout_height: 0,
+                    timeout_timestamp: 1_000_000,
+                },
+            )
+        }
+    }
+
+    async fn assert_datagrams(direction: Direction) {
+        let proof_height = 100;
+
+        let path = ibc_union_spec::ConnectionPath { connection_id: 1 };
+        assert_eq!(
+            direction
+                .make_datagram(
+                    ibc_union_spec::ConnectionOpenInit {
+                        connection_id: UnionId::new(1).unwrap(),
+                        client_id: direction.origin_client_id,
+                        counterparty_client_id: direction.target_client_id,
+                    },
+                    path.clone(),
+                )
+                .await,
+            ibc_union_spec::MsgConnectionOpenTry {
+                client_id: direction.target_client_id,
+                counterparty_client_id: direction.origin_client_id,
+                counterparty_connection_id: UnionId::new(1).unwrap(),
+                proof_init: direction.encoded_proof(path),
+                proof_height,
+            }
+            .into()
+        );
+
+        let path = ibc_union_spec::ChannelPath { channel_id: 1 };
+        assert_eq!(
+            direction
+                .make_datagram(
+                    ibc_union_spec::ChannelOpenInit {
+                        port_id: b"port".into(),
+                        channel_id: UnionId::new(1).unwrap(),
+                        counterparty_port_id: b"counterparty-port".into(),
+                        connection: ibc_union_spec::ChannelConnection {
+                            state: ibc_solidity::ConnectionState::Open,
+                            client_id: direction.origin_client_id,
+                            counterparty_client_id: direction.target_client_id,
+                            counterparty_connection_id: UnionId::new(2).unwrap(),
+                        },
+                        version: "ucs03-zkgm-0".to_owned(),
+                    },
+                    path.clone(),
+                )
+                .await,
+            ibc_union_spec::MsgChannelOpenTry {
+                port_id: b"counterparty-port".into(),
+                channel: ibc_solidity::Channel {
+                    state: ibc_solidity::ChannelState::TryOpen,
+                    counterparty_channel_id: 1,
+                    counterparty_port_id: b"port".into(),
+                    connection_id: 2,
+                    version: "ucs03-zkgm-0".to_owned(),
+                },
+                counterparty_version: "ucs03-zkgm-0".to_owned(),
+                proof_init: direction.encoded_proof(path),
+                proof_height,
+            }
+            .into()
+        );
+
+        let (packet_data, metadata) = direction.packet();
+        let packet = packet(packet_data.clone(), &metadata);
+
+        let path = ibc_union_spec::BatchPacketsPath {
+            channel_id: 1,
+            batch_hash: ibc_union_spec::commit_packet(&packet),
+        };
+        assert_eq!(
+            direction
+                .make_datagram(
+                    ibc_union_spec::SendPacket {
+                        packet_data: packet_data.clone(),
+                        packet: metadata.clone(),
+                        batch: None,
+                    },
+                    path.clone(),
+                )
+                .await,
+            ibc_union_spec::MsgPacketRecv {
+                packets: vec![packet.clone()],
+                relayer_msgs: vec![vec![].into()],
+                proof: direction.encoded_proof(path),
+                proof_height,
+            }
+            .into()
+        );
+
+        // the acknowledgement is written on the origin chain for a packet sent from the target
+        // chain, so the channels are swapped
+        let (packet_data, mut metadata) = direction.packet();
+        std::mem::swap(
+            &mut metadata.source_channel,
+            &mut metadata.destination_channel,
+        );
+        let packet = super::packet(packet_data.clone(), &metadata);
+
+        let path = ibc_union_spec::BatchReceiptsPath::from_packet(&packet);
+        assert_eq!(
+            direction
+                .make_datagram(
+                    ibc_union_spec::WriteAcknowledgement {
+                        packet_data,
+                        packet: metadata,
+                        acknowledgement: b"ack".into(),
+                    },
+                    path.clone(),
+                )
+                .await,
+            ibc_union_spec::MsgPacketAcknowledgement {
+                packets: vec![packet],
+                acknowledgements: vec![b"ack".into()],
+                proof: direction.encoded_proof(path),
+                proof_height,
+            }
+            .into()
+        );
+    }
+
+    #[tokio::test]
+    async fn cosmwasm_to_solidity_datagrams() {
+        assert_datagrams(cosmwasm_to_solidity()).await;
+    }
+
+    #[tokio::test]
+    async fn solidity_to_cosmwasm_datagrams() {
+        assert_datagrams(solidity_to_cosmwasm()).await;
+    }
+}
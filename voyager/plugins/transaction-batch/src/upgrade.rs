@@ -0,0 +1,367 @@
+//! Assembly of `MsgUpgradeClient` for clients of a cosmos-sdk chain that is upgraded with
+//! `x/upgrade`.
+//!
+//! An upgrade that changes the client of a chain (i.e. a new chain id) can't be followed with
+//! client updates, as the chain halts at the height of the upgrade plan and continues with a new
+//! revision. Instead, the chain commits the client and consensus states that its counterparty
+//! clients are upgraded to under the upgrade store, and the counterparty clients are upgraded with
+//! proofs of those states:
+//!
+//! - The client must first be updated to exactly the plan height, since the client verifies the
+//!   proofs against the consensus state at its latest height, under the path of that height.
+//! - The proofs are read at the plan height. As with all proofs, this proves the state committed by
+//!   the block before the plan height, which is the last block executed before the chain halted.
+//! - The states are therefore read at the height before the plan height.
+//!
+//! The plan height is read from the `x/upgrade` plan (see `chain_utils::upgrade_plan`) or the
+//! governance proposal that scheduled it, before the chain halts.
+
+use ibc_classic_spec::{
+    IbcClassic, MsgUpgradeClientData, UpgradedClientStatePath, UpgradedConsensusStatePath,
+};
+use jsonrpsee::core::RpcResult;
+use tracing::debug;
+use unionlabs::{
+    bytes::Bytes,
+    ibc::core::client::{height::Height, msg_upgrade_client::MsgUpgradeClient},
+    id::ClientId,
+};
+use voyager_message::{
+    core::{ChainId, ClientInfo, IbcStorePathKey},
+    error::VoyagerError,
+    query::VoyagerQuery,
+    rpc::IbcProof,
+};
+
+/// Build the `MsgUpgradeClient` that upgrades `client_id` on `chain_id` across the upgrade of
+/// `counterparty_chain_id` at `upgrade_height`.
+///
+/// `upgrade_height` is the height of the upgrade plan, in the revision of the counterparty chain
+/// before the upgrade.
+pub async fn make_msg_upgrade_client(
+    client: &impl VoyagerQuery,
+    chain_id: &ChainId,
+    client_id: ClientId,
+    counterparty_chain_id: &ChainId,
+    upgrade_height: Height,
+) -> RpcResult<MsgUpgradeClientData> {
+    let state_height = match upgrade_height.height().checked_sub(1) {
+        Some(height) if height > 0 => Height::new_with_revision(upgrade_height.revision(), height),
+        _ => {
+            return Err(VoyagerError::fatal(format!(
+                "invalid upgrade height {upgrade_height}, the upgraded states are read at the \
+                height before it"
+            ))
+            .into())
+        }
+    };
+
+    let client_info = client
+        .client_info::<IbcClassic>(chain_id, client_id.clone())
+        .await?;
+
+    debug!(
+        %client_id,
+        %client_info.client_type,
+        %client_info.ibc_interface,
+        %upgrade_height,
+    );
+
+    let client_state_path = UpgradedClientStatePath {
+        upgrade_height: upgrade_height.height(),
+    };
+    let consensus_state_path = UpgradedConsensusStatePath {
+        upgrade_height: upgrade_height.height(),
+    };
+
+    let client_state = client
+        .query_ibc_state(
+            counterparty_chain_id,
+            state_height.into(),
+            client_state_path.clone(),
+        )
+        .await?
+        .state;
+    let consensus_state = client
+        .query_ibc_state(
+            counterparty_chain_id,
+            state_height.into(),
+            consensus_state_path.clone(),
+        )
+        .await?
+        .state;
+
+    let proof_upgrade_client = upgrade_proof(
+        client,
+        &client_info,
+        counterparty_chain_id,
+        upgrade_height,
+        client_state_path,
+    )
+    .await?;
+    let proof_upgrade_consensus_state = upgrade_proof(
+        client,
+        &client_info,
+        counterparty_chain_id,
+        upgrade_height,
+        consensus_state_path,
+    )
+    .await?;
+
+    Ok(MsgUpgradeClientData {
+        msg: MsgUpgradeClient {
+            client_id,
+            client_state,
+            consensus_state,
+            proof_upgrade_client,
+            proof_upgrade_consensus_state,
+        },
+        upgrade_height,
+    })
+}
+
+/// Read the proof of `path` at `upgrade_height`, and encode it for the client described by
+/// `client_info`.
+async fn upgrade_proof<P: IbcStorePathKey<Spec = IbcClassic>>(
+    client: &impl VoyagerQuery,
+    client_info: &ClientInfo,
+    counterparty_chain_id: &ChainId,
+    upgrade_height: Height,
+    path: P,
+) -> RpcResult<Bytes> {
+    let IbcProof { height, proof } = client
+        .query_ibc_proof(counterparty_chain_id, upgrade_height.into(), path)
+        .await?;
+
+    // the client verifies the proof at its latest height, which must be the upgrade height
+    if height != upgrade_height {
+        return Err(VoyagerError::fatal(format!(
+            "the proof of the upgraded states is at height {height}, but must be at the upgrade \
+            height {upgrade_height}"
+        ))
+        .into());
+    }
+
+    client.encode_proof::<IbcClassic>(client_info, proof).await
+}
+
+#[cfg(test)]
+mod tests {
+    use ibc_classic_spec::StorePath;
+    use prost::Message;
+    use serde::Deserialize;
+    use serde_json::Value;
+    use voyager_message::{
+        core::{ClientType, IbcInterface, QueryHeight},
+        into_value,
+        testing::MockVoyager,
+    };
+
+    use super::*;
+
+    /// The chain that is upgraded.
+    const COUNTERPARTY: &str = "union-testnet-8";
+    /// The chain with the client tracking the upgraded chain.
+    const HOST: &str = "osmosis-1";
+
+    /// The upgraded states committed by `x/upgrade` for an upgrade to `union-testnet-9`, with the
+    /// custom fields of the client state zeroed.
+    #[derive(Deserialize)]
+    struct UpgradedStates {
+        client_state: Bytes,
+        consensus_state: Bytes,
+    }
+
+    fn upgraded_states() -> UpgradedStates {
+        serde_json::from_str(include_str!("./test/upgrade/upgraded-states.json")).unwrap()
+    }
+
+    fn client_id() -> ClientId {
+        ClientId::new("07-tendermint", 2)
+    }
+
+    fn voyager() -> MockVoyager {
+        let states = upgraded_states();
+        let counterparty = ChainId::new(COUNTERPARTY);
+
+        let voyager = MockVoyager::new();
+
+        voyager
+            .set_client_info::<IbcClassic>(
+                &ChainId::new(HOST),
+                client_id(),
+                ClientInfo {
+                    client_type: ClientType::new(ClientType::TENDERMINT),
+                    ibc_interface: IbcInterface::new(IbcInterface::IBC_GO_V8_NATIVE),
+                    metadata: Value::Null,
+                },
+            )
+            .set_state(
+                &counterparty,
+                UpgradedClientStatePath {
+                    upgrade_height: 1200,
+                },
+                states.client_state,
+            )
+            .set_state(
+                &counterparty,
+                UpgradedConsensusStatePath {
+                    upgrade_height: 1200,
+                },
+                states.consensus_state,
+            );
+
+        voyager
+    }
+
+    fn encoded_proof<P: IbcStorePathKey<Spec = IbcClassic>>(
+        upgrade_height: Height,
+        path: P,
+    ) -> Bytes {
+        MockVoyager::encoded(&MockVoyager::proof(upgrade_height, path))
+    }
+
+    /// The heights and paths of the calls of `method`.
+    fn queries(voyager: &MockVoyager, method: &str) -> Vec<(Value, Value)> {
+        voyager
+            .calls(method)
+            .into_iter()
+            .map(|params| {
+                assert_eq!(params["chain_id"], COUNTERPARTY);
+                (params["height"].clone(), params["path"].clone())
+            })
+            .collect()
+    }
+
+    async fn make_msg(
+        voyager: &MockVoyager,
+        upgrade_height: Height,
+    ) -> RpcResult<MsgUpgradeClientData> {
+        make_msg_upgrade_client(
+            voyager,
+            &ChainId::new(HOST),
+            client_id(),
+            &ChainId::new(COUNTERPARTY),
+            upgrade_height,
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn upgrade_client() {
+        let voyager = voyager();
+
+        let upgrade_height = Height::new_with_revision(8, 1200);
+
+        let msg = make_msg(&voyager, upgrade_height).await.unwrap();
+
+        let client_state_path = UpgradedClientStatePath {
+            upgrade_height: 1200,
+        };
+        let consensus_state_path = UpgradedConsensusStatePath {
+            upgrade_height: 1200,
+        };
+
+        // the states are read at the last block executed before the upgrade, and proven at the
+        // upgrade height
+        let at = |height: Height| into_value(QueryHeight::Specific(height));
+        let path = |path: StorePath| into_value(path);
+
+        assert_eq!(
+            queries(&voyager, "query_ibc_state"),
+            [
+                (
+                    at(Height::new_with_revision(8, 1199)),
+                    path(client_state_path.clone().into())
+                ),
+                (
+                    at(Height::new_with_revision(8, 1199)),
+                    path(consensus_state_path.clone().into())
+                ),
+            ]
+        );
+        assert_eq!(
+            queries(&voyager, "query_ibc_proof"),
+            [
+                (at(upgrade_height), path(client_state_path.clone().into())),
+                (
+                    at(upgrade_height),
+                    path(consensus_state_path.clone().into())
+                ),
+            ]
+        );
+        assert!(voyager
+            .calls("encode_proof")
+            .iter()
+            .all(|params| params["client_type"] == ClientType::TENDERMINT));
+
+        let states = upgraded_states();
+
+        assert_eq!(
+            msg,
+            MsgUpgradeClientData {
+                msg: MsgUpgradeClient {
+                    client_id: client_id(),
+                    client_state: states.client_state,
+                    consensus_state: states.consensus_state,
+                    proof_upgrade_client: encoded_proof(upgrade_height, client_state_path),
+                    proof_upgrade_consensus_state: encoded_proof(
+                        upgrade_height,
+                        consensus_state_path
+                    ),
+                },
+                upgrade_height,
+            }
+        );
+        assert_eq!(
+            ibc_classic_spec::Datagram::from(msg.clone()).proof_height(),
+            Some(upgrade_height)
+        );
+
+        // the upgraded states are submitted exactly as committed on the counterparty
+        let client_state = protos::google::protobuf::Any::decode(&*msg.msg.client_state).unwrap();
+        assert_eq!(
+            client_state.type_url,
+            "/ibc.lightclients.tendermint.v1.ClientState"
+        );
+        let consensus_state =
+            protos::google::protobuf::Any::decode(&*msg.msg.consensus_state).unwrap();
+        assert_eq!(
+            consensus_state.type_url,
+            "/ibc.lightclients.tendermint.v1.ConsensusState"
+        );
+    }
+
+    #[tokio::test]
+    async fn proof_at_other_height_is_rejected() {
+        let voyager = voyager();
+        voyager.set_proof_height(
+            &ChainId::new(COUNTERPARTY),
+            Height::new_with_revision(8, 1200),
+            Height::new_with_revision(8, 1201),
+        );
+
+        let err = make_msg(&voyager, Height::new_with_revision(8, 1200))
+            .await
+            .unwrap_err();
+
+        assert_eq!(
+            VoyagerError::from(err),
+            VoyagerError::fatal(
+                "the proof of the upgraded states is at height 8-1201, but must be at the upgrade \
+                height 8-1200"
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn upgrade_at_first_block_is_rejected() {
+        let voyager = voyager();
+
+        make_msg(&voyager, Height::new_with_revision(8, 1))
+            .await
+            .unwrap_err();
+
+        assert!(voyager.calls("query_ibc_state").is_empty());
+    }
+}
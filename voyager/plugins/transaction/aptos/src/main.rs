@@ -25,9 +25,10 @@ use sha3::Digest;
 use tracing::instrument;
 use unionlabs::{hash::H256, ErrorReporter};
 use voyager_message::{
-    core::ChainId,
+    cmd::CmdOutput,
+    core::{ChainId, IbcSpec},
     data::{Data, WithChainId},
-    module::{PluginInfo, PluginServer},
+    module::{PluginInfo, PluginKind, PluginServer},
     DefaultCmd, Plugin, PluginMessage, VoyagerMessage, FATAL_JSONRPC_ERROR_CODE,
 };
 use voyager_vm::{call, noop, pass::PassResult, Op};
@@ -112,10 +113,13 @@ end
 "#,
                 chain_id = config.chain_id,
             ),
+            kind: Some(PluginKind::Transaction),
+            chains: vec![config.chain_id.clone()],
+            ibc_specs: vec![IbcUnion::ID],
         }
     }
 
-    async fn cmd(_config: Self::Config, cmd: Self::Cmd) {
+    async fn cmd(_config: Self::Config, cmd: Self::Cmd) -> Result<Box<dyn CmdOutput>, BoxDynError> {
         match cmd {}
     }
 }
@@ -339,14 +343,14 @@ async fn process_msgs<T: aptos_move_ibc::ibc::ClientExt>(
                 msg,
                 client.update_client(
                     ibc_handler_address,
-                    (data.client_id, data.client_message.into_vec()),
+                    (data.client_id.get(), data.client_message.into_vec()),
                 ),
             ),
             Datagram::ConnectionOpenInit(data) => (
                 msg,
                 client.connection_open_init(
                     ibc_handler_address,
-                    (data.client_id, data.counterparty_client_id),
+                    (data.client_id.get(), data.counterparty_client_id.get()),
                 ),
             ),
 
@@ -355,9 +359,9 @@ async fn process_msgs<T: aptos_move_ibc::ibc::ClientExt>(
                 client.connection_open_try(
                     ibc_handler_address,
                     (
-                        data.counterparty_client_id,
-                        data.counterparty_connection_id,
-                        data.client_id,
+                        data.counterparty_client_id.get(),
+                        data.counterparty_connection_id.get(),
+                        data.client_id.get(),
                         data.proof_init.into_vec(),
                         data.proof_height,
                     ),
@@ -368,8 +372,8 @@ async fn process_msgs<T: aptos_move_ibc::ibc::ClientExt>(
                 client.connection_open_ack(
                     ibc_handler_address,
                     (
-                        data.connection_id,
-                        data.counterparty_connection_id,
+                        data.connection_id.get(),
+                        data.counterparty_connection_id.get(),
                         data.proof_try.into_vec(),
                         data.proof_height,
                     ),
@@ -380,7 +384,7 @@ async fn process_msgs<T: aptos_move_ibc::ibc::ClientExt>(
                 client.connection_open_confirm(
                     ibc_handler_address,
                     (
-                        data.connection_id,
+                        data.connection_id.get(),
                         data.proof_ack.into_vec(),
                         data.proof_height,
                     ),
@@ -395,7 +399,7 @@ async fn process_msgs<T: aptos_move_ibc::ibc::ClientExt>(
                             .unwrap()
                             .into(),
                         data.counterparty_port_id.into_vec(),
-                        data.connection_id,
+                        data.connection_id.get(),
                         data.version,
                     ),
                     (ibc_app_witness(data.port_id.as_ref().try_into().unwrap()),),
@@ -423,7 +427,7 @@ async fn process_msgs<T: aptos_move_ibc::ibc::ClientExt>(
             ),
             Datagram::ChannelOpenAck(data) => {
                 let port_id = client
-                    .get_module(ibc_handler_address, None, (data.channel_id,))
+                    .get_module(ibc_handler_address, None, (data.channel_id.get(),))
                     .await
                     .unwrap();
                 (
@@ -432,9 +436,9 @@ async fn process_msgs<T: aptos_move_ibc::ibc::ClientExt>(
                         ibc_handler_address,
                         (
                             port_id,
-                            data.channel_id,
+                            data.channel_id.get(),
                             data.counterparty_version,
-                            data.counterparty_channel_id,
+                            data.counterparty_channel_id.get(),
                             data.proof_try.into_vec(),
                             data.proof_height,
                         ),
@@ -444,7 +448,7 @@ async fn process_msgs<T: aptos_move_ibc::ibc::ClientExt>(
             }
             Datagram::ChannelOpenConfirm(data) => {
                 let port_id = client
-                    .get_module(ibc_handler_address, None, (data.channel_id,))
+                    .get_module(ibc_handler_address, None, (data.channel_id.get(),))
                     .await
                     .unwrap();
                 (
@@ -453,7 +457,7 @@ async fn process_msgs<T: aptos_move_ibc::ibc::ClientExt>(
                         ibc_handler_address,
                         (
                             port_id,
-                            data.channel_id,
+                            data.channel_id.get(),
                             data.proof_ack.into_vec(),
                             data.proof_height,
                         ),
@@ -0,0 +1,506 @@
+//! Broadcasting transactions via multiple rpc endpoints.
+//!
+//! Some nodes (especially public rpc endpoints) accept transactions into their
+//! mempool but never gossip them to the rest of the network. To work around
+//! this, the signed transaction can be broadcast to multiple endpoints at once.
+//!
+//! Each call to an endpoint is bounded by the configured [`TimeoutConfig`], and an endpoint that
+//! doesn't respond in time is treated the same as an endpoint that is down.
+
+use std::future::Future;
+
+use chain_utils::{
+    cosmos_sdk::cosmos_sdk_error::{CosmosSdkError, SdkError},
+    timeout::{CallKind, TimeoutConfig},
+};
+use cometbft_rpc::{rpc_types::BroadcastTxSyncResponse, JsonRpcError};
+use futures::{stream::FuturesUnordered, StreamExt};
+use tracing::{debug, warn};
+use unionlabs::{hash::H256, ErrorReporter};
+
+/// A cometbft rpc endpoint that a transaction can be broadcast to.
+pub trait BroadcastEndpoint: Send + Sync {
+    fn broadcast_tx_sync(
+        &self,
+        tx: &[u8],
+    ) -> impl Future<Output = Result<BroadcastTxSyncResponse, JsonRpcError>> + Send;
+}
+
+impl BroadcastEndpoint for cometbft_rpc::Client {
+    async fn broadcast_tx_sync(&self, tx: &[u8]) -> Result<BroadcastTxSyncResponse, JsonRpcError> {
+        cometbft_rpc::Client::broadcast_tx_sync(self, tx).await
+    }
+}
+
+/// The result of broadcasting a transaction to one or more endpoints.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Broadcast {
+    /// The transaction was accepted into the mempool of at least one endpoint,
+    /// or was already in it.
+    Accepted,
+    /// The transaction was rejected by `CheckTx` on every endpoint that
+    /// responded.
+    Rejected(BroadcastTxSyncResponse),
+}
+
+/// Broadcast `tx` to all of the `endpoints` concurrently.
+///
+/// The first endpoint to accept the transaction wins, rejections and errors
+/// from the other endpoints are only logged. If no endpoint accepted the
+/// transaction, the first rejection is returned, and if no endpoint responded
+/// at all, the last error is returned.
+///
+/// A transaction that is already in the mempool (or mempool cache) of an
+/// endpoint is considered accepted, since this is expected when the
+/// transaction has already been gossiped to it by another endpoint.
+///
+/// Endpoints that don't respond within the broadcast timeout fail with
+/// [`JsonRpcError::RequestTimeout`]. An endpoint that responds with a hash
+/// other than `tx_hash` is treated as failed as well, since its response can't
+/// be for this transaction.
+///
+/// # Panics
+///
+/// Panics if `endpoints` is empty.
+pub async fn broadcast<E: BroadcastEndpoint>(
+    endpoints: &[(String, E)],
+    tx: &[u8],
+    tx_hash: H256,
+    timeouts: &TimeoutConfig,
+) -> Result<Broadcast, JsonRpcError> {
+    assert!(!endpoints.is_empty(), "at least one endpoint is required");
+
+    let mut responses = endpoints
+        .iter()
+        .map(|(url, endpoint)| async move {
+            (
+                url,
+                bounded(
+                    timeouts,
+                    CallKind::Broadcast,
+                    endpoint.broadcast_tx_sync(tx),
+                )
+                .await,
+            )
+        })
+        .collect::<FuturesUnordered<_>>();
+
+    let mut accepted = false;
+    let mut rejection = None;
+    let mut error = None;
+
+    // all broadcasts are driven to completion, even after the transaction has been accepted by
+    // one endpoint
+    while let Some((url, response)) = responses.next().await {
+        match response {
+            Ok(response) if response.hash != tx_hash => {
+                warn!(
+                    %url,
+                    expected = %tx_hash,
+                    found = %response.hash,
+                    "endpoint returned an unexpected tx hash"
+                );
+
+                error = Some(JsonRpcError::Custom(format!(
+                    "endpoint {url} returned tx hash {} instead of {tx_hash}",
+                    response.hash
+                )));
+            }
+            Ok(response) => {
+                debug!(
+                    %url,
+                    check_tx_code = %response.code,
+                    codespace = %response.codespace,
+                    check_tx_log = %response.log,
+                    "broadcast tx"
+                );
+
+                if response.code == 0 || is_already_in_mempool_response(&response) {
+                    accepted = true;
+                } else {
+                    warn!(
+                        %url,
+                        check_tx_code = %response.code,
+                        codespace = %response.codespace,
+                        check_tx_log = %response.log,
+                        "tx was rejected"
+                    );
+
+                    rejection.get_or_insert(response);
+                }
+            }
+            Err(err) if is_already_in_mempool_error(&err) => {
+                debug!(%url, "tx is already in the mempool cache");
+
+                accepted = true;
+            }
+            Err(err) => {
+                warn!(%url, error = %ErrorReporter(&err), "error broadcasting tx");
+
+                error = Some(err);
+            }
+        }
+    }
+
+    if accepted {
+        Ok(Broadcast::Accepted)
+    } else if let Some(rejection) = rejection {
+        Ok(Broadcast::Rejected(rejection))
+    } else {
+        Err(error.expect("at least one endpoint was broadcast to; qed;"))
+    }
+}
+
+/// Run `f` against the first of `endpoints`, falling back to the next endpoint
+/// if it doesn't respond (within the query timeout). Errors returned *by* an
+/// endpoint (i.e. a tx not being found) are returned as-is.
+///
+/// # Panics
+///
+/// Panics if `endpoints` is empty.
+pub async fn with_fallback<'a, E, T, Fut>(
+    endpoints: &'a [(String, E)],
+    timeouts: &TimeoutConfig,
+    f: impl Fn(&'a E) -> Fut,
+) -> Result<T, JsonRpcError>
+where
+    Fut: Future<Output = Result<T, JsonRpcError>>,
+{
+    let (last, rest) = endpoints
+        .split_last()
+        .expect("at least one endpoint is required");
+
+    for (url, endpoint) in rest {
+        match bounded(timeouts, CallKind::Query, f(endpoint)).await {
+            Err(err) if is_unresponsive(&err) => {
+                warn!(
+                    %url,
+                    error = %ErrorReporter(&err),
+                    "endpoint is unresponsive, falling back to the next one"
+                );
+            }
+            res => return res,
+        }
+    }
+
+    bounded(timeouts, CallKind::Query, f(&last.1)).await
+}
+
+/// Await `fut` within the timeout of `kind`, failing with [`JsonRpcError::RequestTimeout`] if it
+/// doesn't complete in time.
+async fn bounded<T>(
+    timeouts: &TimeoutConfig,
+    kind: CallKind,
+    fut: impl Future<Output = Result<T, JsonRpcError>>,
+) -> Result<T, JsonRpcError> {
+    timeouts
+        .with_timeout(kind, fut)
+        .await
+        .unwrap_or_else(|err| {
+            warn!(error = %ErrorReporter(err), "endpoint did not respond in time");
+
+            Err(JsonRpcError::RequestTimeout)
+        })
+}
+
+fn is_unresponsive(err: &JsonRpcError) -> bool {
+    matches!(
+        err,
+        JsonRpcError::Transport(_) | JsonRpcError::RestartNeeded(_) | JsonRpcError::RequestTimeout
+    )
+}
+
+fn is_already_in_mempool_response(response: &BroadcastTxSyncResponse) -> bool {
+    matches!(
+        CosmosSdkError::from_code_and_codespace(&response.codespace, response.code),
+        CosmosSdkError::SdkError(SdkError::ErrTxInMempoolCache)
+    )
+}
+
+fn is_already_in_mempool_error(err: &JsonRpcError) -> bool {
+    // cometbft returns this as an rpc error instead of a CheckTx response
+    matches!(err, JsonRpcError::Call(err) if err.message().contains("tx already exists in cache")
+        || err.data().is_some_and(|data| data.get().contains("tx already exists in cache")))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use jsonrpsee::types::ErrorObject;
+
+    use super::*;
+
+    const TX_HASH: H256 = H256::new([1; 32]);
+
+    enum MockEndpoint {
+        Respond {
+            code: u32,
+            codespace: &'static str,
+        },
+        Down,
+        /// Accepts the request, but never responds.
+        Hung,
+        AlreadyInCache,
+        /// Accepts the tx, but responds with a hash other than [`TX_HASH`].
+        WrongHash,
+    }
+
+    impl MockEndpoint {
+        const OK: Self = Self::Respond {
+            code: 0,
+            codespace: "",
+        };
+    }
+
+    impl BroadcastEndpoint for (MockEndpoint, Arc<AtomicUsize>) {
+        async fn broadcast_tx_sync(
+            &self,
+            _: &[u8],
+        ) -> Result<BroadcastTxSyncResponse, JsonRpcError> {
+            self.1.fetch_add(1, Ordering::SeqCst);
+
+            match self.0 {
+                MockEndpoint::Respond { code, codespace } => Ok(BroadcastTxSyncResponse {
+                    codespace: codespace.to_owned(),
+                    code,
+                    data: Default::default(),
+                    log: String::new(),
+                    hash: TX_HASH.into_encoding(),
+                }),
+                MockEndpoint::Down => Err(JsonRpcError::RequestTimeout),
+                MockEndpoint::Hung => std::future::pending().await,
+                MockEndpoint::AlreadyInCache => Err(JsonRpcError::Call(ErrorObject::owned(
+                    -32603,
+                    "Internal error",
+                    Some("tx already exists in cache"),
+                ))),
+                MockEndpoint::WrongHash => Ok(BroadcastTxSyncResponse {
+                    codespace: String::new(),
+                    code: 0,
+                    data: Default::default(),
+                    log: String::new(),
+                    hash: H256::new([2; 32]).into_encoding(),
+                }),
+            }
+        }
+    }
+
+    fn mock_endpoints(
+        mocks: impl IntoIterator<Item = MockEndpoint>,
+    ) -> (
+        Vec<(String, (MockEndpoint, Arc<AtomicUsize>))>,
+        Arc<AtomicUsize>,
+    ) {
+        let calls = Arc::new(AtomicUsize::new(0));
+
+        (
+            mocks
+                .into_iter()
+                .enumerate()
+                .map(|(i, mock)| (format!("endpoint-{i}"), (mock, calls.clone())))
+                .collect(),
+            calls,
+        )
+    }
+
+    #[tokio::test]
+    async fn single_endpoint() {
+        let (endpoints, calls) = mock_endpoints([MockEndpoint::OK]);
+
+        assert_eq!(
+            broadcast(&endpoints, b"tx", TX_HASH, &TimeoutConfig::default())
+                .await
+                .unwrap(),
+            Broadcast::Accepted
+        );
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn one_endpoint_down() {
+        let (endpoints, calls) = mock_endpoints([MockEndpoint::Down, MockEndpoint::OK]);
+
+        assert_eq!(
+            broadcast(&endpoints, b"tx", TX_HASH, &TimeoutConfig::default())
+                .await
+                .unwrap(),
+            Broadcast::Accepted
+        );
+        // the tx is broadcast to every endpoint
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn all_endpoints_down() {
+        let (endpoints, _) = mock_endpoints([MockEndpoint::Down, MockEndpoint::Down]);
+
+        assert!(matches!(
+            broadcast(&endpoints, b"tx", TX_HASH, &TimeoutConfig::default()).await,
+            Err(JsonRpcError::RequestTimeout)
+        ));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn hung_endpoint_times_out() {
+        let (endpoints, _) = mock_endpoints([MockEndpoint::Hung, MockEndpoint::OK]);
+
+        assert_eq!(
+            broadcast(&endpoints, b"tx", TX_HASH, &TimeoutConfig::default())
+                .await
+                .unwrap(),
+            Broadcast::Accepted
+        );
+
+        let (endpoints, _) = mock_endpoints([MockEndpoint::Hung]);
+
+        let start = tokio::time::Instant::now();
+
+        assert!(matches!(
+            broadcast(&endpoints, b"tx", TX_HASH, &TimeoutConfig::default()).await,
+            Err(JsonRpcError::RequestTimeout)
+        ));
+        assert_eq!(start.elapsed(), CallKind::Broadcast.default_timeout());
+    }
+
+    #[tokio::test]
+    async fn already_in_cache_is_accepted() {
+        let (endpoints, _) = mock_endpoints([
+            MockEndpoint::AlreadyInCache,
+            MockEndpoint::Respond {
+                code: 19,
+                codespace: "sdk",
+            },
+        ]);
+
+        assert_eq!(
+            broadcast(&endpoints, b"tx", TX_HASH, &TimeoutConfig::default())
+                .await
+                .unwrap(),
+            Broadcast::Accepted
+        );
+    }
+
+    #[tokio::test]
+    async fn wrong_hash_is_treated_as_failed() {
+        let (endpoints, _) = mock_endpoints([MockEndpoint::WrongHash]);
+
+        assert!(matches!(
+            broadcast(&endpoints, b"tx", TX_HASH, &TimeoutConfig::default()).await,
+            Err(JsonRpcError::Custom(err)) if err.contains("endpoint-0")
+        ));
+
+        let (endpoints, calls) = mock_endpoints([MockEndpoint::WrongHash, MockEndpoint::OK]);
+
+        assert_eq!(
+            broadcast(&endpoints, b"tx", TX_HASH, &TimeoutConfig::default())
+                .await
+                .unwrap(),
+            Broadcast::Accepted
+        );
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn rejection_is_returned_if_not_accepted() {
+        let wrong_sequence = MockEndpoint::Respond {
+            code: 32,
+            codespace: "sdk",
+        };
+
+        let (endpoints, _) = mock_endpoints([MockEndpoint::Down, wrong_sequence]);
+
+        let Broadcast::Rejected(response) =
+            broadcast(&endpoints, b"tx", TX_HASH, &TimeoutConfig::default())
+                .await
+                .unwrap()
+        else {
+            panic!("expected rejection");
+        };
+
+        assert_eq!(
+            CosmosSdkError::from_code_and_codespace(&response.codespace, response.code),
+            CosmosSdkError::SdkError(SdkError::ErrWrongSequence)
+        );
+
+        // another endpoint accepting the tx takes precedence over the rejection
+        let (endpoints, _) = mock_endpoints([
+            MockEndpoint::Respond {
+                code: 32,
+                codespace: "sdk",
+            },
+            MockEndpoint::OK,
+        ]);
+
+        assert_eq!(
+            broadcast(&endpoints, b"tx", TX_HASH, &TimeoutConfig::default())
+                .await
+                .unwrap(),
+            Broadcast::Accepted
+        );
+    }
+
+    #[tokio::test]
+    async fn fallback_on_unresponsive_endpoint() {
+        let endpoints = [
+            ("primary".to_owned(), Err(JsonRpcError::RequestTimeout)),
+            ("secondary".to_owned(), Ok(2)),
+            ("tertiary".to_owned(), Ok(3)),
+        ];
+
+        assert_eq!(
+            with_fallback(&endpoints, &TimeoutConfig::default(), |res| async move {
+                match res {
+                    Ok(n) => Ok(*n),
+                    Err(_) => Err(JsonRpcError::RequestTimeout),
+                }
+            })
+            .await
+            .unwrap(),
+            2
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn fallback_on_hung_endpoint() {
+        let endpoints = [
+            ("primary".to_owned(), None),
+            ("secondary".to_owned(), Some(2)),
+        ];
+
+        assert_eq!(
+            with_fallback(&endpoints, &TimeoutConfig::default(), |res| async move {
+                match res {
+                    Some(n) => Ok(*n),
+                    None => std::future::pending().await,
+                }
+            })
+            .await
+            .unwrap(),
+            2
+        );
+    }
+
+    #[tokio::test]
+    async fn no_fallback_on_endpoint_error() {
+        let endpoints = [("primary".to_owned(), ()), ("secondary".to_owned(), ())];
+
+        let calls = AtomicUsize::new(0);
+
+        let res = with_fallback(&endpoints, &TimeoutConfig::default(), |_| async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Err::<(), _>(JsonRpcError::Call(ErrorObject::owned(
+                -32603,
+                "tx not found",
+                None::<()>,
+            )))
+        })
+        .await;
+
+        assert!(matches!(res, Err(JsonRpcError::Call(_))));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+}
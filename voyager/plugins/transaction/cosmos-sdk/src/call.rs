@@ -1,15 +1,20 @@
+use chain_utils::{
+    cosmos_sdk::GasMessageKind,
+    relay_progress::{ConfirmedPacket, RelayDirection},
+};
 use enumorph::Enumorph;
 use ibc_classic_spec::IbcClassic;
 use ibc_union_spec::IbcUnion;
-use jsonrpsee::{core::RpcResult, types::ErrorObject};
+use jsonrpsee::core::RpcResult;
 use macros::model;
 use unionlabs::ErrorReporter;
-use voyager_message::{data::IbcDatagram, FATAL_JSONRPC_ERROR_CODE};
+use voyager_message::{balance_monitor::CheckBalances, data::IbcDatagram, error::VoyagerError};
 
 #[model]
 #[derive(Enumorph)]
 pub enum ModuleCall {
     SubmitTransaction(Vec<IbcMessage>),
+    CheckBalances(CheckBalances),
 }
 
 #[model]
@@ -20,27 +25,530 @@ pub enum IbcMessage {
 }
 
 impl IbcMessage {
-    pub fn from_raw_datagram(datagram: IbcDatagram) -> RpcResult<Self> {
+    /// Client updates and upgrades must always be submitted, even if the daily budget is exceeded,
+    /// to avoid the clients expiring.
+    pub fn is_priority(&self) -> bool {
+        matches!(
+            self,
+            IbcMessage::IbcV1(
+                ibc_classic_spec::Datagram::UpdateClient(_)
+                    | ibc_classic_spec::Datagram::UpgradeClient(_)
+            ) | IbcMessage::IbcUnion(ibc_union_spec::Datagram::UpdateClient(_))
+        )
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            IbcMessage::IbcV1(msg) => msg.name(),
+            IbcMessage::IbcUnion(msg) => msg.name(),
+        }
+    }
+
+    /// The kind of this message, for [`GasConfig::per_message_multipliers`].
+    ///
+    /// ibc-union messages that don't correspond to a kind of ibc message are classified as
+    /// [`GasMessageKind::WasmExecute`], since they are all submitted as wasm contract executions.
+    ///
+    /// [`GasConfig::per_message_multipliers`]: chain_utils::cosmos_sdk::GasConfig::per_message_multipliers
+    pub fn gas_kind(&self) -> GasMessageKind {
+        use ibc_classic_spec::Datagram as Classic;
+        use ibc_union_spec::Datagram as Union;
+
+        match self {
+            IbcMessage::IbcV1(msg) => match msg {
+                Classic::CreateClient(_) => GasMessageKind::CreateClient,
+                Classic::UpdateClient(_) | Classic::UpgradeClient(_) => {
+                    GasMessageKind::UpdateClient
+                }
+                Classic::ConnectionOpenInit(_)
+                | Classic::ConnectionOpenTry(_)
+                | Classic::ConnectionOpenAck(_)
+                | Classic::ConnectionOpenConfirm(_) => GasMessageKind::ConnectionHandshake,
+                Classic::ChannelOpenInit(_)
+                | Classic::ChannelOpenTry(_)
+                | Classic::ChannelOpenAck(_)
+                | Classic::ChannelOpenConfirm(_) => GasMessageKind::ChannelHandshake,
+                Classic::RecvPacket(_) => GasMessageKind::PacketRecv,
+                Classic::AcknowledgePacket(_) => GasMessageKind::PacketAck,
+                Classic::TimeoutPacket(_) => GasMessageKind::PacketTimeout,
+            },
+            IbcMessage::IbcUnion(msg) => match msg {
+                Union::CreateClient(_) => GasMessageKind::CreateClient,
+                Union::UpdateClient(_) => GasMessageKind::UpdateClient,
+                Union::ConnectionOpenInit(_)
+                | Union::ConnectionOpenTry(_)
+                | Union::ConnectionOpenAck(_)
+                | Union::ConnectionOpenConfirm(_) => GasMessageKind::ConnectionHandshake,
+                Union::ChannelOpenInit(_)
+                | Union::ChannelOpenTry(_)
+                | Union::ChannelOpenAck(_)
+                | Union::ChannelOpenConfirm(_)
+                | Union::ChannelCloseInit(_)
+                | Union::ChannelCloseConfirm(_) => GasMessageKind::ChannelHandshake,
+                Union::PacketRecv(_) | Union::IntentPacketRecv(_) => GasMessageKind::PacketRecv,
+                Union::PacketAcknowledgement(_) => GasMessageKind::PacketAck,
+                Union::PacketTimeout(_) => GasMessageKind::PacketTimeout,
+                Union::BatchSend(_) | Union::BatchAcks(_) => GasMessageKind::WasmExecute,
+            },
+        }
+    }
+
+    /// The packet relayed by this message, for [`relay_progress`](chain_utils::relay_progress).
+    ///
+    /// Only ibc-classic packets are tracked, since ibc-union packets are not sequenced. Timeouts
+    /// are not tracked either, as they don't relay the packet.
+    pub fn confirmed_packet(&self) -> Option<ConfirmedPacket> {
+        use ibc_classic_spec::Datagram as Classic;
+
+        let (channel_id, direction, sequence) = match self {
+            IbcMessage::IbcV1(Classic::RecvPacket(msg)) => (
+                &msg.packet.destination_channel,
+                RelayDirection::Recv,
+                msg.packet.sequence,
+            ),
+            IbcMessage::IbcV1(Classic::AcknowledgePacket(msg)) => (
+                &msg.packet.source_channel,
+                RelayDirection::Ack,
+                msg.packet.sequence,
+            ),
+            _ => return None,
+        };
+
+        Some(ConfirmedPacket {
+            channel: channel_id.to_string_prefixed(),
+            direction,
+            sequence: sequence.get(),
+        })
+    }
+
+    pub fn from_raw_datagram(datagram: &IbcDatagram) -> RpcResult<Self> {
         match datagram.decode_datagram::<IbcClassic>() {
             Some(Ok(ok)) => Ok(ok.into()),
-            Some(Err(err)) => Err(ErrorObject::owned(
-                FATAL_JSONRPC_ERROR_CODE,
-                format!("unable to decode IBC datagram: {}", ErrorReporter(err)),
-                None::<()>,
-            )),
+            Some(Err(err)) => Err(VoyagerError::fatal(format!(
+                "unable to decode IBC datagram: {}",
+                ErrorReporter(err)
+            ))
+            .into()),
             None => match datagram.decode_datagram::<IbcUnion>() {
                 Some(Ok(ok)) => Ok(ok.into()),
-                Some(Err(err)) => Err(ErrorObject::owned(
-                    FATAL_JSONRPC_ERROR_CODE,
-                    format!("unable to decode IBC datagram: {}", ErrorReporter(err)),
-                    None::<()>,
-                )),
-                None => Err(ErrorObject::owned(
-                    FATAL_JSONRPC_ERROR_CODE,
-                    format!("unknown IBC version id: {}", datagram.ibc_spec_id),
-                    None::<()>,
-                )),
+                Some(Err(err)) => Err(VoyagerError::fatal(format!(
+                    "unable to decode IBC datagram: {}",
+                    ErrorReporter(err)
+                ))
+                .into()),
+                None => Err(VoyagerError::fatal(format!(
+                    "unknown IBC version id: {}",
+                    datagram.ibc_spec_id
+                ))
+                .into()),
             },
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use ibc_classic_spec::{MsgCreateClientData, MsgUpgradeClientData};
+    use unionlabs::{
+        ibc::core::{
+            channel::{
+                self, msg_acknowledgement::MsgAcknowledgement,
+                msg_channel_open_ack::MsgChannelOpenAck,
+                msg_channel_open_confirm::MsgChannelOpenConfirm,
+                msg_channel_open_init::MsgChannelOpenInit, msg_channel_open_try::MsgChannelOpenTry,
+                msg_recv_packet::MsgRecvPacket, msg_timeout::MsgTimeout, order::Order,
+                packet::Packet, state::State,
+            },
+            client::{
+                height::Height, msg_create_client::MsgCreateClient,
+                msg_update_client::MsgUpdateClient, msg_upgrade_client::MsgUpgradeClient,
+            },
+            commitment::merkle_prefix::MerklePrefix,
+            connection::{
+                self, msg_connection_open_ack::MsgConnectionOpenAck,
+                msg_connection_open_confirm::MsgConnectionOpenConfirm,
+                msg_connection_open_init::MsgConnectionOpenInit,
+                msg_connection_open_try::MsgConnectionOpenTry, version::Version,
+            },
+        },
+        id::{ChannelId, ClientId, ConnectionId, PortId},
+    };
+    use voyager_message::core::ClientType;
+
+    use super::*;
+
+    fn classic_messages() -> Vec<(ibc_classic_spec::Datagram, GasMessageKind)> {
+        use ibc_classic_spec::Datagram;
+
+        let client_id = ClientId::new("07-tendermint", 1);
+        let port_id = PortId::new("transfer").unwrap();
+        let version = Version {
+            identifier: "1".to_owned(),
+            features: vec![Order::Unordered],
+        };
+        let connection_counterparty = connection::counterparty::Counterparty {
+            client_id: client_id.clone(),
+            connection_id: Some(ConnectionId::new(1)),
+            prefix: MerklePrefix {
+                key_prefix: b"ibc".into(),
+            },
+        };
+        let channel = channel::channel::Channel {
+            state: State::Init,
+            ordering: Order::Unordered,
+            counterparty: channel::counterparty::Counterparty {
+                port_id: port_id.clone(),
+                channel_id: None,
+            },
+            connection_hops: vec![ConnectionId::new(1)],
+            version: "ics20-1".to_owned(),
+            upgrade_sequence: 0,
+        };
+        let packet = Packet {
+            sequence: 1.try_into().unwrap(),
+            source_port: port_id.clone(),
+            source_channel: ChannelId::new(1),
+            destination_port: port_id.clone(),
+            destination_channel: ChannelId::new(2),
+            data: b"data".into(),
+            timeout_height: Height::new(100),
+            timeout_timestamp: 0,
+        };
+
+        vec![
+            (
+                Datagram::from(MsgCreateClientData {
+                    msg: MsgCreateClient {
+                        client_state: b"client state".into(),
+                        consensus_state: b"consensus state".into(),
+                    },
+                    client_type: ClientType::new(ClientType::TENDERMINT),
+                }),
+                GasMessageKind::CreateClient,
+            ),
+            (
+                Datagram::from(MsgUpdateClient {
+                    client_id: client_id.clone(),
+                    client_message: b"header".into(),
+                }),
+                GasMessageKind::UpdateClient,
+            ),
+            (
+                Datagram::from(MsgUpgradeClientData {
+                    msg: MsgUpgradeClient {
+                        client_id: client_id.clone(),
+                        client_state: b"client state".into(),
+                        consensus_state: b"consensus state".into(),
+                        proof_upgrade_client: b"proof".into(),
+                        proof_upgrade_consensus_state: b"proof".into(),
+                    },
+                    upgrade_height: Height::new(100),
+                }),
+                GasMessageKind::UpdateClient,
+            ),
+            (
+                Datagram::from(MsgConnectionOpenInit {
+                    client_id: client_id.clone(),
+                    counterparty: connection_counterparty.clone(),
+                    version: version.clone(),
+                    delay_period: 0,
+                }),
+                GasMessageKind::ConnectionHandshake,
+            ),
+            (
+                Datagram::from(MsgConnectionOpenTry {
+                    client_id,
+                    counterparty: connection_counterparty,
+                    delay_period: 0,
+                    counterparty_versions: vec![version.clone()],
+                    proof_height: Height::new(1),
+                    proof_init: b"proof".into(),
+                }),
+                GasMessageKind::ConnectionHandshake,
+            ),
+            (
+                Datagram::from(MsgConnectionOpenAck {
+                    connection_id: ConnectionId::new(1),
+                    counterparty_connection_id: ConnectionId::new(2),
+                    version,
+                    client_state: b"client state".into(),
+                    proof_height: Height::new(1),
+                    proof_try: b"proof".into(),
+                    proof_client: b"proof".into(),
+                    proof_consensus: b"proof".into(),
+                    consensus_height: Height::new(1),
+                }),
+                GasMessageKind::ConnectionHandshake,
+            ),
+            (
+                Datagram::from(MsgConnectionOpenConfirm {
+                    connection_id: ConnectionId::new(1),
+                    proof_ack: b"proof".into(),
+                    proof_height: Height::new(1),
+                }),
+                GasMessageKind::ConnectionHandshake,
+            ),
+            (
+                Datagram::from(MsgChannelOpenInit {
+                    port_id: port_id.clone(),
+                    channel: channel.clone(),
+                }),
+                GasMessageKind::ChannelHandshake,
+            ),
+            (
+                Datagram::from(MsgChannelOpenTry {
+                    port_id: port_id.clone(),
+                    channel,
+                    counterparty_version: "ics20-1".to_owned(),
+                    proof_init: b"proof".into(),
+                    proof_height: Height::new(1),
+                }),
+                GasMessageKind::ChannelHandshake,
+            ),
+            (
+                Datagram::from(MsgChannelOpenAck {
+                    port_id: port_id.clone(),
+                    channel_id: ChannelId::new(1),
+                    counterparty_channel_id: ChannelId::new(2),
+                    counterparty_version: "ics20-1".to_owned(),
+                    proof_try: b"proof".into(),
+                    proof_height: Height::new(1),
+                }),
+                GasMessageKind::ChannelHandshake,
+            ),
+            (
+                Datagram::from(MsgChannelOpenConfirm {
+                    port_id,
+                    channel_id: ChannelId::new(1),
+                    proof_ack: b"proof".into(),
+                    proof_height: Height::new(1),
+                }),
+                GasMessageKind::ChannelHandshake,
+            ),
+            (
+                Datagram::from(MsgRecvPacket {
+                    packet: packet.clone(),
+                    proof_commitment: b"proof".into(),
+                    proof_height: Height::new(1),
+                }),
+                GasMessageKind::PacketRecv,
+            ),
+            (
+                Datagram::from(MsgAcknowledgement {
+                    packet: packet.clone(),
+                    acknowledgement: b"ack".into(),
+                    proof_acked: b"proof".into(),
+                    proof_height: Height::new(1),
+                }),
+                GasMessageKind::PacketAck,
+            ),
+            (
+                Datagram::from(MsgTimeout {
+                    packet,
+                    proof_unreceived: b"proof".to_vec(),
+                    proof_height: Height::new(1),
+                    next_sequence_recv: 1.try_into().unwrap(),
+                }),
+                GasMessageKind::PacketTimeout,
+            ),
+        ]
+    }
+
+    fn union_messages() -> Vec<(ibc_union_spec::Datagram, GasMessageKind)> {
+        use ibc_solidity::{Channel, ChannelState, Packet};
+        use ibc_union_spec::{
+            Datagram, MsgBatchAcks, MsgBatchSend, MsgChannelCloseConfirm, MsgChannelCloseInit,
+            MsgChannelOpenAck, MsgChannelOpenConfirm, MsgChannelOpenInit, MsgChannelOpenTry,
+            MsgConnectionOpenAck, MsgConnectionOpenConfirm, MsgConnectionOpenInit,
+            MsgConnectionOpenTry, MsgCreateClient, MsgIntentPacketRecv, MsgPacketAcknowledgement,
+            MsgPacketRecv, MsgPacketTimeout, MsgUpdateClient, UnionId,
+        };
+
+        let packet = Packet {
+            source_channel: 1,
+            destination_channel: 2,
+            data: Default::default(),
+            timeout_height: 0,
+            timeout_timestamp: 100,
+        };
+
+        vec![
+            (
+                Datagram::from(MsgCreateClient {
+                    client_type: ClientType::new(ClientType::COMETBLS),
+                    client_state_bytes: b"client state".into(),
+                    consensus_state_bytes: b"consensus state".into(),
+                }),
+                GasMessageKind::CreateClient,
+            ),
+            (
+                Datagram::from(MsgUpdateClient {
+                    client_id: UnionId::new(1).unwrap(),
+                    client_message: b"header".into(),
+                }),
+                GasMessageKind::UpdateClient,
+            ),
+            (
+                Datagram::from(MsgConnectionOpenInit {
+                    client_id: UnionId::new(1).unwrap(),
+                    counterparty_client_id: UnionId::new(2).unwrap(),
+                }),
+                GasMessageKind::ConnectionHandshake,
+            ),
+            (
+                Datagram::from(MsgConnectionOpenTry {
+                    client_id: UnionId::new(1).unwrap(),
+                    counterparty_client_id: UnionId::new(2).unwrap(),
+                    counterparty_connection_id: UnionId::new(3).unwrap(),
+                    proof_init: b"proof".into(),
+                    proof_height: 1,
+                }),
+                GasMessageKind::ConnectionHandshake,
+            ),
+            (
+                Datagram::from(MsgConnectionOpenAck {
+                    connection_id: UnionId::new(1).unwrap(),
+                    counterparty_connection_id: UnionId::new(2).unwrap(),
+                    proof_try: b"proof".into(),
+                    proof_height: 1,
+                }),
+                GasMessageKind::ConnectionHandshake,
+            ),
+            (
+                Datagram::from(MsgConnectionOpenConfirm {
+                    connection_id: UnionId::new(1).unwrap(),
+                    proof_ack: b"proof".into(),
+                    proof_height: 1,
+                }),
+                GasMessageKind::ConnectionHandshake,
+            ),
+            (
+                Datagram::from(MsgChannelOpenInit {
+                    port_id: b"port".into(),
+                    counterparty_port_id: b"port".into(),
+                    connection_id: UnionId::new(1).unwrap(),
+                    version: "ucs03-zkgm-0".to_owned(),
+                }),
+                GasMessageKind::ChannelHandshake,
+            ),
+            (
+                Datagram::from(MsgChannelOpenTry {
+                    port_id: b"port".into(),
+                    channel: Channel {
+                        state: ChannelState::TryOpen,
+                        connection_id: 1,
+                        counterparty_channel_id: 2,
+                        counterparty_port_id: b"port".to_vec().into(),
+                        version: "ucs03-zkgm-0".to_owned(),
+                    },
+                    counterparty_version: "ucs03-zkgm-0".to_owned(),
+                    proof_init: b"proof".into(),
+                    proof_height: 1,
+                }),
+                GasMessageKind::ChannelHandshake,
+            ),
+            (
+                Datagram::from(MsgChannelOpenAck {
+                    channel_id: UnionId::new(1).unwrap(),
+                    counterparty_version: "ucs03-zkgm-0".to_owned(),
+                    counterparty_channel_id: UnionId::new(2).unwrap(),
+                    proof_try: b"proof".into(),
+                    proof_height: 1,
+                }),
+                GasMessageKind::ChannelHandshake,
+            ),
+            (
+                Datagram::from(MsgChannelOpenConfirm {
+                    channel_id: UnionId::new(1).unwrap(),
+                    proof_ack: b"proof".into(),
+                    proof_height: 1,
+                }),
+                GasMessageKind::ChannelHandshake,
+            ),
+            (
+                Datagram::from(MsgChannelCloseInit {}),
+                GasMessageKind::ChannelHandshake,
+            ),
+            (
+                Datagram::from(MsgChannelCloseConfirm {}),
+                GasMessageKind::ChannelHandshake,
+            ),
+            (
+                Datagram::from(MsgPacketRecv {
+                    packets: vec![packet.clone()],
+                    relayer_msgs: vec![Default::default()],
+                    proof: b"proof".into(),
+                    proof_height: 1,
+                }),
+                GasMessageKind::PacketRecv,
+            ),
+            (
+                Datagram::from(MsgPacketAcknowledgement {
+                    packets: vec![packet.clone()],
+                    acknowledgements: vec![b"ack".into()],
+                    proof: b"proof".into(),
+                    proof_height: 1,
+                }),
+                GasMessageKind::PacketAck,
+            ),
+            (
+                Datagram::from(MsgPacketTimeout {
+                    packet,
+                    proof: b"proof".into(),
+                    proof_height: 1,
+                }),
+                GasMessageKind::PacketTimeout,
+            ),
+            (
+                Datagram::from(MsgIntentPacketRecv {}),
+                GasMessageKind::PacketRecv,
+            ),
+            (Datagram::from(MsgBatchSend {}), GasMessageKind::WasmExecute),
+            (Datagram::from(MsgBatchAcks {}), GasMessageKind::WasmExecute),
+        ]
+    }
+
+    #[test]
+    fn classify_every_message() {
+        let classic = classic_messages();
+        let union = union_messages();
+
+        // one case per variant
+        assert_eq!(classic.len(), 14);
+        assert_eq!(union.len(), 18);
+
+        for (msg, kind) in classic {
+            assert_eq!(IbcMessage::from(msg.clone()).gas_kind(), kind, "{msg:?}");
+        }
+
+        for (msg, kind) in union {
+            assert_eq!(IbcMessage::from(msg.clone()).gas_kind(), kind, "{msg:?}");
+        }
+    }
+
+    #[test]
+    fn confirmed_packets() {
+        let confirmed = classic_messages()
+            .into_iter()
+            .map(|(msg, _)| IbcMessage::from(msg))
+            .chain(union_messages().into_iter().map(|(msg, _)| msg.into()))
+            .filter_map(|msg| msg.confirmed_packet())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            confirmed,
+            vec![
+                ConfirmedPacket {
+                    channel: "channel-2".to_owned(),
+                    direction: RelayDirection::Recv,
+                    sequence: 1,
+                },
+                ConfirmedPacket {
+                    channel: "channel-1".to_owned(),
+                    direction: RelayDirection::Ack,
+                    sequence: 1,
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,314 @@
+//! Validation of the configured [`ibc_union_contract_address`](crate::Config::ibc_union_contract_address).
+//!
+//! All ibc-union messages are submitted as `MsgExecuteContract`s to this address. If it is
+//! misconfigured (i.e. a typo, or the address of the contract on another chain), every message
+//! fails at broadcast with a generic wasm error. The address is instead checked when the plugin is
+//! constructed, and (unless [`skip_contract_check`](crate::Config::skip_contract_check) is set)
+//! queried with `cosmwasm.wasm.v1.Query/ContractInfo` to confirm that a contract exists at it.
+
+use std::convert::Infallible;
+
+use chain_utils::{auth::GrpcAuth, BoxDynError};
+use protos::cosmwasm::wasm::v1::{query_client::QueryClient, QueryContractInfoRequest};
+use tracing::warn;
+use unionlabs::{
+    bech32::{Bech32, Bech32DecodeError},
+    bytes::Bytes,
+};
+
+/// The length of contract addresses, i.e. addresses derived with `instantiate2`.
+pub const CONTRACT_ADDRESS_LEN: usize = 32;
+
+/// The length of account addresses. Contracts instantiated on chains with a custom address
+/// generator may also have addresses of this length.
+pub const ACCOUNT_ADDRESS_LEN: usize = 20;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ContractAddressError {
+    #[error("`ibc_union_contract_address` must not be empty")]
+    Empty,
+    #[error("`ibc_union_contract_address` ({address}) is not a valid bech32 address")]
+    InvalidBech32 {
+        address: String,
+        #[source]
+        source: Bech32DecodeError<Infallible>,
+    },
+    #[error(
+        "`ibc_union_contract_address` ({address}) has the prefix `{found}`, but the bech32 \
+        prefix of the chain is `{expected}`"
+    )]
+    PrefixMismatch {
+        address: String,
+        found: String,
+        expected: String,
+    },
+    #[error(
+        "`ibc_union_contract_address` ({address}) is {len} bytes long, expected \
+        {CONTRACT_ADDRESS_LEN} (or {ACCOUNT_ADDRESS_LEN})"
+    )]
+    InvalidLength { address: String, len: usize },
+    #[error(
+        "no contract exists at `ibc_union_contract_address` ({address}) (set \
+        `skip_contract_check` to skip this check)"
+    )]
+    ContractNotFound { address: String },
+    #[error("error querying the contract at `ibc_union_contract_address` ({address})")]
+    Query {
+        address: String,
+        #[source]
+        source: BoxDynError,
+    },
+}
+
+/// Parse `address` as the bech32 address of a contract on a chain with the bech32 prefix
+/// `bech32_prefix`.
+pub fn parse_contract_address(
+    address: &str,
+    bech32_prefix: &str,
+) -> Result<Bech32<Bytes>, ContractAddressError> {
+    if address.trim().is_empty() {
+        return Err(ContractAddressError::Empty);
+    }
+
+    let parsed =
+        Bech32::<Bytes>::decode(address).map_err(|source| ContractAddressError::InvalidBech32 {
+            address: address.to_owned(),
+            source,
+        })?;
+
+    if parsed.hrp() != bech32_prefix {
+        return Err(ContractAddressError::PrefixMismatch {
+            address: address.to_owned(),
+            found: parsed.hrp().clone(),
+            expected: bech32_prefix.to_owned(),
+        });
+    }
+
+    match parsed.data().len() {
+        CONTRACT_ADDRESS_LEN => {}
+        ACCOUNT_ADDRESS_LEN => warn!(
+            %address,
+            "ibc_union_contract_address is {ACCOUNT_ADDRESS_LEN} bytes long, which is the \
+            length of an account address; contract addresses are usually \
+            {CONTRACT_ADDRESS_LEN} bytes long"
+        ),
+        len => {
+            return Err(ContractAddressError::InvalidLength {
+                address: address.to_owned(),
+                len,
+            })
+        }
+    }
+
+    Ok(parsed)
+}
+
+/// A source of the contracts deployed on a chain.
+#[allow(async_fn_in_trait)]
+pub trait ContractInfo {
+    async fn contract_exists(&self, address: &str) -> Result<bool, BoxDynError>;
+}
+
+/// Query contracts with `cosmwasm.wasm.v1.Query/ContractInfo`.
+#[derive(Debug, Clone)]
+pub struct GrpcContractInfo {
+    pub grpc_url: String,
+    pub grpc_auth: GrpcAuth,
+}
+
+impl ContractInfo for GrpcContractInfo {
+    async fn contract_exists(&self, address: &str) -> Result<bool, BoxDynError> {
+        let res = QueryClient::new(self.grpc_auth.connect(self.grpc_url.clone()).await?)
+            .contract_info(QueryContractInfoRequest {
+                address: address.to_owned(),
+            })
+            .await;
+
+        match res {
+            Ok(res) => Ok(res.into_inner().contract_info.is_some()),
+            // wasmd does not map its "no such contract" error to a grpc status code
+            Err(status)
+                if status.code() == tonic::Code::NotFound
+                    || status.message().contains("no such contract") =>
+            {
+                Ok(false)
+            }
+            Err(status) => Err(status.into()),
+        }
+    }
+}
+
+/// Parse and validate the configured contract address, and check that a contract exists at it
+/// unless `skip_contract_check` is set.
+pub async fn check_contract_address(
+    address: &str,
+    bech32_prefix: &str,
+    skip_contract_check: bool,
+    contract_info: &impl ContractInfo,
+) -> Result<Bech32<Bytes>, ContractAddressError> {
+    let parsed = parse_contract_address(address, bech32_prefix)?;
+
+    if skip_contract_check {
+        return Ok(parsed);
+    }
+
+    let exists = contract_info
+        .contract_exists(address)
+        .await
+        .map_err(|source| ContractAddressError::Query {
+            address: address.to_owned(),
+            source,
+        })?;
+
+    if exists {
+        Ok(parsed)
+    } else {
+        Err(ContractAddressError::ContractNotFound {
+            address: address.to_owned(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    const CONTRACT: &str = "union14hj2tavq8fpesdwxxcu44rty3hh90vhujrvcmstl4zr3txmfvw9s3e9fe2";
+
+    /// The contracts in the set exist.
+    struct MockContractInfo(HashSet<String>);
+
+    impl MockContractInfo {
+        fn new(contracts: impl IntoIterator<Item = &'static str>) -> Self {
+            Self(contracts.into_iter().map(ToOwned::to_owned).collect())
+        }
+    }
+
+    impl ContractInfo for MockContractInfo {
+        async fn contract_exists(&self, address: &str) -> Result<bool, BoxDynError> {
+            Ok(self.0.contains(address))
+        }
+    }
+
+    /// Panics if queried.
+    struct Unreachable;
+
+    impl ContractInfo for Unreachable {
+        async fn contract_exists(&self, _: &str) -> Result<bool, BoxDynError> {
+            panic!("contract info must not be queried")
+        }
+    }
+
+    #[test]
+    fn valid() {
+        let parsed = parse_contract_address(CONTRACT, "union").unwrap();
+
+        assert_eq!(parsed.data().len(), CONTRACT_ADDRESS_LEN);
+        assert_eq!(parsed.to_string(), CONTRACT);
+
+        let account = Bech32::new("union".to_owned(), Bytes::from([1; ACCOUNT_ADDRESS_LEN]));
+
+        assert_eq!(
+            parse_contract_address(&account.to_string(), "union").unwrap(),
+            account
+        );
+    }
+
+    #[test]
+    fn empty() {
+        for address in ["", " "] {
+            let err = parse_contract_address(address, "union").unwrap_err();
+
+            assert!(matches!(err, ContractAddressError::Empty), "{err:?}");
+            assert!(err.to_string().contains("ibc_union_contract_address"));
+        }
+    }
+
+    #[test]
+    fn malformed_bech32() {
+        // last character changed, invalid checksum
+        let address = "union14hj2tavq8fpesdwxxcu44rty3hh90vhujrvcmstl4zr3txmfvw9s3e9fe3";
+
+        let err = parse_contract_address(address, "union").unwrap_err();
+
+        assert!(
+            matches!(err, ContractAddressError::InvalidBech32 { .. }),
+            "{err:?}"
+        );
+        assert!(err.to_string().contains("ibc_union_contract_address"));
+
+        assert!(matches!(
+            parse_contract_address("not an address", "union").unwrap_err(),
+            ContractAddressError::InvalidBech32 { .. }
+        ));
+    }
+
+    #[test]
+    fn prefix_mismatch() {
+        let address = Bech32::new("osmo".to_owned(), <Bytes>::from([2; CONTRACT_ADDRESS_LEN]));
+
+        let err = parse_contract_address(&address.to_string(), "union").unwrap_err();
+
+        assert!(
+            matches!(
+                &err,
+                ContractAddressError::PrefixMismatch { found, expected, .. }
+                    if found == "osmo" && expected == "union"
+            ),
+            "{err:?}"
+        );
+        assert!(err.to_string().contains("ibc_union_contract_address"));
+    }
+
+    #[test]
+    fn invalid_length() {
+        let address = Bech32::new("union".to_owned(), <Bytes>::from([2; 31]));
+
+        assert!(matches!(
+            parse_contract_address(&address.to_string(), "union").unwrap_err(),
+            ContractAddressError::InvalidLength { len: 31, .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn contract_check() {
+        assert_eq!(
+            check_contract_address(CONTRACT, "union", false, &MockContractInfo::new([CONTRACT]))
+                .await
+                .unwrap()
+                .to_string(),
+            CONTRACT
+        );
+
+        let err = check_contract_address(CONTRACT, "union", false, &MockContractInfo::new([]))
+            .await
+            .unwrap_err();
+
+        assert!(
+            matches!(err, ContractAddressError::ContractNotFound { .. }),
+            "{err:?}"
+        );
+        assert!(err.to_string().contains("ibc_union_contract_address"));
+    }
+
+    #[tokio::test]
+    async fn skip_contract_check() {
+        assert_eq!(
+            check_contract_address(CONTRACT, "union", true, &Unreachable)
+                .await
+                .unwrap()
+                .to_string(),
+            CONTRACT
+        );
+
+        // the address itself is still validated
+        assert!(matches!(
+            check_contract_address(CONTRACT, "osmo", true, &Unreachable)
+                .await
+                .unwrap_err(),
+            ContractAddressError::PrefixMismatch { .. }
+        ));
+    }
+}
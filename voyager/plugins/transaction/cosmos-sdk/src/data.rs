@@ -1,6 +1,36 @@
 use enumorph::Enumorph;
 use macros::model;
+use voyager_message::{
+    balance_monitor::SignerBalanceLow, core::ChainId, data::IbcDatagram,
+    suppression::SuppressedDatagram,
+};
+
+use crate::call::IbcMessage;
 
 #[model]
 #[derive(Enumorph)]
-pub enum ModuleData {}
+pub enum ModuleData {
+    UndecodableDatagram(UndecodableDatagram),
+    SuppressedDatagram(SuppressedDatagram),
+    UnencodableMessage(UnencodableMessage),
+    SignerBalanceLow(SignerBalanceLow),
+}
+
+/// A datagram for this chain that could not be decoded. It is dropped from the
+/// transaction it was to be submitted in, and emitted as-is for inspection.
+#[model]
+pub struct UndecodableDatagram {
+    pub chain_id: ChainId,
+    pub datagram: IbcDatagram,
+    pub error: String,
+}
+
+/// A message that could not be encoded for submission (i.e. a client state that is not encoded
+/// as an `Any`). It is dropped from the transaction it was to be submitted in, and emitted as-is
+/// for inspection.
+#[model]
+pub struct UnencodableMessage {
+    pub chain_id: ChainId,
+    pub message: IbcMessage,
+    pub error: String,
+}
@@ -1,57 +1,105 @@
-use std::collections::VecDeque;
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, RwLock,
+    },
+};
 
 use chain_utils::{
+    address::RelayerAddress,
+    auth::{self, AuthorizationHeader, EndpointAuth, GrpcAuth},
     cosmos_sdk::{
         cosmos_sdk_error::{ChannelError, ClientError, CosmosSdkError, IbcWasmError, SdkError},
         CosmosKeyring, GasConfig,
     },
+    endpoint::{GrpcUrl, WsUrl, DEFAULT_PROBE_TIMEOUT},
     keyring::{KeyringConfig, KeyringEntry},
+    net::NetworkConfig,
+    relay_progress::{RelayProgress, RelayProgressConfig, RelayProgressTracker},
+    spend::{Admission, SpendConfig, SpendTracker},
+    timeout::{CallKind, TimedOut, TimeoutConfig},
     BoxDynError,
 };
+use ibc_classic_spec::IbcClassic;
+use ibc_union_spec::IbcUnion;
 use jsonrpsee::{
     core::{async_trait, RpcResult},
-    types::ErrorObject,
+    types::{error::INVALID_PARAMS_CODE, ErrorObject, ErrorObjectOwned},
     Extensions,
 };
 use prost::Message;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use sha2::Digest;
 use tracing::{debug, error, info, instrument, warn};
 use unionlabs::{
     self,
     bech32::Bech32,
     bounded::BoundedI64,
+    bytes::Bytes,
     cosmos::{
         auth::base_account::BaseAccount,
         base::abci::gas_info::GasInfo,
         crypto::{secp256k1, AnyPubKey},
         tx::{
-            auth_info::AuthInfo, mode_info::ModeInfo, sign_doc::SignDoc, signer_info::SignerInfo,
-            signing::sign_info::SignMode, tx::Tx, tx_body::TxBody, tx_raw::TxRaw,
+            auth_info::AuthInfo, fee::Fee, mode_info::ModeInfo, sign_doc::SignDoc,
+            signer_info::SignerInfo, signing::sign_info::SignMode, tx::Tx, tx_body::TxBody,
+            tx_raw::TxRaw,
         },
     },
     encoding::{EncodeAs, Proto},
     google::protobuf::any::{mk_any, Any},
     hash::H256,
+    id::{ChannelId, PortId},
     signer::CosmosSigner,
     ErrorReporter,
 };
 use voyager_message::{
-    core::ChainId,
-    data::{Data, WithChainId},
-    module::{PluginInfo, PluginServer},
-    DefaultCmd, Plugin, PluginMessage, VoyagerMessage, FATAL_JSONRPC_ERROR_CODE,
+    balance_monitor::{BalanceMonitor, BalanceMonitorConfig, CheckBalances},
+    cmd::CmdOutput,
+    core::{ChainId, IbcSpec},
+    data::{Data, IbcDatagram, WithChainId},
+    error::VoyagerError,
+    module::{
+        ensure_chain_id, PluginInfo, PluginKind, PluginServer, ReloadReport, TxEstimate,
+        UnexpectedChainIdError,
+    },
+    proof_verify::{partition_verified, rejected_error, VerifyError},
+    revision::{
+        check_counterparty_revision, check_node_revision, ibc_go_revision, RevisionCheckConfig,
+    },
+    suppression::{SuppressedDatagram, SuppressionList},
+    ExtensionsExt, Plugin, PluginMessage, VoyagerClient, VoyagerMessage,
+};
+use voyager_vm::{
+    call, conc, data, defer, noop, now,
+    pass::{Claim, PassResult},
+    seq, Op,
 };
-use voyager_vm::{call, conc, noop, pass::PassResult, Op};
 
 use crate::{
+    broadcast::{with_fallback, Broadcast},
     call::{IbcMessage, ModuleCall},
     callback::ModuleCallback,
+    contract_address::{check_contract_address, GrpcContractInfo},
+    data::{ModuleData, UndecodableDatagram, UnencodableMessage},
+    ordering::OrderedPackets,
+    store_code::{
+        check_client_type, ProposalOutput, StoreCodeError, StoreCodeProposal,
+        StoreCodeProposalArgs, StoreCodeProposalOutput,
+    },
+    tx_size::{default_max_tx_bytes, message_budget, split_by_size, SizeSplit, TxTooLargeError},
 };
 
+pub mod broadcast;
 pub mod call;
 pub mod callback;
+pub mod contract_address;
 pub mod data;
+pub mod ordering;
+pub mod store_code;
+pub mod tx_size;
 
 #[tokio::main(flavor = "multi_thread")]
 async fn main() {
@@ -61,22 +109,194 @@ async fn main() {
 #[derive(Debug, Clone)]
 pub struct Module {
     pub chain_id: ChainId,
-    pub ibc_union_contract_address: Bech32<H256>,
+    pub ibc_union_contract_address: Bech32<Bytes>,
     pub keyring: CosmosKeyring,
     pub tm_client: cometbft_rpc::Client,
+    /// The endpoints that transactions are broadcast to, the first of which is always [`Self::tm_client`].
+    pub broadcast_endpoints: Vec<(String, cometbft_rpc::Client)>,
     pub grpc_url: String,
-    pub gas_config: GasConfig,
+    pub grpc_auth: GrpcAuth,
+    pub timeouts: TimeoutConfig,
+    /// The config this plugin is running with, including any changes applied with
+    /// [`PluginServer::reload`].
+    pub config: LiveConfig,
     pub bech32_prefix: String,
+    /// The amount of ops that were passed through [`run_pass`] unchanged since this plugin was started.
+    pub pass_through_count: Arc<AtomicU64>,
+    pub spend: SpendTracker,
+    pub relay_progress: RelayProgressTracker,
+    /// See [`Config::verify_proofs_before_submit`].
+    pub verify_proofs_before_submit: bool,
+    pub balance_monitor: Option<BalanceMonitor>,
+    /// The maximum size of a transaction submitted by this plugin, see [`tx_size`].
+    pub max_tx_bytes: u64,
+    /// The IBC revision number of this chain, see [`Config::revision_number_override`].
+    pub revision: u64,
+    /// See [`Config::revision_check`].
+    pub revision_check: Option<RevisionCheckConfig>,
+    /// Set once the revision has been checked against [`Self::revision_check`].
+    pub revision_checked: Arc<AtomicBool>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
     pub chain_id: ChainId,
-    pub ibc_union_contract_address: Bech32<H256>,
+    /// The bech32 address of the ibc-union contract on this chain.
+    pub ibc_union_contract_address: String,
+    /// Don't check that a contract exists at `ibc_union_contract_address` on startup.
+    #[serde(default)]
+    pub skip_contract_check: bool,
     pub keyring: KeyringConfig,
-    pub ws_url: String,
-    pub grpc_url: String,
+    pub ws_url: WsUrl,
+    /// Additional rpc endpoints to broadcast transactions to, alongside `ws_url`. If any are set,
+    /// transactions are broadcast to all endpoints concurrently, and `ws_url` falls back to these
+    /// endpoints when waiting for inclusion if it stops responding.
+    #[serde(default)]
+    pub broadcast_endpoints: Vec<String>,
+    pub grpc_url: GrpcUrl,
+    /// Authentication for `ws_url`, if the rpc provider requires it. This is not used for
+    /// `broadcast_endpoints`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ws_auth: Option<EndpointAuth>,
+    /// Authentication for `grpc_url`, if the grpc provider requires it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub grpc_auth: Option<EndpointAuth>,
+    /// Don't check that `ws_url` and `grpc_url` are reachable on startup.
+    #[serde(default)]
+    pub skip_startup_probe: bool,
+    /// How connections to `ws_url`, `broadcast_endpoints` and `grpc_url` are opened, i.e. through a
+    /// proxy. Only websocket `broadcast_endpoints` can be used with a proxy.
+    #[serde(default)]
+    pub network: NetworkConfig,
+    /// Timeouts of the calls to `ws_url`, `broadcast_endpoints` and `grpc_url`.
+    #[serde(default)]
+    pub timeouts: TimeoutConfig,
     pub gas_config: GasConfig,
+    /// The memo to attach to all transactions. `{version}` is replaced with the version of this
+    /// plugin.
+    #[serde(default = "default_memo")]
+    pub memo: String,
+    /// The maximum size of a transaction, in bytes. Batches that exceed this are split into
+    /// multiple transactions. Defaults to the smaller of the default CometBFT mempool
+    /// `max_tx_bytes` (1 MiB) and the chain's `block.max_bytes` consensus param, which should be
+    /// overridden if the mempool of `ws_url` is configured with a different limit.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tx_bytes: Option<u64>,
+    #[serde(default)]
+    pub spend: SpendConfig,
+    #[serde(default)]
+    pub relay_progress: RelayProgressConfig,
+    /// The address of the governance module account, used as the authority of governance
+    /// proposals generated by this plugin.
+    #[serde(default)]
+    pub gov_authority: Option<String>,
+    /// Channels on this chain to drop datagrams for instead of submitting them, i.e. channels
+    /// whose counterparty has been abandoned.
+    #[serde(default)]
+    pub suppression: SuppressionList,
+    /// Channels on this chain whose packets must be delivered in order. Packet datagrams for these
+    /// channels are submitted one transaction at a time, in order of their sequence.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ordered_channels: Vec<(PortId, ChannelId)>,
+    /// Verify the proofs of ibc-union packet datagrams against the consensus state of the client
+    /// on this chain before submitting them, failing the datagrams whose proofs don't verify
+    /// instead of submitting them. See [`voyager_message::proof_verify`].
+    #[serde(default)]
+    pub verify_proofs_before_submit: bool,
+    /// Periodically check the spendable balance of the gas denom of every key in the keyring,
+    /// alerting on (and optionally pausing) keys that are running low. See
+    /// [`voyager_message::balance_monitor`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub balance_monitor: Option<BalanceMonitorConfig>,
+    /// The IBC revision number of this chain, instead of the revision parsed from the chain id.
+    /// Only set this if the trailing number of the chain id is not the revision number that the
+    /// clients tracking this chain expect. This should match the override of the event source
+    /// plugin for this chain.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revision_number_override: Option<u64>,
+    /// A client tracking this chain on a counterparty, whose latest height the revision of this
+    /// chain is checked against before submitting the first transaction. If not set, the revision
+    /// is checked against the chain id reported by the node instead.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revision_check: Option<RevisionCheckConfig>,
+}
+
+fn default_memo() -> String {
+    "Voyager {version}".to_owned()
+}
+
+/// A [`Config`] that can be updated while the plugin is running.
+///
+/// Only the fields in [`LiveConfig::RELOADABLE`] are updated on reload; all other fields are
+/// captured when the plugin is constructed and as such require a restart to change.
+#[derive(Debug, Clone)]
+pub struct LiveConfig(Arc<RwLock<Config>>);
+
+impl LiveConfig {
+    pub const RELOADABLE: &'static [&'static str] =
+        &["gas_config", "memo", "suppression", "ordered_channels"];
+
+    pub fn new(config: Config) -> Self {
+        Self(Arc::new(RwLock::new(config)))
+    }
+
+    pub fn gas_config(&self) -> GasConfig {
+        self.0
+            .read()
+            .expect("lock is not poisoned")
+            .gas_config
+            .clone()
+    }
+
+    pub fn memo(&self) -> String {
+        self.0
+            .read()
+            .expect("lock is not poisoned")
+            .memo
+            .replace("{version}", env!("CARGO_PKG_VERSION"))
+    }
+
+    pub fn suppression(&self) -> SuppressionList {
+        self.0
+            .read()
+            .expect("lock is not poisoned")
+            .suppression
+            .clone()
+    }
+
+    pub fn ordered_channels(&self) -> Vec<(PortId, ChannelId)> {
+        self.0
+            .read()
+            .expect("lock is not poisoned")
+            .ordered_channels
+            .clone()
+    }
+
+    /// Apply the reloadable fields of `new_config`, and report any other changed fields as
+    /// rejected.
+    pub fn reload(&self, new_config: Config) -> ReloadReport {
+        let mut config = self.0.write().expect("lock is not poisoned");
+
+        let report = ReloadReport::diff(&*config, &new_config, Self::RELOADABLE);
+
+        config.gas_config = new_config.gas_config;
+        config.memo = new_config.memo;
+        config.suppression = new_config.suppression;
+        config.ordered_channels = new_config.ordered_channels;
+
+        report
+    }
+}
+
+#[derive(clap::Subcommand)]
+pub enum Cmd {
+    /// Print the fees spent by this chain's transactions, per day.
+    Spend,
+    /// Print the highest packet sequences relayed on each channel of this chain.
+    RelayProgress,
+    /// Generate a governance proposal to upload new 08-wasm light client code, for chains where
+    /// code uploads are gated behind governance.
+    GenerateStoreCodeProposal(StoreCodeProposalArgs),
 }
 
 impl Plugin for Module {
@@ -84,49 +304,143 @@ impl Plugin for Module {
     type Callback = ModuleCallback;
 
     type Config = Config;
-    type Cmd = DefaultCmd;
+    type Cmd = Cmd;
 
     async fn new(config: Self::Config) -> Result<Self, BoxDynError> {
-        let tm_client = cometbft_rpc::Client::new(config.ws_url).await?;
+        config.gas_config.validate()?;
+
+        let live_config = LiveConfig::new(config.clone());
+
+        if !config.skip_startup_probe {
+            config
+                .ws_url
+                .probe_via(&config.network, DEFAULT_PROBE_TIMEOUT)
+                .await?;
+            config
+                .grpc_url
+                .probe_via(&config.network, DEFAULT_PROBE_TIMEOUT)
+                .await?;
+        }
 
-        let chain_id = tm_client.status().await?.node_info.network.to_string();
+        let ws_auth = auth::resolve(config.ws_auth.as_ref())?;
+        let grpc_auth = GrpcAuth::new(auth::resolve(config.grpc_auth.as_ref())?.as_ref())
+            .with_network(config.network.clone());
 
-        let bech32_prefix = protos::cosmos::auth::v1beta1::query_client::QueryClient::connect(
-            config.grpc_url.clone(),
+        let tm_client = cometbft_rpc::Client::new_with_connector(
+            &config.ws_url,
+            AuthorizationHeader::jsonrpsee_headers(ws_auth.as_ref()),
+            config.network.cometbft_connector(),
+        )
+        .await?;
+
+        let network = tm_client.status().await?.node_info.network;
+
+        ensure_network(&config.chain_id, config.ws_url.as_str(), &network)?;
+
+        let revision = chain_revision(&network, config.revision_number_override);
+
+        if config.revision_check.is_none() {
+            // a mismatch is logged, and is not fatal
+            let _ = check_node_revision(&config.chain_id, &network, revision);
+        }
+
+        let max_tx_bytes = match config.max_tx_bytes {
+            Some(max_tx_bytes) => max_tx_bytes,
+            None => default_max_tx_bytes(
+                tm_client
+                    .consensus_params(None)
+                    .await?
+                    .consensus_params
+                    .block
+                    .max_bytes,
+            ),
+        };
+
+        info!(%max_tx_bytes, "using maximum transaction size");
+
+        let mut broadcast_endpoints = vec![(config.ws_url.into(), tm_client.clone())];
+        for url in config.broadcast_endpoints {
+            let client = cometbft_rpc::Client::new_with_connector(
+                &url,
+                cometbft_rpc::HeaderMap::new(),
+                config.network.cometbft_connector(),
+            )
+            .await?;
+
+            ensure_network(
+                &config.chain_id,
+                &url,
+                &client.status().await?.node_info.network,
+            )?;
+
+            broadcast_endpoints.push((url, client));
+        }
+
+        let bech32_prefix = protos::cosmos::auth::v1beta1::query_client::QueryClient::new(
+            grpc_auth.connect(config.grpc_url.clone()).await?,
         )
-        .await
-        .unwrap()
         .bech32_prefix(protos::cosmos::auth::v1beta1::Bech32PrefixRequest {})
         .await
         .unwrap()
         .into_inner()
         .bech32_prefix;
 
+        let ibc_union_contract_address = check_contract_address(
+            &config.ibc_union_contract_address,
+            &bech32_prefix,
+            config.skip_contract_check,
+            &GrpcContractInfo {
+                grpc_url: config.grpc_url.clone().into(),
+                grpc_auth: grpc_auth.clone(),
+            },
+        )
+        .await?;
+
+        let keys = config.keyring.resolve()?;
+
         Ok(Self {
-            ibc_union_contract_address: config.ibc_union_contract_address,
+            ibc_union_contract_address,
             keyring: CosmosKeyring::new(
                 config.keyring.name,
-                config.keyring.keys.into_iter().map(|entry| {
+                // the resolved keys are zeroized as soon as the signer is constructed
+                keys.into_iter().map(|entry| {
                     let signer = CosmosSigner::new(
                         bip32::secp256k1::ecdsa::SigningKey::from_bytes(
-                            entry.value().as_slice().into(),
+                            entry.key.as_slice().into(),
                         )
                         .expect("invalid private key"),
                         bech32_prefix.clone(),
                     );
 
                     KeyringEntry {
-                        name: entry.name(),
+                        name: entry.name,
                         address: signer.to_string(),
                         signer,
                     }
                 }),
             ),
             tm_client,
-            chain_id: ChainId::new(chain_id),
-            grpc_url: config.grpc_url,
-            gas_config: config.gas_config,
+            broadcast_endpoints,
+            chain_id: config.chain_id.clone(),
+            grpc_url: config.grpc_url.into(),
+            grpc_auth,
+            timeouts: config.timeouts,
+            config: live_config,
             bech32_prefix,
+            pass_through_count: Arc::new(AtomicU64::new(0)),
+            spend: SpendTracker::new(config.chain_id.to_string(), config.spend)?,
+            relay_progress: RelayProgressTracker::new(
+                config.chain_id.to_string(),
+                config.relay_progress,
+            )?,
+            verify_proofs_before_submit: config.verify_proofs_before_submit,
+            balance_monitor: config
+                .balance_monitor
+                .map(|balance_monitor| BalanceMonitor::new(config.chain_id, balance_monitor)),
+            max_tx_bytes,
+            revision,
+            revision_check: config.revision_check,
+            revision_checked: Arc::default(),
         })
     }
 
@@ -147,14 +461,123 @@ end
 "#,
                 chain_id = config.chain_id,
             ),
+            kind: Some(PluginKind::Transaction),
+            chains: vec![config.chain_id.clone()],
+            ibc_specs: vec![IbcClassic::ID, IbcUnion::ID],
         }
     }
 
-    async fn cmd(_config: Self::Config, cmd: Self::Cmd) {
-        match cmd {}
+    async fn cmd(config: Self::Config, cmd: Self::Cmd) -> Result<Box<dyn CmdOutput>, BoxDynError> {
+        match cmd {
+            Cmd::Spend => Ok(Box::new(
+                SpendTracker::new(config.chain_id.to_string(), config.spend)?.report(),
+            )),
+            Cmd::RelayProgress => Ok(Box::new(
+                RelayProgressTracker::new(config.chain_id.to_string(), config.relay_progress)?
+                    .report(),
+            )),
+            Cmd::GenerateStoreCodeProposal(args) => {
+                Ok(Box::new(generate_store_code_proposal(config, args).await?))
+            }
+        }
     }
 }
 
+async fn generate_store_code_proposal(
+    config: Config,
+    args: StoreCodeProposalArgs,
+) -> Result<StoreCodeProposalOutput, BoxDynError> {
+    let authority = config
+        .gov_authority
+        .clone()
+        .ok_or(StoreCodeError::MissingAuthority)?;
+
+    let code = std::fs::read(&args.wasm_path).map_err(|source| StoreCodeError::Read {
+        path: args.wasm_path.clone(),
+        source,
+    })?;
+
+    let client_type = check_client_type(&code, args.force)?;
+
+    let module = Module::new(config).await?;
+
+    let proposer = module
+        .keyring
+        .keys()
+        .next()
+        .map(|(_, address)| address.clone())
+        .ok_or("keyring is empty")?;
+
+    let proposal = StoreCodeProposal {
+        authority,
+        proposer,
+        code,
+        title: args.title,
+        summary: args.summary,
+        deposit: args.deposit,
+    };
+
+    let proposal_output = if args.submit {
+        let (tx_hash, gas_used) = module
+            .keyring
+            .with(|signer| {
+                // the proposer must be the signer of the transaction
+                let msg = StoreCodeProposal {
+                    proposer: signer.to_string(),
+                    ..proposal.clone()
+                }
+                .msg();
+
+                module.broadcast_tx_commit(
+                    signer,
+                    [mk_any(&msg)],
+                    module.config.memo(),
+                    module.config.gas_config().gas_multiplier,
+                )
+            })
+            .await
+            .ok_or("no signers available")??;
+
+        ProposalOutput::Submitted { tx_hash, gas_used }
+    } else {
+        ProposalOutput::Unsigned {
+            tx: proposal.unsigned_tx_json(&module.config.memo()),
+        }
+    };
+
+    Ok(StoreCodeProposalOutput {
+        checksum: proposal.checksum(),
+        client_type,
+        proposal: proposal_output,
+    })
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("endpoint `{url}` is not for the configured chain")]
+pub struct EndpointChainIdError {
+    pub url: String,
+    #[source]
+    pub source: UnexpectedChainIdError,
+}
+
+/// Check that the node at `url` is for the configured chain, since transactions signed for the
+/// configured chain id would otherwise be rejected by it.
+fn ensure_network(
+    chain_id: &ChainId,
+    url: &str,
+    network: &str,
+) -> Result<(), EndpointChainIdError> {
+    ensure_chain_id(chain_id, network).map_err(|source| EndpointChainIdError {
+        url: url.to_owned(),
+        source,
+    })
+}
+
+/// The revision number of the chain with the chain id `network`, unless it is overridden.
+fn chain_revision(network: &str, revision_number_override: Option<u64>) -> u64 {
+    revision_number_override.unwrap_or_else(|| ibc_go_revision(network))
+}
+
 fn plugin_name(chain_id: &ChainId) -> String {
     pub const PLUGIN_NAME: &str = env!("CARGO_PKG_NAME");
 
@@ -162,6 +585,29 @@ fn plugin_name(chain_id: &ChainId) -> String {
 }
 
 impl Module {
+    /// Fail the datagrams that were rejected by [`partition_verified`]. The `verified` datagrams
+    /// are submitted separately, such that they aren't failed along with the rejected ones.
+    fn fail_rejected(
+        &self,
+        verified: Vec<IbcMessage>,
+        rejected: Vec<(IbcMessage, VerifyError)>,
+    ) -> RpcResult<Op<VoyagerMessage>> {
+        if verified.is_empty() {
+            return Err(rejected_error(&rejected).into());
+        }
+
+        Ok(conc(
+            [verified, rejected.into_iter().map(|(msg, _)| msg).collect()]
+                .into_iter()
+                .map(|msgs| {
+                    call(PluginMessage::new(
+                        self.plugin_name(),
+                        ModuleCall::SubmitTransaction(msgs),
+                    ))
+                }),
+        ))
+    }
+
     fn plugin_name(&self) -> String {
         plugin_name(&self.chain_id)
     }
@@ -175,13 +621,75 @@ impl Module {
             .with(|signer| {
                 let msgs = msgs.clone();
 
-                dbg!(&msgs);
+                debug!(?msgs, "submitting messages");
 
                 async move {
-                    // TODO: Figure out a way to thread this value through
-                    let memo = format!("Voyager {}", env!("CARGO_PKG_VERSION"));
+                    let memo = self.config.memo();
+
+                    let gas_multiplier = self
+                        .config
+                        .gas_config()
+                        .multiplier_for(msgs.iter().map(IbcMessage::gas_kind));
+
+                    let (msgs, mut failed) = split_processed(
+                        &self.chain_id,
+                        process_msgs(msgs, signer, self.ibc_union_contract_address.clone()),
+                    );
+
+                    if msgs.is_empty() {
+                        info!("no messages remaining to submit after dropping unencodable messages");
+                        return Ok(failed);
+                    }
+
+                    let SizeSplit {
+                        submit: msgs,
+                        remaining,
+                        too_large,
+                    } = split_by_size(msgs, message_budget(self.max_tx_bytes, &memo));
+
+                    if !too_large.is_empty() {
+                        for (msg, err) in &too_large {
+                            error!(
+                                error = %ErrorReporter(err),
+                                msg = msg.name(),
+                                "message exceeds the maximum transaction size"
+                            );
+                        }
+
+                        // the first message that isn't too large always fits, so this is only the
+                        // case if all messages are too large
+                        if msgs.is_empty() {
+                            return Err(BroadcastTxCommitError::TxTooLarge(
+                                too_large.into_iter().next().expect("not empty; qed").1,
+                            ));
+                        }
+
+                        // submitted on their own such that they fail without affecting the rest
+                        // of the batch
+                        failed.push(call(PluginMessage::new(
+                            self.plugin_name(),
+                            ModuleCall::SubmitTransaction(
+                                too_large.into_iter().map(|(msg, _)| msg).collect(),
+                            ),
+                        )));
+                    }
+
+                    if !remaining.is_empty() {
+                        info!(
+                            batch.size = msgs.len(),
+                            remaining = remaining.len(),
+                            max_tx_bytes = self.max_tx_bytes,
+                            "batch exceeds the maximum transaction size, submitting the remaining \
+                            messages in a subsequent transaction"
+                        );
 
-                    let msgs = process_msgs(msgs, signer, self.ibc_union_contract_address.clone());
+                        // this is only returned (and as such only submitted) once this transaction
+                        // has been included, since the remaining messages may depend on it
+                        failed.push(call(PluginMessage::new(
+                            self.plugin_name(),
+                            ModuleCall::SubmitTransaction(remaining),
+                        )));
+                    }
 
                     // let simulation_results = stream::iter(msgs.clone().into_iter().enumerate())
                     //     .then(move |(idx, (effect, msg))| async move {
@@ -248,7 +756,8 @@ impl Module {
                     match self.broadcast_tx_commit(
                         signer,
                         msgs.iter().map(move |x| x.1.clone()).collect::<Vec<_>>(),
-                        memo
+                        memo,
+                        gas_multiplier,
                     ).await {
                         Ok((tx_hash, gas_used)) => {
                             info!(
@@ -262,14 +771,17 @@ impl Module {
                                 info!(%tx_hash, %msg, "cosmos tx");
                             }
 
-                            Ok(())
+                            // the messages of a cosmos tx either all succeed or all fail
+                            self.record_relay_progress(msgs.iter().map(|(msg, _)| msg));
+
+                            Ok(failed)
                         }
                         Err(err) => match err {
                             BroadcastTxCommitError::Tx(CosmosSdkError::ChannelError(
                                 ChannelError::ErrRedundantTx,
                             )) => {
                                 info!("packet messages are redundant");
-                                Ok(())
+                                Ok(failed)
                             }
                             // BroadcastTxCommitError::Tx(CosmosSdkError::SdkError(
                             //     SdkError::ErrOutOfGas
@@ -313,7 +825,7 @@ impl Module {
 
                 Ok(call(rewrap_msg()))
             }
-            Some(res) => res.map(|()| noop()),
+            Some(res) => res.map(conc),
             // None => Ok(seq([defer_relative(1), effect(WithChainId{chain_id: self.chain_id.clone(), message: msg})])),
             None => Ok(call(rewrap_msg())),
         }
@@ -323,19 +835,27 @@ impl Module {
     /// - submit tx
     /// - wait for inclusion
     /// - return (tx_hash, gas_used)
+    ///
+    /// The simulated gas is multiplied by `gas_multiplier`, see
+    /// [`GasConfig::multiplier_for`].
     pub async fn broadcast_tx_commit(
         &self,
         signer: &CosmosSigner,
         messages: impl IntoIterator<Item = protos::google::protobuf::Any> + Clone,
         memo: String,
+        gas_multiplier: f64,
     ) -> Result<(H256, BoundedI64<0, { i64::MAX }>), BroadcastTxCommitError> {
-        let account = self.account_info(&signer.to_string()).await;
+        let account = self.account_info(&signer.to_string()).await?;
 
         let (tx_body, mut auth_info, simulation_gas_info) =
-            match self.simulate_tx(signer, messages, memo).await {
+            match self.simulate_tx(signer, &account, messages, memo).await {
                 Ok((tx_body, auth_info, simulation_gas_info)) => {
                     (tx_body, auth_info, simulation_gas_info)
                 }
+                // the simulation never completed, so nothing is known about the tx
+                Err((_, _, err)) if err.code() == tonic::Code::DeadlineExceeded => {
+                    return Err(BroadcastTxCommitError::SimulateTx(err));
+                }
                 Err((tx_body, auth_info, _err)) => (
                     tx_body,
                     auth_info,
@@ -353,13 +873,16 @@ impl Module {
             "tx simulation successful"
         );
 
-        auth_info.fee = self.gas_config.mk_fee(simulation_gas_info.gas_used);
+        let gas_config = self.config.gas_config();
+
+        auth_info.fee =
+            gas_config.mk_fee_with_multiplier(simulation_gas_info.gas_used, gas_multiplier);
 
         // dbg!(&auth_info.fee);
 
         info!(
             fee = %auth_info.fee.amount[0].amount,
-            gas_multiplier = %self.gas_config.gas_multiplier,
+            %gas_multiplier,
             "submitting transaction with gas"
         );
 
@@ -389,27 +912,31 @@ impl Module {
             .finalize()
             .into();
 
-        if let Ok(tx) = self.tm_client.tx(tx_hash, false).await {
+        if let Ok(Ok(tx)) = self
+            .timeouts
+            .with_timeout(CallKind::Query, self.tm_client.tx(tx_hash, false))
+            .await
+        {
             debug!(%tx_hash, "tx already included");
             return Ok((tx_hash, tx.tx_result.gas_used));
         }
 
-        let response = self
-            .tm_client
-            .broadcast_tx_sync(&tx_raw_bytes)
-            .await
-            .map_err(BroadcastTxCommitError::BroadcastTxSync)
-            .unwrap();
-
-        assert_eq!(tx_hash, response.hash, "tx hash calculated incorrectly");
+        let broadcast = broadcast::broadcast(
+            &self.broadcast_endpoints,
+            &tx_raw_bytes,
+            tx_hash,
+            &self.timeouts,
+        )
+        .await
+        .map_err(BroadcastTxCommitError::BroadcastTxSync)?;
 
         info!(
-            check_tx_code = %response.code,
-            codespace = %response.codespace,
-            check_tx_log = %response.log
+            ?broadcast,
+            endpoints = self.broadcast_endpoints.len(),
+            "broadcast tx"
         );
 
-        if response.code > 0 {
+        if let Broadcast::Rejected(response) = broadcast {
             let error = CosmosSdkError::from_code_and_codespace(&response.codespace, response.code);
 
             error!(%error, "cosmos tx failed");
@@ -417,9 +944,10 @@ impl Module {
             return Err(BroadcastTxCommitError::Tx(error));
         };
 
-        let mut target_height = self
-            .tm_client
-            .block(None)
+        let mut target_height =
+            with_fallback(&self.broadcast_endpoints, &self.timeouts, |client| {
+                client.block(None)
+            })
             .await
             .map_err(BroadcastTxCommitError::QueryLatestHeight)?
             .block
@@ -430,9 +958,10 @@ impl Module {
         let mut i = 0;
         loop {
             let reached_height = 'l: loop {
-                let current_height = self
-                    .tm_client
-                    .block(None)
+                let current_height =
+                    with_fallback(&self.broadcast_endpoints, &self.timeouts, |client| {
+                        client.block(None)
+                    })
                     .await
                     .map_err(BroadcastTxCommitError::QueryLatestHeight)?
                     .block
@@ -445,12 +974,18 @@ impl Module {
                 tokio::time::sleep(std::time::Duration::from_secs(1)).await;
             };
 
-            let tx_inclusion = self.tm_client.tx(tx_hash, false).await;
+            let tx_inclusion = with_fallback(&self.broadcast_endpoints, &self.timeouts, |client| {
+                client.tx(tx_hash, false)
+            })
+            .await;
 
             debug!(?tx_inclusion);
 
             match tx_inclusion {
                 Ok(tx) => {
+                    // the fee is paid for all included transactions, even if they failed
+                    self.record_fee(&auth_info.fee);
+
                     if tx.tx_result.code == 0 {
                         break Ok((tx_hash, tx.tx_result.gas_used));
                     } else {
@@ -497,20 +1032,19 @@ impl Module {
         }
     }
 
+    /// Simulate a transaction of `messages` signed by `signer`, whose account is `account`.
+    ///
+    /// If the simulation doesn't complete within the configured timeouts, it fails with
+    /// [`tonic::Code::DeadlineExceeded`].
     pub async fn simulate_tx(
         &self,
         signer: &CosmosSigner,
+        account: &BaseAccount,
         messages: impl IntoIterator<Item = protos::google::protobuf::Any> + Clone,
         memo: String,
     ) -> Result<(TxBody, AuthInfo, GasInfo), (TxBody, AuthInfo, tonic::Status)> {
         use protos::cosmos::tx;
 
-        let account = self.account_info(&signer.to_string()).await;
-
-        let mut client = tx::v1beta1::service_client::ServiceClient::connect(self.grpc_url.clone())
-            .await
-            .unwrap();
-
         let tx_body = TxBody {
             // TODO: Use RawAny here
             messages: messages.clone().into_iter().map(Into::into).collect(),
@@ -522,6 +1056,8 @@ impl Module {
             timeout_timestamp: None,
         };
 
+        let gas_config = self.config.gas_config();
+
         let auth_info = AuthInfo {
             signer_infos: [SignerInfo {
                 public_key: Some(AnyPubKey::Secp256k1(secp256k1::PubKey {
@@ -533,7 +1069,7 @@ impl Module {
                 sequence: account.sequence,
             }]
             .to_vec(),
-            fee: self.gas_config.mk_fee(self.gas_config.max_gas).clone(),
+            fee: gas_config.mk_fee(gas_config.max_gas),
         };
 
         let simulation_signature = signer
@@ -549,17 +1085,41 @@ impl Module {
             .expect("signing failed")
             .to_vec();
 
-        let result = client
-            .simulate(tx::v1beta1::SimulateRequest {
-                tx_bytes: Tx {
-                    body: tx_body.clone(),
-                    auth_info: auth_info.clone(),
-                    signatures: [simulation_signature.clone()].to_vec(),
-                }
-                .encode_as::<Proto>(),
-                ..Default::default()
-            })
-            .await;
+        let mut client = match self
+            .timeouts
+            .with_timeout(
+                CallKind::Connect,
+                self.grpc_auth.connect(self.grpc_url.clone()),
+            )
+            .await
+        {
+            Ok(Ok(service)) => tx::v1beta1::service_client::ServiceClient::new(service),
+            Ok(Err(err)) => {
+                return Err((
+                    tx_body,
+                    auth_info,
+                    tonic::Status::unavailable(ErrorReporter(err).to_string()),
+                ))
+            }
+            Err(err) => return Err((tx_body, auth_info, err.into())),
+        };
+
+        let result = self
+            .timeouts
+            .with_timeout(
+                CallKind::Query,
+                client.simulate(tx::v1beta1::SimulateRequest {
+                    tx_bytes: Tx {
+                        body: tx_body.clone(),
+                        auth_info: auth_info.clone(),
+                        signatures: [simulation_signature.clone()].to_vec(),
+                    }
+                    .encode_as::<Proto>(),
+                    ..Default::default()
+                }),
+            )
+            .await
+            .unwrap_or_else(|err| Err(err.into()));
 
         match result {
             Ok(ok) => Ok((
@@ -577,26 +1137,188 @@ impl Module {
         }
     }
 
-    async fn account_info(&self, account: &str) -> BaseAccount {
-        debug!(%account, "fetching account");
+    fn record_fee(&self, fee: &Fee) {
+        let fee = fee_amount(fee, &self.config.gas_config().gas_denom);
+
+        if let Err(err) = self.spend.record(fee) {
+            error!(%fee, error = %ErrorReporter(err), "unable to record tx fee");
+        }
+    }
+
+    /// Check the revision of this chain against [`Self::revision_check`], once. Neither a
+    /// mismatch nor a failed check is fatal, as they are only logged.
+    async fn check_revision_once(&self, voyager_client: &VoyagerClient) {
+        let Some(revision_check) = &self.revision_check else {
+            return;
+        };
+
+        if self.revision_checked.swap(true, Ordering::Relaxed) {
+            return;
+        }
 
-        let Any(account) = protos::cosmos::auth::v1beta1::query_client::QueryClient::connect(
-            self.grpc_url.clone(),
+        if let Err(err) = check_counterparty_revision(
+            voyager_client,
+            &self.chain_id,
+            self.revision,
+            revision_check,
         )
         .await
-        .unwrap()
-        .account(protos::cosmos::auth::v1beta1::QueryAccountRequest {
-            address: account.to_string(),
-        })
-        .await
-        .unwrap()
-        .into_inner()
-        .account
-        .unwrap()
-        .try_into()
-        .unwrap();
+        {
+            warn!(error = %ErrorReporter(err), "unable to check the revision of this chain");
+        }
+    }
+
+    /// Record the packets relayed by `msgs`, all of which were included successfully.
+    fn record_relay_progress<'a>(&self, msgs: impl IntoIterator<Item = &'a IbcMessage>) {
+        if let Err(err) = self
+            .relay_progress
+            .record_confirmed(msgs.into_iter().map(|msg| (true, msg.confirmed_packet())))
+        {
+            error!(error = %ErrorReporter(err), "unable to record relay progress");
+        }
+    }
+
+    /// Start the [`CheckBalances`] loop of this instance, if the balance monitor is enabled and
+    /// it has not been started yet.
+    fn start_balance_monitor(&self) -> Option<Op<VoyagerMessage>> {
+        let check = self.balance_monitor.as_ref()?.start()?;
+
+        Some(call(PluginMessage::new(
+            self.plugin_name(),
+            ModuleCall::from(check),
+        )))
+    }
+
+    async fn check_balances(&self, check: CheckBalances) -> RpcResult<Op<VoyagerMessage>> {
+        let Some(balance_monitor) = self
+            .balance_monitor
+            .as_ref()
+            .filter(|balance_monitor| balance_monitor.is_current(&check))
+        else {
+            debug!("dropping balance check queued by a previous instance");
+            return Ok(noop());
+        };
+
+        let gas_denom = self.config.gas_config().gas_denom;
+
+        let mut client = protos::cosmos::bank::v1beta1::query_client::QueryClient::new(
+            self.timeouts
+                .with_timeout(
+                    CallKind::Connect,
+                    self.grpc_auth.connect(self.grpc_url.clone()),
+                )
+                .await
+                .map_err(VoyagerError::from)?
+                .map_err(|err| VoyagerError::retryable(ErrorReporter(err).to_string()))?,
+        );
 
-        account
+        let mut balances = vec![];
+
+        for (_, address) in self.keyring.keys() {
+            let balance = self
+                .timeouts
+                .with_timeout(
+                    CallKind::Query,
+                    client.spendable_balance_by_denom(
+                        protos::cosmos::bank::v1beta1::QuerySpendableBalanceByDenomRequest {
+                            address: address.clone(),
+                            denom: gas_denom.clone(),
+                        },
+                    ),
+                )
+                .await
+                .map_err(VoyagerError::from)?
+                .map_err(|err| {
+                    VoyagerError::retryable(format!(
+                        "error fetching the balance of {address}: {}",
+                        ErrorReporter(err)
+                    ))
+                })?
+                .into_inner()
+                .balance
+                .map_or(Ok(0), |coin| coin.amount.parse())
+                .map_err(|err| {
+                    VoyagerError::retryable(format!(
+                        "invalid balance for {address}: {}",
+                        ErrorReporter(err)
+                    ))
+                })?;
+
+            balances.push((address.clone(), balance));
+        }
+
+        let low = balance_monitor.update(&self.keyring, balances);
+
+        Ok(conc(
+            low.into_iter()
+                .map(|low| {
+                    data(PluginMessage::new(
+                        self.plugin_name(),
+                        ModuleData::from(low),
+                    ))
+                })
+                .chain([seq([
+                    defer(now() + balance_monitor.config.check_interval),
+                    call(PluginMessage::new(
+                        self.plugin_name(),
+                        ModuleCall::from(check),
+                    )),
+                ])]),
+        ))
+    }
+
+    async fn account_info(&self, account: &str) -> Result<BaseAccount, TimedOut> {
+        debug!(%account, "fetching account");
+
+        let mut client = protos::cosmos::auth::v1beta1::query_client::QueryClient::new(
+            self.timeouts
+                .with_timeout(
+                    CallKind::Connect,
+                    self.grpc_auth.connect(self.grpc_url.clone()),
+                )
+                .await?
+                .unwrap(),
+        );
+
+        let Any(account) = self
+            .timeouts
+            .with_timeout(
+                CallKind::Query,
+                client.account(protos::cosmos::auth::v1beta1::QueryAccountRequest {
+                    address: account.to_string(),
+                }),
+            )
+            .await?
+            .unwrap()
+            .into_inner()
+            .account
+            .unwrap()
+            .try_into()
+            .unwrap();
+
+        Ok(account)
+    }
+}
+
+/// The total amount of `gas_denom` paid by `fee`.
+fn fee_amount(fee: &Fee, gas_denom: &str) -> u128 {
+    fee.amount
+        .iter()
+        .filter(|coin| coin.denom == gas_denom)
+        .fold(0_u128, |acc, coin| acc.saturating_add(coin.amount))
+}
+
+/// The estimate for a transaction that used `gas_used` gas in simulation. The
+/// fee is the same as the one that is paid when the transaction is submitted
+/// with `gas_multiplier`.
+fn tx_estimate(gas_config: &GasConfig, gas_used: u64, gas_multiplier: f64) -> TxEstimate {
+    TxEstimate {
+        gas: gas_used,
+        fee: fee_amount(
+            &gas_config.mk_fee_with_multiplier(gas_used, gas_multiplier),
+            &gas_config.gas_denom,
+        ),
+        denom: gas_config.gas_denom.clone(),
     }
 }
 
@@ -618,6 +1340,151 @@ pub enum BroadcastTxCommitError {
     UnionIbcError(union_ibc::ContractErrorKind),
     #[error("out of gas")]
     OutOfGas,
+    #[error(transparent)]
+    TxTooLarge(TxTooLargeError),
+    #[error(transparent)]
+    TimedOut(#[from] TimedOut),
+}
+
+/// The classification of an error submitting a transaction.
+#[allow(clippy::collapsible_match)]
+fn broadcast_error(err: &BroadcastTxCommitError) -> VoyagerError {
+    match err {
+        BroadcastTxCommitError::Tx(tx_err) => match tx_err {
+            CosmosSdkError::CapabilityError(capability_error) => {
+                VoyagerError::fatal(ErrorReporter(capability_error).to_string())
+            }
+            CosmosSdkError::IbcWasmError(IbcWasmError::ErrInvalidChecksum)
+            | CosmosSdkError::ClientError(ClientError::ErrClientNotFound) => {
+                VoyagerError::fatal(ErrorReporter(err).to_string())
+            }
+            _ => VoyagerError::retryable(ErrorReporter(err).to_string()),
+        },
+        BroadcastTxCommitError::UnionIbcError(_) | BroadcastTxCommitError::TxTooLarge(_) => {
+            VoyagerError::fatal(ErrorReporter(err).to_string())
+        }
+        BroadcastTxCommitError::SimulateTx(status)
+            if status.code() == tonic::Code::ResourceExhausted =>
+        {
+            VoyagerError::RateLimited
+        }
+        BroadcastTxCommitError::TimedOut(timed_out) => timed_out.clone().into(),
+        _ => VoyagerError::retryable(ErrorReporter(err).to_string()),
+    }
+}
+
+/// An error encoding a message for submission. Retrying the message will not fix an invalid
+/// encoding or an unsupported datagram, so the message is dropped from the transaction and
+/// reported instead.
+#[derive(Debug, thiserror::Error)]
+pub enum ProcessMsgError {
+    #[error("error decoding `{field}` of {kind} as an `Any` (0x{bytes})")]
+    DecodeAny {
+        kind: &'static str,
+        field: &'static str,
+        /// A hex prefix of the bytes that failed to decode.
+        bytes: String,
+        #[source]
+        source: prost::DecodeError,
+    },
+    #[error("`{field}` of {kind} is not valid utf-8 (0x{bytes})")]
+    InvalidUtf8 {
+        kind: &'static str,
+        field: &'static str,
+        bytes: String,
+        #[source]
+        source: std::str::Utf8Error,
+    },
+    #[error("error encoding {kind} as a contract execute message")]
+    ExecuteMsg {
+        kind: &'static str,
+        #[source]
+        source: serde_json::Error,
+    },
+    #[error("{kind} is not supported by the ibc-union contract")]
+    UnsupportedDatagram { kind: &'static str },
+}
+
+/// The first 16 bytes of `bytes` as hex, for error messages.
+fn hex_prefix(bytes: &[u8]) -> String {
+    const LEN: usize = 16;
+
+    if bytes.len() > LEN {
+        format!("{}..", hex::encode(&bytes[..LEN]))
+    } else {
+        hex::encode(bytes)
+    }
+}
+
+fn decode_any(
+    kind: &'static str,
+    field: &'static str,
+    bytes: &[u8],
+) -> Result<protos::google::protobuf::Any, ProcessMsgError> {
+    protos::google::protobuf::Any::decode(bytes).map_err(|source| ProcessMsgError::DecodeAny {
+        kind,
+        field,
+        bytes: hex_prefix(bytes),
+        source,
+    })
+}
+
+fn port_id_str(kind: &'static str, port_id: &[u8]) -> Result<String, ProcessMsgError> {
+    std::str::from_utf8(port_id)
+        .map(ToOwned::to_owned)
+        .map_err(|source| ProcessMsgError::InvalidUtf8 {
+            kind,
+            field: "port_id",
+            bytes: hex_prefix(port_id),
+            source,
+        })
+}
+
+fn execute_msg_json(
+    kind: &'static str,
+    msg: &union_ibc_msg::msg::ExecuteMsg,
+) -> Result<Vec<u8>, ProcessMsgError> {
+    serde_json::to_vec(msg).map_err(|source| ProcessMsgError::ExecuteMsg { kind, source })
+}
+
+/// Split the processed messages into the ones that can be submitted and the ones that failed to
+/// encode. An [`UnencodableMessage`] is emitted for each of the latter.
+fn split_processed(
+    chain_id: &ChainId,
+    msgs: Vec<(
+        IbcMessage,
+        Result<protos::google::protobuf::Any, ProcessMsgError>,
+    )>,
+) -> (
+    Vec<(IbcMessage, protos::google::protobuf::Any)>,
+    Vec<Op<VoyagerMessage>>,
+) {
+    let mut encoded = vec![];
+    let mut failed = vec![];
+
+    for (msg, res) in msgs {
+        match res {
+            Ok(any) => encoded.push((msg, any)),
+            Err(err) => {
+                error!(
+                    error = %ErrorReporter(&err),
+                    msg = msg.name(),
+                    "unable to encode message, dropping it from the transaction"
+                );
+
+                failed.push(data(PluginMessage::new(
+                    plugin_name(chain_id),
+                    ModuleData::from(UnencodableMessage {
+                        chain_id: chain_id.clone(),
+                        message: msg,
+                        error: ErrorReporter(err).to_string(),
+                    }),
+                )));
+            }
+        }
+    }
+
+    (encoded, failed)
 }
 
 #[async_trait]
@@ -628,105 +1495,76 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
         _: &Extensions,
         msgs: Vec<Op<VoyagerMessage>>,
     ) -> RpcResult<PassResult<VoyagerMessage>> {
-        Ok(PassResult {
-            optimize_further: vec![],
-            ready: msgs
-                .into_iter()
-                .enumerate()
-                .map(|(idx, msg)| {
-                    Ok((
-                        vec![idx],
-                        match msg {
-                            Op::Data(Data::IdentifiedIbcDatagram(WithChainId {
-                                chain_id,
-                                message,
-                            })) => {
-                                assert_eq!(chain_id, self.chain_id);
-
-                                call(PluginMessage::new(
-                                    self.plugin_name(),
-                                    ModuleCall::SubmitTransaction(vec![
-                                        IbcMessage::from_raw_datagram(message)?,
-                                    ]),
-                                ))
-                            }
-                            Op::Data(Data::IdentifiedIbcDatagramBatch(WithChainId {
-                                chain_id,
-                                message,
-                            })) => {
-                                assert_eq!(chain_id, self.chain_id);
-
-                                call(PluginMessage::new(
-                                    self.plugin_name(),
-                                    ModuleCall::SubmitTransaction(
-                                        message
-                                            .into_iter()
-                                            .map(IbcMessage::from_raw_datagram)
-                                            .collect::<Result<_, _>>()?,
-                                    ),
-                                ))
-                            }
-                            _ => panic!("unexpected message: {msg:?}"),
-                        },
-                    ))
-                })
-                .collect::<RpcResult<_>>()?,
-        })
+        Ok(run_pass(
+            &self.chain_id,
+            &self.pass_through_count,
+            &self.config.suppression(),
+            &self.config.ordered_channels(),
+            msgs,
+        ))
     }
 
     #[instrument(skip_all, fields(chain_id = %self.chain_id))]
-    #[allow(clippy::collapsible_match)]
-    async fn call(&self, _: &Extensions, msg: ModuleCall) -> RpcResult<Op<VoyagerMessage>> {
+    async fn call(&self, e: &Extensions, msg: ModuleCall) -> RpcResult<Op<VoyagerMessage>> {
         match msg {
             ModuleCall::SubmitTransaction(msgs) => {
-                let mut out = vec![];
+                self.check_revision_once(e.try_get::<VoyagerClient>()?)
+                    .await;
+
+                let msgs = if self.verify_proofs_before_submit {
+                    let (verified, rejected) = partition_verified(
+                        e.try_get::<VoyagerClient>()?,
+                        &self.chain_id,
+                        msgs,
+                        |msg| match msg {
+                            IbcMessage::IbcUnion(datagram) => Some(datagram),
+                            IbcMessage::IbcV1(_) => None,
+                        },
+                    )
+                    .await?;
+
+                    if !rejected.is_empty() {
+                        return self.fail_rejected(verified, rejected);
+                    }
 
-                for msgs in msgs.chunks(5) {
+                    verified
+                } else {
+                    msgs
+                };
+
+                let Admission { submit, deferred } =
+                    self.spend.admit(msgs, IbcMessage::is_priority);
+
+                let mut out = self.start_balance_monitor().into_iter().collect::<Vec<_>>();
+
+                if let Some((exceeded, msgs)) = deferred {
+                    warn!(
+                        error = %exceeded,
+                        batch.size = msgs.len(),
+                        "daily gas budget exceeded, deferring submission until the budget window rolls over"
+                    );
+
+                    out.push(seq([
+                        defer(exceeded.resets_at),
+                        call(PluginMessage::new(
+                            self.plugin_name(),
+                            ModuleCall::SubmitTransaction(msgs),
+                        )),
+                    ]));
+                }
+
+                for msgs in submit.chunks(5) {
                     let res = self
                         .do_send_transaction(msgs.to_vec())
                         .await
-                        .map_err(|err| match &err {
-                            BroadcastTxCommitError::Tx(tx_err) => match tx_err {
-                                CosmosSdkError::CapabilityError(capability_error) => {
-                                    ErrorObject::owned(
-                                        FATAL_JSONRPC_ERROR_CODE,
-                                        ErrorReporter(capability_error).to_string(),
-                                        None::<()>,
-                                    )
-                                }
-                                CosmosSdkError::IbcWasmError(IbcWasmError::ErrInvalidChecksum) => {
-                                    ErrorObject::owned(
-                                        FATAL_JSONRPC_ERROR_CODE,
-                                        ErrorReporter(err).to_string(),
-                                        None::<()>,
-                                    )
-                                }
-                                CosmosSdkError::ClientError(ClientError::ErrClientNotFound) => {
-                                    ErrorObject::owned(
-                                        FATAL_JSONRPC_ERROR_CODE,
-                                        ErrorReporter(err).to_string(),
-                                        None::<()>,
-                                    )
-                                }
-                                _ => ErrorObject::owned(
-                                    -1,
-                                    ErrorReporter(err).to_string(),
-                                    None::<()>,
-                                ),
-                            },
-                            BroadcastTxCommitError::UnionIbcError(_) => ErrorObject::owned(
-                                FATAL_JSONRPC_ERROR_CODE,
-                                ErrorReporter(err).to_string(),
-                                None::<()>,
-                            ),
-                            _ => ErrorObject::owned(-1, ErrorReporter(err).to_string(), None::<()>),
-                        })?;
+                        .map_err(|err| ErrorObjectOwned::from(broadcast_error(&err)))?;
 
                     out.push(res);
                 }
 
                 Ok(conc(out))
             }
+            ModuleCall::CheckBalances(check) => self.check_balances(check).await,
         }
     }
 
@@ -739,375 +1577,1538 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
     ) -> RpcResult<Op<VoyagerMessage>> {
         match cb {}
     }
-}
 
-fn process_msgs(
-    msgs: Vec<IbcMessage>,
-    signer: &CosmosSigner,
-    ibc_union_contract_address: Bech32<H256>,
-) -> Vec<(IbcMessage, protos::google::protobuf::Any)> {
-    msgs.into_iter()
-        .map(|msg| {
-            let encoded = match msg.clone() {
-                IbcMessage::IbcV1(msg) => match msg {
-                    ibc_classic_spec::Datagram::ConnectionOpenInit(message) => {
-                        mk_any(&protos::ibc::core::connection::v1::MsgConnectionOpenInit {
-                            client_id: message.client_id.to_string(),
-                            counterparty: Some(message.counterparty.into()),
-                            version: Some(message.version.into()),
-                            signer: signer.to_string(),
-                            delay_period: message.delay_period,
-                        })
-                    }
-                    ibc_classic_spec::Datagram::ConnectionOpenTry(message) => {
-                        mk_any(&protos::ibc::core::connection::v1::MsgConnectionOpenTry {
-                            client_id: message.client_id.to_string(),
-                            counterparty: Some(message.counterparty.into()),
-                            delay_period: message.delay_period,
-                            counterparty_versions: message
-                                .counterparty_versions
-                                .into_iter()
-                                .map(Into::into)
-                                .collect(),
-                            proof_height: Some(message.proof_height.into()),
-                            proof_init: message.proof_init.into(),
-                            signer: signer.to_string(),
-                            ..Default::default()
-                        })
-                    }
-                    #[allow(deprecated)]
-                    ibc_classic_spec::Datagram::ConnectionOpenAck(message) => {
-                        mk_any(&protos::ibc::core::connection::v1::MsgConnectionOpenAck {
-                            client_state: Some(
-                                protos::google::protobuf::Any::decode(&*message.client_state)
-                                    .expect("value should be encoded as an `Any`"),
-                            ),
-                            proof_height: Some(message.proof_height.into()),
-                            proof_client: message.proof_client.into(),
-                            proof_consensus: message.proof_consensus.into(),
-                            consensus_height: Some(message.consensus_height.into()),
-                            signer: signer.to_string(),
-                            host_consensus_state_proof: vec![],
-                            connection_id: message.connection_id.to_string(),
-                            counterparty_connection_id: message
-                                .counterparty_connection_id
-                                .to_string(),
-                            version: Some(message.version.into()),
-                            proof_try: message.proof_try.into(),
-                        })
-                    }
-                    ibc_classic_spec::Datagram::ConnectionOpenConfirm(message) => mk_any(
-                        &protos::ibc::core::connection::v1::MsgConnectionOpenConfirm {
-                            connection_id: message.connection_id.to_string(),
-                            proof_ack: message.proof_ack.into(),
-                            proof_height: Some(message.proof_height.into()),
-                            signer: signer.to_string(),
-                        },
-                    ),
-                    ibc_classic_spec::Datagram::ChannelOpenInit(message) => {
-                        mk_any(&protos::ibc::core::channel::v1::MsgChannelOpenInit {
-                            port_id: message.port_id.to_string(),
-                            channel: Some(message.channel.into()),
-                            signer: signer.to_string(),
-                        })
-                    }
-                    ibc_classic_spec::Datagram::ChannelOpenTry(message) => {
-                        mk_any(&protos::ibc::core::channel::v1::MsgChannelOpenTry {
-                            port_id: message.port_id.to_string(),
-                            channel: Some(message.channel.into()),
-                            counterparty_version: message.counterparty_version,
-                            proof_init: message.proof_init.into(),
-                            proof_height: Some(message.proof_height.into()),
-                            signer: signer.to_string(),
-                            ..Default::default()
-                        })
-                    }
-                    ibc_classic_spec::Datagram::ChannelOpenAck(message) => {
-                        mk_any(&protos::ibc::core::channel::v1::MsgChannelOpenAck {
-                            port_id: message.port_id.to_string(),
-                            channel_id: message.channel_id.to_string(),
-                            counterparty_version: message.counterparty_version,
-                            counterparty_channel_id: message.counterparty_channel_id.to_string(),
-                            proof_try: message.proof_try.into(),
-                            proof_height: Some(message.proof_height.into()),
-                            signer: signer.to_string(),
-                        })
-                    }
-                    ibc_classic_spec::Datagram::ChannelOpenConfirm(message) => {
-                        mk_any(&protos::ibc::core::channel::v1::MsgChannelOpenConfirm {
-                            port_id: message.port_id.to_string(),
-                            channel_id: message.channel_id.to_string(),
-                            proof_height: Some(message.proof_height.into()),
-                            signer: signer.to_string(),
-                            proof_ack: message.proof_ack.into(),
-                        })
-                    }
-                    ibc_classic_spec::Datagram::RecvPacket(message) => {
-                        mk_any(&protos::ibc::core::channel::v1::MsgRecvPacket {
-                            packet: Some(message.packet.into()),
-                            proof_height: Some(message.proof_height.into()),
-                            signer: signer.to_string(),
-                            proof_commitment: message.proof_commitment.into(),
-                        })
-                    }
-                    ibc_classic_spec::Datagram::AcknowledgePacket(message) => {
-                        mk_any(&protos::ibc::core::channel::v1::MsgAcknowledgement {
-                            packet: Some(message.packet.into()),
-                            acknowledgement: message.acknowledgement.into(),
-                            proof_acked: message.proof_acked.into(),
-                            proof_height: Some(message.proof_height.into()),
-                            signer: signer.to_string(),
-                        })
-                    }
-                    ibc_classic_spec::Datagram::TimeoutPacket(message) => {
-                        mk_any(&protos::ibc::core::channel::v1::MsgTimeout {
-                            packet: Some(message.packet.into()),
-                            proof_unreceived: message.proof_unreceived,
-                            proof_height: Some(message.proof_height.into()),
-                            next_sequence_recv: message.next_sequence_recv.get(),
-                            signer: signer.to_string(),
-                        })
-                    }
-                    ibc_classic_spec::Datagram::CreateClient(message) => {
-                        mk_any(&protos::ibc::core::client::v1::MsgCreateClient {
-                            client_state: Some(
-                                protos::google::protobuf::Any::decode(&*message.msg.client_state)
-                                    .expect("value should be encoded as an `Any`"),
-                            ),
-                            consensus_state: Some(
-                                protos::google::protobuf::Any::decode(
-                                    &*message.msg.consensus_state,
-                                )
-                                .expect("value should be encoded as an `Any`"),
-                            ),
-                            signer: signer.to_string(),
-                        })
-                    }
-                    ibc_classic_spec::Datagram::UpdateClient(message) => {
-                        mk_any(&protos::ibc::core::client::v1::MsgUpdateClient {
-                            signer: signer.to_string(),
-                            client_id: message.client_id.to_string(),
-                            client_message: Some(
-                                protos::google::protobuf::Any::decode(&*message.client_message)
-                                    .expect("value should be encoded as an `Any`"),
-                            ),
-                        })
-                    }
-                },
-                IbcMessage::IbcUnion(msg) => match msg {
-                    ibc_union_spec::Datagram::CreateClient(msg_create_client) => {
-                        mk_any(&protos::cosmwasm::wasm::v1::MsgExecuteContract {
-                            sender: signer.to_string(),
-                            contract: ibc_union_contract_address.to_string(),
-                            msg: serde_json::to_vec(&union_ibc_msg::msg::ExecuteMsg::CreateClient(
-                                union_ibc_msg::msg::MsgCreateClient {
-                                    client_type: msg_create_client.client_type.to_string(),
-                                    client_state_bytes: msg_create_client.client_state_bytes,
-                                    consensus_state_bytes: msg_create_client.consensus_state_bytes,
-                                    relayer: signer.to_string(),
-                                },
-                            ))
-                            .unwrap(),
-                            funds: vec![],
-                        })
-                    }
-                    ibc_union_spec::Datagram::UpdateClient(msg_update_client) => {
-                        mk_any(&protos::cosmwasm::wasm::v1::MsgExecuteContract {
-                            sender: signer.to_string(),
-                            contract: ibc_union_contract_address.to_string(),
-                            msg: serde_json::to_vec(&union_ibc_msg::msg::ExecuteMsg::UpdateClient(
-                                union_ibc_msg::msg::MsgUpdateClient {
-                                    client_id: msg_update_client.client_id,
-                                    client_message: msg_update_client.client_message,
-                                    relayer: signer.to_string(),
-                                },
-                            ))
-                            .unwrap(),
-                            funds: vec![],
-                        })
-                    }
-                    ibc_union_spec::Datagram::ConnectionOpenInit(msg_connection_open_init) => {
-                        mk_any(&protos::cosmwasm::wasm::v1::MsgExecuteContract {
-                            sender: signer.to_string(),
-                            contract: ibc_union_contract_address.to_string(),
-                            msg: serde_json::to_vec(
-                                &union_ibc_msg::msg::ExecuteMsg::ConnectionOpenInit(
-                                    union_ibc_msg::msg::MsgConnectionOpenInit {
-                                        client_id: msg_connection_open_init.client_id,
-                                        counterparty_client_id: msg_connection_open_init
-                                            .counterparty_client_id,
-                                        relayer: signer.to_string(),
-                                    },
-                                ),
-                            )
-                            .unwrap(),
-                            funds: vec![],
-                        })
-                    }
-                    ibc_union_spec::Datagram::ConnectionOpenTry(msg_connection_open_try) => {
-                        mk_any(&protos::cosmwasm::wasm::v1::MsgExecuteContract {
-                            sender: signer.to_string(),
-                            contract: ibc_union_contract_address.to_string(),
-                            msg: serde_json::to_vec(
-                                &union_ibc_msg::msg::ExecuteMsg::ConnectionOpenTry(
-                                    union_ibc_msg::msg::MsgConnectionOpenTry {
-                                        counterparty_client_id: msg_connection_open_try
-                                            .counterparty_client_id,
-                                        counterparty_connection_id: msg_connection_open_try
-                                            .counterparty_connection_id,
-                                        client_id: msg_connection_open_try.client_id,
-                                        proof_init: msg_connection_open_try.proof_init,
-                                        proof_height: msg_connection_open_try.proof_height,
-                                        relayer: signer.to_string(),
-                                    },
-                                ),
-                            )
-                            .unwrap(),
-                            funds: vec![],
-                        })
-                    }
-                    ibc_union_spec::Datagram::ConnectionOpenAck(msg_connection_open_ack) => {
-                        mk_any(&protos::cosmwasm::wasm::v1::MsgExecuteContract {
-                            sender: signer.to_string(),
-                            contract: ibc_union_contract_address.to_string(),
-                            msg: serde_json::to_vec(
-                                &union_ibc_msg::msg::ExecuteMsg::ConnectionOpenAck(
-                                    union_ibc_msg::msg::MsgConnectionOpenAck {
-                                        connection_id: msg_connection_open_ack.connection_id,
-                                        counterparty_connection_id: msg_connection_open_ack
-                                            .counterparty_connection_id,
-                                        proof_try: msg_connection_open_ack.proof_try,
-                                        proof_height: msg_connection_open_ack.proof_height,
-                                        relayer: signer.to_string(),
-                                    },
-                                ),
-                            )
-                            .unwrap(),
-                            funds: vec![],
-                        })
-                    }
-                    ibc_union_spec::Datagram::ConnectionOpenConfirm(
-                        msg_connection_open_confirm,
-                    ) => mk_any(&protos::cosmwasm::wasm::v1::MsgExecuteContract {
-                        sender: signer.to_string(),
-                        contract: ibc_union_contract_address.to_string(),
-                        msg: serde_json::to_vec(
-                            &union_ibc_msg::msg::ExecuteMsg::ConnectionOpenConfirm(
-                                union_ibc_msg::msg::MsgConnectionOpenConfirm {
-                                    connection_id: msg_connection_open_confirm.connection_id,
-                                    proof_ack: msg_connection_open_confirm.proof_ack,
-                                    proof_height: msg_connection_open_confirm.proof_height,
-                                    relayer: signer.to_string(),
-                                },
-                            ),
-                        )
-                        .unwrap(),
-                        funds: vec![],
+    #[instrument(skip_all, fields(chain_id = %self.chain_id))]
+    async fn reload(&self, new_config: Value) -> RpcResult<ReloadReport> {
+        let new_config = serde_json::from_value::<Config>(new_config).map_err(|err| {
+            ErrorObject::owned(
+                INVALID_PARAMS_CODE,
+                format!("invalid config: {}", ErrorReporter(err)),
+                None::<()>,
+            )
+        })?;
+
+        new_config.gas_config.validate().map_err(|err| {
+            ErrorObject::owned(
+                INVALID_PARAMS_CODE,
+                format!("invalid config: {}", ErrorReporter(err)),
+                None::<()>,
+            )
+        })?;
+
+        let report = self.config.reload(new_config);
+
+        info!(?report, "reloaded config");
+
+        Ok(report)
+    }
+
+    #[instrument(skip_all, fields(chain_id = %self.chain_id))]
+    async fn estimate(&self, msgs: Vec<IbcDatagram>) -> RpcResult<TxEstimate> {
+        let msgs = msgs
+            .iter()
+            .map(IbcMessage::from_raw_datagram)
+            .collect::<RpcResult<Vec<_>>>()?;
+
+        let gas_config = self.config.gas_config();
+        let gas_multiplier = gas_config.multiplier_for(msgs.iter().map(IbcMessage::gas_kind));
+
+        let res = self
+            .keyring
+            .with(|signer| {
+                let msgs = process_msgs(msgs, signer, self.ibc_union_contract_address.clone())
+                    .into_iter()
+                    .map(|(_, msg)| msg)
+                    .collect::<Result<Vec<_>, _>>();
+
+                async move {
+                    let msgs =
+                        msgs.map_err(|err| VoyagerError::fatal(ErrorReporter(err).to_string()))?;
+
+                    let account = self.account_info(&signer.to_string()).await?;
+
+                    Ok::<_, VoyagerError>(
+                        self.simulate_tx(signer, &account, msgs, self.config.memo())
+                            .await,
+                    )
+                }
+            })
+            .await;
+
+        match res {
+            Some(Err(err)) => Err(err.into()),
+            Some(Ok(Ok((_, _, gas_info)))) => {
+                Ok(tx_estimate(&gas_config, gas_info.gas_used, gas_multiplier))
+            }
+            Some(Ok(Err((_, _, status)))) => Err(VoyagerError::retryable(format!(
+                "tx simulation failed: {}",
+                ErrorReporter(status)
+            ))
+            .into()),
+            None => Err(VoyagerError::retryable("no signers available").into()),
+        }
+    }
+
+    #[instrument(skip_all, fields(chain_id = %self.chain_id, %channel))]
+    async fn relay_progress(&self, channel: String) -> RpcResult<RelayProgress> {
+        Ok(self.relay_progress.progress(&channel))
+    }
+}
+
+/// Convert all datagrams for `chain_id` in `msgs` into transaction submission calls.
+///
+/// Any ops that are not datagrams for this chain are returned untouched, such that a mismatch between the interest filter and this plugin does not take down the entire plugin.
+///
+/// Datagrams that can't be decoded are emitted as [`UndecodableDatagram`]s instead of failing the pass, such that one bad datagram in a batch does not wedge all of the others.
+///
+/// Datagrams for channels in the `suppression` list, or frozen in its freeze list, are not submitted, and are emitted as [`SuppressedDatagram`]s instead.
+///
+/// Packet datagrams for `ordered_channels` are submitted one at a time per channel, in order of their sequence (see [`ordering`]). Since they may be pulled from multiple ops, all of the ops that contained any of them are combined into a single op.
+fn run_pass(
+    chain_id: &ChainId,
+    pass_through_count: &AtomicU64,
+    suppression: &SuppressionList,
+    ordered_channels: &[(PortId, ChannelId)],
+    msgs: Vec<Op<VoyagerMessage>>,
+) -> PassResult<VoyagerMessage> {
+    let pass_through = |msg: Op<VoyagerMessage>, reason: &str| {
+        let count = pass_through_count.fetch_add(1, Ordering::Relaxed) + 1;
+
+        warn!(
+            op_type = op_type(&msg),
+            pass_through_count = count,
+            "{reason}, passing through unchanged"
+        );
+
+        Claim::Ready(msg)
+    };
+
+    let mut ordered = OrderedPackets::default();
+    let mut next_idx = 0;
+
+    let mut result = PassResult::map_claimed(msgs, |msg| {
+        let idx = next_idx;
+        next_idx += 1;
+
+        let datagrams = match msg {
+            Op::Data(Data::IdentifiedIbcDatagram(WithChainId {
+                chain_id: ref datagram_chain_id,
+                ..
+            }))
+            | Op::Data(Data::IdentifiedIbcDatagramBatch(WithChainId {
+                chain_id: ref datagram_chain_id,
+                ..
+            })) if datagram_chain_id != chain_id => {
+                let reason = format!("datagram is for chain {datagram_chain_id}, not {chain_id}");
+
+                return pass_through(msg, &reason);
+            }
+            Op::Data(Data::IdentifiedIbcDatagram(WithChainId { message, .. })) => vec![message],
+            Op::Data(Data::IdentifiedIbcDatagramBatch(WithChainId { message, .. })) => message,
+            msg => return pass_through(msg, "unexpected message"),
+        };
+
+        let datagrams = ordered.take(ordered_channels, idx, datagrams);
+
+        // all of the datagrams are submitted with the ordered channels below
+        if datagrams.is_empty() {
+            return Claim::Unclaimed;
+        }
+
+        Claim::Ready(submit_unsuppressed(chain_id, suppression, datagrams))
+    });
+
+    if ordered.is_empty() {
+        return result;
+    }
+
+    let parents = ordered.parents().iter().copied().collect::<Vec<_>>();
+
+    let (combined, ready) = result
+        .ready
+        .into_iter()
+        .partition::<Vec<_>, _>(|(idxs, _)| idxs.iter().any(|idx| parents.contains(idx)));
+
+    result.ready = ready;
+    result.ready.push((
+        parents,
+        conc(
+            combined
+                .into_iter()
+                .map(|(_, op)| op)
+                .chain(
+                    ordered
+                        .into_fifos()
+                        .map(|((port_id, channel_id), datagrams)| {
+                            debug!(
+                                %port_id,
+                                %channel_id,
+                                count = datagrams.len(),
+                                "submitting packets on ordered channel in sequence"
+                            );
+
+                            seq(datagrams.into_iter().map(|datagram| {
+                                submit_unsuppressed(chain_id, suppression, vec![datagram])
+                            }))
+                        }),
+                ),
+        ),
+    ));
+
+    result
+}
+
+/// Submit all of the `datagrams` that are not for a suppressed channel, emitting a [`SuppressedDatagram`] for each of the ones that are.
+fn submit_unsuppressed(
+    chain_id: &ChainId,
+    suppression: &SuppressionList,
+    datagrams: Vec<IbcDatagram>,
+) -> Op<VoyagerMessage> {
+    let (datagrams, suppressed) = suppression
+        .with_frozen_channels(chain_id)
+        .partition(chain_id, datagrams);
+
+    if suppressed.is_empty() {
+        return submit_decodable(chain_id, datagrams);
+    }
+
+    conc(
+        (!datagrams.is_empty())
+            .then(|| submit_decodable(chain_id, datagrams))
+            .into_iter()
+            .chain(suppressed.into_iter().map(|suppressed| {
+                data(PluginMessage::new(
+                    plugin_name(chain_id),
+                    ModuleData::from(suppressed),
+                ))
+            })),
+    )
+}
+
+/// Submit all of the `datagrams` that can be decoded in a single transaction, emitting an [`UndecodableDatagram`] for each of the ones that can't.
+fn submit_decodable(chain_id: &ChainId, datagrams: Vec<IbcDatagram>) -> Op<VoyagerMessage> {
+    let mut msgs = vec![];
+    let mut undecodable = vec![];
+
+    for datagram in datagrams {
+        match IbcMessage::from_raw_datagram(&datagram) {
+            Ok(msg) => msgs.push(msg),
+            Err(err) => {
+                error!(
+                    error = %err.message(),
+                    datagram = %serde_json::to_string(&datagram).unwrap_or_default(),
+                    "undecodable datagram, dropping it from the transaction"
+                );
+
+                undecodable.push(data(PluginMessage::new(
+                    plugin_name(chain_id),
+                    ModuleData::from(UndecodableDatagram {
+                        chain_id: chain_id.clone(),
+                        datagram,
+                        error: err.message().to_owned(),
                     }),
-                    ibc_union_spec::Datagram::ChannelOpenInit(_msg_channel_open_init) => todo!(),
-                    ibc_union_spec::Datagram::ChannelOpenTry(msg_channel_open_try) => {
-                        dbg!(&msg_channel_open_try);
-
-                        let channel_open_try = union_ibc_msg::msg::ExecuteMsg::ChannelOpenTry(
-                            union_ibc_msg::msg::MsgChannelOpenTry {
-                                port_id: String::from_utf8(msg_channel_open_try.port_id.to_vec())
-                                    .unwrap(),
-                                channel: msg_channel_open_try.channel,
-                                counterparty_version: msg_channel_open_try.counterparty_version,
-                                proof_init: msg_channel_open_try.proof_init,
-                                proof_height: msg_channel_open_try.proof_height,
-                                relayer: signer.to_string(),
+                )));
+            }
+        }
+    }
+
+    if undecodable.is_empty() {
+        return call(PluginMessage::new(
+            plugin_name(chain_id),
+            ModuleCall::SubmitTransaction(msgs),
+        ));
+    }
+
+    conc(
+        (!msgs.is_empty())
+            .then(|| {
+                call(PluginMessage::new(
+                    plugin_name(chain_id),
+                    ModuleCall::SubmitTransaction(msgs),
+                ))
+            })
+            .into_iter()
+            .chain(undecodable),
+    )
+}
+
+/// The `@type` of the op, and of the contained data if it is a data op (i.e. `data/plugin`).
+fn op_type(op: &Op<VoyagerMessage>) -> String {
+    let value = serde_json::to_value(op).unwrap_or_default();
+
+    let ty = |value: &serde_json::Value| {
+        value
+            .get("@type")
+            .and_then(|ty| ty.as_str())
+            .unwrap_or("<unknown>")
+            .to_owned()
+    };
+
+    match op {
+        Op::Data(_) => format!("{}/{}", ty(&value), ty(&value["@value"])),
+        _ => ty(&value),
+    }
+}
+
+/// Encode all of `msgs` as the messages to be submitted in a transaction signed by `signer`.
+///
+/// A message that can't be encoded fails on its own, without affecting the other messages.
+fn process_msgs(
+    msgs: Vec<IbcMessage>,
+    signer: &CosmosSigner,
+    ibc_union_contract_address: Bech32<Bytes>,
+) -> Vec<(
+    IbcMessage,
+    Result<protos::google::protobuf::Any, ProcessMsgError>,
+)> {
+    let relayer = RelayerAddress::cosmos(signer);
+
+    msgs.into_iter()
+        .map(|msg| {
+            let encoded = encode_msg(msg.clone(), &relayer, &ibc_union_contract_address);
+
+            (msg, encoded)
+        })
+        .collect()
+}
+
+/// Encode `msg` to be submitted by `relayer`, which is used as both the signer of the message and
+/// the relayer of ibc-union datagrams.
+fn encode_msg(
+    msg: IbcMessage,
+    relayer: &RelayerAddress,
+    ibc_union_contract_address: &Bech32<Bytes>,
+) -> Result<protos::google::protobuf::Any, ProcessMsgError> {
+    let kind = msg.name();
+
+    let encoded = match msg {
+        IbcMessage::IbcV1(msg) => match msg {
+            ibc_classic_spec::Datagram::ConnectionOpenInit(message) => {
+                mk_any(&protos::ibc::core::connection::v1::MsgConnectionOpenInit {
+                    client_id: message.client_id.to_string(),
+                    counterparty: Some(message.counterparty.into()),
+                    version: Some(message.version.into()),
+                    signer: relayer.to_string(),
+                    delay_period: message.delay_period,
+                })
+            }
+            ibc_classic_spec::Datagram::ConnectionOpenTry(message) => {
+                mk_any(&protos::ibc::core::connection::v1::MsgConnectionOpenTry {
+                    client_id: message.client_id.to_string(),
+                    counterparty: Some(message.counterparty.into()),
+                    delay_period: message.delay_period,
+                    counterparty_versions: message
+                        .counterparty_versions
+                        .into_iter()
+                        .map(Into::into)
+                        .collect(),
+                    proof_height: Some(message.proof_height.into()),
+                    proof_init: message.proof_init.into(),
+                    signer: relayer.to_string(),
+                    ..Default::default()
+                })
+            }
+            #[allow(deprecated)]
+            ibc_classic_spec::Datagram::ConnectionOpenAck(message) => {
+                mk_any(&protos::ibc::core::connection::v1::MsgConnectionOpenAck {
+                    client_state: Some(decode_any(kind, "client_state", &message.client_state)?),
+                    proof_height: Some(message.proof_height.into()),
+                    proof_client: message.proof_client.into(),
+                    proof_consensus: message.proof_consensus.into(),
+                    consensus_height: Some(message.consensus_height.into()),
+                    signer: relayer.to_string(),
+                    host_consensus_state_proof: vec![],
+                    connection_id: message.connection_id.to_string(),
+                    counterparty_connection_id: message.counterparty_connection_id.to_string(),
+                    version: Some(message.version.into()),
+                    proof_try: message.proof_try.into(),
+                })
+            }
+            ibc_classic_spec::Datagram::ConnectionOpenConfirm(message) => mk_any(
+                &protos::ibc::core::connection::v1::MsgConnectionOpenConfirm {
+                    connection_id: message.connection_id.to_string(),
+                    proof_ack: message.proof_ack.into(),
+                    proof_height: Some(message.proof_height.into()),
+                    signer: relayer.to_string(),
+                },
+            ),
+            ibc_classic_spec::Datagram::ChannelOpenInit(message) => {
+                mk_any(&protos::ibc::core::channel::v1::MsgChannelOpenInit {
+                    port_id: message.port_id.to_string(),
+                    channel: Some(message.channel.into()),
+                    signer: relayer.to_string(),
+                })
+            }
+            ibc_classic_spec::Datagram::ChannelOpenTry(message) => {
+                mk_any(&protos::ibc::core::channel::v1::MsgChannelOpenTry {
+                    port_id: message.port_id.to_string(),
+                    channel: Some(message.channel.into()),
+                    counterparty_version: message.counterparty_version,
+                    proof_init: message.proof_init.into(),
+                    proof_height: Some(message.proof_height.into()),
+                    signer: relayer.to_string(),
+                    ..Default::default()
+                })
+            }
+            ibc_classic_spec::Datagram::ChannelOpenAck(message) => {
+                mk_any(&protos::ibc::core::channel::v1::MsgChannelOpenAck {
+                    port_id: message.port_id.to_string(),
+                    channel_id: message.channel_id.to_string(),
+                    counterparty_version: message.counterparty_version,
+                    counterparty_channel_id: message.counterparty_channel_id.to_string(),
+                    proof_try: message.proof_try.into(),
+                    proof_height: Some(message.proof_height.into()),
+                    signer: relayer.to_string(),
+                })
+            }
+            ibc_classic_spec::Datagram::ChannelOpenConfirm(message) => {
+                mk_any(&protos::ibc::core::channel::v1::MsgChannelOpenConfirm {
+                    port_id: message.port_id.to_string(),
+                    channel_id: message.channel_id.to_string(),
+                    proof_height: Some(message.proof_height.into()),
+                    signer: relayer.to_string(),
+                    proof_ack: message.proof_ack.into(),
+                })
+            }
+            ibc_classic_spec::Datagram::RecvPacket(message) => {
+                mk_any(&protos::ibc::core::channel::v1::MsgRecvPacket {
+                    packet: Some(message.packet.into()),
+                    proof_height: Some(message.proof_height.into()),
+                    signer: relayer.to_string(),
+                    proof_commitment: message.proof_commitment.into(),
+                })
+            }
+            ibc_classic_spec::Datagram::AcknowledgePacket(message) => {
+                mk_any(&protos::ibc::core::channel::v1::MsgAcknowledgement {
+                    packet: Some(message.packet.into()),
+                    acknowledgement: message.acknowledgement.into(),
+                    proof_acked: message.proof_acked.into(),
+                    proof_height: Some(message.proof_height.into()),
+                    signer: relayer.to_string(),
+                })
+            }
+            ibc_classic_spec::Datagram::TimeoutPacket(message) => {
+                mk_any(&protos::ibc::core::channel::v1::MsgTimeout {
+                    packet: Some(message.packet.into()),
+                    proof_unreceived: message.proof_unreceived,
+                    proof_height: Some(message.proof_height.into()),
+                    next_sequence_recv: message.next_sequence_recv.get(),
+                    signer: relayer.to_string(),
+                })
+            }
+            ibc_classic_spec::Datagram::CreateClient(message) => {
+                mk_any(&protos::ibc::core::client::v1::MsgCreateClient {
+                    client_state: Some(decode_any(
+                        kind,
+                        "client_state",
+                        &message.msg.client_state,
+                    )?),
+                    consensus_state: Some(decode_any(
+                        kind,
+                        "consensus_state",
+                        &message.msg.consensus_state,
+                    )?),
+                    signer: relayer.to_string(),
+                })
+            }
+            ibc_classic_spec::Datagram::UpdateClient(message) => {
+                mk_any(&protos::ibc::core::client::v1::MsgUpdateClient {
+                    signer: relayer.to_string(),
+                    client_id: message.client_id.to_string(),
+                    client_message: Some(decode_any(
+                        kind,
+                        "client_message",
+                        &message.client_message,
+                    )?),
+                })
+            }
+            ibc_classic_spec::Datagram::UpgradeClient(message) => {
+                mk_any(&protos::ibc::core::client::v1::MsgUpgradeClient {
+                    client_id: message.msg.client_id.to_string(),
+                    client_state: Some(decode_any(
+                        kind,
+                        "client_state",
+                        &message.msg.client_state,
+                    )?),
+                    consensus_state: Some(decode_any(
+                        kind,
+                        "consensus_state",
+                        &message.msg.consensus_state,
+                    )?),
+                    proof_upgrade_client: message.msg.proof_upgrade_client.into(),
+                    proof_upgrade_consensus_state: message.msg.proof_upgrade_consensus_state.into(),
+                    signer: relayer.to_string(),
+                })
+            }
+        },
+        IbcMessage::IbcUnion(msg) => match msg {
+            ibc_union_spec::Datagram::CreateClient(msg_create_client) => {
+                mk_any(&protos::cosmwasm::wasm::v1::MsgExecuteContract {
+                    sender: relayer.to_string(),
+                    contract: ibc_union_contract_address.to_string(),
+                    msg: execute_msg_json(
+                        kind,
+                        &union_ibc_msg::msg::ExecuteMsg::CreateClient(
+                            union_ibc_msg::msg::MsgCreateClient {
+                                client_type: msg_create_client.client_type.to_string(),
+                                client_state_bytes: msg_create_client.client_state_bytes,
+                                consensus_state_bytes: msg_create_client.consensus_state_bytes,
+                                relayer: relayer.to_string(),
                             },
-                        );
+                        ),
+                    )?,
+                    funds: vec![],
+                })
+            }
+            ibc_union_spec::Datagram::UpdateClient(msg_update_client) => {
+                mk_any(&protos::cosmwasm::wasm::v1::MsgExecuteContract {
+                    sender: relayer.to_string(),
+                    contract: ibc_union_contract_address.to_string(),
+                    msg: execute_msg_json(
+                        kind,
+                        &union_ibc_msg::msg::ExecuteMsg::UpdateClient(
+                            union_ibc_msg::msg::MsgUpdateClient {
+                                client_id: msg_update_client.client_id.get(),
+                                client_message: msg_update_client.client_message,
+                                relayer: relayer.to_string(),
+                            },
+                        ),
+                    )?,
+                    funds: vec![],
+                })
+            }
+            ibc_union_spec::Datagram::ConnectionOpenInit(msg_connection_open_init) => {
+                mk_any(&protos::cosmwasm::wasm::v1::MsgExecuteContract {
+                    sender: relayer.to_string(),
+                    contract: ibc_union_contract_address.to_string(),
+                    msg: execute_msg_json(
+                        kind,
+                        &union_ibc_msg::msg::ExecuteMsg::ConnectionOpenInit(
+                            union_ibc_msg::msg::MsgConnectionOpenInit {
+                                client_id: msg_connection_open_init.client_id.get(),
+                                counterparty_client_id: msg_connection_open_init
+                                    .counterparty_client_id
+                                    .get(),
+                                relayer: relayer.to_string(),
+                            },
+                        ),
+                    )?,
+                    funds: vec![],
+                })
+            }
+            ibc_union_spec::Datagram::ConnectionOpenTry(msg_connection_open_try) => {
+                mk_any(&protos::cosmwasm::wasm::v1::MsgExecuteContract {
+                    sender: relayer.to_string(),
+                    contract: ibc_union_contract_address.to_string(),
+                    msg: execute_msg_json(
+                        kind,
+                        &union_ibc_msg::msg::ExecuteMsg::ConnectionOpenTry(
+                            union_ibc_msg::msg::MsgConnectionOpenTry {
+                                counterparty_client_id: msg_connection_open_try
+                                    .counterparty_client_id
+                                    .get(),
+                                counterparty_connection_id: msg_connection_open_try
+                                    .counterparty_connection_id
+                                    .get(),
+                                client_id: msg_connection_open_try.client_id.get(),
+                                proof_init: msg_connection_open_try.proof_init,
+                                proof_height: msg_connection_open_try.proof_height,
+                                relayer: relayer.to_string(),
+                            },
+                        ),
+                    )?,
+                    funds: vec![],
+                })
+            }
+            ibc_union_spec::Datagram::ConnectionOpenAck(msg_connection_open_ack) => {
+                mk_any(&protos::cosmwasm::wasm::v1::MsgExecuteContract {
+                    sender: relayer.to_string(),
+                    contract: ibc_union_contract_address.to_string(),
+                    msg: execute_msg_json(
+                        kind,
+                        &union_ibc_msg::msg::ExecuteMsg::ConnectionOpenAck(
+                            union_ibc_msg::msg::MsgConnectionOpenAck {
+                                connection_id: msg_connection_open_ack.connection_id.get(),
+                                counterparty_connection_id: msg_connection_open_ack
+                                    .counterparty_connection_id
+                                    .get(),
+                                proof_try: msg_connection_open_ack.proof_try,
+                                proof_height: msg_connection_open_ack.proof_height,
+                                relayer: relayer.to_string(),
+                            },
+                        ),
+                    )?,
+                    funds: vec![],
+                })
+            }
+            ibc_union_spec::Datagram::ConnectionOpenConfirm(msg_connection_open_confirm) => {
+                mk_any(&protos::cosmwasm::wasm::v1::MsgExecuteContract {
+                    sender: relayer.to_string(),
+                    contract: ibc_union_contract_address.to_string(),
+                    msg: execute_msg_json(
+                        kind,
+                        &union_ibc_msg::msg::ExecuteMsg::ConnectionOpenConfirm(
+                            union_ibc_msg::msg::MsgConnectionOpenConfirm {
+                                connection_id: msg_connection_open_confirm.connection_id.get(),
+                                proof_ack: msg_connection_open_confirm.proof_ack,
+                                proof_height: msg_connection_open_confirm.proof_height,
+                                relayer: relayer.to_string(),
+                            },
+                        ),
+                    )?,
+                    funds: vec![],
+                })
+            }
+            ibc_union_spec::Datagram::ChannelOpenInit(msg_channel_open_init) => {
+                mk_any(&protos::cosmwasm::wasm::v1::MsgExecuteContract {
+                    sender: relayer.to_string(),
+                    contract: ibc_union_contract_address.to_string(),
+                    msg: execute_msg_json(
+                        kind,
+                        &union_ibc_msg::msg::ExecuteMsg::ChannelOpenInit(
+                            union_ibc_msg::msg::MsgChannelOpenInit {
+                                port_id: port_id_str(kind, &msg_channel_open_init.port_id)?,
+                                counterparty_port_id: msg_channel_open_init.counterparty_port_id,
+                                connection_id: msg_channel_open_init.connection_id.get(),
+                                version: msg_channel_open_init.version,
+                                relayer: relayer.to_string(),
+                            },
+                        ),
+                    )?,
+                    funds: vec![],
+                })
+            }
+            ibc_union_spec::Datagram::ChannelOpenTry(msg_channel_open_try) => {
+                debug!(?msg_channel_open_try, "encoding datagram");
+
+                let channel_open_try = union_ibc_msg::msg::ExecuteMsg::ChannelOpenTry(
+                    union_ibc_msg::msg::MsgChannelOpenTry {
+                        port_id: port_id_str(kind, &msg_channel_open_try.port_id)?,
+                        channel: msg_channel_open_try.channel,
+                        counterparty_version: msg_channel_open_try.counterparty_version,
+                        proof_init: msg_channel_open_try.proof_init,
+                        proof_height: msg_channel_open_try.proof_height,
+                        relayer: relayer.to_string(),
+                    },
+                );
 
-                        dbg!(&channel_open_try);
+                debug!(?channel_open_try, "encoded execute msg");
 
-                        mk_any(&protos::cosmwasm::wasm::v1::MsgExecuteContract {
-                            sender: signer.to_string(),
-                            contract: ibc_union_contract_address.to_string(),
-                            msg: serde_json::to_vec(&channel_open_try).unwrap(),
-                            funds: vec![],
-                        })
-                    }
-                    ibc_union_spec::Datagram::ChannelOpenAck(_msg_channel_open_ack) => todo!(),
-                    ibc_union_spec::Datagram::ChannelOpenConfirm(msg_channel_open_confirm) => {
-                        dbg!(&msg_channel_open_confirm);
-
-                        let channel_open_confirm =
-                            union_ibc_msg::msg::ExecuteMsg::ChannelOpenConfirm(
-                                union_ibc_msg::msg::MsgChannelOpenConfirm {
-                                    channel_id: msg_channel_open_confirm.channel_id,
-                                    proof_ack: msg_channel_open_confirm.proof_ack,
-                                    proof_height: msg_channel_open_confirm.proof_height,
-                                    relayer: signer.to_string(),
-                                },
-                            );
+                mk_any(&protos::cosmwasm::wasm::v1::MsgExecuteContract {
+                    sender: relayer.to_string(),
+                    contract: ibc_union_contract_address.to_string(),
+                    msg: execute_msg_json(kind, &channel_open_try)?,
+                    funds: vec![],
+                })
+            }
+            ibc_union_spec::Datagram::ChannelOpenAck(_) => {
+                return Err(ProcessMsgError::UnsupportedDatagram { kind })
+            }
+            ibc_union_spec::Datagram::ChannelOpenConfirm(msg_channel_open_confirm) => {
+                debug!(?msg_channel_open_confirm, "encoding datagram");
+
+                let channel_open_confirm = union_ibc_msg::msg::ExecuteMsg::ChannelOpenConfirm(
+                    union_ibc_msg::msg::MsgChannelOpenConfirm {
+                        channel_id: msg_channel_open_confirm.channel_id.get(),
+                        proof_ack: msg_channel_open_confirm.proof_ack,
+                        proof_height: msg_channel_open_confirm.proof_height,
+                        relayer: relayer.to_string(),
+                    },
+                );
 
-                        dbg!(&channel_open_confirm);
+                debug!(?channel_open_confirm, "encoded execute msg");
 
-                        mk_any(&protos::cosmwasm::wasm::v1::MsgExecuteContract {
-                            sender: signer.to_string(),
-                            contract: ibc_union_contract_address.to_string(),
-                            msg: serde_json::to_vec(&channel_open_confirm).unwrap(),
-                            funds: vec![],
-                        })
-                    }
-                    ibc_union_spec::Datagram::ChannelCloseInit(_msg_channel_close_init) => todo!(),
-                    ibc_union_spec::Datagram::ChannelCloseConfirm(_msg_channel_close_confirm) => {
-                        todo!()
-                    }
-                    ibc_union_spec::Datagram::PacketRecv(msg_packet_recv) => {
-                        dbg!(&msg_packet_recv);
-
-                        let packet_recv = union_ibc_msg::msg::ExecuteMsg::PacketRecv(
-                            union_ibc_msg::msg::MsgPacketRecv {
-                                packets: msg_packet_recv.packets,
-                                relayer_msgs: msg_packet_recv.relayer_msgs,
-                                proof: msg_packet_recv.proof,
-                                proof_height: msg_packet_recv.proof_height,
-                                relayer: signer.to_string(),
+                mk_any(&protos::cosmwasm::wasm::v1::MsgExecuteContract {
+                    sender: relayer.to_string(),
+                    contract: ibc_union_contract_address.to_string(),
+                    msg: execute_msg_json(kind, &channel_open_confirm)?,
+                    funds: vec![],
+                })
+            }
+            ibc_union_spec::Datagram::ChannelCloseInit(_) => {
+                return Err(ProcessMsgError::UnsupportedDatagram { kind })
+            }
+            ibc_union_spec::Datagram::ChannelCloseConfirm(_) => {
+                return Err(ProcessMsgError::UnsupportedDatagram { kind })
+            }
+            ibc_union_spec::Datagram::PacketRecv(msg_packet_recv) => {
+                debug!(?msg_packet_recv, "encoding datagram");
+
+                let packet_recv =
+                    union_ibc_msg::msg::ExecuteMsg::PacketRecv(union_ibc_msg::msg::MsgPacketRecv {
+                        packets: msg_packet_recv.packets,
+                        relayer_msgs: msg_packet_recv.relayer_msgs,
+                        proof: msg_packet_recv.proof,
+                        proof_height: msg_packet_recv.proof_height,
+                        relayer: relayer.to_string(),
+                    });
+
+                debug!(?packet_recv, "encoded execute msg");
+
+                mk_any(&protos::cosmwasm::wasm::v1::MsgExecuteContract {
+                    sender: relayer.to_string(),
+                    contract: ibc_union_contract_address.to_string(),
+                    msg: execute_msg_json(kind, &packet_recv)?,
+                    funds: vec![],
+                })
+            }
+            ibc_union_spec::Datagram::PacketAcknowledgement(msg_packet_acknowledgement) => {
+                debug!(?msg_packet_acknowledgement, "encoding datagram");
+
+                let packet_recv = union_ibc_msg::msg::ExecuteMsg::PacketAck(
+                    union_ibc_msg::msg::MsgPacketAcknowledgement {
+                        packets: msg_packet_acknowledgement.packets,
+                        acknowledgements: msg_packet_acknowledgement.acknowledgements,
+                        proof: msg_packet_acknowledgement.proof,
+                        proof_height: msg_packet_acknowledgement.proof_height,
+                        relayer: relayer.to_string(),
+                    },
+                );
+
+                debug!(?packet_recv, "encoded execute msg");
+
+                mk_any(&protos::cosmwasm::wasm::v1::MsgExecuteContract {
+                    sender: relayer.to_string(),
+                    contract: ibc_union_contract_address.to_string(),
+                    msg: execute_msg_json(kind, &packet_recv)?,
+                    funds: vec![],
+                })
+            }
+            ibc_union_spec::Datagram::PacketTimeout(msg_packet_timeout) => {
+                mk_any(&protos::cosmwasm::wasm::v1::MsgExecuteContract {
+                    sender: relayer.to_string(),
+                    contract: ibc_union_contract_address.to_string(),
+                    msg: execute_msg_json(
+                        kind,
+                        &union_ibc_msg::msg::ExecuteMsg::PacketTimeout(
+                            union_ibc_msg::msg::MsgPacketTimeout {
+                                packet: msg_packet_timeout.packet,
+                                proof: msg_packet_timeout.proof,
+                                proof_height: msg_packet_timeout.proof_height,
+                                relayer: relayer.to_string(),
                             },
-                        );
+                        ),
+                    )?,
+                    funds: vec![],
+                })
+            }
+            ibc_union_spec::Datagram::IntentPacketRecv(_) => {
+                return Err(ProcessMsgError::UnsupportedDatagram { kind })
+            }
+            ibc_union_spec::Datagram::BatchSend(_) => {
+                return Err(ProcessMsgError::UnsupportedDatagram { kind })
+            }
+            ibc_union_spec::Datagram::BatchAcks(_) => {
+                return Err(ProcessMsgError::UnsupportedDatagram { kind })
+            }
+        },
+    };
 
-                        dbg!(&packet_recv);
+    Ok(encoded)
+}
 
-                        mk_any(&protos::cosmwasm::wasm::v1::MsgExecuteContract {
-                            sender: signer.to_string(),
-                            contract: ibc_union_contract_address.to_string(),
-                            msg: serde_json::to_vec(&packet_recv).unwrap(),
-                            funds: vec![],
-                        })
-                    }
-                    ibc_union_spec::Datagram::PacketAcknowledgement(msg_packet_acknowledgement) => {
-                        dbg!(&msg_packet_acknowledgement);
-
-                        let packet_recv = union_ibc_msg::msg::ExecuteMsg::PacketAck(
-                            union_ibc_msg::msg::MsgPacketAcknowledgement {
-                                packets: msg_packet_acknowledgement.packets,
-                                acknowledgements: msg_packet_acknowledgement.acknowledgements,
-                                proof: msg_packet_acknowledgement.proof,
-                                proof_height: msg_packet_acknowledgement.proof_height,
-                                relayer: signer.to_string(),
+#[cfg(test)]
+mod tests {
+    use ibc_classic_spec::{MsgCreateClientData, MsgUpgradeClientData};
+    use ibc_union_spec::{MsgUpdateClient, UnionId};
+    use serde_json::json;
+    use unionlabs::{
+        ibc::core::client::{
+            height::Height, msg_create_client::MsgCreateClient,
+            msg_upgrade_client::MsgUpgradeClient,
+        },
+        id::ClientId,
+    };
+    use voyager_message::{assert_pass_snapshot, core::ClientType, suppression::SuppressedChannel};
+    use voyager_vm::noop;
+
+    use super::*;
+
+    fn datagram(chain_id: &str) -> Op<VoyagerMessage> {
+        data(WithChainId {
+            chain_id: ChainId::new(chain_id.to_owned()),
+            message: IbcDatagram::new::<IbcUnion>(ibc_union_spec::Datagram::UpdateClient(
+                MsgUpdateClient {
+                    client_id: UnionId::new(1).unwrap(),
+                    client_message: b"header".into(),
+                },
+            )),
+        })
+    }
+
+    #[test]
+    fn endpoint_network_mismatch() {
+        let chain_id = ChainId::new("union-1");
+
+        ensure_network(&chain_id, "ws://localhost:26657/websocket", "union-1").unwrap();
+
+        let err = ensure_network(
+            &chain_id,
+            "ws://localhost:26657/websocket",
+            "union-testnet-9",
+        )
+        .unwrap_err();
+
+        assert_eq!(err.url, "ws://localhost:26657/websocket");
+        assert_eq!(err.source.found, "union-testnet-9");
+
+        let err = ErrorReporter(err).to_string();
+        assert!(err.contains("`union-1`"), "{err}");
+        assert!(err.contains("`union-testnet-9`"), "{err}");
+    }
+
+    #[test]
+    fn chain_revision_override() {
+        assert_eq!(chain_revision("axelar-dojo-1", None), 1);
+        assert_eq!(chain_revision("union", None), 0);
+
+        // the override takes precedence over the parsed revision
+        assert_eq!(chain_revision("axelar-dojo-1", Some(0)), 0);
+        assert_eq!(chain_revision("union", Some(3)), 3);
+    }
+
+    #[test]
+    fn run_pass_passes_through_unexpected_ops() {
+        let chain_id = ChainId::new("union-devnet-1");
+        let pass_through_count = AtomicU64::new(0);
+
+        let foreign_datagram = datagram("stargaze-devnet-1");
+        let unrelated = data(PluginMessage::new("some-other-plugin", "unrelated"));
+
+        let PassResult {
+            optimize_further,
+            ready,
+        } = run_pass(
+            &chain_id,
+            &pass_through_count,
+            &SuppressionList::default(),
+            &[],
+            vec![
+                datagram("union-devnet-1"),
+                foreign_datagram.clone(),
+                unrelated.clone(),
+            ],
+        );
+
+        assert!(optimize_further.is_empty());
+        assert_eq!(
+            ready
+                .iter()
+                .map(|(idxs, _)| idxs.clone())
+                .collect::<Vec<_>>(),
+            vec![vec![0], vec![1], vec![2]]
+        );
+
+        assert_eq!(
+            ready[0].1,
+            call(PluginMessage::new(
+                plugin_name(&chain_id),
+                ModuleCall::SubmitTransaction(vec![IbcMessage::IbcUnion(
+                    ibc_union_spec::Datagram::UpdateClient(MsgUpdateClient {
+                        client_id: UnionId::new(1).unwrap(),
+                        client_message: b"header".into(),
+                    })
+                )]),
+            ))
+        );
+        assert_eq!(ready[1].1, foreign_datagram);
+        assert_eq!(ready[2].1, unrelated);
+
+        assert_eq!(pass_through_count.load(Ordering::Relaxed), 2);
+    }
+
+    fn update_client(client_id: u32) -> IbcDatagram {
+        IbcDatagram::new::<IbcUnion>(ibc_union_spec::Datagram::UpdateClient(MsgUpdateClient {
+            client_id: UnionId::new(client_id).unwrap(),
+            client_message: b"header".into(),
+        }))
+    }
+
+    fn undecodable() -> IbcDatagram {
+        IbcDatagram {
+            ibc_spec_id: IbcUnion::ID,
+            datagram: json!({ "not": "a datagram" }),
+        }
+    }
+
+    fn submit_update_clients(chain_id: &ChainId, client_ids: &[u32]) -> Op<VoyagerMessage> {
+        call(PluginMessage::new(
+            plugin_name(chain_id),
+            ModuleCall::SubmitTransaction(
+                client_ids
+                    .iter()
+                    .map(|client_id| {
+                        IbcMessage::IbcUnion(ibc_union_spec::Datagram::UpdateClient(
+                            MsgUpdateClient {
+                                client_id: UnionId::new(*client_id).unwrap(),
+                                client_message: b"header".into(),
                             },
-                        );
+                        ))
+                    })
+                    .collect(),
+            ),
+        ))
+    }
+
+    fn undecodable_data(chain_id: &ChainId, datagram: IbcDatagram) -> Op<VoyagerMessage> {
+        data(PluginMessage::new(
+            plugin_name(chain_id),
+            ModuleData::from(UndecodableDatagram {
+                chain_id: chain_id.clone(),
+                error: IbcMessage::from_raw_datagram(&datagram)
+                    .unwrap_err()
+                    .message()
+                    .to_owned(),
+                datagram,
+            }),
+        ))
+    }
 
-                        dbg!(&packet_recv);
+    #[tokio::test]
+    async fn run_pass_datagrams() {
+        let chain_id = ChainId::new("union-devnet-1");
+        let pass_through_count = AtomicU64::new(0);
+        let suppression = SuppressionList {
+            channels: vec![SuppressedChannel {
+                ibc_spec_id: IbcUnion::ID,
+                channel_id: 5,
+                reason: Some("abandoned".to_owned()),
+            }],
+            freeze_list: None,
+        };
 
-                        mk_any(&protos::cosmwasm::wasm::v1::MsgExecuteContract {
-                            sender: signer.to_string(),
-                            contract: ibc_union_contract_address.to_string(),
-                            msg: serde_json::to_vec(&packet_recv).unwrap(),
-                            funds: vec![],
-                        })
-                    }
-                    ibc_union_spec::Datagram::PacketTimeout(_msg_packet_timeout) => todo!(),
-                    ibc_union_spec::Datagram::IntentPacketRecv(_msg_intent_packet_recv) => todo!(),
-                    ibc_union_spec::Datagram::BatchSend(_msg_batch_send) => todo!(),
-                    ibc_union_spec::Datagram::BatchAcks(_msg_batch_acks) => todo!(),
+        assert_pass_snapshot!(
+            |ops| run_pass(&chain_id, &pass_through_count, &suppression, &[], ops),
+            "testdata/pass/datagrams.json"
+        );
+
+        assert_eq!(pass_through_count.load(Ordering::Relaxed), 3);
+    }
+
+    #[test]
+    fn run_pass_submits_decodable_datagrams_in_batch() {
+        let chain_id = ChainId::new("union-devnet-1");
+        let pass_through_count = AtomicU64::new(0);
+
+        let PassResult {
+            optimize_further,
+            ready,
+        } = run_pass(
+            &chain_id,
+            &pass_through_count,
+            &SuppressionList::default(),
+            &[],
+            vec![
+                datagram("union-devnet-1"),
+                data(WithChainId {
+                    chain_id: chain_id.clone(),
+                    message: vec![update_client(1), undecodable(), update_client(2)],
+                }),
+                data(WithChainId {
+                    chain_id: chain_id.clone(),
+                    message: vec![undecodable()],
+                }),
+            ],
+        );
+
+        assert!(optimize_further.is_empty());
+        assert_eq!(
+            ready,
+            vec![
+                (vec![0], submit_update_clients(&chain_id, &[1])),
+                (
+                    vec![1],
+                    conc([
+                        submit_update_clients(&chain_id, &[1, 2]),
+                        undecodable_data(&chain_id, undecodable()),
+                    ])
+                ),
+                (vec![2], conc([undecodable_data(&chain_id, undecodable())])),
+            ]
+        );
+
+        assert_eq!(pass_through_count.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn run_pass_emits_undecodable_datagram() {
+        let chain_id = ChainId::new("union-devnet-1");
+        let pass_through_count = AtomicU64::new(0);
+
+        let PassResult {
+            optimize_further,
+            ready,
+        } = run_pass(
+            &chain_id,
+            &pass_through_count,
+            &SuppressionList::default(),
+            &[],
+            vec![data(WithChainId {
+                chain_id: chain_id.clone(),
+                message: undecodable(),
+            })],
+        );
+
+        assert!(optimize_further.is_empty());
+        assert_eq!(
+            ready,
+            vec![(vec![0], conc([undecodable_data(&chain_id, undecodable())]))]
+        );
+    }
+
+    #[test]
+    fn run_pass_emits_suppressed_datagrams() {
+        let chain_id = ChainId::new("union-devnet-1");
+        let pass_through_count = AtomicU64::new(0);
+
+        let suppression = serde_json::from_value::<SuppressionList>(json!({
+            "channels": [{ "ibc_spec_id": "ibc-union", "channel_id": 2 }]
+        }))
+        .unwrap();
+
+        let recv = |destination_channel| {
+            IbcDatagram::new::<IbcUnion>(ibc_union_spec::Datagram::from(
+                ibc_union_spec::MsgPacketRecv {
+                    packets: vec![ibc_solidity::Packet {
+                        source_channel: 1,
+                        destination_channel,
+                        data: Default::default(),
+                        timeout_height: 0,
+                        timeout_timestamp: 100,
+                    }],
+                    relayer_msgs: vec![Default::default()],
+                    proof: Default::default(),
+                    proof_height: 1,
                 },
-            };
+            ))
+        };
 
-            (msg, encoded)
-        })
-        .collect()
+        let suppressed_data = |datagram| {
+            data(PluginMessage::new(
+                plugin_name(&chain_id),
+                ModuleData::from(SuppressedDatagram {
+                    chain_id: chain_id.clone(),
+                    channel_id: 2,
+                    datagram,
+                }),
+            ))
+        };
+
+        let PassResult {
+            optimize_further,
+            ready,
+        } = run_pass(
+            &chain_id,
+            &pass_through_count,
+            &suppression,
+            &[],
+            vec![
+                data(WithChainId {
+                    chain_id: chain_id.clone(),
+                    message: recv(2),
+                }),
+                data(WithChainId {
+                    chain_id: chain_id.clone(),
+                    message: vec![update_client(1), recv(2)],
+                }),
+                data(WithChainId {
+                    chain_id: chain_id.clone(),
+                    message: recv(3),
+                }),
+            ],
+        );
+
+        assert!(optimize_further.is_empty());
+        assert_eq!(ready[0], (vec![0], conc([suppressed_data(recv(2))])));
+        assert_eq!(
+            ready[1],
+            (
+                vec![1],
+                conc([
+                    submit_update_clients(&chain_id, &[1]),
+                    suppressed_data(recv(2)),
+                ])
+            )
+        );
+        assert!(matches!(ready[2].1, Op::Call(_)));
+
+        assert_eq!(pass_through_count.load(Ordering::Relaxed), 0);
+    }
+
+    fn classic_recv(destination_channel: u32, sequence: u64) -> ibc_classic_spec::Datagram {
+        ibc_classic_spec::Datagram::RecvPacket(
+            unionlabs::ibc::core::channel::msg_recv_packet::MsgRecvPacket {
+                packet: unionlabs::ibc::core::channel::packet::Packet {
+                    sequence: sequence.try_into().unwrap(),
+                    source_port: PortId::new("transfer".to_owned()).unwrap(),
+                    source_channel: ChannelId::new(9),
+                    destination_port: PortId::new("transfer".to_owned()).unwrap(),
+                    destination_channel: ChannelId::new(destination_channel),
+                    data: Default::default(),
+                    timeout_height: Default::default(),
+                    timeout_timestamp: 100,
+                },
+                proof_commitment: Default::default(),
+                proof_height: Default::default(),
+            },
+        )
+    }
+
+    fn submit_classic(
+        chain_id: &ChainId,
+        datagram: ibc_classic_spec::Datagram,
+    ) -> Op<VoyagerMessage> {
+        call(PluginMessage::new(
+            plugin_name(chain_id),
+            ModuleCall::SubmitTransaction(vec![IbcMessage::IbcV1(datagram)]),
+        ))
+    }
+
+    #[test]
+    fn run_pass_sequences_ordered_channels() {
+        let chain_id = ChainId::new("union-devnet-1");
+        let pass_through_count = AtomicU64::new(0);
+
+        let ordered_channels = [(
+            PortId::new("transfer".to_owned()).unwrap(),
+            ChannelId::new(1),
+        )];
+
+        let recv =
+            |channel, sequence| IbcDatagram::new::<IbcClassic>(classic_recv(channel, sequence));
+
+        let PassResult {
+            optimize_further,
+            ready,
+        } = run_pass(
+            &chain_id,
+            &pass_through_count,
+            &SuppressionList::default(),
+            &ordered_channels,
+            vec![
+                data(WithChainId {
+                    chain_id: chain_id.clone(),
+                    message: vec![recv(1, 3), recv(2, 1)],
+                }),
+                data(WithChainId {
+                    chain_id: chain_id.clone(),
+                    message: vec![recv(1, 1), update_client(1)],
+                }),
+                data(WithChainId {
+                    chain_id: chain_id.clone(),
+                    message: recv(2, 2),
+                }),
+                data(WithChainId {
+                    chain_id: chain_id.clone(),
+                    message: recv(1, 2),
+                }),
+            ],
+        );
+
+        assert!(optimize_further.is_empty());
+        assert_eq!(
+            ready,
+            vec![
+                // unordered channels are submitted independently
+                (vec![2], submit_classic(&chain_id, classic_recv(2, 2))),
+                (
+                    vec![0, 1, 3],
+                    conc([
+                        submit_classic(&chain_id, classic_recv(2, 1)),
+                        submit_update_clients(&chain_id, &[1]),
+                        seq([
+                            submit_classic(&chain_id, classic_recv(1, 1)),
+                            submit_classic(&chain_id, classic_recv(1, 2)),
+                            submit_classic(&chain_id, classic_recv(1, 3)),
+                        ]),
+                    ])
+                ),
+            ]
+        );
+
+        // without any ordered channels, every op is submitted independently
+        let PassResult { ready, .. } = run_pass(
+            &chain_id,
+            &pass_through_count,
+            &SuppressionList::default(),
+            &[],
+            vec![
+                data(WithChainId {
+                    chain_id: chain_id.clone(),
+                    message: recv(1, 2),
+                }),
+                data(WithChainId {
+                    chain_id: chain_id.clone(),
+                    message: recv(1, 1),
+                }),
+            ],
+        );
+
+        assert_eq!(
+            ready,
+            vec![
+                (vec![0], submit_classic(&chain_id, classic_recv(1, 2))),
+                (vec![1], submit_classic(&chain_id, classic_recv(1, 1))),
+            ]
+        );
+    }
+
+    #[test]
+    fn unencodable_message_is_dropped() {
+        let chain_id = ChainId::new("union-devnet-1");
+        let signer = CosmosSigner::new_from_bytes(H256::new([1; 32]), "union".to_owned()).unwrap();
+        let contract = Bech32::new("union".to_owned(), Bytes::from([2; 32]));
+
+        let update_client = |client_id| {
+            IbcMessage::IbcUnion(ibc_union_spec::Datagram::UpdateClient(MsgUpdateClient {
+                client_id: UnionId::new(client_id).unwrap(),
+                client_message: b"header".into(),
+            }))
+        };
+
+        // not a valid protobuf encoding
+        let corrupt = IbcMessage::IbcV1(ibc_classic_spec::Datagram::CreateClient(
+            MsgCreateClientData {
+                msg: MsgCreateClient {
+                    client_state: b"\xff\xff\xff".into(),
+                    consensus_state: b"".into(),
+                },
+                client_type: ClientType::new(ClientType::COMETBLS),
+            },
+        ));
+
+        let (encoded, failed) = split_processed(
+            &chain_id,
+            process_msgs(
+                vec![update_client(1), corrupt.clone(), update_client(2)],
+                &signer,
+                contract,
+            ),
+        );
+
+        assert_eq!(
+            encoded.into_iter().map(|(msg, _)| msg).collect::<Vec<_>>(),
+            vec![update_client(1), update_client(2)]
+        );
+
+        let [Op::Data(Data::Plugin(failed))] = &failed[..] else {
+            panic!("expected a single reported message: {failed:?}");
+        };
+
+        let ModuleData::UnencodableMessage(UnencodableMessage {
+            chain_id: failed_chain_id,
+            message,
+            error,
+        }) = failed
+            .clone()
+            .downcast::<ModuleData>(plugin_name(&chain_id))
+            .unwrap()
+        else {
+            panic!("expected an unencodable message: {failed:?}");
+        };
+
+        assert_eq!(failed_chain_id, chain_id);
+        assert_eq!(message, corrupt);
+        assert!(
+            error.starts_with(
+                "error decoding `client_state` of create_client as an `Any` (0xffffff)"
+            ),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn unsupported_datagram_is_dropped() {
+        let chain_id = ChainId::new("union-devnet-1");
+        let signer = CosmosSigner::new_from_bytes(H256::new([1; 32]), "union".to_owned()).unwrap();
+        let contract = Bech32::new("union".to_owned(), Bytes::from([2; 32]));
+
+        let close_init = IbcMessage::IbcUnion(ibc_union_spec::Datagram::ChannelCloseInit(
+            ibc_union_spec::MsgChannelCloseInit {},
+        ));
+
+        let (encoded, failed) = split_processed(
+            &chain_id,
+            process_msgs(vec![close_init.clone()], &signer, contract),
+        );
+
+        assert!(encoded.is_empty());
+
+        let [Op::Data(Data::Plugin(failed))] = &failed[..] else {
+            panic!("expected a single reported message: {failed:?}");
+        };
+
+        let ModuleData::UnencodableMessage(UnencodableMessage { message, error, .. }) = failed
+            .clone()
+            .downcast::<ModuleData>(plugin_name(&chain_id))
+            .unwrap()
+        else {
+            panic!("expected an unencodable message: {failed:?}");
+        };
+
+        assert_eq!(message, close_init);
+        assert_eq!(
+            error,
+            "channel_close_init is not supported by the ibc-union contract"
+        );
+    }
+
+    #[test]
+    fn packet_timeout_encoding() {
+        let signer = CosmosSigner::new_from_bytes(H256::new([1; 32]), "union".to_owned()).unwrap();
+        let contract = Bech32::new("union".to_owned(), Bytes::from([2; 32]));
+
+        let msg = IbcMessage::IbcUnion(ibc_union_spec::Datagram::PacketTimeout(
+            ibc_union_spec::MsgPacketTimeout {
+                packet: ibc_solidity::Packet {
+                    source_channel: 1,
+                    destination_channel: 2,
+                    data: b"\xde\xad\xbe\xef".to_vec().into(),
+                    timeout_height: 100,
+                    timeout_timestamp: 1_700_000_000_000_000_000,
+                },
+                proof: b"\x01\x02\x03".into(),
+                proof_height: 123,
+            },
+        ));
+
+        let [(_, encoded)] = process_msgs(vec![msg], &signer, contract.clone())
+            .try_into()
+            .unwrap();
+
+        assert_eq!(
+            encoded.type_url,
+            <protos::cosmwasm::wasm::v1::MsgExecuteContract as prost::Name>::type_url()
+        );
+
+        let execute_contract =
+            protos::cosmwasm::wasm::v1::MsgExecuteContract::decode(&*encoded.value).unwrap();
+
+        assert_eq!(execute_contract.sender, signer.to_string());
+        assert_eq!(execute_contract.contract, contract.to_string());
+        assert!(execute_contract.funds.is_empty());
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&execute_contract.msg).unwrap(),
+            json!({
+                "packet_timeout": {
+                    "packet": {
+                        "source_channel": 1,
+                        "destination_channel": 2,
+                        "data": "0xdeadbeef",
+                        "timeout_height": 100,
+                        "timeout_timestamp": 1_700_000_000_000_000_000_u64,
+                    },
+                    "proof": "0x010203",
+                    "proof_height": 123,
+                    "relayer": signer.to_string(),
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn upgrade_client_encoding() {
+        let signer = CosmosSigner::new_from_bytes(H256::new([1; 32]), "union".to_owned()).unwrap();
+        let contract = Bech32::new("union".to_owned(), Bytes::from([2; 32]));
+
+        let any = |type_url: &str, value: &[u8]| protos::google::protobuf::Any {
+            type_url: type_url.to_owned(),
+            value: value.to_vec(),
+        };
+
+        let client_state = any(
+            "/ibc.lightclients.tendermint.v1.ClientState",
+            b"client state",
+        );
+        let consensus_state = any(
+            "/ibc.lightclients.tendermint.v1.ConsensusState",
+            b"consensus state",
+        );
+
+        let msg = IbcMessage::IbcV1(ibc_classic_spec::Datagram::UpgradeClient(
+            MsgUpgradeClientData {
+                msg: MsgUpgradeClient {
+                    client_id: ClientId::new("07-tendermint", 1),
+                    client_state: client_state.encode_to_vec().into(),
+                    consensus_state: consensus_state.encode_to_vec().into(),
+                    proof_upgrade_client: b"\x01\x02".into(),
+                    proof_upgrade_consensus_state: b"\x03\x04".into(),
+                },
+                upgrade_height: Height::new_with_revision(1, 100),
+            },
+        ));
+
+        assert!(msg.is_priority());
+
+        let [(_, encoded)] = process_msgs(vec![msg], &signer, contract)
+            .try_into()
+            .unwrap();
+
+        assert_eq!(
+            encoded.type_url,
+            <protos::ibc::core::client::v1::MsgUpgradeClient as prost::Name>::type_url()
+        );
+        assert_eq!(
+            protos::ibc::core::client::v1::MsgUpgradeClient::decode(&*encoded.value).unwrap(),
+            protos::ibc::core::client::v1::MsgUpgradeClient {
+                client_id: "07-tendermint-1".to_owned(),
+                client_state: Some(client_state),
+                consensus_state: Some(consensus_state),
+                proof_upgrade_client: vec![1, 2],
+                proof_upgrade_consensus_state: vec![3, 4],
+                signer: signer.to_string(),
+            }
+        );
+    }
+
+    fn config(gas_multiplier: &str, ws_url: &str) -> Config {
+        serde_json::from_value(json!({
+            "chain_id": "union-devnet-1",
+            "ibc_union_contract_address": "union14hj2tavq8fpesdwxxcu44rty3hh90vhujrvcmstl4zr3txmfvw9s3e9fe2",
+            "keyring": {
+                "name": "union-devnet",
+                "keys": [
+                    {
+                        "type": "raw",
+                        "name": "alice",
+                        "key": "0xaa820fa947beb242032a41b6dc9a8b9c37d8f5fbcda0966b1ec80335b10a7d6f"
+                    }
+                ]
+            },
+            "gas_config": {
+                "gas_price": "1.0",
+                "gas_denom": "muno",
+                "gas_multiplier": gas_multiplier,
+                "max_gas": 10_000_000
+            },
+            "ws_url": ws_url,
+            "grpc_url": "http://localhost:9090"
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn reloaded_gas_multiplier_takes_effect() {
+        let live_config = LiveConfig::new(config("1.1", "ws://localhost:26657/websocket"));
+
+        // the module holds a clone of the live config, which must observe the reload
+        let module_config = live_config.clone();
+
+        assert_eq!(module_config.gas_config().mk_fee(1000).gas_limit, 1100);
+        assert_eq!(
+            module_config.memo(),
+            format!("Voyager {}", env!("CARGO_PKG_VERSION"))
+        );
+
+        let report = live_config.reload(config("1.5", "ws://localhost:26657/websocket"));
+
+        assert_eq!(
+            report,
+            ReloadReport {
+                changed: vec!["gas_config".to_owned()],
+                rejected: vec![],
+            }
+        );
+        assert_eq!(module_config.gas_config().mk_fee(1000).gas_limit, 1500);
+    }
+
+    #[test]
+    fn estimate_fee_matches_submitted_fee() {
+        use chain_utils::cosmos_sdk::GasMessageKind;
+
+        let gas_config = GasConfig {
+            gas_price: 0.5,
+            gas_denom: "muno".to_owned(),
+            gas_multiplier: 1.4,
+            max_gas: 10_000_000,
+            min_gas: 0,
+            per_message_multipliers: [(GasMessageKind::PacketRecv.to_string(), 2.0)]
+                .into_iter()
+                .collect(),
+            allow_low_multiplier: false,
+        };
+
+        assert_eq!(
+            tx_estimate(&gas_config, 400_000, gas_config.gas_multiplier),
+            TxEstimate {
+                gas: 400_000,
+                fee: 200_000,
+                denom: "muno".to_owned(),
+            }
+        );
+        assert_eq!(
+            tx_estimate(&gas_config, 400_000, gas_config.gas_multiplier).fee,
+            fee_amount(&gas_config.mk_fee(400_000), "muno")
+        );
+        assert_eq!(fee_amount(&gas_config.mk_fee(400_000), "uatom"), 0);
+
+        let gas_multiplier =
+            gas_config.multiplier_for([GasMessageKind::UpdateClient, GasMessageKind::PacketRecv]);
+        assert_eq!(gas_multiplier, 2.0);
+        assert_eq!(
+            tx_estimate(&gas_config, 400_000, gas_multiplier),
+            TxEstimate {
+                gas: 400_000,
+                fee: 400_000,
+                denom: "muno".to_owned(),
+            }
+        );
+    }
+
+    #[test]
+    fn low_gas_multiplier_is_rejected() {
+        assert!(config("0.9", "ws://localhost:26657/websocket")
+            .gas_config
+            .validate()
+            .is_err());
+        assert!(config("1.0", "ws://localhost:26657/websocket")
+            .gas_config
+            .validate()
+            .is_ok());
+    }
+
+    #[test]
+    fn reload_rejects_structural_settings() {
+        let live_config = LiveConfig::new(config("1.1", "ws://localhost:26657/websocket"));
+
+        let report = live_config.reload(config("1.1", "ws://localhost:36657/websocket"));
+
+        assert!(report.changed.is_empty());
+        assert_eq!(report.rejected.len(), 1);
+        assert_eq!(report.rejected[0].field, "ws_url");
+        assert_eq!(report.rejected[0].reason, "requires restart");
+
+        // rejected fields are reported again until the plugin is restarted
+        assert_eq!(
+            live_config.reload(config("1.1", "ws://localhost:36657/websocket")),
+            report
+        );
+    }
+
+    #[test]
+    fn op_type_includes_data_type() {
+        assert_eq!(
+            op_type(&datagram("union-devnet-1")),
+            "data/identified_ibc_datagram"
+        );
+        assert_eq!(op_type(&noop()), "noop");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn timed_out_broadcast_is_retryable() {
+        let err = BroadcastTxCommitError::from(
+            TimeoutConfig::default()
+                .with_timeout(CallKind::Query, std::future::pending::<()>())
+                .await
+                .unwrap_err(),
+        );
+
+        let classification = broadcast_error(&err);
+
+        assert!(!classification.is_fatal());
+        assert_eq!(
+            classification,
+            VoyagerError::retryable("query call timed out after 10s")
+        );
+    }
 }
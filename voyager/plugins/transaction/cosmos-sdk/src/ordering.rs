@@ -0,0 +1,242 @@
+//! In-order submission of packets on ordered channels.
+//!
+//! Datagrams are normally submitted as independent calls, which are executed
+//! concurrently and as such may be included in any order. On ordered channels
+//! (and for apps that are sensitive to the order of packets on unordered
+//! channels), a packet that is delivered before the packet preceding it is
+//! rejected on chain.
+//!
+//! The packet datagrams for the channels listed in
+//! [`ordered_channels`](crate::Config::ordered_channels) are instead pulled
+//! out of the batches they arrive in, sorted by sequence, and submitted one
+//! transaction at a time per channel. The next transaction is only submitted
+//! once the previous one has been included.
+//!
+//! Only ibc-classic packets are sequenced; the packet datagrams themselves
+//! don't contain the ordering of the channel, so it must be configured.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use ibc_classic_spec::{Datagram, IbcClassic};
+use unionlabs::id::{ChannelId, PortId};
+use voyager_message::data::IbcDatagram;
+
+/// Where a packet datagram falls in the FIFO of its channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PacketDirection {
+    /// The packet is received on this chain, the channel is the destination
+    /// channel.
+    Recv,
+    /// The packet was sent from this chain and is now acknowledged or timed
+    /// out, the channel is the source channel.
+    Sent,
+}
+
+/// The channel on this chain that a packet datagram is handled by, and the
+/// position of the packet in that channel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PacketPosition {
+    pub port_id: PortId,
+    pub channel_id: ChannelId,
+    pub direction: PacketDirection,
+    pub sequence: u64,
+}
+
+impl PacketPosition {
+    /// The position of `datagram`, if it is an ibc-classic packet datagram.
+    #[must_use]
+    pub fn of(datagram: &IbcDatagram) -> Option<Self> {
+        let (packet, direction) = match datagram.decode_datagram::<IbcClassic>()?.ok()? {
+            Datagram::RecvPacket(msg) => (msg.packet, PacketDirection::Recv),
+            Datagram::AcknowledgePacket(msg) => (msg.packet, PacketDirection::Sent),
+            Datagram::TimeoutPacket(msg) => (msg.packet, PacketDirection::Sent),
+            _ => return None,
+        };
+
+        let (port_id, channel_id) = match direction {
+            PacketDirection::Recv => (packet.destination_port, packet.destination_channel),
+            PacketDirection::Sent => (packet.source_port, packet.source_channel),
+        };
+
+        Some(Self {
+            port_id,
+            channel_id,
+            direction,
+            sequence: packet.sequence.get(),
+        })
+    }
+}
+
+/// The packet datagrams for ordered channels collected over a single pass.
+#[derive(Debug, Default)]
+pub struct OrderedPackets {
+    channels: BTreeMap<(PortId, ChannelId), Vec<(PacketDirection, u64, IbcDatagram)>>,
+    parents: BTreeSet<usize>,
+}
+
+impl OrderedPackets {
+    /// Take the packet datagrams for any of `ordered_channels` out of
+    /// `datagrams`, returning the remaining datagrams. `parent` is the index
+    /// of the op that the datagrams are from.
+    pub fn take(
+        &mut self,
+        ordered_channels: &[(PortId, ChannelId)],
+        parent: usize,
+        datagrams: Vec<IbcDatagram>,
+    ) -> Vec<IbcDatagram> {
+        if ordered_channels.is_empty() {
+            return datagrams;
+        }
+
+        datagrams
+            .into_iter()
+            .filter_map(|datagram| {
+                let Some(position) = PacketPosition::of(&datagram).filter(|position| {
+                    ordered_channels.iter().any(|(port_id, channel_id)| {
+                        *port_id == position.port_id && *channel_id == position.channel_id
+                    })
+                }) else {
+                    return Some(datagram);
+                };
+
+                self.parents.insert(parent);
+                self.channels
+                    .entry((position.port_id, position.channel_id))
+                    .or_default()
+                    .push((position.direction, position.sequence, datagram));
+
+                None
+            })
+            .collect()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.channels.is_empty()
+    }
+
+    /// The indexes of all ops that any of the collected datagrams are from.
+    #[must_use]
+    pub fn parents(&self) -> &BTreeSet<usize> {
+        &self.parents
+    }
+
+    /// The collected datagrams per channel, in the order they are to be
+    /// submitted in. Received packets come before acknowledged and timed out
+    /// packets, each ordered by sequence.
+    pub fn into_fifos(self) -> impl Iterator<Item = ((PortId, ChannelId), Vec<IbcDatagram>)> {
+        self.channels.into_iter().map(|(channel, mut packets)| {
+            // stable, such that duplicate datagrams keep the order they were received in
+            packets.sort_by_key(|(direction, sequence, _)| (*direction, *sequence));
+
+            (
+                channel,
+                packets
+                    .into_iter()
+                    .map(|(_, _, datagram)| datagram)
+                    .collect(),
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use unionlabs::ibc::core::channel::{
+        msg_acknowledgement::MsgAcknowledgement, msg_recv_packet::MsgRecvPacket, packet::Packet,
+    };
+
+    use super::*;
+
+    fn packet(sequence: u64) -> Packet {
+        Packet {
+            sequence: sequence.try_into().unwrap(),
+            source_port: PortId::new("transfer".to_owned()).unwrap(),
+            source_channel: ChannelId::new(1),
+            destination_port: PortId::new("transfer".to_owned()).unwrap(),
+            destination_channel: ChannelId::new(2),
+            data: Default::default(),
+            timeout_height: Default::default(),
+            timeout_timestamp: 100,
+        }
+    }
+
+    fn recv(sequence: u64) -> IbcDatagram {
+        IbcDatagram::new::<IbcClassic>(Datagram::RecvPacket(MsgRecvPacket {
+            packet: packet(sequence),
+            proof_commitment: Default::default(),
+            proof_height: Default::default(),
+        }))
+    }
+
+    fn ack(sequence: u64) -> IbcDatagram {
+        IbcDatagram::new::<IbcClassic>(Datagram::AcknowledgePacket(MsgAcknowledgement {
+            packet: packet(sequence),
+            acknowledgement: Default::default(),
+            proof_acked: Default::default(),
+            proof_height: Default::default(),
+        }))
+    }
+
+    fn channel(channel_id: u32) -> (PortId, ChannelId) {
+        (
+            PortId::new("transfer".to_owned()).unwrap(),
+            ChannelId::new(channel_id),
+        )
+    }
+
+    #[test]
+    fn position() {
+        assert_eq!(
+            PacketPosition::of(&recv(5)),
+            Some(PacketPosition {
+                port_id: PortId::new("transfer".to_owned()).unwrap(),
+                channel_id: ChannelId::new(2),
+                direction: PacketDirection::Recv,
+                sequence: 5,
+            })
+        );
+        assert_eq!(
+            PacketPosition::of(&ack(5)),
+            Some(PacketPosition {
+                port_id: PortId::new("transfer".to_owned()).unwrap(),
+                channel_id: ChannelId::new(1),
+                direction: PacketDirection::Sent,
+                sequence: 5,
+            })
+        );
+    }
+
+    #[test]
+    fn take_and_sort() {
+        let mut ordered = OrderedPackets::default();
+
+        // acks are handled by the source channel, receives by the destination channel
+        assert_eq!(
+            ordered.take(&[channel(2)], 0, vec![recv(3), ack(1), recv(1)]),
+            vec![ack(1)]
+        );
+        assert_eq!(ordered.take(&[channel(2)], 1, vec![ack(2)]), vec![ack(2)]);
+        assert_eq!(
+            ordered.take(&[channel(2)], 2, vec![recv(2)]),
+            Vec::<IbcDatagram>::new()
+        );
+
+        assert_eq!(ordered.parents(), &BTreeSet::from([0, 2]));
+        assert_eq!(
+            ordered.into_fifos().collect::<Vec<_>>(),
+            vec![(channel(2), vec![recv(1), recv(2), recv(3)])]
+        );
+    }
+
+    #[test]
+    fn no_ordered_channels() {
+        let mut ordered = OrderedPackets::default();
+
+        assert_eq!(
+            ordered.take(&[], 0, vec![recv(2), recv(1)]),
+            vec![recv(2), recv(1)]
+        );
+        assert!(ordered.is_empty());
+    }
+}
@@ -0,0 +1,384 @@
+//! Governance proposals for uploading 08-wasm light client code.
+//!
+//! On chains where `MsgStoreCode` is gated behind governance, new light client code has to be
+//! uploaded through a `MsgSubmitProposal` wrapping the `MsgStoreCode`, signed by the governance
+//! module account as the authority. The checksum of the code is printed alongside the proposal,
+//! since it is required to create clients with the code once the proposal has passed.
+
+use std::{io, path::PathBuf};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use protos::{
+    cosmos::base::v1beta1::Coin, google::protobuf::Any, ibc::lightclients::wasm::v1::MsgStoreCode,
+};
+use serde::Serialize;
+use serde_json::{json, Value};
+use sha2::Digest;
+use unionlabs::{
+    bounded::BoundedI64, google::protobuf::any::mk_any, hash::H256, parse_wasm_client_type,
+    WasmClientType, WasmClientTypeParseError,
+};
+use voyager_message::{cmd::CmdOutput, into_value};
+
+#[derive(Debug, Clone, clap::Args)]
+pub struct StoreCodeProposalArgs {
+    /// The light client code to upload.
+    pub wasm_path: PathBuf,
+    #[arg(long)]
+    pub title: String,
+    #[arg(long)]
+    pub summary: String,
+    /// The initial deposit of the proposal, i.e. `10000000muno`.
+    #[arg(long, value_parser = parse_coin)]
+    pub deposit: Coin,
+    /// Broadcast the proposal, instead of printing the unsigned transaction.
+    #[arg(long)]
+    pub submit: bool,
+    /// Upload the code even if it does not export a known wasm client type.
+    #[arg(long)]
+    pub force: bool,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum StoreCodeError {
+    #[error("unable to read wasm code from `{}`", path.display())]
+    Read {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    #[error("code does not export a wasm client type (pass --force to upload it anyway)")]
+    NotAClient,
+    #[error("code exports an unknown wasm client type (pass --force to upload it anyway)")]
+    UnknownClientType(#[source] WasmClientTypeParseError),
+    #[error("`gov_authority` must be configured to generate governance proposals")]
+    MissingAuthority,
+}
+
+/// `cosmos.gov.v1.MsgSubmitProposal`, which is not included in the generated protos.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct MsgSubmitProposal {
+    #[prost(message, repeated, tag = "1")]
+    pub messages: Vec<Any>,
+    #[prost(message, repeated, tag = "2")]
+    pub initial_deposit: Vec<Coin>,
+    #[prost(string, tag = "3")]
+    pub proposer: String,
+    #[prost(string, tag = "4")]
+    pub metadata: String,
+    #[prost(string, tag = "5")]
+    pub title: String,
+    #[prost(string, tag = "6")]
+    pub summary: String,
+    #[prost(bool, tag = "7")]
+    pub expedited: bool,
+}
+
+impl ::prost::Name for MsgSubmitProposal {
+    const NAME: &'static str = "MsgSubmitProposal";
+    const PACKAGE: &'static str = "cosmos.gov.v1";
+    fn full_name() -> String {
+        format!("cosmos.gov.v1.{}", Self::NAME)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoreCodeProposal {
+    /// The governance module account.
+    pub authority: String,
+    pub proposer: String,
+    pub code: Vec<u8>,
+    pub title: String,
+    pub summary: String,
+    pub deposit: Coin,
+}
+
+impl StoreCodeProposal {
+    /// The checksum that the code will be stored under.
+    pub fn checksum(&self) -> H256 {
+        checksum(&self.code)
+    }
+
+    pub fn msg(&self) -> MsgSubmitProposal {
+        MsgSubmitProposal {
+            messages: vec![mk_any(&MsgStoreCode {
+                signer: self.authority.clone(),
+                wasm_byte_code: self.code.clone(),
+            })],
+            initial_deposit: vec![self.deposit.clone()],
+            proposer: self.proposer.clone(),
+            metadata: String::new(),
+            title: self.title.clone(),
+            summary: self.summary.clone(),
+            expedited: false,
+        }
+    }
+
+    /// The unsigned transaction containing the proposal, in the json format used by the cosmos-sdk
+    /// cli (i.e. `tx sign`).
+    pub fn unsigned_tx_json(&self, memo: &str) -> Value {
+        json!({
+            "body": {
+                "messages": [{
+                    "@type": "/cosmos.gov.v1.MsgSubmitProposal",
+                    "messages": [{
+                        "@type": "/ibc.lightclients.wasm.v1.MsgStoreCode",
+                        "signer": self.authority,
+                        "wasm_byte_code": STANDARD.encode(&self.code),
+                    }],
+                    "initial_deposit": [{
+                        "denom": self.deposit.denom,
+                        "amount": self.deposit.amount,
+                    }],
+                    "proposer": self.proposer,
+                    "metadata": "",
+                    "title": self.title,
+                    "summary": self.summary,
+                    "expedited": false,
+                }],
+                "memo": memo,
+                "timeout_height": "0",
+                "extension_options": [],
+                "non_critical_extension_options": [],
+            },
+            "auth_info": {
+                "signer_infos": [],
+                "fee": {
+                    "amount": [],
+                    "gas_limit": "0",
+                    "payer": "",
+                    "granter": "",
+                },
+                "tip": null,
+            },
+            "signatures": [],
+        })
+    }
+}
+
+/// The checksum of 08-wasm code, as used by `08-wasm` to identify stored code.
+/// The output of [`Cmd::GenerateStoreCodeProposal`](crate::Cmd::GenerateStoreCodeProposal).
+#[derive(Debug, Clone, Serialize)]
+pub struct StoreCodeProposalOutput {
+    pub checksum: H256,
+    pub client_type: Option<WasmClientType>,
+    #[serde(flatten)]
+    pub proposal: ProposalOutput,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum ProposalOutput {
+    /// The proposal was submitted by the proposer.
+    Submitted {
+        tx_hash: H256,
+        gas_used: BoundedI64<0, { i64::MAX }>,
+    },
+    /// The unsigned proposal transaction, to be signed and broadcast out of band.
+    Unsigned { tx: Value },
+}
+
+impl CmdOutput for StoreCodeProposalOutput {
+    fn to_json(&self) -> Value {
+        into_value(self)
+    }
+
+    fn to_text(&self) -> String {
+        serde_json::to_string_pretty(self).expect("serialization is infallible; qed;")
+    }
+}
+
+pub fn checksum(code: &[u8]) -> H256 {
+    sha2::Sha256::new().chain_update(code).finalize().into()
+}
+
+/// Ensure that `code` is a light client supported by voyager, unless `force` is set.
+pub fn check_client_type(
+    code: &[u8],
+    force: bool,
+) -> Result<Option<WasmClientType>, StoreCodeError> {
+    match parse_wasm_client_type(code) {
+        Ok(Some(client_type)) => Ok(Some(client_type)),
+        _ if force => Ok(None),
+        Ok(None) => Err(StoreCodeError::NotAClient),
+        Err(err) => Err(StoreCodeError::UnknownClientType(err)),
+    }
+}
+
+/// Parse a coin in the format `<amount><denom>`, i.e. `10000000muno`.
+pub fn parse_coin(s: &str) -> Result<Coin, String> {
+    let denom_start = s
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(|| format!("coin `{s}` has no denom"))?;
+
+    let (amount, denom) = s.split_at(denom_start);
+
+    if amount.is_empty() {
+        return Err(format!("coin `{s}` has no amount"));
+    }
+
+    Ok(Coin {
+        denom: denom.to_owned(),
+        amount: amount.to_owned(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use hex_literal::hex;
+    use prost::Message;
+
+    use super::*;
+
+    const COMETBLS_CLIENT: &[u8] = include_bytes!("../testdata/cometbls-client.wasm");
+    const UNKNOWN_CLIENT: &[u8] = include_bytes!("../testdata/unknown-client.wasm");
+    const NOT_A_CLIENT: &[u8] = include_bytes!("../testdata/not-a-client.wasm");
+
+    fn proposal() -> StoreCodeProposal {
+        StoreCodeProposal {
+            authority: "union10d07y265gmmuvt4z0w9aw880jnsr700js0pl8h".to_owned(),
+            proposer: "union1jk9psyhvgkrt2cumz8eytll2244m2nnz4yt2g2".to_owned(),
+            code: COMETBLS_CLIENT.to_vec(),
+            title: "Upload cometbls client".to_owned(),
+            summary: "Upload the cometbls light client".to_owned(),
+            deposit: parse_coin("10000000muno").unwrap(),
+        }
+    }
+
+    #[test]
+    fn checksum_is_sha256_of_code() {
+        assert_eq!(
+            checksum(COMETBLS_CLIENT),
+            H256::new(hex!(
+                "c510ec43bbb765b4f3316e61472ff95e2ca29f3cca3ab606be6add8db916bf31"
+            ))
+        );
+        assert_eq!(proposal().checksum(), checksum(COMETBLS_CLIENT));
+    }
+
+    #[test]
+    fn msg_wraps_store_code() {
+        let proposal = proposal();
+
+        let msg = mk_any(&proposal.msg());
+        assert_eq!(msg.type_url, "/cosmos.gov.v1.MsgSubmitProposal");
+
+        let msg = MsgSubmitProposal::decode(&*msg.value).unwrap();
+        assert_eq!(msg.proposer, proposal.proposer);
+        assert_eq!(msg.title, proposal.title);
+        assert_eq!(msg.summary, proposal.summary);
+        assert_eq!(
+            msg.initial_deposit,
+            [Coin {
+                denom: "muno".to_owned(),
+                amount: "10000000".to_owned(),
+            }]
+        );
+
+        let [store_code] = &*msg.messages else {
+            panic!("expected exactly one message, found {:?}", msg.messages);
+        };
+        assert_eq!(
+            store_code.type_url,
+            "/ibc.lightclients.wasm.v1.MsgStoreCode"
+        );
+
+        let store_code = MsgStoreCode::decode(&*store_code.value).unwrap();
+        assert_eq!(store_code.signer, proposal.authority);
+        assert_eq!(store_code.wasm_byte_code, COMETBLS_CLIENT);
+    }
+
+    #[test]
+    fn unsigned_tx_json() {
+        let tx = proposal().unsigned_tx_json("memo");
+
+        let msg = &tx["body"]["messages"][0];
+        assert_eq!(msg["@type"], "/cosmos.gov.v1.MsgSubmitProposal");
+        assert_eq!(
+            msg["messages"][0]["wasm_byte_code"],
+            STANDARD.encode(COMETBLS_CLIENT)
+        );
+        assert_eq!(
+            msg["messages"][0]["signer"],
+            "union10d07y265gmmuvt4z0w9aw880jnsr700js0pl8h"
+        );
+        assert_eq!(tx["body"]["memo"], "memo");
+        assert_eq!(tx["signatures"], json!([]));
+    }
+
+    #[test]
+    fn proposal_output() {
+        let tx = proposal().unsigned_tx_json("memo");
+
+        assert_eq!(
+            StoreCodeProposalOutput {
+                checksum: proposal().checksum(),
+                client_type: Some(WasmClientType::Cometbls),
+                proposal: ProposalOutput::Unsigned { tx: tx.clone() },
+            }
+            .to_json(),
+            json!({
+                "checksum": "0xc510ec43bbb765b4f3316e61472ff95e2ca29f3cca3ab606be6add8db916bf31",
+                "client_type": "cometbls",
+                "tx": tx,
+            })
+        );
+
+        assert_eq!(
+            StoreCodeProposalOutput {
+                checksum: proposal().checksum(),
+                client_type: None,
+                proposal: ProposalOutput::Submitted {
+                    tx_hash: H256::new([0xab; 32]),
+                    gas_used: BoundedI64::new(150_000).unwrap(),
+                },
+            }
+            .to_json(),
+            json!({
+                "checksum": "0xc510ec43bbb765b4f3316e61472ff95e2ca29f3cca3ab606be6add8db916bf31",
+                "client_type": null,
+                "tx_hash": "0xabababababababababababababababababababababababababababababababab",
+                "gas_used": 150_000,
+            })
+        );
+    }
+
+    #[test]
+    fn client_type_gate() {
+        assert_eq!(
+            check_client_type(COMETBLS_CLIENT, false).unwrap(),
+            Some(WasmClientType::Cometbls)
+        );
+
+        assert!(matches!(
+            check_client_type(UNKNOWN_CLIENT, false),
+            Err(StoreCodeError::UnknownClientType(_))
+        ));
+        assert!(matches!(
+            check_client_type(NOT_A_CLIENT, false),
+            Err(StoreCodeError::NotAClient)
+        ));
+
+        assert_eq!(check_client_type(UNKNOWN_CLIENT, true).unwrap(), None);
+        assert_eq!(check_client_type(NOT_A_CLIENT, true).unwrap(), None);
+    }
+
+    #[test]
+    fn coin() {
+        assert_eq!(
+            parse_coin("100muno").unwrap(),
+            Coin {
+                denom: "muno".to_owned(),
+                amount: "100".to_owned(),
+            }
+        );
+        assert_eq!(
+            parse_coin("100ibc/ABCD").unwrap().denom,
+            "ibc/ABCD".to_owned()
+        );
+
+        parse_coin("muno").unwrap_err();
+        parse_coin("100").unwrap_err();
+        parse_coin("").unwrap_err();
+    }
+}
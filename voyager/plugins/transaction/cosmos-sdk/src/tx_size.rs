@@ -0,0 +1,239 @@
+//! Size-aware batching of transaction messages.
+//!
+//! CometBFT rejects transactions larger than the mempool's `max_tx_bytes`
+//! (1 MiB by default) with `tx too large`, and blocks are additionally
+//! limited to the `block.max_bytes` consensus param. Packet datagrams with
+//! large payloads can exceed this limit once they are batched with their
+//! client update, in which case the whole batch would be rejected on every
+//! retry.
+//!
+//! Before submission, the messages of a batch are split such that the
+//! transaction stays within the limit. The messages are packed in order, and
+//! the first message that doesn't fit (along with every message after it) is
+//! deferred to a later transaction, such that a client update is always
+//! included before the datagrams that depend on it. A message that exceeds
+//! the limit on its own can never be submitted, and is reported as such.
+//!
+//! The mempool `max_tx_bytes` is local to each node and can't be queried
+//! over rpc, so the limit defaults to the smaller of the mempool default and
+//! the chain's `block.max_bytes`, and can be overridden with
+//! [`max_tx_bytes`](crate::Config::max_tx_bytes).
+
+use prost::Message;
+
+/// The default `max_tx_bytes` of the CometBFT mempool.
+pub const DEFAULT_MEMPOOL_MAX_TX_BYTES: u64 = 1_048_576;
+
+/// The bytes reserved for the parts of a transaction other than its messages
+/// and memo (the fee, signer info, signature, and the framing of the tx
+/// body).
+pub const TX_ENVELOPE_BYTES: u64 = 512;
+
+/// The default transaction size limit for a chain with the provided
+/// `block.max_bytes` consensus param.
+///
+/// A `max_bytes` of `-1` (or any other non-positive value) means that blocks
+/// are limited only by CometBFT itself, in which case the mempool default
+/// applies.
+#[must_use]
+pub fn default_max_tx_bytes(block_max_bytes: i64) -> u64 {
+    match u64::try_from(block_max_bytes) {
+        Ok(max_bytes) if max_bytes > 0 => max_bytes.min(DEFAULT_MEMPOOL_MAX_TX_BYTES),
+        _ => DEFAULT_MEMPOOL_MAX_TX_BYTES,
+    }
+}
+
+/// The bytes available for the messages of a transaction with the provided
+/// `memo`, given the total transaction size limit `max_tx_bytes`.
+#[must_use]
+pub fn message_budget(max_tx_bytes: u64, memo: &str) -> u64 {
+    max_tx_bytes.saturating_sub(TX_ENVELOPE_BYTES + memo.len() as u64)
+}
+
+/// The size of `msg` once encoded into the `messages` field of a `TxBody`,
+/// including the field tag and length prefix.
+#[must_use]
+pub fn message_size(msg: &protos::google::protobuf::Any) -> u64 {
+    let len = msg.encoded_len();
+
+    (1 + prost::encoding::encoded_len_varint(len as u64) + len) as u64
+}
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+#[error(
+    "message {type_url} is {size} bytes, which exceeds the transaction size limit of {limit} \
+    bytes; it can never be submitted"
+)]
+pub struct TxTooLargeError {
+    pub type_url: String,
+    pub size: u64,
+    pub limit: u64,
+}
+
+/// The result of [`split_by_size`].
+#[derive(Debug)]
+pub struct SizeSplit<T> {
+    /// The messages to submit in this transaction.
+    pub submit: Vec<(T, protos::google::protobuf::Any)>,
+    /// The messages that didn't fit into this transaction, in their original
+    /// order. These should be submitted in a subsequent transaction, after
+    /// this one has been included.
+    pub remaining: Vec<T>,
+    /// The messages that exceed `limit` on their own.
+    pub too_large: Vec<(T, TxTooLargeError)>,
+}
+
+/// Split `msgs` such that the encoded size of the messages in
+/// [`SizeSplit::submit`] doesn't exceed `limit`. See the [module
+/// docs](self) for how messages are assigned.
+pub fn split_by_size<T>(msgs: Vec<(T, protos::google::protobuf::Any)>, limit: u64) -> SizeSplit<T> {
+    let mut split = SizeSplit {
+        submit: vec![],
+        remaining: vec![],
+        too_large: vec![],
+    };
+
+    let mut total = 0;
+
+    for (msg, any) in msgs {
+        let size = message_size(&any);
+
+        if size > limit {
+            split.too_large.push((
+                msg,
+                TxTooLargeError {
+                    type_url: any.type_url,
+                    size,
+                    limit,
+                },
+            ));
+        } else if split.remaining.is_empty() && total + size <= limit {
+            total += size;
+            split.submit.push((msg, any));
+        } else {
+            split.remaining.push(msg);
+        }
+    }
+
+    split
+}
+
+#[cfg(test)]
+mod tests {
+    use protos::{cosmos::tx::v1beta1::TxBody, google::protobuf::Any};
+
+    use super::*;
+
+    const LIMIT: u64 = 1_000;
+
+    fn msg(type_url: &str, value_len: usize) -> Any {
+        Any {
+            type_url: type_url.to_owned(),
+            value: vec![0; value_len].into(),
+        }
+    }
+
+    #[test]
+    fn message_size_matches_tx_body() {
+        let msgs = vec![msg("/update", 10), msg("/recv", 300), msg("/recv", 100_000)];
+
+        assert_eq!(
+            msgs.iter().map(message_size).sum::<u64>(),
+            TxBody {
+                messages: msgs,
+                ..Default::default()
+            }
+            .encoded_len() as u64
+        );
+    }
+
+    #[test]
+    fn default_limit() {
+        assert_eq!(default_max_tx_bytes(-1), DEFAULT_MEMPOOL_MAX_TX_BYTES);
+        assert_eq!(default_max_tx_bytes(0), DEFAULT_MEMPOOL_MAX_TX_BYTES);
+        assert_eq!(
+            default_max_tx_bytes(22_020_096),
+            DEFAULT_MEMPOOL_MAX_TX_BYTES
+        );
+        assert_eq!(default_max_tx_bytes(500_000), 500_000);
+    }
+
+    #[test]
+    fn fits() {
+        let split = split_by_size(
+            vec![
+                (0, msg("/update", 200)),
+                (1, msg("/recv", 200)),
+                (2, msg("/recv", 200)),
+            ],
+            LIMIT,
+        );
+
+        assert_eq!(
+            split.submit.iter().map(|(i, _)| *i).collect::<Vec<_>>(),
+            [0, 1, 2]
+        );
+        assert!(split.remaining.is_empty());
+        assert!(split.too_large.is_empty());
+    }
+
+    #[test]
+    fn split_needed() {
+        // the update and the large packet don't fit together, so the update is submitted on its
+        // own and the packet (and everything after it) is deferred to the next transaction
+        let split = split_by_size(
+            vec![
+                (0, msg("/update", 200)),
+                (1, msg("/recv", 900)),
+                (2, msg("/recv", 100)),
+            ],
+            LIMIT,
+        );
+
+        assert_eq!(
+            split.submit.iter().map(|(i, _)| *i).collect::<Vec<_>>(),
+            [0]
+        );
+        assert_eq!(split.remaining, [1, 2]);
+        assert!(split.too_large.is_empty());
+
+        // the deferred packet then fits on its own
+        let split = split_by_size(vec![(1, msg("/recv", 900)), (2, msg("/recv", 100))], LIMIT);
+
+        assert_eq!(
+            split.submit.iter().map(|(i, _)| *i).collect::<Vec<_>>(),
+            [1]
+        );
+        assert_eq!(split.remaining, [2]);
+    }
+
+    #[test]
+    fn impossible() {
+        let split = split_by_size(
+            vec![(0, msg("/update", 200)), (1, msg("/recv", 2_000))],
+            LIMIT,
+        );
+
+        assert_eq!(
+            split.submit.iter().map(|(i, _)| *i).collect::<Vec<_>>(),
+            [0]
+        );
+        assert!(split.remaining.is_empty());
+        assert_eq!(
+            split.too_large,
+            [(
+                1,
+                TxTooLargeError {
+                    type_url: "/recv".to_owned(),
+                    size: 2_013,
+                    limit: LIMIT,
+                }
+            )]
+        );
+        assert_eq!(
+            split.too_large[0].1.to_string(),
+            "message /recv is 2013 bytes, which exceeds the transaction size limit of 1000 bytes; \
+            it can never be submitted"
+        );
+    }
+}
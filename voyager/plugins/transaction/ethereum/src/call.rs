@@ -1,8 +1,10 @@
 use enumorph::Enumorph;
 use macros::model;
+use voyager_message::balance_monitor::CheckBalances;
 
 #[model]
 #[derive(Enumorph)]
 pub enum ModuleCall {
     SubmitMulticall(Vec<ibc_union_spec::Datagram>),
+    CheckBalances(CheckBalances),
 }
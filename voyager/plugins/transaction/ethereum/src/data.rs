@@ -1,6 +1,10 @@
 use enumorph::Enumorph;
 use macros::model;
+use voyager_message::{balance_monitor::SignerBalanceLow, suppression::SuppressedDatagram};
 
 #[model]
 #[derive(Enumorph)]
-pub enum ModuleData {}
+pub enum ModuleData {
+    SuppressedDatagram(SuppressedDatagram),
+    SignerBalanceLow(SignerBalanceLow),
+}
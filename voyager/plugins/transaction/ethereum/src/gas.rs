@@ -0,0 +1,318 @@
+//! Attribution of the gas used by a multicall to the messages in it.
+//!
+//! The receipt of a multicall only contains the gas used by the batch as a whole. If `trace_gas`
+//! is enabled, the transaction is traced with the `callTracer` (`debug_traceTransaction`) and the
+//! gas used by each call from the multicall to the IBC handler is read from the trace. Not all
+//! providers support tracing, so if it is disabled or fails, the total gas used is instead split
+//! between the messages proportionally to the size of their calldata, and the attribution is
+//! marked as estimated.
+
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+};
+
+use alloy::transports::TransportError;
+use serde::Deserialize;
+use unionlabs::hash::H160;
+
+/// A call frame as returned by the `callTracer`.
+///
+/// Only the fields required for gas attribution are parsed.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallFrame {
+    #[serde(rename = "type")]
+    pub ty: String,
+    pub from: H160,
+    #[serde(default)]
+    pub to: Option<H160>,
+    #[serde(with = "::serde_utils::u64_hex")]
+    pub gas_used: u64,
+    /// Set if this call reverted, in which case any state changes made by it and its sub-calls
+    /// were discarded (although the gas was still used).
+    #[serde(default)]
+    pub error: Option<String>,
+    #[serde(default)]
+    pub calls: Vec<CallFrame>,
+}
+
+/// The gas used by a single call from the multicall, read from the trace.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TracedCall {
+    pub gas_used: u64,
+    pub reverted: bool,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum TraceError {
+    #[error("error tracing transaction")]
+    Transport(#[from] TransportError),
+    #[error(transparent)]
+    TimedOut(#[from] chain_utils::timeout::TimedOut),
+    #[error("the traced transaction was sent to {found:?}, not the multicall contract {expected}")]
+    UnexpectedRoot { expected: H160, found: Option<H160> },
+    #[error("expected {expected} calls to the IBC handler in the trace, found {found}")]
+    CallCountMismatch { expected: usize, found: usize },
+}
+
+/// Read the gas used by each of the `expected_calls` calls from the multicall to `ibc_handler`
+/// out of the trace of the multicall transaction, in the order they were made (i.e. the order of
+/// the messages in the batch).
+///
+/// Only the direct sub-calls of the multicall are considered; the gas used by any calls made by
+/// the IBC handler (to light clients or apps) is included in the gas used by the message. A
+/// reverted sub-call (i.e. a message that failed) is still attributed the gas it used, as it is
+/// paid for regardless.
+pub fn traced_calls(
+    root: &CallFrame,
+    multicall: H160,
+    ibc_handler: H160,
+    expected_calls: usize,
+) -> Result<Vec<TracedCall>, TraceError> {
+    if root.to != Some(multicall) {
+        return Err(TraceError::UnexpectedRoot {
+            expected: multicall,
+            found: root.to,
+        });
+    }
+
+    let calls = root
+        .calls
+        .iter()
+        .filter(|frame| frame.to == Some(ibc_handler))
+        .map(|frame| TracedCall {
+            gas_used: frame.gas_used,
+            reverted: frame.error.is_some(),
+        })
+        .collect::<Vec<_>>();
+
+    if calls.len() != expected_calls {
+        return Err(TraceError::CallCountMismatch {
+            expected: expected_calls,
+            found: calls.len(),
+        });
+    }
+
+    Ok(calls)
+}
+
+/// Split `total_gas` between the calls proportionally to the length of their calldata.
+///
+/// The shares are rounded with the largest remainder method (ties going to the earlier call), such
+/// that they always sum up to exactly `total_gas`. If all of the calldata is empty, the gas is split
+/// evenly.
+#[must_use]
+pub fn estimate_call_gas(total_gas: u64, calldata_lens: &[usize]) -> Vec<u64> {
+    let weights = if calldata_lens.iter().all(|len| *len == 0) {
+        vec![1; calldata_lens.len()]
+    } else {
+        calldata_lens.iter().map(|len| *len as u128).collect()
+    };
+
+    let total_weight = weights.iter().sum::<u128>();
+
+    if total_weight == 0 {
+        return vec![];
+    }
+
+    let mut shares = weights
+        .iter()
+        .map(|weight| {
+            let share = u128::from(total_gas) * weight;
+            (share / total_weight, share % total_weight)
+        })
+        .collect::<Vec<_>>();
+
+    let remaining = u128::from(total_gas) - shares.iter().map(|(share, _)| share).sum::<u128>();
+
+    let mut by_remainder = (0..shares.len()).collect::<Vec<_>>();
+    by_remainder.sort_by(|a, b| shares[*b].1.cmp(&shares[*a].1).then(a.cmp(b)));
+
+    // remaining is strictly less than the amount of shares
+    for idx in by_remainder.into_iter().take(remaining as usize) {
+        shares[idx].0 += 1;
+    }
+
+    shares
+        .into_iter()
+        .map(|(share, _)| {
+            u64::try_from(share).expect("a share is never greater than the total; qed;")
+        })
+        .collect()
+}
+
+/// The gas used by each message in a multicall.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GasAttribution {
+    Traced(Vec<TracedCall>),
+    /// Estimated with [`estimate_call_gas`].
+    Estimated(Vec<u64>),
+}
+
+impl GasAttribution {
+    #[must_use]
+    pub fn is_estimated(&self) -> bool {
+        matches!(self, Self::Estimated(_))
+    }
+
+    #[must_use]
+    pub fn gas_used(&self, idx: usize) -> Option<u64> {
+        match self {
+            Self::Traced(calls) => calls.get(idx).map(|call| call.gas_used),
+            Self::Estimated(gas) => gas.get(idx).copied(),
+        }
+    }
+}
+
+/// The gas attributed to a message type since startup.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MessageGasTotals {
+    pub count: u64,
+    pub gas_used: u64,
+    /// The part of `gas_used` that was [estimated](GasAttribution::Estimated).
+    pub estimated_gas_used: u64,
+}
+
+/// Running totals of the gas attributed to each message type.
+#[derive(Debug, Clone, Default)]
+pub struct GasAccounting {
+    totals: Arc<Mutex<BTreeMap<&'static str, MessageGasTotals>>>,
+}
+
+impl GasAccounting {
+    /// Add `gas_used` to the totals of `msg`, returning the updated totals.
+    pub fn record(&self, msg: &'static str, gas_used: u64, estimated: bool) -> MessageGasTotals {
+        let mut totals = self.totals.lock().expect("lock is not poisoned; qed;");
+
+        let totals = totals.entry(msg).or_default();
+        totals.count += 1;
+        totals.gas_used = totals.gas_used.saturating_add(gas_used);
+        if estimated {
+            totals.estimated_gas_used = totals.estimated_gas_used.saturating_add(gas_used);
+        }
+
+        *totals
+    }
+
+    #[must_use]
+    pub fn totals(&self) -> BTreeMap<&'static str, MessageGasTotals> {
+        self.totals
+            .lock()
+            .expect("lock is not poisoned; qed;")
+            .clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hex_literal::hex;
+
+    use super::*;
+
+    const MULTICALL: H160 = H160::new(hex!("1111111111111111111111111111111111111111"));
+    const IBC_HANDLER: H160 = H160::new(hex!("2222222222222222222222222222222222222222"));
+
+    fn fixture() -> CallFrame {
+        serde_json::from_str(include_str!("../testdata/gas/call-tracer.json")).unwrap()
+    }
+
+    #[test]
+    fn traced_calls_are_correlated_by_index() {
+        let calls = traced_calls(&fixture(), MULTICALL, IBC_HANDLER, 3).unwrap();
+
+        assert_eq!(
+            calls,
+            vec![
+                TracedCall {
+                    gas_used: 70_000,
+                    reverted: false,
+                },
+                // the reverted call and its reverted sub-call are attributed to the second message
+                TracedCall {
+                    gas_used: 30_000,
+                    reverted: true,
+                },
+                TracedCall {
+                    gas_used: 90_000,
+                    reverted: false,
+                },
+            ]
+        );
+
+        // the multicall overhead is not attributed to any message
+        assert_eq!(
+            fixture().gas_used - calls.iter().map(|call| call.gas_used).sum::<u64>(),
+            60_000
+        );
+    }
+
+    #[test]
+    fn trace_mismatch() {
+        assert!(matches!(
+            traced_calls(&fixture(), MULTICALL, IBC_HANDLER, 4),
+            Err(TraceError::CallCountMismatch {
+                expected: 4,
+                found: 3
+            })
+        ));
+
+        let Err(TraceError::UnexpectedRoot { expected, found }) =
+            traced_calls(&fixture(), IBC_HANDLER, IBC_HANDLER, 3)
+        else {
+            panic!("the root of the trace is the multicall");
+        };
+
+        assert_eq!(expected, IBC_HANDLER);
+        assert_eq!(found, Some(MULTICALL));
+    }
+
+    #[test]
+    fn estimated_gas_is_proportional_to_calldata() {
+        assert_eq!(
+            estimate_call_gas(250_000, &[100, 300, 600]),
+            vec![25_000, 75_000, 150_000]
+        );
+
+        // the remainder goes to the largest fractional parts, then to the earliest calls
+        assert_eq!(estimate_call_gas(1_001, &[1, 1, 1]), vec![334, 334, 333]);
+        assert_eq!(estimate_call_gas(10, &[1, 2]), vec![3, 7]);
+
+        assert_eq!(estimate_call_gas(9, &[0, 0, 0]), vec![3, 3, 3]);
+        assert_eq!(estimate_call_gas(9, &[0, 3]), vec![0, 9]);
+        assert_eq!(estimate_call_gas(9, &[]), Vec::<u64>::new());
+        assert_eq!(estimate_call_gas(0, &[5, 7]), vec![0, 0]);
+
+        for lens in [&[1, 2, 3][..], &[7, 7, 7, 7, 7, 7, 7], &[1_000_000, 1]] {
+            assert_eq!(
+                estimate_call_gas(12_345_677, lens).iter().sum::<u64>(),
+                12_345_677
+            );
+        }
+    }
+
+    #[test]
+    fn accounting_accumulates() {
+        let accounting = GasAccounting::default();
+
+        accounting.record("update_client", 100, false);
+        accounting.record("packet_recv", 50, true);
+
+        assert_eq!(
+            accounting.record("update_client", 10, true),
+            MessageGasTotals {
+                count: 2,
+                gas_used: 110,
+                estimated_gas_used: 10,
+            }
+        );
+        assert_eq!(
+            accounting.totals()["packet_recv"],
+            MessageGasTotals {
+                count: 1,
+                gas_used: 50,
+                estimated_gas_used: 50,
+            }
+        );
+    }
+}
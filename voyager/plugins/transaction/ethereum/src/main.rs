@@ -1,4 +1,4 @@
-use std::collections::VecDeque;
+use std::{collections::VecDeque, future::IntoFuture};
 
 use alloy::{
     contract::{Error, RawCallBuilder},
@@ -10,14 +10,21 @@ use alloy::{
 };
 use bip32::secp256k1::ecdsa::{self, SigningKey};
 use chain_utils::{
+    address::RelayerAddress,
+    auth::{self, EndpointAuth},
+    endpoint::{HttpUrl, DEFAULT_PROBE_TIMEOUT},
     keyring::{ConcurrentKeyring, KeyringConfig, KeyringEntry},
+    net::NetworkConfig,
+    relay_progress::{ConfirmedPacket, RelayProgress, RelayProgressConfig, RelayProgressTracker},
+    spend::{Admission, SpendConfig, SpendTracker},
+    timeout::{CallKind, TimedOut, TimeoutConfig},
     BoxDynError,
 };
 use ibc_solidity::Ibc::{self, IbcErrors};
 use ibc_union_spec::{Datagram, IbcUnion};
 use jsonrpsee::{
     core::{async_trait, RpcResult},
-    types::{ErrorObject, ErrorObjectOwned},
+    types::ErrorObjectOwned,
     Extensions,
 };
 use serde::{Deserialize, Serialize};
@@ -27,22 +34,34 @@ use unionlabs::{
     ErrorReporter,
 };
 use voyager_message::{
+    balance_monitor::{BalanceMonitor, BalanceMonitorConfig, CheckBalances},
+    cmd::CmdOutput,
     core::{ChainId, IbcSpec},
-    data::{Data, WithChainId},
-    module::{PluginInfo, PluginServer},
-    DefaultCmd, Plugin, PluginMessage, VoyagerMessage, FATAL_JSONRPC_ERROR_CODE,
+    data::{Data, IbcDatagram, WithChainId},
+    error::VoyagerError,
+    module::{
+        ensure_chain_id, PluginInfo, PluginKind, PluginServer, TxEstimate, UnexpectedChainIdError,
+    },
+    proof_verify::{partition_verified, rejected_error, VerifyError},
+    suppression::SuppressionList,
+    ExtensionsExt, Plugin, PluginMessage, VoyagerClient, VoyagerMessage,
 };
-use voyager_vm::{call, defer, now, pass::PassResult, seq, Op};
+use voyager_vm::{call, conc, data, defer, noop, now, pass::PassResult, seq, Op};
 
 use crate::{
     call::ModuleCall,
     callback::ModuleCallback,
+    data::ModuleData,
+    gas::{estimate_call_gas, traced_calls, CallFrame, GasAccounting, GasAttribution, TraceError},
     multicall::{Call3, Multicall, MulticallResult},
+    pending::{Bounded, PendingTxConfig, PendingTxs},
 };
 
 pub mod call;
 pub mod callback;
 pub mod data;
+pub mod gas;
+pub mod pending;
 
 #[tokio::main(flavor = "multi_thread")]
 async fn main() {
@@ -59,10 +78,27 @@ pub struct Module {
 
     pub provider: RootProvider<BoxTransport>,
 
+    pub timeouts: TimeoutConfig,
+
     pub keyring: ConcurrentKeyring<alloy::primitives::Address, LocalSigner<SigningKey>>,
 
     pub max_gas_price: Option<u128>,
     pub legacy: bool,
+
+    pub spend: SpendTracker,
+
+    pub relay_progress: RelayProgressTracker,
+
+    pub suppression: SuppressionList,
+
+    pub trace_gas: bool,
+    pub gas_accounting: GasAccounting,
+
+    pub verify_proofs_before_submit: bool,
+
+    pub balance_monitor: Option<BalanceMonitor>,
+
+    pub pending_txs: PendingTxs,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -75,7 +111,24 @@ pub struct Config {
     pub multicall_address: H160,
 
     /// The RPC endpoint for the execution chain.
-    pub eth_rpc_api: String,
+    pub eth_rpc_api: HttpUrl,
+
+    /// Authentication for `eth_rpc_api`, if the rpc provider requires it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub eth_rpc_api_auth: Option<EndpointAuth>,
+
+    /// Don't check that `eth_rpc_api` is reachable on startup.
+    #[serde(default)]
+    pub skip_startup_probe: bool,
+
+    /// How connections to `eth_rpc_api` are opened, i.e. through a proxy.
+    #[serde(default)]
+    pub network: NetworkConfig,
+
+    /// Timeouts of the calls to `eth_rpc_api`. Waiting for the receipt of a submitted transaction
+    /// is bounded by the `broadcast` timeout.
+    #[serde(default)]
+    pub timeouts: TimeoutConfig,
 
     pub keyring: KeyringConfig,
 
@@ -84,6 +137,47 @@ pub struct Config {
 
     #[serde(default)]
     pub legacy: bool,
+
+    #[serde(default)]
+    pub spend: SpendConfig,
+
+    #[serde(default)]
+    pub relay_progress: RelayProgressConfig,
+
+    /// Channels on this chain to drop datagrams for instead of submitting them, i.e. channels
+    /// whose counterparty has been abandoned.
+    #[serde(default)]
+    pub suppression: SuppressionList,
+
+    /// Trace submitted multicalls with `debug_traceTransaction` to attribute the gas used to the
+    /// individual messages. Not all providers support tracing; if this is disabled (or tracing
+    /// fails), the gas used is instead estimated from the calldata size of each message.
+    #[serde(default)]
+    pub trace_gas: bool,
+
+    /// Verify the proofs of packet datagrams against the consensus state of the client on this
+    /// chain before submitting them, failing the datagrams whose proofs don't verify instead of
+    /// submitting them. See [`voyager_message::proof_verify`].
+    #[serde(default)]
+    pub verify_proofs_before_submit: bool,
+
+    /// Periodically check the balance of every key in the keyring, alerting on (and optionally
+    /// pausing) keys that are running low. See [`voyager_message::balance_monitor`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub balance_monitor: Option<BalanceMonitorConfig>,
+
+    /// Prefer the keys with the fewest transactions pending in the mempool when submitting, and
+    /// optionally skip keys with too many pending transactions. See [`pending`].
+    #[serde(default)]
+    pub pending_txs: PendingTxConfig,
+}
+
+#[derive(clap::Subcommand)]
+pub enum Cmd {
+    /// Print the fees spent by this chain's transactions, per day.
+    Spend,
+    /// Print the highest packet sequences relayed on each channel of this chain.
+    RelayProgress,
 }
 
 impl Plugin for Module {
@@ -91,41 +185,45 @@ impl Plugin for Module {
     type Callback = ModuleCallback;
 
     type Config = Config;
-    type Cmd = DefaultCmd;
+    type Cmd = Cmd;
 
     async fn new(config: Self::Config) -> Result<Self, BoxDynError> {
-        let provider = ProviderBuilder::new()
-            .on_builtin(&config.eth_rpc_api)
-            .await?;
+        if !config.skip_startup_probe {
+            config
+                .eth_rpc_api
+                .probe_via(&config.network, DEFAULT_PROBE_TIMEOUT)
+                .await?;
+        }
 
-        let raw_chain_id = provider.get_chain_id().await?;
-        let chain_id = ChainId::new(raw_chain_id.to_string());
+        let provider = auth::http_provider(
+            &config.eth_rpc_api,
+            auth::resolve(config.eth_rpc_api_auth.as_ref())?.as_ref(),
+            &config.network,
+        )?;
 
-        if chain_id != config.chain_id {
-            return Err(format!(
-                "incorrect chain id: expected `{}`, but found `{}`",
-                config.chain_id, chain_id
-            )
-            .into());
-        }
+        let chain_id = ensure_provider_chain_id(&config.chain_id, provider.get_chain_id().await?)?;
+
+        let keys = config.keyring.resolve()?;
 
         Ok(Self {
             chain_id,
             ibc_handler_address: config.ibc_handler_address,
             multicall_address: config.multicall_address,
             provider,
+            timeouts: config.timeouts,
             keyring: ConcurrentKeyring::new(
                 config.keyring.name,
-                config.keyring.keys.into_iter().map(|config| {
+                // the resolved keys are zeroized as soon as the signer is constructed
+                keys.into_iter().map(|entry| {
                     let signing_key = <ecdsa::SigningKey as bip32::PrivateKey>::from_bytes(
-                        &config.value().as_slice().try_into().unwrap(),
+                        &entry.key.as_slice().try_into().unwrap(),
                     )
                     .unwrap();
 
                     let signer = LocalSigner::from_signing_key(signing_key);
 
                     KeyringEntry {
-                        name: config.name(),
+                        name: entry.name,
                         address: signer.address(),
                         signer,
                     }
@@ -133,6 +231,19 @@ impl Plugin for Module {
             ),
             max_gas_price: config.max_gas_price,
             legacy: config.legacy,
+            spend: SpendTracker::new(config.chain_id.to_string(), config.spend)?,
+            relay_progress: RelayProgressTracker::new(
+                config.chain_id.to_string(),
+                config.relay_progress,
+            )?,
+            suppression: config.suppression,
+            trace_gas: config.trace_gas,
+            gas_accounting: GasAccounting::default(),
+            verify_proofs_before_submit: config.verify_proofs_before_submit,
+            balance_monitor: config
+                .balance_monitor
+                .map(|balance_monitor| BalanceMonitor::new(config.chain_id, balance_monitor)),
+            pending_txs: PendingTxs::new(config.pending_txs),
         })
     }
 
@@ -158,14 +269,36 @@ end
                 chain_id = config.chain_id,
                 ibc_spec_id = IbcUnion::ID,
             ),
+            kind: Some(PluginKind::Transaction),
+            chains: vec![config.chain_id.clone()],
+            ibc_specs: vec![IbcUnion::ID],
         }
     }
 
-    async fn cmd(_config: Self::Config, cmd: Self::Cmd) {
-        match cmd {}
+    async fn cmd(config: Self::Config, cmd: Self::Cmd) -> Result<Box<dyn CmdOutput>, BoxDynError> {
+        match cmd {
+            Cmd::Spend => Ok(Box::new(
+                SpendTracker::new(config.chain_id.to_string(), config.spend)?.report(),
+            )),
+            Cmd::RelayProgress => Ok(Box::new(
+                RelayProgressTracker::new(config.chain_id.to_string(), config.relay_progress)?
+                    .report(),
+            )),
+        }
     }
 }
 
+/// Check that `eth_rpc_api` is for the configured chain, since transactions are signed for the
+/// chain id reported by the provider.
+fn ensure_provider_chain_id(
+    chain_id: &ChainId,
+    provider_chain_id: u64,
+) -> Result<ChainId, UnexpectedChainIdError> {
+    ensure_chain_id(chain_id, provider_chain_id.to_string())?;
+
+    Ok(chain_id.clone())
+}
+
 fn plugin_name(chain_id: &ChainId) -> String {
     pub const PLUGIN_NAME: &str = env!("CARGO_PKG_NAME");
 
@@ -176,6 +309,108 @@ impl Module {
     fn plugin_name(&self) -> String {
         plugin_name(&self.chain_id)
     }
+
+    /// Start the [`CheckBalances`] loop of this instance, if the balance monitor is enabled and
+    /// it has not been started yet.
+    fn start_balance_monitor(&self) -> Option<Op<VoyagerMessage>> {
+        let check = self.balance_monitor.as_ref()?.start()?;
+
+        Some(call(PluginMessage::new(
+            self.plugin_name(),
+            ModuleCall::from(check),
+        )))
+    }
+
+    async fn check_balances(&self, check: CheckBalances) -> RpcResult<Op<VoyagerMessage>> {
+        let Some(balance_monitor) = self
+            .balance_monitor
+            .as_ref()
+            .filter(|balance_monitor| balance_monitor.is_current(&check))
+        else {
+            info!("dropping balance check queued by a previous instance");
+            return Ok(noop());
+        };
+
+        let mut balances = vec![];
+
+        for (_, address) in self.keyring.keys() {
+            let balance = self
+                .timeouts
+                .with_timeout(
+                    CallKind::Query,
+                    self.provider.get_balance(*address).into_future(),
+                )
+                .await
+                .map_err(VoyagerError::from)?
+                .map_err(|err| {
+                    VoyagerError::retryable(format!(
+                        "error fetching the balance of {address}: {}",
+                        ErrorReporter(err)
+                    ))
+                })?;
+
+            balances.push((*address, balance.saturating_to::<u128>()));
+        }
+
+        let low = balance_monitor.update(&self.keyring, balances);
+
+        Ok(conc(
+            low.into_iter()
+                .map(|low| {
+                    data(PluginMessage::new(
+                        self.plugin_name(),
+                        ModuleData::from(low),
+                    ))
+                })
+                .chain([seq([
+                    defer(now() + balance_monitor.config.check_interval),
+                    call(PluginMessage::new(
+                        self.plugin_name(),
+                        ModuleCall::from(check),
+                    )),
+                ])]),
+        ))
+    }
+
+    /// Fail the datagrams that were rejected by [`partition_verified`]. The `verified` datagrams
+    /// are submitted separately, such that they aren't failed along with the rejected ones.
+    fn fail_rejected(
+        &self,
+        verified: Vec<Datagram>,
+        rejected: Vec<(Datagram, VerifyError)>,
+    ) -> RpcResult<Op<VoyagerMessage>> {
+        if verified.is_empty() {
+            return Err(rejected_error(&rejected).into());
+        }
+
+        Ok(conc(
+            [verified, rejected.into_iter().map(|(msg, _)| msg).collect()]
+                .into_iter()
+                .map(|msgs| {
+                    call(PluginMessage::new(
+                        self.plugin_name(),
+                        ModuleCall::SubmitMulticall(msgs),
+                    ))
+                }),
+        ))
+    }
+}
+
+/// Client updates must always be submitted, even if the daily budget is exceeded, to avoid the
+/// clients expiring.
+fn is_priority(datagram: &Datagram) -> bool {
+    matches!(datagram, Datagram::UpdateClient(_))
+}
+
+/// The packet relayed by `datagram`, for [`relay_progress`](chain_utils::relay_progress).
+///
+/// ibc-union packets are identified by their commitment rather than a sequence, so there is no
+/// sequence to track for them yet; this is the hook for the multicall results in
+/// [`Module::submit_transaction`].
+fn confirmed_packet(datagram: &Datagram) -> Option<ConfirmedPacket> {
+    let _ = datagram;
+
+    None
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -192,6 +427,8 @@ pub enum TxSubmitError {
     GasPriceTooHigh { max: u128, price: u128 },
     #[error("rpc error (this is just the IbcDatagram conversion functions but i need to make those errors better)")]
     RpcError(#[from] ErrorObjectOwned),
+    #[error(transparent)]
+    TimedOut(#[from] TimedOut),
 }
 
 #[async_trait]
@@ -216,22 +453,11 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
                             })) => {
                                 assert_eq!(chain_id, self.chain_id);
 
-                                call(PluginMessage::new(
-                                    self.plugin_name(),
-                                    ModuleCall::SubmitMulticall(vec![message
-                                        .decode_datagram::<IbcUnion>()
-                                        .unwrap()
-                                        .map_err(|e| {
-                                            ErrorObject::owned(
-                                                FATAL_JSONRPC_ERROR_CODE,
-                                                format!(
-                                                    "unable to deserialize datagram: {}",
-                                                    ErrorReporter(e)
-                                                ),
-                                                None::<()>,
-                                            )
-                                        })?]),
-                                ))
+                                submit_unsuppressed(
+                                    &self.chain_id,
+                                    &self.suppression,
+                                    vec![message],
+                                )?
                             }
                             Op::Data(Data::IdentifiedIbcDatagramBatch(WithChainId {
                                 chain_id,
@@ -239,29 +465,7 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
                             })) => {
                                 assert_eq!(chain_id, self.chain_id);
 
-                                call(PluginMessage::new(
-                                    self.plugin_name(),
-                                    ModuleCall::SubmitMulticall(
-                                        message
-                                            .into_iter()
-                                            .map(|message| {
-                                                message
-                                                    .decode_datagram::<IbcUnion>()
-                                                    .unwrap()
-                                                    .map_err(|e| {
-                                                        ErrorObject::owned(
-                                                            FATAL_JSONRPC_ERROR_CODE,
-                                                            format!(
-                                                            "unable to deserialize datagram: {}",
-                                                            ErrorReporter(e)
-                                                        ),
-                                                            None::<()>,
-                                                        )
-                                                    })
-                                            })
-                                            .collect::<Result<_, _>>()?,
-                                    ),
-                                ))
+                                submit_unsuppressed(&self.chain_id, &self.suppression, message)?
                             }
                             _ => panic!("unexpected message: {msg:?}"),
                         },
@@ -272,46 +476,59 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
     }
 
     #[instrument(skip_all, fields(chain_id = %self.chain_id))]
-    async fn call(&self, _: &Extensions, msg: ModuleCall) -> RpcResult<Op<VoyagerMessage>> {
+    async fn call(&self, e: &Extensions, msg: ModuleCall) -> RpcResult<Op<VoyagerMessage>> {
         match msg {
             ModuleCall::SubmitMulticall(msgs) => {
-                let res = self
-                    .keyring
-                    .with({
-                        let msgs = msgs.clone();
-                        move |wallet| -> _ {
-                            // let call = if self.legacy { call.legacy() } else { call };
-                            self.submit_transaction(wallet, msgs)
-                        }
-                    })
-                    .await;
-
-                let rewrap_msg =
-                    || PluginMessage::new(self.plugin_name(), ModuleCall::SubmitMulticall(msgs));
+                let msgs = if self.verify_proofs_before_submit {
+                    let (verified, rejected) = partition_verified(
+                        e.try_get::<VoyagerClient>()?,
+                        &self.chain_id,
+                        msgs,
+                        |datagram| Some(datagram),
+                    )
+                    .await?;
 
-                match res {
-                    Some(Ok(())) => Ok(Op::Noop),
-                    Some(Err(TxSubmitError::GasPriceTooHigh { .. })) => {
-                        Ok(seq([defer(now() + 6), call(rewrap_msg())]))
-                    }
-                    Some(Err(TxSubmitError::OutOfGas)) => {
-                        Ok(seq([defer(now() + 12), call(rewrap_msg())]))
+                    if !rejected.is_empty() {
+                        return self.fail_rejected(verified, rejected);
                     }
-                    Some(Err(TxSubmitError::EmptyRevert(msgs))) => Ok(seq([
-                        defer(now() + 12),
+
+                    verified
+                } else {
+                    msgs
+                };
+
+                let Admission { submit, deferred } = self.spend.admit(msgs, is_priority);
+
+                let deferred = deferred.map(|(exceeded, msgs)| {
+                    warn!(
+                        error = %exceeded,
+                        batch.size = msgs.len(),
+                        "daily gas budget exceeded, deferring submission until the budget window rolls over"
+                    );
+
+                    seq([
+                        defer(exceeded.resets_at),
                         call(PluginMessage::new(
                             self.plugin_name(),
                             ModuleCall::SubmitMulticall(msgs),
                         )),
-                    ])),
-                    Some(Err(err)) => Err(ErrorObject::owned(
-                        -1,
-                        ErrorReporter(err).to_string(),
-                        None::<()>,
-                    )),
-                    None => Ok(call(rewrap_msg())),
-                }
+                    ])
+                });
+
+                let submitted = if submit.is_empty() {
+                    None
+                } else {
+                    Some(self.submit_multicall(submit).await?)
+                };
+
+                Ok(conc(
+                    self.start_balance_monitor()
+                        .into_iter()
+                        .chain(submitted)
+                        .chain(deferred),
+                ))
             }
+            ModuleCall::CheckBalances(check) => self.check_balances(check).await,
         }
     }
 
@@ -324,9 +541,111 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
     ) -> RpcResult<Op<VoyagerMessage>> {
         match cb {}
     }
+
+    #[instrument(skip_all, fields(chain_id = %self.chain_id))]
+    async fn estimate(&self, msgs: Vec<IbcDatagram>) -> RpcResult<TxEstimate> {
+        let msgs = msgs
+            .iter()
+            .map(|msg| {
+                msg.decode_datagram::<IbcUnion>()
+                    .ok_or_else(|| {
+                        VoyagerError::fatal(format!(
+                            "unsupported IBC spec `{}`, only {} is supported",
+                            msg.ibc_spec_id,
+                            IbcUnion::ID
+                        ))
+                    })?
+                    .map_err(|e| {
+                        VoyagerError::fatal(format!(
+                            "unable to deserialize datagram: {}",
+                            ErrorReporter(e)
+                        ))
+                    })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let res = self
+            .keyring
+            .with(|wallet| self.estimate_transaction(wallet, msgs))
+            .await;
+
+        match res {
+            Some(res) => {
+                res.map_err(|err| VoyagerError::retryable(ErrorReporter(err).to_string()).into())
+            }
+            None => Err(VoyagerError::retryable("no signers available").into()),
+        }
+    }
+
+    #[instrument(skip_all, fields(chain_id = %self.chain_id, %channel))]
+    async fn relay_progress(&self, channel: String) -> RpcResult<RelayProgress> {
+        Ok(self.relay_progress.progress(&channel))
+    }
 }
 
 impl Module {
+    async fn submit_multicall(&self, msgs: Vec<Datagram>) -> RpcResult<Op<VoyagerMessage>> {
+        let rewrap_msg = || {
+            PluginMessage::new(
+                self.plugin_name(),
+                ModuleCall::SubmitMulticall(msgs.clone()),
+            )
+        };
+
+        let pending = self
+            .pending_txs
+            .counts(
+                &Bounded {
+                    provider: &self.provider,
+                    timeouts: &self.timeouts,
+                },
+                self.keyring.keys().map(|(_, address)| *address),
+            )
+            .await;
+
+        if pending.all_saturated() {
+            warn!(
+                max_pending_per_key = self.pending_txs.config.max_pending_per_key,
+                batch.size = msgs.len(),
+                "all keys have too many pending transactions, deferring submission"
+            );
+
+            return Ok(seq([
+                defer(now() + self.pending_txs.config.saturated_retry_seconds),
+                call(rewrap_msg()),
+            ]));
+        }
+
+        let res = self
+            .keyring
+            .with_preferred(|address| pending.rank(address), {
+                let msgs = msgs.clone();
+                move |wallet| -> _ {
+                    // let call = if self.legacy { call.legacy() } else { call };
+                    self.submit_transaction(wallet, msgs)
+                }
+            })
+            .await;
+
+        match res {
+            Some(Ok(())) => Ok(Op::Noop),
+            Some(Err(TxSubmitError::GasPriceTooHigh { .. })) => {
+                Ok(seq([defer(now() + 6), call(rewrap_msg())]))
+            }
+            Some(Err(TxSubmitError::OutOfGas)) => Ok(seq([defer(now() + 12), call(rewrap_msg())])),
+            Some(Err(TxSubmitError::EmptyRevert(msgs))) => Ok(seq([
+                defer(now() + 12),
+                call(PluginMessage::new(
+                    self.plugin_name(),
+                    ModuleCall::SubmitMulticall(msgs),
+                )),
+            ])),
+            Some(Err(TxSubmitError::TimedOut(err))) => Err(VoyagerError::from(err).into()),
+            Some(Err(err)) => Err(VoyagerError::retryable(ErrorReporter(err).to_string()).into()),
+            None => Ok(call(rewrap_msg())),
+        }
+    }
+
     async fn submit_transaction(
         &self,
         wallet: &LocalSigner<SigningKey>,
@@ -340,11 +659,7 @@ impl Module {
             .on_provider(self.provider.clone());
 
         if let Some(max_gas_price) = self.max_gas_price {
-            let gas_price = self
-                .provider
-                .get_gas_price()
-                .await
-                .expect("unable to fetch gas price");
+            let gas_price = gas_price(&self.provider, &self.timeouts).await?;
 
             if gas_price > max_gas_price {
                 warn!(%max_gas_price, %gas_price, "gas price is too high");
@@ -360,7 +675,11 @@ impl Module {
 
         let ibc = Ibc::new(self.ibc_handler_address.into(), &self.provider);
 
-        let msgs = process_msgs(&ibc, ibc_messages, wallet.address().0.into())?;
+        let msgs = process_msgs(
+            &ibc,
+            ibc_messages,
+            &RelayerAddress::eth(wallet.credential()),
+        )?;
 
         dbg!(&msgs);
 
@@ -370,6 +689,11 @@ impl Module {
             .map(|x| (x.0.clone(), x.0.name()))
             .collect::<Vec<_>>();
 
+        let calldata_lens = msgs
+            .iter()
+            .map(|(_, x)| x.calldata().len())
+            .collect::<Vec<_>>();
+
         let call = multicall.multicall(
             msgs.into_iter()
                 .map(|(_, x)| Call3 {
@@ -382,14 +706,28 @@ impl Module {
 
         info!("submitting evm tx");
 
-        match call.gas(30_000_000).send().await {
+        match self
+            .timeouts
+            .with_timeout(CallKind::Broadcast, call.gas(30_000_000).send())
+            .await?
+        {
             Ok(ok) => {
                 let tx_hash = <H256>::from(*ok.tx_hash());
                 async move {
-                    let receipt = ok.get_receipt().await?;
+                    let receipt = self
+                        .timeouts
+                        .with_timeout(CallKind::Broadcast, ok.get_receipt())
+                        .await??;
 
                     info!(%tx_hash, "tx included");
 
+                    let fee =
+                        u128::from(receipt.gas_used).saturating_mul(receipt.effective_gas_price);
+
+                    if let Err(err) = self.spend.record(fee) {
+                        error!(%fee, error = %ErrorReporter(err), "unable to record tx fee");
+                    }
+
                     let result = MulticallResult::decode_log_data(
                         receipt
                             .inner
@@ -407,6 +745,45 @@ impl Module {
                         "submitted batched evm messages"
                     );
 
+                    let gas = self
+                        .attribute_gas(tx_hash, receipt.gas_used, &calldata_lens)
+                        .await;
+
+                    for (idx, (&(_, msg_name), success)) in msg_names
+                        .iter()
+                        .zip(result._0.iter().map(|result| result.success))
+                        .enumerate()
+                    {
+                        let Some(gas_used) = gas.gas_used(idx) else {
+                            continue;
+                        };
+
+                        let totals =
+                            self.gas_accounting
+                                .record(msg_name, gas_used, gas.is_estimated());
+
+                        info!(
+                            msg = %msg_name,
+                            %idx,
+                            %gas_used,
+                            estimated = gas.is_estimated(),
+                            %success,
+                            total.count = totals.count,
+                            total.gas_used = totals.gas_used,
+                            total.estimated_gas_used = totals.estimated_gas_used,
+                            "evm message gas"
+                        );
+                    }
+
+                    if let Err(err) = self.relay_progress.record_confirmed(
+                        msg_names
+                            .iter()
+                            .zip(result._0.iter())
+                            .map(|((msg, _), result)| (result.success, confirmed_packet(msg))),
+                    ) {
+                        error!(error = %ErrorReporter(err), "unable to record relay progress");
+                    }
+
                     let mut retry_msgs = vec![];
 
                     for (idx, (result, (msg, msg_name))) in
@@ -488,14 +865,121 @@ impl Module {
             Err(err) => Err(TxSubmitError::Error(err)),
         }
     }
+
+    /// Attribute the `gas_used` by the multicall `tx_hash` to the messages in it, see the
+    /// [`gas`] module.
+    async fn attribute_gas(
+        &self,
+        tx_hash: H256,
+        gas_used: u64,
+        calldata_lens: &[usize],
+    ) -> GasAttribution {
+        if self.trace_gas {
+            match self.trace_multicall(tx_hash, calldata_lens.len()).await {
+                Ok(calls) => return GasAttribution::Traced(calls),
+                Err(err) => warn!(
+                    error = %ErrorReporter(err),
+                    "unable to trace multicall, estimating the gas used per message from calldata size"
+                ),
+            }
+        }
+
+        GasAttribution::Estimated(estimate_call_gas(gas_used, calldata_lens))
+    }
+
+    async fn trace_multicall(
+        &self,
+        tx_hash: H256,
+        expected_calls: usize,
+    ) -> Result<Vec<gas::TracedCall>, TraceError> {
+        let root = self
+            .timeouts
+            .with_timeout(
+                CallKind::Query,
+                self.provider.raw_request::<_, CallFrame>(
+                    "debug_traceTransaction".into(),
+                    (tx_hash, serde_json::json!({ "tracer": "callTracer" })),
+                ),
+            )
+            .await??;
+
+        traced_calls(
+            &root,
+            self.multicall_address,
+            self.ibc_handler_address,
+            expected_calls,
+        )
+    }
+
+    /// Estimate the gas of submitting `ibc_messages` in a multicall, and the
+    /// fee at the current gas price.
+    ///
+    /// Unlike on submission, the calls are not allowed to fail, such that a
+    /// message that would revert fails the estimation instead of being
+    /// silently estimated as a failed call.
+    async fn estimate_transaction(
+        &self,
+        wallet: &LocalSigner<SigningKey>,
+        ibc_messages: Vec<Datagram>,
+    ) -> Result<TxEstimate, TxSubmitError> {
+        let multicall = Multicall::new(self.multicall_address.into(), &self.provider);
+
+        let ibc = Ibc::new(self.ibc_handler_address.into(), &self.provider);
+
+        let msgs = process_msgs(
+            &ibc,
+            ibc_messages,
+            &RelayerAddress::eth(wallet.credential()),
+        )?;
+
+        let call = multicall
+            .multicall(
+                msgs.into_iter()
+                    .map(|(_, x)| Call3 {
+                        target: self.ibc_handler_address.into(),
+                        allowFailure: false,
+                        callData: x.calldata().clone(),
+                    })
+                    .collect(),
+            )
+            .from(wallet.address());
+
+        let gas = self
+            .timeouts
+            .with_timeout(CallKind::Query, call.estimate_gas())
+            .await??;
+
+        let gas_price = gas_price(&self.provider, &self.timeouts).await?;
+
+        Ok(TxEstimate {
+            gas,
+            fee: u128::from(gas).saturating_mul(gas_price),
+            denom: "wei".to_owned(),
+        })
+    }
+}
+
+/// The current gas price of the chain.
+async fn gas_price(
+    provider: &RootProvider<BoxTransport>,
+    timeouts: &TimeoutConfig,
+) -> Result<u128, TxSubmitError> {
+    Ok(timeouts
+        .with_timeout(CallKind::Query, provider.get_gas_price())
+        .await?
+        .map_err(Error::TransportError)?)
 }
 
 #[allow(clippy::type_complexity)]
 fn process_msgs<T: Transport + Clone, P: Provider<T>>(
     ibc_handler: &ibc_solidity::Ibc::IbcInstance<T, P>,
     msgs: Vec<Datagram>,
-    relayer: H160,
+    relayer: &RelayerAddress,
 ) -> RpcResult<Vec<(Datagram, RawCallBuilder<T, &P>)>> {
+    let Some(relayer) = relayer.as_eth() else {
+        return Err(VoyagerError::fatal(format!("relayer {relayer} is not an EVM address")).into());
+    };
+
     dbg!(&msgs);
 
     msgs.clone()
@@ -517,7 +1001,7 @@ fn process_msgs<T: Transport + Clone, P: Provider<T>>(
                     msg,
                     ibc_handler
                         .updateClient(ibc_solidity::MsgUpdateClient {
-                            client_id: data.client_id,
+                            client_id: data.client_id.get(),
                             client_message: data.client_message.into(),
                             relayer: relayer.into(),
                         })
@@ -527,8 +1011,8 @@ fn process_msgs<T: Transport + Clone, P: Provider<T>>(
                     msg,
                     ibc_handler
                         .connectionOpenInit(ibc_solidity::MsgConnectionOpenInit {
-                            client_id: data.client_id,
-                            counterparty_client_id: data.counterparty_client_id,
+                            client_id: data.client_id.get(),
+                            counterparty_client_id: data.counterparty_client_id.get(),
                             relayer: relayer.into(),
                         })
                         .clear_decoder(),
@@ -537,9 +1021,9 @@ fn process_msgs<T: Transport + Clone, P: Provider<T>>(
                     msg,
                     ibc_handler
                         .connectionOpenTry(ibc_solidity::MsgConnectionOpenTry {
-                            counterparty_client_id: data.counterparty_client_id,
-                            counterparty_connection_id: data.counterparty_connection_id,
-                            client_id: data.client_id,
+                            counterparty_client_id: data.counterparty_client_id.get(),
+                            counterparty_connection_id: data.counterparty_connection_id.get(),
+                            client_id: data.client_id.get(),
                             proof_init: data.proof_init.into(),
                             proof_height: data.proof_height,
                             relayer: relayer.into(),
@@ -550,8 +1034,8 @@ fn process_msgs<T: Transport + Clone, P: Provider<T>>(
                     msg,
                     ibc_handler
                         .connectionOpenAck(ibc_solidity::MsgConnectionOpenAck {
-                            connection_id: data.connection_id,
-                            counterparty_connection_id: data.counterparty_connection_id,
+                            connection_id: data.connection_id.get(),
+                            counterparty_connection_id: data.counterparty_connection_id.get(),
                             proof_height: data.proof_height,
                             proof_try: data.proof_try.into(),
                             relayer: relayer.into(),
@@ -562,7 +1046,7 @@ fn process_msgs<T: Transport + Clone, P: Provider<T>>(
                     msg,
                     ibc_handler
                         .connectionOpenConfirm(ibc_solidity::MsgConnectionOpenConfirm {
-                            connection_id: data.connection_id,
+                            connection_id: data.connection_id.get(),
                             proof_ack: data.proof_ack.into(),
                             proof_height: data.proof_height,
                             relayer: relayer.into(),
@@ -576,7 +1060,7 @@ fn process_msgs<T: Transport + Clone, P: Provider<T>>(
                             port_id: data.port_id.try_into().unwrap(),
                             relayer: relayer.into(),
                             counterparty_port_id: data.counterparty_port_id.into(),
-                            connection_id: data.connection_id,
+                            connection_id: data.connection_id.get(),
                             version: data.version,
                         })
                         .clear_decoder(),
@@ -597,9 +1081,9 @@ fn process_msgs<T: Transport + Clone, P: Provider<T>>(
                     msg,
                     ibc_handler
                         .channelOpenAck(ibc_solidity::MsgChannelOpenAck {
-                            channel_id: data.channel_id,
+                            channel_id: data.channel_id.get(),
                             counterparty_version: data.counterparty_version,
-                            counterparty_channel_id: data.counterparty_channel_id,
+                            counterparty_channel_id: data.counterparty_channel_id.get(),
                             proof_try: data.proof_try.into(),
                             proof_height: data.proof_height,
                             relayer: relayer.into(),
@@ -610,7 +1094,7 @@ fn process_msgs<T: Transport + Clone, P: Provider<T>>(
                     msg,
                     ibc_handler
                         .channelOpenConfirm(ibc_solidity::MsgChannelOpenConfirm {
-                            channel_id: data.channel_id,
+                            channel_id: data.channel_id.get(),
                             proof_ack: data.proof_ack.into(),
                             proof_height: data.proof_height,
                             relayer: relayer.into(),
@@ -685,6 +1169,55 @@ pub mod multicall {
     }
 }
 
+/// Submit all of the `datagrams` that are not for a channel in the `suppression` list (or frozen in
+/// its freeze list) in a single multicall, emitting a [`SuppressedDatagram`] for each of the ones that are.
+///
+/// [`SuppressedDatagram`]: voyager_message::suppression::SuppressedDatagram
+fn submit_unsuppressed(
+    chain_id: &ChainId,
+    suppression: &SuppressionList,
+    datagrams: Vec<IbcDatagram>,
+) -> RpcResult<Op<VoyagerMessage>> {
+    let (datagrams, suppressed) = suppression
+        .with_frozen_channels(chain_id)
+        .partition(chain_id, datagrams);
+
+    let submit = (!datagrams.is_empty() || suppressed.is_empty())
+        .then(|| {
+            datagrams
+                .into_iter()
+                .map(|message| {
+                    message.decode_datagram::<IbcUnion>().unwrap().map_err(|e| {
+                        ErrorObjectOwned::from(VoyagerError::fatal(format!(
+                            "unable to deserialize datagram: {}",
+                            ErrorReporter(e)
+                        )))
+                    })
+                })
+                .collect::<Result<_, _>>()
+                .map(|msgs| {
+                    call(PluginMessage::new(
+                        plugin_name(chain_id),
+                        ModuleCall::SubmitMulticall(msgs),
+                    ))
+                })
+        })
+        .transpose()?;
+
+    if suppressed.is_empty() {
+        return Ok(submit.expect("submission is always built if nothing was suppressed; qed;"));
+    }
+
+    Ok(conc(submit.into_iter().chain(suppressed.into_iter().map(
+        |suppressed| {
+            data(PluginMessage::new(
+                plugin_name(chain_id),
+                ModuleData::from(suppressed),
+            ))
+        },
+    ))))
+}
+
 #[cfg(test)]
 mod tests {
     use alloy::{
@@ -692,9 +1225,24 @@ mod tests {
         primitives::{fixed_bytes, LogData},
     };
     use ibc_solidity::Ibc::ClientCreated;
+    use ibc_union_spec::UnionId;
+    use voyager_message::suppression::SuppressedDatagram;
 
     use super::*;
 
+    #[test]
+    fn provider_chain_id_mismatch() {
+        assert_eq!(
+            ensure_provider_chain_id(&ChainId::new("1"), 1).unwrap(),
+            ChainId::new("1")
+        );
+
+        let err = ensure_provider_chain_id(&ChainId::new("1"), 11155111).unwrap_err();
+
+        assert_eq!(err.expected, ChainId::new("1"));
+        assert_eq!(err.found, "11155111");
+    }
+
     #[test]
     fn multicall_result_decode() {
         let bz = hex::decode("0x0000000000000000000000000000000000000000000000000000000000000020000000000000000000000000000000000000000000000000000000000000000100000000000000000000000000000000000000000000000000000000000000200000000000000000000000000000000000000000000000000000000000000001000000000000000000000000000000000000000000000000000000000000004000000000000000000000000000000000000000000000000000000000000000200000000000000000000000000000000000000000000000000000000000000004").unwrap();
@@ -734,4 +1282,118 @@ mod tests {
 
         dbg!(result);
     }
+
+    #[test]
+    fn client_updates_bypass_budget() {
+        let ledger_path =
+            std::env::temp_dir().join(format!("ethereum-spend-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&ledger_path);
+
+        let spend = SpendTracker::new(
+            "1",
+            SpendConfig {
+                ledger_path,
+                daily_budget: Some(1),
+            },
+        )
+        .unwrap();
+        spend.record(1).unwrap();
+
+        let update = Datagram::UpdateClient(ibc_union_spec::MsgUpdateClient {
+            client_id: UnionId::new(1).unwrap(),
+            client_message: Default::default(),
+        });
+        let open_init = Datagram::ConnectionOpenInit(ibc_union_spec::MsgConnectionOpenInit {
+            client_id: UnionId::new(1).unwrap(),
+            counterparty_client_id: UnionId::new(2).unwrap(),
+        });
+
+        let Admission { submit, deferred } =
+            spend.admit(vec![update.clone(), open_init.clone()], is_priority);
+
+        assert_eq!(submit, vec![update]);
+        assert_eq!(deferred.map(|(_, msgs)| msgs), Some(vec![open_init]));
+    }
+
+    #[test]
+    fn suppressed_datagrams_are_not_submitted() {
+        let chain_id = ChainId::new("1");
+
+        let suppression = serde_json::from_value::<SuppressionList>(serde_json::json!({
+            "channels": [{ "ibc_spec_id": "ibc-union", "channel_id": 2 }]
+        }))
+        .unwrap();
+
+        let update = Datagram::UpdateClient(ibc_union_spec::MsgUpdateClient {
+            client_id: UnionId::new(1).unwrap(),
+            client_message: Default::default(),
+        });
+        let timeout = Datagram::PacketTimeout(ibc_union_spec::MsgPacketTimeout {
+            packet: ibc_solidity::Packet {
+                source_channel: 2,
+                destination_channel: 1,
+                data: Default::default(),
+                timeout_height: 0,
+                timeout_timestamp: 100,
+            },
+            proof: Default::default(),
+            proof_height: 1,
+        });
+
+        let op = submit_unsuppressed(
+            &chain_id,
+            &suppression,
+            vec![
+                IbcDatagram::new::<IbcUnion>(update.clone()),
+                IbcDatagram::new::<IbcUnion>(timeout.clone()),
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(
+            op,
+            conc([
+                call(PluginMessage::new(
+                    plugin_name(&chain_id),
+                    ModuleCall::SubmitMulticall(vec![update]),
+                )),
+                data(PluginMessage::new(
+                    plugin_name(&chain_id),
+                    ModuleData::from(SuppressedDatagram {
+                        chain_id: chain_id.clone(),
+                        channel_id: 2,
+                        datagram: IbcDatagram::new::<IbcUnion>(timeout),
+                    }),
+                )),
+            ])
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn gas_price_times_out() {
+        // accepts connections, but never responds to requests
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let mut connections = vec![];
+            loop {
+                connections.push(listener.accept().await.unwrap());
+            }
+        });
+
+        let provider =
+            auth::http_provider(&url.parse().unwrap(), None, &NetworkConfig::default()).unwrap();
+
+        let err = gas_price(&provider, &TimeoutConfig::default())
+            .await
+            .unwrap_err();
+
+        let TxSubmitError::TimedOut(timed_out) = err else {
+            panic!("expected a timeout, found {err:?}");
+        };
+
+        assert_eq!(timed_out.kind, CallKind::Query);
+        assert_eq!(timed_out.elapsed, CallKind::Query.default_timeout());
+        assert!(!VoyagerError::from(timed_out).is_fatal());
+    }
 }
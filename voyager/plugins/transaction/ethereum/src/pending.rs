@@ -0,0 +1,469 @@
+//! Selection of signers by the number of their transactions that are pending in the mempool.
+//!
+//! A key with several transactions stuck in the mempool (i.e. because of slow inclusion) should
+//! not be handed more batches: they queue up behind the pending transactions with ever increasing
+//! nonces, while the other keys in the keyring sit idle. Before a batch is submitted, the pending
+//! transaction count (the `pending` nonce minus the `latest` nonce) of every key is queried, and
+//! the key with the fewest pending transactions is used. Keys with more than
+//! [`max_pending_per_key`](PendingTxConfig::max_pending_per_key) pending transactions are not used
+//! at all, and if every key is saturated, the batch is deferred.
+//!
+//! The counts are cached for [`cache_ms`](PendingTxConfig::cache_ms), such that the nonces aren't
+//! queried for every batch under load.
+
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use alloy::{
+    primitives::Address,
+    providers::{Provider, RootProvider},
+    transports::{BoxTransport, TransportError},
+};
+use chain_utils::{
+    bounded::BoundedMap,
+    timeout::{CallKind, TimeoutConfig},
+};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+use unionlabs::ErrorReporter;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PendingTxConfig {
+    /// Keys with more pending transactions than this are not used to submit batches. If every key
+    /// is above the limit, batches are deferred until a key has caught up.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_pending_per_key: Option<u64>,
+    /// How long the pending transaction counts are cached for, in milliseconds.
+    #[serde(default = "default_cache_ms")]
+    pub cache_ms: u64,
+    /// The maximum amount of keys whose counts are cached. Only relevant if the keyring is
+    /// larger than this, in which case the counts of some keys are queried for every batch.
+    #[serde(default = "default_cache_max_entries")]
+    pub cache_max_entries: usize,
+    /// How long to defer a batch for if every key is saturated, in seconds.
+    #[serde(default = "default_saturated_retry_seconds")]
+    pub saturated_retry_seconds: u64,
+}
+
+impl Default for PendingTxConfig {
+    fn default() -> Self {
+        Self {
+            max_pending_per_key: None,
+            cache_ms: default_cache_ms(),
+            cache_max_entries: default_cache_max_entries(),
+            saturated_retry_seconds: default_saturated_retry_seconds(),
+        }
+    }
+}
+
+const fn default_cache_ms() -> u64 {
+    2_000
+}
+
+const fn default_cache_max_entries() -> usize {
+    1_024
+}
+
+const fn default_saturated_retry_seconds() -> u64 {
+    12
+}
+
+/// The nonces of an account, as seen by the mempool and by the latest block.
+#[allow(async_fn_in_trait)]
+pub trait TransactionCounts {
+    async fn transaction_counts(&self, address: Address) -> Result<Nonces, TransportError>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Nonces {
+    pub pending: u64,
+    pub latest: u64,
+}
+
+impl Nonces {
+    /// The number of transactions of the account that are in the mempool.
+    #[must_use]
+    pub fn pending_txs(&self) -> u64 {
+        self.pending.saturating_sub(self.latest)
+    }
+}
+
+impl TransactionCounts for RootProvider<BoxTransport> {
+    async fn transaction_counts(&self, address: Address) -> Result<Nonces, TransportError> {
+        Ok(Nonces {
+            pending: self.get_transaction_count(address).pending().await?,
+            latest: self.get_transaction_count(address).latest().await?,
+        })
+    }
+}
+
+/// The [`TransactionCounts`] of `provider`, failing with a local usage error if they aren't
+/// returned within the query timeout.
+pub struct Bounded<'a, P> {
+    pub provider: &'a P,
+    pub timeouts: &'a TimeoutConfig,
+}
+
+impl<P: TransactionCounts> TransactionCounts for Bounded<'_, P> {
+    async fn transaction_counts(&self, address: Address) -> Result<Nonces, TransportError> {
+        self.timeouts
+            .with_timeout(CallKind::Query, self.provider.transaction_counts(address))
+            .await
+            .map_err(TransportError::local_usage)?
+    }
+}
+
+/// The cached pending transaction counts of the keys in the keyring.
+#[derive(Debug, Clone)]
+pub struct PendingTxs {
+    pub config: PendingTxConfig,
+    cache: Arc<BoundedMap<Address, u64>>,
+}
+
+impl PendingTxs {
+    #[must_use]
+    pub fn new(config: PendingTxConfig) -> Self {
+        Self {
+            cache: Arc::new(BoundedMap::new(
+                "pending_tx_counts",
+                config.cache_max_entries,
+                Some(Duration::from_millis(config.cache_ms)),
+            )),
+            config,
+        }
+    }
+
+    /// The pending transaction counts of `addresses`, queried from `provider` unless they are
+    /// cached. Keys whose counts can't be queried are still usable, but are only selected if no
+    /// other key is available.
+    pub async fn counts(
+        &self,
+        provider: &impl TransactionCounts,
+        addresses: impl IntoIterator<Item = Address>,
+    ) -> PendingCounts {
+        let mut counts = HashMap::new();
+
+        for address in addresses {
+            let count = match self.cache.get(&address) {
+                Some(count) => Some(count),
+                None => match provider.transaction_counts(address).await {
+                    Ok(nonces) => {
+                        let count = nonces.pending_txs();
+
+                        self.cache.insert(address, count);
+
+                        Some(count)
+                    }
+                    Err(err) => {
+                        warn!(
+                            %address,
+                            error = %ErrorReporter(err),
+                            "unable to query the pending transaction count"
+                        );
+
+                        None
+                    }
+                },
+            };
+
+            counts.insert(address, count);
+        }
+
+        PendingCounts {
+            counts,
+            max_pending_per_key: self.config.max_pending_per_key,
+        }
+    }
+}
+
+/// A snapshot of the pending transaction counts of the keys in the keyring.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingCounts {
+    counts: HashMap<Address, Option<u64>>,
+    max_pending_per_key: Option<u64>,
+}
+
+impl PendingCounts {
+    /// The rank of `address` for
+    /// [`ConcurrentKeyring::with_preferred`](chain_utils::keyring::ConcurrentKeyring::with_preferred),
+    /// lowest first, or `None` if the key is saturated. Keys with an unknown count are ranked
+    /// last.
+    #[must_use]
+    pub fn rank(&self, address: &Address) -> Option<(bool, u64)> {
+        match self.counts.get(address).copied().flatten() {
+            Some(count) if self.is_saturated(count) => None,
+            Some(count) => Some((false, count)),
+            None => Some((true, 0)),
+        }
+    }
+
+    /// Whether every key is above the limit of pending transactions. Keys with an unknown count
+    /// are not considered saturated.
+    #[must_use]
+    pub fn all_saturated(&self) -> bool {
+        !self.counts.is_empty()
+            && self
+                .counts
+                .values()
+                .all(|count| count.is_some_and(|count| self.is_saturated(count)))
+    }
+
+    fn is_saturated(&self, count: u64) -> bool {
+        self.max_pending_per_key.is_some_and(|max| count > max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashSet,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    use alloy::primitives::address;
+    use chain_utils::keyring::{ConcurrentKeyring, KeyringEntry};
+
+    use super::*;
+
+    const ALICE: Address = address!("000000000000000000000000000000000000a11c");
+    const BOB: Address = address!("0000000000000000000000000000000000000b0b");
+    const CAROL: Address = address!("00000000000000000000000000000000000ca401");
+
+    /// A provider with fixed nonces, counting the queries made to it.
+    struct MockProvider {
+        nonces: HashMap<Address, Nonces>,
+        queries: AtomicUsize,
+    }
+
+    impl MockProvider {
+        fn new(pending: impl IntoIterator<Item = (Address, u64)>) -> Self {
+            Self {
+                nonces: pending
+                    .into_iter()
+                    .map(|(address, pending)| {
+                        (
+                            address,
+                            Nonces {
+                                pending: 100 + pending,
+                                latest: 100,
+                            },
+                        )
+                    })
+                    .collect(),
+                queries: AtomicUsize::new(0),
+            }
+        }
+
+        fn queries(&self) -> usize {
+            self.queries.load(Ordering::SeqCst)
+        }
+    }
+
+    impl TransactionCounts for MockProvider {
+        async fn transaction_counts(&self, address: Address) -> Result<Nonces, TransportError> {
+            self.queries.fetch_add(1, Ordering::SeqCst);
+
+            self.nonces
+                .get(&address)
+                .copied()
+                .ok_or_else(|| TransportError::local_usage_str("unknown address"))
+        }
+    }
+
+    fn keyring() -> ConcurrentKeyring<Address, &'static str> {
+        ConcurrentKeyring::new(
+            "keyring",
+            [(ALICE, "alice"), (BOB, "bob"), (CAROL, "carol")]
+                .into_iter()
+                .map(|(address, name)| KeyringEntry {
+                    name: name.to_owned(),
+                    address,
+                    signer: name,
+                }),
+        )
+    }
+
+    fn pending_txs(max_pending_per_key: Option<u64>) -> PendingTxs {
+        PendingTxs::new(PendingTxConfig {
+            max_pending_per_key,
+            ..PendingTxConfig::default()
+        })
+    }
+
+    async fn select(
+        keyring: &ConcurrentKeyring<Address, &'static str>,
+        counts: &PendingCounts,
+    ) -> Option<&'static str> {
+        keyring
+            .with_preferred(
+                |address| counts.rank(address),
+                |signer| async move { *signer },
+            )
+            .await
+    }
+
+    #[tokio::test]
+    async fn fewest_pending_is_selected() {
+        let keyring = keyring();
+        let provider = MockProvider::new([(ALICE, 3), (BOB, 1), (CAROL, 2)]);
+
+        let counts = pending_txs(None)
+            .counts(&provider, [ALICE, BOB, CAROL])
+            .await;
+
+        assert_eq!(select(&keyring, &counts).await, Some("bob"));
+
+        keyring.pause(&BOB);
+        assert_eq!(select(&keyring, &counts).await, Some("carol"));
+
+        keyring.pause(&CAROL);
+        assert_eq!(select(&keyring, &counts).await, Some("alice"));
+    }
+
+    #[tokio::test]
+    async fn saturated_keys_are_skipped() {
+        let keyring = keyring();
+        let provider = MockProvider::new([(ALICE, 5), (BOB, 4), (CAROL, 2)]);
+
+        let counts = pending_txs(Some(3))
+            .counts(&provider, [ALICE, BOB, CAROL])
+            .await;
+
+        assert!(!counts.all_saturated());
+        assert_eq!(counts.rank(&ALICE), None);
+        assert_eq!(counts.rank(&BOB), None);
+
+        let mut selected = HashSet::new();
+        for _ in 0..3 {
+            selected.insert(select(&keyring, &counts).await.unwrap());
+        }
+        assert_eq!(selected, HashSet::from(["carol"]));
+
+        keyring.pause(&CAROL);
+        assert_eq!(select(&keyring, &counts).await, None);
+    }
+
+    #[tokio::test]
+    async fn all_saturated() {
+        let provider = MockProvider::new([(ALICE, 5), (BOB, 4), (CAROL, 4)]);
+
+        let counts = pending_txs(Some(3))
+            .counts(&provider, [ALICE, BOB, CAROL])
+            .await;
+
+        assert!(counts.all_saturated());
+        assert_eq!(select(&keyring(), &counts).await, None);
+
+        // at the limit is not saturated
+        let counts = pending_txs(Some(4))
+            .counts(&provider, [ALICE, BOB, CAROL])
+            .await;
+
+        assert!(!counts.all_saturated());
+        assert!(matches!(
+            select(&keyring(), &counts).await,
+            Some("bob" | "carol")
+        ));
+
+        // without a limit, keys are never saturated
+        let counts = pending_txs(None)
+            .counts(&provider, [ALICE, BOB, CAROL])
+            .await;
+
+        assert!(!counts.all_saturated());
+    }
+
+    #[tokio::test]
+    async fn unknown_counts_are_ranked_last() {
+        let provider = MockProvider::new([(ALICE, 3), (BOB, 4)]);
+
+        let counts = pending_txs(Some(3))
+            .counts(&provider, [ALICE, BOB, CAROL])
+            .await;
+
+        assert!(!counts.all_saturated());
+        assert_eq!(select(&keyring(), &counts).await, Some("alice"));
+
+        let keyring = keyring();
+        keyring.pause(&ALICE);
+        assert_eq!(select(&keyring, &counts).await, Some("carol"));
+    }
+
+    #[tokio::test]
+    async fn counts_are_cached() {
+        let provider = MockProvider::new([(ALICE, 1), (BOB, 2), (CAROL, 3)]);
+
+        let pending_txs = pending_txs(None);
+
+        let first = pending_txs.counts(&provider, [ALICE, BOB, CAROL]).await;
+        let second = pending_txs.counts(&provider, [ALICE, BOB, CAROL]).await;
+
+        assert_eq!(first, second);
+        assert_eq!(provider.queries(), 3);
+
+        let uncached = PendingTxs::new(PendingTxConfig {
+            cache_ms: 0,
+            ..PendingTxConfig::default()
+        });
+
+        uncached.counts(&provider, [ALICE]).await;
+        uncached.counts(&provider, [ALICE]).await;
+
+        assert_eq!(provider.queries(), 5);
+    }
+
+    #[tokio::test]
+    async fn cache_is_bounded() {
+        let addresses = (0..10_000_u64)
+            .map(|i| Address::left_padding_from(&i.to_be_bytes()))
+            .collect::<Vec<_>>();
+
+        let provider = MockProvider::new(addresses.iter().map(|address| (*address, 1)));
+
+        let pending_txs = PendingTxs::new(PendingTxConfig {
+            cache_max_entries: 100,
+            cache_ms: 60_000,
+            ..PendingTxConfig::default()
+        });
+
+        for chunk in addresses.chunks(10) {
+            pending_txs.counts(&provider, chunk.iter().copied()).await;
+        }
+
+        assert_eq!(pending_txs.cache.len(), 100);
+        assert_eq!(provider.queries(), 10_000);
+
+        // the most recently queried keys are still cached
+        pending_txs.counts(&provider, [addresses[9_999]]).await;
+        assert_eq!(provider.queries(), 10_000);
+    }
+
+    struct HungProvider;
+
+    impl TransactionCounts for HungProvider {
+        async fn transaction_counts(&self, _: Address) -> Result<Nonces, TransportError> {
+            std::future::pending().await
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn hung_provider_times_out() {
+        let start = tokio::time::Instant::now();
+
+        let counts = pending_txs(Some(3))
+            .counts(
+                &Bounded {
+                    provider: &HungProvider,
+                    timeouts: &TimeoutConfig::default(),
+                },
+                [ALICE],
+            )
+            .await;
+
+        assert_eq!(start.elapsed(), CallKind::Query.default_timeout());
+
+        // the key is still usable
+        assert!(!counts.all_saturated());
+        assert_eq!(counts.rank(&ALICE), Some((true, 0)));
+    }
+}
@@ -0,0 +1,658 @@
+//! Signed delivery of [`PublicEvent`]s to webhook endpoints, with retries and dead-lettering.
+
+use std::{
+    fmt,
+    num::NonZeroU32,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use hmac::{Hmac, Mac};
+use reqwest::{header::CONTENT_TYPE, StatusCode, Url};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::{fs::OpenOptions, io::AsyncWriteExt, sync::Mutex};
+use tokio_util::task::TaskTracker;
+use tracing::{debug, error, info, warn};
+use unionlabs::ErrorReporter;
+use voyager_message::public_event::PublicEvent;
+use zeroize::Zeroizing;
+
+/// The unix timestamp (in seconds) at which the request was signed.
+pub const TIMESTAMP_HEADER: &str = "x-voyager-timestamp";
+/// `sha256=<hex>`, the HMAC-SHA256 of `<timestamp>.<body>` keyed with the endpoint's secret.
+pub const SIGNATURE_HEADER: &str = "x-voyager-signature";
+/// The hex encoded sha256 of the body. This is the same for all attempts of a delivery, and can be
+/// used by receivers to deduplicate deliveries.
+pub const DELIVERY_ID_HEADER: &str = "x-voyager-delivery-id";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RetryConfig {
+    /// The maximum number of attempts per delivery, after which the event is dead-lettered.
+    #[serde(default = "default_max_attempts")]
+    pub max_attempts: NonZeroU32,
+    /// The backoff after the first failed attempt. This is doubled after every subsequent failed
+    /// attempt.
+    #[serde(default = "default_initial_backoff_milliseconds")]
+    pub initial_backoff_milliseconds: u64,
+    /// The maximum backoff between two attempts.
+    #[serde(default = "default_max_backoff_milliseconds")]
+    pub max_backoff_milliseconds: u64,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: default_max_attempts(),
+            initial_backoff_milliseconds: default_initial_backoff_milliseconds(),
+            max_backoff_milliseconds: default_max_backoff_milliseconds(),
+        }
+    }
+}
+
+fn default_max_attempts() -> NonZeroU32 {
+    NonZeroU32::new(5).unwrap()
+}
+
+fn default_initial_backoff_milliseconds() -> u64 {
+    500
+}
+
+fn default_max_backoff_milliseconds() -> u64 {
+    60_000
+}
+
+/// The time to wait after the `attempt`th (starting at 1) failed attempt.
+#[must_use]
+pub fn backoff(attempt: u32, config: &RetryConfig) -> Duration {
+    let factor = 2_u64.saturating_pow(attempt.saturating_sub(1));
+
+    Duration::from_millis(
+        config
+            .initial_backoff_milliseconds
+            .saturating_mul(factor)
+            .min(config.max_backoff_milliseconds),
+    )
+}
+
+/// The HMAC key of an endpoint.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Secret(Zeroizing<Vec<u8>>);
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Secret(<redacted>)")
+    }
+}
+
+impl Secret {
+    #[must_use]
+    pub fn new(secret: impl Into<Vec<u8>>) -> Self {
+        Self(Zeroizing::new(secret.into()))
+    }
+}
+
+/// Sign a request body sent at `timestamp`, returning the value of the [`SIGNATURE_HEADER`].
+#[must_use]
+pub fn sign(secret: &Secret, timestamp: u64, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(&secret.0).expect("hmac accepts keys of any length; qed;");
+
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+#[derive(Debug, Clone)]
+pub struct Endpoint {
+    pub url: Url,
+    pub secret: Secret,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum AttemptError {
+    #[error("error sending the request")]
+    Transport(#[source] reqwest::Error),
+    #[error("the endpoint responded with {0}")]
+    Status(StatusCode),
+}
+
+impl AttemptError {
+    /// Whether the delivery should be attempted again. Rate limits, server errors and transport
+    /// errors are retried, any other non-success response is not.
+    #[must_use]
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::Transport(_) => true,
+            Self::Status(status) => {
+                status.is_server_error() || *status == StatusCode::TOO_MANY_REQUESTS
+            }
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("delivery failed after {attempts} attempt(s)")]
+pub struct DeliveryError {
+    pub attempts: u32,
+    #[source]
+    pub source: AttemptError,
+}
+
+/// A record of an event that could not be delivered to an endpoint. These are appended to the
+/// dead-letter file, one per line.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeadLetter {
+    /// The unix timestamp (in milliseconds) at which the delivery was given up on.
+    pub failed_at: u64,
+    pub endpoint: String,
+    pub attempts: u32,
+    pub error: String,
+    pub event: PublicEvent,
+}
+
+#[derive(Debug, Default)]
+pub struct DeliveryStats {
+    delivered: AtomicU64,
+    retried: AtomicU64,
+    dead_lettered: AtomicU64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeliveryStatsSnapshot {
+    pub delivered: u64,
+    pub retried: u64,
+    pub dead_lettered: u64,
+}
+
+impl DeliveryStats {
+    #[must_use]
+    pub fn snapshot(&self) -> DeliveryStatsSnapshot {
+        DeliveryStatsSnapshot {
+            delivered: self.delivered.load(Ordering::Relaxed),
+            retried: self.retried.load(Ordering::Relaxed),
+            dead_lettered: self.dead_lettered.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Delivers events to all configured endpoints in the background.
+#[derive(Debug)]
+pub struct Deliveries {
+    client: reqwest::Client,
+    endpoints: Vec<Endpoint>,
+    retry: RetryConfig,
+    dead_letter_file: Option<PathBuf>,
+    /// Serializes writes to the dead-letter file, such that records are never interleaved.
+    dead_letter_lock: Mutex<()>,
+    tracker: TaskTracker,
+    stats: DeliveryStats,
+}
+
+impl Deliveries {
+    #[must_use]
+    pub fn new(
+        client: reqwest::Client,
+        endpoints: Vec<Endpoint>,
+        retry: RetryConfig,
+        dead_letter_file: Option<PathBuf>,
+    ) -> Self {
+        Self {
+            client,
+            endpoints,
+            retry,
+            dead_letter_file,
+            dead_letter_lock: Mutex::new(()),
+            tracker: TaskTracker::new(),
+            stats: DeliveryStats::default(),
+        }
+    }
+
+    #[must_use]
+    pub fn stats(&self) -> &DeliveryStats {
+        &self.stats
+    }
+
+    /// The tracker of all in-flight deliveries.
+    #[must_use]
+    pub fn tracker(&self) -> &TaskTracker {
+        &self.tracker
+    }
+
+    /// Spawn a delivery of `event` to every endpoint. This does not wait for the deliveries to
+    /// complete.
+    pub fn spawn(self: &Arc<Self>, event: PublicEvent) {
+        let event = Arc::new(event);
+
+        for idx in 0..self.endpoints.len() {
+            let this = self.clone();
+            let event = event.clone();
+
+            self.tracker.spawn(async move {
+                let endpoint = &this.endpoints[idx];
+
+                match this.deliver(endpoint, &event).await {
+                    Ok(attempts) => {
+                        debug!(url = %endpoint.url, attempts, "delivered event");
+
+                        this.stats.delivered.fetch_add(1, Ordering::Relaxed);
+                    }
+                    Err(err) => {
+                        this.dead_letter(DeadLetter {
+                            failed_at: unix_time().as_millis() as u64,
+                            endpoint: endpoint.url.to_string(),
+                            attempts: err.attempts,
+                            error: ErrorReporter(&err.source).to_string(),
+                            event: (*event).clone(),
+                        })
+                        .await;
+                    }
+                }
+            });
+        }
+    }
+
+    /// Deliver `event` to `endpoint`, retrying as per the [`RetryConfig`]. Returns the number of
+    /// attempts it took on success.
+    pub async fn deliver(
+        &self,
+        endpoint: &Endpoint,
+        event: &PublicEvent,
+    ) -> Result<u32, DeliveryError> {
+        let body = serde_json::to_vec(event).expect("serialization is infallible; qed;");
+        let delivery_id = hex::encode(Sha256::digest(&body));
+
+        let mut attempt = 0;
+
+        loop {
+            attempt += 1;
+
+            match self.attempt(endpoint, &delivery_id, &body).await {
+                Ok(()) => return Ok(attempt),
+                Err(err) if err.is_retryable() && attempt < self.retry.max_attempts.get() => {
+                    let backoff = backoff(attempt, &self.retry);
+
+                    warn!(
+                        url = %endpoint.url,
+                        %delivery_id,
+                        attempt,
+                        ?backoff,
+                        "delivery failed, retrying: {}",
+                        ErrorReporter(&err)
+                    );
+
+                    self.stats.retried.fetch_add(1, Ordering::Relaxed);
+
+                    tokio::time::sleep(backoff).await;
+                }
+                Err(source) => {
+                    return Err(DeliveryError {
+                        attempts: attempt,
+                        source,
+                    })
+                }
+            }
+        }
+    }
+
+    async fn attempt(
+        &self,
+        endpoint: &Endpoint,
+        delivery_id: &str,
+        body: &[u8],
+    ) -> Result<(), AttemptError> {
+        let timestamp = unix_time().as_secs();
+
+        let response = self
+            .client
+            .post(endpoint.url.clone())
+            .header(CONTENT_TYPE, "application/json")
+            .header(TIMESTAMP_HEADER, timestamp)
+            .header(SIGNATURE_HEADER, sign(&endpoint.secret, timestamp, body))
+            .header(DELIVERY_ID_HEADER, delivery_id)
+            .body(body.to_vec())
+            .send()
+            .await
+            .map_err(AttemptError::Transport)?;
+
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(AttemptError::Status(response.status()))
+        }
+    }
+
+    async fn dead_letter(&self, record: DeadLetter) {
+        self.stats.dead_lettered.fetch_add(1, Ordering::Relaxed);
+
+        let Some(path) = &self.dead_letter_file else {
+            error!(
+                endpoint = %record.endpoint,
+                attempts = record.attempts,
+                event = %serde_json::to_string(&record.event).expect("serialization is infallible; qed;"),
+                "dropping undeliverable event: {}",
+                record.error
+            );
+
+            return;
+        };
+
+        let mut line = serde_json::to_vec(&record).expect("serialization is infallible; qed;");
+        line.push(b'\n');
+
+        let _guard = self.dead_letter_lock.lock().await;
+
+        let res = async {
+            let mut file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .await?;
+
+            file.write_all(&line).await?;
+            file.flush().await
+        }
+        .await;
+
+        match res {
+            Ok(()) => info!(
+                endpoint = %record.endpoint,
+                attempts = record.attempts,
+                "dead-lettered undeliverable event: {}",
+                record.error
+            ),
+            Err(err) => error!(
+                path = %path.display(),
+                record = %String::from_utf8_lossy(&line).trim_end(),
+                "error writing dead-letter record: {}",
+                ErrorReporter(err)
+            ),
+        }
+    }
+}
+
+fn unix_time() -> Duration {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("the system clock is set before the unix epoch")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::SocketAddr, sync::Mutex as StdMutex};
+
+    use tokio::{
+        io::{AsyncBufReadExt, AsyncReadExt, BufReader},
+        net::TcpListener,
+    };
+    use voyager_message::public_event::{PublicEventKind, PUBLIC_EVENT_VERSION};
+
+    use super::*;
+
+    #[test]
+    fn backoff_is_exponential_and_capped() {
+        let config = RetryConfig {
+            max_attempts: NonZeroU32::new(10).unwrap(),
+            initial_backoff_milliseconds: 500,
+            max_backoff_milliseconds: 3_000,
+        };
+
+        assert_eq!(
+            (1..=6)
+                .map(|attempt| backoff(attempt, &config))
+                .collect::<Vec<_>>(),
+            [500, 1_000, 2_000, 3_000, 3_000, 3_000].map(Duration::from_millis)
+        );
+
+        // does not overflow
+        assert_eq!(backoff(u32::MAX, &config), Duration::from_millis(3_000));
+    }
+
+    #[test]
+    fn signature() {
+        // echo -n '1700000000.{"a":1}' | openssl dgst -sha256 -hmac secret
+        assert_eq!(
+            sign(&Secret::new("secret"), 1_700_000_000, br#"{"a":1}"#),
+            "sha256=49f24e537407743fa4a0242bb63b94b9a47ee99cbbe071ccd8a22550ae411686"
+        );
+
+        assert_ne!(
+            sign(&Secret::new("secret"), 1_700_000_000, br#"{"a":1}"#),
+            sign(&Secret::new("secret"), 1_700_000_001, br#"{"a":1}"#),
+        );
+        assert_ne!(
+            sign(&Secret::new("secret"), 1_700_000_000, br#"{"a":1}"#),
+            sign(&Secret::new("other"), 1_700_000_000, br#"{"a":1}"#),
+        );
+    }
+
+    #[test]
+    fn secret_is_redacted() {
+        assert_eq!(
+            format!("{:?}", Secret::new("hunter2")),
+            "Secret(<redacted>)"
+        );
+    }
+
+    #[derive(Debug)]
+    struct Request {
+        headers: Vec<(String, String)>,
+        body: Vec<u8>,
+    }
+
+    impl Request {
+        fn header(&self, name: &str) -> &str {
+            &self
+                .headers
+                .iter()
+                .find(|(n, _)| n == name)
+                .unwrap_or_else(|| panic!("missing header {name}"))
+                .1
+        }
+    }
+
+    /// A minimal http server that responds to the nth request with the nth of `statuses`, and
+    /// records all requests.
+    async fn mock_server(statuses: Vec<u16>) -> (SocketAddr, Arc<StdMutex<Vec<Request>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let requests = Arc::new(StdMutex::new(vec![]));
+
+        tokio::spawn({
+            let requests = requests.clone();
+
+            async move {
+                for status in statuses {
+                    let (stream, _) = listener.accept().await.unwrap();
+                    let mut stream = BufReader::new(stream);
+
+                    let mut headers = vec![];
+                    loop {
+                        let mut line = String::new();
+                        stream.read_line(&mut line).await.unwrap();
+
+                        let line = line.trim_end();
+                        if line.is_empty() {
+                            break;
+                        }
+
+                        // the request line has no colon and is skipped
+                        if let Some((name, value)) = line.split_once(':') {
+                            headers.push((name.trim().to_lowercase(), value.trim().to_owned()));
+                        }
+                    }
+
+                    let content_length = headers
+                        .iter()
+                        .find(|(name, _)| name == "content-length")
+                        .map_or(0, |(_, value)| value.parse().unwrap());
+
+                    let mut body = vec![0; content_length];
+                    stream.read_exact(&mut body).await.unwrap();
+
+                    requests.lock().unwrap().push(Request { headers, body });
+
+                    stream
+                        .get_mut()
+                        .write_all(
+                            format!(
+                                "HTTP/1.1 {status} Mock\r\ncontent-length: 0\r\nconnection: close\r\n\r\n"
+                            )
+                            .as_bytes(),
+                        )
+                        .await
+                        .unwrap();
+                    stream.get_mut().shutdown().await.unwrap();
+                }
+            }
+        });
+
+        (addr, requests)
+    }
+
+    fn event() -> PublicEvent {
+        PublicEvent {
+            version: PUBLIC_EVENT_VERSION,
+            chain_id: "union-testnet-9".to_owned(),
+            counterparty_chain_id: "stargaze-1".to_owned(),
+            ibc_spec_id: "ibc-classic".to_owned(),
+            tx_hash: "0x0000000000000000000000000000000000000000000000000000000000000001"
+                .to_owned(),
+            provable_height: "1-100".to_owned(),
+            observed_at: Some(1_700_000_000_000),
+            event: PublicEventKind::Other {
+                name: Some("create_client".to_owned()),
+            },
+        }
+    }
+
+    fn deliveries(
+        addr: SocketAddr,
+        max_attempts: u32,
+        dead_letter_file: Option<PathBuf>,
+    ) -> Arc<Deliveries> {
+        Arc::new(Deliveries::new(
+            reqwest::Client::new(),
+            vec![Endpoint {
+                url: format!("http://{addr}/hook").parse().unwrap(),
+                secret: Secret::new("secret"),
+            }],
+            RetryConfig {
+                max_attempts: NonZeroU32::new(max_attempts).unwrap(),
+                initial_backoff_milliseconds: 1,
+                max_backoff_milliseconds: 10,
+            },
+            dead_letter_file,
+        ))
+    }
+
+    #[tokio::test]
+    async fn delivery_is_retried_and_signed() {
+        let (addr, requests) = mock_server(vec![503, 500, 200]).await;
+
+        let deliveries = deliveries(addr, 5, None);
+
+        let attempts = deliveries
+            .deliver(&deliveries.endpoints[0], &event())
+            .await
+            .unwrap();
+
+        assert_eq!(attempts, 3);
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 3);
+
+        for request in requests.iter() {
+            assert_eq!(request.header("content-type"), "application/json");
+
+            let timestamp = request.header(TIMESTAMP_HEADER).parse::<u64>().unwrap();
+
+            assert_eq!(
+                request.header(SIGNATURE_HEADER),
+                sign(&Secret::new("secret"), timestamp, &request.body)
+            );
+            assert_eq!(
+                request.header(DELIVERY_ID_HEADER),
+                hex::encode(Sha256::digest(&request.body))
+            );
+            assert_eq!(
+                serde_json::from_slice::<PublicEvent>(&request.body).unwrap(),
+                event()
+            );
+        }
+
+        assert_eq!(deliveries.stats().snapshot().retried, 2);
+    }
+
+    #[tokio::test]
+    async fn dead_letter_after_max_attempts() {
+        let (addr, requests) = mock_server(vec![500, 502, 503]).await;
+
+        let path = std::env::temp_dir().join(format!(
+            "voyager-plugin-webhook-dead-letter-{}.jsonl",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+
+        let deliveries = deliveries(addr, 3, Some(path.clone()));
+
+        deliveries.spawn(event());
+
+        deliveries.tracker().close();
+        deliveries.tracker().wait().await;
+
+        assert_eq!(requests.lock().unwrap().len(), 3);
+
+        let records = std::fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str::<DeadLetter>(line).unwrap())
+            .collect::<Vec<_>>();
+
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].endpoint, format!("http://{addr}/hook"));
+        assert_eq!(records[0].attempts, 3);
+        assert_eq!(
+            records[0].error,
+            "the endpoint responded with 503 Service Unavailable"
+        );
+        assert_eq!(records[0].event, event());
+
+        assert_eq!(
+            deliveries.stats().snapshot(),
+            DeliveryStatsSnapshot {
+                delivered: 0,
+                retried: 2,
+                dead_lettered: 1,
+            }
+        );
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn client_errors_are_not_retried() {
+        let (addr, requests) = mock_server(vec![400]).await;
+
+        let deliveries = deliveries(addr, 5, None);
+
+        let err = deliveries
+            .deliver(&deliveries.endpoints[0], &event())
+            .await
+            .unwrap_err();
+
+        assert_eq!(err.attempts, 1);
+        assert!(matches!(
+            err.source,
+            AttemptError::Status(StatusCode::BAD_REQUEST)
+        ));
+        assert_eq!(requests.lock().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,392 @@
+use std::{
+    collections::{BTreeSet, VecDeque},
+    env,
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use jsonrpsee::{
+    core::{async_trait, RpcResult},
+    Extensions,
+};
+use schemars::gen::{SchemaGenerator, SchemaSettings};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::{instrument, trace};
+use unionlabs::never::Never;
+use voyager_message::{
+    cmd::CmdOutput,
+    data::Data,
+    into_value,
+    module::{PluginInfo, PluginServer},
+    public_event::{PublicEvent, PublicEventKind},
+    Plugin, VoyagerMessage,
+};
+use voyager_vm::{pass::PassResult, BoxDynError, Op};
+use zeroize::Zeroizing;
+
+use crate::delivery::{Deliveries, DeliveryStatsSnapshot, Endpoint, RetryConfig, Secret};
+
+pub mod delivery;
+
+#[tokio::main(flavor = "multi_thread")]
+async fn main() {
+    Module::run().await
+}
+
+#[derive(Debug, Clone)]
+pub struct Module {
+    pub filter: EventFilter,
+    pub deliveries: Arc<Deliveries>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// The endpoints that every event is delivered to.
+    pub endpoints: Vec<EndpointConfig>,
+    /// Only deliver events of these `event_type`s. If empty, events of all types are delivered.
+    #[serde(default)]
+    pub event_types: BTreeSet<String>,
+    /// Only deliver events that reference any of these channel ids, on either end. If empty, events
+    /// are not filtered by channel.
+    #[serde(default)]
+    pub channels: BTreeSet<String>,
+    #[serde(default)]
+    pub retry: RetryConfig,
+    /// The timeout of a single delivery attempt.
+    #[serde(default = "default_timeout_seconds")]
+    pub timeout_seconds: u64,
+    /// The file that records of undeliverable events are appended to. If not set, undeliverable
+    /// events are logged and dropped.
+    #[serde(default)]
+    pub dead_letter_file: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EndpointConfig {
+    pub url: String,
+    /// The environment variable containing the HMAC secret for this endpoint.
+    pub secret_env: String,
+}
+
+fn default_timeout_seconds() -> u64 {
+    10
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error("unknown event type `{event_type}`, expected one of {}", PublicEventKind::NAMES.join(", "))]
+    UnknownEventType { event_type: String },
+    #[error("invalid endpoint url `{url}`")]
+    InvalidUrl {
+        url: String,
+        #[source]
+        source: url::ParseError,
+    },
+    #[error("unable to read environment variable {var}")]
+    Env {
+        var: String,
+        #[source]
+        source: env::VarError,
+    },
+    #[error("environment variable {var} is empty")]
+    EmptyEnv { var: String },
+}
+
+#[derive(clap::Subcommand)]
+pub enum Cmd {
+    /// Print the JSON schema of the events that are delivered.
+    Schema,
+}
+
+/// Selects which events are delivered.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EventFilter {
+    pub event_types: BTreeSet<String>,
+    pub channels: BTreeSet<String>,
+}
+
+impl EventFilter {
+    pub fn new(
+        event_types: BTreeSet<String>,
+        channels: BTreeSet<String>,
+    ) -> Result<Self, ConfigError> {
+        if let Some(event_type) = event_types
+            .iter()
+            .find(|event_type| !PublicEventKind::NAMES.contains(&event_type.as_str()))
+        {
+            return Err(ConfigError::UnknownEventType {
+                event_type: event_type.clone(),
+            });
+        }
+
+        Ok(Self {
+            event_types,
+            channels,
+        })
+    }
+
+    /// Note that if any channels are configured, events that don't reference a channel (i.e.
+    /// connection handshakes) never match.
+    #[must_use]
+    pub fn matches(&self, event: &PublicEvent) -> bool {
+        (self.event_types.is_empty() || self.event_types.contains(event.event.name()))
+            && (self.channels.is_empty()
+                || event
+                    .event
+                    .channel_ids()
+                    .into_iter()
+                    .any(|channel_id| self.channels.contains(channel_id)))
+    }
+}
+
+impl Plugin for Module {
+    type Call = Never;
+    type Callback = Never;
+
+    type Config = Config;
+    type Cmd = Cmd;
+
+    async fn new(config: Self::Config) -> Result<Self, BoxDynError> {
+        let filter = EventFilter::new(config.event_types, config.channels)?;
+
+        let endpoints = config
+            .endpoints
+            .into_iter()
+            .map(|endpoint| {
+                Ok(Endpoint {
+                    url: endpoint
+                        .url
+                        .parse()
+                        .map_err(|source| ConfigError::InvalidUrl {
+                            url: endpoint.url.clone(),
+                            source,
+                        })?,
+                    secret: read_secret(&endpoint.secret_env)?,
+                })
+            })
+            .collect::<Result<Vec<_>, ConfigError>>()?;
+
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(config.timeout_seconds))
+            .build()?;
+
+        Ok(Self {
+            filter,
+            deliveries: Arc::new(Deliveries::new(
+                client,
+                endpoints,
+                config.retry,
+                config.dead_letter_file,
+            )),
+        })
+    }
+
+    fn info(_config: Self::Config) -> PluginInfo {
+        PluginInfo {
+            name: plugin_name(),
+            interest_filter: include_str!("interest_filter.jq").to_owned(),
+            kind: None,
+            chains: vec![],
+            ibc_specs: vec![],
+        }
+    }
+
+    async fn cmd(_config: Self::Config, cmd: Self::Cmd) -> Result<Box<dyn CmdOutput>, BoxDynError> {
+        match cmd {
+            Cmd::Schema => Ok(Box::new(SchemaOutput(into_value(
+                SchemaGenerator::new(SchemaSettings::draft2019_09().with(|s| {
+                    s.option_nullable = true;
+                    s.option_add_null_type = false;
+                }))
+                .into_root_schema_for::<PublicEvent>(),
+            )))),
+        }
+    }
+}
+
+/// The output of [`Cmd::Schema`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(transparent)]
+pub struct SchemaOutput(pub Value);
+
+impl CmdOutput for SchemaOutput {
+    fn to_json(&self) -> Value {
+        self.0.clone()
+    }
+
+    fn to_text(&self) -> String {
+        serde_json::to_string_pretty(&self.0).expect("serialization is infallible; qed;")
+    }
+}
+
+/// The state of the plugin, as returned by `debug_state`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DebugState {
+    pub in_flight: usize,
+    #[serde(flatten)]
+    pub stats: DeliveryStatsSnapshot,
+}
+
+fn read_secret(var: &str) -> Result<Secret, ConfigError> {
+    let value = Zeroizing::new(env::var(var).map_err(|source| ConfigError::Env {
+        var: var.to_owned(),
+        source,
+    })?);
+
+    if value.trim().is_empty() {
+        return Err(ConfigError::EmptyEnv {
+            var: var.to_owned(),
+        });
+    }
+
+    Ok(Secret::new(value.trim()))
+}
+
+fn plugin_name() -> String {
+    pub const PLUGIN_NAME: &str = env!("CARGO_PKG_NAME");
+
+    PLUGIN_NAME.to_owned()
+}
+
+impl Module {
+    /// Spawn deliveries of all top level chain events in `msgs` that match the filter.
+    ///
+    /// The events are only observed by this plugin (the ops are copies of events that are handled
+    /// by the other plugins as usual), so they are all dropped afterwards.
+    pub fn webhook_pass(&self, msgs: Vec<Op<VoyagerMessage>>) -> PassResult<VoyagerMessage> {
+        let observed_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("the system clock is set before the unix epoch")
+            .as_millis() as u64;
+
+        for msg in &msgs {
+            let Op::Data(Data::IbcEvent(chain_event)) = msg else {
+                continue;
+            };
+
+            let event = PublicEvent::from(chain_event.clone()).with_observed_at(observed_at);
+
+            if self.filter.matches(&event) {
+                self.deliveries.spawn(event);
+            } else {
+                trace!(
+                    event_type = event.event.name(),
+                    tx_hash = %event.tx_hash,
+                    "event does not match the filter"
+                );
+            }
+        }
+
+        PassResult::default()
+    }
+}
+
+#[async_trait]
+impl PluginServer<Never, Never> for Module {
+    #[instrument(skip_all)]
+    async fn run_pass(
+        &self,
+        _: &Extensions,
+        msgs: Vec<Op<VoyagerMessage>>,
+    ) -> RpcResult<PassResult<VoyagerMessage>> {
+        Ok(self.webhook_pass(msgs))
+    }
+
+    #[instrument]
+    async fn call(&self, _: &Extensions, msg: Never) -> RpcResult<Op<VoyagerMessage>> {
+        match msg {}
+    }
+
+    #[instrument]
+    async fn callback(
+        &self,
+        _: &Extensions,
+        cb: Never,
+        _data: VecDeque<Data>,
+    ) -> RpcResult<Op<VoyagerMessage>> {
+        match cb {}
+    }
+
+    #[instrument(skip_all)]
+    async fn debug_state(&self) -> RpcResult<Value> {
+        Ok(into_value(DebugState {
+            in_flight: self.deliveries.tracker().len(),
+            stats: self.deliveries.stats().snapshot(),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use voyager_message::public_event::{ChannelHandshake, PUBLIC_EVENT_VERSION};
+
+    use super::*;
+
+    fn event(kind: PublicEventKind) -> PublicEvent {
+        PublicEvent {
+            version: PUBLIC_EVENT_VERSION,
+            chain_id: "union-testnet-9".to_owned(),
+            counterparty_chain_id: "stargaze-1".to_owned(),
+            ibc_spec_id: "ibc-classic".to_owned(),
+            tx_hash: "0x00".to_owned(),
+            provable_height: "1-100".to_owned(),
+            observed_at: None,
+            event: kind,
+        }
+    }
+
+    fn channel_open_init(channel_id: &str) -> PublicEvent {
+        event(PublicEventKind::ChannelOpenInit(ChannelHandshake {
+            port_id: "transfer".to_owned(),
+            channel_id: channel_id.to_owned(),
+            counterparty_port_id: "transfer".to_owned(),
+            counterparty_channel_id: None,
+            client_id: "07-tendermint-0".to_owned(),
+            counterparty_client_id: "08-wasm-0".to_owned(),
+            version: "ics20-1".to_owned(),
+        }))
+    }
+
+    #[test]
+    fn filter() {
+        let set = |xs: &[&str]| xs.iter().map(|x| x.to_string()).collect::<BTreeSet<_>>();
+
+        let other = event(PublicEventKind::Other { name: None });
+
+        let filter = EventFilter::default();
+        assert!(filter.matches(&other));
+        assert!(filter.matches(&channel_open_init("channel-1")));
+
+        let filter = EventFilter::new(set(&["channel_open_init"]), set(&[])).unwrap();
+        assert!(!filter.matches(&other));
+        assert!(filter.matches(&channel_open_init("channel-1")));
+
+        let filter = EventFilter::new(set(&[]), set(&["channel-1"])).unwrap();
+        assert!(!filter.matches(&other));
+        assert!(filter.matches(&channel_open_init("channel-1")));
+        assert!(!filter.matches(&channel_open_init("channel-2")));
+
+        assert!(matches!(
+            EventFilter::new(set(&["channel_open"]), set(&[])),
+            Err(ConfigError::UnknownEventType { event_type }) if event_type == "channel_open"
+        ));
+    }
+
+    #[test]
+    fn config_defaults() {
+        let config = serde_json::from_value::<Config>(serde_json::json!({
+            "endpoints": [{ "url": "https://example.com/hook", "secret_env": "WEBHOOK_SECRET" }]
+        }))
+        .unwrap();
+
+        assert!(config.event_types.is_empty());
+        assert!(config.channels.is_empty());
+        assert_eq!(config.retry, RetryConfig::default());
+        assert_eq!(config.timeout_seconds, 10);
+        assert_eq!(config.dead_letter_file, None);
+    }
+}
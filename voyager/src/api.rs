@@ -3,7 +3,6 @@ use std::net::SocketAddr;
 use axum::{
     extract::State,
     routing::{get, post},
-    Json,
 };
 use futures::{
     channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender},
@@ -12,7 +11,7 @@ use futures::{
 use prometheus::TextEncoder;
 use reqwest::StatusCode;
 use tracing::error;
-use voyager_message::VoyagerMessage;
+use voyager_message::{decode::decode_op, VoyagerMessage};
 use voyager_vm::Op;
 
 pub fn run(laddr: &SocketAddr) -> UnboundedReceiver<Op<VoyagerMessage>> {
@@ -39,11 +38,15 @@ pub fn run(laddr: &SocketAddr) -> UnboundedReceiver<Op<VoyagerMessage>> {
 // #[axum::debug_handler]
 async fn enqueue(
     State(mut sender): State<UnboundedSender<Op<VoyagerMessage>>>,
-    Json(op): Json<Op<VoyagerMessage>>,
-) -> StatusCode {
+    body: String,
+) -> Result<StatusCode, (StatusCode, String)> {
+    // decoded manually instead of with the `Json` extractor, such that the error names the
+    // offending field
+    let op = decode_op(&body).map_err(|err| (StatusCode::BAD_REQUEST, err.to_string()))?;
+
     sender.send(op).await.expect("receiver should not close");
 
-    StatusCode::OK
+    Ok(StatusCode::OK)
 }
 
 async fn metrics() -> Result<String, StatusCode> {
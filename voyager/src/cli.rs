@@ -1,10 +1,21 @@
-use std::{ffi::OsString, str::FromStr};
+use std::{ffi::OsString, path::PathBuf, str::FromStr};
 
 use clap::{self, Parser, Subcommand};
-use unionlabs::{self, bounded::BoundedI64, ibc::core::client::height::Height, result_unwrap};
+use unionlabs::{
+    self,
+    bounded::BoundedI64,
+    hash::H256,
+    ibc::core::{channel::order::Order, client::height::Height},
+    result_unwrap,
+};
 use voyager_message::{
+    call::EventDensity,
     core::{ChainId, ClientType, IbcInterface, IbcSpecId, QueryHeight},
+    decode::decode_op,
+    freeze::ClientRef,
     module::{ClientModuleInfo, ConsensusModuleInfo, ProofModuleInfo, StateModuleInfo},
+    purge::PurgeFilter,
+    relay_cost::PacketRef,
     RawClientId, VoyagerMessage,
 };
 use voyager_vm::{BoxDynError, Op};
@@ -54,6 +65,16 @@ pub enum LogFormat {
     Json,
 }
 
+#[derive(Debug, Clone, PartialEq, clap::ValueEnum, derive_more::Display)]
+pub enum OpGraphFormat {
+    /// A Graphviz DOT graph.
+    #[display(fmt = "dot")]
+    Dot,
+    /// A simplified JSON tree.
+    #[display(fmt = "json")]
+    Json,
+}
+
 #[derive(Debug, Subcommand)]
 #[allow(clippy::large_enum_variant)]
 pub enum Command {
@@ -61,13 +82,21 @@ pub enum Command {
     #[command(subcommand)]
     Config(ConfigCmd),
     // Handshake(HandshakeCmd),
-    /// Construct a `FetchBlocks` op to send to the specified chain.
+    /// Construct a `FetchBlocks` op (or a `FetchBlockRange` op, if `--to` is set) to send to the
+    /// specified chain.
     InitFetch {
         #[arg(value_parser(|s: &str| Ok::<_, BoxDynError>(ChainId::new(s.to_owned()))))]
         chain_id: ChainId,
         /// The height to start fetching blocks at.
         #[arg(long, short = 'H', default_value_t = QueryHeight::Latest)]
         height: QueryHeight,
+        /// Stop fetching blocks after this height (inclusive), instead of following the chain.
+        #[arg(long)]
+        to: Option<u64>,
+        /// The expected amount of IBC events per block in the range, used to size the first
+        /// chunk of blocks that is fetched.
+        #[arg(long, requires = "to")]
+        hint_events_per_block: Option<EventDensity>,
         /// Automatically enqueue the op.
         #[arg(long, short = 'e', default_value_t = false)]
         enqueue: bool,
@@ -86,6 +115,41 @@ pub enum Command {
     Rpc(RpcCmd),
     #[command(subcommand)]
     Msg(MsgCmd),
+    /// Find connection and channel handshakes between two chains that are
+    /// stuck part of the way through, and resume them.
+    ///
+    /// Prints a report of all stuck handshakes. Unless --dry-run is passed,
+    /// the ops to resume the handshakes are enqueued.
+    ReconcileHandshakes {
+        #[arg(value_parser(|s: &str| ok(ChainId::new(s.to_owned()))))]
+        chain_a: ChainId,
+        #[arg(value_parser(|s: &str| ok(ChainId::new(s.to_owned()))))]
+        chain_b: ChainId,
+        #[arg(value_parser(|s: &str| ok(IbcSpecId::new(s.to_owned()))))]
+        ibc_spec_id: IbcSpecId,
+        /// Only print the report, without enqueueing any ops.
+        #[arg(long, default_value_t = false)]
+        dry_run: bool,
+        /// The amount of connections and channels to query at once.
+        #[arg(long, default_value_t = 20)]
+        page_size: u32,
+        /// The trusting period of the clients, in seconds. If provided, clients
+        /// whose latest consensus state is older than this are considered
+        /// expired.
+        #[arg(long)]
+        trusting_period: Option<u64>,
+    },
+    /// Render an op as a graph, for debugging.
+    ///
+    /// Every message in the op is summarized by its type and identifying
+    /// fields (such as the chain id).
+    RenderOp {
+        /// The file containing the JSON encoded op.
+        #[arg(long)]
+        file: PathBuf,
+        #[arg(long, default_value_t = OpGraphFormat::Dot)]
+        format: OpGraphFormat,
+    },
     // Query {
     //     #[arg(value_parser(|s: &str| Ok::<_, BoxDynError>(ChainId::new(s.to_owned()))))]
     //     on: ChainId,
@@ -113,7 +177,7 @@ pub enum QueueCmd {
     /// Enqueue a new op to the queue of an already running voyager instance.
     #[command(alias = "e")]
     Enqueue {
-        #[arg(value_parser(|s: &str| serde_json::from_str::<Op<VoyagerMessage>>(s)))]
+        #[arg(value_parser(|s: &str| decode_op(s)))]
         op: Op<VoyagerMessage>,
     },
 
@@ -209,6 +273,111 @@ pub enum RpcCmd {
         #[arg(long, short = 'd', default_value_t = false)]
         decode: bool,
     },
+    /// List the heights of the consensus states of a client, highest first.
+    ConsensusStateHeights {
+        #[arg(value_parser(|s: &str| ok(ChainId::new(s.to_owned()))))]
+        on: ChainId,
+        client_id: RawClientId,
+        #[arg(value_parser(|s: &str| ok(IbcSpecId::new(s.to_owned()))))]
+        ibc_spec_id: IbcSpecId,
+        /// The number of heights to skip.
+        #[arg(long, default_value_t = 0)]
+        offset: u64,
+        #[arg(long, default_value_t = 100)]
+        limit: u64,
+    },
+    /// Print the timestamps (in nanoseconds since the unix epoch) of the
+    /// blocks at the specified heights, in the order they were provided.
+    TimestampsAtHeights {
+        #[arg(value_parser(|s: &str| ok(ChainId::new(s.to_owned()))))]
+        on: ChainId,
+        #[arg(required = true)]
+        heights: Vec<Height>,
+    },
+    /// Compare the client state of a client against the self client state of
+    /// the chain it tracks, and print the fields that differ.
+    ///
+    /// Mismatches that are known to break the client (i.e. an unbonding
+    /// period that has been shortened below the trusting period of the
+    /// client) are flagged.
+    DiffClientState {
+        #[arg(value_parser(|s: &str| ok(ChainId::new(s.to_owned()))))]
+        on: ChainId,
+        client_id: RawClientId,
+        #[arg(value_parser(|s: &str| ok(IbcSpecId::new(s.to_owned()))))]
+        ibc_spec_id: IbcSpecId,
+        /// Also compare the heights and timestamps of the client states.
+        #[arg(long, default_value_t = false)]
+        include_heights: bool,
+    },
+    /// Reload the config of a running plugin from the voyager config file.
+    ///
+    /// Only settings that the plugin supports reloading are applied, the
+    /// report of changed and rejected fields is printed.
+    ReloadPlugin {
+        plugin_name: String,
+    },
+    /// Print the current state of a running plugin, for debugging.
+    PluginDebugState {
+        plugin_name: String,
+    },
+    /// Re-run the event extraction of a running event source plugin for a
+    /// transaction, and print the results.
+    PluginDebugParseTx {
+        plugin_name: String,
+        tx_hash: H256,
+    },
+    /// Export the caches of a running plugin (or of voyager itself, if no
+    /// plugin is specified) as a json snapshot, i.e. to warm up a standby
+    /// instance with `import-caches`.
+    ExportCaches {
+        #[arg(long)]
+        plugin_name: Option<String>,
+        /// The file to write the snapshot to. If not set, the snapshot is
+        /// printed to stdout.
+        #[arg(long)]
+        out: Option<OsString>,
+    },
+    /// Merge a snapshot written by `export-caches` into the caches of a
+    /// running plugin (or of voyager itself, if no plugin is specified).
+    /// Local entries are never overwritten with older entries.
+    ImportCaches {
+        #[arg(long)]
+        plugin_name: Option<String>,
+        /// The file to read the snapshot from.
+        file: OsString,
+    },
+    /// Estimate the gas and fees of relaying a pending packet, on both the
+    /// destination (recv) and source (ack) chains. Nothing is submitted.
+    EstimateRelayCost {
+        /// The packet to estimate, as JSON.
+        #[arg(value_parser(|s: &str| serde_json::from_str::<PacketRef>(s)))]
+        packet_ref: PacketRef,
+    },
+    /// Print the highest packet sequences relayed on a channel, as recorded
+    /// by the transaction plugin of the chain.
+    RelayProgress {
+        #[arg(value_parser(|s: &str| ok(ChainId::new(s.to_owned()))))]
+        on: ChainId,
+        channel: String,
+    },
+    /// Resume relaying over the connections of a frozen client, once the
+    /// freeze has been reviewed. Prints the relaying that was suppressed.
+    UnfreezeRelaying {
+        /// The frozen client, as JSON.
+        #[arg(value_parser(|s: &str| serde_json::from_str::<ClientRef>(s)))]
+        client: ClientRef,
+    },
+    /// Remove the queued ops that match a filter, i.e. to clear out work for an
+    /// abandoned channel. Prints the number of matched ops by type.
+    PurgeOps {
+        /// The filter to match ops against, as JSON.
+        #[arg(value_parser(|s: &str| serde_json::from_str::<PurgeFilter>(s)))]
+        filter: PurgeFilter,
+        /// Only report the matching ops, without removing them.
+        #[arg(long, default_value_t = false)]
+        dry_run: bool,
+    },
 }
 
 #[derive(Debug, Subcommand)]
@@ -233,6 +402,96 @@ pub enum MsgCmd {
             default_value_t = serde_json::Value::Null
         )]
         metadata: serde_json::Value,
+        /// The gRPC endpoint of the host chain, used to verify that the
+        /// checksum of an 08-wasm client is stored on it. Required for 08-wasm
+        /// clients unless `--skip-checksum-verification` is passed.
+        #[arg(long)]
+        grpc_url: Option<String>,
+        /// Don't verify the 08-wasm checksum, for constructing the message
+        /// offline.
+        #[arg(long, default_value_t = false)]
+        skip_checksum_verification: bool,
+        /// The gRPC endpoint of the tracked chain, used to check the client
+        /// state against the unbonding period of the chain. Required for
+        /// 07-tendermint and cometbls clients unless `--offline` is passed.
+        #[arg(long)]
+        tracking_grpc_url: Option<String>,
+        /// Skip the client state checks that require querying the tracked
+        /// chain.
+        #[arg(long, default_value_t = false)]
+        offline: bool,
+
+        /// Automatically enqueue the op.
+        #[arg(long, short = 'e', default_value_t = false)]
+        enqueue: bool,
+    },
+    /// Check a client state for values that are invalid or almost certainly a
+    /// mistake, before creating a client with it.
+    ///
+    /// Prints the violations found. Exits with an error if any of them would
+    /// prevent the client from working.
+    ValidateClientState {
+        /// The client state, as JSON.
+        file: PathBuf,
+        #[arg(long, value_parser(|s: &str| ok(ClientType::new(s.to_owned()))))]
+        client_type: ClientType,
+        /// The gRPC endpoint of the tracked chain, used to check the client
+        /// state against the unbonding period of the chain. Required unless
+        /// `--offline` is passed.
+        #[arg(long)]
+        tracking_grpc_url: Option<String>,
+        /// Skip the checks that require querying the tracked chain.
+        #[arg(long, default_value_t = false)]
+        offline: bool,
+    },
+    /// Start a connection handshake on top of an existing client.
+    ///
+    /// The client must exist and be active. Once the op is enqueued, the rest
+    /// of the handshake is relayed as usual.
+    InitConnection {
+        #[arg(long, value_parser(|s: &str| ok(ChainId::new(s.to_owned()))))]
+        on: ChainId,
+        #[arg(long, value_parser(|s: &str| ok(IbcSpecId::new(s.to_owned()))))]
+        ibc_spec_id: IbcSpecId,
+        client_id: RawClientId,
+        counterparty_client_id: RawClientId,
+        #[arg(long, default_value_t = 0)]
+        delay_period: u64,
+
+        /// Automatically enqueue the op.
+        #[arg(long, short = 'e', default_value_t = false)]
+        enqueue: bool,
+    },
+    /// Start a channel handshake on top of an existing connection.
+    ///
+    /// The connection must be open, and its client must be active. Once the
+    /// op is enqueued, the rest of the handshake is relayed as usual.
+    InitChannel {
+        #[arg(long, value_parser(|s: &str| ok(ChainId::new(s.to_owned()))))]
+        on: ChainId,
+        #[arg(long, value_parser(|s: &str| ok(IbcSpecId::new(s.to_owned()))))]
+        ibc_spec_id: IbcSpecId,
+        connection_id: u32,
+        port_id: String,
+        counterparty_port_id: String,
+        #[arg(long)]
+        version: String,
+        #[arg(
+            long,
+            value_parser(|s: &str| serde_json::from_value::<Order>(serde_json::Value::String(s.to_owned()))),
+            default_value = "unordered"
+        )]
+        ordering: Order,
+        /// Wrap the version with the version of the ICS-29 fee middleware
+        /// (ibc-classic only). If not set, this is detected from the fee
+        /// enabled channels of the counterparty if `--counterparty-grpc-url`
+        /// is passed, and is otherwise disabled.
+        #[arg(long)]
+        wrap_fee_version: Option<bool>,
+        /// The gRPC endpoint of the counterparty chain, used to detect whether
+        /// the counterparty port has the fee middleware enabled.
+        #[arg(long)]
+        counterparty_grpc_url: Option<String>,
 
         /// Automatically enqueue the op.
         #[arg(long, short = 'e', default_value_t = false)]
@@ -1,8 +1,14 @@
-use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::{
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    path::PathBuf,
+};
 
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use voyager_message::context::{ModulesConfig, PluginConfig};
+use voyager_message::{
+    context::{ModulesConfig, PluginConfig},
+    rpc::server::cache::CacheConfig,
+};
 
 use crate::queue::QueueConfig;
 
@@ -29,6 +35,12 @@ pub struct VoyagerConfig {
     // TODO: Specify per plugin
     #[serde(default = "default_optimizer_delay_milliseconds")]
     pub optimizer_delay_milliseconds: u64,
+    #[serde(default)]
+    pub cache: CacheConfig,
+    /// The file that frozen clients are recorded in. Transaction plugins that are configured with
+    /// the same file suppress the relaying over the connections of these clients.
+    #[serde(default = "default_freeze_list")]
+    pub freeze_list: PathBuf,
 }
 
 #[must_use]
@@ -48,3 +60,8 @@ pub const fn default_rpc_laddr() -> SocketAddr {
 pub const fn default_optimizer_delay_milliseconds() -> u64 {
     100
 }
+
+#[must_use]
+pub fn default_freeze_list() -> PathBuf {
+    "frozen-relaying.json".into()
+}
@@ -11,9 +11,10 @@
 
 use std::{
     collections::HashMap, fmt::Write, fs::read_to_string, iter, net::SocketAddr, process::ExitCode,
+    time::Duration,
 };
 
-use anyhow::{anyhow, Context as _};
+use anyhow::{anyhow, bail, Context as _};
 use clap::Parser;
 use ibc_classic_spec::IbcClassic;
 use ibc_union_spec::IbcUnion;
@@ -23,24 +24,33 @@ use serde::Serialize;
 use tikv_jemallocator::Jemalloc;
 use tracing::info;
 use tracing_subscriber::EnvFilter;
+use unionlabs::ibc::core::client::height::Height;
 use voyager_message::{
-    call::FetchBlocks,
+    cache_snapshot::CacheSnapshot,
+    call::{FetchBlockRange, FetchBlocks},
+    client_state_validation::{ClientStateValidator, GrpcUnbondingPeriod},
+    consensus_heights::Pagination,
     context::{get_plugin_info, Context, IbcSpecHandler, ModulesConfig},
     core::{IbcSpec, QueryHeight},
+    decode::decode_op,
+    encoding::GrpcWasmChecksums,
     filter::{make_filter, run_filter, JaqInterestFilter},
+    handshake::{detect_fee_support, GrpcFeeEnabledChannels, InitChannel, InitConnection},
     rpc::{IbcState, VoyagerRpcClient},
     VoyagerMessage,
 };
-use voyager_vm::{call, filter::FilterResult, Op, Queue};
+use voyager_vm::{call, filter::FilterResult, op_graph::OpNode, Op, Queue};
 
 #[global_allocator]
 static GLOBAL: Jemalloc = Jemalloc;
 
 use crate::{
-    cli::{AppArgs, Command, ConfigCmd, ModuleCmd, MsgCmd, PluginCmd, QueueCmd, RpcCmd},
-    config::{default_rest_laddr, default_rpc_laddr, Config, VoyagerConfig},
+    cli::{
+        AppArgs, Command, ConfigCmd, ModuleCmd, MsgCmd, OpGraphFormat, PluginCmd, QueueCmd, RpcCmd,
+    },
+    config::{default_freeze_list, default_rest_laddr, default_rpc_laddr, Config, VoyagerConfig},
     queue::{QueueConfig, Voyager},
-    utils::make_msg_create_client,
+    utils::{make_msg_create_client, tracked_unbonding_period},
 };
 
 #[cfg(not(target_os = "linux"))]
@@ -53,6 +63,7 @@ pub mod api;
 pub mod cli;
 pub mod config;
 pub mod queue;
+pub mod reconcile;
 
 fn main() -> ExitCode {
     let args = AppArgs::parse();
@@ -145,6 +156,7 @@ async fn do_main(args: cli::AppArgs) -> anyhow::Result<()> {
                         max_lifetime: None,
                     }),
                     optimizer_delay_milliseconds: 100,
+                    freeze_list: default_freeze_list(),
                 },
             }),
             ConfigCmd::Schema => print_json(
@@ -184,8 +196,14 @@ async fn do_main(args: cli::AppArgs) -> anyhow::Result<()> {
                 );
 
                 match result {
-                    Ok(FilterResult::Interest(tag)) => println!("interest ({tag})"),
-                    Ok(FilterResult::NoInterest) => println!("no interest"),
+                    Ok(FilterResult {
+                        interest: Some(tag),
+                        ..
+                    }) => println!("interest ({tag})"),
+                    Ok(FilterResult { observers, .. }) if !observers.is_empty() => {
+                        println!("observe ({})", observers.join(", "))
+                    }
+                    Ok(_) => println!("no interest"),
                     Err(()) => println!("failed"),
                 }
             }
@@ -212,7 +230,7 @@ async fn do_main(args: cli::AppArgs) -> anyhow::Result<()> {
                         })?
                         .ok_or(anyhow!("plugin not found"))?;
 
-                    tokio::process::Command::new(&plugin_config.path)
+                    let status = tokio::process::Command::new(&plugin_config.path)
                         .arg("cmd")
                         .arg("--config")
                         .arg(plugin_config.config.to_string())
@@ -220,6 +238,12 @@ async fn do_main(args: cli::AppArgs) -> anyhow::Result<()> {
                         .spawn()?
                         .wait()
                         .await?;
+
+                    // the plugin has already printed the error, forward the exit code so that
+                    // scripts can detect the failure
+                    if !status.success() {
+                        std::process::exit(status.code().unwrap_or(1));
+                    }
                 }
                 None => {
                     println!("available plugins and modules");
@@ -344,7 +368,7 @@ async fn do_main(args: cli::AppArgs) -> anyhow::Result<()> {
                     let record = q.query_failed_by_id(id.inner()).await?;
 
                     if requeue {
-                        if let Some(record) = record.as_ref().map(|r| r.item.0.clone()) {
+                        if let Some(record) = record.as_ref().map(|r| r.item.0 .0.clone()) {
                             q.enqueue(record, &JaqInterestFilter::new(vec![]).unwrap())
                                 .await?;
                         }
@@ -438,17 +462,20 @@ async fn do_main(args: cli::AppArgs) -> anyhow::Result<()> {
         Command::InitFetch {
             chain_id,
             height,
+            to,
+            hint_events_per_block,
             enqueue,
         } => {
             let start_height = match height {
                 QueryHeight::Latest => {
                     let config = get_voyager_config()?;
 
-                    let context = Context::new(config.plugins, config.modules, |h| {
-                        h.register::<IbcClassic>();
-                        h.register::<IbcUnion>();
-                    })
-                    .await?;
+                    let context =
+                        Context::new(config.plugins, config.modules, config.voyager.cache, |h| {
+                            h.register::<IbcClassic>();
+                            h.register::<IbcUnion>();
+                        })
+                        .await?;
 
                     let latest_height = context
                         .rpc_server
@@ -462,11 +489,12 @@ async fn do_main(args: cli::AppArgs) -> anyhow::Result<()> {
                 QueryHeight::Finalized => {
                     let config = get_voyager_config()?;
 
-                    let context = Context::new(config.plugins, config.modules, |h| {
-                        h.register::<IbcClassic>();
-                        h.register::<IbcUnion>();
-                    })
-                    .await?;
+                    let context =
+                        Context::new(config.plugins, config.modules, config.voyager.cache, |h| {
+                            h.register::<IbcClassic>();
+                            h.register::<IbcUnion>();
+                        })
+                        .await?;
 
                     let latest_height = context
                         .rpc_server
@@ -480,10 +508,18 @@ async fn do_main(args: cli::AppArgs) -> anyhow::Result<()> {
                 QueryHeight::Specific(height) => height,
             };
 
-            let op = call::<VoyagerMessage>(FetchBlocks {
-                chain_id: chain_id.clone(),
-                start_height,
-            });
+            let op = match to {
+                Some(to) => call::<VoyagerMessage>(FetchBlockRange {
+                    chain_id: chain_id.clone(),
+                    from_height: start_height,
+                    to_height: Height::new_with_revision(start_height.revision(), to),
+                    hint_events_per_block,
+                }),
+                None => call::<VoyagerMessage>(FetchBlocks {
+                    chain_id: chain_id.clone(),
+                    start_height,
+                }),
+            };
 
             if enqueue {
                 println!("enqueueing op for `{chain_id}` at `{start_height}`");
@@ -585,6 +621,101 @@ async fn do_main(args: cli::AppArgs) -> anyhow::Result<()> {
 
                     todo!()
                 }
+                RpcCmd::ConsensusStateHeights {
+                    on,
+                    client_id,
+                    ibc_spec_id,
+                    offset,
+                    limit,
+                } => {
+                    print_json(
+                        &voyager_client
+                            .consensus_state_heights(
+                                on,
+                                ibc_spec_id,
+                                client_id,
+                                Pagination { offset, limit },
+                            )
+                            .await?,
+                    );
+                }
+                RpcCmd::TimestampsAtHeights { on, heights } => {
+                    print_json(&voyager_client.timestamps_at_heights(on, heights).await?);
+                }
+                RpcCmd::DiffClientState {
+                    on,
+                    client_id,
+                    ibc_spec_id,
+                    include_heights,
+                } => {
+                    print_json(
+                        &voyager_client
+                            .diff_client_state(on, ibc_spec_id, client_id, include_heights)
+                            .await?,
+                    );
+                }
+                RpcCmd::ReloadPlugin { plugin_name } => {
+                    let plugin_config = get_voyager_config()?
+                        .plugins
+                        .into_iter()
+                        .try_find(|plugin_config| {
+                            <anyhow::Result<_>>::Ok(
+                                plugin_name == get_plugin_info(plugin_config)?.name,
+                            )
+                        })?
+                        .ok_or(anyhow!("plugin not found"))?;
+
+                    print_json(
+                        &voyager_client
+                            .reload_plugin(plugin_name, plugin_config.config)
+                            .await?,
+                    );
+                }
+                RpcCmd::PluginDebugState { plugin_name } => {
+                    print_json(&voyager_client.plugin_debug_state(plugin_name).await?);
+                }
+                RpcCmd::PluginDebugParseTx {
+                    plugin_name,
+                    tx_hash,
+                } => {
+                    print_json(
+                        &voyager_client
+                            .plugin_debug_parse_tx(plugin_name, tx_hash)
+                            .await?,
+                    );
+                }
+                RpcCmd::ExportCaches { plugin_name, out } => {
+                    let snapshot = voyager_client.export_caches(plugin_name).await?;
+
+                    match out {
+                        Some(out) => std::fs::write(&out, serde_json::to_string(&snapshot)?)
+                            .with_context(|| {
+                                format!("error writing snapshot to {}", out.to_string_lossy())
+                            })?,
+                        None => print_json(&snapshot),
+                    }
+                }
+                RpcCmd::ImportCaches { plugin_name, file } => {
+                    let snapshot = serde_json::from_str::<CacheSnapshot>(
+                        &read_to_string(&file).with_context(|| {
+                            format!("error reading snapshot from {}", file.to_string_lossy())
+                        })?,
+                    )?;
+
+                    print_json(&voyager_client.import_caches(plugin_name, snapshot).await?);
+                }
+                RpcCmd::EstimateRelayCost { packet_ref } => {
+                    print_json(&voyager_client.estimate_relay_cost(packet_ref).await?);
+                }
+                RpcCmd::RelayProgress { on, channel } => {
+                    print_json(&voyager_client.relay_progress(on, channel).await?);
+                }
+                RpcCmd::UnfreezeRelaying { client } => {
+                    print_json(&voyager_client.unfreeze_relaying(client).await?);
+                }
+                RpcCmd::PurgeOps { filter, dry_run } => {
+                    print_json(&voyager_client.purge_ops(filter, dry_run).await?);
+                }
             }
         }
         Command::Msg(msg) => match msg {
@@ -596,14 +727,23 @@ async fn do_main(args: cli::AppArgs) -> anyhow::Result<()> {
                 client_type,
                 height,
                 metadata,
+                grpc_url,
+                skip_checksum_verification,
+                tracking_grpc_url,
+                offline,
                 enqueue,
             } => {
                 let voyager_config = get_voyager_config()?;
 
-                let ctx = Context::new(voyager_config.plugins, voyager_config.modules, |h| {
-                    h.register::<IbcClassic>();
-                    h.register::<IbcUnion>();
-                })
+                let ctx = Context::new(
+                    voyager_config.plugins,
+                    voyager_config.modules,
+                    voyager_config.voyager.cache,
+                    |h| {
+                        h.register::<IbcClassic>();
+                        h.register::<IbcUnion>();
+                    },
+                )
                 .await?;
 
                 // weird race condition in Context::new that i don't feel like debugging right now
@@ -618,6 +758,10 @@ async fn do_main(args: cli::AppArgs) -> anyhow::Result<()> {
                     ibc_interface,
                     ibc_spec_id,
                     metadata,
+                    grpc_url.map(|grpc_url| GrpcWasmChecksums { grpc_url }),
+                    skip_checksum_verification,
+                    tracking_grpc_url.map(|grpc_url| GrpcUnbondingPeriod { grpc_url }),
+                    offline,
                 )
                 .await?;
 
@@ -637,7 +781,192 @@ async fn do_main(args: cli::AppArgs) -> anyhow::Result<()> {
                     print_json(&msg);
                 }
             }
+            MsgCmd::ValidateClientState {
+                file,
+                client_type,
+                tracking_grpc_url,
+                offline,
+            } => {
+                let Some(validator) = ClientStateValidator::for_client_type(&client_type) else {
+                    bail!("client states of type {client_type} can not be validated");
+                };
+
+                let client_state =
+                    serde_json::from_str(&read_to_string(&file).with_context(|| {
+                        format!("reading client state from {}", file.display())
+                    })?)
+                    .with_context(|| format!("parsing client state from {}", file.display()))?;
+
+                let unbonding_period = tracked_unbonding_period(
+                    "the tracked chain",
+                    tracking_grpc_url.map(|grpc_url| GrpcUnbondingPeriod { grpc_url }),
+                    offline,
+                )
+                .await?;
+
+                let validation = validator.validate(client_state, unbonding_period)?;
+
+                print_json(&validation);
+
+                validation.ensure_valid()?;
+            }
+            MsgCmd::InitConnection {
+                on,
+                ibc_spec_id,
+                client_id,
+                counterparty_client_id,
+                delay_period,
+                enqueue,
+            } => {
+                let voyager_config = get_voyager_config()?;
+
+                let voyager_client = jsonrpsee::http_client::HttpClient::builder()
+                    .build(format!("http://{}", voyager_config.voyager.rpc_laddr))?;
+
+                // validation happens in voyager, such that an invalid handshake is never enqueued
+                let msg = voyager_client
+                    .init_connection(InitConnection {
+                        chain_id: on,
+                        ibc_spec_id,
+                        client_id,
+                        counterparty_client_id,
+                        delay_period,
+                    })
+                    .await?;
+
+                if enqueue {
+                    println!("enqueueing msg");
+                    send_enqueue(&voyager_config.voyager.rest_laddr, msg).await?;
+                } else {
+                    print_json(&msg);
+                }
+            }
+            MsgCmd::InitChannel {
+                on,
+                ibc_spec_id,
+                connection_id,
+                port_id,
+                counterparty_port_id,
+                version,
+                ordering,
+                wrap_fee_version,
+                counterparty_grpc_url,
+                enqueue,
+            } => {
+                let voyager_config = get_voyager_config()?;
+
+                let voyager_client = jsonrpsee::http_client::HttpClient::builder()
+                    .build(format!("http://{}", voyager_config.voyager.rpc_laddr))?;
+
+                let wrap_fee_version = match (wrap_fee_version, counterparty_grpc_url) {
+                    (Some(wrap_fee_version), _) => wrap_fee_version,
+                    (None, Some(grpc_url)) => {
+                        let fee_support = detect_fee_support(
+                            &GrpcFeeEnabledChannels { grpc_url },
+                            &counterparty_port_id,
+                        )
+                        .await?;
+
+                        info!(?fee_support, "counterparty fee support");
+
+                        fee_support.wrap_fee_version()
+                    }
+                    (None, None) => false,
+                };
+
+                let msg = voyager_client
+                    .init_channel(InitChannel {
+                        chain_id: on,
+                        ibc_spec_id,
+                        connection_id,
+                        port_id,
+                        counterparty_port_id,
+                        version,
+                        ordering,
+                        wrap_fee_version,
+                    })
+                    .await?;
+
+                if enqueue {
+                    println!("enqueueing msg");
+                    send_enqueue(&voyager_config.voyager.rest_laddr, msg).await?;
+                } else {
+                    print_json(&msg);
+                }
+            }
         },
+        Command::ReconcileHandshakes {
+            chain_a,
+            chain_b,
+            ibc_spec_id,
+            dry_run,
+            page_size,
+            trusting_period,
+        } => {
+            if ibc_spec_id != IbcUnion::ID {
+                bail!(
+                    "handshake reconciliation is only supported for {}",
+                    IbcUnion::ID
+                );
+            }
+
+            let trusting_period = trusting_period.map(Duration::from_secs);
+
+            let voyager_config = get_voyager_config()?;
+
+            let ctx = Context::new(
+                voyager_config.plugins,
+                voyager_config.modules,
+                voyager_config.voyager.cache,
+                |h| {
+                    h.register::<IbcClassic>();
+                    h.register::<IbcUnion>();
+                },
+            )
+            .await?;
+
+            let a = reconcile::fetch_handshake_state(
+                &ctx,
+                &chain_a,
+                &chain_b,
+                page_size,
+                trusting_period,
+            )
+            .await?;
+            let b = reconcile::fetch_handshake_state(
+                &ctx,
+                &chain_b,
+                &chain_a,
+                page_size,
+                trusting_period,
+            )
+            .await?;
+
+            ctx.shutdown().await;
+
+            let report = reconcile::reconcile(&a, &b);
+
+            if !dry_run {
+                for op in report.ops(&a, &b) {
+                    send_enqueue(&voyager_config.voyager.rest_laddr, op).await?;
+                }
+            }
+
+            print_json(&report);
+        }
+        Command::RenderOp { file, format } => {
+            let op =
+                decode_op(&read_to_string(&file).with_context(|| {
+                    format!("error reading op from {}", file.to_string_lossy())
+                })?)?;
+
+            let tree = OpNode::new(&op);
+
+            match format {
+                OpGraphFormat::Dot => print!("{}", tree.to_dot()),
+                OpGraphFormat::Json => print_json(&tree),
+            }
+        }
     }
 
     Ok(())
@@ -660,16 +989,26 @@ fn print_json<T: Serialize>(t: &T) {
 
 // TODO: Extract all logic here to a plugin
 pub mod utils {
+    use std::{fmt::Display, time::Duration};
+
     use anyhow::{anyhow, bail};
     use ibc_classic_spec::IbcClassic;
     use ibc_union_spec::IbcUnion;
     use serde_json::Value;
-    use tracing::trace;
+    use tracing::{trace, warn};
     use voyager_message::{
+        client_state_validation::{ClientStateValidator, GrpcUnbondingPeriod, UnbondingPeriod},
         context::Context,
-        core::{ChainId, ClientType, IbcInterface, IbcSpecId, QueryHeight},
+        core::{
+            ChainId, ClientInfo, ClientType, IbcInterface, IbcSpecId, QueryHeight,
+            SupportedEncoding,
+        },
         data::{IbcDatagram, WithChainId},
-        module::{ClientModuleClient, ConsensusModuleClient},
+        encoding::{
+            encode_client_state, encode_consensus_state, resolve_encoding, verify_wasm_checksum,
+            GrpcWasmChecksums,
+        },
+        module::ConsensusModuleClient,
         VoyagerMessage,
     };
     use voyager_vm::{data, Op};
@@ -684,6 +1023,10 @@ pub mod utils {
         ibc_interface: IbcInterface,
         ibc_spec_id: IbcSpecId,
         metadata: Value,
+        wasm_checksums: Option<GrpcWasmChecksums>,
+        skip_checksum_verification: bool,
+        unbonding_period: Option<GrpcUnbondingPeriod>,
+        offline: bool,
     ) -> anyhow::Result<Op<VoyagerMessage>> {
         let height = ctx
             .rpc_server
@@ -725,23 +1068,67 @@ pub mod utils {
             ));
         }
 
+        // an invalid client state is accepted by the host chain, and only fails once the first
+        // proof is verified against the client, so check it here
+        if let Some(validator) = ClientStateValidator::for_client_type(&client_type) {
+            let unbonding_period =
+                tracked_unbonding_period(&counterparty_chain_id, unbonding_period, offline).await?;
+
+            let validation = validator.validate(self_client_state.clone(), unbonding_period)?;
+
+            for warning in validation.warnings() {
+                warn!("{warning}");
+            }
+
+            validation.ensure_valid()?;
+        }
+
         let client_module =
             ctx.rpc_server
                 .modules()?
                 .client_module(&client_type, &ibc_interface, &ibc_spec_id)?;
 
+        let client_info = ClientInfo {
+            client_type: client_type.clone(),
+            ibc_interface,
+            metadata,
+        };
+
+        let client_state =
+            encode_client_state(client_module, &client_info, self_client_state).await?;
+
+        let consensus_state =
+            encode_consensus_state(client_module, &client_info, self_consensus_state).await?;
+
+        // creating an 08-wasm client with a checksum that isn't stored on the host chain only fails
+        // once the transaction is executed, so check it here
+        if matches!(
+            resolve_encoding(&client_info)?,
+            SupportedEncoding::Wasm { .. }
+        ) {
+            match wasm_checksums {
+                _ if skip_checksum_verification => {
+                    warn!("not verifying that the wasm checksum is stored on {chain_id}");
+                }
+                Some(wasm_checksums) => {
+                    verify_wasm_checksum(&wasm_checksums, &client_info, &client_state).await?;
+                }
+                None => bail!(
+                    "a grpc url for {chain_id} is required to verify that the wasm \
+                    checksum is stored on it, pass --skip-checksum-verification to \
+                    skip this check"
+                ),
+            }
+        }
+
         Ok(data(WithChainId {
             chain_id,
             message: match ibc_spec_id.as_str() {
                 IbcSpecId::CLASSIC => IbcDatagram::new::<IbcClassic>(
                     ibc_classic_spec::Datagram::from(ibc_classic_spec::MsgCreateClientData {
                         msg: unionlabs::ibc::core::client::msg_create_client::MsgCreateClient {
-                            client_state: client_module
-                                .encode_client_state(self_client_state, metadata)
-                                .await?,
-                            consensus_state: client_module
-                                .encode_consensus_state(self_consensus_state)
-                                .await?,
+                            client_state,
+                            consensus_state,
                         },
                         client_type: client_type.clone(),
                     }),
@@ -749,16 +1136,33 @@ pub mod utils {
                 IbcSpecId::UNION => IbcDatagram::new::<IbcUnion>(ibc_union_spec::Datagram::from(
                     ibc_union_spec::MsgCreateClient {
                         client_type,
-                        client_state_bytes: client_module
-                            .encode_client_state(self_client_state, metadata)
-                            .await?,
-                        consensus_state_bytes: client_module
-                            .encode_consensus_state(self_consensus_state)
-                            .await?,
+                        client_state_bytes: client_state,
+                        consensus_state_bytes: consensus_state,
                     },
                 )),
                 _ => bail!("unknown IBC version id `{ibc_spec_id}`"),
             },
         }))
     }
+
+    /// Query the unbonding period of `chain` to validate a client state against, or return `None`
+    /// if the checks that require it are skipped.
+    pub(crate) async fn tracked_unbonding_period(
+        chain: impl Display,
+        unbonding_period: Option<GrpcUnbondingPeriod>,
+        offline: bool,
+    ) -> anyhow::Result<Option<Duration>> {
+        match unbonding_period {
+            _ if offline => {
+                warn!("not checking the client state against the unbonding period of {chain}");
+
+                Ok(None)
+            }
+            Some(unbonding_period) => Ok(Some(unbonding_period.unbonding_period().await?)),
+            None => bail!(
+                "a grpc url for {chain} is required to check the client state against its \
+                unbonding period, pass --offline to skip this check"
+            ),
+        }
+    }
 }
@@ -1,6 +1,6 @@
 #![allow(clippy::type_complexity)]
 
-use std::{fmt::Debug, net::SocketAddr, panic::AssertUnwindSafe};
+use std::{fmt::Debug, net::SocketAddr, panic::AssertUnwindSafe, sync::Arc};
 
 use anyhow::{bail, Context as _};
 use frame_support_procedural::{CloneNoBound, DebugNoBound};
@@ -18,7 +18,8 @@ use voyager_message::{
     pass::PluginOptPass, rpc::VoyagerRpcServer, VoyagerMessage,
 };
 use voyager_vm::{
-    engine::Engine, in_memory::InMemoryQueue, pass::Pass, BoxDynError, Captures, Op, Queue,
+    engine::Engine, in_memory::InMemoryQueue, pass::Pass, BoxDynError, Captures, InspectQueue, Op,
+    Queue, QueueStats, ScanFilter, ScanPage,
 };
 
 use crate::{api, config::Config};
@@ -142,19 +143,60 @@ impl Queue<VoyagerMessage> for QueueImpl {
     }
 }
 
+impl InspectQueue<VoyagerMessage> for QueueImpl {
+    async fn scan<'a>(
+        &'a self,
+        filter: &'a ScanFilter,
+        after: Option<i64>,
+        limit: usize,
+    ) -> Result<ScanPage<VoyagerMessage>, Self::Error> {
+        match self {
+            QueueImpl::InMemory(queue) => queue
+                .scan(filter, after, limit)
+                .await
+                .map_err(AnyQueueError::InMemory),
+            QueueImpl::PgQueue(queue) => queue
+                .scan(filter, after, limit)
+                .await
+                .map_err(AnyQueueError::PgQueue),
+        }
+    }
+
+    async fn remove<'a>(&'a self, ids: &'a [i64]) -> Result<Vec<i64>, Self::Error> {
+        match self {
+            QueueImpl::InMemory(queue) => queue.remove(ids).await.map_err(AnyQueueError::InMemory),
+            QueueImpl::PgQueue(queue) => queue.remove(ids).await.map_err(AnyQueueError::PgQueue),
+        }
+    }
+
+    async fn stats(&self) -> Result<QueueStats, Self::Error> {
+        match self {
+            QueueImpl::InMemory(queue) => queue.stats().await.map_err(AnyQueueError::InMemory),
+            QueueImpl::PgQueue(queue) => queue.stats().await.map_err(AnyQueueError::PgQueue),
+        }
+    }
+}
+
 impl Voyager {
     pub async fn new(config: Config) -> anyhow::Result<Self> {
         let queue = QueueImpl::new(config.voyager.queue.clone())
             .await
             .context("error initializing queue")?;
 
+        let context = Context::new(config.plugins, config.modules, config.voyager.cache, |h| {
+            h.register::<IbcClassic>();
+            h.register::<IbcUnion>();
+        })
+        .await
+        .context("error initializing plugins")?;
+
+        context.rpc_server.set_queue(Arc::new(queue.clone()));
+        context
+            .rpc_server
+            .set_freeze_list(config.voyager.freeze_list.clone());
+
         Ok(Self {
-            context: Context::new(config.plugins, config.modules, |h| {
-                h.register::<IbcClassic>();
-                h.register::<IbcUnion>();
-            })
-            .await
-            .context("error initializing plugins")?,
+            context,
             num_workers: config.voyager.num_workers,
             rest_laddr: config.voyager.rest_laddr,
             rpc_laddr: config.voyager.rpc_laddr,
@@ -173,6 +215,9 @@ impl Voyager {
                 .map(|(name, interest_filter)| PluginInfo {
                     name,
                     interest_filter,
+                    kind: None,
+                    chains: vec![],
+                    ibc_specs: vec![],
                 })
                 .collect(),
         )?;
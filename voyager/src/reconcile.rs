@@ -0,0 +1,1050 @@
+//! Detection and resumption of half-open connection and channel handshakes.
+//!
+//! After relayer downtime, handshakes can get stuck part of the way through
+//! (i.e. a connection in `TRYOPEN` on one chain, with the counterparty still in
+//! `INIT`). The events that would have driven the next step of the handshake
+//! have already been emitted, so the relayer will never pick them up again.
+//!
+//! [`reconcile`] compares the connection and channel ends stored on both chains
+//! and, for every handshake that can progress, reconstructs the event that
+//! would have triggered the next step. These events are then enqueued as
+//! [`ChainEvent`]s, where they are picked up and turned into the
+//! corresponding datagrams by the same plugins that handle live events.
+//!
+//! Only `ibc-union` is currently supported.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    future::Future,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Context as _;
+use ibc_solidity::{Channel, ChannelState, Connection, ConnectionState};
+use ibc_union_spec::{
+    ChannelConnection, ChannelOpenAck, ChannelOpenTry, ChannelPath, ConnectionOpenAck,
+    ConnectionOpenInit, ConnectionOpenTry, ConnectionPath, ConsensusStatePath, FullEvent, IbcUnion,
+    UnionId,
+};
+use serde::Serialize;
+use tracing::{debug, info};
+use unionlabs::{hash::H256, ibc::core::client::height::Height};
+use voyager_message::{
+    context::Context,
+    core::{ChainId, ClientInfo, IbcSpec, QueryHeight},
+    data::{ChainEvent, Data},
+    into_value,
+    module::ClientModuleClient,
+    RawClientId, VoyagerMessage,
+};
+use voyager_vm::{data, Op};
+
+/// The handshake state of one of the chains being reconciled.
+#[derive(Debug, Clone)]
+pub struct ChainHandshakeState {
+    pub chain_id: ChainId,
+    /// The height that all of the state was read at.
+    pub height: Height,
+    /// All clients on this chain that are referenced by [`Self::connections`].
+    pub clients: BTreeMap<u32, ClientSummary>,
+    pub connections: BTreeMap<u32, Connection>,
+    pub channels: BTreeMap<u32, Channel>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ClientSummary {
+    pub info: ClientInfo,
+    /// The chain that this client tracks.
+    pub counterparty_chain_id: ChainId,
+    pub status: ClientStatus,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClientStatus {
+    Active,
+    Frozen,
+    Expired,
+}
+
+/// The handshake step that is required to progress a stuck handshake.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NextStep {
+    ConnectionOpenTry,
+    ConnectionOpenAck,
+    ConnectionOpenConfirm,
+    ChannelOpenTry,
+    ChannelOpenAck,
+    ChannelOpenConfirm,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StuckHandshake {
+    /// The chain that is ahead in the handshake, and that the proofs for the
+    /// next step will be read from.
+    pub origin_chain_id: ChainId,
+    /// The client on the origin chain that the handshake is built on top of.
+    pub origin_client_id: u32,
+    /// The chain that the next step of the handshake must be submitted to.
+    pub target_chain_id: ChainId,
+    /// The client on the target chain that will verify the proofs for the next
+    /// step.
+    pub target_client_id: u32,
+    pub next_step: NextStep,
+    /// The reconstructed event on the origin chain that triggers the next step.
+    pub event: FullEvent,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SkippedHandshake {
+    pub handshake: StuckHandshake,
+    /// The chain of the client that is not active.
+    pub chain_id: ChainId,
+    pub client_id: u32,
+    pub status: ClientStatus,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UnresolvableHandshake {
+    pub chain_id: ChainId,
+    pub channel_id: u32,
+    pub next_step: NextStep,
+    pub reason: UnresolvableReason,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnresolvableReason {
+    /// The port of a channel is not part of the stored channel end, and as
+    /// such a `ChannelOpenInit` event cannot be reconstructed from state.
+    UnknownPortId,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ReconcileReport {
+    /// Handshakes that can be resumed.
+    pub resumable: Vec<StuckHandshake>,
+    /// Handshakes that are stuck, but where one of the clients involved is
+    /// frozen or expired.
+    pub skipped: Vec<SkippedHandshake>,
+    /// Handshakes that are stuck, but where the next step cannot be constructed
+    /// from the stored state.
+    pub unresolvable: Vec<UnresolvableHandshake>,
+}
+
+impl ReconcileReport {
+    /// The ops that resume all of the [resumable](Self::resumable)
+    /// handshakes.
+    ///
+    /// `a` and `b` must be the same states that were passed to [`reconcile`].
+    #[must_use]
+    pub fn ops(&self, a: &ChainHandshakeState, b: &ChainHandshakeState) -> Vec<Op<VoyagerMessage>> {
+        self.resumable
+            .iter()
+            .map(|handshake| {
+                let origin = if handshake.origin_chain_id == a.chain_id {
+                    a
+                } else {
+                    b
+                };
+
+                data(Data::IbcEvent(ChainEvent {
+                    chain_id: origin.chain_id.clone(),
+                    client_info: origin.clients[&handshake.origin_client_id].info.clone(),
+                    counterparty_chain_id: handshake.target_chain_id.clone(),
+                    // there is no transaction for a reconstructed event
+                    tx_hash: H256::default(),
+                    provable_height: origin.height,
+                    ibc_spec_id: IbcUnion::ID,
+                    event: into_value(handshake.event.clone()),
+                    raw_events: None,
+                    denom_trace: None,
+                }))
+            })
+            .collect()
+    }
+}
+
+/// Find all stuck handshakes between the two chains.
+///
+/// For every handshake, the next step is determined by the end that is furthest
+/// ahead:
+///
+/// | origin    | counterparty | next step on counterparty |
+/// |-----------|--------------|---------------------------|
+/// | `INIT`    | (none)       | `OpenTry`                 |
+/// | `TRYOPEN` | `INIT`       | `OpenAck`                 |
+/// | `OPEN`    | `TRYOPEN`    | `OpenConfirm`             |
+///
+/// Channels are only considered once their connection is open on both chains.
+#[must_use]
+pub fn reconcile(a: &ChainHandshakeState, b: &ChainHandshakeState) -> ReconcileReport {
+    let mut report = ReconcileReport::default();
+
+    for (origin, target) in [(a, b), (b, a)] {
+        reconcile_connections(origin, target, &mut report);
+        reconcile_channels(origin, target, &mut report);
+    }
+
+    report
+}
+
+fn reconcile_connections(
+    origin: &ChainHandshakeState,
+    target: &ChainHandshakeState,
+    report: &mut ReconcileReport,
+) {
+    for (&connection_id, connection) in connections_between(origin, target) {
+        let counterparty = target
+            .connections
+            .get(&connection.counterparty_connection_id);
+
+        // the null id `0` is never assigned, so ends that reference it are not part of a handshake
+        let (Some(connection_id), Some(client_id), Some(counterparty_client_id)) = (
+            UnionId::new(connection_id),
+            UnionId::new(connection.client_id),
+            UnionId::new(connection.counterparty_client_id),
+        ) else {
+            continue;
+        };
+
+        let (next_step, event) = match (
+            connection.state,
+            UnionId::new(connection.counterparty_connection_id),
+        ) {
+            (ConnectionState::Init, _) => {
+                let has_counterparty = target.connections.values().any(|c| {
+                    c.counterparty_connection_id == connection_id.get()
+                        && c.client_id == connection.counterparty_client_id
+                        && c.counterparty_client_id == connection.client_id
+                });
+
+                if has_counterparty {
+                    continue;
+                }
+
+                (
+                    NextStep::ConnectionOpenTry,
+                    FullEvent::from(ConnectionOpenInit {
+                        connection_id,
+                        client_id,
+                        counterparty_client_id,
+                    }),
+                )
+            }
+            (ConnectionState::TryOpen, Some(counterparty_connection_id))
+                if counterparty.is_some_and(|c| c.state == ConnectionState::Init) =>
+            {
+                (
+                    NextStep::ConnectionOpenAck,
+                    FullEvent::from(ConnectionOpenTry {
+                        connection_id,
+                        client_id,
+                        counterparty_client_id,
+                        counterparty_connection_id,
+                    }),
+                )
+            }
+            (ConnectionState::Open, Some(counterparty_connection_id))
+                if counterparty.is_some_and(|c| c.state == ConnectionState::TryOpen) =>
+            {
+                (
+                    NextStep::ConnectionOpenConfirm,
+                    FullEvent::from(ConnectionOpenAck {
+                        connection_id,
+                        client_id,
+                        counterparty_client_id,
+                        counterparty_connection_id,
+                    }),
+                )
+            }
+            _ => continue,
+        };
+
+        push_handshake(
+            origin,
+            target,
+            StuckHandshake {
+                origin_chain_id: origin.chain_id.clone(),
+                origin_client_id: connection.client_id,
+                target_chain_id: target.chain_id.clone(),
+                target_client_id: connection.counterparty_client_id,
+                next_step,
+                event,
+            },
+            report,
+        );
+    }
+}
+
+fn reconcile_channels(
+    origin: &ChainHandshakeState,
+    target: &ChainHandshakeState,
+    report: &mut ReconcileReport,
+) {
+    let open_connections = connections_between(origin, target)
+        .filter(|(_, connection)| connection.state == ConnectionState::Open)
+        .filter(|&(&connection_id, connection)| {
+            target
+                .connections
+                .get(&connection.counterparty_connection_id)
+                .is_some_and(|c| {
+                    c.state == ConnectionState::Open
+                        && c.counterparty_connection_id == connection_id
+                })
+        })
+        .collect::<BTreeMap<_, _>>();
+
+    for (&channel_id, channel) in &origin.channels {
+        let Some(&connection) = open_connections.get(&channel.connection_id) else {
+            continue;
+        };
+
+        let counterparty = target.channels.get(&channel.counterparty_channel_id);
+
+        // as for connections, ends that reference the null id are not part of a handshake
+        let ids = UnionId::new(channel_id)
+            .zip(UnionId::new(channel.counterparty_channel_id))
+            .zip(ChannelConnection::try_from(connection.clone()).ok());
+
+        let (next_step, event) = match channel.state {
+            ChannelState::Init => {
+                let has_counterparty = target.channels.values().any(|c| {
+                    c.counterparty_channel_id == channel_id
+                        && c.connection_id == connection.counterparty_connection_id
+                });
+
+                if !has_counterparty {
+                    report.unresolvable.push(UnresolvableHandshake {
+                        chain_id: origin.chain_id.clone(),
+                        channel_id,
+                        next_step: NextStep::ChannelOpenTry,
+                        reason: UnresolvableReason::UnknownPortId,
+                    });
+                }
+
+                continue;
+            }
+            ChannelState::TryOpen => match (counterparty, ids) {
+                (Some(counterparty), Some(((channel_id, counterparty_channel_id), connection)))
+                    if counterparty.state == ChannelState::Init =>
+                {
+                    (
+                        NextStep::ChannelOpenAck,
+                        FullEvent::from(ChannelOpenTry {
+                            // the counterparty stores the port of this channel
+                            port_id: counterparty.counterparty_port_id.clone().into(),
+                            channel_id,
+                            counterparty_port_id: channel.counterparty_port_id.clone().into(),
+                            counterparty_channel_id,
+                            connection,
+                            version: channel.version.clone(),
+                        }),
+                    )
+                }
+                _ => continue,
+            },
+            ChannelState::Open => match (counterparty, ids) {
+                (Some(counterparty), Some(((channel_id, counterparty_channel_id), connection)))
+                    if counterparty.state == ChannelState::TryOpen =>
+                {
+                    (
+                        NextStep::ChannelOpenConfirm,
+                        FullEvent::from(ChannelOpenAck {
+                            port_id: counterparty.counterparty_port_id.clone().into(),
+                            channel_id,
+                            counterparty_port_id: channel.counterparty_port_id.clone().into(),
+                            counterparty_channel_id,
+                            connection,
+                            version: channel.version.clone(),
+                        }),
+                    )
+                }
+                _ => continue,
+            },
+            _ => continue,
+        };
+
+        push_handshake(
+            origin,
+            target,
+            StuckHandshake {
+                origin_chain_id: origin.chain_id.clone(),
+                origin_client_id: connection.client_id,
+                target_chain_id: target.chain_id.clone(),
+                target_client_id: connection.counterparty_client_id,
+                next_step,
+                event,
+            },
+            report,
+        );
+    }
+}
+
+/// All connections on `origin` that are built on a client tracking `target`.
+fn connections_between<'a>(
+    origin: &'a ChainHandshakeState,
+    target: &'a ChainHandshakeState,
+) -> impl Iterator<Item = (&'a u32, &'a Connection)> + 'a {
+    origin.connections.iter().filter(|(_, connection)| {
+        origin
+            .clients
+            .get(&connection.client_id)
+            .is_some_and(|client| client.counterparty_chain_id == target.chain_id)
+    })
+}
+
+fn push_handshake(
+    origin: &ChainHandshakeState,
+    target: &ChainHandshakeState,
+    handshake: StuckHandshake,
+    report: &mut ReconcileReport,
+) {
+    let inactive_client = [
+        (origin, handshake.origin_client_id),
+        (target, handshake.target_client_id),
+    ]
+    .into_iter()
+    // the client on the target chain may not be referenced by any of the target chain's
+    // connections yet, in which case its status is unknown
+    .find_map(|(state, client_id)| {
+        state
+            .clients
+            .get(&client_id)
+            .filter(|client| client.status != ClientStatus::Active)
+            .map(|client| (state.chain_id.clone(), client_id, client.status))
+    });
+
+    match inactive_client {
+        Some((chain_id, client_id, status)) => report.skipped.push(SkippedHandshake {
+            handshake,
+            chain_id,
+            client_id,
+            status,
+        }),
+        None => report.resumable.push(handshake),
+    }
+}
+
+/// Determine the status of a client from the timestamp of its latest consensus
+/// state.
+///
+/// A client is considered expired if its latest consensus state is older than
+/// `trusting_period`. If no trusting period is provided, the client is always
+/// considered active.
+#[must_use]
+pub fn client_status(
+    latest_consensus_timestamp_nanos: u64,
+    now: Duration,
+    trusting_period: Option<Duration>,
+) -> ClientStatus {
+    match trusting_period {
+        Some(trusting_period)
+            if now.saturating_sub(Duration::from_nanos(latest_consensus_timestamp_nanos))
+                > trusting_period =>
+        {
+            ClientStatus::Expired
+        }
+        _ => ClientStatus::Active,
+    }
+}
+
+/// Read all of the handshake state on `chain_id` that is relevant for
+/// reconciling handshakes with `counterparty_chain_id`.
+///
+/// Connections and channels are enumerated by querying sequential ids in pages
+/// of `page_size` until the first id that does not exist.
+///
+/// Note that the client module interface does not expose whether a client is
+/// frozen, so clients read here are only ever reported as active or expired.
+pub async fn fetch_handshake_state(
+    ctx: &Context,
+    chain_id: &ChainId,
+    counterparty_chain_id: &ChainId,
+    page_size: u32,
+    trusting_period: Option<Duration>,
+) -> anyhow::Result<ChainHandshakeState> {
+    let height = ctx.rpc_server.query_latest_height(chain_id, true).await?;
+
+    info!(%chain_id, %height, "reading handshake state");
+
+    let connections = enumerate(page_size, |connection_id| async move {
+        ctx.rpc_server
+            .query_ibc_state::<ConnectionPath>(
+                chain_id,
+                height,
+                ConnectionPath { connection_id }.into(),
+            )
+            .await
+            .map(|state| state.state)
+    })
+    .await
+    .with_context(|| format!("error enumerating connections on {chain_id}"))?;
+
+    let channels = enumerate(page_size, |channel_id| async move {
+        ctx.rpc_server
+            .query_ibc_state::<ChannelPath>(chain_id, height, ChannelPath { channel_id }.into())
+            .await
+            .map(|state| state.state)
+    })
+    .await
+    .with_context(|| format!("error enumerating channels on {chain_id}"))?;
+
+    debug!(
+        %chain_id,
+        connections = connections.len(),
+        channels = channels.len(),
+        "read handshake state"
+    );
+
+    let mut clients = BTreeMap::new();
+
+    for client_id in connections
+        .values()
+        .map(|connection| connection.client_id)
+        .collect::<BTreeSet<_>>()
+    {
+        clients.insert(
+            client_id,
+            fetch_client_summary(
+                ctx,
+                chain_id,
+                counterparty_chain_id,
+                height,
+                client_id,
+                trusting_period,
+            )
+            .await?,
+        );
+    }
+
+    Ok(ChainHandshakeState {
+        chain_id: chain_id.clone(),
+        height,
+        clients,
+        connections,
+        channels,
+    })
+}
+
+async fn fetch_client_summary(
+    ctx: &Context,
+    chain_id: &ChainId,
+    counterparty_chain_id: &ChainId,
+    height: Height,
+    client_id: u32,
+    trusting_period: Option<Duration>,
+) -> anyhow::Result<ClientSummary> {
+    let info = ctx
+        .rpc_server
+        .client_info(chain_id, &IbcUnion::ID, RawClientId::new(client_id))
+        .await?;
+
+    let meta = ctx
+        .rpc_server
+        .client_meta(
+            chain_id,
+            &IbcUnion::ID,
+            QueryHeight::Specific(height),
+            RawClientId::new(client_id),
+        )
+        .await?;
+
+    // only the status of clients tracking the counterparty is relevant
+    let status = if trusting_period.is_some() && &meta.chain_id == counterparty_chain_id {
+        let consensus_state = ctx
+            .rpc_server
+            .query_ibc_state::<ConsensusStatePath>(
+                chain_id,
+                height,
+                ConsensusStatePath {
+                    client_id,
+                    height: meta.height.height(),
+                }
+                .into(),
+            )
+            .await?
+            .state;
+
+        let consensus_state_meta = ctx
+            .rpc_server
+            .modules()?
+            .client_module(&info.client_type, &info.ibc_interface, &IbcUnion::ID)?
+            .decode_consensus_state_meta(consensus_state)
+            .await?;
+
+        client_status(
+            consensus_state_meta.timestamp_nanos,
+            SystemTime::now().duration_since(UNIX_EPOCH)?,
+            trusting_period,
+        )
+    } else {
+        ClientStatus::Active
+    };
+
+    Ok(ClientSummary {
+        info,
+        counterparty_chain_id: meta.chain_id,
+        status,
+    })
+}
+
+/// Query sequential ids (starting at 1) in pages of `page_size`, until the first
+/// id that does not exist.
+async fn enumerate<T, E, F, Fut>(page_size: u32, query: F) -> Result<BTreeMap<u32, T>, E>
+where
+    F: Fn(u32) -> Fut,
+    Fut: Future<Output = Result<Option<T>, E>>,
+{
+    let page_size = page_size.max(1);
+
+    let mut out = BTreeMap::new();
+    let mut page_start = 1_u32;
+
+    loop {
+        let page_end = page_start.saturating_add(page_size);
+
+        let page = futures::future::try_join_all((page_start..page_end).map(&query)).await?;
+
+        let mut exhausted = false;
+
+        for (id, value) in (page_start..).zip(page) {
+            match value {
+                Some(value) => {
+                    out.insert(id, value);
+                }
+                None => exhausted = true,
+            }
+        }
+
+        if exhausted || page_end == u32::MAX {
+            break;
+        }
+
+        page_start = page_end;
+    }
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+    use voyager_message::core::{ClientType, IbcInterface};
+
+    use super::*;
+
+    const CLIENT_A: u32 = 1;
+    const CLIENT_B: u32 = 7;
+
+    fn union_id(id: u32) -> UnionId {
+        UnionId::new(id).unwrap()
+    }
+
+    fn chain_a() -> ChainId {
+        ChainId::new("chain-a")
+    }
+
+    fn chain_b() -> ChainId {
+        ChainId::new("chain-b")
+    }
+
+    fn state(
+        chain_id: ChainId,
+        counterparty_chain_id: ChainId,
+        client_id: u32,
+    ) -> ChainHandshakeState {
+        ChainHandshakeState {
+            chain_id,
+            height: Height::new(100),
+            clients: [(
+                client_id,
+                ClientSummary {
+                    info: ClientInfo {
+                        client_type: ClientType::new("client-type"),
+                        ibc_interface: IbcInterface::new("ibc-interface"),
+                        metadata: Value::Null,
+                    },
+                    counterparty_chain_id,
+                    status: ClientStatus::Active,
+                },
+            )]
+            .into_iter()
+            .collect(),
+            connections: BTreeMap::new(),
+            channels: BTreeMap::new(),
+        }
+    }
+
+    fn states() -> (ChainHandshakeState, ChainHandshakeState) {
+        (
+            state(chain_a(), chain_b(), CLIENT_A),
+            state(chain_b(), chain_a(), CLIENT_B),
+        )
+    }
+
+    fn connection(
+        state: ConnectionState,
+        client_id: u32,
+        counterparty_client_id: u32,
+        counterparty_connection_id: u32,
+    ) -> Connection {
+        Connection {
+            state,
+            client_id,
+            counterparty_client_id,
+            counterparty_connection_id,
+        }
+    }
+
+    fn channel(
+        state: ChannelState,
+        connection_id: u32,
+        counterparty_channel_id: u32,
+        counterparty_port_id: &[u8],
+    ) -> Channel {
+        Channel {
+            state,
+            connection_id,
+            counterparty_channel_id,
+            counterparty_port_id: counterparty_port_id.to_vec().into(),
+            version: "ucs01".to_owned(),
+        }
+    }
+
+    /// Connection 1 on chain a and connection 2 on chain b, both open.
+    fn open_connections() -> (ChainHandshakeState, ChainHandshakeState) {
+        let (mut a, mut b) = states();
+
+        a.connections
+            .insert(1, connection(ConnectionState::Open, CLIENT_A, CLIENT_B, 2));
+        b.connections
+            .insert(2, connection(ConnectionState::Open, CLIENT_B, CLIENT_A, 1));
+
+        (a, b)
+    }
+
+    #[test]
+    fn connection_init_without_counterparty() {
+        let (mut a, b) = states();
+
+        a.connections
+            .insert(3, connection(ConnectionState::Init, CLIENT_A, CLIENT_B, 0));
+
+        assert_eq!(
+            reconcile(&a, &b),
+            ReconcileReport {
+                resumable: vec![StuckHandshake {
+                    origin_chain_id: chain_a(),
+                    origin_client_id: CLIENT_A,
+                    target_chain_id: chain_b(),
+                    target_client_id: CLIENT_B,
+                    next_step: NextStep::ConnectionOpenTry,
+                    event: ConnectionOpenInit {
+                        connection_id: union_id(3),
+                        client_id: union_id(CLIENT_A),
+                        counterparty_client_id: union_id(CLIENT_B),
+                    }
+                    .into(),
+                }],
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn connection_try_open_with_init_counterparty() {
+        let (mut a, mut b) = states();
+
+        a.connections
+            .insert(3, connection(ConnectionState::Init, CLIENT_A, CLIENT_B, 0));
+        b.connections.insert(
+            5,
+            connection(ConnectionState::TryOpen, CLIENT_B, CLIENT_A, 3),
+        );
+
+        assert_eq!(
+            reconcile(&a, &b),
+            ReconcileReport {
+                resumable: vec![StuckHandshake {
+                    origin_chain_id: chain_b(),
+                    origin_client_id: CLIENT_B,
+                    target_chain_id: chain_a(),
+                    target_client_id: CLIENT_A,
+                    next_step: NextStep::ConnectionOpenAck,
+                    event: ConnectionOpenTry {
+                        connection_id: union_id(5),
+                        client_id: union_id(CLIENT_B),
+                        counterparty_client_id: union_id(CLIENT_A),
+                        counterparty_connection_id: union_id(3),
+                    }
+                    .into(),
+                }],
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn connection_open_with_try_open_counterparty() {
+        let (mut a, mut b) = states();
+
+        a.connections
+            .insert(3, connection(ConnectionState::Open, CLIENT_A, CLIENT_B, 5));
+        b.connections.insert(
+            5,
+            connection(ConnectionState::TryOpen, CLIENT_B, CLIENT_A, 3),
+        );
+
+        assert_eq!(
+            reconcile(&a, &b),
+            ReconcileReport {
+                resumable: vec![StuckHandshake {
+                    origin_chain_id: chain_a(),
+                    origin_client_id: CLIENT_A,
+                    target_chain_id: chain_b(),
+                    target_client_id: CLIENT_B,
+                    next_step: NextStep::ConnectionOpenConfirm,
+                    event: ConnectionOpenAck {
+                        connection_id: union_id(3),
+                        client_id: union_id(CLIENT_A),
+                        counterparty_client_id: union_id(CLIENT_B),
+                        counterparty_connection_id: union_id(5),
+                    }
+                    .into(),
+                }],
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn open_connections_are_not_stuck() {
+        let (a, b) = open_connections();
+
+        assert_eq!(reconcile(&a, &b), ReconcileReport::default());
+    }
+
+    #[test]
+    fn connections_to_other_chains_are_ignored() {
+        let (mut a, b) = states();
+
+        a.clients.insert(
+            2,
+            ClientSummary {
+                counterparty_chain_id: ChainId::new("chain-c"),
+                ..a.clients[&CLIENT_A].clone()
+            },
+        );
+        a.connections
+            .insert(3, connection(ConnectionState::Init, 2, 4, 0));
+
+        assert_eq!(reconcile(&a, &b), ReconcileReport::default());
+    }
+
+    #[test]
+    fn channel_init_without_counterparty_is_unresolvable() {
+        let (mut a, b) = open_connections();
+
+        a.channels
+            .insert(4, channel(ChannelState::Init, 1, 0, b"port-b"));
+
+        assert_eq!(
+            reconcile(&a, &b),
+            ReconcileReport {
+                unresolvable: vec![UnresolvableHandshake {
+                    chain_id: chain_a(),
+                    channel_id: 4,
+                    next_step: NextStep::ChannelOpenTry,
+                    reason: UnresolvableReason::UnknownPortId,
+                }],
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn channel_try_open_with_init_counterparty() {
+        let (mut a, mut b) = open_connections();
+
+        a.channels
+            .insert(4, channel(ChannelState::Init, 1, 0, b"port-b"));
+        b.channels
+            .insert(6, channel(ChannelState::TryOpen, 2, 4, b"port-a"));
+
+        assert_eq!(
+            reconcile(&a, &b),
+            ReconcileReport {
+                resumable: vec![StuckHandshake {
+                    origin_chain_id: chain_b(),
+                    origin_client_id: CLIENT_B,
+                    target_chain_id: chain_a(),
+                    target_client_id: CLIENT_A,
+                    next_step: NextStep::ChannelOpenAck,
+                    event: ChannelOpenTry {
+                        port_id: b"port-b".to_vec().into(),
+                        channel_id: union_id(6),
+                        counterparty_port_id: b"port-a".to_vec().into(),
+                        counterparty_channel_id: union_id(4),
+                        connection: b.connections[&2].clone().try_into().unwrap(),
+                        version: "ucs01".to_owned(),
+                    }
+                    .into(),
+                }],
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn channel_open_with_try_open_counterparty() {
+        let (mut a, mut b) = open_connections();
+
+        a.channels
+            .insert(4, channel(ChannelState::Open, 1, 6, b"port-b"));
+        b.channels
+            .insert(6, channel(ChannelState::TryOpen, 2, 4, b"port-a"));
+
+        assert_eq!(
+            reconcile(&a, &b),
+            ReconcileReport {
+                resumable: vec![StuckHandshake {
+                    origin_chain_id: chain_a(),
+                    origin_client_id: CLIENT_A,
+                    target_chain_id: chain_b(),
+                    target_client_id: CLIENT_B,
+                    next_step: NextStep::ChannelOpenConfirm,
+                    event: ChannelOpenAck {
+                        port_id: b"port-a".to_vec().into(),
+                        channel_id: union_id(4),
+                        counterparty_port_id: b"port-b".to_vec().into(),
+                        counterparty_channel_id: union_id(6),
+                        connection: a.connections[&1].clone().try_into().unwrap(),
+                        version: "ucs01".to_owned(),
+                    }
+                    .into(),
+                }],
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn channels_on_unopened_connections_are_ignored() {
+        let (mut a, mut b) = open_connections();
+
+        b.connections.insert(
+            2,
+            connection(ConnectionState::TryOpen, CLIENT_B, CLIENT_A, 1),
+        );
+        a.channels
+            .insert(4, channel(ChannelState::Open, 1, 6, b"port-b"));
+        b.channels
+            .insert(6, channel(ChannelState::TryOpen, 2, 4, b"port-a"));
+
+        // only the connection handshake is resumed
+        assert_eq!(
+            reconcile(&a, &b)
+                .resumable
+                .into_iter()
+                .map(|h| h.next_step)
+                .collect::<Vec<_>>(),
+            [NextStep::ConnectionOpenConfirm]
+        );
+    }
+
+    #[test]
+    fn inactive_clients_are_skipped() {
+        for (chain, status) in [
+            (chain_a(), ClientStatus::Frozen),
+            (chain_b(), ClientStatus::Expired),
+        ] {
+            let (mut a, mut b) = states();
+
+            a.connections
+                .insert(3, connection(ConnectionState::Init, CLIENT_A, CLIENT_B, 0));
+            b.connections.insert(
+                5,
+                connection(ConnectionState::TryOpen, CLIENT_B, CLIENT_A, 3),
+            );
+
+            let client_id = if chain == chain_a() {
+                a.clients.get_mut(&CLIENT_A).expect("client exists").status = status;
+                CLIENT_A
+            } else {
+                b.clients.get_mut(&CLIENT_B).expect("client exists").status = status;
+                CLIENT_B
+            };
+
+            let report = reconcile(&a, &b);
+
+            assert!(report.resumable.is_empty());
+            assert_eq!(report.skipped.len(), 1);
+            assert_eq!(report.skipped[0].chain_id, chain);
+            assert_eq!(report.skipped[0].client_id, client_id);
+            assert_eq!(report.skipped[0].status, status);
+            assert_eq!(
+                report.skipped[0].handshake.next_step,
+                NextStep::ConnectionOpenAck
+            );
+        }
+    }
+
+    #[test]
+    fn ops_are_ibc_events_on_the_origin_chain() {
+        let (mut a, mut b) = states();
+
+        a.connections
+            .insert(3, connection(ConnectionState::Init, CLIENT_A, CLIENT_B, 0));
+        b.connections.insert(
+            5,
+            connection(ConnectionState::TryOpen, CLIENT_B, CLIENT_A, 3),
+        );
+
+        let report = reconcile(&a, &b);
+
+        let [op] = <[_; 1]>::try_from(report.ops(&a, &b)).expect("one resumable handshake");
+
+        let Op::Data(Data::IbcEvent(event)) = op else {
+            panic!("expected an ibc event, found {op:?}");
+        };
+
+        assert_eq!(event.chain_id, chain_b());
+        assert_eq!(event.counterparty_chain_id, chain_a());
+        assert_eq!(event.provable_height, b.height);
+        assert_eq!(
+            event.decode_event::<IbcUnion>().map(Result::ok),
+            Some(Some(report.resumable[0].event.clone()))
+        );
+    }
+
+    #[test]
+    fn client_expiry() {
+        let now = Duration::from_secs(1_000);
+
+        assert_eq!(client_status(0, now, None), ClientStatus::Active);
+        assert_eq!(
+            client_status(900_000_000_000, now, Some(Duration::from_secs(100))),
+            ClientStatus::Active
+        );
+        assert_eq!(
+            client_status(899_999_999_999, now, Some(Duration::from_secs(100))),
+            ClientStatus::Expired
+        );
+    }
+
+    #[tokio::test]
+    async fn enumerate_stops_at_first_missing_id() {
+        let found = enumerate(
+            3,
+            |id| async move { Ok::<_, ()>((id <= 7).then_some(id * 10)) },
+        )
+        .await;
+
+        assert_eq!(
+            found,
+            Ok((1..=7).map(|id| (id, id * 10)).collect::<BTreeMap<_, _>>())
+        );
+    }
+}